@@ -34,7 +34,7 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     let storage = Storage::new(storage_path);
 
     // Ensure storage directory exists
-    storage.ensure_snapshots_dir()?;
+    storage.ensure_snapshots_dir().await?;
 
     // Create a comprehensive test snapshot with multiple request types
     let test_snapshot = Snapshot {
@@ -63,8 +63,19 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
                     },
                     body: create_test_html().into_bytes(),
                     content_type: "text/html".to_string(),
+                    failure: None,
+                    set_cookie: Vec::new(),
+                    http_version: None,
+                    reason: None,
+                    informational: Vec::new(),
                 },
                 timestamp: Utc::now(),
+                initiator: None,
+                timing: Default::default(),
+                vary_key: Default::default(),
+                variant: None,
+                sequence: None,
+                frame_id: None,
             },
 
             // API endpoint
@@ -87,8 +98,19 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
                     },
                     body: create_test_json().into_bytes(),
                     content_type: "application/json".to_string(),
+                    failure: None,
+                    set_cookie: Vec::new(),
+                    http_version: None,
+                    reason: None,
+                    informational: Vec::new(),
                 },
                 timestamp: Utc::now(),
+                initiator: None,
+                timing: Default::default(),
+                vary_key: Default::default(),
+                variant: None,
+                sequence: None,
+                frame_id: None,
             },
 
             // CSS file
@@ -107,8 +129,19 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
                     },
                     body: create_test_css().into_bytes(),
                     content_type: "text/css".to_string(),
+                    failure: None,
+                    set_cookie: Vec::new(),
+                    http_version: None,
+                    reason: None,
+                    informational: Vec::new(),
                 },
                 timestamp: Utc::now(),
+                initiator: None,
+                timing: Default::default(),
+                vary_key: Default::default(),
+                variant: None,
+                sequence: None,
+                frame_id: None,
             },
 
             // JavaScript file
@@ -127,8 +160,19 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
                     },
                     body: create_test_js().into_bytes(),
                     content_type: "application/javascript".to_string(),
+                    failure: None,
+                    set_cookie: Vec::new(),
+                    http_version: None,
+                    reason: None,
+                    informational: Vec::new(),
                 },
                 timestamp: Utc::now(),
+                initiator: None,
+                timing: Default::default(),
+                vary_key: Default::default(),
+                variant: None,
+                sequence: None,
+                frame_id: None,
             },
 
             // POST API request example
@@ -151,10 +195,22 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
                     },
                     body: b"{\"id\": 123, \"status\": \"created\", \"message\": \"User created successfully\"}".to_vec(),
                     content_type: "application/json".to_string(),
+                    failure: None,
+                    set_cookie: Vec::new(),
+                    http_version: None,
+                    reason: None,
+                    informational: Vec::new(),
                 },
                 timestamp: Utc::now(),
+                initiator: None,
+                timing: Default::default(),
+                vary_key: Default::default(),
+                variant: None,
+                sequence: None,
+                frame_id: None,
             }
         ],
+        ..Default::default()
     };
 
     // Save the snapshot
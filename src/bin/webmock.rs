@@ -1,27 +1,92 @@
 use clap::{CommandFactory, Parser};
-use clap_complete::{generate, Shell};
-use std::io;
+use std::io::{self, Write};
+use std::path::PathBuf;
 use std::process;
 use tracing::info;
-use tracing_subscriber::{self, EnvFilter};
+use tracing_subscriber::layer::SubscriberExt;
+use tracing_subscriber::util::SubscriberInitExt;
+use tracing_subscriber::{EnvFilter, Layer};
 
 use webmock_cli::{
-    cli::{Cli, Commands, Shell as CompletionShell},
-    commands::{capture_command, delete_command, inspect_command, list_command, serve_command},
+    capture::{
+        browser::BrowserController,
+        proxy::{HostAllowlist, RedactionRules},
+        sitemap::DEFAULT_SITEMAP_LIMIT,
+        variant::{check_unique_names, parse_variant_name},
+        AbortConditions, CaptureEnvironment, CaptureOptions, CaptureVariant, Provenance,
+        SettleStrategy, SitemapOptions, StatusPattern,
+    },
+    cli::{
+        CaAction, Cli, Commands, CompletionsAction, ExportFormat, ReplayFailuresMode,
+        Shell as CompletionShell,
+    },
+    commands::{
+        analyze::AnalyzeOptions,
+        analyze_command,
+        annotate::AnnotationChange,
+        annotate_command,
+        assert::AssertOptions,
+        assert_command,
+        bench::{parse_bench_duration, BenchOptions},
+        bench_command, browse_command,
+        ca::CaOptions,
+        ca_trust_command, ca_untrust_command,
+        capture::{capture_sitemap_command, parse_age},
+        capture_command,
+        completions::{completion_script, CompletionsOptions},
+        completions_install_command, config_command, decrypt_command, delete_command,
+        doctor_command,
+        drift::DriftOptions,
+        drift_command, encrypt_command,
+        export::{self, ExportOptions},
+        export_command, get_storage_path,
+        inspect::{InspectOptions, RecordSelector},
+        inspect_command, list_command, list_summary_command, manifest_command,
+        recapture::RecaptureOptions,
+        recapture_command,
+        serve::{
+            apply_serve_defaults, check_duplicate_server,
+            daemon::{daemonize, is_daemon_child, DaemonOptions},
+            parse_lifetime_limit,
+            registry::ServerRegistry,
+            DEFAULT_SERVE_PORT,
+        },
+        serve_all_command, serve_command, set_entry_command,
+        shadow::ShadowOptions,
+        shadow_command,
+        split::SplitOptions,
+        split_command, stop_command,
+        trim::TrimOptions,
+        trim_command, verify_command,
+    },
     error::{Result, WebMockError},
-    feedback::{ErrorDisplay, UserFeedback},
+    feedback::{output, ErrorDisplay, UserFeedback, ValidationHelper},
+    serve::{
+        parse_dns_upstream, parse_method_fallback, BodyLogging, DnsOptions, ForwardTarget,
+        MethodMatching, OpenMode, OriginMap, OutputLevel, ReplayFailures, ResponseOverrides,
+        ServeOptions, Throttle, ThrottleProfile, DEFAULT_MAX_LOADED_SNAPSHOTS,
+    },
+    storage::ServeDefaults,
 };
 
 #[tokio::main]
 async fn main() {
-    // Initialize logging with better error handling
-    if let Err(e) = initialize_logging() {
-        eprintln!("Failed to initialize logging: {}", e);
-        process::exit(1);
-    }
-
     // Parse CLI arguments
     let cli = Cli::parse();
+    output::configure(cli.quiet, cli.no_color, cli.utc);
+
+    // Initialize logging with better error handling
+    let otlp_endpoint = match &cli.command {
+        Some(Commands::Serve { otlp_endpoint, .. }) => otlp_endpoint.clone(),
+        _ => None,
+    };
+    let telemetry = match initialize_logging(otlp_endpoint.as_deref()) {
+        Ok(telemetry) => telemetry,
+        Err(e) => {
+            eprintln!("Failed to initialize logging: {}", e);
+            process::exit(1);
+        }
+    };
 
     // Handle shell completion generation
     if let Some(shell) = cli.generate_completion {
@@ -33,7 +98,9 @@ async fn main() {
     show_welcome_message();
 
     // Run the main application with comprehensive error handling
-    match run(cli).await {
+    let result = run(cli).await;
+    telemetry.shutdown();
+    match result {
         Ok(()) => {
             // Success - exit normally
             process::exit(0);
@@ -60,34 +127,91 @@ async fn main() {
 
 /// Generate shell completion script
 fn generate_completion(shell: CompletionShell) {
-    let mut cmd = Cli::command();
-    let shell_variant = match shell {
-        CompletionShell::Bash => Shell::Bash,
-        CompletionShell::Elvish => Shell::Elvish,
-        CompletionShell::Fish => Shell::Fish,
-        CompletionShell::PowerShell => Shell::PowerShell,
-        CompletionShell::Zsh => Shell::Zsh,
-    };
-    generate(shell_variant, &mut cmd, "webmock", &mut io::stdout());
+    let _ = io::stdout().write_all(&completion_script(&shell));
+}
+
+/// Parse the `capture --variant` values, reading their cookie files
+fn parse_variants(specs: &[String]) -> Result<Vec<CaptureVariant>> {
+    let variants = specs
+        .iter()
+        .map(|spec| CaptureVariant::parse(spec))
+        .collect::<Result<Vec<_>>>()?;
+    check_unique_names(&variants)?;
+    Ok(variants)
+}
+
+/// Map a `--replay-failures` value to the serve setting
+fn replay_failures_mode(mode: ReplayFailuresMode) -> ReplayFailures {
+    match mode {
+        ReplayFailuresMode::BadGateway => ReplayFailures::BadGateway,
+        ReplayFailuresMode::AsTimeout => ReplayFailures::AsTimeout,
+    }
+}
+
+/// Map a `--format` value to the export format
+fn export_format(format: ExportFormat) -> export::ExportFormat {
+    match format {
+        ExportFormat::OpenApi => export::ExportFormat::OpenApi,
+    }
+}
+
+/// Exporter installed by `serve --otlp-endpoint`, flushed before exiting
+#[derive(Default)]
+struct Telemetry {
+    #[cfg(feature = "otlp")]
+    otlp: Option<webmock_cli::serve::telemetry::otlp::OtlpGuard>,
+}
+
+impl Telemetry {
+    fn shutdown(self) {
+        #[cfg(feature = "otlp")]
+        if let Some(otlp) = self.otlp {
+            otlp.shutdown();
+        }
+    }
 }
 
 /// Initialize logging with proper error handling
-fn initialize_logging() -> std::result::Result<(), String> {
-    tracing_subscriber::fmt()
-        .with_env_filter(EnvFilter::from_default_env())
-        .try_init()
-        .map_err(|e| e.to_string())
+fn initialize_logging(otlp_endpoint: Option<&str>) -> std::result::Result<Telemetry, String> {
+    let fmt = tracing_subscriber::fmt::layer()
+        .with_writer(io::stderr)
+        .with_ansi(colored::control::SHOULD_COLORIZE.should_colorize())
+        .with_filter(EnvFilter::from_default_env());
+    let registry = tracing_subscriber::registry().with(fmt);
+
+    match otlp_endpoint {
+        None => {
+            registry.try_init().map_err(|e| e.to_string())?;
+            Ok(Telemetry::default())
+        }
+        #[cfg(feature = "otlp")]
+        Some(endpoint) => {
+            let (layer, guard) = webmock_cli::serve::telemetry::otlp::install(endpoint)
+                .map_err(|e| e.to_string())?;
+            registry.with(layer).try_init().map_err(|e| e.to_string())?;
+            Ok(Telemetry { otlp: Some(guard) })
+        }
+        #[cfg(not(feature = "otlp"))]
+        Some(_) => Err(
+            "--otlp-endpoint needs webmock built with `cargo install webmock-cli --features otlp`"
+                .to_string(),
+        ),
+    }
 }
 
 /// Show a friendly welcome message
 fn show_welcome_message() {
     // Only show welcome for interactive terminals and when not in CI
-    if atty::is(atty::Stream::Stdout) && std::env::var("CI").is_err() {
+    if atty::is(atty::Stream::Stderr) && std::env::var("CI").is_err() {
         UserFeedback::info("🌐 WebMock CLI - Record and replay web pages locally");
     }
 }
 
 async fn run(cli: Cli) -> Result<()> {
+    if let Some(read_only) = cli.storage_ro {
+        webmock_cli::storage::set_read_only_layer(PathBuf::from(read_only))?;
+    }
+
     // Check if a command was provided
     let command = match cli.command {
         Some(cmd) => cmd,
@@ -104,25 +228,430 @@ async fn run(cli: Cli) -> Result<()> {
             url,
             name,
             timeout,
+            from_sitemap,
+            limit,
+            same_host_only,
+            total_timeout,
             storage,
+            tail,
+            redact_header,
+            redact_defaults,
+            redact_body_pattern,
+            max_body_size,
+            max_requests,
+            only_hosts,
+            upstream_retries,
+            max_connections_per_host,
+            if_missing,
+            max_age,
+            print_age,
+            locale,
+            timezone,
+            viewport,
+            user_agent,
+            device,
+            attach,
+            attach_port,
+            proxy_port,
+            share_browser,
+            deterministic,
+            emulate_offline_after_load,
+            no_artifacts,
+            variant,
+            metrics,
+            encrypt,
+            settle_strategy,
+            fail_on_warnings,
+            abort_on_js_errors,
+            abort_on_status,
+            save_on_abort,
+            consent_note,
+            no_provenance,
         } => {
-            info!("Starting capture for URL: {}", url);
-            capture_command(&url, &name, timeout, storage).await?;
+            if let Some(port) = proxy_port {
+                ValidationHelper::validate_port(port)?;
+            }
+            if save_on_abort && abort_on_js_errors.is_none() && abort_on_status.is_none() {
+                return Err(WebMockError::config(
+                    "--save-on-abort needs --abort-on-js-errors or --abort-on-status",
+                ));
+            }
+            let options = CaptureOptions {
+                redaction: RedactionRules::from_args(
+                    &redact_header,
+                    redact_defaults,
+                    &redact_body_pattern,
+                    max_body_size,
+                )?,
+                if_missing,
+                max_age: max_age.as_deref().map(parse_age).transpose()?,
+                print_age,
+                environment: CaptureEnvironment::from_args(
+                    locale.as_deref(),
+                    timezone.as_deref(),
+                    viewport.as_deref(),
+                )?
+                .with_device(device.as_deref(), user_agent.as_deref())?,
+                tail,
+                max_requests: match max_requests {
+                    Some(0) => {
+                        return Err(WebMockError::config("--max-requests must be at least 1"))
+                    }
+                    max_requests => max_requests,
+                },
+                upstream_retries,
+                max_connections_per_host: match max_connections_per_host {
+                    Some(0) => {
+                        return Err(WebMockError::config(
+                            "--max-connections-per-host must be at least 1",
+                        ))
+                    }
+                    max => max,
+                },
+                serve_defaults: Default::default(),
+                attach: BrowserController::attach_endpoint(attach.as_deref(), attach_port)?,
+                proxy_port,
+                share_browser,
+                deterministic,
+                emulate_offline_after_load,
+                no_artifacts,
+                variants: parse_variants(&variant)?,
+                metrics,
+                encrypt,
+                only_hosts: only_hosts
+                    .as_deref()
+                    .map(HostAllowlist::parse)
+                    .transpose()?,
+                settle_strategy: settle_strategy
+                    .as_deref()
+                    .map(SettleStrategy::parse)
+                    .transpose()?
+                    .unwrap_or_default(),
+                fail_on_warnings,
+                abort: AbortConditions {
+                    max_js_errors: abort_on_js_errors,
+                    on_status: abort_on_status
+                        .as_deref()
+                        .map(StatusPattern::parse)
+                        .transpose()?,
+                    save_on_abort,
+                },
+                provenance: Some(Provenance::collect(
+                    std::env::args(),
+                    consent_note,
+                    !no_provenance,
+                )),
+            };
+            match (from_sitemap, url) {
+                (Some(sitemap_url), _) => {
+                    info!("Starting capture from sitemap: {}", sitemap_url);
+                    let sitemap = SitemapOptions {
+                        limit: limit.unwrap_or(DEFAULT_SITEMAP_LIMIT),
+                        same_host_only,
+                    };
+                    capture_sitemap_command(
+                        &sitemap_url,
+                        &name,
+                        timeout,
+                        total_timeout,
+                        storage,
+                        options,
+                        sitemap,
+                    )
+                    .await?;
+                }
+                (None, Some(url)) => {
+                    info!("Starting capture for URL: {}", url);
+                    capture_command(&url, &name, timeout, storage, options).await?;
+                }
+                (None, None) => unreachable!("clap requires a URL or --from-sitemap"),
+            }
         }
-        Commands::List { storage } => {
-            info!("Listing snapshots");
-            list_command(storage).await?;
+        Commands::List {
+            storage,
+            summary,
+            json,
+        } => {
+            if summary {
+                info!("Summarizing snapshot storage");
+                list_summary_command(storage, json).await?;
+            } else {
+                info!("Listing snapshots");
+                list_command(storage, json).await?;
+            }
         }
         Commands::Serve {
             snapshot_name,
+            all,
+            path_routing: _,
+            max_loaded_snapshots,
             port,
             storage,
+            open,
+            open_in_chrome_proxy,
+            replay_failures,
+            verbose,
+            overrides,
+            method_fallback,
+            honor_method_override,
+            redirect_loop_limit,
+            strict_connect,
+            stateful,
+            simulate_rate_limits,
+            sniff_content_type,
+            synthesize_common_assets,
+            generate_etags,
+            replay_early_hints,
+            forward_unmatched,
+            throttle,
+            throttle_shared,
+            variant,
+            encode,
+            map_origin,
+            rewrite_absolute_urls,
+            idle_timeout,
+            max_lifetime,
+            dns_port,
+            advertise_ip,
+            dns_upstream,
+            log_bodies,
+            redact_header,
+            redact_defaults,
+            redact_body_pattern,
+            otlp_endpoint: _,
+            daemon,
+            pid_file,
+            port_file,
+            coverage,
+            allow_duplicate,
+            log_file,
+        } => {
+            // clap only sees the conflict when --quiet follows the subcommand
+            if cli.quiet && verbose {
+                return Err(WebMockError::config(
+                    "--quiet and --verbose can't be used together",
+                ));
+            }
+
+            // Settings a snapshot can store defaults for; serve resolves them
+            let flags = ServeDefaults {
+                port,
+                replay_failures: replay_failures
+                    .map(|mode| replay_failures_mode(mode).as_str().to_string()),
+                encode,
+                strict_connect,
+                stateful,
+            };
+            let mut options = ServeOptions {
+                open: if open_in_chrome_proxy {
+                    Some(OpenMode::ChromeProxy)
+                } else if open {
+                    Some(OpenMode::Default)
+                } else {
+                    None
+                },
+                output: if cli.quiet {
+                    OutputLevel::Quiet
+                } else if verbose {
+                    OutputLevel::Verbose
+                } else {
+                    OutputLevel::Normal
+                },
+                overrides: match overrides {
+                    Some(path) => ResponseOverrides::load(std::path::Path::new(&path))?,
+                    None => ResponseOverrides::default(),
+                },
+                methods: MethodMatching {
+                    fallbacks: match method_fallback {
+                        Some(spec) => parse_method_fallback(&spec)?,
+                        None => Vec::new(),
+                    },
+                    honor_override_header: honor_method_override,
+                },
+                redirect_loop_limit,
+                simulate_rate_limits,
+                sniff_content_type,
+                synthesize_common_assets,
+                generate_etags,
+                replay_early_hints,
+                forward_unmatched: forward_unmatched
+                    .map(|base| ForwardTarget::parse(&base))
+                    .transpose()?,
+                throttle: throttle
+                    .map(|spec| ThrottleProfile::parse(&spec))
+                    .transpose()?
+                    .map(|profile| Throttle::new(profile, throttle_shared)),
+                variant: variant.as_deref().map(parse_variant_name).transpose()?,
+                origin_map: OriginMap::parse(&map_origin)?,
+                rewrite_absolute_urls,
+                idle_timeout: idle_timeout
+                    .map(|value| parse_lifetime_limit(&value, "--idle-timeout"))
+                    .transpose()?,
+                max_lifetime: max_lifetime
+                    .map(|value| parse_lifetime_limit(&value, "--max-lifetime"))
+                    .transpose()?,
+                pid_file: pid_file.as_ref().map(PathBuf::from),
+                port_file: port_file.map(PathBuf::from),
+                coverage: coverage.map(PathBuf::from),
+                // The background server was checked for before it was started
+                allow_duplicate: allow_duplicate || is_daemon_child(),
+                dns: match dns_port {
+                    Some(port) => Some(DnsOptions {
+                        port,
+                        advertise_ip,
+                        upstream: dns_upstream
+                            .as_deref()
+                            .map(parse_dns_upstream)
+                            .transpose()?,
+                    }),
+                    None => None,
+                },
+                log_bodies: match log_bodies {
+                    Some(max_bytes) => Some(BodyLogging {
+                        max_bytes,
+                        redaction: RedactionRules::from_args(
+                            &redact_header,
+                            redact_defaults,
+                            &redact_body_pattern,
+                            None,
+                        )?,
+                    }),
+                    None => None,
+                },
+                ..ServeOptions::default()
+            };
+            if daemon && !is_daemon_child() {
+                // Asked here, where there is a terminal to ask on
+                if let Some(snapshot_name) = snapshot_name.as_deref().filter(|_| !all) {
+                    let storage_path = get_storage_path(storage.clone())?;
+                    check_duplicate_server(
+                        &ServerRegistry::new(&storage_path),
+                        snapshot_name,
+                        allow_duplicate,
+                    )?;
+                }
+                // The background server is this same command run again
+                let options = DaemonOptions {
+                    pid_file: PathBuf::from(pid_file.unwrap_or_default()),
+                    log_file: PathBuf::from(log_file.unwrap_or_default()),
+                };
+                info!("Starting background server");
+                daemonize(&options).await?;
+                return Ok(());
+            }
+            match snapshot_name {
+                Some(snapshot_name) if !all => {
+                    info!("Starting server for snapshot: {}", snapshot_name);
+                    serve_command(&snapshot_name, &flags, storage, options).await?;
+                }
+                _ => {
+                    apply_serve_defaults(&flags, &mut options)?;
+                    let port = port.unwrap_or(DEFAULT_SERVE_PORT);
+                    info!(
+                        "Starting path-routed server for all snapshots on port: {}",
+                        port
+                    );
+                    let max_loaded = max_loaded_snapshots.unwrap_or(DEFAULT_MAX_LOADED_SNAPSHOTS);
+                    serve_all_command(port, storage, options, max_loaded).await?;
+                }
+            }
+        }
+        Commands::Config {
+            snapshot_name,
+            port,
+            replay_failures,
+            encode,
+            strict_connect,
+            stateful,
+            clear,
+            storage,
+        } => {
+            info!("Configuring serve defaults of snapshot: {}", snapshot_name);
+            let changes = ServeDefaults {
+                port,
+                replay_failures: replay_failures
+                    .map(|mode| replay_failures_mode(mode).as_str().to_string()),
+                encode,
+                strict_connect,
+                stateful,
+            };
+            config_command(&snapshot_name, changes, clear, storage).await?;
+        }
+        Commands::SetEntry {
+            snapshot_name,
+            url,
+            force,
+            storage,
+        } => {
+            info!("Setting entry point of snapshot: {}", snapshot_name);
+            set_entry_command(&snapshot_name, &url, force, storage).await?;
+        }
+        Commands::Annotate {
+            snapshot_name,
+            request,
+            note,
+            remove: _,
+            storage,
+        } => {
+            info!("Annotating snapshot: {}", snapshot_name);
+            let change = match note {
+                Some(note) => AnnotationChange::Set(note),
+                None => AnnotationChange::Remove,
+            };
+            annotate_command(&snapshot_name, request, change, storage).await?;
+        }
+        Commands::Manifest {
+            snapshot_name,
+            storage,
+        } => {
+            info!("Printing manifest of snapshot: {}", snapshot_name);
+            manifest_command(&snapshot_name, storage).await?;
+        }
+        Commands::Verify {
+            snapshot_name,
+            manifest,
+            storage,
         } => {
-            info!(
-                "Starting server for snapshot: {} on port: {}",
-                snapshot_name, port
-            );
-            serve_command(&snapshot_name, port, storage).await?;
+            info!("Verifying snapshot: {}", snapshot_name);
+            verify_command(&snapshot_name, manifest, storage).await?;
+        }
+        Commands::Doctor { json, storage } => {
+            info!("Running doctor checks");
+            doctor_command(storage, json).await?;
+        }
+        Commands::Encrypt {
+            snapshot_name,
+            storage,
+        } => {
+            info!("Encrypting snapshot: {}", snapshot_name);
+            encrypt_command(&snapshot_name, storage).await?;
+        }
+        Commands::Decrypt {
+            snapshot_name,
+            storage,
+        } => {
+            info!("Decrypting snapshot: {}", snapshot_name);
+            decrypt_command(&snapshot_name, storage).await?;
+        }
+        Commands::Stop { pid_file, timeout } => {
+            info!("Stopping server with PID file: {}", pid_file);
+            let timeout = parse_lifetime_limit(&timeout, "--timeout")?;
+            stop_command(std::path::Path::new(&pid_file), timeout).await?;
+        }
+        Commands::Bench {
+            snapshot_name,
+            connections,
+            duration,
+            url,
+            storage,
+        } => {
+            info!("Benchmarking snapshot: {}", snapshot_name);
+            let options = BenchOptions {
+                connections,
+                duration: parse_bench_duration(&duration)?,
+                url,
+            };
+            bench_command(&snapshot_name, storage, options).await?;
         }
         Commands::Delete {
             snapshot_name,
@@ -131,13 +660,186 @@ async fn run(cli: Cli) -> Result<()> {
             info!("Deleting snapshot: {}", snapshot_name);
             delete_command(&snapshot_name, storage).await?;
         }
+        Commands::Recapture {
+            snapshot_name,
+            timeout,
+            no_backup,
+            diff,
+            storage,
+        } => {
+            info!("Recapturing snapshot: {}", snapshot_name);
+            let options = RecaptureOptions {
+                timeout,
+                backup: !no_backup,
+                diff,
+            };
+            recapture_command(&snapshot_name, storage, options).await?;
+        }
+        Commands::Split {
+            source,
+            destination,
+            url_glob,
+            method,
+            invert,
+            storage,
+        } => {
+            info!("Splitting snapshot {} into {}", source, destination);
+            let options = SplitOptions {
+                url_glob,
+                method,
+                invert,
+            };
+            split_command(&source, &destination, storage, &options).await?;
+        }
+        Commands::Trim {
+            snapshot_name,
+            coverage,
+            output,
+            keep_misses,
+            min_hits,
+            storage,
+        } => {
+            info!("Trimming snapshot {} by {}", snapshot_name, coverage);
+            let options = TrimOptions {
+                coverage: PathBuf::from(coverage),
+                output,
+                keep_misses,
+                min_hits,
+            };
+            trim_command(&snapshot_name, storage, &options).await?;
+        }
+        Commands::Analyze {
+            snapshot_name,
+            json,
+            strict,
+            storage,
+        } => {
+            info!("Analyzing resources of snapshot: {}", snapshot_name);
+            let options = AnalyzeOptions { json, strict };
+            analyze_command(&snapshot_name, storage, &options).await?;
+        }
+        Commands::Drift {
+            snapshot_name,
+            live: _,
+            url_glob,
+            header,
+            strict,
+            storage,
+        } => {
+            info!("Comparing snapshot {} with the live API", snapshot_name);
+            let options = DriftOptions {
+                url_glob,
+                headers: header,
+                strict,
+            };
+            drift_command(&snapshot_name, storage, &options).await?;
+        }
+        Commands::Shadow {
+            snapshot_name,
+            listen,
+            upstream,
+            report,
+            storage,
+        } => {
+            info!("Shadowing {} with snapshot {}", upstream, snapshot_name);
+            let options = ShadowOptions {
+                listen,
+                upstream,
+                report: report.map(PathBuf::from),
+            };
+            shadow_command(&snapshot_name, storage, &options).await?;
+        }
+        Commands::Assert {
+            snapshot_name,
+            expect,
+            expect_file,
+            json,
+            storage,
+        } => {
+            info!("Checking expectations against snapshot: {}", snapshot_name);
+            let options = AssertOptions {
+                expect,
+                expect_file,
+                json,
+            };
+            assert_command(&snapshot_name, storage, &options).await?;
+        }
+        Commands::Export {
+            snapshot_name,
+            format,
+            output,
+            storage,
+        } => {
+            info!("Exporting snapshot: {}", snapshot_name);
+            let options = ExportOptions {
+                format: export_format(format),
+                output: output.map(PathBuf::from),
+            };
+            export_command(&snapshot_name, storage, &options).await?;
+        }
         Commands::Inspect {
             snapshot_name,
+            detailed,
+            request,
+            url,
+            dump_body,
+            dump_request_body,
+            show_headers,
+            show_body,
+            max_body_bytes,
+            console,
+            diagnostics,
+            json,
             storage,
         } => {
             info!("Inspecting snapshot: {}", snapshot_name);
-            inspect_command(&snapshot_name, storage).await?;
+            let options = InspectOptions {
+                detailed,
+                record: request
+                    .map(RecordSelector::Index)
+                    .or(url.map(RecordSelector::Url)),
+                dump_body: dump_body.map(PathBuf::from),
+                dump_request_body: dump_request_body.map(PathBuf::from),
+                show_headers,
+                console,
+                diagnostics,
+                show_body,
+                max_body_bytes: (max_body_bytes > 0).then_some(max_body_bytes),
+                json,
+            };
+            inspect_command(&snapshot_name, storage, &options).await?;
+        }
+        Commands::Browse {
+            snapshot_name,
+            storage,
+        } => {
+            info!("Browsing snapshot: {}", snapshot_name);
+            browse_command(&snapshot_name, storage).await?;
         }
+        Commands::Ca { action } => match action {
+            CaAction::Trust { yes, dry_run } => {
+                info!("Trusting webmock certificate");
+                ca_trust_command(&CaOptions {
+                    yes,
+                    dry_run,
+                    ..CaOptions::default()
+                })?;
+            }
+            CaAction::Untrust { yes, dry_run } => {
+                info!("Untrusting webmock certificate");
+                ca_untrust_command(&CaOptions {
+                    yes,
+                    dry_run,
+                    ..CaOptions::default()
+                })?;
+            }
+        },
+        Commands::Completions { action } => match action {
+            CompletionsAction::Install { shell, uninstall } => {
+                info!("Installing shell completions");
+                completions_install_command(&CompletionsOptions::from_env(shell, uninstall)?)?;
+            }
+        },
     }
 
     Ok(())
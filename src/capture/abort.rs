@@ -0,0 +1,223 @@
+//! Giving up early on a page that is fundamentally broken
+//!
+//! A capture of a site that is down, or whose scripts throw on every load,
+//! otherwise only fails after its full timeout, or saves a useless snapshot.
+//! `capture --abort-on-js-errors N` stops once the page threw more than N
+//! uncaught exceptions before it settled, and `--abort-on-status 5xx` once
+//! the proxy recorded a matching status for the page's main document. An
+//! aborted capture saves nothing unless `--save-on-abort` is set.
+
+use std::fmt;
+
+use tokio::sync::{mpsc, watch};
+use tracing::debug;
+
+use crate::capture::browser::page_events::{PageEvent, PageEventSource, MAX_PAGE_EVENTS};
+use crate::capture::proxy::DocumentResponse;
+use crate::error::{Result, WebMockError};
+
+/// When a capture gives up before its page settled
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct AbortConditions {
+    /// Abort once the page threw more uncaught exceptions than this
+    pub max_js_errors: Option<u64>,
+    /// Abort when the main document's status matches
+    pub on_status: Option<StatusPattern>,
+    /// Save what was recorded before aborting, instead of nothing
+    pub save_on_abort: bool,
+}
+
+impl AbortConditions {
+    /// Check whether no condition is set, so a capture never aborts
+    pub fn is_empty(&self) -> bool {
+        self.max_js_errors.is_none() && self.on_status.is_none()
+    }
+
+    /// Tell why the capture must abort after `exceptions` and with the main `document`, if it must
+    pub fn check(
+        &self,
+        exceptions: u64,
+        document: Option<&DocumentResponse>,
+    ) -> Option<AbortReason> {
+        if let Some(max) = self.max_js_errors.filter(|max| exceptions > *max) {
+            return Some(AbortReason::JsErrors { exceptions, max });
+        }
+        match (&self.on_status, document) {
+            (Some(pattern), Some(document)) if pattern.matches(document.status) => {
+                Some(AbortReason::DocumentStatus {
+                    url: document.url.clone(),
+                    status: document.status,
+                })
+            }
+            _ => None,
+        }
+    }
+}
+
+/// Statuses `--abort-on-status` aborts on: a class like `5xx`, or one code
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StatusPattern {
+    /// Every status of the class, e.g. 5 for 500 to 599
+    Class(u16),
+    Exact(u16),
+}
+
+impl StatusPattern {
+    /// Parse a class from `1xx` to `5xx`, or a status code from 100 to 599
+    pub fn parse(value: &str) -> Result<Self> {
+        let invalid = || {
+            WebMockError::config(format!(
+                "Invalid status '{}': use a class like 5xx or 4xx, or a status code like 503",
+                value
+            ))
+        };
+
+        let value = value.trim().to_ascii_lowercase();
+        if let Some(class) = value.strip_suffix("xx") {
+            return match class.parse::<u16>() {
+                Ok(digit @ 1..=5) if class.len() == 1 => Ok(Self::Class(digit)),
+                _ => Err(invalid()),
+            };
+        }
+        match value.parse::<u16>() {
+            Ok(status @ 100..=599) => Ok(Self::Exact(status)),
+            _ => Err(invalid()),
+        }
+    }
+
+    /// Check whether `status` matches
+    pub fn matches(&self, status: u16) -> bool {
+        match self {
+            Self::Class(class) => status / 100 == *class,
+            Self::Exact(code) => status == *code,
+        }
+    }
+}
+
+impl fmt::Display for StatusPattern {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Class(class) => write!(f, "{}xx", class),
+            Self::Exact(code) => write!(f, "{}", code),
+        }
+    }
+}
+
+/// Why a capture was aborted
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AbortReason {
+    /// The page threw more uncaught exceptions than allowed
+    JsErrors { exceptions: u64, max: u64 },
+    /// The main document came back with a status `--abort-on-status` matches
+    DocumentStatus { url: String, status: u16 },
+}
+
+impl fmt::Display for AbortReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::JsErrors { exceptions, max } => write!(
+                f,
+                "the page threw {} uncaught exceptions before it settled, more than --abort-on-js-errors {}",
+                exceptions, max
+            ),
+            Self::DocumentStatus { url, status } => {
+                write!(f, "the main document {} returned {}", url, status)
+            }
+        }
+    }
+}
+
+/// Pass page events from `events` on, counting the uncaught exceptions among them
+///
+/// The count keeps going when the returned receiver is full or gone, so
+/// events the session drops still count against `--abort-on-js-errors`.
+pub fn count_exceptions(
+    mut events: mpsc::Receiver<PageEvent>,
+) -> (mpsc::Receiver<PageEvent>, watch::Receiver<u64>) {
+    let (forward, forwarded) = mpsc::channel(MAX_PAGE_EVENTS);
+    let (count, counted) = watch::channel(0);
+    tokio::spawn(async move {
+        while let Some(event) = events.recv().await {
+            if event.source == PageEventSource::Exception {
+                count.send_modify(|count| *count += 1);
+            }
+            // Full when the session stopped keeping events; the browser side warned already
+            let _ = forward.try_send(event);
+        }
+        debug!("Stopped counting page exceptions");
+    });
+    (forwarded, counted)
+}
+
+/// Watches a loading page for the abort conditions
+///
+/// Exceptions count from when the watch is created, so each page loaded in
+/// a session gets the whole `--abort-on-js-errors` budget.
+pub struct AbortWatch {
+    conditions: AbortConditions,
+    exceptions: Option<watch::Receiver<u64>>,
+    exceptions_before: u64,
+    document: Option<watch::Receiver<Option<DocumentResponse>>>,
+}
+
+impl AbortWatch {
+    /// Watch `exceptions` and the main `document`, either of which may be unavailable
+    pub fn new(
+        conditions: AbortConditions,
+        exceptions: Option<watch::Receiver<u64>>,
+        document: Option<watch::Receiver<Option<DocumentResponse>>>,
+    ) -> Self {
+        let exceptions_before = exceptions.as_ref().map_or(0, |count| *count.borrow());
+        Self {
+            conditions,
+            exceptions,
+            exceptions_before,
+            document,
+        }
+    }
+
+    /// Tell why the capture must abort as things stand, if it must
+    pub fn check(&self) -> Option<AbortReason> {
+        let exceptions = self.exceptions.as_ref().map_or(0, |count| {
+            count.borrow().saturating_sub(self.exceptions_before)
+        });
+        let document = self
+            .document
+            .as_ref()
+            .and_then(|document| document.borrow().clone());
+        self.conditions.check(exceptions, document.as_ref())
+    }
+
+    /// Wait until the capture must abort; never returns if it needn't
+    pub async fn triggered(&mut self) -> AbortReason {
+        // A closed channel keeps its last value but won't change again
+        let mut exceptions_open = self.exceptions.is_some();
+        let mut document_open = self.document.is_some();
+        loop {
+            if let Some(reason) = self.check() {
+                return reason;
+            }
+            if !exceptions_open && !document_open {
+                return std::future::pending().await;
+            }
+            tokio::select! {
+                changed = changed(&mut self.exceptions), if exceptions_open => {
+                    exceptions_open = changed.is_ok();
+                }
+                changed = changed(&mut self.document), if document_open => {
+                    document_open = changed.is_ok();
+                }
+            }
+        }
+    }
+}
+
+/// Wait for `receiver` to change; forever without one
+async fn changed<T>(
+    receiver: &mut Option<watch::Receiver<T>>,
+) -> std::result::Result<(), watch::error::RecvError> {
+    match receiver {
+        Some(receiver) => receiver.changed().await,
+        None => std::future::pending().await,
+    }
+}
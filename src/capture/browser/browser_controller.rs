@@ -1,29 +1,280 @@
-use chromiumoxide::cdp::browser_protocol::network::EventLoadingFinished;
-use chromiumoxide::cdp::browser_protocol::page::EventLoadEventFired;
+use chromiumoxide::cdp::browser_protocol::browser::BrowserContextId;
+use chromiumoxide::cdp::browser_protocol::emulation::{
+    SetDeviceMetricsOverrideParams, SetLocaleOverrideParams, SetTimezoneOverrideParams,
+    SetTouchEmulationEnabledParams, SetUserAgentOverrideParams,
+};
+use chromiumoxide::cdp::browser_protocol::network::{
+    ClearBrowserCookiesParams, CookieParam, EmulateNetworkConditionsParams, EnableParams,
+    EventLoadingFailed, EventLoadingFinished, EventRequestWillBeSent, EventResponseReceived,
+    Headers, SetCookiesParams, SetExtraHttpHeadersParams, TimeSinceEpoch,
+};
+use chromiumoxide::cdp::browser_protocol::page::{
+    CaptureScreenshotFormat, EventFrameDetached, EventFrameStartedLoading,
+    EventFrameStoppedLoading, EventLoadEventFired, GetFrameTreeParams, NavigateParams,
+};
+use chromiumoxide::cdp::browser_protocol::service_worker::{
+    EnableParams as ServiceWorkerEnableParams, EventWorkerVersionUpdated,
+    ServiceWorkerVersionStatus,
+};
+use chromiumoxide::cdp::browser_protocol::target::{
+    CreateBrowserContextParams, CreateTargetParams,
+};
+use chromiumoxide::cdp::js_protocol::runtime::{
+    EnableParams as RuntimeEnableParams, EventConsoleApiCalled, EventExceptionThrown,
+};
+use chromiumoxide::page::ScreenshotParams;
 use chromiumoxide::{Browser, BrowserConfig, Page};
 use futures::StreamExt;
+use std::sync::{Arc, Mutex};
 use std::time::Duration;
+use tokio::sync::{mpsc, watch};
+use tokio::task::JoinHandle;
 use tokio::time::timeout;
 use tracing::{debug, error, info, warn};
 use url::Url;
 
+use super::frames::{FrameInfo, FrameLoads};
+use super::initiators::ObservedRequest;
+use super::offline::{response_outcome, OfflineRequest, OfflineRequestLog};
+use super::page_events::PageEvent;
+use crate::capture::environment::CaptureEnvironment;
+use crate::capture::settle::SettleStrategy;
+use crate::capture::variant::{CaptureVariant, VariantCookie};
 use crate::error::{Result, WebMockError};
 
+/// Host used for `--attach-port`
+const ATTACH_HOST: &str = "127.0.0.1";
+
+/// Longest wait for DOMContentLoaded, matching the wait for the load event
+const DOM_CONTENT_LOADED_TIMEOUT: Duration = Duration::from_secs(30);
+
 pub struct BrowserController {
     browser: Browser,
     page: Page,
+    /// Connected to a browser someone else launched, which must outlive the capture
+    attached: bool,
+    /// Context of the tab in a shared browser, routing only that tab through our proxy
+    context: Option<BrowserContextId>,
+    /// What counts as a loaded page
+    settle_strategy: SettleStrategy,
 }
 
 impl BrowserController {
     /// Create a new browser controller with proxy configuration
     pub async fn new(proxy_port: u16) -> Result<Self> {
+        Self::with_environment(proxy_port, &CaptureEnvironment::default()).await
+    }
+
+    /// Create a browser controller that emulates the given locale, timezone, viewport and device
+    pub async fn with_environment(
+        proxy_port: u16,
+        environment: &CaptureEnvironment,
+    ) -> Result<Self> {
         info!(
             "Creating browser controller with proxy port: {}",
             proxy_port
         );
 
+        let config = BrowserConfig::builder()
+            .with_head() // Use headless mode for automation
+            .args(Self::launch_args(proxy_port, environment))
+            .build()
+            .map_err(|e| {
+                error!("Failed to build browser config: {}", e);
+                WebMockError::config(format!("Browser config error: {}", e))
+            })?;
+
+        // Launch browser
+        debug!("Launching Chrome browser with proxy configuration");
+        let (browser, mut handler) = Browser::launch(config).await.map_err(|e| {
+            error!("Failed to launch browser: {}", e);
+            match e.to_string().contains("No such file or directory") {
+                true => WebMockError::ChromeNotFound,
+                false => WebMockError::Browser(Box::new(e)),
+            }
+        })?;
+
+        // Spawn handler task to manage browser process
+        tokio::spawn(async move {
+            while let Some(h) = handler.next().await {
+                if let Err(e) = h {
+                    error!("Browser handler error: {}", e);
+                    break;
+                }
+            }
+        });
+
+        // Create new page
+        debug!("Creating new browser page");
+        let page = browser.new_page("about:blank").await.map_err(|e| {
+            error!("Failed to create new page: {}", e);
+            WebMockError::Browser(Box::new(e))
+        })?;
+
+        // Additional page setup for better compatibility
+        debug!("Setting up page for network capture");
+        // Note: Network capture for HTTPS will be limited due to encryption
+        Self::apply_environment(&page, environment).await?;
+
+        info!("Browser controller created successfully");
+        Ok(Self {
+            browser,
+            page,
+            attached: false,
+            context: None,
+            settle_strategy: SettleStrategy::default(),
+        })
+    }
+
+    /// Drive a new tab of an already running Chrome instead of launching one
+    ///
+    /// `endpoint` is the browser's DevTools WebSocket URL, or the
+    /// `http://host:port` of its remote debugging port. The browser must
+    /// already route its traffic through the capture proxy; only the
+    /// emulation overrides are applied to the new tab.
+    pub async fn attach(endpoint: &str, environment: &CaptureEnvironment) -> Result<Self> {
+        info!("Attaching to running browser at {}", endpoint);
+
+        let (browser, mut handler) = Browser::connect(endpoint).await.map_err(|e| {
+            error!("Failed to attach to browser: {}", e);
+            WebMockError::config(format!(
+                "Could not attach to Chrome at {}: {}. Is it running with --remote-debugging-port?",
+                endpoint, e
+            ))
+        })?;
+
+        tokio::spawn(async move {
+            while let Some(h) = handler.next().await {
+                if let Err(e) = h {
+                    error!("Browser handler error: {}", e);
+                    break;
+                }
+            }
+        });
+
+        debug!("Opening a new tab in the attached browser");
+        let page = browser.new_page("about:blank").await.map_err(|e| {
+            error!("Failed to open a tab: {}", e);
+            WebMockError::Browser(Box::new(e))
+        })?;
+        Self::apply_environment(&page, environment).await?;
+
+        info!("Attached to browser at {}", endpoint);
+        Ok(Self {
+            browser,
+            page,
+            attached: true,
+            context: None,
+            settle_strategy: SettleStrategy::default(),
+        })
+    }
+
+    /// Open a tab in the browser another capture shares, routed through the proxy on `proxy_port`
+    ///
+    /// The tab gets a browser context of its own with a proxy override, so
+    /// the sharing capture keeps recording only its own tabs. Chrome versions
+    /// without per-context proxies ignore the override, which
+    /// [`is_guest`](Self::is_guest) lets the capture check for afterwards.
+    pub async fn share(
+        endpoint: &str,
+        proxy_port: u16,
+        environment: &CaptureEnvironment,
+    ) -> Result<Self> {
+        info!("Opening a tab in the shared browser at {}", endpoint);
+
+        let (browser, mut handler) = Browser::connect(endpoint)
+            .await
+            .map_err(|e| WebMockError::Browser(Box::new(e)))?;
+        tokio::spawn(async move {
+            while let Some(h) = handler.next().await {
+                if let Err(e) = h {
+                    error!("Browser handler error: {}", e);
+                    break;
+                }
+            }
+        });
+
+        let context = browser
+            .create_browser_context(CreateBrowserContextParams {
+                proxy_server: Some(format!("127.0.0.1:{}", proxy_port)),
+                ..Default::default()
+            })
+            .await
+            .map_err(|e| WebMockError::Browser(Box::new(e)))?;
+        let target = CreateTargetParams::builder()
+            .url("about:blank")
+            .browser_context_id(context.clone())
+            .build()
+            .map_err(|e| WebMockError::config(format!("Invalid tab parameters: {}", e)))?;
+        let page = match browser.new_page(target).await {
+            Ok(page) => page,
+            Err(e) => {
+                let _ = browser.dispose_browser_context(context).await;
+                return Err(WebMockError::Browser(Box::new(e)));
+            }
+        };
+        Self::apply_environment(&page, environment).await?;
+
+        info!("Opened a tab in the shared browser at {}", endpoint);
+        Ok(Self {
+            browser,
+            page,
+            attached: true,
+            context: Some(context),
+            settle_strategy: SettleStrategy::default(),
+        })
+    }
+
+    /// DevTools endpoint for `--attach` or `--attach-port`
+    ///
+    /// A bare port is taken as a local remote debugging port. WebSocket and
+    /// HTTP URLs are passed on as given.
+    pub fn attach_endpoint(
+        attach: Option<&str>,
+        attach_port: Option<u16>,
+    ) -> Result<Option<String>> {
+        match (attach, attach_port) {
+            (Some(_), Some(_)) => Err(WebMockError::config(
+                "Use either --attach or --attach-port, not both",
+            )),
+            (Some(endpoint), None) => {
+                let url = Url::parse(endpoint)
+                    .map_err(|e| WebMockError::InvalidUrl(endpoint.to_string(), e.to_string()))?;
+                match url.scheme() {
+                    "ws" | "wss" | "http" | "https" => Ok(Some(endpoint.to_string())),
+                    scheme => Err(WebMockError::InvalidUrl(
+                        endpoint.to_string(),
+                        format!(
+                            "Unsupported scheme '{}'. Use a ws:// DevTools URL or http://host:port",
+                            scheme
+                        ),
+                    )),
+                }
+            }
+            (None, Some(port)) => Ok(Some(format!("http://{}:{}", ATTACH_HOST, port))),
+            (None, None) => Ok(None),
+        }
+    }
+
+    /// Check whether the browser was attached to rather than launched
+    pub fn is_attached(&self) -> bool {
+        self.attached
+    }
+
+    /// Check whether the tab is in a browser another capture launched and shares
+    pub fn is_guest(&self) -> bool {
+        self.context.is_some()
+    }
+
+    /// DevTools WebSocket URL of the browser, for other captures to share it
+    pub fn websocket_address(&self) -> &str {
+        self.browser.websocket_address()
+    }
+
+    /// Chrome command line used for capture, routed through the proxy on `proxy_port`
+    pub(crate) fn launch_args(proxy_port: u16, environment: &CaptureEnvironment) -> Vec<String> {
         // Configure browser with minimal proxy settings to avoid HTTPS issues
-        let args = [
+        let mut args = vec![
             // Basic browser settings
             "--no-sandbox".to_string(),
             "--disable-dev-shm-usage".to_string(),
@@ -61,54 +312,458 @@ impl BrowserController {
             "--ignore-certificate-errors-spki-list".to_string(),
             "--disable-web-security".to_string(),
             "--allow-running-insecure-content".to_string(),
-            "--disable-features=VizDisplayCompositor".to_string(),
+            // Keep cross-origin iframes in the page's process, so their frame
+            // and network events reach the page's session
+            "--disable-features=VizDisplayCompositor,IsolateOrigins,site-per-process".to_string(),
+            "--disable-site-isolation-trials".to_string(),
             "--aggressive-cache-discard".to_string(),
             // Proxy configuration for both HTTP and HTTPS
             format!("--proxy-server=127.0.0.1:{}", proxy_port),
         ];
 
-        let config = BrowserConfig::builder()
-            .with_head() // Use headless mode for automation
-            .args(args)
-            .build()
+        // Match the UI language and window to the emulated environment
+        if let Some(locale) = &environment.locale {
+            args.push(format!("--lang={}", locale));
+        }
+        if let Some(viewport) = environment.viewport {
+            args.push(format!(
+                "--window-size={},{}",
+                viewport.width, viewport.height
+            ));
+        }
+        if let Some(user_agent) = &environment.user_agent {
+            args.push(format!("--user-agent={}", user_agent));
+        }
+
+        args
+    }
+
+    /// Apply locale, timezone, viewport and device overrides before the first navigation
+    async fn apply_environment(page: &Page, environment: &CaptureEnvironment) -> Result<()> {
+        let cdp_error = |e: chromiumoxide::error::CdpError| {
+            error!("Failed to apply capture environment: {}", e);
+            WebMockError::Browser(Box::new(e))
+        };
+
+        if let Some(locale) = &environment.locale {
+            debug!("Emulating locale: {}", locale);
+            // Emulation takes an ICU style locale such as fr_FR
+            page.execute(
+                SetLocaleOverrideParams::builder()
+                    .locale(locale.replace('-', "_"))
+                    .build(),
+            )
+            .await
+            .map_err(cdp_error)?;
+        }
+
+        if let Some(accept_language) = environment.accept_language() {
+            page.execute(EnableParams::default())
+                .await
+                .map_err(cdp_error)?;
+            page.execute(SetExtraHttpHeadersParams::new(Headers::new(
+                serde_json::json!({ "Accept-Language": accept_language }),
+            )))
+            .await
+            .map_err(cdp_error)?;
+        }
+
+        if let Some(timezone) = &environment.timezone {
+            debug!("Emulating timezone: {}", timezone);
+            page.execute(SetTimezoneOverrideParams::new(timezone.clone()))
+                .await
+                .map_err(cdp_error)?;
+        }
+
+        if let Some(user_agent) = &environment.user_agent {
+            debug!("Emulating user agent: {}", user_agent);
+            page.execute(SetUserAgentOverrideParams::new(user_agent.clone()))
+                .await
+                .map_err(cdp_error)?;
+        }
+
+        let preset = environment.device_preset();
+        if let Some(viewport) = environment.viewport {
+            debug!("Emulating viewport: {}", viewport);
+            let (scale, mobile) = preset
+                .map(|preset| (preset.device_scale_factor, preset.mobile))
+                .unwrap_or((1.0, false));
+            page.execute(SetDeviceMetricsOverrideParams::new(
+                viewport.width,
+                viewport.height,
+                scale,
+                mobile,
+            ))
+            .await
+            .map_err(cdp_error)?;
+        }
+
+        if let Some(preset) = preset.filter(|preset| preset.touch) {
+            debug!("Emulating touch input for {}", preset.name);
+            page.execute(
+                SetTouchEmulationEnabledParams::builder()
+                    .enabled(true)
+                    .max_touch_points(5)
+                    .build()
+                    .map_err(WebMockError::config)?,
+            )
+            .await
+            .map_err(cdp_error)?;
+        }
+
+        Ok(())
+    }
+
+    /// Send the page's console messages and uncaught errors to `events`
+    ///
+    /// Events are forwarded until the page closes. Once the channel is full
+    /// further events are dropped rather than holding up the browser.
+    pub async fn forward_page_events(&self, events: mpsc::Sender<PageEvent>) -> Result<()> {
+        let cdp_error = |e: chromiumoxide::error::CdpError| {
+            error!("Failed to subscribe to page events: {}", e);
+            WebMockError::Browser(Box::new(e))
+        };
+
+        let mut console = self
+            .page
+            .event_listener::<EventConsoleApiCalled>()
+            .await
+            .map_err(cdp_error)?;
+        let mut exceptions = self
+            .page
+            .event_listener::<EventExceptionThrown>()
+            .await
+            .map_err(cdp_error)?;
+        self.page
+            .execute(RuntimeEnableParams::default())
+            .await
+            .map_err(cdp_error)?;
+
+        tokio::spawn(async move {
+            let mut dropped = 0usize;
+            loop {
+                let event = tokio::select! {
+                    Some(event) = console.next() => PageEvent::from_console(&event),
+                    Some(event) = exceptions.next() => PageEvent::from_exception(&event),
+                    else => break,
+                };
+                if events.try_send(event).is_err() {
+                    if events.is_closed() {
+                        break;
+                    }
+                    if dropped == 0 {
+                        warn!("Too many console messages, later ones are not kept");
+                    }
+                    dropped += 1;
+                }
+            }
+            debug!("Page event stream ended ({} dropped)", dropped);
+        });
+
+        Ok(())
+    }
+
+    /// Forward every request the page starts, with its initiator, to `requests`
+    ///
+    /// Like [`forward_page_events`](Self::forward_page_events), requests past
+    /// the channel's capacity are dropped rather than holding up the browser.
+    pub async fn forward_requests(&self, requests: mpsc::Sender<ObservedRequest>) -> Result<()> {
+        let cdp_error = |e: chromiumoxide::error::CdpError| {
+            error!("Failed to subscribe to network events: {}", e);
+            WebMockError::Browser(Box::new(e))
+        };
+
+        let mut will_be_sent = self
+            .page
+            .event_listener::<EventRequestWillBeSent>()
+            .await
+            .map_err(cdp_error)?;
+        self.page
+            .execute(EnableParams::default())
+            .await
+            .map_err(cdp_error)?;
+
+        tokio::spawn(async move {
+            let mut dropped = 0usize;
+            while let Some(event) = will_be_sent.next().await {
+                if requests
+                    .try_send(ObservedRequest::from_event(&event))
+                    .is_err()
+                {
+                    if requests.is_closed() {
+                        break;
+                    }
+                    dropped += 1;
+                }
+            }
+            debug!("Network event stream ended ({} dropped)", dropped);
+        });
+
+        Ok(())
+    }
+
+    /// Follow which frames of the page are loading
+    ///
+    /// The receiver is updated on every frame that starts or stops loading
+    /// or is removed. Subscribe before navigating, so frames added during the
+    /// first load are seen starting.
+    pub async fn track_frames(&self) -> Result<watch::Receiver<FrameLoads>> {
+        let cdp_error = |e: chromiumoxide::error::CdpError| {
+            error!("Failed to subscribe to frame events: {}", e);
+            WebMockError::Browser(Box::new(e))
+        };
+
+        let mut started = self
+            .page
+            .event_listener::<EventFrameStartedLoading>()
+            .await
+            .map_err(cdp_error)?;
+        let mut stopped = self
+            .page
+            .event_listener::<EventFrameStoppedLoading>()
+            .await
+            .map_err(cdp_error)?;
+        let mut detached = self
+            .page
+            .event_listener::<EventFrameDetached>()
+            .await
+            .map_err(cdp_error)?;
+
+        let (loads, receiver) = watch::channel(FrameLoads::default());
+        tokio::spawn(async move {
+            loop {
+                tokio::select! {
+                    Some(event) = started.next() => {
+                        loads.send_modify(|loads| loads.started(event.frame_id.inner()))
+                    }
+                    Some(event) = stopped.next() => {
+                        loads.send_modify(|loads| loads.stopped(event.frame_id.inner()))
+                    }
+                    Some(event) = detached.next() => {
+                        loads.send_modify(|loads| loads.detached(event.frame_id.inner()))
+                    }
+                    else => break,
+                }
+            }
+            debug!("Frame event stream ended");
+        });
+
+        Ok(receiver)
+    }
+
+    /// List the frames attached to the page, the main frame first
+    pub async fn frames(&self) -> Result<Vec<FrameInfo>> {
+        let tree = self
+            .page
+            .execute(GetFrameTreeParams::default())
+            .await
             .map_err(|e| {
-                error!("Failed to build browser config: {}", e);
-                WebMockError::config(format!("Browser config error: {}", e))
+                error!("Failed to get frame tree: {}", e);
+                WebMockError::Browser(Box::new(e))
             })?;
+        Ok(FrameInfo::from_tree(&tree.result.frame_tree))
+    }
 
-        // Launch browser
-        debug!("Launching Chrome browser with proxy configuration");
-        let (browser, mut handler) = Browser::launch(config).await.map_err(|e| {
-            error!("Failed to launch browser: {}", e);
-            match e.to_string().contains("No such file or directory") {
-                true => WebMockError::ChromeNotFound,
-                false => WebMockError::Browser(Box::new(e)),
-            }
-        })?;
+    /// Watch for a service worker of the page to activate
+    ///
+    /// The receiver holds the script URL of the latest activated worker, or
+    /// `None` until one activates. Subscribe before navigating, since a
+    /// worker usually activates during the first load.
+    pub async fn watch_service_worker(&self) -> Result<watch::Receiver<Option<String>>> {
+        let cdp_error = |e: chromiumoxide::error::CdpError| {
+            error!("Failed to subscribe to service worker events: {}", e);
+            WebMockError::Browser(Box::new(e))
+        };
 
-        // Spawn handler task to manage browser process
+        let mut updates = self
+            .page
+            .event_listener::<EventWorkerVersionUpdated>()
+            .await
+            .map_err(cdp_error)?;
+        self.page
+            .execute(ServiceWorkerEnableParams::default())
+            .await
+            .map_err(cdp_error)?;
+
+        let (activated, receiver) = watch::channel(None);
         tokio::spawn(async move {
-            while let Some(h) = handler.next().await {
-                if let Err(e) = h {
-                    error!("Browser handler error: {}", e);
-                    break;
+            while let Some(event) = updates.next().await {
+                for version in &event.versions {
+                    if version.status == ServiceWorkerVersionStatus::Activated {
+                        debug!("Service worker activated: {}", version.script_url);
+                        activated.send_replace(Some(version.script_url.clone()));
+                    }
                 }
             }
         });
 
-        // Create new page
-        debug!("Creating new browser page");
-        let page = browser.new_page("about:blank").await.map_err(|e| {
-            error!("Failed to create new page: {}", e);
+        Ok(receiver)
+    }
+
+    /// Turn offline emulation of the page on or off
+    pub async fn set_offline(&self, offline: bool) -> Result<()> {
+        let cdp_error = |e: chromiumoxide::error::CdpError| {
+            error!("Failed to change network emulation: {}", e);
             WebMockError::Browser(Box::new(e))
-        })?;
+        };
 
-        // Additional page setup for better compatibility
-        debug!("Setting up page for network capture");
-        // Note: Network capture for HTTPS will be limited due to encryption
+        debug!("Setting offline emulation to {}", offline);
+        self.page
+            .execute(EnableParams::default())
+            .await
+            .map_err(cdp_error)?;
+        self.page
+            .execute(EmulateNetworkConditionsParams::new(
+                offline, 0.0, -1.0, -1.0,
+            ))
+            .await
+            .map_err(cdp_error)?;
+        Ok(())
+    }
 
-        info!("Browser controller created successfully");
-        Ok(Self { browser, page })
+    /// Log how each request the page makes from now on is answered
+    ///
+    /// Used for the offline reload, where the proxy sees little of the
+    /// traffic; [`OfflineRequestLogger::finish`] stops logging.
+    pub async fn log_requests(&self) -> Result<OfflineRequestLogger> {
+        let cdp_error = |e: chromiumoxide::error::CdpError| {
+            error!("Failed to subscribe to network events: {}", e);
+            WebMockError::Browser(Box::new(e))
+        };
+
+        let mut started = self
+            .page
+            .event_listener::<EventRequestWillBeSent>()
+            .await
+            .map_err(cdp_error)?;
+        let mut responded = self
+            .page
+            .event_listener::<EventResponseReceived>()
+            .await
+            .map_err(cdp_error)?;
+        let mut failed = self
+            .page
+            .event_listener::<EventLoadingFailed>()
+            .await
+            .map_err(cdp_error)?;
+
+        let log = Arc::new(Mutex::new(OfflineRequestLog::default()));
+        let task = tokio::spawn({
+            let log = log.clone();
+            async move {
+                loop {
+                    tokio::select! {
+                        Some(event) = started.next() => log.lock().unwrap().started(
+                            event.request_id.inner(),
+                            &event.request.method,
+                            &event.request.url,
+                        ),
+                        Some(event) = responded.next() => log
+                            .lock()
+                            .unwrap()
+                            .responded(event.request_id.inner(), response_outcome(&event.response)),
+                        Some(event) = failed.next() => log
+                            .lock()
+                            .unwrap()
+                            .failed(event.request_id.inner(), &event.error_text),
+                        else => break,
+                    }
+                }
+            }
+        });
+
+        Ok(OfflineRequestLogger { log, task })
+    }
+
+    /// Replace the browser's cookies and extra headers with those of a capture variant
+    ///
+    /// Cookies left from the previous load are cleared first, so a variant
+    /// only carries its own session. The environment's `Accept-Language` is
+    /// kept among the extra headers.
+    pub async fn apply_variant(
+        &self,
+        variant: &CaptureVariant,
+        environment: &CaptureEnvironment,
+    ) -> Result<()> {
+        let cdp_error = |e: chromiumoxide::error::CdpError| {
+            error!("Failed to apply variant '{}': {}", variant.name, e);
+            WebMockError::Browser(Box::new(e))
+        };
+
+        debug!(
+            "Applying variant '{}': {} cookies, {} headers",
+            variant.name,
+            variant.cookies.len(),
+            variant.headers.len()
+        );
+        self.page
+            .execute(EnableParams::default())
+            .await
+            .map_err(cdp_error)?;
+        self.page
+            .execute(ClearBrowserCookiesParams::default())
+            .await
+            .map_err(cdp_error)?;
+        if !variant.cookies.is_empty() {
+            let cookies = variant.cookies.iter().map(cookie_param).collect();
+            self.page
+                .execute(SetCookiesParams::new(cookies))
+                .await
+                .map_err(cdp_error)?;
+        }
+
+        let mut headers = serde_json::Map::new();
+        if let Some(accept_language) = environment.accept_language() {
+            headers.insert("Accept-Language".to_string(), accept_language.into());
+        }
+        for (name, value) in &variant.headers {
+            headers.insert(name.clone(), value.clone().into());
+        }
+        self.page
+            .execute(SetExtraHttpHeadersParams::new(Headers::new(
+                serde_json::Value::Object(headers),
+            )))
+            .await
+            .map_err(cdp_error)?;
+        Ok(())
+    }
+
+    /// Take a full-page PNG screenshot of the page
+    pub async fn screenshot(&self) -> Result<Vec<u8>> {
+        let params = ScreenshotParams::builder()
+            .format(CaptureScreenshotFormat::Png)
+            .full_page(true)
+            .build();
+        self.page.screenshot(params).await.map_err(|e| {
+            error!("Failed to take screenshot: {}", e);
+            WebMockError::Browser(Box::new(e))
+        })
+    }
+
+    /// Serialize the page's current DOM as HTML
+    pub async fn content(&self) -> Result<String> {
+        self.page.content().await.map_err(|e| {
+            error!("Failed to read page content: {}", e);
+            WebMockError::Browser(Box::new(e))
+        })
+    }
+
+    /// Reload the page and wait for it to load
+    pub async fn reload(&mut self) -> Result<()> {
+        info!("Reloading page");
+        match timeout(Duration::from_secs(45), self.page.reload()).await {
+            Ok(Ok(_)) => {}
+            // An offline reload the service worker can't answer fails the navigation
+            Ok(Err(e)) => warn!("Reload failed: {}", e),
+            Err(_) => {
+                error!("Reload timed out after 45 seconds");
+                return Err(WebMockError::timeout(
+                    "Page reload",
+                    Duration::from_secs(45),
+                ));
+            }
+        }
+        self.wait_for_load().await
     }
 
     /// Navigate to the specified URL
@@ -144,6 +799,11 @@ impl BrowserController {
             );
         }
 
+        // Other strategies don't wait for the load event, which may never fire
+        if self.settle_strategy != SettleStrategy::Load {
+            return self.start_navigation(url).await;
+        }
+
         // Navigate to URL with extended timeout and better error handling
         debug!("Starting navigation to: {}", url);
         let navigation_result = timeout(Duration::from_secs(45), self.page.goto(url)).await;
@@ -156,35 +816,100 @@ impl BrowserController {
             Ok(Err(e)) => {
                 let error_msg = e.to_string();
                 error!("Navigation failed: {}", error_msg);
+                Err(Self::navigation_error(url, &error_msg)
+                    .unwrap_or_else(|| WebMockError::Browser(Box::new(e))))
+            }
+            Err(_) => {
+                error!("Navigation timed out after 45 seconds");
+                Err(WebMockError::timeout(
+                    "Page navigation",
+                    Duration::from_secs(45),
+                ))
+            }
+        }
+    }
 
-                // Provide more specific error messages
-                if error_msg.contains("ERR_CONNECTION_CLOSED") {
-                    return Err(WebMockError::Browser(Box::new(chromiumoxide::error::CdpError::Io(std::io::Error::new(
-                        std::io::ErrorKind::ConnectionAborted,
-                        format!("Connection to {} was closed. This may be due to:\n  - Network connectivity issues\n  - The target server rejecting proxy connections\n  - Firewall or security software blocking the connection\n  - The website using advanced security measures", url)
-                    )))));
-                } else if error_msg.contains("ERR_PROXY_CONNECTION_FAILED") {
-                    return Err(WebMockError::config(
-                        "Proxy connection failed. The internal proxy server may not be running properly."
-                    ));
-                } else if error_msg.contains("ERR_NAME_NOT_RESOLVED") {
-                    return Err(WebMockError::Browser(Box::new(chromiumoxide::error::CdpError::Io(std::io::Error::new(
-                        std::io::ErrorKind::NotFound,
-                        format!("Could not resolve hostname for {}. Please check the URL and your internet connection.", url)
-                    )))));
+    /// Start navigating to `url`, returning once the new document is committed
+    async fn start_navigation(&self, url: &str) -> Result<()> {
+        debug!("Starting navigation to: {}", url);
+        match timeout(
+            Duration::from_secs(45),
+            self.page.execute(NavigateParams::new(url)),
+        )
+        .await
+        {
+            Ok(Ok(response)) => match &response.result.error_text {
+                Some(error_text) => {
+                    error!("Navigation failed: {}", error_text);
+                    Err(Self::navigation_error(url, error_text).unwrap_or_else(|| {
+                        WebMockError::config(format!(
+                            "Navigation to {} failed: {}",
+                            url, error_text
+                        ))
+                    }))
                 }
-
-                Err(WebMockError::Browser(Box::new(e)))
+                None => {
+                    info!("Navigation to {} committed", url);
+                    Ok(())
+                }
+            },
+            Ok(Err(e)) => {
+                error!("Navigation failed: {}", e);
+                Err(Self::navigation_error(url, &e.to_string())
+                    .unwrap_or_else(|| WebMockError::Browser(Box::new(e))))
             }
             Err(_) => {
                 error!("Navigation timed out after 45 seconds");
-                Err(WebMockError::Timeout(45))
+                Err(WebMockError::timeout(
+                    "Page navigation",
+                    Duration::from_secs(45),
+                ))
             }
         }
     }
 
-    /// Wait for page to fully load including all resources
+    /// A more specific error for the navigation failures people can do something about
+    fn navigation_error(url: &str, error_msg: &str) -> Option<WebMockError> {
+        if error_msg.contains("ERR_CONNECTION_CLOSED") {
+            Some(WebMockError::Browser(Box::new(chromiumoxide::error::CdpError::Io(std::io::Error::new(
+                std::io::ErrorKind::ConnectionAborted,
+                format!("Connection to {} was closed. This may be due to:\n  - Network connectivity issues\n  - The target server rejecting proxy connections\n  - Firewall or security software blocking the connection\n  - The website using advanced security measures", url)
+            )))))
+        } else if error_msg.contains("ERR_PROXY_CONNECTION_FAILED") {
+            Some(WebMockError::config(
+                "Proxy connection failed. The internal proxy server may not be running properly.",
+            ))
+        } else if error_msg.contains("ERR_NAME_NOT_RESOLVED") {
+            Some(WebMockError::Browser(Box::new(chromiumoxide::error::CdpError::Io(std::io::Error::new(
+                std::io::ErrorKind::NotFound,
+                format!("Could not resolve hostname for {}. Please check the URL and your internet connection.", url)
+            )))))
+        } else {
+            None
+        }
+    }
+
+    /// Choose what counts as a loaded page for `navigate` and `wait_for_load`
+    pub fn set_settle_strategy(&mut self, strategy: SettleStrategy) {
+        self.settle_strategy = strategy;
+    }
+
+    /// Wait until the page counts as loaded under the settle strategy
+    ///
+    /// With the default strategy that is the load event; the others return
+    /// once the DOM is parsed, or after their fixed wait.
     pub async fn wait_for_load(&mut self) -> Result<()> {
+        match self.settle_strategy {
+            SettleStrategy::Load => {}
+            SettleStrategy::NetworkIdle | SettleStrategy::DomContentLoaded => {
+                return self.wait_for_dom_content_loaded().await;
+            }
+            SettleStrategy::Fixed(wait) => {
+                debug!("Waiting {:?} before counting the page as loaded", wait);
+                tokio::time::sleep(wait).await;
+                return Ok(());
+            }
+        }
         debug!("Waiting for page load completion");
 
         // Wait for load event with timeout
@@ -206,6 +931,34 @@ impl BrowserController {
         }
     }
 
+    /// Wait until the DOM of the current document is parsed
+    ///
+    /// Polls `document.readyState` rather than listening for the event, which
+    /// may have fired before a listener could be attached.
+    async fn wait_for_dom_content_loaded(&self) -> Result<()> {
+        debug!("Waiting for DOM content loaded");
+        let started = std::time::Instant::now();
+        while started.elapsed() < DOM_CONTENT_LOADED_TIMEOUT {
+            match self.page.evaluate("document.readyState").await {
+                Ok(result) => {
+                    let state: Option<String> = result.into_value().ok();
+                    if matches!(state.as_deref(), Some("interactive" | "complete")) {
+                        info!("DOM content loaded");
+                        return Ok(());
+                    }
+                }
+                // The page is between documents while a navigation commits
+                Err(e) => debug!("Ready state unavailable: {}", e),
+            }
+            tokio::time::sleep(Duration::from_millis(100)).await;
+        }
+        warn!(
+            "DOM content not loaded after {} seconds, continuing anyway",
+            DOM_CONTENT_LOADED_TIMEOUT.as_secs()
+        );
+        Ok(())
+    }
+
     /// Internal method to wait for various load events
     pub async fn wait_for_load_events(&mut self) -> Result<()> {
         debug!("Waiting for DOM content loaded event");
@@ -274,8 +1027,27 @@ impl BrowserController {
         Ok(title.unwrap_or_else(|| "Untitled".to_string()))
     }
 
+    /// Close the tab the capture opened, leaving an attached browser running
+    pub async fn close_tab(mut self) -> Result<()> {
+        info!("Closing capture tab");
+        if let Err(e) = self.page.clone().close().await {
+            warn!("Failed to close tab gracefully: {}", e);
+        }
+        if let Some(context) = self.context.take() {
+            if let Err(e) = self.browser.dispose_browser_context(context).await {
+                warn!("Failed to dispose the tab's browser context: {}", e);
+            }
+        }
+        Ok(())
+    }
+
     /// Close the browser and cleanup resources
+    ///
+    /// An attached browser isn't ours to close, so only its tab is.
     pub async fn close(mut self) -> Result<()> {
+        if self.attached {
+            return self.close_tab().await;
+        }
         info!("Closing browser and cleaning up resources");
 
         // Close the page first
@@ -296,8 +1068,36 @@ impl BrowserController {
     }
 }
 
+/// Requests logged by [`BrowserController::log_requests`]
+pub struct OfflineRequestLogger {
+    log: Arc<Mutex<OfflineRequestLog>>,
+    task: JoinHandle<()>,
+}
+
+impl OfflineRequestLogger {
+    /// Stop logging and return the requests seen so far
+    pub fn finish(self) -> Vec<OfflineRequest> {
+        self.task.abort();
+        let mut log = self.log.lock().unwrap_or_else(|e| e.into_inner());
+        std::mem::take(&mut *log).finish()
+    }
+}
+
 impl Drop for BrowserController {
     fn drop(&mut self) {
         debug!("BrowserController dropped, browser should be cleaned up");
     }
 }
+
+/// CDP form of a cookie from a variant's cookie file
+fn cookie_param(cookie: &VariantCookie) -> CookieParam {
+    let mut param = CookieParam::new(cookie.name.clone(), cookie.value.clone());
+    param.domain = Some(cookie.domain.clone());
+    param.path = Some(cookie.path.clone());
+    param.secure = Some(cookie.secure);
+    param.http_only = Some(cookie.http_only);
+    param.expires = cookie
+        .expires
+        .map(|expires| TimeSinceEpoch::new(expires as f64));
+    param
+}
@@ -0,0 +1,86 @@
+use chromiumoxide::cdp::browser_protocol::page::FrameTree;
+use std::collections::HashMap;
+
+/// A frame of the page, as `Page.getFrameTree` lists it
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FrameInfo {
+    pub id: String,
+    /// `None` for the main frame
+    pub parent_id: Option<String>,
+    pub url: String,
+}
+
+impl FrameInfo {
+    /// List the frames of a frame tree, parents before their children
+    pub fn from_tree(tree: &FrameTree) -> Vec<Self> {
+        let mut frames = Vec::new();
+        let mut stack = vec![tree];
+        while let Some(tree) = stack.pop() {
+            frames.push(Self {
+                id: tree.frame.id.inner().clone(),
+                parent_id: tree.frame.parent_id.as_ref().map(|id| id.inner().clone()),
+                url: tree.frame.url.clone(),
+            });
+            if let Some(children) = &tree.child_frames {
+                stack.extend(children.iter().rev());
+            }
+        }
+        frames
+    }
+
+    pub fn is_main(&self) -> bool {
+        self.parent_id.is_none()
+    }
+}
+
+/// Which frames of the page are loading, from its frame load events
+///
+/// The main frame's load event doesn't wait for iframes added later, such as
+/// a payment widget inserted by a script, so network idle detection also
+/// waits for every frame that started loading to stop.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct FrameLoads {
+    /// Whether each frame seen starting or stopping is still loading
+    loading: HashMap<String, bool>,
+}
+
+impl FrameLoads {
+    /// `Page.frameStartedLoading`
+    pub fn started(&mut self, frame_id: &str) {
+        self.loading.insert(frame_id.to_string(), true);
+    }
+
+    /// `Page.frameStoppedLoading`
+    pub fn stopped(&mut self, frame_id: &str) {
+        self.loading.insert(frame_id.to_string(), false);
+    }
+
+    /// `Page.frameDetached`; a removed frame won't report stopping
+    pub fn detached(&mut self, frame_id: &str) {
+        self.loading.remove(frame_id);
+    }
+
+    /// IDs of the frames that started loading and haven't stopped
+    pub fn loading(&self) -> Vec<&str> {
+        let mut loading: Vec<&str> = self
+            .loading
+            .iter()
+            .filter(|(_, loading)| **loading)
+            .map(|(id, _)| id.as_str())
+            .collect();
+        loading.sort_unstable();
+        loading
+    }
+
+    /// The frames of `attached` still loading
+    ///
+    /// Frames missing from `attached` were removed without their detach
+    /// event being seen, and frames never seen starting were loaded before
+    /// tracking began, so neither holds up the capture.
+    pub fn pending<'a>(&self, attached: &'a [FrameInfo]) -> Vec<&'a FrameInfo> {
+        attached
+            .iter()
+            .filter(|frame| self.loading.get(&frame.id).copied().unwrap_or(false))
+            .collect()
+    }
+}
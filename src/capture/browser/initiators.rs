@@ -0,0 +1,111 @@
+use chromiumoxide::cdp::browser_protocol::network::{
+    EventRequestWillBeSent, Initiator, InitiatorType,
+};
+use std::collections::{HashMap, VecDeque};
+use url::Url;
+
+use crate::capture::canonical_url::canonical_url;
+use crate::capture::proxy::records::{InitiatorKind, RequestInitiator, RequestRecord};
+
+/// Most browser requests kept per capture for initiator matching
+pub const MAX_OBSERVED_REQUESTS: usize = 50_000;
+
+/// A request as the browser announced it, with what triggered it
+#[derive(Debug, Clone, PartialEq)]
+pub struct ObservedRequest {
+    pub method: String,
+    pub url: String,
+    pub initiator: RequestInitiator,
+    /// Frame the request was made for, if Chrome named one
+    pub frame_id: Option<String>,
+}
+
+impl ObservedRequest {
+    /// Convert a `Network.requestWillBeSent` event
+    pub fn from_event(event: &EventRequestWillBeSent) -> Self {
+        Self {
+            method: event.request.method.clone(),
+            url: event.request.url.clone(),
+            initiator: initiator(&event.initiator),
+            frame_id: event.frame_id.as_ref().map(|id| id.inner().clone()),
+        }
+    }
+}
+
+fn initiator(initiator: &Initiator) -> RequestInitiator {
+    let kind = match initiator.r#type {
+        InitiatorType::Parser => InitiatorKind::Parser,
+        InitiatorType::Script => InitiatorKind::Script,
+        InitiatorType::Preload => InitiatorKind::Preload,
+        InitiatorType::SignedExchange => InitiatorKind::SignedExchange,
+        InitiatorType::Preflight => InitiatorKind::Preflight,
+        InitiatorType::Other => InitiatorKind::Other,
+    };
+
+    // Script initiators usually only name the script in their stack
+    let top_frame = initiator
+        .stack
+        .as_ref()
+        .and_then(|stack| stack.call_frames.iter().find(|frame| !frame.url.is_empty()));
+    let (url, line) = match (&initiator.url, top_frame) {
+        (Some(url), _) => (Some(url.clone()), initiator.line_number),
+        (None, Some(frame)) => (Some(frame.url.clone()), Some(frame.line_number as f64)),
+        (None, None) => (None, None),
+    };
+
+    RequestInitiator {
+        kind,
+        url,
+        // CDP line numbers start at 0
+        line: line.map(|line| line as u32 + 1),
+    }
+}
+
+/// Give each record the initiator and frame of the browser request it belongs to
+///
+/// Requests are paired by method and URL, in the order they were seen: the
+/// n-th record of a URL gets the n-th browser request for it. Browser
+/// requests without a counterpart, such as ones served from cache or whose
+/// URL the proxy saw differently, are dropped instead of being attributed to
+/// another record. Returns how many records got an initiator.
+pub fn attach_initiators(records: &mut [RequestRecord], observed: Vec<ObservedRequest>) -> usize {
+    let mut pending: HashMap<(String, String), VecDeque<ObservedRequest>> = HashMap::new();
+    for request in observed {
+        pending
+            .entry(match_key(&request.method, &request.url))
+            .or_default()
+            .push_back(request);
+    }
+
+    let mut attached = 0;
+    for record in records.iter_mut() {
+        let Some(queue) = pending.get_mut(&match_key(&record.method, &record.url)) else {
+            continue;
+        };
+        if let Some(request) = queue.pop_front() {
+            record.initiator = Some(request.initiator);
+            record.frame_id = request.frame_id;
+            attached += 1;
+        }
+    }
+    attached
+}
+
+/// Method and URL a record and a browser request are paired on
+///
+/// The proxy can see default ports spelled out and the browser keeps
+/// fragments out of the request, so URLs are compared normalized.
+fn match_key(method: &str, url: &str) -> (String, String) {
+    (method.to_ascii_uppercase(), normalize_url(url))
+}
+
+/// Canonical form of a URL without its fragment, or the URL as is if invalid
+pub(crate) fn normalize_url(url: &str) -> String {
+    match Url::parse(url) {
+        Ok(mut parsed) => {
+            parsed.set_fragment(None);
+            canonical_url(parsed.as_str())
+        }
+        Err(_) => url.to_string(),
+    }
+}
@@ -1,2 +1,11 @@
 mod browser_controller;
+pub mod frames;
+pub mod initiators;
+pub mod offline;
+pub mod page_events;
+
 pub use browser_controller::*;
+pub use frames::{FrameInfo, FrameLoads};
+pub use initiators::{attach_initiators, ObservedRequest};
+pub use offline::{OfflineComparison, OfflineOutcome, OfflineReload, OfflineRequest};
+pub use page_events::{PageEvent, PageEventLevel, PageEventSource};
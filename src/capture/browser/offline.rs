@@ -0,0 +1,202 @@
+use chromiumoxide::cdp::browser_protocol::network::Response;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::time::Duration;
+
+use super::initiators::normalize_url;
+use crate::capture::proxy::records::RequestRecord;
+
+/// How long `--emulate-offline-after-load` waits for a service worker to activate
+pub const SERVICE_WORKER_TIMEOUT: Duration = Duration::from_secs(15);
+
+/// How a request of the offline reload was answered
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum OfflineOutcome {
+    /// Answered by the page's service worker
+    ServiceWorker,
+    /// Answered from the browser's disk or prefetch cache
+    Cache,
+    /// Went to the network despite the offline emulation
+    Network,
+    /// Failed, usually with `net::ERR_INTERNET_DISCONNECTED`
+    Failed,
+}
+
+impl fmt::Display for OfflineOutcome {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            OfflineOutcome::ServiceWorker => "service worker",
+            OfflineOutcome::Cache => "cache",
+            OfflineOutcome::Network => "network",
+            OfflineOutcome::Failed => "failed",
+        })
+    }
+}
+
+/// A request the page made while reloading offline
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct OfflineRequest {
+    pub method: String,
+    pub url: String,
+    pub outcome: OfflineOutcome,
+    /// Chrome's error text for failed requests
+    pub error: Option<String>,
+}
+
+/// The offline reload of a `--emulate-offline-after-load` capture
+///
+/// Stored alongside the online records, which stay what the network served.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct OfflineReload {
+    /// Script URL of the service worker that was active, if one activated in time
+    pub service_worker: Option<String>,
+    /// Requests the page made during the reload, in the order it started them
+    pub requests: Vec<OfflineRequest>,
+    /// Traffic that still reached the proxy during the reload
+    pub records: Vec<RequestRecord>,
+}
+
+impl OfflineReload {
+    /// Requests of the reload answered the given way
+    pub fn with_outcome(&self, outcome: OfflineOutcome) -> impl Iterator<Item = &OfflineRequest> {
+        self.requests
+            .iter()
+            .filter(move |request| request.outcome == outcome)
+    }
+
+    /// Compare the reload with the records of the online load
+    pub fn compare(&self, online: &[RequestRecord]) -> OfflineComparison {
+        let online_urls: HashSet<String> = online
+            .iter()
+            .map(|record| normalize_url(&record.url))
+            .collect();
+        let offline_urls: HashSet<String> = self
+            .requests
+            .iter()
+            .map(|request| normalize_url(&request.url))
+            .collect();
+
+        let mut not_requested: Vec<String> =
+            online_urls.difference(&offline_urls).cloned().collect();
+        not_requested.sort();
+        let mut offline_only: Vec<String> =
+            offline_urls.difference(&online_urls).cloned().collect();
+        offline_only.sort();
+
+        OfflineComparison {
+            online: online_urls.len(),
+            served_offline: self.with_outcome(OfflineOutcome::ServiceWorker).count()
+                + self.with_outcome(OfflineOutcome::Cache).count(),
+            network: self
+                .with_outcome(OfflineOutcome::Network)
+                .map(|request| request.url.clone())
+                .collect(),
+            failed: self
+                .with_outcome(OfflineOutcome::Failed)
+                .map(|request| request.url.clone())
+                .collect(),
+            not_requested,
+            offline_only,
+        }
+    }
+}
+
+/// Online and offline request sets of a capture, side by side
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct OfflineComparison {
+    /// Distinct URLs recorded during the online load
+    pub online: usize,
+    /// Requests of the reload answered by the service worker or the cache
+    pub served_offline: usize,
+    /// URLs the reload still fetched from the network
+    pub network: Vec<String>,
+    /// URLs the reload failed to load
+    pub failed: Vec<String>,
+    /// URLs of the online load the reload never requested
+    pub not_requested: Vec<String>,
+    /// URLs only the reload requested
+    pub offline_only: Vec<String>,
+}
+
+/// How Chrome answered a request, from its `Network.responseReceived` response
+pub fn response_outcome(response: &Response) -> OfflineOutcome {
+    if response.from_service_worker == Some(true) {
+        OfflineOutcome::ServiceWorker
+    } else if response.from_disk_cache == Some(true) || response.from_prefetch_cache == Some(true) {
+        OfflineOutcome::Cache
+    } else {
+        OfflineOutcome::Network
+    }
+}
+
+#[derive(Debug)]
+struct LoggedRequest {
+    method: String,
+    url: String,
+    outcome: Option<(OfflineOutcome, Option<String>)>,
+}
+
+/// Collects the outcome of each request the browser reports during the reload
+#[derive(Debug, Default)]
+pub struct OfflineRequestLog {
+    order: Vec<String>,
+    requests: HashMap<String, LoggedRequest>,
+}
+
+impl OfflineRequestLog {
+    /// Note a `Network.requestWillBeSent`; a redirect replaces the request's URL
+    pub fn started(&mut self, request_id: &str, method: &str, url: &str) {
+        match self.requests.get_mut(request_id) {
+            Some(request) => request.url = url.to_string(),
+            None => {
+                self.order.push(request_id.to_string());
+                self.requests.insert(
+                    request_id.to_string(),
+                    LoggedRequest {
+                        method: method.to_string(),
+                        url: url.to_string(),
+                        outcome: None,
+                    },
+                );
+            }
+        }
+    }
+
+    /// Note a `Network.responseReceived`, see [`response_outcome`]
+    pub fn responded(&mut self, request_id: &str, outcome: OfflineOutcome) {
+        self.settle(request_id, outcome, None);
+    }
+
+    /// Note a `Network.loadingFailed`
+    pub fn failed(&mut self, request_id: &str, error: &str) {
+        self.settle(request_id, OfflineOutcome::Failed, Some(error.to_string()));
+    }
+
+    /// Keep the first outcome, so a body cut off after a response still counts as answered
+    fn settle(&mut self, request_id: &str, outcome: OfflineOutcome, error: Option<String>) {
+        if let Some(request) = self.requests.get_mut(request_id) {
+            request.outcome.get_or_insert((outcome, error));
+        }
+    }
+
+    /// The requests in the order they started; ones still pending count as failed
+    pub fn finish(mut self) -> Vec<OfflineRequest> {
+        self.order
+            .iter()
+            .filter_map(|id| self.requests.remove(id))
+            .map(|request| {
+                let (outcome, error) = request.outcome.unwrap_or((
+                    OfflineOutcome::Failed,
+                    Some("no response before the reload settled".to_string()),
+                ));
+                OfflineRequest {
+                    method: request.method,
+                    url: request.url,
+                    outcome,
+                    error,
+                }
+            })
+            .collect()
+    }
+}
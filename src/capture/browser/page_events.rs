@@ -0,0 +1,139 @@
+use chromiumoxide::cdp::js_protocol::runtime::{
+    ConsoleApiCalledType, EventConsoleApiCalled, EventExceptionThrown, RemoteObject, StackTrace,
+    Timestamp,
+};
+use chrono::{DateTime, TimeZone, Utc};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// Most page events kept per capture; later ones are dropped
+pub const MAX_PAGE_EVENTS: usize = 1000;
+
+/// Severity of a console message or page error
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub enum PageEventLevel {
+    Debug,
+    Info,
+    Warning,
+    Error,
+}
+
+impl fmt::Display for PageEventLevel {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            PageEventLevel::Debug => "debug",
+            PageEventLevel::Info => "info",
+            PageEventLevel::Warning => "warning",
+            PageEventLevel::Error => "error",
+        })
+    }
+}
+
+/// Where a page event came from
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum PageEventSource {
+    /// A `console.*` call
+    Console,
+    /// An uncaught exception or unhandled rejection
+    Exception,
+}
+
+/// A console message or uncaught error the page produced during capture
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PageEvent {
+    pub timestamp: DateTime<Utc>,
+    pub level: PageEventLevel,
+    pub source: PageEventSource,
+    pub message: String,
+    /// `url:line` of the script that produced the event, when known
+    pub location: Option<String>,
+}
+
+impl PageEvent {
+    /// Convert a `Runtime.consoleAPICalled` event
+    pub fn from_console(event: &EventConsoleApiCalled) -> Self {
+        let level = match event.r#type {
+            ConsoleApiCalledType::Error | ConsoleApiCalledType::Assert => PageEventLevel::Error,
+            ConsoleApiCalledType::Warning => PageEventLevel::Warning,
+            ConsoleApiCalledType::Debug | ConsoleApiCalledType::Trace => PageEventLevel::Debug,
+            _ => PageEventLevel::Info,
+        };
+        let message = event
+            .args
+            .iter()
+            .map(describe)
+            .collect::<Vec<_>>()
+            .join(" ");
+
+        Self {
+            timestamp: timestamp(&event.timestamp),
+            level,
+            source: PageEventSource::Console,
+            message,
+            location: event.stack_trace.as_ref().and_then(top_frame),
+        }
+    }
+
+    /// Convert a `Runtime.exceptionThrown` event
+    pub fn from_exception(event: &EventExceptionThrown) -> Self {
+        let details = &event.exception_details;
+        // The description starts with e.g. "TypeError: x is not a function" and
+        // continues with the stack
+        let message = match details
+            .exception
+            .as_ref()
+            .and_then(|exception| exception.description.as_deref())
+            .and_then(|description| description.lines().next())
+        {
+            Some(summary) => format!("{} {}", details.text, summary),
+            None => details.text.clone(),
+        };
+        let location = details
+            .stack_trace
+            .as_ref()
+            .and_then(top_frame)
+            .or_else(|| {
+                details
+                    .url
+                    .as_ref()
+                    .map(|url| format!("{}:{}", url, details.line_number + 1))
+            });
+
+        Self {
+            timestamp: timestamp(&event.timestamp),
+            level: PageEventLevel::Error,
+            source: PageEventSource::Exception,
+            message,
+            location,
+        }
+    }
+}
+
+/// Render a console argument the way DevTools prints it in a log line
+fn describe(arg: &RemoteObject) -> String {
+    match &arg.value {
+        Some(serde_json::Value::String(text)) => text.clone(),
+        Some(value) => value.to_string(),
+        None => arg
+            .unserializable_value
+            .as_ref()
+            .map(|value| value.inner().clone())
+            .or_else(|| arg.description.clone())
+            .unwrap_or_else(|| format!("{:?}", arg.r#type).to_lowercase()),
+    }
+}
+
+fn top_frame(stack: &StackTrace) -> Option<String> {
+    stack
+        .call_frames
+        .iter()
+        .find(|frame| !frame.url.is_empty())
+        .map(|frame| format!("{}:{}", frame.url, frame.line_number + 1))
+}
+
+/// Runtime timestamps are milliseconds since the epoch
+fn timestamp(timestamp: &Timestamp) -> DateTime<Utc> {
+    Utc.timestamp_millis_opt(*timestamp.inner() as i64)
+        .single()
+        .unwrap_or_else(Utc::now)
+}
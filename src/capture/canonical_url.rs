@@ -0,0 +1,122 @@
+//! One spelling for every URL that names the same resource
+//!
+//! A URL with an internationalized host or non-ASCII path can be written
+//! with the Unicode host or its punycode, and with raw or percent-encoded
+//! characters, and browsers and tools don't agree on which they send.
+//! Records store the canonical form from [`canonical_url`] and incoming
+//! requests are put in the same form before matching. [`display_url`] turns
+//! it back into something readable for output.
+
+use url::{Position, Url};
+
+/// The canonical form of a URL
+///
+/// The URL is serialized per the WHATWG URL rules, which gives a lowercase
+/// punycode host and percent-encodes non-ASCII characters of the path and
+/// query. Percent escapes are then normalized: those of unreserved ASCII
+/// characters (`A-Z a-z 0-9 - . _ ~`) are decoded and the rest use
+/// uppercase hex, so `%7e` and `~`, or `%c3%a9` and `%C3%A9`, end up the
+/// same. URLs that don't parse, and those of other schemes, are kept as they
+/// are.
+pub fn canonical_url(url: &str) -> String {
+    let Ok(mut parsed) = Url::parse(url) else {
+        return url.to_string();
+    };
+    if !matches!(parsed.scheme(), "http" | "https" | "ws" | "wss") {
+        return url.to_string();
+    }
+    let path = normalize_escapes(parsed.path());
+    parsed.set_path(&path);
+    if let Some(query) = parsed.query() {
+        let query = normalize_escapes(query);
+        parsed.set_query(Some(&query));
+    }
+    parsed.to_string()
+}
+
+/// The URL as a person would write it: Unicode host, non-ASCII characters unescaped
+///
+/// Escapes of ASCII characters stay, so the result still tells apart what
+/// the canonical form does. Returns the URL unchanged when it has nothing
+/// to show differently, or when its escapes aren't UTF-8.
+pub fn display_url(url: &str) -> String {
+    let Ok(parsed) = Url::parse(url) else {
+        return url.to_string();
+    };
+    let Some(host) = parsed.host_str() else {
+        return url.to_string();
+    };
+    let unicode_host = match idna::domain_to_unicode(host) {
+        (unicode, Ok(())) if unicode != host => Some(unicode),
+        _ => None,
+    };
+    let rest = &parsed[Position::AfterHost..];
+    let Some(decoded) = decode_non_ascii(rest) else {
+        return url.to_string();
+    };
+    if unicode_host.is_none() && decoded == rest {
+        return url.to_string();
+    }
+    format!(
+        "{}{}{}",
+        &parsed[..Position::BeforeHost],
+        unicode_host.as_deref().unwrap_or(host),
+        decoded
+    )
+}
+
+/// Decode escapes of unreserved characters and uppercase the others
+fn normalize_escapes(value: &str) -> String {
+    let bytes = value.as_bytes();
+    let mut normalized = String::with_capacity(value.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        match escaped_byte(bytes, i) {
+            Some(byte) if byte.is_ascii_alphanumeric() || b"-._~".contains(&byte) => {
+                normalized.push(char::from(byte));
+                i += 3;
+            }
+            Some(byte) => {
+                normalized.push_str(&format!("%{:02X}", byte));
+                i += 3;
+            }
+            None => {
+                normalized.push(char::from(bytes[i]));
+                i += 1;
+            }
+        }
+    }
+    normalized
+}
+
+/// Decode escapes of bytes outside ASCII, if they spell UTF-8
+fn decode_non_ascii(value: &str) -> Option<String> {
+    let bytes = value.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        match escaped_byte(bytes, i) {
+            Some(byte) if !byte.is_ascii() => {
+                decoded.push(byte);
+                i += 3;
+            }
+            _ => {
+                decoded.push(bytes[i]);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8(decoded).ok()
+}
+
+/// The byte a `%XX` escape at `at` stands for
+fn escaped_byte(bytes: &[u8], at: usize) -> Option<u8> {
+    if bytes.get(at) != Some(&b'%') {
+        return None;
+    }
+    let hex = bytes.get(at + 1..at + 3)?;
+    if !hex.iter().all(u8::is_ascii_hexdigit) {
+        return None;
+    }
+    u8::from_str_radix(std::str::from_utf8(hex).ok()?, 16).ok()
+}
@@ -0,0 +1,241 @@
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+use crate::error::{Result, WebMockError};
+
+/// Largest viewport edge accepted, in CSS pixels
+const MAX_VIEWPORT_EDGE: u32 = 10_000;
+
+/// Browser window size in CSS pixels
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Viewport {
+    pub width: u32,
+    pub height: u32,
+}
+
+impl Viewport {
+    /// Parse a viewport such as `1440x900`
+    pub fn parse(value: &str) -> Result<Self> {
+        let invalid = || {
+            WebMockError::config(format!(
+                "Invalid viewport '{}': use WIDTHxHEIGHT with sizes from 1 to {} (e.g. 1440x900)",
+                value, MAX_VIEWPORT_EDGE
+            ))
+        };
+
+        let (width, height) = value.trim().split_once(['x', 'X']).ok_or_else(invalid)?;
+        let parse_edge = |edge: &str| {
+            edge.trim()
+                .parse::<u32>()
+                .ok()
+                .filter(|edge| (1..=MAX_VIEWPORT_EDGE).contains(edge))
+                .ok_or_else(invalid)
+        };
+
+        Ok(Self {
+            width: parse_edge(width)?,
+            height: parse_edge(height)?,
+        })
+    }
+}
+
+impl fmt::Display for Viewport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}x{}", self.width, self.height)
+    }
+}
+
+/// Device the browser can emulate with `capture --device`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DevicePreset {
+    pub name: &'static str,
+    pub user_agent: &'static str,
+    pub viewport: Viewport,
+    pub device_scale_factor: f64,
+    /// Emulate a mobile browser (meta viewport, overlay scrollbars)
+    pub mobile: bool,
+    pub touch: bool,
+}
+
+/// Presets accepted by `capture --device`
+pub const DEVICE_PRESETS: &[DevicePreset] = &[
+    DevicePreset {
+        name: "iphone-13",
+        user_agent: "Mozilla/5.0 (iPhone; CPU iPhone OS 15_0 like Mac OS X) AppleWebKit/605.1.15 (KHTML, like Gecko) Version/15.0 Mobile/15E148 Safari/604.1",
+        viewport: Viewport {
+            width: 390,
+            height: 844,
+        },
+        device_scale_factor: 3.0,
+        mobile: true,
+        touch: true,
+    },
+    DevicePreset {
+        name: "pixel-7",
+        user_agent: "Mozilla/5.0 (Linux; Android 13; Pixel 7) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/116.0.0.0 Mobile Safari/537.36",
+        viewport: Viewport {
+            width: 412,
+            height: 915,
+        },
+        device_scale_factor: 2.625,
+        mobile: true,
+        touch: true,
+    },
+    DevicePreset {
+        name: "ipad",
+        user_agent: "Mozilla/5.0 (iPad; CPU OS 15_0 like Mac OS X) AppleWebKit/605.1.15 (KHTML, like Gecko) Version/15.0 Mobile/15E148 Safari/604.1",
+        viewport: Viewport {
+            width: 820,
+            height: 1180,
+        },
+        device_scale_factor: 2.0,
+        mobile: true,
+        touch: true,
+    },
+];
+
+impl DevicePreset {
+    /// Look up a preset by name, case-insensitively
+    pub fn find(name: &str) -> Result<&'static DevicePreset> {
+        let name = name.trim();
+        DEVICE_PRESETS
+            .iter()
+            .find(|preset| preset.name.eq_ignore_ascii_case(name))
+            .ok_or_else(|| {
+                let available: Vec<&str> =
+                    DEVICE_PRESETS.iter().map(|preset| preset.name).collect();
+                WebMockError::config(format!(
+                    "Unknown device '{}': available presets are {}",
+                    name,
+                    available.join(", ")
+                ))
+            })
+    }
+}
+
+/// Locale, timezone, viewport and device the browser emulates during capture
+///
+/// Stored with the snapshot so `inspect` shows how a capture was made.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CaptureEnvironment {
+    /// BCP 47 language tag, e.g. `fr-FR`
+    pub locale: Option<String>,
+    /// IANA timezone name, e.g. `Europe/Paris`
+    pub timezone: Option<String>,
+    pub viewport: Option<Viewport>,
+    /// User-Agent sent by the browser, from `--user-agent` or the device preset
+    #[serde(default)]
+    pub user_agent: Option<String>,
+    /// Name of the emulated device preset, e.g. `iphone-13`
+    #[serde(default)]
+    pub device: Option<String>,
+}
+
+impl CaptureEnvironment {
+    /// Build and validate the environment from the capture command line options
+    pub fn from_args(
+        locale: Option<&str>,
+        timezone: Option<&str>,
+        viewport: Option<&str>,
+    ) -> Result<Self> {
+        Ok(Self {
+            locale: locale.map(parse_locale).transpose()?,
+            timezone: timezone.map(parse_timezone).transpose()?,
+            viewport: viewport.map(Viewport::parse).transpose()?,
+            ..Self::default()
+        })
+    }
+
+    /// Emulate a device preset and/or send a custom User-Agent
+    ///
+    /// An explicit `user_agent` or viewport takes precedence over the preset's.
+    pub fn with_device(mut self, device: Option<&str>, user_agent: Option<&str>) -> Result<Self> {
+        if let Some(device) = device {
+            let preset = DevicePreset::find(device)?;
+            self.viewport = self.viewport.or(Some(preset.viewport));
+            self.user_agent = Some(preset.user_agent.to_string());
+            self.device = Some(preset.name.to_string());
+        }
+        if let Some(user_agent) = user_agent {
+            self.user_agent = Some(parse_user_agent(user_agent)?);
+        }
+        Ok(self)
+    }
+
+    /// Get the emulated device preset, if any
+    pub fn device_preset(&self) -> Option<&'static DevicePreset> {
+        self.device
+            .as_deref()
+            .and_then(|device| DevicePreset::find(device).ok())
+    }
+
+    /// Check whether the browser defaults are used for everything
+    pub fn is_default(&self) -> bool {
+        self.locale.is_none()
+            && self.timezone.is_none()
+            && self.viewport.is_none()
+            && self.user_agent.is_none()
+            && self.device.is_none()
+    }
+
+    /// Accept-Language header value for the locale, e.g. `fr-FR,fr;q=0.9`
+    pub fn accept_language(&self) -> Option<String> {
+        let locale = self.locale.as_ref()?;
+        match locale.split_once('-') {
+            Some((language, _)) => Some(format!("{},{};q=0.9", locale, language)),
+            None => Some(locale.clone()),
+        }
+    }
+}
+
+/// Validate a BCP 47 language tag such as `fr-FR` and normalize `_` to `-`
+pub fn parse_locale(value: &str) -> Result<String> {
+    let invalid = || {
+        WebMockError::config(format!(
+            "Invalid locale '{}': use a language tag such as en-US, fr-FR or pt-BR",
+            value
+        ))
+    };
+
+    let normalized = value.trim().replace('_', "-");
+    let mut subtags = normalized.split('-');
+
+    let language = subtags.next().unwrap_or_default();
+    if !(2..=3).contains(&language.len()) || !language.chars().all(|c| c.is_ascii_alphabetic()) {
+        return Err(invalid());
+    }
+
+    for subtag in subtags {
+        if !(1..=8).contains(&subtag.len()) || !subtag.chars().all(|c| c.is_ascii_alphanumeric()) {
+            return Err(invalid());
+        }
+    }
+
+    Ok(normalized)
+}
+
+/// Validate an IANA timezone name such as `Europe/Paris`
+pub fn parse_timezone(value: &str) -> Result<String> {
+    let value = value.trim();
+    value.parse::<chrono_tz::Tz>().map_err(|_| {
+        WebMockError::config(format!(
+            "Unknown timezone '{}': use an IANA name such as Europe/Paris or America/New_York",
+            value
+        ))
+    })?;
+
+    Ok(value.to_string())
+}
+
+/// Validate a User-Agent so it can be sent as a header value
+pub fn parse_user_agent(value: &str) -> Result<String> {
+    let value = value.trim();
+    if value.is_empty() || value.chars().any(|c| c.is_control()) {
+        return Err(WebMockError::config(format!(
+            "Invalid user agent '{}': use a non-empty single-line string",
+            value.escape_default()
+        )));
+    }
+
+    Ok(value.to_string())
+}
@@ -7,6 +7,8 @@ mod request_timer;
 
 #[cfg(test)]
 mod memory_tracker_tests;
+#[cfg(test)]
+mod performance_metrics_tests;
 
 pub use memory_tracker::*;
 pub use performance_metrics::*;
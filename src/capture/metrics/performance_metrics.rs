@@ -1,4 +1,8 @@
 use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+use super::MemoryTracker;
+use crate::capture::proxy::client_pool::ConnectionStats;
 
 /// Performance metrics collected during request processing
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -19,6 +23,33 @@ pub struct PerformanceMetrics {
     pub total_data_transferred: u64,
     /// Cache hit ratio (0.0 to 1.0)
     pub cache_hit_ratio: f64,
+    /// Wall-clock time of a whole capture, browser launch included, in milliseconds
+    #[serde(default)]
+    pub capture_duration_ms: u64,
+    /// Time spent waiting for the browser to load pages and go idle, in milliseconds
+    #[serde(default)]
+    pub browser_wait_ms: u64,
+    /// Time the proxy spent forwarding requests upstream, summed over requests, in milliseconds
+    #[serde(default)]
+    pub proxy_forward_ms: u64,
+    /// Requests recorded per second of capture
+    #[serde(default)]
+    pub requests_per_second: f64,
+    /// Upstream connections the proxy opened
+    #[serde(default)]
+    pub connections_opened: u64,
+    /// Requests the proxy sent on an already open upstream connection
+    #[serde(default)]
+    pub connections_reused: u64,
+    /// Requests that waited for a free upstream connection
+    #[serde(default)]
+    pub connections_queued: u64,
+    /// Time requests waited for a free upstream connection, summed, in milliseconds
+    #[serde(default)]
+    pub connection_queue_ms: u64,
+    /// Requests still open when the page counted as loaded, as `METHOD URL`; they aren't in the snapshot
+    #[serde(default)]
+    pub stragglers: Vec<String>,
 }
 
 impl Default for PerformanceMetrics {
@@ -32,6 +63,15 @@ impl Default for PerformanceMetrics {
             failed_requests: 0,
             total_data_transferred: 0,
             cache_hit_ratio: 0.0,
+            capture_duration_ms: 0,
+            browser_wait_ms: 0,
+            proxy_forward_ms: 0,
+            requests_per_second: 0.0,
+            connections_opened: 0,
+            connections_reused: 0,
+            connections_queued: 0,
+            connection_queue_ms: 0,
+            stragglers: Vec::new(),
         }
     }
 }
@@ -98,4 +138,82 @@ impl PerformanceMetrics {
             format!("{:.2} GB", bytes / (1024.0 * 1024.0 * 1024.0))
         }
     }
+
+    /// Close a capture's metrics once it has run for `elapsed`
+    ///
+    /// Derives the forwarding time and request rate from the requests
+    /// folded in with `update_from_request`.
+    pub fn finish_capture(&mut self, elapsed: Duration) {
+        self.capture_duration_ms = elapsed.as_millis() as u64;
+        self.proxy_forward_ms =
+            (self.avg_response_time_ms * self.requests_processed as f64).round() as u64;
+        let seconds = elapsed.as_secs_f64();
+        self.requests_per_second = if seconds > 0.0 {
+            self.requests_processed as f64 / seconds
+        } else {
+            0.0
+        };
+    }
+
+    /// Take the upstream connection counters of the capture proxy's client pool
+    pub fn record_connections(&mut self, stats: ConnectionStats) {
+        self.connections_opened = stats.opened;
+        self.connections_reused = stats.reused;
+        self.connections_queued = stats.queued;
+        self.connection_queue_ms = stats.queued_time.as_millis() as u64;
+    }
+
+    /// The capture metrics as JSON, with durations in milliseconds and sizes in bytes
+    pub fn to_json(&self) -> serde_json::Value {
+        serde_json::json!({
+            "capture_duration_ms": self.capture_duration_ms,
+            "browser_wait_ms": self.browser_wait_ms,
+            "proxy_forward_ms": self.proxy_forward_ms,
+            "requests": self.requests_processed,
+            "failed_requests": self.failed_requests,
+            "requests_per_second": self.requests_per_second,
+            "bytes_downloaded": self.total_data_transferred,
+            "peak_memory_bytes": self.peak_memory_usage,
+            "connections_opened": self.connections_opened,
+            "connections_reused": self.connections_reused,
+            "connections_queued": self.connections_queued,
+            "connection_queue_ms": self.connection_queue_ms,
+            "stragglers": self.stragglers,
+        })
+    }
+
+    /// Compact summary of a capture, one line per measure
+    pub fn capture_summary(&self) -> Vec<String> {
+        vec![
+            format!(
+                "Duration: {} (browser wait {}, proxy forwarding {})",
+                format_ms(self.capture_duration_ms),
+                format_ms(self.browser_wait_ms),
+                format_ms(self.proxy_forward_ms)
+            ),
+            format!(
+                "Requests: {} ({} failed), {:.1}/s",
+                self.requests_processed, self.failed_requests, self.requests_per_second
+            ),
+            format!(
+                "Downloaded: {}",
+                MemoryTracker::bytes_to_human(self.total_data_transferred)
+            ),
+            format!(
+                "Connections: {} opened, {} reused, {} requests queued for {}",
+                self.connections_opened,
+                self.connections_reused,
+                self.connections_queued,
+                format_ms(self.connection_queue_ms)
+            ),
+            format!(
+                "Peak memory: {}",
+                MemoryTracker::bytes_to_human(self.peak_memory_usage)
+            ),
+        ]
+    }
+}
+
+fn format_ms(ms: u64) -> String {
+    format!("{:.2}s", ms as f64 / 1000.0)
 }
@@ -0,0 +1,132 @@
+use super::{PerformanceMetrics, PerformanceMonitor};
+use crate::capture::proxy::RequestRecord;
+use crate::test_utils::test_helpers::create_test_record;
+use std::time::Duration;
+
+fn record(body_size: usize, duration_ms: u64, failure: Option<&str>) -> RequestRecord {
+    let mut record = create_test_record(
+        "GET",
+        "https://example.com/",
+        200,
+        &[],
+        &vec![b'x'; body_size],
+    );
+    record.response.failure = failure.map(str::to_string);
+    record.with_timing(0, duration_ms)
+}
+
+#[test]
+fn test_finish_capture_derives_forwarding_time_and_rate() {
+    let mut metrics = PerformanceMetrics::new();
+    metrics.update_from_request(100.0, 1024, true);
+    metrics.update_from_request(300.0, 2048, true);
+    metrics.update_from_request(200.0, 0, false);
+    metrics.update_from_request(400.0, 512, true);
+
+    metrics.finish_capture(Duration::from_secs(2));
+
+    assert_eq!(metrics.capture_duration_ms, 2000);
+    assert_eq!(metrics.proxy_forward_ms, 1000);
+    assert_eq!(metrics.requests_per_second, 2.0);
+    assert_eq!(metrics.failed_requests, 1);
+    assert_eq!(metrics.total_data_transferred, 3584);
+}
+
+#[test]
+fn test_finish_capture_without_time_or_requests() {
+    let mut metrics = PerformanceMetrics::new();
+    metrics.finish_capture(Duration::ZERO);
+    assert_eq!(metrics.proxy_forward_ms, 0);
+    assert_eq!(metrics.requests_per_second, 0.0);
+
+    metrics.update_from_request(50.0, 10, true);
+    metrics.finish_capture(Duration::ZERO);
+    assert_eq!(metrics.requests_per_second, 0.0);
+}
+
+#[tokio::test]
+async fn test_monitor_folds_capture_records_into_metrics() {
+    let monitor = PerformanceMonitor::new();
+    monitor
+        .record_browser_wait(Duration::from_millis(1500))
+        .await;
+    monitor
+        .record_browser_wait(Duration::from_millis(500))
+        .await;
+
+    let records = [
+        record(1000, 120, None),
+        record(3000, 80, None),
+        record(0, 30_000, Some("connection reset")),
+    ];
+    let metrics = monitor.finish_capture(&records).await;
+
+    assert_eq!(metrics.browser_wait_ms, 2000);
+    assert_eq!(metrics.requests_processed, 3);
+    assert_eq!(metrics.failed_requests, 1);
+    assert_eq!(metrics.total_data_transferred, 4000);
+    assert_eq!(metrics.proxy_forward_ms, 30_200);
+    assert!(metrics.requests_per_second > 0.0);
+    #[cfg(target_os = "linux")]
+    assert!(metrics.peak_memory_usage > 0);
+}
+
+#[test]
+fn test_to_json_names_units() {
+    let metrics = PerformanceMetrics {
+        requests_processed: 8,
+        failed_requests: 1,
+        total_data_transferred: 65_536,
+        peak_memory_usage: 50 * 1024 * 1024,
+        capture_duration_ms: 4000,
+        browser_wait_ms: 3000,
+        proxy_forward_ms: 1200,
+        requests_per_second: 2.0,
+        connections_opened: 3,
+        connections_reused: 5,
+        connections_queued: 2,
+        connection_queue_ms: 150,
+        ..PerformanceMetrics::default()
+    };
+
+    let json = metrics.to_json();
+    assert_eq!(json["capture_duration_ms"], 4000);
+    assert_eq!(json["browser_wait_ms"], 3000);
+    assert_eq!(json["proxy_forward_ms"], 1200);
+    assert_eq!(json["requests"], 8);
+    assert_eq!(json["failed_requests"], 1);
+    assert_eq!(json["requests_per_second"], 2.0);
+    assert_eq!(json["bytes_downloaded"], 65_536);
+    assert_eq!(json["peak_memory_bytes"], 50 * 1024 * 1024);
+    assert_eq!(json["connections_opened"], 3);
+    assert_eq!(json["connections_reused"], 5);
+    assert_eq!(json["connections_queued"], 2);
+    assert_eq!(json["connection_queue_ms"], 150);
+
+    let summary = metrics.capture_summary().join("\n");
+    assert!(summary.contains("Duration: 4.00s (browser wait 3.00s, proxy forwarding 1.20s)"));
+    assert!(summary.contains("Requests: 8 (1 failed), 2.0/s"));
+    assert!(summary.contains("Downloaded: 64.00 KB"));
+    assert!(summary.contains("Connections: 3 opened, 5 reused, 2 requests queued for 0.15s"));
+    assert!(summary.contains("Peak memory: 50.00 MB"));
+}
+
+#[test]
+fn test_metrics_saved_before_capture_fields_still_load() {
+    // The eight fields the struct had before capture metrics, as rmp_serde writes them
+    let old = rmp_serde::to_vec(&(
+        0u64, 0usize, 3usize, 12.5f64, 4096u64, 1usize, 2048u64, 0.5f64,
+    ))
+    .unwrap();
+
+    let metrics: PerformanceMetrics = rmp_serde::from_slice(&old).unwrap();
+    assert_eq!(metrics.requests_processed, 3);
+    assert_eq!(metrics.peak_memory_usage, 4096);
+    assert_eq!(metrics.total_data_transferred, 2048);
+    assert_eq!(metrics.capture_duration_ms, 0);
+    assert_eq!(metrics.requests_per_second, 0.0);
+
+    let current = rmp_serde::to_vec(&metrics).unwrap();
+    let restored: PerformanceMetrics = rmp_serde::from_slice(&current).unwrap();
+    assert_eq!(restored.requests_processed, 3);
+}
@@ -5,6 +5,8 @@ use tokio::sync::RwLock;
 use tracing::info;
 
 use super::performance_metrics::PerformanceMetrics;
+use super::MemoryTracker;
+use crate::capture::proxy::RequestRecord;
 
 /// Performance monitor for tracking system metrics
 #[derive(Debug)]
@@ -12,6 +14,7 @@ pub struct PerformanceMonitor {
     metrics: Arc<RwLock<PerformanceMetrics>>,
     start_time: Instant,
     request_times: Arc<RwLock<Vec<Duration>>>,
+    memory: Arc<RwLock<MemoryTracker>>,
 }
 
 impl PerformanceMonitor {
@@ -21,6 +24,7 @@ impl PerformanceMonitor {
             metrics: Arc::new(RwLock::new(PerformanceMetrics::new())),
             start_time: Instant::now(),
             request_times: Arc::new(RwLock::new(Vec::new())),
+            memory: Arc::new(RwLock::new(MemoryTracker::new())),
         }
     }
 
@@ -70,6 +74,47 @@ impl PerformanceMonitor {
         }
     }
 
+    /// Sample this process's memory, keeping the peak
+    pub async fn sample_memory(&self) {
+        let mut memory = self.memory.write().await;
+        memory.update();
+        let mut metrics = self.metrics.write().await;
+        metrics.memory_usage = MemoryTracker::get_current_memory_usage();
+        metrics.peak_memory_usage = metrics.peak_memory_usage.max(memory.peak_memory());
+    }
+
+    /// Add time spent waiting for the browser to load a page
+    pub async fn record_browser_wait(&self, wait: Duration) {
+        let mut metrics = self.metrics.write().await;
+        metrics.browser_wait_ms += wait.as_millis() as u64;
+    }
+
+    /// Note requests still open when a page counted as loaded
+    pub async fn record_stragglers(&self, stragglers: impl IntoIterator<Item = String>) {
+        let mut metrics = self.metrics.write().await;
+        for straggler in stragglers {
+            if !metrics.stragglers.contains(&straggler) {
+                metrics.stragglers.push(straggler);
+            }
+        }
+    }
+
+    /// Fold a capture's records into the metrics and close them
+    ///
+    /// Each record counts with the time the proxy took to forward it and
+    /// the size of its response body.
+    pub async fn finish_capture(&self, records: &[RequestRecord]) -> PerformanceMetrics {
+        self.sample_memory().await;
+        let mut metrics = self.metrics.write().await;
+        for record in records {
+            let forward_ms = record.timing.duration_ms.unwrap_or(0) as f64;
+            let success = record.response.failure.is_none();
+            metrics.update_from_request(forward_ms, record.response.body.len() as u64, success);
+        }
+        metrics.finish_capture(self.start_time.elapsed());
+        metrics.clone()
+    }
+
     /// Calculate average response time
     pub async fn calculate_average_response_time(&self) -> f64 {
         let request_times = self.request_times.read().await;
@@ -1,16 +1,36 @@
+pub mod abort;
 pub mod browser;
+pub mod canonical_url;
+pub mod environment;
 pub mod metrics;
 pub mod network;
+pub mod options;
 pub mod performance;
+pub mod provenance;
 pub mod proxy;
+pub mod registry;
 pub mod resource_manager;
 pub mod session;
+pub mod settle;
+pub mod sitemap;
 pub mod validation;
+pub mod variant;
+pub mod warnings;
 
 #[cfg(test)]
 mod tests;
 
 // Re-export the main types for convenience
+pub use abort::{AbortConditions, AbortReason, StatusPattern};
+pub use canonical_url::{canonical_url, display_url};
+pub use environment::{CaptureEnvironment, DevicePreset, Viewport};
 pub use metrics::{PerformanceMetrics, PerformanceMonitor, RequestTimer};
+pub use options::CaptureOptions;
+pub use provenance::Provenance;
+pub use registry::{CaptureEntry, CaptureRegistry};
 pub use resource_manager::ResourceManager;
-pub use session::CaptureSession;
+pub use session::{CaptureSession, PageCaptureReport};
+pub use settle::SettleStrategy;
+pub use sitemap::SitemapOptions;
+pub use variant::{CaptureVariant, VariantCookie};
+pub use warnings::{CaptureStatus, CaptureWarning, CaptureWarningKind, CaptureWarnings};
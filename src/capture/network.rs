@@ -1,92 +1,115 @@
-use std::time::Duration;
+use std::time::{Duration, Instant};
 use tokio::time::sleep;
-use tracing::{debug, error, info, warn};
+use tracing::{debug, info, warn};
 
+use super::proxy::InFlightRequest;
 use super::session::CaptureSession;
-use crate::error::{Result, WebMockError};
+use super::settle::{IdleTracker, LONG_REQUEST_AFTER};
+use crate::error::Result;
 
-impl CaptureSession {
-    /// Find an available port for the proxy server
-    pub async fn find_available_port(&self) -> Result<u16> {
-        use std::net::{SocketAddr, TcpListener};
-
-        // Try a wider range of ports to avoid conflicts
-        let port_ranges = [
-            8000..9000,   // Primary range
-            9000..10000,  // Secondary range
-            10000..11000, // Tertiary range
-        ];
-
-        for range in port_ranges {
-            for port in range {
-                let addr = SocketAddr::from(([127, 0, 0, 1], port));
-                if TcpListener::bind(addr).is_ok() {
-                    debug!("Found available port: {}", port);
-                    return Ok(port);
-                }
-            }
-        }
+/// How often the network is looked at while waiting for it to go idle
+const CHECK_INTERVAL: Duration = Duration::from_millis(500);
 
-        error!("No available ports found in any range");
-        Err(WebMockError::config(
-            "No available ports found for proxy server",
-        ))
-    }
+/// How long the network must stay quiet to count as idle
+const QUIET_PERIOD: Duration = Duration::from_millis(1000);
 
+/// Longest wait for network idle before the capture goes on regardless
+const MAX_IDLE_WAIT: Duration = Duration::from_secs(10);
+
+impl CaptureSession {
     /// Wait for network requests to settle (network idle detection)
+    ///
+    /// The network only counts as idle once every frame of the page has
+    /// stopped loading, so iframes inserted after the main frame's load event
+    /// get their subresources recorded too. Requests still open are waited
+    /// for, unless they are long-polls or streams that won't finish, see
+    /// [`IdleTracker`]. Whatever is still open at the end is noted in the
+    /// capture metrics as stragglers.
     pub(crate) async fn wait_for_network_idle(&self) -> Result<()> {
         debug!("Waiting for network idle state");
+        if self.proxy.is_none() {
+            return Ok(());
+        }
+
+        let mut tracker = IdleTracker::new(QUIET_PERIOD, LONG_REQUEST_AFTER);
+        let start_time = Instant::now();
+        let mut idle = false;
 
-        let mut last_request_count = 0;
-        let mut stable_count = 0;
-        const REQUIRED_STABLE_ITERATIONS: u32 = 3;
-        const CHECK_INTERVAL_MS: u64 = 500;
-        const MAX_WAIT_TIME_MS: u64 = 10000; // 10 seconds max
-
-        let start_time = std::time::Instant::now();
-
-        while start_time.elapsed().as_millis() < MAX_WAIT_TIME_MS as u128 {
-            let current_request_count = if let Some(proxy) = &self.proxy {
-                proxy.get_records().await.len()
-            } else {
-                0
-            };
-
-            if current_request_count == last_request_count {
-                stable_count += 1;
-                debug!(
-                    "Network stable iteration {}/{}",
-                    stable_count, REQUIRED_STABLE_ITERATIONS
-                );
-
-                if stable_count >= REQUIRED_STABLE_ITERATIONS {
-                    info!(
-                        "Network idle detected after {} requests",
-                        current_request_count
-                    );
-                    break;
-                }
-            } else {
-                debug!(
-                    "New requests detected: {} -> {}",
-                    last_request_count, current_request_count
-                );
-                stable_count = 0;
-                last_request_count = current_request_count;
+        while start_time.elapsed() < MAX_IDLE_WAIT {
+            let (now_ms, record_count, in_flight) = self.network_state();
+            let pending_frames = self.pending_frames().await;
+            if !pending_frames.is_empty() {
+                debug!("Waiting for frames to load: {}", pending_frames.join(", "));
             }
 
-            sleep(Duration::from_millis(CHECK_INTERVAL_MS)).await;
+            if tracker.observe(now_ms, record_count, &in_flight, !pending_frames.is_empty()) {
+                info!("Network idle detected after {} requests", record_count);
+                idle = true;
+                break;
+            }
+            for request in tracker.long_lived(&in_flight, now_ms) {
+                debug!("Not waiting for long-lived request: {}", request);
+            }
+
+            sleep(CHECK_INTERVAL).await;
         }
 
-        if start_time.elapsed().as_millis() >= MAX_WAIT_TIME_MS as u128 {
+        if !idle {
             warn!(
                 "Network idle detection timed out after {}ms",
-                MAX_WAIT_TIME_MS
+                MAX_IDLE_WAIT.as_millis()
             );
         }
+        self.note_stragglers().await;
 
         Ok(())
     }
+
+    /// Note the requests still open now that the page counts as loaded
+    pub(crate) async fn note_stragglers(&self) {
+        let (_, _, in_flight) = self.network_state();
+        if in_flight.is_empty() {
+            return;
+        }
+        info!(
+            "Page counts as loaded with {} requests still open",
+            in_flight.len()
+        );
+        self.monitor
+            .record_stragglers(in_flight.iter().map(ToString::to_string))
+            .await;
+    }
+
+    /// The proxy's clock, number of records and open requests
+    fn network_state(&self) -> (u64, usize, Vec<InFlightRequest>) {
+        match &self.proxy {
+            Some(proxy) => (proxy.elapsed_ms(), proxy.record_count(), proxy.in_flight()),
+            None => (0, 0, Vec::new()),
+        }
+    }
+
+    /// URLs of the page's frames that started loading and haven't stopped
+    ///
+    /// The frame tree is asked for each time, so frames removed without a
+    /// detach event don't hold up the capture. Without it, every frame still
+    /// loading according to the events counts, named by its ID.
+    async fn pending_frames(&self) -> Vec<String> {
+        let (Some(browser), Some(frames)) = (&self.browser, &self.frames) else {
+            return Vec::new();
+        };
+        let loads = frames.borrow().clone();
+        match browser.frames().await {
+            Ok(attached) => loads
+                .pending(&attached)
+                .into_iter()
+                .map(|frame| frame.url.clone())
+                .collect(),
+            Err(e) => {
+                debug!("Frame tree is unavailable: {}", e);
+                loads.loading().into_iter().map(str::to_string).collect()
+            }
+        }
+    }
 }
 
 #[cfg(test)]
@@ -96,19 +119,6 @@ mod tests {
     use std::sync::Arc;
     use tempfile::TempDir;
 
-    #[tokio::test]
-    async fn test_find_available_port() {
-        let temp_dir = TempDir::new().unwrap();
-        let storage = Arc::new(Storage::new(temp_dir.path().to_path_buf()));
-        let session = CaptureSession::new(storage).await.unwrap();
-
-        let port = session.find_available_port().await;
-        assert!(port.is_ok());
-
-        let port_num = port.unwrap();
-        assert!((8000..11000).contains(&port_num));
-    }
-
     #[tokio::test]
     async fn test_wait_for_network_idle_no_proxy() {
         let temp_dir = TempDir::new().unwrap();
@@ -0,0 +1,62 @@
+use std::time::Duration;
+
+use crate::capture::abort::AbortConditions;
+use crate::capture::environment::CaptureEnvironment;
+use crate::capture::provenance::Provenance;
+use crate::capture::proxy::{HostAllowlist, RedactionRules};
+use crate::capture::settle::SettleStrategy;
+use crate::capture::variant::CaptureVariant;
+use crate::storage::ServeDefaults;
+
+/// Options controlling how a capture session records traffic
+#[derive(Debug, Clone, Default)]
+pub struct CaptureOptions {
+    /// Redaction and size rules applied to every record before it is stored
+    pub redaction: RedactionRules,
+    /// Skip the capture when a snapshot with the same name already exists
+    pub if_missing: bool,
+    /// Recapture only when the existing snapshot is older than this
+    pub max_age: Option<Duration>,
+    /// Print the age of an existing snapshot in seconds
+    pub print_age: bool,
+    /// Locale, timezone, viewport and device the browser emulates
+    pub environment: CaptureEnvironment,
+    /// Print a line for each request as it is recorded
+    pub tail: bool,
+    /// Stop recording after this many requests, while still forwarding traffic
+    pub max_requests: Option<usize>,
+    /// Retry GET and HEAD requests that fail transiently upstream up to this many times
+    pub upstream_retries: u32,
+    /// Open at most this many upstream connections per host; 6 if unset, as browsers do
+    pub max_connections_per_host: Option<usize>,
+    /// Serve settings stored with the snapshot, kept across recaptures
+    pub serve_defaults: ServeDefaults,
+    /// DevTools endpoint of a running Chrome to capture in, instead of launching one
+    pub attach: Option<String>,
+    /// Port the proxy listens on; any free port if unset
+    pub proxy_port: Option<u16>,
+    /// Open a tab in the browser of another `share_browser` capture of the same storage, if one runs
+    pub share_browser: bool,
+    /// Save the snapshot so identical captured content yields identical bytes
+    pub deterministic: bool,
+    /// Reload the page offline once its service worker is active and log what it serves
+    pub emulate_offline_after_load: bool,
+    /// Don't save a screenshot and the final DOM next to the snapshot
+    pub no_artifacts: bool,
+    /// States the page is reloaded in after the default load, with records tagged by name
+    pub variants: Vec<CaptureVariant>,
+    /// Print the capture's duration, throughput and memory when it finishes
+    pub metrics: bool,
+    /// Hosts the proxy forwards to; requests to others are refused with a 403
+    pub only_hosts: Option<HostAllowlist>,
+    /// Save the snapshot encrypted with a passphrase
+    pub encrypt: bool,
+    /// What counts as a loaded page, after which the capture completes
+    pub settle_strategy: SettleStrategy,
+    /// Fail the capture command when the capture saved its snapshot with warnings
+    pub fail_on_warnings: bool,
+    /// Provenance block saved with the snapshot; none when unset
+    pub provenance: Option<Provenance>,
+    /// When to give up on a broken page before it settles
+    pub abort: AbortConditions,
+}
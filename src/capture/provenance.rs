@@ -0,0 +1,215 @@
+//! Where a snapshot came from and under what conditions
+//!
+//! Captures record the command line that made them, the webmock version, the
+//! operating system and the capture machine's hostname, along with a
+//! free-form `--consent-note` such as who approved capturing the site. The
+//! command line is stored with secrets taken out: values of `--cookie`,
+//! `--header` and options named like a password or token, credentials in
+//! URLs and query parameters named like one.
+
+use serde::{Deserialize, Serialize};
+use url::Url;
+
+use crate::capture::proxy::redaction::REDACTED;
+
+/// Options whose whole value is a secret
+const SECRET_OPTIONS: &[&str] = &["--cookie", "--header"];
+
+/// Parts of option and query parameter names that mark their value as a secret
+const SECRET_NAME_PARTS: &[&str] = &[
+    "password",
+    "passwd",
+    "passphrase",
+    "secret",
+    "token",
+    "apikey",
+    "api_key",
+    "api-key",
+    "signature",
+    "credential",
+    "session",
+    "auth",
+];
+
+/// Provenance block kept in snapshot metadata
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Provenance {
+    /// Command line of the capture, program name first, with secrets replaced by `[REDACTED]`
+    pub invocation: Vec<String>,
+    /// Version of webmock that made the capture
+    pub webmock_version: String,
+    /// Operating system and architecture, e.g. `linux 6.8.0 (x86_64)`
+    pub os: String,
+    /// Hostname of the capture machine, unless left out with `--no-provenance`
+    pub hostname: Option<String>,
+    /// Free-form note given with `--consent-note`, e.g. who approved the capture
+    pub consent_note: Option<String>,
+}
+
+impl Provenance {
+    /// Provenance of a capture run with the command line `args`
+    pub fn collect(
+        args: impl IntoIterator<Item = String>,
+        consent_note: Option<String>,
+        include_hostname: bool,
+    ) -> Self {
+        let hostname = if include_hostname {
+            sys_info::hostname()
+                .ok()
+                .map(|hostname| hostname.trim().to_string())
+                .filter(|hostname| !hostname.is_empty())
+        } else {
+            None
+        };
+        Self {
+            invocation: redact_invocation(args),
+            webmock_version: env!("CARGO_PKG_VERSION").to_string(),
+            os: operating_system(),
+            hostname,
+            consent_note: consent_note
+                .map(|note| note.trim().to_string())
+                .filter(|note| !note.is_empty()),
+        }
+    }
+
+    /// The invocation as it could be typed in a shell
+    pub fn command_line(&self) -> String {
+        self.invocation
+            .iter()
+            .map(|arg| shell_quote(arg))
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+}
+
+/// Operating system, its release when known, and architecture
+fn operating_system() -> String {
+    match sys_info::os_release() {
+        Ok(release) if !release.trim().is_empty() => format!(
+            "{} {} ({})",
+            std::env::consts::OS,
+            release.trim(),
+            std::env::consts::ARCH
+        ),
+        _ => format!("{} ({})", std::env::consts::OS, std::env::consts::ARCH),
+    }
+}
+
+/// The command line `args` with every secret replaced by `[REDACTED]`
+///
+/// The program is kept by file name only, since its path may name the user.
+pub fn redact_invocation(args: impl IntoIterator<Item = String>) -> Vec<String> {
+    let mut args = args.into_iter();
+    let mut redacted = Vec::new();
+    if let Some(program) = args.next() {
+        let name = std::path::Path::new(&program)
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or(program);
+        redacted.push(name);
+    }
+
+    // Set when the previous argument was an option whose value is a secret
+    let mut pending: Option<String> = None;
+    for arg in args {
+        if let Some(option) = pending.take() {
+            redacted.push(redact_value(&option, &arg));
+            continue;
+        }
+        if let Some((option, value)) = arg.split_once('=').filter(|(o, _)| o.starts_with('-')) {
+            if is_secret_option(option) {
+                redacted.push(format!("{}={}", option, redact_value(option, value)));
+            } else {
+                redacted.push(format!("{}={}", option, redact_url(value)));
+            }
+            continue;
+        }
+        if arg.starts_with('-') && is_secret_option(&arg) {
+            pending = Some(arg.clone());
+            redacted.push(arg);
+            continue;
+        }
+        redacted.push(redact_url(&arg));
+    }
+    redacted
+}
+
+fn is_secret_option(option: &str) -> bool {
+    let option = option.to_ascii_lowercase();
+    SECRET_OPTIONS.contains(&option.as_str()) || is_secret_name(option.trim_start_matches('-'))
+}
+
+fn is_secret_name(name: &str) -> bool {
+    let name = name.to_ascii_lowercase();
+    SECRET_NAME_PARTS.iter().any(|part| name.contains(part))
+}
+
+/// The value of a secret option: header names and cookie names stay, values go
+fn redact_value(option: &str, value: &str) -> String {
+    match option.to_ascii_lowercase().as_str() {
+        "--header" => match value.split_once(':') {
+            Some((name, _)) => format!("{}: {}", name.trim(), REDACTED),
+            None => REDACTED.to_string(),
+        },
+        "--cookie" => value
+            .split(';')
+            .map(|pair| match pair.split_once('=') {
+                Some((name, _)) => format!("{}={}", name.trim(), REDACTED),
+                None => REDACTED.to_string(),
+            })
+            .collect::<Vec<_>>()
+            .join("; "),
+        _ => REDACTED.to_string(),
+    }
+}
+
+/// `arg` with URL credentials and secret-looking query values replaced, if it is a URL
+fn redact_url(arg: &str) -> String {
+    let Ok(url) = Url::parse(arg) else {
+        return arg.to_string();
+    };
+    if !url.has_host() {
+        return arg.to_string();
+    }
+    let credentials = !url.username().is_empty() || url.password().is_some();
+    let secret_query = url.query_pairs().any(|(name, _)| is_secret_name(&name));
+    if !credentials && !secret_query {
+        return arg.to_string();
+    }
+
+    let mut redacted = url.clone();
+    if credentials {
+        // Placeholders stay readable, where [REDACTED] would be percent-encoded
+        let _ = redacted.set_username("REDACTED");
+        let _ = redacted.set_password(None);
+    }
+    if secret_query {
+        let pairs: Vec<(String, String)> = url
+            .query_pairs()
+            .map(|(name, value)| {
+                let value = if is_secret_name(&name) {
+                    "REDACTED".to_string()
+                } else {
+                    value.into_owned()
+                };
+                (name.into_owned(), value)
+            })
+            .collect();
+        redacted.query_pairs_mut().clear().extend_pairs(pairs);
+    }
+    redacted.to_string()
+}
+
+/// `arg` single-quoted when a shell would otherwise split or expand it
+fn shell_quote(arg: &str) -> String {
+    let plain = !arg.is_empty()
+        && arg.chars().all(|c| {
+            c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.' | '/' | ':' | '=' | ',' | '@')
+        });
+    if plain {
+        arg.to_string()
+    } else {
+        format!("'{}'", arg.replace('\'', r"'\''"))
+    }
+}
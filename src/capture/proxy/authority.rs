@@ -0,0 +1,38 @@
+//! Host and port handling shared by capture and serve
+//!
+//! CONNECT requests name their target as `host:port`, while URLs leave out
+//! the scheme's default port. Requests sent through a tunnel are recorded
+//! under [`origin`] of the tunnel, e.g. `https://example.com/` for port 443
+//! and `https://example.com:8443/` otherwise, so serve rebuilds the same URLs
+//! to find them.
+
+/// Port assumed when a CONNECT target has none
+pub const DEFAULT_TUNNEL_PORT: u16 = 443;
+
+/// Split a CONNECT target such as `example.com:8443` or `[::1]:443` into host and port
+///
+/// The port defaults to 443 when it is missing or invalid.
+pub fn split_authority(authority: &str) -> (String, u16) {
+    match authority.rsplit_once(':') {
+        // A colon inside brackets belongs to an IPv6 address
+        Some((host, port)) if !host.contains(':') || host.ends_with(']') => (
+            host.to_string(),
+            port.parse().unwrap_or(DEFAULT_TUNNEL_PORT),
+        ),
+        _ => (authority.to_string(), DEFAULT_TUNNEL_PORT),
+    }
+}
+
+/// Build `scheme://host:port`, leaving out the port if it is the scheme's default
+pub fn origin(scheme: &str, host: &str, port: u16) -> String {
+    let default_port = match scheme {
+        "https" => Some(443),
+        "http" => Some(80),
+        _ => None,
+    };
+    if default_port == Some(port) {
+        format!("{}://{}", scheme, host)
+    } else {
+        format!("{}://{}:{}", scheme, host, port)
+    }
+}
@@ -1,20 +1,123 @@
 use bytes::Bytes;
-use http_body_util::Full;
-use hyper_tls::HttpsConnector;
+use http_body_util::combinators::BoxBody;
+use hyper_rustls::{HttpsConnector, HttpsConnectorBuilder};
 use hyper_util::client::legacy::{connect::HttpConnector, Client};
-use std::collections::HashMap;
-use std::sync::Arc;
-use std::time::Duration;
-use tokio::sync::RwLock;
+use rustls::{ClientConfig, RootCertStore};
+use std::collections::hash_map::RandomState;
+use std::collections::{BTreeMap, HashMap};
+use std::hash::BuildHasher;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::sync::{OwnedSemaphorePermit, RwLock, Semaphore};
+use tracing::warn;
 
-type HttpsClient = Client<HttpsConnector<HttpConnector>, Full<Bytes>>;
+use super::connection_info::{InspectingConnector, ObservedTls, TlsDetails};
+use super::diagnostics::DiagnosticsLog;
+use crate::capture::warnings::{CaptureWarningKind, CaptureWarnings};
+
+/// Request body type accepted by pooled clients, so bodies can be buffered or streamed
+pub type ProxyBody = BoxBody<Bytes, Box<dyn std::error::Error + Send + Sync>>;
+
+pub type HttpsClient = Client<InspectingConnector, ProxyBody>;
 type SharedClient = Arc<HttpsClient>;
 type ClientMap = HashMap<String, SharedClient>;
 
+/// Connections opened to one host at most, as browsers do
+pub const DEFAULT_MAX_CONNECTIONS_PER_HOST: usize = 6;
+
+/// Connections opened across all hosts at most
+pub const DEFAULT_MAX_CONNECTIONS: usize = 64;
+
+/// How often and how patiently idempotent requests are retried upstream
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RetryPolicy {
+    /// Retries after the first attempt; 0 disables retrying
+    pub max_retries: u32,
+    /// Delay before the first retry, doubled for each further one
+    pub base_delay: Duration,
+    /// Upper bound of a single delay
+    pub max_delay: Duration,
+}
+
+impl RetryPolicy {
+    /// Retry up to `max_retries` times with the default delays
+    pub fn new(max_retries: u32) -> Self {
+        Self {
+            max_retries,
+            base_delay: Duration::from_millis(200),
+            max_delay: Duration::from_secs(5),
+        }
+    }
+
+    /// Delay before retry number `retry` (starting at 1)
+    ///
+    /// The exponential delay is capped at `max_delay`, and a random amount of
+    /// up to half of it is added so parallel retries don't fire in lockstep.
+    pub fn backoff(&self, retry: u32) -> Duration {
+        let exponential = self
+            .base_delay
+            .saturating_mul(1u32 << retry.saturating_sub(1).min(16))
+            .min(self.max_delay);
+        let jitter_range = exponential.as_millis() as u64 / 2;
+        let jitter = if jitter_range == 0 {
+            0
+        } else {
+            RandomState::new().hash_one(Instant::now()) % (jitter_range + 1)
+        };
+        exponential + Duration::from_millis(jitter)
+    }
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self::new(0)
+    }
+}
+
+/// How the pool's upstream connections were used
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ConnectionStats {
+    /// Connections opened upstream
+    pub opened: u64,
+    /// Requests answered on a connection that was already open
+    pub reused: u64,
+    /// Requests that had to wait for a connection slot
+    pub queued: u64,
+    /// Time requests waited for a connection slot, summed
+    pub queued_time: Duration,
+}
+
+/// A request's place under the pool's connection limits, freed when dropped
+pub(crate) struct ConnectionSlot {
+    _host: OwnedSemaphorePermit,
+    _total: OwnedSemaphorePermit,
+}
+
 /// HTTP client pool for efficient connection reuse
+///
+/// Requests to a host beyond `max_connections_per_host`, or to any host
+/// beyond `max_connections`, wait for a request to finish instead of making
+/// the client open another connection.
 pub struct HttpClientPool {
     clients: Arc<RwLock<ClientMap>>,
     max_idle_per_host: usize,
+    max_connections_per_host: usize,
+    host_slots: Mutex<HashMap<String, Arc<Semaphore>>>,
+    slots: Arc<Semaphore>,
+    observed_tls: ObservedTls,
+    retry: RetryPolicy,
+    /// Retries are only started if they can finish waiting before this
+    retry_deadline: Mutex<Option<Instant>>,
+    retried_requests: AtomicU64,
+    connections_opened: Arc<AtomicU64>,
+    answered_requests: AtomicU64,
+    queued_requests: AtomicU64,
+    queued_micros: AtomicU64,
+    /// Where connections that couldn't be opened are counted
+    diagnostics: Arc<DiagnosticsLog>,
+    /// Where retried requests are noted for the capture's final report
+    warnings: CaptureWarnings,
 }
 
 impl HttpClientPool {
@@ -23,14 +126,152 @@ impl HttpClientPool {
         Self {
             clients: Arc::new(RwLock::new(HashMap::new())),
             max_idle_per_host: 10,
+            max_connections_per_host: DEFAULT_MAX_CONNECTIONS_PER_HOST,
+            host_slots: Mutex::new(HashMap::new()),
+            slots: Arc::new(Semaphore::new(DEFAULT_MAX_CONNECTIONS)),
+            observed_tls: ObservedTls::default(),
+            retry: RetryPolicy::default(),
+            retry_deadline: Mutex::new(None),
+            retried_requests: AtomicU64::new(0),
+            connections_opened: Arc::new(AtomicU64::new(0)),
+            answered_requests: AtomicU64::new(0),
+            queued_requests: AtomicU64::new(0),
+            queued_micros: AtomicU64::new(0),
+            diagnostics: Arc::new(DiagnosticsLog::new()),
+            warnings: CaptureWarnings::new(),
+        }
+    }
+
+    /// Open at most `max` connections to any one host
+    pub fn with_max_connections_per_host(mut self, max: usize) -> Self {
+        self.max_connections_per_host = max.max(1);
+        self
+    }
+
+    /// Open at most `max` connections across all hosts
+    pub fn with_max_connections(mut self, max: usize) -> Self {
+        self.slots = Arc::new(Semaphore::new(max.max(1)));
+        self
+    }
+
+    /// Get how many connections are opened to one host at most
+    pub fn max_connections_per_host(&self) -> usize {
+        self.max_connections_per_host
+    }
+
+    /// Retry idempotent requests that fail transiently according to `policy`
+    pub fn with_retry_policy(mut self, policy: RetryPolicy) -> Self {
+        self.retry = policy;
+        self
+    }
+
+    /// Count connection failures in `diagnostics`, e.g. the recorder's
+    pub fn with_diagnostics(mut self, diagnostics: Arc<DiagnosticsLog>) -> Self {
+        self.diagnostics = diagnostics;
+        self
+    }
+
+    /// Note retried requests in `warnings`, e.g. the recorder's
+    pub fn with_warnings(mut self, warnings: CaptureWarnings) -> Self {
+        self.warnings = warnings;
+        self
+    }
+
+    /// Get where connection failures are counted
+    pub fn diagnostics(&self) -> &Arc<DiagnosticsLog> {
+        &self.diagnostics
+    }
+
+    /// Get the retry policy requests are forwarded with
+    pub fn retry_policy(&self) -> RetryPolicy {
+        self.retry
+    }
+
+    /// Stop starting retries whose backoff would end after `deadline`
+    ///
+    /// Set from the capture timeout, so a flaky origin can't keep a page
+    /// load waiting past its budget.
+    pub fn set_retry_deadline(&self, deadline: Option<Instant>) {
+        *self
+            .retry_deadline
+            .lock()
+            .unwrap_or_else(|e| e.into_inner()) = deadline;
+    }
+
+    /// Check whether a retry after waiting `delay` still fits the deadline
+    pub fn retry_fits(&self, delay: Duration) -> bool {
+        let deadline = *self
+            .retry_deadline
+            .lock()
+            .unwrap_or_else(|e| e.into_inner());
+        deadline.is_none_or(|deadline| Instant::now() + delay < deadline)
+    }
+
+    /// Count a request to `url` that needed at least one retry
+    pub(crate) fn note_retried(&self, url: &str) {
+        self.retried_requests.fetch_add(1, Ordering::Relaxed);
+        self.warnings.note(CaptureWarningKind::RetriedRequest, url);
+    }
+
+    /// Get how many requests needed at least one retry
+    pub fn retried_requests(&self) -> u64 {
+        self.retried_requests.load(Ordering::Relaxed)
+    }
+
+    /// Wait until a request to `host` fits under the connection limits
+    ///
+    /// The slot is held until the response has been read, so the client
+    /// never needs more connections to a host than there are slots.
+    pub(crate) async fn acquire_slot(&self, host: &str) -> ConnectionSlot {
+        let host_slots = {
+            let mut host_slots = self.host_slots.lock().unwrap_or_else(|e| e.into_inner());
+            Arc::clone(
+                host_slots
+                    .entry(host.to_string())
+                    .or_insert_with(|| Arc::new(Semaphore::new(self.max_connections_per_host))),
+            )
+        };
+
+        // Take the host's slot first, so a busy host doesn't hold global slots while it waits
+        let started = Instant::now();
+        let (host_permit, host_waited) = take_slot(host_slots).await;
+        let (total_permit, total_waited) = take_slot(Arc::clone(&self.slots)).await;
+        if host_waited || total_waited {
+            self.queued_requests.fetch_add(1, Ordering::Relaxed);
+            self.queued_micros
+                .fetch_add(started.elapsed().as_micros() as u64, Ordering::Relaxed);
+        }
+
+        ConnectionSlot {
+            _host: host_permit,
+            _total: total_permit,
+        }
+    }
+
+    /// Count a request the upstream answered
+    pub(crate) fn note_answered(&self) {
+        self.answered_requests.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Get how the pool's upstream connections were used so far
+    ///
+    /// Every answered request that didn't need a new connection counts as
+    /// reusing one.
+    pub fn connection_stats(&self) -> ConnectionStats {
+        let opened = self.connections_opened.load(Ordering::Relaxed);
+        ConnectionStats {
+            opened,
+            reused: self
+                .answered_requests
+                .load(Ordering::Relaxed)
+                .saturating_sub(opened),
+            queued: self.queued_requests.load(Ordering::Relaxed),
+            queued_time: Duration::from_micros(self.queued_micros.load(Ordering::Relaxed)),
         }
     }
 
     /// Get or create a client for the given host
-    pub async fn get_client(
-        &self,
-        host: &str,
-    ) -> Arc<Client<HttpsConnector<HttpConnector>, Full<Bytes>>> {
+    pub async fn get_client(&self, host: &str) -> Arc<HttpsClient> {
         // Check if we already have a client for this host
         {
             let clients = self.clients.read().await;
@@ -40,7 +281,11 @@ impl HttpClientPool {
         }
 
         // Create a new client with optimized settings
-        let https = HttpsConnector::new();
+        let https = InspectingConnector::new(
+            Self::https_connector(),
+            Arc::clone(&self.observed_tls),
+            Arc::clone(&self.connections_opened),
+        );
         let client = Client::builder(hyper_util::rt::TokioExecutor::new())
             .pool_idle_timeout(Duration::from_secs(30))
             .pool_max_idle_per_host(self.max_idle_per_host)
@@ -60,10 +305,42 @@ impl HttpClientPool {
         client
     }
 
+    /// Build an HTTP/1.1 connector that trusts the platform root certificates
+    pub(crate) fn https_connector() -> HttpsConnector<HttpConnector> {
+        let builder = HttpsConnectorBuilder::new()
+            .with_native_roots()
+            .unwrap_or_else(|e| {
+                warn!("No platform root certificates available: {}", e);
+                HttpsConnectorBuilder::new().with_tls_config(
+                    ClientConfig::builder()
+                        .with_root_certificates(RootCertStore::empty())
+                        .with_no_client_auth(),
+                )
+            });
+
+        builder.https_or_http().enable_http1().build()
+    }
+
+    /// Get the TLS details of every origin connected to so far, keyed by `host:port`
+    pub fn tls_details(&self) -> BTreeMap<String, TlsDetails> {
+        self.observed_tls
+            .lock()
+            .map(|observed| observed.clone())
+            .unwrap_or_default()
+    }
+
     /// Clear all cached clients (useful for cleanup)
+    ///
+    /// Dropping a client drops its idle connections, which closes them
+    /// right away instead of after the idle timeout.
     pub async fn clear(&self) {
         let mut clients = self.clients.write().await;
         clients.clear();
+        drop(clients);
+        self.host_slots
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .clear();
     }
 
     /// Get the number of cached clients
@@ -73,6 +350,18 @@ impl HttpClientPool {
     }
 }
 
+/// Take a slot of `slots`, reporting whether it had to wait for one
+async fn take_slot(slots: Arc<Semaphore>) -> (OwnedSemaphorePermit, bool) {
+    if let Ok(permit) = Arc::clone(&slots).try_acquire_owned() {
+        return (permit, false);
+    }
+    let permit = slots
+        .acquire_owned()
+        .await
+        .expect("connection slots are never closed");
+    (permit, true)
+}
+
 impl Default for HttpClientPool {
     fn default() -> Self {
         Self::new()
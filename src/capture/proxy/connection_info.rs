@@ -0,0 +1,115 @@
+//! Connection details observed by the upstream client during capture
+
+use hyper::Uri;
+use hyper_rustls::{HttpsConnector, MaybeHttpsStream};
+use hyper_util::client::legacy::connect::HttpConnector;
+use hyper_util::rt::TokioIo;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll};
+use tokio::net::TcpStream;
+use tower_service::Service;
+use tracing::debug;
+
+/// TLS parameters negotiated with an origin
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TlsDetails {
+    /// Protocol version, e.g. `TLSv1.3`
+    pub protocol_version: Option<String>,
+    /// Negotiated cipher suite, e.g. `TLS13_AES_128_GCM_SHA256`
+    pub cipher_suite: Option<String>,
+    /// ALPN protocol the origin selected, if any
+    pub alpn: Option<String>,
+}
+
+/// TLS details keyed by `host:port`, shared by every pooled client
+pub type ObservedTls = Arc<Mutex<BTreeMap<String, TlsDetails>>>;
+
+type UpstreamStream = MaybeHttpsStream<TokioIo<TcpStream>>;
+type BoxError = Box<dyn std::error::Error + Send + Sync>;
+
+/// HTTPS connector that records the TLS parameters of each new connection
+#[derive(Clone)]
+pub struct InspectingConnector {
+    inner: HttpsConnector<HttpConnector>,
+    observed: ObservedTls,
+    /// Incremented for every connection opened
+    opened: Arc<AtomicU64>,
+}
+
+impl InspectingConnector {
+    pub fn new(
+        inner: HttpsConnector<HttpConnector>,
+        observed: ObservedTls,
+        opened: Arc<AtomicU64>,
+    ) -> Self {
+        Self {
+            inner,
+            observed,
+            opened,
+        }
+    }
+}
+
+impl Service<Uri> for InspectingConnector {
+    type Response = UpstreamStream;
+    type Error = BoxError;
+    type Future = Pin<Box<dyn Future<Output = Result<UpstreamStream, BoxError>> + Send>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, uri: Uri) -> Self::Future {
+        let host = host_key(&uri);
+        let connecting = self.inner.call(uri);
+        let observed = Arc::clone(&self.observed);
+        let opened = Arc::clone(&self.opened);
+
+        Box::pin(async move {
+            let stream = connecting.await?;
+            opened.fetch_add(1, Ordering::Relaxed);
+
+            if let (MaybeHttpsStream::Https(tls), Some(host)) = (&stream, host) {
+                let (_, connection) = tls.inner().get_ref();
+                let details = TlsDetails {
+                    protocol_version: connection
+                        .protocol_version()
+                        .and_then(|version| version.as_str())
+                        .map(|version| version.replace('_', ".")),
+                    cipher_suite: connection
+                        .negotiated_cipher_suite()
+                        .and_then(|suite| suite.suite().as_str())
+                        .map(str::to_string),
+                    alpn: connection
+                        .alpn_protocol()
+                        .map(|alpn| String::from_utf8_lossy(alpn).into_owned()),
+                };
+                debug!("TLS connection to {}: {:?}", host, details);
+
+                if let Ok(mut observed) = observed.lock() {
+                    observed.insert(host, details);
+                }
+            }
+
+            Ok(stream)
+        })
+    }
+}
+
+/// Key a URI by `host:port`, defaulting the port from the scheme
+fn host_key(uri: &Uri) -> Option<String> {
+    let host = uri.host()?;
+    let port = uri
+        .port_u16()
+        .unwrap_or(if uri.scheme_str() == Some("http") {
+            80
+        } else {
+            443
+        });
+    Some(format!("{}:{}", host, port))
+}
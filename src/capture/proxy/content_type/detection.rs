@@ -1,4 +1,5 @@
 use super::image::ImageDetector;
+use super::magic::MagicDetector;
 use super::text::TextDetector;
 use mime::Mime;
 
@@ -52,6 +53,48 @@ impl ContentTypeHelper {
         "application/octet-stream".to_string()
     }
 
+    /// Check whether a content type value says anything useful about the body
+    ///
+    /// Empty values, `unknown` and unparseable strings are what origins that
+    /// don't set the header leave behind.
+    pub fn is_usable(content_type: &str) -> bool {
+        let content_type = content_type.trim();
+        !content_type.is_empty()
+            && !content_type.eq_ignore_ascii_case("unknown")
+            && content_type.parse::<Mime>().is_ok()
+    }
+
+    /// Best guess at the content type of a response that didn't declare one
+    ///
+    /// Binary signatures win, then the URL's file extension, then text
+    /// heuristics. Returns `None` when nothing points anywhere.
+    pub fn sniff(body: &[u8], url: Option<&str>) -> Option<String> {
+        if let Some(mime) = MagicDetector::detect(body) {
+            return Some(mime.to_string());
+        }
+
+        let from_path = url
+            .and_then(|url| url::Url::parse(url).ok())
+            .and_then(|url| mime_guess::from_path(url.path()).first());
+        if let Some(mime) = from_path {
+            return Some(mime.to_string());
+        }
+
+        if body.is_empty() {
+            None
+        } else if Self::is_likely_html(body) {
+            Some("text/html".to_string())
+        } else if Self::is_likely_json(body) {
+            Some("application/json".to_string())
+        } else if Self::is_likely_xml(body) {
+            Some("application/xml".to_string())
+        } else if Self::is_likely_text(body) {
+            Some("text/plain".to_string())
+        } else {
+            None
+        }
+    }
+
     /// Check if body content is likely to be HTML
     pub fn is_likely_html(body: &[u8]) -> bool {
         TextDetector::is_likely_html(body)
@@ -0,0 +1,47 @@
+/// Helper for recognizing binary formats by their leading bytes
+pub struct MagicDetector;
+
+/// Signatures checked at the start of the body, most specific first
+const SIGNATURES: &[(&[u8], &str)] = &[
+    (b"\x89PNG\r\n\x1a\n", "image/png"),
+    (b"\xFF\xD8\xFF", "image/jpeg"),
+    (b"GIF87a", "image/gif"),
+    (b"GIF89a", "image/gif"),
+    (b"\x00\x00\x01\x00", "image/x-icon"),
+    (b"%PDF-", "application/pdf"),
+    (b"wOFF", "font/woff"),
+    (b"wOF2", "font/woff2"),
+    (b"OTTO", "font/otf"),
+    (b"\x00\x01\x00\x00\x00", "font/ttf"),
+    (b"\x00asm", "application/wasm"),
+    (b"\x1F\x8B", "application/gzip"),
+    (b"PK\x03\x04", "application/zip"),
+];
+
+impl MagicDetector {
+    /// Get the MIME type of a body whose format is unambiguous from its first bytes
+    ///
+    /// Text formats are left to the URL and text heuristics, so a `None` here
+    /// doesn't mean the body is unknown.
+    pub fn detect(body: &[u8]) -> Option<&'static str> {
+        if let Some((_, mime)) = SIGNATURES
+            .iter()
+            .find(|(signature, _)| body.starts_with(signature))
+        {
+            return Some(mime);
+        }
+
+        // Container formats carry their type a few bytes in
+        if body.len() >= 12 && body.starts_with(b"RIFF") && &body[8..12] == b"WEBP" {
+            return Some("image/webp");
+        }
+        if body.len() >= 12 && &body[4..8] == b"ftyp" {
+            return Some(match &body[8..12] {
+                b"avif" | b"avis" => "image/avif",
+                _ => "video/mp4",
+            });
+        }
+
+        None
+    }
+}
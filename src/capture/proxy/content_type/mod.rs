@@ -1,5 +1,6 @@
 pub mod detection;
 pub mod image;
+pub mod magic;
 pub mod text;
 
 pub use detection::ContentTypeHelper;
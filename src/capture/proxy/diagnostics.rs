@@ -0,0 +1,203 @@
+//! Connection-level bookkeeping of the capture proxy
+//!
+//! A capture that lacks requests may have never been sent them, or the proxy
+//! may have lost them before a record was made: a client that refused the
+//! TLS handshake, an origin that couldn't be reached, or a client that went
+//! away before the answer. Records only cover requests that got that far, so
+//! those failures are counted here and kept with the snapshot.
+
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt;
+use std::sync::Mutex;
+use std::time::Instant;
+
+/// Most errors kept in [`CaptureDiagnostics::recent_errors`]
+pub const MAX_RECENT_ERRORS: usize = 50;
+
+/// What happened to the proxy's connections during a capture
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CaptureDiagnostics {
+    /// Client connections the proxy accepted
+    pub accepted_connections: u64,
+    /// TLS handshakes with the client that failed, by the SNI host it asked for
+    pub tls_handshake_failures: BTreeMap<String, u64>,
+    /// Connections to origins that couldn't be opened, by host
+    pub upstream_connect_failures: BTreeMap<String, u64>,
+    /// Requests that arrived but failed before a record was made
+    pub unrecorded_requests: u64,
+    /// The latest of the failures above, oldest first
+    pub recent_errors: Vec<DiagnosticEvent>,
+}
+
+/// One failure kept in [`CaptureDiagnostics::recent_errors`]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DiagnosticEvent {
+    /// When it happened, in milliseconds since the capture started
+    pub offset_ms: u64,
+    pub kind: DiagnosticKind,
+    /// Host, or request for an unrecorded one, e.g. `GET https://example.com/api`
+    pub target: String,
+    pub error: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DiagnosticKind {
+    TlsHandshake,
+    UpstreamConnect,
+    UnrecordedRequest,
+}
+
+impl fmt::Display for DiagnosticKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.pad(match self {
+            DiagnosticKind::TlsHandshake => "TLS handshake",
+            DiagnosticKind::UpstreamConnect => "upstream connect",
+            DiagnosticKind::UnrecordedRequest => "unrecorded request",
+        })
+    }
+}
+
+impl CaptureDiagnostics {
+    /// Check whether nothing was counted, as for snapshots captured before diagnostics were kept
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+
+    /// Number of failures that may have cost the capture requests
+    pub fn failures(&self) -> u64 {
+        self.tls_handshake_failures.values().sum::<u64>()
+            + self.upstream_connect_failures.values().sum::<u64>()
+            + self.unrecorded_requests
+    }
+
+    /// One line per counter with failures, e.g. `TLS handshake failures: 3 (api.example.com 3)`
+    pub fn summary_lines(&self) -> Vec<String> {
+        let by_host = |counts: &BTreeMap<String, u64>| {
+            let mut hosts: Vec<(&String, &u64)> = counts.iter().collect();
+            hosts.sort_by(|a, b| b.1.cmp(a.1).then_with(|| a.0.cmp(b.0)));
+            hosts
+                .iter()
+                .map(|(host, count)| format!("{} {}", host, count))
+                .collect::<Vec<_>>()
+                .join(", ")
+        };
+        let mut lines = Vec::new();
+        if !self.tls_handshake_failures.is_empty() {
+            lines.push(format!(
+                "TLS handshake failures: {} ({})",
+                self.tls_handshake_failures.values().sum::<u64>(),
+                by_host(&self.tls_handshake_failures)
+            ));
+        }
+        if !self.upstream_connect_failures.is_empty() {
+            lines.push(format!(
+                "Upstream connect failures: {} ({})",
+                self.upstream_connect_failures.values().sum::<u64>(),
+                by_host(&self.upstream_connect_failures)
+            ));
+        }
+        if self.unrecorded_requests > 0 {
+            lines.push(format!(
+                "Requests lost before recording: {}",
+                self.unrecorded_requests
+            ));
+        }
+        lines
+    }
+}
+
+/// Collects a [`CaptureDiagnostics`] while the proxy runs
+///
+/// Shared by the recorder and the client pool, which see different failures.
+#[derive(Debug)]
+pub struct DiagnosticsLog {
+    started: Mutex<Instant>,
+    diagnostics: Mutex<CaptureDiagnostics>,
+}
+
+impl DiagnosticsLog {
+    pub fn new() -> Self {
+        Self {
+            started: Mutex::new(Instant::now()),
+            diagnostics: Mutex::new(CaptureDiagnostics::default()),
+        }
+    }
+
+    /// Count a client connection the proxy accepted
+    pub fn connection_accepted(&self) {
+        self.update(|diagnostics| diagnostics.accepted_connections += 1);
+    }
+
+    /// Count a failed TLS handshake with a client that asked for `host`
+    pub fn tls_handshake_failed(&self, host: &str, error: &str) {
+        self.count_host(DiagnosticKind::TlsHandshake, host, error);
+    }
+
+    /// Count a connection to `host` that couldn't be opened
+    pub fn upstream_connect_failed(&self, host: &str, error: &str) {
+        self.count_host(DiagnosticKind::UpstreamConnect, host, error);
+    }
+
+    /// Count a request that failed before it was recorded, e.g. `GET https://example.com/`
+    pub fn request_unrecorded(&self, request: &str, error: &str) {
+        self.note(DiagnosticKind::UnrecordedRequest, request, error);
+        self.update(|diagnostics| diagnostics.unrecorded_requests += 1);
+    }
+
+    /// What was counted so far
+    pub fn diagnostics(&self) -> CaptureDiagnostics {
+        self.diagnostics
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .clone()
+    }
+
+    /// Start counting again, with event times from now
+    pub fn clear(&self) {
+        *self.diagnostics.lock().unwrap_or_else(|e| e.into_inner()) = CaptureDiagnostics::default();
+        *self.started.lock().unwrap_or_else(|e| e.into_inner()) = Instant::now();
+    }
+
+    /// Keep the event and count it against `host`
+    fn count_host(&self, kind: DiagnosticKind, host: &str, error: &str) {
+        self.note(kind, host, error);
+        self.update(|diagnostics| {
+            let counts = match kind {
+                DiagnosticKind::TlsHandshake => &mut diagnostics.tls_handshake_failures,
+                _ => &mut diagnostics.upstream_connect_failures,
+            };
+            *counts.entry(host.to_ascii_lowercase()).or_insert(0) += 1;
+        });
+    }
+
+    fn note(&self, kind: DiagnosticKind, target: &str, error: &str) {
+        let offset_ms = {
+            let started = self.started.lock().unwrap_or_else(|e| e.into_inner());
+            u64::try_from(started.elapsed().as_millis()).unwrap_or(u64::MAX)
+        };
+        self.update(|diagnostics| {
+            if diagnostics.recent_errors.len() >= MAX_RECENT_ERRORS {
+                diagnostics.recent_errors.remove(0);
+            }
+            diagnostics.recent_errors.push(DiagnosticEvent {
+                offset_ms,
+                kind,
+                target: target.to_string(),
+                error: error.to_string(),
+            });
+        });
+    }
+
+    fn update(&self, change: impl FnOnce(&mut CaptureDiagnostics)) {
+        change(&mut self.diagnostics.lock().unwrap_or_else(|e| e.into_inner()));
+    }
+}
+
+impl Default for DiagnosticsLog {
+    fn default() -> Self {
+        Self::new()
+    }
+}
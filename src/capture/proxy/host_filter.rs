@@ -0,0 +1,72 @@
+//! Host allowlist for `capture --only-hosts`
+//!
+//! Pages pull in analytics, ads and fonts from hosts a snapshot rarely
+//! needs. With an allowlist, the capture proxy answers plain requests and
+//! CONNECTs to any other host with a 403 before connecting upstream, so the
+//! browser gives up on them at once and the page settles sooner. The hosts
+//! of the captured pages are always allowed.
+
+use globset::{GlobBuilder, GlobMatcher};
+
+use crate::error::{Result, WebMockError};
+
+/// Hosts the capture proxy forwards to, as exact names or globs such as `*.example.com`
+#[derive(Debug, Clone, Default)]
+pub struct HostAllowlist {
+    patterns: Vec<(String, GlobMatcher)>,
+}
+
+impl HostAllowlist {
+    /// Parse an `--only-hosts` value such as `app.example.com,*.cdn.example.com`
+    pub fn parse(spec: &str) -> Result<Self> {
+        let mut allowlist = Self::default();
+        for pattern in spec.split(',').map(str::trim).filter(|p| !p.is_empty()) {
+            if pattern.contains(['/', ':']) {
+                return Err(WebMockError::config(format!(
+                    "Invalid --only-hosts entry '{}': give a host name without scheme, port or path",
+                    pattern
+                )));
+            }
+            allowlist.push(pattern)?;
+        }
+        if allowlist.patterns.is_empty() {
+            return Err(WebMockError::config(
+                "--only-hosts needs at least one host, e.g. app.example.com",
+            ));
+        }
+        Ok(allowlist)
+    }
+
+    /// Allow `host` as well, e.g. the host of a page being captured
+    pub fn allow(&mut self, host: &str) {
+        if !self.allows(host) {
+            // A bare host name can't be an invalid glob once its brackets are escaped
+            let _ = self.push(&globset::escape(host));
+        }
+    }
+
+    /// Check whether requests to `host` may be forwarded
+    pub fn allows(&self, host: &str) -> bool {
+        let host = host.trim_end_matches('.').to_ascii_lowercase();
+        self.patterns
+            .iter()
+            .any(|(_, matcher)| matcher.is_match(&host))
+    }
+
+    /// The allowed hosts and globs, in the order they were given
+    pub fn patterns(&self) -> impl Iterator<Item = &str> {
+        self.patterns.iter().map(|(pattern, _)| pattern.as_str())
+    }
+
+    fn push(&mut self, pattern: &str) -> Result<()> {
+        let matcher = GlobBuilder::new(pattern)
+            .case_insensitive(true)
+            .build()
+            .map_err(|e| {
+                WebMockError::config(format!("Invalid --only-hosts entry '{}': {}", pattern, e))
+            })?
+            .compile_matcher();
+        self.patterns.push((pattern.to_string(), matcher));
+        Ok(())
+    }
+}
@@ -1,13 +1,25 @@
+pub mod authority;
 pub mod client_pool;
+pub mod connection_info;
 pub mod content_type;
+pub mod diagnostics;
+pub mod host_filter;
 pub mod recorder;
 pub mod records;
+pub mod redaction;
 pub mod server;
 pub mod streaming;
+pub mod tail;
 
+pub use crate::tls::{certificate, WebMockCertificate};
 pub use client_pool::HttpClientPool;
+pub use connection_info::TlsDetails;
 pub use content_type::ContentTypeHelper;
-pub use recorder::RequestRecorder;
-pub use records::{RequestRecord, ResponseRecord};
+pub use diagnostics::{CaptureDiagnostics, DiagnosticEvent, DiagnosticKind};
+pub use host_filter::HostAllowlist;
+pub use recorder::{DocumentResponse, InFlightRequest, RequestRecorder};
+pub use records::{InterimResponse, RecordTiming, RequestRecord, ResponseRecord, TunnelRecord};
+pub use redaction::RedactionRules;
 pub use server::HttpProxy;
 pub use streaming::{ResponseCollector, StreamingBody, StreamingWriter};
+pub use tail::{RecordTail, RecordedRequest, TailEvent};
@@ -1,36 +1,490 @@
-use std::sync::Arc;
-use tokio::sync::Mutex;
-use tracing::debug;
+use hyper::header::{ACCEPT, UPGRADE};
+use hyper::HeaderMap;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex as StdMutex};
+use std::time::Instant;
+use tokio::sync::{broadcast, watch, Mutex};
+use tracing::{debug, warn};
+use url::Url;
 
-use super::records::RequestRecord;
+use super::diagnostics::{CaptureDiagnostics, DiagnosticsLog};
+use super::host_filter::HostAllowlist;
+use super::records::{RequestRecord, TunnelRecord};
+use super::redaction::RedactionRules;
+use super::tail::{record_channel, RecordTail, RecordedRequest};
+use crate::capture::canonical_url::canonical_url;
+use crate::capture::warnings::{CaptureWarningKind, CaptureWarnings};
 
+/// Collects the records of a capture
+///
+/// Records are pushed under a short synchronous lock that is never held
+/// across an await, so recording can't stall the proxy's forwarding. With a
+/// request cap, requests past it are still forwarded but only counted.
+///
+/// Records are timed against the recorder's own monotonic clock rather than
+/// the wall clock, and come out in the order their requests arrived.
 pub struct RequestRecorder {
-    records: Arc<Mutex<Vec<RequestRecord>>>,
+    records: Arc<StdMutex<Vec<RequestRecord>>>,
+    tunnels: Arc<Mutex<Vec<TunnelRecord>>>,
+    redaction: RedactionRules,
+    tail: broadcast::Sender<RecordedRequest>,
+    max_requests: Option<usize>,
+    /// Slots handed out under `max_requests`
+    accepted: AtomicUsize,
+    skipped: AtomicU64,
+    /// Sequence number of the next request to arrive
+    sequence: AtomicU64,
+    /// Start of the session that record offsets count from
+    started: StdMutex<Instant>,
+    /// Capture variant new records are tagged with
+    variant: StdMutex<Option<String>>,
+    /// Hosts requests may be forwarded to with `--only-hosts`
+    only_hosts: Option<HostAllowlist>,
+    /// Requests refused by `only_hosts`, by host
+    blocked: StdMutex<BTreeMap<String, u64>>,
+    /// Requests forwarded and not answered yet, by sequence number
+    in_flight: Arc<StdMutex<BTreeMap<u64, InFlightRequest>>>,
+    /// Connection-level failures, shared with the client pool
+    diagnostics: Arc<DiagnosticsLog>,
+    /// Warnings for the capture's final report, shared with the session
+    warnings: CaptureWarnings,
+    /// URL the navigation's main document is expected at, until its record is flagged
+    expected_document: StdMutex<Option<String>>,
+    /// The flagged main document record of the latest navigation
+    document: watch::Sender<Option<DocumentResponse>>,
+}
+
+/// The record the proxy made of a navigation's main document
+///
+/// Flagged by the recorder after [`RequestRecorder::expect_document`], once
+/// the redirects of the navigated URL lead to a response that isn't one.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DocumentResponse {
+    /// Sequence number of the flagged record
+    pub sequence: u64,
+    pub url: String,
+    pub status: u16,
+}
+
+/// A request the proxy is still waiting on upstream
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InFlightRequest {
+    pub method: String,
+    pub url: String,
+    /// When it arrived, in milliseconds on the recorder's clock
+    pub started_ms: u64,
+    /// Asked for an event stream or a websocket, so it stays open by design
+    pub streaming: bool,
+}
+
+impl InFlightRequest {
+    /// Check whether the request streams or has been open for `after_ms` at `now_ms`
+    pub fn is_long_lived(&self, now_ms: u64, after_ms: u64) -> bool {
+        self.streaming || now_ms.saturating_sub(self.started_ms) >= after_ms
+    }
+}
+
+impl fmt::Display for InFlightRequest {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} {}", self.method, self.url)
+    }
+}
+
+/// Keeps a request listed as in flight until dropped
+///
+/// A guard dropped before [`InFlightGuard::finish`] means the request was
+/// abandoned, usually because the client went away, and it is counted as
+/// unrecorded in the capture diagnostics.
+pub struct InFlightGuard {
+    in_flight: Arc<StdMutex<BTreeMap<u64, InFlightRequest>>>,
+    diagnostics: Arc<DiagnosticsLog>,
+    sequence: u64,
+    finished: bool,
+}
+
+impl InFlightGuard {
+    /// Mark the request as handled, its record made or deliberately skipped
+    pub fn finish(mut self) {
+        self.finished = true;
+    }
+}
+
+impl Drop for InFlightGuard {
+    fn drop(&mut self) {
+        let request = self.in_flight.lock().unwrap().remove(&self.sequence);
+        if let (false, Some(request)) = (self.finished, request) {
+            debug!("{} was abandoned before it was recorded", request);
+            self.diagnostics.request_unrecorded(
+                &request.to_string(),
+                "the client closed the connection before the response",
+            );
+        }
+    }
 }
 
 impl RequestRecorder {
     pub fn new() -> Self {
+        Self::with_redaction(RedactionRules::default())
+    }
+
+    /// Create a recorder that scrubs every record before storing it
+    pub fn with_redaction(redaction: RedactionRules) -> Self {
         Self {
-            records: Arc::new(Mutex::new(Vec::new())),
+            records: Arc::new(StdMutex::new(Vec::new())),
+            tunnels: Arc::new(Mutex::new(Vec::new())),
+            redaction,
+            tail: record_channel(),
+            max_requests: None,
+            accepted: AtomicUsize::new(0),
+            skipped: AtomicU64::new(0),
+            sequence: AtomicU64::new(0),
+            started: StdMutex::new(Instant::now()),
+            variant: StdMutex::new(None),
+            only_hosts: None,
+            blocked: StdMutex::new(BTreeMap::new()),
+            in_flight: Arc::new(StdMutex::new(BTreeMap::new())),
+            diagnostics: Arc::new(DiagnosticsLog::new()),
+            warnings: CaptureWarnings::new(),
+            expected_document: StdMutex::new(None),
+            document: watch::channel(None).0,
+        }
+    }
+
+    /// Stop recording after `max_requests` records; `None` records everything
+    pub fn with_max_requests(mut self, max_requests: Option<usize>) -> Self {
+        self.max_requests = max_requests;
+        self
+    }
+
+    /// Refuse requests to hosts not on `only_hosts`; `None` forwards to any host
+    pub fn with_only_hosts(mut self, only_hosts: Option<HostAllowlist>) -> Self {
+        self.only_hosts = only_hosts;
+        self
+    }
+
+    /// Note warnings in `warnings` instead of the recorder's own collector
+    pub fn with_warnings(mut self, warnings: CaptureWarnings) -> Self {
+        self.warnings = warnings;
+        self
+    }
+
+    /// Publish recorded requests on `tail` instead of the recorder's own channel
+    ///
+    /// Lets subscriptions taken before the recorder existed see its records.
+    pub fn with_tail(mut self, tail: broadcast::Sender<RecordedRequest>) -> Self {
+        self.tail = tail;
+        self
+    }
+
+    /// Follow requests as they are recorded
+    pub fn subscribe(&self) -> RecordTail {
+        RecordTail::new(&self.tail)
+    }
+
+    /// Tag records made from now on with a capture variant, or none with `None`
+    pub fn set_variant(&self, variant: Option<String>) {
+        *self.variant.lock().unwrap() = variant;
+    }
+
+    /// Flag the record of the main document when the browser navigates to `url`
+    ///
+    /// Forgets the document flagged for an earlier navigation. Redirects are
+    /// followed, so the flagged record is the response the page was built from.
+    pub fn expect_document(&self, url: &str) {
+        *self.expected_document.lock().unwrap() = Some(document_url(url));
+        self.document.send_replace(None);
+    }
+
+    /// Follow the main document flagged since the latest [`expect_document`](Self::expect_document)
+    pub fn document(&self) -> watch::Receiver<Option<DocumentResponse>> {
+        self.document.subscribe()
+    }
+
+    /// Flag `record` if it is the expected main document, or follow it if it redirects there
+    fn flag_document(&self, record: &RequestRecord) {
+        let mut expected = self.expected_document.lock().unwrap();
+        if record.method != "GET" || expected.as_deref() != Some(record.url.as_str()) {
+            return;
+        }
+        if record.response.is_redirect() {
+            let target = record
+                .response
+                .headers
+                .iter()
+                .find(|(name, _)| name.eq_ignore_ascii_case("location"))
+                .and_then(|(_, location)| Url::parse(&record.url).ok()?.join(location).ok());
+            if let Some(target) = target {
+                debug!("Main document redirects to {}", target);
+                *expected = Some(document_url(target.as_str()));
+                return;
+            }
+        }
+        *expected = None;
+        if let Some(sequence) = record.sequence {
+            debug!(
+                "Flagged {} {} as the main document",
+                record.response.status, record.url
+            );
+            self.document.send_replace(Some(DocumentResponse {
+                sequence,
+                url: record.url.clone(),
+                status: record.response.status,
+            }));
         }
     }
 
-    pub async fn record_request(&self, record: RequestRecord) {
+    /// Check whether requests to `host` may be forwarded, counting them as blocked if not
+    pub fn allow_host(&self, host: &str) -> bool {
+        let Some(only_hosts) = &self.only_hosts else {
+            return true;
+        };
+        if only_hosts.allows(host) {
+            return true;
+        }
+        debug!("Blocking request to {}, not in --only-hosts", host);
+        self.warnings.note(CaptureWarningKind::BlockedRequest, host);
+        *self
+            .blocked
+            .lock()
+            .unwrap()
+            .entry(host.to_ascii_lowercase())
+            .or_insert(0) += 1;
+        false
+    }
+
+    /// Requests refused because their host isn't in `--only-hosts`, by host
+    pub fn blocked_hosts(&self) -> BTreeMap<String, u64> {
+        self.blocked.lock().unwrap().clone()
+    }
+
+    /// Milliseconds since the session started, from a monotonic clock
+    pub fn elapsed_ms(&self) -> u64 {
+        let started = *self.started.lock().unwrap();
+        u64::try_from(started.elapsed().as_millis()).unwrap_or(u64::MAX)
+    }
+
+    /// Number a request as it arrives; see [`RequestRecord::sequence`]
+    pub fn next_sequence(&self) -> u64 {
+        self.sequence.fetch_add(1, Ordering::Relaxed)
+    }
+
+    /// List a request as in flight until the returned guard is dropped
+    ///
+    /// Requests asking for `text/event-stream` or a websocket upgrade are
+    /// marked as streaming.
+    pub fn begin_request(
+        &self,
+        sequence: u64,
+        method: &str,
+        url: &str,
+        headers: &HeaderMap,
+    ) -> InFlightGuard {
+        let streaming = headers
+            .get_all(ACCEPT)
+            .iter()
+            .filter_map(|value| value.to_str().ok())
+            .any(|accept| accept.to_ascii_lowercase().contains("text/event-stream"))
+            || headers
+                .get(UPGRADE)
+                .and_then(|value| value.to_str().ok())
+                .is_some_and(|upgrade| upgrade.eq_ignore_ascii_case("websocket"));
+        let request = InFlightRequest {
+            method: method.to_string(),
+            url: url.to_string(),
+            started_ms: self.elapsed_ms(),
+            streaming,
+        };
+        self.in_flight.lock().unwrap().insert(sequence, request);
+        InFlightGuard {
+            in_flight: Arc::clone(&self.in_flight),
+            diagnostics: Arc::clone(&self.diagnostics),
+            sequence,
+            finished: false,
+        }
+    }
+
+    /// Requests forwarded and not answered yet, in the order they arrived
+    pub fn in_flight(&self) -> Vec<InFlightRequest> {
+        self.in_flight.lock().unwrap().values().cloned().collect()
+    }
+
+    /// Where connection-level failures are counted, see [`CaptureDiagnostics`]
+    pub fn diagnostics(&self) -> &Arc<DiagnosticsLog> {
+        &self.diagnostics
+    }
+
+    /// Warnings noted for the capture's final report
+    pub fn warnings(&self) -> &CaptureWarnings {
+        &self.warnings
+    }
+
+    /// Connection-level failures counted so far
+    pub fn capture_diagnostics(&self) -> CaptureDiagnostics {
+        self.diagnostics.diagnostics()
+    }
+
+    /// Record a request, timing and numbering it now unless its handler already did
+    pub async fn record_request(&self, mut record: RequestRecord) {
+        if !self.reserve_slot() {
+            let skipped = self.skipped.fetch_add(1, Ordering::Relaxed) + 1;
+            if skipped == 1 {
+                warn!(
+                    "Reached the cap of {} recorded requests; further requests are forwarded but not recorded",
+                    self.max_requests.unwrap_or_default()
+                );
+            }
+            debug!("Not recording request: {} {}", record.method, record.url);
+            self.warnings
+                .note(CaptureWarningKind::SkippedRequest, &record.url);
+            return;
+        }
+
         debug!("Recording request: {} {}", record.method, record.url);
-        let mut records = self.records.lock().await;
-        records.push(record);
+        // CONNECT targets are host:port pairs rather than resources
+        if record.method != "CONNECT" {
+            record.url = canonical_url(&record.url);
+        }
+        if record.timing.offset_ms.is_none() {
+            record.timing.offset_ms = Some(self.elapsed_ms());
+        }
+        if record.sequence.is_none() {
+            record.sequence = Some(self.next_sequence());
+        }
+        if record.variant.is_none() {
+            record.variant = self.variant.lock().unwrap().clone();
+        }
+        // Matched before redaction, which may rewrite the URL
+        self.flag_document(&record);
+        if self.redaction.truncates(&record) {
+            self.warnings
+                .note(CaptureWarningKind::TruncatedBody, &record.url);
+        }
+        self.redaction.apply(&mut record);
+        // Keyed after redaction, so scrubbed values don't leak into the key
+        if record.vary_key.is_empty() {
+            record.vary_key = record.vary_key_from_headers();
+        }
+        if self.tail.receiver_count() > 0 {
+            // Only fails when the last subscriber has just gone away
+            let _ = self.tail.send(RecordedRequest::from(&record));
+        }
+        self.records.lock().unwrap().push(record);
     }
 
+    /// Claim room for one more record under the cap
+    fn reserve_slot(&self) -> bool {
+        match self.max_requests {
+            None => true,
+            Some(max) => self
+                .accepted
+                .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |accepted| {
+                    (accepted < max).then_some(accepted + 1)
+                })
+                .is_ok(),
+        }
+    }
+
+    /// Number of requests forwarded without being recorded because of the cap
+    pub fn skipped_requests(&self) -> u64 {
+        self.skipped.load(Ordering::Relaxed)
+    }
+
+    /// Number of records kept so far, without copying them
+    pub fn record_count(&self) -> usize {
+        self.records.lock().unwrap().len()
+    }
+
+    /// Records in the order their requests arrived
     pub async fn get_records(&self) -> Vec<RequestRecord> {
-        let records = self.records.lock().await;
-        records.clone()
+        let mut records = self.records.lock().unwrap().clone();
+        sort_by_arrival(&mut records);
+        records
+    }
+
+    /// Move the records out in the order their requests arrived, leaving the recorder empty
+    ///
+    /// Avoids holding a second copy of every body when a capture finishes.
+    pub async fn take_records(&self) -> Vec<RequestRecord> {
+        let mut records = std::mem::take(&mut *self.records.lock().unwrap());
+        sort_by_arrival(&mut records);
+        records
     }
 
     pub async fn clear_records(&self) {
-        let mut records = self.records.lock().await;
-        records.clear();
+        self.records.lock().unwrap().clear();
+        self.tunnels.lock().await.clear();
+        self.accepted.store(0, Ordering::Relaxed);
+        self.skipped.store(0, Ordering::Relaxed);
+        self.sequence.store(0, Ordering::Relaxed);
+        *self.started.lock().unwrap() = Instant::now();
+        self.diagnostics.clear();
         debug!("Cleared all recorded requests");
     }
+
+    /// Remember that the client rejected TLS interception for `host`
+    ///
+    /// Returns false if the host was already known to be pinned.
+    pub async fn mark_pinned(&self, host: &str) -> bool {
+        let mut tunnels = self.tunnels.lock().await;
+        let newly_pinned = match tunnels.iter_mut().find(|tunnel| tunnel.host == host) {
+            Some(tunnel) => !std::mem::replace(&mut tunnel.mitm_failed, true),
+            None => {
+                tunnels.push(TunnelRecord::pinned(host));
+                true
+            }
+        };
+        if newly_pinned {
+            self.warnings.note(CaptureWarningKind::PinnedHost, host);
+        }
+        newly_pinned
+    }
+
+    /// Check whether tunnels to `host` must be passed through without interception
+    pub async fn is_pinned(&self, host: &str) -> bool {
+        let tunnels = self.tunnels.lock().await;
+        tunnels
+            .iter()
+            .any(|tunnel| tunnel.host == host && tunnel.mitm_failed)
+    }
+
+    /// Add the traffic of a finished pass-through tunnel to its host's record
+    pub async fn record_tunnel_traffic(&self, host: &str, bytes_up: u64, bytes_down: u64) {
+        let mut tunnels = self.tunnels.lock().await;
+        match tunnels.iter_mut().find(|tunnel| tunnel.host == host) {
+            Some(tunnel) => {
+                tunnel.bytes_up += bytes_up;
+                tunnel.bytes_down += bytes_down;
+            }
+            None => tunnels.push(TunnelRecord {
+                host: host.to_string(),
+                bytes_up,
+                bytes_down,
+                mitm_failed: false,
+            }),
+        }
+    }
+
+    pub async fn get_tunnels(&self) -> Vec<TunnelRecord> {
+        let tunnels = self.tunnels.lock().await;
+        tunnels.clone()
+    }
+}
+
+/// The canonical form of `url` as the proxy sees it, without the fragment
+fn document_url(url: &str) -> String {
+    let mut url = canonical_url(url);
+    if let Some(fragment) = url.find('#') {
+        url.truncate(fragment);
+    }
+    url
+}
+
+/// Records are pushed as responses finish, so a slow request lands after
+/// faster ones that arrived later; sequence numbers order equal offsets
+fn sort_by_arrival(records: &mut [RequestRecord]) {
+    records.sort_by_key(|record| (record.timing.offset_ms, record.sequence));
 }
 
 impl Default for RequestRecorder {
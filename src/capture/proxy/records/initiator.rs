@@ -0,0 +1,52 @@
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// Why the browser started a request, as Chrome's network panel shows it
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum InitiatorKind {
+    /// Found by the HTML parser, e.g. a `<script src>` or `<img>`
+    Parser,
+    /// Started by a script, e.g. `fetch` or `XMLHttpRequest`
+    Script,
+    /// A `<link rel=preload>` or similar hint
+    Preload,
+    SignedExchange,
+    /// A CORS preflight for another request
+    Preflight,
+    /// Navigations and anything else
+    Other,
+}
+
+impl fmt::Display for InitiatorKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            InitiatorKind::Parser => "parser",
+            InitiatorKind::Script => "script",
+            InitiatorKind::Preload => "preload",
+            InitiatorKind::SignedExchange => "signed exchange",
+            InitiatorKind::Preflight => "preflight",
+            InitiatorKind::Other => "other",
+        })
+    }
+}
+
+/// The resource that triggered a recorded request
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RequestInitiator {
+    pub kind: InitiatorKind,
+    /// Document or script that started the load, when known
+    pub url: Option<String>,
+    /// 1-based line in `url`, when known
+    pub line: Option<u32>,
+}
+
+impl fmt::Display for RequestInitiator {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.kind)?;
+        match (&self.url, self.line) {
+            (Some(url), Some(line)) => write!(f, " {}:{}", url, line),
+            (Some(url), None) => write!(f, " {}", url),
+            _ => Ok(()),
+        }
+    }
+}
@@ -1,6 +1,10 @@
+pub mod initiator;
 pub mod request;
 pub mod response;
 pub mod serialization;
+pub mod tunnel;
 
-pub use request::RequestRecord;
-pub use response::ResponseRecord;
+pub use initiator::{InitiatorKind, RequestInitiator};
+pub use request::{RecordTiming, RequestRecord};
+pub use response::{InterimResponse, ResponseRecord};
+pub use tunnel::TunnelRecord;
@@ -1,21 +1,67 @@
 use chrono::{DateTime, Utc};
 use mime::Mime;
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 
+use super::initiator::RequestInitiator;
 use super::response::ResponseRecord;
-use super::serialization::optional_body_serialization;
+use super::serialization::{optional_body_serialization, sorted_headers};
+use crate::capture::canonical_url::canonical_url;
 use crate::capture::proxy::content_type::ContentTypeHelper;
 
+/// When a request was made during its capture, from a monotonic clock
+///
+/// NTP can step the wall clock mid-capture, so [`RequestRecord::timestamp`]
+/// is only shown to users; ordering and durations use these offsets.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RecordTiming {
+    /// Milliseconds from the start of the capture session until the request arrived
+    pub offset_ms: Option<u64>,
+    /// Milliseconds from then until its response was recorded
+    pub duration_ms: Option<u64>,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct RequestRecord {
     pub method: String,
     pub url: String,
+    #[serde(serialize_with = "sorted_headers::serialize")]
     pub headers: HashMap<String, String>,
     #[serde(with = "optional_body_serialization")]
     pub body: Option<Vec<u8>>,
     pub response: ResponseRecord,
     pub timestamp: DateTime<Utc>,
+    /// What made the browser load this request, when Chrome reported it
+    #[serde(default)]
+    pub initiator: Option<RequestInitiator>,
+    /// Missing in snapshots saved before it was recorded; filled in from
+    /// wall clocks when they are loaded
+    #[serde(default)]
+    pub timing: RecordTiming,
+    /// Request headers named by the response's `Vary`, with the values this
+    /// request sent; an empty value means the request didn't send the header
+    ///
+    /// Tells apart records of one URL captured with different variants, such
+    /// as a gzip and an identity response to different `Accept-Encoding`s.
+    #[serde(default)]
+    pub vary_key: BTreeMap<String, String>,
+    /// Name of the `capture --variant` whose cookies the request was made
+    /// with; `None` for the capture's default state
+    #[serde(default)]
+    pub variant: Option<String>,
+    /// Position the request arrived in during its capture, counting from 0
+    ///
+    /// Unlike millisecond offsets it never ties, so it orders records
+    /// captured at the same moment. Snapshots saved before it was recorded
+    /// get one when they are loaded, from the order of their records.
+    #[serde(default)]
+    pub sequence: Option<u64>,
+    /// Chrome's ID of the frame that made the request, when Chrome reported it
+    ///
+    /// Requests of an iframe share its ID, so records can be grouped by the
+    /// document that loaded them.
+    #[serde(default)]
+    pub frame_id: Option<String>,
 }
 
 impl RequestRecord {
@@ -34,9 +80,107 @@ impl RequestRecord {
             body,
             response,
             timestamp: Utc::now(),
+            initiator: None,
+            timing: RecordTiming::default(),
+            vary_key: Default::default(),
+            variant: None,
+            sequence: None,
+            frame_id: None,
+        }
+    }
+
+    /// Set when the request arrived and when its response was recorded, as
+    /// milliseconds since the session started
+    pub fn with_timing(mut self, offset_ms: u64, finished_ms: u64) -> Self {
+        self.timing = RecordTiming {
+            offset_ms: Some(offset_ms),
+            duration_ms: Some(finished_ms.saturating_sub(offset_ms)),
+        };
+        self
+    }
+
+    /// Set the position the request arrived in during its capture
+    pub fn with_sequence(mut self, sequence: u64) -> Self {
+        self.sequence = Some(sequence);
+        self
+    }
+
+    /// Tag the record as made in the named capture variant
+    pub fn with_variant(mut self, variant: impl Into<String>) -> Self {
+        self.variant = Some(variant.into());
+        self
+    }
+
+    /// Give records without a monotonic offset one derived from their wall clocks
+    ///
+    /// Offsets count from the earliest timestamp, so a clock stepped back
+    /// can't make them negative.
+    pub fn fill_missing_offsets(records: &mut [RequestRecord]) {
+        let Some(earliest) = records
+            .iter()
+            .filter(|record| record.timing.offset_ms.is_none())
+            .map(|record| record.timestamp)
+            .min()
+        else {
+            return;
+        };
+        for record in records
+            .iter_mut()
+            .filter(|record| record.timing.offset_ms.is_none())
+        {
+            let offset = (record.timestamp - earliest).num_milliseconds().max(0);
+            record.timing.offset_ms = Some(offset as u64);
         }
     }
 
+    /// Number the records without a sequence after those that have one, in slice order
+    pub fn fill_missing_sequences(records: &mut [RequestRecord]) {
+        let next = records
+            .iter()
+            .filter_map(|record| record.sequence)
+            .max()
+            .map_or(0, |last| last + 1);
+        let missing = records
+            .iter_mut()
+            .filter(|record| record.sequence.is_none());
+        for (sequence, record) in (next..).zip(missing) {
+            record.sequence = Some(sequence);
+        }
+    }
+
+    /// Put URLs spelled with Unicode hosts or raw non-ASCII characters in canonical form
+    ///
+    /// Snapshots recorded before URLs were canonicalized can hold either
+    /// spelling, which requests in canonical form wouldn't match.
+    pub fn canonicalize_unicode_urls(records: &mut [RequestRecord]) {
+        for record in records
+            .iter_mut()
+            .filter(|record| !record.url.is_ascii() && record.method != "CONNECT")
+        {
+            record.url = canonical_url(&record.url);
+        }
+    }
+
+    /// Values of the request headers the response varies on
+    ///
+    /// Names are lowercased. A `Vary: *` response can't be keyed on headers,
+    /// so `*` is skipped.
+    pub fn vary_key_from_headers(&self) -> BTreeMap<String, String> {
+        let Some(vary) = header_value(&self.response.headers, "vary") else {
+            return BTreeMap::new();
+        };
+        vary.split(',')
+            .map(|name| name.trim().to_ascii_lowercase())
+            .filter(|name| !name.is_empty() && name != "*")
+            .map(|name| {
+                let value = header_value(&self.headers, &name)
+                    .unwrap_or_default()
+                    .to_string();
+                (name, value)
+            })
+            .collect()
+    }
+
     /// Get the content type of the request body from headers
     pub fn get_request_content_type(&self) -> Option<Mime> {
         self.headers
@@ -68,3 +212,11 @@ impl RequestRecord {
         self.body.as_ref().map(|b| b.len()).unwrap_or(0)
     }
 }
+
+/// Value of a header stored under any capitalization of `name`
+fn header_value<'a>(headers: &'a HashMap<String, String>, name: &str) -> Option<&'a str> {
+    headers
+        .iter()
+        .find(|(key, _)| key.eq_ignore_ascii_case(name))
+        .map(|(_, value)| value.as_str())
+}
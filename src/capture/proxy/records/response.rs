@@ -2,16 +2,40 @@ use mime::Mime;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 
-use super::serialization::body_serialization;
+use super::serialization::{body_serialization, sorted_headers};
 use crate::capture::proxy::content_type::ContentTypeHelper;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ResponseRecord {
     pub status: u16,
+    #[serde(serialize_with = "sorted_headers::serialize")]
     pub headers: HashMap<String, String>,
     #[serde(with = "body_serialization")]
     pub body: Vec<u8>,
     pub content_type: String,
+    /// Why the upstream request failed during capture, if it did
+    #[serde(default)]
+    pub failure: Option<String>,
+    /// Every Set-Cookie value in order; `headers` only holds one per name
+    #[serde(default)]
+    pub set_cookie: Vec<String>,
+    /// HTTP version the origin answered with, e.g. `HTTP/1.1`
+    #[serde(default)]
+    pub http_version: Option<String>,
+    /// Reason phrase the origin sent, when it isn't the standard one for `status`
+    #[serde(default)]
+    pub reason: Option<String>,
+    /// 1xx responses the origin sent before this one, e.g. `103 Early Hints`, in order
+    #[serde(default)]
+    pub informational: Vec<InterimResponse>,
+}
+
+/// An informational (1xx) response sent ahead of the final response
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct InterimResponse {
+    pub status: u16,
+    /// Headers in the order sent, repeated names kept (one `Link` per preload)
+    pub headers: Vec<(String, String)>,
 }
 
 impl ResponseRecord {
@@ -29,35 +53,133 @@ impl ResponseRecord {
             headers,
             body,
             content_type,
+            failure: None,
+            set_cookie: Vec::new(),
+            http_version: None,
+            reason: None,
+            informational: Vec::new(),
+        }
+    }
+
+    /// Attach every Set-Cookie value received from the origin
+    pub fn with_set_cookie(mut self, set_cookie: Vec<String>) -> Self {
+        self.set_cookie = set_cookie;
+        self
+    }
+
+    /// Record the HTTP version the origin answered with
+    pub fn with_http_version(mut self, version: hyper::Version) -> Self {
+        self.http_version = Some(format!("{:?}", version));
+        self
+    }
+
+    /// Record a non-standard reason phrase sent by the origin
+    pub fn with_reason(mut self, reason: Option<String>) -> Self {
+        self.reason = reason;
+        self
+    }
+
+    /// Record the 1xx responses the origin sent before this one
+    pub fn with_informational(mut self, informational: Vec<InterimResponse>) -> Self {
+        self.informational = informational;
+        self
+    }
+
+    /// Status line text, e.g. `200 OK` or `299 Legacy Success`
+    ///
+    /// Uses the recorded reason phrase, then the standard one, and shows the
+    /// bare number for codes without either.
+    pub fn status_text(&self) -> String {
+        let reason = self.reason.as_deref().or_else(|| {
+            hyper::StatusCode::from_u16(self.status)
+                .ok()
+                .and_then(|status| status.canonical_reason())
+        });
+        match reason {
+            Some(reason) => format!("{} {}", self.status, reason),
+            None => self.status.to_string(),
         }
     }
 
+    /// Get all Set-Cookie values, falling back to the header map for older snapshots
+    pub fn set_cookies(&self) -> Vec<&str> {
+        if !self.set_cookie.is_empty() {
+            return self.set_cookie.iter().map(String::as_str).collect();
+        }
+
+        self.headers
+            .iter()
+            .filter(|(name, _)| name.eq_ignore_ascii_case("set-cookie"))
+            .map(|(_, value)| value.as_str())
+            .collect()
+    }
+
+    /// Create a record for a request that never got an upstream response
+    ///
+    /// Keeps a 502 status so older tooling treats it as a gateway error.
+    pub fn failed(reason: impl Into<String>) -> Self {
+        let reason = reason.into();
+        Self {
+            status: 502,
+            headers: HashMap::new(),
+            body: format!("Failed during capture: {}", reason).into_bytes(),
+            content_type: "text/plain".to_string(),
+            failure: Some(reason),
+            set_cookie: Vec::new(),
+            http_version: None,
+            reason: None,
+            informational: Vec::new(),
+        }
+    }
+
+    /// Check if the upstream request failed during capture
+    pub fn is_failure(&self) -> bool {
+        self.failure.is_some()
+    }
+
     /// Detect content type from headers, body content, or URL extension
+    ///
+    /// Without a usable `content-type` header the type is sniffed from the
+    /// body and URL, falling back to `application/octet-stream`.
     pub fn detect_content_type(
         headers: &HashMap<String, String>,
         body: &[u8],
         url: Option<&str>,
     ) -> String {
         // First, try to get content type from headers
-        if let Some(ct) = headers
-            .get("content-type")
-            .or_else(|| headers.get("Content-Type"))
-        {
+        let declared = headers
+            .iter()
+            .find(|(name, _)| name.eq_ignore_ascii_case("content-type"))
+            .map(|(_, value)| value)
+            .filter(|value| ContentTypeHelper::is_usable(value));
+        if let Some(ct) = declared {
             return ct.clone();
         }
 
-        // If no content-type header, try to guess from URL extension
-        if let Some(url_str) = url {
-            if let Ok(url_parsed) = url::Url::parse(url_str) {
-                let path = url_parsed.path();
-                if let Some(guessed) = mime_guess::from_path(path).first() {
-                    return guessed.to_string();
-                }
-            }
+        ContentTypeHelper::sniff(body, url)
+            .unwrap_or_else(|| "application/octet-stream".to_string())
+    }
+
+    /// Content type to replay when the origin didn't send a usable header
+    ///
+    /// Returns `None` when the recorded headers already carry one, or when
+    /// neither the stored guess nor sniffing the body says anything.
+    pub fn fallback_content_type(&self, url: &str) -> Option<String> {
+        let declared = self.headers.iter().any(|(name, value)| {
+            name.eq_ignore_ascii_case("content-type") && !value.trim().is_empty()
+        });
+        if declared {
+            return None;
+        }
+
+        // Snapshots store a best guess made at capture time
+        if ContentTypeHelper::is_usable(&self.content_type)
+            && self.get_mime_type() != Some(mime::APPLICATION_OCTET_STREAM)
+        {
+            return Some(self.content_type.clone());
         }
 
-        // If still no content type, try to detect from body content
-        ContentTypeHelper::detect_from_body(body)
+        ContentTypeHelper::sniff(&self.body, Some(url))
     }
 
     /// Get the parsed MIME type of the response
@@ -56,3 +56,21 @@ pub mod optional_body_serialization {
         }
     }
 }
+
+/// Header maps written in sorted order, so equal maps always encode to equal bytes
+///
+/// `HashMap` iteration order differs between runs; reading accepts any order.
+pub mod sorted_headers {
+    use serde::{Serialize, Serializer};
+    use std::collections::{BTreeMap, HashMap};
+
+    pub fn serialize<S>(headers: &HashMap<String, String>, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        headers
+            .iter()
+            .collect::<BTreeMap<_, _>>()
+            .serialize(serializer)
+    }
+}
@@ -0,0 +1,30 @@
+use serde::{Deserialize, Serialize};
+
+/// A CONNECT tunnel whose traffic was proxied without being recorded
+///
+/// Hosts that reject the capture certificate (certificate pinning) can't be
+/// intercepted, so their tunnels are passed through blindly and only the
+/// byte counts are kept.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TunnelRecord {
+    /// Tunnel target as `host:port`
+    pub host: String,
+    /// Bytes sent from the browser to the host
+    pub bytes_up: u64,
+    /// Bytes sent from the host to the browser
+    pub bytes_down: u64,
+    /// The client rejected the TLS interception for this host
+    pub mitm_failed: bool,
+}
+
+impl TunnelRecord {
+    /// Create a record for a host whose TLS interception was rejected
+    pub fn pinned(host: impl Into<String>) -> Self {
+        Self {
+            host: host.into(),
+            bytes_up: 0,
+            bytes_down: 0,
+            mitm_failed: true,
+        }
+    }
+}
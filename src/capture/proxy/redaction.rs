@@ -0,0 +1,181 @@
+use std::borrow::Cow;
+
+use regex::Regex;
+use tracing::debug;
+
+use super::records::RequestRecord;
+use crate::error::{Result, WebMockError};
+
+/// Replacement text for redacted header values and body matches
+pub const REDACTED: &str = "[REDACTED]";
+
+/// Headers scrubbed when `--redact-defaults` is passed
+pub const DEFAULT_REDACTED_HEADERS: &[&str] = &[
+    "authorization",
+    "proxy-authorization",
+    "cookie",
+    "set-cookie",
+];
+
+/// Rules applied to every record before it is stored
+///
+/// Header redaction keeps the header present but replaces its value, so replay
+/// still sees the same header set. Body patterns are applied to text bodies only,
+/// and the size cap truncates request and response bodies alike.
+#[derive(Debug, Clone, Default)]
+pub struct RedactionRules {
+    headers: Vec<String>,
+    body_patterns: Vec<Regex>,
+    max_body_size: Option<usize>,
+}
+
+impl RedactionRules {
+    /// Create an empty rule set that leaves records untouched
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Build rules from the capture command line options
+    pub fn from_args(
+        headers: &[String],
+        use_defaults: bool,
+        body_patterns: &[String],
+        max_body_size: Option<usize>,
+    ) -> Result<Self> {
+        let mut rules = Self::new();
+
+        if use_defaults {
+            for header in DEFAULT_REDACTED_HEADERS {
+                rules = rules.redact_header(header);
+            }
+        }
+
+        for header in headers {
+            if header.trim().is_empty() {
+                return Err(WebMockError::config("Redacted header name cannot be empty"));
+            }
+            rules = rules.redact_header(header);
+        }
+
+        for pattern in body_patterns {
+            rules = rules.redact_body_pattern(pattern)?;
+        }
+
+        if let Some(max) = max_body_size {
+            rules = rules.with_max_body_size(max);
+        }
+
+        Ok(rules)
+    }
+
+    /// Redact the value of the given header (case-insensitive)
+    pub fn redact_header(mut self, name: &str) -> Self {
+        let name = name.trim().to_lowercase();
+        if !self.headers.contains(&name) {
+            self.headers.push(name);
+        }
+        self
+    }
+
+    /// Replace every match of the given regex in text bodies
+    pub fn redact_body_pattern(mut self, pattern: &str) -> Result<Self> {
+        let regex = Regex::new(pattern).map_err(|e| {
+            WebMockError::config(format!("Invalid redaction pattern '{}': {}", pattern, e))
+        })?;
+        self.body_patterns.push(regex);
+        Ok(self)
+    }
+
+    /// Truncate stored bodies larger than the given number of bytes
+    pub fn with_max_body_size(mut self, max_body_size: usize) -> Self {
+        self.max_body_size = Some(max_body_size);
+        self
+    }
+
+    /// Check whether any rule is configured
+    pub fn is_empty(&self) -> bool {
+        self.headers.is_empty() && self.body_patterns.is_empty() && self.max_body_size.is_none()
+    }
+
+    /// Get the lowercase names of redacted headers
+    pub fn redacted_headers(&self) -> &[String] {
+        &self.headers
+    }
+
+    /// Get the configured body size cap
+    pub fn max_body_size(&self) -> Option<usize> {
+        self.max_body_size
+    }
+
+    /// Check whether applying the rules would cut one of the record's bodies
+    pub fn truncates(&self, record: &RequestRecord) -> bool {
+        self.max_body_size.is_some_and(|max| {
+            record.response.body.len() > max
+                || record.body.as_ref().is_some_and(|body| body.len() > max)
+        })
+    }
+
+    /// Check whether the value of the named header is redacted
+    pub fn redacts_header(&self, name: &str) -> bool {
+        self.headers.contains(&name.to_lowercase())
+    }
+
+    /// Replace every match of the body patterns in `text`
+    pub fn scrub_text<'t>(&self, text: &'t str) -> Cow<'t, str> {
+        let mut scrubbed = Cow::Borrowed(text);
+        for pattern in &self.body_patterns {
+            let replaced = match pattern.replace_all(&scrubbed, REDACTED) {
+                Cow::Owned(replaced) => replaced,
+                Cow::Borrowed(_) => continue,
+            };
+            scrubbed = Cow::Owned(replaced);
+        }
+        scrubbed
+    }
+
+    /// Apply all rules to a record in place
+    pub fn apply(&self, record: &mut RequestRecord) {
+        if self.is_empty() {
+            return;
+        }
+
+        self.redact_headers(&mut record.headers);
+        self.redact_headers(&mut record.response.headers);
+        if self.headers.iter().any(|name| name == "set-cookie") {
+            for cookie in record.response.set_cookie.iter_mut() {
+                *cookie = REDACTED.to_string();
+            }
+        }
+
+        if let Some(body) = record.body.as_mut() {
+            self.scrub_body(body);
+        }
+        self.scrub_body(&mut record.response.body);
+    }
+
+    fn redact_headers(&self, headers: &mut std::collections::HashMap<String, String>) {
+        for (name, value) in headers.iter_mut() {
+            if self.redacts_header(name) {
+                debug!("Redacting header: {}", name);
+                *value = REDACTED.to_string();
+            }
+        }
+    }
+
+    fn scrub_body(&self, body: &mut Vec<u8>) {
+        if !self.body_patterns.is_empty() {
+            if let Ok(text) = std::str::from_utf8(body) {
+                if let Cow::Owned(scrubbed) = self.scrub_text(text) {
+                    *body = scrubbed.into_bytes();
+                }
+            }
+        }
+
+        if let Some(max) = self.max_body_size {
+            if body.len() > max {
+                debug!("Truncating body from {} to {} bytes", body.len(), max);
+                body.truncate(max);
+            }
+        }
+    }
+}
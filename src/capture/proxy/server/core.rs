@@ -1,18 +1,22 @@
 use hyper::service::service_fn;
 use hyper_util::rt::TokioIo;
 use hyper_util::server::conn::auto::Builder;
-use rcgen::{generate_simple_self_signed, CertifiedKey};
-use rustls::pki_types::PrivateKeyDer;
 use rustls::server::ServerConfig;
+use std::collections::BTreeMap;
 use std::net::SocketAddr;
 use std::sync::Arc;
+use std::time::Instant;
 use tokio::net::TcpListener;
+use tokio::sync::watch;
 use tracing::info;
 
 use super::handlers::{handle_connect_mitm, handle_request};
-use crate::capture::proxy::client_pool::HttpClientPool;
-use crate::capture::proxy::recorder::RequestRecorder;
-use crate::capture::proxy::records::RequestRecord;
+use super::utils::is_malformed_request;
+use crate::capture::proxy::client_pool::{ConnectionStats, HttpClientPool};
+use crate::capture::proxy::connection_info::TlsDetails;
+use crate::capture::proxy::diagnostics::CaptureDiagnostics;
+use crate::capture::proxy::recorder::{DocumentResponse, InFlightRequest, RequestRecorder};
+use crate::capture::proxy::records::{RequestRecord, TunnelRecord};
 use crate::error::Result;
 
 pub struct HttpProxy {
@@ -26,94 +30,33 @@ pub struct HttpProxy {
 }
 
 impl HttpProxy {
-    /// Generate a self-signed certificate for TLS MITM
-    fn generate_tls_config() -> Result<Arc<ServerConfig>> {
-        // Generate a self-signed certificate with broad domain coverage
-        let subject_alt_names = [
-            "localhost".to_string(),
-            "127.0.0.1".to_string(),
-            "::1".to_string(),
-            "*.com".to_string(),
-            "*.org".to_string(),
-            "*.net".to_string(),
-            "*.io".to_string(),
-            "*.co.uk".to_string(),
-            "*.edu".to_string(),
-            "*.gov".to_string(),
-            "*.mil".to_string(),
-            "*.info".to_string(),
-            "*.biz".to_string(),
-            "*.name".to_string(),
-            "*.tv".to_string(),
-            "*.me".to_string(),
-            "*.dev".to_string(),
-            "*.app".to_string(),
-            "*.cloud".to_string(),
-            "*.ai".to_string(),
-            "*.cn".to_string(),
-            "*.jp".to_string(),
-            "*.de".to_string(),
-            "*.fr".to_string(),
-            "*.ru".to_string(),
-            "*.in".to_string(),
-            "*.br".to_string(),
-            "*.ca".to_string(),
-            "*.au".to_string(),
-            "*.kr".to_string(),
-            "*.it".to_string(),
-            "*.es".to_string(),
-            "*.mx".to_string(),
-            "*.ar".to_string(),
-            "*.za".to_string(),
-            "*.ng".to_string(),
-            "*.eg".to_string(),
-            "*.sa".to_string(),
-            "*.ae".to_string(),
-            "*.tr".to_string(),
-            "*.id".to_string(),
-            "*.th".to_string(),
-            "*.vn".to_string(),
-            "*.ph".to_string(),
-            "*.my".to_string(),
-            "*.sg".to_string(),
-            "*.tw".to_string(),
-            "*.hk".to_string(),
-            "*.mo".to_string(),
-        ];
-
-        let CertifiedKey { cert, key_pair } = generate_simple_self_signed(subject_alt_names)
-            .map_err(|e| {
-                crate::error::WebMockError::config(format!(
-                    "Failed to generate TLS certificate: {}",
-                    e
-                ))
-            })?;
-
-        // Convert to rustls format
-        let cert_der = cert.der().clone();
-        let key_der = PrivateKeyDer::try_from(key_pair.serialize_der())
-            .map_err(|_| crate::error::WebMockError::config("Failed to serialize private key"))?;
-
-        // Create TLS config with optimized settings
-        let mut config = ServerConfig::builder()
-            .with_no_client_auth()
-            .with_single_cert(vec![cert_der], key_der)
-            .map_err(|e| {
-                crate::error::WebMockError::config(format!("Failed to create TLS config: {}", e))
-            })?;
-
-        // Optimize for compatibility
-        config.alpn_protocols = vec![b"http/1.1".to_vec(), b"h2".to_vec()];
-
-        Ok(Arc::new(config))
+    /// Start the proxy on the given port
+    ///
+    /// Pass port 0 to let the OS assign a free port; `get_port` reports the
+    /// port that was actually bound.
+    pub async fn start(port: u16) -> Result<Self> {
+        Self::start_with_recorder(port, Arc::new(RequestRecorder::new())).await
     }
 
-    pub async fn start(port: u16) -> Result<Self> {
+    /// Start the proxy using a preconfigured recorder (e.g. with redaction rules)
+    pub async fn start_with_recorder(port: u16, recorder: Arc<RequestRecorder>) -> Result<Self> {
+        Self::start_with_client_pool(port, recorder, HttpClientPool::new()).await
+    }
+
+    /// Start the proxy forwarding through a preconfigured client pool (e.g. with retries)
+    pub async fn start_with_client_pool(
+        port: u16,
+        recorder: Arc<RequestRecorder>,
+        client_pool: HttpClientPool,
+    ) -> Result<Self> {
         info!("Starting HTTP proxy on port: {}", port);
 
-        let recorder = Arc::new(RequestRecorder::new());
-        let client_pool = Arc::new(HttpClientPool::new());
-        let tls_config = Self::generate_tls_config()?;
+        let client_pool = Arc::new(
+            client_pool
+                .with_diagnostics(Arc::clone(recorder.diagnostics()))
+                .with_warnings(recorder.warnings().clone()),
+        );
+        let tls_config = crate::tls::server_config()?;
         let recorder_clone = Arc::clone(&recorder);
         let client_pool_clone = Arc::clone(&client_pool);
         let tls_config_clone = Arc::clone(&tls_config);
@@ -124,6 +67,10 @@ impl HttpProxy {
         let listener = TcpListener::bind(addr).await.map_err(|e| {
             crate::error::WebMockError::config(format!("Failed to bind to {}: {}", addr, e))
         })?;
+        let addr = listener.local_addr().map_err(|e| {
+            crate::error::WebMockError::config(format!("Failed to read proxy address: {}", e))
+        })?;
+        let port = addr.port();
 
         let server_handle = tokio::spawn(async move {
             loop {
@@ -131,7 +78,9 @@ impl HttpProxy {
                     result = listener.accept() => {
                         match result {
                             Ok((stream, remote_addr)) => {
+                                recorder_clone.diagnostics().connection_accepted();
                                 let recorder = Arc::clone(&recorder_clone);
+                                let diagnostics = Arc::clone(recorder.diagnostics());
                                 let client_pool = Arc::clone(&client_pool_clone);
                                 let tls_config = Arc::clone(&tls_config_clone);
 
@@ -159,6 +108,12 @@ impl HttpProxy {
                                     let conn = builder.serve_connection_with_upgrades(io, service);
 
                                     if let Err(e) = conn.await {
+                                        if is_malformed_request(e.as_ref()) {
+                                            diagnostics.request_unrecorded(
+                                                &format!("request from {}", remote_addr),
+                                                &e.to_string(),
+                                            );
+                                        }
                                         // Only log actual errors, not normal connection closures
                                         let error_str = e.to_string();
                                         if !error_str.contains("connection closed") &&
@@ -201,10 +156,85 @@ impl HttpProxy {
         self.recorder.get_records().await
     }
 
+    /// Get how many requests have been recorded so far
+    pub fn record_count(&self) -> usize {
+        self.recorder.record_count()
+    }
+
+    /// Get the requests still waiting on upstream, in the order they arrived
+    pub fn in_flight(&self) -> Vec<InFlightRequest> {
+        self.recorder.in_flight()
+    }
+
+    /// Get the milliseconds since recording started, the clock in-flight requests are timed on
+    pub fn elapsed_ms(&self) -> u64 {
+        self.recorder.elapsed_ms()
+    }
+
+    /// Move the recorded requests out of the proxy
+    pub async fn take_records(&self) -> Vec<RequestRecord> {
+        self.recorder.take_records().await
+    }
+
     pub async fn clear_records(&self) {
         self.recorder.clear_records().await
     }
 
+    /// Get how many requests were forwarded but not recorded because of the request cap
+    pub fn skipped_requests(&self) -> u64 {
+        self.recorder.skipped_requests()
+    }
+
+    /// Get how many requests needed at least one upstream retry
+    pub fn retried_requests(&self) -> u64 {
+        self.client_pool.retried_requests()
+    }
+
+    /// Get how the upstream connections were used so far
+    pub fn connection_stats(&self) -> ConnectionStats {
+        self.client_pool.connection_stats()
+    }
+
+    /// Stop retrying upstream requests whose backoff would end after `deadline`
+    pub fn set_retry_deadline(&self, deadline: Option<Instant>) {
+        self.client_pool.set_retry_deadline(deadline);
+    }
+
+    /// Tag records made from now on with a capture variant, or none with `None`
+    pub fn set_variant(&self, variant: Option<String>) {
+        self.recorder.set_variant(variant);
+    }
+
+    /// Flag the record of the main document when the browser navigates to `url`
+    pub fn expect_document(&self, url: &str) {
+        self.recorder.expect_document(url);
+    }
+
+    /// Follow the main document flagged for the latest navigation
+    pub fn document(&self) -> watch::Receiver<Option<DocumentResponse>> {
+        self.recorder.document()
+    }
+
+    /// Get how many requests to each host outside `--only-hosts` were refused
+    pub fn blocked_hosts(&self) -> BTreeMap<String, u64> {
+        self.recorder.blocked_hosts()
+    }
+
+    /// Get the tunnels that were passed through without being recorded
+    pub async fn get_tunnels(&self) -> Vec<TunnelRecord> {
+        self.recorder.get_tunnels().await
+    }
+
+    /// Get the connection-level failures counted so far, see [`CaptureDiagnostics`]
+    pub fn capture_diagnostics(&self) -> CaptureDiagnostics {
+        self.recorder.capture_diagnostics()
+    }
+
+    /// Get the TLS details negotiated with each origin so far, keyed by `host:port`
+    pub fn tls_details(&self) -> BTreeMap<String, TlsDetails> {
+        self.client_pool.tls_details()
+    }
+
     pub fn get_port(&self) -> u16 {
         self.port
     }
@@ -7,12 +7,17 @@ use std::convert::Infallible;
 use std::net::SocketAddr;
 use std::sync::Arc;
 use tracing::debug;
+use url::Url;
 
 use crate::capture::metrics::RequestTimer;
 use crate::capture::proxy::client_pool::HttpClientPool;
 use crate::capture::proxy::recorder::RequestRecorder;
 use crate::capture::proxy::records::{RequestRecord, ResponseRecord};
-use crate::capture::proxy::server::utils::forward_request_with_pool;
+use crate::capture::proxy::server::utils::{
+    blocked_host_response, forward_request_with_pool, forward_streaming_request_with_pool,
+    is_idempotent,
+};
+use crate::capture::proxy::streaming::{RecordingBody, MAX_MEMORY_BUFFER};
 
 pub async fn handle_request(
     req: Request<Incoming>,
@@ -21,6 +26,8 @@ pub async fn handle_request(
     _remote_addr: SocketAddr,
 ) -> std::result::Result<Response<Full<Bytes>>, Infallible> {
     let _timer = RequestTimer::start();
+    let received_ms = recorder.elapsed_ms();
+    let sequence = recorder.next_sequence();
     let method = req.method().clone();
     let uri = req.uri().clone();
     let version = req.version();
@@ -36,20 +43,6 @@ pub async fn handle_request(
         .await;
     }
 
-    // Extract request body
-    let (_parts, body) = req.into_parts();
-    let body_bytes = match body.collect().await {
-        Ok(collected) => collected.to_bytes().to_vec(),
-        Err(e) => {
-            tracing::warn!("Failed to read request body for {} {}: {}", method, uri, e);
-            return Ok(Response::builder()
-                .status(StatusCode::BAD_REQUEST)
-                .header("Connection", "close")
-                .body(Full::new(Bytes::from("Failed to read request body")))
-                .unwrap());
-        }
-    };
-
     // Convert headers to HashMap
     let mut header_map = HashMap::new();
     for (name, value) in headers.iter() {
@@ -81,28 +74,82 @@ pub async fn handle_request(
         )
     };
 
+    // Refused before anything is sent upstream, so the browser fails fast
+    let target_host = Url::parse(&target_url)
+        .ok()
+        .and_then(|url| url.host_str().map(str::to_string))
+        .unwrap_or_default();
+    if !recorder.allow_host(&target_host) {
+        return Ok(blocked_host_response(&target_host));
+    }
+
     debug!("Forwarding request to: {}", target_url);
+    // Lets idle detection see requests that haven't been answered yet
+    let in_flight = recorder.begin_request(sequence, method.as_str(), &target_url, &headers);
+
+    // Stream the request body upstream while keeping a copy for the record.
+    // Hyper answers `Expect: 100-continue` once the body is first polled.
+    let (_parts, body) = req.into_parts();
+    let (body, recording) = RecordingBody::new(body, MAX_MEMORY_BUFFER);
+
+    // Forward the request to the target server using connection pool. Requests
+    // that may be retried are buffered, so their body can be sent again.
+    let response = if client_pool.retry_policy().max_retries > 0 && is_idempotent(&method) {
+        match body.collect().await {
+            Ok(buffered) => {
+                forward_request_with_pool(
+                    method.clone(),
+                    &target_url,
+                    header_map.clone(),
+                    buffered.to_bytes().to_vec(),
+                    version,
+                    client_pool,
+                )
+                .await
+            }
+            Err(e) => Err(crate::error::WebMockError::Proxy(format!(
+                "Failed to read request body: {}",
+                e
+            ))),
+        }
+    } else {
+        forward_streaming_request_with_pool(
+            method.clone(),
+            &target_url,
+            header_map.clone(),
+            body.boxed(),
+            version,
+            client_pool,
+        )
+        .await
+    };
 
-    // Forward the request to the target server using connection pool
-    let response = forward_request_with_pool(
-        method.clone(),
-        &target_url,
-        header_map.clone(),
-        body_bytes.clone(),
-        version,
-        client_pool,
-    )
-    .await;
+    let body_bytes = match recording.finish().await {
+        Ok(bytes) => bytes,
+        Err(e) => {
+            tracing::warn!(
+                "Failed to record request body for {} {}: {}",
+                method,
+                uri,
+                e
+            );
+            Vec::new()
+        }
+    };
 
     match response {
-        Ok((status, response_headers, response_body)) => {
+        Ok(upstream) => {
             // Create response record
             let response_record = ResponseRecord::new(
-                status.as_u16(),
-                response_headers.clone(),
-                response_body.clone(),
+                upstream.status.as_u16(),
+                upstream.headers.clone(),
+                upstream.body.clone(),
                 Some(&target_url),
-            );
+            )
+            .with_set_cookie(upstream.set_cookie.clone())
+            .with_http_version(upstream.version)
+            .with_reason(upstream.reason.clone())
+            .with_informational(upstream.informational.clone());
 
             // Create request record
             let request_record = RequestRecord::new(
@@ -115,16 +162,28 @@ pub async fn handle_request(
                     Some(body_bytes)
                 },
                 response_record,
-            );
+            )
+            .with_timing(received_ms, recorder.elapsed_ms())
+            .with_sequence(sequence);
 
             // Record the request
             recorder.record_request(request_record).await;
+            in_flight.finish();
+
+            // Build response, passing a custom reason phrase on to the client
+            let mut response_builder = Response::builder().status(upstream.status);
+            if let Some(reason) = upstream
+                .reason
+                .and_then(|reason| hyper::ext::ReasonPhrase::try_from(reason).ok())
+            {
+                response_builder = response_builder.extension(reason);
+            }
 
-            // Build response
-            let mut response_builder = Response::builder().status(status);
-
-            // Add response headers
-            for (name, value) in response_headers {
+            // Add response headers, emitting each Set-Cookie value separately
+            for (name, value) in upstream.headers {
+                if name.eq_ignore_ascii_case("set-cookie") && !upstream.set_cookie.is_empty() {
+                    continue;
+                }
                 if let (Ok(header_name), Ok(header_value)) = (
                     hyper::header::HeaderName::from_bytes(name.as_bytes()),
                     hyper::header::HeaderValue::from_str(&value),
@@ -132,21 +191,26 @@ pub async fn handle_request(
                     response_builder = response_builder.header(header_name, header_value);
                 }
             }
+            for cookie in upstream.set_cookie {
+                if let Ok(header_value) = hyper::header::HeaderValue::from_str(&cookie) {
+                    response_builder =
+                        response_builder.header(hyper::header::SET_COOKIE, header_value);
+                }
+            }
 
             Ok(response_builder
-                .body(Full::new(Bytes::from(response_body)))
+                .body(Full::new(Bytes::from(upstream.body)))
                 .unwrap())
         }
         Err(e) => {
             tracing::error!("Failed to forward request: {}", e);
 
-            // Create error response record
-            let error_response = ResponseRecord::new(
-                502,
-                HashMap::new(),
-                format!("Proxy Error: {}", e).into_bytes(),
-                Some(&target_url),
-            );
+            // Keep the reason so replay can reproduce the failure instead of a 404
+            let reason = match &e {
+                crate::error::WebMockError::Proxy(msg) => msg.clone(),
+                other => other.to_string(),
+            };
+            let error_response = ResponseRecord::failed(reason);
 
             let request_record = RequestRecord::new(
                 method.to_string(),
@@ -158,9 +222,12 @@ pub async fn handle_request(
                     Some(body_bytes)
                 },
                 error_response,
-            );
+            )
+            .with_timing(received_ms, recorder.elapsed_ms())
+            .with_sequence(sequence);
 
             recorder.record_request(request_record).await;
+            in_flight.finish();
 
             Ok(Response::builder()
                 .status(StatusCode::BAD_GATEWAY)
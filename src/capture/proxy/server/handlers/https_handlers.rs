@@ -9,8 +9,10 @@ use std::sync::Arc;
 use tokio::net::TcpStream;
 use tracing::{debug, error, info, warn};
 
+use crate::capture::proxy::authority::split_authority;
 use crate::capture::proxy::recorder::RequestRecorder;
 use crate::capture::proxy::records::{RequestRecord, ResponseRecord};
+use crate::capture::proxy::server::utils::{blocked_host_response, describe_client_error};
 
 pub async fn handle_connect_request(
     req: Request<Incoming>,
@@ -24,12 +26,10 @@ pub async fn handle_connect_request(
 
     debug!("Handling CONNECT request to: {}", host_port);
 
-    // Parse host and port
-    let (host, port) = if let Some((h, p)) = host_port.split_once(':') {
-        (h.to_string(), p.parse().unwrap_or(443))
-    } else {
-        (host_port.clone(), 443)
-    };
+    let (host, port) = split_authority(&host_port);
+    if !recorder.allow_host(&host) {
+        return Ok(blocked_host_response(&host));
+    }
 
     // Try to establish connection to target server first
     match TcpStream::connect(&host_port).await {
@@ -85,6 +85,9 @@ pub async fn handle_connect_request(
         }
         Err(e) => {
             warn!("Failed to connect to {}: {}", host_port, e);
+            recorder
+                .diagnostics()
+                .upstream_connect_failed(&host, &describe_client_error(&e));
 
             // Record failed connection
             let error_record = RequestRecord::new(
@@ -92,12 +95,7 @@ pub async fn handle_connect_request(
                 format!("https://{}:{}", host, port),
                 HashMap::new(),
                 None,
-                ResponseRecord::new(
-                    502,
-                    HashMap::new(),
-                    format!("Failed to connect to {}: {}", host_port, e).into_bytes(),
-                    Some(&format!("https://{}:{}", host, port)),
-                ),
+                ResponseRecord::failed(describe_client_error(&e)),
             );
 
             recorder.record_request(error_record).await;
@@ -4,17 +4,24 @@ use hyper::body::Incoming;
 use hyper::Request;
 use hyper::Response;
 use hyper_util::rt::TokioIo;
+use rustls::server::Acceptor;
 use rustls::ServerConfig;
 use std::collections::HashMap;
 use std::convert::Infallible;
 use std::sync::Arc;
-use tokio_rustls::TlsAcceptor;
-use tracing::{debug, error};
+use tokio::net::TcpStream;
+use tokio_rustls::LazyConfigAcceptor;
+use tracing::{debug, error, info, warn};
 
+use crate::capture::proxy::authority::{origin, split_authority};
 use crate::capture::proxy::client_pool::HttpClientPool;
 use crate::capture::proxy::recorder::RequestRecorder;
 use crate::capture::proxy::records::{RequestRecord, ResponseRecord};
 use crate::capture::proxy::server::handlers::http_handlers::handle_request as handle_http_request;
+use crate::capture::proxy::server::handlers::tunnel::tunnel_counted;
+use crate::capture::proxy::server::utils::{
+    blocked_host_response, describe_client_error, is_malformed_request,
+};
 
 pub async fn handle_connect_mitm(
     req: Request<Incoming>,
@@ -30,12 +37,16 @@ pub async fn handle_connect_mitm(
 
     debug!("Handling CONNECT MITM request to: {}", host_port);
 
-    // Parse host and port
-    let (host, port) = if let Some((h, p)) = host_port.split_once(':') {
-        (h.to_string(), p.parse().unwrap_or(443))
-    } else {
-        (host_port.clone(), 443)
-    };
+    let (host, port) = split_authority(&host_port);
+    if !recorder.allow_host(&host) {
+        return Ok(blocked_host_response(&host));
+    }
+
+    // Hosts that rejected our certificate before are passed through untouched
+    let tunnel_host = format!("{}:{}", host, port);
+    if recorder.is_pinned(&tunnel_host).await {
+        return Ok(pass_through(req, recorder, tunnel_host));
+    }
 
     // Record the CONNECT request
     let host_port = format!("https://{}:{}", host, port);
@@ -63,6 +74,9 @@ pub async fn handle_connect_mitm(
         .body(Full::new(Bytes::new()))
         .unwrap();
 
+    // Requests inside the tunnel keep a nonstandard port in their URL
+    let tunnel_origin = origin("https", &host, port);
+
     // Upgrade the connection to handle TLS
     let upgraded = hyper::upgrade::on(req);
     let tls_config = Arc::clone(&tls_config);
@@ -74,17 +88,25 @@ pub async fn handle_connect_mitm(
             Ok(upgraded) => {
                 let upgraded = TokioIo::new(upgraded);
 
-                // Perform TLS handshake with the client
-                let acceptor = TlsAcceptor::from(tls_config);
-                match acceptor.accept(upgraded).await {
+                // Perform TLS handshake with the client, reading its hello
+                // first so a failure can be put down to the host it asked for
+                let handshake = async move {
+                    let start = LazyConfigAcceptor::new(Acceptor::default(), upgraded)
+                        .await
+                        .map_err(|e| (None, e))?;
+                    let sni = start.client_hello().server_name().map(str::to_string);
+                    start.into_stream(tls_config).await.map_err(|e| (sni, e))
+                };
+                match handshake.await {
                     Ok(tls_stream) => {
                         let tls_io = TokioIo::new(tls_stream);
+                        let diagnostics = Arc::clone(recorder.diagnostics());
 
                         // Create a service to handle the decrypted HTTPS traffic
                         let service = hyper::service::service_fn(move |mut req| {
                             let recorder = Arc::clone(&recorder);
                             let client_pool = Arc::clone(&client_pool);
-                            let host = host.clone();
+                            let origin = tunnel_origin.clone();
                             async move {
                                 debug!(
                                     "Processing decrypted HTTPS request: {} {}",
@@ -101,12 +123,12 @@ pub async fn handle_connect_mitm(
                                         // Already a full URL
                                         uri.to_string()
                                     } else {
-                                        // Relative path, need to prepend https://host
-                                        format!("https://{}{}", host, path)
+                                        // Relative path, need to prepend the tunnel's origin
+                                        format!("{}{}", origin, path)
                                     }
                                 } else {
                                     // Just path
-                                    format!("https://{}{}", host, uri.path())
+                                    format!("{}{}", origin, uri.path())
                                 };
 
                                 // Update the request URI to be the full URL
@@ -131,6 +153,12 @@ pub async fn handle_connect_mitm(
                         let conn = builder.serve_connection(tls_io, service);
 
                         if let Err(e) = conn.await {
+                            if is_malformed_request(e.as_ref()) {
+                                diagnostics.request_unrecorded(
+                                    &format!("request to {}", tunnel_host),
+                                    &e.to_string(),
+                                );
+                            }
                             let error_str = e.to_string().to_lowercase();
                             if !error_str.contains("connection closed")
                                 && !error_str.contains("broken pipe")
@@ -143,8 +171,22 @@ pub async fn handle_connect_mitm(
                             }
                         }
                     }
-                    Err(e) => {
-                        error!("TLS handshake failed: {}", e);
+                    Err((sni, e)) => {
+                        recorder
+                            .diagnostics()
+                            .tls_handshake_failed(sni.as_deref().unwrap_or(&host), &e.to_string());
+                        if is_certificate_rejection(&e) {
+                            // This connection is lost, but the browser's retries are tunneled
+                            if recorder.mark_pinned(&tunnel_host).await {
+                                warn!(
+                                    "{} rejected the capture certificate (certificate pinning?), \
+                                     its traffic will be tunneled without recording",
+                                    tunnel_host
+                                );
+                            }
+                        } else {
+                            error!("TLS handshake failed: {}", e);
+                        }
                     }
                 }
             }
@@ -156,3 +198,73 @@ pub async fn handle_connect_mitm(
 
     Ok(response)
 }
+
+/// Tunnel a CONNECT to a pinned host without intercepting it, counting the bytes
+fn pass_through(
+    req: Request<Incoming>,
+    recorder: Arc<RequestRecorder>,
+    host_port: String,
+) -> Response<Full<Bytes>> {
+    debug!("Passing CONNECT to pinned host {} through", host_port);
+
+    tokio::spawn(async move {
+        let upgraded = match hyper::upgrade::on(req).await {
+            Ok(upgraded) => upgraded,
+            Err(e) => {
+                error!("Failed to upgrade connection: {}", e);
+                return;
+            }
+        };
+        let server = match TcpStream::connect(&host_port).await {
+            Ok(server) => server,
+            Err(e) => {
+                warn!("Failed to connect to pinned host {}: {}", host_port, e);
+                let (host, _) = split_authority(&host_port);
+                recorder
+                    .diagnostics()
+                    .upstream_connect_failed(&host, &describe_client_error(&e));
+                return;
+            }
+        };
+
+        match tunnel_counted(upgraded, server).await {
+            Ok((bytes_up, bytes_down)) => {
+                info!(
+                    "Tunnel to pinned host {} closed ({} bytes up, {} bytes down)",
+                    host_port, bytes_up, bytes_down
+                );
+                recorder
+                    .record_tunnel_traffic(&host_port, bytes_up, bytes_down)
+                    .await;
+            }
+            Err(e) => debug!("Tunnel to pinned host {} ended: {}", host_port, e),
+        }
+    });
+
+    Response::builder()
+        .status(hyper::StatusCode::OK)
+        .body(Full::new(Bytes::new()))
+        .unwrap()
+}
+
+/// Check whether a TLS handshake failed because the client refused our certificate
+pub(crate) fn is_certificate_rejection(error: &std::io::Error) -> bool {
+    use rustls::AlertDescription::{
+        BadCertificate, CertificateExpired, CertificateRevoked, CertificateUnknown, UnknownCA,
+        UnsupportedCertificate,
+    };
+
+    matches!(
+        error
+            .get_ref()
+            .and_then(|inner| inner.downcast_ref::<rustls::Error>()),
+        Some(rustls::Error::AlertReceived(
+            BadCertificate
+                | UnsupportedCertificate
+                | CertificateRevoked
+                | CertificateExpired
+                | CertificateUnknown
+                | UnknownCA
+        ))
+    )
+}
@@ -23,3 +23,13 @@ pub async fn tunnel_data(
 
     Ok(())
 }
+
+/// Copy data both ways until either side closes, returning the bytes sent
+/// from the client and from the server
+pub async fn tunnel_counted(
+    client: Upgraded,
+    mut server: TcpStream,
+) -> std::io::Result<(u64, u64)> {
+    let mut client_io = TokioIo::new(client);
+    tokio::io::copy_bidirectional(&mut client_io, &mut server).await
+}
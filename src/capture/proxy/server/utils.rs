@@ -3,19 +3,37 @@ use http_body_util::{BodyExt, Full};
 use hyper::{Method, Request, StatusCode, Version};
 use hyper_util::client::legacy::Client;
 use std::collections::HashMap;
-use std::sync::Arc;
+use std::sync::{Arc, Mutex};
+use tracing::debug;
 use url::Url;
 
-use crate::capture::proxy::client_pool::HttpClientPool;
+use crate::capture::proxy::client_pool::{HttpClientPool, ProxyBody};
+use crate::capture::proxy::records::InterimResponse;
 use crate::error::{Result, WebMockError};
 
+/// Response received from the upstream server
+#[derive(Debug)]
+pub struct UpstreamResponse {
+    pub status: StatusCode,
+    /// HTTP version the origin answered with
+    pub version: hyper::Version,
+    pub headers: HashMap<String, String>,
+    /// Every Set-Cookie value, since the header map keeps only one per name
+    pub set_cookie: Vec<String>,
+    pub body: Vec<u8>,
+    /// Reason phrase, only when the origin sent a non-standard one
+    pub reason: Option<String>,
+    /// 1xx responses received before the final one, except `100 Continue`
+    pub informational: Vec<InterimResponse>,
+}
+
 pub async fn forward_request(
     method: Method,
     url: &str,
     headers: HashMap<String, String>,
     body: Vec<u8>,
     _version: Version,
-) -> Result<(StatusCode, HashMap<String, String>, Vec<u8>)> {
+) -> Result<UpstreamResponse> {
     let client = Client::builder(hyper_util::rt::TokioExecutor::new()).build_http();
 
     // Parse the target URL
@@ -40,25 +58,23 @@ pub async fn forward_request(
         }
     }
 
-    let request = request_builder
+    let mut request = request_builder
         .body(Full::new(Bytes::from(body)))
         .map_err(|e| WebMockError::Proxy(format!("Failed to build request: {}", e)))?;
+    let informational = collect_informational(&mut request);
 
     // Send the request
     let response = client
         .request(request)
         .await
-        .map_err(|e| WebMockError::Proxy(format!("Request failed: {}", e)))?;
+        .map_err(|e| WebMockError::Proxy(describe_client_error(&e)))?;
 
     let status = response.status();
+    let response_version = response.version();
+    let reason = reason_phrase(&response);
 
-    // Extract response headers
-    let mut response_headers = HashMap::new();
-    for (name, value) in response.headers() {
-        if let Ok(value_str) = value.to_str() {
-            response_headers.insert(name.to_string(), value_str.to_string());
-        }
-    }
+    // Extract response headers, keeping every Set-Cookie value
+    let (headers, set_cookie) = collect_response_headers(response.headers());
 
     // Read response body
     let response_body = response
@@ -69,9 +85,23 @@ pub async fn forward_request(
         .to_bytes()
         .to_vec();
 
-    Ok((status, response_headers, response_body))
+    Ok(UpstreamResponse {
+        status,
+        version: response_version,
+        headers,
+        set_cookie,
+        body: response_body,
+        reason,
+        informational: take_informational(&informational),
+    })
 }
 
+/// Forward a buffered request, retrying idempotent ones that fail transiently
+///
+/// GET and HEAD requests are sent again after connect errors, dropped
+/// connections and 502/503/504 responses, as allowed by the pool's
+/// [`RetryPolicy`](crate::capture::proxy::client_pool::RetryPolicy). Only the
+/// last attempt's outcome is returned.
 pub async fn forward_request_with_pool(
     method: Method,
     url: &str,
@@ -79,7 +109,112 @@ pub async fn forward_request_with_pool(
     body: Vec<u8>,
     _version: Version,
     client_pool: Arc<HttpClientPool>,
-) -> Result<(StatusCode, HashMap<String, String>, Vec<u8>)> {
+) -> Result<UpstreamResponse> {
+    let body = Bytes::from(body);
+    let policy = client_pool.retry_policy();
+    let max_retries = if is_idempotent(&method) {
+        policy.max_retries
+    } else {
+        0
+    };
+
+    let mut retry = 0;
+    loop {
+        let attempt_body = Full::new(body.clone())
+            .map_err(|never| match never {})
+            .boxed();
+        let outcome = send_upstream(
+            method.clone(),
+            url,
+            headers.clone(),
+            attempt_body,
+            &client_pool,
+        )
+        .await;
+
+        let transient = match &outcome {
+            Ok(response) => is_retryable_status(response.status),
+            Err(failure) => failure.transient,
+        };
+        if !transient || retry >= max_retries {
+            return outcome.map_err(|failure| failure.error);
+        }
+
+        retry += 1;
+        let delay = policy.backoff(retry);
+        if !client_pool.retry_fits(delay) {
+            debug!("Not retrying {} {}: capture timeout is near", method, url);
+            return outcome.map_err(|failure| failure.error);
+        }
+        if retry == 1 {
+            client_pool.note_retried(url);
+        }
+        debug!(
+            "Retrying {} {} in {:?} (retry {} of {})",
+            method, url, delay, retry, max_retries
+        );
+        tokio::time::sleep(delay).await;
+    }
+}
+
+/// Forward a request whose body is sent upstream as it is read
+///
+/// The body is not buffered first, so clients waiting on `Expect: 100-continue`
+/// get their interim response as soon as the upstream starts reading. A
+/// streamed body can't be sent twice, so these requests are never retried.
+pub async fn forward_streaming_request_with_pool(
+    method: Method,
+    url: &str,
+    headers: HashMap<String, String>,
+    body: ProxyBody,
+    _version: Version,
+    client_pool: Arc<HttpClientPool>,
+) -> Result<UpstreamResponse> {
+    send_upstream(method, url, headers, body, &client_pool)
+        .await
+        .map_err(|failure| failure.error)
+}
+
+/// Check whether a method may be sent again without changing the outcome
+///
+/// Only the safe methods are retried; PUT and DELETE are idempotent on
+/// paper but commonly aren't treated so by the servers that receive them.
+pub fn is_idempotent(method: &Method) -> bool {
+    matches!(*method, Method::GET | Method::HEAD)
+}
+
+/// Check whether an upstream status is worth another attempt
+pub fn is_retryable_status(status: StatusCode) -> bool {
+    matches!(
+        status,
+        StatusCode::BAD_GATEWAY | StatusCode::SERVICE_UNAVAILABLE | StatusCode::GATEWAY_TIMEOUT
+    )
+}
+
+/// A failed attempt to reach the upstream
+struct UpstreamFailure {
+    error: WebMockError,
+    /// The connection failed or dropped, so trying again may succeed
+    transient: bool,
+}
+
+impl From<WebMockError> for UpstreamFailure {
+    fn from(error: WebMockError) -> Self {
+        Self {
+            error,
+            transient: false,
+        }
+    }
+}
+
+/// Send a single request through the pool and read the whole response
+async fn send_upstream(
+    method: Method,
+    url: &str,
+    headers: HashMap<String, String>,
+    body: ProxyBody,
+    client_pool: &HttpClientPool,
+) -> std::result::Result<UpstreamResponse, UpstreamFailure> {
     // Parse the target URL to extract host
     let parsed_url =
         Url::parse(url).map_err(|e| WebMockError::Proxy(format!("Invalid URL: {}", e)))?;
@@ -88,6 +223,9 @@ pub async fn forward_request_with_pool(
         .host_str()
         .ok_or_else(|| WebMockError::Proxy("No host in URL".to_string()))?;
 
+    // Wait for a connection slot, held until the body has been read
+    let _slot = client_pool.acquire_slot(host).await;
+
     // Get client from pool
     let client = client_pool.get_client(host).await;
 
@@ -113,36 +251,201 @@ pub async fn forward_request_with_pool(
         }
     }
 
-    let request = request_builder
-        .body(Full::new(Bytes::from(body)))
+    let mut request = request_builder
+        .body(body)
         .map_err(|e| WebMockError::Proxy(format!("Failed to build request: {}", e)))?;
+    let informational = collect_informational(&mut request);
 
     // Send the request using pooled client
-    let response = client
-        .request(request)
-        .await
-        .map_err(|e| WebMockError::Proxy(format!("Request failed: {}", e)))?;
+    let response = client.request(request).await.map_err(|e| {
+        let reason = describe_client_error(&e);
+        if e.is_connect() {
+            client_pool
+                .diagnostics()
+                .upstream_connect_failed(host, &reason);
+        }
+        UpstreamFailure {
+            error: WebMockError::Proxy(reason),
+            transient: e.is_connect() || is_dropped_connection(&e),
+        }
+    })?;
+    client_pool.note_answered();
 
     let status = response.status();
+    let response_version = response.version();
+    let reason = reason_phrase(&response);
 
-    // Extract response headers
-    let mut response_headers = HashMap::new();
-    for (name, value) in response.headers() {
-        if let Ok(value_str) = value.to_str() {
-            response_headers.insert(name.to_string(), value_str.to_string());
-        }
-    }
+    // Extract response headers, keeping every Set-Cookie value
+    let (headers, set_cookie) = collect_response_headers(response.headers());
 
     // Read response body
     let response_body = response
         .into_body()
         .collect()
         .await
-        .map_err(|e| WebMockError::Proxy(format!("Failed to read response body: {}", e)))?
+        .map_err(|e| UpstreamFailure {
+            error: WebMockError::Proxy(format!("Failed to read response body: {}", e)),
+            transient: is_dropped_connection(&e),
+        })?
         .to_bytes()
         .to_vec();
 
-    Ok((status, response_headers, response_body))
+    Ok(UpstreamResponse {
+        status,
+        version: response_version,
+        headers,
+        set_cookie,
+        body: response_body,
+        reason,
+        informational: take_informational(&informational),
+    })
+}
+
+/// Have the 1xx responses the origin sends before the final one collected
+///
+/// hyper hands them to a callback as they arrive, on HTTP/1 connections
+/// only. `100 Continue` answers the request's own `Expect` header rather
+/// than saying anything about the resource, so it is left out.
+fn collect_informational<B>(request: &mut Request<B>) -> Arc<Mutex<Vec<InterimResponse>>> {
+    let collected = Arc::new(Mutex::new(Vec::new()));
+    let sink = Arc::clone(&collected);
+    hyper::ext::on_informational(request, move |response| {
+        if response.status() == StatusCode::CONTINUE {
+            return;
+        }
+        let headers = response
+            .headers()
+            .iter()
+            .filter_map(|(name, value)| {
+                let value = value.to_str().ok()?;
+                Some((name.to_string(), value.to_string()))
+            })
+            .collect();
+        debug!("Received informational response {}", response.status());
+        sink.lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .push(InterimResponse {
+                status: response.status().as_u16(),
+                headers,
+            });
+    });
+    collected
+}
+
+/// The 1xx responses collected so far
+fn take_informational(collected: &Mutex<Vec<InterimResponse>>) -> Vec<InterimResponse> {
+    std::mem::take(&mut *collected.lock().unwrap_or_else(|e| e.into_inner()))
+}
+
+/// Check whether the origin closed or reset the connection mid-exchange
+fn is_dropped_connection(error: &(dyn std::error::Error + 'static)) -> bool {
+    let mut current: Option<&(dyn std::error::Error + 'static)> = Some(error);
+    while let Some(err) = current {
+        if let Some(hyper_err) = err.downcast_ref::<hyper::Error>() {
+            if hyper_err.is_incomplete_message() || hyper_err.is_closed() {
+                return true;
+            }
+        }
+        if let Some(io_err) = err.downcast_ref::<std::io::Error>() {
+            if matches!(
+                io_err.kind(),
+                std::io::ErrorKind::ConnectionReset
+                    | std::io::ErrorKind::ConnectionAborted
+                    | std::io::ErrorKind::BrokenPipe
+                    | std::io::ErrorKind::UnexpectedEof
+            ) {
+                return true;
+            }
+        }
+        current = err.source();
+    }
+    false
+}
+
+/// Check whether a client connection ended because hyper couldn't parse a request on it
+///
+/// Such a request arrived but never reached a handler, so it has no record.
+pub fn is_malformed_request(error: &(dyn std::error::Error + 'static)) -> bool {
+    let mut current: Option<&(dyn std::error::Error + 'static)> = Some(error);
+    while let Some(err) = current {
+        if let Some(hyper_err) = err.downcast_ref::<hyper::Error>() {
+            if hyper_err.is_parse() {
+                return true;
+            }
+        }
+        current = err.source();
+    }
+    false
+}
+
+/// Non-standard reason phrase of an upstream response
+///
+/// hyper only keeps the phrase when it differs from the canonical one.
+pub fn reason_phrase<B>(response: &hyper::Response<B>) -> Option<String> {
+    response
+        .extensions()
+        .get::<hyper::ext::ReasonPhrase>()
+        .map(|reason| String::from_utf8_lossy(reason.as_bytes()).into_owned())
+}
+
+/// Describe why an upstream request failed, leading with a short category
+///
+/// The hyper client error only says "client error (Connect)", so the source
+/// chain is walked to find out whether DNS, the TCP connect or TLS failed.
+pub fn describe_client_error(error: &(dyn std::error::Error + 'static)) -> String {
+    let mut causes = Vec::new();
+    let mut io_kind = None;
+    let mut current: Option<&(dyn std::error::Error + 'static)> = Some(error);
+
+    while let Some(err) = current {
+        if let Some(io_err) = err.downcast_ref::<std::io::Error>() {
+            io_kind.get_or_insert(io_err.kind());
+        }
+        causes.push(err.to_string());
+        current = err.source();
+    }
+
+    let detail = causes.last().cloned().unwrap_or_default();
+    let all = causes.join(": ").to_lowercase();
+
+    let category = if all.contains("dns error") || all.contains("failed to lookup address") {
+        "DNS error"
+    } else if io_kind == Some(std::io::ErrorKind::ConnectionRefused) {
+        "connection refused"
+    } else if io_kind == Some(std::io::ErrorKind::TimedOut) || all.contains("timed out") {
+        "connection timed out"
+    } else if io_kind == Some(std::io::ErrorKind::ConnectionReset) {
+        "connection reset"
+    } else if all.contains("tls") || all.contains("certificate") || all.contains("handshake") {
+        "TLS error"
+    } else if all.contains("connect") {
+        "connection failed"
+    } else {
+        "request failed"
+    };
+
+    format!("{}: {}", category, detail)
+}
+
+/// Convert response headers to a map, collecting Set-Cookie values separately
+pub fn collect_response_headers(
+    header_map: &hyper::HeaderMap,
+) -> (HashMap<String, String>, Vec<String>) {
+    let mut headers = HashMap::new();
+    for (name, value) in header_map {
+        if let Ok(value_str) = value.to_str() {
+            headers.insert(name.to_string(), value_str.to_string());
+        }
+    }
+
+    let set_cookie = header_map
+        .get_all(hyper::header::SET_COOKIE)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .map(str::to_string)
+        .collect();
+
+    (headers, set_cookie)
 }
 
 pub fn is_hop_by_hop_header(name: &str) -> bool {
@@ -158,3 +461,15 @@ pub fn is_hop_by_hop_header(name: &str) -> bool {
             | "upgrade"
     )
 }
+
+/// Answer to a request or CONNECT refused by `--only-hosts`, sent without connecting upstream
+pub fn blocked_host_response(host: &str) -> hyper::Response<Full<Bytes>> {
+    hyper::Response::builder()
+        .status(StatusCode::FORBIDDEN)
+        .header(hyper::header::CONNECTION, "close")
+        .body(Full::new(Bytes::from(format!(
+            "Blocked by webmock: {} is not in --only-hosts",
+            host
+        ))))
+        .unwrap()
+}
@@ -2,6 +2,10 @@ use bytes::{Bytes, BytesMut};
 use http_body_util::BodyExt;
 use hyper::body::Incoming;
 use pin_project_lite::pin_project;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use tokio::sync::mpsc;
+use tokio::task::JoinHandle;
 
 /// Maximum size for in-memory buffering before switching to streaming
 pub const MAX_MEMORY_BUFFER: usize = 10 * 1024 * 1024; // 10MB
@@ -109,8 +113,10 @@ impl Default for ResponseCollector {
 /// Streaming writer for large response bodies to temporary files
 pub struct StreamingWriter {
     temp_file: Option<tempfile::NamedTempFile>,
+    file: Option<tokio::fs::File>,
     in_memory_buffer: BytesMut,
     max_memory_size: usize,
+    total_size: usize,
 }
 
 impl StreamingWriter {
@@ -118,13 +124,19 @@ impl StreamingWriter {
     pub fn new(max_memory_size: usize) -> Self {
         Self {
             temp_file: None,
+            file: None,
             in_memory_buffer: BytesMut::new(),
             max_memory_size,
+            total_size: 0,
         }
     }
 
     /// Write data to the streaming writer
     pub async fn write(&mut self, data: &[u8]) -> Result<(), std::io::Error> {
+        use tokio::io::AsyncWriteExt;
+
+        self.total_size += data.len();
+
         // If we haven't exceeded memory limit, keep in memory
         if self.in_memory_buffer.len() + data.len() <= self.max_memory_size
             && self.temp_file.is_none()
@@ -133,40 +145,41 @@ impl StreamingWriter {
             return Ok(());
         }
 
-        // Switch to file-based storage
+        // Switch to file-based storage, keeping one handle so writes append
         if self.temp_file.is_none() {
             let temp_file = tempfile::NamedTempFile::new()?;
+            let mut async_file = tokio::fs::File::from_std(temp_file.reopen()?);
 
             // Write existing buffer to file
             if !self.in_memory_buffer.is_empty() {
-                use tokio::io::AsyncWriteExt;
-                let mut async_file = tokio::fs::File::from_std(temp_file.reopen()?);
                 async_file.write_all(&self.in_memory_buffer).await?;
-                async_file.flush().await?;
                 self.in_memory_buffer.clear();
             }
 
             self.temp_file = Some(temp_file);
+            self.file = Some(async_file);
         }
 
         // Write new data to file
-        if let Some(ref mut temp_file) = self.temp_file {
-            use tokio::io::AsyncWriteExt;
-            let mut async_file = tokio::fs::File::from_std(temp_file.reopen()?);
+        if let Some(ref mut async_file) = self.file {
             async_file.write_all(data).await?;
-            async_file.flush().await?;
         }
 
         Ok(())
     }
 
     /// Finalize and get all data
-    pub async fn finalize(self) -> Result<Vec<u8>, std::io::Error> {
-        if let Some(temp_file) = self.temp_file {
+    pub async fn finalize(mut self) -> Result<Vec<u8>, std::io::Error> {
+        if let Some(temp_file) = self.temp_file.take() {
+            use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+            if let Some(mut async_file) = self.file.take() {
+                async_file.flush().await?;
+            }
+
             // Read from temporary file
-            use tokio::io::AsyncReadExt;
             let mut async_file = tokio::fs::File::from_std(temp_file.reopen()?);
-            let mut buffer = Vec::new();
+            let mut buffer = Vec::with_capacity(self.total_size);
             async_file.read_to_end(&mut buffer).await?;
             Ok(buffer)
         } else {
@@ -175,14 +188,108 @@ impl StreamingWriter {
         }
     }
 
-    /// Get the current size
+    /// Get the total number of bytes written so far
     pub fn size(&self) -> usize {
-        if self.temp_file.is_some() {
-            // For file-based storage, we'd need to track size separately
-            // For now, return 0 to indicate file-based storage
-            0
-        } else {
-            self.in_memory_buffer.len()
+        self.total_size
+    }
+
+    /// Check whether data has spilled over to a temporary file
+    pub fn is_on_disk(&self) -> bool {
+        self.temp_file.is_some()
+    }
+}
+
+pin_project! {
+    /// Body wrapper that passes frames through while keeping a copy of the data
+    ///
+    /// Used to stream request bodies upstream as they arrive instead of buffering
+    /// them first. The copy is written through a [`StreamingWriter`], so large
+    /// uploads spill to disk the same way large responses do.
+    pub struct RecordingBody<B> {
+        #[pin]
+        inner: B,
+        tx: Option<mpsc::UnboundedSender<Bytes>>,
+    }
+}
+
+impl<B> RecordingBody<B> {
+    /// Wrap a body, returning the wrapper and a handle to the recorded copy
+    pub fn new(inner: B, max_memory_size: usize) -> (Self, BodyRecording) {
+        let (tx, mut rx) = mpsc::unbounded_channel::<Bytes>();
+
+        let handle = tokio::spawn(async move {
+            let mut writer = StreamingWriter::new(max_memory_size);
+            while let Some(chunk) = rx.recv().await {
+                writer.write(&chunk).await?;
+            }
+            writer.finalize().await
+        });
+
+        (
+            Self {
+                inner,
+                tx: Some(tx),
+            },
+            BodyRecording { handle },
+        )
+    }
+}
+
+impl<B> http_body::Body for RecordingBody<B>
+where
+    B: http_body::Body<Data = Bytes>,
+    B::Error: Into<Box<dyn std::error::Error + Send + Sync>>,
+{
+    type Data = Bytes;
+    type Error = Box<dyn std::error::Error + Send + Sync>;
+
+    fn poll_frame(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Result<http_body::Frame<Self::Data>, Self::Error>>> {
+        let this = self.project();
+        match this.inner.poll_frame(cx) {
+            Poll::Ready(Some(Ok(frame))) => {
+                if let (Some(data), Some(tx)) = (frame.data_ref(), this.tx.as_ref()) {
+                    // The receiver only goes away if the recording task failed
+                    let _ = tx.send(data.clone());
+                }
+                Poll::Ready(Some(Ok(frame)))
+            }
+            Poll::Ready(Some(Err(e))) => {
+                this.tx.take();
+                Poll::Ready(Some(Err(e.into())))
+            }
+            Poll::Ready(None) => {
+                this.tx.take();
+                Poll::Ready(None)
+            }
+            Poll::Pending => Poll::Pending,
         }
     }
+
+    fn is_end_stream(&self) -> bool {
+        self.inner.is_end_stream()
+    }
+
+    fn size_hint(&self) -> http_body::SizeHint {
+        self.inner.size_hint()
+    }
+}
+
+/// Handle to the data copied by a [`RecordingBody`]
+pub struct BodyRecording {
+    handle: JoinHandle<Result<Vec<u8>, std::io::Error>>,
+}
+
+impl BodyRecording {
+    /// Wait for the body to be dropped or fully read and return the recorded bytes
+    ///
+    /// If the body was only partially sent (for example because the upstream
+    /// failed), the bytes seen so far are returned.
+    pub async fn finish(self) -> Result<Vec<u8>, std::io::Error> {
+        self.handle
+            .await
+            .map_err(|e| std::io::Error::other(format!("Body recording task failed: {}", e)))?
+    }
 }
@@ -0,0 +1,83 @@
+//! Live feed of requests as the proxy records them
+//!
+//! The recorder publishes a short summary of every record on a bounded
+//! broadcast channel. Subscribers that fall behind lose the oldest summaries
+//! instead of slowing recording down, and are told how many they missed.
+
+use tokio::sync::broadcast::{self, error::RecvError};
+
+use super::records::RequestRecord;
+
+/// Summaries buffered per subscriber before the oldest are dropped
+pub const RECORD_TAIL_CAPACITY: usize = 1024;
+
+/// What a subscriber learns about each recorded request
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RecordedRequest {
+    pub method: String,
+    pub url: String,
+    pub status: u16,
+    /// Response body size in bytes
+    pub size: usize,
+    /// Why the upstream request failed, if it did
+    pub failure: Option<String>,
+}
+
+impl From<&RequestRecord> for RecordedRequest {
+    fn from(record: &RequestRecord) -> Self {
+        Self {
+            method: record.method.clone(),
+            url: record.url.clone(),
+            status: record.response.status,
+            size: record.response.body.len(),
+            failure: record.response.failure.clone(),
+        }
+    }
+}
+
+/// Item received from a [`RecordTail`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TailEvent {
+    /// A request was recorded
+    Recorded(RecordedRequest),
+    /// This many summaries were dropped because the subscriber fell behind
+    Dropped(u64),
+}
+
+/// Create the sender that a recorder publishes summaries on
+pub fn record_channel() -> broadcast::Sender<RecordedRequest> {
+    broadcast::channel(RECORD_TAIL_CAPACITY).0
+}
+
+/// Subscription to the requests recorded from now on
+#[derive(Debug)]
+pub struct RecordTail {
+    receiver: broadcast::Receiver<RecordedRequest>,
+    dropped: u64,
+}
+
+impl RecordTail {
+    pub(crate) fn new(sender: &broadcast::Sender<RecordedRequest>) -> Self {
+        Self {
+            receiver: sender.subscribe(),
+            dropped: 0,
+        }
+    }
+
+    /// Wait for the next event; `None` once the recorder and its session are gone
+    pub async fn recv(&mut self) -> Option<TailEvent> {
+        match self.receiver.recv().await {
+            Ok(request) => Some(TailEvent::Recorded(request)),
+            Err(RecvError::Lagged(count)) => {
+                self.dropped += count;
+                Some(TailEvent::Dropped(count))
+            }
+            Err(RecvError::Closed) => None,
+        }
+    }
+
+    /// Total summaries dropped so far
+    pub fn dropped(&self) -> u64 {
+        self.dropped
+    }
+}
@@ -0,0 +1,300 @@
+//! Captures running against one storage directory
+//!
+//! Each capture registers itself with a file in `captures/` under the storage
+//! directory and holds an exclusive lock on it until it finishes. The lock
+//! goes away with the process, so a file that can be locked belongs to a
+//! capture that exited without removing it; listing the registry deletes
+//! those. A capture started with `--share-browser` also records the DevTools
+//! endpoint of the browser it launched, and later `--share-browser` captures
+//! open a tab in that browser instead of launching their own.
+
+use std::fs::{File, OpenOptions};
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::time::{Duration, Instant};
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use tracing::{debug, warn};
+
+use crate::error::{Result, WebMockError};
+use crate::storage::file_lock::{self, TryLockError};
+
+/// Directory under the storage directory holding one file per running capture
+pub const REGISTRY_DIR: &str = "captures";
+
+/// File locked while a `--share-browser` capture looks for or launches the shared browser
+const LAUNCH_LOCK: &str = "launch.lock";
+
+/// How often a lock held by another capture is retried
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Leases taken by this process so far, so each gets its own file
+static NEXT_LEASE: AtomicU32 = AtomicU32::new(0);
+
+/// A capture that is running, as it registered itself
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CaptureEntry {
+    pub pid: u32,
+    /// Snapshot being captured, when the capture has a single one
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub snapshot: Option<String>,
+    pub started_at: DateTime<Utc>,
+    /// DevTools endpoint of the browser this capture launched for others to share
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub shared_browser: Option<String>,
+    /// DevTools endpoint of the shared browser this capture opened its tab in
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub guest_of: Option<String>,
+}
+
+impl CaptureEntry {
+    /// Entry for a capture by this process
+    pub fn new(snapshot: Option<&str>) -> Self {
+        Self {
+            pid: std::process::id(),
+            snapshot: snapshot.map(str::to_string),
+            started_at: Utc::now(),
+            shared_browser: None,
+            guest_of: None,
+        }
+    }
+
+    /// Short description for messages, e.g. "snapshot 'shop', PID 4242"
+    pub fn describe(&self) -> String {
+        match &self.snapshot {
+            Some(snapshot) => format!("snapshot '{}', PID {}", snapshot, self.pid),
+            None => format!("PID {}", self.pid),
+        }
+    }
+}
+
+/// The captures registered in one storage directory
+#[derive(Debug, Clone)]
+pub struct CaptureRegistry {
+    dir: PathBuf,
+}
+
+impl CaptureRegistry {
+    /// Registry of the storage directory at `storage_path`
+    pub fn new(storage_path: &Path) -> Self {
+        Self {
+            dir: storage_path.join(REGISTRY_DIR),
+        }
+    }
+
+    /// Directory the registry files are in
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// Register a running capture until the returned lease is dropped
+    ///
+    /// The entry is written and locked under a temporary name and only then
+    /// renamed into place, so it's never seen unlocked and taken for stale.
+    pub fn register(&self, entry: CaptureEntry) -> Result<CaptureLease> {
+        std::fs::create_dir_all(&self.dir)
+            .map_err(|e| WebMockError::io("create capture registry", &self.dir, e))?;
+        let name = format!(
+            "{}-{}",
+            entry.pid,
+            NEXT_LEASE.fetch_add(1, Ordering::Relaxed)
+        );
+        let temp_path = self.dir.join(format!("{}.tmp", name));
+        let path = self.dir.join(format!("{}.json", name));
+
+        let mut file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .open(&temp_path)
+            .map_err(|e| WebMockError::io("create capture registry entry", &temp_path, e))?;
+        let written = match file_lock::try_lock(&file) {
+            Ok(()) => write_entry(&mut file, &entry)
+                .map_err(|e| WebMockError::io("write capture registry entry", &temp_path, e)),
+            Err(TryLockError::WouldBlock) => Err(WebMockError::config(format!(
+                "Capture registry entry {} is locked by another process",
+                temp_path.display()
+            ))),
+            Err(TryLockError::Error(e)) => Err(WebMockError::io(
+                "lock capture registry entry",
+                &temp_path,
+                e,
+            )),
+        }
+        .and_then(|()| {
+            std::fs::rename(&temp_path, &path)
+                .map_err(|e| WebMockError::io("register capture", &path, e))
+        });
+        if let Err(e) = written {
+            let _ = std::fs::remove_file(&temp_path);
+            return Err(e);
+        }
+
+        debug!(
+            "Registered capture ({}) at {}",
+            entry.describe(),
+            path.display()
+        );
+        Ok(CaptureLease { path, file, entry })
+    }
+
+    /// Captures that are still running, oldest first
+    ///
+    /// Entries left behind by captures that exited are removed along the way.
+    pub fn running(&self) -> Result<Vec<CaptureEntry>> {
+        let listing = match std::fs::read_dir(&self.dir) {
+            Ok(listing) => listing,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(WebMockError::io("read capture registry", &self.dir, e)),
+        };
+
+        let mut entries = Vec::new();
+        for item in listing {
+            let path = match item {
+                Ok(item) => item.path(),
+                Err(e) => return Err(WebMockError::io("read capture registry", &self.dir, e)),
+            };
+            if path.extension().is_none_or(|extension| extension != "json") {
+                continue;
+            }
+            // The entry may be removed by its capture or another reader meanwhile
+            let mut file = match OpenOptions::new().read(true).write(true).open(&path) {
+                Ok(file) => file,
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => continue,
+                Err(e) => return Err(WebMockError::io("open capture registry entry", &path, e)),
+            };
+            match file_lock::try_lock(&file) {
+                Ok(()) => {
+                    debug!("Removing stale capture registry entry {}", path.display());
+                    if let Err(e) = std::fs::remove_file(&path) {
+                        warn!(
+                            "Failed to remove stale capture entry {}: {}",
+                            path.display(),
+                            e
+                        );
+                    }
+                }
+                Err(TryLockError::WouldBlock) => match read_entry(&mut file) {
+                    Some(entry) => entries.push(entry),
+                    None => debug!("Skipping unreadable capture entry {}", path.display()),
+                },
+                Err(TryLockError::Error(e)) => {
+                    return Err(WebMockError::io("lock capture registry entry", &path, e))
+                }
+            }
+        }
+        entries.sort_by_key(|entry| entry.started_at);
+        Ok(entries)
+    }
+
+    /// The running capture whose browser is open for sharing, if any
+    pub fn shared_browser(&self) -> Result<Option<CaptureEntry>> {
+        Ok(self
+            .running()?
+            .into_iter()
+            .find(|entry| entry.shared_browser.is_some()))
+    }
+
+    /// Running captures with a tab in the shared browser at `endpoint`
+    pub fn guests_of(&self, endpoint: &str) -> Result<Vec<CaptureEntry>> {
+        Ok(self
+            .running()?
+            .into_iter()
+            .filter(|entry| entry.guest_of.as_deref() == Some(endpoint))
+            .collect())
+    }
+
+    /// Wait up to `wait` until no other capture is looking for or launching the shared browser
+    ///
+    /// Held from checking for a shared browser until the capture is
+    /// registered as its owner or guest, so two captures starting together
+    /// don't both launch one.
+    pub async fn lock_launch(&self, wait: Duration) -> Result<LaunchLock> {
+        std::fs::create_dir_all(&self.dir)
+            .map_err(|e| WebMockError::io("create capture registry", &self.dir, e))?;
+        let path = self.dir.join(LAUNCH_LOCK);
+        let file = OpenOptions::new()
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(&path)
+            .map_err(|e| WebMockError::io("open capture launch lock", &path, e))?;
+
+        let deadline = Instant::now() + wait;
+        loop {
+            match file_lock::try_lock(&file) {
+                Ok(()) => return Ok(LaunchLock { _file: file }),
+                Err(TryLockError::WouldBlock) if Instant::now() < deadline => {
+                    tokio::time::sleep(POLL_INTERVAL).await;
+                }
+                Err(TryLockError::WouldBlock) => {
+                    return Err(WebMockError::command_failed(format!(
+                        "Another capture has been starting its shared browser for over {:?}; wait for it or capture without --share-browser",
+                        wait
+                    )));
+                }
+                Err(TryLockError::Error(e)) => {
+                    return Err(WebMockError::io("lock capture launch lock", &path, e))
+                }
+            }
+        }
+    }
+}
+
+/// Registration of a running capture, removed when dropped
+#[derive(Debug)]
+pub struct CaptureLease {
+    path: PathBuf,
+    file: File,
+    entry: CaptureEntry,
+}
+
+impl CaptureLease {
+    /// The entry as registered
+    pub fn entry(&self) -> &CaptureEntry {
+        &self.entry
+    }
+
+    /// Change the registered entry
+    pub fn update(&mut self, change: impl FnOnce(&mut CaptureEntry)) -> Result<()> {
+        change(&mut self.entry);
+        write_entry(&mut self.file, &self.entry)
+            .map_err(|e| WebMockError::io("update capture registry entry", &self.path, e))
+    }
+}
+
+impl Drop for CaptureLease {
+    fn drop(&mut self) {
+        // Removed while still locked, so no reader takes it for stale meanwhile
+        if let Err(e) = std::fs::remove_file(&self.path) {
+            warn!(
+                "Failed to remove capture registry entry {}: {}",
+                self.path.display(),
+                e
+            );
+        }
+    }
+}
+
+/// Held while looking for or launching the shared browser, released when dropped
+#[derive(Debug)]
+pub struct LaunchLock {
+    _file: File,
+}
+
+fn write_entry(file: &mut File, entry: &CaptureEntry) -> std::io::Result<()> {
+    let json = serde_json::to_vec(entry)?;
+    file.set_len(0)?;
+    file.seek(SeekFrom::Start(0))?;
+    file.write_all(&json)?;
+    file.flush()
+}
+
+fn read_entry(file: &mut File) -> Option<CaptureEntry> {
+    let mut json = Vec::new();
+    file.read_to_end(&mut json).ok()?;
+    serde_json::from_slice(&json).ok()
+}
@@ -0,0 +1,56 @@
+use crate::capture::CaptureSession;
+use crate::storage::PageArtifacts;
+use std::time::Duration;
+use tokio::time::timeout;
+use tracing::{info, warn};
+
+/// How long taking the screenshot or reading the DOM may take
+///
+/// Full-page screenshots of very long pages can take a while to render.
+pub const ARTIFACT_TIMEOUT: Duration = Duration::from_secs(30);
+
+impl CaptureSession {
+    /// Take a screenshot and the serialized DOM of the loaded page
+    ///
+    /// Does nothing with `--no-artifacts`. Artifacts only illustrate the
+    /// capture, so one that can't be taken is logged and left out.
+    pub(crate) async fn capture_artifacts(&mut self) {
+        if self.options.no_artifacts {
+            return;
+        }
+        let Some(browser) = &self.browser else {
+            return;
+        };
+
+        info!("Taking a screenshot and the DOM of the loaded page");
+        let screenshot = match timeout(ARTIFACT_TIMEOUT, browser.screenshot()).await {
+            Ok(Ok(png)) => Some(png),
+            Ok(Err(e)) => {
+                warn!("The screenshot will not be saved: {}", e);
+                None
+            }
+            Err(_) => {
+                warn!(
+                    "The screenshot will not be saved: no image after {}s",
+                    ARTIFACT_TIMEOUT.as_secs()
+                );
+                None
+            }
+        };
+        let dom = match timeout(ARTIFACT_TIMEOUT, browser.content()).await {
+            Ok(Ok(html)) => Some(html),
+            Ok(Err(e)) => {
+                warn!("The DOM will not be saved: {}", e);
+                None
+            }
+            Err(_) => {
+                warn!(
+                    "The DOM will not be saved: no content after {}s",
+                    ARTIFACT_TIMEOUT.as_secs()
+                );
+                None
+            }
+        };
+        self.artifacts = Some(PageArtifacts { screenshot, dom });
+    }
+}
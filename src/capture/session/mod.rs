@@ -1,3 +1,9 @@
+mod artifacts;
+mod multi_page;
+mod offline;
 mod session_workflow;
+mod shared_browser;
 mod types;
+mod variants;
+pub use multi_page::PageCaptureReport;
 pub use types::CaptureSession;
@@ -0,0 +1,102 @@
+use crate::capture::validation::validate_url;
+use crate::capture::CaptureSession;
+use crate::error::{Result, WebMockError};
+use std::time::{Duration, Instant};
+use tracing::{info, warn};
+
+/// Outcome of capturing several pages into one session
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PageCaptureReport {
+    /// Pages that loaded, in capture order
+    pub captured: Vec<String>,
+    /// Pages that failed to load, with the reason
+    pub failed: Vec<(String, String)>,
+    /// Pages not attempted because the total timeout ran out
+    pub skipped: usize,
+}
+
+impl CaptureSession {
+    /// Capture several pages one after another in the same browser session
+    ///
+    /// All traffic accumulates in the session's proxy, so a following `stop`
+    /// saves every page into one snapshot. A page that fails is reported and
+    /// skipped; only a session where no page loads is an error. `on_page` is
+    /// called with the 1-based page number before each page.
+    pub async fn capture_pages<F>(
+        &mut self,
+        urls: &[String],
+        page_timeout: Duration,
+        total_timeout: Option<Duration>,
+        mut on_page: F,
+    ) -> Result<PageCaptureReport>
+    where
+        F: FnMut(usize, &str),
+    {
+        if urls.is_empty() {
+            return Err(WebMockError::config("No pages to capture"));
+        }
+        for url in urls {
+            validate_url(url)?;
+        }
+        self.allow_page_hosts(urls.iter().map(String::as_str));
+
+        let started = Instant::now();
+        self.start(None).await?;
+
+        let mut report = PageCaptureReport::default();
+        for (index, url) in urls.iter().enumerate() {
+            // Never let a single page run past the overall deadline
+            let budget = match total_timeout {
+                Some(total) => match total.checked_sub(started.elapsed()) {
+                    Some(remaining) if !remaining.is_zero() => page_timeout.min(remaining),
+                    _ => {
+                        report.skipped = urls.len() - index;
+                        warn!(
+                            "Total timeout reached, skipping the remaining {} pages",
+                            report.skipped
+                        );
+                        break;
+                    }
+                },
+                None => page_timeout,
+            };
+
+            on_page(index + 1, url);
+            match self.load_page(url, budget).await {
+                Ok(()) => {
+                    // The first page that loads stands for the whole capture
+                    if self.artifacts.is_none() {
+                        self.capture_artifacts().await;
+                    }
+                    report.captured.push(url.clone());
+                }
+                Err(e) => {
+                    warn!(
+                        "Page {} of {} failed ({}): {}",
+                        index + 1,
+                        urls.len(),
+                        url,
+                        e
+                    );
+                    report.failed.push((url.clone(), e.user_message()));
+                }
+            }
+        }
+
+        info!(
+            "Captured {} of {} pages ({} failed, {} skipped)",
+            report.captured.len(),
+            urls.len(),
+            report.failed.len(),
+            report.skipped
+        );
+
+        if report.captured.is_empty() {
+            return Err(WebMockError::config(format!(
+                "None of the {} pages could be captured",
+                urls.len()
+            )));
+        }
+        Ok(report)
+    }
+}
@@ -0,0 +1,74 @@
+use crate::capture::browser::offline::SERVICE_WORKER_TIMEOUT;
+use crate::capture::browser::OfflineReload;
+use crate::capture::CaptureSession;
+use crate::error::{Result, WebMockError};
+use std::time::Duration;
+use tokio::time::timeout;
+use tracing::{info, warn};
+
+impl CaptureSession {
+    /// Reload the loaded page offline and log how each of its requests is answered
+    ///
+    /// Waits up to [`SERVICE_WORKER_TIMEOUT`] for a service worker to activate
+    /// first; without one the reload still runs, and usually fails outright.
+    /// Records the proxy makes from here on belong to the reload, and `stop`
+    /// moves them into the snapshot's offline section.
+    pub(crate) async fn reload_offline(&mut self, page_timeout: Duration) -> Result<()> {
+        let service_worker = self.wait_for_service_worker().await;
+        let online_records = self.get_request_count().await;
+
+        let Some(browser) = &mut self.browser else {
+            return Err(WebMockError::config("Browser not initialized"));
+        };
+        info!("Reloading the page offline");
+        let logger = browser.log_requests().await?;
+        browser.set_offline(true).await?;
+        let reloaded = timeout(page_timeout, browser.reload()).await;
+        let settled = match reloaded {
+            Ok(Ok(())) => self.wait_for_network_idle().await,
+            Ok(Err(e)) => Err(e),
+            Err(_) => Err(WebMockError::timeout("Offline reload", page_timeout)),
+        };
+        let requests = logger.finish();
+        if let Some(browser) = &self.browser {
+            browser.set_offline(false).await?;
+        }
+        settled?;
+
+        info!(
+            "Offline reload made {} requests ({} reached the proxy)",
+            requests.len(),
+            self.get_request_count()
+                .await
+                .saturating_sub(online_records)
+        );
+        self.offline_reload = Some((
+            online_records,
+            OfflineReload {
+                service_worker,
+                requests,
+                records: Vec::new(),
+            },
+        ));
+        Ok(())
+    }
+
+    /// Wait for the page's service worker to activate, returning its script URL
+    async fn wait_for_service_worker(&mut self) -> Option<String> {
+        let receiver = self.service_worker.as_mut()?;
+        match timeout(SERVICE_WORKER_TIMEOUT, receiver.wait_for(Option::is_some)).await {
+            Ok(Ok(activated)) => {
+                let script = activated.clone();
+                info!("Service worker active: {}", script.as_deref().unwrap_or(""));
+                script
+            }
+            _ => {
+                warn!(
+                    "No service worker activated within {}s, reloading offline anyway",
+                    SERVICE_WORKER_TIMEOUT.as_secs()
+                );
+                None
+            }
+        }
+    }
+}
@@ -1,13 +1,20 @@
-use crate::capture::browser::BrowserController;
-use crate::capture::proxy::HttpProxy;
+use crate::capture::abort::{count_exceptions, AbortWatch};
+use crate::capture::browser::initiators::MAX_OBSERVED_REQUESTS;
+use crate::capture::browser::page_events::MAX_PAGE_EVENTS;
+use crate::capture::browser::{attach_initiators, BrowserController, PageEventLevel};
+use crate::capture::proxy::client_pool::RetryPolicy;
+use crate::capture::proxy::{HttpClientPool, HttpProxy, RequestRecorder};
 use crate::capture::validation::validate_url;
-use crate::capture::CaptureSession;
+use crate::capture::{CaptureSession, CaptureWarningKind};
 use crate::error::{Result, WebMockError};
-use crate::storage::Snapshot;
+use crate::storage::{ArtifactKind, Snapshot, SnapshotSerializer};
 use chrono::Utc;
-use std::time::Duration;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::sync::mpsc;
 use tokio::time::timeout;
-use tracing::{error, info, warn};
+use tracing::{debug, error, info, warn};
+use url::Url;
 
 impl CaptureSession {
     /// Start the capture process for the given URL and snapshot name
@@ -17,6 +24,7 @@ impl CaptureSession {
     /// 3. Navigate to target URL
     /// 4. Wait for page load and network requests
     /// 5. Record all HTTP traffic
+    /// 6. Reload the page in each `--variant` state
     pub async fn capture(&mut self, url: &str, name: &str, timeout_seconds: u64) -> Result<()> {
         info!(
             "Starting capture session for URL: {} with name: {}",
@@ -25,40 +33,141 @@ impl CaptureSession {
 
         // Validate URL format early
         validate_url(url)?;
+        self.allow_page_hosts([url]);
 
+        self.start(Some(name)).await?;
+        self.load_page(url, Duration::from_secs(timeout_seconds))
+            .await?;
+        self.capture_artifacts().await;
+        self.capture_variants(url, Duration::from_secs(timeout_seconds))
+            .await?;
+        if self.options.emulate_offline_after_load {
+            self.reload_offline(Duration::from_secs(timeout_seconds))
+                .await?;
+        }
+
+        info!("Capture session completed successfully for {}", url);
+        Ok(())
+    }
+
+    /// Start the proxy and launch a browser routed through it
+    ///
+    /// `snapshot` names the capture to others running against the same storage.
+    pub(crate) async fn start(&mut self, snapshot: Option<&str>) -> Result<()> {
         // Step 1: Start HTTP proxy server
         info!("Step 1/4: Starting HTTP proxy server");
-        let proxy_port = self.find_available_port().await?;
-        let proxy = HttpProxy::start(proxy_port).await.map_err(|e| {
-            error!("Failed to start proxy server on port {}: {}", proxy_port, e);
-            e
-        })?;
-
-        self.proxy_port = proxy_port;
-        self.proxy = Some(proxy);
-        info!("HTTP proxy started successfully on port {}", proxy_port);
+        let proxy_port = self.start_proxy().await?;
 
         // Give the proxy server time to fully initialize
         tokio::time::sleep(Duration::from_millis(1000)).await;
         info!("Proxy server initialization complete");
 
-        // Step 2: Launch browser with proxy configuration
-        info!("Step 2/4: Launching browser with proxy configuration");
-        let browser = BrowserController::new(proxy_port).await.map_err(|e| {
-            error!("Failed to launch browser: {}", e);
+        // Step 2: Launch browser with proxy configuration, or attach to the user's
+        let mut browser = match &self.options.attach {
+            Some(endpoint) => {
+                info!("Step 2/4: Attaching to running browser at {}", endpoint);
+                BrowserController::attach(endpoint, &self.options.environment).await
+            }
+            None => {
+                info!("Step 2/4: Launching browser with proxy configuration");
+                self.open_browser(snapshot, proxy_port).await
+            }
+        }
+        .map_err(|e| {
+            error!("Failed to start browser: {}", e);
             e
         })?;
+        browser.set_settle_strategy(self.options.settle_strategy);
+
+        // Keep console output for debugging replays; capture works without it
+        let (events, receiver) = mpsc::channel(MAX_PAGE_EVENTS);
+        match browser.forward_page_events(events).await {
+            Ok(()) => {
+                let (receiver, exceptions) = count_exceptions(receiver);
+                self.page_events = Some(receiver);
+                self.exceptions = Some(exceptions);
+            }
+            Err(e) => {
+                warn!("Console messages will not be kept: {}", e);
+                self.exceptions = None;
+                if self.options.abort.max_js_errors.is_some() {
+                    warn!("Uncaught exceptions can't be counted, --abort-on-js-errors is ignored");
+                }
+            }
+        }
+
+        // Initiators only annotate records, so capture goes on without them
+        let (requests, receiver) = mpsc::channel(MAX_OBSERVED_REQUESTS);
+        match browser.forward_requests(requests).await {
+            Ok(()) => self.observed_requests = Some(receiver),
+            Err(e) => warn!("Request initiators will not be recorded: {}", e),
+        }
+
+        // Without frame events only the main frame's load is awaited
+        match browser.track_frames().await {
+            Ok(receiver) => self.frames = Some(receiver),
+            Err(e) => warn!("Iframe loads will not be awaited: {}", e),
+        }
+
+        // The worker usually activates during the first load, so watch from the start
+        self.offline_reload = None;
+        self.artifacts = None;
+        self.entry_points.clear();
+        if self.options.emulate_offline_after_load {
+            match browser.watch_service_worker().await {
+                Ok(receiver) => self.service_worker = Some(receiver),
+                Err(e) => warn!("Service worker activation can't be awaited: {}", e),
+            }
+        }
 
         self.browser = Some(browser);
+        self.monitor.sample_memory().await;
         info!("Browser launched successfully");
+        Ok(())
+    }
 
+    /// Navigate the running browser to `url` and wait until its traffic settles
+    ///
+    /// Fails with [`WebMockError::CaptureAborted`] as soon as a condition of
+    /// `--abort-on-js-errors` or `--abort-on-status` is met, without waiting
+    /// for the page to settle.
+    pub(crate) async fn load_page(&mut self, url: &str, page_timeout: Duration) -> Result<()> {
         // Step 3: Navigate to target URL with timeout
         info!("Step 3/4: Navigating to target URL: {}", url);
-        let navigation_result = timeout(
-            Duration::from_secs(timeout_seconds),
-            self.navigate_and_wait(url),
-        )
-        .await;
+        if let Some(proxy) = &self.proxy {
+            proxy.set_retry_deadline(Some(Instant::now() + page_timeout));
+            proxy.expect_document(url);
+        }
+        let started = Instant::now();
+        let mut abort = AbortWatch::new(
+            self.options.abort.clone(),
+            self.exceptions.clone(),
+            self.proxy.as_ref().map(HttpProxy::document),
+        );
+        let watch_abort = !self.options.abort.is_empty();
+        let settled = tokio::select! {
+            settled = self.settle_page(url, page_timeout) => settled,
+            reason = abort.triggered(), if watch_abort => {
+                warn!("Aborting the load of {}: {}", url, reason);
+                return Err(WebMockError::CaptureAborted(reason.to_string()));
+            }
+        };
+        settled?;
+        // The page may have settled on the same tick it broke
+        if let Some(reason) = abort.check() {
+            warn!("Aborting the load of {}: {}", url, reason);
+            return Err(WebMockError::CaptureAborted(reason.to_string()));
+        }
+        self.monitor.record_browser_wait(started.elapsed()).await;
+        self.monitor.sample_memory().await;
+        self.record_entry_point(url).await;
+
+        self.check_attached_browser_proxied().await
+    }
+
+    /// Navigate to `url` within `page_timeout`, then wait as the settle strategy says
+    async fn settle_page(&mut self, url: &str, page_timeout: Duration) -> Result<()> {
+        let navigation_result = timeout(page_timeout, self.navigate_and_wait(url)).await;
 
         match navigation_result {
             Ok(Ok(_)) => {
@@ -69,26 +178,139 @@ impl CaptureSession {
                 return Err(e);
             }
             Err(_) => {
-                error!("Navigation timed out after {} seconds", timeout_seconds);
-                return Err(WebMockError::Timeout(timeout_seconds));
+                error!("Navigation timed out after {:?}", page_timeout);
+                return Err(WebMockError::timeout("Page navigation", page_timeout));
             }
         }
 
         // Step 4: Wait for additional network requests to complete
-        info!("Step 4/4: Waiting for network requests to settle");
-        self.wait_for_network_idle().await?;
+        if self.options.settle_strategy.waits_for_network_idle() {
+            info!("Step 4/4: Waiting for network requests to settle");
+            self.wait_for_network_idle().await
+        } else {
+            info!(
+                "Step 4/4: Page loaded under settle strategy {}",
+                self.options.settle_strategy
+            );
+            self.note_stragglers().await;
+            Ok(())
+        }
+    }
 
-        info!("Capture session completed successfully for {}", url);
+    /// Remember a loaded page, and the URL it ended up at after redirects
+    async fn record_entry_point(&mut self, url: &str) {
+        let mut loaded = vec![url.to_string()];
+        if let Some(browser) = &self.browser {
+            match browser.current_url().await {
+                Ok(current)
+                    if current.starts_with("http://") || current.starts_with("https://") =>
+                {
+                    loaded.push(current)
+                }
+                Ok(_) => {}
+                Err(e) => debug!("Final URL of {} is unknown: {}", url, e),
+            }
+        }
+        for entry_point in loaded {
+            if !self.entry_points.contains(&entry_point) {
+                self.entry_points.push(entry_point);
+            }
+        }
+    }
+
+    /// Fail if an attached browser loaded a page without going through the proxy
+    ///
+    /// A launched browser is always pointed at the proxy, but an attached one
+    /// has whatever proxy its user started it with, and would leave an empty
+    /// snapshot behind.
+    ///
+    /// A tab in a shared browser gets its proxy per tab, which Chrome
+    /// versions without per-context proxies silently ignore.
+    async fn check_attached_browser_proxied(&self) -> Result<()> {
+        let attached = self
+            .browser
+            .as_ref()
+            .is_some_and(BrowserController::is_attached);
+        let recorded = match &self.proxy {
+            Some(proxy) => proxy.record_count() + proxy.skipped_requests() as usize,
+            None => 0,
+        };
+        let guest = self
+            .browser
+            .as_ref()
+            .is_some_and(BrowserController::is_guest);
+        if guest && recorded == 0 {
+            return Err(WebMockError::config(
+                "Another capture is running and its browser didn't route this capture's tab through the webmock proxy; its Chrome may not support per-tab proxies. Wait for it to finish, or capture without --share-browser",
+            ));
+        }
+        if attached && recorded == 0 {
+            return Err(WebMockError::config(format!(
+                "The attached browser sent no requests through the webmock proxy. Start Chrome with --proxy-server=127.0.0.1:{} --ignore-certificate-errors",
+                self.proxy_port
+            )));
+        }
         Ok(())
     }
 
+    /// Start the recording proxy and return the port it is listening on
+    ///
+    /// Without a `proxy_port` option the proxy binds port 0 so the OS hands
+    /// out a free port atomically; the browser is only launched afterwards,
+    /// with the port that was bound.
+    pub(crate) async fn start_proxy(&mut self) -> Result<u16> {
+        // A capture started over doesn't carry the warnings of the failed attempt
+        self.warnings.clear();
+        let recorder = Arc::new(
+            RequestRecorder::with_redaction(self.options.redaction.clone())
+                .with_tail(self.tail.clone())
+                .with_warnings(self.warnings.clone())
+                .with_max_requests(self.options.max_requests)
+                .with_only_hosts(self.options.only_hosts.clone()),
+        );
+        let mut client_pool = HttpClientPool::new()
+            .with_retry_policy(RetryPolicy::new(self.options.upstream_retries));
+        if let Some(max) = self.options.max_connections_per_host {
+            client_pool = client_pool.with_max_connections_per_host(max);
+        }
+        let port = self.options.proxy_port.unwrap_or(0);
+        let proxy = HttpProxy::start_with_client_pool(port, recorder, client_pool)
+            .await
+            .map_err(|e| {
+                error!("Failed to start proxy server: {}", e);
+                e
+            })?;
+
+        let proxy_port = proxy.get_port();
+        self.proxy_port = proxy_port;
+        self.proxy = Some(proxy);
+        info!("HTTP proxy started successfully on port {}", proxy_port);
+
+        Ok(proxy_port)
+    }
+
+    /// Add the hosts of the pages being captured to `--only-hosts`, so they are never blocked
+    pub(crate) fn allow_page_hosts<'a>(&mut self, urls: impl IntoIterator<Item = &'a str>) {
+        let Some(only_hosts) = &mut self.options.only_hosts else {
+            return;
+        };
+        for url in urls {
+            if let Some(host) = Url::parse(url)
+                .ok()
+                .and_then(|url| url.host_str().map(str::to_string))
+            {
+                only_hosts.allow(&host);
+            }
+        }
+    }
+
     /// Stop the capture session and return the recorded snapshot
     pub async fn stop(&mut self, name: &str, url: &str) -> Result<Snapshot> {
         info!("Stopping capture session and creating snapshot: {}", name);
 
         // Get recorded requests from proxy
-        let requests = if let Some(proxy) = &self.proxy {
-            let records = proxy.get_records().await;
+        let mut requests = if let Some(proxy) = &self.proxy {
+            let records = proxy.take_records().await;
             info!("Captured {} HTTP requests", records.len());
 
             // Debug: show what types of requests were captured
@@ -119,13 +341,142 @@ impl CaptureSession {
             Vec::new()
         };
 
+        // Which resource triggered each request, as far as the browser's view matches
+        let observed = self.take_observed_requests();
+        let attached = attach_initiators(&mut requests, observed);
+        debug!(
+            "Found initiators for {} of {} requests",
+            attached,
+            requests.len()
+        );
+
+        // Traffic of the offline reload goes into its own section
+        let offline_reload = self.offline_reload.take().map(|(online, mut reload)| {
+            reload.records = requests.split_off(online.min(requests.len()));
+            reload
+        });
+
+        // TLS parameters the proxy negotiated with each origin
+        let tls = self
+            .proxy
+            .as_ref()
+            .map(|proxy| proxy.tls_details())
+            .unwrap_or_default();
+
+        // Tunnels to hosts that refused interception, which are missing from the
+        // records; the recorder noted them as warnings when they were pinned
+        let tunnels = match &self.proxy {
+            Some(proxy) => proxy.get_tunnels().await,
+            None => Vec::new(),
+        };
+
+        // Requests the proxy forwarded past --max-requests without recording
+        let skipped_requests = self
+            .proxy
+            .as_ref()
+            .map_or(0, |proxy| proxy.skipped_requests());
+
+        // Requests that only succeeded, or finally failed, after retrying upstream
+        let retried_requests = self
+            .proxy
+            .as_ref()
+            .map_or(0, |proxy| proxy.retried_requests());
+        if retried_requests > 0 {
+            info!("{} requests needed upstream retries", retried_requests);
+        }
+
+        // Requests refused by --only-hosts, which are missing from the records
+        let blocked_hosts = self
+            .proxy
+            .as_ref()
+            .map(|proxy| proxy.blocked_hosts())
+            .unwrap_or_default();
+        if !blocked_hosts.is_empty() {
+            info!(
+                "Blocked {} requests to {} hosts outside --only-hosts",
+                blocked_hosts.values().sum::<u64>(),
+                blocked_hosts.len()
+            );
+        }
+
+        // Connections the proxy lost before a record was made, which explain gaps in the records
+        let capture_diagnostics = self
+            .proxy
+            .as_ref()
+            .map(|proxy| proxy.capture_diagnostics())
+            .unwrap_or_default();
+        for (host, count) in capture_diagnostics
+            .tls_handshake_failures
+            .iter()
+            .chain(&capture_diagnostics.upstream_connect_failures)
+        {
+            self.warnings
+                .note_many(CaptureWarningKind::ConnectionFailure, *count, host);
+        }
+        self.warnings.note_many(
+            CaptureWarningKind::ConnectionFailure,
+            capture_diagnostics.unrecorded_requests,
+            "",
+        );
+
+        // Console messages and uncaught errors logged by the page
+        let page_events = self.take_page_events();
+        let page_errors = page_events
+            .iter()
+            .filter(|event| event.level == PageEventLevel::Error)
+            .count();
+        if page_errors > 0 {
+            warn!("The page logged {} errors during capture", page_errors);
+        }
+
+        // Screenshot and DOM go next to the snapshot; failing to write them is only logged
+        let artifacts = match self.artifacts.take() {
+            Some(artifacts) => {
+                let saved = self.storage.save_artifacts(name, &artifacts).await;
+                for kind in ArtifactKind::ALL {
+                    if artifacts.data(kind).is_some() && saved.get(kind).is_none() {
+                        self.warnings
+                            .note(CaptureWarningKind::UnsavedArtifact, kind.label());
+                    }
+                }
+                saved
+            }
+            None => Default::default(),
+        };
+
+        // Offline reload traffic is left out, like it is of the records
+        let mut metrics = self.monitor.finish_capture(&requests).await;
+        if let Some(proxy) = &self.proxy {
+            metrics.record_connections(proxy.connection_stats());
+        }
+        debug!("Capture metrics: {:?}", metrics);
+
         // Create snapshot
-        let snapshot = Snapshot {
+        let mut snapshot = Snapshot {
             name: name.to_string(),
             url: url.to_string(),
             created_at: Utc::now(),
             requests,
+            environment: self.options.environment.clone(),
+            tls,
+            tunnels,
+            skipped_requests,
+            page_events,
+            retried_requests,
+            serve_defaults: self.options.serve_defaults.clone(),
+            offline_reload,
+            artifacts,
+            metrics: Some(metrics),
+            blocked_hosts,
+            entry_points: std::mem::take(&mut self.entry_points),
+            capture_diagnostics,
+            provenance: self.options.provenance.clone(),
+            encrypted: self.options.encrypt,
+            ..Default::default()
         };
+        if self.options.deterministic {
+            SnapshotSerializer::make_deterministic(&mut snapshot);
+        }
 
         // Save snapshot to storage
         info!("Saving snapshot to storage");
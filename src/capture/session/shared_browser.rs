@@ -0,0 +1,145 @@
+//! Browsers shared between captures of one storage directory
+//!
+//! Every capture registers in the storage's [`CaptureRegistry`] while its
+//! browser is open. With `share_browser`, the first capture launches Chrome
+//! and registers its DevTools endpoint; later ones open a tab in it with a
+//! per-tab proxy override pointing at their own proxy. The capture that
+//! launched the browser keeps it open until those tabs are closed.
+
+use std::time::{Duration, Instant};
+
+use tracing::{debug, info, warn};
+
+use crate::capture::browser::BrowserController;
+use crate::capture::registry::{CaptureEntry, CaptureRegistry};
+use crate::capture::CaptureSession;
+use crate::error::{Result, WebMockError};
+
+/// Longest a capture waits for another to finish launching the shared browser
+const LAUNCH_LOCK_WAIT: Duration = Duration::from_secs(60);
+
+/// Longest the capture that launched a shared browser keeps it open for others
+const SHARED_BROWSER_WAIT: Duration = Duration::from_secs(300);
+
+/// How often the captures still using a shared browser are checked
+const GUEST_POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+impl CaptureSession {
+    /// Launch a browser routed through the proxy on `proxy_port`, or open a tab in a shared one
+    pub(crate) async fn open_browser(
+        &mut self,
+        snapshot: Option<&str>,
+        proxy_port: u16,
+    ) -> Result<BrowserController> {
+        let registry = CaptureRegistry::new(self.storage.base_path());
+        let mut entry = CaptureEntry::new(snapshot);
+
+        if !self.options.share_browser {
+            // Only there for other captures to see, so capture without it if need be
+            match registry.register(entry) {
+                Ok(lease) => self.lease = Some(lease),
+                Err(e) => warn!("This capture won't be visible to others: {}", e),
+            }
+            return BrowserController::with_environment(proxy_port, &self.options.environment)
+                .await;
+        }
+
+        let _launch = registry.lock_launch(LAUNCH_LOCK_WAIT).await?;
+        if let Some(owner) = registry.shared_browser()? {
+            let endpoint = owner.shared_browser.clone().unwrap_or_default();
+            info!("Sharing the browser of capture ({})", owner.describe());
+            let browser =
+                BrowserController::share(&endpoint, proxy_port, &self.options.environment)
+                    .await
+                    .map_err(|e| {
+                        WebMockError::config(format!(
+                            "Another capture is running ({}) but its browser can't open a tab for this one: {}. Wait for it to finish, or capture without --share-browser",
+                            owner.describe(),
+                            e
+                        ))
+                    })?;
+            entry.guest_of = Some(endpoint);
+            self.lease = Some(registry.register(entry)?);
+            return Ok(browser);
+        }
+
+        let browser =
+            BrowserController::with_environment(proxy_port, &self.options.environment).await?;
+        entry.shared_browser = Some(browser.websocket_address().to_string());
+        self.lease = Some(registry.register(entry)?);
+        info!("Sharing the browser at {}", browser.websocket_address());
+        Ok(browser)
+    }
+
+    /// Close the capture's browser, or just its tab in one it doesn't own
+    ///
+    /// A shared browser is first withdrawn from the registry, then kept open
+    /// for up to [`SHARED_BROWSER_WAIT`] while other captures still have tabs
+    /// in it.
+    pub(crate) async fn close_browser(&mut self) {
+        if let Some(browser) = self.browser.take() {
+            if browser.is_attached() {
+                debug!("Closing capture tab of attached browser");
+                if let Err(e) = browser.close_tab().await {
+                    warn!("Failed to close capture tab gracefully: {}", e);
+                }
+            } else {
+                self.withdraw_shared_browser().await;
+                debug!("Closing browser");
+                if let Err(e) = browser.close().await {
+                    warn!("Failed to close browser gracefully: {}", e);
+                }
+            }
+        }
+        self.lease = None;
+    }
+
+    /// Stop offering the browser to new captures and wait for those using it
+    async fn withdraw_shared_browser(&mut self) {
+        let Some(lease) = &mut self.lease else {
+            return;
+        };
+        let Some(endpoint) = lease.entry().shared_browser.clone() else {
+            return;
+        };
+        let registry = CaptureRegistry::new(self.storage.base_path());
+
+        {
+            // Not while a capture is opening a tab it hasn't registered yet
+            let _launch = registry.lock_launch(LAUNCH_LOCK_WAIT).await;
+            if let Err(e) = lease.update(|entry| entry.shared_browser = None) {
+                warn!("Failed to withdraw the shared browser: {}", e);
+            }
+        }
+
+        let deadline = Instant::now() + SHARED_BROWSER_WAIT;
+        let mut waiting = false;
+        loop {
+            let guests = match registry.guests_of(&endpoint) {
+                Ok(guests) => guests,
+                Err(e) => {
+                    warn!("Can't tell which captures still use the browser: {}", e);
+                    return;
+                }
+            };
+            if guests.is_empty() {
+                return;
+            }
+            if Instant::now() >= deadline {
+                warn!(
+                    "Closing the shared browser with {} captures still using it",
+                    guests.len()
+                );
+                return;
+            }
+            if !waiting {
+                info!(
+                    "Waiting for {} captures using this browser to finish",
+                    guests.len()
+                );
+                waiting = true;
+            }
+            tokio::time::sleep(GUEST_POLL_INTERVAL).await;
+        }
+    }
+}
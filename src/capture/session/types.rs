@@ -1,9 +1,14 @@
-use crate::capture::browser::BrowserController;
-use crate::capture::proxy::HttpProxy;
-use crate::capture::ResourceManager;
+use crate::capture::browser::{
+    BrowserController, FrameLoads, ObservedRequest, OfflineReload, PageEvent,
+};
+use crate::capture::proxy::tail::record_channel;
+use crate::capture::proxy::{HttpProxy, RecordTail, RecordedRequest};
+use crate::capture::registry::CaptureLease;
+use crate::capture::{CaptureOptions, CaptureWarnings, PerformanceMonitor, ResourceManager};
 use crate::error::Result;
-use crate::storage::Storage;
+use crate::storage::{PageArtifacts, Storage};
 use std::sync::Arc;
+use tokio::sync::{broadcast, mpsc, watch};
 use tracing::{debug, info, warn};
 
 /// Main capture session that coordinates browser and proxy components
@@ -13,11 +18,41 @@ pub struct CaptureSession {
     pub(crate) storage: Arc<Storage>,
     pub(crate) proxy_port: u16,
     pub(crate) resource_manager: Arc<ResourceManager>,
+    pub(crate) options: CaptureOptions,
+    /// Shared with every recorder the session starts, so subscriptions outlive them
+    pub(crate) tail: broadcast::Sender<RecordedRequest>,
+    /// Console messages and page errors forwarded by the browser
+    pub(crate) page_events: Option<mpsc::Receiver<PageEvent>>,
+    /// Uncaught exceptions among the page events so far, for `--abort-on-js-errors`
+    pub(crate) exceptions: Option<watch::Receiver<u64>>,
+    /// Requests the browser started, used to find each record's initiator
+    pub(crate) observed_requests: Option<mpsc::Receiver<ObservedRequest>>,
+    /// Frames of the page still loading, awaited along with network idle
+    pub(crate) frames: Option<watch::Receiver<FrameLoads>>,
+    /// Script URL of the page's activated service worker, with `--emulate-offline-after-load`
+    pub(crate) service_worker: Option<watch::Receiver<Option<String>>>,
+    /// The offline reload, with the number of records the proxy made before it
+    pub(crate) offline_reload: Option<(usize, OfflineReload)>,
+    /// Screenshot and DOM of the loaded page, saved next to the snapshot by `stop`
+    pub(crate) artifacts: Option<PageArtifacts>,
+    /// Timing, throughput and memory of the capture, stored with the snapshot
+    pub(crate) monitor: PerformanceMonitor,
+    /// Pages loaded so far and where their redirects ended, in load order
+    pub(crate) entry_points: Vec<String>,
+    /// Registration in the storage's capture registry while the browser is open
+    pub(crate) lease: Option<CaptureLease>,
+    /// Shared with every recorder the session starts, for the capture's final report
+    pub(crate) warnings: CaptureWarnings,
 }
 
 impl CaptureSession {
     /// Create a new capture session with the given storage backend
     pub async fn new(storage: Arc<Storage>) -> Result<Self> {
+        Self::with_options(storage, CaptureOptions::default()).await
+    }
+
+    /// Create a new capture session with custom capture options
+    pub async fn with_options(storage: Arc<Storage>, options: CaptureOptions) -> Result<Self> {
         info!("Creating new capture session");
         let resource_manager = Arc::new(ResourceManager::new());
 
@@ -27,9 +62,36 @@ impl CaptureSession {
             storage,
             proxy_port: 0, // Will be set when proxy starts
             resource_manager,
+            options,
+            tail: record_channel(),
+            page_events: None,
+            exceptions: None,
+            observed_requests: None,
+            frames: None,
+            service_worker: None,
+            offline_reload: None,
+            artifacts: Default::default(),
+            monitor: PerformanceMonitor::new(),
+            entry_points: Vec::new(),
+            lease: None,
+            warnings: CaptureWarnings::new(),
         })
     }
 
+    /// Follow requests as the proxy records them
+    ///
+    /// May be called before `capture` starts. A subscriber that falls behind
+    /// gets a [`TailEvent::Dropped`](crate::capture::proxy::TailEvent) count
+    /// in place of the oldest requests, so recording never waits for it.
+    pub fn subscribe(&self) -> RecordTail {
+        RecordTail::new(&self.tail)
+    }
+
+    /// Warnings noted by the current capture attempt so far
+    pub fn warnings(&self) -> &CaptureWarnings {
+        &self.warnings
+    }
+
     /// Get the current proxy port (for testing/debugging)
     pub fn get_proxy_port(&self) -> u16 {
         self.proxy_port
@@ -44,6 +106,28 @@ impl CaptureSession {
         }
     }
 
+    /// Take the page events forwarded so far, in the order they happened
+    pub(crate) fn take_page_events(&mut self) -> Vec<PageEvent> {
+        let mut events = Vec::new();
+        if let Some(receiver) = &mut self.page_events {
+            while let Ok(event) = receiver.try_recv() {
+                events.push(event);
+            }
+        }
+        events
+    }
+
+    /// Take the requests the browser reported so far, in the order it started them
+    pub(crate) fn take_observed_requests(&mut self) -> Vec<ObservedRequest> {
+        let mut requests = Vec::new();
+        if let Some(receiver) = &mut self.observed_requests {
+            while let Ok(request) = receiver.try_recv() {
+                requests.push(request);
+            }
+        }
+        requests
+    }
+
     /// Check if capture session is active
     pub fn is_active(&self) -> bool {
         self.browser.is_some() && self.proxy.is_some()
@@ -56,13 +140,8 @@ impl CaptureSession {
         // Shutdown resource manager first (this will abort any running tasks)
         self.resource_manager.shutdown().await;
 
-        // Close browser first
-        if let Some(browser) = self.browser.take() {
-            debug!("Closing browser");
-            if let Err(e) = browser.close().await {
-                warn!("Failed to close browser gracefully: {}", e);
-            }
-        }
+        // Close browser first; an attached browser keeps running without our tab
+        self.close_browser().await;
 
         // Stop proxy server
         if let Some(proxy) = self.proxy.take() {
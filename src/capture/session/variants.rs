@@ -0,0 +1,45 @@
+use crate::capture::CaptureSession;
+use crate::error::{Result, WebMockError};
+use std::time::Duration;
+use tracing::info;
+
+impl CaptureSession {
+    /// Load `url` again in each `--variant` state, tagging the records of each load
+    ///
+    /// Runs after the default load, whose records stay untagged. Each variant
+    /// starts from a cleared cookie jar holding only its own cookies.
+    pub(crate) async fn capture_variants(
+        &mut self,
+        url: &str,
+        page_timeout: Duration,
+    ) -> Result<()> {
+        if self.options.variants.is_empty() {
+            return Ok(());
+        }
+        if self.options.emulate_offline_after_load {
+            return Err(WebMockError::config(
+                "--variant can't be combined with --emulate-offline-after-load",
+            ));
+        }
+
+        let variants = self.options.variants.clone();
+        for variant in &variants {
+            let Some(browser) = &self.browser else {
+                return Err(WebMockError::config("Browser not initialized"));
+            };
+            info!("Loading the page as variant '{}'", variant.name);
+            browser
+                .apply_variant(variant, &self.options.environment)
+                .await?;
+            if let Some(proxy) = &self.proxy {
+                proxy.set_variant(Some(variant.name.clone()));
+            }
+            let loaded = self.load_page(url, page_timeout).await;
+            if let Some(proxy) = &self.proxy {
+                proxy.set_variant(None);
+            }
+            loaded?;
+        }
+        Ok(())
+    }
+}
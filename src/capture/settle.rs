@@ -0,0 +1,140 @@
+//! When a captured page counts as loaded
+//!
+//! `capture --settle-strategy` picks the moment: the load event (the
+//! default), network idle, DOMContentLoaded, or a fixed number of seconds
+//! after navigation. Once it is reached the capture completes, even if some
+//! requests are still open; those stragglers are missing from the snapshot
+//! and listed in the capture summary.
+//!
+//! Network idle doesn't wait for requests that have been open longer than
+//! [`LONG_REQUEST_AFTER`], or that ask for an event stream or a websocket:
+//! long-polls and streams of single-page apps never finish on their own.
+
+use std::fmt;
+use std::time::Duration;
+
+use crate::capture::proxy::InFlightRequest;
+use crate::error::{Result, WebMockError};
+
+/// How long a request may stay open before network idle stops waiting for it
+pub const LONG_REQUEST_AFTER: Duration = Duration::from_secs(5);
+
+/// What "done loading" means for a captured page
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum SettleStrategy {
+    /// The load event fired, then the network went idle
+    #[default]
+    Load,
+    /// The DOM was parsed and the network went idle, whether or not the load event fires
+    NetworkIdle,
+    /// The DOM was parsed
+    DomContentLoaded,
+    /// This long after navigation, whatever the page is doing
+    Fixed(Duration),
+}
+
+impl SettleStrategy {
+    /// Parse `load`, `networkidle`, `domcontentloaded` or `fixed:<secs>`
+    pub fn parse(value: &str) -> Result<Self> {
+        let invalid = || {
+            WebMockError::config(format!(
+                "Invalid settle strategy '{}': use load, networkidle, domcontentloaded or fixed:<secs> (e.g. fixed:10)",
+                value
+            ))
+        };
+
+        let value = value.trim();
+        match value.to_ascii_lowercase().as_str() {
+            "load" => Ok(Self::Load),
+            "networkidle" => Ok(Self::NetworkIdle),
+            "domcontentloaded" => Ok(Self::DomContentLoaded),
+            other => {
+                let seconds = other.strip_prefix("fixed:").ok_or_else(invalid)?;
+                let seconds: f64 = seconds.trim().parse().map_err(|_| invalid())?;
+                if !seconds.is_finite() || seconds <= 0.0 || seconds > 600.0 {
+                    return Err(WebMockError::config(format!(
+                        "Invalid settle strategy '{}': fixed waits must be more than 0 and at most 600 seconds",
+                        value
+                    )));
+                }
+                Ok(Self::Fixed(Duration::from_secs_f64(seconds)))
+            }
+        }
+    }
+
+    /// Check whether the capture waits for the network to go idle once the page is loaded
+    pub fn waits_for_network_idle(&self) -> bool {
+        matches!(self, Self::Load | Self::NetworkIdle)
+    }
+}
+
+impl fmt::Display for SettleStrategy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Load => write!(f, "load"),
+            Self::NetworkIdle => write!(f, "networkidle"),
+            Self::DomContentLoaded => write!(f, "domcontentloaded"),
+            Self::Fixed(wait) => write!(f, "fixed:{}", wait.as_secs_f64()),
+        }
+    }
+}
+
+/// Decides when the network of a loading page has gone idle
+///
+/// Fed one observation at a time, with times in milliseconds on the
+/// recorder's clock. The network is idle once nothing changed for the quiet
+/// period: no new records, no frame loading and no open request other than
+/// long-lived ones.
+#[derive(Debug, Clone)]
+pub struct IdleTracker {
+    quiet_period_ms: u64,
+    long_request_after_ms: u64,
+    last_record_count: Option<usize>,
+    quiet_since_ms: Option<u64>,
+}
+
+impl IdleTracker {
+    /// Tracker that needs `quiet_period` without activity, ignoring requests open for `long_request_after`
+    pub fn new(quiet_period: Duration, long_request_after: Duration) -> Self {
+        Self {
+            quiet_period_ms: quiet_period.as_millis() as u64,
+            long_request_after_ms: long_request_after.as_millis() as u64,
+            last_record_count: None,
+            quiet_since_ms: None,
+        }
+    }
+
+    /// Take one look at the network at `now_ms` and tell whether it is idle
+    pub fn observe(
+        &mut self,
+        now_ms: u64,
+        record_count: usize,
+        in_flight: &[InFlightRequest],
+        frames_loading: bool,
+    ) -> bool {
+        let new_records = self.last_record_count != Some(record_count);
+        self.last_record_count = Some(record_count);
+        let waiting = in_flight
+            .iter()
+            .any(|request| !request.is_long_lived(now_ms, self.long_request_after_ms));
+
+        if new_records || frames_loading || waiting {
+            self.quiet_since_ms = None;
+            return false;
+        }
+        let quiet_since = *self.quiet_since_ms.get_or_insert(now_ms);
+        now_ms.saturating_sub(quiet_since) >= self.quiet_period_ms
+    }
+
+    /// Open requests idle detection no longer waits for at `now_ms`
+    pub fn long_lived<'a>(
+        &self,
+        in_flight: &'a [InFlightRequest],
+        now_ms: u64,
+    ) -> Vec<&'a InFlightRequest> {
+        in_flight
+            .iter()
+            .filter(|request| request.is_long_lived(now_ms, self.long_request_after_ms))
+            .collect()
+    }
+}
@@ -0,0 +1,249 @@
+//! Sitemap discovery for `capture --from-sitemap`
+//!
+//! Both `<urlset>` sitemaps and `<sitemapindex>` files are supported, plain or
+//! gzip-compressed. Relative `<loc>` entries are resolved against the URL of
+//! the sitemap they appear in.
+
+use std::collections::{HashSet, VecDeque};
+use std::io::Read;
+use std::time::Duration;
+
+use bytes::Bytes;
+use flate2::read::GzDecoder;
+use http_body_util::{BodyExt, Empty, Limited};
+use hyper::header::LOCATION;
+use hyper::{Request, StatusCode};
+use hyper_util::client::legacy::Client;
+use tracing::{debug, info, warn};
+use url::Url;
+
+use crate::capture::proxy::HttpClientPool;
+use crate::error::{Result, WebMockError};
+
+/// Pages captured from a sitemap when `--limit` is not given
+pub const DEFAULT_SITEMAP_LIMIT: usize = 50;
+
+/// Largest sitemap accepted, compressed or not (the protocol caps them at 50MB)
+pub const MAX_SITEMAP_BYTES: usize = 50 * 1024 * 1024;
+
+/// How many levels of sitemap index files are followed
+const MAX_SITEMAP_DEPTH: usize = 3;
+
+/// Redirects followed when fetching a sitemap
+const MAX_SITEMAP_REDIRECTS: usize = 5;
+
+/// Time allowed for fetching a single sitemap file
+const SITEMAP_FETCH_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Parsed contents of a sitemap file
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SitemapDocument {
+    /// Page URLs from a `<urlset>`
+    Pages(Vec<Url>),
+    /// Nested sitemap URLs from a `<sitemapindex>`
+    Index(Vec<Url>),
+}
+
+/// Which sitemap pages are captured
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SitemapOptions {
+    /// Most pages to capture
+    pub limit: usize,
+    /// Skip pages on a different host than the sitemap
+    pub same_host_only: bool,
+}
+
+impl Default for SitemapOptions {
+    fn default() -> Self {
+        Self {
+            limit: DEFAULT_SITEMAP_LIMIT,
+            same_host_only: false,
+        }
+    }
+}
+
+/// Parse a sitemap or sitemap index, decompressing it first if gzipped
+pub fn parse_sitemap(data: &[u8], base: &Url) -> Result<SitemapDocument> {
+    let data = decompress(data)?;
+    let text = std::str::from_utf8(&data)
+        .map_err(|_| WebMockError::config(format!("Sitemap {} is not valid UTF-8", base)))?;
+    let document = roxmltree::Document::parse(text)
+        .map_err(|e| WebMockError::config(format!("Sitemap {} is not valid XML: {}", base, e)))?;
+
+    let root = document.root_element();
+    let entry_tag = match root.tag_name().name() {
+        "urlset" => "url",
+        "sitemapindex" => "sitemap",
+        other => {
+            return Err(WebMockError::config(format!(
+                "{} is not a sitemap: unexpected <{}> root element",
+                base, other
+            )));
+        }
+    };
+
+    let mut urls = Vec::new();
+    for entry in root
+        .children()
+        .filter(|node| node.tag_name().name() == entry_tag)
+    {
+        let Some(loc) = entry
+            .children()
+            .find(|node| node.tag_name().name() == "loc")
+            .and_then(|node| node.text())
+            .map(str::trim)
+            .filter(|loc| !loc.is_empty())
+        else {
+            continue;
+        };
+
+        match base.join(loc) {
+            Ok(url) if matches!(url.scheme(), "http" | "https") => urls.push(url),
+            Ok(url) => debug!("Skipping non-HTTP sitemap entry: {}", url),
+            Err(e) => debug!("Skipping invalid sitemap entry '{}': {}", loc, e),
+        }
+    }
+
+    Ok(match entry_tag {
+        "url" => SitemapDocument::Pages(urls),
+        _ => SitemapDocument::Index(urls),
+    })
+}
+
+/// Fetch a sitemap, following index files, and return up to `limit` page URLs
+///
+/// Nested sitemaps that fail to load are skipped with a warning; only a
+/// failure of the sitemap given on the command line is an error.
+pub async fn collect_sitemap_urls(
+    sitemap_url: &str,
+    options: &SitemapOptions,
+) -> Result<Vec<String>> {
+    let root = Url::parse(sitemap_url)
+        .map_err(|e| WebMockError::InvalidUrl(sitemap_url.to_string(), e.to_string()))?;
+
+    let client = Client::builder(hyper_util::rt::TokioExecutor::new())
+        .build(HttpClientPool::https_connector());
+
+    let mut pages = Vec::new();
+    let mut seen_pages = HashSet::new();
+    let mut seen_sitemaps = HashSet::from([root.clone()]);
+    let mut queue = VecDeque::from([(root.clone(), 0)]);
+
+    while let Some((url, depth)) = queue.pop_front() {
+        if pages.len() >= options.limit {
+            break;
+        }
+
+        // Relative entries resolve against where the sitemap was served from
+        let document = match fetch_sitemap(&client, &url).await {
+            Ok((location, data)) => parse_sitemap(&data, &location),
+            Err(e) => Err(e),
+        };
+        let document = match document {
+            Ok(document) => document,
+            Err(e) if url == root => return Err(e),
+            Err(e) => {
+                warn!("Skipping sitemap {}: {}", url, e);
+                continue;
+            }
+        };
+
+        match document {
+            SitemapDocument::Pages(urls) => {
+                for page in urls {
+                    if pages.len() >= options.limit {
+                        break;
+                    }
+                    if options.same_host_only && page.host_str() != root.host_str() {
+                        debug!("Skipping page on another host: {}", page);
+                        continue;
+                    }
+                    if seen_pages.insert(page.clone()) {
+                        pages.push(page.to_string());
+                    }
+                }
+            }
+            SitemapDocument::Index(sitemaps) if depth < MAX_SITEMAP_DEPTH => {
+                for sitemap in sitemaps {
+                    if seen_sitemaps.insert(sitemap.clone()) {
+                        queue.push_back((sitemap, depth + 1));
+                    }
+                }
+            }
+            SitemapDocument::Index(_) => {
+                warn!("Not following sitemap index {} nested too deeply", url);
+            }
+        }
+    }
+
+    info!("Found {} pages in sitemap {}", pages.len(), root);
+    Ok(pages)
+}
+
+/// Download a sitemap file, following redirects, and return its final URL and body
+async fn fetch_sitemap<C>(client: &Client<C, Empty<Bytes>>, url: &Url) -> Result<(Url, Vec<u8>)>
+where
+    C: hyper_util::client::legacy::connect::Connect + Clone + Send + Sync + 'static,
+{
+    let failed = |url: &Url, reason: String| {
+        WebMockError::config(format!("Failed to fetch sitemap {}: {}", url, reason))
+    };
+
+    let mut url = url.clone();
+    for _ in 0..=MAX_SITEMAP_REDIRECTS {
+        debug!("Fetching sitemap: {}", url);
+        let request = Request::get(url.as_str())
+            .header("user-agent", concat!("webmock/", env!("CARGO_PKG_VERSION")))
+            .body(Empty::new())
+            .map_err(|e| failed(&url, e.to_string()))?;
+
+        let response = tokio::time::timeout(SITEMAP_FETCH_TIMEOUT, client.request(request))
+            .await
+            .map_err(|_| failed(&url, "timed out".to_string()))?
+            .map_err(|e| failed(&url, e.to_string()))?;
+
+        let status = response.status();
+        if status.is_redirection() {
+            let location = response
+                .headers()
+                .get(LOCATION)
+                .and_then(|value| value.to_str().ok())
+                .ok_or_else(|| failed(&url, format!("{} without a Location header", status)))?;
+            url = url
+                .join(location)
+                .map_err(|e| failed(&url, format!("invalid redirect: {}", e)))?;
+            continue;
+        }
+        if status != StatusCode::OK {
+            return Err(failed(&url, format!("server answered {}", status)));
+        }
+
+        let body = Limited::new(response.into_body(), MAX_SITEMAP_BYTES)
+            .collect()
+            .await
+            .map_err(|e| failed(&url, e.to_string()))?;
+        return Ok((url, body.to_bytes().to_vec()));
+    }
+
+    Err(failed(&url, "too many redirects".to_string()))
+}
+
+/// Inflate gzip data, recognized by its magic bytes; other data is returned as is
+fn decompress(data: &[u8]) -> Result<Vec<u8>> {
+    if !data.starts_with(&[0x1f, 0x8b]) {
+        return Ok(data.to_vec());
+    }
+
+    let mut inflated = Vec::new();
+    GzDecoder::new(data)
+        .take(MAX_SITEMAP_BYTES as u64 + 1)
+        .read_to_end(&mut inflated)
+        .map_err(|e| WebMockError::config(format!("Invalid gzip sitemap: {}", e)))?;
+    if inflated.len() > MAX_SITEMAP_BYTES {
+        return Err(WebMockError::config(format!(
+            "Sitemap is larger than {}MB once decompressed",
+            MAX_SITEMAP_BYTES / 1024 / 1024
+        )));
+    }
+    Ok(inflated)
+}
@@ -0,0 +1,227 @@
+use crate::capture::abort::{count_exceptions, AbortWatch};
+use crate::capture::browser::page_events::{PageEvent, PageEventLevel, PageEventSource};
+use crate::capture::proxy::records::RequestRecord;
+use crate::capture::proxy::{DocumentResponse, RequestRecorder};
+use crate::capture::{AbortConditions, AbortReason, StatusPattern};
+use crate::test_utils::test_helpers::create_test_record;
+use chrono::Utc;
+use tokio::sync::{mpsc, watch};
+use tokio::time::{timeout, Duration};
+
+fn event(source: PageEventSource, message: &str) -> PageEvent {
+    PageEvent {
+        timestamp: Utc::now(),
+        level: PageEventLevel::Error,
+        source,
+        message: message.to_string(),
+        location: None,
+    }
+}
+
+fn record(method: &str, url: &str, status: u16, headers: &[(&str, &str)]) -> RequestRecord {
+    create_test_record(method, url, status, headers, b"")
+}
+
+fn document(status: u16) -> DocumentResponse {
+    DocumentResponse {
+        sequence: 0,
+        url: "https://shop.example/".to_string(),
+        status,
+    }
+}
+
+#[test]
+fn test_status_pattern_parse_and_match() {
+    let class = StatusPattern::parse("5xx").unwrap();
+    assert_eq!(class, StatusPattern::Class(5));
+    assert!(class.matches(500) && class.matches(503) && class.matches(599));
+    assert!(!class.matches(404) && !class.matches(600));
+    assert_eq!(
+        StatusPattern::parse(" 4XX ").unwrap(),
+        StatusPattern::Class(4)
+    );
+
+    let exact = StatusPattern::parse("503").unwrap();
+    assert!(exact.matches(503) && !exact.matches(502));
+    assert_eq!(exact.to_string(), "503");
+    assert_eq!(class.to_string(), "5xx");
+
+    for invalid in ["", "xx", "6xx", "0xx", "55xx", "99", "600", "5x", "server"] {
+        assert!(StatusPattern::parse(invalid).is_err(), "{}", invalid);
+    }
+}
+
+#[test]
+fn test_abort_conditions_check() {
+    let conditions = AbortConditions {
+        max_js_errors: Some(2),
+        on_status: Some(StatusPattern::Class(5)),
+        save_on_abort: false,
+    };
+    assert_eq!(conditions.check(2, Some(&document(200))), None);
+    assert_eq!(
+        conditions.check(3, None),
+        Some(AbortReason::JsErrors {
+            exceptions: 3,
+            max: 2
+        })
+    );
+    let reason = conditions.check(0, Some(&document(502))).unwrap();
+    assert_eq!(
+        reason.to_string(),
+        "the main document https://shop.example/ returned 502"
+    );
+
+    // Without conditions nothing aborts
+    assert!(AbortConditions::default().is_empty());
+    assert_eq!(
+        AbortConditions::default().check(100, Some(&document(500))),
+        None
+    );
+}
+
+#[tokio::test]
+async fn test_count_exceptions_counts_and_forwards_page_events() {
+    let (events, receiver) = mpsc::channel(8);
+    let (mut forwarded, exceptions) = count_exceptions(receiver);
+
+    events
+        .send(event(PageEventSource::Console, "loading"))
+        .await
+        .unwrap();
+    events
+        .send(event(
+            PageEventSource::Exception,
+            "TypeError: x is undefined",
+        ))
+        .await
+        .unwrap();
+    events
+        .send(event(PageEventSource::Exception, "ReferenceError: y"))
+        .await
+        .unwrap();
+    drop(events);
+
+    let mut messages = Vec::new();
+    while let Some(event) = timeout(Duration::from_secs(5), forwarded.recv())
+        .await
+        .unwrap()
+    {
+        messages.push(event.message);
+    }
+    assert_eq!(
+        messages,
+        vec!["loading", "TypeError: x is undefined", "ReferenceError: y"]
+    );
+    // The count is kept after the stream ends
+    assert_eq!(*exceptions.borrow(), 2);
+}
+
+#[tokio::test]
+async fn test_recorder_flags_the_main_document_after_redirects() {
+    let recorder = RequestRecorder::new();
+    let document = recorder.document();
+    // The proxy never sees the fragment
+    recorder.expect_document("https://shop.example/#top");
+
+    recorder
+        .record_request(record(
+            "GET",
+            "https://shop.example/",
+            301,
+            &[("Location", "/home")],
+        ))
+        .await;
+    // Neither a subresource nor another method to the target is the document
+    recorder
+        .record_request(record("GET", "https://shop.example/app.js", 503, &[]))
+        .await;
+    recorder
+        .record_request(record("POST", "https://shop.example/home", 200, &[]))
+        .await;
+    assert_eq!(*document.borrow(), None);
+
+    recorder
+        .record_request(record("GET", "https://shop.example/home", 503, &[]))
+        .await;
+    assert_eq!(
+        *document.borrow(),
+        Some(DocumentResponse {
+            sequence: 3,
+            url: "https://shop.example/home".to_string(),
+            status: 503,
+        })
+    );
+
+    // Only the first response is the document; a reload isn't flagged again
+    recorder
+        .record_request(record("GET", "https://shop.example/home", 200, &[]))
+        .await;
+    assert_eq!(document.borrow().as_ref().unwrap().status, 503);
+
+    // A new navigation forgets the old document
+    recorder.expect_document("https://shop.example/other");
+    assert_eq!(*document.borrow(), None);
+}
+
+#[tokio::test]
+async fn test_abort_watch_triggers_on_stubbed_events() {
+    let conditions = AbortConditions {
+        max_js_errors: Some(1),
+        on_status: Some(StatusPattern::Class(5)),
+        save_on_abort: false,
+    };
+
+    // Exceptions thrown before the watch started don't count against it
+    let (exceptions, counted) = watch::channel(4u64);
+    let (flagged, documents) = watch::channel(None);
+    let mut abort = AbortWatch::new(conditions.clone(), Some(counted), Some(documents));
+    assert_eq!(abort.check(), None);
+
+    let triggered = tokio::spawn(async move { abort.triggered().await });
+    exceptions.send_replace(5);
+    flagged.send_replace(Some(document(200)));
+    exceptions.send_replace(6);
+    let reason = timeout(Duration::from_secs(5), triggered)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(
+        reason,
+        AbortReason::JsErrors {
+            exceptions: 2,
+            max: 1
+        }
+    );
+
+    // Without page events, the document status alone aborts
+    let (flagged, documents) = watch::channel(None);
+    let mut abort = AbortWatch::new(conditions, None, Some(documents));
+    let triggered = tokio::spawn(async move { abort.triggered().await });
+    flagged.send_replace(Some(document(502)));
+    let reason = timeout(Duration::from_secs(5), triggered)
+        .await
+        .unwrap()
+        .unwrap();
+    assert!(matches!(
+        reason,
+        AbortReason::DocumentStatus { status: 502, .. }
+    ));
+}
+
+#[tokio::test]
+async fn test_abort_watch_waits_forever_once_nothing_can_change() {
+    let (exceptions, counted) = watch::channel(0u64);
+    let mut abort = AbortWatch::new(
+        AbortConditions {
+            max_js_errors: Some(0),
+            ..AbortConditions::default()
+        },
+        Some(counted),
+        None,
+    );
+    drop(exceptions);
+    assert!(timeout(Duration::from_millis(100), abort.triggered())
+        .await
+        .is_err());
+}
@@ -0,0 +1,102 @@
+use crate::capture::browser::{FrameInfo, FrameLoads};
+use chromiumoxide::cdp::browser_protocol::page::FrameTree;
+use serde_json::json;
+
+fn frame(id: &str, parent_id: Option<&str>, url: &str) -> serde_json::Value {
+    let mut frame = json!({
+        "id": id,
+        "loaderId": format!("L-{}", id),
+        "url": url,
+        "domainAndRegistry": "example.com",
+        "securityOrigin": url,
+        "mimeType": "text/html",
+        "secureContextType": "Secure",
+        "crossOriginIsolatedContextType": "NotIsolated",
+        "gatedAPIFeatures": []
+    });
+    if let Some(parent_id) = parent_id {
+        frame["parentId"] = json!(parent_id);
+    }
+    frame
+}
+
+fn info(id: &str, parent_id: Option<&str>, url: &str) -> FrameInfo {
+    FrameInfo {
+        id: id.to_string(),
+        parent_id: parent_id.map(str::to_string),
+        url: url.to_string(),
+    }
+}
+
+#[test]
+fn test_frames_from_tree_parents_first() {
+    let tree: FrameTree = serde_json::from_value(json!({
+        "frame": frame("MAIN", None, "https://shop.example/checkout"),
+        "childFrames": [
+            {
+                "frame": frame("PAY", Some("MAIN"), "https://pay.example/widget"),
+                "childFrames": [
+                    { "frame": frame("3DS", Some("PAY"), "https://bank.example/verify") }
+                ]
+            },
+            { "frame": frame("MAP", Some("MAIN"), "https://maps.example/embed") }
+        ]
+    }))
+    .unwrap();
+
+    let frames = FrameInfo::from_tree(&tree);
+    assert_eq!(
+        frames,
+        [
+            info("MAIN", None, "https://shop.example/checkout"),
+            info("PAY", Some("MAIN"), "https://pay.example/widget"),
+            info("3DS", Some("PAY"), "https://bank.example/verify"),
+            info("MAP", Some("MAIN"), "https://maps.example/embed"),
+        ]
+    );
+    assert!(frames[0].is_main());
+    assert!(!frames[1].is_main());
+}
+
+#[test]
+fn test_frame_loads_follow_events() {
+    let mut loads = FrameLoads::default();
+    loads.started("MAIN");
+    loads.started("PAY");
+    loads.started("AD");
+    loads.stopped("MAIN");
+    assert_eq!(loads.loading(), ["AD", "PAY"]);
+
+    // A removed frame never stops loading
+    loads.detached("AD");
+    assert_eq!(loads.loading(), ["PAY"]);
+
+    // Navigating the frame again starts it over
+    loads.stopped("PAY");
+    assert!(loads.loading().is_empty());
+    loads.started("PAY");
+    assert_eq!(loads.loading(), ["PAY"]);
+}
+
+#[test]
+fn test_pending_frames_are_attached_and_loading() {
+    let mut loads = FrameLoads::default();
+    loads.started("MAIN");
+    loads.stopped("MAIN");
+    loads.started("PAY");
+    // Removed without its detach event being seen
+    loads.started("GONE");
+
+    let attached = [
+        info("MAIN", None, "https://shop.example/checkout"),
+        info("PAY", Some("MAIN"), "https://pay.example/widget"),
+        // Loaded before tracking began
+        info("OLD", Some("MAIN"), "https://maps.example/embed"),
+    ];
+    let pending: Vec<&str> = loads
+        .pending(&attached)
+        .into_iter()
+        .map(|frame| frame.id.as_str())
+        .collect();
+    assert_eq!(pending, ["PAY"]);
+}
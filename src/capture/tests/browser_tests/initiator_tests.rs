@@ -0,0 +1,192 @@
+use crate::capture::browser::{attach_initiators, ObservedRequest};
+use crate::capture::proxy::records::{InitiatorKind, RequestInitiator, RequestRecord};
+use crate::test_utils::test_helpers::create_test_record;
+use chromiumoxide::cdp::browser_protocol::network::EventRequestWillBeSent;
+use chromiumoxide::cdp::browser_protocol::page::FrameId;
+use serde_json::json;
+
+fn request_will_be_sent(url: &str, initiator: serde_json::Value) -> EventRequestWillBeSent {
+    serde_json::from_value(json!({
+        "requestId": "1000.1",
+        "loaderId": "L1",
+        "documentURL": "https://example.com/",
+        "request": {
+            "url": url,
+            "method": "GET",
+            "headers": {},
+            "initialPriority": "High",
+            "referrerPolicy": "strict-origin-when-cross-origin"
+        },
+        "timestamp": 1.5,
+        "wallTime": 1_700_000_000.0,
+        "initiator": initiator,
+        "redirectHasExtraInfo": false
+    }))
+    .unwrap()
+}
+
+fn record(method: &str, url: &str) -> RequestRecord {
+    create_test_record(method, url, 200, &[], b"")
+}
+
+fn observed(method: &str, url: &str, script: &str) -> ObservedRequest {
+    ObservedRequest {
+        method: method.to_string(),
+        url: url.to_string(),
+        initiator: RequestInitiator {
+            kind: InitiatorKind::Script,
+            url: Some(script.to_string()),
+            line: Some(1),
+        },
+        frame_id: None,
+    }
+}
+
+#[test]
+fn test_parser_initiator_from_event() {
+    let event = request_will_be_sent(
+        "https://example.com/app.js",
+        json!({ "type": "parser", "url": "https://example.com/", "lineNumber": 9 }),
+    );
+
+    let request = ObservedRequest::from_event(&event);
+    assert_eq!(request.method, "GET");
+    assert_eq!(request.url, "https://example.com/app.js");
+    assert_eq!(request.frame_id, None);
+    assert_eq!(
+        request.initiator,
+        RequestInitiator {
+            kind: InitiatorKind::Parser,
+            url: Some("https://example.com/".to_string()),
+            line: Some(10),
+        }
+    );
+}
+
+#[test]
+fn test_script_initiator_uses_top_stack_frame() {
+    let event = request_will_be_sent(
+        "https://api.example.com/items",
+        json!({
+            "type": "script",
+            "stack": {
+                "callFrames": [
+                    { "functionName": "", "scriptId": "3", "url": "", "lineNumber": 0, "columnNumber": 0 },
+                    { "functionName": "load", "scriptId": "4", "url": "https://example.com/app.js", "lineNumber": 41, "columnNumber": 7 }
+                ]
+            }
+        }),
+    );
+
+    let initiator = ObservedRequest::from_event(&event).initiator;
+    assert_eq!(initiator.kind, InitiatorKind::Script);
+    assert_eq!(initiator.url.as_deref(), Some("https://example.com/app.js"));
+    assert_eq!(initiator.line, Some(42));
+    assert_eq!(
+        initiator.to_string(),
+        "script https://example.com/app.js:42"
+    );
+}
+
+#[test]
+fn test_attach_initiators_in_sequence() {
+    let mut records = vec![
+        record("GET", "https://example.com:443/poll"),
+        record("GET", "https://example.com/poll"),
+        record("POST", "https://example.com/poll"),
+    ];
+    let attached = attach_initiators(
+        &mut records,
+        vec![
+            observed(
+                "GET",
+                "https://example.com/poll",
+                "https://example.com/first.js",
+            ),
+            observed(
+                "GET",
+                "https://example.com/poll#x",
+                "https://example.com/second.js",
+            ),
+            observed(
+                "post",
+                "https://example.com/poll",
+                "https://example.com/form.js",
+            ),
+        ],
+    );
+
+    assert_eq!(attached, 3);
+    let scripts: Vec<_> = records
+        .iter()
+        .map(|record| record.initiator.as_ref().unwrap().url.clone().unwrap())
+        .collect();
+    assert_eq!(
+        scripts,
+        [
+            "https://example.com/first.js",
+            "https://example.com/second.js",
+            "https://example.com/form.js"
+        ]
+    );
+}
+
+#[test]
+fn test_unmatched_initiators_are_dropped() {
+    let mut records = vec![
+        record("GET", "https://example.com/a"),
+        record("GET", "https://example.com/a"),
+    ];
+    let attached = attach_initiators(
+        &mut records,
+        vec![
+            observed("GET", "https://example.com/a", "https://example.com/app.js"),
+            // Seen by the browser only, e.g. served from its cache
+            observed(
+                "GET",
+                "https://example.com/cached",
+                "https://example.com/app.js",
+            ),
+            observed(
+                "GET",
+                "data:image/png;base64,AAAA",
+                "https://example.com/app.js",
+            ),
+        ],
+    );
+
+    assert_eq!(attached, 1);
+    assert!(records[0].initiator.is_some());
+    // The second record has no browser request of its own and gets nothing
+    assert!(records[1].initiator.is_none());
+}
+
+#[test]
+fn test_attach_initiators_records_frame() {
+    let mut event = request_will_be_sent(
+        "https://pay.example/widget.js",
+        json!({ "type": "parser", "url": "https://pay.example/widget", "lineNumber": 3 }),
+    );
+    event.frame_id = Some(FrameId::new("PAYFRAME"));
+    let in_iframe = ObservedRequest::from_event(&event);
+    assert_eq!(in_iframe.frame_id.as_deref(), Some("PAYFRAME"));
+
+    let mut records = vec![
+        record("GET", "https://shop.example/"),
+        record("GET", "https://pay.example/widget.js"),
+    ];
+    let attached = attach_initiators(
+        &mut records,
+        vec![
+            ObservedRequest {
+                frame_id: Some("MAINFRAME".to_string()),
+                ..observed("GET", "https://shop.example/", "https://shop.example/")
+            },
+            in_iframe,
+        ],
+    );
+
+    assert_eq!(attached, 2);
+    assert_eq!(records[0].frame_id.as_deref(), Some("MAINFRAME"));
+    assert_eq!(records[1].frame_id.as_deref(), Some("PAYFRAME"));
+}
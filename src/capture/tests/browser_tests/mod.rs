@@ -1,5 +1,9 @@
 mod browser_creation_tests;
+mod frame_tests;
+mod initiator_tests;
 mod navigation_tests;
+mod offline_tests;
+mod page_event_tests;
 mod page_info_tests;
 mod page_load_tests;
 mod url_validation_tests;
@@ -0,0 +1,176 @@
+use crate::capture::browser::offline::{response_outcome, OfflineRequestLog};
+use crate::capture::browser::{OfflineOutcome, OfflineReload, OfflineRequest};
+use crate::capture::proxy::records::RequestRecord;
+use crate::storage::{Snapshot, SnapshotSerializer};
+use crate::test_utils::test_helpers::create_test_record;
+use chromiumoxide::cdp::browser_protocol::network::Response;
+use serde_json::json;
+
+fn response(extra: serde_json::Value) -> Response {
+    let mut response = json!({
+        "url": "https://app.example.com/",
+        "status": 200,
+        "statusText": "OK",
+        "headers": {},
+        "mimeType": "text/html",
+        "charset": "utf-8",
+        "connectionReused": false,
+        "connectionId": 0.0,
+        "encodedDataLength": 0.0,
+        "securityState": "secure"
+    });
+    response
+        .as_object_mut()
+        .unwrap()
+        .extend(extra.as_object().unwrap().clone());
+    serde_json::from_value(response).unwrap()
+}
+
+fn record(url: &str) -> RequestRecord {
+    create_test_record("GET", url, 200, &[], b"")
+}
+
+fn offline(url: &str, outcome: OfflineOutcome) -> OfflineRequest {
+    OfflineRequest {
+        method: "GET".to_string(),
+        url: url.to_string(),
+        outcome,
+        error: None,
+    }
+}
+
+#[test]
+fn test_response_outcome_from_chrome_flags() {
+    assert_eq!(
+        response_outcome(&response(json!({ "fromServiceWorker": true }))),
+        OfflineOutcome::ServiceWorker
+    );
+    assert_eq!(
+        response_outcome(&response(json!({ "fromDiskCache": true }))),
+        OfflineOutcome::Cache
+    );
+    assert_eq!(
+        response_outcome(&response(json!({ "fromPrefetchCache": true }))),
+        OfflineOutcome::Cache
+    );
+    assert_eq!(
+        response_outcome(&response(json!({}))),
+        OfflineOutcome::Network
+    );
+}
+
+#[test]
+fn test_request_log_keeps_start_order_and_first_outcome() {
+    let mut log = OfflineRequestLog::default();
+    log.started("1", "GET", "https://app.example.com/");
+    log.started("2", "GET", "https://app.example.com/app.js");
+    log.started("3", "POST", "https://api.example.com/events");
+    log.started("4", "GET", "https://app.example.com/old");
+    // A redirect is announced with the same id and the new URL
+    log.started("4", "GET", "https://app.example.com/new");
+
+    log.failed("3", "net::ERR_INTERNET_DISCONNECTED");
+    log.responded("2", OfflineOutcome::Cache);
+    log.responded("1", OfflineOutcome::ServiceWorker);
+    // Cut off after the service worker answered
+    log.failed("1", "net::ERR_ABORTED");
+    // Events for requests started before logging are ignored
+    log.responded("0", OfflineOutcome::Network);
+
+    let requests = log.finish();
+    let summary: Vec<(&str, OfflineOutcome)> = requests
+        .iter()
+        .map(|request| (request.url.as_str(), request.outcome))
+        .collect();
+    assert_eq!(
+        summary,
+        [
+            ("https://app.example.com/", OfflineOutcome::ServiceWorker),
+            ("https://app.example.com/app.js", OfflineOutcome::Cache),
+            ("https://api.example.com/events", OfflineOutcome::Failed),
+            ("https://app.example.com/new", OfflineOutcome::Failed),
+        ]
+    );
+    assert_eq!(requests[0].error, None);
+    assert_eq!(
+        requests[2].error.as_deref(),
+        Some("net::ERR_INTERNET_DISCONNECTED")
+    );
+    assert_eq!(requests[2].method, "POST");
+    // Still pending when the reload settled
+    assert!(requests[3].error.is_some());
+}
+
+#[test]
+fn test_offline_reload_compared_with_online_load() {
+    let online = vec![
+        record("https://app.example.com/"),
+        record("https://app.example.com/app.js"),
+        record("https://app.example.com/app.js#main"),
+        record("https://api.example.com/feed"),
+        record("https://fonts.example.com/font.woff2"),
+    ];
+    let reload = OfflineReload {
+        service_worker: Some("https://app.example.com/sw.js".to_string()),
+        requests: vec![
+            offline("https://app.example.com/", OfflineOutcome::ServiceWorker),
+            offline("https://app.example.com/app.js", OfflineOutcome::Cache),
+            offline("https://api.example.com/feed", OfflineOutcome::Network),
+            offline(
+                "https://app.example.com/offline.html",
+                OfflineOutcome::Failed,
+            ),
+        ],
+        records: Vec::new(),
+    };
+
+    let comparison = reload.compare(&online);
+    assert_eq!(comparison.online, 4);
+    assert_eq!(comparison.served_offline, 2);
+    assert_eq!(comparison.network, ["https://api.example.com/feed"]);
+    assert_eq!(comparison.failed, ["https://app.example.com/offline.html"]);
+    assert_eq!(
+        comparison.not_requested,
+        ["https://fonts.example.com/font.woff2"]
+    );
+    assert_eq!(
+        comparison.offline_only,
+        ["https://app.example.com/offline.html"]
+    );
+}
+
+#[test]
+fn test_offline_reload_is_saved_with_the_snapshot() {
+    let mut snapshot = Snapshot {
+        name: "pwa".to_string(),
+        url: "https://app.example.com/".to_string(),
+        created_at: chrono::Utc::now(),
+        requests: vec![record("https://app.example.com/")],
+        ..Default::default()
+    };
+    let data = SnapshotSerializer::serialize(&snapshot).unwrap();
+    assert!(SnapshotSerializer::deserialize(&data)
+        .unwrap()
+        .offline_reload
+        .is_none());
+
+    snapshot.offline_reload = Some(OfflineReload {
+        service_worker: Some("https://app.example.com/sw.js".to_string()),
+        requests: vec![offline(
+            "https://api.example.com/feed",
+            OfflineOutcome::Network,
+        )],
+        records: vec![record("https://api.example.com/feed")],
+    });
+    let data = SnapshotSerializer::serialize(&snapshot).unwrap();
+    let reload = SnapshotSerializer::deserialize(&data)
+        .unwrap()
+        .offline_reload
+        .unwrap();
+    assert_eq!(
+        reload.service_worker,
+        snapshot.offline_reload.as_ref().unwrap().service_worker
+    );
+    assert_eq!(reload.requests[0].outcome, OfflineOutcome::Network);
+    assert_eq!(reload.records[0].url, "https://api.example.com/feed");
+}
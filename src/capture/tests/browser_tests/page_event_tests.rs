@@ -0,0 +1,88 @@
+use crate::capture::browser::{PageEvent, PageEventLevel, PageEventSource};
+use chromiumoxide::cdp::js_protocol::runtime::{EventConsoleApiCalled, EventExceptionThrown};
+use serde_json::json;
+
+fn console_event(kind: &str, args: serde_json::Value) -> EventConsoleApiCalled {
+    serde_json::from_value(json!({
+        "type": kind,
+        "args": args,
+        "executionContextId": 1,
+        "timestamp": 1_700_000_000_123.0,
+        "stackTrace": {
+            "callFrames": [{
+                "functionName": "init",
+                "scriptId": "7",
+                "url": "https://example.com/app.js",
+                "lineNumber": 41,
+                "columnNumber": 3
+            }]
+        }
+    }))
+    .unwrap()
+}
+
+#[test]
+fn test_console_call_becomes_page_event() {
+    let event = PageEvent::from_console(&console_event(
+        "warning",
+        json!([
+            { "type": "string", "value": "retrying" },
+            { "type": "number", "value": 3 },
+            { "type": "number", "unserializableValue": "NaN", "description": "NaN" },
+            { "type": "object", "className": "Object", "description": "Object" }
+        ]),
+    ));
+
+    assert_eq!(event.level, PageEventLevel::Warning);
+    assert_eq!(event.source, PageEventSource::Console);
+    assert_eq!(event.message, "retrying 3 NaN Object");
+    assert_eq!(
+        event.location.as_deref(),
+        Some("https://example.com/app.js:42")
+    );
+    assert_eq!(event.timestamp.timestamp_millis(), 1_700_000_000_123);
+}
+
+#[test]
+fn test_console_levels() {
+    let level = |kind| PageEvent::from_console(&console_event(kind, json!([]))).level;
+
+    assert_eq!(level("error"), PageEventLevel::Error);
+    assert_eq!(level("assert"), PageEventLevel::Error);
+    assert_eq!(level("log"), PageEventLevel::Info);
+    assert_eq!(level("info"), PageEventLevel::Info);
+    assert_eq!(level("debug"), PageEventLevel::Debug);
+}
+
+#[test]
+fn test_uncaught_exception_becomes_page_error() {
+    let event: EventExceptionThrown = serde_json::from_value(json!({
+        "timestamp": 1_700_000_000_000.0,
+        "exceptionDetails": {
+            "exceptionId": 1,
+            "text": "Uncaught",
+            "lineNumber": 9,
+            "columnNumber": 14,
+            "url": "https://example.com/vendor.js",
+            "exception": {
+                "type": "object",
+                "subtype": "error",
+                "className": "TypeError",
+                "description": "TypeError: widget.mount is not a function\n    at https://example.com/vendor.js:10:15"
+            }
+        }
+    }))
+    .unwrap();
+    let event = PageEvent::from_exception(&event);
+
+    assert_eq!(event.level, PageEventLevel::Error);
+    assert_eq!(event.source, PageEventSource::Exception);
+    assert_eq!(
+        event.message,
+        "Uncaught TypeError: widget.mount is not a function"
+    );
+    assert_eq!(
+        event.location.as_deref(),
+        Some("https://example.com/vendor.js:10")
+    );
+}
@@ -0,0 +1,110 @@
+use tempfile::TempDir;
+
+use crate::capture::canonical_url::{canonical_url, display_url};
+use crate::capture::proxy::recorder::RequestRecorder;
+use crate::capture::proxy::records::RequestRecord;
+use crate::storage::Storage;
+use crate::test_utils::test_helpers::{create_multi_request_snapshot, create_test_record};
+
+/// A URL with an IDN host, an emoji in the path and a non-ASCII query value
+const CANONICAL: &str = "https://xn--bcher-kva.example/%F0%9F%98%80/suche?q=Z%C3%BCrich&n=1";
+const READABLE: &str = "https://bücher.example/😀/suche?q=Zürich&n=1";
+
+fn record(url: &str) -> RequestRecord {
+    create_test_record("GET", url, 200, &[], b"ok")
+}
+
+#[test]
+fn test_spellings_of_an_idn_url_share_one_canonical_form() {
+    for spelling in [
+        READABLE,
+        "https://Bücher.EXAMPLE/😀/suche?q=Zürich&n=1",
+        "https://xn--bcher-kva.example/%f0%9f%98%80/suche?q=Z%c3%bcrich&n=1",
+        "https://xn--bcher-kva.example:443/%F0%9F%98%80/suche?q=Z%C3%BCrich&n=1",
+        CANONICAL,
+    ] {
+        assert_eq!(canonical_url(spelling), CANONICAL, "{}", spelling);
+    }
+}
+
+#[test]
+fn test_canonical_url_normalizes_escapes() {
+    // Unreserved characters are decoded, reserved ones stay escaped in uppercase
+    assert_eq!(
+        canonical_url("https://example.com/%7Euser/a%2db?x=%41"),
+        "https://example.com/~user/a-b?x=A"
+    );
+    assert_eq!(
+        canonical_url("https://example.com/a%2fb?q=a%2bb%26c"),
+        "https://example.com/a%2Fb?q=a%2Bb%26c"
+    );
+    // Raw and encoded query values match; a stray % is left alone
+    assert_eq!(
+        canonical_url("https://example.com/search?q=café latte"),
+        canonical_url("https://example.com/search?q=caf%C3%A9%20latte")
+    );
+    assert_eq!(
+        canonical_url("https://example.com/100%?x=%zz"),
+        "https://example.com/100%?x=%zz"
+    );
+    assert_eq!(canonical_url("https://example.com"), "https://example.com/");
+}
+
+#[test]
+fn test_canonical_url_keeps_other_urls_as_they_are() {
+    for url in [
+        "data:text/plain,h%c3%a9",
+        "blob:https://example.com/4f1c",
+        "example.com:443",
+        "not a url",
+    ] {
+        assert_eq!(canonical_url(url), url);
+    }
+}
+
+#[test]
+fn test_display_url_is_readable() {
+    assert_eq!(display_url(CANONICAL), READABLE);
+    // ASCII escapes stay, so the readable form means the same
+    assert_eq!(
+        display_url("https://example.com/a%20b?q=%E2%9C%93%26"),
+        "https://example.com/a%20b?q=✓%26"
+    );
+    // Nothing to show differently, or escapes that aren't UTF-8
+    assert_eq!(display_url("https://example.com"), "https://example.com");
+    assert_eq!(
+        display_url("https://example.com/caf%E9"),
+        "https://example.com/caf%E9"
+    );
+    assert_eq!(canonical_url(&display_url(CANONICAL)), CANONICAL);
+}
+
+#[tokio::test]
+async fn test_recorder_stores_canonical_urls() {
+    let recorder = RequestRecorder::new();
+    recorder.record_request(record(READABLE)).await;
+    let mut connect = record("https://xn--bcher-kva.example:443");
+    connect.method = "CONNECT".to_string();
+    recorder.record_request(connect).await;
+
+    let records = recorder.get_records().await;
+    assert_eq!(records[0].url, CANONICAL);
+    // CONNECT targets keep their port
+    assert_eq!(records[1].url, "https://xn--bcher-kva.example:443");
+}
+
+#[tokio::test]
+async fn test_loading_canonicalizes_unicode_urls_of_older_snapshots() {
+    let temp_dir = TempDir::new().unwrap();
+    let storage = Storage::new(temp_dir.path().to_path_buf());
+    let mut snapshot = create_multi_request_snapshot("idn");
+    snapshot.requests.push(record(READABLE));
+    storage.save_snapshot(snapshot).await.unwrap();
+
+    let loaded = storage.load_snapshot("idn").await.unwrap();
+    let urls: Vec<&str> = loaded.requests.iter().map(|r| r.url.as_str()).collect();
+    assert!(urls.contains(&CANONICAL), "{:?}", urls);
+    assert!(!urls.contains(&READABLE));
+    // ASCII URLs are left as they were saved
+    assert!(urls.contains(&"https://example.com/"));
+}
@@ -0,0 +1,139 @@
+use crate::capture::environment::*;
+
+#[test]
+fn test_viewport_parse() {
+    assert_eq!(
+        Viewport::parse("1440x900").unwrap(),
+        Viewport {
+            width: 1440,
+            height: 900
+        }
+    );
+    assert_eq!(Viewport::parse("375X812").unwrap().to_string(), "375x812");
+
+    assert!(Viewport::parse("1440").is_err());
+    assert!(Viewport::parse("0x900").is_err());
+    assert!(Viewport::parse("1440x-1").is_err());
+    assert!(Viewport::parse("20000x900").is_err());
+}
+
+#[test]
+fn test_parse_locale() {
+    assert_eq!(parse_locale("fr-FR").unwrap(), "fr-FR");
+    assert_eq!(parse_locale("pt_BR").unwrap(), "pt-BR");
+    assert_eq!(parse_locale("zh-Hant-TW").unwrap(), "zh-Hant-TW");
+    assert_eq!(parse_locale("de").unwrap(), "de");
+
+    assert!(parse_locale("").is_err());
+    assert!(parse_locale("french").is_err());
+    assert!(parse_locale("fr-").is_err());
+    assert!(parse_locale("fr FR").is_err());
+}
+
+#[test]
+fn test_parse_timezone() {
+    assert_eq!(parse_timezone("Europe/Paris").unwrap(), "Europe/Paris");
+    assert_eq!(parse_timezone("UTC").unwrap(), "UTC");
+
+    assert!(parse_timezone("Europe/Atlantis").is_err());
+    assert!(parse_timezone("+02:00").is_err());
+}
+
+#[test]
+fn test_environment_from_args() {
+    let environment =
+        CaptureEnvironment::from_args(Some("fr_FR"), Some("Europe/Paris"), Some("1440x900"))
+            .unwrap();
+
+    assert_eq!(environment.locale.as_deref(), Some("fr-FR"));
+    assert_eq!(environment.timezone.as_deref(), Some("Europe/Paris"));
+    assert_eq!(environment.viewport.unwrap().width, 1440);
+    assert!(!environment.is_default());
+
+    assert!(CaptureEnvironment::from_args(None, None, None)
+        .unwrap()
+        .is_default());
+
+    let error = CaptureEnvironment::from_args(None, Some("Mars/Olympus"), None).unwrap_err();
+    assert!(matches!(error, crate::error::WebMockError::Config(_)));
+}
+
+#[test]
+fn test_accept_language() {
+    let environment = CaptureEnvironment::from_args(Some("fr-FR"), None, None).unwrap();
+    assert_eq!(
+        environment.accept_language().as_deref(),
+        Some("fr-FR,fr;q=0.9")
+    );
+
+    let environment = CaptureEnvironment::from_args(Some("de"), None, None).unwrap();
+    assert_eq!(environment.accept_language().as_deref(), Some("de"));
+
+    assert!(CaptureEnvironment::default().accept_language().is_none());
+}
+
+#[test]
+fn test_device_preset_lookup() {
+    let preset = DevicePreset::find("iPhone-13").unwrap();
+    assert_eq!(preset.name, "iphone-13");
+    assert!(preset.user_agent.contains("iPhone"));
+    assert!(preset.touch);
+
+    for preset in DEVICE_PRESETS {
+        assert_eq!(DevicePreset::find(preset.name).unwrap(), preset);
+    }
+}
+
+#[test]
+fn test_unknown_device_lists_presets() {
+    let error = DevicePreset::find("nokia-3310").unwrap_err();
+    let message = error.to_string();
+    assert!(message.contains("nokia-3310"));
+    assert!(message.contains("iphone-13, pixel-7, ipad"));
+}
+
+#[test]
+fn test_environment_with_device() {
+    let environment = CaptureEnvironment::default()
+        .with_device(Some("pixel-7"), None)
+        .unwrap();
+    assert_eq!(environment.device.as_deref(), Some("pixel-7"));
+    assert!(environment
+        .user_agent
+        .as_deref()
+        .unwrap()
+        .contains("Pixel 7"));
+    assert_eq!(environment.viewport.unwrap().to_string(), "412x915");
+    assert_eq!(
+        environment.device_preset().unwrap().device_scale_factor,
+        2.625
+    );
+    assert!(!environment.is_default());
+
+    // Explicit options win over the preset
+    let environment = CaptureEnvironment::from_args(None, None, Some("1024x768"))
+        .unwrap()
+        .with_device(Some("ipad"), Some("CustomAgent/1.0"))
+        .unwrap();
+    assert_eq!(environment.device.as_deref(), Some("ipad"));
+    assert_eq!(environment.user_agent.as_deref(), Some("CustomAgent/1.0"));
+    assert_eq!(environment.viewport.unwrap().to_string(), "1024x768");
+
+    let environment = CaptureEnvironment::default()
+        .with_device(None, Some("CustomAgent/1.0"))
+        .unwrap();
+    assert!(environment.device_preset().is_none());
+    assert!(environment.viewport.is_none());
+}
+
+#[test]
+fn test_parse_user_agent() {
+    assert_eq!(
+        parse_user_agent(" Mozilla/5.0 (X11) ").unwrap(),
+        "Mozilla/5.0 (X11)"
+    );
+
+    assert!(parse_user_agent("").is_err());
+    assert!(parse_user_agent("   ").is_err());
+    assert!(parse_user_agent("agent\r\nX-Injected: 1").is_err());
+}
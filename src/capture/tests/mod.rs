@@ -1,4 +1,13 @@
+mod abort_tests;
 mod browser_tests;
+mod canonical_url_tests;
+mod environment_tests;
+mod provenance_tests;
 mod proxy_tests;
+mod registry_tests;
 mod resource_manager_tests;
 mod session_tests;
+mod settle_tests;
+mod sitemap_tests;
+mod variant_tests;
+mod warnings_tests;
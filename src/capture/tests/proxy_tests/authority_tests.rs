@@ -0,0 +1,30 @@
+use crate::capture::proxy::authority::{origin, split_authority};
+
+#[test]
+fn test_split_authority() {
+    assert_eq!(
+        split_authority("example.com:8443"),
+        ("example.com".to_string(), 8443)
+    );
+    assert_eq!(
+        split_authority("example.com"),
+        ("example.com".to_string(), 443)
+    );
+    assert_eq!(split_authority("[::1]:9443"), ("[::1]".to_string(), 9443));
+    assert_eq!(split_authority("[::1]"), ("[::1]".to_string(), 443));
+    assert_eq!(
+        split_authority("example.com:https"),
+        ("example.com".to_string(), 443)
+    );
+}
+
+#[test]
+fn test_origin_leaves_out_default_ports() {
+    assert_eq!(origin("https", "example.com", 443), "https://example.com");
+    assert_eq!(
+        origin("https", "example.com", 8443),
+        "https://example.com:8443"
+    );
+    assert_eq!(origin("http", "example.com", 80), "http://example.com");
+    assert_eq!(origin("http", "example.com", 443), "http://example.com:443");
+}
@@ -0,0 +1,74 @@
+use crate::capture::proxy::certificate::{WebMockCertificate, CERT_FILE, KEY_FILE};
+use rustls::ServerConfig;
+use tempfile::TempDir;
+
+#[test]
+fn test_certificate_is_generated_once_and_reused() {
+    let temp_dir = TempDir::new().unwrap();
+    let dir = temp_dir.path().join("ca");
+
+    let first = WebMockCertificate::load_or_generate(&dir).unwrap();
+    assert!(dir.join(CERT_FILE).exists());
+    assert!(dir.join(KEY_FILE).exists());
+    assert_eq!(WebMockCertificate::cert_path(&dir), dir.join(CERT_FILE));
+
+    let second = WebMockCertificate::load_or_generate(&dir).unwrap();
+    assert_eq!(first.cert_pem(), second.cert_pem());
+}
+
+#[test]
+fn test_certificate_builds_server_config() {
+    let certificate = WebMockCertificate::generate().unwrap();
+
+    let config = ServerConfig::builder()
+        .with_no_client_auth()
+        .with_single_cert(
+            certificate.cert_chain().unwrap(),
+            certificate.private_key().unwrap(),
+        );
+    assert!(config.is_ok());
+}
+
+#[cfg(unix)]
+#[test]
+fn test_private_key_is_not_world_readable() {
+    use std::os::unix::fs::PermissionsExt;
+
+    let temp_dir = TempDir::new().unwrap();
+    let dir = temp_dir.path().join("ca");
+    WebMockCertificate::load_or_generate(&dir).unwrap();
+
+    let mode = std::fs::metadata(dir.join(KEY_FILE))
+        .unwrap()
+        .permissions()
+        .mode();
+    assert_eq!(mode & 0o077, 0);
+}
+
+#[test]
+fn test_damaged_certificate_cache_is_reported() {
+    let temp_dir = TempDir::new().unwrap();
+    std::fs::write(temp_dir.path().join(CERT_FILE), "not a certificate").unwrap();
+    std::fs::write(temp_dir.path().join(KEY_FILE), "not a key").unwrap();
+
+    let error = WebMockCertificate::load_or_generate(temp_dir.path())
+        .unwrap_err()
+        .to_string();
+    assert!(error.contains("to regenerate the certificate"), "{}", error);
+}
+
+#[test]
+fn test_certificate_coverage_follows_wildcard_rules() {
+    assert!(WebMockCertificate::covers("localhost"));
+    assert!(WebMockCertificate::covers("example.com"));
+    assert!(WebMockCertificate::covers("Example.COM."));
+    assert!(WebMockCertificate::covers("maps.google.com"));
+    // A wildcard matches a single label only
+    assert!(!WebMockCertificate::covers("fonts.gstatic.com"));
+    assert!(!WebMockCertificate::covers("com"));
+    assert!(!WebMockCertificate::covers("shop.example"));
+
+    let certificate = WebMockCertificate::for_host("fonts.gstatic.com").unwrap();
+    assert_eq!(certificate.cert_chain().unwrap().len(), 1);
+    certificate.private_key().unwrap();
+}
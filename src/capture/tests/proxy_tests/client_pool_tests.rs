@@ -0,0 +1,135 @@
+use crate::capture::proxy::client_pool::{HttpClientPool, DEFAULT_MAX_CONNECTIONS_PER_HOST};
+use crate::capture::proxy::server::utils::forward_request_with_pool;
+use hyper::{Method, Version};
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpListener;
+
+/// Connections a keep-alive test server has open now and at most
+#[derive(Default)]
+struct ConnectionCounter {
+    open: AtomicUsize,
+    peak: AtomicUsize,
+    accepted: AtomicUsize,
+}
+
+/// Serve slow keep-alive responses on a local port, counting concurrent connections
+async fn start_counting_server() -> (String, Arc<ConnectionCounter>) {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let base = format!("http://{}", listener.local_addr().unwrap());
+    let counter = Arc::new(ConnectionCounter::default());
+
+    let server_counter = Arc::clone(&counter);
+    tokio::spawn(async move {
+        while let Ok((mut stream, _)) = listener.accept().await {
+            let counter = Arc::clone(&server_counter);
+            counter.accepted.fetch_add(1, Ordering::SeqCst);
+            let open = counter.open.fetch_add(1, Ordering::SeqCst) + 1;
+            counter.peak.fetch_max(open, Ordering::SeqCst);
+
+            tokio::spawn(async move {
+                let mut request = Vec::new();
+                let mut buf = [0u8; 1024];
+                loop {
+                    match stream.read(&mut buf).await {
+                        Ok(0) | Err(_) => break,
+                        Ok(n) => request.extend_from_slice(&buf[..n]),
+                    }
+                    // Answer each complete bodyless request on the same connection
+                    while let Some(end) = request.windows(4).position(|w| w == b"\r\n\r\n") {
+                        request.drain(..end + 4);
+                        tokio::time::sleep(Duration::from_millis(30)).await;
+                        let response = b"HTTP/1.1 200 OK\r\nContent-Length: 2\r\n\r\nok";
+                        if stream.write_all(response).await.is_err() {
+                            break;
+                        }
+                    }
+                }
+                counter.open.fetch_sub(1, Ordering::SeqCst);
+            });
+        }
+    });
+
+    (base, counter)
+}
+
+async fn fetch_all(pool: &Arc<HttpClientPool>, base: &str, count: usize) {
+    let requests = (0..count).map(|i| {
+        let pool = Arc::clone(pool);
+        let url = format!("{}/image-{}.png", base, i);
+        async move {
+            forward_request_with_pool(
+                Method::GET,
+                &url,
+                HashMap::new(),
+                Vec::new(),
+                Version::HTTP_11,
+                pool,
+            )
+            .await
+        }
+    });
+    for response in futures::future::join_all(requests).await {
+        assert_eq!(response.unwrap().status, 200);
+    }
+}
+
+#[test]
+fn test_default_limit_matches_browsers() {
+    assert_eq!(DEFAULT_MAX_CONNECTIONS_PER_HOST, 6);
+    assert_eq!(HttpClientPool::new().max_connections_per_host(), 6);
+    assert_eq!(
+        HttpClientPool::new()
+            .with_max_connections_per_host(0)
+            .max_connections_per_host(),
+        1
+    );
+}
+
+#[tokio::test]
+async fn test_per_host_limit_caps_concurrent_connections() {
+    let (base, counter) = start_counting_server().await;
+    let pool = Arc::new(HttpClientPool::new().with_max_connections_per_host(3));
+
+    fetch_all(&pool, &base, 24).await;
+
+    assert!(counter.peak.load(Ordering::SeqCst) <= 3);
+    let stats = pool.connection_stats();
+    assert_eq!(stats.opened, counter.accepted.load(Ordering::SeqCst) as u64);
+    assert!(stats.opened <= 3);
+    assert_eq!(stats.opened + stats.reused, 24);
+    assert!(stats.queued > 0);
+    assert!(stats.queued_time > Duration::ZERO);
+}
+
+#[tokio::test]
+async fn test_global_limit_caps_connections_below_the_host_limit() {
+    let (base, counter) = start_counting_server().await;
+    let pool = Arc::new(HttpClientPool::new().with_max_connections(2));
+
+    fetch_all(&pool, &base, 12).await;
+
+    assert!(counter.peak.load(Ordering::SeqCst) <= 2);
+    assert!(pool.connection_stats().opened <= 2);
+}
+
+#[tokio::test]
+async fn test_clear_closes_idle_connections() {
+    let (base, counter) = start_counting_server().await;
+    let pool = Arc::new(HttpClientPool::new());
+
+    fetch_all(&pool, &base, 4).await;
+    assert!(counter.open.load(Ordering::SeqCst) > 0);
+
+    pool.clear().await;
+    let closed = tokio::time::timeout(Duration::from_secs(2), async {
+        while counter.open.load(Ordering::SeqCst) > 0 {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+    })
+    .await;
+    assert!(closed.is_ok(), "idle connections stayed open after clear()");
+}
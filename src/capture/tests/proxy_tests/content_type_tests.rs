@@ -186,3 +186,28 @@ fn test_response_record_detect_content_type_from_body() {
     // Should detect from body content
     assert_eq!(content_type, "text/html");
 }
+
+#[test]
+fn test_response_record_sniffs_blank_content_type() {
+    let mut headers = HashMap::new();
+    headers.insert("content-type".to_string(), " ".to_string());
+
+    // Binary signatures beat a misleading extension
+    let content_type = ResponseRecord::detect_content_type(
+        &headers,
+        b"GIF89a\x01\x00\x01\x00",
+        Some("https://example.com/pixel.php"),
+    );
+    assert_eq!(content_type, "image/gif");
+
+    let content_type = ResponseRecord::detect_content_type(
+        &headers,
+        b"[1, 2, 3]",
+        Some("https://example.com/api/items"),
+    );
+    assert_eq!(content_type, "application/json");
+
+    assert!(!ContentTypeHelper::is_usable("unknown"));
+    assert!(ContentTypeHelper::is_usable("text/html; charset=utf-8"));
+    assert_eq!(ContentTypeHelper::sniff(b"\x00\x01\x02\xFF", None), None);
+}
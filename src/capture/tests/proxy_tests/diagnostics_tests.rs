@@ -0,0 +1,288 @@
+use crate::capture::proxy::diagnostics::{
+    CaptureDiagnostics, DiagnosticKind, DiagnosticsLog, MAX_RECENT_ERRORS,
+};
+use crate::capture::proxy::recorder::RequestRecorder;
+use crate::capture::proxy::server::HttpProxy;
+use hyper::HeaderMap;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+
+/// Wait for the proxy's diagnostics to satisfy `done`, which its connection tasks update on their own time
+async fn wait_for(
+    proxy: &HttpProxy,
+    done: impl Fn(&CaptureDiagnostics) -> bool,
+) -> CaptureDiagnostics {
+    for _ in 0..100 {
+        let diagnostics = proxy.capture_diagnostics();
+        if done(&diagnostics) {
+            return diagnostics;
+        }
+        tokio::time::sleep(Duration::from_millis(20)).await;
+    }
+    panic!(
+        "Diagnostics never got there: {:?}",
+        proxy.capture_diagnostics()
+    );
+}
+
+/// A local port nothing listens on
+async fn closed_port() -> u16 {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    listener.local_addr().unwrap().port()
+}
+
+#[test]
+fn test_log_counts_failures_by_host() {
+    let log = DiagnosticsLog::new();
+    assert!(log.diagnostics().is_empty());
+
+    log.connection_accepted();
+    log.connection_accepted();
+    log.tls_handshake_failed("Pinned.Example.com", "received fatal alert: UnknownCA");
+    log.tls_handshake_failed("pinned.example.com", "received fatal alert: UnknownCA");
+    log.upstream_connect_failed("down.example.com", "connection refused");
+    log.request_unrecorded("GET https://example.com/slow", "client went away");
+
+    let diagnostics = log.diagnostics();
+    assert_eq!(diagnostics.accepted_connections, 2);
+    assert_eq!(
+        diagnostics.tls_handshake_failures.get("pinned.example.com"),
+        Some(&2)
+    );
+    assert_eq!(
+        diagnostics
+            .upstream_connect_failures
+            .get("down.example.com"),
+        Some(&1)
+    );
+    assert_eq!(diagnostics.unrecorded_requests, 1);
+    assert_eq!(diagnostics.failures(), 4);
+
+    let kinds: Vec<DiagnosticKind> = diagnostics
+        .recent_errors
+        .iter()
+        .map(|event| event.kind)
+        .collect();
+    assert_eq!(
+        kinds,
+        vec![
+            DiagnosticKind::TlsHandshake,
+            DiagnosticKind::TlsHandshake,
+            DiagnosticKind::UpstreamConnect,
+            DiagnosticKind::UnrecordedRequest,
+        ]
+    );
+    assert_eq!(
+        diagnostics.summary_lines(),
+        vec![
+            "TLS handshake failures: 2 (pinned.example.com 2)",
+            "Upstream connect failures: 1 (down.example.com 1)",
+            "Requests lost before recording: 1",
+        ]
+    );
+
+    log.clear();
+    assert!(log.diagnostics().is_empty());
+}
+
+#[test]
+fn test_log_keeps_only_the_latest_errors() {
+    let log = DiagnosticsLog::new();
+    for i in 0..MAX_RECENT_ERRORS + 5 {
+        log.upstream_connect_failed(&format!("host-{}.example", i), "connection refused");
+    }
+
+    let diagnostics = log.diagnostics();
+    assert_eq!(diagnostics.recent_errors.len(), MAX_RECENT_ERRORS);
+    assert_eq!(diagnostics.recent_errors[0].target, "host-5.example");
+    // The counters still cover every failure
+    assert_eq!(diagnostics.failures(), (MAX_RECENT_ERRORS + 5) as u64);
+}
+
+#[test]
+fn test_abandoned_requests_count_as_unrecorded() {
+    let recorder = RequestRecorder::new();
+
+    let answered = recorder.begin_request(0, "GET", "https://example.com/", &HeaderMap::new());
+    answered.finish();
+    assert!(recorder.capture_diagnostics().is_empty());
+
+    let abandoned = recorder.begin_request(1, "POST", "https://example.com/api", &HeaderMap::new());
+    drop(abandoned);
+    let diagnostics = recorder.capture_diagnostics();
+    assert_eq!(diagnostics.unrecorded_requests, 1);
+    assert_eq!(
+        diagnostics.recent_errors[0].target,
+        "POST https://example.com/api"
+    );
+    assert!(recorder.in_flight().is_empty());
+}
+
+#[tokio::test]
+async fn test_proxy_counts_upstream_connect_failures() {
+    let port = closed_port().await;
+    let proxy = HttpProxy::start(0).await.unwrap();
+    let client = reqwest::Client::builder()
+        .proxy(reqwest::Proxy::http(format!("http://127.0.0.1:{}", proxy.get_port())).unwrap())
+        .build()
+        .unwrap();
+
+    // Not retried, so the failure is counted once
+    let response = client
+        .post(format!("http://127.0.0.1:{}/unreachable", port))
+        .body("payload")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 502);
+
+    let diagnostics = wait_for(&proxy, |d| !d.upstream_connect_failures.is_empty()).await;
+    assert!(diagnostics.accepted_connections >= 1);
+    assert_eq!(
+        diagnostics.upstream_connect_failures.get("127.0.0.1"),
+        Some(&1)
+    );
+    let event = diagnostics.recent_errors.last().unwrap();
+    assert_eq!(event.kind, DiagnosticKind::UpstreamConnect);
+    assert!(
+        event.error.starts_with("connection refused"),
+        "{}",
+        event.error
+    );
+    // The failed request still has its record
+    assert_eq!(diagnostics.unrecorded_requests, 0);
+    assert_eq!(proxy.get_records().await.len(), 1);
+
+    proxy.stop().await.unwrap();
+}
+
+#[tokio::test]
+async fn test_proxy_counts_malformed_requests() {
+    let proxy = HttpProxy::start(0).await.unwrap();
+
+    let mut stream = TcpStream::connect(("127.0.0.1", proxy.get_port()))
+        .await
+        .unwrap();
+    stream
+        .write_all(b"NOT A REQUEST\x01\r\n\r\n")
+        .await
+        .unwrap();
+    let mut answer = Vec::new();
+    let _ = tokio::time::timeout(Duration::from_secs(5), stream.read_to_end(&mut answer)).await;
+
+    let diagnostics = wait_for(&proxy, |d| d.unrecorded_requests > 0).await;
+    assert_eq!(diagnostics.accepted_connections, 1);
+    assert_eq!(diagnostics.unrecorded_requests, 1);
+    assert!(diagnostics.recent_errors[0]
+        .target
+        .starts_with("request from 127.0.0.1:"));
+    assert!(proxy.get_records().await.is_empty());
+
+    proxy.stop().await.unwrap();
+}
+
+#[tokio::test]
+async fn test_proxy_counts_requests_abandoned_by_the_client() {
+    // An origin that accepts the request and never answers
+    let upstream = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let upstream_port = upstream.local_addr().unwrap().port();
+    let (accepted_tx, accepted_rx) = tokio::sync::oneshot::channel();
+    tokio::spawn(async move {
+        let (stream, _) = upstream.accept().await.unwrap();
+        let _ = accepted_tx.send(());
+        tokio::time::sleep(Duration::from_secs(30)).await;
+        drop(stream);
+    });
+
+    let proxy = HttpProxy::start(0).await.unwrap();
+    let mut client = TcpStream::connect(("127.0.0.1", proxy.get_port()))
+        .await
+        .unwrap();
+    let url = format!("http://127.0.0.1:{}/hang", upstream_port);
+    client
+        .write_all(
+            format!(
+                "GET {} HTTP/1.1\r\nHost: 127.0.0.1:{}\r\n\r\n",
+                url, upstream_port
+            )
+            .as_bytes(),
+        )
+        .await
+        .unwrap();
+    tokio::time::timeout(Duration::from_secs(5), accepted_rx)
+        .await
+        .expect("The request never reached the origin")
+        .unwrap();
+    drop(client);
+
+    let diagnostics = wait_for(&proxy, |d| d.unrecorded_requests > 0).await;
+    let event = diagnostics.recent_errors.last().unwrap();
+    assert_eq!(event.kind, DiagnosticKind::UnrecordedRequest);
+    assert_eq!(event.target, format!("GET {}", url));
+    assert!(proxy.get_records().await.is_empty());
+    assert!(proxy.in_flight().is_empty());
+
+    proxy.stop().await.unwrap();
+}
+
+#[tokio::test]
+async fn test_proxy_counts_tls_handshake_failures_by_sni() {
+    let proxy = HttpProxy::start(0).await.unwrap();
+
+    let mut stream = TcpStream::connect(("127.0.0.1", proxy.get_port()))
+        .await
+        .unwrap();
+    stream
+        .write_all(b"CONNECT connect.example:443 HTTP/1.1\r\nHost: connect.example:443\r\n\r\n")
+        .await
+        .unwrap();
+    let mut head = Vec::new();
+    while !head.ends_with(b"\r\n\r\n") {
+        let mut byte = [0u8; 1];
+        stream.read_exact(&mut byte).await.unwrap();
+        head.push(byte[0]);
+    }
+    assert!(head.starts_with(b"HTTP/1.1 200"));
+
+    // A client that trusts nothing refuses the capture certificate
+    let config = rustls::ClientConfig::builder()
+        .with_root_certificates(rustls::RootCertStore::empty())
+        .with_no_client_auth();
+    let connector = tokio_rustls::TlsConnector::from(Arc::new(config));
+    let server_name = rustls::pki_types::ServerName::try_from("sni.example".to_string()).unwrap();
+    assert!(connector.connect(server_name, stream).await.is_err());
+
+    let diagnostics = wait_for(&proxy, |d| !d.tls_handshake_failures.is_empty()).await;
+    assert_eq!(
+        diagnostics.tls_handshake_failures.get("sni.example"),
+        Some(&1)
+    );
+    assert!(!diagnostics
+        .tls_handshake_failures
+        .contains_key("connect.example"));
+
+    // A client that doesn't speak TLS at all is put down to the CONNECT host
+    let mut stream = TcpStream::connect(("127.0.0.1", proxy.get_port()))
+        .await
+        .unwrap();
+    stream
+        .write_all(b"CONNECT plain.example:443 HTTP/1.1\r\nHost: plain.example:443\r\n\r\n")
+        .await
+        .unwrap();
+    let mut response = [0u8; 256];
+    let _ = stream.read(&mut response).await.unwrap();
+    stream
+        .write_all(b"GET / HTTP/1.1\r\nHost: plain.example\r\n\r\n")
+        .await
+        .unwrap();
+
+    let diagnostics = wait_for(&proxy, |d| {
+        d.tls_handshake_failures.contains_key("plain.example")
+    })
+    .await;
+    assert_eq!(diagnostics.failures(), 2);
+
+    proxy.stop().await.unwrap();
+}
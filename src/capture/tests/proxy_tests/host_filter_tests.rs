@@ -0,0 +1,40 @@
+use crate::capture::proxy::HostAllowlist;
+
+#[test]
+fn test_allowlist_matches_hosts_and_globs() {
+    let allowlist = HostAllowlist::parse("app.example.com, *.cdn.example.com").unwrap();
+
+    assert!(allowlist.allows("app.example.com"));
+    assert!(allowlist.allows("APP.Example.com"));
+    assert!(allowlist.allows("app.example.com."));
+    assert!(allowlist.allows("img.cdn.example.com"));
+    assert!(!allowlist.allows("cdn.example.com"));
+    assert!(!allowlist.allows("example.com"));
+    assert!(!allowlist.allows("app.example.com.evil.test"));
+    assert_eq!(
+        allowlist.patterns().collect::<Vec<_>>(),
+        ["app.example.com", "*.cdn.example.com"]
+    );
+}
+
+#[test]
+fn test_allowlist_allows_page_hosts_once() {
+    let mut allowlist = HostAllowlist::parse("api.example.com").unwrap();
+    allowlist.allow("www.example.com");
+    allowlist.allow("api.example.com");
+    allowlist.allow("[::1]");
+
+    assert!(allowlist.allows("www.example.com"));
+    assert!(allowlist.allows("[::1]"));
+    assert_eq!(allowlist.patterns().count(), 3);
+}
+
+#[test]
+fn test_allowlist_rejects_invalid_entries() {
+    assert!(HostAllowlist::parse("").is_err());
+    assert!(HostAllowlist::parse(" , ").is_err());
+    assert!(HostAllowlist::parse("https://app.example.com").is_err());
+    assert!(HostAllowlist::parse("app.example.com:8443").is_err());
+    assert!(HostAllowlist::parse("app.example.com/path").is_err());
+    assert!(HostAllowlist::parse("[unclosed.example.com").is_err());
+}
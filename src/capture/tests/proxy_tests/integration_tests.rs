@@ -18,6 +18,11 @@ fn create_test_response(status: u16, content_type: &str, body: Vec<u8>) -> Respo
         headers,
         body,
         content_type: content_type.to_string(),
+        failure: None,
+        set_cookie: Vec::new(),
+        http_version: None,
+        reason: None,
+        informational: Vec::new(),
     }
 }
 
@@ -52,6 +57,150 @@ async fn test_request_recorder() {
     assert!(records.is_empty());
 }
 
+#[tokio::test]
+async fn test_recorder_orders_records_by_arrival_not_wall_clock() {
+    let recorder = RequestRecorder::new();
+    let record = |path: &str, offset_ms: u64, finished_ms: u64, clock_skew_secs: i64| {
+        let mut record = RequestRecord::new(
+            "GET".to_string(),
+            format!("https://example.com/{}", path),
+            create_test_headers(),
+            None,
+            create_test_response(200, "text/plain", Vec::new()),
+        )
+        .with_timing(offset_ms, finished_ms);
+        // A wall clock stepped back between requests
+        record.timestamp -= chrono::Duration::seconds(clock_skew_secs);
+        record
+    };
+
+    // Pushed as their responses finish: the slow page last
+    recorder
+        .record_request(record("style.css", 20, 30, 60))
+        .await;
+    recorder
+        .record_request(record("script.js", 25, 40, 120))
+        .await;
+    recorder
+        .record_request(record("index.html", 10, 90, 0))
+        .await;
+
+    let records = recorder.get_records().await;
+    let urls: Vec<&str> = records.iter().map(|r| r.url.as_str()).collect();
+    assert_eq!(
+        urls,
+        vec![
+            "https://example.com/index.html",
+            "https://example.com/style.css",
+            "https://example.com/script.js",
+        ]
+    );
+    assert_eq!(records[0].timing.duration_ms, Some(80));
+
+    // Records built without timing are timed when recorded
+    recorder
+        .record_request(RequestRecord::new(
+            "CONNECT".to_string(),
+            "example.com:443".to_string(),
+            HashMap::new(),
+            None,
+            create_test_response(200, "text/plain", Vec::new()),
+        ))
+        .await;
+    let records = recorder.take_records().await;
+    assert_eq!(records.len(), 4);
+    assert!(records.iter().all(|r| r.timing.offset_ms.is_some()));
+}
+
+#[tokio::test]
+async fn test_recorder_keys_records_on_vary_headers() {
+    let recorder = RequestRecorder::new();
+    let variant = |accept_encoding: Option<&str>, content_encoding: Option<&str>| {
+        let mut headers = create_test_headers();
+        if let Some(accept_encoding) = accept_encoding {
+            headers.insert("Accept-Encoding".to_string(), accept_encoding.to_string());
+        }
+        let mut response = create_test_response(200, "text/css", b"body{}".to_vec());
+        response.headers.insert(
+            "Vary".to_string(),
+            "Accept-Encoding, User-Agent, *".to_string(),
+        );
+        if let Some(content_encoding) = content_encoding {
+            response
+                .headers
+                .insert("Content-Encoding".to_string(), content_encoding.to_string());
+        }
+        RequestRecord::new(
+            "GET".to_string(),
+            "https://example.com/site.css".to_string(),
+            headers,
+            None,
+            response,
+        )
+    };
+
+    recorder
+        .record_request(variant(Some("gzip, br"), Some("gzip")))
+        .await;
+    recorder.record_request(variant(None, None)).await;
+    recorder
+        .record_request(RequestRecord::new(
+            "GET".to_string(),
+            "https://example.com/".to_string(),
+            create_test_headers(),
+            None,
+            create_test_response(200, "text/html", Vec::new()),
+        ))
+        .await;
+
+    let records = recorder.take_records().await;
+    let key = |record: &RequestRecord| {
+        record
+            .vary_key
+            .iter()
+            .map(|(name, value)| format!("{}={}", name, value))
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(
+        key(&records[0]),
+        ["accept-encoding=gzip, br", "user-agent=test-agent"]
+    );
+    assert_eq!(
+        key(&records[1]),
+        ["accept-encoding=", "user-agent=test-agent"]
+    );
+    // Responses without Vary aren't keyed
+    assert!(records[2].vary_key.is_empty());
+}
+
+#[tokio::test]
+async fn test_recorder_tags_records_with_the_current_variant() {
+    let recorder = RequestRecorder::new();
+    let record = |path: &str| {
+        RequestRecord::new(
+            "GET".to_string(),
+            format!("https://example.com{}", path),
+            create_test_headers(),
+            None,
+            create_test_response(200, "text/html", Vec::new()),
+        )
+    };
+
+    recorder.record_request(record("/")).await;
+    recorder.set_variant(Some("admin".to_string()));
+    recorder.record_request(record("/admin")).await;
+    recorder.set_variant(None);
+    recorder.record_request(record("/late")).await;
+
+    let variants: Vec<Option<String>> = recorder
+        .take_records()
+        .await
+        .into_iter()
+        .map(|record| record.variant)
+        .collect();
+    assert_eq!(variants, [None, Some("admin".to_string()), None]);
+}
+
 #[tokio::test]
 #[ignore = "slow test - requires network binding"]
 async fn test_http_proxy_lifecycle() {
@@ -68,3 +217,601 @@ async fn test_http_proxy_lifecycle() {
     // Test stopping the proxy
     proxy.stop().await.unwrap();
 }
+
+#[tokio::test]
+async fn test_proxy_records_unreachable_upstream_as_failure() {
+    let (proxy_port, closed_port) = {
+        let proxy = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let closed = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        (
+            proxy.local_addr().unwrap().port(),
+            closed.local_addr().unwrap().port(),
+        )
+    };
+    let proxy = HttpProxy::start(proxy_port).await.unwrap();
+
+    let client = reqwest::Client::builder()
+        .proxy(reqwest::Proxy::http(format!("http://127.0.0.1:{}", proxy_port)).unwrap())
+        .build()
+        .unwrap();
+    let response = client
+        .get(format!("http://127.0.0.1:{}/missing", closed_port))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 502);
+
+    let records = proxy.get_records().await;
+    assert_eq!(records.len(), 1);
+    let failure = records[0].response.failure.as_deref().unwrap();
+    assert!(
+        failure.starts_with("connection refused"),
+        "got: {}",
+        failure
+    );
+
+    proxy.stop().await.unwrap();
+}
+
+#[tokio::test]
+async fn test_multiple_set_cookie_headers_round_trip() {
+    use crate::storage::{Snapshot, Storage};
+    use wiremock::matchers::{method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    let upstream = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/login"))
+        .respond_with(
+            ResponseTemplate::new(200)
+                .append_header("set-cookie", "session=abc; Path=/; HttpOnly")
+                .append_header("set-cookie", "csrf=def; Path=/")
+                .append_header("set-cookie", "theme=dark; Max-Age=3600")
+                .set_body_string("ok"),
+        )
+        .mount(&upstream)
+        .await;
+
+    let proxy_port = {
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        listener.local_addr().unwrap().port()
+    };
+    let proxy = HttpProxy::start(proxy_port).await.unwrap();
+
+    let client = reqwest::Client::builder()
+        .proxy(reqwest::Proxy::http(format!("http://127.0.0.1:{}", proxy_port)).unwrap())
+        .build()
+        .unwrap();
+    let response = client
+        .post(format!("{}/login", upstream.uri()))
+        .send()
+        .await
+        .unwrap();
+
+    // The client sees every cookie during capture
+    assert_eq!(response.headers().get_all("set-cookie").iter().count(), 3);
+
+    let records = proxy.get_records().await;
+    proxy.stop().await.unwrap();
+    assert_eq!(records.len(), 1);
+    assert_eq!(
+        records[0].response.set_cookie,
+        vec![
+            "session=abc; Path=/; HttpOnly",
+            "csrf=def; Path=/",
+            "theme=dark; Max-Age=3600"
+        ]
+    );
+
+    // And every cookie survives storage
+    let temp_dir = tempfile::TempDir::new().unwrap();
+    let storage = Storage::new(temp_dir.path().to_path_buf());
+    let captured = records[0].response.set_cookie.clone();
+    storage
+        .save_snapshot(Snapshot {
+            name: "cookies".to_string(),
+            url: format!("{}/login", upstream.uri()),
+            created_at: chrono::Utc::now(),
+            requests: records,
+            ..Default::default()
+        })
+        .await
+        .unwrap();
+    let snapshot = storage.load_snapshot("cookies").await.unwrap();
+    assert_eq!(snapshot.requests[0].response.set_cookie, captured);
+}
+
+#[tokio::test]
+async fn test_proxy_records_upstream_http_version() {
+    use wiremock::matchers::{method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    let upstream = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/version"))
+        .respond_with(ResponseTemplate::new(200).set_body_string("ok"))
+        .mount(&upstream)
+        .await;
+
+    let proxy_port = {
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        listener.local_addr().unwrap().port()
+    };
+    let proxy = HttpProxy::start(proxy_port).await.unwrap();
+
+    let client = reqwest::Client::builder()
+        .proxy(reqwest::Proxy::http(format!("http://127.0.0.1:{}", proxy_port)).unwrap())
+        .build()
+        .unwrap();
+    let response = client
+        .get(format!("{}/version", upstream.uri()))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 200);
+
+    let records = proxy.get_records().await;
+    // Plain HTTP upstreams never produce TLS details
+    assert!(proxy.tls_details().is_empty());
+    proxy.stop().await.unwrap();
+
+    assert_eq!(records.len(), 1);
+    assert_eq!(
+        records[0].response.http_version.as_deref(),
+        Some("HTTP/1.1")
+    );
+}
+
+#[tokio::test]
+async fn test_proxy_reports_os_assigned_port() {
+    let proxy = HttpProxy::start(0).await.unwrap();
+    let port = proxy.get_port();
+    assert_ne!(port, 0);
+
+    // A second proxy gets a different port instead of failing to bind
+    let other = HttpProxy::start(0).await.unwrap();
+    assert_ne!(other.get_port(), port);
+
+    other.stop().await.unwrap();
+    proxy.stop().await.unwrap();
+}
+
+/// Open a CONNECT tunnel through the proxy and consume the proxy's answer
+async fn open_tunnel(proxy_port: u16, target: &str) -> tokio::net::TcpStream {
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    let mut stream = tokio::net::TcpStream::connect(("127.0.0.1", proxy_port))
+        .await
+        .unwrap();
+    stream
+        .write_all(format!("CONNECT {0} HTTP/1.1\r\nHost: {0}\r\n\r\n", target).as_bytes())
+        .await
+        .unwrap();
+
+    let mut head = Vec::new();
+    while !head.ends_with(b"\r\n\r\n") {
+        let mut byte = [0u8; 1];
+        stream.read_exact(&mut byte).await.unwrap();
+        head.push(byte[0]);
+    }
+    assert!(head.starts_with(b"HTTP/1.1 200"), "{:?}", head);
+    stream
+}
+
+#[tokio::test]
+async fn test_pinned_host_falls_back_to_blind_tunnel() {
+    use crate::capture::proxy::TunnelRecord;
+    use rustls::pki_types::ServerName;
+    use std::sync::Arc;
+    use std::time::Duration;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    // Echo server standing in for a certificate-pinned host
+    let echo = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let target = echo.local_addr().unwrap().to_string();
+    tokio::spawn(async move {
+        while let Ok((mut stream, _)) = echo.accept().await {
+            tokio::spawn(async move {
+                let (mut reader, mut writer) = stream.split();
+                let _ = tokio::io::copy(&mut reader, &mut writer).await;
+            });
+        }
+    });
+
+    let proxy = HttpProxy::start(0).await.unwrap();
+
+    // A client that trusts nothing rejects the capture certificate, like a pinned app
+    let config = rustls::ClientConfig::builder()
+        .with_root_certificates(rustls::RootCertStore::empty())
+        .with_no_client_auth();
+    let connector = tokio_rustls::TlsConnector::from(Arc::new(config));
+    let stream = open_tunnel(proxy.get_port(), &target).await;
+    let server_name = ServerName::try_from("localhost").unwrap();
+    assert!(connector.connect(server_name, stream).await.is_err());
+
+    let wait_for = |check: fn(&[TunnelRecord]) -> bool| {
+        let proxy = &proxy;
+        async move {
+            for _ in 0..100 {
+                if check(&proxy.get_tunnels().await) {
+                    return;
+                }
+                tokio::time::sleep(Duration::from_millis(20)).await;
+            }
+            panic!("tunnels never reached the expected state");
+        }
+    };
+    wait_for(|tunnels| tunnels.iter().any(|tunnel| tunnel.mitm_failed)).await;
+
+    // Later tunnels to the host are passed through without interception
+    let mut stream = open_tunnel(proxy.get_port(), &target).await;
+    stream.write_all(b"ping").await.unwrap();
+    let mut echoed = [0u8; 4];
+    stream.read_exact(&mut echoed).await.unwrap();
+    assert_eq!(&echoed, b"ping");
+    drop(stream);
+
+    wait_for(|tunnels| tunnels.iter().any(|tunnel| tunnel.bytes_down == 4)).await;
+    let tunnels = proxy.get_tunnels().await;
+    assert_eq!(
+        tunnels,
+        vec![TunnelRecord {
+            host: target.clone(),
+            bytes_up: 4,
+            bytes_down: 4,
+            mitm_failed: true,
+        }]
+    );
+    // Pass-through traffic is never recorded as requests
+    let records = proxy.get_records().await;
+    assert!(records.iter().all(|record| record.method == "CONNECT"));
+
+    proxy.stop().await.unwrap();
+}
+
+#[tokio::test]
+async fn test_recorder_tail_drops_oldest_when_behind() {
+    use crate::capture::proxy::tail::RECORD_TAIL_CAPACITY;
+    use crate::capture::proxy::TailEvent;
+
+    let recorder = RequestRecorder::new();
+    let mut tail = recorder.subscribe();
+
+    for i in 0..RECORD_TAIL_CAPACITY + 5 {
+        let record = RequestRecord::new(
+            "GET".to_string(),
+            format!("https://example.com/{}", i),
+            HashMap::new(),
+            None,
+            create_test_response(200, "text/plain", b"ok".to_vec()),
+        );
+        recorder.record_request(record).await;
+    }
+
+    // Recording never waited, and every record was kept
+    assert_eq!(recorder.get_records().await.len(), RECORD_TAIL_CAPACITY + 5);
+    assert_eq!(tail.recv().await, Some(TailEvent::Dropped(5)));
+    match tail.recv().await {
+        Some(TailEvent::Recorded(request)) => {
+            assert_eq!(request.url, "https://example.com/5");
+            assert_eq!(request.status, 200);
+            assert_eq!(request.size, 2);
+        }
+        other => panic!("expected a recorded request, got {:?}", other),
+    }
+    assert_eq!(tail.dropped(), 5);
+}
+
+#[tokio::test]
+async fn test_recorder_caps_records_without_blocking() {
+    use std::sync::Arc;
+    use tokio::time::{timeout, Duration};
+
+    let recorder = Arc::new(RequestRecorder::new().with_max_requests(Some(500)));
+    // A subscriber that never reads must not hold recording up either
+    let _idle_tail = recorder.subscribe();
+
+    let writers: Vec<_> = (0..8)
+        .map(|writer| {
+            let recorder = Arc::clone(&recorder);
+            tokio::spawn(async move {
+                for i in 0..2_500 {
+                    let record = RequestRecord::new(
+                        "GET".to_string(),
+                        format!("https://spa.example/poll?w={}&i={}", writer, i),
+                        HashMap::new(),
+                        None,
+                        create_test_response(200, "application/json", b"{}".to_vec()),
+                    );
+                    recorder.record_request(record).await;
+                }
+            })
+        })
+        .collect();
+    timeout(Duration::from_secs(10), async {
+        for writer in writers {
+            writer.await.unwrap();
+        }
+    })
+    .await
+    .expect("recording blocked");
+
+    assert_eq!(recorder.get_records().await.len(), 500);
+    assert_eq!(recorder.skipped_requests(), 19_500);
+
+    // Taking the records empties the recorder, clearing also resets the cap
+    assert_eq!(recorder.take_records().await.len(), 500);
+    assert!(recorder.get_records().await.is_empty());
+    recorder.clear_records().await;
+    assert_eq!(recorder.skipped_requests(), 0);
+}
+
+#[tokio::test]
+async fn test_proxy_keeps_forwarding_past_request_cap() {
+    use std::sync::Arc;
+    use wiremock::matchers::method;
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    let upstream = MockServer::start().await;
+    Mock::given(method("GET"))
+        .respond_with(ResponseTemplate::new(200).set_body_string("fresh"))
+        .mount(&upstream)
+        .await;
+
+    let recorder = Arc::new(RequestRecorder::new().with_max_requests(Some(2)));
+    let proxy = HttpProxy::start_with_recorder(0, recorder).await.unwrap();
+    let client = reqwest::Client::builder()
+        .proxy(reqwest::Proxy::http(format!("http://127.0.0.1:{}", proxy.get_port())).unwrap())
+        .build()
+        .unwrap();
+
+    for i in 0..5 {
+        let response = client
+            .get(format!("{}/poll/{}", upstream.uri(), i))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status(), 200);
+        assert_eq!(response.text().await.unwrap(), "fresh");
+    }
+
+    let records = proxy.get_records().await;
+    let skipped = proxy.skipped_requests();
+    proxy.stop().await.unwrap();
+
+    assert_eq!(records.len(), 2);
+    assert!(records[0].url.ends_with("/poll/0"));
+    assert_eq!(skipped, 3);
+}
+
+#[tokio::test]
+async fn test_proxy_records_only_final_outcome_of_retried_request() {
+    use crate::capture::proxy::client_pool::RetryPolicy;
+    use crate::capture::proxy::HttpClientPool;
+    use std::sync::Arc;
+    use std::time::Duration;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    // The origin resets the first connection and answers on the next one
+    let upstream = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let upstream_port = upstream.local_addr().unwrap().port();
+    tokio::spawn(async move {
+        let (mut stream, _) = upstream.accept().await.unwrap();
+        let mut request = [0u8; 1024];
+        let _ = stream.read(&mut request).await.unwrap();
+        drop(stream);
+
+        let (mut stream, _) = upstream.accept().await.unwrap();
+        let _ = stream.read(&mut request).await.unwrap();
+        stream
+            .write_all(b"HTTP/1.1 200 OK\r\ncontent-length: 6\r\n\r\nstable")
+            .await
+            .unwrap();
+    });
+
+    let client_pool = HttpClientPool::new().with_retry_policy(RetryPolicy {
+        base_delay: Duration::from_millis(10),
+        ..RetryPolicy::new(2)
+    });
+    let proxy = HttpProxy::start_with_client_pool(0, Arc::new(RequestRecorder::new()), client_pool)
+        .await
+        .unwrap();
+    let client = reqwest::Client::builder()
+        .proxy(reqwest::Proxy::http(format!("http://127.0.0.1:{}", proxy.get_port())).unwrap())
+        .build()
+        .unwrap();
+
+    let response = client
+        .get(format!("http://127.0.0.1:{}/data", upstream_port))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 200);
+    assert_eq!(response.text().await.unwrap(), "stable");
+
+    let records = proxy.get_records().await;
+    let retried = proxy.retried_requests();
+    proxy.stop().await.unwrap();
+
+    assert_eq!(records.len(), 1);
+    assert_eq!(records[0].response.status, 200);
+    assert_eq!(retried, 1);
+}
+
+#[tokio::test]
+async fn test_proxy_records_custom_reason_phrase() {
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    // wiremock can't send custom reason phrases, so answer by hand
+    let upstream = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let upstream_port = upstream.local_addr().unwrap().port();
+    tokio::spawn(async move {
+        let (mut stream, _) = upstream.accept().await.unwrap();
+        let mut request = [0u8; 1024];
+        let _ = stream.read(&mut request).await.unwrap();
+        stream
+            .write_all(b"HTTP/1.1 299 Legacy Success\r\ncontent-length: 2\r\n\r\nok")
+            .await
+            .unwrap();
+    });
+
+    let proxy = HttpProxy::start(0).await.unwrap();
+    let client = reqwest::Client::builder()
+        .proxy(reqwest::Proxy::http(format!("http://127.0.0.1:{}", proxy.get_port())).unwrap())
+        .build()
+        .unwrap();
+    let response = client
+        .get(format!("http://127.0.0.1:{}/legacy", upstream_port))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status().as_u16(), 299);
+
+    let records = proxy.get_records().await;
+    proxy.stop().await.unwrap();
+
+    assert_eq!(records.len(), 1);
+    assert_eq!(records[0].response.status, 299);
+    assert_eq!(
+        records[0].response.reason.as_deref(),
+        Some("Legacy Success")
+    );
+}
+
+#[tokio::test]
+async fn test_proxy_records_early_hints() {
+    use crate::capture::proxy::records::InterimResponse;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    // wiremock can't send informational responses, so answer by hand
+    let upstream = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let upstream_port = upstream.local_addr().unwrap().port();
+    tokio::spawn(async move {
+        let (mut stream, _) = upstream.accept().await.unwrap();
+        let mut request = [0u8; 1024];
+        let _ = stream.read(&mut request).await.unwrap();
+        stream
+            .write_all(
+                b"HTTP/1.1 103 Early Hints\r\n\
+                  link: </app.css>; rel=preload; as=style\r\n\
+                  link: </app.js>; rel=preload; as=script\r\n\r\n\
+                  HTTP/1.1 200 OK\r\ncontent-length: 2\r\n\r\nok",
+            )
+            .await
+            .unwrap();
+    });
+
+    let proxy = HttpProxy::start(0).await.unwrap();
+    let client = reqwest::Client::builder()
+        .proxy(reqwest::Proxy::http(format!("http://127.0.0.1:{}", proxy.get_port())).unwrap())
+        .build()
+        .unwrap();
+    let response = client
+        .get(format!("http://127.0.0.1:{}/hinted", upstream_port))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 200);
+    assert_eq!(response.text().await.unwrap(), "ok");
+
+    let records = proxy.get_records().await;
+    proxy.stop().await.unwrap();
+
+    assert_eq!(records.len(), 1);
+    assert_eq!(
+        records[0].response.informational,
+        vec![InterimResponse {
+            status: 103,
+            headers: vec![
+                (
+                    "link".to_string(),
+                    "</app.css>; rel=preload; as=style".to_string()
+                ),
+                (
+                    "link".to_string(),
+                    "</app.js>; rel=preload; as=script".to_string()
+                ),
+            ],
+        }]
+    );
+}
+
+#[tokio::test]
+async fn test_proxy_blocks_hosts_outside_allowlist() {
+    use crate::capture::proxy::HostAllowlist;
+    use std::sync::Arc;
+    use std::time::{Duration, Instant};
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use wiremock::matchers::method;
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    let upstream = MockServer::start().await;
+    Mock::given(method("GET"))
+        .respond_with(ResponseTemplate::new(200).set_body_string("allowed"))
+        .mount(&upstream)
+        .await;
+
+    let only_hosts = HostAllowlist::parse("127.0.0.1").unwrap();
+    let recorder = Arc::new(RequestRecorder::new().with_only_hosts(Some(only_hosts)));
+    let proxy = HttpProxy::start_with_recorder(0, recorder).await.unwrap();
+    let client = reqwest::Client::builder()
+        .proxy(reqwest::Proxy::http(format!("http://127.0.0.1:{}", proxy.get_port())).unwrap())
+        .build()
+        .unwrap();
+
+    let response = client
+        .get(format!("{}/app.js", upstream.uri()))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 200);
+    assert_eq!(response.text().await.unwrap(), "allowed");
+
+    // Refused without a DNS lookup or connection attempt, so it answers at once
+    let started = Instant::now();
+    let response = client
+        .get("http://tracker.invalid/pixel.gif")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 403);
+    assert!(started.elapsed() < Duration::from_secs(2));
+
+    // A CONNECT to a blocked host gets no tunnel
+    let mut stream = tokio::net::TcpStream::connect(("127.0.0.1", proxy.get_port()))
+        .await
+        .unwrap();
+    stream
+        .write_all(b"CONNECT ads.invalid:443 HTTP/1.1\r\nHost: ads.invalid:443\r\n\r\n")
+        .await
+        .unwrap();
+    let mut answer = Vec::new();
+    stream.read_to_end(&mut answer).await.unwrap();
+    assert!(answer.starts_with(b"HTTP/1.1 403"), "{:?}", answer);
+
+    // Tunnels to allowed hosts are still established
+    drop(open_tunnel(proxy.get_port(), &upstream.address().to_string()).await);
+
+    let records = proxy.get_records().await;
+    let blocked = proxy.blocked_hosts();
+    proxy.stop().await.unwrap();
+
+    assert!(records
+        .iter()
+        .all(|record| !record.url.contains(".invalid")));
+    assert!(records.iter().any(|record| record.url.ends_with("/app.js")));
+    assert_eq!(
+        blocked.into_iter().collect::<Vec<_>>(),
+        [
+            ("ads.invalid".to_string(), 1),
+            ("tracker.invalid".to_string(), 1)
+        ]
+    );
+}
+
+#[tokio::test]
+async fn test_proxy_without_allowlist_blocks_nothing() {
+    let recorder = RequestRecorder::new();
+    assert!(recorder.allow_host("anything.example.com"));
+    assert!(recorder.blocked_hosts().is_empty());
+}
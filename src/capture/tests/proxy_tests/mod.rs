@@ -1,5 +1,12 @@
+mod authority_tests;
+mod certificate_tests;
+mod client_pool_tests;
 mod content_type_tests;
+mod diagnostics_tests;
+mod host_filter_tests;
 mod integration_tests;
 mod performance_tests;
 mod records_tests;
+mod redaction_tests;
 mod server_tests;
+mod upload_tests;
@@ -70,6 +70,21 @@ async fn test_streaming_writer_large_data() {
     assert_eq!(result, large_data);
 }
 
+#[tokio::test]
+async fn test_streaming_writer_multiple_chunks_spill_to_disk() {
+    let mut writer = StreamingWriter::new(1024);
+    let chunks: Vec<Vec<u8>> = (0..4u8).map(|i| vec![b'a' + i; 700]).collect();
+
+    for chunk in &chunks {
+        writer.write(chunk).await.unwrap();
+    }
+    assert!(writer.is_on_disk());
+    assert_eq!(writer.size(), 2800);
+
+    let result = writer.finalize().await.unwrap();
+    assert_eq!(result, chunks.concat());
+}
+
 #[tokio::test]
 async fn test_performance_monitor() {
     let monitor = PerformanceMonitor::new();
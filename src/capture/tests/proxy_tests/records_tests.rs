@@ -16,6 +16,11 @@ fn create_test_response(status: u16, content_type: &str, body: Vec<u8>) -> Respo
         headers,
         body,
         content_type: content_type.to_string(),
+        failure: None,
+        set_cookie: Vec::new(),
+        http_version: None,
+        reason: None,
+        informational: Vec::new(),
     }
 }
 
@@ -157,6 +162,11 @@ fn test_serialization_deserialization() {
         headers: headers.clone(),
         body: b"binary\x00\x01\x02data".to_vec(),
         content_type: "application/json".to_string(),
+        failure: None,
+        set_cookie: Vec::new(),
+        http_version: None,
+        reason: None,
+        informational: Vec::new(),
     };
 
     let request = RequestRecord {
@@ -166,6 +176,12 @@ fn test_serialization_deserialization() {
         body: Some(b"request\x00\x01body".to_vec()),
         response,
         timestamp: chrono::Utc::now(),
+        initiator: None,
+        timing: Default::default(),
+        vary_key: Default::default(),
+        variant: None,
+        sequence: None,
+        frame_id: None,
     };
 
     // Test serialization to JSON (which uses base64 for binary data)
@@ -181,3 +197,225 @@ fn test_serialization_deserialization() {
     assert_eq!(deserialized.response.body, request.response.body);
     assert_eq!(deserialized.response.status, request.response.status);
 }
+
+#[test]
+fn test_response_record_failed() {
+    let response = ResponseRecord::failed("DNS error: failed to lookup address information");
+
+    assert!(response.is_failure());
+    assert_eq!(response.status, 502);
+    assert_eq!(
+        response.failure.as_deref(),
+        Some("DNS error: failed to lookup address information")
+    );
+    assert!(!ResponseRecord::new(200, HashMap::new(), Vec::new(), None).is_failure());
+}
+
+#[test]
+fn test_response_record_without_failure_field_deserializes() {
+    // Layout written before the failure field existed
+    #[derive(serde::Serialize)]
+    struct LegacyResponseRecord {
+        status: u16,
+        headers: HashMap<String, String>,
+        body: String,
+        content_type: String,
+    }
+
+    let legacy = LegacyResponseRecord {
+        status: 200,
+        headers: HashMap::new(),
+        body: "aGVsbG8=".to_string(),
+        content_type: "text/plain".to_string(),
+    };
+    let bytes = rmp_serde::to_vec(&legacy).unwrap();
+
+    let response: ResponseRecord = rmp_serde::from_slice(&bytes).unwrap();
+    assert_eq!(response.status, 200);
+    assert_eq!(response.body, b"hello");
+    assert!(response.failure.is_none());
+    assert!(response.informational.is_empty());
+}
+
+#[test]
+fn test_fill_missing_offsets_from_skewed_wall_clocks() {
+    let now = chrono::Utc::now();
+    let record = |secs_ago: i64| {
+        let mut record = RequestRecord::new(
+            "GET".to_string(),
+            "https://example.com/".to_string(),
+            HashMap::new(),
+            None,
+            create_test_response(200, "text/plain", Vec::new()),
+        );
+        record.timestamp = now - chrono::Duration::seconds(secs_ago);
+        record
+    };
+
+    // The clock went back 5 seconds before the second request
+    let mut records = vec![
+        record(2),
+        record(7),
+        record(1),
+        record(0).with_timing(42, 50),
+    ];
+    RequestRecord::fill_missing_offsets(&mut records);
+
+    let offsets: Vec<Option<u64>> = records.iter().map(|r| r.timing.offset_ms).collect();
+    assert_eq!(offsets, vec![Some(5000), Some(0), Some(6000), Some(42)]);
+    assert!(records[0].timing.duration_ms.is_none());
+}
+
+#[test]
+fn test_fill_missing_sequences_numbers_after_existing_ones() {
+    let record = || {
+        RequestRecord::new(
+            "GET".to_string(),
+            "https://example.com/".to_string(),
+            HashMap::new(),
+            None,
+            create_test_response(200, "text/plain", Vec::new()),
+        )
+    };
+
+    let mut records = vec![record(), record().with_sequence(4), record()];
+    RequestRecord::fill_missing_sequences(&mut records);
+
+    let sequences: Vec<Option<u64>> = records.iter().map(|r| r.sequence).collect();
+    assert_eq!(sequences, vec![Some(5), Some(4), Some(6)]);
+}
+
+#[test]
+fn test_failed_response_record_roundtrip() {
+    let response = ResponseRecord::failed("connection refused: Connection refused");
+    let bytes = rmp_serde::to_vec(&response).unwrap();
+
+    let decoded: ResponseRecord = rmp_serde::from_slice(&bytes).unwrap();
+    assert_eq!(decoded.failure, response.failure);
+    assert_eq!(decoded.status, 502);
+}
+
+#[test]
+fn test_set_cookies_falls_back_to_header_map() {
+    let mut headers = HashMap::new();
+    headers.insert("Set-Cookie".to_string(), "legacy=1".to_string());
+    let legacy = ResponseRecord::new(200, headers.clone(), Vec::new(), None);
+    assert_eq!(legacy.set_cookies(), vec!["legacy=1"]);
+
+    let current = ResponseRecord::new(200, headers, Vec::new(), None)
+        .with_set_cookie(vec!["a=1".to_string(), "b=2".to_string()]);
+    assert_eq!(current.set_cookies(), vec!["a=1", "b=2"]);
+}
+
+#[test]
+fn test_http_version_roundtrip() {
+    let response = ResponseRecord::new(200, HashMap::new(), b"ok".to_vec(), None)
+        .with_http_version(hyper::Version::HTTP_11);
+    assert_eq!(response.http_version.as_deref(), Some("HTTP/1.1"));
+
+    let bytes = rmp_serde::to_vec(&response).unwrap();
+    let decoded: ResponseRecord = rmp_serde::from_slice(&bytes).unwrap();
+    assert_eq!(decoded.http_version, response.http_version);
+}
+
+#[test]
+fn test_reason_phrase_roundtrip_and_status_text() {
+    let response = ResponseRecord::new(299, HashMap::new(), Vec::new(), None)
+        .with_reason(Some("Legacy Success".to_string()));
+    let bytes = rmp_serde::to_vec(&response).unwrap();
+    let decoded: ResponseRecord = rmp_serde::from_slice(&bytes).unwrap();
+    assert_eq!(decoded.status, 299);
+    assert_eq!(decoded.reason.as_deref(), Some("Legacy Success"));
+    assert_eq!(decoded.status_text(), "299 Legacy Success");
+
+    let unknown = ResponseRecord::new(599, HashMap::new(), Vec::new(), None);
+    assert_eq!(unknown.status_text(), "599");
+    let standard = ResponseRecord::new(404, HashMap::new(), Vec::new(), None);
+    assert_eq!(standard.status_text(), "404 Not Found");
+}
+
+#[test]
+fn test_informational_responses_roundtrip() {
+    use crate::capture::proxy::records::InterimResponse;
+
+    let hints = vec![InterimResponse {
+        status: 103,
+        headers: vec![
+            ("link".to_string(), "</a.css>; rel=preload".to_string()),
+            ("link".to_string(), "</b.js>; rel=preload".to_string()),
+        ],
+    }];
+    let response = ResponseRecord::new(200, HashMap::new(), b"ok".to_vec(), None)
+        .with_informational(hints.clone());
+
+    let bytes = rmp_serde::to_vec(&response).unwrap();
+    let decoded: ResponseRecord = rmp_serde::from_slice(&bytes).unwrap();
+    assert_eq!(decoded.informational, hints);
+
+    let json = serde_json::to_string(&response).unwrap();
+    let decoded: ResponseRecord = serde_json::from_str(&json).unwrap();
+    assert_eq!(decoded.informational, hints);
+}
+
+#[test]
+fn test_records_saved_before_vary_key_still_load() {
+    use crate::capture::proxy::records::RecordTiming;
+    use chrono::{DateTime, Utc};
+
+    /// A record as serialized before `vary_key` existed
+    #[derive(serde::Serialize)]
+    struct RecordWithoutVaryKey {
+        method: String,
+        url: String,
+        headers: HashMap<String, String>,
+        body: Option<String>,
+        response: ResponseRecord,
+        timestamp: DateTime<Utc>,
+        initiator: Option<()>,
+        timing: RecordTiming,
+    }
+
+    let old = RecordWithoutVaryKey {
+        method: "GET".to_string(),
+        url: "https://example.com/site.css".to_string(),
+        headers: create_test_headers(),
+        body: None,
+        response: create_test_response(200, "text/css", b"body{}".to_vec()),
+        timestamp: Utc::now(),
+        initiator: None,
+        timing: RecordTiming::default(),
+    };
+    let data = rmp_serde::to_vec(&old).unwrap();
+
+    let record: RequestRecord = rmp_serde::from_slice(&data).unwrap();
+    assert_eq!(record.url, old.url);
+    assert!(record.vary_key.is_empty());
+    assert!(record.variant.is_none());
+
+    let mut keyed = record.clone();
+    keyed
+        .vary_key
+        .insert("accept-encoding".to_string(), "gzip".to_string());
+    let roundtrip: RequestRecord =
+        rmp_serde::from_slice(&rmp_serde::to_vec(&keyed).unwrap()).unwrap();
+    assert_eq!(roundtrip.vary_key, keyed.vary_key);
+}
+
+#[test]
+fn test_variant_tag_survives_serialization() {
+    let untagged = RequestRecord::new(
+        "GET".to_string(),
+        "https://example.com/".to_string(),
+        create_test_headers(),
+        None,
+        create_test_response(200, "text/html", b"<p>guest</p>".to_vec()),
+    );
+    let tagged = untagged.clone().with_variant("admin");
+    assert_eq!(tagged.variant.as_deref(), Some("admin"));
+
+    for record in [untagged, tagged] {
+        let roundtrip: RequestRecord =
+            rmp_serde::from_slice(&rmp_serde::to_vec(&record).unwrap()).unwrap();
+        assert_eq!(roundtrip.variant, record.variant);
+    }
+}
@@ -0,0 +1,134 @@
+use crate::capture::proxy::recorder::RequestRecorder;
+use crate::capture::proxy::records::{RequestRecord, ResponseRecord};
+use crate::capture::proxy::redaction::{RedactionRules, REDACTED};
+use crate::capture::proxy::server::HttpProxy;
+use std::collections::HashMap;
+use std::sync::Arc;
+
+fn create_authenticated_record() -> RequestRecord {
+    let mut headers = HashMap::new();
+    headers.insert(
+        "Authorization".to_string(),
+        "Bearer secret-token".to_string(),
+    );
+    headers.insert("Accept".to_string(), "application/json".to_string());
+
+    let mut response_headers = HashMap::new();
+    response_headers.insert("content-type".to_string(), "application/json".to_string());
+    response_headers.insert("set-cookie".to_string(), "session=abc123".to_string());
+
+    RequestRecord::new(
+        "POST".to_string(),
+        "https://api.example.com/login".to_string(),
+        headers,
+        Some(b"{\"password\": \"hunter2\"}".to_vec()),
+        ResponseRecord::new(
+            200,
+            response_headers,
+            b"{\"token\": \"tok_123456\"}".to_vec(),
+            None,
+        )
+        .with_set_cookie(vec!["session=abc123".to_string(), "csrf=xyz".to_string()]),
+    )
+}
+
+#[test]
+fn test_redaction_rules_default_headers() {
+    let rules = RedactionRules::from_args(&[], true, &[], None).unwrap();
+    let mut record = create_authenticated_record();
+
+    rules.apply(&mut record);
+
+    // Header presence is preserved, values are scrubbed
+    assert_eq!(record.headers.get("Authorization").unwrap(), REDACTED);
+    assert_eq!(record.response.set_cookie, vec![REDACTED, REDACTED]);
+    assert_eq!(record.response.headers.get("set-cookie").unwrap(), REDACTED);
+    assert_eq!(record.headers.get("Accept").unwrap(), "application/json");
+}
+
+#[test]
+fn test_redaction_rules_body_patterns() {
+    let rules = RedactionRules::from_args(&[], false, &["tok_[0-9]+".to_string()], None).unwrap();
+    let mut record = create_authenticated_record();
+
+    rules.apply(&mut record);
+
+    let body = String::from_utf8(record.response.body.clone()).unwrap();
+    assert_eq!(body, "{\"token\": \"[REDACTED]\"}");
+    // Headers are untouched without header rules
+    assert_eq!(
+        record.headers.get("Authorization").unwrap(),
+        "Bearer secret-token"
+    );
+}
+
+#[test]
+fn test_redaction_rules_max_body_size() {
+    let rules = RedactionRules::new().with_max_body_size(4);
+    let mut record = create_authenticated_record();
+
+    rules.apply(&mut record);
+
+    assert_eq!(record.response.body.len(), 4);
+    assert_eq!(record.body.as_ref().unwrap().len(), 4);
+}
+
+#[test]
+fn test_redaction_rules_invalid_pattern() {
+    let result = RedactionRules::from_args(&[], false, &["(unclosed".to_string()], None);
+    assert!(result.is_err());
+}
+
+#[tokio::test]
+async fn test_recorder_applies_redaction() {
+    let rules = RedactionRules::new().redact_header("authorization");
+    let recorder = RequestRecorder::with_redaction(rules);
+
+    recorder.record_request(create_authenticated_record()).await;
+
+    let records = recorder.get_records().await;
+    assert_eq!(records.len(), 1);
+    assert_eq!(records[0].headers.get("Authorization").unwrap(), REDACTED);
+}
+
+#[tokio::test]
+async fn test_proxy_capture_scrubs_authorization_header() {
+    use wiremock::matchers::{method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    let upstream = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/profile"))
+        .respond_with(ResponseTemplate::new(200).set_body_string("{\"name\": \"test\"}"))
+        .mount(&upstream)
+        .await;
+
+    let port = {
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        listener.local_addr().unwrap().port()
+    };
+    let recorder = Arc::new(RequestRecorder::with_redaction(
+        RedactionRules::from_args(&[], true, &[], None).unwrap(),
+    ));
+    let proxy = HttpProxy::start_with_recorder(port, recorder)
+        .await
+        .unwrap();
+
+    let client = reqwest::Client::builder()
+        .proxy(reqwest::Proxy::http(format!("http://127.0.0.1:{}", port)).unwrap())
+        .build()
+        .unwrap();
+    let response = client
+        .get(format!("{}/profile", upstream.uri()))
+        .header("Authorization", "Bearer secret-token")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 200);
+
+    let records = proxy.get_records().await;
+    assert_eq!(records.len(), 1);
+    assert_eq!(records[0].headers.get("authorization").unwrap(), REDACTED);
+
+    proxy.stop().await.unwrap();
+}
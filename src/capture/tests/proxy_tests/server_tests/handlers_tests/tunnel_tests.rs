@@ -45,3 +45,31 @@ fn test_split_functionality() {
     }
     can_split::<TcpStream>();
 }
+
+#[test]
+fn test_certificate_rejection_detection() {
+    use crate::capture::proxy::server::handlers::mitm_handlers::is_certificate_rejection;
+    use rustls::AlertDescription;
+    use std::io::{Error, ErrorKind};
+
+    let alert = |description| {
+        Error::new(
+            ErrorKind::InvalidData,
+            rustls::Error::AlertReceived(description),
+        )
+    };
+
+    assert!(is_certificate_rejection(&alert(
+        AlertDescription::UnknownCA
+    )));
+    assert!(is_certificate_rejection(&alert(
+        AlertDescription::BadCertificate
+    )));
+    assert!(!is_certificate_rejection(&alert(
+        AlertDescription::HandshakeFailure
+    )));
+    assert!(!is_certificate_rejection(&Error::new(
+        ErrorKind::UnexpectedEof,
+        "tls handshake eof"
+    )));
+}
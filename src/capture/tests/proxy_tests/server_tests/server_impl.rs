@@ -125,3 +125,176 @@ async fn test_forward_request_with_pool_and_hop_by_hop_headers() {
     // but we can check that the function executes without panicking
     assert!(result.is_ok() || result.is_err());
 }
+
+mod retries {
+    use super::*;
+    use crate::capture::proxy::client_pool::RetryPolicy;
+    use std::time::{Duration, Instant};
+    use wiremock::matchers::method;
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    fn fast_retries(max_retries: u32) -> Arc<HttpClientPool> {
+        Arc::new(HttpClientPool::new().with_retry_policy(RetryPolicy {
+            max_retries,
+            base_delay: Duration::from_millis(10),
+            max_delay: Duration::from_millis(50),
+        }))
+    }
+
+    /// Upstream answering 503 to the first request and `200 ok` afterwards
+    async fn flaky_upstream() -> MockServer {
+        let upstream = MockServer::start().await;
+        Mock::given(method("GET"))
+            .respond_with(ResponseTemplate::new(503))
+            .up_to_n_times(1)
+            .with_priority(1)
+            .mount(&upstream)
+            .await;
+        Mock::given(wiremock::matchers::any())
+            .respond_with(ResponseTemplate::new(200).set_body_string("ok"))
+            .mount(&upstream)
+            .await;
+        upstream
+    }
+
+    #[test]
+    fn test_backoff_grows_exponentially_with_jitter() {
+        let policy = RetryPolicy {
+            max_retries: 5,
+            base_delay: Duration::from_millis(100),
+            max_delay: Duration::from_millis(300),
+        };
+
+        for _ in 0..20 {
+            let first = policy.backoff(1);
+            assert!(first >= Duration::from_millis(100) && first <= Duration::from_millis(150));
+            let second = policy.backoff(2);
+            assert!(second >= Duration::from_millis(200) && second <= Duration::from_millis(300));
+            // Capped before jitter is added
+            let fifth = policy.backoff(5);
+            assert!(fifth >= Duration::from_millis(300) && fifth <= Duration::from_millis(450));
+        }
+    }
+
+    #[tokio::test]
+    async fn test_retries_get_after_unavailable_response() {
+        let upstream = flaky_upstream().await;
+        let client_pool = fast_retries(2);
+
+        let response = forward_request_with_pool(
+            Method::GET,
+            &format!("{}/flaky", upstream.uri()),
+            HashMap::new(),
+            vec![],
+            hyper::Version::HTTP_11,
+            Arc::clone(&client_pool),
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(response.status, 200);
+        assert_eq!(response.body, b"ok");
+        assert_eq!(upstream.received_requests().await.unwrap().len(), 2);
+        assert_eq!(client_pool.retried_requests(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_never_retries_non_idempotent_methods() {
+        let upstream = MockServer::start().await;
+        Mock::given(method("POST"))
+            .respond_with(ResponseTemplate::new(502))
+            .mount(&upstream)
+            .await;
+        let client_pool = fast_retries(3);
+
+        let response = forward_request_with_pool(
+            Method::POST,
+            &format!("{}/order", upstream.uri()),
+            HashMap::new(),
+            b"item=1".to_vec(),
+            hyper::Version::HTTP_11,
+            Arc::clone(&client_pool),
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(response.status, 502);
+        assert_eq!(upstream.received_requests().await.unwrap().len(), 1);
+        assert_eq!(client_pool.retried_requests(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_gives_up_after_max_retries() {
+        let upstream = MockServer::start().await;
+        Mock::given(method("GET"))
+            .respond_with(ResponseTemplate::new(504))
+            .mount(&upstream)
+            .await;
+        let client_pool = fast_retries(2);
+
+        let response = forward_request_with_pool(
+            Method::GET,
+            &upstream.uri(),
+            HashMap::new(),
+            vec![],
+            hyper::Version::HTTP_11,
+            Arc::clone(&client_pool),
+        )
+        .await
+        .unwrap();
+
+        // The last attempt's outcome is what the caller records
+        assert_eq!(response.status, 504);
+        assert_eq!(upstream.received_requests().await.unwrap().len(), 3);
+        assert_eq!(client_pool.retried_requests(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_retries_connect_errors() {
+        // Bind and drop a listener to get a port nothing listens on
+        let port = std::net::TcpListener::bind("127.0.0.1:0")
+            .unwrap()
+            .local_addr()
+            .unwrap()
+            .port();
+        let client_pool = fast_retries(1);
+
+        let result = forward_request_with_pool(
+            Method::HEAD,
+            &format!("http://127.0.0.1:{}/", port),
+            HashMap::new(),
+            vec![],
+            hyper::Version::HTTP_11,
+            Arc::clone(&client_pool),
+        )
+        .await;
+
+        assert!(result
+            .unwrap_err()
+            .to_string()
+            .contains("connection refused"));
+        assert_eq!(client_pool.retried_requests(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_retries_stop_at_deadline() {
+        let upstream = flaky_upstream().await;
+        let client_pool = fast_retries(2);
+        client_pool.set_retry_deadline(Some(Instant::now()));
+
+        let response = forward_request_with_pool(
+            Method::GET,
+            &upstream.uri(),
+            HashMap::new(),
+            vec![],
+            hyper::Version::HTTP_11,
+            Arc::clone(&client_pool),
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(response.status, 503);
+        assert_eq!(upstream.received_requests().await.unwrap().len(), 1);
+        assert_eq!(client_pool.retried_requests(), 0);
+    }
+}
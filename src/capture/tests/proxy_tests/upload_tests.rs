@@ -0,0 +1,91 @@
+use crate::capture::proxy::recorder::RequestRecorder;
+use crate::capture::proxy::server::HttpProxy;
+use crate::capture::proxy::streaming::RecordingBody;
+use bytes::Bytes;
+use http_body_util::{BodyExt, Full};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpStream;
+
+fn free_port() -> u16 {
+    let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    listener.local_addr().unwrap().port()
+}
+
+/// Read from the stream until the end of a response header block
+async fn read_head(stream: &mut TcpStream) -> String {
+    let mut head = Vec::new();
+    let mut byte = [0u8; 1];
+    while !head.ends_with(b"\r\n\r\n") {
+        let n = stream.read(&mut byte).await.unwrap();
+        assert_eq!(n, 1, "connection closed before headers were complete");
+        head.push(byte[0]);
+    }
+    String::from_utf8(head).unwrap()
+}
+
+#[tokio::test]
+async fn test_recording_body_passes_data_through() {
+    let (body, recording) = RecordingBody::new(Full::new(Bytes::from_static(b"hello upload")), 4);
+
+    let forwarded = body.collect().await.unwrap().to_bytes();
+    let recorded = recording.finish().await.unwrap();
+
+    assert_eq!(&forwarded[..], b"hello upload");
+    assert_eq!(recorded, b"hello upload");
+}
+
+#[tokio::test]
+async fn test_expect_continue_large_upload_is_streamed_and_recorded() {
+    use wiremock::matchers::{header, method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    let upstream = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/upload"))
+        .and(header("expect", "100-continue"))
+        .respond_with(ResponseTemplate::new(201).set_body_string("stored"))
+        .mount(&upstream)
+        .await;
+
+    let port = free_port();
+    let proxy = HttpProxy::start_with_recorder(port, Arc::new(RequestRecorder::new()))
+        .await
+        .unwrap();
+
+    let body: Vec<u8> = (0..3 * 1024 * 1024).map(|i| (i % 251) as u8).collect();
+    let upstream_addr = upstream.address();
+
+    let mut stream = TcpStream::connect(("127.0.0.1", port)).await.unwrap();
+    let head = format!(
+        "POST {}/upload HTTP/1.1\r\nHost: {}\r\nContent-Length: {}\r\nExpect: 100-continue\r\nConnection: close\r\n\r\n",
+        upstream.uri(),
+        upstream_addr,
+        body.len()
+    );
+    stream.write_all(head.as_bytes()).await.unwrap();
+
+    // The interim response must arrive before any of the body is sent
+    let interim = tokio::time::timeout(Duration::from_secs(5), read_head(&mut stream))
+        .await
+        .expect("proxy did not answer Expect: 100-continue");
+    assert!(interim.starts_with("HTTP/1.1 100"), "got: {}", interim);
+
+    stream.write_all(&body).await.unwrap();
+
+    let mut response = Vec::new();
+    tokio::time::timeout(Duration::from_secs(10), stream.read_to_end(&mut response))
+        .await
+        .expect("proxy did not respond to upload")
+        .unwrap();
+    let response = String::from_utf8_lossy(&response);
+    assert!(response.starts_with("HTTP/1.1 201"), "got: {}", response);
+
+    let records = proxy.get_records().await;
+    assert_eq!(records.len(), 1);
+    assert_eq!(records[0].response.status, 201);
+    assert_eq!(records[0].body.as_deref(), Some(body.as_slice()));
+
+    proxy.stop().await.unwrap();
+}
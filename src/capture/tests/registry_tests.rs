@@ -0,0 +1,134 @@
+use std::time::Duration;
+
+use tempfile::TempDir;
+
+use crate::capture::registry::{CaptureEntry, CaptureRegistry, REGISTRY_DIR};
+use crate::error::WebMockError;
+
+fn registry_files(temp_dir: &TempDir) -> Vec<String> {
+    let mut files: Vec<String> = std::fs::read_dir(temp_dir.path().join(REGISTRY_DIR))
+        .unwrap()
+        .map(|entry| entry.unwrap().file_name().to_string_lossy().into_owned())
+        .filter(|name| name.ends_with(".json"))
+        .collect();
+    files.sort();
+    files
+}
+
+#[test]
+fn test_registry_without_captures_is_empty() {
+    let temp_dir = TempDir::new().unwrap();
+    let registry = CaptureRegistry::new(temp_dir.path());
+
+    assert!(registry.running().unwrap().is_empty());
+    assert_eq!(registry.shared_browser().unwrap(), None);
+    assert!(!registry.dir().exists());
+}
+
+#[test]
+fn test_running_capture_is_detected_until_lease_is_dropped() {
+    let temp_dir = TempDir::new().unwrap();
+    let registry = CaptureRegistry::new(temp_dir.path());
+
+    let lease = registry.register(CaptureEntry::new(Some("shop"))).unwrap();
+    let running = registry.running().unwrap();
+    assert_eq!(running, vec![lease.entry().clone()]);
+    assert_eq!(running[0].pid, std::process::id());
+    assert_eq!(
+        running[0].describe(),
+        format!("snapshot 'shop', PID {}", std::process::id())
+    );
+
+    // Another capture in the same process gets an entry of its own
+    let other = registry.register(CaptureEntry::new(None)).unwrap();
+    assert_eq!(registry.running().unwrap().len(), 2);
+    assert_eq!(registry_files(&temp_dir).len(), 2);
+
+    drop(lease);
+    assert_eq!(registry.running().unwrap(), vec![other.entry().clone()]);
+    drop(other);
+    assert!(registry.running().unwrap().is_empty());
+    assert!(registry_files(&temp_dir).is_empty());
+}
+
+#[test]
+fn test_stale_entries_are_removed() {
+    let temp_dir = TempDir::new().unwrap();
+    let registry = CaptureRegistry::new(temp_dir.path());
+    let live = registry.register(CaptureEntry::new(Some("live"))).unwrap();
+
+    // Left behind by a capture that was killed: nothing holds its lock
+    let mut crashed = CaptureEntry::new(Some("crashed"));
+    crashed.pid = 1;
+    crashed.shared_browser = Some("ws://127.0.0.1:9222/devtools/browser/gone".to_string());
+    std::fs::write(
+        registry.dir().join("1-0.json"),
+        serde_json::to_vec(&crashed).unwrap(),
+    )
+    .unwrap();
+    std::fs::write(registry.dir().join("2-0.json"), b"not json").unwrap();
+    assert_eq!(registry_files(&temp_dir).len(), 3);
+
+    assert_eq!(registry.running().unwrap(), vec![live.entry().clone()]);
+    assert_eq!(registry.shared_browser().unwrap(), None);
+    assert_eq!(registry_files(&temp_dir).len(), 1);
+}
+
+#[test]
+fn test_shared_browser_and_guests() {
+    let temp_dir = TempDir::new().unwrap();
+    let registry = CaptureRegistry::new(temp_dir.path());
+    let endpoint = "ws://127.0.0.1:9222/devtools/browser/4f1c";
+
+    let _unshared = registry.register(CaptureEntry::new(Some("plain"))).unwrap();
+    assert_eq!(registry.shared_browser().unwrap(), None);
+
+    let mut owner = CaptureEntry::new(Some("owner"));
+    owner.shared_browser = Some(endpoint.to_string());
+    let mut owner = registry.register(owner).unwrap();
+    let mut guest = CaptureEntry::new(Some("guest"));
+    guest.guest_of = Some(endpoint.to_string());
+    let guest = registry.register(guest).unwrap();
+
+    assert_eq!(
+        registry.shared_browser().unwrap().as_ref(),
+        Some(owner.entry())
+    );
+    assert_eq!(
+        registry.guests_of(endpoint).unwrap(),
+        vec![guest.entry().clone()]
+    );
+    assert!(registry.guests_of("ws://elsewhere").unwrap().is_empty());
+
+    // A closing owner withdraws its browser but stays registered
+    owner.update(|entry| entry.shared_browser = None).unwrap();
+    assert_eq!(registry.shared_browser().unwrap(), None);
+    assert_eq!(registry.running().unwrap().len(), 3);
+
+    drop(guest);
+    assert!(registry.guests_of(endpoint).unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn test_launch_lock_is_exclusive() {
+    let temp_dir = TempDir::new().unwrap();
+    let registry = CaptureRegistry::new(temp_dir.path());
+
+    let launch = registry.lock_launch(Duration::ZERO).await.unwrap();
+    let error = CaptureRegistry::new(temp_dir.path())
+        .lock_launch(Duration::from_millis(250))
+        .await
+        .unwrap_err();
+    assert!(
+        matches!(error, WebMockError::CommandFailed(_)),
+        "{:?}",
+        error
+    );
+    assert!(error.to_string().contains("--share-browser"));
+
+    drop(launch);
+    registry
+        .lock_launch(Duration::from_millis(250))
+        .await
+        .unwrap();
+}
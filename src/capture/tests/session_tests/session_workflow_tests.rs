@@ -1,5 +1,6 @@
+use crate::capture::CaptureOptions;
 use crate::capture::CaptureSession;
-use crate::storage::Storage;
+use crate::storage::{ArtifactKind, PageArtifacts, Storage};
 use crate::test_utils::test_helpers::*;
 use std::sync::Arc;
 use tempfile::TempDir;
@@ -19,6 +20,30 @@ async fn test_capture_invalid_url() {
     assert!(result.is_err());
 }
 
+#[tokio::test]
+async fn test_capture_pages_rejects_bad_input_before_starting() {
+    let temp_dir = TempDir::new().unwrap();
+    let storage = Arc::new(Storage::new(temp_dir.path().to_path_buf()));
+    let mut session = CaptureSession::new(storage).await.unwrap();
+    let timeout = std::time::Duration::from_secs(30);
+
+    let result = session.capture_pages(&[], timeout, None, |_, _| {}).await;
+    assert!(result.is_err());
+
+    // One invalid URL fails the whole run before any browser is launched
+    let urls = vec![
+        "https://example.com/".to_string(),
+        "ftp://example.com/file".to_string(),
+    ];
+    let mut visited = 0;
+    let result = session
+        .capture_pages(&urls, timeout, None, |_, _| visited += 1)
+        .await;
+    assert!(result.is_err());
+    assert_eq!(visited, 0);
+    assert!(!session.is_active());
+}
+
 #[tokio::test]
 async fn test_navigate_and_wait_no_browser() {
     let temp_dir = TempDir::new().unwrap();
@@ -294,7 +319,7 @@ fn test_error_handling_patterns() {
     let errors = [
         WebMockError::ChromeNotFound,
         WebMockError::InvalidUrl("invalid".to_string(), "bad format".to_string()),
-        WebMockError::Timeout(30),
+        WebMockError::timeout("Page navigation", std::time::Duration::from_secs(30)),
         WebMockError::config("Browser not initialized"),
     ];
 
@@ -309,7 +334,7 @@ fn test_error_handling_patterns() {
         match error {
             WebMockError::ChromeNotFound => assert!(!is_recoverable),
             WebMockError::InvalidUrl(_, _) => assert!(!is_recoverable),
-            WebMockError::Timeout(_) => assert!(is_recoverable),
+            WebMockError::Timeout { .. } => assert!(is_recoverable),
             _ => {} // Other errors may vary
         }
     }
@@ -322,7 +347,7 @@ async fn test_storage_integration() {
     let (_temp_dir, storage) = create_temp_storage();
 
     // Test that storage is properly initialized
-    assert!(storage.ensure_snapshots_dir().is_ok());
+    assert!(storage.ensure_snapshots_dir().await.is_ok());
 
     // Test snapshot operations
     let snapshot = create_test_snapshot_with_name("session-test");
@@ -372,3 +397,385 @@ fn test_url_parsing_and_validation() {
         }
     }
 }
+
+#[tokio::test]
+async fn test_start_proxy_uses_os_assigned_port() {
+    use crate::capture::browser::BrowserController;
+    use crate::capture::CaptureEnvironment;
+
+    let temp_dir = TempDir::new().unwrap();
+    let storage = Arc::new(Storage::new(temp_dir.path().to_path_buf()));
+    let mut session = CaptureSession::new(storage).await.unwrap();
+
+    let port = session.start_proxy().await.unwrap();
+    assert_ne!(port, 0);
+    assert_eq!(session.get_proxy_port(), port);
+
+    // The proxy is already listening on the reported port
+    assert!(tokio::net::TcpStream::connect(("127.0.0.1", port))
+        .await
+        .is_ok());
+
+    // And the browser is pointed at that same port
+    let args =
+        BrowserController::launch_args(session.get_proxy_port(), &CaptureEnvironment::default());
+    assert!(args.contains(&format!("--proxy-server=127.0.0.1:{}", port)));
+
+    session.cleanup().await.unwrap();
+}
+
+#[tokio::test]
+async fn test_start_proxy_uses_configured_port() {
+    use crate::capture::CaptureOptions;
+
+    // A port that was free a moment ago
+    let port = std::net::TcpListener::bind(("127.0.0.1", 0))
+        .unwrap()
+        .local_addr()
+        .unwrap()
+        .port();
+    let temp_dir = TempDir::new().unwrap();
+    let storage = Arc::new(Storage::new(temp_dir.path().to_path_buf()));
+    let options = CaptureOptions {
+        proxy_port: Some(port),
+        ..CaptureOptions::default()
+    };
+    let mut session = CaptureSession::with_options(storage, options)
+        .await
+        .unwrap();
+
+    assert_eq!(session.start_proxy().await.unwrap(), port);
+    assert!(tokio::net::TcpStream::connect(("127.0.0.1", port))
+        .await
+        .is_ok());
+
+    session.cleanup().await.unwrap();
+}
+
+#[test]
+fn test_attach_endpoint() {
+    use crate::capture::browser::BrowserController;
+
+    assert_eq!(
+        BrowserController::attach_endpoint(None, None).unwrap(),
+        None
+    );
+    assert_eq!(
+        BrowserController::attach_endpoint(None, Some(9222)).unwrap(),
+        Some("http://127.0.0.1:9222".to_string())
+    );
+    let ws = "ws://127.0.0.1:9222/devtools/browser/4f1c";
+    assert_eq!(
+        BrowserController::attach_endpoint(Some(ws), None).unwrap(),
+        Some(ws.to_string())
+    );
+
+    assert!(BrowserController::attach_endpoint(Some("127.0.0.1:9222"), None).is_err());
+    assert!(BrowserController::attach_endpoint(Some("file:///tmp/chrome"), None).is_err());
+    assert!(BrowserController::attach_endpoint(Some(ws), Some(9222)).is_err());
+}
+
+#[test]
+fn test_launch_args_include_user_agent() {
+    use crate::capture::browser::BrowserController;
+    use crate::capture::CaptureEnvironment;
+
+    let environment = CaptureEnvironment::default()
+        .with_device(Some("iphone-13"), None)
+        .unwrap();
+    let args = BrowserController::launch_args(8080, &environment);
+
+    let user_agent = environment.user_agent.as_deref().unwrap();
+    assert!(args.contains(&format!("--user-agent={}", user_agent)));
+    assert!(args.contains(&"--window-size=390,844".to_string()));
+
+    let args = BrowserController::launch_args(8080, &CaptureEnvironment::default());
+    assert!(!args.iter().any(|arg| arg.starts_with("--user-agent=")));
+    // Cross-origin iframes stay in the page's process, where their events are seen
+    assert!(args
+        .iter()
+        .any(|arg| arg.starts_with("--disable-features=") && arg.contains("site-per-process")));
+}
+
+#[tokio::test]
+#[ignore = "slow test - requires Chrome"]
+async fn test_capture_sends_device_user_agent_to_origin() {
+    use crate::capture::{CaptureEnvironment, CaptureOptions};
+    use wiremock::matchers::{method, path};
+    use wiremock::{Mock, MockServer, Request, ResponseTemplate};
+
+    // The origin echoes the User-Agent it received back in the page
+    let origin = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/"))
+        .respond_with(|request: &Request| {
+            let user_agent = request
+                .headers
+                .get("user-agent")
+                .and_then(|value| value.to_str().ok())
+                .unwrap_or_default()
+                .to_string();
+            ResponseTemplate::new(200)
+                .insert_header("content-type", "text/html")
+                .set_body_string(format!("<html><body>{}</body></html>", user_agent))
+        })
+        .mount(&origin)
+        .await;
+
+    let temp_dir = TempDir::new().unwrap();
+    let storage = Arc::new(Storage::new(temp_dir.path().to_path_buf()));
+    let environment = CaptureEnvironment::default()
+        .with_device(Some("pixel-7"), None)
+        .unwrap();
+    let user_agent = environment.user_agent.clone().unwrap();
+    let options = CaptureOptions {
+        environment,
+        ..CaptureOptions::default()
+    };
+
+    let mut session = CaptureSession::with_options(storage.clone(), options)
+        .await
+        .unwrap();
+    let url = format!("{}/", origin.uri());
+    session.capture(&url, "device-ua", 30).await.unwrap();
+
+    let snapshot = storage.load_snapshot("device-ua").await.unwrap();
+    assert_eq!(snapshot.environment.device.as_deref(), Some("pixel-7"));
+    let record = snapshot
+        .requests
+        .iter()
+        .find(|record| record.url == url)
+        .expect("page request was captured");
+    let body = String::from_utf8_lossy(&record.response.body);
+    assert!(body.contains(&user_agent), "origin saw: {}", body);
+}
+
+#[tokio::test]
+#[ignore = "slow test - requires Chrome"]
+async fn test_capture_waits_for_delayed_iframe() {
+    use crate::capture::CaptureOptions;
+    use std::time::Duration;
+    use wiremock::matchers::{method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    // The page adds an iframe after its load event, and the iframe is slow to answer
+    let origin = MockServer::start().await;
+    let html = |body: &str| {
+        ResponseTemplate::new(200)
+            .insert_header("content-type", "text/html")
+            .set_body_string(body)
+    };
+    Mock::given(method("GET"))
+        .and(path("/"))
+        .respond_with(html(
+            r#"<html><body><script>
+            window.addEventListener("load", () => setTimeout(() => {
+                const frame = document.createElement("iframe");
+                frame.src = "/widget";
+                document.body.appendChild(frame);
+            }, 1000));
+            </script></body></html>"#,
+        ))
+        .mount(&origin)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/widget"))
+        .respond_with(
+            html(r#"<html><head><link rel="stylesheet" href="/widget.css"></head></html>"#)
+                .set_delay(Duration::from_secs(3)),
+        )
+        .mount(&origin)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/widget.css"))
+        .respond_with(
+            ResponseTemplate::new(200)
+                .insert_header("content-type", "text/css")
+                .set_body_string("body { color: teal; }"),
+        )
+        .mount(&origin)
+        .await;
+
+    let temp_dir = TempDir::new().unwrap();
+    let storage = Arc::new(Storage::new(temp_dir.path().to_path_buf()));
+    let mut session = CaptureSession::with_options(storage, CaptureOptions::default())
+        .await
+        .unwrap();
+    let url = format!("{}/", origin.uri());
+    session.capture(&url, "iframe", 30).await.unwrap();
+    let snapshot = session.stop("iframe", &url).await.unwrap();
+    let frame_of = |path: &str| {
+        snapshot
+            .requests
+            .iter()
+            .find(|record| record.url == format!("{}{}", origin.uri(), path))
+            .unwrap_or_else(|| panic!("{} was not captured", path))
+            .frame_id
+            .clone()
+    };
+    let page_frame = frame_of("/");
+    let widget_frame = frame_of("/widget");
+    assert!(page_frame.is_some());
+    assert_ne!(page_frame, widget_frame);
+    // The iframe's subresource is recorded, as requested by the iframe
+    assert_eq!(frame_of("/widget.css"), widget_frame);
+}
+
+#[tokio::test]
+async fn test_session_subscription_sees_proxy_records() {
+    use crate::capture::proxy::TailEvent;
+    use wiremock::matchers::method;
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    let origin = MockServer::start().await;
+    Mock::given(method("GET"))
+        .respond_with(ResponseTemplate::new(201).set_body_string("created"))
+        .mount(&origin)
+        .await;
+
+    let temp_dir = TempDir::new().unwrap();
+    let storage = Arc::new(Storage::new(temp_dir.path().to_path_buf()));
+    let mut session = CaptureSession::new(storage).await.unwrap();
+
+    // Subscribing before the proxy exists still sees its records
+    let mut tail = session.subscribe();
+    let port = session.start_proxy().await.unwrap();
+
+    let client = reqwest::Client::builder()
+        .proxy(reqwest::Proxy::http(format!("http://127.0.0.1:{}", port)).unwrap())
+        .build()
+        .unwrap();
+    let url = format!("{}/items", origin.uri());
+    client.get(&url).send().await.unwrap();
+
+    let event = tokio::time::timeout(std::time::Duration::from_secs(5), tail.recv())
+        .await
+        .expect("no tail event");
+    match event {
+        Some(TailEvent::Recorded(request)) => {
+            assert_eq!(request.method, "GET");
+            assert_eq!(request.url, url);
+            assert_eq!(request.status, 201);
+            assert_eq!(request.size, 7);
+        }
+        other => panic!("expected a recorded request, got {:?}", other),
+    }
+
+    session.cleanup().await.unwrap();
+}
+
+#[tokio::test]
+async fn test_stop_saves_page_artifacts_next_to_the_snapshot() {
+    let temp_dir = TempDir::new().unwrap();
+    let storage = Arc::new(Storage::new(temp_dir.path().to_path_buf()));
+    let mut session = CaptureSession::new(Arc::clone(&storage)).await.unwrap();
+
+    // What capture_artifacts takes from the browser once the page has loaded
+    session.artifacts = Some(PageArtifacts {
+        screenshot: Some(b"\x89PNG".to_vec()),
+        dom: Some("<html></html>".to_string()),
+    });
+    let snapshot = session
+        .stop("with-artifacts", "http://example.com")
+        .await
+        .unwrap();
+    assert_eq!(
+        snapshot.artifacts.get(ArtifactKind::Screenshot),
+        Some("with-artifacts.screenshot.png")
+    );
+    assert_eq!(
+        snapshot.artifacts.get(ArtifactKind::Dom),
+        Some("with-artifacts.dom.html")
+    );
+    assert_eq!(
+        storage
+            .artifact_files("with-artifacts")
+            .await
+            .unwrap()
+            .len(),
+        2
+    );
+
+    // Without a browser, or with --no-artifacts, nothing is taken
+    let options = CaptureOptions {
+        no_artifacts: true,
+        ..CaptureOptions::default()
+    };
+    let mut session = CaptureSession::with_options(Arc::clone(&storage), options)
+        .await
+        .unwrap();
+    session.capture_artifacts().await;
+    let snapshot = session
+        .stop("without-artifacts", "http://example.com")
+        .await
+        .unwrap();
+    assert!(snapshot.artifacts.is_empty());
+    assert!(storage
+        .artifact_files("without-artifacts")
+        .await
+        .unwrap()
+        .is_empty());
+}
+
+#[tokio::test]
+async fn test_only_hosts_allows_page_host_and_saves_blocked_hosts() {
+    use crate::capture::proxy::HostAllowlist;
+
+    let temp_dir = TempDir::new().unwrap();
+    let storage = Arc::new(Storage::new(temp_dir.path().to_path_buf()));
+    let options = CaptureOptions {
+        only_hosts: Some(HostAllowlist::parse("api.example.com").unwrap()),
+        ..CaptureOptions::default()
+    };
+    let mut session = CaptureSession::with_options(storage, options)
+        .await
+        .unwrap();
+
+    session.allow_page_hosts(["https://www.example.com/start"]);
+    let only_hosts = session.options.only_hosts.as_ref().unwrap();
+    assert!(only_hosts.allows("www.example.com"));
+    assert!(only_hosts.allows("api.example.com"));
+
+    let port = session.start_proxy().await.unwrap();
+    let client = reqwest::Client::builder()
+        .proxy(reqwest::Proxy::http(format!("http://127.0.0.1:{}", port)).unwrap())
+        .build()
+        .unwrap();
+    for _ in 0..2 {
+        let response = client
+            .get("http://tracker.invalid/collect")
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status(), 403);
+    }
+
+    let snapshot = session
+        .stop("only-hosts", "https://www.example.com/start")
+        .await
+        .unwrap();
+    assert_eq!(snapshot.blocked_hosts.get("tracker.invalid"), Some(&2));
+    assert!(snapshot.requests.is_empty());
+}
+
+#[tokio::test]
+async fn test_stop_saves_entry_points() {
+    let temp_dir = TempDir::new().unwrap();
+    let storage = Arc::new(Storage::new(temp_dir.path().to_path_buf()));
+    let mut session = CaptureSession::new(storage).await.unwrap();
+
+    // What load_page records for a page that redirected
+    session.entry_points = vec![
+        "http://example.com/".to_string(),
+        "https://www.example.com/".to_string(),
+    ];
+    let snapshot = session
+        .stop("redirected", "http://example.com/")
+        .await
+        .unwrap();
+    assert_eq!(snapshot.url, "http://example.com/");
+    assert_eq!(
+        snapshot.entry_points(),
+        ["http://example.com/", "https://www.example.com/"]
+    );
+}
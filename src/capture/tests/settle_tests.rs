@@ -0,0 +1,225 @@
+use std::time::Duration;
+
+use hyper::header::{ACCEPT, UPGRADE};
+use hyper::HeaderMap;
+use wiremock::matchers::{method, path};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+use crate::capture::proxy::{HttpProxy, InFlightRequest, RequestRecorder};
+use crate::capture::settle::{IdleTracker, SettleStrategy, LONG_REQUEST_AFTER};
+
+const QUIET: Duration = Duration::from_millis(1000);
+
+fn request(url: &str, started_ms: u64) -> InFlightRequest {
+    InFlightRequest {
+        method: "GET".to_string(),
+        url: url.to_string(),
+        started_ms,
+        streaming: false,
+    }
+}
+
+fn tracker() -> IdleTracker {
+    IdleTracker::new(QUIET, LONG_REQUEST_AFTER)
+}
+
+#[test]
+fn test_parse_settle_strategy() {
+    assert_eq!(SettleStrategy::parse("load").unwrap(), SettleStrategy::Load);
+    assert_eq!(
+        SettleStrategy::parse("NetworkIdle").unwrap(),
+        SettleStrategy::NetworkIdle
+    );
+    assert_eq!(
+        SettleStrategy::parse("domcontentloaded").unwrap(),
+        SettleStrategy::DomContentLoaded
+    );
+    assert_eq!(
+        SettleStrategy::parse("fixed:2.5").unwrap(),
+        SettleStrategy::Fixed(Duration::from_millis(2500))
+    );
+    assert_eq!(SettleStrategy::default(), SettleStrategy::Load);
+
+    for invalid in [
+        "",
+        "idle",
+        "fixed",
+        "fixed:",
+        "fixed:abc",
+        "fixed:0",
+        "fixed:-1",
+        "fixed:601",
+        "fixed:inf",
+    ] {
+        let error = SettleStrategy::parse(invalid).unwrap_err().to_string();
+        assert!(error.contains("settle strategy"), "{}: {}", invalid, error);
+    }
+
+    for strategy in ["load", "networkidle", "domcontentloaded", "fixed:10"] {
+        assert_eq!(
+            SettleStrategy::parse(strategy).unwrap().to_string(),
+            strategy
+        );
+    }
+}
+
+#[test]
+fn test_only_load_and_network_idle_wait_for_the_network() {
+    assert!(SettleStrategy::Load.waits_for_network_idle());
+    assert!(SettleStrategy::NetworkIdle.waits_for_network_idle());
+    assert!(!SettleStrategy::DomContentLoaded.waits_for_network_idle());
+    assert!(!SettleStrategy::Fixed(Duration::from_secs(1)).waits_for_network_idle());
+}
+
+#[test]
+fn test_idle_after_a_quiet_period() {
+    let mut tracker = tracker();
+    // The first look has nothing to compare with
+    assert!(!tracker.observe(0, 3, &[], false));
+    assert!(!tracker.observe(500, 3, &[], false));
+    assert!(!tracker.observe(1000, 3, &[], false));
+    assert!(tracker.observe(1500, 3, &[], false));
+}
+
+#[test]
+fn test_new_records_and_loading_frames_restart_the_quiet_period() {
+    let mut tracker = tracker();
+    assert!(!tracker.observe(0, 1, &[], false));
+    assert!(!tracker.observe(500, 1, &[], false));
+    assert!(!tracker.observe(1000, 2, &[], false));
+    assert!(!tracker.observe(1500, 2, &[], false));
+    assert!(!tracker.observe(2000, 2, &[], true));
+    assert!(!tracker.observe(2500, 2, &[], false));
+    assert!(!tracker.observe(3000, 2, &[], false));
+    assert!(tracker.observe(3500, 2, &[], false));
+}
+
+#[test]
+fn test_open_requests_are_waited_for() {
+    let mut tracker = tracker();
+    let open = [request("https://example.com/api/slow", 0)];
+    assert!(!tracker.observe(0, 1, &open, false));
+    assert!(!tracker.observe(1000, 1, &open, false));
+    assert!(!tracker.observe(2000, 1, &open, false));
+    // It finished, adding its record
+    assert!(!tracker.observe(2500, 2, &[], false));
+    assert!(!tracker.observe(3000, 2, &[], false));
+    assert!(!tracker.observe(3500, 2, &[], false));
+    assert!(tracker.observe(4000, 2, &[], false));
+}
+
+#[test]
+fn test_long_polls_stop_blocking_idle() {
+    let mut tracker = tracker();
+    let poll = [request("https://example.com/poll", 200)];
+    let mut idle_at = None;
+    for now in (0..10_000).step_by(500) {
+        if tracker.observe(now, 4, &poll, false) {
+            idle_at = Some(now);
+            break;
+        }
+    }
+    // Waited for until it had been open 5 seconds, then quiet for one more
+    assert_eq!(idle_at, Some(6_500));
+    assert_eq!(tracker.long_lived(&poll, 6_500), vec![&poll[0]]);
+    assert!(tracker.long_lived(&poll, 5_000).is_empty());
+}
+
+#[test]
+fn test_streams_never_block_idle() {
+    let mut tracker = tracker();
+    let stream = [InFlightRequest {
+        streaming: true,
+        ..request("https://example.com/events", 900)
+    }];
+    assert!(!tracker.observe(1000, 2, &stream, false));
+    assert!(!tracker.observe(1500, 2, &stream, false));
+    assert!(!tracker.observe(2000, 2, &stream, false));
+    assert!(tracker.observe(2500, 2, &stream, false));
+}
+
+#[test]
+fn test_a_new_request_blocks_idle_next_to_a_long_poll() {
+    let mut tracker = tracker();
+    let poll = request("https://example.com/poll", 0);
+    let fetch = request("https://example.com/api/items", 6_000);
+    assert!(!tracker.observe(6_000, 5, std::slice::from_ref(&poll), false));
+    assert!(!tracker.observe(6_500, 5, &[poll.clone(), fetch.clone()], false));
+    assert!(!tracker.observe(7_000, 5, &[poll.clone(), fetch], false));
+    assert!(!tracker.observe(7_500, 6, std::slice::from_ref(&poll), false));
+    assert!(!tracker.observe(8_000, 6, std::slice::from_ref(&poll), false));
+    assert!(!tracker.observe(8_500, 6, std::slice::from_ref(&poll), false));
+    assert!(tracker.observe(9_000, 6, &[poll], false));
+}
+
+#[test]
+fn test_recorder_tracks_requests_in_flight() {
+    let recorder = RequestRecorder::new();
+    let mut sse = HeaderMap::new();
+    sse.insert(ACCEPT, "text/event-stream".parse().unwrap());
+    let mut websocket = HeaderMap::new();
+    websocket.insert(UPGRADE, "WebSocket".parse().unwrap());
+
+    let page = recorder.begin_request(0, "GET", "https://example.com/", &HeaderMap::new());
+    let events = recorder.begin_request(1, "GET", "https://example.com/events", &sse);
+    let socket = recorder.begin_request(2, "GET", "https://example.com/ws", &websocket);
+
+    let in_flight = recorder.in_flight();
+    let urls: Vec<String> = in_flight.iter().map(ToString::to_string).collect();
+    assert_eq!(
+        urls,
+        [
+            "GET https://example.com/",
+            "GET https://example.com/events",
+            "GET https://example.com/ws",
+        ]
+    );
+    let streaming: Vec<bool> = in_flight.iter().map(|r| r.streaming).collect();
+    assert_eq!(streaming, [false, true, true]);
+
+    drop(events);
+    drop(page);
+    assert_eq!(recorder.in_flight().len(), 1);
+    drop(socket);
+    assert!(recorder.in_flight().is_empty());
+}
+
+#[tokio::test]
+async fn test_proxy_lists_requests_until_answered() {
+    let upstream = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/poll"))
+        .respond_with(
+            ResponseTemplate::new(200)
+                .set_body_string("{}")
+                .set_delay(Duration::from_millis(1500)),
+        )
+        .mount(&upstream)
+        .await;
+
+    let proxy = HttpProxy::start(0).await.unwrap();
+    let client = reqwest::Client::builder()
+        .proxy(reqwest::Proxy::http(format!("http://127.0.0.1:{}", proxy.get_port())).unwrap())
+        .build()
+        .unwrap();
+    let url = format!("{}/poll", upstream.uri());
+    let pending = tokio::spawn(client.get(&url).send());
+
+    let mut in_flight = Vec::new();
+    for _ in 0..50 {
+        in_flight = proxy.in_flight();
+        if !in_flight.is_empty() {
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(20)).await;
+    }
+    assert_eq!(in_flight.len(), 1);
+    assert!(in_flight[0].url.ends_with("/poll"), "{}", in_flight[0]);
+    assert_eq!(proxy.record_count(), 0);
+
+    assert_eq!(pending.await.unwrap().unwrap().status(), 200);
+    assert!(proxy.in_flight().is_empty());
+    assert_eq!(proxy.record_count(), 1);
+
+    proxy.stop().await.unwrap();
+}
@@ -0,0 +1,161 @@
+use std::io::Write;
+
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use url::Url;
+use wiremock::matchers::{method, path};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+use crate::capture::sitemap::*;
+
+const PAGES: &[u8] = include_bytes!("fixtures/sitemaps/pages.xml");
+const INDEX: &[u8] = include_bytes!("fixtures/sitemaps/index.xml");
+const POSTS: &[u8] = include_bytes!("fixtures/sitemaps/posts.xml");
+
+fn gzip(data: &[u8]) -> Vec<u8> {
+    let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+    encoder.write_all(data).unwrap();
+    encoder.finish().unwrap()
+}
+
+fn urls(document: SitemapDocument) -> Vec<String> {
+    match document {
+        SitemapDocument::Pages(urls) | SitemapDocument::Index(urls) => {
+            urls.into_iter().map(String::from).collect()
+        }
+    }
+}
+
+#[test]
+fn test_parse_sitemap_resolves_relative_urls() {
+    let base = Url::parse("https://example.com/sitemaps/pages.xml").unwrap();
+    let document = parse_sitemap(PAGES, &base).unwrap();
+
+    assert!(matches!(document, SitemapDocument::Pages(_)));
+    assert_eq!(
+        urls(document),
+        vec![
+            "https://example.com/",
+            "https://example.com/about",
+            "https://example.com/sitemaps/blog/first-post?ref=sitemap&lang=en",
+            "https://cdn.example.net/landing",
+        ]
+    );
+}
+
+#[test]
+fn test_parse_gzipped_sitemap() {
+    let base = Url::parse("https://example.com/sitemaps/pages.xml.gz").unwrap();
+    let plain = parse_sitemap(PAGES, &base).unwrap();
+    let compressed = parse_sitemap(&gzip(PAGES), &base).unwrap();
+
+    assert_eq!(compressed, plain);
+}
+
+#[test]
+fn test_parse_sitemap_index() {
+    let base = Url::parse("https://example.com/sitemaps/index.xml").unwrap();
+    let document = parse_sitemap(INDEX, &base).unwrap();
+
+    assert!(matches!(document, SitemapDocument::Index(_)));
+    assert_eq!(
+        urls(document),
+        vec![
+            "https://example.com/sitemaps/pages.xml",
+            "https://example.com/sitemaps/posts.xml.gz",
+            "https://example.com/sitemaps/missing.xml",
+        ]
+    );
+}
+
+#[test]
+fn test_parse_sitemap_rejects_other_documents() {
+    let base = Url::parse("https://example.com/sitemap.xml").unwrap();
+
+    let error = parse_sitemap(b"<html><body>Not found</body></html>", &base).unwrap_err();
+    assert!(error.to_string().contains("not a sitemap"));
+    assert!(parse_sitemap(b"<urlset><url>", &base).is_err());
+    assert!(parse_sitemap(&[0x1f, 0x8b, 0x00, 0x01], &base).is_err());
+}
+
+async fn sitemap_server() -> MockServer {
+    let server = MockServer::start().await;
+    for (route, body) in [
+        ("/sitemaps/index.xml", INDEX.to_vec()),
+        ("/sitemaps/pages.xml", PAGES.to_vec()),
+        ("/sitemaps/posts.xml.gz", gzip(POSTS)),
+    ] {
+        Mock::given(method("GET"))
+            .and(path(route))
+            .respond_with(ResponseTemplate::new(200).set_body_bytes(body))
+            .mount(&server)
+            .await;
+    }
+    Mock::given(method("GET"))
+        .and(path("/sitemap.xml"))
+        .respond_with(ResponseTemplate::new(301).insert_header("location", "/sitemaps/index.xml"))
+        .mount(&server)
+        .await;
+    server
+}
+
+#[tokio::test]
+async fn test_collect_sitemap_urls_follows_index() {
+    let server = sitemap_server().await;
+    let origin = server.uri();
+
+    let pages = collect_sitemap_urls(
+        &format!("{}/sitemap.xml", origin),
+        &SitemapOptions::default(),
+    )
+    .await
+    .unwrap();
+
+    // The missing nested sitemap is skipped and duplicates are dropped
+    assert_eq!(
+        pages,
+        vec![
+            "https://example.com/".to_string(),
+            format!("{}/about", origin),
+            format!("{}/sitemaps/blog/first-post?ref=sitemap&lang=en", origin),
+            "https://cdn.example.net/landing".to_string(),
+            format!("{}/posts/1", origin),
+            format!("{}/posts/2", origin),
+        ]
+    );
+}
+
+#[tokio::test]
+async fn test_collect_sitemap_urls_limit_and_same_host() {
+    let server = sitemap_server().await;
+    let origin = server.uri();
+    let sitemap_url = format!("{}/sitemaps/index.xml", origin);
+
+    let options = SitemapOptions {
+        limit: 3,
+        same_host_only: true,
+    };
+    let pages = collect_sitemap_urls(&sitemap_url, &options).await.unwrap();
+
+    assert_eq!(
+        pages,
+        vec![
+            format!("{}/about", origin),
+            format!("{}/sitemaps/blog/first-post?ref=sitemap&lang=en", origin),
+            format!("{}/posts/1", origin),
+        ]
+    );
+}
+
+#[tokio::test]
+async fn test_collect_sitemap_urls_missing_root_is_an_error() {
+    let server = MockServer::start().await;
+
+    let error = collect_sitemap_urls(
+        &format!("{}/sitemap.xml", server.uri()),
+        &SitemapOptions::default(),
+    )
+    .await
+    .unwrap_err();
+    assert!(error.to_string().contains("404"));
+}
@@ -0,0 +1,85 @@
+use std::io::Write;
+
+use crate::capture::variant::*;
+
+const COOKIES: &str = "# Netscape HTTP Cookie File\n\
+.example.com\tTRUE\t/\tTRUE\t2000000000\tsession\tadmin-token\n\
+#HttpOnly_example.com\tFALSE\t/account\tFALSE\t0\tcsrf\tabc=def\n\
+\n\
+Authorization: Bearer admin\n";
+
+#[test]
+fn test_cookie_file_parses_cookies_and_headers() {
+    let variant = CaptureVariant::from_cookie_file("admin".to_string(), COOKIES).unwrap();
+
+    assert_eq!(variant.name, "admin");
+    assert_eq!(
+        variant.cookies,
+        vec![
+            VariantCookie {
+                name: "session".to_string(),
+                value: "admin-token".to_string(),
+                domain: ".example.com".to_string(),
+                path: "/".to_string(),
+                secure: true,
+                http_only: false,
+                expires: Some(2_000_000_000),
+            },
+            VariantCookie {
+                name: "csrf".to_string(),
+                value: "abc=def".to_string(),
+                domain: "example.com".to_string(),
+                path: "/account".to_string(),
+                secure: false,
+                http_only: true,
+                expires: None,
+            },
+        ]
+    );
+    assert_eq!(
+        variant.headers,
+        vec![("Authorization".to_string(), "Bearer admin".to_string())]
+    );
+}
+
+#[test]
+fn test_cookie_file_rejects_malformed_lines() {
+    let error = CaptureVariant::from_cookie_file(
+        "admin".to_string(),
+        "example.com\tFALSE\t/\tFALSE\tsoon\tsession\tx\n",
+    )
+    .unwrap_err();
+    assert!(error.to_string().contains("line 1"), "{}", error);
+
+    let error = CaptureVariant::from_cookie_file("admin".to_string(), "# comment\nnot a cookie\n")
+        .unwrap_err();
+    assert!(error.to_string().contains("line 2"), "{}", error);
+
+    assert!(CaptureVariant::from_cookie_file("admin".to_string(), "# only comments\n").is_err());
+}
+
+#[test]
+fn test_variant_spec_reads_the_cookie_file() {
+    let mut file = tempfile::NamedTempFile::new().unwrap();
+    file.write_all(COOKIES.as_bytes()).unwrap();
+
+    let variant = CaptureVariant::parse(&format!("admin:{}", file.path().display())).unwrap();
+    assert_eq!(variant.name, "admin");
+    assert_eq!(variant.cookies.len(), 2);
+
+    assert!(CaptureVariant::parse("admin").is_err());
+    assert!(CaptureVariant::parse("admin:/nonexistent/cookies.txt").is_err());
+    assert!(CaptureVariant::parse(&format!("bad name:{}", file.path().display())).is_err());
+}
+
+#[test]
+fn test_variant_names() {
+    assert_eq!(parse_variant_name(" guest_2 ").unwrap(), "guest_2");
+    assert!(parse_variant_name("").is_err());
+    assert!(parse_variant_name("admin/1").is_err());
+
+    let admin = CaptureVariant::from_cookie_file("admin".to_string(), "X-Role: admin").unwrap();
+    let member = CaptureVariant::from_cookie_file("member".to_string(), "X-Role: member").unwrap();
+    assert!(check_unique_names(&[admin.clone(), member]).is_ok());
+    assert!(check_unique_names(&[admin.clone(), admin]).is_err());
+}
@@ -0,0 +1,147 @@
+use crate::capture::proxy::recorder::RequestRecorder;
+use crate::capture::proxy::{HostAllowlist, RedactionRules, RequestRecord};
+use crate::capture::warnings::MAX_EXAMPLES;
+use crate::capture::{CaptureStatus, CaptureWarning, CaptureWarningKind, CaptureWarnings};
+use crate::test_utils::test_helpers::create_test_record;
+
+fn record(url: &str, body: &[u8]) -> RequestRecord {
+    create_test_record("GET", url, 200, &[], body)
+}
+
+fn kinds(warnings: &CaptureWarnings) -> Vec<CaptureWarningKind> {
+    warnings.list().iter().map(|warning| warning.kind).collect()
+}
+
+#[test]
+fn test_warnings_are_grouped_by_kind() {
+    let warnings = CaptureWarnings::new();
+    assert!(warnings.is_empty());
+
+    warnings.note(CaptureWarningKind::UnsavedArtifact, "Screenshot");
+    warnings.note(CaptureWarningKind::BlockedRequest, "ads.example.com");
+    warnings.note(CaptureWarningKind::BlockedRequest, "ads.example.com");
+    warnings.note(CaptureWarningKind::BlockedRequest, "cdn.example.net");
+    warnings.note_many(CaptureWarningKind::ConnectionFailure, 0, "nothing.example");
+
+    // Listed in kind order, not the order they were noted in
+    assert_eq!(
+        kinds(&warnings),
+        vec![
+            CaptureWarningKind::BlockedRequest,
+            CaptureWarningKind::UnsavedArtifact,
+        ]
+    );
+    let blocked = &warnings.list()[0];
+    assert_eq!(blocked.count, 3);
+    assert_eq!(blocked.examples, vec!["ads.example.com", "cdn.example.net"]);
+    assert_eq!(
+        blocked.to_string(),
+        "3 requests were blocked by --only-hosts (ads.example.com, cdn.example.net)"
+    );
+
+    warnings.clear();
+    assert!(warnings.is_empty());
+}
+
+#[test]
+fn test_warnings_keep_few_examples() {
+    let warnings = CaptureWarnings::new();
+    for i in 0..MAX_EXAMPLES + 3 {
+        warnings.note(
+            CaptureWarningKind::TruncatedBody,
+            &format!("https://example.com/{}", i),
+        );
+    }
+    warnings.note_many(CaptureWarningKind::UncapturedPage, 4, "");
+
+    let list = warnings.list();
+    assert_eq!(list[0].count, (MAX_EXAMPLES + 3) as u64);
+    assert_eq!(list[0].examples.len(), MAX_EXAMPLES);
+    assert_eq!(list[1].to_string(), "4 sitemap pages were not captured");
+}
+
+#[test]
+fn test_clones_share_warnings() {
+    let warnings = CaptureWarnings::new();
+    let shared = warnings.clone();
+    shared.note(CaptureWarningKind::PinnedHost, "bank.example.com:443");
+    assert_eq!(kinds(&warnings), vec![CaptureWarningKind::PinnedHost]);
+}
+
+#[test]
+fn test_capture_status_exit_codes() {
+    let warning = CaptureWarning {
+        kind: CaptureWarningKind::RetriedRequest,
+        count: 1,
+        examples: Vec::new(),
+    };
+    assert_eq!(CaptureStatus::of(&[]), CaptureStatus::Success);
+    assert_eq!(
+        CaptureStatus::of(&[warning]),
+        CaptureStatus::SuccessWithWarnings
+    );
+
+    // (status, --fail-on-warnings, exit code)
+    let matrix = [
+        (CaptureStatus::Success, false, 0),
+        (CaptureStatus::Success, true, 0),
+        (CaptureStatus::SuccessWithWarnings, false, 0),
+        (CaptureStatus::SuccessWithWarnings, true, 1),
+        (CaptureStatus::Failure, false, 1),
+        (CaptureStatus::Failure, true, 1),
+    ];
+    for (status, fail_on_warnings, code) in matrix {
+        assert_eq!(
+            status.exit_code(fail_on_warnings),
+            code,
+            "{:?} with fail_on_warnings={}",
+            status,
+            fail_on_warnings
+        );
+    }
+}
+
+#[tokio::test]
+async fn test_recorder_notes_filtered_and_truncated_requests() {
+    let warnings = CaptureWarnings::new();
+    let recorder = RequestRecorder::with_redaction(RedactionRules::new().with_max_body_size(4))
+        .with_max_requests(Some(2))
+        .with_only_hosts(Some(HostAllowlist::parse("example.com").unwrap()))
+        .with_warnings(warnings.clone());
+
+    assert!(!recorder.allow_host("tracker.example.net"));
+    recorder
+        .record_request(record("https://example.com/small", b"tiny"))
+        .await;
+    recorder
+        .record_request(record("https://example.com/large", b"far too large"))
+        .await;
+    recorder
+        .record_request(record("https://example.com/late", b""))
+        .await;
+
+    assert_eq!(
+        kinds(&warnings),
+        vec![
+            CaptureWarningKind::BlockedRequest,
+            CaptureWarningKind::SkippedRequest,
+            CaptureWarningKind::TruncatedBody,
+        ]
+    );
+    let list = warnings.list();
+    assert_eq!(list[0].examples, vec!["tracker.example.net"]);
+    assert_eq!(list[1].examples, vec!["https://example.com/late"]);
+    assert_eq!(list[2].examples, vec!["https://example.com/large"]);
+}
+
+#[tokio::test]
+async fn test_recorder_notes_each_pinned_host_once() {
+    let recorder = RequestRecorder::new();
+    assert!(recorder.mark_pinned("bank.example.com:443").await);
+    assert!(!recorder.mark_pinned("bank.example.com:443").await);
+
+    let list = recorder.warnings().list();
+    assert_eq!(list.len(), 1);
+    assert_eq!(list[0].kind, CaptureWarningKind::PinnedHost);
+    assert_eq!(list[0].count, 1);
+}
@@ -0,0 +1,150 @@
+//! Named capture variants for `capture --variant name:cookie-file`
+//!
+//! A page often looks different to a signed-in admin than to a guest, and
+//! most of what it loads is the same in both. Instead of two captures, the
+//! page is loaded once in the default state and then once more per variant
+//! with that variant's cookies, and the records of each reload are tagged
+//! with the variant's name. `serve --variant` picks one state to replay.
+//!
+//! The cookie file uses the Netscape `cookies.txt` format written by curl
+//! and browser extensions. Lines of the form `Header-Name: value` add a
+//! request header instead, for sessions carried in an `Authorization`
+//! header.
+
+use std::path::Path;
+
+use crate::error::{Result, WebMockError};
+
+/// Prefix curl gives HttpOnly cookies in a cookie file
+const HTTP_ONLY_PREFIX: &str = "#HttpOnly_";
+
+/// A cookie set in the browser before a variant's reload
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VariantCookie {
+    pub name: String,
+    pub value: String,
+    /// Domain as written in the file; a leading dot includes subdomains
+    pub domain: String,
+    pub path: String,
+    pub secure: bool,
+    pub http_only: bool,
+    /// Expiry in seconds since the Unix epoch; `None` for a session cookie
+    pub expires: Option<i64>,
+}
+
+/// A state the page is captured in, in addition to the default one
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CaptureVariant {
+    /// Name the variant's records are tagged with
+    pub name: String,
+    pub cookies: Vec<VariantCookie>,
+    /// Extra request headers sent with every request of the reload
+    pub headers: Vec<(String, String)>,
+}
+
+impl CaptureVariant {
+    /// Parse a `--variant` value such as `admin:cookies/admin.txt`, reading the cookie file
+    pub fn parse(spec: &str) -> Result<Self> {
+        let (name, path) = spec.split_once(':').ok_or_else(|| {
+            WebMockError::config(format!(
+                "Invalid --variant '{}': expected NAME:COOKIE_FILE, e.g. admin:admin-cookies.txt",
+                spec
+            ))
+        })?;
+        let name = parse_variant_name(name)?;
+        let path = Path::new(path.trim());
+        let contents = std::fs::read_to_string(path)
+            .map_err(|e| WebMockError::io("read cookie file", path, e))?;
+        Self::from_cookie_file(name, &contents)
+            .map_err(|e| WebMockError::config(format!("{}: {}", path.display(), e)))
+    }
+
+    /// Build a variant from the contents of its cookie file
+    pub fn from_cookie_file(name: String, contents: &str) -> Result<Self> {
+        let mut variant = Self {
+            name,
+            cookies: Vec::new(),
+            headers: Vec::new(),
+        };
+        for (index, line) in contents.lines().enumerate() {
+            let line = line.trim_end_matches('\r');
+            let invalid =
+                |reason: &str| WebMockError::config(format!("line {}: {}", index + 1, reason));
+
+            let (line, http_only) = match line.strip_prefix(HTTP_ONLY_PREFIX) {
+                Some(rest) => (rest, true),
+                None => (line, false),
+            };
+            if line.trim().is_empty() || (!http_only && line.starts_with('#')) {
+                continue;
+            }
+
+            let fields: Vec<&str> = line.split('\t').collect();
+            if fields.len() == 7 {
+                let expires = fields[4]
+                    .trim()
+                    .parse::<i64>()
+                    .map_err(|_| invalid("expiry must be a Unix timestamp"))?;
+                variant.cookies.push(VariantCookie {
+                    name: fields[5].to_string(),
+                    value: fields[6].to_string(),
+                    domain: fields[0].to_string(),
+                    path: fields[2].to_string(),
+                    secure: fields[3].eq_ignore_ascii_case("TRUE"),
+                    http_only,
+                    expires: (expires > 0).then_some(expires),
+                });
+            } else if let Some((header, value)) = line.split_once(':').filter(|_| fields.len() == 1)
+            {
+                let header = header.trim();
+                if header.is_empty() || header.contains(char::is_whitespace) {
+                    return Err(invalid("expected a header name before ':'"));
+                }
+                variant
+                    .headers
+                    .push((header.to_string(), value.trim().to_string()));
+            } else {
+                return Err(invalid(
+                    "expected a tab-separated cookies.txt line or a 'Header: value' line",
+                ));
+            }
+        }
+
+        if variant.cookies.is_empty() && variant.headers.is_empty() {
+            return Err(WebMockError::config("no cookies or headers found"));
+        }
+        Ok(variant)
+    }
+}
+
+/// Check a variant name: letters, digits, `-` and `_`
+pub fn parse_variant_name(name: &str) -> Result<String> {
+    let name = name.trim();
+    if name.is_empty()
+        || !name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+    {
+        return Err(WebMockError::config(format!(
+            "Invalid variant name '{}': use letters, digits, '-' and '_'",
+            name
+        )));
+    }
+    Ok(name.to_string())
+}
+
+/// Check that no two variants share a name
+pub fn check_unique_names(variants: &[CaptureVariant]) -> Result<()> {
+    for (index, variant) in variants.iter().enumerate() {
+        if variants[..index]
+            .iter()
+            .any(|other| other.name == variant.name)
+        {
+            return Err(WebMockError::config(format!(
+                "Variant '{}' is given more than once",
+                variant.name
+            )));
+        }
+    }
+    Ok(())
+}
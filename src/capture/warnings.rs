@@ -0,0 +1,188 @@
+//! Warnings a capture collects on its way to a snapshot
+//!
+//! A capture can succeed and still miss traffic: hosts refused by a filter,
+//! bodies cut at `--max-body-size`, hosts that pinned their certificate, or
+//! artifacts that couldn't be written. The session and the proxy note these
+//! in a shared [`CaptureWarnings`] as they happen, and the capture command
+//! prints them in one block at the end; with `--fail-on-warnings` they turn
+//! the exit status into a failure, see [`CaptureStatus`].
+
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::sync::{Arc, Mutex};
+
+/// Most examples kept for each kind of warning
+pub const MAX_EXAMPLES: usize = 5;
+
+/// What went wrong, in the order the warnings block lists it
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CaptureWarningKind {
+    /// A host rejected the capture certificate and its traffic was tunneled unrecorded
+    PinnedHost,
+    /// Requests refused because their host isn't in `--only-hosts`
+    BlockedRequest,
+    /// Requests forwarded past `--max-requests` without a record
+    SkippedRequest,
+    /// Bodies cut at `--max-body-size`
+    TruncatedBody,
+    /// Requests that needed upstream retries
+    RetriedRequest,
+    /// Connections the proxy lost before a record was made
+    ConnectionFailure,
+    /// Capture attempts that failed and were started over
+    RetriedCapture,
+    /// Sitemap pages that failed or weren't reached before `--total-timeout`
+    UncapturedPage,
+    /// Screenshots or DOM dumps that couldn't be written next to the snapshot
+    UnsavedArtifact,
+}
+
+/// One kind of warning, with how often it happened and a few examples
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct CaptureWarning {
+    pub kind: CaptureWarningKind,
+    pub count: u64,
+    /// Hosts, URLs or reasons, the first [`MAX_EXAMPLES`] distinct ones
+    pub examples: Vec<String>,
+}
+
+impl fmt::Display for CaptureWarning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let count = self.count;
+        match self.kind {
+            CaptureWarningKind::PinnedHost => write!(
+                f,
+                "{} hosts rejected the capture certificate and were tunneled without recording",
+                count
+            )?,
+            CaptureWarningKind::BlockedRequest => {
+                write!(f, "{} requests were blocked by --only-hosts", count)?
+            }
+            CaptureWarningKind::SkippedRequest => write!(
+                f,
+                "{} requests past --max-requests were forwarded but not recorded",
+                count
+            )?,
+            CaptureWarningKind::TruncatedBody => {
+                write!(f, "{} bodies were truncated to --max-body-size", count)?
+            }
+            CaptureWarningKind::RetriedRequest => {
+                write!(f, "{} requests needed upstream retries", count)?
+            }
+            CaptureWarningKind::ConnectionFailure => write!(
+                f,
+                "{} proxy connections failed before their requests were recorded",
+                count
+            )?,
+            CaptureWarningKind::RetriedCapture => {
+                write!(f, "The capture was started over {} times", count)?
+            }
+            CaptureWarningKind::UncapturedPage => {
+                write!(f, "{} sitemap pages were not captured", count)?
+            }
+            CaptureWarningKind::UnsavedArtifact => {
+                write!(f, "{} capture artifacts were not saved", count)?
+            }
+        }
+        if !self.examples.is_empty() {
+            write!(f, " ({})", self.examples.join(", "))?;
+        }
+        Ok(())
+    }
+}
+
+/// Collects warnings while a capture runs; clones share the same list
+#[derive(Debug, Clone, Default)]
+pub struct CaptureWarnings {
+    warnings: Arc<Mutex<BTreeMap<CaptureWarningKind, CaptureWarning>>>,
+}
+
+impl CaptureWarnings {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Count one warning of `kind` about `subject`, e.g. a host or URL
+    pub fn note(&self, kind: CaptureWarningKind, subject: &str) {
+        self.note_many(kind, 1, subject);
+    }
+
+    /// Count `count` warnings of `kind` at once, with `subject` as their example
+    pub fn note_many(&self, kind: CaptureWarningKind, count: u64, subject: &str) {
+        if count == 0 {
+            return;
+        }
+        let mut warnings = self.warnings.lock().unwrap_or_else(|e| e.into_inner());
+        let warning = warnings.entry(kind).or_insert_with(|| CaptureWarning {
+            kind,
+            count: 0,
+            examples: Vec::new(),
+        });
+        warning.count += count;
+        if !subject.is_empty()
+            && warning.examples.len() < MAX_EXAMPLES
+            && !warning.examples.iter().any(|example| example == subject)
+        {
+            warning.examples.push(subject.to_string());
+        }
+    }
+
+    /// Warnings noted so far, in [`CaptureWarningKind`] order
+    pub fn list(&self) -> Vec<CaptureWarning> {
+        self.warnings
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .values()
+            .cloned()
+            .collect()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.warnings
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .is_empty()
+    }
+
+    /// Forget the warnings noted so far, as when a capture starts over
+    pub fn clear(&self) {
+        self.warnings
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .clear();
+    }
+}
+
+/// How a capture ended, as far as CI is concerned
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CaptureStatus {
+    /// The snapshot was saved and nothing went wrong
+    Success,
+    /// The snapshot was saved, but warnings were noted on the way
+    SuccessWithWarnings,
+    /// No snapshot was saved
+    Failure,
+}
+
+impl CaptureStatus {
+    /// Status of a capture that saved its snapshot with `warnings`
+    pub fn of(warnings: &[CaptureWarning]) -> Self {
+        if warnings.is_empty() {
+            CaptureStatus::Success
+        } else {
+            CaptureStatus::SuccessWithWarnings
+        }
+    }
+
+    /// Exit code the process ends with; warnings only fail with `--fail-on-warnings`
+    pub fn exit_code(self, fail_on_warnings: bool) -> i32 {
+        match self {
+            CaptureStatus::Success => 0,
+            CaptureStatus::SuccessWithWarnings if fail_on_warnings => 1,
+            CaptureStatus::SuccessWithWarnings => 0,
+            CaptureStatus::Failure => 1,
+        }
+    }
+}
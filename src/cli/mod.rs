@@ -1,9 +1,11 @@
 #[cfg(test)]
 mod tests;
 
+use std::net::IpAddr;
+
 use clap::{Parser, Subcommand, ValueEnum};
 
-#[derive(Debug, Clone, ValueEnum)]
+#[derive(Debug, Clone, PartialEq, Eq, ValueEnum)]
 pub enum Shell {
     Bash,
     Elvish,
@@ -12,6 +14,23 @@ pub enum Shell {
     Zsh,
 }
 
+/// How `serve` replays requests that failed during capture
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum ReplayFailuresMode {
+    /// Respond with 502 Bad Gateway
+    BadGateway,
+    /// Stall, then drop the connection
+    AsTimeout,
+}
+
+/// Formats `export` can write a snapshot in
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum ExportFormat {
+    /// OpenAPI 3.0 skeleton of the captured API calls
+    #[value(name = "openapi")]
+    OpenApi,
+}
+
 #[derive(Parser)]
 #[command(name = "webmock")]
 #[command(about = "A CLI tool for recording and mocking web pages")]
@@ -57,6 +76,7 @@ COMMON WORKFLOWS:
 MANAGEMENT:
     webmock list                    # View all snapshots
     webmock delete old-snapshot     # Clean up storage
+    webmock ca trust                # Trust the HTTPS certificate
 
 REQUIREMENTS:
     • Google Chrome or Chromium browser
@@ -74,10 +94,46 @@ pub struct Cli {
     #[arg(long, value_enum, hide = true)]
     pub generate_completion: Option<Shell>,
 
+    /// Only print warnings, errors and command output
+    #[arg(
+        long,
+        short = 'q',
+        global = true,
+        help = "Don't print progress or status messages, only warnings, errors and command output (for serve: no line per request)"
+    )]
+    pub quiet: bool,
+
+    /// Print without colors; also enabled by a non-empty NO_COLOR variable
+    #[arg(
+        long,
+        global = true,
+        help = "Disable colored output (also set by the NO_COLOR environment variable)"
+    )]
+    pub no_color: bool,
+
+    /// Print dates in UTC rather than local time
+    #[arg(
+        long,
+        global = true,
+        help = "Print snapshot dates in UTC without the relative time, as before (scripts should prefer --json)"
+    )]
+    pub utc: bool,
+
+    /// Read snapshots missing from the storage directory from this one, never writing to it
+    #[arg(
+        long,
+        global = true,
+        value_name = "DIR",
+        help = "Also read snapshots from this read-only storage directory; writes go to --storage, and deleting a read-only snapshot only hides it"
+    )]
+    pub storage_ro: Option<String>,
+
     #[command(subcommand)]
     pub command: Option<Commands>,
 }
 
+// Parsed once per run, so the size of the capture variant doesn't matter
+#[allow(clippy::large_enum_variant)]
 #[derive(Subcommand)]
 pub enum Commands {
     /// Capture a web page and all its network requests
@@ -104,6 +160,67 @@ EXAMPLES:
     # Capture a local development site
     webmock capture http://localhost:3000 --name local-app
 
+    # Scrub credentials before they are stored
+    webmock capture https://app.example.com --name app --redact-defaults --redact-header x-api-key
+
+    # In CI, only recapture when the snapshot is missing or older than a day
+    webmock capture https://app.example.com --name app --max-age 24h
+
+    # Capture the French site as a desktop user in Paris would see it
+    webmock capture https://example.com --name fr --locale fr-FR --timezone Europe/Paris --viewport 1440x900
+
+    # Capture the mobile site as an iPhone 13
+    webmock capture https://example.com --name mobile --device iphone-13
+
+    # A single-page app holding a long-poll open, done once its DOM is parsed and the network is quiet
+    webmock capture https://app.example.com --name app --settle-strategy networkidle
+
+    # Capture up to 20 pages of a site's sitemap into one snapshot
+    webmock capture --from-sitemap https://example.com/sitemap.xml --name site --limit 20 --same-host-only
+
+    # Watch each request as it is recorded
+    webmock capture https://example.com --name my-site --tail
+
+    # Keep a page that polls forever from filling memory; traffic past the cap is still forwarded
+    webmock capture https://example.com --name my-site --max-requests 5000
+
+    # Block third-party hosts so the capture finishes sooner and stays small
+    webmock capture https://app.example.com --name app --only-hosts 'api.example.com,*.cdn.example.com'
+
+    # Ride out a flaky origin that sometimes answers 502
+    webmock capture https://example.com --name my-site --upstream-retries 3
+
+    # Capture in a Chrome you started yourself; only the tab webmock opens is closed
+    google-chrome --remote-debugging-port=9222 --proxy-server=127.0.0.1:8899 --ignore-certificate-errors
+    webmock capture https://example.com --name my-site --attach-port 9222 --proxy-port 8899
+
+    # Save byte-identical snapshots of an unchanged test site, for content-addressed caches
+    webmock capture http://localhost:3000 --name fixture --deterministic
+
+    # See what a PWA's service worker serves offline, compared to the online load
+    webmock capture https://app.example.com --name pwa --emulate-offline-after-load
+
+    # Skip the screenshot and DOM saved next to the snapshot
+    webmock capture https://example.com --name my-site --no-artifacts
+
+    # Also capture the page as signed-in users, from exported cookie files
+    webmock capture https://example.com --name my-site --variant admin:admin-cookies.txt --variant member:member-cookies.txt
+
+    # Show how long the capture took and how much it downloaded
+    webmock capture https://example.com --name my-site --metrics
+
+    # Encrypt the snapshot at rest
+    webmock capture https://example.com --name my-site --encrypt
+
+    # Fail a CI job when the capture saved its snapshot with warnings
+    webmock capture https://example.com --name my-site --fail-on-warnings
+
+    # Give up early, without a snapshot, on a site that is down or throws on load
+    webmock capture https://example.com --name my-site --abort-on-status 5xx --abort-on-js-errors 5
+
+    # Record who approved the capture in the snapshot's provenance, without this machine's hostname
+    webmock capture https://example.com --name my-site --consent-note \"approved by legal, ticket ABC-123\" --no-provenance
+
 REQUIREMENTS:
     • Google Chrome or Chromium browser must be installed
     • Internet connection (for external URLs)
@@ -111,8 +228,12 @@ REQUIREMENTS:
     )]
     Capture {
         /// The URL to capture (must be HTTP or HTTPS)
-        #[arg(help = "The URL to capture (e.g., https://example.com)")]
-        url: String,
+        #[arg(
+            required_unless_present = "from_sitemap",
+            conflicts_with = "from_sitemap",
+            help = "The URL to capture (e.g., https://example.com)"
+        )]
+        url: Option<String>,
 
         /// Name for the snapshot (alphanumeric, hyphens, underscores only)
         #[arg(long, help = "Name for the snapshot (e.g., my-site, api-v1)")]
@@ -122,13 +243,304 @@ REQUIREMENTS:
         #[arg(
             long,
             default_value = "30",
-            help = "Timeout in seconds (default: 30, max: 600)"
+            help = "Timeout in seconds (default: 30, max: 600); per page with --from-sitemap"
         )]
         timeout: u64,
 
+        /// Capture every page listed in a sitemap into one snapshot
+        #[arg(
+            long,
+            value_name = "SITEMAP_URL",
+            help = "Capture the pages listed in this sitemap (or sitemap index) into one snapshot"
+        )]
+        from_sitemap: Option<String>,
+
+        /// Most sitemap pages to capture
+        #[arg(
+            long,
+            conflicts_with = "url",
+            help = "Capture at most this many sitemap pages (default: 50)"
+        )]
+        limit: Option<usize>,
+
+        /// Only capture sitemap pages on the sitemap's host
+        #[arg(
+            long,
+            conflicts_with = "url",
+            help = "Skip sitemap pages on a different host than the sitemap"
+        )]
+        same_host_only: bool,
+
+        /// Overall timeout in seconds for a sitemap capture
+        #[arg(
+            long,
+            value_name = "SECONDS",
+            conflicts_with = "url",
+            help = "Stop capturing further sitemap pages after this many seconds"
+        )]
+        total_timeout: Option<u64>,
+
         /// Custom storage directory path (default: ~/.webmock)
         #[arg(long, help = "Custom storage directory path (default: ~/.webmock)")]
         storage: Option<String>,
+
+        /// Print each request as it is recorded
+        #[arg(
+            long,
+            help = "Print a line per recorded request (method, URL, status, size) while capturing"
+        )]
+        tail: bool,
+
+        /// Header whose value is replaced with [REDACTED] in stored records (repeatable)
+        #[arg(
+            long = "redact-header",
+            value_name = "NAME",
+            help = "Replace the value of this header with [REDACTED] in stored records (repeatable)"
+        )]
+        redact_header: Vec<String>,
+
+        /// Redact authorization, cookie and set-cookie headers
+        #[arg(
+            long,
+            help = "Redact common credential headers (authorization, cookie, set-cookie)"
+        )]
+        redact_defaults: bool,
+
+        /// Regex whose matches are replaced with [REDACTED] in text bodies (repeatable)
+        #[arg(
+            long = "redact-body-pattern",
+            value_name = "REGEX",
+            help = "Replace matches of this regex in text bodies with [REDACTED] (repeatable)"
+        )]
+        redact_body_pattern: Vec<String>,
+
+        /// Maximum stored size of request and response bodies in bytes
+        #[arg(
+            long,
+            value_name = "BYTES",
+            help = "Truncate stored request/response bodies larger than this many bytes"
+        )]
+        max_body_size: Option<usize>,
+
+        /// Stop recording after this many requests
+        #[arg(
+            long,
+            value_name = "N",
+            help = "Stop recording after N requests; later requests are still forwarded so the page keeps working"
+        )]
+        max_requests: Option<usize>,
+
+        /// Only forward requests to these hosts during capture
+        #[arg(
+            long,
+            value_name = "HOSTS",
+            help = "Comma-separated hosts or globs (e.g. app.example.com,*.cdn.example.com) the proxy forwards to; others get an immediate 403. The captured page's host is always allowed"
+        )]
+        only_hosts: Option<String>,
+
+        /// Retry GET and HEAD requests that fail transiently upstream
+        #[arg(
+            long,
+            value_name = "N",
+            default_value_t = 0,
+            help = "Retry GET/HEAD requests up to N times on connect errors and 502/503/504, with exponential backoff"
+        )]
+        upstream_retries: u32,
+
+        /// Most upstream connections the proxy opens to one host
+        #[arg(
+            long,
+            value_name = "N",
+            help = "Open at most N upstream connections per host (default: 6, like browsers); further requests wait for a free connection"
+        )]
+        max_connections_per_host: Option<usize>,
+
+        /// Do nothing if a snapshot with this name already exists
+        #[arg(long, help = "Skip the capture if the snapshot already exists")]
+        if_missing: bool,
+
+        /// Recapture only if the existing snapshot is older than this (e.g. 30m, 12h, 7d)
+        #[arg(
+            long,
+            value_name = "DURATION",
+            help = "Recapture only if the existing snapshot is older than this (e.g. 30m, 12h, 7d)"
+        )]
+        max_age: Option<String>,
+
+        /// Print the age of an existing snapshot in seconds
+        #[arg(
+            long,
+            help = "Print the existing snapshot's age in seconds (for scripting)"
+        )]
+        print_age: bool,
+
+        /// Browser locale and Accept-Language (e.g. fr-FR)
+        #[arg(
+            long,
+            help = "Render the page in this locale and send it as Accept-Language (e.g. fr-FR)"
+        )]
+        locale: Option<String>,
+
+        /// Browser timezone (IANA name, e.g. Europe/Paris)
+        #[arg(long, help = "Emulate this IANA timezone (e.g. Europe/Paris)")]
+        timezone: Option<String>,
+
+        /// Browser viewport size (e.g. 1440x900)
+        #[arg(
+            long,
+            value_name = "WIDTHxHEIGHT",
+            help = "Emulate this viewport size in CSS pixels (e.g. 1440x900)"
+        )]
+        viewport: Option<String>,
+
+        /// User-Agent the browser sends
+        #[arg(
+            long,
+            value_name = "STRING",
+            help = "Send this User-Agent (overrides the --device preset's)"
+        )]
+        user_agent: Option<String>,
+
+        /// Device preset to emulate (iphone-13, pixel-7, ipad)
+        #[arg(
+            long,
+            value_name = "DEVICE",
+            help = "Emulate a device's User-Agent, viewport, pixel ratio and touch input (iphone-13, pixel-7, ipad)"
+        )]
+        device: Option<String>,
+
+        /// Capture in a new tab of a running Chrome instead of launching one
+        #[arg(
+            long,
+            value_name = "URL",
+            requires = "proxy_port",
+            help = "Capture in a running Chrome via its DevTools URL (ws://... or http://127.0.0.1:9222)"
+        )]
+        attach: Option<String>,
+
+        /// Remote debugging port of a running local Chrome to capture in
+        #[arg(
+            long,
+            value_name = "PORT",
+            conflicts_with = "attach",
+            requires = "proxy_port",
+            help = "Capture in the running Chrome started with --remote-debugging-port=PORT"
+        )]
+        attach_port: Option<u16>,
+
+        /// Port for the recording proxy
+        #[arg(
+            long,
+            value_name = "PORT",
+            help = "Listen for browser traffic on this port (default: any free port); the attached Chrome's --proxy-server"
+        )]
+        proxy_port: Option<u16>,
+
+        /// Share one browser with other captures of the same storage
+        #[arg(
+            long,
+            conflicts_with_all = ["attach", "attach_port"],
+            help = "Open a tab in the browser of another running --share-browser capture instead of launching Chrome again; the first one launches it"
+        )]
+        share_browser: bool,
+
+        /// Save the snapshot reproducibly
+        #[arg(
+            long,
+            help = "Save identical captured content as byte-identical files: records in arrival order, sub-second precision dropped from timestamps and timings"
+        )]
+        deterministic: bool,
+
+        /// Reload the page offline to capture what its service worker serves
+        #[arg(
+            long,
+            conflicts_with = "from_sitemap",
+            help = "After the page loads and its service worker activates, reload it offline and record which requests the worker answers and which still need the network"
+        )]
+        emulate_offline_after_load: bool,
+
+        /// Don't save a screenshot and the DOM of the loaded page
+        #[arg(
+            long,
+            help = "Don't save a full-page screenshot and the final DOM next to the snapshot (<name>.screenshot.png, <name>.dom.html)"
+        )]
+        no_artifacts: bool,
+
+        /// Reload the page with each variant's cookies, tagging its records (repeatable)
+        #[arg(
+            long = "variant",
+            value_name = "NAME:COOKIE_FILE",
+            conflicts_with_all = ["from_sitemap", "attach", "attach_port", "emulate_offline_after_load"],
+            help = "After the default load, load the page again with the cookies (cookies.txt format) and 'Header: value' lines of COOKIE_FILE and tag its records NAME, e.g. admin:admin-cookies.txt (repeatable)"
+        )]
+        variant: Vec<String>,
+
+        /// Print duration, throughput and memory of the capture when it finishes
+        #[arg(
+            long,
+            help = "Print how long the capture took (browser wait vs proxy forwarding), requests per second, bytes downloaded and peak memory"
+        )]
+        metrics: bool,
+
+        /// Save the snapshot encrypted with a passphrase
+        #[arg(
+            long,
+            help = "Encrypt the snapshot with a passphrase from WEBMOCK_PASSPHRASE, or asked for on the terminal"
+        )]
+        encrypt: bool,
+
+        /// What counts as a loaded page
+        #[arg(
+            long,
+            value_name = "STRATEGY",
+            help = "When the page counts as loaded: load (default), networkidle, domcontentloaded or fixed:<secs>. Network idle ignores requests open over 5s, event streams and websockets; requests still open are listed in the summary instead of failing the capture"
+        )]
+        settle_strategy: Option<String>,
+
+        /// Exit with an error when the capture finishes with warnings
+        #[arg(
+            long,
+            help = "Exit with status 1 when the snapshot was saved but the capture had warnings (pinned or blocked hosts, truncated bodies, retries, connection failures, unsaved artifacts or uncaptured pages), for CI"
+        )]
+        fail_on_warnings: bool,
+
+        /// Abort when the page throws more than this many uncaught exceptions
+        #[arg(
+            long,
+            value_name = "N",
+            help = "Abort the capture and exit with an error once the page threw more than N uncaught exceptions before it settled, instead of waiting out the timeout"
+        )]
+        abort_on_js_errors: Option<u64>,
+
+        /// Abort when the page's main document comes back with this status
+        #[arg(
+            long,
+            value_name = "STATUS",
+            help = "Abort the capture and exit with an error when the proxy recorded this status for the page's main document, after redirects: a class like 5xx or a code like 503"
+        )]
+        abort_on_status: Option<String>,
+
+        /// Save what was recorded when the capture aborts
+        #[arg(
+            long,
+            help = "Save what was recorded before --abort-on-js-errors or --abort-on-status aborted the capture as the snapshot; the capture still exits with an error"
+        )]
+        save_on_abort: bool,
+
+        /// Note on why the capture is allowed, kept in the snapshot's provenance
+        #[arg(
+            long,
+            value_name = "TEXT",
+            help = "Keep this note in the snapshot's provenance, e.g. \"approved by X, ticket ABC-123\"; shown by inspect"
+        )]
+        consent_note: Option<String>,
+
+        /// Leave the capture machine's hostname out of the provenance
+        #[arg(
+            long,
+            help = "Leave the capture machine's hostname out of the snapshot's provenance; the redacted command line, webmock version and OS are still kept"
+        )]
+        no_provenance: bool,
     },
 
     /// List all saved snapshots with details
@@ -148,6 +560,12 @@ EXAMPLES:
     # List snapshots from custom storage directory
     webmock list --storage /path/to/custom/storage
 
+    # Show total disk usage and the largest snapshots
+    webmock list --summary
+
+    # Same summary as JSON for scripts
+    webmock list --summary --json
+
 OUTPUT FORMAT:
     Each snapshot shows:
     📸 snapshot-name
@@ -159,6 +577,20 @@ OUTPUT FORMAT:
         /// Custom storage directory path (default: ~/.webmock)
         #[arg(long, help = "Custom storage directory path (default: ~/.webmock)")]
         storage: Option<String>,
+
+        /// Show aggregate disk usage instead of listing snapshots
+        #[arg(
+            long,
+            help = "Show snapshot count, total size, largest snapshots and date range"
+        )]
+        summary: bool,
+
+        /// Print the snapshots or the summary as JSON
+        #[arg(
+            long,
+            help = "Print the snapshots, with their provenance, or the summary as JSON"
+        )]
+        json: bool,
     },
 
     /// Start a mock server serving a saved snapshot
@@ -175,6 +607,8 @@ The server runs until stopped with Ctrl+C and automatically handles:
 • Content type detection
 • CORS headers when needed
 
+With --all --path-routing, every snapshot in storage is served from one plain web server instead: snapshot <name> is at /_s/<name>/ and an index of snapshots is at /. Snapshots are loaded on first use and links in their HTML are rewritten to stay under the prefix.
+
 EXAMPLES:
     # Start server on default port (8080)
     webmock serve my-site
@@ -185,6 +619,77 @@ EXAMPLES:
     # Start server with custom storage directory
     webmock serve my-site --storage /path/to/custom/storage
 
+    # Open the captured page in the default browser
+    webmock serve my-site --open
+
+    # Launch Chrome through the mock server so HTTPS pages replay
+    webmock serve my-site --open-in-chrome-proxy
+
+    # Keep the console quiet under load, or show per-request timings
+    webmock serve my-site --quiet
+    webmock serve my-site --verbose
+
+    # Make requests that failed during capture hang and drop like the original
+    webmock serve my-site --replay-failures as-timeout
+
+    # Change selected responses without editing the snapshot
+    webmock serve my-site --overrides overrides.yaml
+
+    # Serve PATCH requests from captured PUT records, and honor X-HTTP-Method-Override
+    webmock serve my-site --method-fallback PATCH=PUT --honor-method-override
+
+    # Break captured redirect loops after 5 hops instead of the default 10
+    webmock serve my-site --redirect-loop-limit 5
+
+    # Fail loudly with 502 for HTTPS hosts that weren't captured
+    webmock serve my-site --strict-connect
+
+    # Only serve the captured cart after the POST that created it
+    webmock serve checkout --stateful
+
+    # Answer captured 429s to clients that request an endpoint too fast
+    webmock serve my-api --simulate-rate-limits
+
+    # Serve records that lacked a content type exactly as captured, without guessing
+    webmock serve my-site --sniff-content-type false
+
+    # Compress text responses with brotli or gzip for clients that accept them
+    webmock serve my-site --encode br,gzip
+
+    # Let browsers revalidate assets captured without validators instead of re-downloading them
+    webmock serve my-site --generate-etags
+
+    # Mock what was captured and pass everything else through to a live staging server
+    webmock serve my-site --forward-unmatched https://staging.example.com
+
+    # See how the page loads on a slow phone connection, or a custom link
+    webmock serve my-site --throttle slow-3g
+    webmock serve my-site --throttle down=400kbps,latency=300ms
+
+    # Replay the state captured with --variant admin
+    webmock serve my-site --variant admin
+
+    # Point an app at http://localhost:8080 instead of the captured https://app.example.com
+    webmock serve my-site --map-origin https://app.example.com
+
+    # Several captured hosts, told apart by the Host header
+    webmock serve my-site --map-origin https://app.example.com=localhost:8080 \
+        --map-origin https://api.example.com=api.localhost:8080
+
+    # Don't leak a background server in CI: stop after 5 idle minutes or an hour at most
+    webmock serve my-site --idle-timeout 300s --max-lifetime 1h &
+
+    # Run in the background, then stop it again
+    webmock serve my-site --daemon --pid-file webmock.pid --log-file webmock.log
+    webmock stop --pid-file webmock.pid
+
+    # Export a span per connection and request to an OpenTelemetry collector
+    # (needs a build with --features otlp)
+    webmock serve my-site --otlp-endpoint http://localhost:4317
+
+    # Serve every snapshot at http://localhost:8080/_s/<name>/ with an index at /
+    webmock serve --all --path-routing --open
+
     # Server will show:
     🚀 Starting mock server...
        🌐 Server URL: http://localhost:8080
@@ -194,60 +699,1193 @@ EXAMPLES:
     Serve {
         /// Name of the snapshot to serve
         #[arg(
+            required_unless_present = "all",
+            conflicts_with = "all",
             help = "Name of the snapshot to serve (use 'webmock list' to see available snapshots)"
         )]
-        snapshot_name: String,
+        snapshot_name: Option<String>,
+
+        /// Serve every snapshot in the storage directory
+        #[arg(
+            long,
+            requires = "path_routing",
+            help = "Serve every snapshot in the storage directory (requires --path-routing)"
+        )]
+        all: bool,
+
+        /// Route plain requests to snapshots by a /_s/<name>/ path prefix
+        #[arg(
+            long,
+            requires = "all",
+            conflicts_with = "snapshot_name",
+            help = "Serve each snapshot under /_s/<name>/ as a plain web server instead of a proxy"
+        )]
+        path_routing: bool,
+
+        /// Snapshots kept in memory at once in path routing mode
+        #[arg(
+            long,
+            value_name = "N",
+            requires = "path_routing",
+            conflicts_with = "snapshot_name",
+            help = "Most snapshots kept loaded at once with --path-routing; the least recently used is unloaded (default: 4)"
+        )]
+        max_loaded_snapshots: Option<usize>,
 
         /// Port to run the server on (1024-65535)
         #[arg(
             long,
-            default_value = "8080",
-            help = "Port to run the server on (default: 8080)"
+            help = "Port to run the server on (default: the snapshot's stored port, or 8080)"
         )]
-        port: u16,
+        port: Option<u16>,
 
         /// Custom storage directory path (default: ~/.webmock)
         #[arg(long, help = "Custom storage directory path (default: ~/.webmock)")]
         storage: Option<String>,
-    },
-
-    /// Delete a saved snapshot permanently
-    #[command(
-        long_about = "Permanently delete a saved snapshot from disk. This action cannot be undone.
 
-The delete process:
-1. Checks if the snapshot exists
-2. Asks for confirmation (unless --force is used)
-3. Removes the snapshot file from the storage directory/snapshots/
+        /// Open the snapshot in the default browser once the server is running
+        #[arg(
+            long,
+            help = "Open the snapshot's entry page on localhost in the default browser"
+        )]
+        open: bool,
 
-EXAMPLES:
-    # Delete a snapshot
-    webmock delete old-snapshot
+        /// Launch Chrome with the mock server as its proxy
+        #[arg(
+            long,
+            conflicts_with_all = ["open", "all"],
+            help = "Launch Chrome proxied through the mock server (works for HTTPS snapshots)"
+        )]
+        open_in_chrome_proxy: bool,
 
-    # Delete from custom storage directory
-    webmock delete old-snapshot --storage /path/to/custom/storage
-4. Reports success or failure
+        /// How to replay requests that failed during capture (DNS errors, refused connections)
+        #[arg(
+            long,
+            value_enum,
+            help = "Replay capture failures as a 502 (bad-gateway, the default) or a stalled, dropped connection (as-timeout)"
+        )]
+        replay_failures: Option<ReplayFailuresMode>,
 
-EXAMPLES:
-    # Delete with confirmation prompt
-    webmock delete old-snapshot
+        /// Print matched record, latency and body size for each request
+        #[arg(
+            long,
+            short = 'v',
+            conflicts_with = "quiet",
+            help = "Add the matched record, latency and body size to each request line"
+        )]
+        verbose: bool,
+
+        /// YAML file of per-endpoint status, body and header overrides
+        #[arg(
+            long,
+            value_name = "FILE",
+            help = "YAML file overriding the status, body or headers of matching requests"
+        )]
+        overrides: Option<String>,
+
+        /// Methods to try when the request method has no record, e.g. PATCH=PUT,DELETE=POST
+        #[arg(
+            long,
+            value_name = "FROM=TO,...",
+            help = "Serve a request from a record captured with another method when its own method misses (e.g. PATCH=PUT,DELETE=POST)"
+        )]
+        method_fallback: Option<String>,
+
+        /// Match on the X-HTTP-Method-Override header
+        #[arg(
+            long,
+            help = "Match requests on the method named in their X-HTTP-Method-Override header"
+        )]
+        honor_method_override: bool,
+
+        /// Refuse a redirect served this many times in a row to one client
+        #[arg(
+            long,
+            value_name = "N",
+            default_value = "10",
+            help = "Answer 508 Loop Detected once a client is sent the same redirect more than N times in a row (0 disables)"
+        )]
+        redirect_loop_limit: u32,
+
+        /// Refuse CONNECTs to hosts that aren't in the snapshot
+        #[arg(
+            long,
+            value_name = "BOOL",
+            num_args = 0..=1,
+            require_equals = true,
+            default_missing_value = "true",
+            help = "Answer CONNECT to hosts missing from the snapshot with 502 instead of tunneling and serving 404s"
+        )]
+        strict_connect: Option<bool>,
+
+        /// Serve records only after the earlier records they depend on
+        #[arg(
+            long,
+            value_name = "BOOL",
+            num_args = 0..=1,
+            require_equals = true,
+            default_missing_value = "true",
+            help = "Answer 404 for a record until the earlier POST/PUT/PATCH/DELETE records on its path were requested (tune with 'dependencies' in --overrides)"
+        )]
+        stateful: Option<bool>,
+
+        /// Enforce the rate limits implied by captured 429 responses
+        #[arg(
+            long,
+            help = "Answer a captured 429 to clients requesting its endpoint more often than its Retry-After allows, and the captured 200 otherwise; the rate comes from RateLimit-Limit or the 200s captured before the 429"
+        )]
+        simulate_rate_limits: bool,
+
+        /// Guess content types for records captured without one
+        #[arg(
+            long,
+            value_name = "BOOL",
+            default_value_t = true,
+            action = clap::ArgAction::Set,
+            help = "Sniff a content type from the body and URL for records captured without one"
+        )]
+        sniff_content_type: bool,
+
+        /// Answer missing favicon, robots.txt and apple-touch icon requests
+        #[arg(
+            long,
+            value_name = "BOOL",
+            default_value_t = true,
+            action = clap::ArgAction::Set,
+            help = "Answer GETs for /favicon.ico, /robots.txt and apple-touch icons missing from the snapshot with harmless defaults instead of 404"
+        )]
+        synthesize_common_assets: bool,
+
+        /// Generate ETags for records captured without validators
+        #[arg(
+            long,
+            help = "Give responses captured without an ETag or Last-Modified header an ETag hashed from the body, and answer matching If-None-Match requests with 304"
+        )]
+        generate_etags: bool,
+
+        /// Send captured 103 Early Hints before the responses they preceded
+        #[arg(
+            long,
+            help = "Send the 1xx informational responses captured before a response (e.g. 103 Early Hints with preload Link headers) ahead of it; only HTTP/1.1 clients get them, others get the final response alone"
+        )]
+        replay_early_hints: bool,
+
+        /// Live backend to forward requests without a matching record to
+        #[arg(
+            long,
+            value_name = "BASE_URL",
+            help = "Forward requests the snapshot has no record for to the same path on this live backend (e.g. https://staging.example.com) instead of answering 404; nothing is recorded"
+        )]
+        forward_unmatched: Option<String>,
+
+        /// Slow responses down to a network profile or custom rate and latency
+        #[arg(
+            long,
+            value_name = "PROFILE|SPEC",
+            help = "Simulate a slow network: slow-3g, fast-3g, dsl, or a spec such as down=400kbps,latency=300ms; each connection is limited separately"
+        )]
+        throttle: Option<String>,
+
+        /// Share one throttled link between all connections
+        #[arg(
+            long,
+            requires = "throttle",
+            help = "Limit all connections together to the --throttle rate instead of each one separately"
+        )]
+        throttle_shared: bool,
+
+        /// Serve the records of a capture variant
+        #[arg(
+            long,
+            value_name = "NAME",
+            help = "Match the records captured with --variant NAME first, falling back to untagged records (default: untagged records only)"
+        )]
+        variant: Option<String>,
+
+        /// Compress text-like responses with these encodings when the client accepts them
+        #[arg(
+            long,
+            value_name = "ENCODINGS",
+            help = "Compress text-like responses on the fly for clients that accept it, in order of preference (br, gzip, zstd; e.g. br,gzip)"
+        )]
+        encode: Option<String>,
+
+        /// Answer plain requests as if they were made to this captured origin (repeatable)
+        #[arg(
+            long = "map-origin",
+            value_name = "ORIGIN[=LOCAL]",
+            conflicts_with = "all",
+            help = "Match plain (non-proxy) requests as if made to ORIGIN and rewrite redirects and cookie domains back; add =LOCAL (e.g. api.localhost:8080) to pick the origin by Host header (repeatable)"
+        )]
+        map_origin: Vec<String>,
+
+        /// Serve absolute URLs on the captured site's own origin as paths
+        #[arg(
+            long,
+            help = "Rewrite absolute URLs to the snapshot's entry origins in text bodies served from those origins to paths (https://app.example.com/asset.js becomes /asset.js), so pages served directly with --map-origin load their own assets from the mock server"
+        )]
+        rewrite_absolute_urls: bool,
+
+        /// Shut down once no request has arrived for this long
+        #[arg(
+            long,
+            value_name = "DURATION",
+            help = "Shut down cleanly after this long without requests, e.g. 300s or 5m"
+        )]
+        idle_timeout: Option<String>,
+
+        /// Shut down after running this long
+        #[arg(
+            long,
+            value_name = "DURATION",
+            help = "Shut down cleanly after running this long, even while serving requests, e.g. 1h"
+        )]
+        max_lifetime: Option<String>,
+
+        /// Answer DNS queries for the snapshot's hosts on this UDP port
+        #[arg(
+            long,
+            value_name = "PORT",
+            conflicts_with = "all",
+            help = "Run a DNS server on this UDP port that resolves the snapshot's hosts to this machine, for devices whose DNS is set to it; other names get NXDOMAIN. The mock server then listens on all interfaces"
+        )]
+        dns_port: Option<u16>,
+
+        /// Address given in DNS answers
+        #[arg(
+            long,
+            value_name = "IP",
+            requires = "dns_port",
+            help = "Address the snapshot's hosts resolve to with --dns-port (default: this machine's LAN address)"
+        )]
+        advertise_ip: Option<IpAddr>,
+
+        /// Resolver DNS queries for other hosts are relayed to
+        #[arg(
+            long,
+            value_name = "IP[:PORT]",
+            requires = "dns_port",
+            help = "Relay DNS queries for hosts outside the snapshot to this resolver instead of answering NXDOMAIN"
+        )]
+        dns_upstream: Option<String>,
+
+        /// Log the start of each request and response body, for debugging
+        #[arg(
+            long,
+            value_name = "MAX_BYTES",
+            num_args = 0..=1,
+            require_equals = true,
+            default_missing_value = "1024",
+            help = "Log request headers and the first MAX_BYTES (default 1024) of the request and response bodies of each request to stderr, or the --log-file of a --daemon server; only text bodies are shown, after redaction, others by size and hash"
+        )]
+        log_bodies: Option<usize>,
+
+        /// Header whose value is replaced with [REDACTED] in logged requests (repeatable)
+        #[arg(
+            long = "redact-header",
+            value_name = "NAME",
+            requires = "log_bodies",
+            help = "Replace the value of this header with [REDACTED] in --log-bodies output (repeatable)"
+        )]
+        redact_header: Vec<String>,
+
+        /// Redact authorization, cookie and set-cookie headers in logged requests
+        #[arg(
+            long,
+            requires = "log_bodies",
+            help = "Redact common credential headers (authorization, cookie, set-cookie) in --log-bodies output"
+        )]
+        redact_defaults: bool,
+
+        /// Regex whose matches are replaced with [REDACTED] in logged bodies (repeatable)
+        #[arg(
+            long = "redact-body-pattern",
+            value_name = "REGEX",
+            requires = "log_bodies",
+            help = "Replace matches of this regex in --log-bodies text bodies with [REDACTED] (repeatable)"
+        )]
+        redact_body_pattern: Vec<String>,
+
+        /// Export serve spans to an OpenTelemetry collector
+        #[arg(
+            long,
+            value_name = "URL",
+            help = "Export connection and request spans over OTLP/gRPC to this collector (requires the otlp feature)"
+        )]
+        otlp_endpoint: Option<String>,
+
+        /// Run the server in the background
+        #[arg(
+            long,
+            requires_all = ["pid_file", "log_file"],
+            help = "Run the server in the background and return once it is listening (requires --pid-file and --log-file)"
+        )]
+        daemon: bool,
+
+        /// File to write the server's PID to
+        #[arg(
+            long,
+            value_name = "PATH",
+            help = "Write the server's PID to this file while it runs, for 'webmock stop'"
+        )]
+        pid_file: Option<String>,
+
+        /// File the server's port is written to once it listens
+        #[arg(
+            long,
+            value_name = "PATH",
+            conflicts_with = "all",
+            help = "Write the snapshot, port, PID and start time as JSON to PATH once listening, removed on shutdown; a directory (trailing /) gets <snapshot>.port.json"
+        )]
+        port_file: Option<String>,
+
+        /// File per-record hit counts are written to on shutdown
+        #[arg(
+            long,
+            value_name = "PATH",
+            conflicts_with = "all",
+            help = "When the server stops, write how often each record was served as JSON to PATH, for 'webmock trim'"
+        )]
+        coverage: Option<String>,
+
+        /// Serve even if another server already serves this snapshot
+        #[arg(
+            long,
+            conflicts_with = "all",
+            help = "Start even when another server of this storage already serves the snapshot, without asking (for scripts)"
+        )]
+        allow_duplicate: bool,
+
+        /// File the background server's output is appended to
+        #[arg(
+            long,
+            value_name = "PATH",
+            requires = "daemon",
+            help = "Append the background server's output to this file"
+        )]
+        log_file: Option<String>,
+    },
+
+    /// Stop a mock server started with --pid-file
+    #[command(
+        long_about = "Stop a mock server that was started with --pid-file, usually one running in the background with 'webmock serve --daemon'. The server shuts down the same way as on Ctrl+C and removes its PID file.
+
+A PID file left behind by a server that is no longer running is removed without an error, so the command is safe to run in cleanup steps.
+
+EXAMPLES:
+    # Stop the server started with --pid-file webmock.pid
+    webmock stop --pid-file webmock.pid
+
+    # Give a busy server longer to finish
+    webmock stop --pid-file webmock.pid --timeout 30s"
+    )]
+    Stop {
+        /// PID file of the server to stop
+        #[arg(
+            long,
+            value_name = "PATH",
+            help = "PID file the server was started with"
+        )]
+        pid_file: String,
+
+        /// How long to wait for the server to exit
+        #[arg(
+            long,
+            value_name = "DURATION",
+            default_value = "10s",
+            help = "How long to wait for the server to exit, e.g. 10s"
+        )]
+        timeout: String,
+    },
+
+    /// Measure how fast a snapshot can be replayed
+    #[command(
+        long_about = "Start the mock server for a snapshot in-process on an ephemeral port and send it as many requests as possible for a fixed duration. Requests go round-robin over every captured GET URL, or to a single URL with --url.
+
+The report shows:
+• Completed requests and requests per second
+• Latency percentiles (p50, p90, p99, max)
+• Responses with an error status and connection errors
+
+No external load tool is needed, and the server is stopped when the run ends.
+
+EXAMPLES:
+    # Benchmark with 64 connections for 10 seconds
+    webmock bench my-site
+
+    # A shorter run with fewer connections
+    webmock bench my-site --connections 8 --duration 5s
+
+    # Hammer a single captured URL
+    webmock bench my-site --url https://example.com/api/data"
+    )]
+    Bench {
+        /// Name of the snapshot to benchmark
+        #[arg(
+            help = "Name of the snapshot to benchmark (use 'webmock list' to see available snapshots)"
+        )]
+        snapshot_name: String,
+
+        /// Number of concurrent connections
+        #[arg(
+            long,
+            default_value = "64",
+            help = "Number of concurrent keep-alive connections"
+        )]
+        connections: usize,
+
+        /// How long to run (e.g. 10s, 1m)
+        #[arg(
+            long,
+            value_name = "DURATION",
+            default_value = "10s",
+            help = "How long to send requests (e.g. 10s, 1m)"
+        )]
+        duration: String,
+
+        /// Request only this URL instead of every captured GET
+        #[arg(long, help = "Request only this URL instead of every captured GET URL")]
+        url: Option<String>,
+
+        /// Custom storage directory path (default: ~/.webmock)
+        #[arg(long, help = "Custom storage directory path (default: ~/.webmock)")]
+        storage: Option<String>,
+    },
+
+    /// Delete a saved snapshot permanently
+    #[command(
+        long_about = "Permanently delete a saved snapshot from disk. This action cannot be undone.
+
+The delete process:
+1. Checks if the snapshot exists
+2. Asks for confirmation (unless --force is used)
+3. Removes the snapshot file from the storage directory/snapshots/
+
+EXAMPLES:
+    # Delete a snapshot
+    webmock delete old-snapshot
+
+    # Delete from custom storage directory
+    webmock delete old-snapshot --storage /path/to/custom/storage
+4. Reports success or failure
+
+EXAMPLES:
+    # Delete with confirmation prompt
+    webmock delete old-snapshot
 
     # The command will ask:
     Are you sure you want to delete snapshot 'old-snapshot'? 
     This action cannot be undone. (y/N):
 
-SAFETY:
-    • Always asks for confirmation before deletion
-    • Validates snapshot exists before prompting
-    • Provides clear error messages if snapshot not found"
+SAFETY:
+    • Always asks for confirmation before deletion
+    • Validates snapshot exists before prompting
+    • Provides clear error messages if snapshot not found"
+    )]
+    Delete {
+        /// Name of the snapshot to delete
+        #[arg(
+            help = "Name of the snapshot to delete (use 'webmock list' to see available snapshots)"
+        )]
+        snapshot_name: String,
+
+        /// Custom storage directory path (default: ~/.webmock)
+        #[arg(long, help = "Custom storage directory path (default: ~/.webmock)")]
+        storage: Option<String>,
+    },
+
+    /// Capture an existing snapshot again with its stored settings
+    #[command(
+        long_about = "Refresh a snapshot by capturing its URL again with the locale, timezone,
+viewport, device and User-Agent it was captured with.
+
+The recapture process:
+1. Loads the existing snapshot's URL and settings
+2. Captures into a staging area next to the snapshots directory
+3. Replaces the snapshot only once the new capture has been saved
+4. Keeps the previous version as <name>.prev.msgpack (unless --no-backup)
+
+If the capture fails, the existing snapshot is left untouched.
+
+EXAMPLES:
+    # Refresh a snapshot, keeping the old version as a backup
+    webmock recapture my-site
+
+    # Show which requests were added, removed or changed before replacing
+    webmock recapture my-site --diff
+
+    # Replace without keeping a backup
+    webmock recapture my-site --no-backup --timeout 60"
+    )]
+    Recapture {
+        /// Name of the snapshot to refresh
+        #[arg(
+            help = "Name of the snapshot to recapture (use 'webmock list' to see available snapshots)"
+        )]
+        snapshot_name: String,
+
+        /// Timeout in seconds for the capture process (1-600)
+        #[arg(
+            long,
+            default_value = "30",
+            help = "Timeout in seconds (default: 30, max: 600)"
+        )]
+        timeout: u64,
+
+        /// Don't keep the previous version
+        #[arg(long, help = "Don't keep the previous version as <name>.prev.msgpack")]
+        no_backup: bool,
+
+        /// Print differences from the previous version
+        #[arg(
+            long,
+            help = "Print requests added, removed or changed since the previous version before replacing it"
+        )]
+        diff: bool,
+
+        /// Custom storage directory path (default: ~/.webmock)
+        #[arg(long, help = "Custom storage directory path (default: ~/.webmock)")]
+        storage: Option<String>,
+    },
+
+    /// Copy part of a snapshot into a new snapshot
+    #[command(
+        long_about = "Create a new snapshot from the records of an existing one whose URL matches a glob, optionally restricted to one method. The source snapshot is left unchanged.
+
+Records are read and written one at a time, so even very large snapshots are split without loading them into memory. The new snapshot keeps the source's capture settings and notes which snapshot and filter it was made from (shown by 'webmock inspect').
+
+In globs, * matches any run of characters including /, ? matches one character and {a,b} matches either alternative.
+
+EXAMPLES:
+    # Keep only the billing API
+    webmock split full-app billing --url-glob 'https://app.example.com/api/billing/*'
+
+    # Only its GET requests
+    webmock split full-app billing-reads --url-glob 'https://app.example.com/api/billing/*' --method GET
+
+    # Everything except videos and images
+    webmock split full-app no-media --url-glob '*.{mp4,webm,png,jpg}' --invert"
+    )]
+    Split {
+        /// Name of the snapshot to copy records from
+        #[arg(
+            help = "Name of the snapshot to copy records from (use 'webmock list' to see available snapshots)"
+        )]
+        source: String,
+
+        /// Name of the new snapshot
+        #[arg(help = "Name of the new snapshot (must not exist yet)")]
+        destination: String,
+
+        /// Glob the record URL must match
+        #[arg(
+            long,
+            value_name = "GLOB",
+            help = "Copy records whose URL matches this glob, e.g. 'https://app.example.com/api/*'"
+        )]
+        url_glob: String,
+
+        /// Method the record must have
+        #[arg(long, help = "Only copy records with this method, e.g. GET")]
+        method: Option<String>,
+
+        /// Copy the records that don't match instead
+        #[arg(long, help = "Copy every record except the matching ones")]
+        invert: bool,
+
+        /// Custom storage directory path (default: ~/.webmock)
+        #[arg(long, help = "Custom storage directory path (default: ~/.webmock)")]
+        storage: Option<String>,
+    },
+
+    /// Remove the records a coverage report shows were never served
+    #[command(
+        long_about = "Remove the records of a snapshot that a test run never used, going by the report 'webmock serve --coverage' wrote when it stopped. Each removed record's method, URL, status, size and hits stay listed in the snapshot (shown by 'webmock inspect'), so you can tell what the capture had.
+
+The report must come from the same snapshot file: trimming refuses a report of another snapshot, or one written before the snapshot last changed.
+
+The snapshot is trimmed in place, keeping the previous version as <name>.prev.msgpack, unless --output names a new snapshot to write instead.
+
+EXAMPLES:
+    # Record what the test suite uses, then drop the rest
+    webmock serve my-site --coverage coverage.json
+    webmock trim my-site --coverage coverage.json
+
+    # Keep records served at least 3 times, in a new snapshot
+    webmock trim my-site --coverage coverage.json --min-hits 3 --output my-site-lean"
+    )]
+    Trim {
+        /// Name of the snapshot to trim
+        #[arg(
+            help = "Name of the snapshot to trim (use 'webmock list' to see available snapshots)"
+        )]
+        snapshot_name: String,
+
+        /// Report written by serve --coverage
+        #[arg(
+            long,
+            value_name = "PATH",
+            help = "Coverage report written by 'webmock serve --coverage' for this snapshot"
+        )]
+        coverage: String,
+
+        /// Write the trimmed snapshot under this new name
+        #[arg(
+            long,
+            value_name = "NAME",
+            help = "Write the trimmed snapshot as this new snapshot and leave the original unchanged"
+        )]
+        output: Option<String>,
+
+        /// Keep records the report doesn't list
+        #[arg(
+            long,
+            help = "Keep records missing from the report, e.g. of other variants, instead of removing them as never served"
+        )]
+        keep_misses: bool,
+
+        /// Remove records served fewer times than this
+        #[arg(
+            long,
+            value_name = "N",
+            default_value = "1",
+            help = "Remove records served fewer than N times"
+        )]
+        min_hits: u64,
+
+        /// Custom storage directory path (default: ~/.webmock)
+        #[arg(long, help = "Custom storage directory path (default: ~/.webmock)")]
+        storage: Option<String>,
+    },
+
+    /// Show or change the default serve settings stored with a snapshot
+    #[command(
+        long_about = "Store default serve settings in a snapshot, so 'webmock serve <snapshot>' replays it the way it needs without repeating flags. Without settings, print the stored defaults.
+
+Flags given to 'webmock serve' override the stored defaults, which override the built-in defaults. Stored defaults travel with the snapshot file and survive 'webmock recapture'.
+
+EXAMPLES:
+    # Always serve this snapshot on port 8090, compressed and in captured order
+    webmock config checkout --port 8090 --encode br,gzip --stateful
+
+    # Show the stored defaults
+    webmock config checkout
+
+    # Turn one setting back off; an explicit false is stored too
+    webmock config checkout --stateful=false
+
+    # Forget all stored defaults
+    webmock config checkout --clear"
+    )]
+    Config {
+        /// Name of the snapshot to configure
+        #[arg(
+            help = "Name of the snapshot to configure (use 'webmock list' to see available snapshots)"
+        )]
+        snapshot_name: String,
+
+        /// Port to serve the snapshot on
+        #[arg(long, help = "Port to serve the snapshot on (1024-65535)")]
+        port: Option<u16>,
+
+        /// How to replay requests that failed during capture
+        #[arg(
+            long,
+            value_enum,
+            help = "Replay capture failures as a 502 (bad-gateway) or a stalled, dropped connection (as-timeout)"
+        )]
+        replay_failures: Option<ReplayFailuresMode>,
+
+        /// Encodings to compress responses with
+        #[arg(
+            long,
+            value_name = "ENCODINGS",
+            help = "Compress responses with these encodings, e.g. br,gzip"
+        )]
+        encode: Option<String>,
+
+        /// Answer CONNECT to unknown hosts with 502
+        #[arg(
+            long,
+            value_name = "BOOL",
+            num_args = 0..=1,
+            require_equals = true,
+            default_missing_value = "true",
+            help = "Answer CONNECT to hosts missing from the snapshot with 502"
+        )]
+        strict_connect: Option<bool>,
+
+        /// Replay records in captured order
+        #[arg(
+            long,
+            value_name = "BOOL",
+            num_args = 0..=1,
+            require_equals = true,
+            default_missing_value = "true",
+            help = "Answer 404 for a record until the earlier writes on its path were requested"
+        )]
+        stateful: Option<bool>,
+
+        /// Remove the stored defaults before applying the given settings
+        #[arg(
+            long,
+            help = "Remove all stored defaults, keeping only the settings given with it"
+        )]
+        clear: bool,
+
+        /// Custom storage directory path (default: ~/.webmock)
+        #[arg(long, help = "Custom storage directory path (default: ~/.webmock)")]
+        storage: Option<String>,
+    },
+
+    /// Change the primary entry point of a snapshot
+    #[command(
+        long_about = "Make another URL the primary entry point of a snapshot, the page 'webmock serve --open' opens. The previous primary stays among the snapshot's entry points, which 'webmock inspect' lists.
+
+Captures record every page they load, plus where redirects ended up, as entry points. The URL must have a GET record in the snapshot unless --force is given.
+
+EXAMPLES:
+    # Open on the page the capture was redirected to
+    webmock set-entry my-site https://www.example.com/home
+
+    # Point at a URL the snapshot has no record of
+    webmock set-entry my-site https://example.com/#/dashboard --force"
+    )]
+    SetEntry {
+        /// Name of the snapshot to change
+        #[arg(
+            help = "Name of the snapshot to change (use 'webmock list' to see available snapshots)"
+        )]
+        snapshot_name: String,
+
+        /// URL to make the primary entry point
+        #[arg(help = "URL to make the primary entry point, e.g. https://www.example.com/home")]
+        url: String,
+
+        /// Set the URL even if no record answers it
+        #[arg(
+            long,
+            help = "Set the URL even if the snapshot has no GET record for it"
+        )]
+        force: bool,
+
+        /// Custom storage directory path (default: ~/.webmock)
+        #[arg(long, help = "Custom storage directory path (default: ~/.webmock)")]
+        storage: Option<String>,
+    },
+
+    /// Leave a note on a record of a snapshot
+    #[command(
+        long_about = "Attach a note to a record, so teammates triaging the snapshot see it. Notes show up under their record in 'webmock inspect' and in 'webmock inspect --json'.
+
+Records are picked by their number in the 'webmock inspect' listing. A note stays with its record when the snapshot is changed by other commands, such as 'webmock config' or 'webmock set-entry', and 'webmock split' copies the notes of the records it keeps.
+
+EXAMPLES:
+    # Flag a response that needs capturing again
+    webmock annotate my-site --request 12 --note \"stale, re-capture after the API v2 rollout\"
+
+    # Remove the note again
+    webmock annotate my-site --request 12 --remove"
+    )]
+    Annotate {
+        /// Name of the snapshot to change
+        #[arg(
+            help = "Name of the snapshot to change (use 'webmock list' to see available snapshots)"
+        )]
+        snapshot_name: String,
+
+        /// Number of the record in the inspect listing
+        #[arg(
+            long,
+            value_name = "INDEX",
+            help = "Number of the record in the 'webmock inspect' listing"
+        )]
+        request: usize,
+
+        /// Note to attach to the record
+        #[arg(
+            long,
+            value_name = "TEXT",
+            required_unless_present = "remove",
+            conflicts_with = "remove",
+            help = "Note to attach to the record, replacing any existing one"
+        )]
+        note: Option<String>,
+
+        /// Remove the record's note
+        #[arg(long, help = "Remove the note of the record")]
+        remove: bool,
+
+        /// Custom storage directory path (default: ~/.webmock)
+        #[arg(long, help = "Custom storage directory path (default: ~/.webmock)")]
+        storage: Option<String>,
+    },
+
+    /// Print the manifest describing a snapshot file
+    #[command(
+        long_about = "Print the manifest of a snapshot as JSON: the webmock version that wrote it, its entry URLs, creation time, record count, file size and the SHA-256 of the snapshot file.
+
+Every save writes the manifest next to the snapshot as <name>.manifest.json, so CI can key artifact caches on it without reading the snapshot. Snapshots saved without one get it written first.
+
+EXAMPLES:
+    # Use the snapshot hash as a cache key
+    webmock manifest my-site | jq -r .sha256"
+    )]
+    Manifest {
+        /// Name of the snapshot
+        #[arg(help = "Name of the snapshot (use 'webmock list' to see available snapshots)")]
+        snapshot_name: String,
+
+        /// Custom storage directory path (default: ~/.webmock)
+        #[arg(long, help = "Custom storage directory path (default: ~/.webmock)")]
+        storage: Option<String>,
+    },
+
+    /// Check that a snapshot file is intact
+    #[command(
+        long_about = "Check that a snapshot file reads back, for example after restoring it from a CI cache. With --manifest the file is first checked against the SHA-256 and size in its manifest, and any difference fails the command.
+
+EXAMPLES:
+    # Fail the job if the cached snapshot was truncated or changed
+    webmock verify my-site --manifest"
+    )]
+    Verify {
+        /// Name of the snapshot to check
+        #[arg(
+            help = "Name of the snapshot to check (use 'webmock list' to see available snapshots)"
+        )]
+        snapshot_name: String,
+
+        /// Cross-check the file against its manifest
+        #[arg(
+            long,
+            help = "Also check the snapshot file against the hash and size in its manifest"
+        )]
+        manifest: bool,
+
+        /// Custom storage directory path (default: ~/.webmock)
+        #[arg(long, help = "Custom storage directory path (default: ~/.webmock)")]
+        storage: Option<String>,
+    },
+
+    /// Check that this machine can capture and serve
+    #[command(
+        long_about = "Check everything webmock depends on and report the results together: Chrome, the storage directory, writing and reading back a snapshot, free disk space, binding a local port, a TLS handshake with a locally generated certificate, and DNS.
+
+Every check runs even when another fails. Free disk space and DNS only warn; the command exits non-zero only when a check that rules out capturing or serving fails.
+
+EXAMPLES:
+    # Show the checks as a table
+    webmock doctor
+
+    # Save the results to attach to a bug report
+    webmock doctor --json > doctor.json"
+    )]
+    Doctor {
+        /// Print the report as JSON
+        #[arg(long, help = "Print the report as JSON")]
+        json: bool,
+
+        /// Custom storage directory path (default: ~/.webmock)
+        #[arg(long, help = "Custom storage directory path (default: ~/.webmock)")]
+        storage: Option<String>,
+    },
+
+    /// Encrypt a saved snapshot with a passphrase
+    #[command(
+        long_about = "Encrypt a snapshot at rest, so captured session tokens and personal data aren't readable by anyone who can read the storage directory.
+
+The passphrase comes from WEBMOCK_PASSPHRASE, or is asked for twice on the terminal. Commands that read the snapshot (serve, inspect, diff, ...) ask for it the same way; 'webmock list' shows encrypted snapshots without it. A lost passphrase can't be recovered.
+
+EXAMPLES:
+    # Encrypt a snapshot, typing the passphrase
+    webmock encrypt my-site
+
+    # Encrypt in a script
+    WEBMOCK_PASSPHRASE=\"$SECRET\" webmock encrypt my-site"
+    )]
+    Encrypt {
+        /// Name of the snapshot to encrypt
+        #[arg(
+            help = "Name of the snapshot to encrypt (use 'webmock list' to see available snapshots)"
+        )]
+        snapshot_name: String,
+
+        /// Custom storage directory path (default: ~/.webmock)
+        #[arg(long, help = "Custom storage directory path (default: ~/.webmock)")]
+        storage: Option<String>,
+    },
+
+    /// Store an encrypted snapshot in the clear again
+    #[command(
+        long_about = "Decrypt a snapshot encrypted with 'webmock encrypt' or 'webmock capture --encrypt' and store it unencrypted.
+
+The passphrase comes from WEBMOCK_PASSPHRASE, or is asked for on the terminal.
+
+EXAMPLES:
+    # Decrypt a snapshot
+    webmock decrypt my-site"
     )]
-    Delete {
-        /// Name of the snapshot to delete
+    Decrypt {
+        /// Name of the snapshot to decrypt
         #[arg(
-            help = "Name of the snapshot to delete (use 'webmock list' to see available snapshots)"
+            help = "Name of the snapshot to decrypt (use 'webmock list' to see available snapshots)"
+        )]
+        snapshot_name: String,
+
+        /// Custom storage directory path (default: ~/.webmock)
+        #[arg(long, help = "Custom storage directory path (default: ~/.webmock)")]
+        storage: Option<String>,
+    },
+
+    /// Check that a snapshot holds every resource its pages reference
+    #[command(
+        long_about = "Parse the HTML and CSS captured in a snapshot and list the resources they reference (images, scripts, stylesheets, fonts, srcset candidates, CSS url() and @import) that no record answers. Those resources get 404s when the snapshot is served.
+
+References are resolved against the document that contains them, including relative, protocol-relative and <base href> URLs, and looked up the way 'webmock serve' looks up requests. Links to other pages (<a href>) are not resources and are not checked.
+
+Capture runs the same check and warns when resources are missing.
+
+EXAMPLES:
+    # List missing resources
+    webmock analyze my-site
+
+    # Fail a CI job when the snapshot isn't self-contained
+    webmock analyze my-site --strict
+
+    # Machine-readable report
+    webmock analyze my-site --json"
+    )]
+    Analyze {
+        /// Name of the snapshot to analyze
+        #[arg(
+            help = "Name of the snapshot to analyze (use 'webmock list' to see available snapshots)"
+        )]
+        snapshot_name: String,
+
+        /// Print the report as JSON
+        #[arg(long, help = "Print the report as JSON")]
+        json: bool,
+
+        /// Exit with an error when resources are missing
+        #[arg(
+            long,
+            help = "Exit with an error when referenced resources are missing"
+        )]
+        strict: bool,
+
+        /// Custom storage directory path (default: ~/.webmock)
+        #[arg(long, help = "Custom storage directory path (default: ~/.webmock)")]
+        storage: Option<String>,
+    },
+
+    /// Compare the JSON structure of captured endpoints with the live API
+    #[command(
+        long_about = "Fetch the live version of every JSON GET response captured in a snapshot and compare the structure of the bodies: which fields exist and whether they hold objects, arrays, strings, numbers or booleans. Values are not compared. Each endpoint is listed with the fields added (+), removed (-) or retyped (~) since capture, e.g. '~ $.items[].id: number -> string'.
+
+Array elements are merged, so a field present in any element counts. A field that is null on one side isn't reported as retyped, since null says nothing about what it holds otherwise.
+
+Endpoints that can't be fetched, answer with an error status or don't return JSON are reported as not checked and don't stop the others.
+
+EXAMPLES:
+    # Check every JSON endpoint of a snapshot
+    webmock drift my-api --live
+
+    # Only the billing endpoints, authenticated
+    webmock drift my-api --live --url-glob 'https://api.example.com/billing/*' --header \"Authorization: Bearer $TOKEN\"
+
+    # Fail a CI job when the API has drifted from the snapshot
+    webmock drift my-api --live --strict"
+    )]
+    Drift {
+        /// Name of the snapshot to compare
+        #[arg(
+            help = "Name of the snapshot to compare (use 'webmock list' to see available snapshots)"
+        )]
+        snapshot_name: String,
+
+        /// Compare against the live API
+        #[arg(
+            long,
+            required = true,
+            help = "Fetch each captured endpoint from the live API to compare with"
+        )]
+        live: bool,
+
+        /// Glob the record URL must match
+        #[arg(
+            long,
+            value_name = "GLOB",
+            help = "Only compare endpoints whose URL matches this glob, e.g. 'https://api.example.com/v1/*'"
+        )]
+        url_glob: Option<String>,
+
+        /// Header sent with every live request
+        #[arg(
+            long,
+            value_name = "NAME: VALUE",
+            help = "Send this header with every live request, e.g. 'Authorization: Bearer <token>' (repeatable)"
+        )]
+        header: Vec<String>,
+
+        /// Exit with an error when any endpoint drifted
+        #[arg(long, help = "Exit with an error when any endpoint drifted")]
+        strict: bool,
+
+        /// Custom storage directory path (default: ~/.webmock)
+        #[arg(long, help = "Custom storage directory path (default: ~/.webmock)")]
+        storage: Option<String>,
+    },
+
+    /// Proxy live traffic and report what a snapshot would have missed
+    #[command(
+        long_about = "Run a reverse proxy in front of a live backend and check every request that goes through it against a snapshot, without ever answering from the snapshot. Point a test suite at the proxy instead of the backend: responses are the live ones, so the suite behaves as usual, while each request is looked up the way 'webmock serve' would.
+
+Each request is logged as in the snapshot, not in the snapshot (the mock would answer 404), or drifted: in the snapshot, but the live status or content type differs from the recorded one. Stop the proxy with Ctrl+C to see requests per host, the requests the snapshot misses and the drifted ones.
+
+EXAMPLES:
+    # Shadow staging on port 8081 and point the tests at http://127.0.0.1:8081
+    webmock shadow my-api --upstream https://staging.example.com
+
+    # Keep the report for later
+    webmock shadow my-api --listen 9000 --upstream https://staging.example.com --report shadow.json"
+    )]
+    Shadow {
+        /// Name of the snapshot to check requests against
+        #[arg(
+            help = "Name of the snapshot to check requests against (use 'webmock list' to see available snapshots)"
+        )]
+        snapshot_name: String,
+
+        /// Local port to listen on
+        #[arg(
+            long,
+            value_name = "PORT",
+            default_value_t = 8081,
+            help = "Listen on this port of 127.0.0.1"
+        )]
+        listen: u16,
+
+        /// Live backend to forward every request to
+        #[arg(
+            long,
+            value_name = "URL",
+            help = "Forward every request to this base URL, e.g. https://staging.example.com"
+        )]
+        upstream: String,
+
+        /// File to write the report to as JSON
+        #[arg(
+            long,
+            value_name = "FILE",
+            help = "Also write the report to this file as JSON when the proxy stops"
+        )]
+        report: Option<String>,
+
+        /// Custom storage directory path (default: ~/.webmock)
+        #[arg(long, help = "Custom storage directory path (default: ~/.webmock)")]
+        storage: Option<String>,
+    },
+
+    /// Check that a snapshot still holds the endpoints tests rely on
+    #[command(
+        long_about = "Check a snapshot against expectations and fail when any of them doesn't hold, e.g. in CI after an automated recapture.
+
+An expectation names a method and URL the snapshot must have a record of, optionally followed by '->' and what the response must look like, in any order:
+• a status code (200) or class (2xx)
+• a content type: html, css, js, json, xml, image, font, media, text or other
+• body-contains TEXT, which takes the rest of the line (quotes are optional)
+
+An expectations file is YAML with an 'expectations' list of such lines, or of maps with method, url, status, content_type and body_contains keys.
+
+A PASS or FAIL line is printed per expectation, and the command exits non-zero if any failed.
+
+EXAMPLES:
+    # The users endpoint must answer 200 with JSON
+    webmock assert my-site --expect 'GET https://api.example.com/users -> 200 json'
+
+    # Several expectations, including a body check
+    webmock assert my-site --expect 'GET https://example.com/ -> 2xx html body-contains \"Welcome\"' --expect 'GET https://example.com/app.js -> js'
+
+    # Expectations kept in a file, with a report for CI
+    webmock assert my-site --expect-file expectations.yaml --json"
+    )]
+    Assert {
+        /// Name of the snapshot to check
+        #[arg(
+            help = "Name of the snapshot to check (use 'webmock list' to see available snapshots)"
+        )]
+        snapshot_name: String,
+
+        /// Expectation such as 'GET https://api.example.com/users -> 200 json'
+        #[arg(
+            long,
+            value_name = "EXPECTATION",
+            required_unless_present = "expect_file",
+            help = "Expect a record, e.g. 'GET https://api.example.com/users -> 200 json body-contains \"id\"' (repeatable)"
+        )]
+        expect: Vec<String>,
+
+        /// YAML file of expectations
+        #[arg(
+            long,
+            value_name = "FILE",
+            help = "YAML file with an 'expectations' list, checked after any --expect"
+        )]
+        expect_file: Option<String>,
+
+        /// Print the report as JSON
+        #[arg(long, help = "Print the report as JSON")]
+        json: bool,
+
+        /// Custom storage directory path (default: ~/.webmock)
+        #[arg(long, help = "Custom storage directory path (default: ~/.webmock)")]
+        storage: Option<String>,
+    },
+
+    /// Export a snapshot for other tools, e.g. as an OpenAPI document
+    #[command(
+        long_about = "Export what a snapshot captured in a format other tools read.
+
+openapi writes an OpenAPI 3.0 skeleton of the snapshot's API calls (JSON, XML and other non-asset responses, and every request other than GET or HEAD). Records are grouped by host and path, with numeric, UUID and long hexadecimal path segments turned into {id} parameters. Each path lists the methods, status codes and content types seen, an example response body (truncated) and a JSON schema inferred from the JSON bodies. It is a starting point to refine by hand: everything is guessed from traffic and marked x-webmock-inferred: true.
+
+The document is written as YAML, or as JSON when the output file ends in .json.
+
+EXAMPLES:
+    # Start an API description from a capture
+    webmock export my-site --format openapi --output api.yaml
+
+    # Print it as YAML
+    webmock export my-site
+
+    # JSON for tools that want it
+    webmock export my-site --output api.json"
+    )]
+    Export {
+        /// Name of the snapshot to export
+        #[arg(
+            help = "Name of the snapshot to export (use 'webmock list' to see available snapshots)"
         )]
         snapshot_name: String,
 
+        /// Format to export in
+        #[arg(
+            long,
+            value_enum,
+            default_value = "openapi",
+            help = "Format to export in"
+        )]
+        format: ExportFormat,
+
+        /// File to write instead of stdout
+        #[arg(
+            short,
+            long,
+            value_name = "FILE",
+            help = "Write to this file instead of stdout; JSON if it ends in .json, YAML otherwise"
+        )]
+        output: Option<String>,
+
         /// Custom storage directory path (default: ~/.webmock)
         #[arg(long, help = "Custom storage directory path (default: ~/.webmock)")]
         storage: Option<String>,
@@ -270,6 +1908,27 @@ EXAMPLES:
     # Inspect from custom storage directory
     webmock inspect my-site --storage /path/to/custom/storage
 
+    # Include upstream HTTP versions and negotiated TLS parameters
+    webmock inspect my-site --detailed
+
+    # Check whether the page already logged errors while it was captured
+    webmock inspect my-site --console
+
+    # Save the response body of record #12 from the listing, byte for byte
+    webmock inspect my-site --request 12 --dump-body payload.json
+
+    # Show every header of one record and pipe its response body on
+    webmock inspect my-site --url https://example.com/api/data --show-headers --dump-body - | jq .
+
+    # Read the start of a huge response without printing all of it
+    webmock inspect my-site --request 3 --show-body --max-body-bytes 4096
+
+    # List the records and their notes for a script
+    webmock inspect my-site --json
+
+    # See why requests may be missing: failed TLS handshakes, unreachable origins, dropped requests
+    webmock inspect my-site --diagnostics
+
 OUTPUT FORMAT:
     📸 Snapshot: my-site
        🌐 Original URL: https://example.com
@@ -288,8 +1947,240 @@ OUTPUT FORMAT:
         )]
         snapshot_name: String,
 
+        /// Show upstream HTTP versions and TLS details
+        #[arg(
+            long,
+            help = "Show the upstream HTTP version per record and the TLS parameters per host"
+        )]
+        detailed: bool,
+
+        /// Show a single record by its number in the listing
+        #[arg(
+            long,
+            value_name = "INDEX",
+            group = "record",
+            help = "Show only the record with this number in the listing"
+        )]
+        request: Option<usize>,
+
+        /// Show a single record by its exact URL
+        #[arg(
+            long,
+            value_name = "URL",
+            group = "record",
+            help = "Show only the record with this exact URL; errors with the candidates if several match"
+        )]
+        url: Option<String>,
+
+        /// Write the selected record's response body to a file
+        #[arg(
+            long,
+            value_name = "PATH",
+            requires = "record",
+            help = "Write the selected record's raw response body to PATH, or to stdout with -"
+        )]
+        dump_body: Option<String>,
+
+        /// Write the selected record's request body to a file
+        #[arg(
+            long,
+            value_name = "PATH",
+            requires = "record",
+            help = "Write the selected record's raw request body to PATH, or to stdout with -"
+        )]
+        dump_request_body: Option<String>,
+
+        /// Print the selected record's headers
+        #[arg(
+            long,
+            requires = "record",
+            help = "Print the full request and response headers of the selected record"
+        )]
+        show_headers: bool,
+
+        /// Print the selected record's text bodies
+        #[arg(
+            long,
+            requires = "record",
+            help = "Print the selected record's request and response bodies if they are text"
+        )]
+        show_body: bool,
+
+        /// Cut printed bodies after this many bytes
+        #[arg(
+            long,
+            value_name = "BYTES",
+            default_value_t = 1024 * 1024,
+            help = "Cut bodies printed by --show-body after BYTES, 0 for no limit (default: 1 MB); --dump-body is never cut"
+        )]
+        max_body_bytes: usize,
+
+        /// Print console messages and page errors from the capture
+        #[arg(
+            long,
+            help = "Print the console messages and uncaught errors the page logged during capture"
+        )]
+        console: bool,
+
+        /// Print the capture proxy's connection-level failures
+        #[arg(
+            long,
+            help = "Print the connections the capture proxy accepted and the TLS handshake, upstream connect and request failures that may explain requests missing from the snapshot"
+        )]
+        diagnostics: bool,
+
+        /// Print the overview and records as JSON
+        #[arg(
+            long,
+            conflicts_with_all = ["record", "console"],
+            help = "Print the snapshot overview and its records, with their notes, as JSON"
+        )]
+        json: bool,
+
+        /// Custom storage directory path (default: ~/.webmock)
+        #[arg(long, help = "Custom storage directory path (default: ~/.webmock)")]
+        storage: Option<String>,
+    },
+
+    /// Browse a snapshot's records interactively in the terminal
+    #[command(
+        long_about = "Open a full-screen view of a snapshot: the list of its records with method, status, size and URL, and below it the selected record's headers and response body. JSON bodies are pretty-printed, other text is shown as it is and binary bodies as a hex dump.
+
+Records are read from the snapshot one at a time, so large snapshots open without loading every body; a body is read when you press Enter on its record.
+
+KEYS:
+    ↑/↓ or j/k       Move through the list (PageUp/PageDown, Home/End or g/G to go further)
+    /                Filter the list; every word must appear in the method, URL or status
+    n/N or Tab       Jump to the first record of the next/previous host
+    Enter            Load the selected record's body
+    x                Toggle the hex view of the body
+    d/u              Scroll the detail pane down/up
+    s                Save the selected body to a file (existing files aren't overwritten)
+    Esc              Clear the filter, or quit when there is none
+    q                Quit
+
+Needs an interactive terminal; use 'webmock inspect' from scripts.
+
+EXAMPLES:
+    webmock browse my-site"
+    )]
+    Browse {
+        /// Name of the snapshot to browse
+        #[arg(
+            help = "Name of the snapshot to browse (use 'webmock list' to see available snapshots)"
+        )]
+        snapshot_name: String,
+
         /// Custom storage directory path (default: ~/.webmock)
         #[arg(long, help = "Custom storage directory path (default: ~/.webmock)")]
         storage: Option<String>,
     },
+
+    /// Manage the certificate used to intercept and replay HTTPS
+    #[command(
+        long_about = "Manage the certificate webmock uses to terminate HTTPS during capture and serve.
+
+The certificate is generated once and cached in ~/.webmock/ca (or $WEBMOCK_CA_DIR). Trusting it lets browsers and tools accept webmock's HTTPS responses without --insecure style flags.
+
+Supported trust stores:
+• macOS system keychain (security add-trusted-cert)
+• Debian/Ubuntu (update-ca-certificates)
+• Fedora/RHEL (update-ca-trust)
+• Other Linux distributions with p11-kit (trust anchor)
+
+Changing the trust store needs administrator privileges. Without them, the exact commands to run are printed instead.
+
+EXAMPLES:
+    # Show what would be changed
+    webmock ca trust --dry-run
+
+    # Trust the certificate without prompting
+    sudo webmock ca trust --yes
+
+    # Remove it again
+    sudo webmock ca untrust"
+    )]
+    Ca {
+        #[command(subcommand)]
+        action: CaAction,
+    },
+
+    /// Manage shell completions
+    #[command(
+        long_about = "Install the completion script for your shell where it is loaded from.
+
+Scripts go to the conventional per-user directory, so no root is needed:
+• bash: ~/.local/share/bash-completion/completions/webmock
+• zsh: ~/.zfunc/_webmock
+• fish: ~/.config/fish/completions/webmock.fish
+• elvish: ~/.config/elvish/lib/webmock.elv
+• PowerShell: ~/.config/powershell/webmock.ps1
+
+$XDG_DATA_HOME and $XDG_CONFIG_HOME replace ~/.local/share and ~/.config. A file webmock didn't install is never overwritten.
+
+EXAMPLES:
+    # Install for the shell in $SHELL
+    webmock completions install
+
+    # Install for zsh, then add the printed fpath line to ~/.zshrc
+    webmock completions install --shell zsh
+
+    # Remove them again
+    webmock completions install --uninstall"
+    )]
+    Completions {
+        #[command(subcommand)]
+        action: CompletionsAction,
+    },
+}
+
+/// Actions of the `completions` command
+#[derive(Subcommand, Debug)]
+pub enum CompletionsAction {
+    /// Write the completion script to your shell's completion directory
+    Install {
+        /// Shell to install completions for
+        #[arg(
+            long,
+            value_enum,
+            help = "Shell to install completions for (default: detected from $SHELL)"
+        )]
+        shell: Option<Shell>,
+
+        /// Remove the installed script
+        #[arg(long, help = "Remove the completion script installed for the shell")]
+        uninstall: bool,
+    },
+}
+
+/// Actions of the `ca` command
+#[derive(Subcommand, Debug)]
+pub enum CaAction {
+    /// Add the webmock certificate to the OS trust store
+    Trust {
+        /// Don't ask for confirmation
+        #[arg(long, short = 'y', help = "Don't ask for confirmation")]
+        yes: bool,
+
+        /// Print the commands without running them
+        #[arg(
+            long,
+            help = "Print the commands that would be run without changing anything"
+        )]
+        dry_run: bool,
+    },
+
+    /// Remove the webmock certificate from the OS trust store
+    Untrust {
+        /// Don't ask for confirmation
+        #[arg(long, short = 'y', help = "Don't ask for confirmation")]
+        yes: bool,
+
+        /// Print the commands without running them
+        #[arg(
+            long,
+            help = "Print the commands that would be run without changing anything"
+        )]
+        dry_run: bool,
+    },
 }
@@ -17,7 +17,7 @@ fn test_cli_parsing_capture_command() {
         Some(Commands::Capture {
             url, name, timeout, ..
         }) => {
-            assert_eq!(url, "https://example.com".to_string());
+            assert_eq!(url.as_deref(), Some("https://example.com"));
             assert_eq!(name, "test-site".to_string());
             assert_eq!(timeout, 30); // default value
         }
@@ -43,7 +43,7 @@ fn test_cli_parsing_capture_with_timeout() {
         Some(Commands::Capture {
             url, name, timeout, ..
         }) => {
-            assert_eq!(url, "https://example.com");
+            assert_eq!(url.as_deref(), Some("https://example.com"));
             assert_eq!(name, "test-site");
             assert_eq!(timeout, 60);
         }
@@ -63,8 +63,8 @@ fn test_cli_parsing_serve_command() {
             port,
             ..
         }) => {
-            assert_eq!(snapshot_name, "test-snapshot");
-            assert_eq!(port, 8080); // default value
+            assert_eq!(snapshot_name.as_deref(), Some("test-snapshot"));
+            assert_eq!(port, None); // resolved by serve, 8080 unless the snapshot stores a port
         }
         _ => panic!("Expected Serve command"),
     }
@@ -82,8 +82,136 @@ fn test_cli_parsing_serve_with_port() {
             port,
             ..
         }) => {
-            assert_eq!(snapshot_name, "test-snapshot");
-            assert_eq!(port, 3000);
+            assert_eq!(snapshot_name.as_deref(), Some("test-snapshot"));
+            assert_eq!(port, Some(3000));
+        }
+        _ => panic!("Expected Serve command"),
+    }
+}
+
+#[test]
+fn test_cli_parsing_serve_open_flags() {
+    let args = ["webmock", "serve", "test-snapshot", "--open"];
+    let cli = Cli::try_parse_from(args).unwrap();
+
+    match cli.command {
+        Some(Commands::Serve {
+            open,
+            open_in_chrome_proxy,
+            ..
+        }) => {
+            assert!(open);
+            assert!(!open_in_chrome_proxy);
+        }
+        _ => panic!("Expected Serve command"),
+    }
+
+    // The two browser modes are mutually exclusive
+    let args = [
+        "webmock",
+        "serve",
+        "test-snapshot",
+        "--open",
+        "--open-in-chrome-proxy",
+    ];
+    assert!(Cli::try_parse_from(args).is_err());
+}
+
+#[test]
+fn test_cli_parsing_serve_output_levels() {
+    let cli = Cli::try_parse_from(["webmock", "serve", "test-snapshot", "--quiet"]).unwrap();
+    assert!(cli.quiet);
+    match cli.command {
+        Some(Commands::Serve { verbose, .. }) => assert!(!verbose),
+        _ => panic!("Expected Serve command"),
+    }
+
+    let cli = Cli::try_parse_from(["webmock", "serve", "test-snapshot", "-v"]).unwrap();
+    assert!(!cli.quiet);
+    match cli.command {
+        Some(Commands::Serve { verbose, .. }) => assert!(verbose),
+        _ => panic!("Expected Serve command"),
+    }
+
+    assert!(Cli::try_parse_from(["webmock", "serve", "test-snapshot", "-q", "-v"]).is_err());
+}
+
+#[test]
+fn test_cli_parsing_global_output_flags() {
+    let cli = Cli::try_parse_from(["webmock", "list"]).unwrap();
+    assert!(!cli.quiet);
+    assert!(!cli.no_color);
+    assert!(!cli.utc);
+
+    let cli = Cli::try_parse_from(["webmock", "--quiet", "--no-color", "list"]).unwrap();
+    assert!(cli.quiet);
+    assert!(cli.no_color);
+
+    // Global flags are accepted after the subcommand too
+    let cli = Cli::try_parse_from(["webmock", "inspect", "my-site", "-q", "--no-color"]).unwrap();
+    assert!(cli.quiet);
+    assert!(cli.no_color);
+    assert!(matches!(cli.command, Some(Commands::Inspect { .. })));
+
+    let cli = Cli::try_parse_from(["webmock", "list", "--utc"]).unwrap();
+    assert!(cli.utc);
+}
+
+#[test]
+fn test_cli_parsing_storage_ro() {
+    let cli = Cli::try_parse_from(["webmock", "list"]).unwrap();
+    assert!(cli.storage_ro.is_none());
+
+    // Global, so it works with every command and alongside --storage
+    let cli = Cli::try_parse_from([
+        "webmock",
+        "delete",
+        "my-site",
+        "--storage-ro",
+        "/opt/snapshots",
+        "--storage",
+        "/tmp/webmock",
+    ])
+    .unwrap();
+    assert_eq!(cli.storage_ro.as_deref(), Some("/opt/snapshots"));
+    assert!(matches!(cli.command, Some(Commands::Delete { .. })));
+}
+
+#[test]
+fn test_cli_parsing_manifest_and_verify() {
+    let cli = Cli::try_parse_from(["webmock", "manifest", "my-site"]).unwrap();
+    match cli.command {
+        Some(Commands::Manifest { snapshot_name, .. }) => assert_eq!(snapshot_name, "my-site"),
+        _ => panic!("Expected Manifest command"),
+    }
+
+    let cli = Cli::try_parse_from(["webmock", "verify", "my-site", "--manifest"]).unwrap();
+    match cli.command {
+        Some(Commands::Verify {
+            snapshot_name,
+            manifest,
+            ..
+        }) => {
+            assert_eq!(snapshot_name, "my-site");
+            assert!(manifest);
+        }
+        _ => panic!("Expected Verify command"),
+    }
+}
+
+#[test]
+fn test_cli_parsing_serve_overrides() {
+    let cli = Cli::try_parse_from([
+        "webmock",
+        "serve",
+        "test-snapshot",
+        "--overrides",
+        "overrides.yaml",
+    ])
+    .unwrap();
+    match cli.command {
+        Some(Commands::Serve { overrides, .. }) => {
+            assert_eq!(overrides.as_deref(), Some("overrides.yaml"));
         }
         _ => panic!("Expected Serve command"),
     }
@@ -96,13 +224,43 @@ fn test_cli_parsing_list_command() {
     let cli = Cli::try_parse_from(args).unwrap();
 
     match cli.command {
-        Some(Commands::List { storage: None }) => {
+        Some(Commands::List {
+            storage: None,
+            summary: false,
+            json: false,
+        }) => {
             // List command has no parameters
         }
         _ => panic!("Expected List command"),
     }
 }
 
+#[test]
+fn test_cli_parsing_list_summary_json() {
+    let args = ["webmock", "list", "--summary", "--json"];
+    let cli = Cli::try_parse_from(args).unwrap();
+
+    match cli.command {
+        Some(Commands::List { summary, json, .. }) => {
+            assert!(summary);
+            assert!(json);
+        }
+        _ => panic!("Expected List command"),
+    }
+
+    // Without --summary the snapshots themselves are printed as JSON
+    match Cli::try_parse_from(["webmock", "list", "--json"])
+        .unwrap()
+        .command
+    {
+        Some(Commands::List { summary, json, .. }) => {
+            assert!(!summary);
+            assert!(json);
+        }
+        _ => panic!("Expected List command"),
+    }
+}
+
 #[test]
 fn test_cli_parsing_delete_command() {
     // Test delete command
@@ -117,6 +275,320 @@ fn test_cli_parsing_delete_command() {
     }
 }
 
+#[test]
+fn test_cli_parsing_split_command() {
+    let cli = Cli::try_parse_from([
+        "webmock",
+        "split",
+        "full-app",
+        "billing",
+        "--url-glob",
+        "https://app.example.com/api/billing/*",
+        "--method",
+        "GET",
+        "--invert",
+    ])
+    .unwrap();
+
+    match cli.command {
+        Some(Commands::Split {
+            source,
+            destination,
+            url_glob,
+            method,
+            invert,
+            ..
+        }) => {
+            assert_eq!(source, "full-app");
+            assert_eq!(destination, "billing");
+            assert_eq!(url_glob, "https://app.example.com/api/billing/*");
+            assert_eq!(method.as_deref(), Some("GET"));
+            assert!(invert);
+        }
+        _ => panic!("Expected Split command"),
+    }
+
+    // A glob is required
+    assert!(Cli::try_parse_from(["webmock", "split", "full-app", "billing"]).is_err());
+}
+
+#[test]
+fn test_cli_parsing_trim_command() {
+    let cli = Cli::try_parse_from([
+        "webmock",
+        "trim",
+        "full-app",
+        "--coverage",
+        "coverage.json",
+        "--output",
+        "lean",
+        "--keep-misses",
+        "--min-hits",
+        "3",
+    ])
+    .unwrap();
+
+    match cli.command {
+        Some(Commands::Trim {
+            snapshot_name,
+            coverage,
+            output,
+            keep_misses,
+            min_hits,
+            ..
+        }) => {
+            assert_eq!(snapshot_name, "full-app");
+            assert_eq!(coverage, "coverage.json");
+            assert_eq!(output.as_deref(), Some("lean"));
+            assert!(keep_misses);
+            assert_eq!(min_hits, 3);
+        }
+        _ => panic!("Expected Trim command"),
+    }
+
+    let cli = Cli::try_parse_from(["webmock", "trim", "full-app", "--coverage", "c.json"]).unwrap();
+    match cli.command {
+        Some(Commands::Trim {
+            output,
+            keep_misses,
+            min_hits,
+            ..
+        }) => {
+            assert_eq!(output, None);
+            assert!(!keep_misses);
+            assert_eq!(min_hits, 1);
+        }
+        _ => panic!("Expected Trim command"),
+    }
+
+    // The report is required
+    assert!(Cli::try_parse_from(["webmock", "trim", "full-app"]).is_err());
+}
+
+#[test]
+fn test_cli_parsing_serve_daemon_and_stop() {
+    let cli = Cli::try_parse_from([
+        "webmock",
+        "serve",
+        "my-site",
+        "--daemon",
+        "--pid-file",
+        "webmock.pid",
+        "--log-file",
+        "webmock.log",
+    ])
+    .unwrap();
+
+    match cli.command {
+        Some(Commands::Serve {
+            daemon,
+            pid_file,
+            log_file,
+            ..
+        }) => {
+            assert!(daemon);
+            assert_eq!(pid_file.as_deref(), Some("webmock.pid"));
+            assert_eq!(log_file.as_deref(), Some("webmock.log"));
+        }
+        _ => panic!("Expected Serve command"),
+    }
+
+    // A background server needs somewhere to write its PID and output
+    assert!(Cli::try_parse_from(["webmock", "serve", "my-site", "--daemon"]).is_err());
+    assert!(Cli::try_parse_from([
+        "webmock",
+        "serve",
+        "my-site",
+        "--daemon",
+        "--pid-file",
+        "webmock.pid"
+    ])
+    .is_err());
+    // A foreground server may still write a PID file
+    assert!(
+        Cli::try_parse_from(["webmock", "serve", "my-site", "--pid-file", "webmock.pid"]).is_ok()
+    );
+
+    let cli = Cli::try_parse_from(["webmock", "stop", "--pid-file", "webmock.pid"]).unwrap();
+    match cli.command {
+        Some(Commands::Stop { pid_file, timeout }) => {
+            assert_eq!(pid_file, "webmock.pid");
+            assert_eq!(timeout, "10s");
+        }
+        _ => panic!("Expected Stop command"),
+    }
+}
+
+#[test]
+fn test_cli_parsing_serve_simulate_rate_limits() {
+    let cli = Cli::try_parse_from(["webmock", "serve", "my-api"]).unwrap();
+    match cli.command {
+        Some(Commands::Serve {
+            simulate_rate_limits,
+            ..
+        }) => assert!(!simulate_rate_limits),
+        _ => panic!("Expected Serve command"),
+    }
+
+    let cli =
+        Cli::try_parse_from(["webmock", "serve", "my-api", "--simulate-rate-limits"]).unwrap();
+    match cli.command {
+        Some(Commands::Serve {
+            simulate_rate_limits,
+            ..
+        }) => assert!(simulate_rate_limits),
+        _ => panic!("Expected Serve command"),
+    }
+}
+
+#[test]
+fn test_cli_parsing_serve_rewrite_absolute_urls() {
+    let cli = Cli::try_parse_from(["webmock", "serve", "my-site"]).unwrap();
+    match cli.command {
+        Some(Commands::Serve {
+            rewrite_absolute_urls,
+            ..
+        }) => assert!(!rewrite_absolute_urls),
+        _ => panic!("Expected Serve command"),
+    }
+
+    let cli = Cli::try_parse_from([
+        "webmock",
+        "serve",
+        "my-site",
+        "--map-origin",
+        "https://app.example.com",
+        "--rewrite-absolute-urls",
+    ])
+    .unwrap();
+    match cli.command {
+        Some(Commands::Serve {
+            rewrite_absolute_urls,
+            ..
+        }) => assert!(rewrite_absolute_urls),
+        _ => panic!("Expected Serve command"),
+    }
+}
+
+#[test]
+fn test_cli_parsing_serve_coverage() {
+    let cli = Cli::try_parse_from(["webmock", "serve", "my-site", "--coverage", "coverage.json"])
+        .unwrap();
+    match cli.command {
+        Some(Commands::Serve { coverage, .. }) => {
+            assert_eq!(coverage.as_deref(), Some("coverage.json"));
+        }
+        _ => panic!("Expected Serve command"),
+    }
+
+    // Counted per snapshot, so not for all of them at once
+    assert!(Cli::try_parse_from([
+        "webmock",
+        "serve",
+        "--all",
+        "--path-routing",
+        "--coverage",
+        "coverage.json",
+    ])
+    .is_err());
+}
+
+#[test]
+fn test_cli_parsing_serve_port_file() {
+    let cli =
+        Cli::try_parse_from(["webmock", "serve", "my-site", "--port-file", "ports/"]).unwrap();
+    match cli.command {
+        Some(Commands::Serve { port_file, .. }) => {
+            assert_eq!(port_file.as_deref(), Some("ports/"));
+        }
+        _ => panic!("Expected Serve command"),
+    }
+
+    // One port file per snapshot, so not for all of them at once
+    assert!(Cli::try_parse_from([
+        "webmock",
+        "serve",
+        "--all",
+        "--path-routing",
+        "--port-file",
+        "ports/",
+    ])
+    .is_err());
+}
+
+#[test]
+fn test_cli_parsing_config_command() {
+    let cli = Cli::try_parse_from([
+        "webmock",
+        "config",
+        "checkout",
+        "--port",
+        "8090",
+        "--replay-failures",
+        "as-timeout",
+        "--stateful",
+        "--strict-connect=false",
+    ])
+    .unwrap();
+
+    match cli.command {
+        Some(Commands::Config {
+            snapshot_name,
+            port,
+            replay_failures,
+            encode,
+            strict_connect,
+            stateful,
+            clear,
+            ..
+        }) => {
+            assert_eq!(snapshot_name, "checkout");
+            assert_eq!(port, Some(8090));
+            assert_eq!(replay_failures, Some(ReplayFailuresMode::AsTimeout));
+            assert_eq!(encode, None);
+            assert_eq!(strict_connect, Some(false));
+            assert_eq!(stateful, Some(true));
+            assert!(!clear);
+        }
+        _ => panic!("Expected Config command"),
+    }
+
+    // Serve only overrides stored defaults for the flags it is given
+    let cli = Cli::try_parse_from(["webmock", "serve", "checkout", "--stateful=false"]).unwrap();
+    match cli.command {
+        Some(Commands::Serve {
+            port,
+            strict_connect,
+            stateful,
+            ..
+        }) => {
+            assert_eq!(port, None);
+            assert_eq!(strict_connect, None);
+            assert_eq!(stateful, Some(false));
+        }
+        _ => panic!("Expected Serve command"),
+    }
+}
+
+#[test]
+fn test_cli_parsing_analyze_command() {
+    let cli = Cli::try_parse_from(["webmock", "analyze", "my-site", "--strict", "--json"]).unwrap();
+    match cli.command {
+        Some(Commands::Analyze {
+            snapshot_name,
+            json,
+            strict,
+            storage,
+        }) => {
+            assert_eq!(snapshot_name, "my-site");
+            assert!(json);
+            assert!(strict);
+            assert_eq!(storage, None);
+        }
+        _ => panic!("Expected Analyze command"),
+    }
+}
+
 #[test]
 fn test_cli_parsing_missing_required_args() {
     // Test capture command without required name argument
@@ -230,7 +702,7 @@ fn test_cli_parsing_edge_cases() {
 
     match cli.command {
         Some(Commands::Serve { port, .. }) => {
-            assert_eq!(port, 1);
+            assert_eq!(port, Some(1));
         }
         _ => panic!("Expected Serve command"),
     }
@@ -241,7 +713,7 @@ fn test_cli_parsing_edge_cases() {
 
     match cli.command {
         Some(Commands::Serve { port, .. }) => {
-            assert_eq!(port, 65535);
+            assert_eq!(port, Some(65535));
         }
         _ => panic!("Expected Serve command"),
     }
@@ -272,7 +744,7 @@ fn test_cli_parsing_special_characters_in_names() {
 
     match cli.command {
         Some(Commands::Serve { snapshot_name, .. }) => {
-            assert_eq!(snapshot_name, "my-test_snapshot-v2");
+            assert_eq!(snapshot_name.as_deref(), Some("my-test_snapshot-v2"));
         }
         _ => panic!("Expected Serve command"),
     }
@@ -289,7 +761,7 @@ fn test_cli_parsing_urls_with_paths_and_params() {
         Some(Commands::Capture {
             url: parsed_url, ..
         }) => {
-            assert_eq!(parsed_url, url);
+            assert_eq!(parsed_url.as_deref(), Some(url));
         }
         _ => panic!("Expected Capture command"),
     }
@@ -303,7 +775,7 @@ fn test_cli_parsing_urls_with_paths_and_params() {
         Some(Commands::Capture {
             url: parsed_url, ..
         }) => {
-            assert_eq!(parsed_url, localhost_url);
+            assert_eq!(parsed_url.as_deref(), Some(localhost_url));
         }
         _ => panic!("Expected Capture command"),
     }
@@ -326,3 +798,1323 @@ fn test_cli_parsing_generate_completion() {
     let cli = Cli::try_parse_from(args).unwrap();
     assert!(cli.generate_completion.is_some());
 }
+
+#[test]
+fn test_cli_parsing_bench_command() {
+    let args = ["webmock", "bench", "my-site"];
+    let cli = Cli::try_parse_from(args).unwrap();
+
+    match cli.command {
+        Some(Commands::Bench {
+            snapshot_name,
+            connections,
+            duration,
+            url,
+            ..
+        }) => {
+            assert_eq!(snapshot_name, "my-site");
+            assert_eq!(connections, 64);
+            assert_eq!(duration, "10s");
+            assert!(url.is_none());
+        }
+        _ => panic!("Expected Bench command"),
+    }
+
+    let args = [
+        "webmock",
+        "bench",
+        "my-site",
+        "--connections",
+        "8",
+        "--duration",
+        "5s",
+        "--url",
+        "https://example.com/api",
+    ];
+    let cli = Cli::try_parse_from(args).unwrap();
+
+    match cli.command {
+        Some(Commands::Bench {
+            connections,
+            duration,
+            url,
+            ..
+        }) => {
+            assert_eq!(connections, 8);
+            assert_eq!(duration, "5s");
+            assert_eq!(url.as_deref(), Some("https://example.com/api"));
+        }
+        _ => panic!("Expected Bench command"),
+    }
+}
+
+#[test]
+fn test_cli_parsing_capture_environment() {
+    let args = [
+        "webmock",
+        "capture",
+        "https://example.com",
+        "--name",
+        "fr",
+        "--locale",
+        "fr-FR",
+        "--timezone",
+        "Europe/Paris",
+        "--viewport",
+        "1440x900",
+    ];
+    let cli = Cli::try_parse_from(args).unwrap();
+
+    match cli.command {
+        Some(Commands::Capture {
+            locale,
+            timezone,
+            viewport,
+            ..
+        }) => {
+            assert_eq!(locale.as_deref(), Some("fr-FR"));
+            assert_eq!(timezone.as_deref(), Some("Europe/Paris"));
+            assert_eq!(viewport.as_deref(), Some("1440x900"));
+        }
+        _ => panic!("Expected Capture command"),
+    }
+}
+
+#[test]
+fn test_cli_parsing_inspect_detailed() {
+    let args = ["webmock", "inspect", "my-site", "--detailed"];
+    let cli = Cli::try_parse_from(args).unwrap();
+
+    match cli.command {
+        Some(Commands::Inspect {
+            snapshot_name,
+            detailed,
+            ..
+        }) => {
+            assert_eq!(snapshot_name, "my-site");
+            assert!(detailed);
+        }
+        _ => panic!("Expected Inspect command"),
+    }
+}
+
+#[test]
+fn test_cli_parsing_inspect_record_selection() {
+    let args = [
+        "webmock",
+        "inspect",
+        "my-site",
+        "--request",
+        "3",
+        "--dump-body",
+        "-",
+        "--show-headers",
+    ];
+    match Cli::try_parse_from(args).unwrap().command {
+        Some(Commands::Inspect {
+            request,
+            url,
+            dump_body,
+            show_headers,
+            ..
+        }) => {
+            assert_eq!(request, Some(3));
+            assert!(url.is_none());
+            assert_eq!(dump_body.as_deref(), Some("-"));
+            assert!(show_headers);
+        }
+        _ => panic!("Expected Inspect command"),
+    }
+
+    // One selector at a time, and dumping needs one
+    assert!(Cli::try_parse_from([
+        "webmock",
+        "inspect",
+        "my-site",
+        "--request",
+        "1",
+        "--url",
+        "https://example.com/",
+    ])
+    .is_err());
+    assert!(
+        Cli::try_parse_from(["webmock", "inspect", "my-site", "--dump-body", "out.bin"]).is_err()
+    );
+
+    // Printed bodies are cut at 1 MB unless told otherwise
+    match Cli::try_parse_from([
+        "webmock",
+        "inspect",
+        "my-site",
+        "--request",
+        "3",
+        "--show-body",
+    ])
+    .unwrap()
+    .command
+    {
+        Some(Commands::Inspect {
+            show_body,
+            max_body_bytes,
+            ..
+        }) => {
+            assert!(show_body);
+            assert_eq!(max_body_bytes, 1024 * 1024);
+        }
+        _ => panic!("Expected Inspect command"),
+    }
+}
+
+#[test]
+fn test_cli_parsing_inspect_diagnostics() {
+    match Cli::try_parse_from(["webmock", "inspect", "my-site", "--diagnostics"])
+        .unwrap()
+        .command
+    {
+        Some(Commands::Inspect { diagnostics, .. }) => assert!(diagnostics),
+        _ => panic!("Expected Inspect command"),
+    }
+    match Cli::try_parse_from(["webmock", "inspect", "my-site"])
+        .unwrap()
+        .command
+    {
+        Some(Commands::Inspect { diagnostics, .. }) => assert!(!diagnostics),
+        _ => panic!("Expected Inspect command"),
+    }
+}
+
+#[test]
+fn test_cli_parsing_ca_commands() {
+    let cli = Cli::try_parse_from(["webmock", "ca", "trust", "--dry-run"]).unwrap();
+    match cli.command {
+        Some(Commands::Ca {
+            action: CaAction::Trust { yes, dry_run },
+        }) => {
+            assert!(!yes);
+            assert!(dry_run);
+        }
+        _ => panic!("Expected ca trust command"),
+    }
+
+    let cli = Cli::try_parse_from(["webmock", "ca", "untrust", "-y"]).unwrap();
+    match cli.command {
+        Some(Commands::Ca {
+            action: CaAction::Untrust { yes, dry_run },
+        }) => {
+            assert!(yes);
+            assert!(!dry_run);
+        }
+        _ => panic!("Expected ca untrust command"),
+    }
+
+    assert!(Cli::try_parse_from(["webmock", "ca"]).is_err());
+}
+
+#[test]
+fn test_cli_parsing_completions_install() {
+    let cli = Cli::try_parse_from(["webmock", "completions", "install", "--shell", "zsh"]).unwrap();
+    match cli.command {
+        Some(Commands::Completions {
+            action: CompletionsAction::Install { shell, uninstall },
+        }) => {
+            assert_eq!(shell, Some(Shell::Zsh));
+            assert!(!uninstall);
+        }
+        _ => panic!("Expected completions install command"),
+    }
+
+    let cli = Cli::try_parse_from(["webmock", "completions", "install", "--uninstall"]).unwrap();
+    match cli.command {
+        Some(Commands::Completions {
+            action: CompletionsAction::Install { shell, uninstall },
+        }) => {
+            assert!(shell.is_none());
+            assert!(uninstall);
+        }
+        _ => panic!("Expected completions install command"),
+    }
+}
+
+#[test]
+fn test_cli_parsing_serve_method_matching() {
+    let cli = Cli::try_parse_from([
+        "webmock",
+        "serve",
+        "test-snapshot",
+        "--method-fallback",
+        "PATCH=PUT,DELETE=POST",
+        "--honor-method-override",
+    ])
+    .unwrap();
+    match cli.command {
+        Some(Commands::Serve {
+            method_fallback,
+            honor_method_override,
+            ..
+        }) => {
+            assert_eq!(method_fallback.as_deref(), Some("PATCH=PUT,DELETE=POST"));
+            assert!(honor_method_override);
+        }
+        _ => panic!("Expected Serve command"),
+    }
+}
+
+#[test]
+fn test_cli_parsing_serve_generate_etags() {
+    let cli =
+        Cli::try_parse_from(["webmock", "serve", "test-snapshot", "--generate-etags"]).unwrap();
+    match cli.command {
+        Some(Commands::Serve { generate_etags, .. }) => assert!(generate_etags),
+        _ => panic!("Expected Serve command"),
+    }
+}
+
+#[test]
+fn test_cli_parsing_serve_forward_unmatched() {
+    let cli = Cli::try_parse_from([
+        "webmock",
+        "serve",
+        "test-snapshot",
+        "--forward-unmatched",
+        "https://staging.example.com",
+    ])
+    .unwrap();
+    match cli.command {
+        Some(Commands::Serve {
+            forward_unmatched, ..
+        }) => assert_eq!(
+            forward_unmatched.as_deref(),
+            Some("https://staging.example.com")
+        ),
+        _ => panic!("Expected Serve command"),
+    }
+}
+
+#[test]
+fn test_cli_parsing_capture_device_options() {
+    let args = [
+        "webmock",
+        "capture",
+        "https://example.com",
+        "--name",
+        "mobile",
+        "--device",
+        "iphone-13",
+        "--user-agent",
+        "CustomAgent/1.0",
+    ];
+    let cli = Cli::try_parse_from(args).unwrap();
+
+    match cli.command {
+        Some(Commands::Capture {
+            device, user_agent, ..
+        }) => {
+            assert_eq!(device.as_deref(), Some("iphone-13"));
+            assert_eq!(user_agent.as_deref(), Some("CustomAgent/1.0"));
+        }
+        _ => panic!("Expected Capture command"),
+    }
+}
+
+#[test]
+fn test_cli_parsing_capture_attach() {
+    let args = [
+        "webmock",
+        "capture",
+        "https://example.com",
+        "--name",
+        "site",
+        "--attach-port",
+        "9222",
+        "--proxy-port",
+        "8899",
+    ];
+    let cli = Cli::try_parse_from(args).unwrap();
+
+    match cli.command {
+        Some(Commands::Capture {
+            attach,
+            attach_port,
+            proxy_port,
+            ..
+        }) => {
+            assert!(attach.is_none());
+            assert_eq!(attach_port, Some(9222));
+            assert_eq!(proxy_port, Some(8899));
+        }
+        _ => panic!("Expected Capture command"),
+    }
+
+    // The attached browser can only be pointed at a known proxy port
+    let args = [
+        "webmock",
+        "capture",
+        "https://example.com",
+        "--name",
+        "site",
+        "--attach",
+        "ws://127.0.0.1:9222/devtools/browser/4f1c",
+    ];
+    assert!(Cli::try_parse_from(args).is_err());
+
+    let args = [
+        "webmock",
+        "capture",
+        "https://example.com",
+        "--name",
+        "site",
+        "--attach",
+        "http://127.0.0.1:9222",
+        "--attach-port",
+        "9222",
+        "--proxy-port",
+        "8899",
+    ];
+    assert!(Cli::try_parse_from(args).is_err());
+}
+
+#[test]
+fn test_cli_parsing_capture_from_sitemap() {
+    let args = [
+        "webmock",
+        "capture",
+        "--from-sitemap",
+        "https://example.com/sitemap.xml",
+        "--name",
+        "site",
+        "--limit",
+        "20",
+        "--same-host-only",
+        "--total-timeout",
+        "300",
+    ];
+    let cli = Cli::try_parse_from(args).unwrap();
+
+    match cli.command {
+        Some(Commands::Capture {
+            url,
+            from_sitemap,
+            limit,
+            same_host_only,
+            total_timeout,
+            ..
+        }) => {
+            assert!(url.is_none());
+            assert_eq!(
+                from_sitemap.as_deref(),
+                Some("https://example.com/sitemap.xml")
+            );
+            assert_eq!(limit, Some(20));
+            assert!(same_host_only);
+            assert_eq!(total_timeout, Some(300));
+        }
+        _ => panic!("Expected Capture command"),
+    }
+
+    // A URL or a sitemap is required, but not both
+    assert!(Cli::try_parse_from(["webmock", "capture", "--name", "site"]).is_err());
+    assert!(Cli::try_parse_from([
+        "webmock",
+        "capture",
+        "https://example.com",
+        "--from-sitemap",
+        "https://example.com/sitemap.xml",
+        "--name",
+        "site",
+    ])
+    .is_err());
+    // Sitemap options need a sitemap
+    assert!(Cli::try_parse_from([
+        "webmock",
+        "capture",
+        "https://example.com",
+        "--name",
+        "site",
+        "--limit",
+        "5",
+    ])
+    .is_err());
+}
+
+#[test]
+fn test_cli_parsing_capture_deterministic() {
+    let args = [
+        "webmock",
+        "capture",
+        "http://localhost:3000",
+        "--name",
+        "fixture",
+        "--deterministic",
+    ];
+    match Cli::try_parse_from(args).unwrap().command {
+        Some(Commands::Capture { deterministic, .. }) => assert!(deterministic),
+        _ => panic!("Expected Capture command"),
+    }
+}
+
+#[test]
+fn test_cli_parsing_capture_emulate_offline_after_load() {
+    let args = [
+        "webmock",
+        "capture",
+        "https://app.example.com",
+        "--name",
+        "pwa",
+        "--emulate-offline-after-load",
+    ];
+    match Cli::try_parse_from(args).unwrap().command {
+        Some(Commands::Capture {
+            emulate_offline_after_load,
+            ..
+        }) => assert!(emulate_offline_after_load),
+        _ => panic!("Expected Capture command"),
+    }
+
+    assert!(Cli::try_parse_from([
+        "webmock",
+        "capture",
+        "--from-sitemap",
+        "https://app.example.com/sitemap.xml",
+        "--name",
+        "pwa",
+        "--emulate-offline-after-load",
+    ])
+    .is_err());
+}
+
+#[test]
+fn test_cli_parsing_serve_throttle() {
+    let args = [
+        "webmock",
+        "serve",
+        "my-site",
+        "--throttle",
+        "down=400kbps,latency=300ms",
+        "--throttle-shared",
+    ];
+    match Cli::try_parse_from(args).unwrap().command {
+        Some(Commands::Serve {
+            throttle,
+            throttle_shared,
+            ..
+        }) => {
+            assert_eq!(throttle.as_deref(), Some("down=400kbps,latency=300ms"));
+            assert!(throttle_shared);
+        }
+        _ => panic!("Expected Serve command"),
+    }
+
+    // Sharing needs a throttle to share
+    assert!(Cli::try_parse_from(["webmock", "serve", "my-site", "--throttle-shared"]).is_err());
+}
+
+#[test]
+fn test_cli_parsing_capture_no_artifacts() {
+    let parse = |extra: &[&str]| {
+        let mut args = vec![
+            "webmock",
+            "capture",
+            "https://example.com",
+            "--name",
+            "site",
+        ];
+        args.extend_from_slice(extra);
+        match Cli::try_parse_from(args).unwrap().command {
+            Some(Commands::Capture { no_artifacts, .. }) => no_artifacts,
+            _ => panic!("Expected Capture command"),
+        }
+    };
+    assert!(!parse(&[]));
+    assert!(parse(&["--no-artifacts"]));
+}
+
+#[test]
+fn test_cli_parsing_variants() {
+    let cli = Cli::try_parse_from([
+        "webmock",
+        "capture",
+        "https://example.com",
+        "--name",
+        "site",
+        "--variant",
+        "admin:admin.txt",
+        "--variant",
+        "member:member.txt",
+    ])
+    .unwrap();
+    match cli.command {
+        Some(Commands::Capture { variant, .. }) => {
+            assert_eq!(variant, ["admin:admin.txt", "member:member.txt"]);
+        }
+        _ => panic!("Expected Capture command"),
+    }
+
+    // Variant reloads can't run after the page has been taken offline
+    assert!(Cli::try_parse_from([
+        "webmock",
+        "capture",
+        "https://example.com",
+        "--name",
+        "site",
+        "--variant",
+        "admin:admin.txt",
+        "--emulate-offline-after-load",
+    ])
+    .is_err());
+
+    let cli = Cli::try_parse_from(["webmock", "serve", "site", "--variant", "admin"]).unwrap();
+    match cli.command {
+        Some(Commands::Serve { variant, .. }) => assert_eq!(variant.as_deref(), Some("admin")),
+        _ => panic!("Expected Serve command"),
+    }
+}
+
+#[test]
+fn test_cli_parsing_capture_metrics() {
+    let parse = |extra: &[&str]| {
+        let mut args = vec![
+            "webmock",
+            "capture",
+            "https://example.com",
+            "--name",
+            "site",
+        ];
+        args.extend_from_slice(extra);
+        match Cli::try_parse_from(args).unwrap().command {
+            Some(Commands::Capture { metrics, .. }) => metrics,
+            _ => panic!("Expected Capture command"),
+        }
+    };
+    assert!(!parse(&[]));
+    assert!(parse(&["--metrics"]));
+}
+
+#[test]
+fn test_cli_parsing_capture_only_hosts() {
+    let cli = Cli::try_parse_from([
+        "webmock",
+        "capture",
+        "https://app.example.com",
+        "--name",
+        "app",
+        "--only-hosts",
+        "api.example.com,*.cdn.example.com",
+    ])
+    .unwrap();
+    match cli.command {
+        Some(Commands::Capture { only_hosts, .. }) => {
+            assert_eq!(
+                only_hosts.as_deref(),
+                Some("api.example.com,*.cdn.example.com")
+            );
+        }
+        _ => panic!("Expected Capture command"),
+    }
+}
+
+#[test]
+fn test_cli_parsing_capture_max_connections_per_host() {
+    let parse = |extra: &[&str]| {
+        let mut args = vec![
+            "webmock",
+            "capture",
+            "https://example.com",
+            "--name",
+            "site",
+        ];
+        args.extend_from_slice(extra);
+        match Cli::try_parse_from(args).unwrap().command {
+            Some(Commands::Capture {
+                max_connections_per_host,
+                ..
+            }) => max_connections_per_host,
+            _ => panic!("Expected Capture command"),
+        }
+    };
+
+    assert_eq!(parse(&[]), None);
+    assert_eq!(parse(&["--max-connections-per-host", "2"]), Some(2));
+}
+
+#[test]
+fn test_cli_parsing_set_entry() {
+    let cli = Cli::try_parse_from([
+        "webmock",
+        "set-entry",
+        "my-site",
+        "https://www.example.com/home",
+        "--force",
+    ])
+    .unwrap();
+    match cli.command {
+        Some(Commands::SetEntry {
+            snapshot_name,
+            url,
+            force,
+            storage,
+        }) => {
+            assert_eq!(snapshot_name, "my-site");
+            assert_eq!(url, "https://www.example.com/home");
+            assert!(force);
+            assert!(storage.is_none());
+        }
+        _ => panic!("Expected SetEntry command"),
+    }
+
+    assert!(Cli::try_parse_from(["webmock", "set-entry", "my-site"]).is_err());
+}
+
+#[test]
+fn test_cli_parsing_annotate() {
+    let cli = Cli::try_parse_from([
+        "webmock",
+        "annotate",
+        "my-site",
+        "--request",
+        "12",
+        "--note",
+        "stale, re-capture after the API v2 rollout",
+    ])
+    .unwrap();
+    match cli.command {
+        Some(Commands::Annotate {
+            snapshot_name,
+            request,
+            note,
+            remove,
+            storage,
+        }) => {
+            assert_eq!(snapshot_name, "my-site");
+            assert_eq!(request, 12);
+            assert_eq!(
+                note.as_deref(),
+                Some("stale, re-capture after the API v2 rollout")
+            );
+            assert!(!remove);
+            assert!(storage.is_none());
+        }
+        _ => panic!("Expected Annotate command"),
+    }
+
+    let cli = Cli::try_parse_from([
+        "webmock",
+        "annotate",
+        "my-site",
+        "--request",
+        "12",
+        "--remove",
+    ])
+    .unwrap();
+    assert!(matches!(
+        cli.command,
+        Some(Commands::Annotate {
+            note: None,
+            remove: true,
+            ..
+        })
+    ));
+
+    // A note or --remove is needed, but not both
+    assert!(Cli::try_parse_from(["webmock", "annotate", "my-site", "--request", "12"]).is_err());
+    assert!(Cli::try_parse_from([
+        "webmock",
+        "annotate",
+        "my-site",
+        "--request",
+        "12",
+        "--note",
+        "x",
+        "--remove",
+    ])
+    .is_err());
+    assert!(Cli::try_parse_from(["webmock", "annotate", "my-site", "--note", "x"]).is_err());
+
+    // inspect --json lists the notes, but not next to a single record
+    let cli = Cli::try_parse_from(["webmock", "inspect", "my-site", "--json"]).unwrap();
+    assert!(matches!(
+        cli.command,
+        Some(Commands::Inspect { json: true, .. })
+    ));
+    assert!(
+        Cli::try_parse_from(["webmock", "inspect", "my-site", "--json", "--request", "1"]).is_err()
+    );
+}
+
+#[test]
+fn test_cli_parsing_assert() {
+    let cli = Cli::try_parse_from([
+        "webmock",
+        "assert",
+        "my-site",
+        "--expect",
+        "GET https://api.example.com/users -> 200 json",
+        "--expect",
+        "GET https://example.com/app.js -> js",
+        "--json",
+    ])
+    .unwrap();
+    match cli.command {
+        Some(Commands::Assert {
+            snapshot_name,
+            expect,
+            expect_file,
+            json,
+            storage,
+        }) => {
+            assert_eq!(snapshot_name, "my-site");
+            assert_eq!(
+                expect,
+                [
+                    "GET https://api.example.com/users -> 200 json",
+                    "GET https://example.com/app.js -> js"
+                ]
+            );
+            assert!(expect_file.is_none());
+            assert!(json);
+            assert!(storage.is_none());
+        }
+        _ => panic!("Expected Assert command"),
+    }
+
+    assert!(Cli::try_parse_from([
+        "webmock",
+        "assert",
+        "my-site",
+        "--expect-file",
+        "expectations.yaml"
+    ])
+    .is_ok());
+    // Something to check is required
+    assert!(Cli::try_parse_from(["webmock", "assert", "my-site"]).is_err());
+}
+
+#[test]
+fn test_cli_parsing_encrypt_and_decrypt() {
+    let cli = Cli::try_parse_from([
+        "webmock",
+        "capture",
+        "https://example.com",
+        "--name",
+        "site",
+        "--encrypt",
+    ])
+    .unwrap();
+    match cli.command {
+        Some(Commands::Capture { encrypt, .. }) => assert!(encrypt),
+        _ => panic!("Expected Capture command"),
+    }
+
+    let cli =
+        Cli::try_parse_from(["webmock", "encrypt", "my-site", "--storage", "/tmp/s"]).unwrap();
+    match cli.command {
+        Some(Commands::Encrypt {
+            snapshot_name,
+            storage,
+        }) => {
+            assert_eq!(snapshot_name, "my-site");
+            assert_eq!(storage.as_deref(), Some("/tmp/s"));
+        }
+        _ => panic!("Expected Encrypt command"),
+    }
+
+    let cli = Cli::try_parse_from(["webmock", "decrypt", "my-site"]).unwrap();
+    match cli.command {
+        Some(Commands::Decrypt {
+            snapshot_name,
+            storage,
+        }) => {
+            assert_eq!(snapshot_name, "my-site");
+            assert!(storage.is_none());
+        }
+        _ => panic!("Expected Decrypt command"),
+    }
+    assert!(Cli::try_parse_from(["webmock", "encrypt"]).is_err());
+}
+
+#[test]
+fn test_cli_parsing_serve_log_bodies() {
+    let parse = |extra: &[&str]| {
+        let mut args = vec!["webmock", "serve", "my-site"];
+        args.extend_from_slice(extra);
+        Cli::try_parse_from(args)
+    };
+
+    match parse(&["--log-bodies"]).unwrap().command {
+        Some(Commands::Serve { log_bodies, .. }) => assert_eq!(log_bodies, Some(1024)),
+        _ => panic!("Expected Serve command"),
+    }
+    match parse(&[
+        "--log-bodies=256",
+        "--redact-header",
+        "x-api-key",
+        "--redact-defaults",
+        "--redact-body-pattern",
+        "token=\\w+",
+    ])
+    .unwrap()
+    .command
+    {
+        Some(Commands::Serve {
+            log_bodies,
+            redact_header,
+            redact_defaults,
+            redact_body_pattern,
+            ..
+        }) => {
+            assert_eq!(log_bodies, Some(256));
+            assert_eq!(redact_header, vec!["x-api-key"]);
+            assert!(redact_defaults);
+            assert_eq!(redact_body_pattern, vec!["token=\\w+"]);
+        }
+        _ => panic!("Expected Serve command"),
+    }
+    match parse(&[]).unwrap().command {
+        Some(Commands::Serve { log_bodies, .. }) => assert_eq!(log_bodies, None),
+        _ => panic!("Expected Serve command"),
+    }
+
+    // Redaction only applies to logged bodies
+    assert!(parse(&["--redact-header", "x-api-key"]).is_err());
+}
+
+#[test]
+fn test_cli_parsing_serve_dns() {
+    let args = [
+        "webmock",
+        "serve",
+        "my-site",
+        "--dns-port",
+        "5353",
+        "--advertise-ip",
+        "192.168.1.20",
+        "--dns-upstream",
+        "1.1.1.1",
+    ];
+    match Cli::try_parse_from(args).unwrap().command {
+        Some(Commands::Serve {
+            dns_port,
+            advertise_ip,
+            dns_upstream,
+            ..
+        }) => {
+            assert_eq!(dns_port, Some(5353));
+            assert_eq!(advertise_ip, Some("192.168.1.20".parse().unwrap()));
+            assert_eq!(dns_upstream.as_deref(), Some("1.1.1.1"));
+        }
+        _ => panic!("Expected Serve command"),
+    }
+
+    // The address and upstream only apply to the DNS responder
+    assert!(
+        Cli::try_parse_from(["webmock", "serve", "my-site", "--advertise-ip", "10.0.0.2"]).is_err()
+    );
+    assert!(Cli::try_parse_from([
+        "webmock",
+        "serve",
+        "my-site",
+        "--dns-port",
+        "5353",
+        "--advertise-ip",
+        "not-an-ip"
+    ])
+    .is_err());
+}
+
+#[test]
+fn test_cli_parsing_export() {
+    let cli = Cli::try_parse_from([
+        "webmock", "export", "my-site", "--format", "openapi", "--output", "api.yaml",
+    ])
+    .unwrap();
+    match cli.command {
+        Some(Commands::Export {
+            snapshot_name,
+            format,
+            output,
+            storage,
+        }) => {
+            assert_eq!(snapshot_name, "my-site");
+            assert_eq!(format, ExportFormat::OpenApi);
+            assert_eq!(output.as_deref(), Some("api.yaml"));
+            assert!(storage.is_none());
+        }
+        _ => panic!("Expected Export command"),
+    }
+
+    // OpenAPI to stdout by default
+    let cli = Cli::try_parse_from(["webmock", "export", "my-site"]).unwrap();
+    assert!(matches!(
+        cli.command,
+        Some(Commands::Export {
+            format: ExportFormat::OpenApi,
+            output: None,
+            ..
+        })
+    ));
+    assert!(Cli::try_parse_from(["webmock", "export", "my-site", "--format", "har"]).is_err());
+}
+
+#[test]
+fn test_cli_parsing_capture_share_browser() {
+    let args = [
+        "webmock",
+        "capture",
+        "https://example.com",
+        "--name",
+        "site",
+        "--share-browser",
+    ];
+    let cli = Cli::try_parse_from(args).unwrap();
+
+    match cli.command {
+        Some(Commands::Capture { share_browser, .. }) => assert!(share_browser),
+        _ => panic!("Expected Capture command"),
+    }
+
+    // An attached browser is the user's, not one to share
+    let args = [
+        "webmock",
+        "capture",
+        "https://example.com",
+        "--name",
+        "site",
+        "--share-browser",
+        "--attach-port",
+        "9222",
+        "--proxy-port",
+        "8899",
+    ];
+    assert!(Cli::try_parse_from(args).is_err());
+}
+
+#[test]
+fn test_cli_parsing_doctor() {
+    let cli = Cli::try_parse_from(["webmock", "doctor"]).unwrap();
+    assert!(matches!(
+        cli.command,
+        Some(Commands::Doctor {
+            json: false,
+            storage: None
+        })
+    ));
+
+    let cli = Cli::try_parse_from(["webmock", "doctor", "--json", "--storage", "/tmp/wm"]).unwrap();
+    match cli.command {
+        Some(Commands::Doctor { json, storage }) => {
+            assert!(json);
+            assert_eq!(storage.as_deref(), Some("/tmp/wm"));
+        }
+        _ => panic!("Expected Doctor command"),
+    }
+}
+
+#[test]
+fn test_cli_parsing_serve_allow_duplicate() {
+    let cli = Cli::try_parse_from(["webmock", "serve", "my-site"]).unwrap();
+    assert!(matches!(
+        cli.command,
+        Some(Commands::Serve {
+            allow_duplicate: false,
+            ..
+        })
+    ));
+
+    let cli = Cli::try_parse_from(["webmock", "serve", "my-site", "--allow-duplicate"]).unwrap();
+    assert!(matches!(
+        cli.command,
+        Some(Commands::Serve {
+            allow_duplicate: true,
+            ..
+        })
+    ));
+}
+
+#[test]
+fn test_cli_parsing_drift_command() {
+    let cli = Cli::try_parse_from([
+        "webmock",
+        "drift",
+        "my-api",
+        "--live",
+        "--url-glob",
+        "https://api.example.com/*",
+        "--header",
+        "Authorization: Bearer abc",
+        "--header",
+        "X-Tenant: 1",
+        "--strict",
+    ])
+    .unwrap();
+    match cli.command {
+        Some(Commands::Drift {
+            snapshot_name,
+            live,
+            url_glob,
+            header,
+            strict,
+            storage,
+        }) => {
+            assert_eq!(snapshot_name, "my-api");
+            assert!(live);
+            assert_eq!(url_glob.as_deref(), Some("https://api.example.com/*"));
+            assert_eq!(header, vec!["Authorization: Bearer abc", "X-Tenant: 1"]);
+            assert!(strict);
+            assert_eq!(storage, None);
+        }
+        _ => panic!("Expected Drift command"),
+    }
+
+    // There is nothing but the live API to compare with yet
+    assert!(Cli::try_parse_from(["webmock", "drift", "my-api"]).is_err());
+}
+
+#[test]
+fn test_capture_settle_strategy_parsing() {
+    let cli = Cli::try_parse_from([
+        "webmock",
+        "capture",
+        "https://example.com",
+        "--name",
+        "spa",
+        "--settle-strategy",
+        "fixed:10",
+    ])
+    .unwrap();
+    match cli.command {
+        Some(Commands::Capture {
+            settle_strategy, ..
+        }) => {
+            assert_eq!(settle_strategy.as_deref(), Some("fixed:10"));
+        }
+        _ => panic!("Expected Capture command"),
+    }
+
+    let cli = Cli::try_parse_from(["webmock", "capture", "https://example.com", "--name", "spa"])
+        .unwrap();
+    match cli.command {
+        Some(Commands::Capture {
+            settle_strategy, ..
+        }) => assert_eq!(settle_strategy, None),
+        _ => panic!("Expected Capture command"),
+    }
+}
+
+#[test]
+fn test_cli_parsing_serve_replay_early_hints() {
+    let cli =
+        Cli::try_parse_from(["webmock", "serve", "test-snapshot", "--replay-early-hints"]).unwrap();
+    match cli.command {
+        Some(Commands::Serve {
+            replay_early_hints, ..
+        }) => assert!(replay_early_hints),
+        _ => panic!("Expected Serve command"),
+    }
+
+    let cli = Cli::try_parse_from(["webmock", "serve", "test-snapshot"]).unwrap();
+    match cli.command {
+        Some(Commands::Serve {
+            replay_early_hints, ..
+        }) => assert!(!replay_early_hints),
+        _ => panic!("Expected Serve command"),
+    }
+}
+
+#[test]
+fn test_cli_parsing_browse_command() {
+    let cli =
+        Cli::try_parse_from(["webmock", "browse", "my-site", "--storage", "/tmp/mocks"]).unwrap();
+    match cli.command {
+        Some(Commands::Browse {
+            snapshot_name,
+            storage,
+        }) => {
+            assert_eq!(snapshot_name, "my-site");
+            assert_eq!(storage.as_deref(), Some("/tmp/mocks"));
+        }
+        _ => panic!("Expected Browse command"),
+    }
+
+    assert!(Cli::try_parse_from(["webmock", "browse"]).is_err());
+}
+
+#[test]
+fn test_cli_parsing_capture_fail_on_warnings() {
+    let cli = Cli::try_parse_from([
+        "webmock",
+        "capture",
+        "https://example.com",
+        "--name",
+        "ci",
+        "--fail-on-warnings",
+    ])
+    .unwrap();
+    match cli.command {
+        Some(Commands::Capture {
+            fail_on_warnings, ..
+        }) => assert!(fail_on_warnings),
+        _ => panic!("Expected Capture command"),
+    }
+
+    let cli =
+        Cli::try_parse_from(["webmock", "capture", "https://example.com", "--name", "ci"]).unwrap();
+    match cli.command {
+        Some(Commands::Capture {
+            fail_on_warnings, ..
+        }) => assert!(!fail_on_warnings),
+        _ => panic!("Expected Capture command"),
+    }
+}
+
+#[test]
+fn test_cli_parsing_capture_abort_conditions() {
+    let cli = Cli::try_parse_from([
+        "webmock",
+        "capture",
+        "https://example.com",
+        "--name",
+        "ci",
+        "--abort-on-js-errors",
+        "5",
+        "--abort-on-status",
+        "5xx",
+        "--save-on-abort",
+    ])
+    .unwrap();
+    match cli.command {
+        Some(Commands::Capture {
+            abort_on_js_errors,
+            abort_on_status,
+            save_on_abort,
+            ..
+        }) => {
+            assert_eq!(abort_on_js_errors, Some(5));
+            assert_eq!(abort_on_status.as_deref(), Some("5xx"));
+            assert!(save_on_abort);
+        }
+        _ => panic!("Expected Capture command"),
+    }
+
+    let cli =
+        Cli::try_parse_from(["webmock", "capture", "https://example.com", "--name", "ci"]).unwrap();
+    match cli.command {
+        Some(Commands::Capture {
+            abort_on_js_errors,
+            abort_on_status,
+            save_on_abort,
+            ..
+        }) => {
+            assert_eq!(abort_on_js_errors, None);
+            assert_eq!(abort_on_status, None);
+            assert!(!save_on_abort);
+        }
+        _ => panic!("Expected Capture command"),
+    }
+
+    assert!(Cli::try_parse_from([
+        "webmock",
+        "capture",
+        "https://example.com",
+        "--name",
+        "ci",
+        "--abort-on-js-errors",
+        "-1",
+    ])
+    .is_err());
+}
+
+#[test]
+fn test_cli_parsing_shadow() {
+    let cli = Cli::try_parse_from([
+        "webmock",
+        "shadow",
+        "my-api",
+        "--upstream",
+        "https://staging.example.com",
+        "--report",
+        "out.json",
+    ])
+    .unwrap();
+    match cli.command {
+        Some(Commands::Shadow {
+            snapshot_name,
+            listen,
+            upstream,
+            report,
+            storage,
+        }) => {
+            assert_eq!(snapshot_name, "my-api");
+            assert_eq!(listen, 8081);
+            assert_eq!(upstream, "https://staging.example.com");
+            assert_eq!(report.as_deref(), Some("out.json"));
+            assert_eq!(storage, None);
+        }
+        _ => panic!("Expected Shadow command"),
+    }
+
+    let cli = Cli::try_parse_from([
+        "webmock",
+        "shadow",
+        "my-api",
+        "--listen",
+        "9000",
+        "--upstream",
+        "http://localhost:3000",
+    ])
+    .unwrap();
+    match cli.command {
+        Some(Commands::Shadow { listen, .. }) => assert_eq!(listen, 9000),
+        _ => panic!("Expected Shadow command"),
+    }
+
+    // There is nothing to shadow without a backend
+    assert!(Cli::try_parse_from(["webmock", "shadow", "my-api"]).is_err());
+}
+
+#[test]
+fn test_cli_parsing_capture_provenance() {
+    let cli = Cli::try_parse_from([
+        "webmock",
+        "capture",
+        "https://example.com",
+        "--name",
+        "site",
+        "--consent-note",
+        "approved by legal, ticket ABC-123",
+        "--no-provenance",
+    ])
+    .unwrap();
+    match cli.command {
+        Some(Commands::Capture {
+            consent_note,
+            no_provenance,
+            ..
+        }) => {
+            assert_eq!(
+                consent_note.as_deref(),
+                Some("approved by legal, ticket ABC-123")
+            );
+            assert!(no_provenance);
+        }
+        _ => panic!("Expected Capture command"),
+    }
+
+    let cli = Cli::try_parse_from([
+        "webmock",
+        "capture",
+        "https://example.com",
+        "--name",
+        "site",
+    ])
+    .unwrap();
+    match cli.command {
+        Some(Commands::Capture {
+            consent_note,
+            no_provenance,
+            ..
+        }) => {
+            assert_eq!(consent_note, None);
+            assert!(!no_provenance);
+        }
+        _ => panic!("Expected Capture command"),
+    }
+}
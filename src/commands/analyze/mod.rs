@@ -0,0 +1,217 @@
+//! Analyze command implementation
+//!
+//! Checks whether a snapshot is self-contained: every resource its captured
+//! HTML and CSS reference should have a record that replay can answer it
+//! with. References are looked up the way `serve` looks up requests, so a
+//! resource reported missing is one the page will get a 404 for.
+
+pub mod references;
+
+use std::collections::HashMap;
+use std::num::NonZeroUsize;
+
+use colored::Colorize;
+use hyper::{HeaderMap, Method};
+use serde::Serialize;
+use tracing::info;
+use url::Url;
+
+use crate::error::{Result, WebMockError};
+use crate::feedback::{UserFeedback, ValidationHelper};
+use crate::serve::{DefaultMatcher, IncomingRequest, RequestMatcher};
+use crate::storage::{Snapshot, Storage};
+
+use references::{extract_references, DocumentKind};
+
+/// Documents listed per missing resource before the rest are counted
+const REFERRERS_SHOWN: usize = 3;
+
+/// Options for the analyze command
+#[derive(Debug, Clone, Default)]
+pub struct AnalyzeOptions {
+    /// Print the report as JSON
+    pub json: bool,
+    /// Fail if any referenced resource is missing
+    pub strict: bool,
+}
+
+/// A referenced resource that no record answers
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct MissingResource {
+    pub url: String,
+    /// URLs of the documents referencing it, in snapshot order
+    pub referenced_by: Vec<String>,
+}
+
+/// Resources referenced by a snapshot's HTML and CSS, and which are missing
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct ResourceReport {
+    pub snapshot: String,
+    /// HTML and CSS documents analyzed
+    pub documents: usize,
+    /// Distinct resources referenced
+    pub references: usize,
+    pub missing: Vec<MissingResource>,
+}
+
+impl ResourceReport {
+    /// Analyze a snapshot's documents, spread over the available cores
+    pub fn analyze(snapshot: &Snapshot) -> Self {
+        let documents: Vec<(&str, &str, DocumentKind, Url)> = snapshot
+            .requests
+            .iter()
+            .filter(|record| !record.response.is_failure())
+            .filter_map(|record| {
+                let kind = DocumentKind::of(record)?;
+                let body = std::str::from_utf8(&record.response.body).ok()?;
+                let url = Url::parse(&record.url).ok()?;
+                Some((record.url.as_str(), body, kind, url))
+            })
+            .collect();
+
+        let threads = std::thread::available_parallelism().map_or(1, NonZeroUsize::get);
+        let chunk_size = documents.len().div_ceil(threads).max(1);
+        // (document, reference, answered by a record) per reference, in document order
+        let checked: Vec<(&str, Url, bool)> = std::thread::scope(|scope| {
+            let workers: Vec<_> = documents
+                .chunks(chunk_size)
+                .map(|chunk| {
+                    scope.spawn(move || {
+                        let headers = HeaderMap::new();
+                        let mut checked = Vec::new();
+                        for (document, body, kind, url) in chunk {
+                            for reference in extract_references(body, *kind, url) {
+                                let request =
+                                    IncomingRequest::new(Method::GET, reference.as_str(), &headers);
+                                let found = DefaultMatcher.find(snapshot, &request).is_some();
+                                checked.push((*document, reference, found));
+                            }
+                        }
+                        checked
+                    })
+                })
+                .collect();
+            workers
+                .into_iter()
+                .flat_map(|worker| worker.join().expect("analysis thread panicked"))
+                .collect()
+        });
+
+        let mut referenced: HashMap<Url, Option<usize>> = HashMap::new();
+        let mut missing: Vec<MissingResource> = Vec::new();
+        for (document, reference, found) in checked {
+            let slot = referenced.entry(reference.clone()).or_insert_with(|| {
+                (!found).then(|| {
+                    missing.push(MissingResource {
+                        url: reference.to_string(),
+                        referenced_by: Vec::new(),
+                    });
+                    missing.len() - 1
+                })
+            });
+            if let Some(index) = *slot {
+                let referenced_by = &mut missing[index].referenced_by;
+                if !referenced_by.iter().any(|existing| existing == document) {
+                    referenced_by.push(document.to_string());
+                }
+            }
+        }
+
+        Self {
+            snapshot: snapshot.name.clone(),
+            documents: documents.len(),
+            references: referenced.len(),
+            missing,
+        }
+    }
+
+    /// Check whether every referenced resource has a record
+    pub fn is_self_contained(&self) -> bool {
+        self.missing.is_empty()
+    }
+}
+
+/// Handle the analyze command
+///
+/// With `json` set only the report is written to stdout, so it can be piped.
+/// With `strict` set, missing resources fail the command after the report is
+/// printed.
+pub async fn analyze_command(
+    snapshot_name: &str,
+    storage_arg: Option<String>,
+    options: &AnalyzeOptions,
+) -> Result<ResourceReport> {
+    info!("Starting analyze command for snapshot: {}", snapshot_name);
+
+    ValidationHelper::validate_snapshot_name(snapshot_name)?;
+    let storage_path = crate::commands::get_storage_path(storage_arg)?;
+    let storage = Storage::new(storage_path);
+    let snapshot = match storage.load_snapshot(snapshot_name).await {
+        Ok(snapshot) => snapshot,
+        Err(e) => {
+            if matches!(e, WebMockError::SnapshotNotFound(_)) && !options.json {
+                UserFeedback::tip("Use 'webmock list' to see available snapshots");
+            }
+            return Err(e);
+        }
+    };
+
+    // Parsing and matching is CPU bound, so it runs off the async runtime
+    let report = tokio::task::spawn_blocking(move || ResourceReport::analyze(&snapshot)).await?;
+
+    if options.json {
+        let output = serde_json::to_string_pretty(&report)
+            .map_err(|e| WebMockError::config(format!("Failed to encode report as JSON: {}", e)))?;
+        println!("{}", output);
+    } else {
+        print_report(&report);
+    }
+
+    if options.strict && !report.is_self_contained() {
+        return Err(WebMockError::command_failed(format!(
+            "{} referenced resources are missing from snapshot '{}'",
+            report.missing.len(),
+            snapshot_name
+        )));
+    }
+    Ok(report)
+}
+
+fn print_report(report: &ResourceReport) {
+    UserFeedback::section(&format!("🔎 Resources of '{}'", report.snapshot));
+    println!(
+        "   📄 Documents analyzed: {} (HTML and CSS)",
+        report.documents
+    );
+    println!("   🔗 Resources referenced: {}", report.references);
+
+    if report.is_self_contained() {
+        UserFeedback::success("Every referenced resource is in the snapshot");
+        return;
+    }
+
+    UserFeedback::warning(&format!(
+        "{} referenced resources are not in the snapshot and will get 404s when served:",
+        report.missing.len()
+    ));
+    for resource in &report.missing {
+        println!("   ❌ {}", resource.url);
+        let shown: Vec<&str> = resource
+            .referenced_by
+            .iter()
+            .take(REFERRERS_SHOWN)
+            .map(String::as_str)
+            .collect();
+        let more = resource.referenced_by.len().saturating_sub(REFERRERS_SHOWN);
+        let referrers = if more > 0 {
+            format!("{} and {} more", shown.join(", "), more)
+        } else {
+            shown.join(", ")
+        };
+        println!("      {}", format!("referenced by {}", referrers).dimmed());
+    }
+    UserFeedback::tip(&format!(
+        "Resources loaded after capture stopped are missed; try 'webmock recapture {} --timeout 60'",
+        report.snapshot
+    ));
+}
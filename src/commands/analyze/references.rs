@@ -0,0 +1,205 @@
+//! URLs of the resources captured HTML and CSS load
+//!
+//! A tolerant pattern pass, not a browser: it finds `src`, `srcset`,
+//! `poster` and `data` attributes of the elements that load them,
+//! `<link href>` for stylesheets, icons, preloads and manifests, CSS `url()`
+//! and `@import`. Navigation links such as `<a href>` are not resources and
+//! are left out.
+
+use std::collections::HashSet;
+use std::sync::LazyLock;
+
+use regex::Regex;
+use url::Url;
+
+use crate::capture::proxy::RequestRecord;
+
+/// Opening tags of elements that load resources
+static RESOURCE_TAG: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"(?i)<(img|script|link|source|video|audio|track|iframe|embed|object|input)\b[^>]*>")
+        .unwrap()
+});
+
+/// Attributes in a tag, with double-quoted, single-quoted or bare values
+static ATTRIBUTE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r#"(?i)([a-z][a-z0-9_:-]*)\s*=\s*(?:"([^"]*)"|'([^']*)'|([^\s"'>]+))"#).unwrap()
+});
+
+static BASE_HREF: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r#"(?i)<base\b[^>]*\bhref\s*=\s*(?:"([^"]*)"|'([^']*)'|([^\s"'>]+))"#).unwrap()
+});
+
+static CSS_URL: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r#"(?i)url\(\s*(?:"([^"]*)"|'([^']*)'|([^\s"')]+))\s*\)"#).unwrap()
+});
+
+static CSS_IMPORT: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r#"(?i)@import\s+(?:"([^"]*)"|'([^']*)')"#).unwrap());
+
+static HTML_COMMENT: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"(?s)<!--.*?-->").unwrap());
+
+/// `<link rel>` values whose `href` the browser loads
+const LOADED_LINK_RELS: [&str; 6] = [
+    "stylesheet",
+    "icon",
+    "preload",
+    "modulepreload",
+    "prefetch",
+    "manifest",
+];
+
+/// Kind of captured document whose references are checked
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DocumentKind {
+    Html,
+    Css,
+}
+
+impl DocumentKind {
+    /// Kind of a record's response, if it is HTML or CSS
+    pub fn of(record: &RequestRecord) -> Option<Self> {
+        let essence = record
+            .response
+            .get_mime_type()
+            .map(|mime| mime.essence_str().to_ascii_lowercase())?;
+        match essence.as_str() {
+            "text/html" | "application/xhtml+xml" => Some(DocumentKind::Html),
+            "text/css" => Some(DocumentKind::Css),
+            _ => None,
+        }
+    }
+}
+
+/// Resources a document references, resolved against its URL
+///
+/// Relative and protocol-relative references are resolved like a browser
+/// would, honoring `<base href>`. Fragments are dropped, duplicates removed,
+/// and only `http` and `https` URLs are returned, in document order.
+pub fn extract_references(body: &str, kind: DocumentKind, document_url: &Url) -> Vec<Url> {
+    let (raw, base) = match kind {
+        DocumentKind::Html => {
+            let html = HTML_COMMENT.replace_all(body, "");
+            let base = BASE_HREF
+                .captures(&html)
+                .and_then(|captures| value(&captures, 1))
+                .and_then(|href| document_url.join(&decode(&href)).ok())
+                .unwrap_or_else(|| document_url.clone());
+            (html_references(&html), base)
+        }
+        DocumentKind::Css => (css_references(body), document_url.clone()),
+    };
+
+    let mut seen = HashSet::new();
+    raw.iter()
+        .filter_map(|reference| resolve(&base, reference))
+        .filter(|url| seen.insert(url.clone()))
+        .collect()
+}
+
+fn html_references(html: &str) -> Vec<String> {
+    let mut references = Vec::new();
+    for tag in RESOURCE_TAG.captures_iter(html) {
+        let element = tag[1].to_ascii_lowercase();
+        let attributes: Vec<(String, String)> = ATTRIBUTE
+            .captures_iter(&tag[0])
+            .filter_map(|attribute| {
+                let name = attribute[1].to_ascii_lowercase();
+                value(&attribute, 2).map(|value| (name, decode(&value)))
+            })
+            .collect();
+        let attribute = |name: &str| {
+            attributes
+                .iter()
+                .find(|(attribute, _)| attribute == name)
+                .map(|(_, value)| value.as_str())
+        };
+
+        match element.as_str() {
+            "link" => {
+                let rel = attribute("rel").unwrap_or("").to_ascii_lowercase();
+                // Also apple-touch-icon and similar
+                let loaded = rel
+                    .split_ascii_whitespace()
+                    .any(|rel| LOADED_LINK_RELS.contains(&rel) || rel.ends_with("-icon"));
+                if loaded {
+                    references.extend(attribute("href").map(str::to_string));
+                    references.extend(attribute("imagesrcset").into_iter().flat_map(srcset_urls));
+                }
+            }
+            "object" => references.extend(attribute("data").map(str::to_string)),
+            "input" => {
+                if attribute("type").is_some_and(|kind| kind.eq_ignore_ascii_case("image")) {
+                    references.extend(attribute("src").map(str::to_string));
+                }
+            }
+            _ => {
+                references.extend(attribute("src").map(str::to_string));
+                references.extend(attribute("poster").map(str::to_string));
+                references.extend(attribute("srcset").into_iter().flat_map(srcset_urls));
+            }
+        }
+    }
+
+    // Inline styles and <style> blocks
+    references.extend(css_references(html).into_iter().map(|value| decode(&value)));
+    references
+}
+
+fn css_references(css: &str) -> Vec<String> {
+    CSS_URL
+        .captures_iter(css)
+        .chain(CSS_IMPORT.captures_iter(css))
+        .filter_map(|captures| value(&captures, 1))
+        .collect()
+}
+
+/// URLs of the candidates in a `srcset`, e.g. `a.png 1x, b.png 2x`
+///
+/// URLs may contain commas themselves, as data: URLs do, so candidates are
+/// split where the HTML spec splits them rather than at every comma.
+fn srcset_urls(srcset: &str) -> Vec<String> {
+    let mut urls = Vec::new();
+    let mut rest = srcset;
+    loop {
+        rest = rest.trim_start_matches(|c: char| c.is_ascii_whitespace() || c == ',');
+        if rest.is_empty() {
+            return urls;
+        }
+        let end = rest
+            .find(|c: char| c.is_ascii_whitespace())
+            .unwrap_or(rest.len());
+        let (url, after) = rest.split_at(end);
+        let trimmed = url.trim_end_matches(',');
+        rest = if trimmed.len() < url.len() {
+            // A trailing comma ends a candidate without descriptors
+            after
+        } else {
+            after.find(',').map_or("", |index| &after[index + 1..])
+        };
+        urls.push(trimmed.to_string());
+    }
+}
+
+/// The value in the first of the quoted or bare alternatives, starting at group `first`
+fn value(captures: &regex::Captures<'_>, first: usize) -> Option<String> {
+    (first..captures.len())
+        .find_map(|group| captures.get(group))
+        .map(|value| value.as_str().trim().to_string())
+}
+
+fn decode(value: &str) -> String {
+    html_escape::decode_html_entities(value).into_owned()
+}
+
+fn resolve(base: &Url, reference: &str) -> Option<Url> {
+    let reference = reference.trim();
+    if reference.is_empty() || reference.starts_with('#') {
+        return None;
+    }
+    let mut url = base.join(reference).ok()?;
+    if !matches!(url.scheme(), "http" | "https") {
+        return None;
+    }
+    url.set_fragment(None);
+    Some(url)
+}
@@ -0,0 +1,111 @@
+//! Annotate command implementation
+//!
+//! Attaches a note to a record, e.g. "stale, re-capture after the v2
+//! rollout", so whoever inspects the snapshot next sees it. Records are
+//! picked by their number in the `inspect` listing, but notes are stored
+//! against the record's capture sequence, which doesn't change when other
+//! records are added, removed or reordered.
+
+use tracing::info;
+
+use crate::capture::proxy::RequestRecord;
+use crate::error::{Result, WebMockError};
+use crate::feedback::{UserFeedback, ValidationHelper};
+use crate::storage::{Snapshot, Storage};
+
+/// What to do with the note of a record
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AnnotationChange {
+    /// Set the note, replacing any existing one
+    Set(String),
+    /// Remove the note
+    Remove,
+}
+
+/// Handle the annotate command
+pub async fn annotate_command(
+    snapshot_name: &str,
+    index: usize,
+    change: AnnotationChange,
+    storage_arg: Option<String>,
+) -> Result<()> {
+    info!("Annotating record #{} of '{}'", index, snapshot_name);
+
+    ValidationHelper::validate_snapshot_name(snapshot_name)?;
+
+    let storage_path = crate::commands::get_storage_path(storage_arg)?;
+    let storage = Storage::new(storage_path);
+    let mut snapshot = match storage.load_snapshot(snapshot_name).await {
+        Ok(snapshot) => snapshot,
+        Err(e) => {
+            if matches!(e, WebMockError::SnapshotNotFound(_)) {
+                UserFeedback::tip("Use 'webmock list' to see available snapshots");
+            }
+            return Err(e);
+        }
+    };
+
+    let removing = change == AnnotationChange::Remove;
+    let previous = annotate(&mut snapshot, index, change)?;
+    if removing && previous.is_none() {
+        UserFeedback::info(&format!("Record #{} has no note", index));
+        return Ok(());
+    }
+
+    storage.rewrite_snapshot(snapshot, false).await?;
+
+    if removing {
+        UserFeedback::success(&format!(
+            "Removed the note of record #{} in '{}'",
+            index, snapshot_name
+        ));
+    } else {
+        UserFeedback::success(&format!("Noted record #{} in '{}'", index, snapshot_name));
+        UserFeedback::tip(&format!("See it with: webmock inspect {}", snapshot_name));
+    }
+    Ok(())
+}
+
+/// Set or remove the note of the record numbered `index` in the inspect listing
+///
+/// Returns the note the record had before.
+pub fn annotate(
+    snapshot: &mut Snapshot,
+    index: usize,
+    change: AnnotationChange,
+) -> Result<Option<String>> {
+    // Loaded snapshots are numbered already; this covers ones built in memory
+    RequestRecord::fill_missing_sequences(&mut snapshot.requests);
+    let sequence = listed_record(&snapshot.requests, index)?
+        .sequence
+        .unwrap_or_default();
+
+    match change {
+        AnnotationChange::Set(note) => {
+            let note = note.trim();
+            if note.is_empty() {
+                return Err(WebMockError::config(
+                    "The note is empty; use --remove to delete a note",
+                ));
+            }
+            Ok(snapshot.annotations.insert(sequence, note.to_string()))
+        }
+        AnnotationChange::Remove => Ok(snapshot.annotations.remove(&sequence)),
+    }
+}
+
+/// The record numbered `index` in the inspect listing, leaving the stored order alone
+fn listed_record(requests: &[RequestRecord], index: usize) -> Result<&RequestRecord> {
+    let mut listing: Vec<&RequestRecord> = requests.iter().collect();
+    listing.sort_by(|a, b| a.url.cmp(&b.url));
+    index
+        .checked_sub(1)
+        .and_then(|i| listing.get(i).copied())
+        .ok_or_else(|| {
+            WebMockError::config(format!(
+                "No record #{}; the snapshot has records 1 to {}",
+                index,
+                requests.len()
+            ))
+        })
+}
@@ -0,0 +1,394 @@
+//! Expectations `webmock assert` checks a snapshot against
+//!
+//! On the command line an expectation is one line:
+//!
+//! ```text
+//! GET https://api.example.com/users -> 200 json body-contains "alice"
+//! ```
+//!
+//! Everything after `->` is optional and may come in any order, except that
+//! `body-contains` takes the rest of the line. The status is a code such as
+//! `200` or a class such as `2xx`. An expectations file is YAML with an
+//! `expectations` list of such lines or of maps with `method`, `url`,
+//! `status`, `content_type` and `body_contains` keys.
+
+use std::fmt;
+use std::path::Path;
+
+use mime::Mime;
+use serde::Deserialize;
+use url::Url;
+
+use crate::error::{Result, WebMockError};
+
+/// Example shown with parse errors
+const EXAMPLE: &str = "GET https://api.example.com/users -> 200 json";
+
+/// What a response's content type is, coarsely
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ContentCategory {
+    Html,
+    Css,
+    Js,
+    Json,
+    Xml,
+    Image,
+    Font,
+    Media,
+    Text,
+    Other,
+}
+
+impl ContentCategory {
+    /// Every category, in the order they are listed in error messages
+    pub const ALL: [ContentCategory; 10] = [
+        ContentCategory::Html,
+        ContentCategory::Css,
+        ContentCategory::Js,
+        ContentCategory::Json,
+        ContentCategory::Xml,
+        ContentCategory::Image,
+        ContentCategory::Font,
+        ContentCategory::Media,
+        ContentCategory::Text,
+        ContentCategory::Other,
+    ];
+
+    /// Parse a category name such as `json`; `javascript` is accepted for `js`
+    pub fn parse(name: &str) -> Option<Self> {
+        let name = name.to_ascii_lowercase();
+        if name == "javascript" {
+            return Some(ContentCategory::Js);
+        }
+        Self::ALL
+            .into_iter()
+            .find(|category| category.name() == name)
+    }
+
+    /// Categorize a MIME type; `+json` and `+xml` suffixes count as JSON and XML
+    pub fn of(mime: &Mime) -> Self {
+        let essence = mime.essence_str().to_ascii_lowercase();
+        let suffix = mime
+            .suffix()
+            .map(|suffix| suffix.as_str().to_ascii_lowercase());
+        match (mime.type_().as_str(), mime.subtype().as_str()) {
+            _ if essence == "text/html" || essence == "application/xhtml+xml" => {
+                ContentCategory::Html
+            }
+            ("text", "css") => ContentCategory::Css,
+            (_, subtype) if subtype.contains("javascript") || subtype.contains("ecmascript") => {
+                ContentCategory::Js
+            }
+            (_, "json") => ContentCategory::Json,
+            _ if suffix.as_deref() == Some("json") => ContentCategory::Json,
+            (_, "xml") => ContentCategory::Xml,
+            _ if suffix.as_deref() == Some("xml") => ContentCategory::Xml,
+            ("image", _) => ContentCategory::Image,
+            ("font", _) => ContentCategory::Font,
+            ("application", subtype)
+                if subtype.starts_with("font-")
+                    || subtype.starts_with("x-font-")
+                    || subtype == "vnd.ms-fontobject" =>
+            {
+                ContentCategory::Font
+            }
+            ("audio", _) | ("video", _) => ContentCategory::Media,
+            ("text", _) => ContentCategory::Text,
+            _ => ContentCategory::Other,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            ContentCategory::Html => "html",
+            ContentCategory::Css => "css",
+            ContentCategory::Js => "js",
+            ContentCategory::Json => "json",
+            ContentCategory::Xml => "xml",
+            ContentCategory::Image => "image",
+            ContentCategory::Font => "font",
+            ContentCategory::Media => "media",
+            ContentCategory::Text => "text",
+            ContentCategory::Other => "other",
+        }
+    }
+}
+
+impl fmt::Display for ContentCategory {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+/// An expected status: one code, or a class like `4xx`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StatusExpectation {
+    Code(u16),
+    Class(u16),
+}
+
+impl StatusExpectation {
+    /// Parse `200` or `2xx`
+    pub fn parse(value: &str) -> std::result::Result<Self, String> {
+        let invalid = || {
+            format!(
+                "invalid status '{}': use a code from 100 to 599 or a class like 2xx",
+                value
+            )
+        };
+        let lower = value.to_ascii_lowercase();
+        if let Some(class) = lower.strip_suffix("xx") {
+            return match class.parse::<u16>() {
+                Ok(digit @ 1..=5) if class.len() == 1 => Ok(StatusExpectation::Class(digit)),
+                _ => Err(invalid()),
+            };
+        }
+        match value.parse::<u16>() {
+            Ok(code @ 100..=599) => Ok(StatusExpectation::Code(code)),
+            _ => Err(invalid()),
+        }
+    }
+
+    /// Check whether `status` is the expected one
+    pub fn matches(self, status: u16) -> bool {
+        match self {
+            StatusExpectation::Code(code) => status == code,
+            StatusExpectation::Class(class) => status / 100 == class,
+        }
+    }
+}
+
+impl fmt::Display for StatusExpectation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            StatusExpectation::Code(code) => write!(f, "{}", code),
+            StatusExpectation::Class(class) => write!(f, "{}xx", class),
+        }
+    }
+}
+
+/// A request the snapshot must hold, and what its response must look like
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Expectation {
+    pub method: String,
+    pub url: String,
+    pub status: Option<StatusExpectation>,
+    pub content_type: Option<ContentCategory>,
+    /// Text the response body must contain
+    pub body_contains: Option<String>,
+}
+
+impl Expectation {
+    /// Parse a `--expect` line such as `GET https://api.example.com/users -> 200 json`
+    pub fn parse(line: &str) -> Result<Self> {
+        Self::parse_line(line).map_err(|reason| {
+            WebMockError::config(format!(
+                "Invalid expectation '{}': {} (e.g. '{}')",
+                line.trim(),
+                reason,
+                EXAMPLE
+            ))
+        })
+    }
+
+    fn parse_line(line: &str) -> std::result::Result<Self, String> {
+        let line = line.trim();
+        let (request, predicates) = match line.split_once("->") {
+            Some((request, predicates)) => (request, Some(predicates)),
+            None => (line, None),
+        };
+
+        let mut words = request.split_whitespace();
+        let (method, url) = match (words.next(), words.next(), words.next()) {
+            (Some(method), Some(url), None) => (method, url),
+            (Some(_), Some(_), Some(extra)) => {
+                return Err(format!("unexpected '{}' before '->'", extra))
+            }
+            _ => return Err("expected a method and a URL".to_string()),
+        };
+        let mut expectation = Self::new(method, url)?;
+
+        let Some(mut rest) = predicates.map(str::trim_start) else {
+            return Ok(expectation);
+        };
+        if rest.is_empty() {
+            return Err("nothing to check after '->'".to_string());
+        }
+        while !rest.is_empty() {
+            let (word, after) = rest.split_once(char::is_whitespace).unwrap_or((rest, ""));
+            if word.eq_ignore_ascii_case("body-contains") {
+                expectation.set_body_contains(unquote(after.trim()))?;
+                break;
+            }
+            if word.starts_with(|c: char| c.is_ascii_digit()) {
+                if expectation.status.is_some() {
+                    return Err(format!("status given twice ('{}')", word));
+                }
+                expectation.status = Some(StatusExpectation::parse(word)?);
+            } else {
+                if expectation.content_type.is_some() {
+                    return Err(format!("content type given twice ('{}')", word));
+                }
+                expectation.content_type = Some(parse_category(word)?);
+            }
+            rest = after.trim_start();
+        }
+        Ok(expectation)
+    }
+
+    /// An expectation that a `method` request for `url` was captured
+    fn new(method: &str, url: &str) -> std::result::Result<Self, String> {
+        if method.is_empty() || !method.chars().all(|c| c.is_ascii_alphabetic()) {
+            return Err(format!("invalid method '{}'", method));
+        }
+        match Url::parse(url) {
+            Ok(parsed) if matches!(parsed.scheme(), "http" | "https") => {}
+            Ok(_) => return Err(format!("'{}' is not an http(s) URL", url)),
+            Err(e) => return Err(format!("invalid URL '{}': {}", url, e)),
+        }
+        Ok(Self {
+            method: method.to_ascii_uppercase(),
+            url: url.to_string(),
+            status: None,
+            content_type: None,
+            body_contains: None,
+        })
+    }
+
+    fn set_body_contains(&mut self, text: &str) -> std::result::Result<(), String> {
+        if text.is_empty() {
+            return Err("'body-contains' needs the text to look for".to_string());
+        }
+        self.body_contains = Some(text.to_string());
+        Ok(())
+    }
+}
+
+impl fmt::Display for Expectation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} {}", self.method, self.url)?;
+        if self.status.is_none() && self.content_type.is_none() && self.body_contains.is_none() {
+            return Ok(());
+        }
+        f.write_str(" ->")?;
+        if let Some(status) = self.status {
+            write!(f, " {}", status)?;
+        }
+        if let Some(category) = self.content_type {
+            write!(f, " {}", category)?;
+        }
+        if let Some(text) = &self.body_contains {
+            write!(f, " body-contains {:?}", text)?;
+        }
+        Ok(())
+    }
+}
+
+/// Strip one pair of matching double or single quotes
+fn unquote(text: &str) -> &str {
+    for quote in ['"', '\''] {
+        if let Some(inner) = text
+            .strip_prefix(quote)
+            .and_then(|rest| rest.strip_suffix(quote))
+        {
+            return inner;
+        }
+    }
+    text
+}
+
+fn parse_category(word: &str) -> std::result::Result<ContentCategory, String> {
+    ContentCategory::parse(word).ok_or_else(|| {
+        let names: Vec<&str> = ContentCategory::ALL
+            .iter()
+            .map(|category| category.name())
+            .collect();
+        format!(
+            "unknown content type '{}': use one of {}",
+            word,
+            names.join(", ")
+        )
+    })
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ExpectationsFile {
+    expectations: Vec<serde_yaml::Value>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ExpectationEntry {
+    method: String,
+    url: String,
+    status: Option<serde_yaml::Value>,
+    content_type: Option<String>,
+    body_contains: Option<String>,
+}
+
+/// Load the expectations of an `--expect-file`
+pub fn load_expectations(path: &Path) -> Result<Vec<Expectation>> {
+    let content = std::fs::read_to_string(path).map_err(|e| {
+        WebMockError::config(format!(
+            "Cannot read expectations file {}: {}",
+            path.display(),
+            e
+        ))
+    })?;
+    parse_expectations(&content).map_err(|e| match e {
+        WebMockError::Config(msg) => WebMockError::config(format!(
+            "Invalid expectations file {}: {}",
+            path.display(),
+            msg
+        )),
+        other => other,
+    })
+}
+
+/// Parse an expectations file's YAML
+pub fn parse_expectations(content: &str) -> Result<Vec<Expectation>> {
+    let file: ExpectationsFile =
+        serde_yaml::from_str(content).map_err(|e| WebMockError::config(e.to_string()))?;
+
+    let mut expectations = Vec::new();
+    for (index, value) in file.expectations.into_iter().enumerate() {
+        let position = index + 1;
+        let expectation = compile(value)
+            .map_err(|msg| WebMockError::config(format!("expectation {}: {}", position, msg)))?;
+        expectations.push(expectation);
+    }
+    if expectations.is_empty() {
+        return Err(WebMockError::config("'expectations' is empty"));
+    }
+    Ok(expectations)
+}
+
+fn compile(value: serde_yaml::Value) -> std::result::Result<Expectation, String> {
+    if let serde_yaml::Value::String(line) = &value {
+        return Expectation::parse_line(line);
+    }
+    let entry: ExpectationEntry = serde_yaml::from_value(value).map_err(|e| {
+        format!(
+            "{} (write a line like '{}' or a map with method and url)",
+            e, EXAMPLE
+        )
+    })?;
+
+    let mut expectation = Expectation::new(&entry.method, &entry.url)?;
+    expectation.status = match entry.status {
+        None => None,
+        Some(serde_yaml::Value::Number(code)) => Some(StatusExpectation::parse(&code.to_string())?),
+        Some(serde_yaml::Value::String(status)) => Some(StatusExpectation::parse(&status)?),
+        Some(_) => return Err("'status' must be a code like 200 or a class like 2xx".to_string()),
+    };
+    expectation.content_type = entry
+        .content_type
+        .as_deref()
+        .map(parse_category)
+        .transpose()?;
+    if let Some(text) = &entry.body_contains {
+        expectation.set_body_contains(text)?;
+    }
+    Ok(expectation)
+}
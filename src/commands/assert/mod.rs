@@ -0,0 +1,220 @@
+//! Assert command implementation
+//!
+//! Checks a snapshot against a list of expectations, so a CI job fails when
+//! a recapture loses an endpoint the tests rely on or captures it with a
+//! different status, content type or body. Each expectation passes if any
+//! record of its method and URL satisfies all of its predicates.
+
+pub mod expectation;
+
+use std::path::Path;
+
+use colored::Colorize;
+use serde::Serialize;
+use tracing::info;
+
+use crate::capture::browser::initiators::normalize_url;
+use crate::capture::proxy::RequestRecord;
+use crate::error::{Result, WebMockError};
+use crate::feedback::{UserFeedback, ValidationHelper};
+use crate::storage::{Snapshot, Storage};
+
+pub use expectation::{load_expectations, ContentCategory, Expectation, StatusExpectation};
+
+/// Options for the assert command
+#[derive(Debug, Clone, Default)]
+pub struct AssertOptions {
+    /// `--expect` lines, checked before the file's expectations
+    pub expect: Vec<String>,
+    /// YAML file of further expectations
+    pub expect_file: Option<String>,
+    /// Print the report as JSON
+    pub json: bool,
+}
+
+/// Outcome of one expectation
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ExpectationResult {
+    pub expectation: String,
+    pub passed: bool,
+    /// Why the expectation failed
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reason: Option<String>,
+}
+
+/// Outcome of checking a snapshot against its expectations
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct AssertReport {
+    pub snapshot: String,
+    pub passed: usize,
+    pub failed: usize,
+    pub results: Vec<ExpectationResult>,
+}
+
+impl AssertReport {
+    /// Check each expectation against the snapshot's records
+    pub fn check(snapshot: &Snapshot, expectations: &[Expectation]) -> Self {
+        let results: Vec<ExpectationResult> = expectations
+            .iter()
+            .map(|expectation| {
+                let reason = check_expectation(snapshot, expectation).err();
+                ExpectationResult {
+                    expectation: expectation.to_string(),
+                    passed: reason.is_none(),
+                    reason,
+                }
+            })
+            .collect();
+        let passed = results.iter().filter(|result| result.passed).count();
+
+        Self {
+            snapshot: snapshot.name.clone(),
+            passed,
+            failed: results.len() - passed,
+            results,
+        }
+    }
+
+    /// Check whether every expectation holds
+    pub fn all_passed(&self) -> bool {
+        self.failed == 0
+    }
+}
+
+/// Check one expectation, returning why it fails if it does
+///
+/// When several records share the method and URL, the reason given is the
+/// first record's.
+pub fn check_expectation(
+    snapshot: &Snapshot,
+    expectation: &Expectation,
+) -> std::result::Result<(), String> {
+    let url = normalize_url(&expectation.url);
+    let candidates: Vec<&RequestRecord> = snapshot
+        .requests
+        .iter()
+        .filter(|record| {
+            record.method.eq_ignore_ascii_case(&expectation.method)
+                && normalize_url(&record.url) == url
+        })
+        .collect();
+
+    let Some(first) = candidates.first() else {
+        return Err(format!("no {} record", expectation.method));
+    };
+    if candidates
+        .iter()
+        .any(|record| mismatches(record, expectation).is_empty())
+    {
+        return Ok(());
+    }
+    Err(mismatches(first, expectation).join(", "))
+}
+
+/// Describe each predicate of `expectation` the record fails
+fn mismatches(record: &RequestRecord, expectation: &Expectation) -> Vec<String> {
+    let response = &record.response;
+    let mut mismatches = Vec::new();
+
+    if let Some(failure) = &response.failure {
+        mismatches.push(format!("request failed during capture: {}", failure));
+        return mismatches;
+    }
+    if let Some(status) = expectation.status {
+        if !status.matches(response.status) {
+            mismatches.push(format!("status {} (expected {})", response.status, status));
+        }
+    }
+    if let Some(expected) = expectation.content_type {
+        let actual = response
+            .get_mime_type()
+            .map_or(ContentCategory::Other, |mime| ContentCategory::of(&mime));
+        if actual != expected {
+            mismatches.push(format!(
+                "content type {} '{}' (expected {})",
+                actual, response.content_type, expected
+            ));
+        }
+    }
+    if let Some(text) = &expectation.body_contains {
+        let body = String::from_utf8_lossy(&response.body);
+        if !body.contains(text.as_str()) {
+            mismatches.push(format!("body does not contain {:?}", text));
+        }
+    }
+    mismatches
+}
+
+/// Handle the assert command
+///
+/// With `json` set only the report is written to stdout, so it can be piped.
+/// Any failed expectation fails the command after the report is printed.
+pub async fn assert_command(
+    snapshot_name: &str,
+    storage_arg: Option<String>,
+    options: &AssertOptions,
+) -> Result<AssertReport> {
+    info!("Starting assert command for snapshot: {}", snapshot_name);
+
+    ValidationHelper::validate_snapshot_name(snapshot_name)?;
+    let mut expectations = options
+        .expect
+        .iter()
+        .map(|line| Expectation::parse(line))
+        .collect::<Result<Vec<_>>>()?;
+    if let Some(path) = &options.expect_file {
+        expectations.extend(load_expectations(Path::new(path))?);
+    }
+    if expectations.is_empty() {
+        return Err(WebMockError::config(
+            "Nothing to check: pass --expect or --expect-file",
+        ));
+    }
+
+    let storage_path = crate::commands::get_storage_path(storage_arg)?;
+    let storage = Storage::new(storage_path);
+    let snapshot = match storage.load_snapshot(snapshot_name).await {
+        Ok(snapshot) => snapshot,
+        Err(e) => {
+            if matches!(e, WebMockError::SnapshotNotFound(_)) && !options.json {
+                UserFeedback::tip("Use 'webmock list' to see available snapshots");
+            }
+            return Err(e);
+        }
+    };
+
+    let report = AssertReport::check(&snapshot, &expectations);
+    if options.json {
+        let output = serde_json::to_string_pretty(&report)
+            .map_err(|e| WebMockError::config(format!("Failed to encode report as JSON: {}", e)))?;
+        println!("{}", output);
+    } else {
+        print_report(&report);
+    }
+
+    if !report.all_passed() {
+        return Err(WebMockError::command_failed(format!(
+            "{} of {} expectations failed for snapshot '{}'",
+            report.failed,
+            report.results.len(),
+            snapshot_name
+        )));
+    }
+    Ok(report)
+}
+
+fn print_report(report: &AssertReport) {
+    UserFeedback::section(&format!("🧪 Expectations for '{}'", report.snapshot));
+    for result in &report.results {
+        match &result.reason {
+            None => println!("   {} {}", "PASS".green(), result.expectation),
+            Some(reason) => {
+                println!("   {} {}", "FAIL".red(), result.expectation);
+                println!("        {}", reason.dimmed());
+            }
+        }
+    }
+    if report.all_passed() {
+        UserFeedback::success(&format!("All {} expectations hold", report.passed));
+    }
+}
@@ -0,0 +1,116 @@
+use std::time::Duration;
+
+use tokio::sync::oneshot;
+
+use crate::commands::capture::parse_age;
+use crate::error::{Result, WebMockError};
+use crate::feedback::{ProgressReporter, UserFeedback, ValidationHelper};
+use crate::serve::{MockServer, OutputLevel, ServeOptions};
+use crate::storage::Storage;
+
+pub mod runner;
+
+pub use runner::{bench_targets, run_bench, BenchOptions, BenchReport};
+
+/// Parse a benchmark duration such as `10s` or `1m`
+pub fn parse_bench_duration(value: &str) -> Result<Duration> {
+    let duration = parse_age(value).map_err(|_| {
+        WebMockError::config(format!(
+            "Invalid duration '{}': use a number followed by s, m or h (e.g. 10s, 1m)",
+            value
+        ))
+    })?;
+
+    if duration.is_zero() {
+        return Err(WebMockError::config("Benchmark duration must be positive"));
+    }
+
+    Ok(duration)
+}
+
+/// Handle the bench command: replay a snapshot under load and report throughput
+pub async fn bench_command(
+    snapshot_name: &str,
+    storage_arg: Option<String>,
+    options: BenchOptions,
+) -> Result<BenchReport> {
+    ValidationHelper::validate_snapshot_name(snapshot_name)?;
+    if options.connections == 0 {
+        return Err(WebMockError::config("--connections must be at least 1"));
+    }
+    if let Some(url) = &options.url {
+        ValidationHelper::validate_url(url)?;
+    }
+
+    let storage_path = crate::commands::get_storage_path(storage_arg)?;
+    let storage = Storage::new(storage_path);
+    let snapshot = storage.load_snapshot(snapshot_name).await?;
+    let targets = bench_targets(&snapshot, options.url.as_deref())?;
+    let target_count = targets.len();
+
+    // Serve in-process on an ephemeral port without per-request output
+    let server = MockServer::with_options(
+        snapshot,
+        ServeOptions {
+            output: OutputLevel::Quiet,
+            banner: false,
+            ..ServeOptions::default()
+        },
+    );
+    let (ready_tx, ready_rx) = oneshot::channel();
+    let server_task = tokio::spawn(async move { server.start_with_ready(0, ready_tx).await });
+
+    let addr = match ready_rx.await {
+        Ok(addr) => addr,
+        Err(_) => {
+            // The server exited before it was listening
+            return Err(match server_task.await {
+                Ok(Err(e)) => e,
+                _ => WebMockError::Proxy("Mock server failed to start".to_string()),
+            });
+        }
+    };
+
+    let progress = ProgressReporter::new();
+    let spinner = progress.create_spinner(&format!(
+        "Benchmarking '{}' for {}s with {} connections across {} URLs",
+        snapshot_name,
+        options.duration.as_secs_f64(),
+        options.connections,
+        target_count
+    ));
+
+    let report = run_bench(addr, targets, &options).await;
+
+    spinner.finish_with_message("✅ Benchmark finished");
+    server_task.abort();
+
+    print_report(&report);
+    Ok(report)
+}
+
+fn print_report(report: &BenchReport) {
+    let ms = |latency: Option<Duration>| {
+        latency
+            .map(|latency| format!("{:.2}ms", latency.as_secs_f64() * 1000.0))
+            .unwrap_or_else(|| "-".to_string())
+    };
+
+    UserFeedback::section("📊 Benchmark Results");
+    println!("   📨 Requests: {}", report.requests);
+    println!("   ⏱️  Duration: {:.2}s", report.elapsed.as_secs_f64());
+    println!("   🚀 Throughput: {:.1} req/s", report.requests_per_sec());
+    println!(
+        "   📈 Latency: p50 {}  p90 {}  p99 {}  max {}",
+        ms(report.percentile(50.0)),
+        ms(report.percentile(90.0)),
+        ms(report.percentile(99.0)),
+        ms(report.max_latency())
+    );
+    println!("   ⚠️  Non-2xx/3xx responses: {}", report.non_success);
+    println!("   ❌ Errors: {}", report.errors);
+
+    if report.non_success > 0 {
+        UserFeedback::tip("Requests not found in the snapshot are answered with 404");
+    }
+}
@@ -0,0 +1,246 @@
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use bytes::Bytes;
+use http_body_util::{BodyExt, Empty};
+use hyper::{Request, Uri};
+use hyper_util::rt::TokioIo;
+use tokio::net::TcpStream;
+use tracing::debug;
+use url::Url;
+
+use crate::error::{Result, WebMockError};
+use crate::storage::Snapshot;
+
+/// Default number of concurrent connections
+pub const DEFAULT_CONNECTIONS: usize = 64;
+
+/// Default benchmark duration
+pub const DEFAULT_DURATION: Duration = Duration::from_secs(10);
+
+/// Options for a replay benchmark run
+#[derive(Debug, Clone)]
+pub struct BenchOptions {
+    /// Number of concurrent keep-alive connections
+    pub connections: usize,
+    /// How long to keep sending requests
+    pub duration: Duration,
+    /// Benchmark a single URL instead of every captured GET
+    pub url: Option<String>,
+}
+
+impl Default for BenchOptions {
+    fn default() -> Self {
+        Self {
+            connections: DEFAULT_CONNECTIONS,
+            duration: DEFAULT_DURATION,
+            url: None,
+        }
+    }
+}
+
+/// Aggregated results of a benchmark run
+#[derive(Debug, Clone, Default)]
+pub struct BenchReport {
+    /// Requests that received a complete response
+    pub requests: u64,
+    /// Responses with a 4xx or 5xx status
+    pub non_success: u64,
+    /// Connection or protocol errors
+    pub errors: u64,
+    /// Wall-clock time of the run
+    pub elapsed: Duration,
+    /// Sorted latencies of completed requests
+    latencies: Vec<Duration>,
+}
+
+impl BenchReport {
+    /// Completed requests per second
+    pub fn requests_per_sec(&self) -> f64 {
+        let secs = self.elapsed.as_secs_f64();
+        if secs > 0.0 {
+            self.requests as f64 / secs
+        } else {
+            0.0
+        }
+    }
+
+    /// Latency at the given percentile (0-100), using the nearest-rank method
+    pub fn percentile(&self, percentile: f64) -> Option<Duration> {
+        if self.latencies.is_empty() {
+            return None;
+        }
+
+        let rank = (percentile / 100.0 * self.latencies.len() as f64).ceil() as usize;
+        let index = rank.clamp(1, self.latencies.len()) - 1;
+        Some(self.latencies[index])
+    }
+
+    /// Slowest completed request
+    pub fn max_latency(&self) -> Option<Duration> {
+        self.latencies.last().copied()
+    }
+
+    fn merge(&mut self, other: WorkerStats) {
+        self.requests += other.latencies.len() as u64;
+        self.non_success += other.non_success;
+        self.errors += other.errors;
+        self.latencies.extend(other.latencies);
+    }
+}
+
+#[derive(Default)]
+struct WorkerStats {
+    latencies: Vec<Duration>,
+    non_success: u64,
+    errors: u64,
+}
+
+/// Collect the URLs to request, sent as absolute-form proxy requests
+///
+/// HTTPS records are requested over plain HTTP, which the mock server matches
+/// by host, path and query regardless of scheme.
+pub fn bench_targets(snapshot: &Snapshot, url: Option<&str>) -> Result<Vec<Uri>> {
+    let urls: Vec<&str> = match url {
+        Some(url) => vec![url],
+        None => snapshot
+            .requests
+            .iter()
+            .filter(|record| record.method == "GET" && !record.response.is_failure())
+            .map(|record| record.url.as_str())
+            .collect(),
+    };
+
+    if urls.is_empty() {
+        return Err(WebMockError::config(format!(
+            "Snapshot '{}' has no GET requests to benchmark; pass --url to pick one",
+            snapshot.name
+        )));
+    }
+
+    urls.into_iter().map(to_proxy_uri).collect()
+}
+
+fn to_proxy_uri(url: &str) -> Result<Uri> {
+    let invalid = |reason: String| WebMockError::InvalidUrl(url.to_string(), reason);
+
+    let mut parsed = Url::parse(url).map_err(|e| invalid(e.to_string()))?;
+    if parsed.scheme() == "https" {
+        parsed
+            .set_scheme("http")
+            .map_err(|_| invalid("cannot be requested over HTTP".to_string()))?;
+    }
+
+    parsed
+        .as_str()
+        .parse::<Uri>()
+        .map_err(|e| invalid(e.to_string()))
+}
+
+/// Send requests to the mock server at `addr` until the duration elapses
+pub async fn run_bench(addr: SocketAddr, targets: Vec<Uri>, options: &BenchOptions) -> BenchReport {
+    let targets = Arc::new(targets);
+    let started = Instant::now();
+    let deadline = started + options.duration;
+
+    let workers: Vec<_> = (0..options.connections)
+        .map(|worker| {
+            let targets = Arc::clone(&targets);
+            tokio::spawn(run_worker(addr, targets, worker, deadline))
+        })
+        .collect();
+
+    let mut report = BenchReport::default();
+    for worker in workers {
+        match worker.await {
+            Ok(stats) => report.merge(stats),
+            Err(e) => {
+                debug!("Benchmark worker failed: {}", e);
+                report.errors += 1;
+            }
+        }
+    }
+
+    report.elapsed = started.elapsed();
+    report.latencies.sort_unstable();
+    report
+}
+
+/// Drive one keep-alive connection, reconnecting after errors
+async fn run_worker(
+    addr: SocketAddr,
+    targets: Arc<Vec<Uri>>,
+    offset: usize,
+    deadline: Instant,
+) -> WorkerStats {
+    let mut stats = WorkerStats::default();
+    // Start workers at different targets so URLs are spread across connections
+    let mut next = offset;
+
+    while Instant::now() < deadline {
+        let mut sender = match connect(addr).await {
+            Ok(sender) => sender,
+            Err(e) => {
+                debug!("Benchmark connection failed: {}", e);
+                stats.errors += 1;
+                tokio::time::sleep(Duration::from_millis(10)).await;
+                continue;
+            }
+        };
+
+        while Instant::now() < deadline {
+            let uri = targets[next % targets.len()].clone();
+            next += 1;
+
+            let request = Request::get(uri)
+                .body(Empty::<Bytes>::new())
+                .expect("GET request with a valid URI");
+
+            let start = Instant::now();
+            let result = async {
+                sender.ready().await?;
+                let response = sender.send_request(request).await?;
+                let status = response.status();
+                response.into_body().collect().await?;
+                Ok::<_, hyper::Error>(status)
+            }
+            .await;
+
+            match result {
+                Ok(status) => {
+                    stats.latencies.push(start.elapsed());
+                    if status.is_client_error() || status.is_server_error() {
+                        stats.non_success += 1;
+                    }
+                }
+                Err(e) => {
+                    debug!("Benchmark request failed: {}", e);
+                    stats.errors += 1;
+                    break;
+                }
+            }
+        }
+    }
+
+    stats
+}
+
+async fn connect(
+    addr: SocketAddr,
+) -> std::result::Result<
+    hyper::client::conn::http1::SendRequest<Empty<Bytes>>,
+    Box<dyn std::error::Error + Send + Sync>,
+> {
+    let stream = TcpStream::connect(addr).await?;
+    stream.set_nodelay(true)?;
+
+    let (sender, connection) = hyper::client::conn::http1::handshake(TokioIo::new(stream)).await?;
+    tokio::spawn(async move {
+        if let Err(e) = connection.await {
+            debug!("Benchmark connection closed: {}", e);
+        }
+    });
+
+    Ok(sender)
+}
@@ -0,0 +1,152 @@
+//! Browse command implementation
+//!
+//! An interactive terminal view of a snapshot: a filterable list of its
+//! records and a detail pane with the selected record's headers and body.
+//! Records are read from the snapshot one at a time, so only their headers
+//! stay in memory and a body is read when it is asked for.
+
+pub mod state;
+pub mod view;
+
+use std::io::{ErrorKind, IsTerminal};
+use std::path::Path;
+
+use console::Term;
+
+use crate::commands::get_storage_path;
+use crate::commands::inspect::{format_size, write_body};
+use crate::error::{Result, WebMockError};
+use crate::storage::{SnapshotRecords, Storage};
+
+use state::{BrowserState, Effect, Key, RecordSummary};
+
+/// Browse a snapshot's records interactively
+pub async fn browse_command(snapshot_name: &str, storage_arg: Option<String>) -> Result<()> {
+    if !std::io::stdout().is_terminal() || !std::io::stdin().is_terminal() {
+        return Err(WebMockError::config(
+            "browse needs an interactive terminal; use 'webmock inspect' to list a snapshot's records from scripts",
+        ));
+    }
+
+    let storage_path = get_storage_path(storage_arg)?;
+    let storage = Storage::new(storage_path);
+    let records = storage.open_records(snapshot_name).await?;
+
+    let name = snapshot_name.to_string();
+    tokio::task::spawn_blocking(move || {
+        let summaries = summarize(&records)?;
+        run(&name, &records, BrowserState::new(summaries))
+    })
+    .await?
+}
+
+/// Read every record once, keeping what the list and detail pane show of it
+fn summarize(records: &SnapshotRecords) -> Result<Vec<RecordSummary>> {
+    let (_, reader) = records.read()?;
+    reader
+        .enumerate()
+        .map(|(index, record)| record.map(|record| RecordSummary::of(index, &record)))
+        .collect()
+}
+
+/// Draw and handle keys until the user quits
+fn run(name: &str, records: &SnapshotRecords, mut state: BrowserState) -> Result<()> {
+    let term = Term::stdout();
+    let _screen = Screen::enter(&term)?;
+
+    loop {
+        let (rows, columns) = term.size();
+        let lines = view::render(&mut state, name, columns as usize, rows as usize);
+        draw(&term, &lines)?;
+
+        let key = match term.read_key() {
+            Ok(console::Key::CtrlC) => break,
+            Ok(key) => key,
+            Err(e) if e.kind() == ErrorKind::Interrupted => break,
+            Err(e) => return Err(e.into()),
+        };
+        let Some(key) = map_key(key) else {
+            continue;
+        };
+        match state.handle(key) {
+            Effect::None => {}
+            Effect::Quit => break,
+            Effect::LoadBody(index) => match records.record(index) {
+                Ok(Some(record)) => state.body_loaded(index, record.response.body),
+                Ok(None) => state.set_message(format!("Record {} is gone", index + 1)),
+                Err(e) => state.set_message(format!("Failed to read the body: {}", e)),
+            },
+            Effect::Export { index, path } => {
+                let message = match export_body(records, index, &path) {
+                    Ok(size) => format!("Wrote {} to {}", format_size(size), path.display()),
+                    Err(e) => e.to_string(),
+                };
+                state.set_message(message);
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Write the body of the record at `index` to `path`, which must not exist yet
+fn export_body(records: &SnapshotRecords, index: usize, path: &Path) -> Result<usize> {
+    if path.exists() {
+        return Err(WebMockError::config(format!(
+            "{} already exists, pick another name",
+            path.display()
+        )));
+    }
+    let record = records
+        .record(index)?
+        .ok_or_else(|| WebMockError::config(format!("Record {} is gone", index + 1)))?;
+    write_body(&record.response.body, path)?;
+    Ok(record.response.body.len())
+}
+
+fn map_key(key: console::Key) -> Option<Key> {
+    Some(match key {
+        console::Key::ArrowUp => Key::Up,
+        console::Key::ArrowDown => Key::Down,
+        console::Key::PageUp => Key::PageUp,
+        console::Key::PageDown => Key::PageDown,
+        console::Key::Home => Key::Home,
+        console::Key::End => Key::End,
+        console::Key::Enter => Key::Enter,
+        console::Key::Escape => Key::Escape,
+        console::Key::Backspace => Key::Backspace,
+        console::Key::Tab => Key::Tab,
+        console::Key::BackTab => Key::BackTab,
+        console::Key::Char(c) if !c.is_control() => Key::Char(c),
+        _ => return None,
+    })
+}
+
+/// Replace the screen with `lines`, clearing what's left of each row
+fn draw(term: &Term, lines: &[String]) -> Result<()> {
+    let mut frame = String::new();
+    for (row, line) in lines.iter().enumerate() {
+        frame.push_str(&format!("\x1b[{};1H{}\x1b[K", row + 1, line));
+    }
+    term.write_str(&frame)?;
+    term.flush()?;
+    Ok(())
+}
+
+/// The alternate screen with a hidden cursor, given back to the shell when dropped
+struct Screen<'a>(&'a Term);
+
+impl<'a> Screen<'a> {
+    fn enter(term: &'a Term) -> Result<Self> {
+        term.write_str("\x1b[?1049h")?;
+        term.hide_cursor()?;
+        Ok(Self(term))
+    }
+}
+
+impl Drop for Screen<'_> {
+    fn drop(&mut self) {
+        let _ = self.0.show_cursor();
+        let _ = self.0.write_str("\x1b[?1049l");
+        let _ = self.0.flush();
+    }
+}
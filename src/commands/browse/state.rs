@@ -0,0 +1,413 @@
+//! What the browser shows and how keys change it, kept apart from the terminal
+//!
+//! [`BrowserState::handle`] takes a key and returns the [`Effect`] the caller
+//! has to carry out, such as reading a record's body, so the whole interaction
+//! can be driven without a terminal or a snapshot file.
+
+use std::path::PathBuf;
+
+use crate::capture::proxy::RequestRecord;
+use crate::commands::inspect::extract_content_type;
+
+/// What the list and the detail pane show of a record, without its body
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RecordSummary {
+    /// Position of the record in the snapshot
+    pub index: usize,
+    pub method: String,
+    pub url: String,
+    /// Host of the URL, empty when it has none
+    pub host: String,
+    pub status: u16,
+    /// Why the request failed during capture
+    pub failure: Option<String>,
+    /// Size of the response body
+    pub size: usize,
+    pub content_type: Option<String>,
+    /// Request headers, sorted by name
+    pub request_headers: Vec<(String, String)>,
+    /// Response headers, sorted by name
+    pub response_headers: Vec<(String, String)>,
+}
+
+impl RecordSummary {
+    pub fn of(index: usize, record: &RequestRecord) -> Self {
+        let sorted = |headers: &std::collections::HashMap<String, String>| {
+            let mut headers: Vec<_> = headers
+                .iter()
+                .map(|(name, value)| (name.clone(), value.clone()))
+                .collect();
+            headers.sort();
+            headers
+        };
+        let host = url::Url::parse(&record.url)
+            .ok()
+            .and_then(|url| url.host_str().map(str::to_string))
+            .unwrap_or_default();
+        Self {
+            index,
+            method: record.method.clone(),
+            url: record.url.clone(),
+            host,
+            status: record.response.status,
+            failure: record.response.failure.clone(),
+            size: record.response.body.len(),
+            content_type: extract_content_type(&record.response.headers),
+            request_headers: sorted(&record.headers),
+            response_headers: sorted(&record.response.headers),
+        }
+    }
+
+    /// Status as listed, `ERR` for a request that failed during capture
+    pub fn status_label(&self) -> String {
+        if self.failure.is_some() {
+            "ERR".to_string()
+        } else {
+            self.status.to_string()
+        }
+    }
+
+    /// Whether every whitespace-separated term of `filter` is in the method, URL or status
+    fn matches(&self, filter: &str) -> bool {
+        let haystack =
+            format!("{} {} {}", self.method, self.url, self.status_label()).to_lowercase();
+        filter
+            .to_lowercase()
+            .split_whitespace()
+            .all(|term| haystack.contains(term))
+    }
+}
+
+/// A key press, as far as the browser cares
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Key {
+    Up,
+    Down,
+    PageUp,
+    PageDown,
+    Home,
+    End,
+    Enter,
+    Escape,
+    Backspace,
+    Tab,
+    BackTab,
+    Char(char),
+}
+
+/// What keys typed go to
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Mode {
+    /// Keys move around the list
+    Browse,
+    /// Keys edit the filter
+    Filter,
+    /// Keys edit the path to write the selected body to
+    Export(String),
+}
+
+/// Work the caller has to do after a key
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Effect {
+    None,
+    Quit,
+    /// Read the body of the record at this index and hand it to [`BrowserState::body_loaded`]
+    LoadBody(usize),
+    /// Write the body of the record at this index to `path`
+    Export {
+        index: usize,
+        path: PathBuf,
+    },
+}
+
+/// State of the browser: records, filter, selection and what the detail pane shows
+#[derive(Debug)]
+pub struct BrowserState {
+    records: Vec<RecordSummary>,
+    filter: String,
+    /// Positions in `records` of the records the filter lets through
+    visible: Vec<usize>,
+    /// Position of the selected record in `visible`
+    cursor: usize,
+    /// First visible record shown in the list
+    offset: usize,
+    /// Rows the list has room for, set while rendering
+    page: usize,
+    mode: Mode,
+    hex: bool,
+    detail_offset: usize,
+    /// The one body read so far, by record index
+    body: Option<(usize, Vec<u8>)>,
+    message: Option<String>,
+}
+
+impl BrowserState {
+    pub fn new(records: Vec<RecordSummary>) -> Self {
+        let visible = (0..records.len()).collect();
+        Self {
+            records,
+            filter: String::new(),
+            visible,
+            cursor: 0,
+            offset: 0,
+            page: 10,
+            mode: Mode::Browse,
+            hex: false,
+            detail_offset: 0,
+            body: None,
+            message: None,
+        }
+    }
+
+    pub fn total(&self) -> usize {
+        self.records.len()
+    }
+
+    /// Records the filter lets through, in snapshot order
+    pub fn visible(&self) -> impl Iterator<Item = &RecordSummary> {
+        self.visible.iter().map(|&position| &self.records[position])
+    }
+
+    pub fn visible_count(&self) -> usize {
+        self.visible.len()
+    }
+
+    pub fn selected(&self) -> Option<&RecordSummary> {
+        self.visible
+            .get(self.cursor)
+            .map(|&position| &self.records[position])
+    }
+
+    pub fn cursor(&self) -> usize {
+        self.cursor
+    }
+
+    pub fn offset(&self) -> usize {
+        self.offset
+    }
+
+    pub fn filter(&self) -> &str {
+        &self.filter
+    }
+
+    pub fn mode(&self) -> &Mode {
+        &self.mode
+    }
+
+    pub fn hex(&self) -> bool {
+        self.hex
+    }
+
+    pub fn detail_offset(&self) -> usize {
+        self.detail_offset
+    }
+
+    /// Body of the selected record, once it was read
+    pub fn body(&self) -> Option<&[u8]> {
+        match (&self.body, self.selected()) {
+            (Some((index, body)), Some(selected)) if *index == selected.index => Some(body),
+            _ => None,
+        }
+    }
+
+    pub fn message(&self) -> Option<&str> {
+        self.message.as_deref()
+    }
+
+    pub fn set_message(&mut self, message: impl Into<String>) {
+        self.message = Some(message.into());
+    }
+
+    /// Keep the body read for an [`Effect::LoadBody`]
+    pub fn body_loaded(&mut self, index: usize, body: Vec<u8>) {
+        self.body = Some((index, body));
+        self.detail_offset = 0;
+    }
+
+    /// Set how many rows the list shows and scroll it to keep the selection in view
+    pub fn set_page(&mut self, rows: usize) {
+        self.page = rows.max(1);
+        if self.cursor < self.offset {
+            self.offset = self.cursor;
+        } else if self.cursor >= self.offset + self.page {
+            self.offset = self.cursor + 1 - self.page;
+        }
+    }
+
+    /// Keep the detail pane from scrolling past its last line
+    pub fn clamp_detail_offset(&mut self, lines: usize) {
+        self.detail_offset = self.detail_offset.min(lines.saturating_sub(1));
+    }
+
+    pub fn handle(&mut self, key: Key) -> Effect {
+        match self.mode.clone() {
+            Mode::Browse => self.handle_browse(key),
+            Mode::Filter => {
+                self.handle_filter(key);
+                Effect::None
+            }
+            Mode::Export(path) => self.handle_export(key, path),
+        }
+    }
+
+    fn handle_browse(&mut self, key: Key) -> Effect {
+        self.message = None;
+        match key {
+            Key::Up | Key::Char('k') => self.select(self.cursor.saturating_sub(1)),
+            Key::Down | Key::Char('j') => self.select(self.cursor + 1),
+            Key::PageUp => self.select(self.cursor.saturating_sub(self.page)),
+            Key::PageDown => self.select(self.cursor + self.page),
+            Key::Home | Key::Char('g') => self.select(0),
+            Key::End | Key::Char('G') => self.select(usize::MAX),
+            Key::Tab | Key::Char('n') => self.jump_host(true),
+            Key::BackTab | Key::Char('N') => self.jump_host(false),
+            Key::Char('/') => self.mode = Mode::Filter,
+            Key::Char('x') => {
+                self.hex = !self.hex;
+                self.detail_offset = 0;
+            }
+            Key::Char('d') => self.detail_offset += self.page.div_ceil(2),
+            Key::Char('u') => {
+                self.detail_offset = self.detail_offset.saturating_sub(self.page.div_ceil(2))
+            }
+            Key::Enter => {
+                if let Some(selected) = self.selected() {
+                    if self.body().is_none() {
+                        return Effect::LoadBody(selected.index);
+                    }
+                }
+            }
+            Key::Char('s') => {
+                if let Some(selected) = self.selected() {
+                    self.mode = Mode::Export(default_export_name(selected));
+                }
+            }
+            Key::Escape if !self.filter.is_empty() => self.set_filter(String::new()),
+            Key::Escape | Key::Char('q') => return Effect::Quit,
+            _ => {}
+        }
+        Effect::None
+    }
+
+    fn handle_filter(&mut self, key: Key) {
+        match key {
+            Key::Enter => self.mode = Mode::Browse,
+            Key::Escape => {
+                self.mode = Mode::Browse;
+                self.set_filter(String::new());
+            }
+            Key::Backspace => {
+                let mut filter = self.filter.clone();
+                filter.pop();
+                self.set_filter(filter);
+            }
+            Key::Char(c) => {
+                let filter = format!("{}{}", self.filter, c);
+                self.set_filter(filter);
+            }
+            _ => {}
+        }
+    }
+
+    fn handle_export(&mut self, key: Key, mut path: String) -> Effect {
+        match key {
+            Key::Escape => {
+                self.mode = Mode::Browse;
+                self.message = Some("Export cancelled".to_string());
+            }
+            Key::Backspace => {
+                path.pop();
+                self.mode = Mode::Export(path);
+            }
+            Key::Char(c) => {
+                path.push(c);
+                self.mode = Mode::Export(path);
+            }
+            Key::Enter => {
+                let path = path.trim();
+                if path.is_empty() {
+                    self.message = Some("Type a file name to write the body to".to_string());
+                } else if path == "-" {
+                    // Standard output is the browser itself
+                    self.message =
+                        Some("Can't write the body to stdout while browsing".to_string());
+                } else if let Some(selected) = self.selected() {
+                    let effect = Effect::Export {
+                        index: selected.index,
+                        path: PathBuf::from(path),
+                    };
+                    self.mode = Mode::Browse;
+                    return effect;
+                }
+            }
+            _ => {}
+        }
+        Effect::None
+    }
+
+    /// Select the visible record at `position`, or the last one past the end
+    fn select(&mut self, position: usize) {
+        let position = position.min(self.visible.len().saturating_sub(1));
+        if position != self.cursor {
+            self.cursor = position;
+            self.detail_offset = 0;
+        }
+    }
+
+    /// Select the first record of the next or previous host, in host name order
+    fn jump_host(&mut self, forward: bool) {
+        let Some(current) = self.selected().map(|record| record.host.clone()) else {
+            return;
+        };
+        let mut hosts: Vec<&str> = self.visible().map(|record| record.host.as_str()).collect();
+        hosts.sort_unstable();
+        hosts.dedup();
+        let at = hosts.binary_search(&current.as_str()).unwrap_or(0);
+        let target = if forward {
+            hosts[(at + 1) % hosts.len()]
+        } else {
+            hosts[(at + hosts.len() - 1) % hosts.len()]
+        }
+        .to_string();
+        let position = self.visible().position(|record| record.host == target);
+        if let Some(position) = position {
+            self.select(position);
+        }
+    }
+
+    /// Apply a new filter, keeping the selected record selected while it still matches
+    fn set_filter(&mut self, filter: String) {
+        let selected = self.visible.get(self.cursor).copied();
+        self.filter = filter;
+        self.visible = (0..self.records.len())
+            .filter(|&position| self.records[position].matches(&self.filter))
+            .collect();
+        let cursor = selected
+            .and_then(|selected| self.visible.iter().position(|&p| p == selected))
+            .unwrap_or(0);
+        if cursor != self.cursor || selected != self.visible.get(cursor).copied() {
+            self.detail_offset = 0;
+        }
+        self.cursor = cursor;
+        self.offset = self.offset.min(cursor);
+    }
+}
+
+/// File name offered for a record's body: the last segment of its URL path, or `body-<index>`
+pub fn default_export_name(record: &RecordSummary) -> String {
+    url::Url::parse(&record.url)
+        .ok()
+        .and_then(|url| {
+            url.path_segments()
+                .and_then(|mut segments| segments.next_back().map(str::to_string))
+        })
+        .filter(|segment| {
+            !segment.is_empty()
+                && segment != "."
+                && segment != ".."
+                && segment
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || "._-".contains(c))
+        })
+        .unwrap_or_else(|| format!("body-{}", record.index))
+}
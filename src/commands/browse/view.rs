@@ -0,0 +1,202 @@
+//! Drawing a [`BrowserState`] as lines of terminal text
+
+use console::{style, truncate_str};
+
+use super::state::{BrowserState, Mode, RecordSummary};
+use crate::commands::inspect::{body_preview, format_size};
+
+/// Text bodies shown in the detail pane are cut after this many bytes
+const MAX_TEXT_PREVIEW: usize = 256 * 1024;
+
+/// Binary bodies shown in the detail pane are cut after this many bytes
+const MAX_HEX_PREVIEW: usize = 64 * 1024;
+
+/// Render the browser into exactly `height` lines of at most `width` columns
+pub fn render(state: &mut BrowserState, name: &str, width: usize, height: usize) -> Vec<String> {
+    let width = width.max(20);
+    let height = height.max(8);
+    // Title, separator and footer take a line each
+    let list_rows = ((height - 3) / 2).max(1);
+    let detail_rows = height - 3 - list_rows;
+    state.set_page(list_rows);
+
+    let mut lines = Vec::with_capacity(height);
+    let mut title = format!(
+        "webmock browse — {} — {}/{} records",
+        name,
+        state.visible_count(),
+        state.total()
+    );
+    if !state.filter().is_empty() {
+        title.push_str(&format!(" matching '{}'", state.filter()));
+    }
+    lines.push(style(fit(&title, width)).bold().to_string());
+
+    let rows: Vec<(usize, String)> = state
+        .visible()
+        .enumerate()
+        .skip(state.offset())
+        .take(list_rows)
+        .map(|(position, record)| (position, list_row(record)))
+        .collect();
+    for (position, row) in &rows {
+        let row = fit(row, width);
+        if *position == state.cursor() {
+            lines.push(style(format!("{:<width$}", row)).reverse().to_string());
+        } else {
+            lines.push(row);
+        }
+    }
+    if state.visible_count() == 0 {
+        lines.push(fit("  No records match the filter", width));
+    }
+    lines.resize(1 + list_rows, String::new());
+
+    lines.push(fit(&format!("── Details {}", "─".repeat(width)), width));
+    let detail = match state.selected() {
+        Some(record) => detail_lines(record, state.body(), state.hex()),
+        None => Vec::new(),
+    };
+    state.clamp_detail_offset(detail.len());
+    lines.extend(
+        detail
+            .iter()
+            .skip(state.detail_offset())
+            .take(detail_rows)
+            .map(|line| fit(line, width)),
+    );
+    lines.resize(height - 1, String::new());
+
+    let footer = match (state.mode(), state.message()) {
+        (Mode::Filter, _) => format!("Filter: {}█  (Enter keeps it, Esc clears it)", state.filter()),
+        (Mode::Export(path), _) => format!("Write body to: {}█  (Enter writes, Esc cancels)", path),
+        (Mode::Browse, Some(message)) => message.to_string(),
+        (Mode::Browse, None) => {
+            "↑↓ move  / filter  n/N next/prev host  Enter load body  x hex  d/u scroll  s save body  q quit"
+                .to_string()
+        }
+    };
+    lines.push(style(fit(&footer, width)).dim().to_string());
+    lines
+}
+
+/// One line of the record list: method, status, size and URL
+fn list_row(record: &RecordSummary) -> String {
+    format!(
+        "{:<7} {:>3} {:>9}  {}",
+        record.method,
+        record.status_label(),
+        format_size(record.size),
+        record.url
+    )
+}
+
+/// Lines of the detail pane for `record`, with its body once it was read
+pub fn detail_lines(record: &RecordSummary, body: Option<&[u8]>, hex: bool) -> Vec<String> {
+    let mut lines = vec![
+        format!("{} {}", record.method, record.url),
+        format!(
+            "Status: {}   Size: {}   Type: {}",
+            record.status_label(),
+            format_size(record.size),
+            record.content_type.as_deref().unwrap_or("unknown")
+        ),
+    ];
+    if let Some(failure) = &record.failure {
+        lines.push(format!("Failed during capture: {}", failure));
+    }
+
+    for (label, headers) in [
+        ("Request headers", &record.request_headers),
+        ("Response headers", &record.response_headers),
+    ] {
+        lines.push(String::new());
+        lines.push(format!("{}:", label));
+        if headers.is_empty() {
+            lines.push("  (none)".to_string());
+        }
+        lines.extend(
+            headers
+                .iter()
+                .map(|(name, value)| format!("  {}: {}", name, value)),
+        );
+    }
+
+    lines.push(String::new());
+    match body {
+        Some(body) => {
+            lines.push(format!("Body{}:", if hex { " (hex)" } else { "" }));
+            lines.extend(body_lines(body, record.content_type.as_deref(), hex));
+        }
+        None if record.size == 0 => lines.push("Body: empty".to_string()),
+        None => lines.push(format!(
+            "Body: {}, press Enter to load it",
+            format_size(record.size)
+        )),
+    }
+    lines
+}
+
+/// Lines showing a body: pretty-printed JSON, text, or a hex dump for binary bodies or when `hex` is set
+pub fn body_lines(body: &[u8], content_type: Option<&str>, hex: bool) -> Vec<String> {
+    if body.is_empty() {
+        return vec!["(empty)".to_string()];
+    }
+    if !hex {
+        let looks_json = content_type.is_some_and(|content_type| content_type.contains("json"));
+        if looks_json && body.len() <= MAX_TEXT_PREVIEW {
+            if let Ok(value) = serde_json::from_slice::<serde_json::Value>(body) {
+                if let Ok(pretty) = serde_json::to_string_pretty(&value) {
+                    return pretty.lines().map(str::to_string).collect();
+                }
+            }
+        }
+        if let Some(text) = body_preview(body, Some(MAX_TEXT_PREVIEW)) {
+            return text.lines().map(str::to_string).collect();
+        }
+    }
+    hex_dump(body)
+}
+
+/// `hexdump -C` style lines: offset, sixteen bytes in hex and the printable ones
+fn hex_dump(body: &[u8]) -> Vec<String> {
+    let shown = &body[..body.len().min(MAX_HEX_PREVIEW)];
+    let mut lines: Vec<String> = shown
+        .chunks(16)
+        .enumerate()
+        .map(|(row, chunk)| {
+            let hex: Vec<String> = chunk.iter().map(|byte| format!("{:02x}", byte)).collect();
+            let text: String = chunk
+                .iter()
+                .map(|&byte| {
+                    if byte.is_ascii_graphic() || byte == b' ' {
+                        byte as char
+                    } else {
+                        '.'
+                    }
+                })
+                .collect();
+            format!("{:08x}  {:<47}  |{}|", row * 16, hex.join(" "), text)
+        })
+        .collect();
+    if shown.len() < body.len() {
+        lines.push(format!(
+            "... {} more not shown (save the body with s)",
+            format_size(body.len() - shown.len())
+        ));
+    }
+    lines
+}
+
+/// Make `line` safe to print and cut it to `width` columns
+///
+/// Captured URLs, headers and bodies can hold tabs and escape sequences,
+/// which would move the cursor or restyle the terminal if printed as they are.
+fn fit(line: &str, width: usize) -> String {
+    let clean: String = line
+        .replace('\t', "    ")
+        .chars()
+        .map(|c| if c.is_control() { ' ' } else { c })
+        .collect();
+    truncate_str(&clean, width, "…").into_owned()
+}
@@ -0,0 +1,128 @@
+use std::path::PathBuf;
+
+use tracing::info;
+
+use crate::error::{Result, WebMockError};
+use crate::feedback::UserFeedback;
+use crate::tls::WebMockCertificate;
+
+pub mod trust_store;
+
+pub use trust_store::{has_admin_privileges, TrustPlan, TrustStore};
+
+/// Options shared by `ca trust` and `ca untrust`
+#[derive(Debug, Clone, Default)]
+pub struct CaOptions {
+    /// Skip the confirmation prompt
+    pub yes: bool,
+    /// Print the commands without running them
+    pub dry_run: bool,
+    /// Certificate cache directory, `~/.webmock/ca` when unset
+    pub cert_dir: Option<PathBuf>,
+    /// Trust store to use instead of detecting the platform's
+    pub store: Option<TrustStore>,
+}
+
+impl CaOptions {
+    fn cert_dir(&self) -> Result<PathBuf> {
+        match &self.cert_dir {
+            Some(dir) => Ok(dir.clone()),
+            None => WebMockCertificate::default_dir(),
+        }
+    }
+
+    fn store(&self) -> Result<TrustStore> {
+        match self.store.clone().or_else(TrustStore::detect) {
+            Some(store) => Ok(store),
+            None => {
+                UserFeedback::error("No supported trust store found on this system");
+                UserFeedback::tip(
+                    "Supported: macOS keychain, update-ca-certificates, update-ca-trust and p11-kit",
+                );
+                Err(WebMockError::config(
+                    "No supported trust store found on this system",
+                ))
+            }
+        }
+    }
+}
+
+/// Handle `ca trust`: add the webmock certificate to the OS trust store
+pub fn ca_trust_command(options: &CaOptions) -> Result<TrustPlan> {
+    let cert_dir = options.cert_dir()?;
+    WebMockCertificate::load_or_generate(&cert_dir)?;
+    let cert_path = WebMockCertificate::cert_path(&cert_dir);
+    let store = options.store()?;
+    let plan = store.trust_plan(&cert_path);
+
+    UserFeedback::info(&format!("🔐 Certificate: {}", cert_path.display()));
+    UserFeedback::info(&format!("🗄️  Trust store: {}", store));
+
+    let prompt = "Trust the webmock certificate for all HTTPS connections on this machine?";
+    if apply_plan(&plan, options, prompt)? {
+        UserFeedback::success("WebMock certificate trusted");
+        UserFeedback::tip("Browsers may need a restart to pick up the new certificate");
+    }
+    Ok(plan)
+}
+
+/// Handle `ca untrust`: remove the webmock certificate from the OS trust store
+pub fn ca_untrust_command(options: &CaOptions) -> Result<TrustPlan> {
+    let cert_dir = options.cert_dir()?;
+    let cert_path = WebMockCertificate::cert_path(&cert_dir);
+    if !cert_path.exists() {
+        return Err(WebMockError::config(format!(
+            "No webmock certificate found at {}",
+            cert_path.display()
+        )));
+    }
+    let store = options.store()?;
+    let plan = store.untrust_plan(&cert_path);
+
+    UserFeedback::info(&format!("🔐 Certificate: {}", cert_path.display()));
+    UserFeedback::info(&format!("🗄️  Trust store: {}", store));
+
+    let prompt = "Remove the webmock certificate from the trust store?";
+    if apply_plan(&plan, options, prompt)? {
+        UserFeedback::success("WebMock certificate removed from the trust store");
+    }
+    Ok(plan)
+}
+
+/// Print, confirm and run a plan, or explain how to run it manually
+///
+/// Returns whether the trust store was changed.
+fn apply_plan(plan: &TrustPlan, options: &CaOptions, prompt: &str) -> Result<bool> {
+    if options.dry_run {
+        UserFeedback::info("Dry run, the following commands would be run:");
+        for line in plan.command_lines() {
+            println!("   {}", line);
+        }
+        return Ok(false);
+    }
+
+    if !has_admin_privileges() {
+        UserFeedback::warning("Changing the system trust store requires administrator privileges");
+        UserFeedback::info("Run these commands manually:");
+        for line in plan.manual_commands() {
+            UserFeedback::line(&format!("   {}", line));
+        }
+        return Err(WebMockError::permission_denied(
+            "the system trust store can only be changed by an administrator",
+        ));
+    }
+
+    if !options.yes && !UserFeedback::confirm(prompt).unwrap_or(false) {
+        UserFeedback::info("Cancelled by user");
+        return Ok(false);
+    }
+
+    plan.run().inspect_err(|_| {
+        UserFeedback::info("To finish manually, run:");
+        for line in plan.command_lines() {
+            UserFeedback::line(&format!("   {}", line));
+        }
+    })?;
+    info!("Trust store updated");
+    Ok(true)
+}
@@ -0,0 +1,244 @@
+//! OS trust store integration for the webmock certificate
+
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use tracing::{debug, info};
+
+use crate::error::{Result, WebMockError};
+
+/// Debian and Ubuntu anchor directory, picked up by `update-ca-certificates`
+pub const DEBIAN_ANCHOR_DIR: &str = "/usr/local/share/ca-certificates";
+
+/// Fedora, RHEL and openSUSE anchor directory, picked up by `update-ca-trust`
+pub const FEDORA_ANCHOR_DIR: &str = "/etc/pki/ca-trust/source/anchors";
+
+/// macOS keychain holding system-wide trust settings
+pub const MACOS_SYSTEM_KEYCHAIN: &str = "/Library/Keychains/System.keychain";
+
+/// Trust store the certificate can be installed into
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TrustStore {
+    /// macOS system keychain via `security`
+    MacOs,
+    /// Debian-style anchors refreshed with `update-ca-certificates`
+    Debian { anchor_dir: PathBuf },
+    /// Fedora-style anchors refreshed with `update-ca-trust`
+    Fedora { anchor_dir: PathBuf },
+    /// p11-kit `trust anchor`, used by Arch and others
+    P11Kit,
+}
+
+impl TrustStore {
+    /// Detect the trust store of the running system
+    #[cfg(target_os = "macos")]
+    pub fn detect() -> Option<Self> {
+        Some(TrustStore::MacOs)
+    }
+
+    /// Detect the trust store of the running system
+    #[cfg(target_os = "linux")]
+    pub fn detect() -> Option<Self> {
+        if Path::new(DEBIAN_ANCHOR_DIR).is_dir() && command_exists("update-ca-certificates") {
+            Some(TrustStore::Debian {
+                anchor_dir: PathBuf::from(DEBIAN_ANCHOR_DIR),
+            })
+        } else if Path::new(FEDORA_ANCHOR_DIR).is_dir() && command_exists("update-ca-trust") {
+            Some(TrustStore::Fedora {
+                anchor_dir: PathBuf::from(FEDORA_ANCHOR_DIR),
+            })
+        } else if command_exists("trust") {
+            Some(TrustStore::P11Kit)
+        } else {
+            None
+        }
+    }
+
+    /// Detect the trust store of the running system
+    #[cfg(not(any(target_os = "macos", target_os = "linux")))]
+    pub fn detect() -> Option<Self> {
+        None
+    }
+
+    /// Commands that add the certificate at `cert_path` to this store
+    pub fn trust_plan(&self, cert_path: &Path) -> TrustPlan {
+        let cert = cert_path.display().to_string();
+        let steps = match self {
+            TrustStore::MacOs => vec![vec![
+                "security".to_string(),
+                "add-trusted-cert".to_string(),
+                "-d".to_string(),
+                "-r".to_string(),
+                "trustRoot".to_string(),
+                "-k".to_string(),
+                MACOS_SYSTEM_KEYCHAIN.to_string(),
+                cert,
+            ]],
+            TrustStore::Debian { anchor_dir } => vec![
+                vec![
+                    "cp".to_string(),
+                    cert,
+                    anchor_dir.join("webmock-ca.crt").display().to_string(),
+                ],
+                vec!["update-ca-certificates".to_string()],
+            ],
+            TrustStore::Fedora { anchor_dir } => vec![
+                vec![
+                    "cp".to_string(),
+                    cert,
+                    anchor_dir.join("webmock-ca.pem").display().to_string(),
+                ],
+                vec!["update-ca-trust".to_string()],
+            ],
+            TrustStore::P11Kit => vec![vec![
+                "trust".to_string(),
+                "anchor".to_string(),
+                "--store".to_string(),
+                cert,
+            ]],
+        };
+
+        TrustPlan { steps }
+    }
+
+    /// Commands that remove the certificate at `cert_path` from this store
+    pub fn untrust_plan(&self, cert_path: &Path) -> TrustPlan {
+        let cert = cert_path.display().to_string();
+        let steps = match self {
+            TrustStore::MacOs => vec![vec![
+                "security".to_string(),
+                "remove-trusted-cert".to_string(),
+                "-d".to_string(),
+                cert,
+            ]],
+            TrustStore::Debian { anchor_dir } => vec![
+                vec![
+                    "rm".to_string(),
+                    "-f".to_string(),
+                    anchor_dir.join("webmock-ca.crt").display().to_string(),
+                ],
+                vec!["update-ca-certificates".to_string(), "--fresh".to_string()],
+            ],
+            TrustStore::Fedora { anchor_dir } => vec![
+                vec![
+                    "rm".to_string(),
+                    "-f".to_string(),
+                    anchor_dir.join("webmock-ca.pem").display().to_string(),
+                ],
+                vec!["update-ca-trust".to_string()],
+            ],
+            TrustStore::P11Kit => vec![vec![
+                "trust".to_string(),
+                "anchor".to_string(),
+                "--remove".to_string(),
+                cert,
+            ]],
+        };
+
+        TrustPlan { steps }
+    }
+}
+
+impl fmt::Display for TrustStore {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TrustStore::MacOs => write!(f, "macOS system keychain"),
+            TrustStore::Debian { anchor_dir } => {
+                write!(f, "update-ca-certificates ({})", anchor_dir.display())
+            }
+            TrustStore::Fedora { anchor_dir } => {
+                write!(f, "update-ca-trust ({})", anchor_dir.display())
+            }
+            TrustStore::P11Kit => write!(f, "p11-kit trust anchors"),
+        }
+    }
+}
+
+/// Ordered shell commands that change the trust store
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TrustPlan {
+    pub steps: Vec<Vec<String>>,
+}
+
+impl TrustPlan {
+    /// Render each step as a command line a user can paste into a root shell
+    pub fn command_lines(&self) -> Vec<String> {
+        self.steps
+            .iter()
+            .map(|step| {
+                step.iter()
+                    .map(|arg| shell_quote(arg))
+                    .collect::<Vec<_>>()
+                    .join(" ")
+            })
+            .collect()
+    }
+
+    /// Render each step prefixed with `sudo`, for users without privileges
+    pub fn manual_commands(&self) -> Vec<String> {
+        self.command_lines()
+            .into_iter()
+            .map(|line| format!("sudo {}", line))
+            .collect()
+    }
+
+    /// Run every step, stopping at the first failure
+    pub fn run(&self) -> Result<()> {
+        for step in &self.steps {
+            let Some((program, args)) = step.split_first() else {
+                continue;
+            };
+            info!("Running: {}", step.join(" "));
+
+            let output = Command::new(program).args(args).output().map_err(|e| {
+                WebMockError::command_failed(format!("Failed to run {}: {}", program, e))
+            })?;
+            if !output.status.success() {
+                let stderr = String::from_utf8_lossy(&output.stderr);
+                return Err(WebMockError::command_failed(format!(
+                    "{} exited with {}: {}",
+                    program,
+                    output.status,
+                    stderr.trim()
+                )));
+            }
+            debug!("{} completed", program);
+        }
+        Ok(())
+    }
+}
+
+/// Check whether the current process can modify the system trust store
+#[cfg(unix)]
+pub fn has_admin_privileges() -> bool {
+    Command::new("id")
+        .arg("-u")
+        .output()
+        .map(|output| String::from_utf8_lossy(&output.stdout).trim() == "0")
+        .unwrap_or(false)
+}
+
+/// Check whether the current process can modify the system trust store
+#[cfg(not(unix))]
+pub fn has_admin_privileges() -> bool {
+    false
+}
+
+#[cfg(target_os = "linux")]
+fn command_exists(program: &str) -> bool {
+    std::env::var_os("PATH")
+        .map(|paths| std::env::split_paths(&paths).any(|dir| dir.join(program).is_file()))
+        .unwrap_or(false)
+}
+
+fn shell_quote(arg: &str) -> String {
+    let safe = arg
+        .chars()
+        .all(|c| c.is_ascii_alphanumeric() || "-_./=:@".contains(c));
+    if safe && !arg.is_empty() {
+        arg.to_string()
+    } else {
+        format!("'{}'", arg.replace('\'', r"'\''"))
+    }
+}
@@ -3,9 +3,12 @@
 use std::time::Duration;
 use tracing::{error, warn};
 
-use crate::capture::CaptureSession;
-use crate::error::Result;
-use crate::feedback::ProgressReporter;
+use crate::capture::browser::OfflineReload;
+use crate::capture::{CaptureSession, CaptureStatus, CaptureWarning, CaptureWarningKind};
+use crate::commands::analyze::ResourceReport;
+use crate::error::{Result, WebMockError};
+use crate::feedback::{ProgressReporter, UserFeedback};
+use crate::storage::Snapshot;
 
 /// Run capture with comprehensive progress reporting and error handling
 pub async fn run_capture_with_progress(
@@ -14,7 +17,7 @@ pub async fn run_capture_with_progress(
     url: &str,
     name: &str,
     timeout: u64,
-) -> Result<()> {
+) -> Result<Snapshot> {
     // Start main progress indicator
     let main_progress = progress.start_capture_progress(url);
 
@@ -22,9 +25,9 @@ pub async fn run_capture_with_progress(
     let capture_result = run_capture_with_recovery(session, progress, url, name, timeout).await;
 
     match capture_result {
-        Ok(_) => {
+        Ok(snapshot) => {
             progress.finish_capture_success(name);
-            Ok(())
+            Ok(snapshot)
         }
         Err(e) => {
             progress.finish_capture_error(&e.user_message());
@@ -47,9 +50,10 @@ async fn run_capture_with_recovery(
     url: &str,
     name: &str,
     timeout: u64,
-) -> Result<()> {
+) -> Result<Snapshot> {
     const MAX_RETRIES: u32 = 3;
     let mut retry_count = 0;
+    let mut last_error = String::new();
 
     loop {
         // Update progress with current attempt
@@ -62,13 +66,22 @@ async fn run_capture_with_recovery(
         match session.capture(url, name, timeout).await {
             Ok(_) => {
                 progress.update_capture_step("Saving snapshot...");
+                session.warnings().note_many(
+                    CaptureWarningKind::RetriedCapture,
+                    retry_count.into(),
+                    &last_error,
+                );
 
                 // Capture successful, now stop and save
-                session.stop(name, url).await?;
-                return Ok(());
+                return session.stop(name, url).await;
+            }
+            Err(WebMockError::CaptureAborted(reason)) => {
+                error!("Capture aborted: {}", reason);
+                return Err(aborted(session, name, url, reason, progress).await);
             }
             Err(e) if e.is_recoverable() && retry_count < MAX_RETRIES => {
                 retry_count += 1;
+                last_error = e.to_string();
                 warn!(
                     "Capture attempt {} failed ({}), retrying... ({}/{})",
                     retry_count, e, retry_count, MAX_RETRIES
@@ -97,3 +110,207 @@ async fn run_capture_with_recovery(
         }
     }
 }
+
+/// Settle an aborted capture, saving what was recorded with `--save-on-abort`
+///
+/// Returns the error the capture fails with either way; the browser and
+/// proxy are shut down by the caller's cleanup.
+async fn aborted(
+    session: &mut CaptureSession,
+    name: &str,
+    url: &str,
+    reason: String,
+    progress: &mut ProgressReporter,
+) -> WebMockError {
+    if !session.options.abort.save_on_abort {
+        return WebMockError::CaptureAborted(format!(
+            "{}; no snapshot was saved, keep what was recorded with --save-on-abort",
+            reason
+        ));
+    }
+    progress.update_capture_step("Saving what was recorded before aborting...");
+    match session.stop(name, url).await {
+        Ok(snapshot) => WebMockError::CaptureAborted(format!(
+            "{}; the {} requests recorded before were saved as snapshot '{}'",
+            reason,
+            snapshot.requests.len(),
+            name
+        )),
+        Err(e) => {
+            warn!("Failed to save the aborted capture: {}", e);
+            WebMockError::CaptureAborted(format!(
+                "{}; saving what was recorded failed: {}",
+                reason, e
+            ))
+        }
+    }
+}
+
+/// Tell the user what the capture left out or couldn't serve
+///
+/// Counts that are also capture warnings are left to [`report_capture_warnings`].
+pub fn report_capture_summary(snapshot: &Snapshot) {
+    if !snapshot.blocked_hosts.is_empty() {
+        report_blocked_hosts(snapshot);
+    }
+    if snapshot.capture_diagnostics.failures() > 0 {
+        report_capture_diagnostics(snapshot);
+    }
+    let resources = ResourceReport::analyze(snapshot);
+    if !resources.is_self_contained() {
+        UserFeedback::warning(&format!(
+            "{} resources referenced by captured pages aren't in the snapshot and will get 404s when served; list them with 'webmock analyze {}'",
+            resources.missing.len(),
+            snapshot.name
+        ));
+    }
+    if let Some(metrics) = snapshot
+        .metrics
+        .as_ref()
+        .filter(|m| !m.stragglers.is_empty())
+    {
+        report_stragglers(&metrics.stragglers);
+    }
+    if let Some(reload) = &snapshot.offline_reload {
+        report_offline_reload(reload, snapshot);
+    }
+}
+
+/// Most requests listed in the stragglers report
+const STRAGGLERS_SHOWN: usize = 10;
+
+/// List the requests still open when the page counted as loaded
+fn report_stragglers(stragglers: &[String]) {
+    UserFeedback::info(&format!(
+        "{} requests were still open when the page counted as loaded and aren't in the snapshot (long-polls, event streams or slow requests):",
+        stragglers.len()
+    ));
+    for straggler in stragglers.iter().take(STRAGGLERS_SHOWN) {
+        UserFeedback::line(&format!("  {}", straggler));
+    }
+    if stragglers.len() > STRAGGLERS_SHOWN {
+        UserFeedback::line(&format!(
+            "  … and {} more",
+            stragglers.len() - STRAGGLERS_SHOWN
+        ));
+    }
+}
+
+/// Most hosts listed in the `--only-hosts` report
+const BLOCKED_HOSTS_SHOWN: usize = 10;
+
+/// List the hosts `--only-hosts` refused, most requested first
+fn report_blocked_hosts(snapshot: &Snapshot) {
+    let mut hosts: Vec<(&String, &u64)> = snapshot.blocked_hosts.iter().collect();
+    hosts.sort_by(|a, b| b.1.cmp(a.1).then_with(|| a.0.cmp(b.0)));
+    UserFeedback::info(&format!(
+        "--only-hosts blocked {} requests to {} hosts",
+        hosts.iter().map(|(_, count)| **count).sum::<u64>(),
+        hosts.len()
+    ));
+    for (host, count) in hosts.iter().take(BLOCKED_HOSTS_SHOWN) {
+        UserFeedback::line(&format!("  {} ({})", host, count));
+    }
+    if hosts.len() > BLOCKED_HOSTS_SHOWN {
+        UserFeedback::line(&format!(
+            "  … and {} more",
+            hosts.len() - BLOCKED_HOSTS_SHOWN
+        ));
+    }
+}
+
+/// Tell the user about connections the proxy lost, which may explain requests missing from the records
+fn report_capture_diagnostics(snapshot: &Snapshot) {
+    let diagnostics = &snapshot.capture_diagnostics;
+    UserFeedback::info(&format!(
+        "{} connection failures across {} client connections may have cost the capture requests; list them with 'webmock inspect {} --diagnostics'",
+        diagnostics.failures(),
+        diagnostics.accepted_connections,
+        snapshot.name
+    ));
+    for line in diagnostics.summary_lines() {
+        UserFeedback::line(&format!("  {}", line));
+    }
+}
+
+/// Print the warnings a finished capture collected in one block
+pub fn report_capture_warnings(warnings: &[CaptureWarning]) {
+    if warnings.is_empty() {
+        return;
+    }
+    UserFeedback::section("Capture warnings");
+    for warning in warnings {
+        UserFeedback::warning(&warning.to_string());
+    }
+}
+
+/// Settle how a capture that saved its snapshot ended
+///
+/// With `fail_on_warnings`, a capture with warnings fails so CI notices.
+pub fn capture_status(
+    name: &str,
+    warnings: &[CaptureWarning],
+    fail_on_warnings: bool,
+) -> Result<CaptureStatus> {
+    let status = CaptureStatus::of(warnings);
+    if status.exit_code(fail_on_warnings) != 0 {
+        return Err(WebMockError::command_failed(format!(
+            "Snapshot '{}' was saved, but the capture had {} warnings and --fail-on-warnings is set",
+            name,
+            warnings.iter().map(|warning| warning.count).sum::<u64>()
+        )));
+    }
+    Ok(status)
+}
+
+/// Print the `--metrics` block of a finished capture
+pub fn report_capture_metrics(snapshot: &Snapshot) {
+    let Some(metrics) = &snapshot.metrics else {
+        return;
+    };
+    println!("⏱️  Capture metrics:");
+    for line in metrics.capture_summary() {
+        println!("   {}", line);
+    }
+}
+
+/// Most URLs listed per group in the offline reload report
+const OFFLINE_URLS_SHOWN: usize = 10;
+
+/// Compare the offline reload with the online load
+fn report_offline_reload(reload: &OfflineReload, snapshot: &Snapshot) {
+    let comparison = reload.compare(&snapshot.requests);
+    UserFeedback::section("Offline reload");
+    match &reload.service_worker {
+        Some(script) => UserFeedback::info(&format!("Service worker: {}", script)),
+        None => UserFeedback::warning(
+            "No service worker activated, so the offline reload had nothing to serve it",
+        ),
+    }
+    println!(
+        "Online load: {} URLs; offline reload: {} requests, {} served by the service worker or cache",
+        comparison.online,
+        reload.requests.len(),
+        comparison.served_offline
+    );
+    print_offline_urls("Still needed the network", &comparison.network);
+    print_offline_urls("Failed offline", &comparison.failed);
+    print_offline_urls(
+        "Loaded online but not requested offline",
+        &comparison.not_requested,
+    );
+    print_offline_urls("Only requested offline", &comparison.offline_only);
+}
+
+fn print_offline_urls(label: &str, urls: &[String]) {
+    if urls.is_empty() {
+        return;
+    }
+    println!("{} ({}):", label, urls.len());
+    for url in urls.iter().take(OFFLINE_URLS_SHOWN) {
+        println!("  {}", url);
+    }
+    if urls.len() > OFFLINE_URLS_SHOWN {
+        println!("  … and {} more", urls.len() - OFFLINE_URLS_SHOWN);
+    }
+}
@@ -4,26 +4,35 @@
 //! network requests for later replay.
 
 mod execution;
+mod sitemap;
 mod storage;
+mod tail;
 mod validation;
 
 pub use execution::*;
+pub use sitemap::*;
 pub use storage::*;
+pub use tail::*;
 pub use validation::*;
 
-use tracing::info;
+use tokio::signal;
+use tracing::{info, warn};
 
-use crate::capture::CaptureSession;
-use crate::error::Result;
+use crate::capture::{CaptureOptions, CaptureSession, CaptureStatus};
+use crate::error::{Result, WebMockError};
 use crate::feedback::{chrome_detection::ChromeDetection, ProgressReporter, UserFeedback};
 
 /// Handle the capture command
+///
+/// A capture that saved its snapshot ends with a [`CaptureStatus`]; with
+/// `--fail-on-warnings` one that had warnings fails instead.
 pub async fn capture_command(
     url: &str,
     name: &str,
     timeout: u64,
     storage_arg: Option<String>,
-) -> Result<()> {
+    options: CaptureOptions,
+) -> Result<CaptureStatus> {
     info!(
         "Starting capture command for URL: {} with name: {}",
         url, name
@@ -31,39 +40,89 @@ pub async fn capture_command(
 
     // Step 0: System requirements check
     UserFeedback::info("Checking system requirements...");
-    crate::feedback::ValidationHelper::check_system_requirements()?;
+    let storage_path = crate::commands::get_storage_path(storage_arg.clone())?;
+    crate::feedback::ValidationHelper::check_system_requirements(&storage_path).await?;
 
     // Step 1: Validate inputs with enhanced feedback
     UserFeedback::info("Validating inputs...");
     validate_inputs(url, name, timeout)?;
     UserFeedback::success("Input validation passed");
 
-    // Step 1.5: Check Chrome availability
-    UserFeedback::info("Checking Chrome browser availability...");
-    ChromeDetection::validate_and_guide()?;
-
     // Step 2: Initialize storage
     let storage = initialize_storage(storage_arg).await?;
 
-    // Step 3: Check if snapshot already exists
-    check_snapshot_exists(&storage, name)?;
+    // Step 3: Check if snapshot already exists, before paying for a browser launch
+    if let CaptureDecision::Skip { .. } = check_snapshot_exists(&storage, name, &options).await? {
+        UserFeedback::success("Existing snapshot reused, nothing to capture");
+        return Ok(CaptureStatus::Success);
+    }
+    if options.encrypt {
+        // Ask now rather than after the capture, when the snapshot is saved
+        storage.passphrase().get_new()?;
+    }
+
+    // Step 3.5: Check Chrome availability, unless capturing in one already running
+    if let Some(endpoint) = &options.attach {
+        UserFeedback::info(&format!("Attaching to running Chrome at {}", endpoint));
+    } else {
+        warn_about_running_captures(&storage, &options);
+        UserFeedback::info("Checking Chrome browser availability...");
+        ChromeDetection::validate_and_guide()?;
+    }
 
     // Step 4: Create and run capture session with progress reporting
     let mut progress = ProgressReporter::new();
-    let mut session = CaptureSession::new(storage).await?;
+    if !options.redaction.is_empty() {
+        UserFeedback::info(
+            "Redaction rules enabled: sensitive values will be scrubbed before saving",
+        );
+    }
+    let tail = options.tail;
+    let print_metrics = options.metrics;
+    let fail_on_warnings = options.fail_on_warnings;
+    let mut session = CaptureSession::with_options(storage, options).await?;
+    let tail_printer = tail.then(|| spawn_tail_printer(session.subscribe(), &progress));
 
     // Step 5: Start capture with comprehensive progress reporting
-    run_capture_with_progress(&mut session, &mut progress, url, name, timeout).await?;
+    // On Ctrl+C the capture is dropped, which also abandons a save in progress
+    // without leaving a partial snapshot file behind
+    let result = tokio::select! {
+        result = run_capture_with_progress(&mut session, &mut progress, url, name, timeout) => Some(result),
+        Ok(()) = signal::ctrl_c() => None,
+    };
+    if let Some(printer) = tail_printer {
+        printer.abort();
+    }
+    let Some(result) = result else {
+        progress.finish_capture_error("interrupted");
+        if let Err(cleanup_err) = session.cleanup().await {
+            warn!(
+                "Failed to cleanup after interrupted capture: {}",
+                cleanup_err
+            );
+        }
+        return Err(WebMockError::command_failed(format!(
+            "Capture of '{}' was interrupted",
+            name
+        )));
+    };
+    let snapshot = result?;
 
     // Success feedback
     UserFeedback::success("Capture completed successfully!");
-    println!("📸 Snapshot '{}' has been saved", name);
-    println!();
+    report_capture_summary(&snapshot);
+    if print_metrics {
+        report_capture_metrics(&snapshot);
+    }
+    let warnings = session.warnings().list();
+    report_capture_warnings(&warnings);
+    UserFeedback::line(&format!("📸 Snapshot '{}' has been saved", name));
+    UserFeedback::line("");
     UserFeedback::tip("Use 'webmock list' to see all snapshots");
     UserFeedback::tip(&format!(
         "Use 'webmock serve {}' to start the mock server",
         name
     ));
 
-    Ok(())
+    capture_status(name, &warnings, fail_on_warnings)
 }
@@ -0,0 +1,164 @@
+//! Multi-page capture driven by a sitemap
+
+use std::time::Duration;
+use tracing::{info, warn};
+
+use super::{
+    capture_status, check_snapshot_exists, initialize_storage, report_capture_metrics,
+    report_capture_summary, report_capture_warnings, spawn_tail_printer, validate_inputs,
+    warn_about_running_captures, CaptureDecision,
+};
+use crate::capture::sitemap::collect_sitemap_urls;
+use crate::capture::{
+    CaptureOptions, CaptureSession, CaptureWarningKind, PageCaptureReport, SitemapOptions,
+};
+use crate::error::{Result, WebMockError};
+use crate::feedback::{chrome_detection::ChromeDetection, ProgressReporter, UserFeedback};
+
+/// Handle `capture --from-sitemap`: capture every sitemap page into one snapshot
+///
+/// `timeout` applies to each page; `total_timeout` bounds the whole run.
+pub async fn capture_sitemap_command(
+    sitemap_url: &str,
+    name: &str,
+    timeout: u64,
+    total_timeout: Option<u64>,
+    storage_arg: Option<String>,
+    options: CaptureOptions,
+    sitemap: SitemapOptions,
+) -> Result<PageCaptureReport> {
+    info!(
+        "Starting sitemap capture from {} with name: {}",
+        sitemap_url, name
+    );
+
+    UserFeedback::info("Checking system requirements...");
+    let storage_path = crate::commands::get_storage_path(storage_arg.clone())?;
+    crate::feedback::ValidationHelper::check_system_requirements(&storage_path).await?;
+
+    UserFeedback::info("Validating inputs...");
+    validate_inputs(sitemap_url, name, timeout)?;
+    if sitemap.limit == 0 {
+        return Err(WebMockError::config("--limit must be at least 1"));
+    }
+    if total_timeout == Some(0) {
+        return Err(WebMockError::config("--total-timeout must be at least 1"));
+    }
+    UserFeedback::success("Input validation passed");
+
+    let storage = initialize_storage(storage_arg).await?;
+    if let CaptureDecision::Skip { .. } = check_snapshot_exists(&storage, name, &options).await? {
+        UserFeedback::success("Existing snapshot reused, nothing to capture");
+        return Ok(PageCaptureReport::default());
+    }
+    if options.encrypt {
+        // Ask now rather than after the capture, when the snapshot is saved
+        storage.passphrase().get_new()?;
+    }
+
+    // Read the sitemap before paying for a browser launch
+    let mut progress = ProgressReporter::new();
+    let spinner = progress.create_spinner("Reading sitemap...");
+    let urls = match collect_sitemap_urls(sitemap_url, &sitemap).await {
+        Ok(urls) => {
+            spinner.finish_with_message(format!("✅ Found {} pages", urls.len()));
+            urls
+        }
+        Err(e) => {
+            spinner.finish_with_message("❌ Failed to read sitemap");
+            return Err(e);
+        }
+    };
+    if urls.is_empty() {
+        UserFeedback::tip(
+            "Check the sitemap URL, or drop --same-host-only if pages live elsewhere",
+        );
+        return Err(WebMockError::config(format!(
+            "No pages found in sitemap {}",
+            sitemap_url
+        )));
+    }
+
+    if let Some(endpoint) = &options.attach {
+        UserFeedback::info(&format!("Attaching to running Chrome at {}", endpoint));
+    } else {
+        warn_about_running_captures(&storage, &options);
+        UserFeedback::info("Checking Chrome browser availability...");
+        ChromeDetection::validate_and_guide()?;
+    }
+
+    let tail = options.tail;
+    let print_metrics = options.metrics;
+    let fail_on_warnings = options.fail_on_warnings;
+    let mut session = CaptureSession::with_options(storage, options).await?;
+    let tail_printer = tail.then(|| spawn_tail_printer(session.subscribe(), &progress));
+    let main_progress = progress.start_capture_progress(sitemap_url);
+    let total = urls.len();
+    let result = session
+        .capture_pages(
+            &urls,
+            Duration::from_secs(timeout),
+            total_timeout.map(Duration::from_secs),
+            |page, url| main_progress.set_message(format!("📡 page {}/{}: {}", page, total, url)),
+        )
+        .await;
+    if let Some(printer) = tail_printer {
+        printer.abort();
+    }
+
+    let report = match result {
+        Ok(report) => {
+            for (url, _) in &report.failed {
+                session
+                    .warnings()
+                    .note(CaptureWarningKind::UncapturedPage, url);
+            }
+            session.warnings().note_many(
+                CaptureWarningKind::UncapturedPage,
+                report.skipped as u64,
+                "",
+            );
+            progress.update_capture_step("Saving snapshot...");
+            // The first page that loaded is the entry point served by --open
+            let snapshot = session.stop(name, &report.captured[0]).await?;
+            progress.finish_capture_success(name);
+            report_capture_summary(&snapshot);
+            if print_metrics {
+                report_capture_metrics(&snapshot);
+            }
+            report
+        }
+        Err(e) => {
+            progress.finish_capture_error(&e.user_message());
+            if let Err(cleanup_err) = session.cleanup().await {
+                warn!("Failed to cleanup after capture failure: {}", cleanup_err);
+            }
+            return Err(e);
+        }
+    };
+
+    UserFeedback::success(&format!(
+        "Captured {} of {} pages into '{}'",
+        report.captured.len(),
+        total,
+        name
+    ));
+    for (url, reason) in &report.failed {
+        UserFeedback::info(&format!("Skipped {}: {}", url, reason));
+    }
+    if report.skipped > 0 {
+        UserFeedback::info(&format!(
+            "{} pages were not captured before --total-timeout ran out",
+            report.skipped
+        ));
+    }
+    let warnings = session.warnings().list();
+    report_capture_warnings(&warnings);
+    UserFeedback::tip(&format!(
+        "Use 'webmock serve {}' to start the mock server",
+        name
+    ));
+
+    capture_status(name, &warnings, fail_on_warnings)?;
+    Ok(report)
+}
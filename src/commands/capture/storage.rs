@@ -1,8 +1,12 @@
 //! Storage initialization and management for capture command
 
+use chrono::Utc;
 use std::sync::Arc;
-use tracing::info;
+use std::time::Duration;
+use tracing::{info, warn};
 
+use super::validation::format_age;
+use crate::capture::{CaptureOptions, CaptureRegistry};
 use crate::error::{Result, WebMockError};
 use crate::feedback::UserFeedback;
 use crate::storage::Storage;
@@ -22,7 +26,8 @@ pub async fn initialize_storage(storage_arg: Option<String>) -> Result<Arc<Stora
             .await
             .map_err(|e| {
                 WebMockError::permission_denied(format!(
-                    "Failed to create storage directory: {}",
+                    "Failed to create storage directory {}: {}",
+                    storage_path.display(),
                     e
                 ))
             })?;
@@ -31,23 +36,143 @@ pub async fn initialize_storage(storage_arg: Option<String>) -> Result<Arc<Stora
     let storage = Arc::new(Storage::new(storage_path.clone()));
 
     // Ensure snapshots subdirectory exists
-    storage.ensure_snapshots_dir()?;
+    storage.ensure_snapshots_dir().await?;
 
     UserFeedback::success("Storage initialized");
     info!("Storage initialized at: {}", storage_path.display());
     Ok(storage)
 }
 
-/// Check if snapshot already exists and provide helpful feedback
-pub fn check_snapshot_exists(storage: &Storage, name: &str) -> Result<()> {
-    if storage.snapshot_exists(name) {
-        UserFeedback::error(&format!("Snapshot '{}' already exists", name));
-        UserFeedback::tip("Use a different name or delete the existing snapshot first");
-        UserFeedback::tip(&format!("Delete with: webmock delete {}", name));
-        return Err(WebMockError::config(format!(
-            "Snapshot '{}' already exists",
-            name
-        )));
+/// Warn when other captures of the same storage are running, each with a browser of its own
+///
+/// Captures with `--share-browser` find each other, so only those without it are warned.
+pub fn warn_about_running_captures(storage: &Storage, options: &CaptureOptions) {
+    if options.share_browser {
+        return;
     }
-    Ok(())
+    let running = match CaptureRegistry::new(storage.base_path()).running() {
+        Ok(running) => running,
+        Err(e) => {
+            warn!("Could not check for other running captures: {}", e);
+            return;
+        }
+    };
+    if let Some(other) = running.first() {
+        UserFeedback::warning(&format!(
+            "Another capture is running ({}); this one launches a second Chrome",
+            other.describe()
+        ));
+        UserFeedback::tip(
+            "Use --share-browser for captures running side by side, or wait for it to finish",
+        );
+    }
+}
+
+/// What to do about a capture given any existing snapshot with the same name
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CaptureDecision {
+    /// No snapshot exists yet
+    Capture,
+    /// The existing snapshot is older than `--max-age` (or of unknown age) and will be replaced
+    Recapture { age: Option<Duration> },
+    /// The existing snapshot is reused and nothing is captured
+    Skip { reason: String },
+}
+
+/// Decide whether to capture based on the age of an existing snapshot
+///
+/// An existing snapshot whose age is unknown (e.g. unreadable metadata) is
+/// treated as stale.
+pub fn decide_capture(
+    name: &str,
+    exists: bool,
+    age: Option<Duration>,
+    options: &CaptureOptions,
+) -> Result<CaptureDecision> {
+    if !exists {
+        return Ok(CaptureDecision::Capture);
+    }
+
+    if let Some(max_age) = options.max_age {
+        return Ok(match age {
+            Some(age) if age <= max_age => CaptureDecision::Skip {
+                reason: format!(
+                    "Snapshot '{}' is {} old, within --max-age {}",
+                    name,
+                    format_age(age),
+                    format_age(max_age)
+                ),
+            },
+            age => CaptureDecision::Recapture { age },
+        });
+    }
+
+    if options.if_missing {
+        return Ok(CaptureDecision::Skip {
+            reason: format!("Snapshot '{}' already exists (--if-missing)", name),
+        });
+    }
+
+    Err(WebMockError::config(format!(
+        "Snapshot '{}' already exists",
+        name
+    )))
+}
+
+/// Check if snapshot already exists and decide whether to capture it again
+pub async fn check_snapshot_exists(
+    storage: &Storage,
+    name: &str,
+    options: &CaptureOptions,
+) -> Result<CaptureDecision> {
+    let exists = storage.snapshot_exists(name)?;
+    let age = if exists {
+        let age = match storage.load_snapshot_metadata(name).await {
+            Ok(info) => Some(
+                (Utc::now() - info.created_at)
+                    .to_std()
+                    .unwrap_or(Duration::ZERO),
+            ),
+            Err(e) => {
+                warn!("Could not read metadata of snapshot '{}': {}", name, e);
+                None
+            }
+        };
+
+        if options.print_age {
+            if let Some(age) = age {
+                println!("{}", age.as_secs());
+            }
+        }
+
+        age
+    } else {
+        None
+    };
+
+    let decision = decide_capture(name, exists, age, options);
+    match &decision {
+        Ok(CaptureDecision::Capture) => {}
+        Ok(CaptureDecision::Recapture { age }) => {
+            let age = match age {
+                Some(age) => format!("{} old", format_age(*age)),
+                None => "of unknown age".to_string(),
+            };
+            UserFeedback::info(&format!(
+                "Snapshot '{}' is {}, recapturing (--max-age)",
+                name, age
+            ));
+        }
+        Ok(CaptureDecision::Skip { reason }) => {
+            UserFeedback::info(reason);
+        }
+        Err(_) => {
+            UserFeedback::error(&format!("Snapshot '{}' already exists", name));
+            UserFeedback::tip("Use a different name or delete the existing snapshot first");
+            UserFeedback::tip(&format!("Delete with: webmock delete {}", name));
+            UserFeedback::tip("Use --if-missing or --max-age to reuse existing snapshots");
+        }
+    }
+
+    decision
 }
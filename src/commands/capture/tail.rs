@@ -0,0 +1,55 @@
+//! Live request lines for `capture --tail`
+
+use colored::*;
+use tokio::task::JoinHandle;
+
+use crate::capture::proxy::{RecordTail, RecordedRequest, TailEvent};
+use crate::commands::inspect::format_size;
+use crate::feedback::ProgressReporter;
+
+/// Print a line per recorded request above the progress spinner until aborted
+pub fn spawn_tail_printer(mut tail: RecordTail, progress: &ProgressReporter) -> JoinHandle<()> {
+    let print = progress.line_printer();
+    tokio::spawn(async move {
+        while let Some(event) = tail.recv().await {
+            print(&format_tail_event(&event));
+        }
+    })
+}
+
+/// Format a tail event as one console line
+pub fn format_tail_event(event: &TailEvent) -> String {
+    match event {
+        TailEvent::Recorded(request) => format_recorded(request),
+        TailEvent::Dropped(count) => format!(
+            "   {}",
+            format!("… {} requests not shown (output fell behind)", count).dimmed()
+        ),
+    }
+}
+
+fn format_recorded(request: &RecordedRequest) -> String {
+    if let Some(reason) = &request.failure {
+        return format!(
+            "   {} {} {} ({})",
+            "ERR".red(),
+            request.method,
+            request.url,
+            reason
+        );
+    }
+
+    let status = request.status.to_string();
+    let status = match request.status {
+        200..=299 => status.green(),
+        300..=399 => status.yellow(),
+        _ => status.red(),
+    };
+    format!(
+        "   {} {} {} ({})",
+        status,
+        request.method,
+        request.url,
+        format_size(request.size)
+    )
+}
@@ -1,7 +1,8 @@
 //! Input validation for capture command
 
-use crate::error::Result;
+use crate::error::{Result, WebMockError};
 use crate::feedback::ValidationHelper;
+use std::time::Duration;
 use tracing::info;
 
 /// Validate command inputs with enhanced feedback
@@ -18,3 +19,51 @@ pub fn validate_inputs(url: &str, name: &str, timeout: u64) -> Result<()> {
     info!("Input validation passed");
     Ok(())
 }
+
+/// Parse a snapshot age such as `30m`, `12h` or `7d`
+pub fn parse_age(value: &str) -> Result<Duration> {
+    let value = value.trim();
+    let invalid = || {
+        WebMockError::config(format!(
+            "Invalid age '{}': use a number followed by s, m, h or d (e.g. 30m, 12h, 7d)",
+            value
+        ))
+    };
+
+    let unit_start = value
+        .find(|c: char| !c.is_ascii_digit())
+        .ok_or_else(invalid)?;
+    let (amount, unit) = value.split_at(unit_start);
+    let amount: u64 = amount.parse().map_err(|_| invalid())?;
+
+    let seconds_per_unit = match unit {
+        "s" => 1,
+        "m" => 60,
+        "h" => 60 * 60,
+        "d" => 24 * 60 * 60,
+        _ => return Err(invalid()),
+    };
+
+    amount
+        .checked_mul(seconds_per_unit)
+        .map(Duration::from_secs)
+        .ok_or_else(invalid)
+}
+
+/// Format a snapshot age for display, e.g. `2d 3h` or `45m`
+pub fn format_age(age: Duration) -> String {
+    let total_minutes = age.as_secs() / 60;
+    let days = total_minutes / (24 * 60);
+    let hours = (total_minutes / 60) % 24;
+    let minutes = total_minutes % 60;
+
+    if days > 0 {
+        format!("{}d {}h", days, hours)
+    } else if hours > 0 {
+        format!("{}h {}m", hours, minutes)
+    } else if minutes > 0 {
+        format!("{}m", minutes)
+    } else {
+        format!("{}s", age.as_secs())
+    }
+}
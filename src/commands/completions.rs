@@ -0,0 +1,224 @@
+//! Completions command implementation
+//!
+//! Installs the completion script for a shell into its conventional
+//! user-level directory, so completions work without root and without
+//! editing system files. Scripts carry a marker line; a file without it
+//! belongs to someone else and is never overwritten or removed.
+
+use std::path::{Path, PathBuf};
+
+use clap::CommandFactory;
+use tracing::info;
+
+use crate::cli::{Cli, Shell};
+use crate::error::{Result, WebMockError};
+use crate::feedback::UserFeedback;
+
+/// First line of every installed script, identifying it as webmock's
+pub const SCRIPT_MARKER: &str = "# webmock completions, installed by 'webmock completions install'";
+
+/// Options for `completions install`
+#[derive(Debug, Clone)]
+pub struct CompletionsOptions {
+    /// Shell to install for, detected from `login_shell` when unset
+    pub shell: Option<Shell>,
+    /// Remove the installed script instead
+    pub uninstall: bool,
+    pub home: PathBuf,
+    /// `$XDG_DATA_HOME`, or `~/.local/share`
+    pub data_home: PathBuf,
+    /// `$XDG_CONFIG_HOME`, or `~/.config`
+    pub config_home: PathBuf,
+    /// `$SHELL`
+    pub login_shell: Option<String>,
+}
+
+impl CompletionsOptions {
+    /// Install under `home` with the XDG default directories
+    pub fn with_home(home: &Path) -> Self {
+        Self {
+            shell: None,
+            uninstall: false,
+            home: home.to_path_buf(),
+            data_home: home.join(".local/share"),
+            config_home: home.join(".config"),
+            login_shell: None,
+        }
+    }
+
+    /// Install under the current user's home, honoring `$XDG_*_HOME` and `$SHELL`
+    pub fn from_env(shell: Option<Shell>, uninstall: bool) -> Result<Self> {
+        let home = dirs::home_dir()
+            .ok_or_else(|| WebMockError::config("Could not determine home directory"))?;
+        let env_dir = |name: &str| {
+            std::env::var_os(name)
+                .map(PathBuf::from)
+                .filter(|dir| dir.is_absolute())
+        };
+        let defaults = Self::with_home(&home);
+        Ok(Self {
+            shell,
+            uninstall,
+            data_home: env_dir("XDG_DATA_HOME").unwrap_or(defaults.data_home),
+            config_home: env_dir("XDG_CONFIG_HOME").unwrap_or(defaults.config_home),
+            login_shell: std::env::var("SHELL").ok(),
+            home,
+        })
+    }
+
+    fn shell(&self) -> Result<Shell> {
+        if let Some(shell) = &self.shell {
+            return Ok(shell.clone());
+        }
+        let login_shell = self.login_shell.as_deref().unwrap_or_default();
+        shell_from_path(login_shell).ok_or_else(|| {
+            WebMockError::config(format!(
+                "Could not detect your shell from $SHELL ({}); pass --shell",
+                if login_shell.is_empty() {
+                    "unset"
+                } else {
+                    login_shell
+                }
+            ))
+        })
+    }
+}
+
+/// What `completions install` did
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CompletionInstall {
+    pub shell: Shell,
+    pub path: PathBuf,
+    /// Whether a script was written, or with `--uninstall` removed
+    pub changed: bool,
+    /// Line the user still has to add to their shell's startup file
+    pub rc_line: Option<String>,
+}
+
+/// Shell named by a `$SHELL` path such as `/usr/bin/zsh`
+pub fn shell_from_path(path: &str) -> Option<Shell> {
+    let name = Path::new(path).file_name()?.to_str()?;
+    match name.trim_end_matches(".exe") {
+        "bash" => Some(Shell::Bash),
+        "zsh" => Some(Shell::Zsh),
+        "fish" => Some(Shell::Fish),
+        "elvish" => Some(Shell::Elvish),
+        "pwsh" | "powershell" => Some(Shell::PowerShell),
+        _ => None,
+    }
+}
+
+/// Completion script for `shell`
+pub fn completion_script(shell: &Shell) -> Vec<u8> {
+    let generator = match shell {
+        Shell::Bash => clap_complete::Shell::Bash,
+        Shell::Elvish => clap_complete::Shell::Elvish,
+        Shell::Fish => clap_complete::Shell::Fish,
+        Shell::PowerShell => clap_complete::Shell::PowerShell,
+        Shell::Zsh => clap_complete::Shell::Zsh,
+    };
+    let mut script = Vec::new();
+    clap_complete::generate(generator, &mut Cli::command(), "webmock", &mut script);
+    script
+}
+
+/// Where the script for `shell` goes, and the startup file line it needs, if any
+pub fn install_location(shell: &Shell, options: &CompletionsOptions) -> (PathBuf, Option<String>) {
+    match shell {
+        // Loaded on demand by bash-completion
+        Shell::Bash => (
+            options
+                .data_home
+                .join("bash-completion/completions/webmock"),
+            None,
+        ),
+        Shell::Zsh => (
+            options.home.join(".zfunc/_webmock"),
+            Some("fpath=(~/.zfunc $fpath); autoload -Uz compinit && compinit".to_string()),
+        ),
+        Shell::Fish => (
+            options.config_home.join("fish/completions/webmock.fish"),
+            None,
+        ),
+        Shell::Elvish => (
+            options.config_home.join("elvish/lib/webmock.elv"),
+            Some("use webmock".to_string()),
+        ),
+        Shell::PowerShell => {
+            let path = options.config_home.join("powershell/webmock.ps1");
+            let rc_line = format!(". \"{}\"", path.display());
+            (path, Some(rc_line))
+        }
+    }
+}
+
+/// Handle `completions install`: write or remove the completion script
+pub fn completions_install_command(options: &CompletionsOptions) -> Result<CompletionInstall> {
+    let shell = options.shell()?;
+    let (path, rc_line) = install_location(&shell, options);
+    info!("Completion script for {:?}: {}", shell, path.display());
+
+    if path.exists() && !is_webmock_script(&path)? {
+        return Err(WebMockError::config(format!(
+            "{} exists and wasn't installed by webmock; move it away first",
+            path.display()
+        )));
+    }
+
+    if options.uninstall {
+        let changed = path.exists();
+        if changed {
+            std::fs::remove_file(&path)?;
+            UserFeedback::success(&format!("Removed {}", path.display()));
+        } else {
+            UserFeedback::info(&format!("No completion script at {}", path.display()));
+        }
+        return Ok(CompletionInstall {
+            shell,
+            path,
+            changed,
+            rc_line: None,
+        });
+    }
+
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir).map_err(|e| {
+            WebMockError::config(format!("Failed to create {}: {}", dir.display(), e))
+        })?;
+    }
+    let mut script = format!("{}\n", SCRIPT_MARKER).into_bytes();
+    script.extend(completion_script(&shell));
+    std::fs::write(&path, script)
+        .map_err(|e| WebMockError::config(format!("Failed to write {}: {}", path.display(), e)))?;
+
+    UserFeedback::success(&format!("Wrote {}", path.display()));
+    match &rc_line {
+        Some(line) => UserFeedback::tip(&format!(
+            "Add this line to your {} startup file if it isn't there yet: {}",
+            shell_name(&shell),
+            line
+        )),
+        None => UserFeedback::tip("Open a new shell to use the completions"),
+    }
+    Ok(CompletionInstall {
+        shell,
+        path,
+        changed: true,
+        rc_line,
+    })
+}
+
+fn is_webmock_script(path: &Path) -> Result<bool> {
+    let contents = std::fs::read(path)?;
+    Ok(contents.starts_with(SCRIPT_MARKER.as_bytes()))
+}
+
+fn shell_name(shell: &Shell) -> &'static str {
+    match shell {
+        Shell::Bash => "bash",
+        Shell::Elvish => "elvish",
+        Shell::Fish => "fish",
+        Shell::PowerShell => "PowerShell",
+        Shell::Zsh => "zsh",
+    }
+}
@@ -0,0 +1,79 @@
+//! Config command implementation
+//!
+//! Stores default serve settings in a snapshot, so `webmock serve <name>`
+//! replays it the way it needs without repeating the flags every time. Flags
+//! given to `serve` still win over the stored defaults.
+
+use tracing::info;
+
+use crate::commands::serve::apply_serve_defaults;
+use crate::error::{Result, WebMockError};
+use crate::feedback::{UserFeedback, ValidationHelper};
+use crate::serve::ServeOptions;
+use crate::storage::{ServeDefaults, Storage};
+
+/// Handle the config command
+///
+/// Without `changes` and `clear` the stored defaults are printed. Otherwise
+/// `changes` are stored over the current defaults, or replace them all with
+/// `clear`.
+pub async fn config_command(
+    snapshot_name: &str,
+    changes: ServeDefaults,
+    clear: bool,
+    storage_arg: Option<String>,
+) -> Result<()> {
+    info!("Starting config command for snapshot: {}", snapshot_name);
+
+    ValidationHelper::validate_snapshot_name(snapshot_name)?;
+    if let Some(port) = changes.port {
+        ValidationHelper::validate_port(port)?;
+    }
+    // Reject values serve would reject before they are stored
+    apply_serve_defaults(&changes, &mut ServeOptions::default())?;
+
+    let storage_path = crate::commands::get_storage_path(storage_arg)?;
+    let storage = Storage::new(storage_path);
+    let mut snapshot = match storage.load_snapshot(snapshot_name).await {
+        Ok(snapshot) => snapshot,
+        Err(e) => {
+            if matches!(e, WebMockError::SnapshotNotFound(_)) {
+                UserFeedback::tip("Use 'webmock list' to see available snapshots");
+            }
+            return Err(e);
+        }
+    };
+
+    if changes.is_empty() && !clear {
+        print_serve_defaults(snapshot_name, &snapshot.serve_defaults);
+        return Ok(());
+    }
+
+    snapshot.serve_defaults = if clear {
+        changes
+    } else {
+        changes.or(&snapshot.serve_defaults)
+    };
+    let serve_defaults = snapshot.serve_defaults.clone();
+
+    storage.rewrite_snapshot(snapshot, false).await?;
+
+    UserFeedback::success(&format!("Updated serve defaults of '{}'", snapshot_name));
+    print_serve_defaults(snapshot_name, &serve_defaults);
+    Ok(())
+}
+
+fn print_serve_defaults(snapshot_name: &str, serve_defaults: &ServeDefaults) {
+    if serve_defaults.is_empty() {
+        println!("No serve defaults stored with '{}'", snapshot_name);
+        return;
+    }
+    println!("Serve defaults of '{}':", snapshot_name);
+    for flag in serve_defaults.flags() {
+        println!("   {}", flag);
+    }
+    println!(
+        "Flags given to 'webmock serve {}' override these.",
+        snapshot_name
+    );
+}
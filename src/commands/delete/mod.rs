@@ -1,7 +1,8 @@
+use chrono::{DateTime, Utc};
 use tracing::{info, warn};
 
 use crate::error::{Result, WebMockError};
-use crate::feedback::{ProgressReporter, UserFeedback};
+use crate::feedback::{format_date, ProgressReporter, UserFeedback};
 use crate::storage::Storage;
 
 /// Handle the delete command with enhanced feedback
@@ -23,7 +24,7 @@ pub async fn delete_command(snapshot_name: &str, storage_arg: Option<String>) ->
         "Checking if snapshot '{}' exists...",
         snapshot_name
     ));
-    if !storage.snapshot_exists(snapshot_name) {
+    if !storage.snapshot_exists(snapshot_name)? {
         UserFeedback::error(&format!("Snapshot '{}' not found", snapshot_name));
         UserFeedback::tip("Use 'webmock list' to see available snapshots");
         return Err(WebMockError::SnapshotNotFound(snapshot_name.to_string()));
@@ -31,8 +32,13 @@ pub async fn delete_command(snapshot_name: &str, storage_arg: Option<String>) ->
 
     UserFeedback::success(&format!("Found snapshot '{}'", snapshot_name));
 
-    // Ask for user confirmation with enhanced dialog
-    if !confirm_deletion(snapshot_name)? {
+    // Ask for user confirmation with enhanced dialog, showing when it was captured
+    let created_at = storage
+        .load_snapshot_metadata(snapshot_name)
+        .await
+        .ok()
+        .map(|info| info.created_at);
+    if !confirm_deletion(snapshot_name, created_at)? {
         UserFeedback::info("Deletion cancelled by user");
         return Ok(());
     }
@@ -71,12 +77,19 @@ pub async fn delete_command(snapshot_name: &str, storage_arg: Option<String>) ->
 }
 
 /// Ask user for confirmation before deleting the snapshot with enhanced dialog
-fn confirm_deletion(snapshot_name: &str) -> Result<bool> {
+fn confirm_deletion(snapshot_name: &str, created_at: Option<DateTime<Utc>>) -> Result<bool> {
     UserFeedback::warning("This action cannot be undone!");
 
-    let message = format!(
-        "Are you sure you want to delete snapshot '{}'?",
-        snapshot_name
-    );
+    let message = match created_at {
+        Some(created_at) => format!(
+            "Are you sure you want to delete snapshot '{}', captured {}?",
+            snapshot_name,
+            format_date(created_at)
+        ),
+        None => format!(
+            "Are you sure you want to delete snapshot '{}'?",
+            snapshot_name
+        ),
+    };
     Ok(UserFeedback::confirm(&message).unwrap_or(false))
 }
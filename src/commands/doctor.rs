@@ -0,0 +1,388 @@
+//! Doctor command implementation
+//!
+//! Runs every check of the environment webmock depends on and reports them
+//! together, as a table or as JSON to attach to a bug report. Each check runs
+//! on its own task with a time limit, so one that fails, hangs or panics
+//! doesn't keep the others from being reported. Only failures that rule out
+//! capturing or serving fail the command; warnings don't.
+
+use std::future::Future;
+use std::path::{Path, PathBuf};
+use std::pin::Pin;
+use std::sync::Arc;
+use std::time::Duration;
+
+use chrono::Utc;
+use serde::Serialize;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+use tracing::info;
+
+use crate::capture::proxy::{RequestRecord, ResponseRecord};
+use crate::error::{Result, WebMockError};
+use crate::feedback::chrome_detection::ChromeDetection;
+use crate::feedback::{UserFeedback, ValidationHelper};
+use crate::storage::{Snapshot, SnapshotSerializer};
+use crate::tls::{server_config_for, WebMockCertificate};
+
+/// Longest a single check may take before it is reported as failed
+pub const CHECK_TIMEOUT: Duration = Duration::from_secs(15);
+
+/// Free disk space below which a warning is reported
+pub const LOW_DISK_SPACE_BYTES: u64 = 1024 * 1024 * 1024;
+
+/// Outcome of one check
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CheckStatus {
+    Pass,
+    Warn,
+    Fail,
+}
+
+impl CheckStatus {
+    fn icon(self) -> &'static str {
+        match self {
+            CheckStatus::Pass => "✅",
+            CheckStatus::Warn => "⚠️",
+            CheckStatus::Fail => "❌",
+        }
+    }
+}
+
+/// One line of the report
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct CheckResult {
+    pub name: String,
+    pub status: CheckStatus,
+    pub detail: String,
+}
+
+/// Everything `webmock doctor` found
+#[derive(Debug, Clone, Serialize)]
+pub struct DoctorReport {
+    pub version: String,
+    pub os: String,
+    pub arch: String,
+    pub storage: PathBuf,
+    pub checks: Vec<CheckResult>,
+}
+
+impl DoctorReport {
+    /// Checks that failed outright
+    pub fn failures(&self) -> usize {
+        self.count(CheckStatus::Fail)
+    }
+
+    /// Checks that passed with a warning
+    pub fn warnings(&self) -> usize {
+        self.count(CheckStatus::Warn)
+    }
+
+    fn count(&self, status: CheckStatus) -> usize {
+        self.checks
+            .iter()
+            .filter(|check| check.status == status)
+            .count()
+    }
+}
+
+/// What a check found when it didn't fail: a pass or a warning, with details
+type CheckOutcome = Result<(CheckStatus, String)>;
+
+type CheckFuture = Pin<Box<dyn Future<Output = CheckOutcome> + Send>>;
+
+/// Run `check` on a task of its own and turn whatever happens into a result
+///
+/// An error, a panic or running past `timeout` is reported as a failure
+/// when the check is `required`, and as a warning otherwise.
+pub async fn run_check<F>(name: &str, required: bool, timeout: Duration, check: F) -> CheckResult
+where
+    F: Future<Output = CheckOutcome> + Send + 'static,
+{
+    let failed = if required {
+        CheckStatus::Fail
+    } else {
+        CheckStatus::Warn
+    };
+    let (status, detail) = match tokio::time::timeout(timeout, tokio::spawn(check)).await {
+        Ok(Ok(Ok(outcome))) => outcome,
+        Ok(Ok(Err(e))) => (failed, e.to_string()),
+        Ok(Err(e)) => (failed, format!("Check crashed: {}", e)),
+        Err(_) => (failed, format!("Check did not finish within {:?}", timeout)),
+    };
+    CheckResult {
+        name: name.to_string(),
+        status,
+        detail,
+    }
+}
+
+/// Run every check against the storage directory at `storage_path`
+pub async fn run_checks(storage_path: &Path) -> DoctorReport {
+    let storage = storage_path.to_path_buf();
+    let checks: Vec<(&str, bool, CheckFuture)> = vec![
+        ("Chrome", true, Box::pin(check_chrome())),
+        (
+            "Storage directory",
+            true,
+            Box::pin(check_storage(storage.clone())),
+        ),
+        (
+            "Snapshot round trip",
+            true,
+            Box::pin(check_snapshot_round_trip(storage.clone())),
+        ),
+        ("Free disk space", false, Box::pin(check_disk_space())),
+        ("Local port", true, Box::pin(check_local_port())),
+        ("TLS handshake", true, Box::pin(check_tls_handshake())),
+        ("Network", false, Box::pin(check_network())),
+    ];
+
+    // Started together, so a slow check doesn't hold up the others
+    let running: Vec<_> = checks
+        .into_iter()
+        .map(|(name, required, check)| run_check(name, required, CHECK_TIMEOUT, check))
+        .collect();
+    let checks = futures::future::join_all(running).await;
+
+    DoctorReport {
+        version: env!("CARGO_PKG_VERSION").to_string(),
+        os: std::env::consts::OS.to_string(),
+        arch: std::env::consts::ARCH.to_string(),
+        storage,
+        checks,
+    }
+}
+
+/// Handle the doctor command
+///
+/// With `json` set only the report is written to stdout, so it can be
+/// attached to a bug report. The command fails only when a required check
+/// failed, after the report is printed.
+pub async fn doctor_command(storage_arg: Option<String>, json: bool) -> Result<DoctorReport> {
+    info!("Running environment checks");
+
+    // Not created here: a directory that can't be created is one of the findings
+    let storage_path = match storage_arg {
+        Some(custom_path) => crate::storage::paths::normalize_separators(&custom_path),
+        None => crate::storage::default_storage_path()?,
+    };
+    let report = run_checks(&storage_path).await;
+
+    if json {
+        let output = serde_json::to_string_pretty(&report)
+            .map_err(|e| WebMockError::config(format!("Failed to encode report as JSON: {}", e)))?;
+        println!("{}", output);
+    } else {
+        print_report(&report);
+    }
+
+    if report.failures() > 0 {
+        return Err(WebMockError::command_failed(format!(
+            "{} of {} checks failed",
+            report.failures(),
+            report.checks.len()
+        )));
+    }
+    Ok(report)
+}
+
+fn print_report(report: &DoctorReport) {
+    UserFeedback::section(&format!(
+        "🩺 webmock {} on {} {}",
+        report.version, report.os, report.arch
+    ));
+    println!("   Storage: {}", report.storage.display());
+    let width = report
+        .checks
+        .iter()
+        .map(|check| check.name.len())
+        .max()
+        .unwrap_or(0);
+    for check in &report.checks {
+        println!(
+            "   {} {:<width$}  {}",
+            check.status.icon(),
+            check.name,
+            check.detail,
+            width = width
+        );
+    }
+
+    match (report.failures(), report.warnings()) {
+        (0, 0) => UserFeedback::success("All checks passed"),
+        (0, warnings) => UserFeedback::warning(&format!(
+            "All required checks passed, {} with warnings",
+            warnings
+        )),
+        (failures, _) => {
+            UserFeedback::error(&format!("{} checks failed", failures));
+            UserFeedback::tip("Attach the output of 'webmock doctor --json' when reporting a bug");
+        }
+    }
+}
+
+async fn check_chrome() -> CheckOutcome {
+    let found = tokio::task::spawn_blocking(ChromeDetection::check_chrome_availability).await??;
+    Ok((CheckStatus::Pass, found))
+}
+
+async fn check_storage(storage: PathBuf) -> CheckOutcome {
+    ValidationHelper::check_permissions(&storage).await?;
+    Ok((
+        CheckStatus::Pass,
+        format!("{} is writable", storage.display()),
+    ))
+}
+
+/// Write a tiny snapshot as MessagePack next to the real ones and read it back
+async fn check_snapshot_round_trip(storage: PathBuf) -> CheckOutcome {
+    let snapshot = probe_snapshot();
+    let data = SnapshotSerializer::serialize(&snapshot)?;
+    let path = storage.join(format!(".doctor-{}.msgpack", std::process::id()));
+
+    let written = tokio::fs::write(&path, &data)
+        .await
+        .map_err(|e| WebMockError::io("write test snapshot", &path, e));
+    let read = match written {
+        Ok(()) => tokio::fs::read(&path)
+            .await
+            .map_err(|e| WebMockError::io("read test snapshot", &path, e)),
+        Err(e) => Err(e),
+    };
+    let _ = tokio::fs::remove_file(&path).await;
+
+    let loaded = SnapshotSerializer::deserialize(&read?)?;
+    let same = loaded.name == snapshot.name
+        && loaded.requests.len() == 1
+        && loaded.requests[0].url == snapshot.requests[0].url
+        && loaded.requests[0].response.body == snapshot.requests[0].response.body;
+    if !same {
+        return Err(WebMockError::config(
+            "Test snapshot read back differently from how it was written",
+        ));
+    }
+    Ok((
+        CheckStatus::Pass,
+        format!("Wrote and read back a {} byte snapshot", data.len()),
+    ))
+}
+
+fn probe_snapshot() -> Snapshot {
+    let response = ResponseRecord::new(200, Default::default(), b"doctor".to_vec(), None);
+    let request = RequestRecord::new(
+        "GET".to_string(),
+        "https://example.com/".to_string(),
+        Default::default(),
+        None,
+        response,
+    );
+    Snapshot {
+        name: "doctor".to_string(),
+        url: "https://example.com/".to_string(),
+        created_at: Utc::now(),
+        requests: vec![request],
+        ..Default::default()
+    }
+}
+
+async fn check_disk_space() -> CheckOutcome {
+    let disk = sys_info::disk_info()
+        .map_err(|e| WebMockError::config(format!("Can't read free disk space: {}", e)))?;
+    let free = disk.free.saturating_mul(1024);
+    let detail = format!("{} free", format_gib(free));
+    if free < LOW_DISK_SPACE_BYTES {
+        return Ok((
+            CheckStatus::Warn,
+            format!("Only {}; large captures may fail to save", detail),
+        ));
+    }
+    Ok((CheckStatus::Pass, detail))
+}
+
+fn format_gib(bytes: u64) -> String {
+    format!("{:.1} GiB", bytes as f64 / (1024.0 * 1024.0 * 1024.0))
+}
+
+async fn check_local_port() -> CheckOutcome {
+    let listener = TcpListener::bind("127.0.0.1:0")
+        .await
+        .map_err(|e| WebMockError::config(format!("Can't bind a local port: {}", e)))?;
+    let address = listener
+        .local_addr()
+        .map_err(|e| WebMockError::config(format!("Can't read the bound port: {}", e)))?;
+    Ok((CheckStatus::Pass, format!("Bound {}", address)))
+}
+
+/// Complete a TLS handshake with a freshly generated certificate over loopback
+async fn check_tls_handshake() -> CheckOutcome {
+    let certificate = WebMockCertificate::generate()?;
+    let acceptor = tokio_rustls::TlsAcceptor::from(server_config_for(&certificate)?);
+
+    let mut roots = rustls::RootCertStore::empty();
+    for cert in certificate.cert_chain()? {
+        roots.add(cert).map_err(|e| {
+            WebMockError::config(format!("Can't trust the test certificate: {}", e))
+        })?;
+    }
+    let client = rustls::ClientConfig::builder()
+        .with_root_certificates(roots)
+        .with_no_client_auth();
+    let connector = tokio_rustls::TlsConnector::from(Arc::new(client));
+
+    let handshake_failed =
+        |e: std::io::Error| WebMockError::config(format!("TLS handshake failed: {}", e));
+    let listener = TcpListener::bind("127.0.0.1:0")
+        .await
+        .map_err(handshake_failed)?;
+    let address = listener.local_addr().map_err(handshake_failed)?;
+    let server = tokio::spawn(async move {
+        let (stream, _) = listener.accept().await?;
+        let mut stream = acceptor.accept(stream).await?;
+        stream.write_all(b"ok").await?;
+        stream.shutdown().await
+    });
+
+    let stream = TcpStream::connect(address)
+        .await
+        .map_err(handshake_failed)?;
+    let server_name = rustls::pki_types::ServerName::try_from("localhost")
+        .map_err(|e| WebMockError::config(format!("Invalid TLS server name: {}", e)))?;
+    let mut stream = connector
+        .connect(server_name, stream)
+        .await
+        .map_err(handshake_failed)?;
+    let mut reply = Vec::new();
+    stream
+        .read_to_end(&mut reply)
+        .await
+        .map_err(handshake_failed)?;
+    server.await?.map_err(handshake_failed)?;
+    if reply != b"ok" {
+        return Err(WebMockError::config(
+            "TLS connection returned unexpected data",
+        ));
+    }
+
+    let protocol = stream
+        .get_ref()
+        .1
+        .protocol_version()
+        .map(|version| format!("{:?}", version))
+        .unwrap_or_else(|| "TLS".to_string());
+    Ok((
+        CheckStatus::Pass,
+        format!("{} with a locally generated certificate", protocol),
+    ))
+}
+
+async fn check_network() -> CheckOutcome {
+    match ValidationHelper::check_network_connectivity().await {
+        Ok(()) => Ok((CheckStatus::Pass, "DNS lookups work".to_string())),
+        Err(_) => Ok((
+            CheckStatus::Warn,
+            "DNS lookups fail; capturing live sites needs network access".to_string(),
+        )),
+    }
+}
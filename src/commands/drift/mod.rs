@@ -0,0 +1,312 @@
+//! Drift command implementation
+//!
+//! Fetches the live version of each JSON endpoint captured in a snapshot and
+//! compares the structure of the two bodies: which fields exist and what kind
+//! of value they hold, not the values themselves. An old snapshot whose
+//! endpoints have drifted mocks an API that no longer exists, so tests
+//! passing against it prove little.
+//!
+//! Endpoints that can't be fetched are reported and the others still checked.
+
+pub mod shape;
+
+use std::collections::HashSet;
+use std::time::Duration;
+
+use bytes::Bytes;
+use colored::Colorize;
+use futures::stream::{self, StreamExt};
+use globset::{Glob, GlobMatcher};
+use http_body_util::{BodyExt, Empty, Limited};
+use hyper::header::{HeaderName, HeaderValue, ACCEPT, ACCEPT_ENCODING, LOCATION, USER_AGENT};
+use hyper::{HeaderMap, Request};
+use hyper_util::client::legacy::Client;
+use serde_json::Value;
+use tracing::{debug, info};
+use url::Url;
+
+use crate::capture::proxy::HttpClientPool;
+use crate::error::{Result, WebMockError};
+use crate::feedback::{UserFeedback, ValidationHelper};
+use crate::storage::{Snapshot, Storage};
+
+use shape::{compare, FieldChange};
+
+/// Endpoints fetched at the same time
+const DRIFT_CONCURRENCY: usize = 4;
+
+/// Time allowed for fetching one endpoint
+const DRIFT_FETCH_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Redirects followed when fetching an endpoint
+const MAX_DRIFT_REDIRECTS: usize = 5;
+
+/// Largest live response body compared
+const MAX_DRIFT_BODY_BYTES: usize = 50 * 1024 * 1024;
+
+/// Options for the drift command
+#[derive(Debug, Clone, Default)]
+pub struct DriftOptions {
+    /// Only check endpoints whose URL matches this glob
+    pub url_glob: Option<String>,
+    /// Headers sent with every live request, as `Name: value`
+    pub headers: Vec<String>,
+    /// Fail when any endpoint drifted
+    pub strict: bool,
+}
+
+/// How the live version of one captured endpoint compares
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EndpointDrift {
+    pub url: String,
+    /// Structural differences, empty when the shapes match
+    pub changes: Vec<FieldChange>,
+    /// Why the live version couldn't be compared, if it couldn't
+    pub error: Option<String>,
+}
+
+impl EndpointDrift {
+    /// Check whether the live version was fetched and differs
+    pub fn drifted(&self) -> bool {
+        !self.changes.is_empty()
+    }
+}
+
+/// Drift of every JSON endpoint checked, in snapshot order
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct DriftReport {
+    pub snapshot: String,
+    pub endpoints: Vec<EndpointDrift>,
+}
+
+impl DriftReport {
+    /// Number of endpoints whose structure changed
+    pub fn drifted(&self) -> usize {
+        self.endpoints.iter().filter(|e| e.drifted()).count()
+    }
+
+    /// Number of endpoints that couldn't be fetched or compared
+    pub fn failed(&self) -> usize {
+        self.endpoints.iter().filter(|e| e.error.is_some()).count()
+    }
+
+    /// Number of endpoints whose structure is unchanged
+    pub fn unchanged(&self) -> usize {
+        self.endpoints.len() - self.drifted() - self.failed()
+    }
+}
+
+/// Parse a `--header` value like `Authorization: Bearer abc`
+pub fn parse_header(spec: &str) -> Result<(HeaderName, HeaderValue)> {
+    let invalid = |reason: &str| {
+        // The value may be a credential, so only the name is repeated back
+        let name = spec.split(':').next().unwrap_or_default().trim();
+        WebMockError::config(format!(
+            "Invalid --header '{}: ...': {}, expected e.g. 'Authorization: Bearer <token>'",
+            name, reason
+        ))
+    };
+
+    let (name, value) = spec.split_once(':').ok_or_else(|| invalid("missing ':'"))?;
+    let name = HeaderName::from_bytes(name.trim().as_bytes())
+        .map_err(|_| invalid("not a valid header name"))?;
+    let value =
+        HeaderValue::from_str(value.trim()).map_err(|_| invalid("not a valid header value"))?;
+    Ok((name, value))
+}
+
+/// Captured JSON GET responses to check, one per URL, with their parsed bodies
+pub fn json_endpoints(snapshot: &Snapshot, url_glob: Option<&GlobMatcher>) -> Vec<(String, Value)> {
+    let mut seen = HashSet::new();
+    snapshot
+        .requests
+        .iter()
+        .filter(|record| record.method.eq_ignore_ascii_case("GET"))
+        .filter(|record| record.response.is_success() && record.response.is_json())
+        .filter(|record| url_glob.is_none_or(|glob| glob.is_match(&record.url)))
+        .filter_map(|record| {
+            let body = serde_json::from_slice(&record.response.body).ok()?;
+            seen.insert(record.url.as_str())
+                .then(|| (record.url.clone(), body))
+        })
+        .collect()
+}
+
+/// Fetch the live version of each JSON endpoint of `snapshot` and compare their structure
+pub async fn check_drift(snapshot: &Snapshot, options: &DriftOptions) -> Result<DriftReport> {
+    let url_glob = options
+        .url_glob
+        .as_deref()
+        .map(|glob| {
+            Glob::new(glob)
+                .map(|glob| glob.compile_matcher())
+                .map_err(|e| WebMockError::config(format!("Invalid --url-glob '{}': {}", glob, e)))
+        })
+        .transpose()?;
+    let mut headers = HeaderMap::new();
+    for spec in &options.headers {
+        let (name, value) = parse_header(spec)?;
+        headers.append(name, value);
+    }
+
+    let client = Client::builder(hyper_util::rt::TokioExecutor::new())
+        .build(HttpClientPool::https_connector());
+    let endpoints: Vec<EndpointDrift> = stream::iter(json_endpoints(snapshot, url_glob.as_ref()))
+        .map(|(url, captured)| {
+            let client = &client;
+            let headers = &headers;
+            async move {
+                match fetch_json(client, &url, headers).await {
+                    Ok(live) => EndpointDrift {
+                        changes: compare(&captured, &live),
+                        url,
+                        error: None,
+                    },
+                    Err(error) => EndpointDrift {
+                        url,
+                        changes: Vec::new(),
+                        error: Some(error),
+                    },
+                }
+            }
+        })
+        .buffered(DRIFT_CONCURRENCY)
+        .collect()
+        .await;
+
+    Ok(DriftReport {
+        snapshot: snapshot.name.clone(),
+        endpoints,
+    })
+}
+
+/// Fetch a live JSON body, following redirects; errors are reasons to show per endpoint
+async fn fetch_json<C>(
+    client: &Client<C, Empty<Bytes>>,
+    url: &str,
+    headers: &HeaderMap,
+) -> std::result::Result<Value, String>
+where
+    C: hyper_util::client::legacy::connect::Connect + Clone + Send + Sync + 'static,
+{
+    let mut url = Url::parse(url).map_err(|e| format!("invalid URL: {}", e))?;
+    let origin = url.origin();
+    for _ in 0..=MAX_DRIFT_REDIRECTS {
+        debug!("Fetching live endpoint: {}", url);
+        let mut request = Request::get(url.as_str())
+            .header(USER_AGENT, concat!("webmock/", env!("CARGO_PKG_VERSION")))
+            .header(ACCEPT, "application/json")
+            .header(ACCEPT_ENCODING, "identity")
+            .body(Empty::new())
+            .map_err(|e| e.to_string())?;
+        // Credentials aren't handed to whatever host a redirect points at
+        if url.origin() == origin {
+            // Replaces the defaults above with the user's own, keeping repeated ones
+            request.headers_mut().extend(headers.clone());
+        }
+
+        let response = tokio::time::timeout(DRIFT_FETCH_TIMEOUT, client.request(request))
+            .await
+            .map_err(|_| "timed out".to_string())?
+            .map_err(|e| e.to_string())?;
+
+        let status = response.status();
+        if status.is_redirection() {
+            let location = response
+                .headers()
+                .get(LOCATION)
+                .and_then(|value| value.to_str().ok())
+                .ok_or_else(|| format!("{} without a Location header", status))?;
+            url = url
+                .join(location)
+                .map_err(|e| format!("invalid redirect: {}", e))?;
+            continue;
+        }
+        if !status.is_success() {
+            return Err(format!("server answered {}", status));
+        }
+
+        let body = Limited::new(response.into_body(), MAX_DRIFT_BODY_BYTES)
+            .collect()
+            .await
+            .map_err(|e| e.to_string())?
+            .to_bytes();
+        return serde_json::from_slice(&body)
+            .map_err(|e| format!("live response is not JSON: {}", e));
+    }
+
+    Err("too many redirects".to_string())
+}
+
+/// Handle the drift command
+///
+/// With `strict` set, drift fails the command after the report is printed.
+/// Endpoints that couldn't be fetched never do.
+pub async fn drift_command(
+    snapshot_name: &str,
+    storage_arg: Option<String>,
+    options: &DriftOptions,
+) -> Result<DriftReport> {
+    info!("Starting drift command for snapshot: {}", snapshot_name);
+
+    ValidationHelper::validate_snapshot_name(snapshot_name)?;
+    let storage_path = crate::commands::get_storage_path(storage_arg)?;
+    let storage = Storage::new(storage_path);
+    let snapshot = match storage.load_snapshot(snapshot_name).await {
+        Ok(snapshot) => snapshot,
+        Err(e) => {
+            if matches!(e, WebMockError::SnapshotNotFound(_)) {
+                UserFeedback::tip("Use 'webmock list' to see available snapshots");
+            }
+            return Err(e);
+        }
+    };
+
+    let report = check_drift(&snapshot, options).await?;
+    print_report(&report);
+
+    if options.strict && report.drifted() > 0 {
+        return Err(WebMockError::command_failed(format!(
+            "{} of {} endpoints of snapshot '{}' drifted from the live API",
+            report.drifted(),
+            report.endpoints.len(),
+            snapshot_name
+        )));
+    }
+    Ok(report)
+}
+
+fn print_report(report: &DriftReport) {
+    UserFeedback::section(&format!("🧭 Drift of '{}'", report.snapshot));
+    if report.endpoints.is_empty() {
+        UserFeedback::warning("No captured JSON GET responses to compare");
+        return;
+    }
+
+    for endpoint in &report.endpoints {
+        if let Some(error) = &endpoint.error {
+            println!("   ⚠️  {}", endpoint.url);
+            println!("      {}", format!("not checked: {}", error).dimmed());
+        } else if endpoint.drifted() {
+            println!("   ❌ {}", endpoint.url);
+            for change in &endpoint.changes {
+                println!("      {}", change);
+            }
+        } else {
+            println!("   ✅ {}", endpoint.url);
+        }
+    }
+
+    println!(
+        "\n   {} drifted, {} unchanged, {} not checked",
+        report.drifted(),
+        report.unchanged(),
+        report.failed()
+    );
+    if report.drifted() > 0 {
+        UserFeedback::tip(&format!(
+            "Refresh the snapshot with 'webmock recapture {}'",
+            report.snapshot
+        ));
+    }
+}
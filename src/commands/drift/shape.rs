@@ -0,0 +1,192 @@
+//! Structure of JSON documents, without their values
+//!
+//! A [`Shape`] records which kinds of value appear at each place in a
+//! document. The elements of an array are merged into one shape, so
+//! `[{"id": 1}, {"id": "a", "tag": null}]` has elements with an `id` that is
+//! a number or a string and a `tag` that is null.
+//!
+//! Null says nothing about what a field holds when it is set, so a field that
+//! is only null on one side is not reported as retyped, and neither are the
+//! fields under it. The same goes for the elements of an empty array.
+
+use std::collections::BTreeMap;
+use std::fmt;
+
+use serde_json::Value;
+
+/// Kinds of value that can appear at one place in a JSON document
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Shape {
+    pub null: bool,
+    pub boolean: bool,
+    pub number: bool,
+    pub string: bool,
+    /// Merged shape of the elements, when an array appears here
+    pub array: Option<Box<Shape>>,
+    /// Shape of each field, when an object appears here
+    pub object: Option<BTreeMap<String, Shape>>,
+}
+
+impl Shape {
+    /// Shape of a JSON value
+    pub fn of(value: &Value) -> Self {
+        let mut shape = Self::default();
+        shape.add(value);
+        shape
+    }
+
+    /// Widen the shape so it also covers `value`
+    pub fn add(&mut self, value: &Value) {
+        match value {
+            Value::Null => self.null = true,
+            Value::Bool(_) => self.boolean = true,
+            Value::Number(_) => self.number = true,
+            Value::String(_) => self.string = true,
+            Value::Array(elements) => {
+                let shape = self.array.get_or_insert_with(Default::default);
+                for element in elements {
+                    shape.add(element);
+                }
+            }
+            Value::Object(fields) => {
+                let shape = self.object.get_or_insert_with(Default::default);
+                for (name, value) in fields {
+                    shape.entry(name.clone()).or_default().add(value);
+                }
+            }
+        }
+    }
+
+    /// Names of the kinds of value, e.g. `["number", "string"]`
+    ///
+    /// Null is left out when other kinds appear too.
+    pub fn kinds(&self) -> Vec<&'static str> {
+        let kinds: Vec<&'static str> = [
+            (self.boolean, "boolean"),
+            (self.number, "number"),
+            (self.string, "string"),
+            (self.array.is_some(), "array"),
+            (self.object.is_some(), "object"),
+        ]
+        .into_iter()
+        .filter_map(|(present, kind)| present.then_some(kind))
+        .collect();
+        if kinds.is_empty() && self.null {
+            return vec!["null"];
+        }
+        kinds
+    }
+
+    /// Check whether only null, or nothing at all, was seen here
+    fn is_unknown(&self) -> bool {
+        self.kinds().iter().all(|kind| *kind == "null")
+    }
+}
+
+impl fmt::Display for Shape {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.kinds().as_slice() {
+            [] => write!(f, "nothing"),
+            kinds => write!(f, "{}", kinds.join(" or ")),
+        }
+    }
+}
+
+/// How a field differs between the captured and the live document
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChangeKind {
+    /// Only in the live document
+    Added,
+    /// Only in the captured document
+    Removed,
+    /// In both, holding different kinds of value
+    Retyped,
+}
+
+/// One structural difference, at a path like `$.users[].email`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FieldChange {
+    pub path: String,
+    pub kind: ChangeKind,
+    /// Kinds of value captured, unless the field was added
+    pub captured: Option<String>,
+    /// Kinds of value served live, unless the field was removed
+    pub live: Option<String>,
+}
+
+impl fmt::Display for FieldChange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let captured = self.captured.as_deref().unwrap_or_default();
+        let live = self.live.as_deref().unwrap_or_default();
+        match self.kind {
+            ChangeKind::Added => write!(f, "+ {} ({})", self.path, live),
+            ChangeKind::Removed => write!(f, "- {} ({})", self.path, captured),
+            ChangeKind::Retyped => write!(f, "~ {}: {} -> {}", self.path, captured, live),
+        }
+    }
+}
+
+/// Structural differences between a captured and a live JSON document, in path order
+pub fn compare(captured: &Value, live: &Value) -> Vec<FieldChange> {
+    let mut changes = Vec::new();
+    compare_shapes("$", &Shape::of(captured), &Shape::of(live), &mut changes);
+    changes
+}
+
+fn compare_shapes(path: &str, captured: &Shape, live: &Shape, changes: &mut Vec<FieldChange>) {
+    if captured.is_unknown() || live.is_unknown() {
+        return;
+    }
+
+    if captured.kinds() != live.kinds() {
+        changes.push(FieldChange {
+            path: path.to_string(),
+            kind: ChangeKind::Retyped,
+            captured: Some(captured.to_string()),
+            live: Some(live.to_string()),
+        });
+    }
+
+    if let (Some(captured), Some(live)) = (&captured.array, &live.array) {
+        compare_shapes(&format!("{}[]", path), captured, live, changes);
+    }
+
+    if let (Some(captured), Some(live)) = (&captured.object, &live.object) {
+        let mut names: Vec<&String> = captured.keys().chain(live.keys()).collect();
+        names.sort();
+        names.dedup();
+        for name in names {
+            let field = field_path(path, name);
+            match (captured.get(name), live.get(name)) {
+                (Some(captured), Some(live)) => compare_shapes(&field, captured, live, changes),
+                (Some(captured), None) => changes.push(FieldChange {
+                    path: field,
+                    kind: ChangeKind::Removed,
+                    captured: Some(captured.to_string()),
+                    live: None,
+                }),
+                (None, Some(live)) => changes.push(FieldChange {
+                    path: field,
+                    kind: ChangeKind::Added,
+                    captured: None,
+                    live: Some(live.to_string()),
+                }),
+                (None, None) => {}
+            }
+        }
+    }
+}
+
+/// Path of a field, e.g. `$.user.name`, quoting names that aren't plain identifiers
+fn field_path(parent: &str, name: &str) -> String {
+    let plain = name
+        .chars()
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_');
+    if plain {
+        format!("{}.{}", parent, name)
+    } else {
+        format!("{}[{}]", parent, Value::String(name.to_string()))
+    }
+}
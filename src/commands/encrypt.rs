@@ -0,0 +1,87 @@
+//! Encrypt and decrypt command implementation
+//!
+//! Snapshots hold whatever the captured pages sent, session tokens and
+//! personal data included. `encrypt` rewrites a snapshot sealed with a
+//! passphrase and `decrypt` stores it in the clear again. Both write the
+//! whole snapshot aside first, so it is replaced atomically.
+
+use tracing::info;
+
+use crate::error::{Result, WebMockError};
+use crate::feedback::{UserFeedback, ValidationHelper};
+use crate::storage::Storage;
+
+/// Handle the encrypt command
+pub async fn encrypt_command(snapshot_name: &str, storage_arg: Option<String>) -> Result<()> {
+    info!("Encrypting snapshot: {}", snapshot_name);
+    change_encryption_command(snapshot_name, storage_arg, true).await
+}
+
+/// Handle the decrypt command
+pub async fn decrypt_command(snapshot_name: &str, storage_arg: Option<String>) -> Result<()> {
+    info!("Decrypting snapshot: {}", snapshot_name);
+    change_encryption_command(snapshot_name, storage_arg, false).await
+}
+
+async fn change_encryption_command(
+    snapshot_name: &str,
+    storage_arg: Option<String>,
+    encrypt: bool,
+) -> Result<()> {
+    ValidationHelper::validate_snapshot_name(snapshot_name)?;
+    let storage_path = crate::commands::get_storage_path(storage_arg)?;
+    let storage = Storage::new(storage_path);
+
+    match set_encrypted(&storage, snapshot_name, encrypt).await {
+        Ok(true) if encrypt => {
+            UserFeedback::success(&format!("Snapshot '{}' encrypted", snapshot_name));
+            UserFeedback::tip("Keep the passphrase safe: it can't be recovered");
+        }
+        Ok(true) => {
+            UserFeedback::success(&format!("Snapshot '{}' decrypted", snapshot_name));
+        }
+        Ok(false) => {
+            let state = if encrypt {
+                "encrypted"
+            } else {
+                "not encrypted"
+            };
+            UserFeedback::info(&format!(
+                "Snapshot '{}' is already {}, nothing to do",
+                snapshot_name, state
+            ));
+        }
+        Err(e) => {
+            if matches!(e, WebMockError::SnapshotNotFound(_)) {
+                UserFeedback::tip("Use 'webmock list' to see available snapshots");
+            }
+            return Err(e);
+        }
+    }
+    Ok(())
+}
+
+/// Store the snapshot `name` encrypted or in the clear
+///
+/// Returns whether the snapshot was rewritten; it isn't when it is already
+/// stored the requested way. The passphrase comes from the storage.
+pub async fn set_encrypted(storage: &Storage, name: &str, encrypt: bool) -> Result<bool> {
+    if !storage.snapshot_exists(name)? {
+        return Err(WebMockError::SnapshotNotFound(name.to_string()));
+    }
+    if storage.load_snapshot_metadata(name).await?.encrypted == encrypt {
+        return Ok(false);
+    }
+    if encrypt {
+        storage.passphrase().get_new()?;
+    }
+
+    let mut snapshot = storage.load_snapshot(name).await?;
+    snapshot.encrypted = encrypt;
+
+    let staging = storage.staging();
+    let staged = staging.get_snapshot_path(name)?;
+    staging.save_snapshot(snapshot).await?;
+    storage.replace_snapshot(name, &staged, false).await?;
+    Ok(true)
+}
@@ -0,0 +1,115 @@
+//! Export command implementation
+//!
+//! Turns a snapshot into a document for other tools. The only format so far
+//! is an OpenAPI skeleton of the API calls the snapshot captured, meant as a
+//! starting point for a backend team to refine by hand.
+
+pub mod openapi;
+pub mod schema;
+
+use std::path::{Path, PathBuf};
+
+use tracing::info;
+
+use crate::error::{Result, WebMockError};
+use crate::feedback::{UserFeedback, ValidationHelper};
+use crate::storage::{Snapshot, Storage};
+
+/// Formats a snapshot can be exported in
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ExportFormat {
+    /// OpenAPI 3.0 document of the captured API calls
+    #[default]
+    OpenApi,
+}
+
+/// Options for the export command
+#[derive(Debug, Clone, Default)]
+pub struct ExportOptions {
+    pub format: ExportFormat,
+    /// File to write; stdout if unset. Written as JSON if it ends in `.json`,
+    /// YAML otherwise
+    pub output: Option<PathBuf>,
+}
+
+/// Render the export of a snapshot, as JSON for `.json` outputs and YAML otherwise
+pub fn render_export(
+    snapshot: &Snapshot,
+    format: ExportFormat,
+    output: Option<&Path>,
+) -> Result<String> {
+    let document = match format {
+        ExportFormat::OpenApi => openapi::openapi_document(snapshot),
+    };
+    let json = output
+        .and_then(Path::extension)
+        .is_some_and(|extension| extension.eq_ignore_ascii_case("json"));
+    if json {
+        serde_json::to_string_pretty(&document)
+            .map(|text| text + "\n")
+            .map_err(|e| WebMockError::config(format!("Failed to encode export as JSON: {}", e)))
+    } else {
+        serde_yaml::to_string(&document)
+            .map_err(|e| WebMockError::config(format!("Failed to encode export as YAML: {}", e)))
+    }
+}
+
+/// Handle the export command
+///
+/// Without an output file only the document is written to stdout, so it can
+/// be piped.
+pub async fn export_command(
+    snapshot_name: &str,
+    storage_arg: Option<String>,
+    options: &ExportOptions,
+) -> Result<()> {
+    info!(
+        "Exporting snapshot '{}' as {:?}",
+        snapshot_name, options.format
+    );
+
+    ValidationHelper::validate_snapshot_name(snapshot_name)?;
+    let storage_path = crate::commands::get_storage_path(storage_arg)?;
+    let storage = Storage::new(storage_path);
+    let snapshot = match storage.load_snapshot(snapshot_name).await {
+        Ok(snapshot) => snapshot,
+        Err(e) => {
+            if matches!(e, WebMockError::SnapshotNotFound(_)) {
+                UserFeedback::tip("Use 'webmock list' to see available snapshots");
+            }
+            return Err(e);
+        }
+    };
+
+    let api_records = snapshot
+        .requests
+        .iter()
+        .filter(|record| openapi::is_api_record(record))
+        .count();
+    if api_records == 0 {
+        UserFeedback::warning(&format!(
+            "Snapshot '{}' has no API calls, only page assets; the document has no paths",
+            snapshot_name
+        ));
+    }
+    let rendered = render_export(&snapshot, options.format, options.output.as_deref())?;
+
+    match &options.output {
+        Some(path) => {
+            std::fs::write(path, rendered).map_err(|e| {
+                WebMockError::config(format!("Failed to write {}: {}", path.display(), e))
+            })?;
+            UserFeedback::success(&format!(
+                "Exported {} API calls of '{}' to {}",
+                api_records,
+                snapshot_name,
+                path.display()
+            ));
+            UserFeedback::tip(
+                "Everything in it is inferred from captured traffic and marked x-webmock-inferred",
+            );
+        }
+        None => print!("{}", rendered),
+    }
+    Ok(())
+}
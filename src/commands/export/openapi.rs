@@ -0,0 +1,449 @@
+//! OpenAPI skeleton of the API surface a snapshot captured
+//!
+//! Records of API calls (anything but page assets: JSON, XML, plain text and
+//! other non-document responses, and every request that isn't a GET or HEAD)
+//! are grouped by path template, with numeric, UUID and long hexadecimal
+//! path segments collapsed into `{id}` parameters. Each path lists the
+//! methods seen on it, and each operation the status codes and content
+//! types it answered with, an example response body and a JSON schema
+//! inferred from every JSON body of that response.
+//!
+//! All of it is guessed from traffic, so every object of the document is
+//! marked `x-webmock-inferred: true`.
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use hyper::StatusCode;
+use serde::Serialize;
+use serde_json::{json, Map, Value};
+use url::Url;
+
+use crate::capture::proxy::RequestRecord;
+use crate::commands::assert::expectation::ContentCategory;
+use crate::storage::Snapshot;
+
+use super::schema::InferredSchema;
+
+/// Version of the OpenAPI specification documents are written in
+pub const OPENAPI_VERSION: &str = "3.0.3";
+
+/// Extension marking what was inferred from captured traffic
+pub const INFERRED_EXTENSION: &str = "x-webmock-inferred";
+
+/// Text examples longer than this are cut
+pub const MAX_EXAMPLE_BYTES: usize = 2048;
+
+/// Items of JSON arrays kept in examples
+pub const MAX_EXAMPLE_ITEMS: usize = 3;
+
+/// Characters of JSON strings kept in examples
+pub const MAX_EXAMPLE_STRING: usize = 200;
+
+/// Methods OpenAPI path items can describe, in the order they are written
+const METHODS: [&str; 8] = [
+    "get", "put", "post", "delete", "options", "head", "patch", "trace",
+];
+
+/// Type of the values a path parameter was seen with
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ParameterType {
+    Integer,
+    Uuid,
+    String,
+}
+
+impl ParameterType {
+    /// How a path segment looks if it is an identifier: all digits, a UUID,
+    /// or at least 16 hexadecimal characters with a digit among them
+    pub fn of(segment: &str) -> Option<Self> {
+        if !segment.is_empty() && segment.bytes().all(|b| b.is_ascii_digit()) {
+            return Some(ParameterType::Integer);
+        }
+        if is_uuid(segment) {
+            return Some(ParameterType::Uuid);
+        }
+        let hex = segment.len() >= 16
+            && segment.bytes().all(|b| b.is_ascii_hexdigit())
+            && segment.bytes().any(|b| b.is_ascii_digit());
+        hex.then_some(ParameterType::String)
+    }
+
+    /// Type of a parameter seen as both `self` and `other`
+    fn merge(self, other: Self) -> Self {
+        if self == other {
+            self
+        } else {
+            ParameterType::String
+        }
+    }
+
+    fn schema(self) -> Value {
+        match self {
+            ParameterType::Integer => json!({ "type": "integer" }),
+            ParameterType::Uuid => json!({ "type": "string", "format": "uuid" }),
+            ParameterType::String => json!({ "type": "string" }),
+        }
+    }
+}
+
+/// A path parameter standing for identifier segments
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PathParameter {
+    pub name: String,
+    pub kind: ParameterType,
+    /// Segment it replaced in the first record seen
+    pub example: String,
+}
+
+/// A URL path with its identifier segments replaced by parameters
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PathTemplate {
+    /// e.g. `/users/{id}/orders/{id2}`
+    pub path: String,
+    pub parameters: Vec<PathParameter>,
+}
+
+/// Collapse the identifier segments of `path` into parameters
+///
+/// Parameters are named `id`, `id2`, `id3`, ... from left to right, so
+/// paths of the same shape get the same template.
+pub fn template_path(path: &str) -> PathTemplate {
+    let mut parameters: Vec<PathParameter> = Vec::new();
+    let segments: Vec<String> = path
+        .trim_start_matches('/')
+        .split('/')
+        .map(|segment| match ParameterType::of(segment) {
+            Some(kind) => {
+                let name = match parameters.len() {
+                    0 => "id".to_string(),
+                    n => format!("id{}", n + 1),
+                };
+                let placeholder = format!("{{{}}}", name);
+                parameters.push(PathParameter {
+                    name,
+                    kind,
+                    example: segment.to_string(),
+                });
+                placeholder
+            }
+            None => segment.to_string(),
+        })
+        .collect();
+    PathTemplate {
+        path: format!("/{}", segments.join("/")),
+        parameters,
+    }
+}
+
+/// Whether a record is a call to an API rather than a page asset
+pub fn is_api_record(record: &RequestRecord) -> bool {
+    if record.response.is_failure() {
+        return false;
+    }
+    if !record.method.eq_ignore_ascii_case("GET") && !record.method.eq_ignore_ascii_case("HEAD") {
+        return true;
+    }
+    let category = record
+        .response
+        .get_mime_type()
+        .map_or(ContentCategory::Other, |mime| ContentCategory::of(&mime));
+    matches!(
+        category,
+        ContentCategory::Json
+            | ContentCategory::Xml
+            | ContentCategory::Text
+            | ContentCategory::Other
+    )
+}
+
+/// Responses of one status code, by content type
+#[derive(Default)]
+struct ResponseGroup {
+    records: usize,
+    content: BTreeMap<String, ContentGroup>,
+}
+
+#[derive(Default)]
+struct ContentGroup {
+    /// Folded from every JSON body; `None` for other content
+    schema: Option<InferredSchema>,
+    example: Option<Value>,
+}
+
+#[derive(Default)]
+struct OperationGroup {
+    records: usize,
+    responses: BTreeMap<u16, ResponseGroup>,
+}
+
+struct PathGroup {
+    origins: BTreeSet<String>,
+    parameters: Vec<PathParameter>,
+    /// By index into [`METHODS`]
+    operations: BTreeMap<usize, OperationGroup>,
+}
+
+/// An OpenAPI document, serialized with its top-level fields in the usual order
+#[derive(Debug, Clone, Serialize)]
+pub struct OpenApiDocument {
+    pub openapi: &'static str,
+    pub info: Value,
+    pub servers: Vec<Value>,
+    pub paths: Map<String, Value>,
+    #[serde(rename = "x-webmock-inferred")]
+    pub inferred: bool,
+}
+
+/// Build an OpenAPI 3.0 document of the snapshot's API calls
+pub fn openapi_document(snapshot: &Snapshot) -> OpenApiDocument {
+    let mut paths: BTreeMap<String, PathGroup> = BTreeMap::new();
+    for record in snapshot
+        .requests
+        .iter()
+        .filter(|record| is_api_record(record))
+    {
+        let Ok(url) = Url::parse(&record.url) else {
+            continue;
+        };
+        let method = record.method.to_ascii_lowercase();
+        let Some(method) = METHODS.iter().position(|known| *known == method) else {
+            continue;
+        };
+        let status = record.response.status;
+        if !(100..=599).contains(&status) {
+            continue;
+        }
+
+        let template = template_path(url.path());
+        let group = paths.entry(template.path).or_insert_with(|| PathGroup {
+            origins: BTreeSet::new(),
+            parameters: template.parameters.clone(),
+            operations: BTreeMap::new(),
+        });
+        group.origins.insert(url.origin().ascii_serialization());
+        for (parameter, seen) in group.parameters.iter_mut().zip(&template.parameters) {
+            parameter.kind = parameter.kind.merge(seen.kind);
+        }
+
+        let operation = group.operations.entry(method).or_default();
+        operation.records += 1;
+        let response = operation.responses.entry(status).or_default();
+        response.records += 1;
+        if record.response.body.is_empty() {
+            continue;
+        }
+        let Some(mime) = record.response.get_mime_type() else {
+            continue;
+        };
+        let content = response
+            .content
+            .entry(mime.essence_str().to_ascii_lowercase())
+            .or_default();
+        sample_body(content, &record.response.body, &mime);
+    }
+
+    let servers = servers(snapshot, &paths);
+    let primary: BTreeSet<String> = servers.first().cloned().into_iter().collect();
+    let paths: Map<String, Value> = paths
+        .into_iter()
+        .map(|(path, group)| {
+            let item = path_item(&path, group, &primary);
+            (path, item)
+        })
+        .collect();
+
+    OpenApiDocument {
+        openapi: OPENAPI_VERSION,
+        info: json!({
+            "title": format!("{} API", snapshot.name),
+            "version": snapshot.created_at.format("%Y-%m-%d").to_string(),
+            "description": format!(
+                "Inferred by webmock from the requests snapshot '{}' captured from {} on {}. \
+                 Paths, parameters and schemas are guesses from the observed traffic.",
+                snapshot.name,
+                snapshot.url,
+                snapshot.created_at.format("%Y-%m-%d %H:%M:%S UTC")
+            ),
+            INFERRED_EXTENSION: true,
+        }),
+        servers: servers
+            .iter()
+            .map(|origin| json!({ "url": origin }))
+            .collect(),
+        paths,
+        inferred: true,
+    }
+}
+
+/// Origins of the API calls, the snapshot's own origin first
+fn servers(snapshot: &Snapshot, paths: &BTreeMap<String, PathGroup>) -> Vec<String> {
+    let origins: BTreeSet<&String> = paths.values().flat_map(|group| &group.origins).collect();
+    let own = Url::parse(&snapshot.url)
+        .ok()
+        .map(|url| url.origin().ascii_serialization());
+    let mut servers: Vec<String> = own
+        .iter()
+        .filter(|own| origins.contains(own))
+        .cloned()
+        .collect();
+    servers.extend(
+        origins
+            .into_iter()
+            .filter(|origin| Some(*origin) != own.as_ref())
+            .cloned(),
+    );
+    servers
+}
+
+fn path_item(path: &str, group: PathGroup, primary: &BTreeSet<String>) -> Value {
+    let mut item = Map::new();
+    // Paths only seen on other origins than the document's first server say where they live
+    if &group.origins != primary {
+        item.insert(
+            "servers".to_string(),
+            group
+                .origins
+                .iter()
+                .map(|origin| json!({ "url": origin }))
+                .collect::<Vec<_>>()
+                .into(),
+        );
+    }
+    if !group.parameters.is_empty() {
+        item.insert(
+            "parameters".to_string(),
+            group
+                .parameters
+                .iter()
+                .map(|parameter| {
+                    let example = match parameter.kind {
+                        ParameterType::Integer => parameter
+                            .example
+                            .parse::<u64>()
+                            .map_or_else(|_| json!(parameter.example), |id| json!(id)),
+                        _ => json!(parameter.example),
+                    };
+                    json!({
+                        "name": parameter.name,
+                        "in": "path",
+                        "required": true,
+                        "schema": parameter.kind.schema(),
+                        "example": example,
+                        INFERRED_EXTENSION: true,
+                    })
+                })
+                .collect::<Vec<_>>()
+                .into(),
+        );
+    }
+    for (method, operation) in group.operations {
+        let name = METHODS[method];
+        let responses: Map<String, Value> = operation
+            .responses
+            .into_iter()
+            .map(|(status, response)| (status.to_string(), response_object(status, response)))
+            .collect();
+        item.insert(
+            name.to_string(),
+            json!({
+                "summary": format!("{} {}", name.to_ascii_uppercase(), path),
+                "description": format!("Captured {}.", times(operation.records)),
+                "responses": responses,
+                INFERRED_EXTENSION: true,
+            }),
+        );
+    }
+    item.insert(INFERRED_EXTENSION.to_string(), Value::Bool(true));
+    Value::Object(item)
+}
+
+fn response_object(status: u16, response: ResponseGroup) -> Value {
+    let reason = StatusCode::from_u16(status)
+        .ok()
+        .and_then(|status| status.canonical_reason())
+        .unwrap_or("Response");
+    let mut object = json!({
+        "description": format!("{}, captured {}", reason, times(response.records)),
+        INFERRED_EXTENSION: true,
+    });
+    if !response.content.is_empty() {
+        let content: Map<String, Value> = response
+            .content
+            .into_iter()
+            .map(|(content_type, content)| {
+                let mut media = Map::new();
+                if let Some(schema) = content.schema {
+                    let mut schema = schema.to_json();
+                    schema[INFERRED_EXTENSION] = Value::Bool(true);
+                    media.insert("schema".to_string(), schema);
+                }
+                if let Some(example) = content.example {
+                    media.insert("example".to_string(), example);
+                }
+                media.insert(INFERRED_EXTENSION.to_string(), Value::Bool(true));
+                (content_type, Value::Object(media))
+            })
+            .collect();
+        object["content"] = Value::Object(content);
+    }
+    object
+}
+
+/// Fold a response body into the schema and example of its content type
+fn sample_body(content: &mut ContentGroup, body: &[u8], mime: &mime::Mime) {
+    if ContentCategory::of(mime) == ContentCategory::Json {
+        let Ok(value) = serde_json::from_slice::<Value>(body) else {
+            return;
+        };
+        content
+            .schema
+            .get_or_insert_with(InferredSchema::default)
+            .sample(&value);
+        if content.example.is_none() {
+            content.example = Some(truncate_example(&value));
+        }
+    } else if content.example.is_none() {
+        if let Some(text) = crate::commands::inspect::body_preview(body, Some(MAX_EXAMPLE_BYTES)) {
+            content.example = Some(Value::String(text.into_owned()));
+        }
+    }
+}
+
+/// Shorten a JSON example: arrays keep their first items and long strings are cut
+pub fn truncate_example(value: &Value) -> Value {
+    match value {
+        Value::Array(items) => Value::Array(
+            items
+                .iter()
+                .take(MAX_EXAMPLE_ITEMS)
+                .map(truncate_example)
+                .collect(),
+        ),
+        Value::Object(fields) => Value::Object(
+            fields
+                .iter()
+                .map(|(name, field)| (name.clone(), truncate_example(field)))
+                .collect(),
+        ),
+        Value::String(text) if text.chars().count() > MAX_EXAMPLE_STRING => {
+            let cut: String = text.chars().take(MAX_EXAMPLE_STRING).collect();
+            Value::String(format!("{}…", cut))
+        }
+        other => other.clone(),
+    }
+}
+
+fn times(count: usize) -> String {
+    match count {
+        1 => "once".to_string(),
+        n => format!("{} times", n),
+    }
+}
+
+fn is_uuid(segment: &str) -> bool {
+    segment.len() == 36
+        && segment.char_indices().all(|(i, c)| match i {
+            8 | 13 | 18 | 23 => c == '-',
+            _ => c.is_ascii_hexdigit(),
+        })
+}
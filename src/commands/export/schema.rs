@@ -0,0 +1,172 @@
+//! JSON schemas inferred from sample values
+//!
+//! Every sampled body is folded into one [`InferredSchema`]: object
+//! properties are the union of those seen, and a property is required when
+//! every sampled object had it. Integers and other numbers merge into
+//! `number`, `null` makes a schema `nullable`, and values of conflicting
+//! types leave the schema open (`{}`). The result uses the OpenAPI 3.0
+//! dialect of JSON Schema, which has `nullable` instead of a `null` type.
+
+use std::collections::BTreeMap;
+
+use serde_json::{json, Map, Value};
+
+/// Items of one array sampled, so a long list isn't walked element by element
+pub const MAX_ARRAY_SAMPLES: usize = 20;
+
+/// Nesting below which values are left untyped
+pub const MAX_DEPTH: usize = 16;
+
+/// Structure shared by the values sampled so far
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct InferredSchema {
+    shape: Shape,
+    nullable: bool,
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+enum Shape {
+    /// Nothing but nulls seen yet
+    #[default]
+    Unseen,
+    Boolean,
+    Integer,
+    Number,
+    String,
+    Array(Box<InferredSchema>),
+    Object {
+        /// Objects sampled
+        samples: usize,
+        /// Samples each property was seen in, and its schema
+        properties: BTreeMap<String, (usize, InferredSchema)>,
+    },
+    /// Values of conflicting types
+    Any,
+}
+
+impl InferredSchema {
+    /// Infer the schema of a single value
+    pub fn of(value: &Value) -> Self {
+        let mut schema = Self::default();
+        schema.sample(value);
+        schema
+    }
+
+    /// Fold another sample into the schema
+    pub fn sample(&mut self, value: &Value) {
+        self.sample_at(value, 0);
+    }
+
+    fn sample_at(&mut self, value: &Value, depth: usize) {
+        if depth >= MAX_DEPTH {
+            self.shape = Shape::Any;
+            return;
+        }
+        let shape = match value {
+            Value::Null => {
+                self.nullable = true;
+                return;
+            }
+            Value::Bool(_) => Shape::Boolean,
+            Value::Number(number) if number.is_i64() || number.is_u64() => Shape::Integer,
+            Value::Number(_) => Shape::Number,
+            Value::String(_) => Shape::String,
+            Value::Array(items) => {
+                let mut item_schema = match &mut self.shape {
+                    Shape::Array(existing) => std::mem::take(existing.as_mut()),
+                    _ => InferredSchema::default(),
+                };
+                for item in items.iter().take(MAX_ARRAY_SAMPLES) {
+                    item_schema.sample_at(item, depth + 1);
+                }
+                Shape::Array(Box::new(item_schema))
+            }
+            Value::Object(fields) => {
+                let (mut samples, mut properties) = match std::mem::take(&mut self.shape) {
+                    Shape::Object {
+                        samples,
+                        properties,
+                    } => (samples, properties),
+                    other => {
+                        self.shape = other;
+                        (0, BTreeMap::new())
+                    }
+                };
+                samples += 1;
+                for (name, field) in fields {
+                    let (seen, schema) = properties.entry(name.clone()).or_default();
+                    *seen += 1;
+                    schema.sample_at(field, depth + 1);
+                }
+                Shape::Object {
+                    samples,
+                    properties,
+                }
+            }
+        };
+        self.shape = merge(std::mem::take(&mut self.shape), shape);
+    }
+
+    /// The schema as an OpenAPI 3.0 schema object
+    pub fn to_json(&self) -> Value {
+        let mut schema = match &self.shape {
+            Shape::Unseen | Shape::Any => Map::new(),
+            Shape::Boolean => type_only("boolean"),
+            Shape::Integer => type_only("integer"),
+            Shape::Number => type_only("number"),
+            Shape::String => type_only("string"),
+            Shape::Array(items) => {
+                let mut schema = type_only("array");
+                schema.insert("items".to_string(), items.to_json());
+                schema
+            }
+            Shape::Object {
+                samples,
+                properties,
+            } => {
+                let mut schema = type_only("object");
+                let required: Vec<&String> = properties
+                    .iter()
+                    .filter(|(_, (seen, _))| seen == samples)
+                    .map(|(name, _)| name)
+                    .collect();
+                schema.insert(
+                    "properties".to_string(),
+                    properties
+                        .iter()
+                        .map(|(name, (_, property))| (name.clone(), property.to_json()))
+                        .collect::<Map<_, _>>()
+                        .into(),
+                );
+                // OpenAPI 3.0 doesn't allow an empty `required` list
+                if !required.is_empty() {
+                    schema.insert("required".to_string(), json!(required));
+                }
+                schema
+            }
+        };
+        if self.nullable {
+            schema.insert("nullable".to_string(), Value::Bool(true));
+        }
+        Value::Object(schema)
+    }
+}
+
+/// Shape of values seen as both `existing` and `new`
+fn merge(existing: Shape, new: Shape) -> Shape {
+    match (existing, new) {
+        (Shape::Unseen, shape) | (shape, Shape::Unseen) => shape,
+        (Shape::Integer, Shape::Number) | (Shape::Number, Shape::Integer) => Shape::Number,
+        // Arrays and objects were already folded together while sampling
+        (Shape::Array(_), shape @ Shape::Array(_)) => shape,
+        (Shape::Object { .. }, shape @ Shape::Object { .. }) => shape,
+        (existing, new) if existing == new => existing,
+        _ => Shape::Any,
+    }
+}
+
+fn type_only(name: &str) -> Map<String, Value> {
+    let mut schema = Map::new();
+    schema.insert("type".to_string(), Value::String(name.to_string()));
+    schema
+}
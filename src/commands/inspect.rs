@@ -1,44 +1,258 @@
+use crate::capture::browser::initiators::normalize_url;
+use crate::capture::browser::{PageEvent, PageEventLevel, PageEventSource};
+use crate::capture::canonical_url::{canonical_url, display_url};
+use crate::capture::proxy::records::RequestRecord;
+use crate::capture::proxy::CaptureDiagnostics;
+use crate::capture::Provenance;
 use crate::error::{Result, WebMockError};
-use crate::feedback::UserFeedback;
-use crate::storage::Storage;
+use crate::feedback::{format_date, UserFeedback};
+use crate::storage::{
+    ArtifactFile, ArtifactKind, CaptureArtifacts, Snapshot, Storage, TrimmedRecord,
+};
+use chrono::{DateTime, Utc};
+use colored::Colorize;
+use serde::Serialize;
 
+use std::borrow::Cow;
 use std::collections::HashMap;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+/// Default `--max-body-bytes`: bodies printed by inspect are cut after this many bytes
+pub const DEFAULT_MAX_BODY_BYTES: usize = 1024 * 1024;
+
+/// Trimmed records listed in the overview; the JSON report has all of them
+const TRIMMED_SHOWN: usize = 10;
+
+/// Which record of a snapshot to look at on its own
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RecordSelector {
+    /// Position in the numbered list inspect prints, starting at 1
+    Index(usize),
+    /// Exact recorded URL; must match a single record
+    Url(String),
+}
+
+/// Options for the inspect command
+#[derive(Debug, Clone, Default)]
+pub struct InspectOptions {
+    /// Show upstream HTTP versions and TLS details
+    pub detailed: bool,
+    /// Show only this record instead of the whole listing
+    pub record: Option<RecordSelector>,
+    /// Write the selected record's raw response body here; `-` is stdout
+    pub dump_body: Option<PathBuf>,
+    /// Write the selected record's raw request body here; `-` is stdout
+    pub dump_request_body: Option<PathBuf>,
+    /// Print the selected record's request and response headers
+    pub show_headers: bool,
+    /// Print the console messages and page errors logged during capture
+    pub console: bool,
+    /// Print the capture proxy's connection-level failures
+    pub diagnostics: bool,
+    /// Print the selected record's text bodies
+    pub show_body: bool,
+    /// Cut printed bodies after this many bytes; `None` prints them whole.
+    /// Dumped bodies are never cut.
+    pub max_body_bytes: Option<usize>,
+    /// Print an [`InspectReport`] as JSON instead of the listing
+    pub json: bool,
+}
+
+impl InspectOptions {
+    /// Check whether a body is dumped to stdout, leaving no room for other output
+    fn dumps_to_stdout(&self) -> bool {
+        [&self.dump_body, &self.dump_request_body]
+            .into_iter()
+            .flatten()
+            .any(|path| is_stdout(path))
+    }
+}
 
 /// Handle the inspect command to view all records in a snapshot
-pub async fn inspect_command(snapshot_name: &str, storage_arg: Option<String>) -> Result<()> {
-    UserFeedback::info(&format!("🔍 Inspecting snapshot: {}", snapshot_name));
+///
+/// With `detailed`, each record also shows the upstream HTTP version and the
+/// TLS parameters negotiated with every HTTPS host are listed. Hosts whose
+/// traffic could not be recorded because of certificate pinning are always
+/// listed. With a record selected, only that record is shown and its bodies
+/// can be written out byte for byte.
+pub async fn inspect_command(
+    snapshot_name: &str,
+    storage_arg: Option<String>,
+    options: &InspectOptions,
+) -> Result<()> {
+    let detailed = options.detailed;
+    let quiet = options.json || options.dumps_to_stdout();
+    if !quiet {
+        UserFeedback::info(&format!("🔍 Inspecting snapshot: {}", snapshot_name));
+    }
 
     // Initialize storage
     let storage_path = crate::commands::get_storage_path(storage_arg)?;
     let storage = Storage::new(storage_path);
 
     // Load the snapshot
-    let snapshot = storage
+    let mut snapshot = storage
         .load_snapshot(snapshot_name)
         .await
         .map_err(|_| WebMockError::SnapshotNotFound(snapshot_name.to_string()))?;
 
+    if options.json {
+        let output = serde_json::to_string_pretty(&InspectReport::new(&snapshot)).map_err(|e| {
+            WebMockError::config(format!("Failed to encode snapshot as JSON: {}", e))
+        })?;
+        println!("{}", output);
+        return Ok(());
+    }
+
+    if let Some(selector) = &options.record {
+        let mut requests = std::mem::take(&mut snapshot.requests);
+        sort_for_listing(&mut requests);
+        let (index, record) = select_record(&requests, selector)?;
+        return inspect_record(index, record, snapshot.annotation(record), options);
+    }
+
     UserFeedback::success(&format!("✅ Loaded snapshot: {}", snapshot_name));
 
     // Display snapshot summary
     println!();
     println!("📋 Snapshot Overview");
     println!("   📍 Original URL: {}", snapshot.url);
+    if let Some(readable) = readable_url(&snapshot.url) {
+        println!("      {}", readable.dimmed());
+    }
+    let entry_points = snapshot.entry_points();
+    if entry_points.len() > 1 {
+        println!("   🚪 Other entry points:");
+        for entry_point in &entry_points[1..] {
+            println!("      {}", entry_point);
+        }
+    }
     println!("   📊 Total records: {}", snapshot.requests.len());
-    println!(
-        "   📅 Created: {}",
-        snapshot.created_at.format("%Y-%m-%d %H:%M:%S UTC")
-    );
+    println!("   📅 Created: {}", format_date(snapshot.created_at));
+    if snapshot.encrypted {
+        println!("   🔒 Encrypted at rest");
+    }
+    if !snapshot.annotations.is_empty() {
+        println!(
+            "   📝 Notes on {} records (change them with webmock annotate)",
+            snapshot.annotations.len()
+        );
+    }
+    if let Some(locale) = &snapshot.environment.locale {
+        println!("   🗣️  Locale: {}", locale);
+    }
+    if let Some(timezone) = &snapshot.environment.timezone {
+        println!("   🕒 Timezone: {}", timezone);
+    }
+    if let Some(viewport) = snapshot.environment.viewport {
+        println!("   🖥️  Viewport: {}", viewport);
+    }
+    if let Some(device) = &snapshot.environment.device {
+        println!("   📱 Device: {}", device);
+    }
+    if let Some(user_agent) = &snapshot.environment.user_agent {
+        println!("   🕵️  User-Agent: {}", user_agent);
+    }
+    if let Some(split_from) = &snapshot.split_from {
+        println!(
+            "   🪓 Split from '{}': {}",
+            split_from.snapshot, split_from.filter
+        );
+    }
+    if !snapshot.trimmed.is_empty() {
+        display_trimmed(&snapshot.trimmed);
+    }
+    match &snapshot.provenance {
+        Some(provenance) => display_provenance(provenance),
+        None => println!("   📜 No provenance (saved before webmock recorded it)"),
+    }
+    if !snapshot.serve_defaults.is_empty() {
+        println!(
+            "   ⚙️  Serve defaults: {}",
+            snapshot.serve_defaults.flags().join(" ")
+        );
+    }
+    let variants = crate::serve::snapshot_variants(&snapshot);
+    if !variants.is_empty() {
+        let tagged = snapshot
+            .requests
+            .iter()
+            .filter(|record| record.variant.is_some())
+            .count();
+        println!(
+            "   🎭 Variants: {} ({} tagged records, serve one with --variant)",
+            variants.join(", "),
+            tagged
+        );
+    }
+    if let Some(metrics) = &snapshot.metrics {
+        println!(
+            "   ⏱️  Captured in {:.1}s: {} requests, {:.1}/s",
+            metrics.capture_duration_ms as f64 / 1000.0,
+            metrics.requests_processed,
+            metrics.requests_per_second
+        );
+    }
+    if !snapshot.artifacts.is_empty() {
+        let files = storage.artifact_files(snapshot_name).await?;
+        display_artifacts(&snapshot.artifacts, &files);
+    }
+    if snapshot.skipped_requests > 0 {
+        println!(
+            "   ✂️  Request cap reached: {} later requests were not recorded",
+            snapshot.skipped_requests
+        );
+    }
+    if !snapshot.blocked_hosts.is_empty() {
+        println!(
+            "   🚫 Blocked by --only-hosts: {} requests to {} hosts",
+            snapshot.blocked_hosts.values().sum::<u64>(),
+            snapshot.blocked_hosts.len()
+        );
+    }
+    if snapshot.retried_requests > 0 {
+        println!(
+            "   🔁 {} requests needed upstream retries",
+            snapshot.retried_requests
+        );
+    }
+    let page_errors = snapshot
+        .page_events
+        .iter()
+        .filter(|event| event.level == PageEventLevel::Error)
+        .count();
+    if !snapshot.page_events.is_empty() && !options.console {
+        println!(
+            "   🧾 Console: {} messages, {} errors (show them with --console)",
+            snapshot.page_events.len(),
+            page_errors
+        );
+    }
+    let failures = snapshot.capture_diagnostics.failures();
+    if failures > 0 && !options.diagnostics {
+        println!(
+            "   🔌 Connection failures during capture: {} (show them with --diagnostics)",
+            failures
+        );
+    }
     println!();
 
+    if options.console {
+        display_page_events(&snapshot.page_events);
+    }
+    if options.diagnostics {
+        display_capture_diagnostics(&snapshot.capture_diagnostics);
+    }
+
     if snapshot.requests.is_empty() {
         UserFeedback::warning("No records found in this snapshot");
         return Ok(());
     }
 
     // Sort records by URL for better readability
-    let mut requests = snapshot.requests;
-    requests.sort_by(|a, b| a.url.cmp(&b.url));
+    let mut requests = std::mem::take(&mut snapshot.requests);
+    sort_for_listing(&mut requests);
 
     println!("📄 Detailed Records:");
     println!();
@@ -61,38 +275,109 @@ pub async fn inspect_command(snapshot_name: &str, storage_arg: Option<String>) -
             extract_content_type(&record.response.headers).unwrap_or_else(|| "unknown".to_string());
         let size = format_size(record.response.body.len());
 
-        // Color coding for terminal output
-        let method_color = match method.as_str() {
-            "GET" => "\x1b[32m",     // Green
-            "POST" => "\x1b[34m",    // Blue
-            "PUT" => "\x1b[33m",     // Yellow
-            "DELETE" => "\x1b[31m",  // Red
-            "CONNECT" => "\x1b[35m", // Magenta
-            "PATCH" => "\x1b[36m",   // Cyan
-            _ => "\x1b[0m",          // Reset
+        // Color coding for terminal output; padded first so the columns line up
+        let method_cell = format!("{:<8}", method);
+        let method_cell = match method.as_str() {
+            "GET" => method_cell.green(),
+            "POST" => method_cell.blue(),
+            "PUT" => method_cell.yellow(),
+            "DELETE" => method_cell.red(),
+            "CONNECT" => method_cell.magenta(),
+            "PATCH" => method_cell.cyan(),
+            _ => method_cell.normal(),
         };
 
-        let status_color = if (200..300).contains(&status) {
-            "\x1b[32m" // Green
-        } else if (300..400).contains(&status) {
-            "\x1b[33m" // Yellow
-        } else if (400..600).contains(&status) {
-            "\x1b[31m" // Red
-        } else {
-            "\x1b[0m" // Reset
+        let status_paint = |text: String| {
+            if (200..300).contains(&status) {
+                text.green()
+            } else if (300..400).contains(&status) {
+                text.yellow()
+            } else if (400..600).contains(&status) {
+                text.red()
+            } else {
+                text.normal()
+            }
         };
 
-        let reset = "\x1b[0m";
-
         print!("{:<4} ", index + 1);
-        print!("{}{:<8}{}", method_color, method, reset);
+        print!("{}", method_cell);
         print!(" {:<47}", url);
-        print!(" {}{}{}", status_color, status, reset);
+        print!(" {}", status_paint(status.to_string()));
         print!(" {:<15}", content_type);
         println!(" {:<10}", size);
+
+        let cookies = record.response.set_cookies();
+        if cookies.len() > 1 {
+            let names: Vec<&str> = cookies
+                .iter()
+                .map(|cookie| cookie.split('=').next().unwrap_or(cookie).trim())
+                .collect();
+            println!(
+                "     ↳ sets {} cookies: {}",
+                cookies.len(),
+                names.join(", ")
+            );
+        }
+
+        if let Some(variant) = &record.variant {
+            println!("     ↳ variant: {}", variant);
+        }
+
+        if let Some(note) = snapshot.annotation(record) {
+            println!("     {}", format!("↳ note: {}", note).dimmed());
+        }
+
+        if detailed {
+            if let Some(version) = &record.response.http_version {
+                println!("     ↳ {}", version);
+            }
+        }
+
+        if record.response.reason.is_some() {
+            println!("     ↳ status line: {}", record.response.status_text());
+        }
+
+        if let Some(reason) = &record.response.failure {
+            println!(
+                "     {}",
+                status_paint(format!("↳ failed during capture: {}", reason))
+            );
+        }
     }
 
     println!();
+    if detailed {
+        display_initiator_tree(&requests);
+    }
+    display_frames(&requests);
+    if detailed && !snapshot.tls.is_empty() {
+        println!("🔐 TLS Connections:");
+        for (host, details) in &snapshot.tls {
+            println!(
+                "   {} — {}, {}, ALPN: {}",
+                host,
+                details.protocol_version.as_deref().unwrap_or("unknown"),
+                details.cipher_suite.as_deref().unwrap_or("unknown"),
+                details.alpn.as_deref().unwrap_or("none")
+            );
+        }
+        println!();
+    }
+    let pinned: Vec<_> = snapshot
+        .tunnels
+        .iter()
+        .filter(|tunnel| tunnel.mitm_failed)
+        .collect();
+    if !pinned.is_empty() {
+        println!("📌 Certificate-pinned hosts (traffic not recorded):");
+        for tunnel in pinned {
+            println!(
+                "   {} — {} bytes up, {} bytes down",
+                tunnel.host, tunnel.bytes_up, tunnel.bytes_down
+            );
+        }
+        println!();
+    }
     display_summary_stats(&requests);
 
     UserFeedback::success("Inspection completed successfully!");
@@ -100,6 +385,584 @@ pub async fn inspect_command(snapshot_name: &str, storage_arg: Option<String>) -
     Ok(())
 }
 
+/// Print the console messages and page errors recorded during capture
+/// List the screenshot and DOM saved with the snapshot, noting any that went missing
+fn display_artifacts(artifacts: &CaptureArtifacts, files: &[ArtifactFile]) {
+    for kind in ArtifactKind::ALL {
+        let Some(file_name) = artifacts.get(kind) else {
+            continue;
+        };
+        let icon = match kind {
+            ArtifactKind::Screenshot => "📸",
+            ArtifactKind::Dom => "🧱",
+        };
+        match files.iter().find(|file| file.kind == kind) {
+            Some(file) => println!(
+                "   {} {}: {} ({})",
+                icon,
+                kind.label(),
+                file.path.display(),
+                format_size(file.size as usize)
+            ),
+            None => println!("   {} {}: {} (missing)", icon, kind.label(), file_name),
+        }
+    }
+}
+
+/// Print how and where the snapshot was captured
+fn display_provenance(provenance: &Provenance) {
+    let host = provenance
+        .hostname
+        .as_deref()
+        .map(|hostname| format!(" on {}", hostname))
+        .unwrap_or_default();
+    println!(
+        "   📜 Captured with webmock {} ({}){}",
+        provenance.webmock_version, provenance.os, host
+    );
+    if !provenance.invocation.is_empty() {
+        println!("      {}", provenance.command_line().dimmed());
+    }
+    if let Some(note) = &provenance.consent_note {
+        println!("   ✍️  Consent: {}", note);
+    }
+}
+
+/// Print the records `webmock trim` removed, most recent trim last
+fn display_trimmed(trimmed: &[TrimmedRecord]) {
+    println!(
+        "   ✂️  Trimmed {} unused record(s) (webmock trim):",
+        trimmed.len()
+    );
+    for record in trimmed.iter().take(TRIMMED_SHOWN) {
+        let hits = match record.hits {
+            Some(hits) => format!("served {}x", hits),
+            None => "not in coverage report".to_string(),
+        };
+        println!(
+            "      {} {} {}",
+            record.method,
+            record.url,
+            format!("({}, {})", hits, format_date(record.trimmed_at)).dimmed()
+        );
+    }
+    if trimmed.len() > TRIMMED_SHOWN {
+        println!("      ... and {} more", trimmed.len() - TRIMMED_SHOWN);
+    }
+}
+
+/// Print the connection-level bookkeeping of the capture proxy
+fn display_capture_diagnostics(diagnostics: &CaptureDiagnostics) {
+    if diagnostics.is_empty() {
+        println!("🔌 No capture diagnostics were kept (captured with an older version?)");
+        println!();
+        return;
+    }
+
+    println!("🔌 Capture diagnostics:");
+    println!(
+        "   Client connections accepted: {}",
+        diagnostics.accepted_connections
+    );
+    let lines = diagnostics.summary_lines();
+    if lines.is_empty() {
+        println!("   No connection failures");
+    }
+    for line in lines {
+        println!("   {}", line);
+    }
+    if !diagnostics.recent_errors.is_empty() {
+        println!("   Latest errors:");
+        for event in &diagnostics.recent_errors {
+            println!(
+                "   {:>8.1}s  {:<18}  {}: {}",
+                event.offset_ms as f64 / 1000.0,
+                event.kind,
+                event.target,
+                event.error
+            );
+        }
+    }
+    println!();
+}
+
+fn display_page_events(events: &[PageEvent]) {
+    if events.is_empty() {
+        println!("🧾 No console messages or page errors were recorded");
+        println!();
+        return;
+    }
+
+    println!("🧾 Console ({} messages):", events.len());
+    for event in events {
+        let source = match event.source {
+            PageEventSource::Console => "",
+            PageEventSource::Exception => " uncaught",
+        };
+        let level = format!("{:<7}{}", event.level, source);
+        let (icon, level) = match event.level {
+            PageEventLevel::Error => ("❌", level.red()),
+            PageEventLevel::Warning => ("⚠️ ", level.yellow()),
+            PageEventLevel::Info => ("  ", level.normal()),
+            PageEventLevel::Debug => ("  ", level.dimmed()),
+        };
+        println!(
+            "   {} {} {} {}",
+            icon,
+            event.timestamp.format("%H:%M:%S%.3f"),
+            level,
+            event.message
+        );
+        if let Some(location) = &event.location {
+            println!("        at {}", location);
+        }
+    }
+    println!();
+}
+
+/// Print which record triggered which, if any record knows its initiator
+fn display_initiator_tree(requests: &[RequestRecord]) {
+    if requests.iter().all(|record| record.initiator.is_none()) {
+        return;
+    }
+
+    println!("🌳 Initiators:");
+    for line in initiator_tree(requests) {
+        println!("   {}", line);
+    }
+    println!();
+}
+
+/// Lay out records as a tree of listing numbers by initiator
+///
+/// A record hangs under the record of the URL its initiator names; records
+/// whose initiator wasn't captured, or is unknown, are roots.
+pub(crate) fn initiator_tree(requests: &[RequestRecord]) -> Vec<String> {
+    let mut by_url: HashMap<String, usize> = HashMap::new();
+    for (index, record) in requests.iter().enumerate() {
+        by_url.entry(normalize_url(&record.url)).or_insert(index);
+    }
+    let parents: Vec<Option<usize>> = requests
+        .iter()
+        .enumerate()
+        .map(|(index, record)| {
+            let url = record.initiator.as_ref()?.url.as_deref()?;
+            by_url
+                .get(&normalize_url(url))
+                .copied()
+                .filter(|&parent| parent != index)
+        })
+        .collect();
+    let mut children: Vec<Vec<usize>> = vec![Vec::new(); requests.len()];
+    for (index, parent) in parents.iter().enumerate() {
+        if let Some(parent) = parent {
+            children[*parent].push(index);
+        }
+    }
+
+    let mut lines = Vec::new();
+    let mut printed = vec![false; requests.len()];
+    let roots = (0..requests.len()).filter(|&index| parents[index].is_none());
+    // Records caught in an initiator cycle have no root, so they start their own tree
+    for root in roots.chain(0..requests.len()) {
+        let mut stack = vec![(root, 0usize)];
+        while let Some((index, depth)) = stack.pop() {
+            if printed[index] {
+                continue;
+            }
+            printed[index] = true;
+
+            let record = &requests[index];
+            let indent = if depth == 0 {
+                String::new()
+            } else {
+                format!("{}└ ", "  ".repeat(depth - 1))
+            };
+            let how = match &record.initiator {
+                Some(initiator) => match initiator.line {
+                    Some(line) if parents[index].is_some() => {
+                        format!(" ({}, line {})", initiator.kind, line)
+                    }
+                    _ if parents[index].is_some() => format!(" ({})", initiator.kind),
+                    _ => format!(" ({})", initiator),
+                },
+                None => String::new(),
+            };
+            lines.push(format!(
+                "{}#{} {} {}{}",
+                indent,
+                index + 1,
+                record.method,
+                truncate_url(&record.url, 60),
+                how
+            ));
+
+            for &child in children[index].iter().rev() {
+                stack.push((child, depth + 1));
+            }
+        }
+    }
+    lines
+}
+
+/// Print the records of each frame, if they came from more than one
+fn display_frames(requests: &[RequestRecord]) {
+    let groups = frame_groups(requests);
+    if groups.len() < 2 {
+        return;
+    }
+
+    println!("🖼️  Frames ({}):", groups.len());
+    for line in groups {
+        println!("   {}", line);
+    }
+    println!();
+}
+
+/// Lay out the listing numbers of records by the frame that requested them
+///
+/// Frames are listed in the order of their first request and named by its
+/// URL, which is the frame's document unless that came from the browser
+/// cache. The frame of the earliest request is the main frame. Records
+/// Chrome named no frame for are left out.
+pub(crate) fn frame_groups(requests: &[RequestRecord]) -> Vec<String> {
+    let mut order: Vec<usize> = (0..requests.len()).collect();
+    order.sort_by_key(|&index| (requests[index].sequence.unwrap_or(u64::MAX), index));
+
+    let mut frames: Vec<(&str, Vec<usize>)> = Vec::new();
+    for index in order {
+        let Some(frame_id) = requests[index].frame_id.as_deref() else {
+            continue;
+        };
+        match frames.iter_mut().find(|(id, _)| *id == frame_id) {
+            Some((_, records)) => records.push(index),
+            None => frames.push((frame_id, vec![index])),
+        }
+    }
+
+    frames
+        .into_iter()
+        .enumerate()
+        .map(|(position, (_, mut records))| {
+            let first = &requests[records[0]];
+            let kind = if position == 0 { "main frame" } else { "frame" };
+            records.sort_unstable();
+            let numbers: Vec<String> = records
+                .iter()
+                .map(|index| format!("#{}", index + 1))
+                .collect();
+            format!(
+                "{} {} — {}",
+                kind,
+                truncate_url(&first.url, 60),
+                numbers.join(", ")
+            )
+        })
+        .collect()
+}
+
+/// Snapshot overview and records, as `inspect --json` prints them
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct InspectReport {
+    pub name: String,
+    pub url: String,
+    pub created_at: DateTime<Utc>,
+    /// Every entry point, `url` first
+    pub entry_points: Vec<String>,
+    pub encrypted: bool,
+    /// Where and how the snapshot was captured, if it was recorded
+    pub provenance: Option<Provenance>,
+    /// Records removed by `webmock trim`
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub trimmed: Vec<TrimmedRecord>,
+    /// Records in listing order
+    pub records: Vec<RecordReport>,
+}
+
+/// One record of an [`InspectReport`]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct RecordReport {
+    /// Number in the listing, as `--request` and `webmock annotate` take it
+    pub index: usize,
+    pub method: String,
+    pub url: String,
+    /// `url` with a Unicode host and unescaped non-ASCII characters, when that differs
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub display_url: Option<String>,
+    pub status: u16,
+    pub content_type: Option<String>,
+    /// Response body size in bytes
+    pub size: usize,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub variant: Option<String>,
+    /// Note left by `webmock annotate`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub note: Option<String>,
+    /// Chrome's ID of the frame that made the request
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub frame_id: Option<String>,
+}
+
+impl InspectReport {
+    pub fn new(snapshot: &Snapshot) -> Self {
+        let mut requests: Vec<&RequestRecord> = snapshot.requests.iter().collect();
+        requests.sort_by(|a, b| a.url.cmp(&b.url));
+        let records = requests
+            .into_iter()
+            .enumerate()
+            .map(|(index, record)| RecordReport {
+                index: index + 1,
+                method: record.method.clone(),
+                url: record.url.clone(),
+                display_url: readable_url(&record.url),
+                status: record.response.status,
+                content_type: extract_content_type(&record.response.headers),
+                size: record.response.body.len(),
+                variant: record.variant.clone(),
+                note: snapshot.annotation(record).map(str::to_string),
+                frame_id: record.frame_id.clone(),
+            })
+            .collect();
+
+        InspectReport {
+            name: snapshot.name.clone(),
+            url: snapshot.url.clone(),
+            created_at: snapshot.created_at,
+            entry_points: snapshot.entry_points(),
+            encrypted: snapshot.encrypted,
+            provenance: snapshot.provenance.clone(),
+            trimmed: snapshot.trimmed.clone(),
+            records,
+        }
+    }
+}
+
+/// The readable form of a URL, if it isn't the URL itself
+fn readable_url(url: &str) -> Option<String> {
+    let readable = display_url(url);
+    (readable != url).then_some(readable)
+}
+
+/// Order records the way the numbered listing shows them
+fn sort_for_listing(requests: &mut [RequestRecord]) {
+    requests.sort_by(|a, b| a.url.cmp(&b.url));
+}
+
+/// Find the record a selector refers to in a listing-ordered slice
+///
+/// Returns the record with its 1-based listing number.
+pub(crate) fn select_record<'a>(
+    requests: &'a [RequestRecord],
+    selector: &RecordSelector,
+) -> Result<(usize, &'a RequestRecord)> {
+    match selector {
+        RecordSelector::Index(index) => index
+            .checked_sub(1)
+            .and_then(|i| requests.get(i))
+            .map(|record| (*index, record))
+            .ok_or_else(|| {
+                WebMockError::config(format!(
+                    "No record #{}; the snapshot has records 1 to {}",
+                    index,
+                    requests.len()
+                ))
+            }),
+        RecordSelector::Url(url) => {
+            // Either spelling of an internationalized URL finds its record
+            let canonical = canonical_url(url);
+            let matches: Vec<(usize, &RequestRecord)> = requests
+                .iter()
+                .enumerate()
+                .filter(|(_, record)| record.url == *url || record.url == canonical)
+                .map(|(i, record)| (i + 1, record))
+                .collect();
+            match matches.as_slice() {
+                [found] => Ok(*found),
+                [] => Err(WebMockError::config(format!("No record for URL {}", url))),
+                candidates => {
+                    let listed: Vec<String> = candidates
+                        .iter()
+                        .map(|(index, record)| {
+                            format!("#{} {} ({})", index, record.method, record.response.status)
+                        })
+                        .collect();
+                    Err(WebMockError::config(format!(
+                        "{} records match URL {}: {}; pick one with --request <index>",
+                        candidates.len(),
+                        url,
+                        listed.join(", ")
+                    )))
+                }
+            }
+        }
+    }
+}
+
+/// Show one record and write out its bodies
+fn inspect_record(
+    index: usize,
+    record: &RequestRecord,
+    note: Option<&str>,
+    options: &InspectOptions,
+) -> Result<()> {
+    // Details go to stderr when stdout carries a body
+    let mut out: Box<dyn Write> = if options.dumps_to_stdout() {
+        Box::new(std::io::stderr())
+    } else {
+        Box::new(std::io::stdout())
+    };
+
+    writeln!(
+        out,
+        "#{} {} {} → {}",
+        index,
+        record.method,
+        record.url,
+        record.response.status_text()
+    )?;
+    if let Some(readable) = readable_url(&record.url) {
+        writeln!(out, "   {}", format!("Readable URL: {}", readable).dimmed())?;
+    }
+    if let Some(note) = note {
+        writeln!(out, "   {}", format!("Note: {}", note).dimmed())?;
+    }
+    writeln!(
+        out,
+        "   Request body: {}, response body: {}",
+        format_size(record.body.as_ref().map_or(0, Vec::len)),
+        format_size(record.response.body.len())
+    )?;
+    writeln!(out, "   {}", describe_timing(record))?;
+
+    if options.show_headers {
+        writeln!(out, "   Request headers:")?;
+        write_headers(&mut out, &record.headers)?;
+        writeln!(out, "   Response headers:")?;
+        write_headers(&mut out, &record.response.headers)?;
+        for cookie in record.response.set_cookies() {
+            writeln!(out, "      set-cookie: {}", cookie)?;
+        }
+    }
+
+    if options.show_body {
+        if let Some(body) = record.body.as_deref().filter(|body| !body.is_empty()) {
+            write_body_preview(&mut out, "Request body", body, options.max_body_bytes)?;
+        }
+        write_body_preview(
+            &mut out,
+            "Response body",
+            &record.response.body,
+            options.max_body_bytes,
+        )?;
+    }
+
+    if let Some(path) = &options.dump_request_body {
+        let body = record.body.as_deref().unwrap_or_default();
+        write_body(body, path)?;
+        if !is_stdout(path) {
+            writeln!(out, "   Request body written to {}", path.display())?;
+        }
+    }
+    if let Some(path) = &options.dump_body {
+        write_body(&record.response.body, path)?;
+        if !is_stdout(path) {
+            writeln!(out, "   Response body written to {}", path.display())?;
+        }
+    }
+
+    Ok(())
+}
+
+/// When a record's request was sent, by the capture's monotonic clock, with its wall clock time
+pub(crate) fn describe_timing(record: &RequestRecord) -> String {
+    let wall_clock = record.timestamp.format("%H:%M:%S%.3f UTC");
+    let Some(offset_ms) = record.timing.offset_ms else {
+        return format!("Recorded at {}", wall_clock);
+    };
+    let took = record
+        .timing
+        .duration_ms
+        .map(|duration| format!(", took {} ms", duration))
+        .unwrap_or_default();
+    format!(
+        "Sent {:.3}s into the capture{} (wall clock {})",
+        offset_ms as f64 / 1000.0,
+        took,
+        wall_clock
+    )
+}
+
+fn write_headers(out: &mut dyn Write, headers: &HashMap<String, String>) -> Result<()> {
+    let mut headers: Vec<_> = headers.iter().collect();
+    headers.sort();
+    for (name, value) in headers {
+        writeln!(out, "      {}: {}", name, value)?;
+    }
+    Ok(())
+}
+
+fn write_body_preview(
+    out: &mut dyn Write,
+    label: &str,
+    body: &[u8],
+    max_bytes: Option<usize>,
+) -> Result<()> {
+    match body_preview(body, max_bytes) {
+        Some(text) => writeln!(out, "   {}:\n{}", label, text)?,
+        None => writeln!(
+            out,
+            "   {}: binary, {} (write it out with --dump-body)",
+            label,
+            format_size(body.len())
+        )?,
+    }
+    Ok(())
+}
+
+/// Text of a body for display, cut at `max_bytes` with a marker giving its full size
+///
+/// Only the shown prefix is decoded, so a huge body costs no more than the
+/// limit. Returns `None` for bodies that aren't UTF-8 text.
+pub(crate) fn body_preview(body: &[u8], max_bytes: Option<usize>) -> Option<Cow<'_, str>> {
+    let limit = max_bytes.map_or(body.len(), |max| max.min(body.len()));
+    let shown = &body[..limit];
+    let text = match std::str::from_utf8(shown) {
+        Ok(text) => text,
+        // A character split by the limit is left out
+        Err(e) if e.error_len().is_none() && limit < body.len() => {
+            std::str::from_utf8(&shown[..e.valid_up_to()]).ok()?
+        }
+        Err(_) => return None,
+    };
+    if limit == body.len() {
+        Some(Cow::Borrowed(text))
+    } else {
+        Some(Cow::Owned(format!(
+            "{}…[truncated, {} bytes total]",
+            text,
+            body.len()
+        )))
+    }
+}
+
+fn is_stdout(path: &Path) -> bool {
+    path.as_os_str() == "-"
+}
+
+/// Write body bytes verbatim to a file, or to stdout for `-`
+pub(crate) fn write_body(body: &[u8], path: &Path) -> Result<()> {
+    if is_stdout(path) {
+        let mut stdout = std::io::stdout().lock();
+        stdout.write_all(body)?;
+        stdout.flush()?;
+    } else {
+        std::fs::write(path, body).map_err(|e| {
+            WebMockError::config(format!("Failed to write {}: {}", path.display(), e))
+        })?;
+    }
+    Ok(())
+}
+
 /// Extract content type from headers
 pub(crate) fn extract_content_type(
     headers: &std::collections::HashMap<String, String>,
@@ -190,6 +1053,14 @@ fn display_summary_stats(requests: &[crate::capture::proxy::records::RequestReco
         println!("      {}. {} ({} requests)", i + 1, domain, count);
     }
 
+    let failures = requests
+        .iter()
+        .filter(|record| record.response.is_failure())
+        .count();
+    if failures > 0 {
+        println!("   ⚠️  Failed during capture: {} requests", failures);
+    }
+
     println!("   💾 Total response size: {}", format_size(total_size));
 }
 
@@ -1,20 +1,29 @@
+use crate::capture::metrics::MemoryTracker;
 use crate::error::{Result, WebMockError};
-use crate::feedback::{ProgressReporter, UserFeedback, ValidationHelper};
+use crate::feedback::{format_date, ProgressReporter, UserFeedback, ValidationHelper};
 use crate::storage::Storage;
 use colored::*;
 
+pub mod summary;
+
+pub use summary::{SnapshotSize, StorageSummary};
+
 /// Handle the list command with enhanced feedback
-pub async fn list_command(storage_arg: Option<String>) -> Result<()> {
+///
+/// With `json` set only the snapshots, with their provenance, are written to
+/// stdout, so they can be piped.
+pub async fn list_command(storage_arg: Option<String>, json: bool) -> Result<()> {
+    let storage_path = crate::commands::get_storage_path(storage_arg)?;
+
     // Step 0: Check system requirements (skip in test environment)
     if std::env::var("WEBMOCK_SKIP_PERMISSION_CHECK").is_err() {
         UserFeedback::info("Checking system access...");
-        ValidationHelper::check_permissions()?;
+        ValidationHelper::check_permissions(&storage_path).await?;
     }
 
     // Initialize storage
     UserFeedback::info("Initializing storage...");
-    let storage_path = crate::commands::get_storage_path(storage_arg)?;
-    let storage = Storage::new(storage_path);
+    let storage = Storage::new(storage_path.clone());
 
     // Get list of snapshots with progress indicator
     let progress = ProgressReporter::new();
@@ -31,7 +40,10 @@ pub async fn list_command(storage_arg: Option<String>) -> Result<()> {
 
             // Provide helpful context for common errors
             if matches!(e, WebMockError::Storage(_)) {
-                UserFeedback::tip("Check that ~/.webmock directory exists and is readable");
+                UserFeedback::tip(&format!(
+                    "Check that {} exists and is readable",
+                    storage_path.display()
+                ));
                 UserFeedback::tip("Try running a capture command first to initialize storage");
             }
 
@@ -39,11 +51,19 @@ pub async fn list_command(storage_arg: Option<String>) -> Result<()> {
         }
     };
 
+    if json {
+        let output = serde_json::to_string_pretty(&snapshots).map_err(|e| {
+            WebMockError::config(format!("Failed to encode snapshots as JSON: {}", e))
+        })?;
+        println!("{}", output);
+        return Ok(());
+    }
+
     // Handle empty snapshot list with friendly message and guidance
     if snapshots.is_empty() {
         UserFeedback::section("📭 No Snapshots Found");
-        println!("You haven't created any snapshots yet.");
-        println!();
+        UserFeedback::line("You haven't created any snapshots yet.");
+        UserFeedback::line("");
         UserFeedback::info("To get started:");
         UserFeedback::tip(
             "Create your first snapshot: webmock capture https://example.com --name my-site",
@@ -60,12 +80,20 @@ pub async fn list_command(storage_arg: Option<String>) -> Result<()> {
     ));
 
     for (index, snapshot) in snapshots.iter().enumerate() {
-        // Format creation date in a user-friendly way
-        let formatted_date = snapshot.created_at.format("%Y-%m-%d %H:%M:%S UTC");
-
-        println!("{}. 📸 {}", index + 1, snapshot.name.bright_cyan());
+        // Encrypted snapshots are listed from their readable header
+        let icon = if snapshot.encrypted { "🔒" } else { "📸" };
+        println!("{}. {} {}", index + 1, icon, snapshot.name.bright_cyan());
         println!("   🌐 URL: {}", snapshot.url);
-        println!("   📅 Created: {}", formatted_date.to_string().dimmed());
+        if snapshot.entry_points.len() > 1 {
+            println!(
+                "   🚪 Entry points: {}",
+                snapshot.entry_points[1..].join(", ")
+            );
+        }
+        println!(
+            "   📅 Created: {}",
+            format_date(snapshot.created_at).dimmed()
+        );
 
         // Add separator between snapshots (except for the last one)
         if index < snapshots.len() - 1 {
@@ -74,7 +102,6 @@ pub async fn list_command(storage_arg: Option<String>) -> Result<()> {
     }
 
     // Show helpful next steps
-    println!();
     UserFeedback::section("💡 Next Steps");
     UserFeedback::tip("Start a mock server: webmock serve <snapshot-name>");
     UserFeedback::tip("Delete a snapshot: webmock delete <snapshot-name>");
@@ -82,3 +109,48 @@ pub async fn list_command(storage_arg: Option<String>) -> Result<()> {
 
     Ok(())
 }
+
+/// Handle `list --summary`: report disk usage without loading any snapshot
+///
+/// With `json` set only the summary is written to stdout, so it can be piped.
+pub async fn list_summary_command(
+    storage_arg: Option<String>,
+    json: bool,
+) -> Result<StorageSummary> {
+    let storage_path = crate::commands::get_storage_path(storage_arg)?;
+    let storage = Storage::new(storage_path);
+    let summary = StorageSummary::from_stats(&storage.snapshot_file_stats().await?);
+
+    if json {
+        let output = serde_json::to_string_pretty(&summary).map_err(|e| {
+            WebMockError::config(format!("Failed to encode summary as JSON: {}", e))
+        })?;
+        println!("{}", output);
+        return Ok(summary);
+    }
+
+    UserFeedback::section("📦 Storage Summary");
+    println!("   📸 Snapshots: {}", summary.snapshot_count);
+    println!(
+        "   💾 Total size: {}",
+        MemoryTracker::bytes_to_human(summary.total_bytes)
+    );
+    if let (Some(oldest), Some(newest)) = (summary.oldest, summary.newest) {
+        println!("   📅 Oldest: {}", format_date(oldest).dimmed());
+        println!("   📅 Newest: {}", format_date(newest).dimmed());
+    }
+
+    if !summary.largest.is_empty() {
+        UserFeedback::section("🏋️ Largest Snapshots");
+        for (index, snapshot) in summary.largest.iter().enumerate() {
+            println!(
+                "{}. {}  {}",
+                index + 1,
+                snapshot.name.bright_cyan(),
+                MemoryTracker::bytes_to_human(snapshot.bytes)
+            );
+        }
+    }
+
+    Ok(summary)
+}
@@ -0,0 +1,50 @@
+//! Aggregate storage statistics for `webmock list --summary`
+
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+
+use crate::storage::SnapshotFileStat;
+
+/// Number of snapshots shown in the largest-snapshots table
+pub const LARGEST_SNAPSHOT_COUNT: usize = 5;
+
+/// Size of a single snapshot file
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct SnapshotSize {
+    pub name: String,
+    pub bytes: u64,
+}
+
+/// Disk usage across all snapshots, computed from file metadata
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct StorageSummary {
+    pub snapshot_count: usize,
+    pub total_bytes: u64,
+    pub largest: Vec<SnapshotSize>,
+    pub oldest: Option<DateTime<Utc>>,
+    pub newest: Option<DateTime<Utc>>,
+}
+
+impl StorageSummary {
+    /// Summarize the given snapshot files
+    pub fn from_stats(stats: &[SnapshotFileStat]) -> Self {
+        let mut by_size: Vec<&SnapshotFileStat> = stats.iter().collect();
+        // Ties are broken by name so the output is stable
+        by_size.sort_by(|a, b| b.size.cmp(&a.size).then_with(|| a.name.cmp(&b.name)));
+
+        Self {
+            snapshot_count: stats.len(),
+            total_bytes: stats.iter().map(|stat| stat.size).sum(),
+            largest: by_size
+                .into_iter()
+                .take(LARGEST_SNAPSHOT_COUNT)
+                .map(|stat| SnapshotSize {
+                    name: stat.name.clone(),
+                    bytes: stat.size,
+                })
+                .collect(),
+            oldest: stats.iter().map(|stat| stat.modified).min(),
+            newest: stats.iter().map(|stat| stat.modified).max(),
+        }
+    }
+}
@@ -0,0 +1,35 @@
+//! Manifest command implementation
+//!
+//! Prints the manifest a save writes next to every snapshot, so CI can key
+//! caches on what a snapshot contains without reading the snapshot file.
+//! Snapshots saved before manifests existed get one written first.
+
+use tracing::info;
+
+use crate::error::{Result, WebMockError};
+use crate::feedback::{UserFeedback, ValidationHelper};
+use crate::storage::Storage;
+
+/// Handle the manifest command
+pub async fn manifest_command(snapshot_name: &str, storage_arg: Option<String>) -> Result<()> {
+    info!("Printing manifest of '{}'", snapshot_name);
+
+    ValidationHelper::validate_snapshot_name(snapshot_name)?;
+
+    let storage_path = crate::commands::get_storage_path(storage_arg)?;
+    let storage = Storage::new(storage_path);
+    let manifest = match storage.manifest(snapshot_name).await {
+        Ok(manifest) => manifest,
+        Err(e) => {
+            if matches!(e, WebMockError::SnapshotNotFound(_)) {
+                UserFeedback::tip("Use 'webmock list' to see available snapshots");
+            }
+            return Err(e);
+        }
+    };
+
+    let output = serde_json::to_string_pretty(&manifest)
+        .map_err(|e| WebMockError::config(format!("Failed to encode manifest as JSON: {}", e)))?;
+    println!("{}", output);
+    Ok(())
+}
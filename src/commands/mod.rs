@@ -1,36 +1,75 @@
+pub mod analyze;
+pub mod annotate;
+pub mod assert;
+pub mod bench;
+pub mod browse;
+pub mod ca;
 pub mod capture;
+pub mod completions;
+pub mod config;
 pub mod delete;
+pub mod doctor;
+pub mod drift;
+pub mod encrypt;
+pub mod export;
 pub mod inspect;
 pub mod list;
+pub mod manifest;
+pub mod recapture;
 pub mod serve;
+pub mod set_entry;
+pub mod shadow;
+pub mod split;
+pub mod trim;
+pub mod verify;
 
 #[cfg(test)]
 mod tests;
 
+pub use analyze::analyze_command;
+pub use annotate::annotate_command;
+pub use assert::assert_command;
+pub use bench::bench_command;
+pub use browse::browse_command;
+pub use ca::{ca_trust_command, ca_untrust_command};
 pub use capture::capture_command;
+pub use completions::completions_install_command;
+pub use config::config_command;
 pub use delete::delete_command;
+pub use doctor::doctor_command;
+pub use drift::drift_command;
+pub use encrypt::{decrypt_command, encrypt_command};
+pub use export::export_command;
 pub use inspect::inspect_command;
-pub use list::list_command;
-pub use serve::serve_command;
+pub use list::{list_command, list_summary_command};
+pub use manifest::manifest_command;
+pub use recapture::recapture_command;
+pub use serve::{daemon::stop_command, serve_all_command, serve_command};
+pub use set_entry::set_entry_command;
+pub use shadow::shadow_command;
+pub use split::split_command;
+pub use trim::trim_command;
+pub use verify::verify_command;
 
 use crate::error::{Result, WebMockError};
 use std::path::PathBuf;
 
-/// Get storage path from CLI argument or use default
+/// Get storage path from CLI argument or use the default, see [`default_storage_path`](crate::storage::default_storage_path)
 pub fn get_storage_path(storage_arg: Option<String>) -> Result<PathBuf> {
     if let Some(custom_path) = storage_arg {
-        let path = PathBuf::from(custom_path);
+        let path = crate::storage::paths::normalize_separators(&custom_path);
         if !path.exists() {
             // Create directory if it doesn't exist
             std::fs::create_dir_all(&path).map_err(|e| {
-                WebMockError::config(format!("Failed to create storage directory: {}", e))
+                WebMockError::config(format!(
+                    "Failed to create storage directory {}: {}",
+                    path.display(),
+                    e
+                ))
             })?;
         }
         Ok(path)
     } else {
-        // Use default ~/.webmock
-        let home_dir = dirs::home_dir()
-            .ok_or_else(|| WebMockError::config("Could not determine home directory"))?;
-        Ok(home_dir.join(".webmock"))
+        crate::storage::default_storage_path()
     }
 }
@@ -0,0 +1,184 @@
+//! Recapture command implementation
+//!
+//! Refreshes an existing snapshot by capturing its URL again with the settings
+//! stored alongside it. The new capture is written to a staging area first
+//! and only replaces the snapshot once it has been saved completely.
+
+use std::future::Future;
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use tracing::{info, warn};
+
+use crate::capture::{CaptureOptions, CaptureSession, Provenance};
+use crate::commands::capture::{
+    initialize_storage, report_capture_summary, report_capture_warnings, run_capture_with_progress,
+    validate_inputs,
+};
+use crate::error::Result;
+use crate::feedback::{chrome_detection::ChromeDetection, ProgressReporter, UserFeedback};
+use crate::storage::{SnapshotDiff, Storage};
+
+/// Options for the recapture command
+#[derive(Debug, Clone)]
+pub struct RecaptureOptions {
+    /// Timeout in seconds for the new capture
+    pub timeout: u64,
+    /// Keep the previous version as `<name>.prev.msgpack`
+    pub backup: bool,
+    /// Print how the new capture differs before replacing the old one
+    pub diff: bool,
+}
+
+impl Default for RecaptureOptions {
+    fn default() -> Self {
+        Self {
+            timeout: 30,
+            backup: true,
+            diff: false,
+        }
+    }
+}
+
+/// What a successful recapture changed
+#[derive(Debug, Clone)]
+pub struct RecaptureOutcome {
+    /// Differences from the previous version, when `diff` was requested
+    pub diff: Option<SnapshotDiff>,
+    /// Where the previous version was kept, if a backup was made
+    pub backup: Option<PathBuf>,
+}
+
+/// Handle the recapture command
+pub async fn recapture_command(
+    name: &str,
+    storage_arg: Option<String>,
+    options: RecaptureOptions,
+) -> Result<()> {
+    info!("Starting recapture command for snapshot: {}", name);
+
+    UserFeedback::info("Checking system requirements...");
+    let storage_path = crate::commands::get_storage_path(storage_arg.clone())?;
+    crate::feedback::ValidationHelper::check_system_requirements(&storage_path).await?;
+
+    let storage = initialize_storage(storage_arg).await?;
+    let timeout = options.timeout;
+
+    let outcome = recapture_snapshot(
+        &storage,
+        name,
+        &options,
+        |staging, url, capture| async move {
+            validate_inputs(&url, name, timeout)?;
+
+            UserFeedback::info("Checking Chrome browser availability...");
+            ChromeDetection::validate_and_guide()?;
+
+            let mut progress = ProgressReporter::new();
+            let mut session = CaptureSession::with_options(staging, capture).await?;
+            let snapshot =
+                run_capture_with_progress(&mut session, &mut progress, &url, name, timeout).await?;
+            report_capture_summary(&snapshot);
+            report_capture_warnings(&session.warnings().list());
+            Ok(())
+        },
+    )
+    .await?;
+
+    UserFeedback::success(&format!("Snapshot '{}' has been recaptured", name));
+    if let Some(backup) = &outcome.backup {
+        UserFeedback::tip(&format!(
+            "The previous version was kept at {}",
+            backup.display()
+        ));
+    }
+
+    Ok(())
+}
+
+/// Capture `name` again and swap it in once the new capture is saved
+///
+/// `capture` receives the staging storage to save into, the snapshot's URL
+/// and capture options rebuilt from its stored settings. The existing
+/// snapshot is left untouched if it fails.
+pub async fn recapture_snapshot<F, Fut>(
+    storage: &Storage,
+    name: &str,
+    options: &RecaptureOptions,
+    capture: F,
+) -> Result<RecaptureOutcome>
+where
+    F: FnOnce(Arc<Storage>, String, CaptureOptions) -> Fut,
+    Fut: Future<Output = Result<()>>,
+{
+    let previous = storage.load_snapshot(name).await?;
+    UserFeedback::info(&format!("Recapturing '{}' from {}", name, previous.url));
+
+    let staging = Arc::new(storage.staging());
+    let staged = staging.get_snapshot_path(name)?;
+    if staged.exists() {
+        tokio::fs::remove_file(&staged).await?;
+    }
+
+    // The consent note and a left-out hostname carry over, the command line is this one
+    let previous_provenance = previous.provenance.as_ref();
+    let capture_options = CaptureOptions {
+        environment: previous.environment.clone(),
+        serve_defaults: previous.serve_defaults.clone(),
+        encrypt: previous.encrypted,
+        provenance: Some(Provenance::collect(
+            std::env::args(),
+            previous_provenance.and_then(|provenance| provenance.consent_note.clone()),
+            previous_provenance.is_none_or(|provenance| provenance.hostname.is_some()),
+        )),
+        ..CaptureOptions::default()
+    };
+    if let Err(e) = capture(Arc::clone(&staging), previous.url.clone(), capture_options).await {
+        if staged.exists() {
+            if let Err(cleanup_err) = tokio::fs::remove_file(&staged).await {
+                warn!("Failed to remove staged snapshot: {}", cleanup_err);
+            }
+        }
+        UserFeedback::warning(&format!("Snapshot '{}' was left unchanged", name));
+        return Err(e);
+    }
+
+    let diff = if options.diff {
+        let current = staging.load_snapshot(name).await?;
+        let diff = SnapshotDiff::between(&previous, &current);
+        print_diff(&diff);
+        Some(diff)
+    } else {
+        None
+    };
+
+    let backup = storage
+        .replace_snapshot(name, &staged, options.backup)
+        .await?;
+    Ok(RecaptureOutcome { diff, backup })
+}
+
+/// Print the summary of a snapshot diff
+pub fn print_diff(diff: &SnapshotDiff) {
+    if diff.is_empty() {
+        println!("No changes ({} requests unchanged)", diff.unchanged);
+        return;
+    }
+
+    println!(
+        "{} added, {} removed, {} changed, {} unchanged",
+        diff.added.len(),
+        diff.removed.len(),
+        diff.changed.len(),
+        diff.unchanged
+    );
+    for request in &diff.added {
+        println!("  + {}", request);
+    }
+    for request in &diff.removed {
+        println!("  - {}", request);
+    }
+    for request in &diff.changed {
+        println!("  ~ {}", request);
+    }
+}
@@ -0,0 +1,125 @@
+use std::path::Path;
+use std::process::{Command, Stdio};
+
+use url::Url;
+
+use crate::error::{Result, WebMockError};
+use crate::feedback::chrome_detection::ChromeDetection;
+use crate::feedback::UserFeedback;
+use crate::serve::OpenMode;
+
+/// Map the snapshot's original URL onto the local mock server
+///
+/// Only the path and query are kept, since the mock server falls back to
+/// path matching for requests that arrive without the original host.
+pub fn entry_url(original_url: &str, port: u16) -> String {
+    let path = Url::parse(original_url)
+        .map(|url| match url.query() {
+            Some(query) => format!("{}?{}", url.path(), query),
+            None => url.path().to_string(),
+        })
+        .unwrap_or_else(|_| "/".to_string());
+
+    format!("http://localhost:{}{}", port, path)
+}
+
+/// Chrome arguments that route all traffic through the mock server
+pub fn chrome_proxy_args(port: u16, user_data_dir: &Path, start_url: &str) -> Vec<String> {
+    vec![
+        format!("--proxy-server=http://127.0.0.1:{}", port),
+        // The mock server terminates TLS with a self-signed certificate
+        "--ignore-certificate-errors".to_string(),
+        format!("--user-data-dir={}", user_data_dir.display()),
+        "--no-first-run".to_string(),
+        "--no-default-browser-check".to_string(),
+        start_url.to_string(),
+    ]
+}
+
+/// Open a browser for the served snapshot
+///
+/// Launch failures are reported as warnings so they never stop the server.
+pub fn open_snapshot(mode: OpenMode, original_url: &str, port: u16) {
+    let result = match mode {
+        OpenMode::Default => {
+            let url = entry_url(original_url, port);
+            UserFeedback::info(&format!("Opening {} in your default browser", url));
+            open_default_browser(&url)
+        }
+        OpenMode::ChromeProxy => {
+            UserFeedback::info(&format!(
+                "Launching Chrome through the mock server for {}",
+                original_url
+            ));
+            open_chrome_proxy(original_url, port)
+        }
+    };
+
+    if let Err(e) = result {
+        UserFeedback::warning(&format!("Could not open a browser: {}", e.user_message()));
+        UserFeedback::tip(&format!(
+            "Open {} manually while the server is running",
+            entry_url(original_url, port)
+        ));
+    }
+}
+
+/// Open the snapshot index of a path-routed server in the default browser
+pub fn open_index(port: u16) {
+    let url = format!("http://localhost:{}/", port);
+    UserFeedback::info(&format!("Opening {} in your default browser", url));
+    if let Err(e) = open_default_browser(&url) {
+        UserFeedback::warning(&format!("Could not open a browser: {}", e.user_message()));
+        UserFeedback::tip(&format!(
+            "Open {} manually while the server is running",
+            url
+        ));
+    }
+}
+
+fn open_default_browser(url: &str) -> Result<()> {
+    #[cfg(target_os = "macos")]
+    let mut command = {
+        let mut command = Command::new("open");
+        command.arg(url);
+        command
+    };
+
+    #[cfg(target_os = "windows")]
+    let mut command = {
+        let mut command = Command::new("cmd");
+        command.args(["/C", "start", "", url]);
+        command
+    };
+
+    #[cfg(not(any(target_os = "macos", target_os = "windows")))]
+    let mut command = {
+        let mut command = Command::new("xdg-open");
+        command.arg(url);
+        command
+    };
+
+    spawn_detached(&mut command)
+}
+
+fn open_chrome_proxy(original_url: &str, port: u16) -> Result<()> {
+    let chrome = ChromeDetection::find_chrome_executable().ok_or(WebMockError::ChromeNotFound)?;
+
+    // A dedicated profile keeps the proxy settings away from the user's own browser
+    let user_data_dir = std::env::temp_dir().join(format!("webmock-chrome-{}", port));
+
+    let mut command = Command::new(chrome);
+    command.args(chrome_proxy_args(port, &user_data_dir, original_url));
+
+    spawn_detached(&mut command)
+}
+
+fn spawn_detached(command: &mut Command) -> Result<()> {
+    command
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()
+        .map(|_| ())
+        .map_err(|e| WebMockError::command_failed(format!("Failed to launch browser: {}", e)))
+}
@@ -0,0 +1,373 @@
+//! Running `serve` in the background and stopping it again
+//!
+//! `serve --daemon` starts the same command once more as a detached process
+//! whose output is appended to `--log-file`, and returns once that process
+//! has written its PID to `--pid-file`. The server keeps the PID file locked
+//! while it runs, so `webmock stop` can tell a running server from a PID file
+//! left behind by one that crashed, even if its PID has been reused since.
+//!
+//! On Unix the server runs in its own session, so closing the terminal or
+//! pressing Ctrl+C there doesn't reach it, and `webmock stop` sends it
+//! SIGTERM. Windows has no signal a detached process can receive, so there
+//! `webmock stop` deletes the PID file and the server watches for that.
+//! Either way the server shuts down the same way as on Ctrl+C.
+
+use std::fs::{File, OpenOptions};
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::time::{Duration, Instant};
+
+use tracing::{debug, info};
+
+use crate::error::{Result, WebMockError};
+use crate::feedback::UserFeedback;
+use crate::storage::file_lock::{self, TryLockError};
+
+/// Set in the environment of the server process started by `--daemon`
+pub const DAEMON_CHILD_ENV: &str = "WEBMOCK_DAEMON_CHILD";
+
+/// How long `serve --daemon` waits for the server to write its PID file
+const STARTUP_TIMEOUT: Duration = Duration::from_secs(30);
+
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Where a background server writes its PID and output
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DaemonOptions {
+    pub pid_file: PathBuf,
+    pub log_file: PathBuf,
+}
+
+/// Check whether this process is the server started by `serve --daemon`
+pub fn is_daemon_child() -> bool {
+    std::env::var_os(DAEMON_CHILD_ENV).is_some()
+}
+
+/// A locked file holding the PID of a running server, removed when dropped
+#[derive(Debug)]
+pub struct PidFile {
+    path: PathBuf,
+    _file: File,
+}
+
+impl PidFile {
+    /// Write this process's PID to `path` and keep it locked
+    ///
+    /// Fails if another running server holds the file. A file left behind by
+    /// a server that is gone is taken over.
+    pub fn create(path: &Path) -> Result<Self> {
+        if let Some(parent) = path
+            .parent()
+            .filter(|parent| !parent.as_os_str().is_empty())
+        {
+            std::fs::create_dir_all(parent)
+                .map_err(|e| WebMockError::io("create directory", parent, e))?;
+        }
+
+        let mut file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(path)
+            .map_err(|e| WebMockError::io("open PID file", path, e))?;
+        match file_lock::try_lock(&file) {
+            Ok(()) => {}
+            Err(TryLockError::WouldBlock) => {
+                return Err(WebMockError::config(format!(
+                    "A server is already running with PID file {}; stop it with 'webmock stop --pid-file {}'",
+                    path.display(),
+                    path.display()
+                )));
+            }
+            Err(TryLockError::Error(e)) => {
+                return Err(WebMockError::io("lock PID file", path, e));
+            }
+        }
+
+        file.set_len(0)
+            .and_then(|_| file.seek(SeekFrom::Start(0)))
+            .and_then(|_| writeln!(file, "{}", std::process::id()))
+            .and_then(|_| file.flush())
+            .map_err(|e| WebMockError::io("write PID file", path, e))?;
+        debug!("Wrote PID {} to {}", std::process::id(), path.display());
+        Ok(Self {
+            path: path.to_path_buf(),
+            _file: file,
+        })
+    }
+
+    /// Get the path of the PID file
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+}
+
+impl Drop for PidFile {
+    fn drop(&mut self) {
+        // The lock is released when the file is closed right after
+        if let Err(e) = std::fs::remove_file(&self.path) {
+            debug!("Failed to remove PID file {}: {}", self.path.display(), e);
+        }
+    }
+}
+
+/// Start the current command again as a detached background server
+///
+/// Returns the server's PID once it has written its PID file. Fails if the
+/// server exits first, e.g. because the snapshot doesn't exist; its log file
+/// says why.
+pub async fn spawn_daemon(options: &DaemonOptions) -> Result<u32> {
+    if is_locked(&options.pid_file)? {
+        return Err(WebMockError::config(format!(
+            "A server is already running with PID file {}; stop it with 'webmock stop --pid-file {}'",
+            options.pid_file.display(),
+            options.pid_file.display()
+        )));
+    }
+
+    let log = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(&options.log_file)
+        .map_err(|e| WebMockError::io("open log file", &options.log_file, e))?;
+    let log_stderr = log
+        .try_clone()
+        .map_err(|e| WebMockError::io("open log file", &options.log_file, e))?;
+    let executable = std::env::current_exe()
+        .map_err(|e| WebMockError::command_failed(format!("Cannot locate webmock: {}", e)))?;
+
+    let mut command = Command::new(executable);
+    command
+        .args(std::env::args_os().skip(1))
+        .env(DAEMON_CHILD_ENV, "1")
+        .env("NO_COLOR", "1")
+        .stdin(Stdio::null())
+        .stdout(log)
+        .stderr(log_stderr);
+    detach(&mut command);
+    let mut child = command.spawn().map_err(|e| {
+        WebMockError::command_failed(format!("Failed to start background server: {}", e))
+    })?;
+    let pid = child.id();
+    info!("Started background server with PID {}", pid);
+
+    let deadline = Instant::now() + STARTUP_TIMEOUT;
+    loop {
+        if let Some(status) = child.try_wait()? {
+            return Err(WebMockError::command_failed(format!(
+                "Background server exited ({}) before it was ready; see {}",
+                status,
+                options.log_file.display()
+            )));
+        }
+        if is_locked(&options.pid_file)? {
+            return Ok(pid);
+        }
+        if Instant::now() > deadline {
+            let _ = child.kill();
+            return Err(WebMockError::command_failed(format!(
+                "Background server did not start within {}s; see {}",
+                STARTUP_TIMEOUT.as_secs(),
+                options.log_file.display()
+            )));
+        }
+        tokio::time::sleep(POLL_INTERVAL).await;
+    }
+}
+
+/// Handle `serve --daemon`: start the server in the background and return
+pub async fn daemonize(options: &DaemonOptions) -> Result<()> {
+    let pid = spawn_daemon(options).await?;
+    UserFeedback::success(&format!(
+        "Mock server running in the background (PID {})",
+        pid
+    ));
+    UserFeedback::line(&format!("   📝 Logs: {}", options.log_file.display()));
+    UserFeedback::line(&format!(
+        "   ⏹️  Stop it with: webmock stop --pid-file {}",
+        options.pid_file.display()
+    ));
+    Ok(())
+}
+
+/// Handle the stop command
+///
+/// Asks the server holding `pid_file` to shut down and waits up to `timeout`
+/// for it to exit. A missing PID file, or one whose server is gone, is
+/// reported and cleaned up without failing.
+pub async fn stop_command(pid_file: &Path, timeout: Duration) -> Result<()> {
+    let mut file = match File::open(pid_file) {
+        Ok(file) => file,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+            UserFeedback::warning(&format!(
+                "No PID file at {}; no server to stop",
+                pid_file.display()
+            ));
+            return Ok(());
+        }
+        Err(e) => return Err(WebMockError::io("open PID file", pid_file, e)),
+    };
+
+    match file_lock::try_lock(&file) {
+        Ok(()) => {
+            let pid = read_pid(&mut file).map_or_else(|| "?".to_string(), |pid| pid.to_string());
+            drop(file);
+            remove_pid_file(pid_file)?;
+            UserFeedback::warning(&format!(
+                "Server with PID {} is no longer running; removed stale PID file {}",
+                pid,
+                pid_file.display()
+            ));
+            return Ok(());
+        }
+        Err(TryLockError::WouldBlock) => {}
+        Err(TryLockError::Error(e)) => return Err(WebMockError::io("lock PID file", pid_file, e)),
+    }
+
+    request_stop(pid_file, &mut file)?;
+    UserFeedback::info("Waiting for the server to stop...");
+
+    let deadline = Instant::now() + timeout;
+    loop {
+        match file_lock::try_lock(&file) {
+            Ok(()) => break,
+            Err(TryLockError::WouldBlock) if Instant::now() < deadline => {
+                tokio::time::sleep(POLL_INTERVAL).await;
+            }
+            Err(TryLockError::WouldBlock) => {
+                return Err(WebMockError::command_failed(format!(
+                    "Server did not stop within {}s",
+                    timeout.as_secs()
+                )));
+            }
+            Err(TryLockError::Error(e)) => {
+                return Err(WebMockError::io("lock PID file", pid_file, e));
+            }
+        }
+    }
+
+    // The server removes its PID file itself unless it couldn't
+    drop(file);
+    remove_pid_file(pid_file)?;
+    UserFeedback::success("Server stopped");
+    Ok(())
+}
+
+/// Wait until `webmock stop` asks this server to shut down, describing how
+#[cfg(unix)]
+pub async fn stop_requested(_pid_file: Option<&Path>) -> String {
+    use tokio::signal::unix::{signal, SignalKind};
+
+    match signal(SignalKind::terminate()) {
+        Ok(mut terminate) => {
+            terminate.recv().await;
+            "Received shutdown signal (SIGTERM)".to_string()
+        }
+        Err(e) => {
+            debug!("Failed to install SIGTERM handler: {}", e);
+            std::future::pending().await
+        }
+    }
+}
+
+/// Wait until `webmock stop` asks this server to shut down, describing how
+#[cfg(not(unix))]
+pub async fn stop_requested(pid_file: Option<&Path>) -> String {
+    let Some(pid_file) = pid_file else {
+        return std::future::pending().await;
+    };
+    while pid_file.exists() {
+        tokio::time::sleep(Duration::from_millis(500)).await;
+    }
+    "PID file removed by webmock stop".to_string()
+}
+
+/// Check whether a running server holds the PID file
+fn is_locked(pid_file: &Path) -> Result<bool> {
+    let file = match File::open(pid_file) {
+        Ok(file) => file,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(false),
+        Err(e) => return Err(WebMockError::io("open PID file", pid_file, e)),
+    };
+    match file_lock::try_lock(&file) {
+        Ok(()) => Ok(false),
+        Err(TryLockError::WouldBlock) => Ok(true),
+        Err(TryLockError::Error(e)) => Err(WebMockError::io("lock PID file", pid_file, e)),
+    }
+}
+
+fn read_pid(file: &mut File) -> Option<u32> {
+    let mut contents = String::new();
+    file.seek(SeekFrom::Start(0)).ok()?;
+    file.read_to_string(&mut contents).ok()?;
+    contents.trim().parse().ok()
+}
+
+fn remove_pid_file(pid_file: &Path) -> Result<()> {
+    match std::fs::remove_file(pid_file) {
+        Err(e) if e.kind() != std::io::ErrorKind::NotFound => {
+            Err(WebMockError::io("remove PID file", pid_file, e))
+        }
+        _ => Ok(()),
+    }
+}
+
+/// Send SIGTERM to the server
+#[cfg(unix)]
+fn request_stop(pid_file: &Path, file: &mut File) -> Result<()> {
+    // 0 and negative PIDs would signal whole process groups
+    let pid = read_pid(file)
+        .and_then(|pid| libc::pid_t::try_from(pid).ok())
+        .filter(|pid| *pid > 0)
+        .ok_or_else(|| {
+            WebMockError::config(format!("PID file {} holds no PID", pid_file.display()))
+        })?;
+    UserFeedback::info(&format!("Stopping server with PID {}...", pid));
+
+    // SAFETY: kill only sends a signal and touches no memory of ours
+    if unsafe { libc::kill(pid, libc::SIGTERM) } == -1 {
+        // The server may have exited in the meantime, which the lock shows next
+        debug!(
+            "Failed to send SIGTERM to {}: {}",
+            pid,
+            std::io::Error::last_os_error()
+        );
+    }
+    Ok(())
+}
+
+/// Delete the PID file, which the server polls for
+#[cfg(not(unix))]
+fn request_stop(pid_file: &Path, _file: &mut File) -> Result<()> {
+    UserFeedback::info("Stopping server...");
+    remove_pid_file(pid_file)
+}
+
+/// Start the server in a new session, away from the terminal's Ctrl+C and hangup
+#[cfg(unix)]
+fn detach(command: &mut Command) {
+    use std::os::unix::process::CommandExt;
+    // SAFETY: setsid is async-signal-safe, so it may run between fork and exec
+    unsafe {
+        command.pre_exec(|| {
+            if libc::setsid() == -1 {
+                return Err(std::io::Error::last_os_error());
+            }
+            Ok(())
+        });
+    }
+}
+
+/// Start the server without a console, in its own process group
+#[cfg(windows)]
+fn detach(command: &mut Command) {
+    use std::os::windows::process::CommandExt;
+    const DETACHED_PROCESS: u32 = 0x0000_0008;
+    const CREATE_NEW_PROCESS_GROUP: u32 = 0x0000_0200;
+    command.creation_flags(DETACHED_PROCESS | CREATE_NEW_PROCESS_GROUP);
+}
+
+#[cfg(not(any(unix, windows)))]
+fn detach(_command: &mut Command) {}
@@ -1,11 +1,225 @@
 use colored::*;
+use std::io::IsTerminal;
 use std::net::{SocketAddr, TcpListener};
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Duration;
 use tokio::signal;
+use tokio::sync::oneshot;
 
+use crate::commands::capture::parse_age;
 use crate::error::{Result, WebMockError};
-use crate::feedback::{ProgressReporter, UserFeedback, ValidationHelper};
-use crate::serve::MockServer;
-use crate::storage::Storage;
+use crate::feedback::{
+    format_date, report_slow, ProgressReporter, UserFeedback, ValidationHelper,
+    SLOW_OPERATION_AFTER,
+};
+use crate::serve::encoding::parse_encodings;
+use crate::serve::host_stats::format_host_table;
+use crate::serve::{
+    count_absolute_urls, parse_replay_failures, snapshot_variants, MockServer, RateLimiter,
+    ServeOptions, SnapshotRouter, ROUTE_PREFIX,
+};
+use crate::storage::manifest::file_sha256;
+use crate::storage::{ServeDefaults, Snapshot, Storage};
+
+pub mod browser;
+pub mod daemon;
+pub mod port_file;
+pub mod registry;
+
+use daemon::PidFile;
+use port_file::{port_file_path, PortFile, PortFileEntry};
+use registry::ServerRegistry;
+
+/// Port `serve` listens on unless a flag or the snapshot says otherwise
+pub const DEFAULT_SERVE_PORT: u16 = 8080;
+
+/// Parse the value of `--idle-timeout` or `--max-lifetime`, such as `300s` or `1h`
+pub fn parse_lifetime_limit(value: &str, flag: &str) -> Result<Duration> {
+    let duration = parse_age(value).map_err(|_| {
+        WebMockError::config(format!(
+            "Invalid {} '{}': use a number followed by s, m or h (e.g. 300s, 1h)",
+            flag, value
+        ))
+    })?;
+
+    if duration.is_zero() {
+        return Err(WebMockError::config(format!("{} must be positive", flag)));
+    }
+
+    Ok(duration)
+}
+
+/// Tell the user the server is stopping and print request lines still queued first
+async fn announce_shutdown(mock_server: &MockServer, reason: &str) {
+    mock_server.flush_console().await;
+    UserFeedback::line("");
+    UserFeedback::separator();
+    UserFeedback::info(reason);
+    print_host_summary(mock_server);
+}
+
+/// Print requests per host, pointing out hosts the snapshot had nothing for
+fn print_host_summary(mock_server: &MockServer) {
+    let Some(summary) = mock_server.host_summary().filter(|hosts| !hosts.is_empty()) else {
+        return;
+    };
+    UserFeedback::section("📊 Requests by host");
+    print!("{}", format_host_table(&summary));
+    let missing = summary.iter().filter(|host| host.all_missed()).count();
+    if missing > 0 {
+        UserFeedback::warning(&format!(
+            "{} host(s) were requested but are not present in the snapshot; consider recapturing",
+            missing
+        ));
+    }
+}
+
+/// Write how often each record was served to the `--coverage` file, with the served file's hash
+fn write_coverage(mock_server: &MockServer, coverage: Option<&(PathBuf, String)>) -> Result<()> {
+    let Some((path, sha256)) = coverage else {
+        return Ok(());
+    };
+    let Some(report) = mock_server.coverage_report(sha256) else {
+        return Ok(());
+    };
+    report.write(path)?;
+    let unused = report
+        .records
+        .iter()
+        .filter(|record| record.hits == 0)
+        .count();
+    UserFeedback::info(&format!(
+        "Coverage written to {}: {} of {} records were never served",
+        path.display(),
+        unused,
+        report.records.len()
+    ));
+    if unused > 0 {
+        UserFeedback::tip(&format!(
+            "Remove them with: webmock trim {} --coverage {}",
+            report.snapshot,
+            path.display()
+        ));
+    }
+    Ok(())
+}
+
+/// Wait for Ctrl+C or `webmock stop`, describing which one arrived
+async fn shutdown_signal(pid_file: Option<&std::path::Path>) -> String {
+    tokio::select! {
+        result = signal::ctrl_c() => {
+            result.expect("Failed to install Ctrl+C handler");
+            "Received shutdown signal (Ctrl+C)".to_string()
+        }
+        reason = daemon::stop_requested(pid_file) => reason,
+    }
+}
+
+/// Describe how the server can be stopped, including its lifetime limits
+fn print_stop_hint(options: &ServeOptions) {
+    if !daemon::is_daemon_child() {
+        UserFeedback::line(&format!(
+            "   ⏹️  Press {} to stop the server",
+            "Ctrl+C".bright_yellow()
+        ));
+    }
+    if let Some(pid_file) = &options.pid_file {
+        UserFeedback::line(&format!(
+            "   ⏹️  Stop it with: {}",
+            format!("webmock stop --pid-file {}", pid_file.display()).bright_yellow()
+        ));
+    }
+    if let Some(idle_timeout) = options.idle_timeout {
+        UserFeedback::line(&format!(
+            "   💤 Stops after {}s without requests",
+            idle_timeout.as_secs()
+        ));
+    }
+    if let Some(max_lifetime) = options.max_lifetime {
+        UserFeedback::line(&format!(
+            "   ⏳ Stops after running for {}s",
+            max_lifetime.as_secs()
+        ));
+    }
+}
+
+/// Fail unless the snapshot has records of the `--variant` to serve
+fn check_variant(snapshot: &Snapshot, variant: Option<&str>) -> Result<()> {
+    let Some(variant) = variant else {
+        return Ok(());
+    };
+    let variants = snapshot_variants(snapshot);
+    if variants.iter().any(|name| name == variant) {
+        return Ok(());
+    }
+    Err(WebMockError::config(if variants.is_empty() {
+        format!(
+            "Snapshot '{}' was captured without variants, so --variant {} has no records",
+            snapshot.name, variant
+        )
+    } else {
+        format!(
+            "Snapshot '{}' has no variant '{}'; captured variants: {}",
+            snapshot.name,
+            variant,
+            variants.join(", ")
+        )
+    }))
+}
+
+/// Stop before serving a snapshot another server of this storage already serves
+///
+/// Editing the snapshot and then testing against the older server is easy
+/// to miss, so the user is asked whether to go on. Without a terminal to
+/// ask on, serving fails unless `allow_duplicate` is set.
+pub fn check_duplicate_server(
+    registry: &ServerRegistry,
+    snapshot_name: &str,
+    allow_duplicate: bool,
+) -> Result<()> {
+    let running = match registry.serving(snapshot_name) {
+        Ok(running) => running,
+        Err(e) => {
+            UserFeedback::warning(&format!(
+                "Can't tell whether '{}' is already being served: {}",
+                snapshot_name,
+                e.user_message()
+            ));
+            return Ok(());
+        }
+    };
+    if running.is_empty() {
+        return Ok(());
+    }
+
+    for server in &running {
+        UserFeedback::warning(&format!(
+            "Snapshot '{}' is already served on http://localhost:{} (PID {}, started {})",
+            snapshot_name,
+            server.port,
+            server.pid,
+            format_date(server.started_at)
+        ));
+    }
+    if allow_duplicate {
+        return Ok(());
+    }
+    let refused = || {
+        WebMockError::command_failed(format!(
+            "Not starting another server for '{}'; use the running one, stop it, or pass --allow-duplicate",
+            snapshot_name
+        ))
+    };
+    if !std::io::stdin().is_terminal() {
+        return Err(refused());
+    }
+    if UserFeedback::confirm("Start another server for it anyway?").unwrap_or(false) {
+        Ok(())
+    } else {
+        Err(refused())
+    }
+}
 
 /// Check if a port is available with detailed diagnostics
 pub fn is_port_available(port: u16) -> bool {
@@ -112,16 +326,64 @@ pub fn check_and_resolve_port(requested_port: u16) -> Result<u16> {
     }
 }
 
+/// Combine serve flags with the defaults stored in a snapshot
+///
+/// Flags given on the command line win over stored defaults, and settings
+/// neither sets keep their built-in default. Also returns the stored
+/// defaults that were used, as flags.
+pub fn resolve_serve_defaults(
+    flags: &ServeDefaults,
+    stored: &ServeDefaults,
+) -> (ServeDefaults, Vec<String>) {
+    let given = flags.flags();
+    let flag_name = |flag: &str| flag.split([' ', '=']).next().unwrap_or("").to_string();
+    let from_snapshot = stored
+        .flags()
+        .into_iter()
+        .filter(|flag| {
+            !given
+                .iter()
+                .any(|given| flag_name(given) == flag_name(flag))
+        })
+        .collect();
+    (flags.or(stored), from_snapshot)
+}
+
+/// Apply serve settings given as flags or stored with a snapshot to `options`
+///
+/// The port isn't part of `options` and is left to the caller.
+pub fn apply_serve_defaults(settings: &ServeDefaults, options: &mut ServeOptions) -> Result<()> {
+    if let Some(mode) = &settings.replay_failures {
+        options.replay_failures = parse_replay_failures(mode)?;
+    }
+    if let Some(encode) = &settings.encode {
+        options.encodings = parse_encodings(encode)?;
+    }
+    if let Some(strict_connect) = settings.strict_connect {
+        options.strict_connect = strict_connect;
+    }
+    if let Some(stateful) = settings.stateful {
+        options.stateful = stateful;
+    }
+    Ok(())
+}
+
 /// Handle the serve command with enhanced feedback
+///
+/// `flags` are the serve flags given on the command line; the snapshot's
+/// stored defaults fill in the rest.
 pub async fn serve_command(
     snapshot_name: &str,
-    requested_port: u16,
+    flags: &ServeDefaults,
     storage_arg: Option<String>,
+    mut options: ServeOptions,
 ) -> Result<()> {
     // Step 0: Validate inputs
     UserFeedback::info("Validating inputs...");
     ValidationHelper::validate_snapshot_name(snapshot_name)?;
-    ValidationHelper::validate_port(requested_port)?;
+    if let Some(port) = flags.port {
+        ValidationHelper::validate_port(port)?;
+    }
     UserFeedback::success("Input validation passed");
 
     // Initialize storage
@@ -129,22 +391,48 @@ pub async fn serve_command(
     let storage_path = crate::commands::get_storage_path(storage_arg)?;
     let storage = Storage::new(storage_path);
 
+    // Before loading, which can take a while for large snapshots
+    let registry = ServerRegistry::new(storage.base_path());
+    check_duplicate_server(&registry, snapshot_name, options.allow_duplicate)?;
+
     // Load the snapshot with detailed status reporting and progress
     let progress = ProgressReporter::new();
     let loading_spinner = progress.create_spinner(&format!("Loading snapshot '{}'", snapshot_name));
 
-    let snapshot = match storage.load_snapshot(snapshot_name).await {
+    let slow_message = format!("Still loading snapshot '{}'", snapshot_name);
+    let load = report_slow(
+        &loading_spinner,
+        &slow_message,
+        SLOW_OPERATION_AFTER,
+        storage.load_snapshot(snapshot_name),
+    );
+    // Dropping the load on Ctrl+C stops it, e.g. on a stalled network filesystem
+    let loaded = tokio::select! {
+        loaded = load => loaded,
+        Ok(()) = signal::ctrl_c() => {
+            loading_spinner.finish_with_message("🛑 Loading cancelled");
+            return Err(WebMockError::command_failed(format!(
+                "Loading snapshot '{}' was interrupted",
+                snapshot_name
+            )));
+        }
+    };
+
+    let snapshot = match loaded {
         Ok(snapshot) => {
             loading_spinner.finish_with_message(format!("✅ Loaded snapshot '{}'", snapshot.name));
 
             // Display snapshot details
             UserFeedback::section("📋 Snapshot Details");
-            println!("   📍 Original URL: {}", snapshot.url);
-            println!("   📊 Recorded requests: {}", snapshot.requests.len());
-            println!(
+            UserFeedback::line(&format!("   📍 Original URL: {}", snapshot.url));
+            UserFeedback::line(&format!(
+                "   📊 Recorded requests: {}",
+                snapshot.requests.len()
+            ));
+            UserFeedback::line(&format!(
                 "   📅 Created: {}",
                 snapshot.created_at.format("%Y-%m-%d %H:%M:%S UTC")
-            );
+            ));
 
             snapshot
         }
@@ -164,59 +452,255 @@ pub async fn serve_command(
         }
     };
 
+    check_variant(&snapshot, options.variant.as_deref())?;
+    // Hashed before serving, so the report names the file that was served
+    let coverage = match options.coverage.clone() {
+        Some(path) => {
+            let snapshot_path = storage.resolve_snapshot_path(snapshot_name)?;
+            let (sha256, _) =
+                tokio::task::spawn_blocking(move || file_sha256(&snapshot_path)).await??;
+            Some((path, sha256))
+        }
+        None => None,
+    };
+
+    let (settings, from_snapshot) = resolve_serve_defaults(flags, &snapshot.serve_defaults);
+    if !from_snapshot.is_empty() {
+        UserFeedback::info(&format!(
+            "Using defaults stored with the snapshot: {}",
+            from_snapshot.join(" ")
+        ));
+    }
+    apply_serve_defaults(&settings, &mut options)?;
+    let requested_port = settings.port.unwrap_or(DEFAULT_SERVE_PORT);
+    ValidationHelper::validate_port(requested_port)?;
+
     // Port availability checking and conflict resolution
     UserFeedback::info("Checking port availability...");
     let port = check_and_resolve_port(requested_port)?;
+    let pid_file = options
+        .pid_file
+        .as_deref()
+        .map(PidFile::create)
+        .transpose()?;
+    let port_file = options
+        .port_file
+        .as_deref()
+        .map(|path| PortFile::claim(&port_file_path(path, snapshot_name)))
+        .transpose()?
+        .map(Arc::new);
+    // Only there for other servers to see, so serve without it if need be
+    let _lease = match registry.register(&PortFileEntry::new(snapshot_name, port)) {
+        Ok(lease) => Some(lease),
+        Err(e) => {
+            UserFeedback::warning(&format!(
+                "Other servers won't see this one: {}",
+                e.user_message()
+            ));
+            None
+        }
+    };
 
     // Create and start the mock server with enhanced status reporting
-    let mock_server = MockServer::new(snapshot);
-
+    let original_url = snapshot.url.clone();
+    let open_mode = options.open;
     UserFeedback::section("🚀 Starting Mock Server");
-    println!(
+    UserFeedback::line(&format!(
         "   🌐 Server URL: {}",
         format!("http://localhost:{}", port).bright_green()
-    );
-    println!("   🎯 Serving snapshot: {}", snapshot_name);
-    println!(
-        "   ⏹️  Press {} to stop the server",
-        "Ctrl+C".bright_yellow()
-    );
+    ));
+    UserFeedback::line(&format!("   🎯 Serving snapshot: {}", snapshot_name));
+    if options.stateful {
+        UserFeedback::line(
+            "   🔗 Stateful: requests out of captured order get 404 until their turn",
+        );
+    }
+    if options.simulate_rate_limits {
+        let endpoints = RateLimiter::new(&snapshot.requests).len();
+        if endpoints == 0 {
+            UserFeedback::warning(
+                "--simulate-rate-limits found no 429 response with Retry-After in the snapshot",
+            );
+        } else {
+            UserFeedback::line(&format!(
+                "   🚦 Rate limits: {} endpoint(s) answer their captured 429 when requested too fast",
+                endpoints
+            ));
+        }
+    }
+    let absolute_urls = count_absolute_urls(&snapshot);
+    if options.rewrite_absolute_urls {
+        UserFeedback::line(&format!(
+            "   🔗 Absolute URLs: {} same-origin URL(s) served as paths",
+            absolute_urls
+        ));
+    } else if absolute_urls > 0 {
+        UserFeedback::warning(&format!(
+            "Snapshot contains {} absolute same-origin URL(s); direct serving without proxy mode will miss them (use --rewrite-absolute-urls)",
+            absolute_urls
+        ));
+    }
+    if let Some(variant) = &options.variant {
+        UserFeedback::line(&format!("   🎭 Variant: {}", variant));
+    }
+    if let Some(port_file) = &port_file {
+        UserFeedback::line(&format!("   🗂️  Port file: {}", port_file.path().display()));
+    }
+    if let Some((path, _)) = &coverage {
+        UserFeedback::line(&format!(
+            "   📈 Coverage: written to {} on shutdown",
+            path.display()
+        ));
+    }
+    print_stop_hint(&options);
+    let mock_server = MockServer::with_options(snapshot, options);
 
     UserFeedback::separator();
     UserFeedback::info("Server logs:");
 
+    // Write the port file and open a browser once the listener is bound
+    let (ready_tx, ready_rx) = oneshot::channel::<SocketAddr>();
+    let ready_port_file = port_file.clone();
+    let entry_snapshot = snapshot_name.to_string();
+    tokio::spawn(async move {
+        let Ok(address) = ready_rx.await else {
+            return;
+        };
+        if let Some(port_file) = ready_port_file {
+            let entry = PortFileEntry::new(&entry_snapshot, address.port());
+            if let Err(e) = port_file.write(&entry) {
+                UserFeedback::warning(&format!("Failed to write port file: {}", e.user_message()));
+            }
+        }
+        if let Some(mode) = open_mode {
+            browser::open_snapshot(mode, &original_url, address.port());
+        }
+    });
+
     // Set up graceful shutdown handling
-    let server_future = mock_server.start(port);
-    let shutdown_signal = async {
-        signal::ctrl_c()
-            .await
-            .expect("Failed to install Ctrl+C handler");
-    };
+    let server_future = mock_server.start_with_ready(port, ready_tx);
 
     // Run server with graceful shutdown
-    tokio::select! {
+    let reason = tokio::select! {
         result = server_future => {
             match result {
-                Ok(_) => {
-                    UserFeedback::success("Server stopped normally");
-                }
+                Ok(_) => match mock_server.shutdown_reason() {
+                    Some(reason) => format!("Shutting down: {}", reason),
+                    None => {
+                        UserFeedback::success("Server stopped normally");
+                        return write_coverage(&mock_server, coverage.as_ref());
+                    }
+                },
                 Err(e) => {
                     UserFeedback::error(&format!("Server error: {}", e.user_message()));
                     return Err(e);
                 }
             }
         }
-        _ = shutdown_signal => {
-            println!();
-            UserFeedback::separator();
-            UserFeedback::info("Received shutdown signal (Ctrl+C)");
+        reason = shutdown_signal(pid_file.as_ref().map(PidFile::path)) => reason,
+    };
 
-            let shutdown_spinner = progress.create_spinner("Gracefully stopping server...");
-            // Give a moment for any ongoing requests to complete
-            tokio::time::sleep(tokio::time::Duration::from_millis(500)).await;
-            shutdown_spinner.finish_with_message("✅ Server stopped successfully");
-        }
+    // Print request lines still queued before the shutdown messages
+    announce_shutdown(&mock_server, &reason).await;
+
+    let shutdown_spinner = progress.create_spinner("Gracefully stopping server...");
+    // Give a moment for any ongoing requests to complete
+    tokio::time::sleep(tokio::time::Duration::from_millis(500)).await;
+    shutdown_spinner.finish_with_message("✅ Server stopped successfully");
+
+    write_coverage(&mock_server, coverage.as_ref())
+}
+
+/// Handle `serve --all --path-routing`, serving every snapshot under /_s/<name>/
+pub async fn serve_all_command(
+    requested_port: u16,
+    storage_arg: Option<String>,
+    options: ServeOptions,
+    max_loaded: usize,
+) -> Result<()> {
+    UserFeedback::info("Validating inputs...");
+    ValidationHelper::validate_port(requested_port)?;
+    if max_loaded == 0 {
+        return Err(WebMockError::config(
+            "--max-loaded-snapshots must be at least 1",
+        ));
+    }
+    UserFeedback::success("Input validation passed");
+
+    UserFeedback::info("Initializing storage...");
+    let storage_path = crate::commands::get_storage_path(storage_arg)?;
+    let storage = Storage::new(storage_path);
+
+    // Only metadata is read up front; snapshots are loaded on first request
+    let snapshots = storage.list_snapshots().await?;
+    if snapshots.is_empty() {
+        UserFeedback::warning("No snapshots found in storage yet");
+        UserFeedback::tip("Snapshots captured while the server runs are picked up automatically");
     }
 
+    UserFeedback::info("Checking port availability...");
+    let port = check_and_resolve_port(requested_port)?;
+    let pid_file = options
+        .pid_file
+        .as_deref()
+        .map(PidFile::create)
+        .transpose()?;
+
+    let open_index = options.open.is_some();
+    let stop_options = options.clone();
+    let mock_server = MockServer::with_router(SnapshotRouter::new(storage, options, max_loaded));
+
+    UserFeedback::section("🚀 Starting Mock Server");
+    UserFeedback::line(&format!(
+        "   🌐 Snapshot index: {}",
+        format!("http://localhost:{}/", port).bright_green()
+    ));
+    UserFeedback::line(&format!("   🗂️  Serving {} snapshots:", snapshots.len()));
+    for snapshot in &snapshots {
+        UserFeedback::line(&format!(
+            "      http://localhost:{}{}{}/  ({})",
+            port, ROUTE_PREFIX, snapshot.name, snapshot.url
+        ));
+    }
+    UserFeedback::line(&format!(
+        "   💾 Up to {} snapshots are kept in memory at once",
+        max_loaded
+    ));
+    print_stop_hint(&stop_options);
+
+    UserFeedback::separator();
+    UserFeedback::info("Server logs:");
+
+    let (ready_tx, ready_rx) = oneshot::channel();
+    if open_index {
+        tokio::spawn(async move {
+            if ready_rx.await.is_ok() {
+                browser::open_index(port);
+            }
+        });
+    }
+
+    let server_future = mock_server.start_with_ready(port, ready_tx);
+
+    let reason = tokio::select! {
+        result = server_future => {
+            if let Err(e) = result {
+                UserFeedback::error(&format!("Server error: {}", e.user_message()));
+                return Err(e);
+            }
+            match mock_server.shutdown_reason() {
+                Some(reason) => format!("Shutting down: {}", reason),
+                None => {
+                    UserFeedback::success("Server stopped normally");
+                    return Ok(());
+                }
+            }
+        }
+        reason = shutdown_signal(pid_file.as_ref().map(PidFile::path)) => reason,
+    };
+
+    announce_shutdown(&mock_server, &reason).await;
+    UserFeedback::success("Server stopped successfully");
+
     Ok(())
 }
@@ -0,0 +1,199 @@
+//! Telling scripts which port a server ended up on
+//!
+//! `serve` moves to the next free port when the requested one is taken, so a
+//! script starting several servers can't know their ports up front. With
+//! `--port-file` the server writes its snapshot, port, PID and start time as
+//! JSON once it is listening, and removes the file when it shuts down. A
+//! path ending in a separator, or naming an existing directory, gets one
+//! `<snapshot>.port.json` per server, so servers can share a directory.
+//!
+//! The file is written next to its final path and renamed into place, so a
+//! reader never sees half of it. A file left behind by a server that crashed
+//! is recognized by its PID no longer running and replaced; one written by a
+//! server that is still running is never taken over.
+
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use tracing::debug;
+
+use crate::error::{Result, WebMockError};
+
+/// Suffix of the per-snapshot files written into a `--port-file` directory
+pub const PORT_FILE_SUFFIX: &str = ".port.json";
+
+/// What a port file says about a running server
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PortFileEntry {
+    pub snapshot: String,
+    pub port: u16,
+    pub pid: u32,
+    pub started_at: DateTime<Utc>,
+}
+
+impl PortFileEntry {
+    /// Describe this process serving `snapshot` on `port`, starting now
+    pub fn new(snapshot: &str, port: u16) -> Self {
+        Self {
+            snapshot: snapshot.to_string(),
+            port,
+            pid: std::process::id(),
+            started_at: Utc::now(),
+        }
+    }
+}
+
+/// Where the port file for `snapshot` goes, given the `--port-file` value
+///
+/// A value ending in a path separator, or naming an existing directory, is a
+/// directory and gets `<snapshot>.port.json` inside it.
+pub fn port_file_path(path: &Path, snapshot: &str) -> PathBuf {
+    let names_directory = path
+        .to_string_lossy()
+        .chars()
+        .last()
+        .is_some_and(std::path::is_separator);
+    if names_directory || path.is_dir() {
+        path.join(format!("{}{}", snapshot, PORT_FILE_SUFFIX))
+    } else {
+        path.to_path_buf()
+    }
+}
+
+/// A port file this server may write, removed when dropped once written
+#[derive(Debug)]
+pub struct PortFile {
+    path: PathBuf,
+    written: AtomicBool,
+}
+
+impl PortFile {
+    /// Claim `path` for this server without writing it yet
+    ///
+    /// Fails if the file was written by a server that is still running. A
+    /// file whose server is gone, or that can't be read as a port file, is
+    /// replaced when this server writes its own.
+    pub fn claim(path: &Path) -> Result<Self> {
+        if let Some(entry) = read_port_file(path) {
+            if entry.pid != std::process::id() && process_running(entry.pid) {
+                return Err(WebMockError::config(format!(
+                    "Port file {} belongs to the server for '{}' on port {} (PID {}), which is still running; use another --port-file",
+                    path.display(),
+                    entry.snapshot,
+                    entry.port,
+                    entry.pid
+                )));
+            }
+            debug!(
+                "Replacing stale port file {} of PID {}",
+                path.display(),
+                entry.pid
+            );
+        }
+        Ok(Self {
+            path: path.to_path_buf(),
+            written: AtomicBool::new(false),
+        })
+    }
+
+    /// Write `entry`, replacing the file in one step
+    pub fn write(&self, entry: &PortFileEntry) -> Result<()> {
+        if let Some(parent) = self
+            .path
+            .parent()
+            .filter(|parent| !parent.as_os_str().is_empty())
+        {
+            std::fs::create_dir_all(parent)
+                .map_err(|e| WebMockError::io("create directory", parent, e))?;
+        }
+
+        let json = serde_json::to_string_pretty(entry).map_err(|e| {
+            WebMockError::config(format!("Failed to encode port file as JSON: {}", e))
+        })?;
+        let file_name = self
+            .path
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        let temp = self
+            .path
+            .with_file_name(format!(".{}.{}.tmp", file_name, std::process::id()));
+        std::fs::write(&temp, json + "\n")
+            .map_err(|e| WebMockError::io("write port file", &temp, e))?;
+        if let Err(e) = std::fs::rename(&temp, &self.path) {
+            let _ = std::fs::remove_file(&temp);
+            return Err(WebMockError::io("write port file", &self.path, e));
+        }
+
+        self.written.store(true, Ordering::Relaxed);
+        debug!("Wrote port {} to {}", entry.port, self.path.display());
+        Ok(())
+    }
+
+    /// Get the path of the port file
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+}
+
+impl Drop for PortFile {
+    fn drop(&mut self) {
+        if !self.written.load(Ordering::Relaxed) {
+            return;
+        }
+        // Leave the file alone if another server has replaced it since
+        if read_port_file(&self.path).is_some_and(|entry| entry.pid != std::process::id()) {
+            return;
+        }
+        if let Err(e) = std::fs::remove_file(&self.path) {
+            debug!("Failed to remove port file {}: {}", self.path.display(), e);
+        }
+    }
+}
+
+/// Read a port file, or `None` if there is none or it isn't one
+pub fn read_port_file(path: &Path) -> Option<PortFileEntry> {
+    let contents = std::fs::read_to_string(path).ok()?;
+    serde_json::from_str(&contents).ok()
+}
+
+/// Check whether a process with `pid` is running
+///
+/// Processes of other users count as running.
+#[cfg(unix)]
+pub fn process_running(pid: u32) -> bool {
+    // 0 and negative PIDs would name process groups rather than a process
+    let Some(pid) = libc::pid_t::try_from(pid).ok().filter(|pid| *pid > 0) else {
+        return false;
+    };
+    // SAFETY: signal 0 only checks that the process exists
+    if unsafe { libc::kill(pid, 0) } == 0 {
+        return true;
+    }
+    std::io::Error::last_os_error().raw_os_error() == Some(libc::EPERM)
+}
+
+/// Check whether a process with `pid` is running
+#[cfg(windows)]
+pub fn process_running(pid: u32) -> bool {
+    use std::process::{Command, Stdio};
+    match Command::new("tasklist")
+        .args(["/FI", &format!("PID eq {}", pid), "/NH", "/FO", "CSV"])
+        .stderr(Stdio::null())
+        .output()
+    {
+        Ok(output) => String::from_utf8_lossy(&output.stdout).contains(&format!("\"{}\"", pid)),
+        Err(e) => {
+            debug!("Failed to run tasklist: {}", e);
+            true
+        }
+    }
+}
+
+/// Check whether a process with `pid` is running
+#[cfg(not(any(unix, windows)))]
+pub fn process_running(_pid: u32) -> bool {
+    true
+}
@@ -0,0 +1,133 @@
+//! Servers running against one storage directory
+//!
+//! Each `serve` registers the snapshot it serves, its port and PID in
+//! `servers/` under the storage directory, so a second server for the same
+//! snapshot can point at the first instead of quietly running beside it.
+//! Entries are written like port files, next to their final path and renamed
+//! into place, and removed when the server shuts down. One whose PID is no
+//! longer running belongs to a server that crashed and is removed when the
+//! registry is read.
+
+use std::path::{Path, PathBuf};
+
+use tracing::debug;
+
+use super::port_file::{process_running, read_port_file, PortFileEntry};
+use crate::error::{Result, WebMockError};
+
+/// Directory under the storage directory holding one file per running server
+pub const SERVER_REGISTRY_DIR: &str = "servers";
+
+/// The servers registered in one storage directory
+#[derive(Debug, Clone)]
+pub struct ServerRegistry {
+    dir: PathBuf,
+}
+
+impl ServerRegistry {
+    /// Registry of the storage directory at `storage_path`
+    pub fn new(storage_path: &Path) -> Self {
+        Self {
+            dir: storage_path.join(SERVER_REGISTRY_DIR),
+        }
+    }
+
+    /// Directory the registry files are in
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// Register a running server until the returned lease is dropped
+    pub fn register(&self, entry: &PortFileEntry) -> Result<ServerLease> {
+        std::fs::create_dir_all(&self.dir)
+            .map_err(|e| WebMockError::io("create server registry", &self.dir, e))?;
+        let name = format!("{}-{}", entry.pid, entry.snapshot);
+        let path = self.dir.join(format!("{}.json", name));
+        let temp = self.dir.join(format!(".{}.tmp", name));
+
+        let json = serde_json::to_string_pretty(entry).map_err(|e| {
+            WebMockError::config(format!("Failed to encode server entry as JSON: {}", e))
+        })?;
+        std::fs::write(&temp, json + "\n")
+            .map_err(|e| WebMockError::io("write server registry entry", &temp, e))?;
+        if let Err(e) = std::fs::rename(&temp, &path) {
+            let _ = std::fs::remove_file(&temp);
+            return Err(WebMockError::io("register server", &path, e));
+        }
+
+        debug!(
+            "Registered server for '{}' on port {} at {}",
+            entry.snapshot,
+            entry.port,
+            path.display()
+        );
+        Ok(ServerLease { path })
+    }
+
+    /// Servers that are still running, oldest first
+    ///
+    /// Entries of servers whose process is gone, and files that can't be
+    /// read as entries, are removed along the way.
+    pub fn running(&self) -> Result<Vec<PortFileEntry>> {
+        let listing = match std::fs::read_dir(&self.dir) {
+            Ok(listing) => listing,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(WebMockError::io("read server registry", &self.dir, e)),
+        };
+
+        let mut entries = Vec::new();
+        for item in listing {
+            let path = match item {
+                Ok(item) => item.path(),
+                Err(e) => return Err(WebMockError::io("read server registry", &self.dir, e)),
+            };
+            if path.extension().is_none_or(|extension| extension != "json") {
+                continue;
+            }
+            match read_port_file(&path) {
+                Some(entry) if process_running(entry.pid) => entries.push(entry),
+                _ => {
+                    debug!("Removing stale server registry entry {}", path.display());
+                    // Another reader may have removed it already
+                    let _ = std::fs::remove_file(&path);
+                }
+            }
+        }
+        entries.sort_by_key(|entry| entry.started_at);
+        Ok(entries)
+    }
+
+    /// Servers of other processes serving `snapshot`
+    pub fn serving(&self, snapshot: &str) -> Result<Vec<PortFileEntry>> {
+        Ok(self
+            .running()?
+            .into_iter()
+            .filter(|entry| entry.snapshot == snapshot && entry.pid != std::process::id())
+            .collect())
+    }
+}
+
+/// Registration of a running server, removed when dropped
+#[derive(Debug)]
+pub struct ServerLease {
+    path: PathBuf,
+}
+
+impl ServerLease {
+    /// Get the path of the registry entry
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+}
+
+impl Drop for ServerLease {
+    fn drop(&mut self) {
+        if let Err(e) = std::fs::remove_file(&self.path) {
+            debug!(
+                "Failed to remove server registry entry {}: {}",
+                self.path.display(),
+                e
+            );
+        }
+    }
+}
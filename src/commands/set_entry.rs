@@ -0,0 +1,94 @@
+//! Set-entry command implementation
+//!
+//! A snapshot's primary URL is whatever was passed to `capture`, which may
+//! not be where the page ended up after redirects, or the page a multi-page
+//! capture should open on. `set-entry` makes another URL the primary one
+//! that `serve --open` opens. The URL must have a GET record in the
+//! snapshot unless `--force` is given.
+
+use tracing::info;
+
+use crate::capture::browser::initiators::normalize_url;
+use crate::capture::validation::validate_url;
+use crate::error::{Result, WebMockError};
+use crate::feedback::{UserFeedback, ValidationHelper};
+use crate::storage::{Snapshot, Storage};
+
+/// Handle the set-entry command
+pub async fn set_entry_command(
+    snapshot_name: &str,
+    url: &str,
+    force: bool,
+    storage_arg: Option<String>,
+) -> Result<()> {
+    info!("Setting entry point of '{}' to {}", snapshot_name, url);
+
+    ValidationHelper::validate_snapshot_name(snapshot_name)?;
+    validate_url(url)?;
+
+    let storage_path = crate::commands::get_storage_path(storage_arg)?;
+    let storage = Storage::new(storage_path);
+    let mut snapshot = match storage.load_snapshot(snapshot_name).await {
+        Ok(snapshot) => snapshot,
+        Err(e) => {
+            if matches!(e, WebMockError::SnapshotNotFound(_)) {
+                UserFeedback::tip("Use 'webmock list' to see available snapshots");
+            }
+            return Err(e);
+        }
+    };
+
+    if !force && !has_document(&snapshot, url) {
+        UserFeedback::tip(&format!(
+            "Entry points of '{}': {}",
+            snapshot_name,
+            snapshot.entry_points().join(", ")
+        ));
+        return Err(WebMockError::config(format!(
+            "'{}' has no GET record for {}; pass --force to set it anyway",
+            snapshot_name, url
+        )));
+    }
+
+    set_entry(&mut snapshot, url);
+    let entry_points = snapshot.entry_points();
+
+    storage.rewrite_snapshot(snapshot, false).await?;
+
+    UserFeedback::success(&format!(
+        "Entry point of '{}' set to {}",
+        snapshot_name, url
+    ));
+    print_entry_points(&entry_points);
+    Ok(())
+}
+
+/// Check whether the snapshot answers a GET for `url`
+pub fn has_document(snapshot: &Snapshot, url: &str) -> bool {
+    let url = normalize_url(url);
+    snapshot
+        .requests
+        .iter()
+        .any(|record| record.method == "GET" && normalize_url(&record.url) == url)
+}
+
+/// Make `url` the primary entry point, keeping the old one among the others
+pub fn set_entry(snapshot: &mut Snapshot, url: &str) {
+    let mut entry_points = snapshot.entry_points();
+    entry_points.retain(|entry_point| entry_point != url);
+    snapshot.url = url.to_string();
+    snapshot.entry_points = std::iter::once(url.to_string())
+        .chain(entry_points)
+        .collect();
+}
+
+fn print_entry_points(entry_points: &[String]) {
+    println!("Entry points:");
+    for (index, entry_point) in entry_points.iter().enumerate() {
+        if index == 0 {
+            println!("   {} (primary)", entry_point);
+        } else {
+            println!("   {}", entry_point);
+        }
+    }
+}
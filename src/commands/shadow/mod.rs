@@ -0,0 +1,400 @@
+//! Shadow command implementation
+//!
+//! Before a test suite moves from a live backend to a mock, it's worth
+//! knowing what the mock would get wrong. `webmock shadow` sits between the
+//! suite and the live backend as a plain reverse proxy: every request is
+//! forwarded and answered live, and on the side it is looked up in the
+//! snapshot the way `serve` would. When the proxy stops, the report lists the
+//! requests the snapshot has no record for and the records whose live
+//! response has since changed status or content type.
+
+pub mod report;
+
+use std::convert::Infallible;
+use std::future::Future;
+use std::net::{Ipv4Addr, SocketAddr};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Instant;
+
+use bytes::Bytes;
+use colored::Colorize;
+use http_body_util::{BodyExt, Full};
+use hyper::body::{Body, Incoming};
+use hyper::header::CONTENT_TYPE;
+use hyper::service::service_fn;
+use hyper::{Request, Response, StatusCode};
+use hyper_util::rt::TokioIo;
+use hyper_util::server::conn::auto::Builder;
+use tokio::net::TcpListener;
+use tokio::signal;
+use tracing::{debug, error, info};
+use url::Url;
+
+use crate::error::{Result, WebMockError};
+use crate::feedback::{UserFeedback, ValidationHelper};
+use crate::serve::console::{ConsoleWriter, OutputLevel, RequestLine};
+use crate::serve::forward::{Forwarder, DEFAULT_FORWARD_TIMEOUT};
+use crate::serve::host_stats::format_host_table;
+use crate::serve::{find_matching_record, ForwardTarget, HostStats, HostSummary};
+use crate::storage::{Snapshot, Storage};
+
+pub use report::{
+    CapturedResponse, LiveResponse, ShadowEndpoint, ShadowLog, ShadowReport, Verdict,
+};
+
+/// Base the path of an incoming request is put under before it's rewritten to the upstream
+const LOCAL_BASE: &str = "http://localhost";
+
+/// Endpoints listed per section of the printed report
+const MAX_LISTED_ENDPOINTS: usize = 20;
+
+/// Options for the shadow command
+#[derive(Debug, Clone, Default)]
+pub struct ShadowOptions {
+    /// Local port the proxy listens on
+    pub listen: u16,
+    /// Base URL of the live backend, e.g. `https://staging.example.com`
+    pub upstream: String,
+    /// Where to write the report as JSON when the proxy stops
+    pub report: Option<PathBuf>,
+}
+
+/// The shadow proxy: forwards to the upstream and checks each request against the snapshot
+pub struct Shadow {
+    snapshot: Snapshot,
+    forwarder: Forwarder,
+    log: ShadowLog,
+    hosts: HostStats,
+    console: Arc<ConsoleWriter>,
+}
+
+impl Shadow {
+    pub fn new(snapshot: Snapshot, target: ForwardTarget, console: Arc<ConsoleWriter>) -> Self {
+        let log = ShadowLog::new(&snapshot.name, target.base().as_str());
+        Self {
+            snapshot,
+            forwarder: Forwarder::new(target, DEFAULT_FORWARD_TIMEOUT),
+            log,
+            hosts: HostStats::default(),
+            console,
+        }
+    }
+
+    /// Everything observed so far
+    pub fn report(&self) -> ShadowReport {
+        self.log.report()
+    }
+
+    /// Hits and misses per upstream host
+    pub fn host_summary(&self) -> Vec<HostSummary> {
+        self.hosts.summary()
+    }
+
+    /// Accept connections on `listener` until `shutdown` completes
+    pub async fn serve(
+        self: Arc<Self>,
+        listener: TcpListener,
+        shutdown: impl Future<Output = ()>,
+    ) -> Result<()> {
+        tokio::pin!(shutdown);
+        loop {
+            let accepted = tokio::select! {
+                accepted = listener.accept() => accepted,
+                _ = &mut shutdown => return Ok(()),
+            };
+            let (stream, _) = accepted.map_err(|e| {
+                error!("Accept error: {}", e);
+                WebMockError::Proxy(format!("Accept error: {}", e))
+            })?;
+            let shadow = Arc::clone(&self);
+            tokio::spawn(async move {
+                let service = service_fn(move |request: Request<Incoming>| {
+                    let shadow = Arc::clone(&shadow);
+                    async move { Ok::<_, Infallible>(shadow.handle(request).await) }
+                });
+                let builder = Builder::new(hyper_util::rt::TokioExecutor::new());
+                if let Err(e) = builder
+                    .serve_connection(TokioIo::new(stream), service)
+                    .await
+                {
+                    debug!("Shadow connection closed: {}", e);
+                }
+            });
+        }
+    }
+
+    /// Forward one request and compare the live answer with the snapshot's
+    async fn handle(&self, request: Request<Incoming>) -> Response<Full<Bytes>> {
+        let started = Instant::now();
+        let (parts, body) = request.into_parts();
+        let path = parts
+            .uri
+            .path_and_query()
+            .map(|path| path.as_str())
+            .unwrap_or("/");
+        let local = format!("{}{}", LOCAL_BASE, path);
+        let Some(url) = self.forwarder.url_for(&local) else {
+            return plain_response(
+                StatusCode::BAD_REQUEST,
+                format!("Invalid request path {}", path),
+            );
+        };
+        let body = match body.collect().await {
+            Ok(body) => body.to_bytes(),
+            Err(e) => {
+                return plain_response(
+                    StatusCode::BAD_REQUEST,
+                    format!("Failed to read request body: {}", e),
+                )
+            }
+        };
+
+        let record = find_matching_record(&self.snapshot, &parts.method, &url);
+        let captured = record.map(|record| CapturedResponse {
+            index: self
+                .snapshot
+                .requests
+                .iter()
+                .position(|candidate| std::ptr::eq(candidate, record))
+                .unwrap_or_default(),
+            status: record.response.status,
+            content_type: record
+                .response
+                .headers
+                .iter()
+                .find(|(name, _)| name.eq_ignore_ascii_case("content-type"))
+                .and_then(|(_, value)| essence(value)),
+        });
+
+        let exchange = self
+            .forwarder
+            .exchange(&parts.method, &local, &parts.headers, body)
+            .await;
+        let live = exchange
+            .as_ref()
+            .map(|response| LiveResponse {
+                status: response.status().as_u16(),
+                content_type: response
+                    .headers()
+                    .get(CONTENT_TYPE)
+                    .and_then(|value| value.to_str().ok())
+                    .and_then(essence),
+            })
+            .map_err(String::as_str);
+        let verdict = self.log.observe(
+            parts.method.as_str(),
+            &url,
+            captured.as_ref(),
+            live.as_ref().map_err(|error| *error),
+        );
+        match record {
+            Some(record) => self.hosts.record_hit(&url, record.response.body.len()),
+            None => self.hosts.record_miss(&url),
+        }
+
+        let (icon, detail) = match &verdict {
+            Verdict::Hit => ("✅", "in snapshot".to_string()),
+            Verdict::Miss => ("❌", "not in snapshot".to_string()),
+            Verdict::Drift(differences) => ("⚠️ ", format!("drift: {}", differences.join(", "))),
+            Verdict::Unanswered => ("🔌", "in snapshot, upstream failed".to_string()),
+        };
+        let response = match exchange {
+            Ok(response) => response,
+            Err(error) => plain_response(
+                StatusCode::BAD_GATEWAY,
+                format!(
+                    "Forwarding to {} failed (WebMock shadow):\n\n{}\n",
+                    url, error
+                ),
+            ),
+        };
+        self.console.request(RequestLine {
+            icon,
+            method: parts.method.to_string(),
+            url,
+            status: response.status().as_u16(),
+            detail,
+            record_index: captured.as_ref().map(|captured| captured.index),
+            body_size: Some(response.body().size_hint().exact().unwrap_or_default() as usize),
+            started,
+        });
+        response
+    }
+}
+
+/// MIME type of a Content-Type value, without parameters
+fn essence(content_type: &str) -> Option<String> {
+    let essence = content_type.split(';').next().unwrap_or_default().trim();
+    (!essence.is_empty()).then(|| essence.to_ascii_lowercase())
+}
+
+fn plain_response(status: StatusCode, message: String) -> Response<Full<Bytes>> {
+    Response::builder()
+        .status(status)
+        .header(CONTENT_TYPE, "text/plain; charset=utf-8")
+        .body(Full::new(Bytes::from(message)))
+        .unwrap()
+}
+
+/// Check whether any record of `snapshot` was captured from the host of `upstream`
+pub fn snapshot_covers_host(snapshot: &Snapshot, upstream: &Url) -> bool {
+    let Some(host) = upstream.host_str() else {
+        return false;
+    };
+    snapshot.requests.iter().any(|record| {
+        Url::parse(&record.url)
+            .ok()
+            .and_then(|url| {
+                url.host_str()
+                    .map(|candidate| candidate.eq_ignore_ascii_case(host))
+            })
+            .unwrap_or(false)
+    })
+}
+
+/// Handle the shadow command
+///
+/// Runs until Ctrl+C, then prints the report and writes it to `--report`.
+pub async fn shadow_command(
+    snapshot_name: &str,
+    storage_arg: Option<String>,
+    options: &ShadowOptions,
+) -> Result<ShadowReport> {
+    info!("Starting shadow command for snapshot: {}", snapshot_name);
+
+    ValidationHelper::validate_snapshot_name(snapshot_name)?;
+    let target = ForwardTarget::parse_option(&options.upstream, "--upstream")?;
+    let storage_path = crate::commands::get_storage_path(storage_arg)?;
+    let storage = Storage::new(storage_path);
+    let snapshot = match storage.load_snapshot(snapshot_name).await {
+        Ok(snapshot) => snapshot,
+        Err(e) => {
+            if matches!(e, WebMockError::SnapshotNotFound(_)) {
+                UserFeedback::tip("Use 'webmock list' to see available snapshots");
+            }
+            return Err(e);
+        }
+    };
+    if !snapshot_covers_host(&snapshot, target.base()) {
+        UserFeedback::warning(&format!(
+            "Snapshot '{}' has no records for {}, so every request will be a miss",
+            snapshot_name,
+            target.base().host_str().unwrap_or_default()
+        ));
+    }
+
+    let addr = SocketAddr::from((Ipv4Addr::LOCALHOST, options.listen));
+    let listener = TcpListener::bind(addr).await.map_err(|e| {
+        error!("Failed to bind to {}: {}", addr, e);
+        WebMockError::Proxy(if e.to_string().contains("Address already in use") {
+            format!(
+                "Port {} is already in use by another process",
+                options.listen
+            )
+        } else {
+            format!("Shadow proxy failed to start: {}", e)
+        })
+    })?;
+    let addr = listener.local_addr().unwrap_or(addr);
+
+    UserFeedback::success("Shadow proxy started");
+    UserFeedback::line(&format!("   📡 Listening on: http://{}", addr));
+    UserFeedback::line(&format!("   🌐 Forwarding to: {}", target.base()));
+    UserFeedback::line(&format!(
+        "   📦 Checking against: {} ({} requests)",
+        snapshot.name,
+        snapshot.requests.len()
+    ));
+    UserFeedback::line("   Press Ctrl+C to stop and see the report");
+
+    let console = Arc::new(ConsoleWriter::stdout(OutputLevel::Normal));
+    let shadow = Arc::new(Shadow::new(snapshot, target, Arc::clone(&console)));
+    Arc::clone(&shadow)
+        .serve(listener, async {
+            if let Err(e) = signal::ctrl_c().await {
+                error!("Failed to listen for Ctrl+C: {}", e);
+            }
+        })
+        .await?;
+    console.flush().await;
+    UserFeedback::line("");
+
+    let report = shadow.report();
+    print_report(&report, &shadow.host_summary());
+    if let Some(path) = &options.report {
+        write_report(&report, path)?;
+        UserFeedback::success(&format!("Wrote shadow report to {}", path.display()));
+    }
+    Ok(report)
+}
+
+/// Write `report` as pretty JSON
+pub fn write_report(report: &ShadowReport, path: &Path) -> Result<()> {
+    let json = serde_json::to_string_pretty(report).map_err(|e| {
+        WebMockError::config(format!("Failed to encode shadow report as JSON: {}", e))
+    })?;
+    std::fs::write(path, json + "\n")
+        .map_err(|e| WebMockError::config(format!("Failed to write {}: {}", path.display(), e)))
+}
+
+fn print_report(report: &ShadowReport, hosts: &[HostSummary]) {
+    UserFeedback::section(&format!("🕵️  Shadow of '{}'", report.snapshot));
+    if report.requests == 0 {
+        UserFeedback::warning("No requests went through the shadow proxy");
+        return;
+    }
+
+    print!("{}", format_host_table(hosts));
+    println!(
+        "\n   {} requests: {} in snapshot ({:.1}%), {} missed, {} drifted",
+        report.requests,
+        report.hits,
+        report.coverage(),
+        report.misses,
+        report.drifted
+    );
+
+    let missed: Vec<&ShadowEndpoint> = report.missed().collect();
+    if !missed.is_empty() {
+        println!("\n   Not in snapshot:");
+        for endpoint in missed.iter().take(MAX_LISTED_ENDPOINTS) {
+            println!(
+                "   ❌ {} {} ({}×)",
+                endpoint.method, endpoint.url, endpoint.requests
+            );
+        }
+        if missed.len() > MAX_LISTED_ENDPOINTS {
+            println!(
+                "{}",
+                format!("      ... and {} more", missed.len() - MAX_LISTED_ENDPOINTS).dimmed()
+            );
+        }
+    }
+
+    let drifted: Vec<&ShadowEndpoint> = report.drifted_endpoints().collect();
+    if !drifted.is_empty() {
+        println!("\n   Drifted from the snapshot:");
+        for endpoint in drifted.iter().take(MAX_LISTED_ENDPOINTS) {
+            println!("   ⚠️  {} {}", endpoint.method, endpoint.url);
+            println!("      {}", endpoint.differences.join(", "));
+        }
+        if drifted.len() > MAX_LISTED_ENDPOINTS {
+            println!(
+                "{}",
+                format!(
+                    "      ... and {} more",
+                    drifted.len() - MAX_LISTED_ENDPOINTS
+                )
+                .dimmed()
+            );
+        }
+    }
+
+    if report.misses > 0 || report.drifted > 0 {
+        UserFeedback::tip(&format!(
+            "Refresh the snapshot with 'webmock recapture {}' before switching to the mock",
+            report.snapshot
+        ));
+    }
+}
@@ -0,0 +1,239 @@
+//! What the shadow proxy saw, and how much of it the snapshot covers
+//!
+//! Requests are grouped by method and upstream URL. Each group remembers the
+//! record the snapshot would have answered with, if any, and how the latest
+//! live response compares with it.
+
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+use serde::Serialize;
+
+/// A live response, as far as drift is concerned
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LiveResponse {
+    pub status: u16,
+    /// MIME type without parameters
+    pub content_type: Option<String>,
+}
+
+/// A recorded response, as far as drift is concerned
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CapturedResponse {
+    /// Position of the record in the snapshot
+    pub index: usize,
+    pub status: u16,
+    /// MIME type the origin declared, without parameters
+    pub content_type: Option<String>,
+}
+
+/// How one request compared with the snapshot
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Verdict {
+    /// The snapshot has a record and the live response agrees with it
+    Hit,
+    /// The snapshot has no record, so the mock would have answered 404
+    Miss,
+    /// The snapshot has a record, but the live response differs: one line per difference
+    Drift(Vec<String>),
+    /// The upstream didn't answer, so only the match was checked
+    Unanswered,
+}
+
+/// Requests for one method and URL
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ShadowEndpoint {
+    pub method: String,
+    /// URL on the upstream
+    pub url: String,
+    pub requests: u64,
+    /// Position of the matching record in the snapshot, `None` for a miss
+    pub record: Option<usize>,
+    pub captured_status: Option<u16>,
+    pub captured_content_type: Option<String>,
+    /// Latest live status, `None` when the upstream never answered
+    pub live_status: Option<u16>,
+    pub live_content_type: Option<String>,
+    /// Requests whose live response differed from the record
+    pub drifted_requests: u64,
+    /// Differences of the latest drifted response, e.g. `status 200 → 404`
+    pub differences: Vec<String>,
+    /// Latest upstream error
+    pub error: Option<String>,
+}
+
+impl ShadowEndpoint {
+    pub fn matched(&self) -> bool {
+        self.record.is_some()
+    }
+}
+
+/// Coverage of the live traffic by a snapshot, written by `shadow --report`
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct ShadowReport {
+    pub snapshot: String,
+    pub upstream: String,
+    pub requests: u64,
+    /// Requests the snapshot had a record for
+    pub hits: u64,
+    /// Requests the mock would have answered 404
+    pub misses: u64,
+    /// Matched requests whose live status or content type differed from the record
+    pub drifted: u64,
+    /// Endpoints in the order they were first requested
+    pub endpoints: Vec<ShadowEndpoint>,
+}
+
+impl ShadowReport {
+    /// Percentage of requests the snapshot had a record for
+    pub fn coverage(&self) -> f64 {
+        if self.requests == 0 {
+            return 100.0;
+        }
+        self.hits as f64 * 100.0 / self.requests as f64
+    }
+
+    /// Endpoints the snapshot has no record for
+    pub fn missed(&self) -> impl Iterator<Item = &ShadowEndpoint> {
+        self.endpoints.iter().filter(|endpoint| !endpoint.matched())
+    }
+
+    /// Matched endpoints whose live responses differed from the record
+    pub fn drifted_endpoints(&self) -> impl Iterator<Item = &ShadowEndpoint> {
+        self.endpoints
+            .iter()
+            .filter(|endpoint| endpoint.drifted_requests > 0)
+    }
+}
+
+/// Compare a live response with the record the snapshot would have served
+///
+/// A record without a declared content type was served with a sniffed one,
+/// which the live response can't be held to, so only its status is compared.
+pub fn compare(captured: &CapturedResponse, live: &LiveResponse) -> Vec<String> {
+    let mut differences = Vec::new();
+    if captured.status != live.status {
+        differences.push(format!("status {} → {}", captured.status, live.status));
+    }
+    let normalized = |content_type: &Option<String>| {
+        content_type
+            .as_deref()
+            .map(|value| value.trim().to_ascii_lowercase())
+            .filter(|value| !value.is_empty())
+    };
+    let (before, after) = (
+        normalized(&captured.content_type),
+        normalized(&live.content_type),
+    );
+    if before.is_some() && before != after {
+        differences.push(format!(
+            "content type {} → {}",
+            before.as_deref().unwrap_or("none"),
+            after.as_deref().unwrap_or("none")
+        ));
+    }
+    differences
+}
+
+/// Collects observations while the shadow proxy runs
+#[derive(Debug, Default)]
+pub struct ShadowLog {
+    state: Mutex<LogState>,
+}
+
+#[derive(Debug, Default)]
+struct LogState {
+    report: ShadowReport,
+    /// Position in `report.endpoints` by method and URL
+    positions: HashMap<(String, String), usize>,
+}
+
+impl ShadowLog {
+    pub fn new(snapshot: &str, upstream: &str) -> Self {
+        Self {
+            state: Mutex::new(LogState {
+                report: ShadowReport {
+                    snapshot: snapshot.to_string(),
+                    upstream: upstream.to_string(),
+                    ..ShadowReport::default()
+                },
+                positions: HashMap::new(),
+            }),
+        }
+    }
+
+    /// Count a request to `url` and how the snapshot compares with the live answer
+    pub fn observe(
+        &self,
+        method: &str,
+        url: &str,
+        captured: Option<&CapturedResponse>,
+        live: std::result::Result<&LiveResponse, &str>,
+    ) -> Verdict {
+        let verdict = match (captured, live) {
+            (None, _) => Verdict::Miss,
+            (Some(_), Err(_)) => Verdict::Unanswered,
+            (Some(captured), Ok(live)) => {
+                let differences = compare(captured, live);
+                if differences.is_empty() {
+                    Verdict::Hit
+                } else {
+                    Verdict::Drift(differences)
+                }
+            }
+        };
+
+        let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        let LogState { report, positions } = &mut *state;
+        report.requests += 1;
+        match &verdict {
+            Verdict::Miss => report.misses += 1,
+            Verdict::Drift(_) => {
+                report.hits += 1;
+                report.drifted += 1;
+            }
+            Verdict::Hit | Verdict::Unanswered => report.hits += 1,
+        }
+
+        let key = (method.to_string(), url.to_string());
+        let position = *positions.entry(key).or_insert_with(|| {
+            report.endpoints.push(ShadowEndpoint {
+                method: method.to_string(),
+                url: url.to_string(),
+                requests: 0,
+                record: captured.map(|captured| captured.index),
+                captured_status: captured.map(|captured| captured.status),
+                captured_content_type: captured.and_then(|captured| captured.content_type.clone()),
+                live_status: None,
+                live_content_type: None,
+                drifted_requests: 0,
+                differences: Vec::new(),
+                error: None,
+            });
+            report.endpoints.len() - 1
+        });
+        let endpoint = &mut report.endpoints[position];
+        endpoint.requests += 1;
+        match live {
+            Ok(live) => {
+                endpoint.live_status = Some(live.status);
+                endpoint.live_content_type = live.content_type.clone();
+            }
+            Err(error) => endpoint.error = Some(error.to_string()),
+        }
+        if let Verdict::Drift(differences) = &verdict {
+            endpoint.drifted_requests += 1;
+            endpoint.differences = differences.clone();
+        }
+        verdict
+    }
+
+    /// Everything observed so far
+    pub fn report(&self) -> ShadowReport {
+        self.state
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .report
+            .clone()
+    }
+}
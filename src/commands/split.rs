@@ -0,0 +1,151 @@
+//! Split command implementation
+//!
+//! Copies the records of a snapshot that match a URL glob, and optionally a
+//! method, into a new snapshot. Handy to hand over only the endpoints a test
+//! suite needs from a capture of a whole app, or with `--invert` to strip
+//! large media out of a snapshot.
+
+use globset::{Glob, GlobMatcher};
+use tracing::info;
+
+use crate::capture::proxy::RequestRecord;
+use crate::commands::inspect::format_size;
+use crate::error::{Result, WebMockError};
+use crate::feedback::{ProgressReporter, UserFeedback, ValidationHelper};
+use crate::storage::Storage;
+
+/// Options for the split command
+#[derive(Debug, Clone, Default)]
+pub struct SplitOptions {
+    /// Glob the record URL must match, e.g. `https://app.example.com/api/billing/*`
+    pub url_glob: String,
+    /// Method the record must have, if any
+    pub method: Option<String>,
+    /// Keep the records that don't match instead
+    pub invert: bool,
+}
+
+/// Decides which records a split keeps
+#[derive(Debug, Clone)]
+pub struct RecordFilter {
+    url: GlobMatcher,
+    method: Option<String>,
+    invert: bool,
+}
+
+impl RecordFilter {
+    /// Build a filter, failing on an invalid glob or method
+    pub fn new(options: &SplitOptions) -> Result<Self> {
+        let url = Glob::new(&options.url_glob)
+            .map_err(|e| {
+                WebMockError::config(format!("Invalid --url-glob '{}': {}", options.url_glob, e))
+            })?
+            .compile_matcher();
+        let method = options
+            .method
+            .as_deref()
+            .map(|method| {
+                let method = method.trim();
+                if method.is_empty() || !method.chars().all(|c| c.is_ascii_alphabetic()) {
+                    return Err(WebMockError::config(format!(
+                        "Invalid --method '{}', expected e.g. GET",
+                        method
+                    )));
+                }
+                Ok(method.to_ascii_uppercase())
+            })
+            .transpose()?;
+
+        Ok(Self {
+            url,
+            method,
+            invert: options.invert,
+        })
+    }
+
+    /// Check whether a record matches the URL glob and method
+    pub fn matches(&self, record: &RequestRecord) -> bool {
+        let method_matches = self
+            .method
+            .as_deref()
+            .is_none_or(|method| method.eq_ignore_ascii_case(&record.method));
+        method_matches && self.url.is_match(&record.url)
+    }
+
+    /// Check whether the new snapshot keeps a record
+    pub fn keeps(&self, record: &RequestRecord) -> bool {
+        self.matches(record) != self.invert
+    }
+
+    /// Describe the filter for the new snapshot's metadata
+    pub fn describe(&self) -> String {
+        let criteria = match &self.method {
+            Some(method) => format!("{} {}", method, self.url.glob()),
+            None => self.url.glob().to_string(),
+        };
+        if self.invert {
+            format!("all except {}", criteria)
+        } else {
+            criteria
+        }
+    }
+}
+
+/// Handle the split command
+pub async fn split_command(
+    source: &str,
+    destination: &str,
+    storage_arg: Option<String>,
+    options: &SplitOptions,
+) -> Result<()> {
+    info!("Starting split command: {} -> {}", source, destination);
+
+    ValidationHelper::validate_snapshot_name(source)?;
+    ValidationHelper::validate_snapshot_name(destination)?;
+    let filter = RecordFilter::new(options)?;
+    let description = filter.describe();
+
+    let storage_path = crate::commands::get_storage_path(storage_arg)?;
+    let storage = Storage::new(storage_path);
+
+    let progress = ProgressReporter::new();
+    let spinner = progress.create_spinner(&format!(
+        "Copying records of '{}' matching {}...",
+        source, description
+    ));
+    let summary = match storage
+        .split_snapshot(source, destination, &description, move |record| {
+            filter.keeps(record)
+        })
+        .await
+    {
+        Ok(summary) => {
+            spinner.finish_with_message(format!("✅ Created snapshot '{}'", destination));
+            summary
+        }
+        Err(e) => {
+            spinner.finish_with_message("❌ Split failed");
+            if matches!(e, WebMockError::SnapshotNotFound(_)) {
+                UserFeedback::tip("Use 'webmock list' to see available snapshots");
+            }
+            return Err(e);
+        }
+    };
+
+    UserFeedback::success(&format!(
+        "Kept {} of {} records ({} left out), {} on disk",
+        summary.kept,
+        summary.total,
+        summary.total - summary.kept,
+        format_size(summary.size as usize)
+    ));
+    if summary.kept == 0 {
+        UserFeedback::warning(&format!(
+            "No records matched, so '{}' is empty; check the glob with 'webmock inspect {}'",
+            destination, source
+        ));
+    }
+    UserFeedback::tip(&format!("Serve it with 'webmock serve {}'", destination));
+
+    Ok(())
+}
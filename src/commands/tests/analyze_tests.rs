@@ -0,0 +1,160 @@
+use tempfile::TempDir;
+use url::Url;
+
+use crate::capture::proxy::RequestRecord;
+use crate::commands::analyze::references::{extract_references, DocumentKind};
+use crate::commands::analyze::{analyze_command, AnalyzeOptions, ResourceReport};
+use crate::storage::Storage;
+use crate::test_utils::test_helpers::{create_test_record, create_test_snapshot_with_name};
+
+fn record(url: &str, content_type: &str, body: &str) -> RequestRecord {
+    let headers = [("content-type", content_type)];
+    create_test_record("GET", url, 200, &headers, body.as_bytes())
+}
+
+fn references(body: &str, kind: DocumentKind, url: &str) -> Vec<String> {
+    extract_references(body, kind, &Url::parse(url).unwrap())
+        .into_iter()
+        .map(String::from)
+        .collect()
+}
+
+#[test]
+fn test_extracts_html_resource_references() {
+    let html = r#"<!DOCTYPE html>
+<html><head>
+  <link rel="stylesheet" href="css/site.css">
+  <link rel="canonical" href="https://shop.example/">
+  <link rel="apple-touch-icon" href=/touch.png>
+  <script src='//cdn.example/app.js?v=1&amp;x=2'></script>
+  <!-- <img src="commented-out.png"> -->
+  <style>body { background: url("/img/bg.png"); }</style>
+</head><body>
+  <a href="/next">Next</a>
+  <img src="../logo.png#top" srcset="logo-1x.png 1x, logo-2x.png 2x" alt="logo">
+  <img srcset="data:image/png;base64,iVBOR,w0KG 1x, /hd.png 2x">
+  <video poster="/poster.jpg"><source src="/movie.mp4" type="video/mp4"></video>
+  <div style="background-image: url(/img/bg.png)"></div>
+  <img src="mailto:someone@shop.example">
+</body></html>"#;
+
+    assert_eq!(
+        references(
+            html,
+            DocumentKind::Html,
+            "https://shop.example/pages/item.html"
+        ),
+        vec![
+            "https://shop.example/pages/css/site.css",
+            "https://shop.example/touch.png",
+            "https://cdn.example/app.js?v=1&x=2",
+            "https://shop.example/logo.png",
+            "https://shop.example/pages/logo-1x.png",
+            "https://shop.example/pages/logo-2x.png",
+            "https://shop.example/hd.png",
+            "https://shop.example/poster.jpg",
+            "https://shop.example/movie.mp4",
+            "https://shop.example/img/bg.png",
+        ]
+    );
+}
+
+#[test]
+fn test_extracts_references_relative_to_base_and_stylesheet() {
+    let html = r#"<base href="https://static.example/v2/"><img src="a.png">"#;
+    assert_eq!(
+        references(html, DocumentKind::Html, "https://shop.example/"),
+        vec!["https://static.example/v2/a.png"]
+    );
+
+    let css = r#"@import "fonts.css";
+@font-face { src: url('../fonts/a.woff2') format("woff2"), url(data:font/woff2;base64,AAAA); }
+.hero { background: url( //cdn.example/hero.jpg ); }"#;
+    assert_eq!(
+        references(css, DocumentKind::Css, "https://shop.example/css/site.css"),
+        vec![
+            "https://shop.example/fonts/a.woff2",
+            "https://cdn.example/hero.jpg",
+            "https://shop.example/css/fonts.css",
+        ]
+    );
+}
+
+#[test]
+fn test_report_lists_resources_without_records() {
+    let mut snapshot = create_test_snapshot_with_name("shop");
+    snapshot.requests = vec![
+        record(
+            "https://shop.example/",
+            "text/html; charset=utf-8",
+            r#"<link rel="stylesheet" href="/site.css"><img src="/logo.png"><img src="/missing.png">"#,
+        ),
+        record(
+            "https://shop.example/about",
+            "text/html",
+            r#"<img src="https://shop.example/missing.png">"#,
+        ),
+        record(
+            "https://shop.example/site.css",
+            "text/css",
+            "body { background: url(bg.png) }",
+        ),
+        record("https://shop.example/logo.png", "image/png", ""),
+    ];
+
+    let report = ResourceReport::analyze(&snapshot);
+    assert_eq!(report.documents, 3);
+    assert_eq!(report.references, 4);
+    assert!(!report.is_self_contained());
+    let missing: Vec<(&str, Vec<&str>)> = report
+        .missing
+        .iter()
+        .map(|resource| {
+            (
+                resource.url.as_str(),
+                resource.referenced_by.iter().map(String::as_str).collect(),
+            )
+        })
+        .collect();
+    assert_eq!(
+        missing,
+        vec![
+            (
+                "https://shop.example/missing.png",
+                vec!["https://shop.example/", "https://shop.example/about"]
+            ),
+            (
+                "https://shop.example/bg.png",
+                vec!["https://shop.example/site.css"]
+            ),
+        ]
+    );
+}
+
+#[tokio::test]
+async fn test_analyze_command_strict_fails_on_missing_resources() {
+    let temp_dir = TempDir::new().unwrap();
+    let storage = Storage::new(temp_dir.path().to_path_buf());
+    let mut snapshot = create_test_snapshot_with_name("shop");
+    snapshot.requests = vec![record(
+        "https://shop.example/",
+        "text/html",
+        r#"<script src="/app.js"></script>"#,
+    )];
+    storage.save_snapshot(snapshot).await.unwrap();
+    let storage_arg = || Some(temp_dir.path().to_string_lossy().to_string());
+
+    let report = analyze_command("shop", storage_arg(), &AnalyzeOptions::default())
+        .await
+        .unwrap();
+    assert_eq!(report.missing.len(), 1);
+
+    let strict = AnalyzeOptions {
+        strict: true,
+        ..AnalyzeOptions::default()
+    };
+    let error = analyze_command("shop", storage_arg(), &strict)
+        .await
+        .unwrap_err();
+    assert!(error.to_string().contains("1 referenced resources"));
+}
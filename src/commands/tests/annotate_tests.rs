@@ -0,0 +1,113 @@
+use tempfile::TempDir;
+
+use crate::commands::annotate::{annotate, annotate_command, AnnotationChange};
+use crate::commands::inspect::InspectReport;
+use crate::commands::set_entry::set_entry_command;
+use crate::storage::{ServeDefaults, Storage};
+use crate::test_utils::test_helpers::create_multi_request_snapshot;
+
+fn set(note: &str) -> AnnotationChange {
+    AnnotationChange::Set(note.to_string())
+}
+
+#[test]
+fn test_annotate_picks_records_by_listing_number() {
+    let mut snapshot = create_multi_request_snapshot("site");
+
+    // The listing is sorted by URL: /, /api/data, /style.css
+    assert_eq!(
+        annotate(&mut snapshot, 3, set("  old stylesheet ")).unwrap(),
+        None
+    );
+    let style = snapshot
+        .requests
+        .iter()
+        .find(|record| record.url.ends_with("/style.css"))
+        .unwrap();
+    assert_eq!(snapshot.annotation(style), Some("old stylesheet"));
+    assert_eq!(snapshot.annotations.len(), 1);
+
+    // The stored order is left alone
+    assert_eq!(snapshot.requests[0].url, "https://example.com/");
+    assert_eq!(snapshot.requests[1].url, "https://example.com/style.css");
+
+    // Setting a note again replaces it
+    assert_eq!(
+        annotate(&mut snapshot, 3, set("new stylesheet")).unwrap(),
+        Some("old stylesheet".to_string())
+    );
+    assert_eq!(
+        annotate(&mut snapshot, 3, AnnotationChange::Remove).unwrap(),
+        Some("new stylesheet".to_string())
+    );
+    assert_eq!(
+        annotate(&mut snapshot, 3, AnnotationChange::Remove).unwrap(),
+        None
+    );
+    assert!(snapshot.annotations.is_empty());
+}
+
+#[test]
+fn test_annotate_rejects_unknown_records_and_empty_notes() {
+    let mut snapshot = create_multi_request_snapshot("site");
+
+    let error = annotate(&mut snapshot, 4, set("missing")).unwrap_err();
+    assert!(error.to_string().contains("records 1 to 3"));
+    assert!(annotate(&mut snapshot, 0, set("missing")).is_err());
+    assert!(annotate(&mut snapshot, 1, set("   ")).is_err());
+    assert!(snapshot.annotations.is_empty());
+}
+
+#[tokio::test]
+async fn test_annotations_survive_other_commands() {
+    let temp_dir = TempDir::new().unwrap();
+    let storage = Storage::new(temp_dir.path().to_path_buf());
+    storage
+        .save_snapshot(create_multi_request_snapshot("site"))
+        .await
+        .unwrap();
+    let storage_arg = || Some(temp_dir.path().to_string_lossy().to_string());
+
+    annotate_command("site", 2, set("stale, re-capture after v2"), storage_arg())
+        .await
+        .unwrap();
+    annotate_command("site", 1, set("landing page"), storage_arg())
+        .await
+        .unwrap();
+    annotate_command("site", 1, AnnotationChange::Remove, storage_arg())
+        .await
+        .unwrap();
+
+    // Commands that save the snapshot again keep its notes
+    set_entry_command("site", "https://example.com/api/data", false, storage_arg())
+        .await
+        .unwrap();
+    let defaults = ServeDefaults {
+        port: Some(8090),
+        ..ServeDefaults::default()
+    };
+    crate::commands::config_command("site", defaults, false, storage_arg())
+        .await
+        .unwrap();
+
+    let snapshot = storage.load_snapshot("site").await.unwrap();
+    let report = InspectReport::new(&snapshot);
+    let notes: Vec<(usize, Option<&str>)> = report
+        .records
+        .iter()
+        .map(|record| (record.index, record.note.as_deref()))
+        .collect();
+    assert_eq!(
+        notes,
+        [
+            (1, None),
+            (2, Some("stale, re-capture after v2")),
+            (3, None)
+        ]
+    );
+    assert_eq!(report.records[1].url, "https://example.com/api/data");
+
+    let json = serde_json::to_value(&report).unwrap();
+    assert_eq!(json["records"][1]["note"], "stale, re-capture after v2");
+    assert!(json["records"][0].get("note").is_none());
+}
@@ -0,0 +1,367 @@
+use tempfile::TempDir;
+
+use crate::capture::proxy::RequestRecord;
+use crate::commands::assert::expectation::parse_expectations;
+use crate::commands::assert::{
+    assert_command, check_expectation, AssertOptions, AssertReport, ContentCategory, Expectation,
+    StatusExpectation,
+};
+use crate::error::WebMockError;
+use crate::storage::{Snapshot, Storage};
+use crate::test_utils::test_helpers::{create_test_record, create_test_snapshot_with_name};
+
+fn record(method: &str, url: &str, status: u16, content_type: &str, body: &str) -> RequestRecord {
+    let headers = [("content-type", content_type)];
+    create_test_record(method, url, status, &headers, body.as_bytes())
+}
+
+fn api_snapshot() -> Snapshot {
+    let mut snapshot = create_test_snapshot_with_name("api");
+    snapshot.requests = vec![
+        record(
+            "GET",
+            "https://api.example.com/users",
+            200,
+            "application/json; charset=utf-8",
+            r#"[{"id":1,"name":"alice"}]"#,
+        ),
+        record(
+            "POST",
+            "https://api.example.com/users",
+            201,
+            "application/json",
+            "{}",
+        ),
+        record(
+            "GET",
+            "https://example.com/app.js",
+            200,
+            "text/javascript",
+            "boot()",
+        ),
+        record(
+            "GET",
+            "https://example.com/missing.png",
+            404,
+            "text/html",
+            "Not found",
+        ),
+    ];
+    snapshot
+}
+
+fn check(line: &str) -> Result<(), String> {
+    check_expectation(&api_snapshot(), &Expectation::parse(line).unwrap())
+}
+
+fn parse_error(line: &str) -> String {
+    match Expectation::parse(line) {
+        Err(WebMockError::Config(message)) => message,
+        other => panic!("expected a config error for '{}', got {:?}", line, other),
+    }
+}
+
+#[test]
+fn test_parse_expectation_line() {
+    let expectation =
+        Expectation::parse("get https://api.example.com/users -> 200 json body-contains \"alice\"")
+            .unwrap();
+    assert_eq!(expectation.method, "GET");
+    assert_eq!(expectation.url, "https://api.example.com/users");
+    assert_eq!(expectation.status, Some(StatusExpectation::Code(200)));
+    assert_eq!(expectation.content_type, Some(ContentCategory::Json));
+    assert_eq!(expectation.body_contains.as_deref(), Some("alice"));
+    assert_eq!(
+        expectation.to_string(),
+        "GET https://api.example.com/users -> 200 json body-contains \"alice\""
+    );
+
+    // Predicates in any order; body-contains keeps the rest of the line
+    let expectation =
+        Expectation::parse("GET https://example.com/ -> html 2xx body-contains Welcome back")
+            .unwrap();
+    assert_eq!(expectation.status, Some(StatusExpectation::Class(2)));
+    assert_eq!(expectation.content_type, Some(ContentCategory::Html));
+    assert_eq!(expectation.body_contains.as_deref(), Some("Welcome back"));
+
+    let expectation = Expectation::parse("HEAD https://example.com/").unwrap();
+    assert_eq!(expectation.status, None);
+    assert_eq!(expectation.to_string(), "HEAD https://example.com/");
+}
+
+#[test]
+fn test_parse_expectation_errors() {
+    assert!(parse_error("GET").contains("expected a method and a URL"));
+    assert!(parse_error("GET https://example.com/ 200").contains("unexpected '200' before '->'"));
+    assert!(parse_error("GET https://example.com/ ->").contains("nothing to check after '->'"));
+    assert!(parse_error("G3T https://example.com/").contains("invalid method 'G3T'"));
+    assert!(parse_error("GET example.com/users").contains("invalid URL 'example.com/users'"));
+    assert!(parse_error("GET ftp://example.com/").contains("not an http(s) URL"));
+    assert!(parse_error("GET https://example.com/ -> 700").contains("invalid status '700'"));
+    assert!(parse_error("GET https://example.com/ -> 6xx").contains("invalid status '6xx'"));
+    assert!(parse_error("GET https://example.com/ -> 200 201").contains("status given twice"));
+    assert!(parse_error("GET https://example.com/ -> jsonn")
+        .contains("unknown content type 'jsonn': use one of html, css, js"));
+    assert!(
+        parse_error("GET https://example.com/ -> json html").contains("content type given twice")
+    );
+    assert!(
+        parse_error("GET https://example.com/ -> body-contains \"\"")
+            .contains("'body-contains' needs the text")
+    );
+
+    // Every message shows what a valid expectation looks like
+    assert!(parse_error("GET").contains("e.g. 'GET https://api.example.com/users -> 200 json'"));
+}
+
+#[test]
+fn test_content_categories() {
+    let category = |mime: &str| ContentCategory::of(&mime.parse().unwrap());
+    assert_eq!(category("text/html; charset=utf-8"), ContentCategory::Html);
+    assert_eq!(category("application/xhtml+xml"), ContentCategory::Html);
+    assert_eq!(category("text/css"), ContentCategory::Css);
+    assert_eq!(category("application/javascript"), ContentCategory::Js);
+    assert_eq!(category("text/javascript"), ContentCategory::Js);
+    assert_eq!(category("application/problem+json"), ContentCategory::Json);
+    assert_eq!(category("application/rss+xml"), ContentCategory::Xml);
+    assert_eq!(category("image/svg+xml"), ContentCategory::Xml);
+    assert_eq!(category("image/webp"), ContentCategory::Image);
+    assert_eq!(category("font/woff2"), ContentCategory::Font);
+    assert_eq!(category("application/font-woff"), ContentCategory::Font);
+    assert_eq!(category("video/mp4"), ContentCategory::Media);
+    assert_eq!(category("text/plain"), ContentCategory::Text);
+    assert_eq!(category("application/octet-stream"), ContentCategory::Other);
+
+    assert_eq!(
+        ContentCategory::parse("JavaScript"),
+        Some(ContentCategory::Js)
+    );
+    assert_eq!(ContentCategory::parse("pdf"), None);
+}
+
+#[test]
+fn test_check_presence() {
+    assert_eq!(check("GET https://api.example.com/users"), Ok(()));
+    assert_eq!(check("POST https://api.example.com/users"), Ok(()));
+    // Fragments never reach the server, so they don't take part in matching
+    assert_eq!(check("GET https://example.com/app.js#main"), Ok(()));
+    assert_eq!(
+        check("DELETE https://api.example.com/users"),
+        Err("no DELETE record".to_string())
+    );
+    assert_eq!(
+        check("GET https://api.example.com/users?page=2"),
+        Err("no GET record".to_string())
+    );
+}
+
+#[test]
+fn test_check_status() {
+    assert_eq!(check("GET https://api.example.com/users -> 200"), Ok(()));
+    assert_eq!(check("POST https://api.example.com/users -> 2xx"), Ok(()));
+    assert_eq!(
+        check("GET https://example.com/missing.png -> 200"),
+        Err("status 404 (expected 200)".to_string())
+    );
+    assert_eq!(
+        check("GET https://example.com/missing.png -> 2xx"),
+        Err("status 404 (expected 2xx)".to_string())
+    );
+}
+
+#[test]
+fn test_check_content_type() {
+    assert_eq!(check("GET https://api.example.com/users -> json"), Ok(()));
+    assert_eq!(
+        check("GET https://example.com/app.js -> javascript"),
+        Ok(())
+    );
+    assert_eq!(
+        check("GET https://example.com/missing.png -> image"),
+        Err("content type html 'text/html' (expected image)".to_string())
+    );
+}
+
+#[test]
+fn test_check_body_contains() {
+    assert_eq!(
+        check(r#"GET https://api.example.com/users -> body-contains "name":"alice""#),
+        Ok(())
+    );
+    assert_eq!(
+        check("GET https://api.example.com/users -> 200 json body-contains bob"),
+        Err("body does not contain \"bob\"".to_string())
+    );
+}
+
+#[test]
+fn test_check_reports_every_mismatch() {
+    assert_eq!(
+        check("GET https://example.com/missing.png -> 200 image body-contains PNG"),
+        Err(
+            "status 404 (expected 200), content type html 'text/html' (expected image), body does not contain \"PNG\""
+                .to_string()
+        )
+    );
+}
+
+#[test]
+fn test_check_passes_if_any_record_matches() {
+    let mut snapshot = api_snapshot();
+    snapshot.requests.insert(
+        0,
+        record(
+            "GET",
+            "https://api.example.com/users",
+            500,
+            "text/plain",
+            "oops",
+        ),
+    );
+    let expectation = Expectation::parse("GET https://api.example.com/users -> 200 json").unwrap();
+    assert_eq!(check_expectation(&snapshot, &expectation), Ok(()));
+}
+
+#[test]
+fn test_check_failed_capture() {
+    let mut snapshot = api_snapshot();
+    snapshot.requests[0].response.failure = Some("connection refused".to_string());
+    let expectation = Expectation::parse("GET https://api.example.com/users").unwrap();
+    assert_eq!(
+        check_expectation(&snapshot, &expectation),
+        Err("request failed during capture: connection refused".to_string())
+    );
+}
+
+#[test]
+fn test_parse_expectations_file() {
+    let expectations = parse_expectations(
+        r#"
+expectations:
+  - GET https://api.example.com/users -> 200 json
+  - method: post
+    url: https://api.example.com/users
+    status: 201
+  - method: GET
+    url: https://example.com/
+    status: 2xx
+    content_type: html
+    body_contains: Welcome
+"#,
+    )
+    .unwrap();
+
+    assert_eq!(expectations.len(), 3);
+    assert_eq!(
+        expectations[0],
+        Expectation::parse("GET https://api.example.com/users -> 200 json").unwrap()
+    );
+    assert_eq!(expectations[1].method, "POST");
+    assert_eq!(expectations[1].status, Some(StatusExpectation::Code(201)));
+    assert_eq!(
+        expectations[2].to_string(),
+        "GET https://example.com/ -> 2xx html body-contains \"Welcome\""
+    );
+}
+
+#[test]
+fn test_parse_expectations_file_errors() {
+    let error = |content: &str| match parse_expectations(content) {
+        Err(WebMockError::Config(message)) => message,
+        other => panic!("expected a config error, got {:?}", other),
+    };
+
+    assert!(
+        error("expectations:\n  - GET https://example.com/ -> 999\n")
+            .starts_with("expectation 1: invalid status '999'")
+    );
+    assert!(
+        error("expectations:\n  - GET https://example.com/\n  - url: https://example.com/\n")
+            .starts_with("expectation 2: missing field `method`")
+    );
+    assert!(error(
+        "expectations:\n  - method: GET\n    url: https://example.com/\n    content: json\n"
+    )
+    .contains("unknown field `content`"));
+    assert!(error(
+        "expectations:\n  - method: GET\n    url: https://example.com/\n    status: [200]\n"
+    )
+    .contains("'status' must be a code like 200 or a class like 2xx"));
+    assert!(error("expectations: []\n").contains("'expectations' is empty"));
+    assert!(error("expect:\n  - GET https://example.com/\n").contains("unknown field `expect`"));
+}
+
+#[test]
+fn test_report_counts_results() {
+    let expectations = [
+        Expectation::parse("GET https://api.example.com/users -> 200 json").unwrap(),
+        Expectation::parse("GET https://example.com/missing.png -> 200").unwrap(),
+    ];
+    let report = AssertReport::check(&api_snapshot(), &expectations);
+
+    assert_eq!(report.snapshot, "api");
+    assert_eq!((report.passed, report.failed), (1, 1));
+    assert!(!report.all_passed());
+
+    let json = serde_json::to_value(&report).unwrap();
+    assert_eq!(json["results"][0]["passed"], true);
+    assert!(json["results"][0].get("reason").is_none());
+    assert_eq!(json["results"][1]["reason"], "status 404 (expected 200)");
+}
+
+#[tokio::test]
+async fn test_assert_command_fails_on_any_failed_expectation() {
+    let temp_dir = TempDir::new().unwrap();
+    let storage = Storage::new(temp_dir.path().to_path_buf());
+    storage.save_snapshot(api_snapshot()).await.unwrap();
+    let storage_arg = || Some(temp_dir.path().to_string_lossy().to_string());
+
+    let expectations_file = temp_dir.path().join("expectations.yaml");
+    std::fs::write(
+        &expectations_file,
+        "expectations:\n  - GET https://example.com/app.js -> 200 js\n",
+    )
+    .unwrap();
+    let passing = AssertOptions {
+        expect: vec!["GET https://api.example.com/users -> 200 json".to_string()],
+        expect_file: Some(expectations_file.to_string_lossy().to_string()),
+        json: false,
+    };
+    let report = assert_command("api", storage_arg(), &passing)
+        .await
+        .unwrap();
+    assert_eq!(report.passed, 2);
+
+    let failing = AssertOptions {
+        expect: vec![
+            "GET https://api.example.com/users -> 200".to_string(),
+            "GET https://example.com/missing.png -> 200".to_string(),
+        ],
+        json: true,
+        ..AssertOptions::default()
+    };
+    let error = assert_command("api", storage_arg(), &failing)
+        .await
+        .unwrap_err();
+    assert!(matches!(error, WebMockError::CommandFailed(_)));
+    assert!(error
+        .to_string()
+        .contains("1 of 2 expectations failed for snapshot 'api'"));
+}
+
+#[tokio::test]
+async fn test_assert_command_rejects_bad_expectations_before_loading() {
+    let temp_dir = TempDir::new().unwrap();
+    let storage_arg = Some(temp_dir.path().to_string_lossy().to_string());
+    let options = AssertOptions {
+        expect: vec!["GET https://example.com/ -> 20".to_string()],
+        ..AssertOptions::default()
+    };
+
+    let error = assert_command("missing", storage_arg, &options)
+        .await
+        .unwrap_err();
+    assert!(error
+        .to_string()
+        .contains("Invalid expectation 'GET https://example.com/ -> 20': invalid status '20'"));
+}
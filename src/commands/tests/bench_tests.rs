@@ -0,0 +1,108 @@
+use crate::capture::proxy::{RequestRecord, ResponseRecord};
+use crate::commands::bench::*;
+use crate::storage::Storage;
+use crate::test_utils::test_helpers::*;
+use std::collections::HashMap;
+use std::time::Duration;
+
+#[test]
+fn test_parse_bench_duration() {
+    assert_eq!(
+        parse_bench_duration("10s").unwrap(),
+        Duration::from_secs(10)
+    );
+    assert_eq!(parse_bench_duration("1m").unwrap(), Duration::from_secs(60));
+    assert!(parse_bench_duration("0s").is_err());
+    assert!(parse_bench_duration("fast").is_err());
+}
+
+#[test]
+fn test_bench_targets_use_captured_gets() {
+    let mut snapshot = create_multi_request_snapshot("bench");
+    snapshot.requests.push(RequestRecord::new(
+        "POST".to_string(),
+        "https://example.com/api/submit".to_string(),
+        HashMap::new(),
+        None,
+        ResponseRecord::new(201, HashMap::new(), Vec::new(), None),
+    ));
+    snapshot.requests.push(RequestRecord::new(
+        "GET".to_string(),
+        "https://down.example.com/widget.js".to_string(),
+        HashMap::new(),
+        None,
+        ResponseRecord::failed("connection refused"),
+    ));
+
+    let targets = bench_targets(&snapshot, None).unwrap();
+    let expected = snapshot
+        .requests
+        .iter()
+        .filter(|r| r.method == "GET" && !r.response.is_failure())
+        .count();
+
+    assert_eq!(targets.len(), expected);
+    // HTTPS records are requested over plain HTTP through the mock server
+    assert_eq!(targets[0].to_string(), "http://example.com/");
+}
+
+#[test]
+fn test_bench_targets_explicit_url() {
+    let snapshot = create_multi_request_snapshot("bench");
+
+    let targets = bench_targets(&snapshot, Some("https://example.com/style.css?v=2")).unwrap();
+
+    assert_eq!(targets.len(), 1);
+    assert_eq!(targets[0].to_string(), "http://example.com/style.css?v=2");
+}
+
+#[test]
+fn test_bench_targets_without_gets() {
+    let mut snapshot = create_multi_request_snapshot("bench");
+    snapshot.requests.retain(|r| r.method != "GET");
+
+    assert!(bench_targets(&snapshot, None).is_err());
+}
+
+#[tokio::test]
+async fn test_bench_command_reports_throughput() {
+    let temp_dir = tempfile::TempDir::new().unwrap();
+    let storage = Storage::new(temp_dir.path().to_path_buf());
+    storage
+        .save_snapshot(create_multi_request_snapshot("bench"))
+        .await
+        .unwrap();
+
+    let options = BenchOptions {
+        connections: 4,
+        duration: Duration::from_millis(300),
+        url: None,
+    };
+    let report = bench_command(
+        "bench",
+        Some(temp_dir.path().to_string_lossy().to_string()),
+        options,
+    )
+    .await
+    .unwrap();
+
+    assert!(report.requests > 0);
+    assert_eq!(report.errors, 0);
+    assert_eq!(report.non_success, 0);
+    assert!(report.requests_per_sec() > 0.0);
+
+    let p50 = report.percentile(50.0).unwrap();
+    let p99 = report.percentile(99.0).unwrap();
+    assert!(p50 <= p99);
+    assert!(p99 <= report.max_latency().unwrap());
+}
+
+#[tokio::test]
+async fn test_bench_command_rejects_zero_connections() {
+    let options = BenchOptions {
+        connections: 0,
+        ..BenchOptions::default()
+    };
+
+    assert!(bench_command("bench", None, options).await.is_err());
+}
@@ -0,0 +1,376 @@
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+use crate::capture::proxy::records::RequestRecord;
+use crate::commands::browse::state::{
+    default_export_name, BrowserState, Effect, Key, Mode, RecordSummary,
+};
+use crate::commands::browse::view::{body_lines, detail_lines, render};
+use crate::test_utils::test_helpers::create_test_record;
+
+fn record(method: &str, url: &str, status: u16, content_type: &str, body: &[u8]) -> RequestRecord {
+    let mut record =
+        create_test_record(method, url, status, &[("content-type", content_type)], body);
+    record.headers = HashMap::from([("accept".to_string(), "*/*".to_string())]);
+    record
+}
+
+fn summaries() -> Vec<RecordSummary> {
+    [
+        record(
+            "GET",
+            "https://www.example.com/",
+            200,
+            "text/html",
+            b"<html></html>",
+        ),
+        record(
+            "GET",
+            "https://cdn.example.com/app.js",
+            200,
+            "application/javascript",
+            b"run()",
+        ),
+        record(
+            "POST",
+            "https://api.example.com/v1/login",
+            401,
+            "application/json",
+            b"{}",
+        ),
+        record(
+            "GET",
+            "https://api.example.com/v1/users",
+            200,
+            "application/json",
+            b"[]",
+        ),
+        record(
+            "GET",
+            "https://cdn.example.com/logo.png",
+            404,
+            "image/png",
+            b"",
+        ),
+    ]
+    .iter()
+    .enumerate()
+    .map(|(index, record)| RecordSummary::of(index, record))
+    .collect()
+}
+
+fn press(state: &mut BrowserState, keys: &[Key]) -> Effect {
+    keys.iter().fold(Effect::None, |_, &key| state.handle(key))
+}
+
+fn type_text(state: &mut BrowserState, text: &str) {
+    for c in text.chars() {
+        state.handle(Key::Char(c));
+    }
+}
+
+fn selected_url(state: &BrowserState) -> &str {
+    &state.selected().unwrap().url
+}
+
+#[test]
+fn test_summary_keeps_what_the_list_shows() {
+    let mut failed = record("GET", "https://api.example.com/slow", 0, "text/plain", b"");
+    failed.response.failure = Some("timed out".to_string());
+    let summary = RecordSummary::of(7, &failed);
+    assert_eq!(summary.index, 7);
+    assert_eq!(summary.host, "api.example.com");
+    assert_eq!(summary.status_label(), "ERR");
+    assert_eq!(
+        summary.request_headers,
+        vec![("accept".to_string(), "*/*".to_string())]
+    );
+
+    let summary = &summaries()[2];
+    assert_eq!(summary.status_label(), "401");
+    assert_eq!(summary.size, 2);
+    assert_eq!(summary.content_type.as_deref(), Some("application/json"));
+}
+
+#[test]
+fn test_navigation_stays_within_the_list() {
+    let mut state = BrowserState::new(summaries());
+    assert_eq!(state.cursor(), 0);
+
+    press(&mut state, &[Key::Up]);
+    assert_eq!(state.cursor(), 0);
+    press(&mut state, &[Key::Down, Key::Char('j')]);
+    assert_eq!(state.cursor(), 2);
+    press(&mut state, &[Key::Char('k')]);
+    assert_eq!(state.cursor(), 1);
+    press(&mut state, &[Key::End]);
+    assert_eq!(state.cursor(), 4);
+    press(&mut state, &[Key::Down, Key::PageDown]);
+    assert_eq!(state.cursor(), 4);
+    press(&mut state, &[Key::Char('g')]);
+    assert_eq!(state.cursor(), 0);
+
+    state.set_page(2);
+    press(&mut state, &[Key::PageDown]);
+    assert_eq!(state.cursor(), 2);
+    state.set_page(2);
+    assert_eq!(state.offset(), 1);
+    press(&mut state, &[Key::Char('G')]);
+    state.set_page(2);
+    assert_eq!(state.offset(), 3);
+}
+
+#[test]
+fn test_filter_matches_every_term_and_keeps_the_selection() {
+    let mut state = BrowserState::new(summaries());
+    press(&mut state, &[Key::Down, Key::Down, Key::Down]);
+    assert_eq!(selected_url(&state), "https://api.example.com/v1/users");
+
+    press(&mut state, &[Key::Char('/')]);
+    assert_eq!(state.mode(), &Mode::Filter);
+    type_text(&mut state, "API get");
+    assert_eq!(state.filter(), "API get");
+    assert_eq!(state.visible_count(), 1);
+    assert_eq!(selected_url(&state), "https://api.example.com/v1/users");
+
+    // Statuses match too, and the selection moves to the top when it's filtered out
+    press(
+        &mut state,
+        &[Key::Backspace, Key::Backspace, Key::Backspace],
+    );
+    type_text(&mut state, "401");
+    assert_eq!(state.visible_count(), 1);
+    assert_eq!(selected_url(&state), "https://api.example.com/v1/login");
+
+    // Enter keeps the filter and goes back to browsing
+    assert_eq!(press(&mut state, &[Key::Enter]), Effect::None);
+    assert_eq!(state.mode(), &Mode::Browse);
+    assert_eq!(state.filter(), "API 401");
+
+    // Esc clears it before it quits
+    assert_eq!(press(&mut state, &[Key::Escape]), Effect::None);
+    assert_eq!(state.filter(), "");
+    assert_eq!(state.visible_count(), 5);
+    assert_eq!(selected_url(&state), "https://api.example.com/v1/login");
+    assert_eq!(press(&mut state, &[Key::Escape]), Effect::Quit);
+}
+
+#[test]
+fn test_filter_typing_doesnt_trigger_keys() {
+    let mut state = BrowserState::new(summaries());
+    press(&mut state, &[Key::Char('/')]);
+    type_text(&mut state, "q");
+    assert_eq!(state.visible_count(), 0);
+    assert!(state.selected().is_none());
+    assert_eq!(press(&mut state, &[Key::Enter]), Effect::None);
+
+    // Nothing to load or jump to in an empty list
+    assert_eq!(
+        press(&mut state, &[Key::Enter, Key::Char('n'), Key::Down]),
+        Effect::None
+    );
+
+    press(&mut state, &[Key::Char('/'), Key::Escape]);
+    assert_eq!(state.visible_count(), 5);
+    assert_eq!(state.cursor(), 0);
+}
+
+#[test]
+fn test_host_jumps_go_through_hosts_in_order() {
+    let mut state = BrowserState::new(summaries());
+    assert_eq!(state.selected().unwrap().host, "www.example.com");
+
+    // Hosts sort as api, cdn, www and wrap around
+    press(&mut state, &[Key::Char('n')]);
+    assert_eq!(selected_url(&state), "https://api.example.com/v1/login");
+    press(&mut state, &[Key::Tab]);
+    assert_eq!(selected_url(&state), "https://cdn.example.com/app.js");
+    press(&mut state, &[Key::Char('n')]);
+    assert_eq!(selected_url(&state), "https://www.example.com/");
+
+    press(&mut state, &[Key::Char('N')]);
+    assert_eq!(selected_url(&state), "https://cdn.example.com/app.js");
+    press(&mut state, &[Key::BackTab, Key::BackTab]);
+    assert_eq!(selected_url(&state), "https://www.example.com/");
+
+    // Only hosts left by the filter count
+    press(&mut state, &[Key::Char('/')]);
+    type_text(&mut state, "get");
+    press(&mut state, &[Key::Enter, Key::Char('n'), Key::Char('n')]);
+    assert_eq!(selected_url(&state), "https://cdn.example.com/app.js");
+}
+
+#[test]
+fn test_bodies_are_loaded_on_request() {
+    let mut state = BrowserState::new(summaries());
+    press(&mut state, &[Key::Down, Key::Down]);
+    assert!(state.body().is_none());
+
+    assert_eq!(press(&mut state, &[Key::Enter]), Effect::LoadBody(2));
+    state.body_loaded(2, b"{}".to_vec());
+    assert_eq!(state.body(), Some(&b"{}"[..]));
+    // Loaded already
+    assert_eq!(press(&mut state, &[Key::Enter]), Effect::None);
+
+    // Only the selected record's body shows
+    press(&mut state, &[Key::Down]);
+    assert!(state.body().is_none());
+    assert_eq!(press(&mut state, &[Key::Enter]), Effect::LoadBody(3));
+    press(&mut state, &[Key::Up]);
+    assert_eq!(state.body(), Some(&b"{}"[..]));
+
+    assert!(!state.hex());
+    press(&mut state, &[Key::Char('x')]);
+    assert!(state.hex());
+}
+
+#[test]
+fn test_detail_pane_scrolls_within_its_lines() {
+    let mut state = BrowserState::new(summaries());
+    state.set_page(4);
+    press(&mut state, &[Key::Char('d'), Key::Char('d')]);
+    assert_eq!(state.detail_offset(), 4);
+    state.clamp_detail_offset(3);
+    assert_eq!(state.detail_offset(), 2);
+    press(&mut state, &[Key::Char('u'), Key::Char('u')]);
+    assert_eq!(state.detail_offset(), 0);
+
+    press(&mut state, &[Key::Char('d'), Key::Down]);
+    assert_eq!(state.detail_offset(), 0);
+}
+
+#[test]
+fn test_export_prompts_for_a_path() {
+    let mut state = BrowserState::new(summaries());
+    press(&mut state, &[Key::Down, Key::Down, Key::Down]);
+
+    press(&mut state, &[Key::Char('s')]);
+    assert_eq!(state.mode(), &Mode::Export("users".to_string()));
+    type_text(&mut state, ".json");
+    assert_eq!(
+        press(&mut state, &[Key::Enter]),
+        Effect::Export {
+            index: 3,
+            path: PathBuf::from("users.json")
+        }
+    );
+    assert_eq!(state.mode(), &Mode::Browse);
+
+    // Keys typed in the prompt don't move the selection or quit
+    press(&mut state, &[Key::Char('s')]);
+    for _ in 0.."users".len() {
+        state.handle(Key::Backspace);
+    }
+    type_text(&mut state, "q");
+    assert_eq!(state.mode(), &Mode::Export("q".to_string()));
+    assert_eq!(press(&mut state, &[Key::Escape]), Effect::None);
+    assert_eq!(state.mode(), &Mode::Browse);
+    assert_eq!(state.message(), Some("Export cancelled"));
+    assert_eq!(state.cursor(), 3);
+
+    // Standard output is the screen being browsed
+    press(&mut state, &[Key::Char('s')]);
+    for _ in 0.."users".len() {
+        state.handle(Key::Backspace);
+    }
+    type_text(&mut state, "-");
+    assert_eq!(press(&mut state, &[Key::Enter]), Effect::None);
+    assert!(matches!(state.mode(), Mode::Export(_)));
+    assert!(state.message().unwrap().contains("stdout"));
+}
+
+#[test]
+fn test_default_export_name() {
+    let records = summaries();
+    assert_eq!(default_export_name(&records[1]), "app.js");
+    assert_eq!(default_export_name(&records[3]), "users");
+    // No last segment to name it after
+    assert_eq!(default_export_name(&records[0]), "body-0");
+
+    let odd = RecordSummary::of(
+        9,
+        &record(
+            "GET",
+            "https://example.com/a/%2e%2e",
+            200,
+            "text/plain",
+            b"",
+        ),
+    );
+    assert_eq!(default_export_name(&odd), "body-9");
+}
+
+#[test]
+fn test_body_lines_pretty_print_json_and_dump_binary() {
+    assert_eq!(
+        body_lines(br#"{"id":1}"#, Some("application/json"), false),
+        vec!["{", "  \"id\": 1", "}"]
+    );
+    // Invalid JSON is shown as the text it is
+    assert_eq!(
+        body_lines(b"{oops", Some("application/json"), false),
+        vec!["{oops"]
+    );
+    assert_eq!(
+        body_lines(b"a\nb", Some("text/plain"), false),
+        vec!["a", "b"]
+    );
+    assert_eq!(body_lines(b"", None, true), vec!["(empty)"]);
+
+    let binary = [
+        0x89, b'P', b'N', b'G', 0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 0xff,
+    ];
+    let lines = body_lines(&binary, Some("image/png"), false);
+    assert_eq!(
+        lines,
+        vec![
+            "00000000  89 50 4e 47 00 01 02 03 04 05 06 07 08 09 0a 0b  |.PNG............|",
+            "00000010  ff                                               |.|",
+        ]
+    );
+    // Text in hex when asked for
+    assert_eq!(
+        body_lines(b"hi", Some("text/plain"), true),
+        vec!["00000000  68 69                                            |hi|"]
+    );
+}
+
+#[test]
+fn test_detail_lines_offer_to_load_the_body() {
+    let records = summaries();
+    let lines = detail_lines(&records[2], None, false);
+    assert_eq!(lines[0], "POST https://api.example.com/v1/login");
+    assert!(lines[1].contains("Status: 401"));
+    assert!(lines.contains(&"  accept: */*".to_string()));
+    assert!(lines.contains(&"  content-type: application/json".to_string()));
+    assert_eq!(lines.last().unwrap(), "Body: 2 B, press Enter to load it");
+
+    let lines = detail_lines(&records[2], Some(b"{}"), false);
+    assert_eq!(&lines[lines.len() - 2..], ["Body:", "{}"]);
+}
+
+#[test]
+fn test_render_fills_the_screen_without_control_characters() {
+    let mut records = summaries();
+    records[0].url = "https://www.example.com/\x1b[31mred".to_string();
+    let mut state = BrowserState::new(records);
+
+    let lines = render(&mut state, "site", 60, 12);
+    assert_eq!(lines.len(), 12);
+    let plain: Vec<String> = lines
+        .iter()
+        .map(|line| console::strip_ansi_codes(line).into_owned())
+        .collect();
+    assert!(plain[0].contains("site — 5/5 records"));
+    assert!(plain[1].contains("https://www.example.com/ [31mred"));
+    assert!(plain
+        .iter()
+        .all(|line| console::measure_text_width(line) <= 60));
+
+    press(&mut state, &[Key::Char('/')]);
+    type_text(&mut state, "cdn");
+    let lines = render(&mut state, "site", 60, 12);
+    let footer = console::strip_ansi_codes(lines.last().unwrap()).into_owned();
+    assert!(footer.starts_with("Filter: cdn"));
+    assert!(console::strip_ansi_codes(&lines[0]).contains("2/5 records matching 'cdn'"));
+}
@@ -0,0 +1,119 @@
+use crate::capture::proxy::certificate::CERT_FILE;
+use crate::commands::ca::{ca_trust_command, ca_untrust_command, CaOptions, TrustStore};
+use std::path::{Path, PathBuf};
+use tempfile::TempDir;
+
+fn debian() -> TrustStore {
+    TrustStore::Debian {
+        anchor_dir: PathBuf::from("/usr/local/share/ca-certificates"),
+    }
+}
+
+fn fedora() -> TrustStore {
+    TrustStore::Fedora {
+        anchor_dir: PathBuf::from("/etc/pki/ca-trust/source/anchors"),
+    }
+}
+
+#[test]
+fn test_trust_plans_per_platform() {
+    let cert = Path::new("/home/dev/.webmock/ca/webmock-ca.pem");
+
+    assert_eq!(
+        TrustStore::MacOs.trust_plan(cert).command_lines(),
+        vec!["security add-trusted-cert -d -r trustRoot -k /Library/Keychains/System.keychain /home/dev/.webmock/ca/webmock-ca.pem"]
+    );
+    assert_eq!(
+        debian().trust_plan(cert).command_lines(),
+        vec![
+            "cp /home/dev/.webmock/ca/webmock-ca.pem /usr/local/share/ca-certificates/webmock-ca.crt",
+            "update-ca-certificates",
+        ]
+    );
+    assert_eq!(
+        fedora().trust_plan(cert).command_lines(),
+        vec![
+            "cp /home/dev/.webmock/ca/webmock-ca.pem /etc/pki/ca-trust/source/anchors/webmock-ca.pem",
+            "update-ca-trust",
+        ]
+    );
+    assert_eq!(
+        TrustStore::P11Kit.trust_plan(cert).command_lines(),
+        vec!["trust anchor --store /home/dev/.webmock/ca/webmock-ca.pem"]
+    );
+}
+
+#[test]
+fn test_untrust_plans_per_platform() {
+    let cert = Path::new("/home/dev/.webmock/ca/webmock-ca.pem");
+
+    assert_eq!(
+        TrustStore::MacOs.untrust_plan(cert).command_lines(),
+        vec!["security remove-trusted-cert -d /home/dev/.webmock/ca/webmock-ca.pem"]
+    );
+    assert_eq!(
+        debian().untrust_plan(cert).command_lines(),
+        vec![
+            "rm -f /usr/local/share/ca-certificates/webmock-ca.crt",
+            "update-ca-certificates --fresh",
+        ]
+    );
+    assert_eq!(
+        fedora().untrust_plan(cert).command_lines(),
+        vec![
+            "rm -f /etc/pki/ca-trust/source/anchors/webmock-ca.pem",
+            "update-ca-trust",
+        ]
+    );
+    assert_eq!(
+        TrustStore::P11Kit.untrust_plan(cert).command_lines(),
+        vec!["trust anchor --remove /home/dev/.webmock/ca/webmock-ca.pem"]
+    );
+}
+
+#[test]
+fn test_manual_commands_use_sudo_and_quote_paths() {
+    let plan = TrustStore::P11Kit.trust_plan(Path::new("/Users/Jo Doe/.webmock/ca/webmock-ca.pem"));
+
+    assert_eq!(
+        plan.manual_commands(),
+        vec!["sudo trust anchor --store '/Users/Jo Doe/.webmock/ca/webmock-ca.pem'"]
+    );
+}
+
+#[test]
+fn test_trust_dry_run_generates_certificate_without_changing_trust_store() {
+    let temp_dir = TempDir::new().unwrap();
+    let cert_dir = temp_dir.path().join("ca");
+    let options = CaOptions {
+        dry_run: true,
+        cert_dir: Some(cert_dir.clone()),
+        store: Some(TrustStore::P11Kit),
+        ..CaOptions::default()
+    };
+
+    let plan = ca_trust_command(&options).unwrap();
+
+    let cert_path = cert_dir.join(CERT_FILE);
+    assert!(cert_path.exists());
+    assert_eq!(plan, TrustStore::P11Kit.trust_plan(&cert_path));
+}
+
+#[test]
+fn test_untrust_dry_run_and_missing_certificate() {
+    let temp_dir = TempDir::new().unwrap();
+    let cert_dir = temp_dir.path().join("ca");
+    let options = CaOptions {
+        dry_run: true,
+        cert_dir: Some(cert_dir.clone()),
+        store: Some(debian()),
+        ..CaOptions::default()
+    };
+
+    // Nothing to remove before a certificate was ever generated
+    assert!(ca_untrust_command(&options).is_err());
+
+    ca_trust_command(&options).unwrap();
+    let plan = ca_untrust_command(&options).unwrap();
+    assert_eq!(plan, debian().untrust_plan(&cert_dir.join(CERT_FILE)));
+}
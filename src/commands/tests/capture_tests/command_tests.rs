@@ -1,8 +1,14 @@
 //! Tests for capture command
 
-use crate::commands::capture::{check_snapshot_exists, initialize_storage, validate_inputs};
+use crate::capture::CaptureOptions;
+use crate::commands::capture::{
+    check_snapshot_exists, decide_capture, format_tail_event, initialize_storage, parse_age,
+    validate_inputs, CaptureDecision,
+};
 use crate::error::WebMockError;
 use crate::storage::Storage;
+use crate::test_utils::test_helpers::create_test_snapshot_with_name;
+use std::time::Duration;
 use tempfile::TempDir;
 
 #[test]
@@ -70,17 +76,17 @@ async fn test_initialize_storage() {
     assert!(result.is_ok());
 
     let storage = result.unwrap();
-    let snapshots_dir = storage.ensure_snapshots_dir();
+    let snapshots_dir = storage.ensure_snapshots_dir().await;
     assert!(snapshots_dir.is_ok());
 }
 
-#[test]
-fn test_check_snapshot_exists() {
+#[tokio::test]
+async fn test_check_snapshot_exists() {
     let temp_dir = TempDir::new().unwrap();
     let storage = Storage::new(temp_dir.path().to_path_buf());
 
     // Should pass when snapshot doesn't exist
-    let result = check_snapshot_exists(&storage, "non-existent");
+    let result = check_snapshot_exists(&storage, "non-existent", &CaptureOptions::default()).await;
     assert!(result.is_ok());
 
     // Create a dummy snapshot file
@@ -89,7 +95,140 @@ fn test_check_snapshot_exists() {
     std::fs::write(snapshots_dir.join("existing.msgpack"), b"dummy").unwrap();
 
     // Should fail when snapshot exists
-    let result = check_snapshot_exists(&storage, "existing");
+    let result = check_snapshot_exists(&storage, "existing", &CaptureOptions::default()).await;
     assert!(result.is_err());
     assert!(matches!(result.unwrap_err(), WebMockError::Config(_)));
 }
+
+#[test]
+fn test_parse_age() {
+    assert_eq!(parse_age("30m").unwrap(), Duration::from_secs(30 * 60));
+    assert_eq!(parse_age("12h").unwrap(), Duration::from_secs(12 * 60 * 60));
+    assert_eq!(
+        parse_age("7d").unwrap(),
+        Duration::from_secs(7 * 24 * 60 * 60)
+    );
+    assert_eq!(parse_age("45s").unwrap(), Duration::from_secs(45));
+
+    for invalid in ["", "12", "h", "1.5h", "3w", "-1d"] {
+        let result = parse_age(invalid);
+        assert!(
+            matches!(result, Err(WebMockError::Config(_))),
+            "{}",
+            invalid
+        );
+    }
+}
+
+#[test]
+fn test_decide_capture_missing_snapshot() {
+    let options = CaptureOptions {
+        if_missing: true,
+        ..Default::default()
+    };
+
+    let decision = decide_capture("site", false, None, &options).unwrap();
+    assert_eq!(decision, CaptureDecision::Capture);
+}
+
+#[test]
+fn test_decide_capture_if_missing_skips_existing() {
+    let options = CaptureOptions {
+        if_missing: true,
+        ..Default::default()
+    };
+
+    let decision = decide_capture("site", true, Some(Duration::from_secs(60)), &options).unwrap();
+    assert!(matches!(decision, CaptureDecision::Skip { .. }));
+}
+
+#[test]
+fn test_decide_capture_max_age() {
+    let options = CaptureOptions {
+        max_age: Some(Duration::from_secs(24 * 60 * 60)),
+        ..Default::default()
+    };
+
+    let fresh = decide_capture("site", true, Some(Duration::from_secs(60 * 60)), &options);
+    assert!(matches!(fresh, Ok(CaptureDecision::Skip { .. })));
+
+    let stale_age = Duration::from_secs(2 * 24 * 60 * 60);
+    let stale = decide_capture("site", true, Some(stale_age), &options).unwrap();
+    assert_eq!(
+        stale,
+        CaptureDecision::Recapture {
+            age: Some(stale_age)
+        }
+    );
+
+    // Unreadable snapshots are replaced rather than trusted
+    let unknown = decide_capture("site", true, None, &options).unwrap();
+    assert_eq!(unknown, CaptureDecision::Recapture { age: None });
+}
+
+#[tokio::test]
+async fn test_check_snapshot_exists_with_max_age() {
+    let temp_dir = TempDir::new().unwrap();
+    let storage = Storage::new(temp_dir.path().to_path_buf());
+
+    let fresh = create_test_snapshot_with_name("fresh");
+    let mut stale = create_test_snapshot_with_name("stale");
+    stale.created_at = chrono::Utc::now() - chrono::Duration::days(3);
+    storage.save_snapshot(fresh).await.unwrap();
+    storage.save_snapshot(stale).await.unwrap();
+
+    let options = CaptureOptions {
+        max_age: Some(parse_age("24h").unwrap()),
+        ..Default::default()
+    };
+
+    let decision = check_snapshot_exists(&storage, "fresh", &options)
+        .await
+        .unwrap();
+    assert!(matches!(decision, CaptureDecision::Skip { .. }));
+
+    let decision = check_snapshot_exists(&storage, "stale", &options)
+        .await
+        .unwrap();
+    assert!(matches!(
+        decision,
+        CaptureDecision::Recapture { age: Some(_) }
+    ));
+
+    let decision = check_snapshot_exists(&storage, "missing", &options)
+        .await
+        .unwrap();
+    assert_eq!(decision, CaptureDecision::Capture);
+}
+
+#[test]
+fn test_format_tail_event() {
+    use crate::capture::proxy::{RecordedRequest, TailEvent};
+
+    let request = RecordedRequest {
+        method: "GET".to_string(),
+        url: "https://example.com/app.js".to_string(),
+        status: 200,
+        size: 2048,
+        failure: None,
+    };
+    let line = format_tail_event(&TailEvent::Recorded(request.clone()));
+    assert!(line.contains("200"), "{}", line);
+    assert!(
+        line.contains("GET https://example.com/app.js (2.0 KB)"),
+        "{}",
+        line
+    );
+
+    let failed = RecordedRequest {
+        status: 502,
+        failure: Some("connection refused".to_string()),
+        ..request
+    };
+    let line = format_tail_event(&TailEvent::Recorded(failed));
+    assert!(line.contains("ERR"), "{}", line);
+    assert!(line.ends_with("(connection refused)"), "{}", line);
+
+    let line = format_tail_event(&TailEvent::Dropped(3));
+    assert!(line.contains("3 requests not shown"), "{}", line);
+}
@@ -3,6 +3,7 @@ use crate::commands::capture::run_capture_with_progress;
 use crate::feedback::ProgressReporter;
 use crate::test_utils::test_helpers::*;
 use std::sync::Arc;
+use std::time::Duration;
 
 #[tokio::test]
 #[ignore = "slow test - requires network/Chrome"]
@@ -164,7 +165,10 @@ fn test_error_recovery_logic() {
     use crate::error::WebMockError;
 
     // Test recoverable errors
-    let recoverable_errors = [WebMockError::Timeout(30), WebMockError::PortInUse(8080)];
+    let recoverable_errors = [
+        WebMockError::timeout("Page navigation", Duration::from_secs(30)),
+        WebMockError::PortInUse(8080),
+    ];
 
     for error in recoverable_errors {
         assert!(
@@ -227,3 +231,29 @@ async fn test_capture_timeout_handling() {
 
     cleanup_test_env();
 }
+
+#[test]
+fn test_capture_status_fails_on_warnings_only_when_asked() {
+    use crate::capture::{CaptureStatus, CaptureWarning, CaptureWarningKind};
+    use crate::commands::capture::capture_status;
+
+    let warnings = vec![CaptureWarning {
+        kind: CaptureWarningKind::PinnedHost,
+        count: 2,
+        examples: vec!["bank.example.com:443".to_string()],
+    }];
+    assert_eq!(
+        capture_status("site", &[], true).unwrap(),
+        CaptureStatus::Success
+    );
+    assert_eq!(
+        capture_status("site", &warnings, false).unwrap(),
+        CaptureStatus::SuccessWithWarnings
+    );
+    let error = capture_status("site", &warnings, true).unwrap_err();
+    assert!(matches!(
+        error,
+        crate::error::WebMockError::CommandFailed(_)
+    ));
+    assert!(error.to_string().contains("2 warnings"), "{}", error);
+}
@@ -1,6 +1,7 @@
 use tempfile::TempDir;
 
 use crate::{
+    capture::CaptureOptions,
     commands::{
         capture::{check_snapshot_exists, initialize_storage},
         capture_command,
@@ -14,7 +15,7 @@ async fn test_capture_command_validation_flow() {
     // Test the full validation flow without actually running capture
 
     // Test 1: Invalid URL should fail early
-    let result = capture_command("not-a-url", "test", 30, None).await;
+    let result = capture_command("not-a-url", "test", 30, None, CaptureOptions::default()).await;
     assert!(result.is_err());
     assert!(matches!(
         result.unwrap_err(),
@@ -22,12 +23,26 @@ async fn test_capture_command_validation_flow() {
     ));
 
     // Test 2: Invalid name should fail early
-    let result = capture_command("https://example.com", "", 30, None).await;
+    let result = capture_command(
+        "https://example.com",
+        "",
+        30,
+        None,
+        CaptureOptions::default(),
+    )
+    .await;
     assert!(result.is_err());
     assert!(matches!(result.unwrap_err(), WebMockError::Config(_)));
 
     // Test 3: Invalid timeout should fail early
-    let result = capture_command("https://example.com", "test", 0, None).await;
+    let result = capture_command(
+        "https://example.com",
+        "test",
+        0,
+        None,
+        CaptureOptions::default(),
+    )
+    .await;
     assert!(result.is_err());
     assert!(matches!(result.unwrap_err(), WebMockError::Config(_)));
 }
@@ -45,7 +60,7 @@ async fn test_capture_command_storage_initialization() {
     let storage = storage_result.unwrap();
 
     // Verify snapshots directory is created
-    let snapshots_dir = storage.ensure_snapshots_dir();
+    let snapshots_dir = storage.ensure_snapshots_dir().await;
     assert!(snapshots_dir.is_ok());
     assert!(snapshots_dir.unwrap().exists());
 }
@@ -55,10 +70,10 @@ async fn test_capture_command_duplicate_snapshot_check() {
     // Set up temporary storage
     let temp_dir = TempDir::new().unwrap();
     let storage = Storage::new(temp_dir.path().to_path_buf());
-    storage.ensure_snapshots_dir().unwrap();
+    storage.ensure_snapshots_dir().await.unwrap();
 
     // Create a dummy snapshot file
-    let snapshot_path = storage.get_snapshot_path("existing-snapshot");
+    let snapshot_path = storage.get_snapshot_path("existing-snapshot").unwrap();
     tokio::fs::create_dir_all(snapshot_path.parent().unwrap())
         .await
         .unwrap();
@@ -67,11 +82,12 @@ async fn test_capture_command_duplicate_snapshot_check() {
         .unwrap();
 
     // Test that duplicate snapshot check works
-    let result = check_snapshot_exists(&storage, "existing-snapshot");
+    let result =
+        check_snapshot_exists(&storage, "existing-snapshot", &CaptureOptions::default()).await;
     assert!(result.is_err());
     assert!(matches!(result.unwrap_err(), WebMockError::Config(_)));
 
     // Test that non-existing snapshot passes
-    let result = check_snapshot_exists(&storage, "new-snapshot");
+    let result = check_snapshot_exists(&storage, "new-snapshot", &CaptureOptions::default()).await;
     assert!(result.is_ok());
 }
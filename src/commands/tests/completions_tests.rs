@@ -0,0 +1,140 @@
+use tempfile::TempDir;
+
+use crate::cli::Shell;
+use crate::commands::completions::{
+    completions_install_command, shell_from_path, CompletionsOptions, SCRIPT_MARKER,
+};
+
+fn install(home: &TempDir, shell: Shell) -> CompletionsOptions {
+    CompletionsOptions {
+        shell: Some(shell),
+        ..CompletionsOptions::with_home(home.path())
+    }
+}
+
+#[test]
+fn test_install_writes_script_to_each_shells_directory() {
+    let cases = [
+        (
+            Shell::Bash,
+            ".local/share/bash-completion/completions/webmock",
+            "complete",
+        ),
+        (Shell::Zsh, ".zfunc/_webmock", "#compdef webmock"),
+        (
+            Shell::Fish,
+            ".config/fish/completions/webmock.fish",
+            "complete -c webmock",
+        ),
+        (
+            Shell::Elvish,
+            ".config/elvish/lib/webmock.elv",
+            "edit:completion",
+        ),
+        (
+            Shell::PowerShell,
+            ".config/powershell/webmock.ps1",
+            "Register-ArgumentCompleter",
+        ),
+    ];
+
+    for (shell, relative, snippet) in cases {
+        let home = TempDir::new().unwrap();
+        let installed = completions_install_command(&install(&home, shell.clone())).unwrap();
+
+        assert_eq!(installed.path, home.path().join(relative), "{:?}", shell);
+        assert!(installed.changed);
+        let script = std::fs::read_to_string(&installed.path).unwrap();
+        assert!(script.starts_with(SCRIPT_MARKER), "{:?}", shell);
+        assert!(script.contains(snippet), "{:?} lacks {}", shell, snippet);
+
+        // Installing again replaces our own script
+        assert!(completions_install_command(&install(&home, shell)).is_ok());
+    }
+}
+
+#[test]
+fn test_install_reports_rc_lines_only_where_needed() {
+    let home = TempDir::new().unwrap();
+    let zsh = completions_install_command(&install(&home, Shell::Zsh)).unwrap();
+    assert!(zsh.rc_line.unwrap().contains("fpath=(~/.zfunc $fpath)"));
+
+    let bash = completions_install_command(&install(&home, Shell::Bash)).unwrap();
+    assert!(bash.rc_line.is_none());
+}
+
+#[test]
+fn test_install_honors_xdg_directories() {
+    let home = TempDir::new().unwrap();
+    let data_home = TempDir::new().unwrap();
+    let options = CompletionsOptions {
+        data_home: data_home.path().to_path_buf(),
+        ..install(&home, Shell::Bash)
+    };
+
+    let installed = completions_install_command(&options).unwrap();
+    assert_eq!(
+        installed.path,
+        data_home.path().join("bash-completion/completions/webmock")
+    );
+}
+
+#[test]
+fn test_install_detects_shell_from_login_shell() {
+    assert_eq!(shell_from_path("/usr/bin/zsh"), Some(Shell::Zsh));
+    assert_eq!(shell_from_path("/opt/homebrew/bin/fish"), Some(Shell::Fish));
+    assert_eq!(
+        shell_from_path("/usr/local/bin/pwsh"),
+        Some(Shell::PowerShell)
+    );
+    assert_eq!(shell_from_path("/bin/tcsh"), None);
+
+    let home = TempDir::new().unwrap();
+    let options = CompletionsOptions {
+        login_shell: Some("/bin/bash".to_string()),
+        ..CompletionsOptions::with_home(home.path())
+    };
+    let installed = completions_install_command(&options).unwrap();
+    assert_eq!(installed.shell, Shell::Bash);
+
+    let options = CompletionsOptions {
+        login_shell: Some("/bin/tcsh".to_string()),
+        ..CompletionsOptions::with_home(home.path())
+    };
+    assert!(completions_install_command(&options).is_err());
+}
+
+#[test]
+fn test_install_never_overwrites_or_removes_foreign_files() {
+    let home = TempDir::new().unwrap();
+    let path = home.path().join(".zfunc/_webmock");
+    std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+    std::fs::write(&path, "#compdef webmock\n# hand written\n").unwrap();
+
+    assert!(completions_install_command(&install(&home, Shell::Zsh)).is_err());
+    let options = CompletionsOptions {
+        uninstall: true,
+        ..install(&home, Shell::Zsh)
+    };
+    assert!(completions_install_command(&options).is_err());
+    assert_eq!(
+        std::fs::read_to_string(&path).unwrap(),
+        "#compdef webmock\n# hand written\n"
+    );
+}
+
+#[test]
+fn test_uninstall_removes_installed_script() {
+    let home = TempDir::new().unwrap();
+    let installed = completions_install_command(&install(&home, Shell::Fish)).unwrap();
+
+    let options = CompletionsOptions {
+        uninstall: true,
+        ..install(&home, Shell::Fish)
+    };
+    assert!(completions_install_command(&options).unwrap().changed);
+    assert!(!installed.path.exists());
+
+    // Nothing left to remove
+    assert!(!completions_install_command(&options).unwrap().changed);
+}
@@ -0,0 +1,102 @@
+use tempfile::TempDir;
+
+use crate::commands::config::config_command;
+use crate::commands::serve::{apply_serve_defaults, resolve_serve_defaults, DEFAULT_SERVE_PORT};
+use crate::serve::ServeOptions;
+use crate::storage::{ServeDefaults, Storage};
+use crate::test_utils::test_helpers::create_test_snapshot_with_name;
+
+fn resolve(flags: &ServeDefaults, stored: &ServeDefaults) -> (u16, ServeOptions, Vec<String>) {
+    let (settings, from_snapshot) = resolve_serve_defaults(flags, stored);
+    let mut options = ServeOptions::default();
+    apply_serve_defaults(&settings, &mut options).unwrap();
+    (
+        settings.port.unwrap_or(DEFAULT_SERVE_PORT),
+        options,
+        from_snapshot,
+    )
+}
+
+#[test]
+fn test_flags_override_stored_defaults() {
+    let stored = ServeDefaults {
+        port: Some(8090),
+        strict_connect: Some(true),
+        ..ServeDefaults::default()
+    };
+
+    // Stored defaults apply when no flag is given
+    let (port, options, from_snapshot) = resolve(&ServeDefaults::default(), &stored);
+    assert_eq!(port, 8090);
+    assert!(options.strict_connect);
+    assert_eq!(from_snapshot, vec!["--port 8090", "--strict-connect=true"]);
+
+    // Flags win, including an explicit false
+    let flags = ServeDefaults {
+        port: Some(3000),
+        strict_connect: Some(false),
+        ..ServeDefaults::default()
+    };
+    let (port, options, from_snapshot) = resolve(&flags, &stored);
+    assert_eq!(port, 3000);
+    assert!(!options.strict_connect);
+    assert!(from_snapshot.is_empty());
+
+    // Settings stored nowhere keep the built-in defaults
+    let (port, options, _) = resolve(&ServeDefaults::default(), &ServeDefaults::default());
+    assert_eq!(port, DEFAULT_SERVE_PORT);
+    assert!(!options.strict_connect);
+}
+
+#[test]
+fn test_apply_serve_defaults_rejects_invalid_values() {
+    let invalid = ServeDefaults {
+        encode: Some("deflate".to_string()),
+        ..ServeDefaults::default()
+    };
+    assert!(apply_serve_defaults(&invalid, &mut ServeOptions::default()).is_err());
+
+    let invalid = ServeDefaults {
+        replay_failures: Some("retry".to_string()),
+        ..ServeDefaults::default()
+    };
+    assert!(apply_serve_defaults(&invalid, &mut ServeOptions::default()).is_err());
+}
+
+#[tokio::test]
+async fn test_config_command_stores_defaults() {
+    let temp_dir = TempDir::new().unwrap();
+    let storage = Storage::new(temp_dir.path().to_path_buf());
+    let snapshot = create_test_snapshot_with_name("checkout");
+    let requests = snapshot.requests.len();
+    storage.save_snapshot(snapshot).await.unwrap();
+    let storage_arg = || Some(temp_dir.path().to_string_lossy().to_string());
+
+    let changes = ServeDefaults {
+        port: Some(8090),
+        stateful: Some(true),
+        ..ServeDefaults::default()
+    };
+    config_command("checkout", changes, false, storage_arg())
+        .await
+        .unwrap();
+
+    // Later changes keep the other stored settings
+    let changes = ServeDefaults {
+        stateful: Some(false),
+        ..ServeDefaults::default()
+    };
+    config_command("checkout", changes, false, storage_arg())
+        .await
+        .unwrap();
+    let snapshot = storage.load_snapshot("checkout").await.unwrap();
+    assert_eq!(snapshot.requests.len(), requests);
+    assert_eq!(snapshot.serve_defaults.port, Some(8090));
+    assert_eq!(snapshot.serve_defaults.stateful, Some(false));
+
+    config_command("checkout", ServeDefaults::default(), true, storage_arg())
+        .await
+        .unwrap();
+    let snapshot = storage.load_snapshot("checkout").await.unwrap();
+    assert!(snapshot.serve_defaults.is_empty());
+}
@@ -49,7 +49,7 @@ async fn test_delete_command_success() {
     storage.save_snapshot(snapshot).await.unwrap();
 
     // Verify snapshot exists before deletion
-    assert!(storage.snapshot_exists("test-delete"));
+    assert!(storage.snapshot_exists("test-delete").unwrap());
 
     // Test storage path logic through delete command
     // The delete command will check if the snapshot exists
@@ -120,20 +120,20 @@ async fn test_storage_operations() {
     let storage = crate::storage::Storage::new(storage_path);
 
     // Test snapshot existence check
-    assert!(!storage.snapshot_exists("nonexistent"));
+    assert!(!storage.snapshot_exists("nonexistent").unwrap());
 
     // Create and save a snapshot
     let snapshot = create_test_snapshot_with_name("test-storage-ops");
     storage.save_snapshot(snapshot.clone()).await.unwrap();
 
     // Verify it exists
-    assert!(storage.snapshot_exists("test-storage-ops"));
+    assert!(storage.snapshot_exists("test-storage-ops").unwrap());
 
     // Delete it
     storage.delete_snapshot("test-storage-ops").await.unwrap();
 
     // Verify it's gone
-    assert!(!storage.snapshot_exists("test-storage-ops"));
+    assert!(!storage.snapshot_exists("test-storage-ops").unwrap());
 }
 
 #[test]
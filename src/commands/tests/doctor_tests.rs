@@ -0,0 +1,103 @@
+use std::time::Duration;
+
+use tempfile::TempDir;
+
+use crate::commands::doctor::{run_check, run_checks, CheckResult, CheckStatus, DoctorReport};
+use crate::error::WebMockError;
+
+fn check<'a>(report: &'a DoctorReport, name: &str) -> &'a CheckResult {
+    report
+        .checks
+        .iter()
+        .find(|check| check.name == name)
+        .unwrap_or_else(|| panic!("no check named {}: {:?}", name, report.checks))
+}
+
+#[tokio::test]
+async fn test_run_check_reports_errors_panics_and_timeouts() {
+    let passed = run_check("ok", true, Duration::from_secs(5), async {
+        Ok((CheckStatus::Pass, "fine".to_string()))
+    })
+    .await;
+    assert_eq!(passed.status, CheckStatus::Pass);
+    assert_eq!(passed.detail, "fine");
+
+    let errored = run_check("error", true, Duration::from_secs(5), async {
+        Err(WebMockError::config("broken"))
+    })
+    .await;
+    assert_eq!(errored.status, CheckStatus::Fail);
+    assert!(errored.detail.contains("broken"), "{}", errored.detail);
+
+    let panicked = run_check("panic", true, Duration::from_secs(5), async {
+        panic!("check blew up")
+    })
+    .await;
+    assert_eq!(panicked.status, CheckStatus::Fail);
+    assert!(panicked.detail.contains("crashed"), "{}", panicked.detail);
+
+    // Optional checks only warn, however they go wrong
+    let hung = run_check("hang", false, Duration::from_millis(50), async {
+        tokio::time::sleep(Duration::from_secs(60)).await;
+        Ok((CheckStatus::Pass, String::new()))
+    })
+    .await;
+    assert_eq!(hung.status, CheckStatus::Warn);
+    assert!(hung.detail.contains("did not finish"), "{}", hung.detail);
+}
+
+#[tokio::test]
+async fn test_doctor_checks_a_writable_storage_directory() {
+    let temp_dir = TempDir::new().unwrap();
+    let report = run_checks(temp_dir.path()).await;
+
+    assert_eq!(report.checks.len(), 7);
+    for name in [
+        "Storage directory",
+        "Snapshot round trip",
+        "Local port",
+        "TLS handshake",
+    ] {
+        assert_eq!(check(&report, name).status, CheckStatus::Pass, "{}", name);
+    }
+    // Nothing is left behind in the storage directory
+    assert_eq!(std::fs::read_dir(temp_dir.path()).unwrap().count(), 0);
+}
+
+#[tokio::test]
+async fn test_failing_check_does_not_stop_the_others() {
+    let temp_dir = TempDir::new().unwrap();
+    let file = temp_dir.path().join("not-a-directory");
+    std::fs::write(&file, b"").unwrap();
+    let report = run_checks(&file.join("storage")).await;
+
+    assert_eq!(report.checks.len(), 7);
+    assert_eq!(
+        check(&report, "Storage directory").status,
+        CheckStatus::Fail
+    );
+    assert_eq!(
+        check(&report, "Snapshot round trip").status,
+        CheckStatus::Fail
+    );
+    assert_eq!(check(&report, "Local port").status, CheckStatus::Pass);
+    assert_eq!(check(&report, "TLS handshake").status, CheckStatus::Pass);
+    assert!(report.failures() >= 2);
+}
+
+#[tokio::test]
+async fn test_doctor_report_json_shape() {
+    let temp_dir = TempDir::new().unwrap();
+    let report = run_checks(temp_dir.path()).await;
+    let json = serde_json::to_value(&report).unwrap();
+
+    assert_eq!(json["version"], env!("CARGO_PKG_VERSION"));
+    assert_eq!(json["os"], std::env::consts::OS);
+    let checks = json["checks"].as_array().unwrap();
+    assert_eq!(checks.len(), report.checks.len());
+    for check in checks {
+        assert!(check["name"].is_string());
+        assert!(check["detail"].is_string());
+        assert!(["pass", "warn", "fail"].contains(&check["status"].as_str().unwrap()));
+    }
+}
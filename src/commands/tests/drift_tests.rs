@@ -0,0 +1,343 @@
+use std::collections::HashMap;
+
+use globset::Glob;
+use serde_json::json;
+use wiremock::matchers::{header, method, path};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+use crate::capture::proxy::records::{RequestRecord, ResponseRecord};
+use crate::commands::drift::shape::{compare, ChangeKind, FieldChange, Shape};
+use crate::commands::drift::{check_drift, json_endpoints, parse_header, DriftOptions};
+use crate::storage::Snapshot;
+use crate::test_utils::test_helpers::create_test_snapshot_with_name;
+
+fn json_record(method: &str, url: &str, status: u16, body: serde_json::Value) -> RequestRecord {
+    let headers = HashMap::from([("content-type".to_string(), "application/json".to_string())]);
+    RequestRecord::new(
+        method.to_string(),
+        url.to_string(),
+        HashMap::new(),
+        None,
+        ResponseRecord::new(status, headers, body.to_string().into_bytes(), Some(url)),
+    )
+}
+
+fn snapshot_with(records: Vec<RequestRecord>) -> Snapshot {
+    let mut snapshot = create_test_snapshot_with_name("drift");
+    snapshot.requests.extend(records);
+    snapshot
+}
+
+/// Changes as their one-line form, e.g. `+ $.email (string)`
+fn changes(captured: serde_json::Value, live: serde_json::Value) -> Vec<String> {
+    compare(&captured, &live)
+        .iter()
+        .map(FieldChange::to_string)
+        .collect()
+}
+
+#[test]
+fn test_identical_structure_with_other_values_has_no_drift() {
+    let captured = json!({"id": 1, "name": "Ada", "tags": ["a"], "active": true});
+    let live = json!({"id": 2, "name": "Grace", "tags": ["b", "c"], "active": false});
+    assert!(changes(captured, live).is_empty());
+    // Integers and floats are both numbers
+    assert!(changes(json!({"price": 10}), json!({"price": 9.99})).is_empty());
+}
+
+#[test]
+fn test_added_removed_and_retyped_fields() {
+    let found = compare(
+        &json!({"id": 1, "name": "Ada", "legacy": "x"}),
+        &json!({"id": "1", "name": "Ada", "email": "ada@example.com"}),
+    );
+    assert_eq!(
+        found,
+        vec![
+            FieldChange {
+                path: "$.email".to_string(),
+                kind: ChangeKind::Added,
+                captured: None,
+                live: Some("string".to_string()),
+            },
+            FieldChange {
+                path: "$.id".to_string(),
+                kind: ChangeKind::Retyped,
+                captured: Some("number".to_string()),
+                live: Some("string".to_string()),
+            },
+            FieldChange {
+                path: "$.legacy".to_string(),
+                kind: ChangeKind::Removed,
+                captured: Some("string".to_string()),
+                live: None,
+            },
+        ]
+    );
+    assert_eq!(found[1].to_string(), "~ $.id: number -> string");
+}
+
+#[test]
+fn test_nested_objects_report_full_paths() {
+    let captured = json!({"user": {"profile": {"name": "Ada", "age": 36}, "id": 1}});
+    let live = json!({"user": {"profile": {"name": "Ada", "age": "36", "bio": {}}, "id": 1}});
+    assert_eq!(
+        changes(captured, live),
+        vec![
+            "~ $.user.profile.age: number -> string",
+            "+ $.user.profile.bio (object)",
+        ]
+    );
+
+    // A whole subtree changing kind is reported once, not per field
+    assert_eq!(
+        changes(json!({"user": {"id": 1}}), json!({"user": [1]})),
+        vec!["~ $.user: object -> array"]
+    );
+}
+
+#[test]
+fn test_array_elements_are_merged() {
+    // A field in any element counts, so an element lacking it isn't drift
+    let captured = json!({"items": [{"id": 1, "note": "x"}, {"id": 2}]});
+    let live = json!({"items": [{"id": 3}, {"id": 4, "note": "y"}]});
+    assert!(changes(captured, live).is_empty());
+
+    let captured = json!({"items": [{"id": 1}, {"id": 2, "sku": "a"}]});
+    let live = json!({"items": [{"id": "3", "sku": "b", "stock": 1}]});
+    assert_eq!(
+        changes(captured, live),
+        vec![
+            "~ $.items[].id: number -> string",
+            "+ $.items[].stock (number)",
+        ]
+    );
+}
+
+#[test]
+fn test_arrays_of_mixed_shapes() {
+    let captured = json!([1, "two", {"three": 3}]);
+    assert_eq!(
+        Shape::of(&captured).array.unwrap().to_string(),
+        "number or string or object"
+    );
+
+    // The same mix in another order and count matches
+    assert!(changes(captured.clone(), json!([{"three": 4}, "five", 6, 7])).is_empty());
+    assert_eq!(
+        changes(captured, json!([1, 2])),
+        vec!["~ $[]: number or string or object -> number"]
+    );
+    // Arrays of arrays are compared element by element
+    assert_eq!(
+        changes(json!({"grid": [[1, 2]]}), json!({"grid": [["1"]]})),
+        vec!["~ $.grid[][]: number -> string"]
+    );
+}
+
+#[test]
+fn test_empty_arrays_tell_nothing_about_their_elements() {
+    assert!(changes(json!({"items": []}), json!({"items": [{"id": 1}]})).is_empty());
+    assert!(changes(json!({"items": [{"id": 1}]}), json!({"items": []})).is_empty());
+    // Still an array either way
+    assert_eq!(
+        changes(json!({"items": []}), json!({"items": {}})),
+        vec!["~ $.items: array -> object"]
+    );
+}
+
+#[test]
+fn test_nulls_are_compatible_with_any_kind() {
+    assert!(changes(
+        json!({"deleted_at": null}),
+        json!({"deleted_at": "2026-10-16"})
+    )
+    .is_empty());
+    assert!(changes(
+        json!({"deleted_at": "2026-10-16"}),
+        json!({"deleted_at": null})
+    )
+    .is_empty());
+    // Fields under a null object aren't reported missing
+    assert!(changes(json!({"owner": {"id": 1}}), json!({"owner": null})).is_empty());
+    // Nullable across elements on one side only
+    assert!(changes(json!([{"v": 1}, {"v": null}]), json!([{"v": 2}])).is_empty());
+
+    // Null fields still exist, so their removal is reported
+    assert_eq!(
+        changes(json!({"owner": null, "id": 1}), json!({"id": 1})),
+        vec!["- $.owner (null)"]
+    );
+    // A nullable field that changes kind is still retyped
+    assert_eq!(
+        changes(
+            json!([{"v": 1}, {"v": null}]),
+            json!([{"v": "1"}, {"v": null}])
+        ),
+        vec!["~ $[].v: number -> string"]
+    );
+}
+
+#[test]
+fn test_unusual_field_names_are_quoted() {
+    assert_eq!(
+        changes(
+            json!({}),
+            json!({"content-type": "x", "a b": 1, "_ok": true})
+        ),
+        vec![
+            "+ $._ok (boolean)",
+            "+ $[\"a b\"] (number)",
+            "+ $[\"content-type\"] (string)",
+        ]
+    );
+}
+
+#[test]
+fn test_top_level_kind_change() {
+    assert_eq!(
+        changes(json!({"items": []}), json!([])),
+        vec!["~ $: object -> array"]
+    );
+    assert!(changes(json!("a"), json!("b")).is_empty());
+}
+
+#[test]
+fn test_parse_header() {
+    let (name, value) = parse_header("Authorization: Bearer a:b").unwrap();
+    assert_eq!(name, "authorization");
+    assert_eq!(value, "Bearer a:b");
+
+    for invalid in ["Authorization", "Bad Name: x", ": x"] {
+        assert!(parse_header(invalid).is_err(), "{}", invalid);
+    }
+    // The value isn't repeated back, it may be a secret
+    let error = parse_header("X-Token: secret\u{1}")
+        .unwrap_err()
+        .to_string();
+    assert!(!error.contains("secret"), "{}", error);
+}
+
+#[test]
+fn test_json_endpoints_selects_captured_json_gets() {
+    let snapshot = snapshot_with(vec![
+        json_record("GET", "https://api.example.com/users", 200, json!([])),
+        json_record("GET", "https://api.example.com/users", 200, json!({})),
+        json_record("POST", "https://api.example.com/users", 201, json!({})),
+        json_record("GET", "https://api.example.com/missing", 404, json!({})),
+        json_record(
+            "GET",
+            "https://api.example.com/billing/plan",
+            200,
+            json!({}),
+        ),
+    ]);
+
+    let urls = |glob: Option<&str>| -> Vec<String> {
+        let glob = glob.map(|glob| Glob::new(glob).unwrap().compile_matcher());
+        json_endpoints(&snapshot, glob.as_ref())
+            .into_iter()
+            .map(|(url, _)| url)
+            .collect()
+    };
+    // The HTML page, POSTs, errors and repeated URLs are left out
+    assert_eq!(
+        urls(None),
+        vec![
+            "https://api.example.com/users",
+            "https://api.example.com/billing/plan",
+        ]
+    );
+    assert_eq!(
+        urls(Some("https://api.example.com/billing/*")),
+        vec!["https://api.example.com/billing/plan"]
+    );
+}
+
+#[tokio::test]
+async fn test_check_drift_against_a_live_server() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/users"))
+        .and(header("authorization", "Bearer t0ken"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!([{"id": "1", "name": "A"}])))
+        .mount(&server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/status"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({"ok": true})))
+        .mount(&server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/gone"))
+        .respond_with(ResponseTemplate::new(404))
+        .mount(&server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/html"))
+        .respond_with(ResponseTemplate::new(200).set_body_string("<html></html>"))
+        .mount(&server)
+        .await;
+
+    let origin = server.uri();
+    let snapshot = snapshot_with(vec![
+        json_record(
+            "GET",
+            &format!("{}/users", origin),
+            200,
+            json!([{"id": 1, "name": "A"}]),
+        ),
+        json_record(
+            "GET",
+            &format!("{}/status", origin),
+            200,
+            json!({"ok": false}),
+        ),
+        json_record("GET", &format!("{}/gone", origin), 200, json!({})),
+        json_record("GET", &format!("{}/html", origin), 200, json!({})),
+    ]);
+    let options = DriftOptions {
+        headers: vec!["Authorization: Bearer t0ken".to_string()],
+        ..DriftOptions::default()
+    };
+
+    let report = check_drift(&snapshot, &options).await.unwrap();
+    assert_eq!(report.endpoints.len(), 4);
+    assert_eq!(
+        report.endpoints[0].changes[0].to_string(),
+        "~ $[].id: number -> string"
+    );
+    assert!(!report.endpoints[1].drifted());
+    assert!(report.endpoints[1].error.is_none());
+    // Failures are reported per endpoint and don't stop the run
+    assert!(report.endpoints[2]
+        .error
+        .as_deref()
+        .unwrap()
+        .contains("404"));
+    assert!(report.endpoints[3]
+        .error
+        .as_deref()
+        .unwrap()
+        .contains("not JSON"));
+    assert_eq!(
+        (report.drifted(), report.unchanged(), report.failed()),
+        (1, 1, 2)
+    );
+}
+
+#[tokio::test]
+async fn test_check_drift_rejects_invalid_options() {
+    let snapshot = snapshot_with(Vec::new());
+    let options = DriftOptions {
+        url_glob: Some("https://example.com/{a".to_string()),
+        ..DriftOptions::default()
+    };
+    assert!(check_drift(&snapshot, &options).await.is_err());
+
+    let options = DriftOptions {
+        headers: vec!["no-colon".to_string()],
+        ..DriftOptions::default()
+    };
+    assert!(check_drift(&snapshot, &options).await.is_err());
+}
@@ -0,0 +1,55 @@
+use tempfile::TempDir;
+
+use crate::commands::encrypt::{decrypt_command, set_encrypted};
+use crate::error::WebMockError;
+use crate::storage::encryption::is_encrypted;
+use crate::storage::{PassphraseSource, Storage};
+use crate::test_utils::test_helpers::create_multi_request_snapshot;
+
+fn is_encrypted_on_disk(storage: &Storage, name: &str) -> bool {
+    is_encrypted(&std::fs::read(storage.get_snapshot_path(name).unwrap()).unwrap())
+}
+
+#[tokio::test]
+async fn test_set_encrypted_round_trips_a_snapshot() {
+    let temp_dir = TempDir::new().unwrap();
+    let storage = Storage::new(temp_dir.path().to_path_buf())
+        .with_passphrase(PassphraseSource::fixed("secret"));
+    let snapshot = create_multi_request_snapshot("site");
+    let requests = snapshot.requests.len();
+    storage.save_snapshot(snapshot).await.unwrap();
+
+    assert!(set_encrypted(&storage, "site", true).await.unwrap());
+    assert!(is_encrypted_on_disk(&storage, "site"));
+    assert!(
+        storage
+            .load_snapshot_metadata("site")
+            .await
+            .unwrap()
+            .encrypted
+    );
+    // Already encrypted, so nothing is rewritten
+    assert!(!set_encrypted(&storage, "site", true).await.unwrap());
+
+    // A wrong passphrase leaves the snapshot encrypted
+    let wrong = Storage::new(temp_dir.path().to_path_buf())
+        .with_passphrase(PassphraseSource::fixed("guess"));
+    let result = set_encrypted(&wrong, "site", false).await;
+    assert!(matches!(result, Err(WebMockError::WrongPassphrase(_))));
+    assert!(is_encrypted_on_disk(&storage, "site"));
+
+    assert!(set_encrypted(&storage, "site", false).await.unwrap());
+    assert!(!is_encrypted_on_disk(&storage, "site"));
+    let snapshot = storage.load_snapshot("site").await.unwrap();
+    assert!(!snapshot.encrypted);
+    assert_eq!(snapshot.requests.len(), requests);
+}
+
+#[tokio::test]
+async fn test_decrypt_command_reports_unknown_snapshots() {
+    let temp_dir = TempDir::new().unwrap();
+    let storage_arg = Some(temp_dir.path().to_string_lossy().to_string());
+
+    let result = decrypt_command("missing", storage_arg).await;
+    assert!(matches!(result, Err(WebMockError::SnapshotNotFound(_))));
+}
@@ -0,0 +1,511 @@
+use std::path::Path;
+
+use serde_json::{json, Value};
+use tempfile::TempDir;
+
+use crate::capture::proxy::records::{RequestRecord, ResponseRecord};
+use crate::commands::export::openapi::{
+    is_api_record, openapi_document, template_path, truncate_example, ParameterType,
+    INFERRED_EXTENSION, MAX_EXAMPLE_ITEMS,
+};
+use crate::commands::export::schema::{InferredSchema, MAX_DEPTH};
+use crate::commands::export::{export_command, render_export, ExportFormat, ExportOptions};
+use crate::storage::{Snapshot, Storage};
+use crate::test_utils::test_helpers::{create_multi_request_snapshot, create_test_record};
+
+fn record(method: &str, url: &str, status: u16, content_type: &str, body: &str) -> RequestRecord {
+    let headers = [("content-type", content_type)];
+    create_test_record(method, url, status, &headers, body.as_bytes())
+}
+
+fn api_snapshot() -> Snapshot {
+    let mut snapshot = create_multi_request_snapshot("shop");
+    snapshot.url = "https://shop.example.com/".to_string();
+    snapshot.requests = vec![
+        record(
+            "GET",
+            "https://shop.example.com/",
+            200,
+            "text/html",
+            "<html></html>",
+        ),
+        record(
+            "GET",
+            "https://shop.example.com/app.js",
+            200,
+            "application/javascript",
+            "1",
+        ),
+        record(
+            "GET",
+            "https://shop.example.com/api/users/17",
+            200,
+            "application/json",
+            r#"{"id": 17, "name": "Ada", "tags": ["admin"], "manager": null}"#,
+        ),
+        record(
+            "GET",
+            "https://shop.example.com/api/users/42?expand=1",
+            200,
+            "application/json; charset=utf-8",
+            r#"{"id": 42, "name": "Bob", "tags": [], "manager": {"id": 17}, "score": 0.5}"#,
+        ),
+        record(
+            "GET",
+            "https://shop.example.com/api/users/999",
+            404,
+            "application/json",
+            r#"{"error": "not found"}"#,
+        ),
+        record(
+            "POST",
+            "https://shop.example.com/api/users/42/orders/6f1c2d3e-4a5b-4c6d-8e9f-0a1b2c3d4e5f",
+            201,
+            "application/json",
+            r#"[{"sku": "A-1", "quantity": 2}, {"sku": "B-2", "quantity": 1.5}]"#,
+        ),
+        record(
+            "DELETE",
+            "https://shop.example.com/api/session",
+            204,
+            "text/plain",
+            "",
+        ),
+        record(
+            "GET",
+            "https://cdn.example.net/config.txt",
+            200,
+            "text/plain",
+            "theme=dark",
+        ),
+    ];
+    snapshot
+}
+
+fn document(snapshot: &Snapshot) -> Value {
+    serde_json::to_value(openapi_document(snapshot)).unwrap()
+}
+
+/// Check the parts of the OpenAPI 3.0 schema a generated document could get wrong
+fn assert_valid_openapi(document: &Value) {
+    assert_eq!(document["openapi"], "3.0.3");
+    assert!(document["info"]["title"].is_string());
+    assert!(document["info"]["version"].is_string());
+    for server in document["servers"].as_array().unwrap() {
+        assert!(server["url"].is_string());
+    }
+
+    for (path, item) in document["paths"].as_object().unwrap() {
+        assert!(path.starts_with('/'), "{}", path);
+        assert!(!path.contains('?'), "{}", path);
+        let declared: Vec<&str> = item["parameters"]
+            .as_array()
+            .map(|parameters| {
+                parameters
+                    .iter()
+                    .map(|parameter| {
+                        assert_eq!(parameter["in"], "path");
+                        assert_eq!(parameter["required"], true);
+                        assert_valid_schema(&parameter["schema"]);
+                        parameter["name"].as_str().unwrap()
+                    })
+                    .collect()
+            })
+            .unwrap_or_default();
+        // Every template parameter is declared, and only those
+        let templated: Vec<&str> = path
+            .split('/')
+            .filter_map(|segment| segment.strip_prefix('{')?.strip_suffix('}'))
+            .collect();
+        assert_eq!(declared, templated, "{}", path);
+
+        for (key, operation) in item.as_object().unwrap() {
+            match key.as_str() {
+                "parameters" | "servers" | "summary" | "description" => continue,
+                key if key.starts_with("x-") => continue,
+                "get" | "put" | "post" | "delete" | "options" | "head" | "patch" | "trace" => {}
+                other => panic!("unexpected path item field {}", other),
+            }
+            let responses = operation["responses"].as_object().unwrap();
+            assert!(!responses.is_empty());
+            for (status, response) in responses {
+                assert!(
+                    status.len() == 3 && status.parse::<u16>().is_ok(),
+                    "{}",
+                    status
+                );
+                assert!(response["description"].is_string());
+                if let Some(content) = response.get("content") {
+                    for media in content.as_object().unwrap().values() {
+                        if let Some(schema) = media.get("schema") {
+                            assert_valid_schema(schema);
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+fn assert_valid_schema(schema: &Value) {
+    let schema = schema.as_object().unwrap();
+    if let Some(kind) = schema.get("type") {
+        assert!(
+            ["boolean", "integer", "number", "string", "array", "object"]
+                .contains(&kind.as_str().unwrap()),
+            "{:?}",
+            kind
+        );
+        if kind == "array" {
+            assert!(schema.contains_key("items"));
+        }
+    }
+    if let Some(required) = schema.get("required") {
+        assert!(!required.as_array().unwrap().is_empty());
+    }
+    if let Some(items) = schema.get("items") {
+        assert_valid_schema(items);
+    }
+    if let Some(properties) = schema.get("properties") {
+        properties
+            .as_object()
+            .unwrap()
+            .values()
+            .for_each(assert_valid_schema);
+    }
+}
+
+#[test]
+fn test_template_path_collapses_identifiers() {
+    let template = template_path("/api/users/42/orders/6F1C2D3E-4A5B-4C6D-8E9F-0A1B2C3D4E5F");
+    assert_eq!(template.path, "/api/users/{id}/orders/{id2}");
+    let kinds: Vec<_> = template
+        .parameters
+        .iter()
+        .map(|parameter| (parameter.name.as_str(), parameter.kind))
+        .collect();
+    assert_eq!(
+        kinds,
+        [("id", ParameterType::Integer), ("id2", ParameterType::Uuid)]
+    );
+    assert_eq!(template.parameters[0].example, "42");
+
+    // Hash-like identifiers such as object IDs
+    assert_eq!(
+        template_path("/objects/507f1f77bcf86cd799439011").path,
+        "/objects/{id}"
+    );
+    assert_eq!(
+        template_path("/objects/507f1f77bcf86cd799439011").parameters[0].kind,
+        ParameterType::String
+    );
+}
+
+#[test]
+fn test_template_path_keeps_words() {
+    for path in [
+        "/",
+        "/api/v2/users",
+        "/static/app.3f2a.js",
+        "/blog/deadbeef",
+        // Hexadecimal but without a digit, so likely a word
+        "/abcdefabcdefabcdef",
+        "/users/me/",
+        "/files/12a",
+    ] {
+        let template = template_path(path);
+        assert_eq!(template.path, path);
+        assert!(template.parameters.is_empty(), "{}", path);
+    }
+    assert_eq!(template_path("").path, "/");
+    assert_eq!(template_path("/users/7/").path, "/users/{id}/");
+    assert_eq!(template_path("/2024/01/post").path, "/{id}/{id2}/post");
+}
+
+#[test]
+fn test_schema_inference_merges_samples() {
+    let mut schema = InferredSchema::of(&json!({
+        "id": 1, "name": "Ada", "tags": ["a"], "manager": null, "score": 1
+    }));
+    schema.sample(&json!({
+        "id": 2, "name": "Bob", "tags": [], "manager": {"id": 1}, "score": 0.5
+    }));
+
+    assert_eq!(
+        schema.to_json(),
+        json!({
+            "type": "object",
+            "properties": {
+                "id": {"type": "integer"},
+                "manager": {
+                    "type": "object",
+                    "properties": {"id": {"type": "integer"}},
+                    "required": ["id"],
+                    "nullable": true,
+                },
+                "name": {"type": "string"},
+                "score": {"type": "number"},
+                "tags": {"type": "array", "items": {"type": "string"}},
+            },
+            "required": ["id", "manager", "name", "score", "tags"],
+        })
+    );
+}
+
+#[test]
+fn test_schema_inference_optional_and_conflicting_fields() {
+    let mut schema = InferredSchema::of(&json!([
+        {"id": 1, "value": "text"},
+        {"id": 2, "value": 3, "extra": true},
+    ]));
+    schema.sample(&json!([]));
+
+    assert_eq!(
+        schema.to_json(),
+        json!({
+            "type": "array",
+            "items": {
+                "type": "object",
+                "properties": {
+                    "extra": {"type": "boolean"},
+                    "id": {"type": "integer"},
+                    // A string and a number, so left open
+                    "value": {},
+                },
+                "required": ["id", "value"],
+            },
+        })
+    );
+
+    // Conflicting top-level types stay conflicting once more samples agree
+    let mut schema = InferredSchema::of(&json!("text"));
+    schema.sample(&json!({"id": 1}));
+    schema.sample(&json!({"id": 1}));
+    assert_eq!(schema.to_json(), json!({}));
+
+    // Only nulls and empty arrays say little
+    assert_eq!(
+        InferredSchema::of(&json!(null)).to_json(),
+        json!({"nullable": true})
+    );
+    assert_eq!(
+        InferredSchema::of(&json!([])).to_json(),
+        json!({"type": "array", "items": {}})
+    );
+}
+
+#[test]
+fn test_schema_inference_stops_at_depth() {
+    let mut value = json!(1);
+    for _ in 0..MAX_DEPTH + 5 {
+        value = json!([value]);
+    }
+    let schema = InferredSchema::of(&value).to_json();
+    assert_valid_schema(&schema);
+
+    let mut depth = 0;
+    let mut current = &schema;
+    while let Some(items) = current.get("items") {
+        current = items;
+        depth += 1;
+    }
+    assert_eq!(depth, MAX_DEPTH);
+    assert_eq!(*current, json!({}));
+}
+
+#[test]
+fn test_truncate_example() {
+    let long = "x".repeat(500);
+    let example = truncate_example(&json!({"items": [1, 2, 3, 4, 5], "text": long}));
+    assert_eq!(
+        example["items"].as_array().unwrap().len(),
+        MAX_EXAMPLE_ITEMS
+    );
+    assert!(example["text"].as_str().unwrap().ends_with('…'));
+    assert!(example["text"].as_str().unwrap().len() < 500);
+}
+
+#[test]
+fn test_api_records_skip_page_assets() {
+    let snapshot = api_snapshot();
+    let api: Vec<&str> = snapshot
+        .requests
+        .iter()
+        .filter(|record| is_api_record(record))
+        .map(|record| record.url.as_str())
+        .collect();
+    assert_eq!(api.len(), 6);
+    assert!(!api.contains(&"https://shop.example.com/"));
+    assert!(!api.contains(&"https://shop.example.com/app.js"));
+
+    let mut failed = record(
+        "POST",
+        "https://shop.example.com/api/x",
+        502,
+        "text/plain",
+        "",
+    );
+    failed.response = ResponseRecord::failed("connection reset");
+    assert!(!is_api_record(&failed));
+}
+
+#[test]
+fn test_openapi_document() {
+    let document = document(&api_snapshot());
+    assert_valid_openapi(&document);
+    assert_eq!(document[INFERRED_EXTENSION], true);
+    assert_eq!(document["info"][INFERRED_EXTENSION], true);
+    assert_eq!(
+        document["servers"],
+        json!([{"url": "https://shop.example.com"}, {"url": "https://cdn.example.net"}])
+    );
+
+    let paths = document["paths"].as_object().unwrap();
+    assert_eq!(
+        paths.keys().collect::<Vec<_>>(),
+        [
+            "/api/session",
+            "/api/users/{id}",
+            "/api/users/{id}/orders/{id2}",
+            "/config.txt"
+        ]
+    );
+
+    let users = &paths["/api/users/{id}"];
+    assert_eq!(users[INFERRED_EXTENSION], true);
+    assert!(users.get("servers").is_none());
+    assert_eq!(users["parameters"][0]["schema"], json!({"type": "integer"}));
+    assert_eq!(users["parameters"][0]["example"], 17);
+    let get = &users["get"];
+    assert_eq!(get[INFERRED_EXTENSION], true);
+    let ok = &get["responses"]["200"];
+    assert_eq!(ok["description"], "OK, captured 2 times");
+    // Parameters of the content type don't make another media type
+    let json = &ok["content"]["application/json"];
+    assert_eq!(json["schema"][INFERRED_EXTENSION], true);
+    assert_eq!(
+        json["schema"]["required"],
+        json!(["id", "manager", "name", "tags"])
+    );
+    assert_eq!(json["schema"]["properties"]["manager"]["nullable"], true);
+    assert_eq!(json["example"]["name"], "Ada");
+    assert_eq!(
+        get["responses"]["404"]["content"]["application/json"]["example"],
+        json!({"error": "not found"})
+    );
+
+    let orders = &paths["/api/users/{id}/orders/{id2}"];
+    assert_eq!(
+        orders["parameters"][1]["schema"],
+        json!({"type": "string", "format": "uuid"})
+    );
+    let created = &orders["post"]["responses"]["201"]["content"]["application/json"];
+    assert_eq!(
+        created["schema"]["items"]["properties"]["quantity"],
+        json!({"type": "number"})
+    );
+
+    // No body, no content
+    let deleted = &paths["/api/session"]["delete"]["responses"]["204"];
+    assert!(deleted.get("content").is_none());
+
+    // Paths of other origins say where they are served
+    let config = &paths["/config.txt"];
+    assert_eq!(
+        config["servers"],
+        json!([{"url": "https://cdn.example.net"}])
+    );
+    assert_eq!(
+        config["get"]["responses"]["200"]["content"]["text/plain"]["example"],
+        "theme=dark"
+    );
+}
+
+#[test]
+fn test_openapi_document_merges_parameter_types() {
+    let mut snapshot = api_snapshot();
+    snapshot.requests.push(record(
+        "GET",
+        "https://shop.example.com/api/users/6f1c2d3e-4a5b-4c6d-8e9f-0a1b2c3d4e5f",
+        200,
+        "application/json",
+        "{}",
+    ));
+    let document = document(&snapshot);
+    assert_eq!(
+        document["paths"]["/api/users/{id}"]["parameters"][0]["schema"],
+        json!({"type": "string"})
+    );
+    assert_valid_openapi(&document);
+}
+
+#[test]
+fn test_openapi_document_without_api_calls() {
+    let mut snapshot = api_snapshot();
+    snapshot.requests.retain(|record| !is_api_record(record));
+    let document = document(&snapshot);
+    assert_eq!(document["paths"], json!({}));
+    assert_valid_openapi(&document);
+}
+
+#[test]
+fn test_render_export_formats() {
+    let snapshot = api_snapshot();
+    let yaml = render_export(
+        &snapshot,
+        ExportFormat::OpenApi,
+        Some(Path::new("api.yaml")),
+    )
+    .unwrap();
+    assert!(yaml.starts_with("openapi: "));
+    // Status codes stay strings, as OpenAPI requires
+    let parsed: Value = serde_yaml::from_str(&yaml).unwrap();
+    assert!(parsed["paths"]["/api/users/{id}"]["get"]["responses"]["200"].is_object());
+    assert_eq!(parsed, document(&snapshot));
+
+    let json = render_export(
+        &snapshot,
+        ExportFormat::OpenApi,
+        Some(Path::new("API.JSON")),
+    )
+    .unwrap();
+    assert_eq!(
+        serde_json::from_str::<Value>(&json).unwrap(),
+        document(&snapshot)
+    );
+    assert!(render_export(&snapshot, ExportFormat::OpenApi, None)
+        .unwrap()
+        .starts_with("openapi:"));
+}
+
+#[tokio::test]
+async fn test_export_command_writes_file() {
+    let temp_dir = TempDir::new().unwrap();
+    let storage = Storage::new(temp_dir.path().to_path_buf());
+    storage.save_snapshot(api_snapshot()).await.unwrap();
+    let output = temp_dir.path().join("api.yaml");
+
+    let options = ExportOptions {
+        format: ExportFormat::OpenApi,
+        output: Some(output.clone()),
+    };
+    export_command(
+        "shop",
+        Some(temp_dir.path().to_string_lossy().into_owned()),
+        &options,
+    )
+    .await
+    .unwrap();
+
+    let written: Value = serde_yaml::from_str(&std::fs::read_to_string(&output).unwrap()).unwrap();
+    assert_valid_openapi(&written);
+    assert_eq!(written["info"]["title"], "shop API");
+
+    let missing = export_command(
+        "missing",
+        Some(temp_dir.path().to_string_lossy().into_owned()),
+        &options,
+    )
+    .await;
+    assert!(missing.is_err());
+}
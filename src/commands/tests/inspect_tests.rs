@@ -1,4 +1,10 @@
-use crate::commands::inspect::{extract_content_type, format_size, truncate_url};
+use crate::capture::proxy::records::{InitiatorKind, RequestInitiator, RequestRecord};
+use crate::commands::inspect::{
+    body_preview, extract_content_type, format_size, frame_groups, initiator_tree, inspect_command,
+    select_record, truncate_url, InspectOptions, RecordSelector,
+};
+use crate::error::WebMockError;
+use crate::test_utils::test_helpers::*;
 use std::collections::HashMap;
 
 #[test]
@@ -125,3 +131,244 @@ fn test_format_size_very_small_values() {
     assert_eq!(format_size(100), "100 B");
     assert_eq!(format_size(999), "999 B");
 }
+
+fn record(method: &str, url: &str, status: u16, body: &[u8]) -> RequestRecord {
+    create_test_record(method, url, status, &[], body)
+}
+
+#[test]
+fn test_select_record_by_index_and_url() {
+    let requests = vec![
+        record("GET", "https://example.com/a", 200, b"a"),
+        record("GET", "https://example.com/b", 200, b"b"),
+        record("POST", "https://example.com/b", 201, b"created"),
+    ];
+
+    let (index, found) = select_record(&requests, &RecordSelector::Index(2)).unwrap();
+    assert_eq!(index, 2);
+    assert_eq!(found.response.body, b"b");
+    assert!(select_record(&requests, &RecordSelector::Index(0)).is_err());
+    assert!(select_record(&requests, &RecordSelector::Index(4)).is_err());
+
+    let (index, found) = select_record(
+        &requests,
+        &RecordSelector::Url("https://example.com/a".to_string()),
+    )
+    .unwrap();
+    assert_eq!(index, 1);
+    assert_eq!(found.response.body, b"a");
+    assert!(select_record(
+        &requests,
+        &RecordSelector::Url("https://example.com/missing".to_string())
+    )
+    .is_err());
+
+    // Several records for one URL name every candidate
+    match select_record(
+        &requests,
+        &RecordSelector::Url("https://example.com/b".to_string()),
+    ) {
+        Err(WebMockError::Config(message)) => {
+            assert!(message.contains("#2 GET (200)"), "{}", message);
+            assert!(message.contains("#3 POST (201)"), "{}", message);
+        }
+        other => panic!(
+            "Expected an ambiguity error, got {:?}",
+            other.map(|(i, _)| i)
+        ),
+    }
+}
+
+#[tokio::test]
+async fn test_inspect_dumps_binary_bodies_verbatim() {
+    let (temp_dir, storage) = create_temp_storage();
+    // Invalid UTF-8 and NUL bytes that a lossy conversion would mangle
+    let response_body: Vec<u8> = (0..=255u8).rev().chain([0xC3, 0x28, 0x00]).collect();
+    let request_body = vec![0xFF, 0xFE, 0x00, 0x80];
+
+    let mut snapshot = create_test_snapshot_with_name("binary");
+    let mut upload = record("POST", "https://example.com/upload", 200, &response_body);
+    upload.body = Some(request_body.clone());
+    snapshot.requests.push(upload);
+    storage.save_snapshot(snapshot.clone()).await.unwrap();
+
+    let mut urls: Vec<_> = snapshot.requests.iter().map(|r| r.url.clone()).collect();
+    urls.sort();
+    let position = urls
+        .iter()
+        .position(|url| url == "https://example.com/upload")
+        .unwrap();
+
+    let response_path = temp_dir.path().join("response.bin");
+    let request_path = temp_dir.path().join("request.bin");
+    let options = InspectOptions {
+        record: Some(RecordSelector::Index(position + 1)),
+        dump_body: Some(response_path.clone()),
+        dump_request_body: Some(request_path.clone()),
+        show_headers: true,
+        ..InspectOptions::default()
+    };
+    inspect_command(
+        "binary",
+        Some(temp_dir.path().to_string_lossy().to_string()),
+        &options,
+    )
+    .await
+    .unwrap();
+
+    assert_eq!(std::fs::read(&response_path).unwrap(), response_body);
+    assert_eq!(std::fs::read(&request_path).unwrap(), request_body);
+
+    // The same record selected by URL
+    let by_url = temp_dir.path().join("by_url.bin");
+    let options = InspectOptions {
+        record: Some(RecordSelector::Url(
+            "https://example.com/upload".to_string(),
+        )),
+        dump_body: Some(by_url.clone()),
+        ..InspectOptions::default()
+    };
+    inspect_command(
+        "binary",
+        Some(temp_dir.path().to_string_lossy().to_string()),
+        &options,
+    )
+    .await
+    .unwrap();
+    assert_eq!(std::fs::read(&by_url).unwrap(), response_body);
+}
+
+#[test]
+fn test_body_preview_truncates_with_total() {
+    let body = "a".repeat(100);
+    assert_eq!(body_preview(body.as_bytes(), None).unwrap(), body);
+    assert_eq!(body_preview(body.as_bytes(), Some(100)).unwrap(), body);
+    assert_eq!(
+        body_preview(body.as_bytes(), Some(10)).unwrap(),
+        "aaaaaaaaaa…[truncated, 100 bytes total]"
+    );
+
+    // A character cut by the limit is left out rather than mangled
+    let body = "ééé".as_bytes();
+    assert_eq!(
+        body_preview(body, Some(3)).unwrap(),
+        "é…[truncated, 6 bytes total]"
+    );
+
+    // Binary bodies are not shown, however short
+    assert!(body_preview(&[0xFF, 0xFE, 0x00], None).is_none());
+    assert!(body_preview(&[0x61, 0xFF, 0x61, 0x61], Some(3)).is_none());
+}
+
+#[tokio::test]
+async fn test_inspect_show_body_keeps_dumped_body_whole() {
+    let (temp_dir, storage) = create_temp_storage();
+    let body = "x".repeat(64 * 1024);
+    let mut snapshot = create_test_snapshot_with_name("large");
+    snapshot.requests = vec![record(
+        "GET",
+        "https://example.com/large.txt",
+        200,
+        body.as_bytes(),
+    )];
+    storage.save_snapshot(snapshot).await.unwrap();
+
+    let dumped = temp_dir.path().join("large.txt");
+    let options = InspectOptions {
+        record: Some(RecordSelector::Index(1)),
+        show_body: true,
+        max_body_bytes: Some(16),
+        dump_body: Some(dumped.clone()),
+        ..InspectOptions::default()
+    };
+    inspect_command(
+        "large",
+        Some(temp_dir.path().to_string_lossy().to_string()),
+        &options,
+    )
+    .await
+    .unwrap();
+
+    assert_eq!(std::fs::read(&dumped).unwrap(), body.as_bytes());
+}
+
+#[test]
+fn test_initiator_tree_nests_records_under_their_initiator() {
+    let initiated = |url: &str, kind: InitiatorKind, by: &str, line: Option<u32>| RequestRecord {
+        initiator: Some(RequestInitiator {
+            kind,
+            url: Some(by.to_string()),
+            line,
+        }),
+        ..record("GET", url, 200, b"")
+    };
+    let mut page = record("GET", "https://example.com/", 200, b"");
+    page.initiator = Some(RequestInitiator {
+        kind: InitiatorKind::Other,
+        url: None,
+        line: None,
+    });
+    let requests = vec![
+        page,
+        initiated(
+            "https://example.com/api",
+            InitiatorKind::Script,
+            "https://example.com/app.js",
+            Some(42),
+        ),
+        initiated(
+            "https://example.com/app.js",
+            InitiatorKind::Parser,
+            "https://example.com/",
+            Some(5),
+        ),
+        // The initiating script wasn't recorded
+        initiated(
+            "https://cdn.example/font.woff2",
+            InitiatorKind::Parser,
+            "https://cdn.example/style.css",
+            None,
+        ),
+        record("GET", "https://example.com/favicon.ico", 200, b""),
+    ];
+
+    assert_eq!(
+        initiator_tree(&requests),
+        [
+            "#1 GET https://example.com/ (other)",
+            "└ #3 GET https://example.com/app.js (parser, line 5)",
+            "  └ #2 GET https://example.com/api (script, line 42)",
+            "#4 GET https://cdn.example/font.woff2 (parser https://cdn.example/style.css)",
+            "#5 GET https://example.com/favicon.ico",
+        ]
+    );
+}
+
+#[test]
+fn test_frame_groups_list_records_by_frame() {
+    let in_frame = |url: &str, frame: Option<&str>, sequence: u64| RequestRecord {
+        frame_id: frame.map(str::to_string),
+        sequence: Some(sequence),
+        ..record("GET", url, 200, b"")
+    };
+    // In listing order, sorted by URL
+    let requests = vec![
+        in_frame("https://maps.example/embed", Some("MAP"), 3),
+        in_frame("https://maps.example/tiles.png", Some("MAP"), 4),
+        in_frame("https://pay.example/widget", Some("PAY"), 2),
+        in_frame("https://shop.example/", Some("MAIN"), 0),
+        in_frame("https://shop.example/app.js", Some("MAIN"), 1),
+        // Chrome named no frame for it
+        in_frame("https://shop.example/beacon", None, 5),
+    ];
+
+    assert_eq!(
+        frame_groups(&requests),
+        [
+            "main frame https://shop.example/ — #4, #5",
+            "frame https://pay.example/widget — #3",
+            "frame https://maps.example/embed — #1, #2",
+        ]
+    );
+    assert!(frame_groups(&requests[5..]).is_empty());
+}
@@ -11,7 +11,7 @@ async fn test_list_empty_snapshots() {
     let storage_path = temp_dir.path().join("webmock-storage");
 
     // This should not fail even with no snapshots
-    let result = list::list_command(Some(storage_path.to_string_lossy().to_string())).await;
+    let result = list::list_command(Some(storage_path.to_string_lossy().to_string()), false).await;
     assert!(result.is_ok());
 }
 
@@ -26,6 +26,90 @@ async fn test_list_with_snapshots() {
     let snapshot = create_test_snapshot_with_name("test-list-snapshot");
     storage.save_snapshot(snapshot).await.unwrap();
 
-    let result = list::list_command(Some(storage_path.to_string_lossy().to_string())).await;
+    let result = list::list_command(Some(storage_path.to_string_lossy().to_string()), false).await;
     assert!(result.is_ok());
 }
+
+fn write_synthetic_snapshot(storage_path: &std::path::Path, name: &str, size: u64, secs: u64) {
+    let snapshots_dir = storage_path.join("snapshots");
+    std::fs::create_dir_all(&snapshots_dir).unwrap();
+    let file = std::fs::File::create(snapshots_dir.join(format!("{}.msgpack", name))).unwrap();
+    file.set_len(size).unwrap();
+    file.set_modified(std::time::UNIX_EPOCH + std::time::Duration::from_secs(secs))
+        .unwrap();
+}
+
+#[tokio::test]
+async fn test_list_summary_aggregates_file_metadata() {
+    let temp_dir = TempDir::new().unwrap();
+    let storage_path = temp_dir.path().join("webmock-storage");
+
+    let sizes = [100, 700, 300, 600, 200, 500, 400];
+    for (index, size) in sizes.iter().enumerate() {
+        let secs = 1_700_000_000 + index as u64 * 86_400;
+        write_synthetic_snapshot(&storage_path, &format!("snap-{}", index), *size, secs);
+    }
+
+    let summary =
+        list::list_summary_command(Some(storage_path.to_string_lossy().to_string()), true)
+            .await
+            .unwrap();
+
+    assert_eq!(summary.snapshot_count, 7);
+    assert_eq!(summary.total_bytes, 2800);
+    let largest: Vec<(&str, u64)> = summary
+        .largest
+        .iter()
+        .map(|snapshot| (snapshot.name.as_str(), snapshot.bytes))
+        .collect();
+    assert_eq!(
+        largest,
+        vec![
+            ("snap-1", 700),
+            ("snap-3", 600),
+            ("snap-5", 500),
+            ("snap-6", 400),
+            ("snap-2", 300),
+        ]
+    );
+    assert_eq!(summary.oldest.unwrap().timestamp(), 1_700_000_000);
+    assert_eq!(
+        summary.newest.unwrap().timestamp(),
+        1_700_000_000 + 6 * 86_400
+    );
+}
+
+#[tokio::test]
+async fn test_list_summary_json_shape() {
+    let temp_dir = TempDir::new().unwrap();
+    let storage_path = temp_dir.path().join("webmock-storage");
+    write_synthetic_snapshot(&storage_path, "only", 1024, 1_700_000_000);
+
+    let summary =
+        list::list_summary_command(Some(storage_path.to_string_lossy().to_string()), false)
+            .await
+            .unwrap();
+
+    let json = serde_json::to_value(&summary).unwrap();
+    assert_eq!(json["snapshot_count"], 1);
+    assert_eq!(json["total_bytes"], 1024);
+    assert_eq!(json["largest"][0]["name"], "only");
+    assert_eq!(json["largest"][0]["bytes"], 1024);
+    assert_eq!(json["oldest"], "2023-11-14T22:13:20Z");
+}
+
+#[tokio::test]
+async fn test_list_summary_empty_storage() {
+    let temp_dir = TempDir::new().unwrap();
+    let storage_path = temp_dir.path().join("webmock-storage");
+
+    let summary =
+        list::list_summary_command(Some(storage_path.to_string_lossy().to_string()), true)
+            .await
+            .unwrap();
+
+    assert_eq!(summary.snapshot_count, 0);
+    assert_eq!(summary.total_bytes, 0);
+    assert!(summary.largest.is_empty());
+    assert!(summary.oldest.is_none());
+}
@@ -1,5 +1,23 @@
+mod analyze_tests;
+mod annotate_tests;
+mod assert_tests;
+mod bench_tests;
+mod browse_tests;
+mod ca_tests;
 mod capture_tests;
+mod completions_tests;
+mod config_tests;
 mod delete_tests;
+mod doctor_tests;
+mod drift_tests;
+mod encrypt_tests;
+mod export_tests;
 mod inspect_tests;
 mod list_tests;
+mod recapture_tests;
 mod serve_tests;
+mod set_entry_tests;
+mod shadow_tests;
+mod split_tests;
+mod trim_tests;
+mod verify_tests;
@@ -0,0 +1,143 @@
+use crate::capture::CaptureEnvironment;
+use crate::commands::recapture::{recapture_snapshot, RecaptureOptions};
+use crate::error::WebMockError;
+use crate::storage::Storage;
+use crate::test_utils::test_helpers::*;
+use std::time::Duration;
+
+/// Save the snapshot a stubbed capture would have produced
+async fn save_recaptured(staging: &Storage, name: &str, status: u16) {
+    let mut snapshot = create_multi_request_snapshot(name);
+    snapshot.requests[0].response.status = status;
+    snapshot.requests.pop();
+    staging.save_snapshot(snapshot).await.unwrap();
+}
+
+#[tokio::test]
+async fn test_recapture_replaces_snapshot_and_keeps_backup() {
+    let (_temp_dir, storage) = create_temp_storage();
+    let mut original = create_multi_request_snapshot("site");
+    original.environment = CaptureEnvironment::from_args(Some("fr-FR"), None, None).unwrap();
+    storage.save_snapshot(original.clone()).await.unwrap();
+
+    let options = RecaptureOptions {
+        diff: true,
+        ..RecaptureOptions::default()
+    };
+    let outcome = recapture_snapshot(&storage, "site", &options, |staging, url, capture| {
+        assert_eq!(url, original.url);
+        assert_eq!(capture.environment.locale.as_deref(), Some("fr-FR"));
+        async move {
+            save_recaptured(&staging, "site", 500).await;
+            Ok(())
+        }
+    })
+    .await
+    .unwrap();
+
+    let replaced = storage.load_snapshot("site").await.unwrap();
+    assert_eq!(replaced.requests.len(), original.requests.len() - 1);
+    assert_eq!(replaced.requests[0].response.status, 500);
+
+    let backup = outcome.backup.expect("backup should be kept by default");
+    assert_eq!(backup, storage.get_backup_path("site").unwrap());
+    let kept =
+        crate::storage::SnapshotSerializer::deserialize(&std::fs::read(&backup).unwrap()).unwrap();
+    assert_eq!(kept.requests.len(), original.requests.len());
+
+    let diff = outcome.diff.expect("diff was requested");
+    assert!(diff.added.is_empty());
+    assert_eq!(diff.removed.len(), 1);
+    assert_eq!(
+        diff.changed,
+        vec![format!("GET {}", original.requests[0].url)]
+    );
+
+    // Backups don't show up as snapshots
+    let names: Vec<_> = storage
+        .list_snapshots()
+        .await
+        .unwrap()
+        .into_iter()
+        .map(|info| info.name)
+        .collect();
+    assert_eq!(names, vec!["site".to_string()]);
+    assert_eq!(storage.snapshot_file_stats().await.unwrap().len(), 1);
+}
+
+#[tokio::test]
+async fn test_recapture_without_backup() {
+    let (_temp_dir, storage) = create_temp_storage();
+    storage
+        .save_snapshot(create_multi_request_snapshot("site"))
+        .await
+        .unwrap();
+
+    let options = RecaptureOptions {
+        backup: false,
+        ..RecaptureOptions::default()
+    };
+    let outcome = recapture_snapshot(&storage, "site", &options, |staging, _, _| async move {
+        save_recaptured(&staging, "site", 200).await;
+        Ok(())
+    })
+    .await
+    .unwrap();
+
+    assert!(outcome.backup.is_none());
+    assert!(outcome.diff.is_none());
+    assert!(!storage.get_backup_path("site").unwrap().exists());
+    assert!(!storage
+        .staging()
+        .get_snapshot_path("site")
+        .unwrap()
+        .exists());
+}
+
+#[tokio::test]
+async fn test_failed_recapture_leaves_snapshot_untouched() {
+    let (_temp_dir, storage) = create_temp_storage();
+    let original = create_multi_request_snapshot("site");
+    storage.save_snapshot(original.clone()).await.unwrap();
+    let before = std::fs::read(storage.get_snapshot_path("site").unwrap()).unwrap();
+
+    let result = recapture_snapshot(
+        &storage,
+        "site",
+        &RecaptureOptions::default(),
+        |staging, _, _| async move {
+            // A partial capture that got saved before the failure must not be used
+            save_recaptured(&staging, "site", 200).await;
+            Err(WebMockError::timeout(
+                "Page navigation",
+                Duration::from_secs(30),
+            ))
+        },
+    )
+    .await;
+
+    assert!(matches!(result, Err(WebMockError::Timeout { .. })));
+    let after = std::fs::read(storage.get_snapshot_path("site").unwrap()).unwrap();
+    assert_eq!(before, after);
+    assert!(!storage.get_backup_path("site").unwrap().exists());
+    assert!(!storage
+        .staging()
+        .get_snapshot_path("site")
+        .unwrap()
+        .exists());
+}
+
+#[tokio::test]
+async fn test_recapture_missing_snapshot() {
+    let (_temp_dir, storage) = create_temp_storage();
+
+    let result = recapture_snapshot(
+        &storage,
+        "missing",
+        &RecaptureOptions::default(),
+        |_, _, _| async { panic!("nothing to recapture") },
+    )
+    .await;
+
+    assert!(matches!(result, Err(WebMockError::SnapshotNotFound(_))));
+}
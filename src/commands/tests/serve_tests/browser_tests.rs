@@ -0,0 +1,37 @@
+use crate::commands::serve::browser::{chrome_proxy_args, entry_url};
+use std::path::Path;
+
+#[test]
+fn test_entry_url_keeps_path_and_query() {
+    assert_eq!(
+        entry_url("https://example.com/app/index.html?tab=2", 8080),
+        "http://localhost:8080/app/index.html?tab=2"
+    );
+}
+
+#[test]
+fn test_entry_url_root() {
+    assert_eq!(
+        entry_url("https://example.com", 3000),
+        "http://localhost:3000/"
+    );
+}
+
+#[test]
+fn test_entry_url_invalid_original_url() {
+    assert_eq!(entry_url("not a url", 8080), "http://localhost:8080/");
+}
+
+#[test]
+fn test_chrome_proxy_args() {
+    let args = chrome_proxy_args(
+        9000,
+        Path::new("/tmp/webmock-chrome"),
+        "https://example.com/",
+    );
+
+    assert!(args.contains(&"--proxy-server=http://127.0.0.1:9000".to_string()));
+    assert!(args.contains(&"--ignore-certificate-errors".to_string()));
+    assert!(args.contains(&"--user-data-dir=/tmp/webmock-chrome".to_string()));
+    assert_eq!(args.last().unwrap(), "https://example.com/");
+}
@@ -0,0 +1,54 @@
+use crate::commands::serve::daemon::{stop_command, PidFile};
+use std::time::Duration;
+use tempfile::TempDir;
+
+#[test]
+fn test_pid_file_holds_pid_until_dropped() {
+    let temp_dir = TempDir::new().unwrap();
+    let path = temp_dir.path().join("run").join("webmock.pid");
+
+    let pid_file = PidFile::create(&path).unwrap();
+    assert_eq!(
+        std::fs::read_to_string(&path).unwrap(),
+        format!("{}\n", std::process::id())
+    );
+
+    // A second server can't take over a PID file that is held
+    let error = PidFile::create(&path).unwrap_err();
+    assert!(error.to_string().contains("already running"), "{}", error);
+
+    drop(pid_file);
+    assert!(!path.exists());
+}
+
+#[test]
+fn test_pid_file_replaces_stale_contents() {
+    let temp_dir = TempDir::new().unwrap();
+    let path = temp_dir.path().join("webmock.pid");
+    std::fs::write(&path, "4194303999\n").unwrap();
+
+    let _pid_file = PidFile::create(&path).unwrap();
+    assert_eq!(
+        std::fs::read_to_string(&path).unwrap().trim(),
+        std::process::id().to_string()
+    );
+}
+
+#[tokio::test]
+async fn test_stop_removes_stale_pid_file() {
+    let temp_dir = TempDir::new().unwrap();
+    let path = temp_dir.path().join("webmock.pid");
+    std::fs::write(&path, "4194303\n").unwrap();
+
+    stop_command(&path, Duration::from_secs(1)).await.unwrap();
+    assert!(!path.exists());
+}
+
+#[tokio::test]
+async fn test_stop_without_pid_file_succeeds() {
+    let temp_dir = TempDir::new().unwrap();
+
+    stop_command(&temp_dir.path().join("missing.pid"), Duration::from_secs(1))
+        .await
+        .unwrap();
+}
@@ -1,4 +1,6 @@
 use crate::commands::serve_command;
+use crate::serve::ServeOptions;
+use crate::storage::ServeDefaults;
 use crate::test_utils::test_helpers::*;
 use std::net::{SocketAddr, TcpListener};
 
@@ -30,8 +32,12 @@ async fn test_serve_command_nonexistent_snapshot() {
     let free_port = find_free_port();
     let result = serve_command(
         "nonexistent-snapshot",
-        free_port,
+        &ServeDefaults {
+            port: Some(free_port),
+            ..ServeDefaults::default()
+        },
         Some(storage_path.to_string_lossy().to_string()),
+        ServeOptions::default(),
     )
     .await;
 
@@ -66,8 +72,12 @@ async fn test_serve_command_with_valid_snapshot() {
         std::time::Duration::from_secs(5),
         serve_command(
             "test-serve",
-            free_port,
+            &ServeDefaults {
+                port: Some(free_port),
+                ..ServeDefaults::default()
+            },
             Some(storage_path.to_string_lossy().to_string()),
+            ServeOptions::default(),
         ),
     )
     .await;
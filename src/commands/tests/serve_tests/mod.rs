@@ -1,4 +1,8 @@
+mod browser_tests;
+mod daemon_tests;
 mod format_tests;
 mod integration_tests;
+mod port_file_tests;
 mod port_tests;
+mod registry_tests;
 mod validation_tests;
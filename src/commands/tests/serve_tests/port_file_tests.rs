@@ -0,0 +1,151 @@
+use std::path::Path;
+use std::time::Duration;
+
+use tempfile::TempDir;
+
+use crate::commands::serve::port_file::{port_file_path, read_port_file, PortFile, PortFileEntry};
+use crate::commands::serve_command;
+use crate::serve::ServeOptions;
+use crate::storage::{ServeDefaults, Storage};
+use crate::test_utils::test_helpers::create_test_snapshot_with_name;
+
+#[test]
+fn test_port_file_path_names_a_file_per_snapshot_in_directories() {
+    let temp_dir = TempDir::new().unwrap();
+
+    let file = temp_dir.path().join("ports.json");
+    assert_eq!(port_file_path(&file, "shop"), file);
+
+    // An existing directory, or a path ending in a separator
+    assert_eq!(
+        port_file_path(temp_dir.path(), "shop"),
+        temp_dir.path().join("shop.port.json")
+    );
+    let new_dir = format!("{}/", temp_dir.path().join("ports").display());
+    assert_eq!(
+        port_file_path(Path::new(&new_dir), "shop"),
+        temp_dir.path().join("ports").join("shop.port.json")
+    );
+}
+
+#[test]
+fn test_port_file_is_written_and_removed_when_dropped() {
+    let temp_dir = TempDir::new().unwrap();
+    let path = temp_dir.path().join("run").join("shop.port.json");
+
+    // Claiming alone writes nothing, and leaves nothing behind
+    drop(PortFile::claim(&path).unwrap());
+    assert!(!path.exists());
+
+    let port_file = PortFile::claim(&path).unwrap();
+    let entry = PortFileEntry::new("shop", 8081);
+    port_file.write(&entry).unwrap();
+    assert_eq!(read_port_file(&path), Some(entry.clone()));
+    let json: serde_json::Value =
+        serde_json::from_str(&std::fs::read_to_string(&path).unwrap()).unwrap();
+    assert_eq!(json["snapshot"], "shop");
+    assert_eq!(json["port"], 8081);
+    assert_eq!(json["pid"], std::process::id());
+    assert!(json["started_at"].is_string());
+
+    // Only the port file itself is left in the directory
+    let files: Vec<_> = std::fs::read_dir(path.parent().unwrap())
+        .unwrap()
+        .map(|entry| entry.unwrap().file_name())
+        .collect();
+    assert_eq!(files, ["shop.port.json"]);
+
+    drop(port_file);
+    assert!(!path.exists());
+}
+
+#[test]
+fn test_port_file_of_a_crashed_server_is_replaced() {
+    let temp_dir = TempDir::new().unwrap();
+    let path = temp_dir.path().join("shop.port.json");
+    let stale = PortFileEntry {
+        pid: 4_194_303_999,
+        ..PortFileEntry::new("shop", 8080)
+    };
+    std::fs::write(&path, serde_json::to_string(&stale).unwrap()).unwrap();
+
+    let port_file = PortFile::claim(&path).unwrap();
+    port_file.write(&PortFileEntry::new("shop", 8082)).unwrap();
+    let entry = read_port_file(&path).unwrap();
+    assert_eq!(entry.port, 8082);
+    assert_eq!(entry.pid, std::process::id());
+
+    // Files that aren't port files are replaced as well
+    std::fs::write(&path, "garbage").unwrap();
+    assert!(PortFile::claim(&path).is_ok());
+}
+
+#[cfg(unix)]
+#[test]
+fn test_port_file_of_a_running_server_is_kept() {
+    let temp_dir = TempDir::new().unwrap();
+    let path = temp_dir.path().join("shop.port.json");
+    let running = PortFileEntry {
+        pid: std::os::unix::process::parent_id(),
+        ..PortFileEntry::new("shop", 8080)
+    };
+    std::fs::write(&path, serde_json::to_string(&running).unwrap()).unwrap();
+
+    let error = PortFile::claim(&path).unwrap_err();
+    assert!(error.to_string().contains("still running"), "{}", error);
+    assert_eq!(read_port_file(&path), Some(running));
+}
+
+#[tokio::test]
+async fn test_serve_writes_port_file_until_shutdown() {
+    let temp_dir = TempDir::new().unwrap();
+    let storage_path = temp_dir.path().join("storage");
+    let storage = Storage::new(storage_path.clone());
+    storage
+        .save_snapshot(create_test_snapshot_with_name("shop"))
+        .await
+        .unwrap();
+    let ports_dir = format!("{}/", temp_dir.path().join("ports").display());
+    let path = temp_dir.path().join("ports").join("shop.port.json");
+
+    let port = std::net::TcpListener::bind("127.0.0.1:0")
+        .unwrap()
+        .local_addr()
+        .unwrap()
+        .port();
+    let flags = ServeDefaults {
+        port: Some(port),
+        ..ServeDefaults::default()
+    };
+    let options = ServeOptions {
+        port_file: Some(ports_dir.into()),
+        max_lifetime: Some(Duration::from_secs(2)),
+        ..ServeOptions::default()
+    };
+    let written = async {
+        for _ in 0..40 {
+            if let Some(entry) = read_port_file(&path) {
+                return Some(entry);
+            }
+            tokio::time::sleep(Duration::from_millis(50)).await;
+        }
+        None
+    };
+    let (result, entry) = tokio::join!(
+        serve_command(
+            "shop",
+            &flags,
+            Some(storage_path.to_string_lossy().to_string()),
+            options,
+        ),
+        written
+    );
+    result.unwrap();
+
+    let entry = entry.expect("port file written once listening");
+    assert_eq!(entry.snapshot, "shop");
+    assert_eq!(entry.port, port);
+    assert_eq!(entry.pid, std::process::id());
+    // Removed on shutdown
+    assert!(!path.exists());
+}
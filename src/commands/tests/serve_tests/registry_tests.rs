@@ -0,0 +1,146 @@
+use std::time::Duration;
+
+use tempfile::TempDir;
+
+use crate::commands::serve::check_duplicate_server;
+use crate::commands::serve::port_file::PortFileEntry;
+use crate::commands::serve::registry::{ServerRegistry, SERVER_REGISTRY_DIR};
+use crate::commands::serve_command;
+use crate::serve::ServeOptions;
+use crate::storage::{ServeDefaults, Storage};
+use crate::test_utils::test_helpers::create_test_snapshot_with_name;
+
+fn registry_files(registry: &ServerRegistry) -> Vec<String> {
+    let mut files: Vec<String> = std::fs::read_dir(registry.dir())
+        .unwrap()
+        .map(|entry| entry.unwrap().file_name().to_string_lossy().into_owned())
+        .collect();
+    files.sort();
+    files
+}
+
+#[test]
+fn test_registered_server_is_listed_until_lease_is_dropped() {
+    let temp_dir = TempDir::new().unwrap();
+    let registry = ServerRegistry::new(temp_dir.path());
+    assert!(registry.running().unwrap().is_empty());
+
+    let entry = PortFileEntry::new("shop", 8081);
+    let lease = registry.register(&entry).unwrap();
+    assert!(lease
+        .path()
+        .starts_with(temp_dir.path().join(SERVER_REGISTRY_DIR)));
+    assert_eq!(registry.running().unwrap(), vec![entry]);
+    // Only the entry itself is left in the directory
+    assert_eq!(
+        registry_files(&registry),
+        [format!("{}-shop.json", std::process::id())]
+    );
+    // Servers of this process aren't duplicates of themselves
+    assert!(registry.serving("shop").unwrap().is_empty());
+
+    drop(lease);
+    assert!(registry.running().unwrap().is_empty());
+    assert!(registry_files(&registry).is_empty());
+}
+
+#[test]
+fn test_entries_of_crashed_servers_are_removed() {
+    let temp_dir = TempDir::new().unwrap();
+    let registry = ServerRegistry::new(temp_dir.path());
+    let live = registry
+        .register(&PortFileEntry::new("live", 8081))
+        .unwrap();
+
+    let crashed = PortFileEntry {
+        pid: 4_194_303_999,
+        ..PortFileEntry::new("shop", 8080)
+    };
+    std::fs::write(
+        registry.dir().join("4194303999-shop.json"),
+        serde_json::to_string(&crashed).unwrap(),
+    )
+    .unwrap();
+    std::fs::write(registry.dir().join("1-garbage.json"), "garbage").unwrap();
+
+    assert!(registry.serving("shop").unwrap().is_empty());
+    assert_eq!(
+        registry_files(&registry),
+        [live.path().file_name().unwrap().to_string_lossy()]
+    );
+}
+
+#[cfg(unix)]
+#[test]
+fn test_server_of_another_process_is_detected() {
+    let temp_dir = TempDir::new().unwrap();
+    let registry = ServerRegistry::new(temp_dir.path());
+    let other = PortFileEntry {
+        pid: std::os::unix::process::parent_id(),
+        ..PortFileEntry::new("shop", 8080)
+    };
+    std::fs::create_dir_all(registry.dir()).unwrap();
+    std::fs::write(
+        registry.dir().join(format!("{}-shop.json", other.pid)),
+        serde_json::to_string(&other).unwrap(),
+    )
+    .unwrap();
+
+    assert_eq!(registry.serving("shop").unwrap(), vec![other]);
+    assert!(registry.serving("blog").unwrap().is_empty());
+
+    // Scripts pass --allow-duplicate to go on without being asked
+    check_duplicate_server(&registry, "shop", true).unwrap();
+    check_duplicate_server(&registry, "blog", false).unwrap();
+}
+
+#[tokio::test]
+async fn test_serve_registers_until_shutdown() {
+    let temp_dir = TempDir::new().unwrap();
+    let storage = Storage::new(temp_dir.path().to_path_buf());
+    storage
+        .save_snapshot(create_test_snapshot_with_name("shop"))
+        .await
+        .unwrap();
+    let registry = ServerRegistry::new(temp_dir.path());
+
+    let port = std::net::TcpListener::bind("127.0.0.1:0")
+        .unwrap()
+        .local_addr()
+        .unwrap()
+        .port();
+    let flags = ServeDefaults {
+        port: Some(port),
+        ..ServeDefaults::default()
+    };
+    let options = ServeOptions {
+        max_lifetime: Some(Duration::from_secs(1)),
+        ..ServeOptions::default()
+    };
+    let registered = async {
+        for _ in 0..40 {
+            if let Some(entry) = registry.running().unwrap().pop() {
+                return Some(entry);
+            }
+            tokio::time::sleep(Duration::from_millis(25)).await;
+        }
+        None
+    };
+    let (result, entry) = tokio::join!(
+        serve_command(
+            "shop",
+            &flags,
+            Some(temp_dir.path().to_string_lossy().to_string()),
+            options,
+        ),
+        registered
+    );
+    result.unwrap();
+
+    let entry = entry.expect("server registered while serving");
+    assert_eq!(entry.snapshot, "shop");
+    assert_eq!(entry.port, port);
+    assert_eq!(entry.pid, std::process::id());
+    // Removed on shutdown
+    assert!(registry.running().unwrap().is_empty());
+}
@@ -41,3 +41,22 @@ fn test_validate_snapshot_name_valid() {
     let result = ValidationHelper::validate_snapshot_name("valid-snapshot_123");
     assert!(result.is_ok());
 }
+
+#[test]
+fn test_parse_lifetime_limit() {
+    use crate::commands::serve::parse_lifetime_limit;
+    use std::time::Duration;
+
+    assert_eq!(
+        parse_lifetime_limit("300s", "--idle-timeout").unwrap(),
+        Duration::from_secs(300)
+    );
+    assert_eq!(
+        parse_lifetime_limit("1h", "--max-lifetime").unwrap(),
+        Duration::from_secs(3600)
+    );
+
+    let error = parse_lifetime_limit("soon", "--idle-timeout").unwrap_err();
+    assert!(error.to_string().contains("--idle-timeout"));
+    assert!(parse_lifetime_limit("0s", "--max-lifetime").is_err());
+}
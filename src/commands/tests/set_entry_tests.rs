@@ -0,0 +1,109 @@
+use tempfile::TempDir;
+
+use crate::commands::set_entry::{has_document, set_entry, set_entry_command};
+use crate::error::WebMockError;
+use crate::storage::Storage;
+use crate::test_utils::test_helpers::{create_multi_request_snapshot, create_test_snapshot};
+
+#[test]
+fn test_entry_points_fall_back_to_the_primary_url() {
+    let mut snapshot = create_test_snapshot();
+    assert_eq!(snapshot.entry_points(), ["https://example.com"]);
+
+    snapshot.entry_points = vec![
+        "https://example.com".to_string(),
+        "https://www.example.com/".to_string(),
+        "https://example.com".to_string(),
+    ];
+    assert_eq!(
+        snapshot.entry_points(),
+        ["https://example.com", "https://www.example.com/"]
+    );
+}
+
+#[test]
+fn test_has_document_matches_get_records_by_normalized_url() {
+    let snapshot = create_multi_request_snapshot("site");
+
+    assert!(has_document(&snapshot, "https://example.com/api/data"));
+    assert!(has_document(
+        &snapshot,
+        "https://example.com:443/style.css#top"
+    ));
+    assert!(has_document(&snapshot, "https://example.com"));
+    assert!(!has_document(&snapshot, "https://example.com/missing"));
+    assert!(!has_document(&snapshot, "http://example.com/api/data"));
+}
+
+#[test]
+fn test_set_entry_keeps_the_previous_primary() {
+    let mut snapshot = create_test_snapshot();
+    snapshot.entry_points = vec!["https://example.com/login".to_string()];
+
+    set_entry(&mut snapshot, "https://example.com/home");
+    assert_eq!(snapshot.url, "https://example.com/home");
+    assert_eq!(
+        snapshot.entry_points,
+        [
+            "https://example.com/home",
+            "https://example.com",
+            "https://example.com/login"
+        ]
+    );
+
+    // Setting an existing entry point moves it to the front
+    set_entry(&mut snapshot, "https://example.com/login");
+    assert_eq!(
+        snapshot.entry_points(),
+        [
+            "https://example.com/login",
+            "https://example.com/home",
+            "https://example.com"
+        ]
+    );
+}
+
+#[tokio::test]
+async fn test_set_entry_command_requires_a_record_or_force() {
+    let temp_dir = TempDir::new().unwrap();
+    let storage = Storage::new(temp_dir.path().to_path_buf());
+    let snapshot = create_multi_request_snapshot("site");
+    let requests = snapshot.requests.len();
+    storage.save_snapshot(snapshot).await.unwrap();
+    let storage_arg = || Some(temp_dir.path().to_string_lossy().to_string());
+
+    set_entry_command("site", "https://example.com/api/data", false, storage_arg())
+        .await
+        .unwrap();
+    let snapshot = storage.load_snapshot("site").await.unwrap();
+    assert_eq!(snapshot.url, "https://example.com/api/data");
+    assert_eq!(snapshot.requests.len(), requests);
+    let info = storage.load_snapshot_metadata("site").await.unwrap();
+    assert_eq!(
+        info.entry_points,
+        ["https://example.com/api/data", "https://example.com"]
+    );
+
+    // No record answers it, so it needs --force
+    let result =
+        set_entry_command("site", "https://example.com/missing", false, storage_arg()).await;
+    assert!(matches!(result, Err(WebMockError::Config(_))));
+    assert_eq!(
+        storage.load_snapshot("site").await.unwrap().url,
+        "https://example.com/api/data"
+    );
+    set_entry_command("site", "https://example.com/missing", true, storage_arg())
+        .await
+        .unwrap();
+    assert_eq!(
+        storage.load_snapshot("site").await.unwrap().url,
+        "https://example.com/missing"
+    );
+
+    // Invalid URLs and unknown snapshots are refused even with --force
+    assert!(set_entry_command("site", "not a url", true, storage_arg())
+        .await
+        .is_err());
+    let result = set_entry_command("missing", "https://example.com/", true, storage_arg()).await;
+    assert!(matches!(result, Err(WebMockError::SnapshotNotFound(_))));
+}
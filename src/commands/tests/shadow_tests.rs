@@ -0,0 +1,300 @@
+use std::sync::Arc;
+
+use tokio::net::TcpListener;
+use tokio::time::{timeout, Duration};
+use url::Url;
+use wiremock::matchers::{method, path};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+use crate::capture::proxy::records::RequestRecord;
+use crate::commands::shadow::report::compare;
+use crate::commands::shadow::{
+    snapshot_covers_host, write_report, CapturedResponse, LiveResponse, Shadow, ShadowLog,
+    ShadowReport, Verdict,
+};
+use crate::serve::console::{ConsoleWriter, OutputLevel};
+use crate::serve::ForwardTarget;
+use crate::storage::Snapshot;
+use crate::test_utils::test_helpers::{create_test_record, create_test_snapshot_with_name};
+
+fn record(method: &str, url: &str, status: u16, content_type: &str) -> RequestRecord {
+    let headers = [("content-type", content_type)];
+    create_test_record(method, url, status, &headers, b"captured")
+}
+
+fn snapshot_with(records: Vec<RequestRecord>) -> Snapshot {
+    let mut snapshot = create_test_snapshot_with_name("shadow");
+    snapshot.requests = records;
+    snapshot
+}
+
+fn captured(status: u16, content_type: Option<&str>) -> CapturedResponse {
+    CapturedResponse {
+        index: 0,
+        status,
+        content_type: content_type.map(str::to_string),
+    }
+}
+
+fn live(status: u16, content_type: Option<&str>) -> LiveResponse {
+    LiveResponse {
+        status,
+        content_type: content_type.map(str::to_string),
+    }
+}
+
+/// Run a shadow proxy for `upstream` until the returned sender fires
+async fn start(
+    snapshot: Snapshot,
+    upstream: &str,
+) -> (
+    Arc<Shadow>,
+    String,
+    tokio::sync::oneshot::Sender<()>,
+    tokio::task::JoinHandle<()>,
+) {
+    let console = Arc::new(ConsoleWriter::new(std::io::sink(), OutputLevel::Quiet));
+    let target = ForwardTarget::parse_option(upstream, "--upstream").unwrap();
+    let shadow = Arc::new(Shadow::new(snapshot, target, console));
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let base = format!("http://{}", listener.local_addr().unwrap());
+    let (stop_tx, stop_rx) = tokio::sync::oneshot::channel::<()>();
+    let server = tokio::spawn({
+        let shadow = Arc::clone(&shadow);
+        async move {
+            shadow
+                .serve(listener, async {
+                    let _ = stop_rx.await;
+                })
+                .await
+                .unwrap();
+        }
+    });
+    (shadow, base, stop_tx, server)
+}
+
+#[test]
+fn test_compare_status_and_content_type() {
+    assert!(compare(
+        &captured(200, Some("application/json")),
+        &live(200, Some("application/json"))
+    )
+    .is_empty());
+    assert_eq!(
+        compare(
+            &captured(200, Some("application/json")),
+            &live(404, Some("text/html"))
+        ),
+        vec![
+            "status 200 → 404",
+            "content type application/json → text/html"
+        ]
+    );
+    assert_eq!(
+        compare(&captured(200, Some("application/json")), &live(200, None)),
+        vec!["content type application/json → none"]
+    );
+    // A sniffed content type isn't held against the live response
+    assert!(compare(&captured(200, None), &live(200, Some("text/html"))).is_empty());
+}
+
+#[test]
+fn test_log_groups_requests_by_endpoint() {
+    let log = ShadowLog::new("shadow", "https://staging.example.com/");
+    let users = captured(200, Some("application/json"));
+
+    assert_eq!(
+        log.observe(
+            "GET",
+            "https://staging.example.com/users",
+            Some(&users),
+            Ok(&live(200, Some("application/json")))
+        ),
+        Verdict::Hit
+    );
+    assert_eq!(
+        log.observe(
+            "GET",
+            "https://staging.example.com/users",
+            Some(&users),
+            Ok(&live(500, Some("application/json")))
+        ),
+        Verdict::Drift(vec!["status 200 → 500".to_string()])
+    );
+    assert_eq!(
+        log.observe(
+            "POST",
+            "https://staging.example.com/users",
+            None,
+            Ok(&live(201, None))
+        ),
+        Verdict::Miss
+    );
+    assert_eq!(
+        log.observe(
+            "GET",
+            "https://staging.example.com/users",
+            Some(&users),
+            Err("connection refused")
+        ),
+        Verdict::Unanswered
+    );
+
+    let report = log.report();
+    assert_eq!(
+        (report.requests, report.hits, report.misses, report.drifted),
+        (4, 3, 1, 1)
+    );
+    assert_eq!(report.coverage(), 75.0);
+    assert_eq!(report.endpoints.len(), 2);
+    let get = &report.endpoints[0];
+    assert_eq!(get.requests, 3);
+    assert_eq!(get.drifted_requests, 1);
+    assert_eq!(get.differences, vec!["status 200 → 500"]);
+    assert_eq!(get.error.as_deref(), Some("connection refused"));
+    let missed: Vec<&str> = report.missed().map(|e| e.method.as_str()).collect();
+    assert_eq!(missed, vec!["POST"]);
+    assert_eq!(ShadowReport::default().coverage(), 100.0);
+}
+
+#[test]
+fn test_snapshot_covers_upstream_host() {
+    let snapshot = snapshot_with(vec![record(
+        "GET",
+        "https://Staging.Example.com/users",
+        200,
+        "application/json",
+    )]);
+    assert!(snapshot_covers_host(
+        &snapshot,
+        &Url::parse("https://staging.example.com/").unwrap()
+    ));
+    assert!(!snapshot_covers_host(
+        &snapshot,
+        &Url::parse("https://prod.example.com/").unwrap()
+    ));
+}
+
+#[test]
+fn test_upstream_must_be_a_base_url() {
+    let error = ForwardTarget::parse_option("ftp://staging.example.com", "--upstream")
+        .unwrap_err()
+        .to_string();
+    assert!(error.contains("Invalid --upstream URL"), "{}", error);
+}
+
+#[tokio::test]
+async fn test_shadow_answers_live_and_reports_coverage() {
+    let upstream = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/users"))
+        .respond_with(ResponseTemplate::new(200).set_body_raw("[1,2]", "application/json"))
+        .mount(&upstream)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/orders"))
+        .respond_with(ResponseTemplate::new(404).set_body_raw("gone", "text/plain"))
+        .mount(&upstream)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/new"))
+        .respond_with(ResponseTemplate::new(200).set_body_raw("fresh", "text/plain"))
+        .mount(&upstream)
+        .await;
+
+    let uri = upstream.uri();
+    let snapshot = snapshot_with(vec![
+        record("GET", &format!("{}/users", uri), 200, "application/json"),
+        record(
+            "GET",
+            &format!("{}/orders", uri),
+            200,
+            "application/json; charset=utf-8",
+        ),
+    ]);
+    let (shadow, base, stop, server) = start(snapshot, &uri).await;
+
+    let client = reqwest::Client::new();
+    // Responses are always the live ones
+    let users = client.get(format!("{}/users", base)).send().await.unwrap();
+    assert_eq!(users.status(), 200);
+    assert_eq!(users.text().await.unwrap(), "[1,2]");
+    let orders = client.get(format!("{}/orders", base)).send().await.unwrap();
+    assert_eq!(orders.status(), 404);
+    assert_eq!(orders.text().await.unwrap(), "gone");
+    let new = client.get(format!("{}/new", base)).send().await.unwrap();
+    assert_eq!(new.status(), 200);
+    assert_eq!(new.text().await.unwrap(), "fresh");
+    client.get(format!("{}/new", base)).send().await.unwrap();
+
+    stop.send(()).unwrap();
+    timeout(Duration::from_secs(5), server)
+        .await
+        .unwrap()
+        .unwrap();
+
+    let report = shadow.report();
+    assert_eq!(report.snapshot, "shadow");
+    assert_eq!(
+        (report.requests, report.hits, report.misses, report.drifted),
+        (4, 2, 2, 1)
+    );
+    assert_eq!(report.endpoints.len(), 3);
+    assert_eq!(report.endpoints[0].record, Some(0));
+    assert_eq!(report.endpoints[0].drifted_requests, 0);
+    assert_eq!(report.endpoints[1].record, Some(1));
+    assert_eq!(
+        report.endpoints[1].differences,
+        vec![
+            "status 200 → 404",
+            "content type application/json → text/plain"
+        ]
+    );
+    let missed: Vec<&str> = report.missed().map(|e| e.url.as_str()).collect();
+    assert_eq!(missed, vec![format!("{}/new", uri)]);
+    assert_eq!(report.endpoints[2].requests, 2);
+
+    let hosts = shadow.host_summary();
+    assert_eq!(hosts.len(), 1);
+    assert_eq!((hosts[0].hits, hosts[0].misses), (2, 2));
+
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("shadow.json");
+    write_report(&report, &path).unwrap();
+    let json: serde_json::Value =
+        serde_json::from_str(&std::fs::read_to_string(&path).unwrap()).unwrap();
+    assert_eq!(json["misses"], 2);
+    assert_eq!(json["endpoints"][2]["record"], serde_json::Value::Null);
+    assert_eq!(json["endpoints"][1]["live_status"], 404);
+}
+
+#[tokio::test]
+async fn test_shadow_answers_502_when_the_upstream_is_down() {
+    let closed = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let uri = format!("http://{}", closed.local_addr().unwrap());
+    drop(closed);
+
+    let snapshot = snapshot_with(vec![record(
+        "GET",
+        &format!("{}/users", uri),
+        200,
+        "application/json",
+    )]);
+    let (shadow, base, stop, server) = start(snapshot, &uri).await;
+
+    let response = reqwest::get(format!("{}/users", base)).await.unwrap();
+    assert_eq!(response.status(), 502);
+    assert!(response.text().await.unwrap().contains("WebMock shadow"));
+
+    stop.send(()).unwrap();
+    timeout(Duration::from_secs(5), server)
+        .await
+        .unwrap()
+        .unwrap();
+
+    // The snapshot still covers the request, only the drift check is skipped
+    let report = shadow.report();
+    assert_eq!((report.hits, report.misses, report.drifted), (1, 0, 0));
+    assert!(report.endpoints[0].error.is_some());
+    assert_eq!(report.endpoints[0].live_status, None);
+}
@@ -0,0 +1,89 @@
+use tempfile::TempDir;
+
+use crate::capture::proxy::RequestRecord;
+use crate::commands::split::{split_command, RecordFilter, SplitOptions};
+use crate::storage::Storage;
+use crate::test_utils::test_helpers::{create_test_record, create_test_snapshot_with_name};
+
+fn record(method: &str, url: &str) -> RequestRecord {
+    create_test_record(method, url, 200, &[], b"")
+}
+
+fn filter(url_glob: &str, method: Option<&str>, invert: bool) -> RecordFilter {
+    RecordFilter::new(&SplitOptions {
+        url_glob: url_glob.to_string(),
+        method: method.map(str::to_string),
+        invert,
+    })
+    .unwrap()
+}
+
+#[test]
+fn test_record_filter_matches_url_glob_and_method() {
+    let billing = filter("https://app.example.com/api/billing/*", Some("get"), false);
+    assert!(billing.keeps(&record(
+        "GET",
+        "https://app.example.com/api/billing/invoices"
+    )));
+    assert!(billing.keeps(&record(
+        "GET",
+        "https://app.example.com/api/billing/invoices/2024?page=2"
+    )));
+    assert!(!billing.keeps(&record("POST", "https://app.example.com/api/billing/pay")));
+    assert!(!billing.keeps(&record("GET", "https://app.example.com/api/users")));
+    assert_eq!(
+        billing.describe(),
+        "GET https://app.example.com/api/billing/*"
+    );
+
+    let no_media = filter("*.{mp4,png}", None, true);
+    assert!(!no_media.keeps(&record("GET", "https://cdn.example.com/intro.mp4")));
+    assert!(no_media.keeps(&record("GET", "https://app.example.com/")));
+    assert_eq!(no_media.describe(), "all except *.{mp4,png}");
+}
+
+#[test]
+fn test_record_filter_rejects_invalid_input() {
+    let invalid = |url_glob: &str, method: Option<&str>| {
+        RecordFilter::new(&SplitOptions {
+            url_glob: url_glob.to_string(),
+            method: method.map(str::to_string),
+            invert: false,
+        })
+        .unwrap_err()
+        .to_string()
+    };
+
+    assert!(invalid("https://example.com/{a,b", None).contains("--url-glob"));
+    assert!(invalid("*", Some("")).contains("--method"));
+    assert!(invalid("*", Some("GET /")).contains("--method"));
+}
+
+#[tokio::test]
+async fn test_split_command_creates_snapshot() {
+    let temp_dir = TempDir::new().unwrap();
+    let storage = Storage::new(temp_dir.path().to_path_buf());
+    let mut snapshot = create_test_snapshot_with_name("source");
+    snapshot.requests = vec![
+        record("GET", "https://example.com/"),
+        record("GET", "https://example.com/api/data"),
+    ];
+    storage.save_snapshot(snapshot).await.unwrap();
+
+    let options = SplitOptions {
+        url_glob: "https://example.com/api/*".to_string(),
+        ..SplitOptions::default()
+    };
+    split_command(
+        "source",
+        "api",
+        Some(temp_dir.path().to_string_lossy().to_string()),
+        &options,
+    )
+    .await
+    .unwrap();
+
+    let api = storage.load_snapshot("api").await.unwrap();
+    assert_eq!(api.requests.len(), 1);
+    assert_eq!(api.split_from.unwrap().filter, "https://example.com/api/*");
+}
@@ -0,0 +1,228 @@
+use std::path::{Path, PathBuf};
+use tempfile::TempDir;
+
+use crate::commands::trim::{check_report, trim, trim_command, TrimOptions};
+use crate::serve::{CoverageReport, RecordCoverage};
+use crate::storage::manifest::file_sha256;
+use crate::storage::{Snapshot, SnapshotSerializer, Storage};
+use crate::test_utils::test_helpers::create_multi_request_snapshot;
+
+/// A report for `snapshot` with the given hits per record, in stored order
+fn report(snapshot: &Snapshot, sha256: &str, hits: &[Option<u64>]) -> CoverageReport {
+    CoverageReport {
+        snapshot: snapshot.name.clone(),
+        sha256: sha256.to_string(),
+        records: snapshot
+            .requests
+            .iter()
+            .zip(hits)
+            .filter_map(|(record, hits)| {
+                Some(RecordCoverage {
+                    sequence: record.sequence.unwrap(),
+                    method: record.method.clone(),
+                    url: record.url.clone(),
+                    hits: (*hits)?,
+                })
+            })
+            .collect(),
+    }
+}
+
+fn options(coverage: &Path) -> TrimOptions {
+    TrimOptions {
+        coverage: coverage.to_path_buf(),
+        ..TrimOptions::default()
+    }
+}
+
+fn urls(snapshot: &Snapshot) -> Vec<&str> {
+    snapshot
+        .requests
+        .iter()
+        .map(|record| record.url.as_str())
+        .collect()
+}
+
+/// Save a snapshot with three records and write a coverage report for it
+async fn saved_with_report(
+    temp_dir: &TempDir,
+    hits: &[Option<u64>],
+) -> (Storage, Option<String>, PathBuf) {
+    let storage = Storage::new(temp_dir.path().to_path_buf());
+    storage
+        .save_snapshot(create_multi_request_snapshot("site"))
+        .await
+        .unwrap();
+    let loaded = storage.load_snapshot("site").await.unwrap();
+    let (sha256, _) = file_sha256(&storage.get_snapshot_path("site").unwrap()).unwrap();
+    let coverage = temp_dir.path().join("coverage.json");
+    report(&loaded, &sha256, hits).write(&coverage).unwrap();
+    let storage_arg = Some(temp_dir.path().to_string_lossy().to_string());
+    (storage, storage_arg, coverage)
+}
+
+#[test]
+fn test_trim_removes_records_served_too_rarely() {
+    let mut snapshot = create_multi_request_snapshot("site");
+    for (sequence, record) in snapshot.requests.iter_mut().enumerate() {
+        record.sequence = Some(sequence as u64);
+    }
+    snapshot.annotations.insert(1, "old stylesheet".to_string());
+    // The report doesn't list the last record
+    let coverage = report(&snapshot, "abc", &[Some(3), Some(0), None]);
+    let defaults = options(Path::new("coverage.json"));
+
+    let mut trimmed = snapshot.clone();
+    let removed = trim(&mut trimmed, &coverage, &defaults);
+    assert_eq!(urls(&trimmed), vec!["https://example.com/"]);
+    assert_eq!(removed, trimmed.trimmed);
+    assert_eq!(removed.len(), 2);
+    assert_eq!(removed[0].url, "https://example.com/style.css");
+    assert_eq!(removed[0].hits, Some(0));
+    assert_eq!(removed[0].annotation.as_deref(), Some("old stylesheet"));
+    assert_eq!(removed[0].body_size, 40);
+    assert_eq!(removed[1].url, "https://example.com/api/data");
+    assert_eq!(removed[1].hits, None);
+    assert!(trimmed.annotations.is_empty());
+
+    let mut kept_misses = snapshot.clone();
+    trim(
+        &mut kept_misses,
+        &coverage,
+        &TrimOptions {
+            keep_misses: true,
+            ..defaults.clone()
+        },
+    );
+    assert_eq!(
+        urls(&kept_misses),
+        vec!["https://example.com/", "https://example.com/api/data"]
+    );
+
+    let mut rarely = snapshot.clone();
+    let removed = trim(
+        &mut rarely,
+        &coverage,
+        &TrimOptions {
+            min_hits: 4,
+            ..defaults.clone()
+        },
+    );
+    assert!(rarely.requests.is_empty());
+    assert_eq!(removed[0].hits, Some(3));
+}
+
+#[test]
+fn test_check_report_needs_the_same_snapshot_file() {
+    let snapshot = create_multi_request_snapshot("site");
+    let coverage = report(&snapshot, "ABC123", &[]);
+
+    assert!(check_report(&coverage, "site", "abc123").is_ok());
+    let error = check_report(&coverage, "other", "abc123")
+        .unwrap_err()
+        .to_string();
+    assert!(error.contains("for snapshot 'site'"), "{}", error);
+    let error = check_report(&coverage, "site", "def456")
+        .unwrap_err()
+        .to_string();
+    assert!(error.contains("changed since"), "{}", error);
+}
+
+#[tokio::test]
+async fn test_trim_command_in_place_keeps_the_previous_version() {
+    let temp_dir = TempDir::new().unwrap();
+    let (storage, storage_arg, coverage) =
+        saved_with_report(&temp_dir, &[Some(2), Some(0), Some(1)]).await;
+
+    trim_command("site", storage_arg.clone(), &options(&coverage))
+        .await
+        .unwrap();
+
+    let trimmed = storage.load_snapshot("site").await.unwrap();
+    assert_eq!(
+        urls(&trimmed),
+        vec!["https://example.com/", "https://example.com/api/data"]
+    );
+    assert_eq!(trimmed.trimmed.len(), 1);
+    assert_eq!(trimmed.trimmed[0].url, "https://example.com/style.css");
+
+    let backup = storage.get_backup_path("site").unwrap();
+    let previous = SnapshotSerializer::deserialize(&std::fs::read(backup).unwrap()).unwrap();
+    assert_eq!(previous.requests.len(), 3);
+    assert!(previous.trimmed.is_empty());
+
+    // The snapshot changed, so the same report no longer applies
+    let error = trim_command("site", storage_arg, &options(&coverage))
+        .await
+        .unwrap_err()
+        .to_string();
+    assert!(error.contains("changed since"), "{}", error);
+}
+
+#[tokio::test]
+async fn test_trim_command_writes_a_new_snapshot() {
+    let temp_dir = TempDir::new().unwrap();
+    let (storage, storage_arg, coverage) =
+        saved_with_report(&temp_dir, &[Some(1), Some(0), None]).await;
+    let source = std::fs::read(storage.get_snapshot_path("site").unwrap()).unwrap();
+
+    let to_lean = TrimOptions {
+        output: Some("lean".to_string()),
+        keep_misses: true,
+        ..options(&coverage)
+    };
+    trim_command("site", storage_arg.clone(), &to_lean)
+        .await
+        .unwrap();
+
+    let lean = storage.load_snapshot("lean").await.unwrap();
+    assert_eq!(lean.name, "lean");
+    assert_eq!(
+        urls(&lean),
+        vec!["https://example.com/", "https://example.com/api/data"]
+    );
+    assert_eq!(
+        std::fs::read(storage.get_snapshot_path("site").unwrap()).unwrap(),
+        source
+    );
+    assert!(!storage.get_backup_path("site").unwrap().exists());
+
+    // An existing snapshot is never overwritten
+    let error = trim_command("site", storage_arg, &to_lean)
+        .await
+        .unwrap_err()
+        .to_string();
+    assert!(error.contains("already exists"), "{}", error);
+}
+
+#[tokio::test]
+async fn test_trim_command_refuses_other_snapshots_reports() {
+    let temp_dir = TempDir::new().unwrap();
+    let (storage, storage_arg, coverage) =
+        saved_with_report(&temp_dir, &[Some(0), Some(0), Some(0)]).await;
+    storage
+        .save_snapshot(create_multi_request_snapshot("other"))
+        .await
+        .unwrap();
+    let other = std::fs::read(storage.get_snapshot_path("other").unwrap()).unwrap();
+
+    let error = trim_command("other", storage_arg.clone(), &options(&coverage))
+        .await
+        .unwrap_err()
+        .to_string();
+    assert!(
+        error.contains("for snapshot 'site', not 'other'"),
+        "{}",
+        error
+    );
+    assert_eq!(
+        std::fs::read(storage.get_snapshot_path("other").unwrap()).unwrap(),
+        other
+    );
+
+    let zero = TrimOptions {
+        min_hits: 0,
+        ..options(&coverage)
+    };
+    assert!(trim_command("site", storage_arg, &zero).await.is_err());
+}
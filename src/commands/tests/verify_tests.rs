@@ -0,0 +1,64 @@
+use tempfile::TempDir;
+
+use crate::commands::verify::verify_command;
+use crate::error::WebMockError;
+use crate::storage::Storage;
+use crate::test_utils::test_helpers::create_multi_request_snapshot;
+
+async fn saved_snapshot() -> (TempDir, Storage, Option<String>) {
+    let temp_dir = TempDir::new().unwrap();
+    let storage = Storage::new(temp_dir.path().to_path_buf());
+    storage
+        .save_snapshot(create_multi_request_snapshot("site"))
+        .await
+        .unwrap();
+    let storage_arg = Some(temp_dir.path().to_string_lossy().into_owned());
+    (temp_dir, storage, storage_arg)
+}
+
+#[tokio::test]
+async fn test_verify_accepts_intact_snapshot() {
+    let (_temp_dir, _storage, storage_arg) = saved_snapshot().await;
+
+    verify_command("site", false, storage_arg.clone())
+        .await
+        .unwrap();
+    verify_command("site", true, storage_arg).await.unwrap();
+}
+
+#[tokio::test]
+async fn test_verify_manifest_fails_on_tampered_file() {
+    let (_temp_dir, storage, storage_arg) = saved_snapshot().await;
+    let snapshot_path = storage.get_snapshot_path("site").unwrap();
+    let mut data = std::fs::read(&snapshot_path).unwrap();
+    data.truncate(data.len() / 2);
+    std::fs::write(&snapshot_path, &data).unwrap();
+
+    let error = verify_command("site", true, storage_arg).await.unwrap_err();
+    assert!(matches!(error, WebMockError::CommandFailed(_)));
+    assert!(error.to_string().contains("changed since its manifest"));
+}
+
+#[tokio::test]
+async fn test_verify_manifest_requires_a_manifest() {
+    let (_temp_dir, storage, storage_arg) = saved_snapshot().await;
+    std::fs::remove_file(storage.get_manifest_path("site").unwrap()).unwrap();
+
+    // Without --manifest the snapshot still verifies
+    verify_command("site", false, storage_arg.clone())
+        .await
+        .unwrap();
+    let error = verify_command("site", true, storage_arg).await.unwrap_err();
+    assert!(error.to_string().contains("has no manifest"));
+}
+
+#[tokio::test]
+async fn test_verify_reports_missing_snapshot() {
+    let temp_dir = TempDir::new().unwrap();
+    let storage_arg = Some(temp_dir.path().to_string_lossy().into_owned());
+
+    assert!(matches!(
+        verify_command("missing", true, storage_arg).await,
+        Err(WebMockError::SnapshotNotFound(_))
+    ));
+}
@@ -0,0 +1,216 @@
+//! Trim command implementation
+//!
+//! Removes the records a test suite never requested from a snapshot, going
+//! by the report `serve --coverage` wrote while the suite ran. What was
+//! removed stays listed in the snapshot's metadata, so `inspect` still
+//! shows what the capture had. The report must come from the very file
+//! being trimmed: a report of another snapshot, or of an older version of
+//! this one, would remove the wrong records.
+
+use std::collections::HashSet;
+use std::path::PathBuf;
+
+use chrono::Utc;
+use tracing::info;
+
+use crate::commands::inspect::format_size;
+use crate::error::{Result, WebMockError};
+use crate::feedback::{UserFeedback, ValidationHelper};
+use crate::serve::CoverageReport;
+use crate::storage::manifest::file_sha256;
+use crate::storage::{Snapshot, Storage, TrimmedRecord};
+
+/// Options for the trim command
+#[derive(Debug, Clone)]
+pub struct TrimOptions {
+    /// Report written by `serve --coverage`
+    pub coverage: PathBuf,
+    /// Write the trimmed records to this new snapshot instead of in place
+    pub output: Option<String>,
+    /// Keep records the report doesn't list, instead of treating them as never served
+    pub keep_misses: bool,
+    /// Records served fewer times than this are removed
+    pub min_hits: u64,
+}
+
+impl Default for TrimOptions {
+    fn default() -> Self {
+        Self {
+            coverage: PathBuf::new(),
+            output: None,
+            keep_misses: false,
+            min_hits: 1,
+        }
+    }
+}
+
+/// Handle the trim command
+pub async fn trim_command(
+    snapshot_name: &str,
+    storage_arg: Option<String>,
+    options: &TrimOptions,
+) -> Result<()> {
+    info!(
+        "Trimming snapshot '{}' by {}",
+        snapshot_name,
+        options.coverage.display()
+    );
+
+    ValidationHelper::validate_snapshot_name(snapshot_name)?;
+    if let Some(output) = &options.output {
+        ValidationHelper::validate_snapshot_name(output)?;
+        if output == snapshot_name {
+            return Err(WebMockError::config(
+                "--output needs a different name than the snapshot; leave it out to trim in place",
+            ));
+        }
+    }
+    if options.min_hits == 0 {
+        return Err(WebMockError::config("--min-hits must be at least 1"));
+    }
+    let report = CoverageReport::load(&options.coverage)?;
+
+    let storage_path = crate::commands::get_storage_path(storage_arg)?;
+    let storage = Storage::new(storage_path);
+    if let Some(output) = &options.output {
+        if storage.snapshot_exists(output)? {
+            return Err(WebMockError::config(format!(
+                "Snapshot '{}' already exists; delete it first or choose another name",
+                output
+            )));
+        }
+    }
+
+    let mut snapshot = match storage.load_snapshot(snapshot_name).await {
+        Ok(snapshot) => snapshot,
+        Err(e) => {
+            if matches!(e, WebMockError::SnapshotNotFound(_)) {
+                UserFeedback::tip("Use 'webmock list' to see available snapshots");
+            }
+            return Err(e);
+        }
+    };
+    let path = storage.resolve_snapshot_path(snapshot_name)?;
+    let (sha256, size_before) = tokio::task::spawn_blocking(move || file_sha256(&path)).await??;
+    check_report(&report, snapshot_name, &sha256)?;
+
+    let records_before = snapshot.requests.len();
+    let trimmed = trim(&mut snapshot, &report, options);
+    if trimmed.is_empty() {
+        UserFeedback::info(&format!(
+            "Every record of '{}' was served at least {} time(s); nothing to trim",
+            snapshot_name, options.min_hits
+        ));
+        return Ok(());
+    }
+
+    let target = options.output.as_deref().unwrap_or(snapshot_name);
+    if target != snapshot_name {
+        // The screenshot and DOM stay with the source, under its name
+        snapshot.artifacts = Default::default();
+    }
+    snapshot.name = target.to_string();
+    let backup = storage
+        .rewrite_snapshot(snapshot, options.output.is_none())
+        .await?;
+    let target_path = storage.get_snapshot_path(target)?;
+    let size_after = tokio::fs::metadata(&target_path)
+        .await
+        .map_err(|e| WebMockError::io("read snapshot file", &target_path, e))?
+        .len();
+
+    UserFeedback::success(&format!(
+        "Removed {} record(s) served fewer than {} time(s)",
+        trimmed.len(),
+        options.min_hits
+    ));
+    UserFeedback::line(&format!(
+        "   📊 Records: {} → {}",
+        records_before,
+        records_before - trimmed.len()
+    ));
+    UserFeedback::line(&format!(
+        "   💾 Size: {} → {}",
+        format_size(size_before as usize),
+        format_size(size_after as usize)
+    ));
+    match (&options.output, backup) {
+        (Some(output), _) => UserFeedback::info(&format!(
+            "Wrote '{}'; '{}' is unchanged",
+            output, snapshot_name
+        )),
+        (None, Some(backup)) => {
+            UserFeedback::info(&format!("Previous version kept at {}", backup.display()))
+        }
+        (None, None) => {}
+    }
+    UserFeedback::tip(&format!(
+        "See what was removed with: webmock inspect {}",
+        target
+    ));
+    Ok(())
+}
+
+/// Fail unless `report` counted the snapshot `name` whose file hashes to `sha256`
+pub fn check_report(report: &CoverageReport, name: &str, sha256: &str) -> Result<()> {
+    if report.snapshot != name {
+        return Err(WebMockError::config(format!(
+            "The coverage report is for snapshot '{}', not '{}'",
+            report.snapshot, name
+        )));
+    }
+    if !report.sha256.eq_ignore_ascii_case(sha256) {
+        return Err(WebMockError::config(format!(
+            "Snapshot '{}' changed since the coverage report was written; serve it with --coverage again",
+            name
+        )));
+    }
+    Ok(())
+}
+
+/// Remove the records of `snapshot` that `report` shows served fewer than `min_hits` times
+///
+/// Records the report doesn't list count as never served, unless
+/// `keep_misses` is set. The removed records are added to the snapshot's
+/// `trimmed` list and returned.
+pub fn trim(
+    snapshot: &mut Snapshot,
+    report: &CoverageReport,
+    options: &TrimOptions,
+) -> Vec<TrimmedRecord> {
+    let hits = report.hits_by_sequence();
+    let trimmed_at = Utc::now();
+    let mut trimmed = Vec::new();
+    let mut removed = HashSet::new();
+    snapshot.requests.retain(|record| {
+        let served = record
+            .sequence
+            .and_then(|sequence| hits.get(&sequence).copied());
+        let keep = match served {
+            Some(served) => served >= options.min_hits,
+            None => options.keep_misses,
+        };
+        if !keep {
+            removed.extend(record.sequence);
+            trimmed.push(TrimmedRecord {
+                sequence: record.sequence,
+                method: record.method.clone(),
+                url: record.url.clone(),
+                status: record.response.status,
+                body_size: record.response.body.len() as u64,
+                hits: served,
+                annotation: record
+                    .sequence
+                    .and_then(|sequence| snapshot.annotations.get(&sequence).cloned()),
+                trimmed_at,
+            });
+        }
+        keep
+    });
+
+    snapshot
+        .annotations
+        .retain(|sequence, _| !removed.contains(sequence));
+    snapshot.trimmed.extend(trimmed.iter().cloned());
+    trimmed
+}
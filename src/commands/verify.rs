@@ -0,0 +1,81 @@
+//! Verify command implementation
+//!
+//! Checks that a snapshot file can be read back, and with `--manifest` that
+//! it is the file its manifest describes, so a CI job restoring snapshots
+//! from a cache fails on a truncated or tampered file instead of serving it.
+
+use tracing::info;
+
+use crate::error::{Result, WebMockError};
+use crate::feedback::{UserFeedback, ValidationHelper};
+use crate::storage::{ManifestCheck, Storage};
+
+/// Handle the verify command
+pub async fn verify_command(
+    snapshot_name: &str,
+    check_manifest: bool,
+    storage_arg: Option<String>,
+) -> Result<()> {
+    info!("Verifying snapshot '{}'", snapshot_name);
+
+    ValidationHelper::validate_snapshot_name(snapshot_name)?;
+
+    let storage_path = crate::commands::get_storage_path(storage_arg)?;
+    let storage = Storage::new(storage_path);
+
+    // Check the hash first, so a tampered file is reported as such rather than as undecodable
+    if check_manifest {
+        match storage.check_manifest(snapshot_name).await? {
+            ManifestCheck::Matches(manifest) => UserFeedback::success(&format!(
+                "Snapshot file matches its manifest (sha256 {})",
+                manifest.sha256
+            )),
+            ManifestCheck::Missing => {
+                UserFeedback::tip(&format!(
+                    "Write one with: webmock manifest {}",
+                    snapshot_name
+                ));
+                return Err(WebMockError::command_failed(format!(
+                    "Snapshot '{}' has no manifest",
+                    snapshot_name
+                )));
+            }
+            ManifestCheck::Mismatch {
+                manifest,
+                sha256,
+                total_bytes,
+            } => {
+                return Err(WebMockError::command_failed(format!(
+                    "Snapshot '{}' changed since its manifest was written: expected sha256 {} ({} bytes), found {} ({} bytes)",
+                    snapshot_name, manifest.sha256, manifest.total_bytes, sha256, total_bytes
+                )));
+            }
+        }
+    }
+
+    let snapshot = match storage.load_snapshot(snapshot_name).await {
+        Ok(snapshot) => snapshot,
+        Err(e) => {
+            if matches!(e, WebMockError::SnapshotNotFound(_)) {
+                UserFeedback::tip("Use 'webmock list' to see available snapshots");
+            }
+            return Err(e);
+        }
+    };
+    UserFeedback::success(&format!(
+        "Snapshot '{}' reads back with {} records",
+        snapshot_name,
+        snapshot.requests.len()
+    ));
+    if snapshot.provenance.is_none() {
+        UserFeedback::warning(&format!(
+            "Snapshot '{}' has no provenance: it was saved before webmock recorded where captures came from",
+            snapshot_name
+        ));
+        UserFeedback::tip(&format!(
+            "Recapture it to record one: webmock recapture {}",
+            snapshot_name
+        ));
+    }
+    Ok(())
+}
@@ -1,6 +1,8 @@
 #[cfg(test)]
 mod tests;
 
+use std::path::Path;
+use std::time::Duration;
 use thiserror::Error;
 
 /// Main error type for WebMock CLI operations
@@ -30,9 +32,9 @@ pub enum WebMockError {
     #[error("Invalid URL '{0}': {1}")]
     InvalidUrl(String, String),
 
-    /// Network timeout error
-    #[error("Network timeout after {0} seconds")]
-    Timeout(u64),
+    /// An operation took longer than it was allowed to
+    #[error("{operation} timed out after {after:?}")]
+    Timeout { operation: String, after: Duration },
 
     /// Serialization/deserialization errors
     #[error("Serialization error: {0}")]
@@ -65,6 +67,14 @@ pub enum WebMockError {
     /// Command execution errors
     #[error("Command execution failed: {0}")]
     CommandFailed(String),
+
+    /// An encrypted snapshot couldn't be decrypted
+    #[error("Wrong passphrase for snapshot '{0}', or its file was modified")]
+    WrongPassphrase(String),
+
+    /// A capture gave up on a broken page before it settled
+    #[error("Capture aborted: {0}")]
+    CaptureAborted(String),
 }
 
 impl WebMockError {
@@ -93,15 +103,42 @@ impl WebMockError {
         WebMockError::CommandFailed(msg.into())
     }
 
+    /// Create a new timeout error for `operation`, e.g. "Page navigation"
+    pub fn timeout<S: Into<String>>(operation: S, after: Duration) -> Self {
+        WebMockError::Timeout {
+            operation: operation.into(),
+            after,
+        }
+    }
+
+    /// Wrap an I/O error with the operation and path it failed on
+    ///
+    /// Produces e.g. "failed to write snapshot file /home/me/.webmock/snapshots/foo.msgpack:
+    /// Permission denied (os error 13)". The error kind is kept, so callers
+    /// can still tell a missing file from a permission problem.
+    pub fn io(operation: &str, path: impl AsRef<Path>, error: std::io::Error) -> Self {
+        WebMockError::Storage(std::io::Error::new(
+            error.kind(),
+            format!(
+                "failed to {} {}: {}",
+                operation,
+                path.as_ref().display(),
+                error
+            ),
+        ))
+    }
+
     /// Check if this error is recoverable
     pub fn is_recoverable(&self) -> bool {
         match self {
             WebMockError::PortInUse(_) => true,
-            WebMockError::Timeout(_) => true,
+            WebMockError::Timeout { .. } => true,
             WebMockError::SnapshotNotFound(_) => false,
             WebMockError::ChromeNotFound => false,
             WebMockError::PermissionDenied(_) => false,
             WebMockError::InvalidUrl(_, _) => false,
+            WebMockError::WrongPassphrase(_) => false,
+            WebMockError::CaptureAborted(_) => false,
             _ => true,
         }
     }
@@ -127,8 +164,8 @@ impl WebMockError {
             WebMockError::InvalidUrl(url, reason) => {
                 format!("Invalid URL '{}': {}. Please provide a valid HTTP or HTTPS URL.", url, reason)
             }
-            WebMockError::Timeout(seconds) => {
-                format!("Operation timed out after {} seconds. Try increasing the timeout with --timeout option.", seconds)
+            WebMockError::Timeout { operation, after } => {
+                format!("{} timed out after {:?}. Try increasing the timeout with --timeout option, or with {} for snapshot storage.", operation, after, crate::storage::IO_TIMEOUT_ENV)
             }
             WebMockError::ChromeNotFound => {
                 "Chrome browser not found. Please install Google Chrome or Chromium browser and ensure it's in your PATH.".to_string()
@@ -139,6 +176,9 @@ impl WebMockError {
             WebMockError::InvalidSnapshot(msg) => {
                 format!("Invalid snapshot data: {}. The snapshot file may be corrupted.", msg)
             }
+            WebMockError::WrongPassphrase(name) => {
+                format!("Could not decrypt snapshot '{}': the passphrase is wrong or the file was modified. Check {}.", name, crate::storage::encryption::PASSPHRASE_ENV)
+            }
             _ => self.to_string(),
         }
     }
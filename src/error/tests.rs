@@ -17,9 +17,13 @@ fn test_user_message() {
 #[test]
 fn test_recoverable_errors() {
     assert!(WebMockError::PortInUse(8080).is_recoverable());
-    assert!(WebMockError::Timeout(30).is_recoverable());
+    assert!(WebMockError::timeout("Page navigation", Duration::from_secs(30)).is_recoverable());
     assert!(!WebMockError::ChromeNotFound.is_recoverable());
     assert!(!WebMockError::SnapshotNotFound("test".to_string()).is_recoverable());
+    assert!(
+        !WebMockError::CaptureAborted("the main document returned 503".to_string())
+            .is_recoverable()
+    );
 }
 
 #[test]
@@ -30,3 +34,21 @@ fn test_error_creation_helpers() {
     let config_error = WebMockError::config("Invalid setting");
     assert!(matches!(config_error, WebMockError::Config(_)));
 }
+
+#[test]
+fn test_io_error_names_operation_and_path() {
+    let error = WebMockError::io(
+        "write snapshot file",
+        std::path::Path::new("/srv/webmock/snapshots/foo.msgpack"),
+        std::io::Error::new(std::io::ErrorKind::PermissionDenied, "permission denied"),
+    );
+
+    assert_eq!(
+        error.to_string(),
+        "Storage error: failed to write snapshot file /srv/webmock/snapshots/foo.msgpack: permission denied"
+    );
+    match error {
+        WebMockError::Storage(e) => assert_eq!(e.kind(), std::io::ErrorKind::PermissionDenied),
+        other => panic!("expected a storage error, got {:?}", other),
+    }
+}
@@ -49,6 +49,24 @@ impl ChromeDetection {
         Err(WebMockError::ChromeNotFound)
     }
 
+    /// Find a runnable Chrome executable, honoring CHROME_PATH first
+    pub fn find_chrome_executable() -> Option<String> {
+        let env_path = std::env::var("CHROME_PATH").ok();
+
+        env_path
+            .into_iter()
+            .chain(
+                Self::get_platform_chrome_paths()
+                    .into_iter()
+                    .map(String::from),
+            )
+            .find(|cmd| {
+                Self::run_command_with_timeout(cmd, Duration::from_secs(1))
+                    .map(|output| output.status.success())
+                    .unwrap_or(false)
+            })
+    }
+
     /// Run a command with a timeout to prevent hanging
     fn run_command_with_timeout(
         cmd: &str,
@@ -134,8 +152,8 @@ impl ChromeDetection {
     pub fn show_installation_help() {
         UserFeedback::section("🌐 Chrome Installation Required");
 
-        println!("WebMock CLI requires Google Chrome or Chromium to capture web pages.");
-        println!();
+        eprintln!("WebMock CLI requires Google Chrome or Chromium to capture web pages.");
+        eprintln!();
 
         // Detect platform and show appropriate instructions
         if cfg!(target_os = "macos") {
@@ -148,70 +166,70 @@ impl ChromeDetection {
             Self::show_generic_instructions();
         }
 
-        println!();
+        eprintln!();
         UserFeedback::tip("After installation, restart your terminal and try the command again");
     }
 
     fn show_macos_instructions() {
         UserFeedback::info("macOS Installation Options:");
-        println!();
-        println!("Option 1 - Homebrew (Recommended):");
-        println!("  brew install --cask google-chrome");
-        println!();
-        println!("Option 2 - Direct Download:");
-        println!("  Visit: https://www.google.com/chrome/");
-        println!("  Download and install the .dmg file");
-        println!();
-        println!("Option 3 - Chromium (Open Source):");
-        println!("  brew install --cask chromium");
+        eprintln!();
+        eprintln!("Option 1 - Homebrew (Recommended):");
+        eprintln!("  brew install --cask google-chrome");
+        eprintln!();
+        eprintln!("Option 2 - Direct Download:");
+        eprintln!("  Visit: https://www.google.com/chrome/");
+        eprintln!("  Download and install the .dmg file");
+        eprintln!();
+        eprintln!("Option 3 - Chromium (Open Source):");
+        eprintln!("  brew install --cask chromium");
     }
 
     fn show_linux_instructions() {
         UserFeedback::info("Linux Installation Options:");
-        println!();
-        println!("Ubuntu/Debian:");
-        println!("  sudo apt update");
-        println!("  sudo apt install google-chrome-stable");
-        println!("  # Or for Chromium:");
-        println!("  sudo apt install chromium-browser");
-        println!();
-        println!("Fedora/RHEL:");
-        println!("  sudo dnf install google-chrome-stable");
-        println!("  # Or for Chromium:");
-        println!("  sudo dnf install chromium");
-        println!();
-        println!("Arch Linux:");
-        println!("  sudo pacman -S google-chrome");
-        println!("  # Or for Chromium:");
-        println!("  sudo pacman -S chromium");
-        println!();
-        println!("Snap (Universal):");
-        println!("  sudo snap install chromium");
+        eprintln!();
+        eprintln!("Ubuntu/Debian:");
+        eprintln!("  sudo apt update");
+        eprintln!("  sudo apt install google-chrome-stable");
+        eprintln!("  # Or for Chromium:");
+        eprintln!("  sudo apt install chromium-browser");
+        eprintln!();
+        eprintln!("Fedora/RHEL:");
+        eprintln!("  sudo dnf install google-chrome-stable");
+        eprintln!("  # Or for Chromium:");
+        eprintln!("  sudo dnf install chromium");
+        eprintln!();
+        eprintln!("Arch Linux:");
+        eprintln!("  sudo pacman -S google-chrome");
+        eprintln!("  # Or for Chromium:");
+        eprintln!("  sudo pacman -S chromium");
+        eprintln!();
+        eprintln!("Snap (Universal):");
+        eprintln!("  sudo snap install chromium");
     }
 
     fn show_windows_instructions() {
         UserFeedback::info("Windows Installation Options:");
-        println!();
-        println!("Option 1 - Direct Download (Recommended):");
-        println!("  Visit: https://www.google.com/chrome/");
-        println!("  Download and run the installer");
-        println!();
-        println!("Option 2 - Chocolatey:");
-        println!("  choco install googlechrome");
-        println!();
-        println!("Option 3 - Winget:");
-        println!("  winget install Google.Chrome");
+        eprintln!();
+        eprintln!("Option 1 - Direct Download (Recommended):");
+        eprintln!("  Visit: https://www.google.com/chrome/");
+        eprintln!("  Download and run the installer");
+        eprintln!();
+        eprintln!("Option 2 - Chocolatey:");
+        eprintln!("  choco install googlechrome");
+        eprintln!();
+        eprintln!("Option 3 - Winget:");
+        eprintln!("  winget install Google.Chrome");
     }
 
     fn show_generic_instructions() {
         UserFeedback::info("Installation Instructions:");
-        println!();
-        println!("Please install Google Chrome or Chromium:");
-        println!("  • Google Chrome: https://www.google.com/chrome/");
-        println!("  • Chromium: https://www.chromium.org/");
-        println!();
-        println!("Make sure the browser is installed in a standard location");
-        println!("or available in your system's PATH.");
+        eprintln!();
+        eprintln!("Please install Google Chrome or Chromium:");
+        eprintln!("  • Google Chrome: https://www.google.com/chrome/");
+        eprintln!("  • Chromium: https://www.chromium.org/");
+        eprintln!();
+        eprintln!("Make sure the browser is installed in a standard location");
+        eprintln!("or available in your system's PATH.");
     }
 
     /// Validate Chrome installation and provide helpful feedback
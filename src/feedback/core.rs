@@ -1,13 +1,21 @@
 use colored::*;
 use std::io::{self, Write};
 
+use super::output;
+
 /// Core user feedback system
+///
+/// Everything is written to stderr, keeping stdout for command data. With
+/// `--quiet` only warnings, errors and prompts are shown.
 pub struct UserFeedback;
 
 impl UserFeedback {
     /// Display a success message
     pub fn success(message: &str) {
-        println!("{} {}", "✅".bright_green(), message.bright_green());
+        if output::is_quiet() {
+            return;
+        }
+        eprintln!("{} {}", "✅".bright_green(), message.bright_green());
     }
 
     /// Display an error message
@@ -17,29 +25,38 @@ impl UserFeedback {
 
     /// Display a warning message
     pub fn warning(message: &str) {
-        println!("{} {}", "⚠️".bright_yellow(), message.bright_yellow());
+        eprintln!("{} {}", "⚠️".bright_yellow(), message.bright_yellow());
     }
 
     /// Display an info message
     pub fn info(message: &str) {
-        println!("{} {}", "ℹ️".bright_blue(), message.bright_blue());
+        if output::is_quiet() {
+            return;
+        }
+        eprintln!("{} {}", "ℹ️".bright_blue(), message.bright_blue());
     }
 
     /// Display a progress message
     pub fn progress(message: &str) {
-        print!("{} {}... ", "⏳".bright_cyan(), message.bright_cyan());
-        io::stdout().flush().unwrap();
+        if output::is_quiet() {
+            return;
+        }
+        eprint!("{} {}... ", "⏳".bright_cyan(), message.bright_cyan());
+        io::stderr().flush().unwrap();
     }
 
     /// Display a completed message
     pub fn completed(message: &str) {
-        println!("{} {}", "✨".bright_magenta(), message.bright_magenta());
+        if output::is_quiet() {
+            return;
+        }
+        eprintln!("{} {}", "✨".bright_magenta(), message.bright_magenta());
     }
 
     /// Ask for user confirmation
     pub fn confirm(prompt: &str) -> io::Result<bool> {
-        print!("{} {} [y/N]: ", "❓".bright_cyan(), prompt);
-        io::stdout().flush()?;
+        eprint!("{} {} [y/N]: ", "❓".bright_cyan(), prompt);
+        io::stderr().flush()?;
 
         let mut input = String::new();
         io::stdin().read_line(&mut input)?;
@@ -50,27 +67,47 @@ impl UserFeedback {
 
     /// Display a loading spinner (simplified)
     pub fn loading(message: &str) {
-        println!("{} {}", "🔄".bright_blue(), message);
+        if output::is_quiet() {
+            return;
+        }
+        eprintln!("{} {}", "🔄".bright_blue(), message);
     }
 
     /// Display a tip message
     pub fn tip(message: &str) {
-        println!("{} {}", "💡".bright_yellow(), message.bright_yellow());
+        if output::is_quiet() {
+            return;
+        }
+        eprintln!("{} {}", "💡".bright_yellow(), message.bright_yellow());
     }
 
     /// Display a section header
     pub fn section(message: &str) {
-        println!(
+        if output::is_quiet() {
+            return;
+        }
+        eprintln!(
             "\n{} {}",
             "📍".bright_cyan().bold(),
             message.bright_cyan().bold()
         );
-        println!("{}", "─".repeat(message.len() + 4).bright_black());
+        eprintln!("{}", "─".repeat(message.len() + 4).bright_black());
     }
 
     /// Display a separator line
     pub fn separator() {
-        println!("{}", "═".repeat(60).bright_black());
+        if output::is_quiet() {
+            return;
+        }
+        eprintln!("{}", "═".repeat(60).bright_black());
+    }
+
+    /// Display a plain informational line, such as a detail under a section
+    pub fn line(message: &str) {
+        if output::is_quiet() {
+            return;
+        }
+        eprintln!("{}", message);
     }
 
     /// Show command-specific help
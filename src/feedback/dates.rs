@@ -0,0 +1,90 @@
+//! Snapshot dates as people read them
+//!
+//! Dates are shown in the local timezone with an explicit offset, followed
+//! by how long ago that was, e.g. `2026-10-16 16:03:12 +02:00 (2 hours ago)`.
+//! With `--utc` they keep the fixed `2026-10-16 14:03:12 UTC` form scripts
+//! may parse. Without timezone data, as in minimal musl containers, dates are
+//! shown in UTC and labelled as such.
+
+use std::path::Path;
+
+use chrono::{DateTime, FixedOffset, Local, Utc};
+
+use super::output;
+
+const MINUTE: u64 = 60;
+const HOUR: u64 = 60 * MINUTE;
+const DAY: u64 = 24 * HOUR;
+const MONTH: u64 = 30 * DAY;
+const YEAR: u64 = 365 * DAY;
+
+/// Format a date for display, in UTC with `--utc` and in local time otherwise
+pub fn format_date(at: DateTime<Utc>) -> String {
+    if output::is_utc() {
+        return format_utc(at);
+    }
+    format_date_in(at, Utc::now(), local_offset(at))
+}
+
+/// The fixed UTC form, e.g. `2026-10-16 14:03:12 UTC`
+pub fn format_utc(at: DateTime<Utc>) -> String {
+    at.format("%Y-%m-%d %H:%M:%S UTC").to_string()
+}
+
+/// `at` at the given UTC offset, or in UTC without one, followed by how long before `now` it was
+pub fn format_date_in(
+    at: DateTime<Utc>,
+    now: DateTime<Utc>,
+    offset: Option<FixedOffset>,
+) -> String {
+    let absolute = match offset {
+        Some(offset) => at
+            .with_timezone(&offset)
+            .format("%Y-%m-%d %H:%M:%S %:z")
+            .to_string(),
+        None => format_utc(at),
+    };
+    format!("{} ({})", absolute, relative_time(at, now))
+}
+
+/// How long before `now` `at` was, e.g. "just now", "5 minutes ago" or "in 2 hours"
+///
+/// Counts are rounded down; a month is 30 days and a year 365.
+pub fn relative_time(at: DateTime<Utc>, now: DateTime<Utc>) -> String {
+    let seconds = now.signed_duration_since(at).num_seconds();
+    let amount = match seconds.unsigned_abs() {
+        elapsed if elapsed < MINUTE => return "just now".to_string(),
+        elapsed if elapsed < HOUR => count(elapsed / MINUTE, "minute"),
+        elapsed if elapsed < DAY => count(elapsed / HOUR, "hour"),
+        elapsed if elapsed < MONTH => count(elapsed / DAY, "day"),
+        elapsed if elapsed < YEAR => count(elapsed / MONTH, "month"),
+        elapsed => count(elapsed / YEAR, "year"),
+    };
+    if seconds > 0 {
+        format!("{} ago", amount)
+    } else {
+        // Saved by a machine whose clock is ahead
+        format!("in {}", amount)
+    }
+}
+
+/// Offset of the local timezone at `at`, or `None` when there is no timezone data
+pub fn local_offset(at: DateTime<Utc>) -> Option<FixedOffset> {
+    has_timezone_data().then(|| *at.with_timezone(&Local).offset())
+}
+
+/// Whether the local timezone is known; chrono would silently assume UTC otherwise
+fn has_timezone_data() -> bool {
+    if !cfg!(unix) {
+        return true;
+    }
+    std::env::var_os("TZ").is_some_and(|tz| !tz.is_empty()) || Path::new("/etc/localtime").exists()
+}
+
+fn count(amount: u64, unit: &str) -> String {
+    if amount == 1 {
+        format!("1 {}", unit)
+    } else {
+        format!("{} {}s", amount, unit)
+    }
+}
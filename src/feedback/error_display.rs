@@ -28,56 +28,60 @@ impl ErrorDisplay {
     fn show_error_context(error: &WebMockError) {
         match error {
             WebMockError::ChromeNotFound => {
-                println!();
+                eprintln!();
                 UserFeedback::info("Chrome browser is required for web page capture");
-                println!("Please install one of the following:");
-                println!("  • Google Chrome: https://www.google.com/chrome/");
-                println!("  • Chromium: https://www.chromium.org/");
-                println!();
+                eprintln!("Please install one of the following:");
+                eprintln!("  • Google Chrome: https://www.google.com/chrome/");
+                eprintln!("  • Chromium: https://www.chromium.org/");
+                eprintln!();
                 UserFeedback::tip("On macOS: brew install --cask google-chrome");
                 UserFeedback::tip("On Ubuntu: sudo apt install chromium-browser");
             }
             WebMockError::PortInUse(port) => {
-                println!();
+                eprintln!();
                 UserFeedback::info(&format!(
                     "Port {} is currently being used by another service",
                     port
                 ));
-                println!("You can:");
-                println!("  • Use a different port: --port <PORT>");
-                println!("  • Stop the service using port {}", port);
-                println!("  • Let WebMock find an available port automatically");
+                eprintln!("You can:");
+                eprintln!("  • Use a different port: --port <PORT>");
+                eprintln!("  • Stop the service using port {}", port);
+                eprintln!("  • Let WebMock find an available port automatically");
             }
             WebMockError::PermissionDenied(_) => {
-                println!();
+                eprintln!();
                 UserFeedback::info("This operation requires file system permissions");
-                println!("Possible solutions:");
-                println!("  • Check that ~/.webmock directory is writable");
-                println!("  • Run with appropriate permissions");
-                println!("  • Check disk space availability");
+                eprintln!("Possible solutions:");
+                eprintln!("  • Check that ~/.webmock directory is writable");
+                eprintln!("  • Run with appropriate permissions");
+                eprintln!("  • Check disk space availability");
             }
             WebMockError::InvalidUrl(url, _) => {
-                println!();
+                eprintln!();
                 UserFeedback::info("URL format requirements:");
-                println!("  • Must start with http:// or https://");
-                println!("  • Must be a valid, accessible URL");
-                println!("  • Example: https://example.com");
+                eprintln!("  • Must start with http:// or https://");
+                eprintln!("  • Must be a valid, accessible URL");
+                eprintln!("  • Example: https://example.com");
                 if !url.is_empty() {
                     UserFeedback::tip(&format!("You provided: {}", url));
                 }
             }
             WebMockError::SnapshotNotFound(name) => {
-                println!();
+                eprintln!();
                 UserFeedback::tip("Use 'webmock list' to see available snapshots");
                 UserFeedback::tip(&format!("Check spelling: '{}'", name));
             }
-            WebMockError::Timeout(seconds) => {
-                println!();
-                UserFeedback::info(&format!("Operation timed out after {} seconds", seconds));
-                println!("Possible solutions:");
-                println!("  • Increase timeout: --timeout <SECONDS>");
-                println!("  • Check network connectivity");
-                println!("  • Try a simpler page first");
+            WebMockError::Timeout { operation, after } => {
+                eprintln!();
+                UserFeedback::info(&format!("{} timed out after {:?}", operation, after));
+                eprintln!("Possible solutions:");
+                eprintln!("  • Increase timeout: --timeout <SECONDS>");
+                eprintln!(
+                    "  • For slow snapshot storage, raise or unset {}",
+                    crate::storage::IO_TIMEOUT_ENV
+                );
+                eprintln!("  • Check network connectivity");
+                eprintln!("  • Try a simpler page first");
             }
             _ => {}
         }
@@ -85,144 +89,144 @@ impl ErrorDisplay {
 
     /// Show recovery suggestions for recoverable errors
     fn show_recovery_suggestions(error: &WebMockError) {
-        println!();
+        eprintln!();
         UserFeedback::section("🔄 Recovery Suggestions");
 
         match error {
             WebMockError::PortInUse(port) => {
-                println!(
+                eprintln!(
                     "• Try a different port: webmock serve <snapshot> --port {}",
                     port + 1
                 );
-                println!("• Let WebMock auto-select: webmock serve <snapshot>");
-                println!("• Stop conflicting service and retry");
+                eprintln!("• Let WebMock auto-select: webmock serve <snapshot>");
+                eprintln!("• Stop conflicting service and retry");
                 Self::show_port_troubleshooting(*port);
             }
-            WebMockError::Timeout(seconds) => {
-                println!(
+            WebMockError::Timeout { after, .. } => {
+                eprintln!(
                     "• Increase timeout: webmock capture <url> --name <name> --timeout {}",
-                    seconds * 2
+                    after.as_secs().max(1) * 2
                 );
-                println!("• Check internet connection speed");
-                println!("• Try capturing a simpler page first");
-                println!("• Capture during off-peak hours");
+                eprintln!("• Check internet connection speed");
+                eprintln!("• Try capturing a simpler page first");
+                eprintln!("• Capture during off-peak hours");
                 Self::show_network_troubleshooting();
             }
             WebMockError::Browser(_) => {
-                println!("• Restart Chrome/Chromium browser");
-                println!("• Check browser installation");
-                println!("• Wait a few seconds and retry");
-                println!("• Close other browser instances");
+                eprintln!("• Restart Chrome/Chromium browser");
+                eprintln!("• Check browser installation");
+                eprintln!("• Wait a few seconds and retry");
+                eprintln!("• Close other browser instances");
                 Self::show_browser_troubleshooting();
             }
             WebMockError::ChromeNotFound => {
-                println!("• Install Google Chrome or Chromium");
-                println!("• Ensure browser is in system PATH");
-                println!("• Restart terminal after installation");
+                eprintln!("• Install Google Chrome or Chromium");
+                eprintln!("• Ensure browser is in system PATH");
+                eprintln!("• Restart terminal after installation");
                 Self::show_chrome_installation_quick_guide();
             }
             WebMockError::PermissionDenied(_) => {
-                println!("• Check ~/.webmock directory permissions");
-                println!("• Ensure sufficient disk space");
-                println!("• Run with appropriate user privileges");
-                println!("• Check parent directory permissions");
+                eprintln!("• Check ~/.webmock directory permissions");
+                eprintln!("• Ensure sufficient disk space");
+                eprintln!("• Run with appropriate user privileges");
+                eprintln!("• Check parent directory permissions");
             }
             WebMockError::SnapshotNotFound(name) => {
-                println!("• Check spelling: '{}'", name);
-                println!("• List available snapshots: webmock list");
-                println!(
+                eprintln!("• Check spelling: '{}'", name);
+                eprintln!("• List available snapshots: webmock list");
+                eprintln!(
                     "• Create the snapshot: webmock capture <url> --name {}",
                     name
                 );
             }
             WebMockError::InvalidUrl(url, _) => {
-                println!("• Ensure URL starts with http:// or https://");
-                println!("• Check URL spelling and format");
-                println!("• Test URL in browser first");
+                eprintln!("• Ensure URL starts with http:// or https://");
+                eprintln!("• Check URL spelling and format");
+                eprintln!("• Test URL in browser first");
                 if !url.is_empty() {
-                    println!("• Your URL: {}", url);
+                    eprintln!("• Your URL: {}", url);
                 }
             }
             _ => {
-                println!("• Check the error message above for specific guidance");
-                println!("• Try the operation again after a short wait");
-                println!("• Use --help for command usage information");
-                println!("• Enable debug logging: RUST_LOG=debug webmock <command>");
+                eprintln!("• Check the error message above for specific guidance");
+                eprintln!("• Try the operation again after a short wait");
+                eprintln!("• Use --help for command usage information");
+                eprintln!("• Enable debug logging: RUST_LOG=debug webmock <command>");
             }
         }
 
         // Always show general help
-        println!();
+        eprintln!();
         UserFeedback::tip("For more help: webmock --help");
         UserFeedback::tip("Report issues: https://github.com/webmock-cli/webmock-cli/issues");
     }
 
     /// Show port-specific troubleshooting
     fn show_port_troubleshooting(port: u16) {
-        println!();
+        eprintln!();
         UserFeedback::info(&format!("Port {} Troubleshooting:", port));
 
         #[cfg(unix)]
         {
-            println!("• Find what's using the port: lsof -i :{}", port);
-            println!("• Kill process by PID: kill <PID>");
+            eprintln!("• Find what's using the port: lsof -i :{}", port);
+            eprintln!("• Kill process by PID: kill <PID>");
         }
 
         #[cfg(windows)]
         {
-            println!(
+            eprintln!(
                 "• Find what's using the port: netstat -ano | findstr :{}",
                 port
             );
-            println!("• Kill process by PID: taskkill /PID <PID> /F");
+            eprintln!("• Kill process by PID: taskkill /PID <PID> /F");
         }
 
-        println!("• Common port users:");
+        eprintln!("• Common port users:");
         match port {
-            8080 => println!("  - Development servers, proxies, Jenkins"),
-            3000 => println!("  - React dev server, Express.js"),
-            8000 => println!("  - Django dev server, Python HTTP server"),
-            9000 => println!("  - Various development tools"),
-            _ => println!("  - Web servers, development tools, other applications"),
+            8080 => eprintln!("  - Development servers, proxies, Jenkins"),
+            3000 => eprintln!("  - React dev server, Express.js"),
+            8000 => eprintln!("  - Django dev server, Python HTTP server"),
+            9000 => eprintln!("  - Various development tools"),
+            _ => eprintln!("  - Web servers, development tools, other applications"),
         }
     }
 
     /// Show network troubleshooting
     fn show_network_troubleshooting() {
-        println!();
+        eprintln!();
         UserFeedback::info("Network Troubleshooting:");
-        println!("• Test connectivity: ping google.com");
-        println!("• Check DNS: nslookup <target-domain>");
-        println!("• Try with VPN disabled");
-        println!("• Check firewall settings");
-        println!("• Test target URL in browser");
+        eprintln!("• Test connectivity: ping google.com");
+        eprintln!("• Check DNS: nslookup <target-domain>");
+        eprintln!("• Try with VPN disabled");
+        eprintln!("• Check firewall settings");
+        eprintln!("• Test target URL in browser");
     }
 
     /// Show browser troubleshooting
     fn show_browser_troubleshooting() {
-        println!();
+        eprintln!();
         UserFeedback::info("Browser Troubleshooting:");
-        println!("• Close all Chrome/Chromium instances");
-        println!("• Clear browser cache and data");
-        println!("• Disable browser extensions");
-        println!("• Try incognito/private mode");
-        println!("• Update browser to latest version");
+        eprintln!("• Close all Chrome/Chromium instances");
+        eprintln!("• Clear browser cache and data");
+        eprintln!("• Disable browser extensions");
+        eprintln!("• Try incognito/private mode");
+        eprintln!("• Update browser to latest version");
     }
 
     /// Show quick Chrome installation guide
     fn show_chrome_installation_quick_guide() {
-        println!();
+        eprintln!();
         UserFeedback::info("Quick Installation:");
 
         #[cfg(target_os = "macos")]
-        println!("• macOS: brew install --cask google-chrome");
+        eprintln!("• macOS: brew install --cask google-chrome");
 
         #[cfg(target_os = "linux")]
-        println!("• Ubuntu/Debian: sudo apt install google-chrome-stable");
+        eprintln!("• Ubuntu/Debian: sudo apt install google-chrome-stable");
 
         #[cfg(target_os = "windows")]
-        println!("• Windows: Download from https://www.google.com/chrome/");
+        eprintln!("• Windows: Download from https://www.google.com/chrome/");
 
-        println!("• Alternative: Install Chromium (open-source version)");
+        eprintln!("• Alternative: Install Chromium (open-source version)");
     }
 }
@@ -1,14 +1,17 @@
 pub mod chrome_detection;
 pub mod core;
+pub mod dates;
 pub mod error_display;
+pub mod output;
 pub mod progress;
 pub mod user_feedback;
 pub mod validation;
 
 // Re-export main types for convenience
 pub use crate::feedback::core::UserFeedback;
+pub use dates::format_date;
 pub use error_display::ErrorDisplay;
-pub use progress::ProgressReporter;
+pub use progress::{report_slow, ProgressReporter, SLOW_OPERATION_AFTER};
 pub use validation::ValidationHelper;
 
 #[cfg(test)]
@@ -0,0 +1,42 @@
+//! Process-wide output settings from the global `--quiet` and `--no-color` flags
+//!
+//! Messages about what a command is doing go to stderr and data such as
+//! snapshot lists and inspect tables to stdout, so `webmock list | grep` only
+//! sees the list. `--quiet` drops the messages but keeps warnings, errors and
+//! data. `--no-color`, or a non-empty `NO_COLOR` variable, turns off colors
+//! on both streams. `--utc` prints dates in UTC instead of local time.
+
+use std::sync::atomic::{AtomicBool, Ordering};
+
+/// Variable turning off colors when set to anything non-empty, see <https://no-color.org>
+pub const NO_COLOR_ENV: &str = "NO_COLOR";
+
+static QUIET: AtomicBool = AtomicBool::new(false);
+
+static UTC: AtomicBool = AtomicBool::new(false);
+
+/// Apply the global output flags; call once before any output
+pub fn configure(quiet: bool, no_color: bool, utc: bool) {
+    QUIET.store(quiet, Ordering::Relaxed);
+    UTC.store(utc, Ordering::Relaxed);
+    if no_color || no_color_requested() {
+        colored::control::set_override(false);
+        console::set_colors_enabled(false);
+        console::set_colors_enabled_stderr(false);
+    }
+}
+
+/// Check whether informational messages are suppressed
+pub fn is_quiet() -> bool {
+    QUIET.load(Ordering::Relaxed)
+}
+
+/// Check whether dates are printed in UTC rather than local time
+pub fn is_utc() -> bool {
+    UTC.load(Ordering::Relaxed)
+}
+
+/// Check whether `NO_COLOR` asks for output without colors
+pub fn no_color_requested() -> bool {
+    std::env::var_os(NO_COLOR_ENV).is_some_and(|value| !value.is_empty())
+}
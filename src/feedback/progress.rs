@@ -1,6 +1,10 @@
-use crate::feedback::UserFeedback;
-use indicatif::{MultiProgress, ProgressBar, ProgressStyle};
-use std::time::Duration;
+use crate::feedback::{output, UserFeedback};
+use indicatif::{MultiProgress, ProgressBar, ProgressDrawTarget, ProgressStyle};
+use std::future::Future;
+use std::time::{Duration, Instant};
+
+/// How long an operation runs before its spinner starts showing the elapsed time
+pub const SLOW_OPERATION_AFTER: Duration = Duration::from_secs(5);
 
 /// Progress reporter for long-running operations
 pub struct ProgressReporter {
@@ -9,10 +13,15 @@ pub struct ProgressReporter {
 }
 
 impl ProgressReporter {
-    /// Create a new progress reporter
+    /// Create a new progress reporter, drawing to stderr unless `--quiet` is set
     pub fn new() -> Self {
+        let multi_progress = if output::is_quiet() {
+            MultiProgress::with_draw_target(ProgressDrawTarget::hidden())
+        } else {
+            MultiProgress::new()
+        };
         Self {
-            multi_progress: MultiProgress::new(),
+            multi_progress,
             main_bar: None,
         }
     }
@@ -33,6 +42,14 @@ impl ProgressReporter {
         pb
     }
 
+    /// Printer for lines that must not garble the progress bars
+    ///
+    /// The bars are cleared while a line is printed and redrawn below it.
+    pub fn line_printer(&self) -> impl Fn(&str) + Send + 'static {
+        let multi_progress = self.multi_progress.clone();
+        move |line| multi_progress.suspend(|| println!("{}", line))
+    }
+
     /// Update capture progress with current step
     pub fn update_capture_step(&self, step: &str) {
         if let Some(pb) = &self.main_bar {
@@ -124,12 +141,40 @@ impl ProgressReporter {
         duration: Duration,
         details: &[(&str, String)],
     ) {
-        println!();
+        UserFeedback::line("");
         UserFeedback::section(&format!("📊 {} Summary", operation));
-        println!("   ⏱️  Duration: {:.2}s", duration.as_secs_f64());
+        UserFeedback::line(&format!("   ⏱️  Duration: {:.2}s", duration.as_secs_f64()));
 
         for (label, value) in details {
-            println!("   📋 {}: {}", label, value);
+            UserFeedback::line(&format!("   📋 {}: {}", label, value));
+        }
+    }
+}
+
+/// Await `operation`, showing the elapsed time on `spinner` once it takes longer than `after`
+///
+/// Until then the spinner keeps its message; afterwards it reads e.g.
+/// "Still loading snapshot 'shop' — 7s elapsed…", updated every second.
+pub async fn report_slow<F: Future>(
+    spinner: &ProgressBar,
+    message: &str,
+    after: Duration,
+    operation: F,
+) -> F::Output {
+    let started = Instant::now();
+    let mut ticks = tokio::time::interval_at(
+        tokio::time::Instant::from_std(started + after),
+        Duration::from_secs(1),
+    );
+    tokio::pin!(operation);
+    loop {
+        tokio::select! {
+            output = &mut operation => return output,
+            _ = ticks.tick() => spinner.set_message(format!(
+                "{} — {}s elapsed…",
+                message,
+                started.elapsed().as_secs()
+            )),
         }
     }
 }
@@ -158,4 +203,28 @@ mod tests {
         // Test spinner creation
         let _spinner = reporter.create_spinner("Processing...");
     }
+
+    #[tokio::test]
+    async fn test_report_slow_shows_elapsed_time() {
+        let spinner = ProgressBar::hidden();
+        spinner.set_message("Loading snapshot 'fast'");
+        let output = report_slow(&spinner, "Still loading", Duration::from_secs(5), async {
+            1
+        })
+        .await;
+        assert_eq!(output, 1);
+        assert_eq!(spinner.message(), "Loading snapshot 'fast'");
+
+        report_slow(
+            &spinner,
+            "Still loading snapshot 'slow'",
+            Duration::from_millis(20),
+            tokio::time::sleep(Duration::from_millis(200)),
+        )
+        .await;
+        assert_eq!(
+            spinner.message(),
+            "Still loading snapshot 'slow' — 0s elapsed…"
+        );
+    }
 }
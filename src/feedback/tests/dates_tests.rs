@@ -0,0 +1,59 @@
+use chrono::{DateTime, Duration, FixedOffset, TimeZone, Utc};
+
+use crate::feedback::dates::{format_date_in, format_utc, relative_time};
+
+fn now() -> DateTime<Utc> {
+    Utc.with_ymd_and_hms(2026, 10, 16, 14, 3, 12).unwrap()
+}
+
+fn ago(seconds: i64) -> String {
+    relative_time(now() - Duration::seconds(seconds), now())
+}
+
+#[test]
+fn test_relative_time_boundaries() {
+    assert_eq!(ago(0), "just now");
+    assert_eq!(ago(59), "just now");
+    assert_eq!(ago(60), "1 minute ago");
+    assert_eq!(ago(2 * 60), "2 minutes ago");
+    assert_eq!(ago(60 * 60 - 1), "59 minutes ago");
+    assert_eq!(ago(60 * 60), "1 hour ago");
+    assert_eq!(ago(24 * 60 * 60 - 1), "23 hours ago");
+    assert_eq!(ago(24 * 60 * 60), "1 day ago");
+    assert_eq!(ago(29 * 24 * 60 * 60), "29 days ago");
+    assert_eq!(ago(30 * 24 * 60 * 60), "1 month ago");
+    assert_eq!(ago(364 * 24 * 60 * 60), "12 months ago");
+    assert_eq!(ago(365 * 24 * 60 * 60), "1 year ago");
+    assert_eq!(ago(3 * 365 * 24 * 60 * 60), "3 years ago");
+}
+
+#[test]
+fn test_relative_time_in_the_future() {
+    assert_eq!(ago(-30), "just now");
+    assert_eq!(ago(-2 * 60 * 60), "in 2 hours");
+}
+
+#[test]
+fn test_format_date_in_local_offset() {
+    let captured = now() - Duration::hours(2);
+    let paris = FixedOffset::east_opt(2 * 60 * 60).unwrap();
+    assert_eq!(
+        format_date_in(captured, now(), Some(paris)),
+        "2026-10-16 14:03:12 +02:00 (2 hours ago)"
+    );
+    let new_york = FixedOffset::west_opt(4 * 60 * 60).unwrap();
+    assert_eq!(
+        format_date_in(captured, now(), Some(new_york)),
+        "2026-10-16 08:03:12 -04:00 (2 hours ago)"
+    );
+}
+
+#[test]
+fn test_format_date_falls_back_to_utc_without_timezone_data() {
+    let captured = now() - Duration::minutes(5);
+    assert_eq!(
+        format_date_in(captured, now(), None),
+        "2026-10-16 13:58:12 UTC (5 minutes ago)"
+    );
+    assert_eq!(format_utc(captured), "2026-10-16 13:58:12 UTC");
+}
@@ -1,5 +1,6 @@
 use crate::error::WebMockError;
 use crate::feedback::ErrorDisplay;
+use std::time::Duration;
 
 #[test]
 fn test_error_display_show_error() {
@@ -15,7 +16,7 @@ fn test_error_display_different_error_types() {
         WebMockError::ChromeNotFound,
         WebMockError::PortInUse(8080),
         WebMockError::InvalidUrl("invalid".to_string(), "bad format".to_string()),
-        WebMockError::Timeout(30),
+        WebMockError::timeout("Page navigation", Duration::from_secs(30)),
     ];
 
     // All of these should not panic
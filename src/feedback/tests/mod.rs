@@ -1,4 +1,5 @@
 mod chrome_detection_tests;
+mod dates_tests;
 mod error_display_tests;
 mod progress_tests;
 mod user_feedback_tests;
@@ -1,5 +1,6 @@
 use crate::error::{Result, WebMockError};
 use crate::feedback::UserFeedback;
+use std::path::Path;
 
 /// Validation helpers with user-friendly error messages
 pub struct ValidationHelper;
@@ -31,39 +32,7 @@ impl ValidationHelper {
 
     /// Validate snapshot name with detailed requirements
     pub fn validate_snapshot_name(name: &str) -> Result<()> {
-        if name.trim().is_empty() {
-            return Err(WebMockError::config("Snapshot name cannot be empty"));
-        }
-
-        if name.len() > 100 {
-            return Err(WebMockError::config(
-                "Snapshot name cannot exceed 100 characters",
-            ));
-        }
-
-        if name.contains('/') || name.contains('\\') {
-            return Err(WebMockError::config(
-                "Snapshot name cannot contain path separators (/ or \\)",
-            ));
-        }
-
-        if name.contains(' ') {
-            return Err(WebMockError::config(
-                "Snapshot name cannot contain spaces. Use hyphens or underscores instead",
-            ));
-        }
-
-        // Check for invalid characters
-        if !name
-            .chars()
-            .all(|c| c.is_alphanumeric() || c == '-' || c == '_')
-        {
-            return Err(WebMockError::config(
-                "Snapshot name can only contain letters, numbers, hyphens, and underscores",
-            ));
-        }
-
-        Ok(())
+        crate::storage::validate_snapshot_name(name)
     }
 
     /// Validate timeout with reasonable limits
@@ -174,22 +143,22 @@ impl ValidationHelper {
         Ok(())
     }
 
-    /// Check system requirements and provide guidance
-    pub fn check_system_requirements() -> Result<()> {
+    /// Check system requirements for storage at `storage_path` and provide guidance
+    pub async fn check_system_requirements(storage_path: &Path) -> Result<()> {
         let mut issues = Vec::new();
 
         // Check available disk space
-        if let Err(e) = Self::check_disk_space() {
+        if let Err(e) = Self::check_disk_space(storage_path).await {
             issues.push(format!("Disk space: {}", e));
         }
 
         // Check network connectivity
-        if let Err(e) = Self::check_network_connectivity() {
+        if let Err(e) = Self::check_network_connectivity().await {
             issues.push(format!("Network: {}", e));
         }
 
         // Check permissions
-        if let Err(e) = Self::check_permissions() {
+        if let Err(e) = Self::check_permissions(storage_path).await {
             issues.push(format!("Permissions: {}", e));
         }
 
@@ -205,64 +174,52 @@ impl ValidationHelper {
     }
 
     /// Check available disk space
-    fn check_disk_space() -> Result<()> {
+    async fn check_disk_space(storage_path: &Path) -> Result<()> {
         // This is a simplified check - in a real implementation you'd use platform-specific APIs
-        let home_dir = dirs::home_dir()
-            .ok_or_else(|| WebMockError::config("Cannot determine home directory"))?;
-
-        let webmock_dir = home_dir.join(".webmock");
-
-        // Try to create a small test file to check write permissions and space
-        if let Ok(mut test_file) = std::fs::File::create(webmock_dir.join(".test_write")) {
-            use std::io::Write;
-            if test_file.write_all(b"test").is_ok() {
-                let _ = std::fs::remove_file(webmock_dir.join(".test_write"));
-                return Ok(());
-            }
-        }
-
-        Err(WebMockError::permission_denied(
-            "Cannot write to storage directory",
-        ))
+        // Try to write a small test file to check write permissions and space
+        probe_write(&storage_path.join(".test_write")).await
     }
 
     /// Check basic network connectivity
-    fn check_network_connectivity() -> Result<()> {
+    pub async fn check_network_connectivity() -> Result<()> {
         // This is a basic check - we just verify we can resolve DNS
-        use std::net::ToSocketAddrs;
-
-        if "google.com:80".to_socket_addrs().is_ok() {
+        if tokio::net::lookup_host("google.com:80").await.is_ok() {
             Ok(())
         } else {
             Err(WebMockError::config("Network connectivity issues detected"))
         }
     }
 
-    /// Check file system permissions
-    pub fn check_permissions() -> Result<()> {
-        let home_dir = dirs::home_dir()
-            .ok_or_else(|| WebMockError::config("Cannot determine home directory"))?;
-
-        let webmock_dir = home_dir.join(".webmock");
-
+    /// Check that the storage directory can be created and written to
+    pub async fn check_permissions(storage_path: &Path) -> Result<()> {
         // Check if we can create the directory
-        if !webmock_dir.exists() {
-            std::fs::create_dir_all(&webmock_dir)
-                .map_err(|_| WebMockError::permission_denied("Cannot create storage directory"))?;
-        }
+        tokio::fs::create_dir_all(storage_path).await.map_err(|e| {
+            WebMockError::permission_denied(format!(
+                "Cannot create storage directory {}: {}",
+                storage_path.display(),
+                e
+            ))
+        })?;
 
         // Check if directory is writable
-        let test_file = webmock_dir.join(".permission_test");
-        std::fs::write(&test_file, b"test")
-            .map_err(|_| WebMockError::permission_denied("Storage directory is not writable"))?;
-
-        std::fs::remove_file(&test_file)
-            .map_err(|_| WebMockError::permission_denied("Cannot remove test file"))?;
-
-        Ok(())
+        probe_write(&storage_path.join(".permission_test"))
+            .await
+            .map_err(|e| {
+                WebMockError::permission_denied(format!("Storage directory is not writable: {}", e))
+            })
     }
 }
 
+/// Write and remove a small file to check that its directory is writable
+async fn probe_write(test_file: &Path) -> Result<()> {
+    tokio::fs::write(test_file, b"test")
+        .await
+        .map_err(|e| WebMockError::io("write test file", test_file, e))?;
+    tokio::fs::remove_file(test_file)
+        .await
+        .map_err(|e| WebMockError::io("remove test file", test_file, e))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -301,6 +258,16 @@ mod tests {
         assert!(ValidationHelper::validate_timeout(700).is_err());
     }
 
+    #[tokio::test]
+    async fn test_probe_write_error_names_the_file() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let test_file = temp_dir.path().join("missing").join(".test_write");
+
+        let error = probe_write(&test_file).await.unwrap_err().to_string();
+        assert!(error.contains("failed to write test file"));
+        assert!(error.contains(&test_file.display().to_string()));
+    }
+
     #[test]
     fn test_validate_port() {
         assert!(ValidationHelper::validate_port(8080).is_ok());
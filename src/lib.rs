@@ -5,6 +5,7 @@ pub mod error;
 pub mod feedback;
 pub mod serve;
 pub mod storage;
+pub mod tls;
 
 #[cfg(test)]
 pub mod test_utils;
@@ -0,0 +1,135 @@
+//! Absolute same-origin URLs in a snapshot's bodies
+//!
+//! A site captured over HTTPS often refers to its own resources by absolute
+//! URL, e.g. `<script src="https://app.example.com/asset.js">`. Through the
+//! proxy such requests reach the mock server like any other, but a browser
+//! loading the page straight from the mock server (`--map-origin`) sends them
+//! to the live site, or blocks them as mixed content on a plain-HTTP page.
+//!
+//! `serve --rewrite-absolute-urls` serves these URLs as paths, e.g.
+//! `/asset.js`. Only bodies of records on one of the snapshot's entry origins
+//! are rewritten, and only URLs on the record's own host, so a path resolves
+//! to the same resource whether the page is loaded through the proxy or
+//! directly. `http://`, `https://` and protocol-relative references to the
+//! host are all rewritten, which also upgrades `http://` references on an
+//! HTTPS origin. Bare origins such as `"https://app.example.com"` without a
+//! path are left alone, as scripts tend to build URLs by appending to them.
+
+use std::collections::BTreeSet;
+
+use url::Url;
+
+use crate::capture::proxy::RequestRecord;
+use crate::storage::Snapshot;
+
+/// Schemes an absolute reference to a host may start with, longest first
+const PREFIXES: [&str; 3] = ["https://", "http://", "//"];
+
+/// Origins of the snapshot's entry points, serialized like `https://app.example.com`
+pub fn entry_origins(snapshot: &Snapshot) -> BTreeSet<String> {
+    snapshot
+        .entry_points()
+        .iter()
+        .filter_map(|url| Url::parse(url).ok())
+        .filter(|url| matches!(url.scheme(), "http" | "https"))
+        .map(|url| url.origin().ascii_serialization())
+        .collect()
+}
+
+/// Count the absolute same-origin URLs `--rewrite-absolute-urls` would rewrite
+pub fn count_absolute_urls(snapshot: &Snapshot) -> usize {
+    let origins = entry_origins(snapshot);
+    snapshot
+        .requests
+        .iter()
+        .filter_map(|record| {
+            let authority = rewritable_authority(record, &origins)?;
+            let text = std::str::from_utf8(&record.response.body).ok()?;
+            Some(relativize(text, &authority).map_or(0, |(_, count)| count))
+        })
+        .sum()
+}
+
+/// Rewrite absolute same-origin URLs in the snapshot's bodies to paths
+///
+/// Returns how many URLs were rewritten.
+pub fn rewrite_absolute_urls(snapshot: &mut Snapshot) -> usize {
+    let origins = entry_origins(snapshot);
+    let mut rewritten = 0;
+    for record in &mut snapshot.requests {
+        let Some(authority) = rewritable_authority(record, &origins) else {
+            continue;
+        };
+        let Ok(text) = std::str::from_utf8(&record.response.body) else {
+            continue;
+        };
+        if let Some((body, count)) = relativize(text, &authority) {
+            record.response.body = body.into_bytes();
+            rewritten += count;
+        }
+    }
+    rewritten
+}
+
+/// Rewrite absolute URLs on `authority` in `text` to paths
+///
+/// `authority` is `host[:port]`, matched case-insensitively. Returns the
+/// rewritten text and how many URLs were rewritten, or `None` if there were
+/// none.
+pub fn relativize(text: &str, authority: &str) -> Option<(String, usize)> {
+    let lower = text.to_ascii_lowercase();
+    let authority = authority.to_ascii_lowercase();
+    let mut out = String::with_capacity(text.len());
+    let mut copied = 0;
+    let mut count = 0;
+
+    for (at, _) in lower.match_indices(&authority) {
+        let end = at + authority.len();
+        // Protocol-relative `//host/path`, unless `//` ends another scheme
+        let Some(prefix) = PREFIXES.iter().find(|prefix| {
+            lower[..at].ends_with(*prefix) && !(**prefix == "//" && lower[..at - 2].ends_with(':'))
+        }) else {
+            continue;
+        };
+        let start = at - prefix.len();
+        // A path must follow, and `host//x` would turn into a protocol-relative URL
+        let rest = &text[end..];
+        if !rest.starts_with('/') || rest.starts_with("//") {
+            continue;
+        }
+
+        out.push_str(&text[copied..start]);
+        copied = end;
+        count += 1;
+    }
+
+    if count == 0 {
+        return None;
+    }
+    out.push_str(&text[copied..]);
+    Some((out, count))
+}
+
+/// `host[:port]` of a record whose body may be rewritten, if it may
+///
+/// The record must be on an entry origin and hold a text body as captured,
+/// not compressed.
+fn rewritable_authority(record: &RequestRecord, origins: &BTreeSet<String>) -> Option<String> {
+    let url = Url::parse(&record.url).ok()?;
+    if !origins.contains(&url.origin().ascii_serialization()) {
+        return None;
+    }
+    let response = &record.response;
+    let encoded = response.headers.iter().any(|(name, value)| {
+        name.eq_ignore_ascii_case("content-encoding") && !value.eq_ignore_ascii_case("identity")
+    });
+    if encoded || response.is_failure() || !response.is_text_content() {
+        return None;
+    }
+
+    let host = url.host_str()?;
+    Some(match url.port() {
+        Some(port) => format!("{}:{}", host, port),
+        None => host.to_string(),
+    })
+}
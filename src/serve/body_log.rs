@@ -0,0 +1,182 @@
+//! Request and response bodies in the serve log, with `--log-bodies`
+//!
+//! When a client misbehaves against the mock it helps to see what it sent
+//! and what it got back. For each request answered from a record, or with a
+//! 404, the request headers and the first bytes of the request body and the
+//! served response body are logged to stderr, which a `--daemon` server
+//! appends to its `--log-file`. Bodies of text-like content types are shown
+//! as text, after the redaction rules; other bodies only by size and hash.
+
+use std::collections::BTreeMap;
+
+use hyper::HeaderMap;
+use mime::Mime;
+
+use crate::capture::proxy::redaction::REDACTED;
+use crate::capture::proxy::{ContentTypeHelper, RedactionRules, RequestRecord};
+use crate::commands::inspect::format_size;
+use crate::serve::console::{ConsoleWriter, OutputLevel};
+
+/// Bytes of each body logged when `--log-bodies` is given without a size
+pub const DEFAULT_LOGGED_BODY_BYTES: usize = 1024;
+
+/// What `--log-bodies` logs
+#[derive(Debug, Clone)]
+pub struct BodyLogging {
+    /// Most bytes of a text body written to the log
+    pub max_bytes: usize,
+    /// Applied to headers and whole text bodies before they are cut down
+    pub redaction: RedactionRules,
+}
+
+impl Default for BodyLogging {
+    fn default() -> Self {
+        Self {
+            max_bytes: DEFAULT_LOGGED_BODY_BYTES,
+            redaction: RedactionRules::default(),
+        }
+    }
+}
+
+impl BodyLogging {
+    /// Render the log entry of one request
+    ///
+    /// `record` is the record the request was answered from, after any
+    /// override, or `None` when it got a 404.
+    pub fn format(
+        &self,
+        method: &str,
+        url: &str,
+        headers: &HeaderMap,
+        body: Option<&[u8]>,
+        record: Option<&RequestRecord>,
+    ) -> String {
+        let status = record.map_or(404, |record| record.response.status);
+        let mut entry = format!("📝 {} {} → {}", method, url, status);
+
+        let headers: BTreeMap<&str, String> = headers
+            .iter()
+            .map(|(name, value)| {
+                let value = if self.redaction.redacts_header(name.as_str()) {
+                    REDACTED.to_string()
+                } else {
+                    String::from_utf8_lossy(value.as_bytes()).into_owned()
+                };
+                (name.as_str(), value)
+            })
+            .collect();
+        if !headers.is_empty() {
+            let headers: Vec<String> = headers
+                .iter()
+                .map(|(name, value)| format!("{}: {}", name, value))
+                .collect();
+            entry.push_str(&format!("\n   request headers: {}", headers.join("; ")));
+        }
+
+        let request_type = headers.get("content-type").map(String::as_str);
+        entry.push_str(&format!(
+            "\n   request body{}",
+            self.describe(body.unwrap_or_default(), request_type, false)
+        ));
+
+        match record {
+            Some(record) => {
+                let response = &record.response;
+                let encoded = response.headers.iter().any(|(name, value)| {
+                    name.eq_ignore_ascii_case("content-encoding")
+                        && !value.eq_ignore_ascii_case("identity")
+                });
+                entry.push_str(&format!(
+                    "\n   response body{}",
+                    self.describe(
+                        &response.body,
+                        Some(response.content_type.as_str()),
+                        encoded
+                    )
+                ));
+            }
+            None => entry.push_str("\n   response: no record"),
+        }
+        entry
+    }
+
+    /// Describe a body as `(type, size): text`, or by size and hash unless it is text
+    fn describe(&self, body: &[u8], content_type: Option<&str>, encoded: bool) -> String {
+        if body.is_empty() {
+            return ": none".to_string();
+        }
+        let content_type = content_type.filter(|value| !value.is_empty());
+        let label = content_type.unwrap_or("no content type");
+        let text = content_type
+            .and_then(|value| value.parse::<Mime>().ok())
+            .filter(|mime| !encoded && ContentTypeHelper::is_text_mime(mime))
+            .and_then(|_| std::str::from_utf8(body).ok());
+
+        let Some(text) = text else {
+            return format!(
+                " ({}, {}, sha256 {})",
+                label,
+                format_size(body.len()),
+                short_hash(body)
+            );
+        };
+        let scrubbed = self.redaction.scrub_text(text);
+        let shown = truncate(&scrubbed, self.max_bytes);
+        let mut line = format!(" ({}, {}): {}", label, format_size(body.len()), shown);
+        if shown.len() < scrubbed.len() {
+            line.push_str(&format!(" … ({} more bytes)", scrubbed.len() - shown.len()));
+        }
+        line
+    }
+}
+
+/// Writes body log entries to stderr without blocking request handlers
+pub(crate) struct BodyLog {
+    settings: BodyLogging,
+    writer: ConsoleWriter,
+}
+
+impl BodyLog {
+    pub fn stderr(settings: BodyLogging) -> Self {
+        Self {
+            settings,
+            writer: ConsoleWriter::new(std::io::stderr(), OutputLevel::Normal),
+        }
+    }
+
+    /// Queue the entry of one request, see [`BodyLogging::format`]
+    pub fn log(
+        &self,
+        method: &str,
+        url: &str,
+        headers: &HeaderMap,
+        body: Option<&[u8]>,
+        record: Option<&RequestRecord>,
+    ) {
+        self.writer
+            .line(self.settings.format(method, url, headers, body, record));
+    }
+
+    /// Wait until every queued entry has been written out
+    pub async fn flush(&self) {
+        self.writer.flush().await;
+    }
+}
+
+/// The longest prefix of `text` of at most `max_bytes` bytes that ends on a character
+fn truncate(text: &str, max_bytes: usize) -> &str {
+    let mut end = max_bytes.min(text.len());
+    while !text.is_char_boundary(end) {
+        end -= 1;
+    }
+    &text[..end]
+}
+
+/// First 16 hex digits of the SHA-256 of `body`
+fn short_hash(body: &[u8]) -> String {
+    let digest = ring::digest::digest(&ring::digest::SHA256, body);
+    digest.as_ref()[..8]
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect()
+}
@@ -0,0 +1,203 @@
+//! Non-blocking console output for per-request serve logs
+//!
+//! Writing to stdout is synchronous, so printing a line per request from the
+//! request handlers slows the server down under load. Lines are instead sent
+//! over a channel to a dedicated thread that batches them into a buffered writer.
+//! The channel is bounded, so a terminal that can't keep up costs lines rather
+//! than memory; the writer reports how many were dropped once it catches up.
+
+use std::io::{BufWriter, Write};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread::JoinHandle;
+use std::time::Instant;
+
+use tokio::sync::{mpsc, oneshot};
+
+use crate::commands::inspect::format_size;
+
+/// Lines queued for the writer thread before new ones are dropped
+pub const QUEUE_LEN: usize = 4096;
+
+/// How much the serve console prints per request
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum OutputLevel {
+    /// Only startup and shutdown messages
+    Quiet,
+    /// One line per request
+    #[default]
+    Normal,
+    /// One line per request with matched record, latency and body size
+    Verbose,
+}
+
+/// A single request line for the serve console
+#[derive(Debug, Clone)]
+pub struct RequestLine {
+    pub icon: &'static str,
+    pub method: String,
+    pub url: String,
+    pub status: u16,
+    pub detail: String,
+    /// Index of the matched record in the snapshot
+    pub record_index: Option<usize>,
+    /// Size of the response body sent back
+    pub body_size: Option<usize>,
+    pub started: Instant,
+}
+
+impl RequestLine {
+    /// Render the line for the given output level
+    pub fn format(&self, level: OutputLevel) -> String {
+        let mut line = format!(
+            "{} {} {} → {} ({})",
+            self.icon, self.method, self.url, self.status, self.detail
+        );
+
+        if level == OutputLevel::Verbose {
+            let record = self
+                .record_index
+                .map(|index| format!("record #{}", index + 1))
+                .unwrap_or_else(|| "no record".to_string());
+            let size = self
+                .body_size
+                .map(format_size)
+                .unwrap_or_else(|| "-".to_string());
+            line.push_str(&format!(
+                " [{}, {:.1}ms, {}]",
+                record,
+                self.started.elapsed().as_secs_f64() * 1000.0,
+                size
+            ));
+        }
+
+        line
+    }
+}
+
+enum Command {
+    Line(String),
+    Flush(oneshot::Sender<()>),
+}
+
+/// Console writer that never blocks the caller on terminal I/O
+pub struct ConsoleWriter {
+    tx: mpsc::Sender<Command>,
+    /// Lines dropped on a full queue since the writer last reported them
+    dropped: Arc<AtomicU64>,
+    level: OutputLevel,
+    thread: Mutex<Option<JoinHandle<()>>>,
+}
+
+impl ConsoleWriter {
+    /// Create a writer for stdout
+    pub fn stdout(level: OutputLevel) -> Self {
+        Self::new(std::io::stdout(), level)
+    }
+
+    /// Create a writer that drains lines into the given sink on its own thread
+    pub fn new<W: Write + Send + 'static>(sink: W, level: OutputLevel) -> Self {
+        let (tx, rx) = mpsc::channel(QUEUE_LEN);
+        let dropped = Arc::new(AtomicU64::new(0));
+        let thread = std::thread::Builder::new()
+            .name("webmock-console".to_string())
+            .spawn({
+                let dropped = Arc::clone(&dropped);
+                move || Self::run(rx, sink, &dropped)
+            })
+            .ok();
+
+        Self {
+            tx,
+            dropped,
+            level,
+            thread: Mutex::new(thread),
+        }
+    }
+
+    /// Get the configured output level
+    pub fn level(&self) -> OutputLevel {
+        self.level
+    }
+
+    /// Queue a request line, unless running quietly
+    pub fn request(&self, line: RequestLine) {
+        if self.level != OutputLevel::Quiet {
+            self.line(line.format(self.level));
+        }
+    }
+
+    /// Queue a raw line regardless of output level, dropping it if the queue is full
+    pub fn line(&self, text: impl Into<String>) {
+        match self.tx.try_send(Command::Line(text.into())) {
+            Ok(()) => {}
+            Err(mpsc::error::TrySendError::Full(_)) => {
+                self.dropped.fetch_add(1, Ordering::Relaxed);
+            }
+            // The writer thread only goes away during shutdown
+            Err(mpsc::error::TrySendError::Closed(_)) => {}
+        }
+    }
+
+    /// Wait until every queued line has been written out
+    pub async fn flush(&self) {
+        let (ack_tx, ack_rx) = oneshot::channel();
+        if self.tx.send(Command::Flush(ack_tx)).await.is_ok() {
+            let _ = ack_rx.await;
+        }
+    }
+
+    /// Flush remaining lines and stop the writer thread
+    pub async fn shutdown(self) {
+        let Self { tx, thread, .. } = self;
+        drop(tx);
+
+        let thread = thread.lock().ok().and_then(|mut thread| thread.take());
+        if let Some(thread) = thread {
+            let _ = tokio::task::spawn_blocking(move || thread.join()).await;
+        }
+    }
+
+    fn run<W: Write>(mut rx: mpsc::Receiver<Command>, sink: W, dropped: &AtomicU64) {
+        let mut out = BufWriter::new(sink);
+
+        while let Some(command) = rx.blocking_recv() {
+            let mut acks = Vec::new();
+            Self::apply(command, &mut out, &mut acks);
+
+            // Drain whatever queued up meanwhile so bursts become a single write
+            while let Ok(command) = rx.try_recv() {
+                Self::apply(command, &mut out, &mut acks);
+            }
+            Self::report_dropped(&mut out, dropped);
+
+            let _ = out.flush();
+            for ack in acks {
+                let _ = ack.send(());
+            }
+        }
+
+        Self::report_dropped(&mut out, dropped);
+        let _ = out.flush();
+    }
+
+    fn report_dropped<W: Write>(out: &mut W, dropped: &AtomicU64) {
+        let count = dropped.swap(0, Ordering::Relaxed);
+        if count > 0 {
+            let _ = writeln!(
+                out,
+                "⚠️  Dropped {} console line(s) while the output was falling behind",
+                count
+            );
+        }
+    }
+
+    fn apply<W: Write>(command: Command, out: &mut W, acks: &mut Vec<oneshot::Sender<()>>) {
+        match command {
+            Command::Line(line) => {
+                let _ = writeln!(out, "{}", line);
+            }
+            Command::Flush(ack) => acks.push(ack),
+        }
+    }
+}
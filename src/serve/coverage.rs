@@ -0,0 +1,118 @@
+//! Per-record hit counts written by `serve --coverage`
+//!
+//! A test suite usually needs only part of what a page loaded during
+//! capture. Counting how often each record was served tells which ones it
+//! never used, and `webmock trim` reads the report back to remove them.
+//! Records are identified by their capture sequence, which stays the same
+//! when records are filtered or reordered, and the report names the snapshot
+//! file it counted so it is never applied to a different one.
+
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use serde::{Deserialize, Serialize};
+
+use crate::error::{Result, WebMockError};
+use crate::storage::Snapshot;
+
+/// Times each record of a served snapshot was answered from, by position
+#[derive(Debug, Default)]
+pub struct RecordHits {
+    hits: Vec<AtomicU64>,
+}
+
+impl RecordHits {
+    /// Start counting for a snapshot with `records` records
+    pub fn new(records: usize) -> Self {
+        Self {
+            hits: (0..records).map(|_| AtomicU64::new(0)).collect(),
+        }
+    }
+
+    /// Count a response served from the record at `index`
+    pub fn record_hit(&self, index: usize) {
+        if let Some(hits) = self.hits.get(index) {
+            hits.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    /// The counts so far for the records of `snapshot`, whose file hashes to `sha256`
+    ///
+    /// Records without a sequence, which only snapshots built in memory
+    /// have, are left out.
+    pub fn report(&self, snapshot: &Snapshot, sha256: &str) -> CoverageReport {
+        let records = snapshot
+            .requests
+            .iter()
+            .zip(&self.hits)
+            .filter_map(|(record, hits)| {
+                Some(RecordCoverage {
+                    sequence: record.sequence?,
+                    method: record.method.clone(),
+                    url: record.url.clone(),
+                    hits: hits.load(Ordering::Relaxed),
+                })
+            })
+            .collect();
+        CoverageReport {
+            snapshot: snapshot.name.clone(),
+            sha256: sha256.to_string(),
+            records,
+        }
+    }
+}
+
+/// How often each record of a snapshot was served, as written by `serve --coverage`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CoverageReport {
+    /// Name of the served snapshot
+    pub snapshot: String,
+    /// Hex SHA-256 of the snapshot file when it was loaded
+    pub sha256: String,
+    /// Every record that could be served, in stored order
+    pub records: Vec<RecordCoverage>,
+}
+
+/// Hits of one record
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RecordCoverage {
+    /// Capture sequence of the record
+    pub sequence: u64,
+    pub method: String,
+    pub url: String,
+    /// Responses served from the record
+    pub hits: u64,
+}
+
+impl CoverageReport {
+    /// Read a report written by `serve --coverage`
+    pub fn load(path: &Path) -> Result<Self> {
+        let data = std::fs::read_to_string(path)
+            .map_err(|e| WebMockError::io("read coverage report", path, e))?;
+        serde_json::from_str(&data).map_err(|e| {
+            WebMockError::config(format!(
+                "{} is not a coverage report written by 'webmock serve --coverage': {}",
+                path.display(),
+                e
+            ))
+        })
+    }
+
+    /// Write the report as JSON
+    pub fn write(&self, path: &Path) -> Result<()> {
+        let json = serde_json::to_string_pretty(self).map_err(|e| {
+            WebMockError::config(format!("Failed to encode coverage report as JSON: {}", e))
+        })?;
+        std::fs::write(path, json + "\n")
+            .map_err(|e| WebMockError::io("write coverage report", path, e))
+    }
+
+    /// Hits of every listed record, by capture sequence
+    pub fn hits_by_sequence(&self) -> HashMap<u64, u64> {
+        self.records
+            .iter()
+            .map(|record| (record.sequence, record.hits))
+            .collect()
+    }
+}
@@ -0,0 +1,476 @@
+//! DNS responder for testing on devices, with `--dns-port`
+//!
+//! Testers can't always edit a phone's hosts file, but they can point its DNS
+//! at their laptop. `webmock serve --dns-port 5353` then answers A and AAAA
+//! queries for every host in the snapshot with the address the mock server
+//! is reached on, so the device's requests for those hosts land on it.
+//!
+//! Only the snapshot's hosts are answered. Everything else gets NXDOMAIN, or
+//! is relayed to the resolver given with `--dns-upstream`, so unrelated
+//! traffic on the device is never pointed at the mock. The wire format is
+//! handled by hand for single-question queries, which is all stub resolvers
+//! send.
+
+use std::collections::BTreeSet;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
+use std::sync::Arc;
+use std::time::Duration;
+
+use tokio::net::UdpSocket;
+use tokio::task::JoinHandle;
+use tracing::{debug, info, warn, Instrument};
+use url::{Host, Url};
+
+use super::console::{ConsoleWriter, OutputLevel};
+use crate::capture::proxy::authority::split_authority;
+use crate::error::{Result, WebMockError};
+use crate::storage::Snapshot;
+
+/// Time to live of answers, short so devices stop using them soon after serving ends
+pub const DNS_TTL: u32 = 60;
+
+/// Port of the upstream resolver when `--dns-upstream` gives none
+pub const DEFAULT_UPSTREAM_PORT: u16 = 53;
+
+/// How long a relayed query waits for the upstream resolver
+pub const UPSTREAM_TIMEOUT: Duration = Duration::from_secs(2);
+
+pub const TYPE_A: u16 = 1;
+pub const TYPE_AAAA: u16 = 28;
+pub const CLASS_IN: u16 = 1;
+
+pub const RCODE_NOERROR: u8 = 0;
+pub const RCODE_FORMERR: u8 = 1;
+pub const RCODE_SERVFAIL: u8 = 2;
+pub const RCODE_NXDOMAIN: u8 = 3;
+pub const RCODE_NOTIMP: u8 = 4;
+
+const HEADER_LEN: usize = 12;
+const FLAG_RESPONSE: u16 = 0x8000;
+const FLAG_AUTHORITATIVE: u16 = 0x0400;
+const MASK_OPCODE: u16 = 0x7800;
+const FLAG_RECURSION_DESIRED: u16 = 0x0100;
+/// Compression pointer to the question name, which follows the header
+const NAME_POINTER: [u8; 2] = [0xc0, HEADER_LEN as u8];
+/// Longest name in wire format
+const MAX_NAME_LEN: usize = 255;
+/// Largest UDP message without EDNS
+const MAX_MESSAGE_LEN: usize = 512;
+
+/// Settings of the DNS responder
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DnsOptions {
+    /// UDP port to answer on, on every interface
+    pub port: u16,
+    /// Address given in answers; the machine's LAN address when `None`
+    pub advertise_ip: Option<IpAddr>,
+    /// Resolver queries for other hosts are relayed to, instead of NXDOMAIN
+    pub upstream: Option<SocketAddr>,
+}
+
+/// Parse a `--dns-upstream` resolver, `IP` or `IP:PORT`
+pub fn parse_dns_upstream(value: &str) -> Result<SocketAddr> {
+    let value = value.trim();
+    if let Ok(addr) = value.parse::<SocketAddr>() {
+        return Ok(addr);
+    }
+    value
+        .parse::<IpAddr>()
+        .map(|ip| SocketAddr::new(ip, DEFAULT_UPSTREAM_PORT))
+        .map_err(|_| {
+            WebMockError::config(format!(
+                "Invalid DNS upstream '{}': expected an IP address, optionally with a port (e.g. 192.168.1.1 or 1.1.1.1:53)",
+                value
+            ))
+        })
+}
+
+/// Address this machine is reached on from the local network
+///
+/// Connecting a UDP socket sends nothing; it only picks the interface the
+/// default route goes through.
+pub fn detect_lan_ip() -> Result<IpAddr> {
+    let detected = std::net::UdpSocket::bind((Ipv4Addr::UNSPECIFIED, 0))
+        .and_then(|socket| {
+            socket.connect((Ipv4Addr::new(192, 0, 2, 1), DEFAULT_UPSTREAM_PORT))?;
+            socket.local_addr()
+        })
+        .map(|addr| addr.ip());
+    match detected {
+        Ok(ip) if !ip.is_unspecified() && !ip.is_loopback() => Ok(ip),
+        _ => Err(WebMockError::config(
+            "Could not detect this machine's LAN address for DNS answers; pass --advertise-ip",
+        )),
+    }
+}
+
+/// Host names of a snapshot's records and tunnels, lowercase
+///
+/// IP literals are left out, since nobody looks them up.
+pub fn snapshot_hosts(snapshot: &Snapshot) -> BTreeSet<String> {
+    let urls = std::iter::once(snapshot.url.as_str())
+        .chain(snapshot.requests.iter().map(|record| record.url.as_str()));
+    let mut hosts: BTreeSet<String> = urls
+        .filter_map(|url| match Url::parse(url).ok()?.host()? {
+            Host::Domain(domain) => Some(normalize_name(domain)),
+            Host::Ipv4(_) | Host::Ipv6(_) => None,
+        })
+        .collect();
+    for tunnel in &snapshot.tunnels {
+        let (host, _) = split_authority(&tunnel.host);
+        if !host.starts_with('[') && host.parse::<IpAddr>().is_err() {
+            hosts.insert(normalize_name(&host));
+        }
+    }
+    hosts.remove("");
+    hosts
+}
+
+/// Lowercase a host name and drop the trailing dot of a fully qualified one
+fn normalize_name(name: &str) -> String {
+    name.trim_end_matches('.').to_ascii_lowercase()
+}
+
+/// Why a message can't be answered as a query
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum QueryError {
+    /// Too short to carry an ID, or itself a response; dropped without a reply
+    Ignored,
+    /// Answered with this response code and no records
+    Rcode { id: u16, flags: u16, rcode: u8 },
+}
+
+/// A single-question DNS query
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DnsQuery {
+    pub id: u16,
+    /// Header flags as sent
+    pub flags: u16,
+    /// Queried name, lowercase and without a trailing dot
+    pub name: String,
+    pub qtype: u16,
+    pub qclass: u16,
+    /// The question section as sent, echoed in responses
+    question: Vec<u8>,
+}
+
+impl DnsQuery {
+    /// Parse a query message
+    pub fn parse(packet: &[u8]) -> std::result::Result<Self, QueryError> {
+        if packet.len() < HEADER_LEN {
+            return Err(QueryError::Ignored);
+        }
+        let id = read_u16(packet, 0);
+        let flags = read_u16(packet, 2);
+        if flags & FLAG_RESPONSE != 0 {
+            return Err(QueryError::Ignored);
+        }
+        let error = |rcode| QueryError::Rcode { id, flags, rcode };
+        if flags & MASK_OPCODE != 0 {
+            return Err(error(RCODE_NOTIMP));
+        }
+        if read_u16(packet, 4) != 1 {
+            return Err(error(RCODE_FORMERR));
+        }
+
+        let mut labels = Vec::new();
+        let mut offset = HEADER_LEN;
+        loop {
+            let len = *packet.get(offset).ok_or_else(|| error(RCODE_FORMERR))? as usize;
+            offset += 1;
+            if len == 0 {
+                break;
+            }
+            // Compression pointers and extended labels have no place in a question
+            if len > 63 || offset - HEADER_LEN + len > MAX_NAME_LEN {
+                return Err(error(RCODE_FORMERR));
+            }
+            let label = packet
+                .get(offset..offset + len)
+                .ok_or_else(|| error(RCODE_FORMERR))?;
+            labels.push(String::from_utf8_lossy(label).into_owned());
+            offset += len;
+        }
+        if packet.len() < offset + 4 {
+            return Err(error(RCODE_FORMERR));
+        }
+
+        Ok(Self {
+            id,
+            flags,
+            name: normalize_name(&labels.join(".")),
+            qtype: read_u16(packet, offset),
+            qclass: read_u16(packet, offset + 2),
+            question: packet[HEADER_LEN..offset + 4].to_vec(),
+        })
+    }
+
+    /// Encode the response to this query, with an answer record for `address`
+    pub fn response(&self, rcode: u8, address: Option<IpAddr>) -> Vec<u8> {
+        let mut message = header(self.id, self.flags, rcode, 1, u16::from(address.is_some()));
+        message.extend_from_slice(&self.question);
+        if let Some(address) = address {
+            let (rtype, data) = match address {
+                IpAddr::V4(ip) => (TYPE_A, ip.octets().to_vec()),
+                IpAddr::V6(ip) => (TYPE_AAAA, ip.octets().to_vec()),
+            };
+            message.extend_from_slice(&NAME_POINTER);
+            message.extend_from_slice(&rtype.to_be_bytes());
+            message.extend_from_slice(&CLASS_IN.to_be_bytes());
+            message.extend_from_slice(&DNS_TTL.to_be_bytes());
+            message.extend_from_slice(&(data.len() as u16).to_be_bytes());
+            message.extend_from_slice(&data);
+        }
+        message
+    }
+
+    /// Name of the query type, for the log
+    pub fn type_name(&self) -> String {
+        match self.qtype {
+            TYPE_A => "A".to_string(),
+            TYPE_AAAA => "AAAA".to_string(),
+            other => format!("TYPE{}", other),
+        }
+    }
+}
+
+/// Encode a response without question or records, for queries that couldn't be parsed
+pub fn error_response(id: u16, flags: u16, rcode: u8) -> Vec<u8> {
+    header(id, flags, rcode, 0, 0)
+}
+
+fn header(id: u16, query_flags: u16, rcode: u8, questions: u16, answers: u16) -> Vec<u8> {
+    let flags = FLAG_RESPONSE
+        | FLAG_AUTHORITATIVE
+        | (query_flags & (MASK_OPCODE | FLAG_RECURSION_DESIRED))
+        | u16::from(rcode);
+    let mut message = Vec::with_capacity(MAX_MESSAGE_LEN);
+    for field in [id, flags, questions, answers, 0, 0] {
+        message.extend_from_slice(&field.to_be_bytes());
+    }
+    message
+}
+
+fn read_u16(packet: &[u8], offset: usize) -> u16 {
+    u16::from_be_bytes([packet[offset], packet[offset + 1]])
+}
+
+/// How a query is answered
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Resolution {
+    /// A snapshot host, answered with the address, or with no records when
+    /// the address is of another family than the query asks for
+    Local(Option<IpAddr>),
+    /// Relayed to the upstream resolver
+    Forward(SocketAddr),
+    /// Not a snapshot host
+    NxDomain,
+}
+
+/// Answers queries for a snapshot's hosts with the mock server's address
+pub struct DnsResponder {
+    hosts: BTreeSet<String>,
+    address: IpAddr,
+    upstream: Option<SocketAddr>,
+    console: Arc<ConsoleWriter>,
+}
+
+impl DnsResponder {
+    pub fn new(
+        hosts: BTreeSet<String>,
+        address: IpAddr,
+        upstream: Option<SocketAddr>,
+        console: Arc<ConsoleWriter>,
+    ) -> Self {
+        Self {
+            hosts,
+            address,
+            upstream,
+            console,
+        }
+    }
+
+    /// Decide how to answer a query
+    pub fn resolve(&self, query: &DnsQuery) -> Resolution {
+        if query.qclass == CLASS_IN && self.hosts.contains(&query.name) {
+            let address = match (query.qtype, self.address) {
+                (TYPE_A, IpAddr::V4(_)) | (TYPE_AAAA, IpAddr::V6(_)) => Some(self.address),
+                _ => None,
+            };
+            return Resolution::Local(address);
+        }
+        match self.upstream {
+            Some(upstream) => Resolution::Forward(upstream),
+            None => Resolution::NxDomain,
+        }
+    }
+
+    /// Answer queries on `socket` until the task is aborted
+    pub async fn serve(self, socket: UdpSocket) {
+        let socket = Arc::new(socket);
+        let mut buf = [0u8; MAX_MESSAGE_LEN];
+        loop {
+            let (len, peer) = match socket.recv_from(&mut buf).await {
+                Ok(received) => received,
+                Err(e) => {
+                    // ICMP errors from earlier replies surface here on some platforms
+                    debug!("DNS receive error: {}", e);
+                    continue;
+                }
+            };
+            let packet = &buf[..len];
+
+            let reply = match DnsQuery::parse(packet) {
+                Ok(query) => match self.resolve(&query) {
+                    Resolution::Local(address) => {
+                        self.log_answer(&query, address);
+                        query.response(RCODE_NOERROR, address)
+                    }
+                    Resolution::NxDomain => {
+                        debug!("DNS {} {} → NXDOMAIN", query.type_name(), query.name);
+                        query.response(RCODE_NXDOMAIN, None)
+                    }
+                    Resolution::Forward(upstream) => {
+                        debug!(
+                            "DNS {} {} → relayed to {}",
+                            query.type_name(),
+                            query.name,
+                            upstream
+                        );
+                        tokio::spawn(
+                            relay(Arc::clone(&socket), query, packet.to_vec(), peer, upstream)
+                                .in_current_span(),
+                        );
+                        continue;
+                    }
+                },
+                Err(QueryError::Ignored) => continue,
+                Err(QueryError::Rcode { id, flags, rcode }) => error_response(id, flags, rcode),
+            };
+            if let Err(e) = socket.send_to(&reply, peer).await {
+                debug!("Failed to send DNS reply to {}: {}", peer, e);
+            }
+        }
+    }
+
+    fn log_answer(&self, query: &DnsQuery, address: Option<IpAddr>) {
+        let answer = address
+            .map(|address| address.to_string())
+            .unwrap_or_else(|| "no records".to_string());
+        info!("DNS {} {} → {}", query.type_name(), query.name, answer);
+        if self.console.level() != OutputLevel::Quiet {
+            self.console.line(format!(
+                "🧭 DNS {} {} → {}",
+                query.type_name(),
+                query.name,
+                answer
+            ));
+        }
+    }
+}
+
+/// Relay a query to the upstream resolver and its reply back to the client
+///
+/// A resolver that doesn't answer in time gets the client a SERVFAIL.
+async fn relay(
+    socket: Arc<UdpSocket>,
+    query: DnsQuery,
+    packet: Vec<u8>,
+    client: SocketAddr,
+    upstream: SocketAddr,
+) {
+    let local: SocketAddr = match upstream {
+        SocketAddr::V4(_) => (Ipv4Addr::UNSPECIFIED, 0).into(),
+        SocketAddr::V6(_) => (Ipv6Addr::UNSPECIFIED, 0).into(),
+    };
+    let exchange = async {
+        let relay = UdpSocket::bind(local).await?;
+        relay.connect(upstream).await?;
+        relay.send(&packet).await?;
+        let mut buf = [0u8; MAX_MESSAGE_LEN];
+        loop {
+            let len = relay.recv(&mut buf).await?;
+            // Stray datagrams with another ID aren't the reply
+            if len >= HEADER_LEN && read_u16(&buf, 0) == query.id {
+                return Ok::<_, std::io::Error>(buf[..len].to_vec());
+            }
+        }
+    };
+    let reply = match tokio::time::timeout(UPSTREAM_TIMEOUT, exchange).await {
+        Ok(Ok(reply)) => reply,
+        Ok(Err(e)) => {
+            warn!("DNS upstream {} failed for {}: {}", upstream, query.name, e);
+            query.response(RCODE_SERVFAIL, None)
+        }
+        Err(_) => {
+            warn!("DNS upstream {} timed out for {}", upstream, query.name);
+            query.response(RCODE_SERVFAIL, None)
+        }
+    };
+    if let Err(e) = socket.send_to(&reply, client).await {
+        debug!("Failed to send DNS reply to {}: {}", client, e);
+    }
+}
+
+/// A running DNS responder, stopped when dropped along with its server
+pub struct DnsTask {
+    handle: JoinHandle<()>,
+    local_addr: SocketAddr,
+    address: IpAddr,
+}
+
+impl DnsTask {
+    /// Bind the responder's UDP port and start answering queries for the snapshot's hosts
+    pub async fn start(
+        options: &DnsOptions,
+        snapshot: &Snapshot,
+        console: Arc<ConsoleWriter>,
+    ) -> Result<Self> {
+        let address = match options.advertise_ip {
+            Some(ip) => ip,
+            None => detect_lan_ip()?,
+        };
+        let socket = UdpSocket::bind((Ipv4Addr::UNSPECIFIED, options.port))
+            .await
+            .map_err(|e| {
+                WebMockError::Proxy(format!(
+                    "Failed to bind DNS responder to UDP port {}: {}",
+                    options.port, e
+                ))
+            })?;
+        let local_addr = socket.local_addr().map_err(|e| {
+            WebMockError::Proxy(format!("Failed to read DNS responder address: {}", e))
+        })?;
+
+        let hosts = snapshot_hosts(snapshot);
+        info!(
+            "DNS responder on udp://{} answering {} hosts with {}",
+            local_addr,
+            hosts.len(),
+            address
+        );
+        let responder = DnsResponder::new(hosts, address, options.upstream, console);
+        let handle = tokio::spawn(responder.serve(socket).in_current_span());
+        Ok(Self {
+            handle,
+            local_addr,
+            address,
+        })
+    }
+
+    /// Address the responder's socket is bound to
+    pub fn local_addr(&self) -> SocketAddr {
+        self.local_addr
+    }
+
+    /// Address given in answers
+    pub fn address(&self) -> IpAddr {
+        self.address
+    }
+}
+
+impl Drop for DnsTask {
+    fn drop(&mut self) {
+        self.handle.abort();
+    }
+}
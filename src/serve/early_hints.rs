@@ -0,0 +1,170 @@
+//! Replay of the 1xx responses captured before a final response, with `--replay-early-hints`
+//!
+//! hyper's server can't send informational responses itself, so the
+//! connection's IO is wrapped in an [`InterimIo`] that writes queued ones out
+//! ahead of anything hyper writes next. Handlers attach a record's interim
+//! responses to its final response as [`EarlyHints`]; the connection's
+//! service moves them to the queue with [`InterimQueue::take_from`] before
+//! the final response is written, and they are flushed out right away.
+//!
+//! Only HTTP/1.1 clients get them. HTTP/1.0 predates 1xx responses, and
+//! writing raw HTTP/1 bytes into an HTTP/2 connection would break it, so
+//! those clients get the final response alone.
+
+use std::io;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::task::{ready, Context, Poll};
+
+use hyper::header::{HeaderName, HeaderValue};
+use hyper::{Response, StatusCode, Version};
+use tokio::io::{AsyncRead, AsyncWrite, ReadBuf};
+use tracing::debug;
+
+use crate::capture::proxy::InterimResponse;
+
+/// Interim responses to send ahead of the response carrying them
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct EarlyHints(pub Vec<InterimResponse>);
+
+/// Encoded interim responses waiting to be written to one connection
+#[derive(Debug, Clone, Default)]
+pub(crate) struct InterimQueue(Arc<Mutex<Vec<u8>>>);
+
+impl InterimQueue {
+    /// Queue the [`EarlyHints`] attached to `response`, if the client's `version` takes them
+    ///
+    /// The extension is removed either way.
+    pub fn take_from<B>(&self, version: Version, response: &mut Response<B>) {
+        let Some(EarlyHints(interim)) = response.extensions_mut().remove::<EarlyHints>() else {
+            return;
+        };
+        if version != Version::HTTP_11 {
+            debug!("Not sending early hints to a {:?} client", version);
+            return;
+        }
+        let encoded = encode_interim(&interim);
+        self.0
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .extend_from_slice(&encoded);
+    }
+}
+
+/// Encode interim responses as HTTP/1.1, e.g. `HTTP/1.1 103 Early Hints` and its headers
+///
+/// `100 Continue` and `101 Switching Protocols` belong to the exchange they
+/// were sent in, and statuses outside 1xx aren't interim at all, so those are
+/// skipped along with headers that aren't valid to send.
+pub fn encode_interim(interim: &[InterimResponse]) -> Vec<u8> {
+    let mut encoded = Vec::new();
+    for response in interim {
+        let status = match StatusCode::from_u16(response.status) {
+            Ok(status)
+                if status.is_informational()
+                    && status != StatusCode::CONTINUE
+                    && status != StatusCode::SWITCHING_PROTOCOLS =>
+            {
+                status
+            }
+            _ => continue,
+        };
+
+        encoded.extend_from_slice(
+            format!(
+                "HTTP/1.1 {} {}\r\n",
+                status.as_u16(),
+                status.canonical_reason().unwrap_or("Informational")
+            )
+            .as_bytes(),
+        );
+        for (name, value) in &response.headers {
+            if let (Ok(name), Ok(value)) = (
+                HeaderName::from_bytes(name.as_bytes()),
+                HeaderValue::from_str(value),
+            ) {
+                encoded.extend_from_slice(name.as_str().as_bytes());
+                encoded.extend_from_slice(b": ");
+                encoded.extend_from_slice(value.as_bytes());
+                encoded.extend_from_slice(b"\r\n");
+            }
+        }
+        encoded.extend_from_slice(b"\r\n");
+    }
+    encoded
+}
+
+/// Connection IO that writes queued interim responses before anything else
+pub(crate) struct InterimIo<S> {
+    inner: S,
+    queue: InterimQueue,
+}
+
+impl<S> InterimIo<S> {
+    pub fn new(inner: S, queue: InterimQueue) -> Self {
+        Self { inner, queue }
+    }
+}
+
+impl<S: AsyncWrite + Unpin> InterimIo<S> {
+    /// Write out whatever is queued, keeping what the connection didn't take yet
+    fn poll_queued(&mut self, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        let mut queued = self.queue.0.lock().unwrap_or_else(|e| e.into_inner());
+        while !queued.is_empty() {
+            let written = ready!(Pin::new(&mut self.inner).poll_write(cx, &queued))?;
+            if written == 0 {
+                return Poll::Ready(Err(io::ErrorKind::WriteZero.into()));
+            }
+            queued.drain(..written);
+        }
+        Poll::Ready(Ok(()))
+    }
+}
+
+impl<S: AsyncRead + Unpin> AsyncRead for InterimIo<S> {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.get_mut().inner).poll_read(cx, buf)
+    }
+}
+
+impl<S: AsyncWrite + Unpin> AsyncWrite for InterimIo<S> {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        let this = self.get_mut();
+        ready!(this.poll_queued(cx))?;
+        Pin::new(&mut this.inner).poll_write(cx, buf)
+    }
+
+    fn poll_write_vectored(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        bufs: &[io::IoSlice<'_>],
+    ) -> Poll<io::Result<usize>> {
+        let this = self.get_mut();
+        ready!(this.poll_queued(cx))?;
+        Pin::new(&mut this.inner).poll_write_vectored(cx, bufs)
+    }
+
+    fn is_write_vectored(&self) -> bool {
+        self.inner.is_write_vectored()
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        // hyper flushes while the service is still working on the final
+        // response, which sends queued hints without waiting for it
+        let this = self.get_mut();
+        ready!(this.poll_queued(cx))?;
+        Pin::new(&mut this.inner).poll_flush(cx)
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.get_mut().inner).poll_shutdown(cx)
+    }
+}
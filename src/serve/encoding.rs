@@ -0,0 +1,345 @@
+//! On-the-fly content encoding of replayed responses
+//!
+//! Snapshots store response bodies decompressed, so by default everything is
+//! served identity-encoded. With `serve --encode br,gzip` text-like bodies are
+//! compressed for clients that accept one of the listed encodings, keeping
+//! transfer sizes close to what the original site served. Compressed variants
+//! are cached per record so repeated requests don't compress again.
+
+use std::collections::{HashMap, VecDeque};
+use std::fmt;
+use std::io::Write;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+
+use bytes::Bytes;
+use http_body_util::{BodyExt, Full};
+use hyper::header::{
+    HeaderMap, HeaderValue, ACCEPT_ENCODING, CONTENT_ENCODING, CONTENT_LENGTH, CONTENT_TYPE, ETAG,
+    VARY,
+};
+use hyper::Response;
+use tracing::warn;
+
+use super::ServeState;
+use crate::capture::proxy::content_type::ContentTypeHelper;
+use crate::error::{Result, WebMockError};
+
+/// Most bytes of compressed bodies kept in the cache
+pub const DEFAULT_ENCODING_CACHE_BYTES: usize = 64 * 1024 * 1024;
+
+/// A content encoding the mock server can apply
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ContentEncoding {
+    Brotli,
+    Gzip,
+    Zstd,
+}
+
+impl ContentEncoding {
+    /// Token used in `Accept-Encoding` and `Content-Encoding`
+    pub fn as_str(self) -> &'static str {
+        match self {
+            ContentEncoding::Brotli => "br",
+            ContentEncoding::Gzip => "gzip",
+            ContentEncoding::Zstd => "zstd",
+        }
+    }
+
+    fn from_token(token: &str) -> Option<Self> {
+        match token.to_ascii_lowercase().as_str() {
+            "br" => Some(ContentEncoding::Brotli),
+            "gzip" | "x-gzip" => Some(ContentEncoding::Gzip),
+            "zstd" => Some(ContentEncoding::Zstd),
+            _ => None,
+        }
+    }
+
+    /// Compress `body` with this encoding
+    pub fn encode(self, body: &[u8]) -> std::io::Result<Vec<u8>> {
+        match self {
+            ContentEncoding::Brotli => {
+                let mut output = Vec::with_capacity(body.len() / 2);
+                {
+                    let mut writer = brotli::CompressorWriter::new(&mut output, 4096, 6, 22);
+                    writer.write_all(body)?;
+                }
+                Ok(output)
+            }
+            ContentEncoding::Gzip => {
+                let mut encoder = flate2::write::GzEncoder::new(
+                    Vec::with_capacity(body.len() / 2),
+                    flate2::Compression::default(),
+                );
+                encoder.write_all(body)?;
+                encoder.finish()
+            }
+            ContentEncoding::Zstd => zstd::encode_all(body, 3),
+        }
+    }
+}
+
+impl fmt::Display for ContentEncoding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// Parse an encoding list such as `br,gzip`, in order of preference
+pub fn parse_encodings(spec: &str) -> Result<Vec<ContentEncoding>> {
+    let mut encodings = Vec::new();
+    for token in spec.split(',').map(str::trim).filter(|t| !t.is_empty()) {
+        let encoding = ContentEncoding::from_token(token).ok_or_else(|| {
+            WebMockError::config(format!(
+                "Unsupported encoding '{}' in --encode, expected br, gzip or zstd",
+                token
+            ))
+        })?;
+        if !encodings.contains(&encoding) {
+            encodings.push(encoding);
+        }
+    }
+
+    if encodings.is_empty() {
+        return Err(WebMockError::config(
+            "--encode needs at least one of br, gzip or zstd",
+        ));
+    }
+
+    Ok(encodings)
+}
+
+/// Pick the encoding to answer an `Accept-Encoding` header with
+///
+/// The client's quality values decide first; ties go to the earliest entry
+/// of `offered`. Encodings refused with `q=0`, directly or through `*`, are
+/// never picked.
+pub fn negotiate(accept: &str, offered: &[ContentEncoding]) -> Option<ContentEncoding> {
+    let mut qualities: HashMap<ContentEncoding, f32> = HashMap::new();
+    let mut wildcard = None;
+
+    for item in accept.split(',') {
+        let mut params = item.split(';');
+        let token = params.next().unwrap_or_default().trim();
+        let quality = params
+            .filter_map(|param| param.trim().strip_prefix("q="))
+            .find_map(|q| q.trim().parse::<f32>().ok())
+            .unwrap_or(1.0);
+
+        if token == "*" {
+            wildcard = Some(quality);
+        } else if let Some(encoding) = ContentEncoding::from_token(token) {
+            qualities.insert(encoding, quality);
+        }
+    }
+
+    let mut best: Option<(ContentEncoding, f32)> = None;
+    for &encoding in offered {
+        let Some(quality) = qualities.get(&encoding).copied().or(wildcard) else {
+            continue;
+        };
+        if quality > 0.0 && best.is_none_or(|(_, q)| quality > q) {
+            best = Some((encoding, quality));
+        }
+    }
+    best.map(|(encoding, _)| encoding)
+}
+
+/// Check whether a content type is worth compressing
+///
+/// Images, video, fonts and archives are already compressed; SVG and wasm
+/// are the binary-looking exceptions that shrink well.
+pub fn is_compressible(content_type: &str) -> bool {
+    let Ok(mime) = content_type.trim().parse::<mime::Mime>() else {
+        return false;
+    };
+    ContentTypeHelper::is_text_mime(&mime)
+        || mime
+            .suffix()
+            .is_some_and(|suffix| suffix == mime::XML || suffix == mime::JSON)
+        || (mime.type_() == mime::APPLICATION && mime.subtype() == "wasm")
+}
+
+/// Bounded cache of compressed bodies keyed by record and encoding
+#[derive(Debug)]
+pub struct EncodingCache {
+    capacity: usize,
+    entries: Mutex<CacheEntries>,
+    hits: AtomicU64,
+    misses: AtomicU64,
+}
+
+#[derive(Debug, Default)]
+struct CacheEntries {
+    bodies: HashMap<(usize, ContentEncoding), Bytes>,
+    order: VecDeque<(usize, ContentEncoding)>,
+    size: usize,
+}
+
+impl EncodingCache {
+    /// Create a cache holding up to `capacity` bytes of compressed bodies
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            entries: Mutex::new(CacheEntries::default()),
+            hits: AtomicU64::new(0),
+            misses: AtomicU64::new(0),
+        }
+    }
+
+    /// Get the compressed body of a record, compressing it on a miss
+    pub fn get_or_encode(
+        &self,
+        record_index: usize,
+        encoding: ContentEncoding,
+        body: &[u8],
+    ) -> std::io::Result<Bytes> {
+        let key = (record_index, encoding);
+        if let Some(cached) = self.lock().bodies.get(&key) {
+            self.hits.fetch_add(1, Ordering::Relaxed);
+            return Ok(cached.clone());
+        }
+
+        // Compress outside the lock; a concurrent miss only does the work twice
+        self.misses.fetch_add(1, Ordering::Relaxed);
+        let encoded = Bytes::from(encoding.encode(body)?);
+        if encoded.len() <= self.capacity {
+            let mut entries = self.lock();
+            if !entries.bodies.contains_key(&key) {
+                while entries.size + encoded.len() > self.capacity {
+                    let Some(oldest) = entries.order.pop_front() else {
+                        break;
+                    };
+                    if let Some(evicted) = entries.bodies.remove(&oldest) {
+                        entries.size -= evicted.len();
+                    }
+                }
+                entries.size += encoded.len();
+                entries.order.push_back(key);
+                entries.bodies.insert(key, encoded.clone());
+            }
+        }
+        Ok(encoded)
+    }
+
+    /// Number of requests answered from the cache
+    pub fn hits(&self) -> u64 {
+        self.hits.load(Ordering::Relaxed)
+    }
+
+    /// Number of requests that had to compress a body
+    pub fn misses(&self) -> u64 {
+        self.misses.load(Ordering::Relaxed)
+    }
+
+    /// Number of compressed bodies currently cached
+    pub fn len(&self) -> usize {
+        self.lock().bodies.len()
+    }
+
+    /// Check whether nothing is cached
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, CacheEntries> {
+        self.entries.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+impl Default for EncodingCache {
+    fn default() -> Self {
+        Self::new(DEFAULT_ENCODING_CACHE_BYTES)
+    }
+}
+
+/// Marks a response whose body is a snapshot record's and can be cached by index
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct CacheKey(pub usize);
+
+/// Compress a replayed response for the client if it and the body allow it
+///
+/// Responses without a [`CacheKey`], such as overridden records, are
+/// compressed without caching. Failures fall back to the identity response.
+pub(crate) async fn encode_response(
+    state: &ServeState,
+    request_headers: &HeaderMap,
+    response: Response<Full<Bytes>>,
+) -> Response<Full<Bytes>> {
+    let offered = &state.options.encodings;
+    if offered.is_empty() {
+        return response;
+    }
+
+    let headers = response.headers();
+    let already_encoded = headers
+        .get(CONTENT_ENCODING)
+        .is_some_and(|value| value.as_bytes() != b"identity");
+    let compressible = headers
+        .get(CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .is_some_and(is_compressible);
+    if already_encoded || !compressible {
+        // Recorded already encoded or not worth it; serve as captured
+        return response;
+    }
+
+    let (mut parts, body) = response.into_parts();
+    let varies = parts.headers.get_all(VARY).iter().any(|value| {
+        value
+            .to_str()
+            .is_ok_and(|value| value.to_ascii_lowercase().contains("accept-encoding"))
+    });
+    if !varies {
+        parts
+            .headers
+            .append(VARY, HeaderValue::from_static("accept-encoding"));
+    }
+
+    let accept = request_headers
+        .get_all(ACCEPT_ENCODING)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .collect::<Vec<_>>()
+        .join(",");
+    let encoding = negotiate(&accept, offered);
+
+    // Full bodies can't fail to collect
+    let body = body
+        .collect()
+        .await
+        .map(|b| b.to_bytes())
+        .unwrap_or_default();
+    let Some(encoding) = encoding.filter(|_| !body.is_empty()) else {
+        return Response::from_parts(parts, Full::new(body));
+    };
+
+    let encoded = match parts.extensions.get::<CacheKey>() {
+        Some(CacheKey(index)) => state.encoded.get_or_encode(*index, encoding, &body),
+        None => encoding.encode(&body).map(Bytes::from),
+    };
+    match encoded {
+        Ok(encoded) => {
+            parts.headers.insert(
+                CONTENT_ENCODING,
+                HeaderValue::from_static(encoding.as_str()),
+            );
+            parts.headers.insert(CONTENT_LENGTH, encoded.len().into());
+            // A strong tag promises identical bytes, which the compressed body isn't
+            if let Some(weak) = parts
+                .headers
+                .get(ETAG)
+                .and_then(|etag| etag.to_str().ok())
+                .filter(|etag| !etag.starts_with("W/"))
+                .and_then(|etag| HeaderValue::from_str(&format!("W/{}", etag)).ok())
+            {
+                parts.headers.insert(ETAG, weak);
+            }
+            Response::from_parts(parts, Full::new(encoded))
+        }
+        Err(e) => {
+            warn!("Failed to {}-encode response: {}", encoding, e);
+            Response::from_parts(parts, Full::new(body))
+        }
+    }
+}
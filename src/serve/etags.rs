@@ -0,0 +1,69 @@
+//! ETags for records captured without validators, with `--generate-etags`
+//!
+//! Without an `etag` or `last-modified` header a browser can't revalidate a
+//! cached response, so it downloads the body again on every reload. A tag
+//! derived from the body gives it something to send back in
+//! `If-None-Match`. Tags are computed the first time a record is served
+//! rather than when the snapshot loads, so large snapshots start at once.
+
+use std::sync::OnceLock;
+
+use hyper::header::{HeaderMap, ETAG, LAST_MODIFIED};
+
+/// Lazily computed ETags of snapshot records, by record index
+#[derive(Debug, Default)]
+pub struct EtagCache {
+    tags: Vec<OnceLock<String>>,
+}
+
+impl EtagCache {
+    /// Create a cache for a snapshot with `records` records
+    pub fn new(records: usize) -> Self {
+        Self {
+            tags: (0..records).map(|_| OnceLock::new()).collect(),
+        }
+    }
+
+    /// Get the ETag of a record's body, hashing it on first use
+    ///
+    /// Indexes outside the snapshot are hashed every time.
+    pub fn get_or_compute(&self, record_index: usize, body: &[u8]) -> String {
+        match self.tags.get(record_index) {
+            Some(tag) => tag.get_or_init(|| body_etag(body)).clone(),
+            None => body_etag(body),
+        }
+    }
+
+    /// Number of records whose ETag has been computed
+    pub fn computed(&self) -> usize {
+        self.tags.iter().filter(|tag| tag.get().is_some()).count()
+    }
+}
+
+/// Strong ETag for a body: a 128-bit FNV-1a hash and the body length
+pub fn body_etag(body: &[u8]) -> String {
+    const OFFSET_BASIS: u128 = 0x6c62272e07bb014262b821756295c58d;
+    const PRIME: u128 = 0x0000000001000000000000000000013b;
+
+    let hash = body.iter().fold(OFFSET_BASIS, |hash, byte| {
+        (hash ^ u128::from(*byte)).wrapping_mul(PRIME)
+    });
+    format!("\"{:032x}-{:x}\"", hash, body.len())
+}
+
+/// Check whether response headers already carry a validator
+pub fn has_validator(headers: &HeaderMap) -> bool {
+    headers.contains_key(ETAG) || headers.contains_key(LAST_MODIFIED)
+}
+
+/// Check whether an `If-None-Match` value matches `etag`
+///
+/// Uses the weak comparison RFC 9110 requires for `If-None-Match`, so
+/// `W/"x"` and `"x"` match each other.
+pub fn if_none_match(header: &str, etag: &str) -> bool {
+    let opaque = |tag: &str| tag.trim().trim_start_matches("W/").to_string();
+    let etag = opaque(etag);
+    header
+        .split(',')
+        .any(|candidate| candidate.trim() == "*" || opaque(candidate) == etag)
+}
@@ -0,0 +1,203 @@
+//! Passing requests the snapshot can't answer to a live backend
+//!
+//! `webmock serve --forward-unmatched https://staging.example.com` sends
+//! every request without a matching record to the same path and query on
+//! the given base URL and relays the live response. Nothing is recorded;
+//! the snapshot is only ever read.
+
+use std::time::Duration;
+
+use bytes::Bytes;
+use http_body_util::{BodyExt, Full};
+use hyper::header::{HeaderMap, HeaderValue, HOST};
+use hyper::{Method, Request, Response, StatusCode};
+use hyper_rustls::HttpsConnector;
+use hyper_util::client::legacy::connect::HttpConnector;
+use hyper_util::client::legacy::Client;
+use tracing::{debug, warn};
+use url::Url;
+
+use crate::capture::proxy::server::is_hop_by_hop_header;
+use crate::capture::proxy::HttpClientPool;
+use crate::error::{Result, WebMockError};
+
+/// How long a forwarded request may take, including its response body
+pub const DEFAULT_FORWARD_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Base URL unmatched requests are forwarded to
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ForwardTarget {
+    base: Url,
+}
+
+impl ForwardTarget {
+    /// Parse a `--forward-unmatched` base URL such as `https://staging.example.com/api`
+    pub fn parse(spec: &str) -> Result<Self> {
+        Self::parse_option(spec, "--forward-unmatched")
+    }
+
+    /// Parse a base URL given with the command line option `option`
+    pub fn parse_option(spec: &str, option: &str) -> Result<Self> {
+        let base = Url::parse(spec.trim())
+            .map_err(|e| WebMockError::InvalidUrl(spec.to_string(), e.to_string()))?;
+        if !matches!(base.scheme(), "http" | "https") || base.host_str().is_none() {
+            return Err(WebMockError::config(format!(
+                "Invalid {} URL '{}': expected an http or https base URL",
+                option, spec
+            )));
+        }
+        if base.query().is_some() || base.fragment().is_some() {
+            return Err(WebMockError::config(format!(
+                "Invalid {} URL '{}': the base URL can't have a query or fragment",
+                option, spec
+            )));
+        }
+        Ok(Self { base })
+    }
+
+    /// The base URL as given
+    pub fn base(&self) -> &Url {
+        &self.base
+    }
+
+    /// URL a request for `url` is forwarded to: its path and query under the base URL
+    pub fn rewrite(&self, url: &str) -> Option<String> {
+        let request = Url::parse(url).ok()?;
+        let mut forwarded = self.base.clone();
+        let base_path = self.base.path().trim_end_matches('/');
+        forwarded.set_path(&format!("{}{}", base_path, request.path()));
+        forwarded.set_query(request.query());
+        Some(forwarded.to_string())
+    }
+}
+
+/// Client forwarding unmatched requests to a [`ForwardTarget`]
+pub struct Forwarder {
+    target: ForwardTarget,
+    client: Client<HttpsConnector<HttpConnector>, Full<Bytes>>,
+    timeout: Duration,
+}
+
+impl Forwarder {
+    pub fn new(target: ForwardTarget, timeout: Duration) -> Self {
+        let client = Client::builder(hyper_util::rt::TokioExecutor::new())
+            .build(HttpClientPool::https_connector());
+        Self {
+            target,
+            client,
+            timeout,
+        }
+    }
+
+    /// URL a request for `url` is forwarded to
+    pub fn url_for(&self, url: &str) -> Option<String> {
+        self.target.rewrite(url)
+    }
+
+    /// Send a request to the backend and relay its response
+    ///
+    /// Connection errors and timeouts are answered with 502, naming the
+    /// upstream error, so they can't be mistaken for the backend's own.
+    pub async fn forward(
+        &self,
+        method: &Method,
+        url: &str,
+        headers: &HeaderMap,
+        body: Bytes,
+    ) -> Response<Full<Bytes>> {
+        let forwarded = self.url_for(url).unwrap_or_else(|| url.to_string());
+        match self.exchange(method, url, headers, body).await {
+            Ok(response) => response,
+            Err(error) => bad_gateway(&forwarded, &error),
+        }
+    }
+
+    /// Send a request to the backend and read its whole response
+    ///
+    /// Fails with the upstream error when no response arrived in time.
+    pub async fn exchange(
+        &self,
+        method: &Method,
+        url: &str,
+        headers: &HeaderMap,
+        body: Bytes,
+    ) -> std::result::Result<Response<Full<Bytes>>, String> {
+        let forwarded = self
+            .url_for(url)
+            .ok_or_else(|| format!("can't forward invalid URL {}", url))?;
+        debug!("Forwarding {} {} to {}", method, url, forwarded);
+
+        let mut request = Request::builder().method(method.clone()).uri(&forwarded);
+        for (name, value) in headers {
+            if !is_forwarded_header(name.as_str()) {
+                request = request.header(name, value);
+            }
+        }
+        let request = request.body(Full::new(body)).map_err(|e| e.to_string())?;
+
+        let exchange = async {
+            let response = self
+                .client
+                .request(request)
+                .await
+                .map_err(|e| error_chain(&e))?;
+            let (parts, body) = response.into_parts();
+            let body = body
+                .collect()
+                .await
+                .map_err(|e| error_chain(&e))?
+                .to_bytes();
+            Ok::<_, String>((parts, body))
+        };
+        let (mut parts, body) = tokio::time::timeout(self.timeout, exchange)
+            .await
+            .map_err(|_| format!("no response within {}s", self.timeout.as_secs_f32()))??;
+
+        // The body is relayed in one piece, so the backend's framing doesn't apply
+        let hop_by_hop: Vec<_> = parts
+            .headers
+            .keys()
+            .filter(|name| is_forwarded_header(name.as_str()))
+            .cloned()
+            .collect();
+        for name in hop_by_hop {
+            parts.headers.remove(name);
+        }
+        parts
+            .headers
+            .insert(hyper::header::CONTENT_LENGTH, HeaderValue::from(body.len()));
+        Ok(Response::from_parts(parts, Full::new(body)))
+    }
+}
+
+/// Headers that describe one connection rather than the exchange, so aren't passed on
+fn is_forwarded_header(name: &str) -> bool {
+    is_hop_by_hop_header(name)
+        || name == HOST
+        || name == "proxy-connection"
+        || name == hyper::header::CONTENT_LENGTH
+}
+
+/// An error and its sources, e.g. "client error (Connect): tcp connect error: Connection refused"
+fn error_chain(error: &(dyn std::error::Error + 'static)) -> String {
+    let mut message = error.to_string();
+    let mut source = error.source();
+    while let Some(cause) = source {
+        message.push_str(": ");
+        message.push_str(&cause.to_string());
+        source = cause.source();
+    }
+    message
+}
+
+fn bad_gateway(url: &str, error: &str) -> Response<Full<Bytes>> {
+    warn!("Forwarding to {} failed: {}", url, error);
+    Response::builder()
+        .status(StatusCode::BAD_GATEWAY)
+        .header("content-type", "text/plain; charset=utf-8")
+        .body(Full::new(Bytes::from(format!(
+            "Not in snapshot, and forwarding to {} failed (WebMock --forward-unmatched):\n\n{}\n",
+            url, error
+        ))))
+        .unwrap()
+}
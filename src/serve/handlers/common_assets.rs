@@ -0,0 +1,84 @@
+//! Stand-in responses for well-known paths missing from a snapshot
+//!
+//! Browsers ask every site for `/favicon.ico`, and crawlers and iOS ask for
+//! `/robots.txt` and apple-touch icons. Captures rarely include them, so
+//! every replay would log 404s for them. Unless disabled with
+//! `--synthesize-common-assets false`, GET requests for these paths that no
+//! record matches get a harmless default instead, marked as synthesized in
+//! the console.
+
+use bytes::Bytes;
+use http_body_util::Full;
+use hyper::{Method, Response, StatusCode};
+use url::Url;
+
+/// Header set on every synthesized response
+pub const SYNTHESIZED_HEADER: &str = "x-webmock-synthesized";
+
+/// A transparent 1x1 PNG
+const TRANSPARENT_PNG: &[u8] = &[
+    0x89, 0x50, 0x4e, 0x47, 0x0d, 0x0a, 0x1a, 0x0a, 0x00, 0x00, 0x00, 0x0d, 0x49, 0x48, 0x44, 0x52,
+    0x00, 0x00, 0x00, 0x01, 0x00, 0x00, 0x00, 0x01, 0x08, 0x06, 0x00, 0x00, 0x00, 0x1f, 0x15, 0xc4,
+    0x89, 0x00, 0x00, 0x00, 0x0b, 0x49, 0x44, 0x41, 0x54, 0x78, 0xda, 0x63, 0x60, 0x00, 0x02, 0x00,
+    0x00, 0x05, 0x00, 0x01, 0xe9, 0xfa, 0xdc, 0xd8, 0x00, 0x00, 0x00, 0x00, 0x49, 0x45, 0x4e, 0x44,
+    0xae, 0x42, 0x60, 0x82,
+];
+
+/// A well-known path answered when the snapshot doesn't contain it
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CommonAsset {
+    /// `/favicon.ico`, answered with a transparent PNG
+    Favicon,
+    /// `/robots.txt`, answered with an empty file
+    Robots,
+    /// `/apple-touch-icon*.png`, answered with 204 No Content
+    AppleTouchIcon,
+}
+
+impl CommonAsset {
+    /// Find the asset a request asks for; only GET requests are answered
+    pub fn for_request(method: &Method, url: &str) -> Option<Self> {
+        if method != Method::GET {
+            return None;
+        }
+        let url = Url::parse(url).ok()?;
+        match url.path() {
+            "/favicon.ico" => Some(CommonAsset::Favicon),
+            "/robots.txt" => Some(CommonAsset::Robots),
+            // Also sized and -precomposed variants such as apple-touch-icon-180x180.png
+            path if path.starts_with("/apple-touch-icon")
+                && path.ends_with(".png")
+                && !path[1..].contains('/') =>
+            {
+                Some(CommonAsset::AppleTouchIcon)
+            }
+            _ => None,
+        }
+    }
+
+    /// Status the synthesized response is sent with
+    pub fn status(&self) -> StatusCode {
+        match self {
+            CommonAsset::AppleTouchIcon => StatusCode::NO_CONTENT,
+            CommonAsset::Favicon | CommonAsset::Robots => StatusCode::OK,
+        }
+    }
+
+    /// Build the synthesized response
+    pub fn response(&self) -> Response<Full<Bytes>> {
+        let builder = Response::builder()
+            .status(self.status())
+            .header(SYNTHESIZED_HEADER, "true")
+            .header("cache-control", "public, max-age=86400");
+        let builder = match self {
+            CommonAsset::Favicon => builder.header("content-type", "image/png"),
+            CommonAsset::Robots => builder.header("content-type", "text/plain; charset=utf-8"),
+            CommonAsset::AppleTouchIcon => builder,
+        };
+        let body = match self {
+            CommonAsset::Favicon => Bytes::from_static(TRANSPARENT_PNG),
+            CommonAsset::Robots | CommonAsset::AppleTouchIcon => Bytes::new(),
+        };
+        builder.body(Full::new(body)).unwrap()
+    }
+}
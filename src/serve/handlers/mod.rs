@@ -1,7 +1,9 @@
 //! Request handling utilities for mock server
 
+pub mod common_assets;
 pub mod request_matcher;
 pub mod response_builder;
 
+pub use common_assets::*;
 pub use request_matcher::*;
 pub use response_builder::*;
@@ -1,181 +1,559 @@
 //! Request matching logic for mock server
+//!
+//! Lookups go through the [`RequestMatcher`] trait so embedders can plug in
+//! their own strategy. [`DefaultMatcher`] is what the CLI uses: it tries
+//! [`ExactUrlMatcher`], [`QueryInsensitiveMatcher`] and [`PathOnlyMatcher`]
+//! in that order.
+//!
+//! When several records could answer a request, as `/search?q=shoes` and
+//! `/search?q=hats` both could for `/search?q=socks`, the built-in matchers
+//! rank them with [`MatchScore`]: the same query string first, then the most
+//! query parameters in common, then the record captured earliest. The order
+//! records are stored in never decides, so every load replays the same one.
+//!
+//! Request URLs are put in [canonical form](crate::capture::canonical_url)
+//! before any matcher sees them, as records were when they were captured.
 
+use crate::capture::canonical_url::canonical_url;
 use crate::capture::proxy::RequestRecord;
+use crate::serve::options::MethodMatching;
 use crate::storage::Snapshot;
-use hyper::Method;
-use tracing::debug;
+use hyper::{HeaderMap, Method};
+use std::cmp::Ordering;
+use std::fmt;
+use tracing::{debug, info};
 use url::Url;
 
-/// Find a matching recorded request for the incoming request
-pub fn find_matching_record<'a>(
-    snapshot: &'a Snapshot,
-    method: &Method,
-    full_url: &str,
-) -> Option<&'a RequestRecord> {
-    let method_str = method.as_str();
+/// Header frameworks use to tunnel another method through a POST
+pub const METHOD_OVERRIDE_HEADER: &str = "x-http-method-override";
+
+/// An incoming request as seen by a [`RequestMatcher`]
+#[derive(Debug, Clone)]
+pub struct IncomingRequest<'r> {
+    /// Method to match, after the method override header has been applied
+    pub method: Method,
+    /// Absolute request URL
+    pub url: &'r str,
+    pub headers: &'r HeaderMap,
+    /// Request body, only read for matchers whose `needs_body` returns true
+    pub body: Option<&'r [u8]>,
+}
+
+impl<'r> IncomingRequest<'r> {
+    pub fn new(method: Method, url: &'r str, headers: &'r HeaderMap) -> Self {
+        Self {
+            method,
+            url,
+            headers,
+            body: None,
+        }
+    }
+
+    /// Attach the request body
+    pub fn with_body(mut self, body: Option<&'r [u8]>) -> Self {
+        self.body = body;
+        self
+    }
+
+    /// The same request under a different method
+    fn with_method(&self, method: Method) -> Self {
+        Self {
+            method,
+            ..self.clone()
+        }
+    }
+}
+
+/// Strategy for finding the recorded request that answers an incoming request
+///
+/// Register a custom implementation with
+/// [`MockServer::with_matcher`](crate::serve::MockServer::with_matcher).
+/// Method fallbacks and the override header are resolved before the matcher
+/// is called, and CONNECT tunnels are always matched by host. The built-in
+/// matchers compare methods case-sensitively, as HTTP does, and treat
+/// extension methods such as PROPFIND like any other.
+pub trait RequestMatcher {
+    /// Find the record to replay for `request`, if any
+    fn find<'a>(
+        &self,
+        snapshot: &'a Snapshot,
+        request: &IncomingRequest<'_>,
+    ) -> Option<&'a RequestRecord>;
+
+    /// Whether request bodies must be read before matching
+    fn needs_body(&self) -> bool {
+        false
+    }
+}
+
+/// Same method and URL; the scheme is ignored if nothing matches exactly
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ExactUrlMatcher;
+
+impl RequestMatcher for ExactUrlMatcher {
+    fn find<'a>(
+        &self,
+        snapshot: &'a Snapshot,
+        request: &IncomingRequest<'_>,
+    ) -> Option<&'a RequestRecord> {
+        let method_str = request.method.as_str();
+        let request_url = parse_request_url(request.url)?;
+
+        // First, try exact URL + method match
+        if let Some(record) =
+            earliest_variant_match(&snapshot.requests, request.headers, |record| {
+                record.method == method_str && record.url == request.url
+            })
+        {
+            debug!("Found exact match!");
+            return Some(record);
+        }
+
+        // Then try host + path + query string match (ignoring protocol)
+        let record = earliest_variant_match(&snapshot.requests, request.headers, |record| {
+            record.method == method_str && same_resource(&record.url, &request_url)
+        })?;
+        debug!("Found host+path+query match!");
+        Some(record)
+    }
+}
+
+/// Same method, host and path, whatever the query string
+///
+/// Of several such records the one with the best [`MatchScore`] is returned.
+/// Capture failures are never returned, so a failed request can't stand in
+/// for a different resource.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct QueryInsensitiveMatcher;
+
+impl RequestMatcher for QueryInsensitiveMatcher {
+    fn find<'a>(
+        &self,
+        snapshot: &'a Snapshot,
+        request: &IncomingRequest<'_>,
+    ) -> Option<&'a RequestRecord> {
+        let request_url = parse_request_url(request.url)?;
+        let request_host = request_url.host_str().unwrap_or("");
+
+        let record = best_scored(&snapshot.requests, request, &request_url, |recorded_url| {
+            recorded_url.host_str().unwrap_or("") == request_host
+                && recorded_url.path() == request_url.path()
+        })?;
+        debug!("Found host+path match!");
+        Some(record)
+    }
+}
 
-    // Debug: show snapshot summary
-    let method_counts: std::collections::HashMap<String, usize> =
-        snapshot
+/// Same method and path on any host; capture failures are never returned
+///
+/// Records are ranked as by [`QueryInsensitiveMatcher`].
+#[derive(Debug, Clone, Copy, Default)]
+pub struct PathOnlyMatcher;
+
+impl RequestMatcher for PathOnlyMatcher {
+    fn find<'a>(
+        &self,
+        snapshot: &'a Snapshot,
+        request: &IncomingRequest<'_>,
+    ) -> Option<&'a RequestRecord> {
+        let request_url = parse_request_url(request.url)?;
+
+        debug!("Trying path-only match: path='{}'", request_url.path());
+        let record = best_scored(&snapshot.requests, request, &request_url, |recorded_url| {
+            recorded_url.path() == request_url.path()
+        })?;
+        debug!("Found path-only match!");
+        Some(record)
+    }
+}
+
+/// Prefer the record of the same URL whose request body is identical
+///
+/// Useful for endpoints such as GraphQL where one URL is captured with many
+/// different bodies. Falls back to [`DefaultMatcher`] when no body matches.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct BodyAwareMatcher;
+
+impl RequestMatcher for BodyAwareMatcher {
+    fn find<'a>(
+        &self,
+        snapshot: &'a Snapshot,
+        request: &IncomingRequest<'_>,
+    ) -> Option<&'a RequestRecord> {
+        if let (Some(body), Some(request_url)) = (request.body, parse_request_url(request.url)) {
+            if let Some(record) = earliest_match(&snapshot.requests, |record| {
+                record.method == request.method.as_str()
+                    && (record.url == request.url || same_resource(&record.url, &request_url))
+                    && record.body.as_deref().unwrap_or_default() == body
+            }) {
+                debug!("Found body match!");
+                return Some(record);
+            }
+        }
+        DefaultMatcher.find(snapshot, request)
+    }
+
+    fn needs_body(&self) -> bool {
+        true
+    }
+}
+
+/// The matching used by the CLI: exact URL, then ignoring the query, then path only
+#[derive(Debug, Clone, Copy, Default)]
+pub struct DefaultMatcher;
+
+impl RequestMatcher for DefaultMatcher {
+    fn find<'a>(
+        &self,
+        snapshot: &'a Snapshot,
+        request: &IncomingRequest<'_>,
+    ) -> Option<&'a RequestRecord> {
+        // Debug: show snapshot summary
+        let method_counts: std::collections::HashMap<String, usize> = snapshot
             .requests
             .iter()
             .fold(std::collections::HashMap::new(), |mut acc, req| {
                 *acc.entry(req.method.clone()).or_insert(0) += 1;
                 acc
             });
-    debug!(
-        "Snapshot contains {} total requests: {:?}",
-        snapshot.requests.len(),
-        method_counts
-    );
-
-    // Handle CONNECT method specially
-    if method == Method::CONNECT {
-        debug!("Looking for CONNECT match for: {}", full_url);
-
-        // Debug: show all CONNECT records in snapshot
-        let connect_records: Vec<_> = snapshot
-            .requests
-            .iter()
-            .filter(|r| r.method == "CONNECT")
-            .collect();
         debug!(
-            "Found {} CONNECT records in snapshot",
-            connect_records.len()
+            "Snapshot contains {} total requests: {:?}",
+            snapshot.requests.len(),
+            method_counts
         );
-        for (i, record) in connect_records.iter().enumerate() {
-            debug!("CONNECT record {}: {}", i, record.url);
-        }
-
-        for record in &snapshot.requests {
-            if record.method == "CONNECT" {
-                debug!("Comparing against recorded CONNECT: {}", record.url);
-
-                // For CONNECT, we need to handle the special format
-                // CONNECT requests are recorded as "https://host:port" but the actual request
-                // might come as just "host:port"
-
-                // Normalize both URLs to host:port format
-                let normalize_connect_url = |url: &str| -> String {
-                    // Handle both "https://host:port" and "host:port" formats
-                    if url.starts_with("https://") {
-                        url.replace("https://", "")
-                    } else if url.starts_with("http://") {
-                        url.replace("http://", "")
-                    } else {
-                        url.to_string()
+
+        if request.method == Method::CONNECT {
+            return find_connect_record(snapshot, request.url);
+        }
+
+        ExactUrlMatcher
+            .find(snapshot, request)
+            .or_else(|| QueryInsensitiveMatcher.find(snapshot, request))
+            .or_else(|| PathOnlyMatcher.find(snapshot, request))
+    }
+}
+
+/// How well a record answers a request it shares a path with
+///
+/// Compares greater for the better record: the same query string wins, then
+/// the most query parameters in common, then the record captured earliest.
+/// Records without a sequence number rank after those with one.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MatchScore {
+    /// The record's query string is the request's
+    pub exact_query: bool,
+    /// Name and value pairs of the request's query that the record's has too
+    pub common_params: usize,
+    /// [`RequestRecord::sequence`] of the record
+    pub sequence: Option<u64>,
+}
+
+impl MatchScore {
+    /// Score a record, parsed as `recorded_url`, against the request URL
+    pub fn of(record: &RequestRecord, recorded_url: &Url, request_url: &Url) -> Self {
+        let mut recorded: Vec<_> = recorded_url.query_pairs().collect();
+        let common_params = request_url
+            .query_pairs()
+            .filter(
+                |pair| match recorded.iter().position(|other| other == pair) {
+                    Some(at) => {
+                        recorded.swap_remove(at);
+                        true
                     }
-                };
+                    None => false,
+                },
+            )
+            .count();
 
-                let normalized_recorded = normalize_connect_url(&record.url);
-                let normalized_request = normalize_connect_url(full_url);
+        Self {
+            exact_query: recorded_url.query() == request_url.query(),
+            common_params,
+            sequence: record.sequence,
+        }
+    }
+}
 
-                debug!(
-                    "Normalized comparison: '{}' vs '{}'",
-                    normalized_recorded, normalized_request
-                );
+impl Ord for MatchScore {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.exact_query
+            .cmp(&other.exact_query)
+            .then(self.common_params.cmp(&other.common_params))
+            .then_with(|| arrival(other.sequence).cmp(&arrival(self.sequence)))
+    }
+}
 
-                if normalized_recorded == normalized_request {
-                    debug!("Found normalized CONNECT match!");
-                    return Some(record);
-                }
+impl PartialOrd for MatchScore {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl fmt::Display for MatchScore {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "exact query {}, {} query params in common, sequence ",
+            if self.exact_query { "yes" } else { "no" },
+            self.common_params
+        )?;
+        match self.sequence {
+            Some(sequence) => write!(f, "{}", sequence),
+            None => write!(f, "unknown"),
+        }
+    }
+}
+
+/// Sort key of a sequence number, with unnumbered records last
+fn arrival(sequence: Option<u64>) -> u64 {
+    sequence.unwrap_or(u64::MAX)
+}
+
+/// Find the record of the request's method, at a place `same_place` accepts,
+/// with the best [`MatchScore`]; capture failures are skipped
+///
+/// Among records with equal scores the first stored wins.
+fn best_scored<'a>(
+    records: &'a [RequestRecord],
+    request: &IncomingRequest<'_>,
+    request_url: &Url,
+    same_place: impl Fn(&Url) -> bool,
+) -> Option<&'a RequestRecord> {
+    let mut best: Option<(MatchScore, &RequestRecord)> = None;
+    for record in records {
+        if record.method != request.method.as_str() || record.response.is_failure() {
+            continue;
+        }
+        let Ok(recorded_url) = Url::parse(&record.url) else {
+            continue;
+        };
+        if !same_place(&recorded_url) {
+            continue;
+        }
+
+        let score = MatchScore::of(record, &recorded_url, request_url);
+        debug!("Candidate {} {}: {}", record.method, record.url, score);
+        if best.as_ref().is_none_or(|(best, _)| score > *best) {
+            best = Some((score, record));
+        }
+    }
+    best.map(|(_, record)| record)
+}
 
-                // Also try host-only matching
-                if let (Ok(recorded_url), Ok(request_url)) = (
-                    Url::parse(&format!("https://{}", normalized_recorded)),
-                    Url::parse(&format!("https://{}", normalized_request)),
-                ) {
-                    if recorded_url.host_str() == request_url.host_str() {
-                        debug!("Found host-only CONNECT match!");
-                        return Some(record);
+/// A recorded request found for an incoming request
+#[derive(Debug)]
+pub struct RecordMatch<'a> {
+    pub record: &'a RequestRecord,
+    /// Method the record was matched under, when it differs from the request's
+    pub matched_method: Option<Method>,
+}
+
+/// Find a matching record, honoring the method override header and fallbacks
+///
+/// The override header (when enabled) replaces the request method before
+/// matching. A configured fallback is only tried when that method misses.
+pub fn find_record_with_methods<'a>(
+    snapshot: &'a Snapshot,
+    matcher: &dyn RequestMatcher,
+    request: &IncomingRequest<'_>,
+    matching: &MethodMatching,
+) -> Option<RecordMatch<'a>> {
+    // Records keep their URLs in canonical form, so requests are matched in it too
+    let canonical = canonical_url(request.url);
+    let request = &IncomingRequest {
+        url: &canonical,
+        ..request.clone()
+    };
+    let method = &request.method;
+    let full_url = request.url;
+    let mut effective = method.clone();
+    if matching.honor_override_header {
+        if let Some(value) = request
+            .headers
+            .get(METHOD_OVERRIDE_HEADER)
+            .and_then(|value| value.to_str().ok())
+        {
+            match Method::from_bytes(value.trim().to_ascii_uppercase().as_bytes()) {
+                Ok(overridden) if overridden != Method::CONNECT => {
+                    if overridden != *method {
+                        info!(
+                            "Matching {} {} as {} ({})",
+                            method, full_url, overridden, METHOD_OVERRIDE_HEADER
+                        );
                     }
+                    effective = overridden;
                 }
+                _ => debug!("Ignoring invalid {}: {}", METHOD_OVERRIDE_HEADER, value),
             }
         }
+    }
+
+    let changed = |matched: &Method| (matched != method).then(|| matched.clone());
 
-        debug!("No CONNECT match found for: {}", full_url);
-        return None;
+    if let Some(record) = matcher.find(snapshot, &request.with_method(effective.clone())) {
+        return Some(RecordMatch {
+            record,
+            matched_method: changed(&effective),
+        });
     }
 
-    // Parse the request URL to extract components
-    let request_url = match Url::parse(full_url) {
-        Ok(url) => url,
-        Err(e) => {
-            debug!("Failed to parse request URL: {}", e);
-            return None;
-        }
-    };
+    let fallback = matching.fallback_for(&effective)?;
+    let record = matcher.find(snapshot, &request.with_method(fallback.clone()))?;
+    info!(
+        "No {} record for {}, serving the captured {} record (method fallback)",
+        effective, full_url, fallback
+    );
+    Some(RecordMatch {
+        record,
+        matched_method: changed(fallback),
+    })
+}
+
+/// Find a matching recorded request with the default matcher
+pub fn find_matching_record<'a>(
+    snapshot: &'a Snapshot,
+    method: &Method,
+    full_url: &str,
+) -> Option<&'a RequestRecord> {
+    let headers = HeaderMap::new();
+    let canonical = canonical_url(full_url);
+    DefaultMatcher.find(
+        snapshot,
+        &IncomingRequest::new(method.clone(), &canonical, &headers),
+    )
+}
 
-    let request_host = request_url.host_str().unwrap_or("");
-    let request_path = request_url.path();
-    let request_query = request_url.query().unwrap_or("");
+/// Find the CONNECT record for a tunnel, by host:port or host alone
+fn find_connect_record<'a>(snapshot: &'a Snapshot, full_url: &str) -> Option<&'a RequestRecord> {
+    debug!("Looking for CONNECT match for: {}", full_url);
 
+    // Debug: show all CONNECT records in snapshot
+    let connect_records: Vec<_> = snapshot
+        .requests
+        .iter()
+        .filter(|r| r.method == "CONNECT")
+        .collect();
     debug!(
-        "Request components: host='{}', path='{}', query='{}'",
-        request_host, request_path, request_query
+        "Found {} CONNECT records in snapshot",
+        connect_records.len()
     );
+    for (i, record) in connect_records.iter().enumerate() {
+        debug!("CONNECT record {}: {}", i, record.url);
+    }
 
-    // First, try exact URL + method match
-    for record in &snapshot.requests {
-        if record.method == method_str && record.url == full_url {
-            debug!("Found exact match!");
-            return Some(record);
-        }
+    // Recorded as "https://host:port" while the lookup may omit the default
+    // port, so compare hosts and ports rather than strings
+    let target = connect_target(full_url)?;
+    if let Some(record) = connect_records
+        .iter()
+        .find(|record| connect_target(&record.url).as_ref() == Some(&target))
+    {
+        debug!("Found CONNECT match for {}:{}", target.0, target.1);
+        return Some(record);
     }
 
-    // Then try host + path + query string match (ignoring protocol)
-    for record in &snapshot.requests {
-        if record.method == method_str {
-            if let Ok(recorded_url) = Url::parse(&record.url) {
-                let recorded_host = recorded_url.host_str().unwrap_or("");
-                let recorded_path = recorded_url.path();
-                let recorded_query = recorded_url.query().unwrap_or("");
+    // Also try host-only matching
+    if let Some(record) = connect_records
+        .iter()
+        .find(|record| connect_target(&record.url).is_some_and(|(host, _)| host == target.0))
+    {
+        debug!("Found host-only CONNECT match!");
+        return Some(record);
+    }
 
-                debug!(
-                    "Recorded components: host='{}', path='{}', query='{}'",
-                    recorded_host, recorded_path, recorded_query
-                );
+    debug!("No CONNECT match found for: {}", full_url);
+    None
+}
 
-                if recorded_host == request_host
-                    && recorded_path == request_path
-                    && recorded_query == request_query
-                {
-                    debug!("Found host+path+query match!");
-                    return Some(record);
-                }
-            }
-        }
+/// Host and port of a CONNECT target given as `https://host:port`, `host:port` or `https://host`
+fn connect_target(url: &str) -> Option<(String, u16)> {
+    let url = if url.contains("://") {
+        Url::parse(url)
+    } else {
+        Url::parse(&format!("https://{}", url))
     }
+    .ok()?;
+    Some((url.host_str()?.to_string(), url.port_or_known_default()?))
+}
 
-    // Try host + path match (ignoring query)
-    for record in &snapshot.requests {
-        if record.method == method_str {
-            if let Ok(recorded_url) = Url::parse(&record.url) {
-                let recorded_host = recorded_url.host_str().unwrap_or("");
-                let recorded_path = recorded_url.path();
+/// Parse the request URL, logging its components
+fn parse_request_url(full_url: &str) -> Option<Url> {
+    match Url::parse(full_url) {
+        Ok(url) => {
+            debug!(
+                "Request components: host='{}', path='{}', query='{}'",
+                url.host_str().unwrap_or(""),
+                url.path(),
+                url.query().unwrap_or("")
+            );
+            Some(url)
+        }
+        Err(e) => {
+            debug!("Failed to parse request URL: {}", e);
+            None
+        }
+    }
+}
 
-                if recorded_host == request_host && recorded_path == request_path {
-                    debug!("Found host+path match!");
-                    return Some(record);
-                }
-            }
+/// Check whether a recorded URL has the request's host, path and query
+fn same_resource(recorded: &str, request_url: &Url) -> bool {
+    match Url::parse(recorded) {
+        Ok(recorded_url) => {
+            recorded_url.host_str().unwrap_or("") == request_url.host_str().unwrap_or("")
+                && recorded_url.path() == request_url.path()
+                && recorded_url.query().unwrap_or("") == request_url.query().unwrap_or("")
         }
+        Err(_) => false,
     }
+}
+
+/// Find the earliest record matching the predicate, preferring the variant
+/// the request asks for when records carry a `vary_key`
+fn earliest_variant_match<'a>(
+    records: &'a [RequestRecord],
+    headers: &HeaderMap,
+    predicate: impl Fn(&RequestRecord) -> bool,
+) -> Option<&'a RequestRecord> {
+    earliest_match(records, |record| {
+        !record.vary_key.is_empty() && predicate(record) && varies_like(record, headers)
+    })
+    .or_else(|| earliest_match(records, predicate))
+}
 
-    // Finally, try path-only match
-    debug!("Trying path-only match: path='{}'", request_path);
-    for record in &snapshot.requests {
-        if record.method == method_str {
-            if let Ok(recorded_url) = Url::parse(&record.url) {
-                let recorded_path = recorded_url.path();
+/// Check whether a request sends the header values a record's variant was captured with
+fn varies_like(record: &RequestRecord, headers: &HeaderMap) -> bool {
+    record.vary_key.iter().all(|(name, value)| {
+        let sent = headers
+            .get_all(name.as_str())
+            .iter()
+            .filter_map(|value| value.to_str().ok())
+            .collect::<Vec<_>>()
+            .join(", ");
+        sent == *value
+    })
+}
 
-                if recorded_path == request_path {
-                    debug!("Found path-only match!");
-                    return Some(record);
-                }
-            }
+/// Find the earliest captured record matching the predicate, preferring real
+/// responses over failures recorded for the same request
+///
+/// Records without a sequence number keep their stored order.
+fn earliest_match(
+    records: &[RequestRecord],
+    mut predicate: impl FnMut(&RequestRecord) -> bool,
+) -> Option<&RequestRecord> {
+    let mut found: Option<&RequestRecord> = None;
+    let mut failed: Option<&RequestRecord> = None;
+    for record in records {
+        if !predicate(record) {
+            continue;
+        }
+        let best = if record.response.is_failure() {
+            &mut failed
+        } else {
+            &mut found
+        };
+        if best.is_none_or(|best| arrival(record.sequence) < arrival(best.sequence)) {
+            *best = Some(record);
         }
     }
-
-    None
+    found.or(failed)
 }
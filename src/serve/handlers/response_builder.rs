@@ -1,19 +1,84 @@
 //! Response building utilities for mock server
 
 use crate::capture::proxy::RequestRecord;
+use crate::serve::options::{ReplayFailures, ServeOptions};
+use crate::serve::routing::ROUTE_PREFIX;
+use crate::storage::SnapshotInfo;
 use bytes::Bytes;
 use http_body_util::Full;
 use hyper::{Response, StatusCode};
-use tracing::error;
+use std::fmt;
+use tracing::{debug, error, warn};
+
+/// Error returned to hyper to drop a connection without sending a response
+#[derive(Debug)]
+pub struct ConnectionAborted(pub String);
+
+impl fmt::Display for ConnectionAborted {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "connection aborted: {}", self.0)
+    }
+}
+
+impl std::error::Error for ConnectionAborted {}
+
+/// Check whether a connection error was caused by an intentional abort
+pub fn is_connection_aborted(error: &(dyn std::error::Error + 'static)) -> bool {
+    let mut current = Some(error);
+    while let Some(err) = current {
+        if err.is::<ConnectionAborted>() {
+            return true;
+        }
+        current = err.source();
+    }
+    false
+}
 
 /// Create an HTTP response from a recorded request
-pub fn create_response_from_record(record: &RequestRecord) -> Response<Full<Bytes>> {
-    let mut response_builder = Response::builder().status(record.response.status);
+///
+/// With `sniff_content_type`, records captured without a content type are
+/// served with a guessed one so browsers render pages and run scripts.
+pub fn create_response_from_record(
+    record: &RequestRecord,
+    options: &ServeOptions,
+) -> Response<Full<Bytes>> {
+    let status = StatusCode::from_u16(record.response.status).unwrap_or_else(|_| {
+        warn!(
+            "Recorded status {} for {} is not a valid HTTP status, serving 502",
+            record.response.status, record.url
+        );
+        StatusCode::BAD_GATEWAY
+    });
+    let mut response_builder = Response::builder().status(status);
+
+    // hyper writes custom reason phrases on HTTP/1 and drops them on HTTP/2
+    if let Some(reason) = record
+        .response
+        .reason
+        .as_deref()
+        .and_then(|reason| hyper::ext::ReasonPhrase::try_from(reason.as_bytes()).ok())
+    {
+        response_builder = response_builder.extension(reason);
+    }
+
+    let fallback_content_type = if options.sniff_content_type {
+        record.response.fallback_content_type(&record.url)
+    } else {
+        None
+    };
 
     // Add headers from the recorded response
     for (key, value) in &record.response.headers {
         // Skip headers that hyper manages automatically, but allow connection header for CONNECT
         let key_lower = key.to_lowercase();
+        if key_lower == "set-cookie" {
+            // Emitted below, one header per recorded cookie
+            continue;
+        }
+        if key_lower == "content-type" && fallback_content_type.is_some() {
+            // A blank value, replaced by the sniffed type below
+            continue;
+        }
         if !matches!(key_lower.as_str(), "content-length" | "transfer-encoding") {
             // For CONNECT responses, preserve connection headers for upgrade support
             if key_lower == "connection" && record.method == "CONNECT" {
@@ -24,6 +89,18 @@ pub fn create_response_from_record(record: &RequestRecord) -> Response<Full<Byte
         }
     }
 
+    for cookie in record.response.set_cookies() {
+        response_builder = response_builder.header("set-cookie", cookie);
+    }
+
+    if let Some(content_type) = fallback_content_type {
+        debug!(
+            "Serving {} with sniffed content type {}",
+            record.url, content_type
+        );
+        response_builder = response_builder.header("content-type", content_type);
+    }
+
     // Always set content-length based on actual body size
     response_builder =
         response_builder.header("content-length", record.response.body.len().to_string());
@@ -40,6 +117,142 @@ pub fn create_response_from_record(record: &RequestRecord) -> Response<Full<Byte
     })
 }
 
+/// Headers a 304 carries over from the response it stands in for (RFC 9110 §15.4.5)
+const NOT_MODIFIED_HEADERS: [&str; 6] = [
+    "cache-control",
+    "content-location",
+    "date",
+    "etag",
+    "expires",
+    "vary",
+];
+
+/// Create a 304 Not Modified response standing in for `response`
+///
+/// Used for every conditional request answered from a record, whether its
+/// ETag was captured or generated, so clients see the same revalidation.
+pub fn create_not_modified_response(response: &Response<Full<Bytes>>) -> Response<Full<Bytes>> {
+    let mut not_modified = Response::builder().status(StatusCode::NOT_MODIFIED);
+    for name in NOT_MODIFIED_HEADERS {
+        for value in response.headers().get_all(name) {
+            not_modified = not_modified.header(name, value);
+        }
+    }
+    not_modified.body(Full::new(Bytes::new())).unwrap()
+}
+
+/// Replay a request that failed during capture
+///
+/// Returns an error in `as-timeout` mode so hyper drops the connection after
+/// the stall, which is closer to the original failure than any status code.
+pub async fn create_failure_response(
+    record: &RequestRecord,
+    options: &ServeOptions,
+) -> std::result::Result<Response<Full<Bytes>>, ConnectionAborted> {
+    let reason = record.response.failure.clone().unwrap_or_default();
+
+    match options.replay_failures {
+        ReplayFailures::BadGateway => Ok(Response::builder()
+            .status(StatusCode::BAD_GATEWAY)
+            .header("content-type", "text/plain; charset=utf-8")
+            .header("x-webmock-capture-failure", sanitize_header_value(&reason))
+            .body(Full::new(Bytes::from(format!(
+                "Failed during capture: {}",
+                reason
+            ))))
+            .unwrap()),
+        ReplayFailures::AsTimeout => {
+            debug!(
+                "Stalling {:?} before dropping {} {}",
+                options.failure_stall, record.method, record.url
+            );
+            tokio::time::sleep(options.failure_stall).await;
+            Err(ConnectionAborted(reason))
+        }
+    }
+}
+
+/// Create a 508 Loop Detected response for a redirect the snapshot keeps repeating
+pub fn create_loop_detected_response(
+    url: &str,
+    location: Option<&str>,
+    redirects: u32,
+) -> Response<Full<Bytes>> {
+    let target = location
+        .map(|location| format!(" to {}", location))
+        .unwrap_or_default();
+    let body = format!(
+        "Redirect loop detected by WebMock.\n\n\
+         {} was redirected{} {} times in a row. The snapshot contains a redirect\n\
+         loop that was recorded during capture, so following it would never end.\n\n\
+         Re-capture the page, or use --overrides to replace one of the redirects.\n",
+        url, target, redirects
+    );
+
+    Response::builder()
+        .status(StatusCode::LOOP_DETECTED)
+        .header("content-type", "text/plain; charset=utf-8")
+        .header("x-webmock-redirect-loop", redirects.to_string())
+        .body(Full::new(Bytes::from(body)))
+        .unwrap()
+}
+
+/// Create a 404 response for a record requested before one captured ahead of it
+///
+/// Only sent with `--stateful`, while `earlier` hasn't been requested yet.
+pub fn create_out_of_order_response(url: &str, earlier: &RequestRecord) -> Response<Full<Bytes>> {
+    let body = format!(
+        "Request out of captured order (WebMock --stateful).\n\n\
+         {} was captured after {} {}, which hasn't been requested yet.\n\
+         Replay the flow in its captured order, or add a 'dependencies' entry\n\
+         to the overrides file to change what this request waits for.\n",
+        url, earlier.method, earlier.url
+    );
+
+    Response::builder()
+        .status(StatusCode::NOT_FOUND)
+        .header("content-type", "text/plain; charset=utf-8")
+        .header(
+            "x-webmock-waiting-for",
+            sanitize_header_value(&format!("{} {}", earlier.method, earlier.url)),
+        )
+        .body(Full::new(Bytes::from(body)))
+        .unwrap()
+}
+
+/// Create a 502 response for a host whose traffic could not be recorded
+///
+/// The host rejected the capture certificate, so its tunnels were passed
+/// through blindly and nothing is in the snapshot to replay.
+pub fn create_pinned_host_response(host: &str) -> Response<Full<Bytes>> {
+    let body = format!(
+        "{} is certificate-pinned.\n\n\
+         The browser rejected WebMock's certificate for this host during capture,\n\
+         so its traffic was tunneled without being recorded and can't be replayed.\n",
+        host
+    );
+
+    Response::builder()
+        .status(StatusCode::BAD_GATEWAY)
+        .header("content-type", "text/plain; charset=utf-8")
+        .header("connection", "close")
+        .body(Full::new(Bytes::from(body)))
+        .unwrap()
+}
+
+fn sanitize_header_value(value: &str) -> String {
+    value
+        .chars()
+        .map(|c| {
+            if c.is_ascii_graphic() || c == ' ' {
+                c
+            } else {
+                '?'
+            }
+        })
+        .collect()
+}
+
 /// Create a 404 Not Found response with helpful information
 pub fn create_404_response(url: &str) -> Response<Full<Bytes>> {
     let body = format!(
@@ -73,3 +286,89 @@ pub fn create_404_response(url: &str) -> Response<Full<Bytes>> {
         .body(Full::new(Bytes::from(body)))
         .unwrap()
 }
+
+/// Create the index page of a path-routed server, linking every snapshot
+pub fn create_snapshot_index_response(snapshots: &[SnapshotInfo]) -> Response<Full<Bytes>> {
+    let body = snapshot_page(
+        "WebMock snapshots",
+        "<h1>Snapshots</h1>",
+        "<p>No snapshots found. Capture one with <code>webmock capture</code>.</p>",
+        snapshots,
+    );
+
+    Response::builder()
+        .status(StatusCode::OK)
+        .header("content-type", "text/html; charset=utf-8")
+        .body(Full::new(Bytes::from(body)))
+        .unwrap()
+}
+
+/// Create a 404 response for a path that names no snapshot, listing the valid ones
+pub fn create_unknown_snapshot_response(
+    path: &str,
+    snapshots: &[SnapshotInfo],
+) -> Response<Full<Bytes>> {
+    let heading = format!(
+        "<h1 class=\"error\">404 Not Found</h1>\n        \
+         <p>No snapshot is served at <span class=\"url\">{}</span>. \
+         Snapshots are served under <code>{}&lt;name&gt;/</code>.</p>",
+        html_escape::encode_text(path),
+        ROUTE_PREFIX
+    );
+    let body = snapshot_page(
+        "404 Not Found - WebMock",
+        &heading,
+        "<p>There are no snapshots to serve.</p>",
+        snapshots,
+    );
+
+    Response::builder()
+        .status(StatusCode::NOT_FOUND)
+        .header("content-type", "text/html; charset=utf-8")
+        .body(Full::new(Bytes::from(body)))
+        .unwrap()
+}
+
+/// HTML page with a heading followed by links to each snapshot
+fn snapshot_page(title: &str, heading: &str, empty: &str, snapshots: &[SnapshotInfo]) -> String {
+    let list = if snapshots.is_empty() {
+        empty.to_string()
+    } else {
+        let items: Vec<String> = snapshots
+            .iter()
+            .map(|snapshot| {
+                format!(
+                    "            <li><a href=\"{}{}/\">{}</a> <span class=\"source\">{}</span></li>",
+                    ROUTE_PREFIX,
+                    html_escape::encode_double_quoted_attribute(&snapshot.name),
+                    html_escape::encode_text(&snapshot.name),
+                    html_escape::encode_text(&snapshot.url)
+                )
+            })
+            .collect();
+        format!("<ul>\n{}\n        </ul>", items.join("\n"))
+    };
+
+    format!(
+        r#"<!DOCTYPE html>
+<html>
+<head>
+    <title>{}</title>
+    <style>
+        body {{ font-family: Arial, sans-serif; margin: 40px; }}
+        .container {{ max-width: 600px; margin: 0 auto; }}
+        .error {{ color: #d32f2f; }}
+        .url {{ background: #f5f5f5; padding: 2px 4px; border-radius: 4px; font-family: monospace; }}
+        .source {{ color: #757575; font-size: 0.9em; }}
+    </style>
+</head>
+<body>
+    <div class="container">
+        {}
+        {}
+    </div>
+</body>
+</html>"#,
+        title, heading, list
+    )
+}
@@ -0,0 +1,208 @@
+//! Request and response hooks for embedding the mock server as a library
+//!
+//! Hooks change how a snapshot is replayed from code, without forking the
+//! serve handlers: a [`RequestHook`] sees every request before it is matched
+//! and may rewrite it, and a [`ResponseHook`] sees every response built from
+//! a record before it is sent. Either kind can answer the request itself by
+//! returning [`HookFlow::Respond`], which skips the hooks after it and sends
+//! that response as is.
+//!
+//! Hooks run in the order they were registered with
+//! [`MockServerBuilder::with_hook`](crate::serve::MockServerBuilder::with_hook),
+//! on plain HTTP requests and on requests inside HTTPS tunnels alike.
+//!
+//! ```no_run
+//! use bytes::Bytes;
+//! use hyper::header::HeaderValue;
+//! use webmock_cli::capture::proxy::RequestRecord;
+//! use webmock_cli::serve::hooks::{Hook, HookFlow, RequestInfo, ResponseHook, ResponseParts};
+//! use webmock_cli::serve::MockServerBuilder;
+//! use webmock_cli::storage::Snapshot;
+//!
+//! /// Turn on a feature flag in every JSON response
+//! struct FeatureFlag;
+//!
+//! impl ResponseHook for FeatureFlag {
+//!     fn on_response(
+//!         &self,
+//!         _request: &RequestInfo,
+//!         _record: &RequestRecord,
+//!         response: &mut ResponseParts,
+//!     ) -> HookFlow {
+//!         response
+//!             .headers
+//!             .insert("x-feature-checkout-v2", HeaderValue::from_static("on"));
+//!         if let Ok(serde_json::Value::Object(mut json)) = serde_json::from_slice(&response.body) {
+//!             json.insert("checkoutV2".to_string(), true.into());
+//!             response.body = Bytes::from(serde_json::Value::Object(json).to_string());
+//!         }
+//!         HookFlow::Continue
+//!     }
+//! }
+//!
+//! async fn serve(snapshot: Snapshot) -> webmock_cli::error::Result<()> {
+//!     let server = MockServerBuilder::new(snapshot)
+//!         .with_hook(Hook::response(FeatureFlag))
+//!         .build();
+//!     server.start(8080).await
+//! }
+//! ```
+
+use std::net::IpAddr;
+use std::sync::Arc;
+
+use bytes::Bytes;
+use http_body_util::Full;
+use hyper::header::{HeaderValue, CONTENT_LENGTH};
+use hyper::{HeaderMap, Method, Response, StatusCode};
+
+use crate::capture::proxy::RequestRecord;
+
+/// A request as the mock server received it
+///
+/// Request hooks may change any of it before the request is matched, and
+/// response hooks see it as the request hooks left it.
+#[derive(Debug, Clone)]
+pub struct RequestInfo {
+    pub method: Method,
+    /// Full URL, with the tunnel's origin for requests inside an HTTPS tunnel
+    pub url: String,
+    pub headers: HeaderMap,
+    /// The request body, when the server buffered it
+    ///
+    /// Bodies are buffered whenever hooks are registered, up to the same
+    /// limit as for body-aware matchers.
+    pub body: Option<Bytes>,
+    /// Address of the client that sent the request
+    pub client: IpAddr,
+}
+
+/// The response built from a record, as response hooks may change it
+///
+/// `Content-Length` is set from the body once the hooks ran.
+#[derive(Debug, Clone)]
+pub struct ResponseParts {
+    pub status: StatusCode,
+    pub headers: HeaderMap,
+    pub body: Bytes,
+}
+
+/// What happens after a hook returns
+#[derive(Debug)]
+pub enum HookFlow {
+    /// Go on to the next hook, then serve the request as usual
+    Continue,
+    /// Answer the request with this response, skipping later hooks
+    Respond(Response<Full<Bytes>>),
+}
+
+/// Called for every request before it is matched against the snapshot
+pub trait RequestHook: Send + Sync {
+    fn on_request(&self, request: &mut RequestInfo) -> HookFlow;
+}
+
+/// Called for every response built from a matched record, before it is sent
+///
+/// `record` is the record being replayed, after `--overrides` were applied.
+/// The recorded body no longer identifies the response a hook may change, so
+/// `--generate-etags` adds no ETags on servers with response hooks.
+pub trait ResponseHook: Send + Sync {
+    fn on_response(
+        &self,
+        request: &RequestInfo,
+        record: &RequestRecord,
+        response: &mut ResponseParts,
+    ) -> HookFlow;
+}
+
+/// A hook registered with a mock server
+#[derive(Clone)]
+pub enum Hook {
+    Request(Arc<dyn RequestHook>),
+    Response(Arc<dyn ResponseHook>),
+}
+
+impl Hook {
+    pub fn request(hook: impl RequestHook + 'static) -> Self {
+        Hook::Request(Arc::new(hook))
+    }
+
+    pub fn response(hook: impl ResponseHook + 'static) -> Self {
+        Hook::Response(Arc::new(hook))
+    }
+}
+
+/// The hooks of a mock server, in registration order
+#[derive(Clone, Default)]
+pub struct ServeHooks {
+    request: Vec<Arc<dyn RequestHook>>,
+    response: Vec<Arc<dyn ResponseHook>>,
+}
+
+impl ServeHooks {
+    pub fn add(&mut self, hook: Hook) {
+        match hook {
+            Hook::Request(hook) => self.request.push(hook),
+            Hook::Response(hook) => self.response.push(hook),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.request.is_empty() && self.response.is_empty()
+    }
+
+    pub fn has_request_hooks(&self) -> bool {
+        !self.request.is_empty()
+    }
+
+    pub fn has_response_hooks(&self) -> bool {
+        !self.response.is_empty()
+    }
+
+    /// Run the request hooks, returning the response one of them answered with
+    pub fn run_request(&self, request: &mut RequestInfo) -> Option<Response<Full<Bytes>>> {
+        self.request
+            .iter()
+            .find_map(|hook| match hook.on_request(request) {
+                HookFlow::Continue => None,
+                HookFlow::Respond(response) => Some(response),
+            })
+    }
+
+    /// Run the response hooks on `response`, built from `record`
+    ///
+    /// Returns true when a hook answered the request itself, in which case
+    /// `response` is that hook's response and should be sent unchanged.
+    pub fn run_response(
+        &self,
+        request: &RequestInfo,
+        record: &RequestRecord,
+        response: &mut Response<Full<Bytes>>,
+    ) -> bool {
+        if self.response.is_empty() {
+            return false;
+        }
+
+        // The built body is the record's, which hyper's `Full` can't hand back
+        let (mut head, _) =
+            std::mem::replace(response, Response::new(Full::new(Bytes::new()))).into_parts();
+        let mut parts = ResponseParts {
+            status: head.status,
+            headers: std::mem::take(&mut head.headers),
+            body: Bytes::from(record.response.body.clone()),
+        };
+        for hook in &self.response {
+            if let HookFlow::Respond(answer) = hook.on_response(request, record, &mut parts) {
+                *response = answer;
+                return true;
+            }
+        }
+
+        head.status = parts.status;
+        head.headers = parts.headers;
+        head.headers
+            .insert(CONTENT_LENGTH, HeaderValue::from(parts.body.len()));
+        *response = Response::from_parts(head, Full::new(parts.body));
+        false
+    }
+}
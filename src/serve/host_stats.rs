@@ -0,0 +1,147 @@
+//! Per-host request counts for the serve shutdown summary
+//!
+//! A page that starts loading from a host the snapshot never saw, such as a
+//! new CDN, shows up as scattered 404 lines among hundreds of others. Counting
+//! hits and misses per host makes such hosts stand out when the server stops.
+
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::RwLock;
+
+use url::Url;
+
+use crate::commands::inspect::format_size;
+
+#[derive(Debug, Default)]
+struct HostCounters {
+    hits: AtomicU64,
+    misses: AtomicU64,
+    bytes: AtomicU64,
+}
+
+/// Hits, misses and bytes served per host
+///
+/// Hosts seen before only take a read lock and atomic adds, so counting
+/// stays cheap on the request path; the write lock is taken once per host.
+#[derive(Debug, Default)]
+pub struct HostStats {
+    hosts: RwLock<HashMap<String, HostCounters>>,
+}
+
+/// Counts for one host, as shown in the summary
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HostSummary {
+    /// `host[:port]`, with the port only when it isn't the scheme's default
+    pub host: String,
+    /// Requests answered from a record
+    pub hits: u64,
+    /// Requests with no matching record
+    pub misses: u64,
+    /// Response body bytes served from records
+    pub bytes: u64,
+}
+
+impl HostSummary {
+    /// Check whether no request to the host was in the snapshot
+    pub fn all_missed(&self) -> bool {
+        self.hits == 0 && self.misses > 0
+    }
+}
+
+impl HostStats {
+    /// Count a request to `url` answered from a record with a `bytes` long body
+    pub fn record_hit(&self, url: &str, bytes: usize) {
+        self.update(url, |counters| {
+            counters.hits.fetch_add(1, Ordering::Relaxed);
+            counters.bytes.fetch_add(bytes as u64, Ordering::Relaxed);
+        });
+    }
+
+    /// Count a request to `url` the snapshot had no record for
+    pub fn record_miss(&self, url: &str) {
+        self.update(url, |counters| {
+            counters.misses.fetch_add(1, Ordering::Relaxed);
+        });
+    }
+
+    /// Counts of every host seen so far, most misses first
+    pub fn summary(&self) -> Vec<HostSummary> {
+        let hosts = self.hosts.read().unwrap_or_else(|e| e.into_inner());
+        let mut summary: Vec<HostSummary> = hosts
+            .iter()
+            .map(|(host, counters)| HostSummary {
+                host: host.clone(),
+                hits: counters.hits.load(Ordering::Relaxed),
+                misses: counters.misses.load(Ordering::Relaxed),
+                bytes: counters.bytes.load(Ordering::Relaxed),
+            })
+            .collect();
+        summary.sort_by(|a, b| {
+            b.misses
+                .cmp(&a.misses)
+                .then(b.hits.cmp(&a.hits))
+                .then_with(|| a.host.cmp(&b.host))
+        });
+        summary
+    }
+
+    fn update(&self, url: &str, count: impl Fn(&HostCounters)) {
+        let host = host_of(url);
+        if let Some(counters) = self
+            .hosts
+            .read()
+            .unwrap_or_else(|e| e.into_inner())
+            .get(&host)
+        {
+            count(counters);
+            return;
+        }
+        let mut hosts = self.hosts.write().unwrap_or_else(|e| e.into_inner());
+        count(hosts.entry(host).or_default());
+    }
+}
+
+/// Host a request URL is counted under
+fn host_of(url: &str) -> String {
+    let Ok(url) = Url::parse(url) else {
+        return "unknown".to_string();
+    };
+    match (url.host_str(), url.port()) {
+        (Some(host), Some(port)) => format!("{}:{}", host, port),
+        (Some(host), None) => host.to_string(),
+        (None, _) => "unknown".to_string(),
+    }
+}
+
+/// Render the summary as a table, flagging hosts the snapshot has nothing for
+pub fn format_host_table(summary: &[HostSummary]) -> String {
+    let width = summary
+        .iter()
+        .map(|host| host.host.len())
+        .max()
+        .unwrap_or(0)
+        .max("HOST".len());
+    let mut table = format!(
+        "{:<width$}  {:>6}  {:>6}  {:>10}\n",
+        "HOST",
+        "HITS",
+        "MISSES",
+        "SERVED",
+        width = width
+    );
+    for host in summary {
+        table.push_str(&format!(
+            "{:<width$}  {:>6}  {:>6}  {:>10}",
+            host.host,
+            host.hits,
+            host.misses,
+            format_size(host.bytes as usize),
+            width = width
+        ));
+        if host.all_missed() {
+            table.push_str("  ← not present in snapshot — consider recapturing");
+        }
+        table.push('\n');
+    }
+    table
+}
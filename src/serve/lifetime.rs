@@ -0,0 +1,113 @@
+//! Automatic shutdown of mock servers that are idle or have run long enough
+//!
+//! A server started in the background by a CI job can outlive the job when
+//! the test suite crashes before stopping it. With `--idle-timeout` the
+//! server stops once no request has arrived for that long, and with
+//! `--max-lifetime` it stops after that long regardless of traffic.
+
+use std::fmt;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, Instant};
+
+/// Why a mock server shut itself down
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ShutdownReason {
+    /// No request arrived within the idle timeout
+    Idle(Duration),
+    /// The server ran for its maximum lifetime
+    MaxLifetime(Duration),
+}
+
+impl fmt::Display for ShutdownReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ShutdownReason::Idle(timeout) => {
+                write!(
+                    f,
+                    "no requests for {}s (--idle-timeout)",
+                    timeout.as_secs_f64()
+                )
+            }
+            ShutdownReason::MaxLifetime(lifetime) => {
+                write!(
+                    f,
+                    "running for {}s (--max-lifetime)",
+                    lifetime.as_secs_f64()
+                )
+            }
+        }
+    }
+}
+
+/// When the last request was handled, updated on every request
+#[derive(Debug)]
+pub struct Activity {
+    started: Instant,
+    /// Milliseconds after `started`, so resetting the idle timer is one store
+    last_request: AtomicU64,
+}
+
+impl Activity {
+    pub fn new() -> Self {
+        Self {
+            started: Instant::now(),
+            last_request: AtomicU64::new(0),
+        }
+    }
+
+    /// Record that a request is being handled now
+    pub fn touch(&self) {
+        let now = self.started.elapsed().as_millis() as u64;
+        self.last_request.fetch_max(now, Ordering::Relaxed);
+    }
+
+    /// Time since the server started
+    pub fn uptime(&self) -> Duration {
+        self.started.elapsed()
+    }
+
+    /// Time since the last request, or since startup if there was none
+    pub fn idle_for(&self) -> Duration {
+        let last = Duration::from_millis(self.last_request.load(Ordering::Relaxed));
+        self.uptime().saturating_sub(last)
+    }
+}
+
+impl Default for Activity {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Wait until the server should shut down, or forever if neither limit is set
+pub async fn expired(
+    activity: &Activity,
+    idle_timeout: Option<Duration>,
+    max_lifetime: Option<Duration>,
+) -> ShutdownReason {
+    loop {
+        let uptime = activity.uptime();
+        let mut wake = None;
+
+        if let Some(lifetime) = max_lifetime {
+            if uptime >= lifetime {
+                return ShutdownReason::MaxLifetime(lifetime);
+            }
+            wake = Some(lifetime - uptime);
+        }
+        if let Some(timeout) = idle_timeout {
+            let idle_for = activity.idle_for();
+            if idle_for >= timeout {
+                return ShutdownReason::Idle(timeout);
+            }
+            // A request in the meantime pushes the deadline back; checked on wake-up
+            let until_idle = timeout - idle_for;
+            wake = Some(wake.map_or(until_idle, |wake: Duration| wake.min(until_idle)));
+        }
+
+        match wake {
+            Some(wake) => tokio::time::sleep(wake).await,
+            None => std::future::pending().await,
+        }
+    }
+}
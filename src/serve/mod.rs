@@ -1,9 +1,8 @@
 #[cfg(test)]
 mod tests;
 
-use std::convert::Infallible;
-use std::net::SocketAddr;
-use std::sync::Arc;
+use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+use std::sync::{Arc, OnceLock};
 
 use bytes::Bytes;
 use http_body_util::Full;
@@ -12,74 +11,386 @@ use hyper::service::service_fn;
 use hyper::{Request, Response};
 use hyper_util::rt::TokioIo;
 use hyper_util::server::conn::auto::Builder;
+use rustls::ServerConfig;
 use tokio::net::TcpListener;
-use tracing::{debug, error, info};
+use tokio::sync::oneshot;
+use tracing::{debug, error, info, Instrument};
 
 use crate::error::{Result, WebMockError};
+use crate::feedback::UserFeedback;
 use crate::storage::Snapshot;
 
+pub mod absolute_urls;
+pub mod body_log;
+pub mod console;
+pub mod coverage;
+pub mod dns;
+mod early_hints;
+pub mod encoding;
+pub mod etags;
+pub mod forward;
 mod handlers;
+pub mod hooks;
+pub mod host_stats;
+pub mod lifetime;
+pub mod options;
+pub mod ordering;
+pub mod origin_map;
+pub mod overrides;
 mod proxy;
-mod tls;
+pub mod rate_limit;
+pub mod redirect_loop;
+pub mod routing;
+pub mod telemetry;
+pub mod throttle;
+pub mod variants;
 
+pub use absolute_urls::{count_absolute_urls, rewrite_absolute_urls};
+use body_log::BodyLog;
+pub use body_log::{BodyLogging, DEFAULT_LOGGED_BODY_BYTES};
+use console::ConsoleWriter;
+pub use console::OutputLevel;
+pub use coverage::{CoverageReport, RecordCoverage, RecordHits};
+use dns::DnsTask;
+pub use dns::{parse_dns_upstream, DnsOptions};
+use early_hints::{InterimIo, InterimQueue};
+pub use encoding::{ContentEncoding, EncodingCache};
+pub use etags::EtagCache;
+pub use forward::ForwardTarget;
+use forward::Forwarder;
+pub use handlers::{
+    find_matching_record, BodyAwareMatcher, DefaultMatcher, ExactUrlMatcher, IncomingRequest,
+    MatchScore, PathOnlyMatcher, QueryInsensitiveMatcher, RequestMatcher,
+};
+use handlers::{is_connection_aborted, ConnectionAborted};
+use hooks::ServeHooks;
+pub use hooks::{Hook, HookFlow, RequestHook, RequestInfo, ResponseHook, ResponseParts};
+pub use host_stats::{HostStats, HostSummary};
+use lifetime::Activity;
+pub use lifetime::ShutdownReason;
+pub use options::{
+    parse_method_fallback, parse_replay_failures, MethodMatching, OpenMode, ReplayFailures,
+    ServeOptions,
+};
+pub use ordering::{DependencyRules, OrderCheck, OrderingGuard};
+pub use origin_map::OriginMap;
+pub use overrides::{ResponseOverride, ResponseOverrides};
 use proxy::ProxyHandler;
+pub use rate_limit::{RateCheck, RateLimiter};
+pub use redirect_loop::{RedirectLoopGuard, DEFAULT_REDIRECT_LOOP_LIMIT};
+pub use routing::{SnapshotRouter, DEFAULT_MAX_LOADED_SNAPSHOTS, ROUTE_PREFIX};
+use throttle::throttle_response;
+pub use throttle::{Throttle, ThrottleProfile};
+pub use variants::{select_variant, snapshot_variants};
+
+/// State shared by every connection of a running mock server
+pub(crate) struct ServeState {
+    pub snapshot: Snapshot,
+    pub options: ServeOptions,
+    pub console: Arc<ConsoleWriter>,
+    pub redirect_loops: RedirectLoopGuard,
+    /// Records served so far, with `--stateful`
+    pub ordering: Option<OrderingGuard>,
+    /// Requests per client to endpoints captured answering 429, with `--simulate-rate-limits`
+    pub rate_limits: Option<RateLimiter>,
+    pub matcher: Arc<dyn RequestMatcher + Send + Sync>,
+    /// Compressed variants of record bodies served with `--encode`
+    pub encoded: EncodingCache,
+    /// Body ETags of records served with `--generate-etags`
+    pub etags: EtagCache,
+    /// Client for `--forward-unmatched`
+    pub forwarder: Option<Forwarder>,
+    /// Hits and misses per host, for the shutdown summary
+    pub hosts: HostStats,
+    /// Hits per record, for `--coverage`
+    pub coverage: RecordHits,
+    /// Request and response bodies, with `--log-bodies`
+    pub body_log: Option<Arc<BodyLog>>,
+    /// Hooks registered by library users with [`MockServerBuilder::with_hook`]
+    pub hooks: ServeHooks,
+    /// Built on the first tunnel so per-host certificates are reused
+    tls: OnceLock<Arc<ServerConfig>>,
+}
+
+impl ServeState {
+    pub fn new(
+        mut snapshot: Snapshot,
+        options: ServeOptions,
+        console: Arc<ConsoleWriter>,
+        matcher: Arc<dyn RequestMatcher + Send + Sync>,
+    ) -> Self {
+        select_variant(&mut snapshot, options.variant.as_deref());
+        if options.rewrite_absolute_urls {
+            rewrite_absolute_urls(&mut snapshot);
+        }
+        let redirect_loops = RedirectLoopGuard::new(options.redirect_loop_limit);
+        let ordering = options.stateful.then(|| {
+            OrderingGuard::new(&snapshot.requests, options.overrides.dependencies().clone())
+        });
+        let rate_limits = options
+            .simulate_rate_limits
+            .then(|| RateLimiter::new(&snapshot.requests));
+        let etags = if options.generate_etags {
+            EtagCache::new(snapshot.requests.len())
+        } else {
+            EtagCache::default()
+        };
+        let forwarder = options
+            .forward_unmatched
+            .clone()
+            .map(|target| Forwarder::new(target, options.forward_timeout));
+        let coverage = RecordHits::new(snapshot.requests.len());
+        Self {
+            snapshot,
+            options,
+            console,
+            redirect_loops,
+            ordering,
+            rate_limits,
+            matcher,
+            encoded: EncodingCache::default(),
+            etags,
+            forwarder,
+            hosts: HostStats::default(),
+            coverage,
+            body_log: None,
+            hooks: ServeHooks::default(),
+            tls: OnceLock::new(),
+        }
+    }
+
+    /// Log bodies to the given log, shared with the other snapshots of a router
+    pub fn with_body_log(mut self, body_log: Option<Arc<BodyLog>>) -> Self {
+        self.body_log = body_log;
+        self
+    }
+
+    /// Run the given hooks on the requests and responses of this snapshot
+    pub fn with_hooks(mut self, hooks: ServeHooks) -> Self {
+        self.hooks = hooks;
+        self
+    }
+
+    /// TLS config for terminating tunnels, shared by every tunnel of this server
+    pub fn tls_config(
+        &self,
+    ) -> std::result::Result<Arc<ServerConfig>, Box<dyn std::error::Error + Send + Sync>> {
+        if let Some(config) = self.tls.get() {
+            return Ok(Arc::clone(config));
+        }
+        let config = crate::tls::server_config()?;
+        Ok(Arc::clone(self.tls.get_or_init(|| config)))
+    }
+}
+
+/// Builds a mock server for one snapshot, with library-only settings such as hooks
+pub struct MockServerBuilder {
+    snapshot: Snapshot,
+    options: ServeOptions,
+    matcher: Box<dyn RequestMatcher + Send + Sync>,
+    hooks: ServeHooks,
+}
+
+impl MockServerBuilder {
+    /// Start with default options and matcher, and no hooks
+    pub fn new(snapshot: Snapshot) -> Self {
+        Self {
+            snapshot,
+            options: ServeOptions::default(),
+            matcher: Box::new(DefaultMatcher),
+            hooks: ServeHooks::default(),
+        }
+    }
+
+    pub fn with_options(mut self, options: ServeOptions) -> Self {
+        self.options = options;
+        self
+    }
+
+    /// Look up recorded responses with a custom matcher
+    pub fn with_matcher(mut self, matcher: Box<dyn RequestMatcher + Send + Sync>) -> Self {
+        self.matcher = matcher;
+        self
+    }
+
+    /// Register a hook, run after the hooks of its kind registered before it
+    pub fn with_hook(mut self, hook: Hook) -> Self {
+        self.hooks.add(hook);
+        self
+    }
+
+    pub fn build(self) -> MockServer {
+        info!("Creating mock server for snapshot: {}", self.snapshot.name);
+        let console = Arc::new(ConsoleWriter::stdout(self.options.output));
+        let body_log = self
+            .options
+            .log_bodies
+            .clone()
+            .map(|settings| Arc::new(BodyLog::stderr(settings)));
+        let state = ServeState::new(
+            self.snapshot,
+            self.options,
+            console,
+            Arc::from(self.matcher),
+        )
+        .with_body_log(body_log)
+        .with_hooks(self.hooks);
+        MockServer {
+            target: ServeTarget::Snapshot(Arc::new(state)),
+            shutdown_reason: OnceLock::new(),
+        }
+    }
+}
+
+/// What a mock server answers requests from
+#[derive(Clone)]
+enum ServeTarget {
+    /// One snapshot, used as an HTTP(S) proxy
+    Snapshot(Arc<ServeState>),
+    /// Every snapshot in a storage directory, under `/_s/<name>/` paths
+    Routed(Arc<SnapshotRouter>),
+}
+
+impl ServeTarget {
+    fn options(&self) -> &ServeOptions {
+        match self {
+            ServeTarget::Snapshot(state) => &state.options,
+            ServeTarget::Routed(router) => router.options(),
+        }
+    }
+}
 
 pub struct MockServer {
-    snapshot: Arc<Snapshot>,
+    target: ServeTarget,
+    /// Set once the server stopped itself because of `--idle-timeout` or `--max-lifetime`
+    shutdown_reason: OnceLock<ShutdownReason>,
 }
 
 impl MockServer {
     pub fn new(snapshot: Snapshot) -> Self {
-        info!("Creating mock server for snapshot: {}", snapshot.name);
+        Self::with_options(snapshot, ServeOptions::default())
+    }
+
+    /// Create a mock server with custom replay options
+    pub fn with_options(snapshot: Snapshot, options: ServeOptions) -> Self {
+        Self::with_matcher(snapshot, options, Box::new(DefaultMatcher))
+    }
+
+    /// Create a mock server that looks up recorded responses with a custom matcher
+    pub fn with_matcher(
+        snapshot: Snapshot,
+        options: ServeOptions,
+        matcher: Box<dyn RequestMatcher + Send + Sync>,
+    ) -> Self {
+        MockServerBuilder::new(snapshot)
+            .with_options(options)
+            .with_matcher(matcher)
+            .build()
+    }
+
+    /// Create a mock server that serves every snapshot of a router by path prefix
+    pub fn with_router(router: SnapshotRouter) -> Self {
         Self {
-            snapshot: Arc::new(snapshot),
+            target: ServeTarget::Routed(Arc::new(router)),
+            shutdown_reason: OnceLock::new(),
         }
     }
 
-    pub async fn start(&self, port: u16) -> Result<()> {
-        info!("Starting mock server on port: {}", port);
+    /// The snapshot being served, unless snapshots are routed by path
+    pub fn snapshot(&self) -> Option<&Snapshot> {
+        match &self.target {
+            ServeTarget::Snapshot(state) => Some(&state.snapshot),
+            ServeTarget::Routed(_) => None,
+        }
+    }
 
-        let addr = SocketAddr::from(([127, 0, 0, 1], port));
-        let snapshot = Arc::clone(&self.snapshot);
+    /// The snapshot router, when snapshots are routed by path
+    pub fn router(&self) -> Option<&SnapshotRouter> {
+        match &self.target {
+            ServeTarget::Snapshot(_) => None,
+            ServeTarget::Routed(router) => Some(router),
+        }
+    }
 
-        // Report successful startup
-        println!("✅ Mock proxy server started successfully!");
-        println!("   📡 Proxy listening on: http://{}", addr);
-        println!(
-            "   📦 Snapshot: {} ({} requests)",
-            self.snapshot.name,
-            self.snapshot.requests.len()
-        );
-        println!("   🌍 Original URL: {}", self.snapshot.url);
-        println!(
-            "   🔧 HTTP Usage: curl -x http://{} http://www.baidu.com/",
-            addr
-        );
-        println!(
-            "   🔐 HTTPS Usage: curl -x http://{} https://www.baidu.com/ --insecure",
-            addr
-        );
-        println!("   ⚠️  Use --insecure/-k with curl due to self-signed certificate");
-        println!(
-            "   💡 Alternative: curl -x http://{} https://www.baidu.com/ -k",
-            addr
-        );
+    /// Wait until all queued request lines and body log entries have been printed
+    pub async fn flush_console(&self) {
+        let body_log = match &self.target {
+            ServeTarget::Snapshot(state) => {
+                state.console.flush().await;
+                state.body_log.as_deref()
+            }
+            ServeTarget::Routed(router) => {
+                router.console().flush().await;
+                router.body_log()
+            }
+        };
+        if let Some(body_log) = body_log {
+            body_log.flush().await;
+        }
+    }
 
-        // Debug: show first few URLs in snapshot
-        println!("   📋 Sample URLs in snapshot:");
-        for (i, request) in self.snapshot.requests.iter().take(5).enumerate() {
-            println!("      {}. {} {}", i + 1, request.method, request.url);
+    /// Requests served per host so far, most misses first
+    ///
+    /// Path-routed servers keep no per-host counts and return `None`.
+    pub fn host_summary(&self) -> Option<Vec<HostSummary>> {
+        match &self.target {
+            ServeTarget::Snapshot(state) => Some(state.hosts.summary()),
+            ServeTarget::Routed(_) => None,
         }
-        if self.snapshot.requests.len() > 5 {
-            println!("      ... and {} more", self.snapshot.requests.len() - 5);
+    }
+
+    /// How often each record was served so far, for the snapshot file hashing to `sha256`
+    ///
+    /// Path-routed servers keep no per-record counts and return `None`.
+    pub fn coverage_report(&self, sha256: &str) -> Option<CoverageReport> {
+        match &self.target {
+            ServeTarget::Snapshot(state) => Some(state.coverage.report(&state.snapshot, sha256)),
+            ServeTarget::Routed(_) => None,
         }
-        println!();
+    }
 
-        info!("Mock proxy server running on http://{}", addr);
-        info!(
-            "Serving snapshot: {} (captured from: {})",
-            self.snapshot.name, self.snapshot.url
-        );
+    /// Why the server stopped itself, if it returned because a lifetime limit was reached
+    pub fn shutdown_reason(&self) -> Option<ShutdownReason> {
+        self.shutdown_reason.get().copied()
+    }
+
+    pub async fn start(&self, port: u16) -> Result<()> {
+        self.run(port, None).await
+    }
+
+    /// Start the server, sending the bound address once it accepts connections
+    pub async fn start_with_ready(
+        &self,
+        port: u16,
+        ready: oneshot::Sender<SocketAddr>,
+    ) -> Result<()> {
+        self.run(port, Some(ready)).await
+    }
+
+    async fn run(&self, port: u16, ready: Option<oneshot::Sender<SocketAddr>>) -> Result<()> {
+        info!("Starting mock server on port: {}", port);
+
+        // Devices resolving snapshot hosts through the DNS responder connect from the network
+        let options = self.target.options();
+        let host = if options.dns.is_some() {
+            Ipv4Addr::UNSPECIFIED
+        } else {
+            Ipv4Addr::LOCALHOST
+        };
+        let addr = SocketAddr::from((host, port));
+
+        match &self.target {
+            ServeTarget::Snapshot(state) => {
+                info!("Mock proxy server running on http://{}", addr);
+                info!(
+                    "Serving snapshot: {} (captured from: {})",
+                    state.snapshot.name, state.snapshot.url
+                );
+            }
+            ServeTarget::Routed(_) => {
+                info!("Mock server routing snapshots by path on http://{}", addr);
+            }
+        }
 
         // Bind to the address
         let listener = match TcpListener::bind(addr).await {
@@ -103,33 +414,112 @@ impl MockServer {
             }
         };
 
+        // Report the bound address, which differs from the requested one for port 0
+        let addr = listener.local_addr().unwrap_or(addr);
+        if let ServeTarget::Snapshot(state) = &self.target {
+            if state.options.banner {
+                Self::print_banner(&state.snapshot, addr);
+            }
+        }
+
+        // Dropped with this future, which stops the responder with the server
+        let _dns = match (&self.target, &options.dns) {
+            (ServeTarget::Snapshot(state), Some(dns)) => {
+                let task = DnsTask::start(dns, &state.snapshot, Arc::clone(&state.console)).await?;
+                if state.options.banner {
+                    UserFeedback::line(&format!(
+                        "   🧭 DNS: snapshot hosts resolve to {} on udp://{}",
+                        task.address(),
+                        task.local_addr()
+                    ));
+                }
+                Some(task)
+            }
+            _ => None,
+        };
+
+        if let Some(ready) = ready {
+            // The receiver may have gone away if nobody is waiting for startup
+            let _ = ready.send(addr);
+        }
+
+        let activity = Arc::new(Activity::new());
+        let expired = lifetime::expired(&activity, options.idle_timeout, options.max_lifetime);
+        tokio::pin!(expired);
+
         // Accept connections
         loop {
-            match listener.accept().await {
-                Ok((stream, _)) => {
-                    let snapshot = Arc::clone(&snapshot);
-                    tokio::spawn(async move {
-                        let io = TokioIo::new(stream);
-                        let service = service_fn(move |req| {
-                            let snapshot = Arc::clone(&snapshot);
-                            Self::handle_request_internal(snapshot, req)
-                        });
-
-                        let builder = Builder::new(hyper_util::rt::TokioExecutor::new());
-                        // Use serve_connection_with_upgrades to support CONNECT tunneling
-                        if let Err(e) = builder.serve_connection_with_upgrades(io, service).await {
-                            // Only log actual errors, not normal connection closures
-                            let error_str = e.to_string();
-                            if !error_str.contains("connection closed")
-                                && !error_str.contains("broken pipe")
-                                && !error_str.contains("Connection reset by peer")
+            let accepted = tokio::select! {
+                accepted = listener.accept() => accepted,
+                reason = &mut expired => {
+                    // Dropping the listener releases the port right away
+                    info!("Shutting down mock server on {}: {}", addr, reason);
+                    let _ = self.shutdown_reason.set(reason);
+                    return Ok(());
+                }
+            };
+            match accepted {
+                Ok((stream, peer)) => {
+                    let target = self.target.clone();
+                    let activity = Arc::clone(&activity);
+                    let throttle = options.throttle.as_ref().map(Throttle::connection);
+                    let span = telemetry::connection_span(peer);
+                    tokio::spawn(
+                        async move {
+                            let interim = InterimQueue::default();
+                            let io = TokioIo::new(InterimIo::new(stream, interim.clone()));
+                            let service = service_fn(move |req: Request<Incoming>| {
+                                let target = target.clone();
+                                let activity = Arc::clone(&activity);
+                                let throttle = throttle.clone();
+                                let interim = interim.clone();
+                                let version = req.version();
+                                let span = telemetry::request_span(req.method(), req.uri());
+                                activity.touch();
+                                async move {
+                                    let mut response = telemetry::traced(span, async {
+                                        let response = match target {
+                                            ServeTarget::Snapshot(state) => {
+                                                Self::handle_request_internal(state, req, peer.ip())
+                                                    .await
+                                            }
+                                            ServeTarget::Routed(router) => {
+                                                router.handle(req, peer.ip()).await
+                                            }
+                                        };
+                                        // Slow responses don't count as idle time
+                                        activity.touch();
+                                        response
+                                    })
+                                    .await;
+                                    if let Ok(response) = &mut response {
+                                        interim.take_from(version, response);
+                                    }
+                                    throttle_response(throttle.as_ref(), response).await
+                                }
+                            });
+
+                            let builder = Builder::new(hyper_util::rt::TokioExecutor::new());
+                            // Use serve_connection_with_upgrades to support CONNECT tunneling
+                            if let Err(e) =
+                                builder.serve_connection_with_upgrades(io, service).await
                             {
-                                error!("Connection error: {}", e);
-                            } else {
-                                debug!("Connection closed normally: {}", e);
+                                // Only log actual errors, not normal connection closures
+                                let error_str = e.to_string();
+                                if is_connection_aborted(e.as_ref()) {
+                                    debug!("Connection dropped to replay a capture failure");
+                                } else if !error_str.contains("connection closed")
+                                    && !error_str.contains("broken pipe")
+                                    && !error_str.contains("Connection reset by peer")
+                                {
+                                    error!("Connection error: {}", e);
+                                } else {
+                                    debug!("Connection closed normally: {}", e);
+                                }
                             }
                         }
-                    });
+                        .instrument(span),
+                    );
                 }
                 Err(e) => {
                     error!("Accept error: {}", e);
@@ -139,10 +529,54 @@ impl MockServer {
         }
     }
 
-    async fn handle_request_internal(
-        snapshot: Arc<Snapshot>,
+    /// Print the startup banner with usage hints and sample URLs
+    fn print_banner(snapshot: &Snapshot, addr: SocketAddr) {
+        UserFeedback::success("Mock proxy server started successfully!");
+        UserFeedback::line(&format!("   📡 Proxy listening on: http://{}", addr));
+        UserFeedback::line(&format!(
+            "   📦 Snapshot: {} ({} requests)",
+            snapshot.name,
+            snapshot.requests.len()
+        ));
+        UserFeedback::line(&format!("   🌍 Original URL: {}", snapshot.url));
+        UserFeedback::line(&format!(
+            "   🔧 HTTP Usage: curl -x http://{} http://www.baidu.com/",
+            addr
+        ));
+        UserFeedback::line(&format!(
+            "   🔐 HTTPS Usage: curl -x http://{} https://www.baidu.com/ --insecure",
+            addr
+        ));
+        UserFeedback::line("   ⚠️  Use --insecure/-k with curl due to self-signed certificate");
+        UserFeedback::line(&format!(
+            "   💡 Alternative: curl -x http://{} https://www.baidu.com/ -k",
+            addr
+        ));
+
+        // Debug: show first few URLs in snapshot
+        UserFeedback::line("   📋 Sample URLs in snapshot:");
+        for (i, request) in snapshot.requests.iter().take(5).enumerate() {
+            UserFeedback::line(&format!(
+                "      {}. {} {}",
+                i + 1,
+                request.method,
+                request.url
+            ));
+        }
+        if snapshot.requests.len() > 5 {
+            UserFeedback::line(&format!(
+                "      ... and {} more",
+                snapshot.requests.len() - 5
+            ));
+        }
+        UserFeedback::line("");
+    }
+
+    pub(crate) async fn handle_request_internal(
+        state: Arc<ServeState>,
         req: Request<Incoming>,
-    ) -> std::result::Result<Response<Full<Bytes>>, Infallible> {
+        client: IpAddr,
+    ) -> std::result::Result<Response<Full<Bytes>>, ConnectionAborted> {
         let method = req.method().clone();
 
         // Route to appropriate handler
@@ -153,9 +587,9 @@ impl MockServer {
                 .map(|auth| auth.to_string())
                 .unwrap_or_else(|| "unknown:443".to_string());
 
-            ProxyHandler::handle_connect_request(snapshot, req, host_port).await
+            ProxyHandler::handle_connect_request(state, req, host_port, client).await
         } else {
-            ProxyHandler::handle_http_request(snapshot, req).await
+            ProxyHandler::handle_http_request(state, req, client).await
         }
     }
 }
@@ -0,0 +1,227 @@
+use std::path::PathBuf;
+use std::time::Duration;
+
+use hyper::Method;
+
+use super::body_log::BodyLogging;
+use super::console::OutputLevel;
+use super::dns::DnsOptions;
+use super::encoding::ContentEncoding;
+use super::forward::{ForwardTarget, DEFAULT_FORWARD_TIMEOUT};
+use super::origin_map::OriginMap;
+use super::overrides::ResponseOverrides;
+use super::redirect_loop::DEFAULT_REDIRECT_LOOP_LIMIT;
+use super::throttle::Throttle;
+use crate::error::{Result, WebMockError};
+
+/// How long `as-timeout` failure replay stalls before dropping the connection
+pub const DEFAULT_FAILURE_STALL: Duration = Duration::from_secs(30);
+
+/// Browser to launch once the mock server is listening
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OpenMode {
+    /// Open the snapshot entry URL on localhost in the default browser
+    Default,
+    /// Launch Chrome with the mock server configured as its proxy
+    ChromeProxy,
+}
+
+/// How requests that failed during capture are replayed
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ReplayFailures {
+    /// Answer with 502 Bad Gateway and the recorded failure reason
+    #[default]
+    BadGateway,
+    /// Stall, then close the connection without a response
+    AsTimeout,
+}
+
+impl ReplayFailures {
+    /// Name of the mode as given to `--replay-failures`
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ReplayFailures::BadGateway => "bad-gateway",
+            ReplayFailures::AsTimeout => "as-timeout",
+        }
+    }
+}
+
+/// Parse a `--replay-failures` mode, `bad-gateway` or `as-timeout`
+pub fn parse_replay_failures(value: &str) -> Result<ReplayFailures> {
+    match value.trim().to_ascii_lowercase().as_str() {
+        "bad-gateway" => Ok(ReplayFailures::BadGateway),
+        "as-timeout" => Ok(ReplayFailures::AsTimeout),
+        _ => Err(WebMockError::config(format!(
+            "Invalid replay failures mode '{}': use bad-gateway or as-timeout",
+            value
+        ))),
+    }
+}
+
+/// How request methods are resolved when looking up recorded responses
+#[derive(Debug, Clone, Default)]
+pub struct MethodMatching {
+    /// Alternative method tried when the request method has no record, e.g. PATCH → PUT
+    pub fallbacks: Vec<(Method, Method)>,
+    /// Match on the `X-HTTP-Method-Override` header instead of the request method
+    pub honor_override_header: bool,
+}
+
+impl MethodMatching {
+    /// Get the fallback method configured for `method`
+    pub fn fallback_for(&self, method: &Method) -> Option<&Method> {
+        self.fallbacks
+            .iter()
+            .find(|(from, _)| from == method)
+            .map(|(_, to)| to)
+    }
+}
+
+/// Parse a method fallback list such as `PATCH=PUT,DELETE=POST`
+pub fn parse_method_fallback(spec: &str) -> Result<Vec<(Method, Method)>> {
+    let parse_method = |value: &str| {
+        let value = value.trim().to_ascii_uppercase();
+        match Method::from_bytes(value.as_bytes()) {
+            Ok(Method::CONNECT) | Err(_) => Err(WebMockError::config(format!(
+                "Invalid method '{}' in --method-fallback",
+                value
+            ))),
+            Ok(method) => Ok(method),
+        }
+    };
+
+    let mut fallbacks: Vec<(Method, Method)> = Vec::new();
+    for pair in spec.split(',').filter(|pair| !pair.trim().is_empty()) {
+        let (from, to) = pair.split_once('=').ok_or_else(|| {
+            WebMockError::config(format!(
+                "Invalid method fallback '{}': expected FROM=TO, e.g. PATCH=PUT",
+                pair.trim()
+            ))
+        })?;
+        let (from, to) = (parse_method(from)?, parse_method(to)?);
+
+        if from == to {
+            return Err(WebMockError::config(format!(
+                "Method fallback '{}' maps a method to itself",
+                pair.trim()
+            )));
+        }
+        if fallbacks.iter().any(|(existing, _)| *existing == from) {
+            return Err(WebMockError::config(format!(
+                "Method {} has more than one fallback",
+                from
+            )));
+        }
+        fallbacks.push((from, to));
+    }
+
+    if fallbacks.is_empty() {
+        return Err(WebMockError::config(
+            "--method-fallback needs at least one FROM=TO pair",
+        ));
+    }
+
+    Ok(fallbacks)
+}
+
+/// Options controlling how a snapshot is served
+#[derive(Debug, Clone)]
+pub struct ServeOptions {
+    /// Browser to open after the listener is bound, if any
+    pub open: Option<OpenMode>,
+    /// Replay behavior for records that failed during capture
+    pub replay_failures: ReplayFailures,
+    /// How long to stall before dropping the connection in `as-timeout` mode
+    pub failure_stall: Duration,
+    /// How much is printed per request
+    pub output: OutputLevel,
+    /// Print the startup banner with usage hints
+    pub banner: bool,
+    /// Response overrides applied after a request is matched
+    pub overrides: ResponseOverrides,
+    /// Method fallbacks and override header handling for matching
+    pub methods: MethodMatching,
+    /// Times one redirect is served to a client in a burst before 508; 0 disables
+    pub redirect_loop_limit: u32,
+    /// Answer CONNECTs to hosts missing from the snapshot with 502 instead of a tunnel
+    pub strict_connect: bool,
+    /// Guess a content type for records captured without one
+    pub sniff_content_type: bool,
+    /// Encodings text-like bodies are compressed with on request, in order of preference
+    pub encodings: Vec<ContentEncoding>,
+    /// Upstream origins plain requests are matched as, for use as a reverse proxy
+    pub origin_map: OriginMap,
+    /// Serve absolute URLs on the snapshot's entry origins as paths, for direct serving
+    pub rewrite_absolute_urls: bool,
+    /// Shut down once no request has arrived for this long
+    pub idle_timeout: Option<Duration>,
+    /// Shut down after running this long, even while serving requests
+    pub max_lifetime: Option<Duration>,
+    /// File holding the server's PID while it runs, for `webmock stop`
+    pub pid_file: Option<PathBuf>,
+    /// File or directory the server's snapshot, port and PID are written to once it listens
+    pub port_file: Option<PathBuf>,
+    /// File how often each record was served is written to as JSON when the server stops
+    pub coverage: Option<PathBuf>,
+    /// Serve even when another server of the same storage serves the snapshot, without asking
+    pub allow_duplicate: bool,
+    /// Refuse records captured after an earlier record they depend on that wasn't served yet
+    pub stateful: bool,
+    /// Answer a captured 429 to clients requesting its endpoint faster than its `Retry-After` allows
+    pub simulate_rate_limits: bool,
+    /// Answer GETs for a favicon, robots.txt or apple-touch icons missing from the snapshot
+    pub synthesize_common_assets: bool,
+    /// Tag records captured without `etag` or `last-modified` and answer `If-None-Match` with 304
+    pub generate_etags: bool,
+    /// Send the 1xx responses captured before a record's response, e.g. `103 Early Hints`, to HTTP/1.1 clients
+    pub replay_early_hints: bool,
+    /// Live backend requests without a matching record are forwarded to, instead of 404
+    pub forward_unmatched: Option<ForwardTarget>,
+    /// How long a forwarded request may take before it's answered with 502
+    pub forward_timeout: Duration,
+    /// Latency and download rate responses are slowed to, per connection or shared
+    pub throttle: Option<Throttle>,
+    /// Capture variant whose records are matched first; only untagged records without one
+    pub variant: Option<String>,
+    /// Log the start of each request and response body to stderr, redacted
+    pub log_bodies: Option<BodyLogging>,
+    /// Answer DNS queries for the snapshot's hosts with the server's address
+    pub dns: Option<DnsOptions>,
+}
+
+impl Default for ServeOptions {
+    fn default() -> Self {
+        Self {
+            open: None,
+            replay_failures: ReplayFailures::default(),
+            failure_stall: DEFAULT_FAILURE_STALL,
+            output: OutputLevel::default(),
+            banner: true,
+            overrides: ResponseOverrides::default(),
+            methods: MethodMatching::default(),
+            redirect_loop_limit: DEFAULT_REDIRECT_LOOP_LIMIT,
+            strict_connect: false,
+            sniff_content_type: true,
+            encodings: Vec::new(),
+            origin_map: OriginMap::default(),
+            rewrite_absolute_urls: false,
+            idle_timeout: None,
+            max_lifetime: None,
+            pid_file: None,
+            port_file: None,
+            coverage: None,
+            allow_duplicate: false,
+            stateful: false,
+            simulate_rate_limits: false,
+            synthesize_common_assets: true,
+            generate_etags: false,
+            replay_early_hints: false,
+            forward_unmatched: None,
+            forward_timeout: DEFAULT_FORWARD_TIMEOUT,
+            throttle: None,
+            variant: None,
+            log_bodies: None,
+            dns: None,
+        }
+    }
+}
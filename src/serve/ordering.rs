@@ -0,0 +1,221 @@
+//! Replaying stateful flows in the order they were captured
+//!
+//! A captured checkout only makes sense in order: the POST that creates a
+//! cart comes before the GET that reads it. Replay is stateless, so a test
+//! that reads the cart first gets data for a cart the app never created.
+//! With `--stateful` the server remembers which records it has served and
+//! answers 404 for a record while an earlier record it depends on hasn't
+//! been requested yet.
+//!
+//! Records are sequenced by their position in the snapshot, which is the
+//! order they were captured in. By default a record depends on every earlier
+//! POST, PUT, PATCH or DELETE on the same host whose path is a prefix of its
+//! own, so `POST /api/cart` must be requested before `GET /api/cart` and
+//! `GET /api/cart/items`. A `dependencies` section in the overrides file
+//! replaces that default for the records it matches:
+//!
+//! ```yaml
+//! dependencies:
+//!   - method: GET
+//!     url: "https://shop.example/api/orders/*"
+//!     after:
+//!       - method: POST
+//!         url: "https://shop.example/api/checkout"
+//!   - url: "https://shop.example/api/health"
+//!     after: []
+//! ```
+//!
+//! Entries are checked in order and the first one matching a record wins.
+//! Records captured more than once with the same method and URL are served
+//! together, since requests for any of them match the same record.
+
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use globset::GlobMatcher;
+use url::Url;
+
+use crate::capture::proxy::RequestRecord;
+
+/// Records matched by method and URL glob
+#[derive(Debug, Clone)]
+pub struct RecordPattern {
+    method: Option<String>,
+    url: GlobMatcher,
+}
+
+impl RecordPattern {
+    /// Match records with `method` (any if `None`) whose URL matches `url`
+    pub fn new(method: Option<String>, url: GlobMatcher) -> Self {
+        Self { method, url }
+    }
+
+    /// Check whether a record with this method and URL matches
+    pub fn matches(&self, method: &str, url: &str) -> bool {
+        let method_matches = self
+            .method
+            .as_deref()
+            .is_none_or(|expected| expected.eq_ignore_ascii_case(method));
+        method_matches && self.url.is_match(url)
+    }
+}
+
+/// Records that must be served before the records a rule covers
+#[derive(Debug, Clone)]
+pub struct DependencyRule {
+    /// Records the rule applies to
+    pub records: RecordPattern,
+    /// Earlier records they wait for; empty means they never wait
+    pub after: Vec<RecordPattern>,
+}
+
+/// Dependency rules from the overrides file, replacing the default for the records they match
+#[derive(Debug, Clone, Default)]
+pub struct DependencyRules {
+    rules: Vec<DependencyRule>,
+}
+
+impl DependencyRules {
+    pub fn new(rules: Vec<DependencyRule>) -> Self {
+        Self { rules }
+    }
+
+    /// Number of rules
+    pub fn len(&self) -> usize {
+        self.rules.len()
+    }
+
+    /// Check whether no rule is configured
+    pub fn is_empty(&self) -> bool {
+        self.rules.is_empty()
+    }
+
+    fn find(&self, method: &str, url: &str) -> Option<usize> {
+        self.rules
+            .iter()
+            .position(|rule| rule.records.matches(method, url))
+    }
+}
+
+/// Outcome of serving a record in stateful mode
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OrderCheck {
+    /// Serve the record
+    Allowed,
+    /// Refuse the record until the earlier record at this index has been served
+    Waiting { for_index: usize },
+}
+
+#[derive(Debug)]
+struct TrackedRecord {
+    method: String,
+    url: String,
+    host: String,
+    path: String,
+    /// Records with the same method and URL share a group and are served together
+    group: usize,
+    /// First dependency rule covering the record
+    rule: Option<usize>,
+}
+
+impl TrackedRecord {
+    fn changes_state(&self) -> bool {
+        matches!(self.method.as_str(), "POST" | "PUT" | "PATCH" | "DELETE")
+    }
+}
+
+/// Which records have been served, and which earlier records each one waits for
+#[derive(Debug)]
+pub struct OrderingGuard {
+    records: Vec<TrackedRecord>,
+    served: Vec<AtomicBool>,
+    rules: DependencyRules,
+}
+
+impl OrderingGuard {
+    /// Track the records of a snapshot, in the order they were captured
+    pub fn new(records: &[RequestRecord], rules: DependencyRules) -> Self {
+        let mut groups: HashMap<(&str, &str), usize> = HashMap::new();
+        let tracked = records
+            .iter()
+            .map(|record| {
+                let next_group = groups.len();
+                let group = *groups
+                    .entry((record.method.as_str(), record.url.as_str()))
+                    .or_insert(next_group);
+                let (host, path) = match Url::parse(&record.url) {
+                    Ok(url) => (
+                        url.host_str().unwrap_or("").to_string(),
+                        url.path().to_string(),
+                    ),
+                    Err(_) => (String::new(), record.url.clone()),
+                };
+                TrackedRecord {
+                    method: record.method.to_ascii_uppercase(),
+                    url: record.url.clone(),
+                    host,
+                    path,
+                    group,
+                    rule: rules.find(&record.method, &record.url),
+                }
+            })
+            .collect();
+
+        Self {
+            records: tracked,
+            served: (0..groups.len()).map(|_| AtomicBool::new(false)).collect(),
+            rules,
+        }
+    }
+
+    /// Record that `record_index` is about to be served, unless it has to wait
+    ///
+    /// A record that has to wait is not marked as served.
+    pub fn check(&self, record_index: usize) -> OrderCheck {
+        let Some(record) = self.records.get(record_index) else {
+            return OrderCheck::Allowed;
+        };
+
+        let waiting = self.records[..record_index].iter().position(|earlier| {
+            earlier.group != record.group
+                && !self.served[earlier.group].load(Ordering::Relaxed)
+                && self.depends_on(record, earlier)
+        });
+        match waiting {
+            Some(for_index) => OrderCheck::Waiting { for_index },
+            None => {
+                self.served[record.group].store(true, Ordering::Relaxed);
+                OrderCheck::Allowed
+            }
+        }
+    }
+
+    /// Check whether a record has been served, or one with the same method and URL
+    pub fn is_served(&self, record_index: usize) -> bool {
+        self.records
+            .get(record_index)
+            .is_some_and(|record| self.served[record.group].load(Ordering::Relaxed))
+    }
+
+    fn depends_on(&self, record: &TrackedRecord, earlier: &TrackedRecord) -> bool {
+        match record.rule {
+            Some(rule) => self.rules.rules[rule]
+                .after
+                .iter()
+                .any(|pattern| pattern.matches(&earlier.method, &earlier.url)),
+            None => {
+                earlier.changes_state()
+                    && earlier.host == record.host
+                    && is_path_prefix(&earlier.path, &record.path)
+            }
+        }
+    }
+}
+
+/// Check whether `prefix` is `path` or one of its parent paths
+fn is_path_prefix(prefix: &str, path: &str) -> bool {
+    match path.strip_prefix(prefix) {
+        Some(rest) => rest.is_empty() || rest.starts_with('/') || prefix.ends_with('/'),
+        None => false,
+    }
+}
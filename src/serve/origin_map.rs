@@ -0,0 +1,267 @@
+//! Serving a snapshot as if the mock server were the captured site
+//!
+//! `webmock serve --map-origin https://app.example.com` answers plain
+//! (non-proxy) requests such as `GET /cart` on `localhost:8080` as if they
+//! were made to `https://app.example.com/cart`. For snapshots spanning
+//! several hosts, each upstream origin can be given the local origin it is
+//! reached on, e.g. `https://api.example.com=api.localhost:8080`, and is then
+//! picked by the request's `Host` header.
+//!
+//! Absolute `Location` headers pointing at a mapped origin are rewritten to
+//! the local one, and `Domain` attributes of cookies are rewritten to the
+//! local host, so redirects and sessions keep working in the browser.
+
+use bytes::Bytes;
+use http_body_util::Full;
+use hyper::header::{HeaderValue, LOCATION, SET_COOKIE};
+use hyper::Response;
+use tracing::debug;
+use url::Url;
+
+use crate::error::{Result, WebMockError};
+
+/// Host and port requests arrive on, with the scheme links should use
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct LocalOrigin {
+    scheme: String,
+    /// Lowercase `host[:port]`, as sent in the `Host` header
+    authority: String,
+}
+
+impl LocalOrigin {
+    fn parse(spec: &str) -> Option<Self> {
+        let with_scheme = if spec.contains("://") {
+            spec.to_string()
+        } else {
+            format!("http://{}", spec)
+        };
+        let url = Url::parse(&with_scheme).ok()?;
+        if !matches!(url.scheme(), "http" | "https") || url.path() != "/" {
+            return None;
+        }
+        let host = url.host_str()?;
+        let authority = match url.port() {
+            Some(port) => format!("{}:{}", host, port),
+            None => host.to_string(),
+        };
+        Some(Self {
+            scheme: url.scheme().to_string(),
+            authority: authority.to_ascii_lowercase(),
+        })
+    }
+
+    /// The host without its port
+    fn host(&self) -> &str {
+        if self.authority.starts_with('[') {
+            // IPv6 literal
+            return self
+                .authority
+                .split_inclusive(']')
+                .next()
+                .unwrap_or(&self.authority);
+        }
+        self.authority.split(':').next().unwrap_or(&self.authority)
+    }
+
+    fn origin(&self) -> String {
+        format!("{}://{}", self.scheme, self.authority)
+    }
+}
+
+/// An upstream origin and the local origin it is served on
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct OriginMapping {
+    /// Serialized upstream origin, e.g. `https://app.example.com`
+    upstream: String,
+    /// `None` maps every request whose host isn't mapped explicitly
+    local: Option<LocalOrigin>,
+}
+
+/// Upstream origins plain requests are matched against
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct OriginMap {
+    mappings: Vec<OriginMapping>,
+}
+
+impl OriginMap {
+    /// Parse `--map-origin` values of the form `UPSTREAM` or `UPSTREAM=LOCAL`
+    pub fn parse<S: AsRef<str>>(specs: &[S]) -> Result<Self> {
+        let mut map = Self::default();
+        for spec in specs {
+            let spec = spec.as_ref().trim();
+            let (upstream, local) = match spec.split_once('=') {
+                Some((upstream, local)) => (upstream.trim(), Some(local.trim())),
+                None => (spec, None),
+            };
+
+            let upstream_url = Url::parse(upstream)
+                .ok()
+                .filter(|url| matches!(url.scheme(), "http" | "https"))
+                .filter(|url| url.host().is_some() && url.path() == "/")
+                .filter(|url| url.query().is_none())
+                .ok_or_else(|| {
+                    WebMockError::config(format!(
+                        "Invalid origin '{}' in --map-origin, expected e.g. https://app.example.com",
+                        upstream
+                    ))
+                })?;
+            let local = local
+                .map(|local| {
+                    LocalOrigin::parse(local).ok_or_else(|| {
+                        WebMockError::config(format!(
+                            "Invalid local origin '{}' in --map-origin, expected e.g. localhost:8080",
+                            local
+                        ))
+                    })
+                })
+                .transpose()?;
+
+            let clash = map
+                .mappings
+                .iter()
+                .any(|existing| match (&existing.local, &local) {
+                    (None, None) => true,
+                    (Some(a), Some(b)) => a.authority == b.authority,
+                    _ => false,
+                });
+            if clash {
+                return Err(WebMockError::config(match &local {
+                    Some(local) => format!("--map-origin maps {} more than once", local.authority),
+                    None => "Only one --map-origin may omit its local origin".to_string(),
+                }));
+            }
+
+            map.mappings.push(OriginMapping {
+                upstream: upstream_url.origin().ascii_serialization(),
+                local,
+            });
+        }
+        Ok(map)
+    }
+
+    /// Check whether no origins are mapped
+    pub fn is_empty(&self) -> bool {
+        self.mappings.is_empty()
+    }
+
+    /// Map a plain request for `path_and_query` on `host` to its upstream URL
+    ///
+    /// Returns `None` if no mapping covers the host.
+    pub fn resolve(&self, host: Option<&str>, path_and_query: &str) -> Option<MappedRequest<'_>> {
+        let host = host.unwrap_or("localhost").trim().to_ascii_lowercase();
+        let explicit = self
+            .mappings
+            .iter()
+            .find(|mapping| mapping.local.as_ref().is_some_and(|l| l.authority == host));
+        let mapping =
+            explicit.or_else(|| self.mappings.iter().find(|mapping| mapping.local.is_none()))?;
+
+        let local = mapping.local.clone().unwrap_or(LocalOrigin {
+            scheme: "http".to_string(),
+            authority: host,
+        });
+        Some(MappedRequest {
+            map: self,
+            url: format!("{}{}", mapping.upstream, path_and_query),
+            local,
+        })
+    }
+}
+
+/// A plain request matched under a mapped upstream origin
+#[derive(Debug, Clone)]
+pub struct MappedRequest<'a> {
+    map: &'a OriginMap,
+    /// The upstream URL the request is matched as
+    pub url: String,
+    /// Where this request arrived, used for origins mapped without a local one
+    local: LocalOrigin,
+}
+
+impl MappedRequest<'_> {
+    /// Point redirects and cookies of a replayed response back at the local origin
+    pub fn rewrite_response(&self, response: &mut Response<Full<Bytes>>) {
+        let headers = response.headers_mut();
+
+        let location = headers
+            .get(LOCATION)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|location| self.rewrite_location(location))
+            .and_then(|location| HeaderValue::from_str(&location).ok());
+        if let Some(location) = location {
+            headers.insert(LOCATION, location);
+        }
+
+        let cookies: Vec<HeaderValue> = headers.get_all(SET_COOKIE).iter().cloned().collect();
+        if !cookies.is_empty() {
+            headers.remove(SET_COOKIE);
+            for cookie in cookies {
+                let rewritten = cookie
+                    .to_str()
+                    .ok()
+                    .and_then(|cookie| HeaderValue::from_str(&self.rewrite_cookie(cookie)).ok())
+                    .unwrap_or(cookie);
+                headers.append(SET_COOKIE, rewritten);
+            }
+        }
+    }
+
+    /// Rewrite an absolute or protocol-relative URL on a mapped origin to its local origin
+    ///
+    /// Relative locations and other origins are left alone.
+    pub fn rewrite_location(&self, location: &str) -> Option<String> {
+        if !(location.starts_with("http://")
+            || location.starts_with("https://")
+            || location.starts_with("//"))
+        {
+            return None;
+        }
+        let target = Url::parse(&self.url).ok()?.join(location).ok()?;
+        let origin = target.origin().ascii_serialization();
+
+        let mapping = self
+            .map
+            .mappings
+            .iter()
+            .find(|mapping| mapping.upstream == origin)?;
+        let local = mapping.local.as_ref().unwrap_or(&self.local);
+
+        let mut rewritten = format!("{}{}", local.origin(), target.path());
+        if let Some(query) = target.query() {
+            rewritten.push('?');
+            rewritten.push_str(query);
+        }
+        if let Some(fragment) = target.fragment() {
+            rewritten.push('#');
+            rewritten.push_str(fragment);
+        }
+        debug!("Rewrote Location {} to {}", location, rewritten);
+        Some(rewritten)
+    }
+
+    /// Rewrite the `Domain` attribute of a `Set-Cookie` value to the local host
+    ///
+    /// Browsers reject `Domain=localhost` and IP addresses, so the attribute
+    /// is dropped there instead, leaving a host-only cookie.
+    pub fn rewrite_cookie(&self, cookie: &str) -> String {
+        let host = self.local.host();
+        let host_only = host.starts_with('[')
+            || !host.contains('.')
+            || host.parse::<std::net::Ipv4Addr>().is_ok();
+
+        let mut parts: Vec<String> = Vec::new();
+        for (index, part) in cookie.split(';').enumerate() {
+            let is_domain = index > 0
+                && part
+                    .split('=')
+                    .next()
+                    .is_some_and(|name| name.trim().eq_ignore_ascii_case("domain"));
+            if !is_domain {
+                parts.push(part.to_string());
+            } else if !host_only {
+                parts.push(format!(" Domain={}", host));
+            }
+        }
+        parts.join(";")
+    }
+}
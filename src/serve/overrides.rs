@@ -0,0 +1,323 @@
+//! Per-endpoint response overrides loaded from a YAML file
+//!
+//! Overrides let a demo or test flip selected endpoints to a different status,
+//! body or header set without editing the snapshot:
+//!
+//! ```yaml
+//! overrides:
+//!   - method: POST
+//!     url: "https://api.payments.example/*"
+//!     status: 503
+//!     body: '{"error": "unavailable"}'
+//!     headers:
+//!       retry-after: "30"
+//!     remove_headers: [etag]
+//!   - url: "https://cdn.example/*.png"
+//!     body_file: fixtures/placeholder.png
+//!     enabled: false
+//! ```
+//!
+//! Entries are checked in order and the first enabled one matching the request
+//! method and URL wins. `body_file` paths are relative to the overrides file.
+//!
+//! The same file can hold a `dependencies` section for `--stateful` replay,
+//! described in [`crate::serve::ordering`].
+
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+use globset::{Glob, GlobMatcher};
+use hyper::header::{HeaderName, HeaderValue};
+use hyper::StatusCode;
+use serde::Deserialize;
+use tracing::debug;
+
+use super::ordering::{DependencyRule, DependencyRules, RecordPattern};
+use crate::capture::proxy::RequestRecord;
+use crate::error::{Result, WebMockError};
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct OverridesFile {
+    #[serde(default)]
+    overrides: Vec<OverrideEntry>,
+    #[serde(default)]
+    dependencies: Vec<DependencyEntry>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct OverrideEntry {
+    method: Option<String>,
+    url: String,
+    #[serde(default = "enabled_by_default")]
+    enabled: bool,
+    status: Option<u16>,
+    body: Option<String>,
+    body_file: Option<PathBuf>,
+    #[serde(default)]
+    headers: BTreeMap<String, String>,
+    #[serde(default)]
+    remove_headers: Vec<String>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct DependencyEntry {
+    method: Option<String>,
+    url: String,
+    after: Vec<PatternEntry>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct PatternEntry {
+    method: Option<String>,
+    url: String,
+}
+
+fn enabled_by_default() -> bool {
+    true
+}
+
+/// A single validated override rule
+#[derive(Debug, Clone)]
+pub struct ResponseOverride {
+    method: Option<String>,
+    url: GlobMatcher,
+    status: Option<u16>,
+    body: Option<Vec<u8>>,
+    headers: Vec<(String, String)>,
+    remove_headers: Vec<String>,
+}
+
+impl ResponseOverride {
+    /// Check whether this rule applies to the given request
+    pub fn matches(&self, method: &str, url: &str) -> bool {
+        let method_matches = self
+            .method
+            .as_deref()
+            .is_none_or(|expected| expected.eq_ignore_ascii_case(method));
+        method_matches && self.url.is_match(url)
+    }
+
+    /// Get the URL glob this rule was built from
+    pub fn url_pattern(&self) -> &str {
+        self.url.glob().glob()
+    }
+
+    /// Build the record to replay with this rule applied
+    ///
+    /// The result is always a regular response, so an override on a request
+    /// that failed during capture replaces the failure replay.
+    pub fn apply(&self, record: &RequestRecord) -> RequestRecord {
+        let mut record = record.clone();
+        let response = &mut record.response;
+        response.failure = None;
+
+        if let Some(status) = self.status {
+            response.status = status;
+            // The captured phrase belonged to the captured status
+            response.reason = None;
+        }
+        if let Some(body) = &self.body {
+            response.body = body.clone();
+        }
+
+        for name in &self.remove_headers {
+            response
+                .headers
+                .retain(|key, _| !key.eq_ignore_ascii_case(name));
+            if name == "set-cookie" {
+                response.set_cookie.clear();
+            }
+        }
+        for (name, value) in &self.headers {
+            response
+                .headers
+                .retain(|key, _| !key.eq_ignore_ascii_case(name));
+            if name == "set-cookie" {
+                response.set_cookie = vec![value.clone()];
+            }
+            response.headers.insert(name.clone(), value.clone());
+        }
+
+        record
+    }
+}
+
+/// Ordered set of response overrides applied after request matching
+#[derive(Debug, Clone, Default)]
+pub struct ResponseOverrides {
+    rules: Vec<ResponseOverride>,
+    /// Record dependencies for `--stateful` replay
+    dependencies: DependencyRules,
+}
+
+impl ResponseOverrides {
+    /// Load and validate an overrides file
+    pub fn load(path: &Path) -> Result<Self> {
+        let content = std::fs::read_to_string(path).map_err(|e| {
+            WebMockError::config(format!(
+                "Cannot read overrides file {}: {}",
+                path.display(),
+                e
+            ))
+        })?;
+        let base_dir = path.parent().unwrap_or_else(|| Path::new("."));
+
+        Self::parse(&content, base_dir).map_err(|e| match e {
+            WebMockError::Config(msg) => WebMockError::config(format!(
+                "Invalid overrides file {}: {}",
+                path.display(),
+                msg
+            )),
+            other => other,
+        })
+    }
+
+    /// Parse overrides from YAML, resolving `body_file` against `base_dir`
+    pub fn parse(content: &str, base_dir: &Path) -> Result<Self> {
+        let file: OverridesFile =
+            serde_yaml::from_str(content).map_err(|e| WebMockError::config(e.to_string()))?;
+
+        let mut rules = Vec::new();
+        for (index, entry) in file.overrides.into_iter().enumerate() {
+            let position = index + 1;
+            let rule = Self::compile(entry, base_dir)
+                .map_err(|msg| WebMockError::config(format!("entry {}: {}", position, msg)))?;
+            if let Some(rule) = rule {
+                rules.push(rule);
+            }
+        }
+
+        let mut dependencies = Vec::new();
+        for (index, entry) in file.dependencies.into_iter().enumerate() {
+            let position = index + 1;
+            let rule = Self::compile_dependency(entry)
+                .map_err(|msg| WebMockError::config(format!("dependency {}: {}", position, msg)))?;
+            dependencies.push(rule);
+        }
+
+        debug!(
+            "Loaded {} enabled response overrides and {} dependencies",
+            rules.len(),
+            dependencies.len()
+        );
+        Ok(Self {
+            rules,
+            dependencies: DependencyRules::new(dependencies),
+        })
+    }
+
+    fn compile(
+        entry: OverrideEntry,
+        base_dir: &Path,
+    ) -> std::result::Result<Option<ResponseOverride>, String> {
+        // Disabled entries are still validated so typos surface early
+        let url = compile_url(&entry.url)?;
+        let method = compile_method(entry.method)?;
+
+        if let Some(status) = entry.status {
+            StatusCode::from_u16(status).map_err(|_| format!("invalid status {}", status))?;
+        }
+
+        let body = match (entry.body, entry.body_file) {
+            (Some(_), Some(_)) => {
+                return Err("'body' and 'body_file' cannot both be set".to_string());
+            }
+            (Some(body), None) => Some(body.into_bytes()),
+            (None, Some(file)) => {
+                let file = base_dir.join(file);
+                Some(
+                    std::fs::read(&file)
+                        .map_err(|e| format!("cannot read body_file {}: {}", file.display(), e))?,
+                )
+            }
+            (None, None) => None,
+        };
+
+        let mut headers = Vec::new();
+        for (name, value) in entry.headers {
+            HeaderName::from_bytes(name.as_bytes())
+                .map_err(|_| format!("invalid header name '{}'", name))?;
+            HeaderValue::from_str(&value)
+                .map_err(|_| format!("invalid value for header '{}'", name))?;
+            headers.push((name.to_lowercase(), value));
+        }
+
+        let mut remove_headers = Vec::new();
+        for name in entry.remove_headers {
+            HeaderName::from_bytes(name.as_bytes())
+                .map_err(|_| format!("invalid header name '{}'", name))?;
+            remove_headers.push(name.to_lowercase());
+        }
+
+        if !entry.enabled {
+            return Ok(None);
+        }
+
+        Ok(Some(ResponseOverride {
+            method,
+            url,
+            status: entry.status,
+            body,
+            headers,
+            remove_headers,
+        }))
+    }
+
+    fn compile_dependency(entry: DependencyEntry) -> std::result::Result<DependencyRule, String> {
+        let records = RecordPattern::new(compile_method(entry.method)?, compile_url(&entry.url)?);
+        let after = entry
+            .after
+            .into_iter()
+            .map(|pattern| {
+                Ok(RecordPattern::new(
+                    compile_method(pattern.method)?,
+                    compile_url(&pattern.url)?,
+                ))
+            })
+            .collect::<std::result::Result<_, String>>()?;
+        Ok(DependencyRule { records, after })
+    }
+
+    /// Get the record dependencies used by `--stateful` replay
+    pub fn dependencies(&self) -> &DependencyRules {
+        &self.dependencies
+    }
+
+    /// Find the first enabled rule for a request
+    pub fn find(&self, method: &str, url: &str) -> Option<&ResponseOverride> {
+        self.rules.iter().find(|rule| rule.matches(method, url))
+    }
+
+    /// Number of enabled rules
+    pub fn len(&self) -> usize {
+        self.rules.len()
+    }
+
+    /// Check whether no rule is enabled
+    pub fn is_empty(&self) -> bool {
+        self.rules.is_empty()
+    }
+}
+
+fn compile_url(url: &str) -> std::result::Result<GlobMatcher, String> {
+    Ok(Glob::new(url)
+        .map_err(|e| format!("invalid url glob '{}': {}", url, e))?
+        .compile_matcher())
+}
+
+/// Parse a method filter; `*` or no method matches every method
+fn compile_method(method: Option<String>) -> std::result::Result<Option<String>, String> {
+    match method {
+        Some(method) if method.trim() == "*" => Ok(None),
+        Some(method) => Ok(Some(
+            hyper::Method::from_bytes(method.trim().as_bytes())
+                .map_err(|_| format!("invalid method '{}'", method))?
+                .to_string(),
+        )),
+        None => Ok(None),
+    }
+}
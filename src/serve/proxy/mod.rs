@@ -1,22 +1,42 @@
-use std::convert::Infallible;
+use std::borrow::Cow;
+use std::net::IpAddr;
 use std::sync::Arc;
+use std::time::{Duration, Instant};
 
 use bytes::Bytes;
-use http_body_util::Full;
-use hyper::body::Incoming;
+use http_body_util::{BodyExt, Full, Limited};
+use hyper::body::{Body, Incoming};
+use hyper::header::{HeaderValue, ETAG, IF_NONE_MATCH, RETRY_AFTER};
 use hyper::upgrade::Upgraded;
-use hyper::Request;
 use hyper::Response;
 use hyper::StatusCode;
+use hyper::{HeaderMap, Request};
 use hyper_util::rt::TokioIo;
 use tokio_rustls::TlsAcceptor;
-use tracing::{debug, error, info, warn};
+use tracing::{debug, error, info, warn, Instrument};
 
+use crate::capture::proxy::authority::{origin, split_authority};
+use crate::capture::proxy::RequestRecord;
+use crate::serve::console::RequestLine;
+use crate::serve::early_hints::{EarlyHints, InterimIo, InterimQueue};
+use crate::serve::encoding;
+use crate::serve::etags;
 use crate::serve::handlers::{
-    create_404_response, create_response_from_record, find_matching_record,
+    create_404_response, create_failure_response, create_loop_detected_response,
+    create_not_modified_response, create_out_of_order_response, create_pinned_host_response,
+    create_response_from_record, find_matching_record, find_record_with_methods,
+    is_connection_aborted, CommonAsset, ConnectionAborted, IncomingRequest,
 };
-use crate::serve::tls::TlsConfig;
-use crate::storage::Snapshot;
+use crate::serve::hooks::RequestInfo;
+use crate::serve::ordering::OrderCheck;
+use crate::serve::rate_limit::{self, RateCheck};
+use crate::serve::redirect_loop::{is_redirect_status, RedirectCheck};
+use crate::serve::telemetry;
+use crate::serve::throttle::{throttle_response, Throttle};
+use crate::serve::ServeState;
+
+/// Largest request body buffered for matchers that compare bodies
+const MAX_MATCH_BODY_BYTES: usize = 10 * 1024 * 1024;
 
 /// HTTP/HTTPS proxy request handler
 pub struct ProxyHandler;
@@ -24,52 +44,155 @@ pub struct ProxyHandler;
 impl ProxyHandler {
     /// Handle regular HTTP requests through proxy
     pub async fn handle_http_request(
-        snapshot: Arc<Snapshot>,
+        state: Arc<ServeState>,
         req: Request<Incoming>,
-    ) -> std::result::Result<Response<Full<Bytes>>, Infallible> {
+        client: IpAddr,
+    ) -> std::result::Result<Response<Full<Bytes>>, ConnectionAborted> {
+        let started = Instant::now();
         let method = req.method().clone();
         let uri = req.uri().clone();
 
         // For regular HTTP requests through proxy
-        let full_url = {
-            let uri_str = uri.to_string();
-            if uri_str.starts_with("http") {
-                uri_str
-            } else {
-                format!("http://unknown{}", uri_str)
-            }
+        let uri_str = uri.to_string();
+        let mapped = if uri_str.starts_with("http") {
+            None
+        } else {
+            // A plain request, answered as the mapped origin when configured
+            let host = req
+                .headers()
+                .get(hyper::header::HOST)
+                .and_then(|value| value.to_str().ok());
+            state.options.origin_map.resolve(host, &uri_str)
+        };
+        let mut full_url = match &mapped {
+            Some(mapped) => mapped.url.clone(),
+            None if uri_str.starts_with("http") => uri_str,
+            None => format!("http://unknown{}", uri_str),
         };
 
         debug!("Handling HTTP request: {} {}", method, full_url);
+        telemetry::record_url(&full_url);
 
-        // Find matching request record
-        match find_matching_record(&snapshot, &method, &full_url) {
-            Some(record) => {
-                let status_icon = if record.response.status >= 200 && record.response.status < 300 {
-                    "✅"
-                } else if record.response.status >= 400 {
-                    "❌"
-                } else {
-                    "ℹ️"
-                };
+        // Find matching request record, as the request hooks left it
+        let (mut parts, body) = req.into_parts();
+        let mut body = Self::read_body_for_matching(&state, body).await;
+        let hooked = match Self::run_request_hooks(
+            &state,
+            &mut parts,
+            &mut full_url,
+            &mut body,
+            client,
+            started,
+        ) {
+            Ok(hooked) => hooked,
+            Err(response) => return Ok(*response),
+        };
+        let method = parts.method.clone();
+        let request = IncomingRequest::new(method.clone(), &full_url, &parts.headers)
+            .with_body(body.as_deref());
+        match find_record_with_methods(
+            &state.snapshot,
+            state.matcher.as_ref(),
+            &request,
+            &state.options.methods,
+        ) {
+            Some(found) => {
+                telemetry::record_matched(true);
+                let matched = found.record;
+                let index = Self::record_index(&state, matched);
+                if let Some(response) =
+                    Self::check_order(&state, index, &method, &full_url, started)
+                {
+                    return Ok(response);
+                }
+                let (matched, index, retry_after) =
+                    Self::check_rate_limit(&state, matched, index, client, &method, &full_url);
+                let record = Self::apply_override(&state, matched, &method, &full_url);
+                if record.response.is_failure() {
+                    return Self::replay_failure(
+                        &state, &record, index, &method, &full_url, started,
+                    )
+                    .await;
+                }
 
-                println!(
-                    "{} {} {} → {} ({})",
-                    status_icon,
-                    method,
-                    full_url,
-                    record.response.status,
-                    record
-                        .response
-                        .headers
-                        .get("content-type")
-                        .unwrap_or(&"unknown".to_string())
+                let label = Self::method_label(&method, found.matched_method.as_ref());
+                if let Some(response) = Self::check_redirect_loop(
+                    &state, &record, index, client, &label, &full_url, started,
+                ) {
+                    return Ok(response);
+                }
+                Self::log_record(&state, &record, index, &label, &full_url, started);
+                Self::log_bodies(
+                    &state,
+                    &label,
+                    &full_url,
+                    &parts.headers,
+                    body.as_deref(),
+                    Some(&*record),
                 );
 
-                Ok(create_response_from_record(record))
+                // Overridden or hooked records may differ per request, so only originals are cached
+                let cache_key = index.filter(|_| {
+                    matches!(record, Cow::Borrowed(_)) && !state.hooks.has_response_hooks()
+                });
+                let mut response = Self::record_response(&record, cache_key, &state);
+                if let Some(wait) = retry_after {
+                    Self::set_retry_after(&mut response, wait);
+                }
+                if let Some(request) = &hooked {
+                    if state.hooks.run_response(request, &record, &mut response) {
+                        return Ok(response);
+                    }
+                }
+                if let Some(not_modified) =
+                    Self::revalidate(&state, &record, cache_key, &parts, &mut response)
+                {
+                    return Ok(not_modified);
+                }
+                if let Some(mapped) = &mapped {
+                    mapped.rewrite_response(&mut response);
+                }
+                Ok(encoding::encode_response(&state, &parts.headers, response).await)
             }
             None => {
-                println!("❌ {} {} → 404 (not found in snapshot)", method, full_url);
+                if let Some(response) = Self::forward_unmatched(
+                    &state,
+                    &method,
+                    &full_url,
+                    &parts.headers,
+                    body.clone(),
+                    started,
+                )
+                .await
+                {
+                    return Ok(response);
+                }
+                // Stand-ins aren't misses, so they stay out of the 404 log and telemetry
+                if let Some(response) =
+                    Self::synthesize_common_asset(&state, &method, &full_url, started)
+                {
+                    return Ok(response);
+                }
+                telemetry::record_matched(false);
+                state.hosts.record_miss(&full_url);
+                state.console.request(RequestLine {
+                    icon: "❌",
+                    method: method.to_string(),
+                    url: full_url.clone(),
+                    status: 404,
+                    detail: "not found in snapshot".to_string(),
+                    record_index: None,
+                    body_size: None,
+                    started,
+                });
+                Self::log_bodies(
+                    &state,
+                    method.as_str(),
+                    &full_url,
+                    &parts.headers,
+                    body.as_deref(),
+                    None,
+                );
                 warn!("Request not in snapshot: {} {}", method, full_url);
 
                 Ok(create_404_response(&full_url))
@@ -79,33 +202,101 @@ impl ProxyHandler {
 
     /// Handle CONNECT requests for HTTPS tunneling
     pub async fn handle_connect_request(
-        snapshot: Arc<Snapshot>,
+        state: Arc<ServeState>,
         req: Request<Incoming>,
         host_port: String,
-    ) -> std::result::Result<Response<Full<Bytes>>, Infallible> {
+        client: IpAddr,
+    ) -> std::result::Result<Response<Full<Bytes>>, ConnectionAborted> {
+        let started = Instant::now();
         let method = req.method().clone();
 
         debug!("Handling CONNECT request for: {}", host_port);
+        let (host, port) = split_authority(&host_port);
 
-        // Check if we have this CONNECT request in our snapshot
-        let connect_url = format!("https://{}", host_port);
+        // Pinned hosts have a CONNECT record but no traffic to replay
+        let tunnel_host = format!("{}:{}", host, port);
+        if state
+            .snapshot
+            .tunnels
+            .iter()
+            .any(|tunnel| tunnel.mitm_failed && tunnel.host == tunnel_host)
+        {
+            state.console.request(RequestLine {
+                icon: "📌",
+                method: method.to_string(),
+                url: host_port.clone(),
+                status: 502,
+                detail: "certificate-pinned, not recorded during capture".to_string(),
+                record_index: None,
+                body_size: None,
+                started,
+            });
+            warn!("CONNECT to certificate-pinned host: {}", host_port);
+            return Ok(create_pinned_host_response(&host_port));
+        }
 
-        match find_matching_record(&snapshot, &method, &connect_url) {
+        // Check if we have this CONNECT request in our snapshot; requests in
+        // the tunnel are looked up under its origin, as they were recorded
+        let connect_url = origin("https", &host, port);
+
+        let record = find_matching_record(&state.snapshot, &method, &connect_url);
+        telemetry::record_matched(record.is_some());
+        match record {
+            Some(record) if record.response.is_failure() => {
+                let index = Self::record_index(&state, record);
+                Self::replay_failure(&state, record, index, &method, &host_port, started).await
+            }
             Some(record) => {
-                println!(
-                    "✅ CONNECT {} → {} (tunnel established)",
-                    host_port, record.response.status
-                );
+                state.console.request(RequestLine {
+                    icon: "✅",
+                    method: method.to_string(),
+                    url: host_port.clone(),
+                    status: record.response.status,
+                    detail: "tunnel established".to_string(),
+                    record_index: Self::record_index(&state, record),
+                    body_size: None,
+                    started,
+                });
                 info!(
                     "Found CONNECT record, establishing tunnel for: {}",
                     connect_url
                 );
 
                 // Return success response and handle tunnel in background
-                Self::spawn_tunnel_handler(req, snapshot, host_port).await
+                Self::spawn_tunnel_handler(req, state, connect_url, client).await
+            }
+            None if !state.options.strict_connect => {
+                state.console.request(RequestLine {
+                    icon: "🔓",
+                    method: method.to_string(),
+                    url: host_port.clone(),
+                    status: 200,
+                    detail: "not in snapshot, tunneled to serve 404s".to_string(),
+                    record_index: None,
+                    body_size: None,
+                    started,
+                });
+                info!(
+                    "CONNECT not in snapshot, establishing tunnel anyway for: {}",
+                    connect_url
+                );
+
+                // Requests inside resolve through the matcher, usually to 404s,
+                // so one uncaptured host doesn't break the whole page
+                Self::spawn_tunnel_handler(req, state, connect_url, client).await
             }
             None => {
-                println!("❌ CONNECT {} → 502 (not found in snapshot)", host_port);
+                state.hosts.record_miss(&connect_url);
+                state.console.request(RequestLine {
+                    icon: "❌",
+                    method: method.to_string(),
+                    url: host_port.clone(),
+                    status: 502,
+                    detail: "not found in snapshot".to_string(),
+                    record_index: None,
+                    body_size: None,
+                    started,
+                });
                 warn!("CONNECT request not in snapshot: {}", connect_url);
 
                 Ok(Response::builder()
@@ -123,31 +314,35 @@ impl ProxyHandler {
     /// Spawn tunnel handler for HTTPS requests
     async fn spawn_tunnel_handler(
         req: Request<Incoming>,
-        snapshot: Arc<Snapshot>,
-        host_port: String,
-    ) -> std::result::Result<Response<Full<Bytes>>, Infallible> {
+        state: Arc<ServeState>,
+        tunnel_origin: String,
+        client: IpAddr,
+    ) -> std::result::Result<Response<Full<Bytes>>, ConnectionAborted> {
         // Spawn a task to handle the tunnel
-        let snapshot_clone = Arc::clone(&snapshot);
-        let host_port_clone = host_port.clone();
-
-        tokio::spawn(async move {
-            match hyper::upgrade::on(req).await {
-                Ok(upgraded) => {
-                    info!("Tunnel upgraded successfully for: {}", host_port_clone);
-                    if let Err(e) =
-                        Self::handle_https_tunnel(upgraded, snapshot_clone, host_port_clone).await
-                    {
-                        error!("Tunnel error: {}", e);
+        let tunnel_origin_clone = tunnel_origin.clone();
+
+        tokio::spawn(
+            async move {
+                match hyper::upgrade::on(req).await {
+                    Ok(upgraded) => {
+                        info!("Tunnel upgraded successfully for: {}", tunnel_origin_clone);
+                        if let Err(e) =
+                            Self::handle_https_tunnel(upgraded, state, tunnel_origin_clone, client)
+                                .await
+                        {
+                            error!("Tunnel error: {}", e);
+                        }
+                    }
+                    Err(e) => {
+                        error!(
+                            "Failed to upgrade CONNECT tunnel for {}: {}",
+                            tunnel_origin_clone, e
+                        );
                     }
-                }
-                Err(e) => {
-                    error!(
-                        "Failed to upgrade CONNECT tunnel for {}: {}",
-                        host_port_clone, e
-                    );
                 }
             }
-        });
+            .in_current_span(),
+        );
 
         // Return successful CONNECT response
         Ok(Response::builder()
@@ -161,16 +356,17 @@ impl ProxyHandler {
     /// Handle HTTPS tunnel with TLS termination
     async fn handle_https_tunnel(
         upgraded: Upgraded,
-        snapshot: Arc<Snapshot>,
-        host_port: String,
+        state: Arc<ServeState>,
+        tunnel_origin: String,
+        client: IpAddr,
     ) -> std::result::Result<(), Box<dyn std::error::Error + Send + Sync>> {
         info!(
             "Starting HTTPS tunnel with TLS termination for: {}",
-            host_port
+            tunnel_origin
         );
 
         // Generate TLS configuration
-        let tls_config = match TlsConfig::generate_tls_config() {
+        let tls_config = match state.tls_config() {
             Ok(config) => config,
             Err(e) => {
                 error!("Failed to generate TLS config: {}", e);
@@ -184,26 +380,42 @@ impl ProxyHandler {
         // Perform TLS handshake
         match acceptor.accept(io).await {
             Ok(tls_stream) => {
-                info!("TLS handshake completed for: {}", host_port);
+                info!("TLS handshake completed for: {}", tunnel_origin);
 
-                // Create HTTP service for the TLS connection
+                // Create HTTP service for the TLS connection; the tunnel is the
+                // client's connection now, so it gets its own throttle
+                let throttle = state.options.throttle.as_ref().map(Throttle::connection);
+                let interim = InterimQueue::default();
+                let io = TokioIo::new(InterimIo::new(tls_stream, interim.clone()));
                 let service = hyper::service::service_fn(move |req: Request<Incoming>| {
-                    let snapshot = Arc::clone(&snapshot);
-                    let host_port = host_port.clone();
-                    async move { Self::handle_tunneled_request(snapshot, req, host_port).await }
+                    let state = Arc::clone(&state);
+                    let tunnel_origin = tunnel_origin.clone();
+                    let throttle = throttle.clone();
+                    let interim = interim.clone();
+                    let version = req.version();
+                    let span = telemetry::request_span(req.method(), req.uri());
+                    async move {
+                        let mut response = telemetry::traced(span, async move {
+                            Self::handle_tunneled_request(state, req, tunnel_origin, client).await
+                        })
+                        .await;
+                        if let Ok(response) = &mut response {
+                            interim.take_from(version, response);
+                        }
+                        throttle_response(throttle.as_ref(), response).await
+                    }
                 });
 
                 // Serve HTTP over TLS
                 let builder = hyper_util::server::conn::auto::Builder::new(
                     hyper_util::rt::TokioExecutor::new(),
                 );
-                if let Err(e) = builder
-                    .serve_connection(TokioIo::new(tls_stream), service)
-                    .await
-                {
+                if let Err(e) = builder.serve_connection(io, service).await {
                     // Only log actual errors, not normal connection closures
                     let error_str = e.to_string();
-                    if !error_str.contains("connection closed")
+                    if is_connection_aborted(e.as_ref()) {
+                        debug!("TLS tunnel connection dropped to replay a capture failure");
+                    } else if !error_str.contains("connection closed")
                         && !error_str.contains("broken pipe")
                         && !error_str.contains("Connection reset by peer")
                     {
@@ -214,7 +426,7 @@ impl ProxyHandler {
                 }
             }
             Err(e) => {
-                error!("TLS handshake failed for {}: {}", host_port, e);
+                error!("TLS handshake failed for {}: {}", tunnel_origin, e);
                 return Err(Box::new(e));
             }
         }
@@ -224,15 +436,17 @@ impl ProxyHandler {
 
     /// Handle HTTP requests that come through the HTTPS tunnel
     async fn handle_tunneled_request(
-        snapshot: Arc<Snapshot>,
+        state: Arc<ServeState>,
         req: Request<Incoming>,
-        host_port: String,
-    ) -> std::result::Result<Response<Full<Bytes>>, Infallible> {
+        tunnel_origin: String,
+        client: IpAddr,
+    ) -> std::result::Result<Response<Full<Bytes>>, ConnectionAborted> {
+        let started = Instant::now();
         let method = req.method().clone();
         let uri = req.uri().clone();
 
         // Reconstruct the full HTTPS URL
-        let full_url = if uri.to_string().starts_with("http") {
+        let mut full_url = if uri.to_string().starts_with("http") {
             uri.to_string()
         } else {
             // For tunneled requests, reconstruct the full HTTPS URL
@@ -241,43 +455,133 @@ impl ProxyHandler {
             } else {
                 uri.path().to_string()
             };
-            format!("https://{}{}", host_port, path_query)
+            format!("{}{}", tunnel_origin, path_query)
         };
 
         debug!("Handling tunneled request: {} {}", method, full_url);
+        telemetry::record_url(&full_url);
 
-        // Find matching request record
-        match find_matching_record(&snapshot, &method, &full_url) {
-            Some(record) => {
-                let status_icon = if record.response.status >= 200 && record.response.status < 300 {
-                    "✅"
-                } else if record.response.status >= 400 {
-                    "❌"
-                } else {
-                    "ℹ️"
-                };
+        // Find matching request record, as the request hooks left it
+        let (mut parts, body) = req.into_parts();
+        let mut body = Self::read_body_for_matching(&state, body).await;
+        let hooked = match Self::run_request_hooks(
+            &state,
+            &mut parts,
+            &mut full_url,
+            &mut body,
+            client,
+            started,
+        ) {
+            Ok(hooked) => hooked,
+            Err(response) => return Ok(*response),
+        };
+        let method = parts.method.clone();
+        let request = IncomingRequest::new(method.clone(), &full_url, &parts.headers)
+            .with_body(body.as_deref());
+        match find_record_with_methods(
+            &state.snapshot,
+            state.matcher.as_ref(),
+            &request,
+            &state.options.methods,
+        ) {
+            Some(found) => {
+                telemetry::record_matched(true);
+                let matched = found.record;
+                let index = Self::record_index(&state, matched);
+                if let Some(response) =
+                    Self::check_order(&state, index, &method, &full_url, started)
+                {
+                    return Ok(response);
+                }
+                let (matched, index, retry_after) =
+                    Self::check_rate_limit(&state, matched, index, client, &method, &full_url);
+                let record = Self::apply_override(&state, matched, &method, &full_url);
+                if record.response.is_failure() {
+                    return Self::replay_failure(
+                        &state, &record, index, &method, &full_url, started,
+                    )
+                    .await;
+                }
 
-                println!(
-                    "{} {} {} → {} ({})",
-                    status_icon,
-                    method,
-                    full_url,
-                    record.response.status,
-                    record
-                        .response
-                        .headers
-                        .get("content-type")
-                        .unwrap_or(&"unknown".to_string())
+                let label = Self::method_label(&method, found.matched_method.as_ref());
+                if let Some(response) = Self::check_redirect_loop(
+                    &state, &record, index, client, &label, &full_url, started,
+                ) {
+                    return Ok(response);
+                }
+                Self::log_record(&state, &record, index, &label, &full_url, started);
+                Self::log_bodies(
+                    &state,
+                    &label,
+                    &full_url,
+                    &parts.headers,
+                    body.as_deref(),
+                    Some(&*record),
                 );
 
                 info!(
                     "Found matching tunneled record: {} {} → {}",
                     method, full_url, record.response.status
                 );
-                Ok(create_response_from_record(record))
+                // Overridden or hooked records may differ per request, so only originals are cached
+                let cache_key = index.filter(|_| {
+                    matches!(record, Cow::Borrowed(_)) && !state.hooks.has_response_hooks()
+                });
+                let mut response = Self::record_response(&record, cache_key, &state);
+                if let Some(wait) = retry_after {
+                    Self::set_retry_after(&mut response, wait);
+                }
+                if let Some(request) = &hooked {
+                    if state.hooks.run_response(request, &record, &mut response) {
+                        return Ok(response);
+                    }
+                }
+                if let Some(not_modified) =
+                    Self::revalidate(&state, &record, cache_key, &parts, &mut response)
+                {
+                    return Ok(not_modified);
+                }
+                Ok(encoding::encode_response(&state, &parts.headers, response).await)
             }
             None => {
-                println!("❌ {} {} → 404 (not found in snapshot)", method, full_url);
+                if let Some(response) = Self::forward_unmatched(
+                    &state,
+                    &method,
+                    &full_url,
+                    &parts.headers,
+                    body.clone(),
+                    started,
+                )
+                .await
+                {
+                    return Ok(response);
+                }
+                // Stand-ins aren't misses, so they stay out of the 404 log and telemetry
+                if let Some(response) =
+                    Self::synthesize_common_asset(&state, &method, &full_url, started)
+                {
+                    return Ok(response);
+                }
+                telemetry::record_matched(false);
+                state.hosts.record_miss(&full_url);
+                state.console.request(RequestLine {
+                    icon: "❌",
+                    method: method.to_string(),
+                    url: full_url.clone(),
+                    status: 404,
+                    detail: "not found in snapshot".to_string(),
+                    record_index: None,
+                    body_size: None,
+                    started,
+                });
+                Self::log_bodies(
+                    &state,
+                    method.as_str(),
+                    &full_url,
+                    &parts.headers,
+                    body.as_deref(),
+                    None,
+                );
                 warn!("Tunneled request not in snapshot: {} {}", method, full_url);
 
                 Ok(Response::builder()
@@ -291,4 +595,458 @@ impl ProxyHandler {
             }
         }
     }
+
+    /// Let request hooks rewrite the request before it is matched
+    ///
+    /// Returns the request as the hooks left it, for the response hooks, or
+    /// the response a hook answered it with. Without hooks nothing is copied.
+    fn run_request_hooks(
+        state: &ServeState,
+        parts: &mut hyper::http::request::Parts,
+        url: &mut String,
+        body: &mut Option<Bytes>,
+        client: IpAddr,
+        started: Instant,
+    ) -> std::result::Result<Option<RequestInfo>, Box<Response<Full<Bytes>>>> {
+        if state.hooks.is_empty() {
+            return Ok(None);
+        }
+        let mut request = RequestInfo {
+            method: parts.method.clone(),
+            url: url.clone(),
+            headers: parts.headers.clone(),
+            body: body.clone(),
+            client,
+        };
+        if let Some(response) = state.hooks.run_request(&mut request) {
+            state.console.request(RequestLine {
+                icon: "🪝",
+                method: request.method.to_string(),
+                url: request.url.clone(),
+                status: response.status().as_u16(),
+                detail: "answered by request hook".to_string(),
+                record_index: None,
+                body_size: Some(response.body().size_hint().exact().unwrap_or(0) as usize),
+                started,
+            });
+            debug!("Request hook answered {} {}", request.method, request.url);
+            return Err(Box::new(response));
+        }
+
+        parts.method = request.method.clone();
+        parts.headers = request.headers.clone();
+        url.clone_from(&request.url);
+        body.clone_from(&request.body);
+        Ok(Some(request))
+    }
+
+    /// Replay a record whose upstream request failed during capture
+    async fn replay_failure(
+        state: &ServeState,
+        record: &RequestRecord,
+        record_index: Option<usize>,
+        method: &hyper::Method,
+        url: &str,
+        started: Instant,
+    ) -> std::result::Result<Response<Full<Bytes>>, ConnectionAborted> {
+        let reason = record.response.failure.as_deref().unwrap_or("unknown");
+        if method != hyper::Method::CONNECT {
+            state.hosts.record_hit(url, 0);
+        }
+        if let Some(index) = record_index {
+            state.coverage.record_hit(index);
+        }
+        state.console.request(RequestLine {
+            icon: "⚠️",
+            method: method.to_string(),
+            url: url.to_string(),
+            status: record.response.status,
+            detail: format!("failed during capture: {}", reason),
+            record_index,
+            body_size: None,
+            started,
+        });
+        info!(
+            "Replaying capture failure for {} {}: {}",
+            method, url, reason
+        );
+        create_failure_response(record, &state.options).await
+    }
+
+    /// Refuse a redirect the client keeps being sent, which means the snapshot loops
+    ///
+    /// Only the first refusal is logged so a browser stuck in the loop cannot
+    /// flood the console.
+    fn check_redirect_loop(
+        state: &ServeState,
+        record: &RequestRecord,
+        record_index: Option<usize>,
+        client: IpAddr,
+        method: &str,
+        url: &str,
+        started: Instant,
+    ) -> Option<Response<Full<Bytes>>> {
+        if !is_redirect_status(record.response.status) {
+            return None;
+        }
+        let index = record_index?;
+
+        match state.redirect_loops.check(client, index, Instant::now()) {
+            RedirectCheck::Allowed => None,
+            RedirectCheck::Loop { first, count } => {
+                if first {
+                    state.console.request(RequestLine {
+                        icon: "🔁",
+                        method: method.to_string(),
+                        url: url.to_string(),
+                        status: 508,
+                        detail: format!(
+                            "redirect loop detected after {} redirects, further loop requests are not logged",
+                            count - 1
+                        ),
+                        record_index,
+                        body_size: None,
+                        started,
+                    });
+                    warn!(
+                        "Redirect loop detected: {} {} redirected {} times to {}",
+                        method,
+                        url,
+                        count - 1,
+                        client
+                    );
+                } else {
+                    debug!("Refusing looping redirect {} {} ({})", method, url, count);
+                }
+                Some(create_loop_detected_response(
+                    url,
+                    record
+                        .response
+                        .headers
+                        .iter()
+                        .find(|(name, _)| name.eq_ignore_ascii_case("location"))
+                        .map(|(_, value)| value.as_str()),
+                    count - 1,
+                ))
+            }
+        }
+    }
+
+    /// Refuse a record while an earlier record it depends on wasn't requested (`--stateful`)
+    fn check_order(
+        state: &ServeState,
+        record_index: Option<usize>,
+        method: &hyper::Method,
+        url: &str,
+        started: Instant,
+    ) -> Option<Response<Full<Bytes>>> {
+        let ordering = state.ordering.as_ref()?;
+        let OrderCheck::Waiting { for_index } = ordering.check(record_index?) else {
+            return None;
+        };
+        let earlier = &state.snapshot.requests[for_index];
+
+        state.console.request(RequestLine {
+            icon: "⏳",
+            method: method.to_string(),
+            url: url.to_string(),
+            status: 404,
+            detail: format!(
+                "out of captured order, waiting for {} {} (record #{})",
+                earlier.method,
+                earlier.url,
+                for_index + 1
+            ),
+            record_index,
+            body_size: None,
+            started,
+        });
+        info!(
+            "Refusing {} {} out of captured order: waiting for {} {}",
+            method, url, earlier.method, earlier.url
+        );
+        Some(create_out_of_order_response(url, earlier))
+    }
+
+    /// Pick the captured 429 or the successful record by the client's request rate (`--simulate-rate-limits`)
+    ///
+    /// Returns the record to serve with its index, and how long the client
+    /// has to wait when it is over the limit.
+    fn check_rate_limit<'a>(
+        state: &'a ServeState,
+        matched: &'a RequestRecord,
+        record_index: Option<usize>,
+        client: IpAddr,
+        method: &hyper::Method,
+        url: &str,
+    ) -> (&'a RequestRecord, Option<usize>, Option<Duration>) {
+        let check = state
+            .rate_limits
+            .as_ref()
+            .zip(record_index)
+            .and_then(|(limits, index)| limits.check(client, index, Instant::now()));
+        match check {
+            None => (matched, record_index, None),
+            Some(RateCheck::Allowed { record_index }) => (
+                &state.snapshot.requests[record_index],
+                Some(record_index),
+                None,
+            ),
+            Some(RateCheck::Limited {
+                record_index,
+                retry_after,
+            }) => {
+                info!(
+                    "Rate limiting {} {} for {}: retry after {:?}",
+                    method, url, client, retry_after
+                );
+                (
+                    &state.snapshot.requests[record_index],
+                    Some(record_index),
+                    Some(retry_after),
+                )
+            }
+        }
+    }
+
+    /// Tell a rate-limited client how long is actually left until it may retry
+    fn set_retry_after(response: &mut Response<Full<Bytes>>, wait: Duration) {
+        response.headers_mut().insert(
+            RETRY_AFTER,
+            HeaderValue::from(rate_limit::retry_after_seconds(wait)),
+        );
+    }
+
+    /// Answer a missing favicon, robots.txt or apple-touch icon with a stand-in
+    fn synthesize_common_asset(
+        state: &ServeState,
+        method: &hyper::Method,
+        url: &str,
+        started: Instant,
+    ) -> Option<Response<Full<Bytes>>> {
+        if !state.options.synthesize_common_assets {
+            return None;
+        }
+        let asset = CommonAsset::for_request(method, url)?;
+        let response = asset.response();
+
+        telemetry::record_synthesized();
+        state.console.request(RequestLine {
+            icon: "⚪",
+            method: method.to_string(),
+            url: url.to_string(),
+            status: response.status().as_u16(),
+            detail: "synthesized, not in snapshot".to_string(),
+            record_index: None,
+            body_size: Some(response.body().size_hint().exact().unwrap_or(0) as usize),
+            started,
+        });
+        debug!("Synthesized {:?} for {} {}", asset, method, url);
+        Some(response)
+    }
+
+    /// Relay a request without a matching record from the `--forward-unmatched` backend
+    async fn forward_unmatched(
+        state: &ServeState,
+        method: &hyper::Method,
+        url: &str,
+        headers: &hyper::HeaderMap,
+        body: Option<Bytes>,
+        started: Instant,
+    ) -> Option<Response<Full<Bytes>>> {
+        let forwarder = state.forwarder.as_ref()?;
+        let response = forwarder
+            .forward(method, url, headers, body.unwrap_or_default())
+            .await;
+
+        telemetry::record_matched(false);
+        telemetry::record_forwarded();
+        state.hosts.record_miss(url);
+        let forwarded = forwarder.url_for(url).unwrap_or_else(|| url.to_string());
+        state.console.request(RequestLine {
+            icon: "↗",
+            method: method.to_string(),
+            url: url.to_string(),
+            status: response.status().as_u16(),
+            detail: format!("forwarded to {}", forwarded),
+            record_index: None,
+            body_size: Some(response.body().size_hint().exact().unwrap_or(0) as usize),
+            started,
+        });
+        Some(response)
+    }
+
+    /// Queue the console line for a request answered from a recorded response
+    fn log_record(
+        state: &ServeState,
+        record: &RequestRecord,
+        record_index: Option<usize>,
+        method: &str,
+        url: &str,
+        started: Instant,
+    ) {
+        let status_icon = if record.response.status >= 200 && record.response.status < 300 {
+            "✅"
+        } else if record.response.status >= 400 {
+            "❌"
+        } else {
+            "ℹ️"
+        };
+
+        state.hosts.record_hit(url, record.response.body.len());
+        if let Some(index) = record_index {
+            state.coverage.record_hit(index);
+        }
+        state.console.request(RequestLine {
+            icon: status_icon,
+            method: method.to_string(),
+            url: url.to_string(),
+            status: record.response.status,
+            detail: record
+                .response
+                .headers
+                .get("content-type")
+                .cloned()
+                .unwrap_or_else(|| "unknown".to_string()),
+            record_index,
+            body_size: Some(record.response.body.len()),
+            started,
+        });
+    }
+
+    /// Queue the `--log-bodies` entry for a request answered from a record or with a 404
+    fn log_bodies(
+        state: &ServeState,
+        method: &str,
+        url: &str,
+        headers: &HeaderMap,
+        body: Option<&[u8]>,
+        record: Option<&RequestRecord>,
+    ) {
+        if let Some(body_log) = &state.body_log {
+            body_log.log(method, url, headers, body, record);
+        }
+    }
+
+    /// Buffer the request body when the matcher compares bodies, misses are
+    /// forwarded, bodies are logged or hooks may look at it
+    ///
+    /// Bodies that fail to read or exceed the limit are matched, forwarded
+    /// and logged as absent.
+    async fn read_body_for_matching(state: &ServeState, body: Incoming) -> Option<Bytes> {
+        if !state.matcher.needs_body()
+            && state.forwarder.is_none()
+            && state.body_log.is_none()
+            && state.hooks.is_empty()
+        {
+            return None;
+        }
+        match Limited::new(body, MAX_MATCH_BODY_BYTES).collect().await {
+            Ok(collected) => Some(collected.to_bytes()),
+            Err(e) => {
+                debug!("Matching without the request body: {}", e);
+                None
+            }
+        }
+    }
+
+    /// Show which method a record was matched under in the console
+    fn method_label(method: &hyper::Method, matched: Option<&hyper::Method>) -> String {
+        match matched {
+            Some(matched) => format!("{} (as {})", method, matched),
+            None => method.to_string(),
+        }
+    }
+
+    /// Apply the first matching override, which takes precedence over failure replay
+    fn apply_override<'a>(
+        state: &ServeState,
+        record: &'a RequestRecord,
+        method: &hyper::Method,
+        url: &str,
+    ) -> Cow<'a, RequestRecord> {
+        match state.options.overrides.find(method.as_str(), url) {
+            Some(rule) => {
+                debug!(
+                    "Applying override '{}' to {} {}",
+                    rule.url_pattern(),
+                    method,
+                    url
+                );
+                Cow::Owned(rule.apply(record))
+            }
+            None => Cow::Borrowed(record),
+        }
+    }
+
+    /// Build the response for a matched record, marked cacheable under `cache_key`
+    fn record_response(
+        record: &RequestRecord,
+        cache_key: Option<usize>,
+        state: &ServeState,
+    ) -> Response<Full<Bytes>> {
+        let mut response = create_response_from_record(record, &state.options);
+        if let Some(index) = cache_key {
+            response.extensions_mut().insert(encoding::CacheKey(index));
+        }
+        if state.options.replay_early_hints && !record.response.informational.is_empty() {
+            response
+                .extensions_mut()
+                .insert(EarlyHints(record.response.informational.clone()));
+        }
+        response
+    }
+
+    /// Tag a record response with a generated ETag and check `If-None-Match`, with `--generate-etags`
+    ///
+    /// Returns the 304 to send when the client's copy is current. Overridden
+    /// records have no `cache_key`, so their tag is computed on every request.
+    fn revalidate(
+        state: &ServeState,
+        record: &RequestRecord,
+        cache_key: Option<usize>,
+        request: &hyper::http::request::Parts,
+        response: &mut Response<Full<Bytes>>,
+    ) -> Option<Response<Full<Bytes>>> {
+        if !state.options.generate_etags
+            || state.hooks.has_response_hooks()
+            || !matches!(request.method, hyper::Method::GET | hyper::Method::HEAD)
+            || !response.status().is_success()
+        {
+            return None;
+        }
+
+        if !etags::has_validator(response.headers()) {
+            let body = &record.response.body;
+            let etag = match cache_key {
+                Some(index) => state.etags.get_or_compute(index, body),
+                None => etags::body_etag(body),
+            };
+            if let Ok(value) = HeaderValue::from_str(&etag) {
+                response.headers_mut().insert(ETAG, value);
+            }
+        }
+
+        let etag = response.headers().get(ETAG)?.to_str().ok()?;
+        let matched = request
+            .headers
+            .get_all(IF_NONE_MATCH)
+            .iter()
+            .filter_map(|value| value.to_str().ok())
+            .any(|value| etags::if_none_match(value, etag));
+        if !matched {
+            return None;
+        }
+        debug!("{} {} not modified ({})", request.method, record.url, etag);
+        Some(create_not_modified_response(response))
+    }
+
+    /// Find the position of a matched record in the snapshot
+    fn record_index(state: &ServeState, record: &RequestRecord) -> Option<usize> {
+        state
+            .snapshot
+            .requests
+            .iter()
+            .position(|candidate| std::ptr::eq(candidate, record))
+    }
 }
@@ -0,0 +1,252 @@
+//! Rate limiting replayed from captured 429 responses
+//!
+//! A snapshot of an API that rate limits often holds both answers for an
+//! endpoint: the 200s served while the client stayed under the limit and a
+//! 429 with `Retry-After` once it didn't. Plain replay matches the same record
+//! every time, so a client hammering the endpoint never sees the 429. With
+//! `--simulate-rate-limits` each such endpoint allows a client a number of
+//! requests per `Retry-After` window and answers the captured 429 beyond that,
+//! with `Retry-After` set to the time left until the client may retry.
+//!
+//! The number of requests allowed per window comes from the 429's
+//! `RateLimit-Limit` or `X-RateLimit-Limit` header, or else from how many
+//! successful responses for the endpoint were captured before it. Requests are
+//! counted per client IP over a sliding window; refused requests don't count.
+//! State lives only in memory, so it starts over whenever the server does, and
+//! only the most recently seen clients are tracked.
+
+use std::collections::{HashMap, VecDeque};
+use std::net::IpAddr;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use chrono::DateTime;
+
+use crate::capture::proxy::RequestRecord;
+
+/// Most clients whose requests are tracked at once
+pub const MAX_RATE_LIMITED_CLIENTS: usize = 1024;
+
+/// Outcome of a request for a rate-limited endpoint
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RateCheck {
+    /// Serve the record at `record_index`, the endpoint's successful response if it has one
+    Allowed { record_index: usize },
+    /// Serve the captured 429 at `record_index`; the client may retry after `retry_after`
+    Limited {
+        record_index: usize,
+        retry_after: Duration,
+    },
+}
+
+/// An endpoint captured answering 429, and the rate it implies
+#[derive(Debug, Clone)]
+struct RateLimitRule {
+    /// Index of the captured 429
+    limited: usize,
+    /// Index of the first successful response, served while under the limit
+    allowed: Option<usize>,
+    /// Requests allowed per window
+    limit: usize,
+    /// From the 429's `Retry-After`
+    window: Duration,
+}
+
+#[derive(Debug)]
+struct ClientRequests {
+    last_seen: Instant,
+    /// Times of the requests counted against each rule, oldest first
+    requests: HashMap<usize, VecDeque<Instant>>,
+}
+
+/// Per-client request rates for endpoints captured answering 429
+#[derive(Debug)]
+pub struct RateLimiter {
+    rules: Vec<RateLimitRule>,
+    /// Rule covering each record of a rate-limited endpoint
+    rule_of_record: HashMap<usize, usize>,
+    capacity: usize,
+    clients: Mutex<HashMap<IpAddr, ClientRequests>>,
+}
+
+impl RateLimiter {
+    /// Find the rate-limited endpoints among the records of a snapshot
+    pub fn new(records: &[RequestRecord]) -> Self {
+        Self::with_capacity(records, MAX_RATE_LIMITED_CLIENTS)
+    }
+
+    /// Create a limiter tracking at most `capacity` clients
+    pub fn with_capacity(records: &[RequestRecord], capacity: usize) -> Self {
+        let mut endpoints: HashMap<(String, &str), Vec<usize>> = HashMap::new();
+        for (index, record) in records.iter().enumerate() {
+            endpoints
+                .entry((record.method.to_ascii_uppercase(), record.url.as_str()))
+                .or_default()
+                .push(index);
+        }
+
+        let mut rules = Vec::new();
+        let mut rule_of_record = HashMap::new();
+        // Sorted so rules are numbered the same on every start
+        let mut endpoints: Vec<Vec<usize>> = endpoints.into_values().collect();
+        endpoints.sort();
+        for indices in endpoints {
+            let Some(rule) = endpoint_rule(records, &indices) else {
+                continue;
+            };
+            for index in indices {
+                rule_of_record.insert(index, rules.len());
+            }
+            rules.push(rule);
+        }
+
+        Self {
+            rules,
+            rule_of_record,
+            capacity: capacity.max(1),
+            clients: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Number of rate-limited endpoints
+    pub fn len(&self) -> usize {
+        self.rules.len()
+    }
+
+    /// Check whether the snapshot has no rate-limited endpoint
+    pub fn is_empty(&self) -> bool {
+        self.rules.is_empty()
+    }
+
+    /// Count a request from `client` matched to `record_index`
+    ///
+    /// Returns `None` when the record's endpoint isn't rate limited.
+    pub fn check(&self, client: IpAddr, record_index: usize, now: Instant) -> Option<RateCheck> {
+        let rule_index = *self.rule_of_record.get(&record_index)?;
+        let rule = &self.rules[rule_index];
+
+        let mut clients = self.clients.lock().unwrap_or_else(|e| e.into_inner());
+        if !clients.contains_key(&client) && clients.len() >= self.capacity {
+            Self::evict(&mut clients);
+        }
+        let tracked = clients.entry(client).or_insert_with(|| ClientRequests {
+            last_seen: now,
+            requests: HashMap::new(),
+        });
+        tracked.last_seen = now;
+
+        let requests = tracked.requests.entry(rule_index).or_default();
+        while requests
+            .front()
+            .is_some_and(|&at| now.saturating_duration_since(at) >= rule.window)
+        {
+            requests.pop_front();
+        }
+
+        if requests.len() >= rule.limit {
+            let oldest = requests.front().copied().unwrap_or(now);
+            return Some(RateCheck::Limited {
+                record_index: rule.limited,
+                retry_after: rule
+                    .window
+                    .saturating_sub(now.saturating_duration_since(oldest)),
+            });
+        }
+        requests.push_back(now);
+        Some(RateCheck::Allowed {
+            record_index: rule.allowed.unwrap_or(record_index),
+        })
+    }
+
+    /// Number of clients currently tracked
+    pub fn tracked_clients(&self) -> usize {
+        self.clients.lock().unwrap_or_else(|e| e.into_inner()).len()
+    }
+
+    /// Forget the least recently seen client
+    fn evict(clients: &mut HashMap<IpAddr, ClientRequests>) {
+        if let Some(oldest) = clients
+            .iter()
+            .min_by_key(|(_, tracked)| tracked.last_seen)
+            .map(|(client, _)| *client)
+        {
+            clients.remove(&oldest);
+        }
+    }
+}
+
+/// The rule for the records of one endpoint, if one of them is a 429 with `Retry-After`
+fn endpoint_rule(records: &[RequestRecord], indices: &[usize]) -> Option<RateLimitRule> {
+    let is_success = |index: &usize| (200..300).contains(&records[*index].response.status);
+
+    let (position, limited, window) =
+        indices.iter().enumerate().find_map(|(position, &index)| {
+            let response = &records[index].response;
+            if response.status != 429 {
+                return None;
+            }
+            let window = parse_retry_after(
+                header(&response.headers, "retry-after")?,
+                header(&response.headers, "date"),
+            )?;
+            Some((position, index, window))
+        })?;
+    if window.is_zero() {
+        return None;
+    }
+
+    let headers = &records[limited].response.headers;
+    let limit = header(headers, "ratelimit-limit")
+        .or_else(|| header(headers, "x-ratelimit-limit"))
+        .and_then(parse_limit)
+        .unwrap_or_else(|| indices[..position].iter().filter(|i| is_success(i)).count());
+
+    Some(RateLimitRule {
+        limited,
+        allowed: indices.iter().copied().find(|index| is_success(index)),
+        limit: limit.max(1),
+        window,
+    })
+}
+
+fn header<'a>(headers: &'a HashMap<String, String>, name: &str) -> Option<&'a str> {
+    headers
+        .iter()
+        .find(|(key, _)| key.eq_ignore_ascii_case(name))
+        .map(|(_, value)| value.as_str())
+}
+
+/// Parse the request count of a `RateLimit-Limit` value such as `100` or `100, 100;w=60`
+fn parse_limit(value: &str) -> Option<usize> {
+    value
+        .split([',', ';'])
+        .next()?
+        .trim()
+        .parse()
+        .ok()
+        .filter(|&limit| limit > 0)
+}
+
+/// Parse a `Retry-After` value, in seconds or as an HTTP date
+///
+/// A date is only meaningful relative to when the response was sent, so it
+/// needs the response's `Date` header.
+pub fn parse_retry_after(value: &str, date: Option<&str>) -> Option<Duration> {
+    let value = value.trim();
+    if let Ok(seconds) = value.parse::<u64>() {
+        return Some(Duration::from_secs(seconds));
+    }
+    let retry_at = DateTime::parse_from_rfc2822(value).ok()?;
+    let sent_at = DateTime::parse_from_rfc2822(date?.trim()).ok()?;
+    (retry_at - sent_at).to_std().ok()
+}
+
+/// Format a wait as whole `Retry-After` seconds, rounded up so clients never retry early
+pub fn retry_after_seconds(wait: Duration) -> u64 {
+    let seconds = wait.as_secs();
+    if wait.subsec_nanos() > 0 {
+        seconds + 1
+    } else {
+        seconds.max(1)
+    }
+}
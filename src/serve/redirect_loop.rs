@@ -0,0 +1,130 @@
+//! Protection against redirect loops captured in a snapshot
+//!
+//! A snapshot can contain a redirect cycle (A 302→B, B 302→A) that a browser
+//! follows forever. The guard counts how often each client is sent the same
+//! redirect record in a burst and, once the limit is exceeded, the server
+//! answers with 508 Loop Detected instead until the client pauses.
+
+use std::collections::HashMap;
+use std::net::IpAddr;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// Times the same redirect may be served to a client in a burst before it is refused
+pub const DEFAULT_REDIRECT_LOOP_LIMIT: u32 = 10;
+
+/// Pause after which a client's redirect counts start over
+pub const REDIRECT_LOOP_WINDOW: Duration = Duration::from_secs(2);
+
+/// Most client/record pairs tracked at once
+pub const MAX_TRACKED_REDIRECTS: usize = 4096;
+
+/// Outcome of serving a redirect record to a client
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RedirectCheck {
+    /// Serve the recorded redirect
+    Allowed,
+    /// Refuse the redirect; `first` is set only when the loop was just detected
+    Loop { first: bool, count: u32 },
+}
+
+#[derive(Debug, Clone, Copy)]
+struct RedirectBurst {
+    count: u32,
+    last_seen: Instant,
+}
+
+/// Bounded per-client redirect counters
+#[derive(Debug)]
+pub struct RedirectLoopGuard {
+    limit: u32,
+    window: Duration,
+    capacity: usize,
+    bursts: Mutex<HashMap<(IpAddr, usize), RedirectBurst>>,
+}
+
+impl RedirectLoopGuard {
+    /// Refuse a redirect once it is served more than `limit` times in a burst; 0 disables
+    pub fn new(limit: u32) -> Self {
+        Self::with_window(limit, REDIRECT_LOOP_WINDOW, MAX_TRACKED_REDIRECTS)
+    }
+
+    /// Create a guard with a custom burst window and tracking capacity
+    pub fn with_window(limit: u32, window: Duration, capacity: usize) -> Self {
+        Self {
+            limit,
+            window,
+            capacity: capacity.max(1),
+            bursts: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Check whether the guard is active
+    pub fn is_enabled(&self) -> bool {
+        self.limit > 0
+    }
+
+    /// Record that `record_index` is about to be served to `client` as a redirect
+    pub fn check(&self, client: IpAddr, record_index: usize, now: Instant) -> RedirectCheck {
+        if !self.is_enabled() {
+            return RedirectCheck::Allowed;
+        }
+
+        let mut bursts = self.bursts.lock().unwrap_or_else(|e| e.into_inner());
+        let key = (client, record_index);
+
+        if !bursts.contains_key(&key) && bursts.len() >= self.capacity {
+            self.evict(&mut bursts, now);
+        }
+
+        let burst = bursts.entry(key).or_insert(RedirectBurst {
+            count: 0,
+            last_seen: now,
+        });
+        if now.saturating_duration_since(burst.last_seen) > self.window {
+            burst.count = 0;
+        }
+        burst.count = burst.count.saturating_add(1);
+        burst.last_seen = now;
+
+        if burst.count > self.limit {
+            RedirectCheck::Loop {
+                first: burst.count == self.limit + 1,
+                count: burst.count,
+            }
+        } else {
+            RedirectCheck::Allowed
+        }
+    }
+
+    /// Number of client/record pairs currently tracked
+    pub fn tracked(&self) -> usize {
+        self.bursts.lock().unwrap_or_else(|e| e.into_inner()).len()
+    }
+
+    /// Drop finished bursts, or the least recently seen one if all are active
+    fn evict(&self, bursts: &mut HashMap<(IpAddr, usize), RedirectBurst>, now: Instant) {
+        bursts.retain(|_, burst| now.saturating_duration_since(burst.last_seen) <= self.window);
+
+        if bursts.len() >= self.capacity {
+            if let Some(oldest) = bursts
+                .iter()
+                .min_by_key(|(_, burst)| burst.last_seen)
+                .map(|(key, _)| *key)
+            {
+                bursts.remove(&oldest);
+            }
+        }
+    }
+}
+
+impl Default for RedirectLoopGuard {
+    fn default() -> Self {
+        Self::new(DEFAULT_REDIRECT_LOOP_LIMIT)
+    }
+}
+
+/// Check whether a status code is a redirect a browser would follow
+pub fn is_redirect_status(status: u16) -> bool {
+    matches!(status, 301 | 302 | 303 | 307 | 308)
+}
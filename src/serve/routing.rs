@@ -0,0 +1,372 @@
+//! Serving every snapshot of a storage directory from one server
+//!
+//! `webmock serve --all --path-routing` answers plain (non-proxy) requests.
+//! `/_s/<name>/...` is served from snapshot `<name>` with the prefix stripped
+//! before matching, so `/_s/shop/cart?id=1` replays `<origin>/cart?id=1` of
+//! the `shop` snapshot. Snapshots are loaded on first use and only the most
+//! recently used ones are kept in memory.
+//!
+//! Pages are served from a different origin than they were captured on, so
+//! root-relative and same-origin links in HTML, and `Location` headers, are
+//! rewritten to stay under the snapshot's prefix. Links to other origins and
+//! URLs built by scripts are left alone.
+
+use std::collections::VecDeque;
+use std::net::IpAddr;
+use std::sync::{Arc, Mutex};
+
+use bytes::Bytes;
+use http_body_util::{BodyExt, Full};
+use hyper::body::Incoming;
+use hyper::header::{ACCEPT_ENCODING, CONTENT_ENCODING, CONTENT_LENGTH, CONTENT_TYPE, LOCATION};
+use hyper::{HeaderMap, Method, Request, Response, StatusCode, Uri};
+use tracing::{debug, info, warn};
+use url::Url;
+
+use crate::error::WebMockError;
+use crate::serve::body_log::BodyLog;
+use crate::serve::console::ConsoleWriter;
+use crate::serve::encoding;
+use crate::serve::handlers::{
+    create_snapshot_index_response, create_unknown_snapshot_response, ConnectionAborted,
+    DefaultMatcher, RequestMatcher,
+};
+use crate::serve::proxy::ProxyHandler;
+use crate::serve::{ServeOptions, ServeState};
+use crate::storage::{validate_snapshot_name, SnapshotInfo, Storage};
+
+/// Path prefix under which snapshots are served
+pub const ROUTE_PREFIX: &str = "/_s/";
+
+/// Snapshots kept in memory when `--max-loaded-snapshots` is not given
+pub const DEFAULT_MAX_LOADED_SNAPSHOTS: usize = 4;
+
+/// Routes requests to snapshots by the name in their path
+pub struct SnapshotRouter {
+    storage: Storage,
+    options: ServeOptions,
+    console: Arc<ConsoleWriter>,
+    body_log: Option<Arc<BodyLog>>,
+    matcher: Arc<dyn RequestMatcher + Send + Sync>,
+    max_loaded: usize,
+    /// Loaded snapshots, least recently used first
+    loaded: Mutex<VecDeque<Arc<ServeState>>>,
+}
+
+impl SnapshotRouter {
+    /// Create a router over `storage` keeping at most `max_loaded` snapshots in memory
+    pub fn new(storage: Storage, options: ServeOptions, max_loaded: usize) -> Self {
+        let console = Arc::new(ConsoleWriter::stdout(options.output));
+        let body_log = options
+            .log_bodies
+            .clone()
+            .map(|settings| Arc::new(BodyLog::stderr(settings)));
+        Self {
+            storage,
+            options,
+            console,
+            body_log,
+            matcher: Arc::new(DefaultMatcher),
+            max_loaded: max_loaded.max(1),
+            loaded: Mutex::new(VecDeque::new()),
+        }
+    }
+
+    pub(crate) fn options(&self) -> &ServeOptions {
+        &self.options
+    }
+
+    pub(crate) fn console(&self) -> &ConsoleWriter {
+        &self.console
+    }
+
+    pub(crate) fn body_log(&self) -> Option<&BodyLog> {
+        self.body_log.as_deref()
+    }
+
+    /// Names of the snapshots currently in memory, least recently used first
+    pub fn loaded(&self) -> Vec<String> {
+        self.loaded
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|state| state.snapshot.name.clone())
+            .collect()
+    }
+
+    pub(crate) async fn handle(
+        &self,
+        req: Request<Incoming>,
+        client: IpAddr,
+    ) -> std::result::Result<Response<Full<Bytes>>, ConnectionAborted> {
+        if req.method() == Method::CONNECT {
+            return Ok(text_response(
+                StatusCode::METHOD_NOT_ALLOWED,
+                "Path routing mode is not a proxy; open the index page in a browser instead.\n",
+            ));
+        }
+
+        let path = req.uri().path().to_string();
+        if matches!(path.as_str(), "/" | "/_s" | ROUTE_PREFIX) {
+            return Ok(create_snapshot_index_response(&self.list().await));
+        }
+
+        let Some((name, rest)) = split_route(&path) else {
+            return Ok(self.unknown_snapshot(&path).await);
+        };
+        if validate_snapshot_name(name).is_err() {
+            return Ok(self.unknown_snapshot(&path).await);
+        }
+
+        let prefix = format!("{}{}", ROUTE_PREFIX, name);
+        if rest.is_empty() {
+            // Relative links only resolve under the prefix with a trailing slash
+            let location = match req.uri().query() {
+                Some(query) => format!("{}/?{}", prefix, query),
+                None => format!("{}/", prefix),
+            };
+            return Ok(Response::builder()
+                .status(StatusCode::PERMANENT_REDIRECT)
+                .header(LOCATION, location)
+                .body(Full::new(Bytes::new()))
+                .unwrap());
+        }
+
+        let state = match self.snapshot(name).await {
+            Ok(state) => state,
+            Err(WebMockError::SnapshotNotFound(_)) => {
+                return Ok(self.unknown_snapshot(&path).await);
+            }
+            Err(e) => {
+                warn!("Failed to load snapshot '{}': {}", name, e);
+                return Ok(text_response(
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    &format!("Failed to load snapshot '{}': {}\n", name, e.user_message()),
+                ));
+            }
+        };
+
+        let origin = snapshot_origin(&state.snapshot.url);
+        let target = match req.uri().query() {
+            Some(query) => format!("{}{}?{}", origin, rest, query),
+            None => format!("{}{}", origin, rest),
+        };
+        let (mut parts, body) = req.into_parts();
+        parts.uri = match target.parse::<Uri>() {
+            Ok(uri) => uri,
+            Err(e) => {
+                debug!("Cannot route {} to {}: {}", path, target, e);
+                return Ok(self.unknown_snapshot(&path).await);
+            }
+        };
+
+        // Links are rewritten in the identity body, so compress afterwards
+        let mut accept = HeaderMap::new();
+        for value in parts.headers.get_all(ACCEPT_ENCODING) {
+            accept.append(ACCEPT_ENCODING, value.clone());
+        }
+        parts.headers.remove(ACCEPT_ENCODING);
+
+        let response = ProxyHandler::handle_http_request(
+            Arc::clone(&state),
+            Request::from_parts(parts, body),
+            client,
+        )
+        .await?;
+        let response = rewrite_response(response, &prefix, &origin).await;
+        Ok(encoding::encode_response(&state, &accept, response).await)
+    }
+
+    /// Get a loaded snapshot, loading it and evicting the least recently used if needed
+    async fn snapshot(&self, name: &str) -> crate::error::Result<Arc<ServeState>> {
+        if let Some(state) = self.touch(name) {
+            return Ok(state);
+        }
+
+        // Load outside the lock so other snapshots keep being served meanwhile
+        let snapshot = self.storage.load_snapshot(name).await?;
+        info!("Loaded snapshot '{}' for path routing", name);
+
+        let mut loaded = self.loaded.lock().unwrap();
+        if let Some(index) = loaded.iter().position(|s| s.snapshot.name == name) {
+            // Another request loaded it first
+            let state = loaded.remove(index).unwrap();
+            loaded.push_back(Arc::clone(&state));
+            return Ok(state);
+        }
+
+        let state = Arc::new(
+            ServeState::new(
+                snapshot,
+                self.options.clone(),
+                Arc::clone(&self.console),
+                Arc::clone(&self.matcher),
+            )
+            .with_body_log(self.body_log.clone()),
+        );
+        loaded.push_back(Arc::clone(&state));
+        while loaded.len() > self.max_loaded {
+            if let Some(evicted) = loaded.pop_front() {
+                debug!("Unloaded snapshot '{}'", evicted.snapshot.name);
+            }
+        }
+        Ok(state)
+    }
+
+    /// Mark a loaded snapshot as most recently used
+    fn touch(&self, name: &str) -> Option<Arc<ServeState>> {
+        let mut loaded = self.loaded.lock().unwrap();
+        let index = loaded.iter().position(|s| s.snapshot.name == name)?;
+        let state = loaded.remove(index)?;
+        loaded.push_back(Arc::clone(&state));
+        Some(state)
+    }
+
+    /// Metadata of every snapshot in storage, sorted by name
+    async fn list(&self) -> Vec<SnapshotInfo> {
+        let mut snapshots = self.storage.list_snapshots().await.unwrap_or_else(|e| {
+            warn!("Failed to list snapshots: {}", e);
+            Vec::new()
+        });
+        snapshots.sort_by(|a, b| a.name.cmp(&b.name));
+        snapshots
+    }
+
+    async fn unknown_snapshot(&self, path: &str) -> Response<Full<Bytes>> {
+        create_unknown_snapshot_response(path, &self.list().await)
+    }
+}
+
+/// Split `/_s/<name>/rest` into the snapshot name and `/rest`
+///
+/// `rest` is empty when the path stops right after the name.
+pub fn split_route(path: &str) -> Option<(&str, &str)> {
+    let routed = path.strip_prefix(ROUTE_PREFIX)?;
+    let (name, rest) = match routed.find('/') {
+        Some(index) => routed.split_at(index),
+        None => (routed, ""),
+    };
+    (!name.is_empty()).then_some((name, rest))
+}
+
+/// Rewrite root-relative and same-origin links in HTML to stay under `prefix`
+///
+/// Attribute values (`href="/a"`, `src='/b'`) and CSS `url(/c)` references
+/// are rewritten; protocol-relative (`//host/`) and other-origin links are not.
+pub fn rewrite_html(html: &str, prefix: &str, origin: &str) -> String {
+    const VALUE_STARTS: [&str; 5] = ["=\"", "='", "url(\"", "url('", "url("];
+
+    let mut out = String::with_capacity(html.len());
+    let mut rest = html;
+    while let Some(index) = rest.find(['=', 'u']) {
+        out.push_str(&rest[..index]);
+        rest = &rest[index..];
+
+        match VALUE_STARTS.iter().find(|start| rest.starts_with(*start)) {
+            Some(start) => {
+                out.push_str(start);
+                rest = &rest[start.len()..];
+                if let Some((consumed, replacement)) = rewrite_link(rest, prefix, origin) {
+                    out.push_str(&replacement);
+                    rest = &rest[consumed..];
+                }
+            }
+            None => {
+                out.push_str(&rest[..1]);
+                rest = &rest[1..];
+            }
+        }
+    }
+    out.push_str(rest);
+    out
+}
+
+/// Rewrite a `Location` header value to stay under `prefix`
+pub fn rewrite_location(location: &str, prefix: &str, origin: &str) -> Option<String> {
+    let (consumed, replacement) = rewrite_link(location, prefix, origin)?;
+    Some(format!("{}{}", replacement, &location[consumed..]))
+}
+
+/// Replacement for the start of a link, as (bytes replaced, new text)
+fn rewrite_link(value: &str, prefix: &str, origin: &str) -> Option<(usize, String)> {
+    if let Some(after) = value.strip_prefix(origin) {
+        if after.is_empty() || after.starts_with(['"', '\'', ')', '?', '#']) {
+            return Some((origin.len(), format!("{}/", prefix)));
+        }
+        if after.starts_with('/') {
+            return Some((origin.len(), prefix.to_string()));
+        }
+        return None;
+    }
+
+    let already_routed = value
+        .strip_prefix(prefix)
+        .is_some_and(|after| after.starts_with('/'));
+    if value.starts_with('/') && !value.starts_with("//") && !already_routed {
+        return Some((0, prefix.to_string()));
+    }
+    None
+}
+
+/// Scheme, host and port of a snapshot's URL, e.g. `https://example.com`
+fn snapshot_origin(url: &str) -> String {
+    Url::parse(url)
+        .map(|url| url.origin().ascii_serialization())
+        .unwrap_or_else(|_| url.trim_end_matches('/').to_string())
+}
+
+/// Keep links in a replayed response under the snapshot's prefix
+async fn rewrite_response(
+    response: Response<Full<Bytes>>,
+    prefix: &str,
+    origin: &str,
+) -> Response<Full<Bytes>> {
+    let (mut parts, body) = response.into_parts();
+
+    let location = parts
+        .headers
+        .get(LOCATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|location| rewrite_location(location, prefix, origin));
+    if let Some(location) = location.and_then(|l| l.parse().ok()) {
+        parts.headers.insert(LOCATION, location);
+    }
+
+    if !is_plain_html(&parts.headers) {
+        return Response::from_parts(parts, body);
+    }
+
+    // Full bodies can't fail to collect
+    let bytes = body
+        .collect()
+        .await
+        .map(|b| b.to_bytes())
+        .unwrap_or_default();
+    let body = match std::str::from_utf8(&bytes) {
+        Ok(html) => Bytes::from(rewrite_html(html, prefix, origin)),
+        Err(_) => bytes,
+    };
+    parts.headers.insert(CONTENT_LENGTH, body.len().into());
+    Response::from_parts(parts, Full::new(body))
+}
+
+/// Whether a response body is uncompressed HTML that links can be rewritten in
+fn is_plain_html(headers: &HeaderMap) -> bool {
+    let html = headers
+        .get(CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .is_some_and(|value| value.to_ascii_lowercase().starts_with("text/html"));
+    let encoded = headers
+        .get(CONTENT_ENCODING)
+        .and_then(|value| value.to_str().ok())
+        .is_some_and(|value| !value.eq_ignore_ascii_case("identity"));
+    html && !encoded
+}
+
+fn text_response(status: StatusCode, body: &str) -> Response<Full<Bytes>> {
+    Response::builder()
+        .status(status)
+        .header(CONTENT_TYPE, "text/plain; charset=utf-8")
+        .body(Full::new(Bytes::from(body.to_string())))
+        .unwrap()
+}
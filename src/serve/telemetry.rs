@@ -0,0 +1,127 @@
+//! Tracing spans for the serve path
+//!
+//! Every accepted connection runs inside a `connection` span and every request
+//! inside a `request` span that ends up carrying its method, URL, whether it
+//! matched a record, and the status sent back. The spans cost a callsite check
+//! when no subscriber is interested in them; with the `otlp` feature they can
+//! be exported to an OpenTelemetry collector through [`otlp::install`].
+
+use std::future::Future;
+use std::net::SocketAddr;
+
+use bytes::Bytes;
+use http_body_util::Full;
+use hyper::{Method, Response, Uri};
+use tracing::{field, info_span, Instrument, Span};
+
+/// Target every serve span is created under, for filtering exporters
+pub const SPAN_TARGET: &str = "webmock_cli::serve";
+
+/// Span covering one accepted client connection
+pub fn connection_span(peer: SocketAddr) -> Span {
+    info_span!(target: SPAN_TARGET, "connection", peer = %peer)
+}
+
+/// Span covering one request; `matched` and `status` are filled in as it's handled
+pub fn request_span(method: &Method, uri: &Uri) -> Span {
+    info_span!(
+        target: SPAN_TARGET,
+        "request",
+        method = %method,
+        url = %uri,
+        matched = field::Empty,
+        synthesized = field::Empty,
+        forwarded = field::Empty,
+        status = field::Empty,
+    )
+}
+
+/// Run a request handler inside `span`, recording the status it answers with
+pub async fn traced<F, E>(span: Span, handler: F) -> Result<Response<Full<Bytes>>, E>
+where
+    F: Future<Output = Result<Response<Full<Bytes>>, E>>,
+{
+    let result = handler.instrument(span.clone()).await;
+    if let Ok(response) = &result {
+        span.record("status", response.status().as_u16());
+    }
+    result
+}
+
+/// Replace the request span's URL once the full URL is known, e.g. inside a tunnel
+pub fn record_url(url: &str) {
+    Span::current().record("url", url);
+}
+
+/// Note on the request span whether the snapshot had a record for it
+pub fn record_matched(matched: bool) {
+    Span::current().record("matched", matched);
+}
+
+/// Note on the request span that a stand-in for a missing asset was sent
+pub fn record_synthesized() {
+    Span::current().record("synthesized", true);
+}
+
+/// Note on the request span that it was forwarded to the `--forward-unmatched` backend
+pub fn record_forwarded() {
+    Span::current().record("forwarded", true);
+}
+
+/// OpenTelemetry export of serve spans
+#[cfg(feature = "otlp")]
+pub mod otlp {
+    use opentelemetry::trace::TracerProvider as _;
+    use opentelemetry_otlp::{SpanExporter, WithExportConfig};
+    use opentelemetry_sdk::trace::SdkTracerProvider;
+    use opentelemetry_sdk::Resource;
+    use tracing::Subscriber;
+    use tracing_subscriber::filter::{LevelFilter, Targets};
+    use tracing_subscriber::registry::LookupSpan;
+    use tracing_subscriber::Layer;
+
+    use super::SPAN_TARGET;
+    use crate::error::{Result, WebMockError};
+
+    /// Keeps the exporter alive; call [`OtlpGuard::shutdown`] to flush pending spans
+    pub struct OtlpGuard {
+        provider: SdkTracerProvider,
+    }
+
+    impl OtlpGuard {
+        /// Export whatever is still buffered and stop the exporter
+        pub fn shutdown(self) {
+            if let Err(e) = self.provider.shutdown() {
+                eprintln!("Failed to flush OpenTelemetry spans: {}", e);
+            }
+        }
+    }
+
+    /// Build a layer exporting serve spans over OTLP/gRPC to `endpoint`
+    ///
+    /// Must be called from within a Tokio runtime. The layer only sees the
+    /// serve spans, so `RUST_LOG` keeps governing what's printed.
+    pub fn install<S>(
+        endpoint: &str,
+    ) -> Result<(Box<dyn Layer<S> + Send + Sync + 'static>, OtlpGuard)>
+    where
+        S: Subscriber + for<'span> LookupSpan<'span> + Send + Sync,
+    {
+        let exporter = SpanExporter::builder()
+            .with_tonic()
+            .with_endpoint(endpoint)
+            .build()
+            .map_err(|e| {
+                WebMockError::config(format!("Invalid OTLP endpoint '{}': {}", endpoint, e))
+            })?;
+        let provider = SdkTracerProvider::builder()
+            .with_batch_exporter(exporter)
+            .with_resource(Resource::builder().with_service_name("webmock").build())
+            .build();
+
+        let layer = tracing_opentelemetry::layer()
+            .with_tracer(provider.tracer("webmock"))
+            .with_filter(Targets::new().with_target(SPAN_TARGET, LevelFilter::INFO));
+        Ok((Box::new(layer), OtlpGuard { provider }))
+    }
+}
@@ -0,0 +1,210 @@
+use crate::capture::proxy::records::RequestRecord;
+use crate::serve::absolute_urls::{entry_origins, relativize};
+use crate::serve::{
+    count_absolute_urls, rewrite_absolute_urls, MockServer, OriginMap, OutputLevel, ServeOptions,
+};
+use crate::storage::Snapshot;
+use crate::test_utils::test_helpers::create_test_record;
+use chrono::Utc;
+use tokio::time::{timeout, Duration};
+
+const PAGE: &str = r#"<!doctype html>
+<link rel="stylesheet" href="https://app.example.com/css/site.css">
+<script src="//app.example.com/asset.js?v=2"></script>
+<img src="http://APP.example.com/logo.png">
+<a href="https://app.example.com">home</a>
+<script src="https://cdn.example.com/lib.js"></script>
+<a href="https://app.example.com.evil.test/phish">x</a>
+"#;
+
+const STYLESHEET: &str = r#"@font-face { src: url(https://app.example.com/fonts/a.woff2); }
+.hero { background: url('https://app.example.com/img/hero.jpg'); }
+.map { background: url("https://maps.example.net/tile.png"); }
+"#;
+
+const SCRIPT: &str = r#"const api = "https://app.example.com/api/v1";
+const base = "https://app.example.com";
+fetch(`https://app.example.com/api/me#${id}`);
+fetch("https://app.example.com:8443/admin");
+"#;
+
+fn record(url: &str, content_type: &str, body: &str) -> RequestRecord {
+    let headers = [("content-type", content_type)];
+    create_test_record("GET", url, 200, &headers, body.as_bytes())
+}
+
+fn snapshot() -> Snapshot {
+    Snapshot {
+        name: "absolute".to_string(),
+        url: "https://app.example.com/".to_string(),
+        created_at: Utc::now(),
+        requests: vec![
+            record("https://app.example.com/", "text/html", PAGE),
+            record(
+                "https://app.example.com/css/site.css",
+                "text/css",
+                STYLESHEET,
+            ),
+            record(
+                "https://app.example.com/asset.js",
+                "application/javascript",
+                SCRIPT,
+            ),
+            // Not an entry origin; a path here would resolve against the CDN
+            record(
+                "https://cdn.example.com/lib.js",
+                "application/javascript",
+                "load('https://cdn.example.com/chunk.js')",
+            ),
+            record(
+                "https://app.example.com/logo.png",
+                "image/png",
+                "https://app.example.com/not-text",
+            ),
+        ],
+        ..Default::default()
+    }
+}
+
+fn body(snapshot: &Snapshot, url: &str) -> String {
+    let record = snapshot
+        .requests
+        .iter()
+        .find(|record| record.url == url)
+        .unwrap();
+    String::from_utf8(record.response.body.clone()).unwrap()
+}
+
+#[test]
+fn test_relativize_html() {
+    let (html, count) = relativize(PAGE, "app.example.com").unwrap();
+    assert_eq!(count, 3);
+    assert!(html.contains(r#"href="/css/site.css""#));
+    assert!(html.contains(r#"src="/asset.js?v=2""#));
+    // Upgraded from http:// by going relative
+    assert!(html.contains(r#"src="/logo.png""#));
+    // Bare origins, other hosts and lookalike hosts stay
+    assert!(html.contains(r#"href="https://app.example.com">"#));
+    assert!(html.contains("https://cdn.example.com/lib.js"));
+    assert!(html.contains("https://app.example.com.evil.test/phish"));
+}
+
+#[test]
+fn test_relativize_css() {
+    let (css, count) = relativize(STYLESHEET, "app.example.com").unwrap();
+    assert_eq!(count, 2);
+    assert!(css.contains("url(/fonts/a.woff2)"));
+    assert!(css.contains("url('/img/hero.jpg')"));
+    assert!(css.contains("https://maps.example.net/tile.png"));
+}
+
+#[test]
+fn test_relativize_js() {
+    let (js, count) = relativize(SCRIPT, "app.example.com").unwrap();
+    assert_eq!(count, 2);
+    assert_eq!(
+        js,
+        r#"const api = "/api/v1";
+const base = "https://app.example.com";
+fetch(`/api/me#${id}`);
+fetch("https://app.example.com:8443/admin");
+"#
+    );
+}
+
+#[test]
+fn test_relativize_leaves_other_urls_alone() {
+    assert!(relativize("see https://example.org/a", "app.example.com").is_none());
+    // Would become the protocol-relative `//evil.test/x`
+    assert!(relativize("https://app.example.com//evil.test/x", "app.example.com").is_none());
+    // `//` of another scheme
+    assert!(relativize("ftp://app.example.com/file", "app.example.com").is_none());
+    // A port is part of the origin
+    assert_eq!(
+        relativize("http://localhost:3000/a", "localhost:3000").unwrap(),
+        ("/a".to_string(), 1)
+    );
+}
+
+#[test]
+fn test_rewrite_limited_to_entry_origins() {
+    let mut snapshot = snapshot();
+    assert_eq!(
+        entry_origins(&snapshot).into_iter().collect::<Vec<_>>(),
+        ["https://app.example.com"]
+    );
+    assert_eq!(count_absolute_urls(&snapshot), 7);
+
+    assert_eq!(rewrite_absolute_urls(&mut snapshot), 7);
+    assert_eq!(count_absolute_urls(&snapshot), 0);
+    assert!(body(&snapshot, "https://app.example.com/").contains(r#"href="/css/site.css""#));
+    assert_eq!(
+        body(&snapshot, "https://cdn.example.com/lib.js"),
+        "load('https://cdn.example.com/chunk.js')"
+    );
+    assert_eq!(
+        body(&snapshot, "https://app.example.com/logo.png"),
+        "https://app.example.com/not-text"
+    );
+
+    // Another entry point adds its origin
+    let mut snapshot = self::snapshot();
+    snapshot
+        .entry_points
+        .push("https://cdn.example.com/".to_string());
+    assert_eq!(count_absolute_urls(&snapshot), 8);
+}
+
+#[test]
+fn test_rewrite_skips_compressed_bodies() {
+    let mut snapshot = snapshot();
+    snapshot.requests[1]
+        .response
+        .headers
+        .insert("Content-Encoding".to_string(), "gzip".to_string());
+    assert_eq!(rewrite_absolute_urls(&mut snapshot), 5);
+    assert_eq!(
+        body(&snapshot, "https://app.example.com/css/site.css"),
+        STYLESHEET
+    );
+}
+
+#[tokio::test]
+async fn test_serve_rewrites_absolute_urls_for_direct_serving() {
+    let options = ServeOptions {
+        output: OutputLevel::Quiet,
+        banner: false,
+        origin_map: OriginMap::parse(&["https://app.example.com"]).unwrap(),
+        rewrite_absolute_urls: true,
+        ..ServeOptions::default()
+    };
+    let (ready_tx, ready_rx) = tokio::sync::oneshot::channel();
+    let server = tokio::spawn(async move {
+        MockServer::with_options(snapshot(), options)
+            .start_with_ready(0, ready_tx)
+            .await
+    });
+    let addr = timeout(Duration::from_secs(5), ready_rx)
+        .await
+        .expect("Server did not signal readiness")
+        .expect("Ready sender dropped");
+
+    let client = reqwest::Client::builder().no_proxy().build().unwrap();
+    let base = format!("http://{}", addr);
+
+    let page = client.get(format!("{}/", base)).send().await.unwrap();
+    let html = page.text().await.unwrap();
+    assert!(html.contains(r#"src="/asset.js?v=2""#));
+
+    // The rewritten reference is served directly
+    let script = client
+        .get(format!("{}/asset.js", base))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(script.status(), 200);
+    let js = script.text().await.unwrap();
+    assert!(js.starts_with(r#"const api = "/api/v1";"#));
+
+    server.abort();
+}
@@ -0,0 +1,112 @@
+use crate::capture::proxy::records::RequestRecord;
+use crate::capture::proxy::RedactionRules;
+use crate::serve::body_log::BodyLogging;
+use crate::test_utils::test_helpers::create_test_record;
+use hyper::header::{HeaderMap, HeaderValue};
+
+fn record(content_type: &str, body: &[u8]) -> RequestRecord {
+    let headers = [("content-type", content_type)];
+    create_test_record("POST", "https://api.example.com/login", 200, &headers, body)
+}
+
+fn json_headers() -> HeaderMap {
+    let mut headers = HeaderMap::new();
+    headers.insert("content-type", HeaderValue::from_static("application/json"));
+    headers.insert("authorization", HeaderValue::from_static("Bearer secret"));
+    headers
+}
+
+fn logging(max_bytes: usize) -> BodyLogging {
+    BodyLogging {
+        max_bytes,
+        redaction: RedactionRules::new()
+            .redact_header("Authorization")
+            .redact_body_pattern(r#""password":"[^"]*""#)
+            .unwrap(),
+    }
+}
+
+#[test]
+fn test_text_bodies_are_redacted() {
+    let record = record("application/json", br#"{"token":"abc"}"#);
+    let entry = logging(1024).format(
+        "POST",
+        "https://api.example.com/login",
+        &json_headers(),
+        Some(&br#"{"user":"ada","password":"hunter2"}"#[..]),
+        Some(&record),
+    );
+
+    assert!(entry.starts_with("📝 POST https://api.example.com/login → 200"));
+    assert!(entry.contains("authorization: [REDACTED]"));
+    assert!(!entry.contains("Bearer secret"));
+    assert!(entry.contains(r#"{"user":"ada",[REDACTED]}"#));
+    assert!(!entry.contains("hunter2"));
+    assert!(entry.contains(r#"response body (application/json, 15 B): {"token":"abc"}"#));
+}
+
+#[test]
+fn test_text_bodies_are_truncated_after_redaction() {
+    let record = record("text/plain", "héllo world".as_bytes());
+    let entry = logging(2).format(
+        "GET",
+        "https://example.com/",
+        &HeaderMap::new(),
+        None,
+        Some(&record),
+    );
+
+    // The cut never splits a character
+    assert!(entry.contains("response body (text/plain, 12 B): h … (11 more bytes)"));
+    assert!(entry.contains("request body: none"));
+}
+
+#[test]
+fn test_binary_bodies_are_logged_by_size_and_hash() {
+    let record = record("image/png", &[0x89, b'P', b'N', b'G', 0, 1, 2, 3]);
+    let entry = logging(1024).format(
+        "GET",
+        "https://example.com/logo.png",
+        &HeaderMap::new(),
+        None,
+        Some(&record),
+    );
+
+    assert!(entry.contains("response body (image/png, 8 B, sha256 "));
+    assert!(!entry.contains("PNG"));
+}
+
+#[test]
+fn test_encoded_text_bodies_are_not_shown() {
+    let mut record = record("text/html", b"<p>compressed</p>");
+    record
+        .response
+        .headers
+        .insert("content-encoding".to_string(), "gzip".to_string());
+    let entry = logging(1024).format(
+        "GET",
+        "https://example.com/",
+        &HeaderMap::new(),
+        None,
+        Some(&record),
+    );
+
+    assert!(entry.contains("response body (text/html, "));
+    assert!(entry.contains("sha256"));
+    assert!(!entry.contains("compressed"));
+}
+
+#[test]
+fn test_unmatched_requests_log_no_response() {
+    let entry = logging(1024).format(
+        "POST",
+        "https://api.example.com/missing",
+        &json_headers(),
+        Some(&br#"{"id":1}"#[..]),
+        None,
+    );
+
+    assert!(entry.starts_with("📝 POST https://api.example.com/missing → 404"));
+    assert!(entry.contains(r#"request body (application/json, 8 B): {"id":1}"#));
+    assert!(entry.contains("response: no record"));
+}
@@ -0,0 +1,119 @@
+use crate::serve::console::{ConsoleWriter, OutputLevel, RequestLine, QUEUE_LEN};
+use std::io::Write;
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+
+/// Sink that collects everything written to it
+#[derive(Clone, Default)]
+struct SharedBuffer(Arc<Mutex<Vec<u8>>>);
+
+impl SharedBuffer {
+    fn contents(&self) -> String {
+        String::from_utf8(self.0.lock().unwrap().clone()).unwrap()
+    }
+}
+
+impl Write for SharedBuffer {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.0.lock().unwrap().extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+fn request_line(index: usize) -> RequestLine {
+    RequestLine {
+        icon: "✅",
+        method: "GET".to_string(),
+        url: format!("https://example.com/{}", index),
+        status: 200,
+        detail: "text/html".to_string(),
+        record_index: Some(index),
+        body_size: Some(2048),
+        started: Instant::now(),
+    }
+}
+
+#[tokio::test]
+async fn test_console_writer_preserves_order() {
+    let buffer = SharedBuffer::default();
+    let console = ConsoleWriter::new(buffer.clone(), OutputLevel::Normal);
+
+    for i in 0..500 {
+        console.request(request_line(i));
+    }
+    console.flush().await;
+
+    let contents = buffer.contents();
+    let lines: Vec<&str> = contents.lines().collect();
+    assert_eq!(lines.len(), 500);
+    for (i, line) in lines.iter().enumerate() {
+        assert_eq!(
+            *line,
+            format!("✅ GET https://example.com/{} → 200 (text/html)", i)
+        );
+    }
+}
+
+#[tokio::test]
+async fn test_console_writer_shutdown_flushes_pending_lines() {
+    let buffer = SharedBuffer::default();
+    let console = ConsoleWriter::new(buffer.clone(), OutputLevel::Normal);
+
+    console.line("first");
+    console.line("second");
+    console.shutdown().await;
+
+    assert_eq!(buffer.contents(), "first\nsecond\n");
+}
+
+#[tokio::test]
+async fn test_console_writer_quiet_skips_request_lines() {
+    let buffer = SharedBuffer::default();
+    let console = ConsoleWriter::new(buffer.clone(), OutputLevel::Quiet);
+
+    console.request(request_line(0));
+    console.line("Server stopped");
+    console.flush().await;
+
+    assert_eq!(buffer.contents(), "Server stopped\n");
+}
+
+#[tokio::test]
+async fn test_console_writer_drops_and_counts_lines_it_cannot_queue() {
+    let buffer = SharedBuffer::default();
+    let console = ConsoleWriter::new(buffer.clone(), OutputLevel::Normal);
+
+    // The writer thread blocks on the sink until it is released
+    let sent = 3 * QUEUE_LEN;
+    let held = buffer.0.lock().unwrap();
+    for i in 0..sent {
+        console.line(format!("line {}", i));
+    }
+    drop(held);
+    console.shutdown().await;
+
+    let contents = buffer.contents();
+    let written = contents
+        .lines()
+        .filter(|line| line.starts_with("line "))
+        .count();
+    let dropped: usize = contents
+        .lines()
+        .filter_map(|line| line.strip_prefix("⚠️  Dropped "))
+        .map(|report| report.split(' ').next().unwrap().parse::<usize>().unwrap())
+        .sum();
+    assert!(dropped >= QUEUE_LEN, "{}", contents);
+    assert_eq!(written + dropped, sent);
+}
+
+#[test]
+fn test_request_line_verbose_format() {
+    let line = request_line(2).format(OutputLevel::Verbose);
+
+    assert!(line.starts_with("✅ GET https://example.com/2 → 200 (text/html) [record #3, "));
+    assert!(line.ends_with("ms, 2.0 KB]"));
+}
@@ -0,0 +1,101 @@
+use crate::capture::proxy::records::RequestRecord;
+use crate::serve::{CoverageReport, MockServer, OutputLevel, RecordHits, ServeOptions};
+use crate::test_utils::test_helpers::{create_test_record, create_test_snapshot_with_name};
+use std::collections::HashMap;
+use std::sync::Arc;
+use tempfile::TempDir;
+use tokio::time::{timeout, Duration};
+
+fn page(url: &str, sequence: Option<u64>) -> RequestRecord {
+    let mut record = create_test_record("GET", url, 200, &[], b"ok");
+    record.sequence = sequence;
+    record
+}
+
+#[test]
+fn test_record_hits_report_by_sequence() {
+    let mut snapshot = create_test_snapshot_with_name("shop");
+    snapshot.requests = vec![
+        page("http://shop.example/", Some(7)),
+        page("http://shop.example/cart", Some(3)),
+        page("http://shop.example/built-in-memory", None),
+    ];
+    let hits = RecordHits::new(snapshot.requests.len());
+    hits.record_hit(0);
+    hits.record_hit(0);
+    hits.record_hit(2);
+    // Positions past the last record are ignored
+    hits.record_hit(9);
+
+    let report = hits.report(&snapshot, "abc123");
+    assert_eq!(report.snapshot, "shop");
+    assert_eq!(report.sha256, "abc123");
+    assert_eq!(report.records.len(), 2);
+    assert_eq!(report.records[0].url, "http://shop.example/");
+    assert_eq!(report.hits_by_sequence(), HashMap::from([(7, 2), (3, 0)]));
+}
+
+#[test]
+fn test_coverage_report_round_trips_as_json() {
+    let temp_dir = TempDir::new().unwrap();
+    let mut snapshot = create_test_snapshot_with_name("shop");
+    snapshot.requests = vec![page("http://shop.example/", Some(0))];
+    let hits = RecordHits::new(1);
+    hits.record_hit(0);
+    let report = hits.report(&snapshot, "abc123");
+
+    let path = temp_dir.path().join("coverage.json");
+    report.write(&path).unwrap();
+    assert_eq!(CoverageReport::load(&path).unwrap(), report);
+
+    std::fs::write(&path, "{\"hosts\": []}").unwrap();
+    let error = CoverageReport::load(&path).unwrap_err().to_string();
+    assert!(error.contains("not a coverage report"), "{}", error);
+    assert!(CoverageReport::load(&temp_dir.path().join("missing.json")).is_err());
+}
+
+#[tokio::test]
+async fn test_mock_server_counts_hits_per_record() {
+    let mut snapshot = create_test_snapshot_with_name("shop");
+    snapshot.requests = vec![
+        page("http://shop.example/", Some(0)),
+        page("http://shop.example/cart", Some(1)),
+        page("http://shop.example/unused", Some(2)),
+    ];
+    let options = ServeOptions {
+        output: OutputLevel::Quiet,
+        banner: false,
+        ..ServeOptions::default()
+    };
+    let server = Arc::new(MockServer::with_options(snapshot, options));
+    let (ready_tx, ready_rx) = tokio::sync::oneshot::channel();
+    let running = {
+        let server = Arc::clone(&server);
+        tokio::spawn(async move { server.start_with_ready(0, ready_tx).await })
+    };
+    let addr = timeout(Duration::from_secs(5), ready_rx)
+        .await
+        .expect("Server did not signal readiness")
+        .expect("Ready sender dropped");
+    let client = reqwest::Client::builder()
+        .proxy(reqwest::Proxy::http(format!("http://{}", addr)).unwrap())
+        .build()
+        .unwrap();
+
+    for url in [
+        "http://shop.example/",
+        "http://shop.example/",
+        "http://shop.example/cart",
+        "http://shop.example/missing",
+    ] {
+        client.get(url).send().await.unwrap();
+    }
+
+    let report = server.coverage_report("abc123").unwrap();
+    assert_eq!(
+        report.hits_by_sequence(),
+        HashMap::from([(0, 2), (1, 1), (2, 0)])
+    );
+
+    running.abort();
+}
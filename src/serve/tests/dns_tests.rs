@@ -0,0 +1,290 @@
+use crate::capture::proxy::records::{RequestRecord, ResponseRecord, TunnelRecord};
+use crate::serve::console::{ConsoleWriter, OutputLevel};
+use crate::serve::dns::{
+    error_response, parse_dns_upstream, snapshot_hosts, DnsQuery, DnsResponder, QueryError,
+    Resolution, RCODE_FORMERR, RCODE_NOERROR, RCODE_NOTIMP, RCODE_NXDOMAIN, TYPE_A, TYPE_AAAA,
+};
+use crate::test_utils::test_helpers::create_test_snapshot;
+use std::collections::{BTreeSet, HashMap};
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
+use std::sync::Arc;
+use tokio::net::UdpSocket;
+use tokio::time::{timeout, Duration};
+
+const LAN_IP: IpAddr = IpAddr::V4(Ipv4Addr::new(192, 168, 1, 20));
+
+/// `api.example.com`, A, IN, recursion desired, ID 0x1234
+const QUERY_A: &[u8] = &[
+    0x12, 0x34, 0x01, 0x00, 0x00, 0x01, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, // header
+    0x03, b'a', b'p', b'i', // api
+    0x07, b'e', b'x', b'a', b'm', b'p', b'l', b'e', // example
+    0x03, b'c', b'o', b'm', 0x00, // com
+    0x00, 0x01, 0x00, 0x01, // A, IN
+];
+
+/// The answer to `QUERY_A` with 192.168.1.20
+const RESPONSE_A: &[u8] = &[
+    0x12, 0x34, 0x85, 0x00, 0x00, 0x01, 0x00, 0x01, 0x00, 0x00, 0x00, 0x00, // header
+    0x03, b'a', b'p', b'i', 0x07, b'e', b'x', b'a', b'm', b'p', b'l', b'e', 0x03, b'c', b'o', b'm',
+    0x00, 0x00, 0x01, 0x00, 0x01, // question
+    0xc0, 0x0c, 0x00, 0x01, 0x00, 0x01, // name pointer, A, IN
+    0x00, 0x00, 0x00, 0x3c, 0x00, 0x04, // TTL 60, 4 bytes
+    0xc0, 0xa8, 0x01, 0x14, // 192.168.1.20
+];
+
+/// `ads.tracker.net`, A, IN, recursion desired, ID 0xbeef
+const QUERY_OTHER: &[u8] = &[
+    0xbe, 0xef, 0x01, 0x00, 0x00, 0x01, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, // header
+    0x03, b'a', b'd', b's', // ads
+    0x07, b't', b'r', b'a', b'c', b'k', b'e', b'r', // tracker
+    0x03, b'n', b'e', b't', 0x00, // net
+    0x00, 0x01, 0x00, 0x01, // A, IN
+];
+
+/// A query like `QUERY_A` for another type or name
+fn query_for(qtype: u16, name: &str) -> Vec<u8> {
+    let mut packet = QUERY_A[..12].to_vec();
+    for label in name.split('.') {
+        packet.push(label.len() as u8);
+        packet.extend_from_slice(label.as_bytes());
+    }
+    packet.push(0);
+    packet.extend_from_slice(&qtype.to_be_bytes());
+    packet.extend_from_slice(&[0x00, 0x01]);
+    packet
+}
+
+fn hosts() -> BTreeSet<String> {
+    ["api.example.com", "example.com"]
+        .into_iter()
+        .map(String::from)
+        .collect()
+}
+
+fn responder(address: IpAddr, upstream: Option<SocketAddr>) -> DnsResponder {
+    let console = Arc::new(ConsoleWriter::new(std::io::sink(), OutputLevel::Quiet));
+    DnsResponder::new(hosts(), address, upstream, console)
+}
+
+#[test]
+fn test_parse_query() {
+    let query = DnsQuery::parse(QUERY_A).unwrap();
+    assert_eq!(query.id, 0x1234);
+    assert_eq!(query.name, "api.example.com");
+    assert_eq!(query.qtype, TYPE_A);
+    assert_eq!(query.type_name(), "A");
+
+    let query = DnsQuery::parse(&query_for(TYPE_AAAA, "API.Example.COM")).unwrap();
+    assert_eq!(query.name, "api.example.com");
+    assert_eq!(query.type_name(), "AAAA");
+}
+
+#[test]
+fn test_encode_a_answer() {
+    let query = DnsQuery::parse(QUERY_A).unwrap();
+    assert_eq!(query.response(RCODE_NOERROR, Some(LAN_IP)), RESPONSE_A);
+}
+
+#[test]
+fn test_encode_aaaa_answer() {
+    let query = DnsQuery::parse(&query_for(TYPE_AAAA, "api.example.com")).unwrap();
+    let response = query.response(RCODE_NOERROR, Some(IpAddr::V6(Ipv6Addr::LOCALHOST)));
+
+    // Header, question, then the record: pointer, AAAA, IN, TTL and 16 bytes
+    assert_eq!(&response[6..8], &[0x00, 0x01]);
+    let record = &response[QUERY_A.len()..];
+    assert_eq!(&record[..4], &[0xc0, 0x0c, 0x00, 0x1c]);
+    assert_eq!(&record[10..12], &[0x00, 0x10]);
+    assert_eq!(&record[12..], &Ipv6Addr::LOCALHOST.octets());
+}
+
+#[test]
+fn test_encode_nxdomain() {
+    let query = DnsQuery::parse(QUERY_OTHER).unwrap();
+    let response = query.response(RCODE_NXDOMAIN, None);
+    assert_eq!(
+        &response[..12],
+        &[0xbe, 0xef, 0x85, 0x03, 0x00, 0x01, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00]
+    );
+    assert_eq!(&response[12..], &QUERY_OTHER[12..]);
+}
+
+#[test]
+fn test_malformed_queries() {
+    // Too short for a header, and responses, are dropped
+    assert_eq!(DnsQuery::parse(&QUERY_A[..8]), Err(QueryError::Ignored));
+    assert_eq!(DnsQuery::parse(RESPONSE_A), Err(QueryError::Ignored));
+
+    // Truncated question
+    assert_eq!(
+        DnsQuery::parse(&QUERY_A[..20]),
+        Err(QueryError::Rcode {
+            id: 0x1234,
+            flags: 0x0100,
+            rcode: RCODE_FORMERR
+        })
+    );
+
+    // Two questions
+    let mut packet = QUERY_A.to_vec();
+    packet[5] = 2;
+    assert!(matches!(
+        DnsQuery::parse(&packet),
+        Err(QueryError::Rcode {
+            rcode: RCODE_FORMERR,
+            ..
+        })
+    ));
+
+    // A compression pointer in the question
+    let mut packet = QUERY_A[..12].to_vec();
+    packet.extend_from_slice(&[0xc0, 0x0c, 0x00, 0x01, 0x00, 0x01]);
+    assert!(matches!(
+        DnsQuery::parse(&packet),
+        Err(QueryError::Rcode {
+            rcode: RCODE_FORMERR,
+            ..
+        })
+    ));
+}
+
+#[test]
+fn test_unsupported_opcode() {
+    // UPDATE
+    let mut packet = QUERY_A.to_vec();
+    packet[2] = 0x28;
+    let Err(QueryError::Rcode { id, flags, rcode }) = DnsQuery::parse(&packet) else {
+        panic!("Expected an error response");
+    };
+    assert_eq!(rcode, RCODE_NOTIMP);
+    assert_eq!(
+        error_response(id, flags, rcode),
+        [0x12, 0x34, 0xac, 0x04, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00]
+    );
+}
+
+#[test]
+fn test_resolve_is_scoped_to_snapshot_hosts() {
+    let responder = responder(LAN_IP, None);
+
+    let query = DnsQuery::parse(QUERY_A).unwrap();
+    assert_eq!(responder.resolve(&query), Resolution::Local(Some(LAN_IP)));
+
+    // No IPv6 address to give, so no records rather than NXDOMAIN
+    let query = DnsQuery::parse(&query_for(TYPE_AAAA, "api.example.com")).unwrap();
+    assert_eq!(responder.resolve(&query), Resolution::Local(None));
+
+    // Subdomains of snapshot hosts aren't snapshot hosts
+    let query = DnsQuery::parse(&query_for(TYPE_A, "cdn.api.example.com")).unwrap();
+    assert_eq!(responder.resolve(&query), Resolution::NxDomain);
+
+    let query = DnsQuery::parse(QUERY_OTHER).unwrap();
+    assert_eq!(responder.resolve(&query), Resolution::NxDomain);
+
+    let upstream: SocketAddr = "10.0.0.1:53".parse().unwrap();
+    let responder = self::responder(LAN_IP, Some(upstream));
+    assert_eq!(responder.resolve(&query), Resolution::Forward(upstream));
+}
+
+#[test]
+fn test_snapshot_hosts() {
+    let mut snapshot = create_test_snapshot();
+    for url in [
+        "https://API.example.com/users",
+        "http://10.0.0.5:8080/health",
+        "https://[::1]/",
+        "https://cdn.example.net./app.js",
+    ] {
+        snapshot.requests.push(RequestRecord::new(
+            "GET".to_string(),
+            url.to_string(),
+            HashMap::new(),
+            None,
+            ResponseRecord::new(200, HashMap::new(), Vec::new(), None),
+        ));
+    }
+    snapshot
+        .tunnels
+        .push(TunnelRecord::pinned("pinned.example.org:443"));
+    snapshot
+        .tunnels
+        .push(TunnelRecord::pinned("192.168.0.9:443"));
+
+    let hosts: Vec<String> = snapshot_hosts(&snapshot).into_iter().collect();
+    assert_eq!(
+        hosts,
+        [
+            "api.example.com",
+            "cdn.example.net",
+            "example.com",
+            "pinned.example.org"
+        ]
+    );
+}
+
+#[test]
+fn test_parse_dns_upstream() {
+    assert_eq!(
+        parse_dns_upstream("192.168.1.1").unwrap(),
+        "192.168.1.1:53".parse::<SocketAddr>().unwrap()
+    );
+    assert_eq!(
+        parse_dns_upstream("1.1.1.1:5353").unwrap(),
+        "1.1.1.1:5353".parse::<SocketAddr>().unwrap()
+    );
+    assert_eq!(
+        parse_dns_upstream("[::1]:53").unwrap(),
+        "[::1]:53".parse::<SocketAddr>().unwrap()
+    );
+    assert!(parse_dns_upstream("dns.google").is_err());
+}
+
+/// Send `packet` to the responder and wait for its reply
+async fn exchange(server: SocketAddr, packet: &[u8]) -> Vec<u8> {
+    let client = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+    client.send_to(packet, server).await.unwrap();
+    let mut buf = [0u8; 512];
+    let (len, _) = timeout(Duration::from_secs(5), client.recv_from(&mut buf))
+        .await
+        .expect("DNS reply timed out")
+        .unwrap();
+    buf[..len].to_vec()
+}
+
+#[tokio::test]
+async fn test_responder_answers_over_udp() {
+    let socket = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+    let server = socket.local_addr().unwrap();
+    let task = tokio::spawn(responder(LAN_IP, None).serve(socket));
+
+    assert_eq!(exchange(server, QUERY_A).await, RESPONSE_A);
+    let nxdomain = exchange(server, QUERY_OTHER).await;
+    assert_eq!(nxdomain[3] & 0x0f, RCODE_NXDOMAIN);
+
+    task.abort();
+}
+
+#[tokio::test]
+async fn test_responder_relays_other_hosts_upstream() {
+    // A resolver that answers every query with 10.1.2.3
+    let upstream = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+    let upstream_addr = upstream.local_addr().unwrap();
+    let resolver = tokio::spawn(async move {
+        let mut buf = [0u8; 512];
+        let (len, peer) = upstream.recv_from(&mut buf).await.unwrap();
+        let query = DnsQuery::parse(&buf[..len]).unwrap();
+        let reply = query.response(RCODE_NOERROR, Some(IpAddr::V4(Ipv4Addr::new(10, 1, 2, 3))));
+        upstream.send_to(&reply, peer).await.unwrap();
+    });
+
+    let socket = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+    let server = socket.local_addr().unwrap();
+    let task = tokio::spawn(responder(LAN_IP, Some(upstream_addr)).serve(socket));
+
+    let reply = exchange(server, QUERY_OTHER).await;
+    assert_eq!(&reply[..2], &[0xbe, 0xef]);
+    assert_eq!(&reply[reply.len() - 4..], &[10, 1, 2, 3]);
+
+    resolver.await.unwrap();
+    task.abort();
+}
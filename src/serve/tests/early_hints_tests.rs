@@ -0,0 +1,249 @@
+use crate::capture::proxy::records::{InterimResponse, RequestRecord, ResponseRecord};
+use crate::serve::early_hints::{encode_interim, EarlyHints, InterimIo, InterimQueue};
+use crate::serve::{MockServer, OutputLevel, ServeOptions};
+use crate::storage::Snapshot;
+use crate::test_utils::test_helpers::create_test_snapshot_with_name;
+use bytes::Bytes;
+use http_body_util::{BodyExt, Empty};
+use hyper::{Request, Response, Version};
+use hyper_util::rt::TokioIo;
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpStream;
+use tokio::time::{timeout, Duration};
+
+const PAGE: &str = "http://hints.example/";
+
+fn early_hints() -> InterimResponse {
+    InterimResponse {
+        status: 103,
+        headers: vec![
+            (
+                "link".to_string(),
+                "</app.css>; rel=preload; as=style".to_string(),
+            ),
+            (
+                "link".to_string(),
+                "</app.js>; rel=preload; as=script".to_string(),
+            ),
+        ],
+    }
+}
+
+fn snapshot() -> Snapshot {
+    let mut headers = HashMap::new();
+    headers.insert("content-type".to_string(), "text/html".to_string());
+    let mut snapshot = create_test_snapshot_with_name("early-hints");
+    snapshot.requests = vec![RequestRecord::new(
+        "GET".to_string(),
+        PAGE.to_string(),
+        HashMap::new(),
+        None,
+        ResponseRecord::new(200, headers, b"<html>hinted</html>".to_vec(), None)
+            .with_informational(vec![early_hints()]),
+    )];
+    snapshot
+}
+
+async fn start_server(replay_early_hints: bool) -> (SocketAddr, tokio::task::JoinHandle<()>) {
+    let options = ServeOptions {
+        output: OutputLevel::Quiet,
+        banner: false,
+        replay_early_hints,
+        ..ServeOptions::default()
+    };
+    let (ready_tx, ready_rx) = tokio::sync::oneshot::channel();
+    let server = tokio::spawn(async move {
+        let _ = MockServer::with_options(snapshot(), options)
+            .start_with_ready(0, ready_tx)
+            .await;
+    });
+    let addr = timeout(Duration::from_secs(5), ready_rx)
+        .await
+        .expect("Server did not signal readiness")
+        .expect("Ready sender dropped");
+    (addr, server)
+}
+
+/// Request the page through the server with a hyper client, returning the
+/// interim responses it saw as `(status, link headers)` and the final status and body
+async fn fetch_with_hyper(addr: SocketAddr) -> (Vec<(u16, Vec<String>)>, u16, String) {
+    let stream = TcpStream::connect(addr).await.unwrap();
+    let (mut sender, connection) = hyper::client::conn::http1::handshake(TokioIo::new(stream))
+        .await
+        .unwrap();
+    tokio::spawn(connection);
+
+    let interim = Arc::new(Mutex::new(Vec::new()));
+    let seen = Arc::clone(&interim);
+    let mut request = Request::get(PAGE)
+        .header(hyper::header::HOST, "hints.example")
+        .body(Empty::<Bytes>::new())
+        .unwrap();
+    hyper::ext::on_informational(&mut request, move |response| {
+        let links = response
+            .headers()
+            .get_all(hyper::header::LINK)
+            .iter()
+            .map(|value| value.to_str().unwrap().to_string())
+            .collect();
+        seen.lock()
+            .unwrap()
+            .push((response.status().as_u16(), links));
+    });
+
+    let response = sender.send_request(request).await.unwrap();
+    let status = response.status().as_u16();
+    let body = response.into_body().collect().await.unwrap().to_bytes();
+    let interim = interim.lock().unwrap().clone();
+    (interim, status, String::from_utf8_lossy(&body).into_owned())
+}
+
+/// Send a raw request and read everything the server answers until it closes
+async fn fetch_raw(addr: SocketAddr, request: &str) -> String {
+    let mut stream = TcpStream::connect(addr).await.unwrap();
+    stream.write_all(request.as_bytes()).await.unwrap();
+    let mut answer = Vec::new();
+    timeout(Duration::from_secs(5), stream.read_to_end(&mut answer))
+        .await
+        .expect("Server kept the connection open")
+        .unwrap();
+    String::from_utf8_lossy(&answer).into_owned()
+}
+
+#[test]
+fn test_encode_interim_keeps_repeated_headers_in_order() {
+    let encoded = String::from_utf8(encode_interim(&[early_hints()])).unwrap();
+    assert_eq!(
+        encoded,
+        "HTTP/1.1 103 Early Hints\r\n\
+         link: </app.css>; rel=preload; as=style\r\n\
+         link: </app.js>; rel=preload; as=script\r\n\
+         \r\n"
+    );
+}
+
+#[test]
+fn test_encode_interim_skips_what_cannot_be_sent() {
+    let interim = |status: u16, headers: &[(&str, &str)]| InterimResponse {
+        status,
+        headers: headers
+            .iter()
+            .map(|(name, value)| (name.to_string(), value.to_string()))
+            .collect(),
+    };
+    // Continue and Switching Protocols belong to their own exchange; 200 isn't interim
+    assert!(encode_interim(&[interim(100, &[]), interim(101, &[]), interim(200, &[])]).is_empty());
+
+    // A hand-edited snapshot can't smuggle in another response
+    let encoded = encode_interim(&[interim(
+        103,
+        &[
+            ("bad name", "x"),
+            ("link", "</a>\r\nX-Evil: 1"),
+            ("link", "</b>"),
+        ],
+    )]);
+    assert_eq!(
+        String::from_utf8(encoded).unwrap(),
+        "HTTP/1.1 103 Early Hints\r\nlink: </b>\r\n\r\n"
+    );
+}
+
+#[tokio::test]
+async fn test_queued_hints_are_written_before_the_response() {
+    for (version, hinted) in [
+        (Version::HTTP_11, true),
+        (Version::HTTP_10, false),
+        (Version::HTTP_2, false),
+    ] {
+        let queue = InterimQueue::default();
+        let (client, mut server) = tokio::io::duplex(4096);
+        let mut io = InterimIo::new(client, queue.clone());
+
+        let mut response = Response::new(());
+        response
+            .extensions_mut()
+            .insert(EarlyHints(vec![early_hints()]));
+        queue.take_from(version, &mut response);
+        assert!(response.extensions().get::<EarlyHints>().is_none());
+
+        io.write_all(b"HTTP/1.1 200 OK\r\n\r\n").await.unwrap();
+        drop(io);
+        let mut written = String::new();
+        server.read_to_string(&mut written).await.unwrap();
+        assert_eq!(
+            written.starts_with("HTTP/1.1 103 Early Hints\r\n"),
+            hinted,
+            "{:?}: {}",
+            version,
+            written
+        );
+        assert!(written.ends_with("HTTP/1.1 200 OK\r\n\r\n"));
+    }
+}
+
+#[tokio::test]
+async fn test_replays_early_hints_to_http11_clients() {
+    let (addr, server) = start_server(true).await;
+
+    let (interim, status, body) = fetch_with_hyper(addr).await;
+    assert_eq!(
+        interim,
+        vec![(
+            103,
+            vec![
+                "</app.css>; rel=preload; as=style".to_string(),
+                "</app.js>; rel=preload; as=script".to_string(),
+            ]
+        )]
+    );
+    assert_eq!(status, 200);
+    assert_eq!(body, "<html>hinted</html>");
+
+    server.abort();
+}
+
+#[tokio::test]
+async fn test_early_hints_are_only_replayed_when_asked() {
+    let (addr, server) = start_server(false).await;
+
+    let (interim, status, body) = fetch_with_hyper(addr).await;
+    assert!(interim.is_empty());
+    assert_eq!(status, 200);
+    assert_eq!(body, "<html>hinted</html>");
+
+    server.abort();
+}
+
+#[tokio::test]
+async fn test_http10_clients_get_only_the_final_response() {
+    let (addr, server) = start_server(true).await;
+
+    let answer = fetch_raw(
+        addr,
+        "GET http://hints.example/ HTTP/1.0\r\nHost: hints.example\r\n\r\n",
+    )
+    .await;
+    assert!(!answer.contains(" 103 "), "{}", answer);
+    assert!(answer.contains(" 200 OK\r\n"), "{}", answer);
+    assert!(answer.ends_with("<html>hinted</html>"), "{}", answer);
+
+    // The same server hints HTTP/1.1 clients on their own connection
+    let answer = fetch_raw(
+        addr,
+        "GET http://hints.example/ HTTP/1.1\r\nHost: hints.example\r\nConnection: close\r\n\r\n",
+    )
+    .await;
+    assert!(
+        answer.starts_with("HTTP/1.1 103 Early Hints\r\n"),
+        "{}",
+        answer
+    );
+    let final_response = answer.find("HTTP/1.1 200 OK\r\n").unwrap();
+    assert!(answer[..final_response].contains("link: </app.js>; rel=preload; as=script\r\n"));
+
+    server.abort();
+}
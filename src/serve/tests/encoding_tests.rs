@@ -0,0 +1,208 @@
+use crate::capture::proxy::records::RequestRecord;
+use crate::serve::console::ConsoleWriter;
+use crate::serve::encoding::{
+    encode_response, is_compressible, negotiate, parse_encodings, CacheKey, ContentEncoding,
+};
+use crate::serve::handlers::{create_response_from_record, DefaultMatcher};
+use crate::serve::{MockServer, OutputLevel, ServeOptions, ServeState};
+use crate::storage::Snapshot;
+use crate::test_utils::test_helpers::create_test_record;
+use chrono::Utc;
+use http_body_util::BodyExt;
+use hyper::header::{HeaderMap, HeaderValue, ACCEPT_ENCODING};
+use std::io::Read;
+use std::sync::Arc;
+use tokio::time::{timeout, Duration};
+
+use ContentEncoding::{Brotli, Gzip, Zstd};
+
+fn page_body() -> Vec<u8> {
+    let mut body = "<!DOCTYPE html><html><body>".to_string();
+    for i in 0..200 {
+        body.push_str(&format!(
+            "<p>Paragraph {} of a page worth compressing</p>",
+            i
+        ));
+    }
+    body.push_str("</body></html>");
+    body.into_bytes()
+}
+
+fn record(url: &str, content_type: &str, body: Vec<u8>) -> RequestRecord {
+    create_test_record("GET", url, 200, &[("content-type", content_type)], &body)
+}
+
+fn snapshot() -> Snapshot {
+    Snapshot {
+        name: "encoded".to_string(),
+        url: "http://encoded.example/".to_string(),
+        created_at: Utc::now(),
+        requests: vec![
+            record("http://encoded.example/", "text/html", page_body()),
+            record(
+                "http://encoded.example/logo.png",
+                "image/png",
+                b"\x89PNG\r\n\x1a\n not really compressible".to_vec(),
+            ),
+        ],
+        ..Default::default()
+    }
+}
+
+fn decode(encoding: &str, body: &[u8]) -> Vec<u8> {
+    let mut decoded = Vec::new();
+    match encoding {
+        "br" => brotli::Decompressor::new(body, 4096)
+            .read_to_end(&mut decoded)
+            .unwrap(),
+        "gzip" => flate2::read::GzDecoder::new(body)
+            .read_to_end(&mut decoded)
+            .unwrap(),
+        "zstd" => return zstd::decode_all(body).unwrap(),
+        other => panic!("unexpected encoding {}", other),
+    };
+    decoded
+}
+
+#[test]
+fn test_parse_encodings() {
+    assert_eq!(parse_encodings("br, GZIP,br").unwrap(), vec![Brotli, Gzip]);
+    assert_eq!(parse_encodings("zstd").unwrap(), vec![Zstd]);
+    assert!(parse_encodings("deflate").is_err());
+    assert!(parse_encodings(" , ").is_err());
+}
+
+#[test]
+fn test_negotiate_encoding() {
+    let offered = [Brotli, Gzip];
+
+    assert_eq!(negotiate("gzip, deflate, br", &offered), Some(Brotli));
+    assert_eq!(negotiate("gzip", &offered), Some(Gzip));
+    assert_eq!(negotiate("br;q=0.5, gzip;q=0.8", &offered), Some(Gzip));
+    assert_eq!(negotiate("br;q=0, gzip", &offered), Some(Gzip));
+    assert_eq!(negotiate("*", &offered), Some(Brotli));
+    assert_eq!(negotiate("gzip;q=0, *;q=0.1", &offered), Some(Brotli));
+    assert_eq!(negotiate("*;q=0", &offered), None);
+    assert_eq!(negotiate("identity", &offered), None);
+    assert_eq!(negotiate("", &offered), None);
+    assert_eq!(negotiate("zstd", &offered), None);
+}
+
+#[test]
+fn test_compressible_content_types() {
+    assert!(is_compressible("text/html; charset=utf-8"));
+    assert!(is_compressible("application/javascript"));
+    assert!(is_compressible("application/json"));
+    assert!(is_compressible("image/svg+xml"));
+    assert!(is_compressible("application/ld+json"));
+    assert!(is_compressible("application/wasm"));
+    assert!(!is_compressible("image/png"));
+    assert!(!is_compressible("video/mp4"));
+    assert!(!is_compressible("font/woff2"));
+    assert!(!is_compressible("application/zip"));
+    assert!(!is_compressible(""));
+}
+
+#[tokio::test]
+async fn test_encoded_bodies_are_cached_per_record() {
+    let options = ServeOptions {
+        encodings: vec![Brotli, Gzip],
+        ..ServeOptions::default()
+    };
+    let state = ServeState::new(
+        snapshot(),
+        options,
+        Arc::new(ConsoleWriter::new(std::io::sink(), OutputLevel::Quiet)),
+        Arc::new(DefaultMatcher),
+    );
+    let page = &state.snapshot.requests[0];
+
+    let serve = |accept: &'static str| {
+        let mut headers = HeaderMap::new();
+        headers.insert(ACCEPT_ENCODING, HeaderValue::from_static(accept));
+        let mut response = create_response_from_record(page, &state.options);
+        response.extensions_mut().insert(CacheKey(0));
+        let state = &state;
+        async move { encode_response(state, &headers, response).await }
+    };
+
+    let first = serve("br").await;
+    let second = serve("br").await;
+    assert_eq!(first.headers()["content-encoding"], "br");
+    assert_eq!(state.encoded.misses(), 1);
+    assert_eq!(state.encoded.hits(), 1);
+    assert_eq!(
+        first.into_body().collect().await.unwrap().to_bytes(),
+        second.into_body().collect().await.unwrap().to_bytes()
+    );
+
+    // Each encoding is cached separately
+    let gzip = serve("gzip").await;
+    assert_eq!(gzip.headers()["content-encoding"], "gzip");
+    assert_eq!(state.encoded.misses(), 2);
+    assert_eq!(state.encoded.len(), 2);
+}
+
+#[tokio::test]
+async fn test_serve_negotiates_content_encoding() {
+    let options = ServeOptions {
+        output: OutputLevel::Quiet,
+        banner: false,
+        encodings: vec![Brotli, Gzip, Zstd],
+        ..ServeOptions::default()
+    };
+    let (ready_tx, ready_rx) = tokio::sync::oneshot::channel();
+    let server = tokio::spawn(async move {
+        MockServer::with_options(snapshot(), options)
+            .start_with_ready(0, ready_tx)
+            .await
+    });
+    let addr = timeout(Duration::from_secs(5), ready_rx)
+        .await
+        .expect("Server did not signal readiness")
+        .expect("Ready sender dropped");
+
+    let client = reqwest::Client::builder()
+        .proxy(reqwest::Proxy::http(format!("http://{}", addr)).unwrap())
+        .build()
+        .unwrap();
+    let get = |url: &'static str, accept: Option<&'static str>| {
+        let mut request = client.get(url);
+        if let Some(accept) = accept {
+            request = request.header("accept-encoding", accept);
+        }
+        async move { request.send().await.unwrap() }
+    };
+
+    for (accept, expected) in [
+        ("br, gzip", "br"),
+        ("gzip, deflate", "gzip"),
+        ("zstd", "zstd"),
+    ] {
+        let response = get("http://encoded.example/", Some(accept)).await;
+        assert_eq!(response.status(), 200);
+        assert_eq!(response.headers()["content-encoding"], expected);
+        assert_eq!(response.headers()["vary"], "accept-encoding");
+        let length: usize = response.headers()["content-length"]
+            .to_str()
+            .unwrap()
+            .parse()
+            .unwrap();
+        let body = response.bytes().await.unwrap();
+        assert_eq!(length, body.len());
+        assert!(body.len() < page_body().len());
+        assert_eq!(decode(expected, &body), page_body());
+    }
+
+    // Clients that don't ask for an encoding get the stored body
+    let identity = get("http://encoded.example/", None).await;
+    assert!(identity.headers().get("content-encoding").is_none());
+    assert_eq!(identity.bytes().await.unwrap(), page_body());
+
+    // Images are served as captured
+    let image = get("http://encoded.example/logo.png", Some("br, gzip")).await;
+    assert!(image.headers().get("content-encoding").is_none());
+    assert!(image.headers().get("vary").is_none());
+
+    server.abort();
+}
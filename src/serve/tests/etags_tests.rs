@@ -0,0 +1,218 @@
+use crate::capture::proxy::records::RequestRecord;
+use crate::serve::encoding::ContentEncoding;
+use crate::serve::etags::{body_etag, has_validator, if_none_match, EtagCache};
+use crate::serve::{MockServer, OutputLevel, ServeOptions, ServeState};
+use crate::storage::Snapshot;
+use crate::test_utils::test_helpers::create_test_record;
+use chrono::Utc;
+use hyper::header::{HeaderMap, HeaderValue};
+use tokio::time::{timeout, Duration};
+
+fn record(url: &str, headers: &[(&str, &str)], body: &[u8]) -> RequestRecord {
+    let mut response_headers = vec![("content-type", "text/css")];
+    response_headers.extend_from_slice(headers);
+    create_test_record("GET", url, 200, &response_headers, body)
+}
+
+fn snapshot() -> Snapshot {
+    Snapshot {
+        name: "etags".to_string(),
+        url: "http://etags.example/".to_string(),
+        created_at: Utc::now(),
+        requests: vec![
+            record("http://etags.example/site.css", &[], b"body { color: red }"),
+            record(
+                "http://etags.example/captured.css",
+                &[("etag", "\"captured-1\"")],
+                b"p { margin: 0 }",
+            ),
+            record(
+                "http://etags.example/dated.css",
+                &[("last-modified", "Tue, 01 Sep 2026 10:00:00 GMT")],
+                b"a { color: blue }",
+            ),
+        ],
+        ..Default::default()
+    }
+}
+
+#[test]
+fn test_body_etag_is_strong_and_content_derived() {
+    let etag = body_etag(b"body { color: red }");
+    assert!(etag.starts_with('"') && etag.ends_with('"'));
+    assert_eq!(etag, body_etag(b"body { color: red }"));
+    assert_ne!(etag, body_etag(b"body { color: blue }"));
+    assert_ne!(body_etag(b""), body_etag(b"\0"));
+}
+
+#[test]
+fn test_if_none_match_uses_weak_comparison() {
+    assert!(if_none_match("\"a\"", "\"a\""));
+    assert!(if_none_match("W/\"a\"", "\"a\""));
+    assert!(if_none_match("\"b\", W/\"a\"", "W/\"a\""));
+    assert!(if_none_match("*", "\"a\""));
+    assert!(!if_none_match("\"b\"", "\"a\""));
+    assert!(!if_none_match("", "\"a\""));
+}
+
+#[test]
+fn test_has_validator() {
+    let mut headers = HeaderMap::new();
+    assert!(!has_validator(&headers));
+    headers.insert("last-modified", HeaderValue::from_static("now"));
+    assert!(has_validator(&headers));
+}
+
+#[test]
+fn test_etags_are_computed_on_first_serve() {
+    let options = ServeOptions {
+        generate_etags: true,
+        ..ServeOptions::default()
+    };
+    let state = ServeState::new(
+        snapshot(),
+        options,
+        std::sync::Arc::new(crate::serve::console::ConsoleWriter::new(
+            std::io::sink(),
+            OutputLevel::Quiet,
+        )),
+        std::sync::Arc::new(crate::serve::handlers::DefaultMatcher),
+    );
+    // Loading the snapshot hashes nothing
+    assert_eq!(state.etags.computed(), 0);
+
+    let body = &state.snapshot.requests[0].response.body;
+    let etag = state.etags.get_or_compute(0, body);
+    assert_eq!(etag, body_etag(body));
+    assert_eq!(state.etags.computed(), 1);
+
+    // Cached by index, so a later call doesn't look at the body again
+    assert_eq!(state.etags.get_or_compute(0, b"ignored"), etag);
+    assert_eq!(EtagCache::new(1).get_or_compute(5, b"x"), body_etag(b"x"));
+}
+
+async fn start(
+    generate_etags: bool,
+    encodings: Vec<ContentEncoding>,
+) -> (reqwest::Client, tokio::task::JoinHandle<()>) {
+    let options = ServeOptions {
+        output: OutputLevel::Quiet,
+        banner: false,
+        generate_etags,
+        encodings,
+        ..ServeOptions::default()
+    };
+    let (ready_tx, ready_rx) = tokio::sync::oneshot::channel();
+    let server = tokio::spawn(async move {
+        let _ = MockServer::with_options(snapshot(), options)
+            .start_with_ready(0, ready_tx)
+            .await;
+    });
+    let addr = timeout(Duration::from_secs(5), ready_rx)
+        .await
+        .expect("Server did not signal readiness")
+        .expect("Ready sender dropped");
+    let client = reqwest::Client::builder()
+        .proxy(reqwest::Proxy::http(format!("http://{}", addr)).unwrap())
+        .build()
+        .unwrap();
+    (client, server)
+}
+
+#[tokio::test]
+async fn test_serve_generates_etags_and_answers_304() {
+    let (client, server) = start(true, Vec::new()).await;
+
+    let first = client
+        .get("http://etags.example/site.css")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(first.status(), 200);
+    let etag = first.headers()["etag"].to_str().unwrap().to_string();
+    assert_eq!(etag, body_etag(b"body { color: red }"));
+
+    let revalidated = client
+        .get("http://etags.example/site.css")
+        .header("if-none-match", &etag)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(revalidated.status(), 304);
+    assert_eq!(revalidated.headers()["etag"], etag.as_str());
+    assert!(revalidated.bytes().await.unwrap().is_empty());
+
+    let stale = client
+        .get("http://etags.example/site.css")
+        .header("if-none-match", "\"something-else\"")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(stale.status(), 200);
+
+    // Captured validators are kept, and a captured ETag revalidates the same way
+    let captured = client
+        .get("http://etags.example/captured.css")
+        .header("if-none-match", "\"captured-1\"")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(captured.status(), 304);
+    assert_eq!(captured.headers()["etag"], "\"captured-1\"");
+
+    let dated = client
+        .get("http://etags.example/dated.css")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(dated.status(), 200);
+    assert!(dated.headers().get("etag").is_none());
+
+    server.abort();
+}
+
+#[tokio::test]
+async fn test_serve_without_generate_etags_is_unchanged() {
+    let (client, server) = start(false, Vec::new()).await;
+
+    let response = client
+        .get("http://etags.example/site.css")
+        .header("if-none-match", "*")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 200);
+    assert!(response.headers().get("etag").is_none());
+
+    server.abort();
+}
+
+#[tokio::test]
+async fn test_compressed_responses_get_weak_etags() {
+    let (client, server) = start(true, vec![ContentEncoding::Gzip]).await;
+    let strong = body_etag(b"body { color: red }");
+
+    let compressed = client
+        .get("http://etags.example/site.css")
+        .header("accept-encoding", "gzip")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(compressed.headers()["content-encoding"], "gzip");
+    assert_eq!(
+        compressed.headers()["etag"],
+        format!("W/{}", strong).as_str()
+    );
+
+    // Either form revalidates, since If-None-Match compares weakly
+    let revalidated = client
+        .get("http://etags.example/site.css")
+        .header("accept-encoding", "gzip")
+        .header("if-none-match", format!("W/{}", strong))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(revalidated.status(), 304);
+
+    server.abort();
+}
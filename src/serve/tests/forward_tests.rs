@@ -0,0 +1,155 @@
+use crate::capture::proxy::records::{RequestRecord, ResponseRecord};
+use crate::serve::{ForwardTarget, MockServer, OutputLevel, ServeOptions};
+use crate::storage::Snapshot;
+use chrono::Utc;
+use std::collections::HashMap;
+use tokio::time::{timeout, Duration};
+use wiremock::matchers::{body_string, header, method, path, query_param};
+use wiremock::{Mock, ResponseTemplate};
+
+fn snapshot() -> Snapshot {
+    let mut headers = HashMap::new();
+    headers.insert("content-type".to_string(), "text/html".to_string());
+    Snapshot {
+        name: "forward".to_string(),
+        url: "http://forward.example/".to_string(),
+        created_at: Utc::now(),
+        requests: vec![RequestRecord::new(
+            "GET".to_string(),
+            "http://forward.example/".to_string(),
+            HashMap::new(),
+            None,
+            ResponseRecord::new(200, headers, b"from the snapshot".to_vec(), None),
+        )],
+        ..Default::default()
+    }
+}
+
+async fn start(
+    backend: &str,
+    forward_timeout: Duration,
+) -> (reqwest::Client, tokio::task::JoinHandle<()>) {
+    let options = ServeOptions {
+        output: OutputLevel::Quiet,
+        banner: false,
+        forward_unmatched: Some(ForwardTarget::parse(backend).unwrap()),
+        forward_timeout,
+        ..ServeOptions::default()
+    };
+    let (ready_tx, ready_rx) = tokio::sync::oneshot::channel();
+    let server = tokio::spawn(async move {
+        let _ = MockServer::with_options(snapshot(), options)
+            .start_with_ready(0, ready_tx)
+            .await;
+    });
+    let addr = timeout(Duration::from_secs(5), ready_rx)
+        .await
+        .expect("Server did not signal readiness")
+        .expect("Ready sender dropped");
+    let client = reqwest::Client::builder()
+        .proxy(reqwest::Proxy::http(format!("http://{}", addr)).unwrap())
+        .build()
+        .unwrap();
+    (client, server)
+}
+
+#[test]
+fn test_forward_target_parse_and_rewrite() {
+    let target = ForwardTarget::parse("https://staging.example.com").unwrap();
+    assert_eq!(
+        target.rewrite("http://app.example/cart?id=1").as_deref(),
+        Some("https://staging.example.com/cart?id=1")
+    );
+
+    // Request paths are appended to a base path, with or without a trailing slash
+    for base in ["http://localhost:9000/api", "http://localhost:9000/api/"] {
+        let target = ForwardTarget::parse(base).unwrap();
+        assert_eq!(
+            target.rewrite("https://app.example/users").as_deref(),
+            Some("http://localhost:9000/api/users")
+        );
+    }
+
+    for invalid in [
+        "staging.example.com",
+        "ftp://staging.example.com",
+        "https://staging.example.com/?env=1",
+    ] {
+        assert!(ForwardTarget::parse(invalid).is_err(), "{}", invalid);
+    }
+}
+
+#[tokio::test]
+async fn test_serve_forwards_unmatched_requests() {
+    let backend = wiremock::MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/api/items"))
+        .and(query_param("page", "2"))
+        .and(header("x-demo", "yes"))
+        .and(body_string("name=lamp"))
+        .respond_with(
+            ResponseTemplate::new(201)
+                .insert_header("x-backend", "staging")
+                .set_body_string("created live"),
+        )
+        .expect(1)
+        .mount(&backend)
+        .await;
+    let (client, server) = start(&format!("{}/api", backend.uri()), Duration::from_secs(5)).await;
+
+    let forwarded = client
+        .post("http://forward.example/items?page=2")
+        .header("x-demo", "yes")
+        .body("name=lamp")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(forwarded.status(), 201);
+    assert_eq!(forwarded.headers()["x-backend"], "staging");
+    assert_eq!(forwarded.text().await.unwrap(), "created live");
+
+    // Matching requests are still answered from the snapshot
+    let matched = client.get("http://forward.example/").send().await.unwrap();
+    assert_eq!(matched.text().await.unwrap(), "from the snapshot");
+
+    server.abort();
+}
+
+#[tokio::test]
+async fn test_forward_failures_are_answered_with_502() {
+    // A port nothing listens on
+    let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let closed = format!("http://{}", listener.local_addr().unwrap());
+    drop(listener);
+
+    let (client, server) = start(&closed, Duration::from_secs(5)).await;
+    let refused = client
+        .get("http://forward.example/missing")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(refused.status(), 502);
+    let body = refused.text().await.unwrap();
+    assert!(body.contains(&format!("{}/missing", closed)), "{}", body);
+    assert!(body.to_lowercase().contains("connect"), "{}", body);
+    server.abort();
+
+    let slow = wiremock::MockServer::start().await;
+    Mock::given(method("GET"))
+        .respond_with(ResponseTemplate::new(200).set_delay(Duration::from_secs(5)))
+        .mount(&slow)
+        .await;
+    let (client, server) = start(&slow.uri(), Duration::from_millis(200)).await;
+    let timed_out = client
+        .get("http://forward.example/slow")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(timed_out.status(), 502);
+    assert!(timed_out
+        .text()
+        .await
+        .unwrap()
+        .contains("no response within"));
+    server.abort();
+}
@@ -0,0 +1,161 @@
+use crate::capture::proxy::records::{RequestRecord, ResponseRecord};
+use crate::serve::handlers::{CommonAsset, SYNTHESIZED_HEADER};
+use crate::serve::{MockServer, OutputLevel, ServeOptions};
+use crate::storage::Snapshot;
+use chrono::Utc;
+use http_body_util::BodyExt;
+use hyper::Method;
+use std::collections::HashMap;
+use tokio::time::{timeout, Duration};
+
+#[test]
+fn test_common_asset_paths() {
+    let get = |url: &str| CommonAsset::for_request(&Method::GET, url);
+
+    assert_eq!(
+        get("https://example.com/favicon.ico"),
+        Some(CommonAsset::Favicon)
+    );
+    assert_eq!(
+        get("https://example.com/robots.txt?x=1"),
+        Some(CommonAsset::Robots)
+    );
+    assert_eq!(
+        get("https://example.com/apple-touch-icon.png"),
+        Some(CommonAsset::AppleTouchIcon)
+    );
+    assert_eq!(
+        get("https://example.com/apple-touch-icon-180x180-precomposed.png"),
+        Some(CommonAsset::AppleTouchIcon)
+    );
+
+    assert_eq!(get("https://example.com/static/favicon.ico"), None);
+    assert_eq!(get("https://example.com/apple-touch-icon/x.png"), None);
+    assert_eq!(get("https://example.com/index.html"), None);
+    assert_eq!(
+        CommonAsset::for_request(&Method::POST, "https://example.com/favicon.ico"),
+        None
+    );
+}
+
+#[tokio::test]
+async fn test_common_asset_responses() {
+    let favicon = CommonAsset::Favicon.response();
+    assert_eq!(favicon.status(), 200);
+    assert_eq!(favicon.headers()["content-type"], "image/png");
+    assert_eq!(favicon.headers()[SYNTHESIZED_HEADER], "true");
+    let body = favicon.into_body().collect().await.unwrap().to_bytes();
+    assert!(body.starts_with(b"\x89PNG\r\n\x1a\n"));
+    // 1x1 pixels
+    assert_eq!(&body[16..24], &[0, 0, 0, 1, 0, 0, 0, 1]);
+
+    let robots = CommonAsset::Robots.response();
+    assert_eq!(robots.status(), 200);
+    assert!(robots
+        .into_body()
+        .collect()
+        .await
+        .unwrap()
+        .to_bytes()
+        .is_empty());
+
+    assert_eq!(CommonAsset::AppleTouchIcon.response().status(), 204);
+}
+
+fn snapshot(requests: Vec<RequestRecord>) -> Snapshot {
+    Snapshot {
+        name: "assets".to_string(),
+        url: "http://assets.example/".to_string(),
+        created_at: Utc::now(),
+        requests,
+        ..Default::default()
+    }
+}
+
+async fn start(
+    snapshot: Snapshot,
+    synthesize: bool,
+) -> (tokio::task::JoinHandle<()>, reqwest::Client) {
+    let options = ServeOptions {
+        banner: false,
+        output: OutputLevel::Quiet,
+        synthesize_common_assets: synthesize,
+        ..ServeOptions::default()
+    };
+    let (ready_tx, ready_rx) = tokio::sync::oneshot::channel();
+    let server = tokio::spawn(async move {
+        let _ = MockServer::with_options(snapshot, options)
+            .start_with_ready(0, ready_tx)
+            .await;
+    });
+    let addr = timeout(Duration::from_secs(5), ready_rx)
+        .await
+        .expect("Server did not signal readiness")
+        .expect("Ready sender dropped");
+    let client = reqwest::Client::builder()
+        .proxy(reqwest::Proxy::http(format!("http://{}", addr)).unwrap())
+        .build()
+        .unwrap();
+    (server, client)
+}
+
+#[tokio::test]
+async fn test_serve_synthesizes_missing_common_assets() {
+    let (server, client) = start(snapshot(Vec::new()), true).await;
+
+    let favicon = client
+        .get("http://assets.example/favicon.ico")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(favicon.status(), 200);
+    assert_eq!(favicon.headers()[SYNTHESIZED_HEADER], "true");
+
+    let icon = client
+        .get("http://assets.example/apple-touch-icon.png")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(icon.status(), 204);
+
+    // Only GETs are answered
+    let post = client
+        .post("http://assets.example/robots.txt")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(post.status(), 404);
+
+    server.abort();
+}
+
+#[tokio::test]
+async fn test_captured_assets_win_and_synthesis_can_be_disabled() {
+    let mut headers = HashMap::new();
+    headers.insert("content-type".to_string(), "image/x-icon".to_string());
+    let captured = RequestRecord::new(
+        "GET".to_string(),
+        "http://assets.example/favicon.ico".to_string(),
+        HashMap::new(),
+        None,
+        ResponseRecord::new(200, headers, b"captured icon".to_vec(), None),
+    );
+    let (server, client) = start(snapshot(vec![captured]), true).await;
+    let favicon = client
+        .get("http://assets.example/favicon.ico")
+        .send()
+        .await
+        .unwrap();
+    assert!(favicon.headers().get(SYNTHESIZED_HEADER).is_none());
+    assert_eq!(favicon.bytes().await.unwrap(), "captured icon");
+    server.abort();
+
+    let (server, client) = start(snapshot(Vec::new()), false).await;
+    let robots = client
+        .get("http://assets.example/robots.txt")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(robots.status(), 404);
+    server.abort();
+}
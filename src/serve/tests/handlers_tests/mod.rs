@@ -1,2 +1,3 @@
+mod common_assets_tests;
 mod request_matcher_tests;
 mod response_builder_tests;
@@ -1,4 +1,4 @@
-use hyper::Method;
+use hyper::{HeaderMap, Method};
 
 use crate::{
     serve::handlers::request_matcher::*, test_utils::test_helpers::create_multi_request_snapshot,
@@ -62,3 +62,475 @@ fn test_find_matching_record_wrong_method() {
 
     assert!(record.is_none());
 }
+
+#[test]
+fn test_find_matching_record_failure_exact_match() {
+    use crate::capture::proxy::{RequestRecord, ResponseRecord};
+
+    let mut snapshot = create_multi_request_snapshot("test");
+    snapshot.requests.push(RequestRecord::new(
+        "GET".to_string(),
+        "https://unreachable.example.net/widget.js".to_string(),
+        std::collections::HashMap::new(),
+        None,
+        ResponseRecord::failed("DNS error: failed to lookup address information"),
+    ));
+
+    let record = find_matching_record(
+        &snapshot,
+        &Method::GET,
+        "https://unreachable.example.net/widget.js",
+    )
+    .unwrap();
+    assert!(record.response.is_failure());
+
+    // Failures are not used for path-only fallback matches
+    let record = find_matching_record(&snapshot, &Method::GET, "http://localhost:8080/widget.js");
+    assert!(record.is_none());
+}
+
+#[test]
+fn test_find_matching_record_prefers_success_over_failure() {
+    use crate::capture::proxy::{RequestRecord, ResponseRecord};
+
+    let mut snapshot = create_multi_request_snapshot("test");
+    snapshot.requests.insert(
+        0,
+        RequestRecord::new(
+            "GET".to_string(),
+            "https://example.com/".to_string(),
+            std::collections::HashMap::new(),
+            None,
+            ResponseRecord::failed("connection reset: Connection reset by peer"),
+        ),
+    );
+
+    let record = find_matching_record(&snapshot, &Method::GET, "https://example.com/").unwrap();
+    assert!(!record.response.is_failure());
+    assert_eq!(record.response.status, 200);
+}
+
+/// Look up a record with the default matcher and an optional override header
+fn lookup<'a>(
+    snapshot: &'a crate::storage::Snapshot,
+    method: &Method,
+    method_override: Option<&str>,
+    url: &str,
+    matching: &crate::serve::MethodMatching,
+) -> Option<RecordMatch<'a>> {
+    let mut headers = HeaderMap::new();
+    if let Some(value) = method_override {
+        headers.insert(METHOD_OVERRIDE_HEADER, value.parse().unwrap());
+    }
+    let request = IncomingRequest::new(method.clone(), url, &headers);
+    find_record_with_methods(snapshot, &DefaultMatcher, &request, matching)
+}
+
+fn snapshot_with_put_record() -> crate::storage::Snapshot {
+    use crate::capture::proxy::{RequestRecord, ResponseRecord};
+
+    let mut snapshot = create_multi_request_snapshot("test");
+    snapshot.requests.push(RequestRecord::new(
+        "PUT".to_string(),
+        "https://example.com/api/items/1".to_string(),
+        std::collections::HashMap::new(),
+        Some(b"{\"name\": \"new\"}".to_vec()),
+        ResponseRecord::new(
+            200,
+            std::collections::HashMap::new(),
+            b"{\"updated\": true}".to_vec(),
+            None,
+        ),
+    ));
+    snapshot
+}
+
+#[test]
+fn test_method_fallback_only_when_configured() {
+    use crate::serve::{parse_method_fallback, MethodMatching};
+
+    let snapshot = snapshot_with_put_record();
+    let url = "https://example.com/api/items/1";
+
+    let found = lookup(
+        &snapshot,
+        &Method::PATCH,
+        None,
+        url,
+        &MethodMatching::default(),
+    );
+    assert!(found.is_none());
+
+    let matching = MethodMatching {
+        fallbacks: parse_method_fallback("PATCH=PUT,DELETE=POST").unwrap(),
+        ..MethodMatching::default()
+    };
+    let found = lookup(&snapshot, &Method::PATCH, None, url, &matching).unwrap();
+    assert_eq!(found.record.method, "PUT");
+    assert_eq!(found.matched_method, Some(Method::PUT));
+
+    // A direct hit never reports a different method
+    let found = lookup(&snapshot, &Method::PUT, None, url, &matching).unwrap();
+    assert_eq!(found.matched_method, None);
+}
+
+#[test]
+fn test_method_override_header_only_when_enabled() {
+    use crate::serve::MethodMatching;
+
+    let snapshot = snapshot_with_put_record();
+    let url = "https://example.com/api/items/1";
+
+    let found = lookup(
+        &snapshot,
+        &Method::POST,
+        Some("PUT"),
+        url,
+        &MethodMatching::default(),
+    );
+    assert!(found.is_none());
+
+    let matching = MethodMatching {
+        honor_override_header: true,
+        ..MethodMatching::default()
+    };
+    let found = lookup(&snapshot, &Method::POST, Some("put"), url, &matching).unwrap();
+    assert_eq!(found.record.method, "PUT");
+    assert_eq!(found.matched_method, Some(Method::PUT));
+
+    // Invalid override values fall back to the request method
+    let found = lookup(
+        &snapshot,
+        &Method::POST,
+        Some("NOT A METHOD"),
+        url,
+        &matching,
+    );
+    assert!(found.is_none());
+}
+
+#[test]
+fn test_parse_method_fallback_errors() {
+    use crate::serve::parse_method_fallback;
+
+    let fallbacks = parse_method_fallback("patch=put, DELETE=POST").unwrap();
+    assert_eq!(
+        fallbacks,
+        vec![(Method::PATCH, Method::PUT), (Method::DELETE, Method::POST)]
+    );
+
+    for spec in [
+        "",
+        "PATCH",
+        "PATCH=PATCH",
+        "PATCH=PUT,PATCH=POST",
+        "PATCH=CONNECT",
+        "PA TCH=PUT",
+    ] {
+        assert!(parse_method_fallback(spec).is_err(), "{}", spec);
+    }
+}
+
+/// Strips a leading `/t/<tenant>` path segment before delegating
+struct TenantStrippingMatcher {
+    calls: std::sync::atomic::AtomicUsize,
+}
+
+impl RequestMatcher for TenantStrippingMatcher {
+    fn find<'a>(
+        &self,
+        snapshot: &'a crate::storage::Snapshot,
+        request: &IncomingRequest<'_>,
+    ) -> Option<&'a crate::capture::proxy::RequestRecord> {
+        self.calls.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+        let mut url = url::Url::parse(request.url).ok()?;
+        let path = url.path().to_string();
+        if let Some(rest) = path.strip_prefix("/t/") {
+            let stripped = rest.split_once('/').map_or("/", |(_, rest)| rest);
+            url.set_path(stripped);
+        }
+        let url = url.to_string();
+        ExactUrlMatcher.find(
+            snapshot,
+            &IncomingRequest {
+                url: &url,
+                ..request.clone()
+            },
+        )
+    }
+}
+
+#[test]
+fn test_custom_matcher_is_consulted() {
+    use crate::serve::MethodMatching;
+
+    let snapshot = create_multi_request_snapshot("test");
+    let matcher = TenantStrippingMatcher {
+        calls: Default::default(),
+    };
+    let headers = HeaderMap::new();
+
+    let request =
+        IncomingRequest::new(Method::GET, "https://example.com/t/acme/api/data", &headers);
+    let found = find_record_with_methods(&snapshot, &matcher, &request, &MethodMatching::default())
+        .unwrap();
+    assert_eq!(found.record.url, "https://example.com/api/data");
+    assert_eq!(matcher.calls.load(std::sync::atomic::Ordering::SeqCst), 1);
+
+    // The default matcher knows nothing about tenants
+    assert!(find_record_with_methods(
+        &snapshot,
+        &DefaultMatcher,
+        &request,
+        &MethodMatching::default()
+    )
+    .is_none());
+}
+
+#[test]
+fn test_builtin_matchers_split_the_default_cascade() {
+    let snapshot = create_multi_request_snapshot("test");
+    let headers = HeaderMap::new();
+    let request = |url| IncomingRequest::new(Method::GET, url, &headers);
+
+    let other_query = request("https://example.com/api/data?page=2");
+    assert!(ExactUrlMatcher.find(&snapshot, &other_query).is_none());
+    assert!(QueryInsensitiveMatcher
+        .find(&snapshot, &other_query)
+        .is_some());
+
+    let other_host = request("http://localhost:8080/api/data");
+    assert!(QueryInsensitiveMatcher
+        .find(&snapshot, &other_host)
+        .is_none());
+    assert!(PathOnlyMatcher.find(&snapshot, &other_host).is_some());
+    assert!(DefaultMatcher.find(&snapshot, &other_host).is_some());
+    assert!(!DefaultMatcher.needs_body());
+}
+
+#[test]
+fn test_body_aware_matcher_prefers_identical_body() {
+    use crate::capture::proxy::{RequestRecord, ResponseRecord};
+    use std::collections::HashMap;
+
+    let mut snapshot = create_multi_request_snapshot("test");
+    for (query, data) in [("first", "1"), ("second", "2")] {
+        snapshot.requests.push(RequestRecord::new(
+            "POST".to_string(),
+            "https://example.com/graphql".to_string(),
+            HashMap::new(),
+            Some(format!("{{\"query\":\"{}\"}}", query).into_bytes()),
+            ResponseRecord::new(200, HashMap::new(), data.as_bytes().to_vec(), None),
+        ));
+    }
+    let headers = HeaderMap::new();
+    let request = |body: Option<&'static [u8]>| {
+        IncomingRequest::new(Method::POST, "https://example.com/graphql", &headers).with_body(body)
+    };
+
+    assert!(BodyAwareMatcher.needs_body());
+    let second = request(Some(br#"{"query":"second"}"#));
+    assert_eq!(
+        BodyAwareMatcher
+            .find(&snapshot, &second)
+            .unwrap()
+            .response
+            .body,
+        b"2"
+    );
+    // The default matcher ignores bodies and serves the first capture
+    assert_eq!(
+        DefaultMatcher
+            .find(&snapshot, &second)
+            .unwrap()
+            .response
+            .body,
+        b"1"
+    );
+    // Unknown or missing bodies fall back to the default matching
+    for body in [Some(&br#"{"query":"third"}"#[..]), None] {
+        assert_eq!(
+            BodyAwareMatcher
+                .find(&snapshot, &request(body))
+                .unwrap()
+                .response
+                .body,
+            b"1"
+        );
+    }
+}
+
+#[test]
+fn test_exact_matching_prefers_the_requested_vary_variant() {
+    use crate::capture::proxy::{RequestRecord, ResponseRecord};
+    use hyper::header::{HeaderValue, ACCEPT_ENCODING};
+    use std::collections::HashMap;
+
+    let mut snapshot = create_multi_request_snapshot("test");
+    for (accept_encoding, body) in [("gzip", "gzipped"), ("", "identity")] {
+        let mut record = RequestRecord::new(
+            "GET".to_string(),
+            "https://example.com/site.css".to_string(),
+            HashMap::new(),
+            None,
+            ResponseRecord::new(200, HashMap::new(), body.as_bytes().to_vec(), None),
+        );
+        record
+            .vary_key
+            .insert("accept-encoding".to_string(), accept_encoding.to_string());
+        snapshot.requests.push(record);
+    }
+    let find = |accept_encoding: Option<&'static str>| {
+        let mut headers = HeaderMap::new();
+        if let Some(value) = accept_encoding {
+            headers.insert(ACCEPT_ENCODING, HeaderValue::from_static(value));
+        }
+        let request = IncomingRequest::new(Method::GET, "https://example.com/site.css", &headers);
+        DefaultMatcher
+            .find(&snapshot, &request)
+            .unwrap()
+            .response
+            .body
+            .clone()
+    };
+
+    assert_eq!(find(Some("gzip")), b"gzipped");
+    assert_eq!(find(None), b"identity");
+    // Variants nobody asked for during capture fall back to the first record
+    assert_eq!(find(Some("br")), b"gzipped");
+}
+
+/// Every order of `records`, by Heap's algorithm
+fn permutations<T: Clone>(records: &[T]) -> Vec<Vec<T>> {
+    fn permute<T: Clone>(k: usize, items: &mut Vec<T>, out: &mut Vec<Vec<T>>) {
+        if k <= 1 {
+            out.push(items.clone());
+            return;
+        }
+        for i in 0..k - 1 {
+            permute(k - 1, items, out);
+            let swap = if k.is_multiple_of(2) { i } else { 0 };
+            items.swap(swap, k - 1);
+        }
+        permute(k - 1, items, out);
+    }
+    let mut out = Vec::new();
+    permute(records.len(), &mut records.to_vec(), &mut out);
+    out
+}
+
+fn search_records() -> Vec<crate::capture::proxy::RequestRecord> {
+    use crate::capture::proxy::{RequestRecord, ResponseRecord};
+    use std::collections::HashMap;
+
+    [
+        "https://shop.example/search?q=shoes",
+        "https://shop.example/search?q=hats&page=2",
+        "https://shop.example/search?q=hats",
+        "https://shop.example/search?page=2&q=boots",
+        "https://shop.example/search?q=hats",
+    ]
+    .iter()
+    .enumerate()
+    .map(|(sequence, url)| {
+        let body = format!("result {}", sequence).into_bytes();
+        RequestRecord::new(
+            "GET".to_string(),
+            url.to_string(),
+            HashMap::new(),
+            None,
+            ResponseRecord::new(200, HashMap::new(), body, None),
+        )
+        .with_sequence(sequence as u64)
+    })
+    .collect()
+}
+
+#[test]
+fn test_overlapping_query_records_match_the_same_record_in_any_order() {
+    let headers = HeaderMap::new();
+    let cases = [
+        // Exact match, the earliest of two identical records
+        ("https://shop.example/search?q=hats", 2),
+        // Both share page=2, so the earlier one wins
+        ("https://shop.example/search?q=socks&page=2", 1),
+        // Both parameters in common beat one, whatever their order
+        ("https://shop.example/search?page=2&q=hats", 1),
+        // Nothing in common: the earliest record
+        ("https://shop.example/search?q=socks", 0),
+        ("http://localhost:8080/search?q=boots", 3),
+    ];
+
+    for records in permutations(&search_records()) {
+        let mut snapshot = create_multi_request_snapshot("shop");
+        snapshot.requests = records;
+        for (url, expected) in cases {
+            let request = IncomingRequest::new(Method::GET, url, &headers);
+            let record = DefaultMatcher.find(&snapshot, &request).unwrap();
+            assert_eq!(record.sequence, Some(expected), "{}", url);
+        }
+    }
+}
+
+#[test]
+fn test_match_score_ranks_exact_then_params_then_sequence() {
+    let score = |exact_query, common_params, sequence| MatchScore {
+        exact_query,
+        common_params,
+        sequence,
+    };
+
+    assert!(score(true, 0, Some(9)) > score(false, 3, Some(0)));
+    assert!(score(false, 2, Some(9)) > score(false, 1, Some(0)));
+    assert!(score(false, 1, Some(0)) > score(false, 1, Some(1)));
+    assert!(score(false, 1, Some(7)) > score(false, 1, None));
+    assert_eq!(
+        score(false, 1, Some(3)).to_string(),
+        "exact query no, 1 query params in common, sequence 3"
+    );
+}
+
+#[test]
+fn test_spellings_of_an_idn_url_match_one_record() {
+    use crate::capture::canonical_url::canonical_url;
+    use crate::capture::proxy::{RequestRecord, ResponseRecord};
+    use crate::serve::MethodMatching;
+
+    let mut snapshot = create_multi_request_snapshot("test");
+    for (query, body) in [("q=Z%C3%BCrich", "zurich"), ("q=Bern", "bern")] {
+        snapshot.requests.push(RequestRecord::new(
+            "GET".to_string(),
+            canonical_url(&format!(
+                "https://xn--bcher-kva.example/%F0%9F%98%80/suche?{}",
+                query
+            )),
+            std::collections::HashMap::new(),
+            None,
+            ResponseRecord::new(
+                200,
+                std::collections::HashMap::new(),
+                body.as_bytes().to_vec(),
+                None,
+            ),
+        ));
+    }
+
+    for url in [
+        "https://bücher.example/😀/suche?q=Zürich",
+        "https://xn--bcher-kva.example/%F0%9F%98%80/suche?q=Z%C3%BCrich",
+        "https://xn--bcher-kva.example/%f0%9f%98%80/suche?q=Z%c3%bcrich",
+    ] {
+        let found = lookup(
+            &snapshot,
+            &Method::GET,
+            None,
+            url,
+            &MethodMatching::default(),
+        )
+        .unwrap_or_else(|| panic!("no record for {}", url));
+        assert_eq!(found.record.response.body, b"zurich", "{}", url);
+
+        let record = find_matching_record(&snapshot, &Method::GET, url).unwrap();
+        assert_eq!(record.response.body, b"zurich", "{}", url);
+    }
+}
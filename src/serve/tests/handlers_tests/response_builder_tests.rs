@@ -1,5 +1,6 @@
 use crate::{
     serve::handlers::response_builder::*,
+    serve::ServeOptions,
     test_utils::test_helpers::{create_multi_request_snapshot, create_test_snapshot_with_name},
 };
 use http_body_util::BodyExt;
@@ -9,7 +10,7 @@ fn test_create_response_from_record() {
     let snapshot = create_multi_request_snapshot("test");
     let record = &snapshot.requests[0]; // HTML page
 
-    let response = create_response_from_record(record);
+    let response = create_response_from_record(record, &ServeOptions::default());
 
     assert_eq!(response.status(), hyper::StatusCode::OK);
 
@@ -24,7 +25,7 @@ fn test_create_response_from_record_css() {
     let snapshot = create_multi_request_snapshot("test");
     let record = &snapshot.requests[1]; // CSS file
 
-    let response = create_response_from_record(record);
+    let response = create_response_from_record(record, &ServeOptions::default());
 
     assert_eq!(response.status(), hyper::StatusCode::OK);
 
@@ -39,7 +40,7 @@ fn test_create_response_from_record_api() {
     let snapshot = create_multi_request_snapshot("test");
     let record = &snapshot.requests[2]; // API endpoint
 
-    let response = create_response_from_record(record);
+    let response = create_response_from_record(record, &ServeOptions::default());
 
     assert_eq!(response.status(), hyper::StatusCode::OK);
 
@@ -81,7 +82,7 @@ async fn test_response_body_content() {
     let snapshot = create_multi_request_snapshot("test");
     let record = &snapshot.requests[2]; // API endpoint
 
-    let response = create_response_from_record(record);
+    let response = create_response_from_record(record, &ServeOptions::default());
     let body_bytes = {
         let body = response.into_body();
         let collected = body.collect().await.unwrap();
@@ -115,7 +116,7 @@ fn test_response_headers_filtering() {
         .headers
         .insert("custom-header".to_string(), "should-be-kept".to_string());
 
-    let response = create_response_from_record(&snapshot.requests[0]);
+    let response = create_response_from_record(&snapshot.requests[0], &ServeOptions::default());
 
     // content-length should be recalculated based on actual body size
     let expected_length = snapshot.requests[0].response.body.len().to_string();
@@ -137,3 +138,141 @@ fn test_response_headers_filtering() {
         "should-be-kept"
     );
 }
+
+fn create_failed_record() -> crate::capture::proxy::RequestRecord {
+    use crate::capture::proxy::{RequestRecord, ResponseRecord};
+
+    RequestRecord::new(
+        "GET".to_string(),
+        "https://unreachable.example.net/widget.js".to_string(),
+        std::collections::HashMap::new(),
+        None,
+        ResponseRecord::failed("DNS error: failed to lookup address information"),
+    )
+}
+
+#[tokio::test]
+async fn test_create_failure_response_bad_gateway() {
+    let record = create_failed_record();
+    let options = crate::serve::ServeOptions::default();
+
+    let response = create_failure_response(&record, &options).await.unwrap();
+
+    assert_eq!(response.status(), hyper::StatusCode::BAD_GATEWAY);
+    assert_eq!(
+        response.headers().get("x-webmock-capture-failure").unwrap(),
+        "DNS error: failed to lookup address information"
+    );
+    let body = response.into_body().collect().await.unwrap().to_bytes();
+    assert!(String::from_utf8_lossy(&body).contains("Failed during capture"));
+}
+
+#[tokio::test]
+async fn test_create_failure_response_as_timeout() {
+    let record = create_failed_record();
+    let options = crate::serve::ServeOptions {
+        replay_failures: crate::serve::ReplayFailures::AsTimeout,
+        failure_stall: std::time::Duration::from_millis(50),
+        ..Default::default()
+    };
+
+    let start = std::time::Instant::now();
+    let result = create_failure_response(&record, &options).await;
+
+    assert!(start.elapsed() >= std::time::Duration::from_millis(50));
+    let error = result.unwrap_err();
+    assert!(is_connection_aborted(&error));
+}
+
+#[test]
+fn test_response_replays_each_set_cookie() {
+    let mut snapshot = create_test_snapshot_with_name("test");
+    let record = &mut snapshot.requests[0];
+    record
+        .response
+        .headers
+        .insert("set-cookie".to_string(), "session=abc".to_string());
+    record.response.set_cookie = vec![
+        "session=abc; HttpOnly".to_string(),
+        "csrf=def".to_string(),
+        "theme=dark".to_string(),
+    ];
+
+    let response = create_response_from_record(record, &ServeOptions::default());
+
+    let cookies: Vec<_> = response
+        .headers()
+        .get_all("set-cookie")
+        .iter()
+        .map(|value| value.to_str().unwrap())
+        .collect();
+    assert_eq!(
+        cookies,
+        vec!["session=abc; HttpOnly", "csrf=def", "theme=dark"]
+    );
+}
+
+/// Record whose origin sent no content-type and whose stored guess is unusable
+fn record_without_content_type(url: &str, body: &[u8]) -> crate::capture::proxy::RequestRecord {
+    use crate::capture::proxy::{RequestRecord, ResponseRecord};
+
+    let mut response =
+        ResponseRecord::new(200, std::collections::HashMap::new(), body.to_vec(), None);
+    response.content_type = "unknown".to_string();
+    RequestRecord::new(
+        "GET".to_string(),
+        url.to_string(),
+        std::collections::HashMap::new(),
+        None,
+        response,
+    )
+}
+
+#[test]
+fn test_response_sniffs_missing_content_type() {
+    let png = record_without_content_type(
+        "https://example.com/avatar",
+        b"\x89PNG\r\n\x1a\n\x00\x00\x00\rIHDR",
+    );
+    let html = record_without_content_type(
+        "https://example.com/",
+        b"<!DOCTYPE html><html><body>Hi</body></html>",
+    );
+    let json = record_without_content_type("https://example.com/api/user", br#"{"id": 1}"#);
+    let script = record_without_content_type("https://example.com/app.js", b"console.log(1)");
+
+    let content_type = |record, options: &ServeOptions| {
+        create_response_from_record(record, options)
+            .headers()
+            .get("content-type")
+            .map(|value| value.to_str().unwrap().to_string())
+    };
+    let options = ServeOptions::default();
+    assert_eq!(content_type(&png, &options).as_deref(), Some("image/png"));
+    assert_eq!(content_type(&html, &options).as_deref(), Some("text/html"));
+    assert_eq!(
+        content_type(&json, &options).as_deref(),
+        Some("application/json")
+    );
+    assert_eq!(
+        content_type(&script, &options).as_deref(),
+        Some("text/javascript")
+    );
+
+    // A declared type is never second-guessed
+    let mut declared = html.clone();
+    declared
+        .response
+        .headers
+        .insert("Content-Type".to_string(), "text/plain".to_string());
+    assert_eq!(
+        content_type(&declared, &options).as_deref(),
+        Some("text/plain")
+    );
+
+    let disabled = ServeOptions {
+        sniff_content_type: false,
+        ..ServeOptions::default()
+    };
+    assert_eq!(content_type(&png, &disabled), None);
+}
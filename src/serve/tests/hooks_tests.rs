@@ -0,0 +1,312 @@
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use bytes::Bytes;
+use http_body_util::Full;
+use hyper::header::HeaderValue;
+use hyper::{Response, StatusCode};
+use tokio::time::timeout;
+
+use crate::capture::proxy::RequestRecord;
+use crate::serve::hooks::*;
+use crate::serve::{MockServerBuilder, OutputLevel, ServeOptions};
+use crate::test_utils::test_helpers::create_multi_request_snapshot;
+
+const SCHEMES: [&str; 2] = ["http", "https"];
+
+/// Names of the hooks that ran, in order
+type Calls = Arc<Mutex<Vec<String>>>;
+
+/// Appends its name to `x-hooks` and, if told to, answers with a 418
+struct Tagger {
+    name: &'static str,
+    calls: Calls,
+    answer: bool,
+}
+
+impl Tagger {
+    fn hook(name: &'static str, calls: &Calls) -> Self {
+        Self {
+            name,
+            calls: Arc::clone(calls),
+            answer: false,
+        }
+    }
+
+    fn answering(name: &'static str, calls: &Calls) -> Self {
+        Self {
+            answer: true,
+            ..Self::hook(name, calls)
+        }
+    }
+
+    fn flow(&self) -> HookFlow {
+        self.calls.lock().unwrap().push(self.name.to_string());
+        if !self.answer {
+            return HookFlow::Continue;
+        }
+        HookFlow::Respond(
+            Response::builder()
+                .status(StatusCode::IM_A_TEAPOT)
+                .header("x-answered-by", self.name)
+                .body(Full::new(Bytes::from_static(b"short-circuited")))
+                .unwrap(),
+        )
+    }
+}
+
+impl RequestHook for Tagger {
+    fn on_request(&self, request: &mut RequestInfo) -> HookFlow {
+        let tags = match request.headers.get("x-hooks") {
+            Some(tags) => format!("{},{}", tags.to_str().unwrap(), self.name),
+            None => self.name.to_string(),
+        };
+        request
+            .headers
+            .insert("x-hooks", HeaderValue::from_str(&tags).unwrap());
+        self.flow()
+    }
+}
+
+impl ResponseHook for Tagger {
+    fn on_response(
+        &self,
+        request: &RequestInfo,
+        _record: &RequestRecord,
+        response: &mut ResponseParts,
+    ) -> HookFlow {
+        // Response hooks see the request as the request hooks left it
+        let tags = match response.headers.get("x-hooks") {
+            Some(tags) => format!("{},{}", tags.to_str().unwrap(), self.name),
+            None => match request.headers.get("x-hooks") {
+                Some(tags) => format!("{},{}", tags.to_str().unwrap(), self.name),
+                None => self.name.to_string(),
+            },
+        };
+        response
+            .headers
+            .insert("x-hooks", HeaderValue::from_str(&tags).unwrap());
+        self.flow()
+    }
+}
+
+/// Sends requests for `/old/...` to the records under `/api/...`
+struct Rewrite;
+
+impl RequestHook for Rewrite {
+    fn on_request(&self, request: &mut RequestInfo) -> HookFlow {
+        request.url = request.url.replace("/old/", "/api/");
+        HookFlow::Continue
+    }
+}
+
+/// Sets a field of JSON bodies
+struct FeatureFlag;
+
+impl ResponseHook for FeatureFlag {
+    fn on_response(
+        &self,
+        _request: &RequestInfo,
+        record: &RequestRecord,
+        response: &mut ResponseParts,
+    ) -> HookFlow {
+        if record.url.ends_with("/api/data") {
+            let mut json: serde_json::Value = serde_json::from_slice(&response.body).unwrap();
+            json["flag"] = true.into();
+            response.body = Bytes::from(json.to_string());
+            response.status = StatusCode::ACCEPTED;
+        }
+        HookFlow::Continue
+    }
+}
+
+/// Serve the test snapshot with its URLs under `scheme`
+async fn start(
+    scheme: &str,
+    hooks: Vec<Hook>,
+) -> (tokio::task::JoinHandle<()>, reqwest::Client, String) {
+    let mut snapshot = create_multi_request_snapshot("hooks");
+    for record in &mut snapshot.requests {
+        record.url = record.url.replacen("https", scheme, 1);
+    }
+    let options = ServeOptions {
+        banner: false,
+        output: OutputLevel::Quiet,
+        ..ServeOptions::default()
+    };
+    let server = hooks
+        .into_iter()
+        .fold(
+            MockServerBuilder::new(snapshot).with_options(options),
+            MockServerBuilder::with_hook,
+        )
+        .build();
+
+    let (ready_tx, ready_rx) = tokio::sync::oneshot::channel();
+    let handle = tokio::spawn(async move {
+        let _ = server.start_with_ready(0, ready_tx).await;
+    });
+    let addr = timeout(Duration::from_secs(5), ready_rx)
+        .await
+        .expect("Server did not signal readiness")
+        .expect("Ready sender dropped");
+
+    let client = reqwest::Client::builder()
+        .proxy(reqwest::Proxy::all(format!("http://{}", addr)).unwrap())
+        .danger_accept_invalid_certs(true)
+        .build()
+        .unwrap();
+    (handle, client, format!("{}://example.com", scheme))
+}
+
+#[tokio::test]
+async fn test_hooks_run_in_registration_order() {
+    for scheme in SCHEMES {
+        let calls = Calls::default();
+        let hooks = vec![
+            Hook::response(Tagger::hook("response-1", &calls)),
+            Hook::request(Tagger::hook("request-1", &calls)),
+            Hook::response(Tagger::hook("response-2", &calls)),
+            Hook::request(Tagger::hook("request-2", &calls)),
+        ];
+        let (server, client, origin) = start(scheme, hooks).await;
+
+        let response = client
+            .get(format!("{}/style.css", origin))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status(), 200, "{}", scheme);
+        assert_eq!(
+            response.headers()["x-hooks"],
+            "request-1,request-2,response-1,response-2",
+            "{}",
+            scheme
+        );
+        assert_eq!(
+            *calls.lock().unwrap(),
+            ["request-1", "request-2", "response-1", "response-2"],
+            "{}",
+            scheme
+        );
+
+        server.abort();
+    }
+}
+
+#[tokio::test]
+async fn test_request_hook_short_circuits_matching() {
+    for scheme in SCHEMES {
+        let calls = Calls::default();
+        let hooks = vec![
+            Hook::request(Tagger::answering("gate", &calls)),
+            Hook::request(Tagger::hook("after-gate", &calls)),
+            Hook::response(Tagger::hook("response", &calls)),
+        ];
+        let (server, client, origin) = start(scheme, hooks).await;
+
+        // Answered even though the snapshot has no such record
+        let response = client
+            .get(format!("{}/not-captured", origin))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status(), 418, "{}", scheme);
+        assert_eq!(response.headers()["x-answered-by"], "gate");
+        assert_eq!(response.text().await.unwrap(), "short-circuited");
+        assert_eq!(*calls.lock().unwrap(), ["gate"], "{}", scheme);
+
+        server.abort();
+    }
+}
+
+#[tokio::test]
+async fn test_response_hook_short_circuits_later_hooks() {
+    for scheme in SCHEMES {
+        let calls = Calls::default();
+        let hooks = vec![
+            Hook::response(Tagger::hook("first", &calls)),
+            Hook::response(Tagger::answering("answer", &calls)),
+            Hook::response(Tagger::hook("skipped", &calls)),
+        ];
+        let (server, client, origin) = start(scheme, hooks).await;
+
+        let response = client.get(format!("{}/", origin)).send().await.unwrap();
+        assert_eq!(response.status(), 418, "{}", scheme);
+        assert_eq!(response.headers()["x-answered-by"], "answer");
+        // The hook's own response is sent, without what earlier hooks changed
+        assert!(response.headers().get("x-hooks").is_none());
+        assert_eq!(*calls.lock().unwrap(), ["first", "answer"], "{}", scheme);
+
+        server.abort();
+    }
+}
+
+#[tokio::test]
+async fn test_hooks_rewrite_request_and_response() {
+    for scheme in SCHEMES {
+        let hooks = vec![Hook::request(Rewrite), Hook::response(FeatureFlag)];
+        let (server, client, origin) = start(scheme, hooks).await;
+
+        let response = client
+            .get(format!("{}/old/data", origin))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status(), 202, "{}", scheme);
+        let json: serde_json::Value = response.json().await.unwrap();
+        assert_eq!(json["message"], "Hello from API");
+        assert_eq!(json["flag"], true);
+
+        // Responses the hooks leave alone are served as recorded
+        let response = client
+            .get(format!("{}/style.css", origin))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(
+            response.text().await.unwrap(),
+            "body { font-family: Arial, sans-serif; }"
+        );
+
+        server.abort();
+    }
+}
+
+#[test]
+fn test_response_hooks_set_content_length() {
+    let mut hooks = ServeHooks::default();
+    hooks.add(Hook::response(FeatureFlag));
+    assert!(hooks.has_response_hooks());
+    assert!(!hooks.has_request_hooks());
+
+    let snapshot = create_multi_request_snapshot("hooks");
+    let record = &snapshot.requests[2];
+    let mut response =
+        crate::serve::handlers::create_response_from_record(record, &ServeOptions::default());
+    let request = RequestInfo {
+        method: hyper::Method::GET,
+        url: record.url.clone(),
+        headers: Default::default(),
+        body: None,
+        client: std::net::Ipv4Addr::LOCALHOST.into(),
+    };
+
+    assert!(!hooks.run_response(&request, record, &mut response));
+    assert_eq!(response.status(), StatusCode::ACCEPTED);
+    let expected = serde_json::json!({
+        "message": "Hello from API",
+        "status": "success",
+        "flag": true,
+    })
+    .to_string();
+    assert_eq!(
+        response.headers()["content-length"],
+        expected.len().to_string().as_str()
+    );
+    assert_eq!(
+        response.headers()["content-type"],
+        "application/json",
+        "recorded headers are kept"
+    );
+}
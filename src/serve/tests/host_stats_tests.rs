@@ -0,0 +1,120 @@
+use crate::capture::proxy::records::RequestRecord;
+use crate::serve::host_stats::format_host_table;
+use crate::serve::{HostStats, HostSummary, MockServer, OutputLevel, ServeOptions};
+use crate::storage::Snapshot;
+use crate::test_utils::test_helpers::create_test_record;
+use chrono::Utc;
+use std::sync::Arc;
+use tokio::time::{timeout, Duration};
+
+fn page(url: &str, body: &str) -> RequestRecord {
+    let headers = [("content-type", "text/html")];
+    create_test_record("GET", url, 200, &headers, body.as_bytes())
+}
+
+#[test]
+fn test_host_stats_aggregate_per_host() {
+    let stats = HostStats::default();
+    stats.record_hit("https://shop.example/", 100);
+    stats.record_hit("https://shop.example/app.js?v=2", 50);
+    stats.record_miss("https://shop.example/missing");
+    stats.record_miss("https://cdn.example:8443/font.woff2");
+    stats.record_miss("https://cdn.example:8443/icon.svg");
+
+    assert_eq!(
+        stats.summary(),
+        vec![
+            HostSummary {
+                host: "cdn.example:8443".to_string(),
+                hits: 0,
+                misses: 2,
+                bytes: 0,
+            },
+            HostSummary {
+                host: "shop.example".to_string(),
+                hits: 2,
+                misses: 1,
+                bytes: 150,
+            },
+        ]
+    );
+}
+
+#[test]
+fn test_host_table_flags_hosts_missing_from_snapshot() {
+    let table = format_host_table(&[
+        HostSummary {
+            host: "fonts.example".to_string(),
+            hits: 0,
+            misses: 3,
+            bytes: 0,
+        },
+        HostSummary {
+            host: "shop.example".to_string(),
+            hits: 9,
+            misses: 1,
+            bytes: 2048,
+        },
+    ]);
+    let lines: Vec<&str> = table.lines().collect();
+    assert_eq!(lines.len(), 3);
+    assert!(lines[0].starts_with("HOST"));
+    assert!(lines[1].contains("not present in snapshot"), "{}", table);
+    assert!(!lines[2].contains("not present in snapshot"), "{}", table);
+}
+
+#[tokio::test]
+async fn test_serve_counts_hits_and_misses_per_host() {
+    let snapshot = Snapshot {
+        name: "hosts".to_string(),
+        url: "http://shop.example/".to_string(),
+        created_at: Utc::now(),
+        requests: vec![
+            page("http://shop.example/", "<html>shop</html>"),
+            page("http://shop.example/cart", "<html>cart</html>"),
+        ],
+        ..Default::default()
+    };
+    let options = ServeOptions {
+        output: OutputLevel::Quiet,
+        banner: false,
+        ..ServeOptions::default()
+    };
+    let server = Arc::new(MockServer::with_options(snapshot, options));
+    let (ready_tx, ready_rx) = tokio::sync::oneshot::channel();
+    let running = {
+        let server = Arc::clone(&server);
+        tokio::spawn(async move { server.start_with_ready(0, ready_tx).await })
+    };
+    let addr = timeout(Duration::from_secs(5), ready_rx)
+        .await
+        .expect("Server did not signal readiness")
+        .expect("Ready sender dropped");
+    let client = reqwest::Client::builder()
+        .proxy(reqwest::Proxy::http(format!("http://{}", addr)).unwrap())
+        .build()
+        .unwrap();
+
+    for url in [
+        "http://shop.example/",
+        "http://shop.example/",
+        "http://shop.example/cart",
+        "http://shop.example/gone",
+        "http://new-cdn.example/lib.js",
+        "http://new-cdn.example/lib.css",
+    ] {
+        client.get(url).send().await.unwrap();
+    }
+
+    let summary = server.host_summary().unwrap();
+    assert_eq!(summary.len(), 2);
+    assert_eq!(summary[0].host, "new-cdn.example");
+    assert_eq!((summary[0].hits, summary[0].misses), (0, 2));
+    assert!(summary[0].all_missed());
+    assert_eq!(summary[1].host, "shop.example");
+    assert_eq!((summary[1].hits, summary[1].misses), (3, 1));
+    assert_eq!(summary[1].bytes, 2 * 17 + 17);
+    assert!(!summary[1].all_missed());
+
+    running.abort();
+}
@@ -1,6 +1,7 @@
 use crate::capture::proxy::records::{RequestRecord, ResponseRecord};
 use crate::serve::MockServer;
 use crate::storage::{Snapshot, Storage};
+use crate::test_utils::test_helpers::create_test_record;
 use chrono::Utc;
 use std::collections::HashMap;
 use std::net::TcpListener;
@@ -23,6 +24,11 @@ async fn create_test_storage_with_snapshot() -> (TempDir, Storage, String) {
         headers: headers.clone(),
         body: b"<html><head><title>Test</title></head><body><h1>Hello from WebMock!</h1></body></html>".to_vec(),
         content_type: "text/html".to_string(),
+        failure: None,
+        set_cookie: Vec::new(),
+        http_version: None,
+        reason: None,
+        informational: Vec::new(),
     };
 
     let request = RequestRecord {
@@ -32,6 +38,12 @@ async fn create_test_storage_with_snapshot() -> (TempDir, Storage, String) {
         body: None,
         response,
         timestamp: Utc::now(),
+        initiator: None,
+        timing: Default::default(),
+        vary_key: Default::default(),
+        variant: None,
+        sequence: None,
+        frame_id: None,
     };
 
     let snapshot = Snapshot {
@@ -39,6 +51,7 @@ async fn create_test_storage_with_snapshot() -> (TempDir, Storage, String) {
         url: "https://example.com/".to_string(),
         created_at: Utc::now(),
         requests: vec![request],
+        ..Default::default()
     };
 
     // Save the snapshot
@@ -95,6 +108,33 @@ async fn test_mock_server_startup_and_shutdown() {
     );
 }
 
+#[tokio::test]
+async fn test_mock_server_signals_ready_after_binding() {
+    let (_temp_dir, storage, snapshot_name) = create_test_storage_with_snapshot().await;
+    let snapshot = storage
+        .load_snapshot(&snapshot_name)
+        .await
+        .expect("Failed to load snapshot");
+
+    let port = find_available_port();
+    let (ready_tx, ready_rx) = tokio::sync::oneshot::channel();
+    let server = tokio::spawn(async move {
+        let mock_server = MockServer::new(snapshot);
+        mock_server.start_with_ready(port, ready_tx).await
+    });
+
+    let addr = timeout(Duration::from_secs(5), ready_rx)
+        .await
+        .expect("Server did not signal readiness")
+        .expect("Ready sender dropped");
+    assert_eq!(addr.port(), port);
+
+    // The listener is bound by the time readiness is reported
+    assert!(tokio::net::TcpStream::connect(addr).await.is_ok());
+
+    server.abort();
+}
+
 #[test]
 fn test_port_availability_check() {
     // Test with a port that should be available
@@ -148,6 +188,801 @@ async fn test_snapshot_loading_and_server_creation() {
     // Verify server was created successfully (no panics)
     // We can't easily test the actual HTTP serving without more complex setup,
     // but we can verify the server object was created properly
-    assert_eq!(mock_server.snapshot.name, "integration-test-snapshot");
-    assert_eq!(mock_server.snapshot.requests.len(), 1);
+    let snapshot = mock_server.snapshot().unwrap();
+    assert_eq!(snapshot.name, "integration-test-snapshot");
+    assert_eq!(snapshot.requests.len(), 1);
+}
+
+#[tokio::test]
+async fn test_mock_server_serves_patch_from_put_record_with_fallback() {
+    use crate::serve::{parse_method_fallback, MethodMatching, OutputLevel, ServeOptions};
+
+    let snapshot = Snapshot {
+        name: "items".to_string(),
+        url: "http://api.example/".to_string(),
+        created_at: Utc::now(),
+        requests: vec![RequestRecord::new(
+            "PUT".to_string(),
+            "http://api.example/items/1".to_string(),
+            HashMap::new(),
+            None,
+            ResponseRecord::new(200, HashMap::new(), b"updated".to_vec(), None),
+        )],
+        ..Default::default()
+    };
+
+    let serve = |methods: MethodMatching| {
+        let snapshot = snapshot.clone();
+        async move {
+            let options = ServeOptions {
+                output: OutputLevel::Quiet,
+                banner: false,
+                methods,
+                ..ServeOptions::default()
+            };
+            let (ready_tx, ready_rx) = tokio::sync::oneshot::channel();
+            let server = tokio::spawn(async move {
+                MockServer::with_options(snapshot, options)
+                    .start_with_ready(0, ready_tx)
+                    .await
+            });
+            let addr = timeout(Duration::from_secs(5), ready_rx)
+                .await
+                .expect("Server did not signal readiness")
+                .expect("Ready sender dropped");
+
+            let client = reqwest::Client::builder()
+                .proxy(reqwest::Proxy::http(format!("http://{}", addr)).unwrap())
+                .build()
+                .unwrap();
+            let status = client
+                .patch("http://api.example/items/1")
+                .send()
+                .await
+                .unwrap()
+                .status();
+            server.abort();
+            status
+        }
+    };
+
+    assert_eq!(serve(MethodMatching::default()).await, 404);
+
+    let fallback = MethodMatching {
+        fallbacks: parse_method_fallback("PATCH=PUT").unwrap(),
+        ..MethodMatching::default()
+    };
+    assert_eq!(serve(fallback).await, 200);
+}
+
+#[tokio::test]
+async fn test_mock_server_breaks_captured_redirect_loop() {
+    use crate::serve::{OutputLevel, ServeOptions, DEFAULT_REDIRECT_LOOP_LIMIT};
+
+    let redirect = |from: &str, to: &str| {
+        let mut headers = HashMap::new();
+        headers.insert("location".to_string(), to.to_string());
+        RequestRecord::new(
+            "GET".to_string(),
+            from.to_string(),
+            HashMap::new(),
+            None,
+            ResponseRecord::new(302, headers, Vec::new(), None),
+        )
+    };
+    let snapshot = Snapshot {
+        name: "loop".to_string(),
+        url: "http://loop.example/a".to_string(),
+        created_at: Utc::now(),
+        requests: vec![
+            redirect("http://loop.example/a", "http://loop.example/b"),
+            redirect("http://loop.example/b", "http://loop.example/a"),
+        ],
+        ..Default::default()
+    };
+
+    let options = ServeOptions {
+        output: OutputLevel::Quiet,
+        banner: false,
+        ..ServeOptions::default()
+    };
+    let (ready_tx, ready_rx) = tokio::sync::oneshot::channel();
+    let server = tokio::spawn(async move {
+        MockServer::with_options(snapshot, options)
+            .start_with_ready(0, ready_tx)
+            .await
+    });
+    let addr = timeout(Duration::from_secs(5), ready_rx)
+        .await
+        .expect("Server did not signal readiness")
+        .expect("Ready sender dropped");
+
+    // Follow the loop by hand, like a browser would
+    let client = reqwest::Client::builder()
+        .proxy(reqwest::Proxy::http(format!("http://{}", addr)).unwrap())
+        .redirect(reqwest::redirect::Policy::none())
+        .build()
+        .unwrap();
+    let mut url = "http://loop.example/a".to_string();
+    let mut redirects = 0;
+    let response = loop {
+        let response = client.get(&url).send().await.unwrap();
+        if response.status() != 302 {
+            break response;
+        }
+        redirects += 1;
+        assert!(redirects <= 100, "redirect loop was never broken");
+        url = response.headers()["location"].to_str().unwrap().to_string();
+    };
+
+    assert_eq!(response.status(), 508);
+    assert_eq!(redirects, 2 * DEFAULT_REDIRECT_LOOP_LIMIT as usize);
+    let body = response.text().await.unwrap();
+    assert!(body.contains("Redirect loop detected"));
+    assert!(body.contains("http://loop.example/a"));
+
+    // The looping pair stays refused while the client keeps hammering it
+    let status = client
+        .get("http://loop.example/b")
+        .send()
+        .await
+        .unwrap()
+        .status();
+    assert_eq!(status, 508);
+
+    server.abort();
+}
+
+#[tokio::test]
+async fn test_mock_server_uses_registered_matcher() {
+    use crate::serve::{BodyAwareMatcher, OutputLevel, ServeOptions};
+
+    let graphql = |query: &str, data: &str| {
+        RequestRecord::new(
+            "POST".to_string(),
+            "http://api.example/graphql".to_string(),
+            HashMap::new(),
+            Some(format!("{{\"query\":\"{}\"}}", query).into_bytes()),
+            ResponseRecord::new(200, HashMap::new(), data.as_bytes().to_vec(), None),
+        )
+    };
+    let snapshot = Snapshot {
+        name: "graphql".to_string(),
+        url: "http://api.example/".to_string(),
+        created_at: Utc::now(),
+        requests: vec![graphql("first", "1"), graphql("second", "2")],
+        ..Default::default()
+    };
+
+    let options = ServeOptions {
+        output: OutputLevel::Quiet,
+        banner: false,
+        ..ServeOptions::default()
+    };
+    let (ready_tx, ready_rx) = tokio::sync::oneshot::channel();
+    let server = tokio::spawn(async move {
+        MockServer::with_matcher(snapshot, options, Box::new(BodyAwareMatcher))
+            .start_with_ready(0, ready_tx)
+            .await
+    });
+    let addr = timeout(Duration::from_secs(5), ready_rx)
+        .await
+        .expect("Server did not signal readiness")
+        .expect("Ready sender dropped");
+
+    let client = reqwest::Client::builder()
+        .proxy(reqwest::Proxy::http(format!("http://{}", addr)).unwrap())
+        .build()
+        .unwrap();
+    let body = client
+        .post("http://api.example/graphql")
+        .body(r#"{"query":"second"}"#)
+        .send()
+        .await
+        .unwrap()
+        .text()
+        .await
+        .unwrap();
+    assert_eq!(body, "2");
+
+    server.abort();
+}
+
+#[tokio::test]
+async fn test_mock_server_reports_pinned_hosts() {
+    use crate::capture::proxy::TunnelRecord;
+    use crate::serve::{OutputLevel, ServeOptions};
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    let connect = RequestRecord::new(
+        "CONNECT".to_string(),
+        "https://pinned.example:443".to_string(),
+        HashMap::new(),
+        None,
+        ResponseRecord::new(200, HashMap::new(), Vec::new(), None),
+    );
+    let snapshot = Snapshot {
+        name: "pinned".to_string(),
+        url: "https://pinned.example/".to_string(),
+        created_at: Utc::now(),
+        requests: vec![connect],
+        tunnels: vec![TunnelRecord::pinned("pinned.example:443")],
+        ..Default::default()
+    };
+
+    let options = ServeOptions {
+        output: OutputLevel::Quiet,
+        banner: false,
+        ..ServeOptions::default()
+    };
+    let (ready_tx, ready_rx) = tokio::sync::oneshot::channel();
+    let server = tokio::spawn(async move {
+        MockServer::with_options(snapshot, options)
+            .start_with_ready(0, ready_tx)
+            .await
+    });
+    let addr = timeout(Duration::from_secs(5), ready_rx)
+        .await
+        .expect("Server did not signal readiness")
+        .expect("Ready sender dropped");
+
+    let mut stream = tokio::net::TcpStream::connect(addr).await.unwrap();
+    stream
+        .write_all(b"CONNECT pinned.example:443 HTTP/1.1\r\nHost: pinned.example:443\r\n\r\n")
+        .await
+        .unwrap();
+    let mut response = String::new();
+    timeout(Duration::from_secs(5), stream.read_to_string(&mut response))
+        .await
+        .expect("Server did not close the connection")
+        .unwrap();
+
+    assert!(response.starts_with("HTTP/1.1 502"), "{}", response);
+    assert!(response.contains("pinned.example:443 is certificate-pinned"));
+
+    server.abort();
+}
+
+#[tokio::test]
+async fn test_mock_server_routes_snapshots_by_path() {
+    use crate::serve::{OutputLevel, ServeOptions, SnapshotRouter};
+    use std::sync::Arc;
+
+    let temp_dir = TempDir::new().unwrap();
+    let storage = Storage::new(temp_dir.path().to_path_buf());
+    let page = |url: &str, content_type: &str, body: &str| {
+        let headers = HashMap::from([("content-type".to_string(), content_type.to_string())]);
+        RequestRecord::new(
+            "GET".to_string(),
+            url.to_string(),
+            HashMap::new(),
+            None,
+            ResponseRecord::new(200, headers, body.as_bytes().to_vec(), None),
+        )
+    };
+    for (name, url, requests) in [
+        (
+            "shop",
+            "https://shop.example/",
+            vec![
+                page(
+                    "https://shop.example/",
+                    "text/html",
+                    r#"<a href="/cart?id=1">Cart</a><img src="https://shop.example/logo.png">"#,
+                ),
+                page("https://shop.example/cart?id=1", "text/plain", "cart 1"),
+            ],
+        ),
+        (
+            "blog",
+            "http://blog.example/",
+            vec![page("http://blog.example/", "text/plain", "blog home")],
+        ),
+    ] {
+        let snapshot = Snapshot {
+            name: name.to_string(),
+            url: url.to_string(),
+            created_at: Utc::now(),
+            requests,
+            ..Default::default()
+        };
+        storage.save_snapshot(snapshot).await.unwrap();
+    }
+
+    let options = ServeOptions {
+        output: OutputLevel::Quiet,
+        banner: false,
+        ..ServeOptions::default()
+    };
+    let server = Arc::new(MockServer::with_router(SnapshotRouter::new(
+        storage, options, 1,
+    )));
+    let (ready_tx, ready_rx) = tokio::sync::oneshot::channel();
+    let running = {
+        let server = Arc::clone(&server);
+        tokio::spawn(async move { server.start_with_ready(0, ready_tx).await })
+    };
+    let addr = timeout(Duration::from_secs(5), ready_rx)
+        .await
+        .expect("Server did not signal readiness")
+        .expect("Ready sender dropped");
+
+    let client = reqwest::Client::builder()
+        .redirect(reqwest::redirect::Policy::none())
+        .build()
+        .unwrap();
+    let get = |path: &str| client.get(format!("http://{}{}", addr, path)).send();
+
+    let index = get("/").await.unwrap().text().await.unwrap();
+    assert!(index.contains(r#"href="/_s/shop/""#), "{}", index);
+    assert!(index.contains(r#"href="/_s/blog/""#), "{}", index);
+    assert!(server.router().unwrap().loaded().is_empty());
+
+    let redirect = get("/_s/shop").await.unwrap();
+    assert_eq!(redirect.status(), 308);
+    assert_eq!(redirect.headers()["location"], "/_s/shop/");
+
+    let home = get("/_s/shop/").await.unwrap().text().await.unwrap();
+    assert_eq!(
+        home,
+        r#"<a href="/_s/shop/cart?id=1">Cart</a><img src="/_s/shop/logo.png">"#
+    );
+    let cart = get("/_s/shop/cart?id=1")
+        .await
+        .unwrap()
+        .text()
+        .await
+        .unwrap();
+    assert_eq!(cart, "cart 1");
+    assert_eq!(server.router().unwrap().loaded(), ["shop"]);
+
+    let blog = get("/_s/blog/").await.unwrap().text().await.unwrap();
+    assert_eq!(blog, "blog home");
+    assert_eq!(server.router().unwrap().loaded(), ["blog"]);
+
+    let missing = get("/_s/missing/").await.unwrap();
+    assert_eq!(missing.status(), 404);
+    let missing = missing.text().await.unwrap();
+    assert!(missing.contains("/_s/missing/"), "{}", missing);
+    assert!(missing.contains(r#"href="/_s/shop/""#), "{}", missing);
+
+    running.abort();
+}
+
+#[tokio::test]
+async fn test_mock_server_tunnels_connect_to_uncaptured_host() {
+    use crate::serve::{OutputLevel, ServeOptions};
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    let snapshot = || Snapshot {
+        name: "shop".to_string(),
+        url: "https://shop.example/".to_string(),
+        created_at: Utc::now(),
+        requests: vec![RequestRecord::new(
+            "CONNECT".to_string(),
+            "https://shop.example:443".to_string(),
+            HashMap::new(),
+            None,
+            ResponseRecord::new(200, HashMap::new(), Vec::new(), None),
+        )],
+        ..Default::default()
+    };
+    let start = |strict_connect: bool| {
+        let options = ServeOptions {
+            output: OutputLevel::Quiet,
+            banner: false,
+            strict_connect,
+            ..ServeOptions::default()
+        };
+        let (ready_tx, ready_rx) = tokio::sync::oneshot::channel();
+        let server = tokio::spawn(async move {
+            MockServer::with_options(snapshot(), options)
+                .start_with_ready(0, ready_tx)
+                .await
+        });
+        (server, ready_rx)
+    };
+
+    // By default the stray host gets a tunnel and a 404 inside it
+    let (server, ready_rx) = start(false);
+    let addr = timeout(Duration::from_secs(5), ready_rx)
+        .await
+        .expect("Server did not signal readiness")
+        .expect("Ready sender dropped");
+    let client = reqwest::Client::builder()
+        .proxy(reqwest::Proxy::all(format!("http://{}", addr)).unwrap())
+        .danger_accept_invalid_certs(true)
+        .build()
+        .unwrap();
+    let response = client
+        .get("https://fonts.stray.example/font.woff2")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 404);
+    server.abort();
+
+    // --strict-connect restores the 502
+    let (server, ready_rx) = start(true);
+    let addr = timeout(Duration::from_secs(5), ready_rx)
+        .await
+        .expect("Server did not signal readiness")
+        .expect("Ready sender dropped");
+    let mut stream = tokio::net::TcpStream::connect(addr).await.unwrap();
+    stream
+        .write_all(
+            b"CONNECT fonts.stray.example:443 HTTP/1.1\r\nHost: fonts.stray.example:443\r\n\r\n",
+        )
+        .await
+        .unwrap();
+    let mut response = String::new();
+    timeout(Duration::from_secs(5), stream.read_to_string(&mut response))
+        .await
+        .expect("Server did not close the connection")
+        .unwrap();
+    assert!(response.starts_with("HTTP/1.1 502"), "{}", response);
+    server.abort();
+}
+
+async fn get_through_connect(records: Vec<RequestRecord>, url: &str) -> (u16, String) {
+    use crate::serve::{OutputLevel, ServeOptions};
+
+    let snapshot = Snapshot {
+        name: "tunnels".to_string(),
+        url: "https://shop.example/".to_string(),
+        created_at: Utc::now(),
+        requests: records,
+        ..Default::default()
+    };
+    // Strict, so a CONNECT that doesn't match its record fails with 502
+    let options = ServeOptions {
+        output: OutputLevel::Quiet,
+        banner: false,
+        strict_connect: true,
+        ..ServeOptions::default()
+    };
+    let (ready_tx, ready_rx) = tokio::sync::oneshot::channel();
+    let server = tokio::spawn(async move {
+        MockServer::with_options(snapshot, options)
+            .start_with_ready(0, ready_tx)
+            .await
+    });
+    let addr = timeout(Duration::from_secs(5), ready_rx)
+        .await
+        .expect("Server did not signal readiness")
+        .expect("Ready sender dropped");
+
+    let client = reqwest::Client::builder()
+        .proxy(reqwest::Proxy::all(format!("http://{}", addr)).unwrap())
+        .danger_accept_invalid_certs(true)
+        .build()
+        .unwrap();
+    let response = client.get(url).send().await.unwrap();
+    let status = response.status().as_u16();
+    let body = response.text().await.unwrap();
+    server.abort();
+    (status, body)
+}
+
+fn connect_record(url: &str) -> RequestRecord {
+    RequestRecord::new(
+        "CONNECT".to_string(),
+        url.to_string(),
+        HashMap::new(),
+        None,
+        ResponseRecord::new(200, HashMap::new(), Vec::new(), None),
+    )
+}
+
+fn text_record(url: &str, body: &str) -> RequestRecord {
+    let headers = [("content-type", "text/plain")];
+    create_test_record("GET", url, 200, &headers, body.as_bytes())
+}
+
+#[tokio::test]
+async fn test_mock_server_matches_connect_on_default_port() {
+    // Captured URLs leave out :443, the CONNECT names it
+    let (status, body) = get_through_connect(
+        vec![
+            connect_record("https://shop.example:443"),
+            text_record("https://shop.example/api/cart", "cart"),
+        ],
+        "https://shop.example/api/cart",
+    )
+    .await;
+    assert_eq!(status, 200);
+    assert_eq!(body, "cart");
+}
+
+#[tokio::test]
+async fn test_mock_server_keeps_nonstandard_connect_ports() {
+    let records = || {
+        vec![
+            connect_record("https://shop.example:8443"),
+            text_record("https://shop.example:8443/api/cart", "port 8443"),
+            connect_record("https://shop.example:443"),
+            text_record("https://shop.example/api/cart", "default port"),
+        ]
+    };
+
+    let (status, body) = get_through_connect(records(), "https://shop.example:8443/api/cart").await;
+    assert_eq!(status, 200);
+    assert_eq!(body, "port 8443");
+
+    let (status, body) = get_through_connect(records(), "https://shop.example/api/cart").await;
+    assert_eq!(status, 200);
+    assert_eq!(body, "default port");
+}
+
+#[tokio::test]
+async fn test_mock_server_replays_non_standard_statuses() {
+    use crate::serve::{OutputLevel, ServeOptions};
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    let record = |path: &str, status: u16, reason: Option<&str>| {
+        RequestRecord::new(
+            "GET".to_string(),
+            format!("http://legacy.example{}", path),
+            HashMap::new(),
+            None,
+            ResponseRecord::new(status, HashMap::new(), b"body".to_vec(), None)
+                .with_reason(reason.map(str::to_string)),
+        )
+    };
+    let snapshot = Snapshot {
+        name: "legacy".to_string(),
+        url: "http://legacy.example/".to_string(),
+        created_at: Utc::now(),
+        requests: vec![
+            record("/ok", 299, Some("Legacy Success")),
+            record("/broken", 599, None),
+        ],
+        ..Default::default()
+    };
+
+    let options = ServeOptions {
+        output: OutputLevel::Quiet,
+        banner: false,
+        ..ServeOptions::default()
+    };
+    let (ready_tx, ready_rx) = tokio::sync::oneshot::channel();
+    let server = tokio::spawn(async move {
+        MockServer::with_options(snapshot, options)
+            .start_with_ready(0, ready_tx)
+            .await
+    });
+    let addr = timeout(Duration::from_secs(5), ready_rx)
+        .await
+        .expect("Server did not signal readiness")
+        .expect("Ready sender dropped");
+
+    // The exact status line, reason phrase included
+    let mut stream = tokio::net::TcpStream::connect(addr).await.unwrap();
+    stream
+        .write_all(
+            b"GET http://legacy.example/ok HTTP/1.1\r\nHost: legacy.example\r\nConnection: close\r\n\r\n",
+        )
+        .await
+        .unwrap();
+    let mut response = String::new();
+    timeout(Duration::from_secs(5), stream.read_to_string(&mut response))
+        .await
+        .expect("Server did not close the connection")
+        .unwrap();
+    assert!(
+        response.starts_with("HTTP/1.1 299 Legacy Success\r\n"),
+        "{}",
+        response
+    );
+
+    let client = reqwest::Client::builder()
+        .proxy(reqwest::Proxy::http(format!("http://{}", addr)).unwrap())
+        .build()
+        .unwrap();
+    let response = client
+        .get("http://legacy.example/broken")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status().as_u16(), 599);
+    assert_eq!(response.text().await.unwrap(), "body");
+
+    server.abort();
+}
+
+#[tokio::test]
+async fn test_mock_server_stops_when_idle_and_releases_port() {
+    use crate::serve::{OutputLevel, ServeOptions, ShutdownReason};
+    use std::sync::Arc;
+
+    let (_temp_dir, storage, snapshot_name) = create_test_storage_with_snapshot().await;
+    let snapshot = storage.load_snapshot(&snapshot_name).await.unwrap();
+    let idle_timeout = Duration::from_millis(300);
+    let server = Arc::new(MockServer::with_options(
+        snapshot,
+        ServeOptions {
+            output: OutputLevel::Quiet,
+            banner: false,
+            idle_timeout: Some(idle_timeout),
+            ..ServeOptions::default()
+        },
+    ));
+
+    let (ready_tx, ready_rx) = tokio::sync::oneshot::channel();
+    let running = {
+        let server = Arc::clone(&server);
+        tokio::spawn(async move { server.start_with_ready(0, ready_tx).await })
+    };
+    let addr = timeout(Duration::from_secs(5), ready_rx)
+        .await
+        .expect("Server did not signal readiness")
+        .expect("Ready sender dropped");
+
+    // Requests keep the server alive past the idle timeout
+    let client = reqwest::Client::builder()
+        .proxy(reqwest::Proxy::http(format!("http://{}", addr)).unwrap())
+        .build()
+        .unwrap();
+    for _ in 0..5 {
+        // Served from the snapshot or answered as missing, either counts as activity
+        client.get("http://example.com/").send().await.unwrap();
+        tokio::time::sleep(Duration::from_millis(100)).await;
+    }
+    assert!(!running.is_finished());
+
+    timeout(Duration::from_secs(5), running)
+        .await
+        .expect("Server did not stop when idle")
+        .unwrap()
+        .unwrap();
+    assert_eq!(
+        server.shutdown_reason(),
+        Some(ShutdownReason::Idle(idle_timeout))
+    );
+    assert!(is_port_available(addr.port()));
+}
+
+#[tokio::test]
+async fn test_mock_server_stops_after_max_lifetime_under_load() {
+    use crate::serve::{OutputLevel, ServeOptions, ShutdownReason};
+    use std::sync::Arc;
+
+    let (_temp_dir, storage, snapshot_name) = create_test_storage_with_snapshot().await;
+    let snapshot = storage.load_snapshot(&snapshot_name).await.unwrap();
+    let max_lifetime = Duration::from_millis(400);
+    let server = Arc::new(MockServer::with_options(
+        snapshot,
+        ServeOptions {
+            output: OutputLevel::Quiet,
+            banner: false,
+            idle_timeout: Some(Duration::from_secs(60)),
+            max_lifetime: Some(max_lifetime),
+            ..ServeOptions::default()
+        },
+    ));
+
+    let (ready_tx, ready_rx) = tokio::sync::oneshot::channel();
+    let running = {
+        let server = Arc::clone(&server);
+        tokio::spawn(async move { server.start_with_ready(0, ready_tx).await })
+    };
+    let addr = timeout(Duration::from_secs(5), ready_rx)
+        .await
+        .expect("Server did not signal readiness")
+        .expect("Ready sender dropped");
+
+    // Keep requesting until the server goes away
+    let load = tokio::spawn(async move {
+        let client = reqwest::Client::builder()
+            .proxy(reqwest::Proxy::http(format!("http://{}", addr)).unwrap())
+            .build()
+            .unwrap();
+        while client.get("http://example.com/").send().await.is_ok() {
+            tokio::time::sleep(Duration::from_millis(20)).await;
+        }
+    });
+
+    timeout(Duration::from_secs(5), running)
+        .await
+        .expect("Server outlived its max lifetime")
+        .unwrap()
+        .unwrap();
+    load.abort();
+    assert_eq!(
+        server.shutdown_reason(),
+        Some(ShutdownReason::MaxLifetime(max_lifetime))
+    );
+    assert!(is_port_available(addr.port()));
+}
+
+#[tokio::test]
+async fn test_uncommon_methods_round_trip_through_capture_and_serve() {
+    use crate::capture::proxy::HttpProxy;
+    use crate::serve::{OutputLevel, ServeOptions};
+    use reqwest::Method;
+    use wiremock::matchers::{method, path};
+    use wiremock::{Mock, ResponseTemplate};
+
+    let methods = [
+        ("OPTIONS", 204, ""),
+        ("TRACE", 200, "TRACE /echo HTTP/1.1"),
+        ("PROPFIND", 207, "<multistatus/>"),
+        ("X-SYNC", 202, "synced"),
+    ];
+
+    let origin = wiremock::MockServer::start().await;
+    for (name, status, body) in methods {
+        Mock::given(method(name))
+            .and(path("/echo"))
+            .respond_with(ResponseTemplate::new(status).set_body_string(body))
+            .mount(&origin)
+            .await;
+    }
+    let url = format!("{}/echo", origin.uri());
+
+    // Capture
+    let proxy = HttpProxy::start(0).await.unwrap();
+    let client = reqwest::Client::builder()
+        .proxy(reqwest::Proxy::http(format!("http://127.0.0.1:{}", proxy.get_port())).unwrap())
+        .build()
+        .unwrap();
+    for (name, status, body) in methods {
+        let method = Method::from_bytes(name.as_bytes()).unwrap();
+        let response = client.request(method, &url).send().await.unwrap();
+        assert_eq!(response.status().as_u16(), status, "{}", name);
+        assert_eq!(response.text().await.unwrap(), body, "{}", name);
+    }
+    let requests = proxy.take_records().await;
+    proxy.stop().await.unwrap();
+    let recorded: Vec<&str> = requests.iter().map(|r| r.method.as_str()).collect();
+    assert_eq!(recorded, vec!["OPTIONS", "TRACE", "PROPFIND", "X-SYNC"]);
+
+    // Storage
+    let temp_dir = TempDir::new().unwrap();
+    let storage = Storage::new(temp_dir.path().to_path_buf());
+    storage
+        .save_snapshot(Snapshot {
+            name: "methods".to_string(),
+            url: url.clone(),
+            created_at: Utc::now(),
+            requests,
+            ..Default::default()
+        })
+        .await
+        .unwrap();
+    let snapshot = storage.load_snapshot("methods").await.unwrap();
+
+    // Serve
+    let options = ServeOptions {
+        output: OutputLevel::Quiet,
+        banner: false,
+        ..ServeOptions::default()
+    };
+    let (ready_tx, ready_rx) = tokio::sync::oneshot::channel();
+    let server = tokio::spawn(async move {
+        MockServer::with_options(snapshot, options)
+            .start_with_ready(0, ready_tx)
+            .await
+    });
+    let addr = timeout(Duration::from_secs(5), ready_rx)
+        .await
+        .expect("Server did not signal readiness")
+        .expect("Ready sender dropped");
+    let client = reqwest::Client::builder()
+        .proxy(reqwest::Proxy::http(format!("http://{}", addr)).unwrap())
+        .build()
+        .unwrap();
+    for (name, status, body) in methods {
+        let method = Method::from_bytes(name.as_bytes()).unwrap();
+        let response = client.request(method, &url).send().await.unwrap();
+        assert_eq!(response.status().as_u16(), status, "{}", name);
+        assert_eq!(response.text().await.unwrap(), body, "{}", name);
+    }
+
+    // Methods are case-sensitive
+    let lowercase = Method::from_bytes(b"x-sync").unwrap();
+    let response = client.request(lowercase, &url).send().await.unwrap();
+    assert_eq!(response.status().as_u16(), 404);
+
+    server.abort();
 }
@@ -0,0 +1,69 @@
+use crate::serve::lifetime::{expired, Activity, ShutdownReason};
+use tokio::time::{timeout, Duration};
+
+#[tokio::test]
+async fn test_requests_reset_idle_timer() {
+    let activity = Activity::new();
+    let idle_timeout = Duration::from_millis(200);
+
+    // Keep the server busy for longer than the idle timeout
+    let busy = async {
+        for _ in 0..5 {
+            tokio::time::sleep(Duration::from_millis(80)).await;
+            activity.touch();
+        }
+    };
+    tokio::select! {
+        _ = busy => {}
+        reason = expired(&activity, Some(idle_timeout), None) => {
+            panic!("shut down while requests kept arriving: {}", reason)
+        }
+    }
+    assert!(activity.idle_for() < idle_timeout);
+
+    let reason = timeout(
+        Duration::from_secs(2),
+        expired(&activity, Some(idle_timeout), None),
+    )
+    .await
+    .expect("idle timeout did not fire");
+    assert_eq!(reason, ShutdownReason::Idle(idle_timeout));
+}
+
+#[tokio::test]
+async fn test_max_lifetime_ignores_activity() {
+    let activity = Activity::new();
+    let max_lifetime = Duration::from_millis(150);
+
+    let busy = async {
+        loop {
+            activity.touch();
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+    };
+    let reason = tokio::select! {
+        _ = busy => unreachable!(),
+        reason = expired(&activity, Some(Duration::from_secs(60)), Some(max_lifetime)) => reason,
+    };
+
+    assert_eq!(reason, ShutdownReason::MaxLifetime(max_lifetime));
+    assert!(activity.uptime() >= max_lifetime);
+}
+
+#[tokio::test]
+async fn test_no_limits_never_expire() {
+    let activity = Activity::new();
+    assert!(
+        timeout(Duration::from_millis(100), expired(&activity, None, None))
+            .await
+            .is_err()
+    );
+}
+
+#[test]
+fn test_shutdown_reason_names_the_option() {
+    let idle = ShutdownReason::Idle(Duration::from_secs(300)).to_string();
+    assert!(idle.contains("300s") && idle.contains("--idle-timeout"));
+    let lifetime = ShutdownReason::MaxLifetime(Duration::from_secs(3600)).to_string();
+    assert!(lifetime.contains("3600s") && lifetime.contains("--max-lifetime"));
+}
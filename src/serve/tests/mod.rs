@@ -1,2 +1,23 @@
+mod absolute_urls_tests;
+mod body_log_tests;
+mod console_tests;
+mod coverage_tests;
+mod dns_tests;
+mod early_hints_tests;
+mod encoding_tests;
+mod etags_tests;
+mod forward_tests;
 mod handlers_tests;
+mod hooks_tests;
+mod host_stats_tests;
 mod integration_tests;
+mod lifetime_tests;
+mod ordering_tests;
+mod origin_map_tests;
+mod overrides_tests;
+mod rate_limit_tests;
+mod redirect_loop_tests;
+mod routing_tests;
+mod telemetry_tests;
+mod throttle_tests;
+mod variants_tests;
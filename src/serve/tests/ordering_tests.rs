@@ -0,0 +1,205 @@
+use crate::capture::proxy::records::RequestRecord;
+use crate::serve::{
+    DependencyRules, MockServer, OrderCheck, OrderingGuard, ResponseOverrides, ServeOptions,
+};
+use crate::storage::Snapshot;
+use crate::test_utils::test_helpers::create_test_record;
+use chrono::Utc;
+use std::path::Path;
+use tokio::time::{timeout, Duration};
+
+fn record(method: &str, url: &str, body: &str) -> RequestRecord {
+    let headers = [("content-type", "application/json")];
+    create_test_record(method, url, 200, &headers, body.as_bytes())
+}
+
+/// A checkout flow as captured: create a cart, read it, add an item, check out
+fn checkout_records() -> Vec<RequestRecord> {
+    vec![
+        record("GET", "http://shop.example/", "<html></html>"),
+        record("POST", "http://shop.example/api/cart", r#"{"id": 7}"#),
+        record(
+            "GET",
+            "http://shop.example/api/cart",
+            r#"{"id": 7, "items": []}"#,
+        ),
+        record(
+            "POST",
+            "http://shop.example/api/cart/items",
+            r#"{"ok": true}"#,
+        ),
+        record(
+            "GET",
+            "http://shop.example/api/cart/items",
+            r#"[{"sku": 1}]"#,
+        ),
+        record("GET", "http://shop.example/api/products", "[]"),
+    ]
+}
+
+fn dependencies(yaml: &str) -> DependencyRules {
+    ResponseOverrides::parse(yaml, Path::new("."))
+        .unwrap()
+        .dependencies()
+        .clone()
+}
+
+#[test]
+fn test_records_wait_for_earlier_writes_on_their_path() {
+    let guard = OrderingGuard::new(&checkout_records(), DependencyRules::default());
+
+    // Nothing was written before the page or the product list
+    assert_eq!(guard.check(0), OrderCheck::Allowed);
+    assert_eq!(guard.check(5), OrderCheck::Allowed);
+
+    // Reading the cart and its items waits for the cart to be created
+    assert_eq!(guard.check(2), OrderCheck::Waiting { for_index: 1 });
+    assert_eq!(guard.check(4), OrderCheck::Waiting { for_index: 1 });
+    assert!(!guard.is_served(2));
+
+    assert_eq!(guard.check(1), OrderCheck::Allowed);
+    assert_eq!(guard.check(2), OrderCheck::Allowed);
+    assert_eq!(guard.check(4), OrderCheck::Waiting { for_index: 3 });
+    assert_eq!(guard.check(3), OrderCheck::Allowed);
+    assert_eq!(guard.check(4), OrderCheck::Allowed);
+
+    // Served records stay available
+    assert_eq!(guard.check(2), OrderCheck::Allowed);
+}
+
+#[test]
+fn test_path_prefixes_respect_segments() {
+    let records = vec![
+        record("POST", "http://shop.example/api/cart", "{}"),
+        record("GET", "http://shop.example/api/cartography", "{}"),
+        record("GET", "http://other.example/api/cart", "{}"),
+    ];
+    let guard = OrderingGuard::new(&records, DependencyRules::default());
+
+    assert_eq!(guard.check(1), OrderCheck::Allowed);
+    assert_eq!(guard.check(2), OrderCheck::Allowed);
+}
+
+#[test]
+fn test_repeated_records_are_served_together() {
+    let records = vec![
+        record("POST", "http://shop.example/api/cart", r#"{"id": 1}"#),
+        record("GET", "http://shop.example/api/cart", "{}"),
+        record("POST", "http://shop.example/api/cart", r#"{"id": 2}"#),
+        record("GET", "http://shop.example/api/cart/summary", "{}"),
+    ];
+    let guard = OrderingGuard::new(&records, DependencyRules::default());
+
+    // The second POST is never matched on its own, so it mustn't block forever
+    assert_eq!(guard.check(0), OrderCheck::Allowed);
+    assert!(guard.is_served(2));
+    assert_eq!(guard.check(3), OrderCheck::Allowed);
+}
+
+#[test]
+fn test_dependency_rules_replace_the_default() {
+    let rules = dependencies(
+        r#"
+dependencies:
+  - method: GET
+    url: "http://shop.example/api/products"
+    after:
+      - method: POST
+        url: "http://shop.example/api/cart/*"
+  - url: "http://shop.example/api/cart/items"
+    after: []
+"#,
+    );
+    assert_eq!(rules.len(), 2);
+    let guard = OrderingGuard::new(&checkout_records(), rules);
+
+    // Products now wait for the item, which itself no longer waits for the cart
+    assert_eq!(guard.check(5), OrderCheck::Waiting { for_index: 3 });
+    assert_eq!(guard.check(4), OrderCheck::Allowed);
+    assert_eq!(guard.check(3), OrderCheck::Allowed);
+    assert_eq!(guard.check(5), OrderCheck::Allowed);
+
+    // Records no rule matches keep the default
+    assert_eq!(guard.check(2), OrderCheck::Waiting { for_index: 1 });
+}
+
+async fn start(stateful: bool) -> (tokio::task::JoinHandle<()>, reqwest::Client) {
+    let snapshot = Snapshot {
+        name: "checkout".to_string(),
+        url: "http://shop.example/".to_string(),
+        created_at: Utc::now(),
+        requests: checkout_records(),
+        ..Default::default()
+    };
+    let options = ServeOptions {
+        banner: false,
+        output: crate::serve::OutputLevel::Quiet,
+        stateful,
+        ..ServeOptions::default()
+    };
+
+    let (ready_tx, ready_rx) = tokio::sync::oneshot::channel();
+    let server = tokio::spawn(async move {
+        let _ = MockServer::with_options(snapshot, options)
+            .start_with_ready(0, ready_tx)
+            .await;
+    });
+    let addr = timeout(Duration::from_secs(5), ready_rx)
+        .await
+        .expect("Server did not signal readiness")
+        .expect("Ready sender dropped");
+
+    let client = reqwest::Client::builder()
+        .proxy(reqwest::Proxy::http(format!("http://{}", addr)).unwrap())
+        .build()
+        .unwrap();
+    (server, client)
+}
+
+#[tokio::test]
+async fn test_stateful_serve_rejects_out_of_order_requests() {
+    let (server, client) = start(true).await;
+
+    let early = client
+        .get("http://shop.example/api/cart")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(early.status(), 404);
+    assert_eq!(
+        early.headers()["x-webmock-waiting-for"],
+        "POST http://shop.example/api/cart"
+    );
+    assert!(early.text().await.unwrap().contains("captured order"));
+
+    let created = client
+        .post("http://shop.example/api/cart")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(created.status(), 200);
+
+    let cart = client
+        .get("http://shop.example/api/cart")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(cart.status(), 200);
+    assert_eq!(cart.text().await.unwrap(), r#"{"id": 7, "items": []}"#);
+
+    server.abort();
+}
+
+#[tokio::test]
+async fn test_serve_ignores_order_without_stateful() {
+    let (server, client) = start(false).await;
+
+    let cart = client
+        .get("http://shop.example/api/cart")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(cart.status(), 200);
+
+    server.abort();
+}
@@ -0,0 +1,225 @@
+use crate::capture::proxy::records::RequestRecord;
+use crate::serve::{MockServer, OriginMap, OutputLevel, ServeOptions};
+use crate::storage::Snapshot;
+use crate::test_utils::test_helpers::create_test_record;
+use chrono::Utc;
+use tokio::time::{timeout, Duration};
+
+fn record(url: &str, status: u16, headers: &[(&str, &str)], body: &str) -> RequestRecord {
+    let mut record = create_test_record("GET", url, status, headers, body.as_bytes());
+    record.response.set_cookie = vec![
+        "session=abc; Domain=.example.com; Path=/; HttpOnly".to_string(),
+        "theme=dark; Path=/".to_string(),
+    ];
+    record
+}
+
+#[test]
+fn test_parse_origin_map() {
+    assert!(OriginMap::parse::<&str>(&[]).unwrap().is_empty());
+    assert!(OriginMap::parse(&["https://app.example.com"]).is_ok());
+    assert!(OriginMap::parse(&[
+        "https://app.example.com=localhost:8080",
+        "https://api.example.com=http://api.localhost:8080",
+    ])
+    .is_ok());
+
+    assert!(OriginMap::parse(&["app.example.com"]).is_err());
+    assert!(OriginMap::parse(&["https://app.example.com/shop"]).is_err());
+    assert!(OriginMap::parse(&["ftp://files.example.com"]).is_err());
+    assert!(OriginMap::parse(&["https://app.example.com=localhost:8080/x"]).is_err());
+    // Each local origin, and the catch-all, can only be mapped once
+    assert!(OriginMap::parse(&["https://a.example.com", "https://b.example.com"]).is_err());
+    assert!(OriginMap::parse(&[
+        "https://a.example.com=localhost:8080",
+        "https://b.example.com=LOCALHOST:8080",
+    ])
+    .is_err());
+}
+
+#[test]
+fn test_resolve_picks_origin_by_host() {
+    let map = OriginMap::parse(&[
+        "https://app.example.com",
+        "https://api.example.com=api.localhost:8080",
+    ])
+    .unwrap();
+
+    let mapped = map.resolve(Some("localhost:8080"), "/cart?id=1").unwrap();
+    assert_eq!(mapped.url, "https://app.example.com/cart?id=1");
+    let mapped = map
+        .resolve(Some("API.localhost:8080"), "/v1/items")
+        .unwrap();
+    assert_eq!(mapped.url, "https://api.example.com/v1/items");
+    let mapped = map.resolve(None, "/").unwrap();
+    assert_eq!(mapped.url, "https://app.example.com/");
+
+    // Without a catch-all, unmapped hosts aren't resolved
+    let map = OriginMap::parse(&["https://api.example.com=api.localhost:8080"]).unwrap();
+    assert!(map.resolve(Some("localhost:8080"), "/").is_none());
+}
+
+#[test]
+fn test_rewrite_location_to_local_origin() {
+    let map = OriginMap::parse(&[
+        "https://app.example.com",
+        "https://auth.example.com=auth.localhost:9000",
+    ])
+    .unwrap();
+    let mapped = map.resolve(Some("localhost:8080"), "/account").unwrap();
+
+    assert_eq!(
+        mapped
+            .rewrite_location("https://app.example.com/login?next=%2Faccount#top")
+            .as_deref(),
+        Some("http://localhost:8080/login?next=%2Faccount#top")
+    );
+    assert_eq!(
+        mapped.rewrite_location("//app.example.com/home").as_deref(),
+        Some("http://localhost:8080/home")
+    );
+    assert_eq!(
+        mapped
+            .rewrite_location("https://auth.example.com/sso")
+            .as_deref(),
+        Some("http://auth.localhost:9000/sso")
+    );
+    // Relative and unmapped locations are left alone
+    assert!(mapped.rewrite_location("/login").is_none());
+    assert!(mapped
+        .rewrite_location("https://partner.example.org/")
+        .is_none());
+    assert!(mapped
+        .rewrite_location("http://app.example.com/insecure")
+        .is_none());
+}
+
+#[test]
+fn test_rewrite_cookie_domain() {
+    let map = OriginMap::parse(&[
+        "https://app.example.com",
+        "https://api.example.com=api.localhost:8080",
+    ])
+    .unwrap();
+
+    let on_localhost = map.resolve(Some("localhost:8080"), "/").unwrap();
+    assert_eq!(
+        on_localhost.rewrite_cookie("sid=1; Domain=.example.com; Path=/; Secure"),
+        "sid=1; Path=/; Secure"
+    );
+    let on_ip = map.resolve(Some("127.0.0.1:8080"), "/").unwrap();
+    assert_eq!(
+        on_ip.rewrite_cookie("sid=1; path=/; domain=app.example.com"),
+        "sid=1; path=/"
+    );
+    let on_subdomain = map.resolve(Some("api.localhost:8080"), "/").unwrap();
+    assert_eq!(
+        on_subdomain.rewrite_cookie("sid=1; Domain=example.com; HttpOnly"),
+        "sid=1; Domain=api.localhost; HttpOnly"
+    );
+    // Host-only cookies and values that merely mention a domain are unchanged
+    assert_eq!(
+        on_subdomain.rewrite_cookie("domain=example.com; Path=/"),
+        "domain=example.com; Path=/"
+    );
+}
+
+#[tokio::test]
+async fn test_serve_as_mapped_origin() {
+    let snapshot = Snapshot {
+        name: "mapped".to_string(),
+        url: "https://app.example.com/".to_string(),
+        created_at: Utc::now(),
+        requests: vec![
+            record(
+                "https://app.example.com/",
+                302,
+                &[("location", "https://app.example.com/login")],
+                "",
+            ),
+            record(
+                "https://app.example.com/login",
+                200,
+                &[("content-type", "text/html")],
+                "app login",
+            ),
+            // Same path on another host must not be hit by mistake
+            record(
+                "https://api.example.com/login",
+                200,
+                &[("content-type", "application/json")],
+                "{\"api\":true}",
+            ),
+        ],
+        ..Default::default()
+    };
+    let options = ServeOptions {
+        output: OutputLevel::Quiet,
+        banner: false,
+        origin_map: OriginMap::parse(&[
+            "https://app.example.com",
+            "https://api.example.com=api.localhost:8080",
+        ])
+        .unwrap(),
+        ..ServeOptions::default()
+    };
+    let (ready_tx, ready_rx) = tokio::sync::oneshot::channel();
+    let server = tokio::spawn(async move {
+        MockServer::with_options(snapshot, options)
+            .start_with_ready(0, ready_tx)
+            .await
+    });
+    let addr = timeout(Duration::from_secs(5), ready_rx)
+        .await
+        .expect("Server did not signal readiness")
+        .expect("Ready sender dropped");
+
+    let client = reqwest::Client::builder()
+        .no_proxy()
+        .redirect(reqwest::redirect::Policy::none())
+        .build()
+        .unwrap();
+    let base = format!("http://{}", addr);
+
+    let redirect = client.get(format!("{}/", base)).send().await.unwrap();
+    assert_eq!(redirect.status(), 302);
+    assert_eq!(
+        redirect.headers()["location"],
+        format!("{}/login", base).as_str()
+    );
+    let cookies: Vec<_> = redirect
+        .headers()
+        .get_all("set-cookie")
+        .iter()
+        .map(|value| value.to_str().unwrap().to_string())
+        .collect();
+    assert_eq!(
+        cookies,
+        vec![
+            "session=abc; Path=/; HttpOnly".to_string(),
+            "theme=dark; Path=/".to_string()
+        ]
+    );
+
+    let login = client.get(format!("{}/login", base)).send().await.unwrap();
+    assert_eq!(login.status(), 200);
+    assert_eq!(login.text().await.unwrap(), "app login");
+
+    let api = client
+        .get(format!("{}/login", base))
+        .header("host", "api.localhost:8080")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(api.status(), 200);
+    assert_eq!(api.text().await.unwrap(), "{\"api\":true}");
+
+    let missing = client
+        .get(format!("{}/nowhere", base))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(missing.status(), 404);
+
+    server.abort();
+}
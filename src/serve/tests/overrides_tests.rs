@@ -0,0 +1,254 @@
+use crate::capture::proxy::records::{RequestRecord, ResponseRecord};
+use crate::serve::{MockServer, ResponseOverrides, ServeOptions};
+use crate::storage::Snapshot;
+use chrono::Utc;
+use std::collections::HashMap;
+use std::path::Path;
+use tokio::time::{timeout, Duration};
+
+fn payment_record() -> RequestRecord {
+    let mut headers = HashMap::new();
+    headers.insert("content-type".to_string(), "application/json".to_string());
+    headers.insert("etag".to_string(), "\"v1\"".to_string());
+
+    RequestRecord::new(
+        "POST".to_string(),
+        "http://payments.example/v1/charges".to_string(),
+        HashMap::new(),
+        None,
+        ResponseRecord::new(200, headers, b"{\"id\": \"ch_1\"}".to_vec(), None),
+    )
+}
+
+fn parse(yaml: &str) -> crate::error::Result<ResponseOverrides> {
+    ResponseOverrides::parse(yaml, Path::new("."))
+}
+
+#[test]
+fn test_override_replaces_status_body_and_headers() {
+    let overrides = parse(
+        r#"
+overrides:
+  - method: post
+    url: "http://payments.example/*"
+    status: 503
+    body: '{"error": "unavailable"}'
+    headers:
+      Retry-After: "30"
+    remove_headers: [ETag]
+"#,
+    )
+    .unwrap();
+    assert_eq!(overrides.len(), 1);
+
+    let rule = overrides
+        .find("POST", "http://payments.example/v1/charges")
+        .unwrap();
+    let record = rule.apply(&payment_record());
+
+    assert_eq!(record.response.status, 503);
+    assert_eq!(record.response.body, b"{\"error\": \"unavailable\"}");
+    assert_eq!(record.response.headers.get("retry-after").unwrap(), "30");
+    assert!(!record.response.headers.contains_key("etag"));
+    assert_eq!(
+        record.response.headers.get("content-type").unwrap(),
+        "application/json"
+    );
+}
+
+#[test]
+fn test_override_matching_respects_method_and_order() {
+    let overrides = parse(
+        r#"
+overrides:
+  - method: GET
+    url: "http://payments.example/*"
+    status: 404
+  - url: "http://payments.example/v1/*"
+    status: 500
+  - url: "*"
+    status: 418
+"#,
+    )
+    .unwrap();
+
+    let url = "http://payments.example/v1/charges";
+    assert_eq!(
+        overrides
+            .find("GET", url)
+            .unwrap()
+            .apply(&payment_record())
+            .response
+            .status,
+        404
+    );
+    assert_eq!(
+        overrides
+            .find("POST", url)
+            .unwrap()
+            .apply(&payment_record())
+            .response
+            .status,
+        500
+    );
+    assert_eq!(
+        overrides
+            .find("POST", "http://other.example/")
+            .unwrap()
+            .apply(&payment_record())
+            .response
+            .status,
+        418
+    );
+}
+
+#[test]
+fn test_disabled_override_is_skipped() {
+    let overrides = parse(
+        r#"
+overrides:
+  - url: "http://payments.example/*"
+    status: 503
+    enabled: false
+"#,
+    )
+    .unwrap();
+
+    assert!(overrides.is_empty());
+    assert!(overrides
+        .find("POST", "http://payments.example/v1/charges")
+        .is_none());
+}
+
+#[test]
+fn test_override_wins_over_capture_failure() {
+    let overrides = parse(
+        r#"
+overrides:
+  - url: "http://payments.example/*"
+    status: 503
+"#,
+    )
+    .unwrap();
+
+    let mut failed = payment_record();
+    failed.response = ResponseRecord::failed("connection refused");
+
+    let record = overrides.find("POST", &failed.url).unwrap().apply(&failed);
+    assert!(!record.response.is_failure());
+    assert_eq!(record.response.status, 503);
+}
+
+#[test]
+fn test_override_body_file_is_relative_to_overrides_file() {
+    let temp_dir = tempfile::TempDir::new().unwrap();
+    std::fs::write(temp_dir.path().join("error.json"), "{\"error\": true}").unwrap();
+    let path = temp_dir.path().join("overrides.yaml");
+    std::fs::write(
+        &path,
+        "overrides:\n  - url: \"http://payments.example/*\"\n    body_file: error.json\n",
+    )
+    .unwrap();
+
+    let overrides = ResponseOverrides::load(&path).unwrap();
+    let record = overrides
+        .find("POST", "http://payments.example/v1/charges")
+        .unwrap()
+        .apply(&payment_record());
+    assert_eq!(record.response.body, b"{\"error\": true}");
+    assert_eq!(record.response.status, 200);
+}
+
+#[test]
+fn test_override_validation_errors() {
+    let cases = [
+        // Unknown keys are rejected instead of silently ignored
+        (
+            "overrides:\n  - url: \"*\"\n    staus: 503\n",
+            "unknown field `staus`",
+        ),
+        ("overrides:\n  - url: \"http://a/[\"\n", "invalid url glob"),
+        (
+            "overrides:\n  - url: \"*\"\n    status: 1000\n",
+            "invalid status 1000",
+        ),
+        (
+            "overrides:\n  - url: \"*\"\n    body: x\n    body_file: y\n",
+            "cannot both be set",
+        ),
+        (
+            "overrides:\n  - url: \"*\"\n    headers:\n      \"bad header\": x\n",
+            "invalid header name",
+        ),
+        // Disabled entries are still validated
+        (
+            "overrides:\n  - url: \"*\"\n  - url: \"[\"\n    enabled: false\n",
+            "entry 2: invalid url glob",
+        ),
+        (
+            "dependencies:\n  - url: \"*\"\n    after:\n      - method: \"NOT A METHOD\"\n        url: \"*\"\n",
+            "dependency 1: invalid method",
+        ),
+        ("dependencies:\n  - url: \"*\"\n", "missing field `after`"),
+    ];
+
+    for (yaml, expected) in cases {
+        let error = parse(yaml).unwrap_err().to_string();
+        assert!(error.contains(expected), "{}: got {}", expected, error);
+    }
+}
+
+#[test]
+fn test_missing_overrides_file() {
+    let error = ResponseOverrides::load(Path::new("/nonexistent/overrides.yaml"))
+        .unwrap_err()
+        .to_string();
+    assert!(error.contains("Cannot read overrides file"));
+}
+
+#[tokio::test]
+async fn test_mock_server_serves_overridden_response() {
+    let snapshot = Snapshot {
+        name: "payments".to_string(),
+        url: "http://payments.example/".to_string(),
+        created_at: Utc::now(),
+        requests: vec![payment_record()],
+        ..Default::default()
+    };
+    let options = ServeOptions {
+        output: crate::serve::OutputLevel::Quiet,
+        banner: false,
+        overrides: parse(
+            "overrides:\n  - method: POST\n    url: \"http://payments.example/*\"\n    status: 503\n",
+        )
+        .unwrap(),
+        ..ServeOptions::default()
+    };
+
+    let (ready_tx, ready_rx) = tokio::sync::oneshot::channel();
+    let server = tokio::spawn(async move {
+        MockServer::with_options(snapshot, options)
+            .start_with_ready(0, ready_tx)
+            .await
+    });
+    let addr = timeout(Duration::from_secs(5), ready_rx)
+        .await
+        .expect("Server did not signal readiness")
+        .expect("Ready sender dropped");
+
+    let client = reqwest::Client::builder()
+        .proxy(reqwest::Proxy::http(format!("http://{}", addr)).unwrap())
+        .build()
+        .unwrap();
+    let response = client
+        .post("http://payments.example/v1/charges")
+        .send()
+        .await
+        .unwrap();
+
+    assert_eq!(response.status(), 503);
+    // The recorded body is kept when the override doesn't replace it
+    assert_eq!(response.text().await.unwrap(), "{\"id\": \"ch_1\"}");
+
+    server.abort();
+}
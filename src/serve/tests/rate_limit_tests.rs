@@ -0,0 +1,244 @@
+use std::net::{IpAddr, Ipv4Addr};
+use std::time::{Duration, Instant};
+
+use chrono::Utc;
+use tokio::time::timeout;
+
+use crate::capture::proxy::records::RequestRecord;
+use crate::serve::rate_limit::*;
+use crate::serve::{MockServer, ServeOptions};
+use crate::storage::Snapshot;
+use crate::test_utils::test_helpers::create_test_record;
+
+const CLIENT: IpAddr = IpAddr::V4(Ipv4Addr::LOCALHOST);
+const OTHER_CLIENT: IpAddr = IpAddr::V4(Ipv4Addr::new(10, 0, 0, 2));
+
+const SEARCH: &str = "http://api.example/search";
+
+fn record(url: &str, status: u16, headers: &[(&str, &str)], body: &str) -> RequestRecord {
+    create_test_record("GET", url, status, headers, body.as_bytes())
+}
+
+/// Two searches answered, then the third refused for `retry_after` seconds
+fn captured_burst(retry_after: &str) -> Vec<RequestRecord> {
+    vec![
+        record("http://api.example/", 200, &[], "<html></html>"),
+        record(SEARCH, 200, &[], "[1]"),
+        record(SEARCH, 200, &[], "[2]"),
+        record(SEARCH, 429, &[("Retry-After", retry_after)], "slow down"),
+    ]
+}
+
+#[test]
+fn test_rate_limit_allows_captured_successes_per_window() {
+    let limiter = RateLimiter::new(&captured_burst("10"));
+    assert_eq!(limiter.len(), 1);
+    let start = Instant::now();
+
+    // Whichever search record the request matched, the first 200 is served
+    for matched in [3, 1] {
+        assert_eq!(
+            limiter.check(CLIENT, matched, start),
+            Some(RateCheck::Allowed { record_index: 1 })
+        );
+    }
+    assert_eq!(
+        limiter.check(CLIENT, 1, start + Duration::from_secs(4)),
+        Some(RateCheck::Limited {
+            record_index: 3,
+            retry_after: Duration::from_secs(6),
+        })
+    );
+
+    // Refused requests don't count, so the window ends 10s after the first requests
+    for _ in 0..2 {
+        assert_eq!(
+            limiter.check(CLIENT, 1, start + Duration::from_secs(10)),
+            Some(RateCheck::Allowed { record_index: 1 })
+        );
+    }
+    assert!(matches!(
+        limiter.check(CLIENT, 1, start + Duration::from_secs(10)),
+        Some(RateCheck::Limited { .. })
+    ));
+
+    // Other endpoints aren't limited
+    assert_eq!(limiter.check(CLIENT, 0, start), None);
+}
+
+#[test]
+fn test_rate_limit_tracks_clients_separately() {
+    let limiter = RateLimiter::new(&captured_burst("10"));
+    let now = Instant::now();
+
+    limiter.check(CLIENT, 1, now);
+    limiter.check(CLIENT, 1, now);
+    assert!(matches!(
+        limiter.check(CLIENT, 1, now),
+        Some(RateCheck::Limited { .. })
+    ));
+    assert_eq!(
+        limiter.check(OTHER_CLIENT, 1, now),
+        Some(RateCheck::Allowed { record_index: 1 })
+    );
+}
+
+#[test]
+fn test_rate_limit_header_sets_the_limit() {
+    let records = vec![
+        record(SEARCH, 200, &[], "[]"),
+        record(
+            SEARCH,
+            429,
+            &[("retry-after", "60"), ("X-RateLimit-Limit", "3")],
+            "",
+        ),
+    ];
+    let limiter = RateLimiter::new(&records);
+    let now = Instant::now();
+
+    for _ in 0..3 {
+        assert_eq!(
+            limiter.check(CLIENT, 1, now),
+            Some(RateCheck::Allowed { record_index: 0 })
+        );
+    }
+    assert!(matches!(
+        limiter.check(CLIENT, 1, now),
+        Some(RateCheck::Limited {
+            record_index: 1,
+            ..
+        })
+    ));
+}
+
+#[test]
+fn test_rate_limit_needs_a_429_with_retry_after() {
+    let records = vec![
+        record(SEARCH, 200, &[], "[]"),
+        record(SEARCH, 429, &[], "no Retry-After"),
+        record("http://api.example/other", 503, &[("Retry-After", "5")], ""),
+    ];
+    assert!(RateLimiter::new(&records).is_empty());
+}
+
+#[test]
+fn test_rate_limit_forgets_least_recently_seen_client() {
+    let limiter = RateLimiter::with_capacity(&captured_burst("10"), 2);
+    let start = Instant::now();
+
+    limiter.check(CLIENT, 1, start);
+    limiter.check(CLIENT, 1, start);
+    limiter.check(OTHER_CLIENT, 1, start + Duration::from_secs(1));
+    limiter.check(
+        IpAddr::V4(Ipv4Addr::new(10, 0, 0, 3)),
+        1,
+        start + Duration::from_secs(2),
+    );
+
+    assert_eq!(limiter.tracked_clients(), 2);
+    // The first client was evicted, so its burst starts over
+    assert_eq!(
+        limiter.check(CLIENT, 1, start + Duration::from_secs(3)),
+        Some(RateCheck::Allowed { record_index: 1 })
+    );
+}
+
+#[test]
+fn test_parse_retry_after() {
+    assert_eq!(
+        parse_retry_after(" 120 ", None),
+        Some(Duration::from_secs(120))
+    );
+    assert_eq!(
+        parse_retry_after(
+            "Wed, 21 Oct 2026 07:28:30 GMT",
+            Some("Wed, 21 Oct 2026 07:28:00 GMT")
+        ),
+        Some(Duration::from_secs(30))
+    );
+    // A date can't be placed without the response's Date header
+    assert_eq!(
+        parse_retry_after("Wed, 21 Oct 2026 07:28:30 GMT", None),
+        None
+    );
+    assert_eq!(parse_retry_after("soon", None), None);
+
+    assert_eq!(retry_after_seconds(Duration::from_millis(1200)), 2);
+    assert_eq!(retry_after_seconds(Duration::from_secs(3)), 3);
+    assert_eq!(retry_after_seconds(Duration::ZERO), 1);
+}
+
+async fn start(simulate_rate_limits: bool) -> (tokio::task::JoinHandle<()>, reqwest::Client) {
+    let snapshot = Snapshot {
+        name: "api".to_string(),
+        url: "http://api.example/".to_string(),
+        created_at: Utc::now(),
+        requests: captured_burst("1"),
+        ..Default::default()
+    };
+    let options = ServeOptions {
+        banner: false,
+        output: crate::serve::OutputLevel::Quiet,
+        simulate_rate_limits,
+        ..ServeOptions::default()
+    };
+
+    let (ready_tx, ready_rx) = tokio::sync::oneshot::channel();
+    let server = tokio::spawn(async move {
+        let _ = MockServer::with_options(snapshot, options)
+            .start_with_ready(0, ready_tx)
+            .await;
+    });
+    let addr = timeout(Duration::from_secs(5), ready_rx)
+        .await
+        .expect("Server did not signal readiness")
+        .expect("Ready sender dropped");
+
+    let client = reqwest::Client::builder()
+        .proxy(reqwest::Proxy::http(format!("http://{}", addr)).unwrap())
+        .build()
+        .unwrap();
+    (server, client)
+}
+
+async fn burst(client: &reqwest::Client, requests: usize) -> Vec<u16> {
+    let mut statuses = Vec::new();
+    for _ in 0..requests {
+        let response = client.get(SEARCH).send().await.unwrap();
+        statuses.push(response.status().as_u16());
+    }
+    statuses
+}
+
+#[tokio::test]
+async fn test_serve_enforces_captured_rate_limit() {
+    let (server, client) = start(true).await;
+
+    // Two requests per second, as captured
+    assert_eq!(burst(&client, 4).await, vec![200, 200, 429, 429]);
+
+    let limited = client.get(SEARCH).send().await.unwrap();
+    assert_eq!(limited.status(), 429);
+    assert_eq!(limited.headers()["retry-after"], "1");
+    assert_eq!(limited.text().await.unwrap(), "slow down");
+
+    // Once the window has passed the client gets its two requests again
+    tokio::time::sleep(Duration::from_millis(1100)).await;
+    let allowed = client.get(SEARCH).send().await.unwrap();
+    assert_eq!(allowed.status(), 200);
+    assert_eq!(allowed.text().await.unwrap(), "[1]");
+    assert_eq!(burst(&client, 2).await, vec![200, 429]);
+
+    server.abort();
+}
+
+#[tokio::test]
+async fn test_serve_without_rate_limits_never_refuses() {
+    let (server, client) = start(false).await;
+
+    let statuses = burst(&client, 5).await;
+    assert!(statuses.iter().all(|&status| status == statuses[0]));
+
+    server.abort();
+}
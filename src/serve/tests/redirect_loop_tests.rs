@@ -0,0 +1,106 @@
+use std::net::{IpAddr, Ipv4Addr};
+use std::time::{Duration, Instant};
+
+use crate::serve::redirect_loop::*;
+
+const CLIENT: IpAddr = IpAddr::V4(Ipv4Addr::LOCALHOST);
+const OTHER_CLIENT: IpAddr = IpAddr::V4(Ipv4Addr::new(10, 0, 0, 2));
+
+#[test]
+fn test_redirect_guard_refuses_after_limit() {
+    let guard = RedirectLoopGuard::new(3);
+    let now = Instant::now();
+
+    for _ in 0..3 {
+        assert_eq!(guard.check(CLIENT, 0, now), RedirectCheck::Allowed);
+    }
+    assert_eq!(
+        guard.check(CLIENT, 0, now),
+        RedirectCheck::Loop {
+            first: true,
+            count: 4
+        }
+    );
+    // Only the first refusal is reported as newly detected
+    assert_eq!(
+        guard.check(CLIENT, 0, now),
+        RedirectCheck::Loop {
+            first: false,
+            count: 5
+        }
+    );
+}
+
+#[test]
+fn test_redirect_guard_tracks_clients_and_records_separately() {
+    let guard = RedirectLoopGuard::new(2);
+    let now = Instant::now();
+
+    guard.check(CLIENT, 0, now);
+    guard.check(CLIENT, 0, now);
+    assert_eq!(guard.check(CLIENT, 1, now), RedirectCheck::Allowed);
+    assert_eq!(guard.check(OTHER_CLIENT, 0, now), RedirectCheck::Allowed);
+    assert!(matches!(
+        guard.check(CLIENT, 0, now),
+        RedirectCheck::Loop { .. }
+    ));
+}
+
+#[test]
+fn test_redirect_guard_resets_after_pause() {
+    let guard = RedirectLoopGuard::with_window(2, Duration::from_millis(100), 16);
+    let start = Instant::now();
+
+    guard.check(CLIENT, 0, start);
+    guard.check(CLIENT, 0, start);
+    assert!(matches!(
+        guard.check(CLIENT, 0, start),
+        RedirectCheck::Loop { .. }
+    ));
+
+    // A reload after the client settles is served normally again
+    let later = start + Duration::from_millis(500);
+    assert_eq!(guard.check(CLIENT, 0, later), RedirectCheck::Allowed);
+}
+
+#[test]
+fn test_redirect_guard_disabled_with_zero_limit() {
+    let guard = RedirectLoopGuard::new(0);
+    let now = Instant::now();
+
+    assert!(!guard.is_enabled());
+    for _ in 0..100 {
+        assert_eq!(guard.check(CLIENT, 0, now), RedirectCheck::Allowed);
+    }
+    assert_eq!(guard.tracked(), 0);
+}
+
+#[test]
+fn test_redirect_guard_memory_is_bounded() {
+    let guard = RedirectLoopGuard::with_window(10, Duration::from_secs(60), 8);
+    let now = Instant::now();
+
+    for index in 0..100 {
+        guard.check(CLIENT, index, now + Duration::from_millis(index as u64));
+    }
+    assert_eq!(guard.tracked(), 8);
+
+    // The most recently seen records are the ones kept
+    for index in 92..100 {
+        assert_eq!(
+            guard.check(CLIENT, index, now + Duration::from_millis(100)),
+            RedirectCheck::Allowed
+        );
+    }
+    assert_eq!(guard.tracked(), 8);
+}
+
+#[test]
+fn test_is_redirect_status() {
+    for status in [301, 302, 303, 307, 308] {
+        assert!(is_redirect_status(status));
+    }
+    for status in [200, 300, 304, 404, 508] {
+        assert!(!is_redirect_status(status));
+    }
+}
@@ -0,0 +1,62 @@
+use crate::serve::routing::*;
+
+const PREFIX: &str = "/_s/shop";
+const ORIGIN: &str = "https://shop.example";
+
+#[test]
+fn test_split_route() {
+    assert_eq!(split_route("/_s/shop/cart"), Some(("shop", "/cart")));
+    assert_eq!(split_route("/_s/shop/"), Some(("shop", "/")));
+    assert_eq!(split_route("/_s/shop"), Some(("shop", "")));
+    assert_eq!(split_route("/_s/"), None);
+    assert_eq!(split_route("/_s//cart"), None);
+    assert_eq!(split_route("/cart"), None);
+}
+
+#[test]
+fn test_rewrite_html_keeps_links_under_prefix() {
+    let html = r#"<a href="/cart">Cart</a><img src='/logo.png'><a href="https://shop.example/about?x=1">About</a><a href="https://shop.example">Home</a>"#;
+    assert_eq!(
+        rewrite_html(html, PREFIX, ORIGIN),
+        r#"<a href="/_s/shop/cart">Cart</a><img src='/_s/shop/logo.png'><a href="/_s/shop/about?x=1">About</a><a href="/_s/shop/">Home</a>"#
+    );
+
+    let css = "body { background: url(/bg.png) } .a { background: url(\"/a.png\") }";
+    assert_eq!(
+        rewrite_html(css, PREFIX, ORIGIN),
+        "body { background: url(/_s/shop/bg.png) } .a { background: url(\"/_s/shop/a.png\") }"
+    );
+}
+
+#[test]
+fn test_rewrite_html_leaves_other_links_alone() {
+    let html = concat!(
+        r#"<a href="//cdn.example/x.js">"#,
+        r#"<a href="https://other.example/">"#,
+        r#"<a href="https://shop.example.evil/">"#,
+        r#"<a href="relative/page">"#,
+        r#"<a href="/_s/shop/already">"#,
+        "<p>uses = /not/a/link</p>"
+    );
+    assert_eq!(rewrite_html(html, PREFIX, ORIGIN), html);
+}
+
+#[test]
+fn test_rewrite_location() {
+    assert_eq!(
+        rewrite_location("/login?next=%2F", PREFIX, ORIGIN).as_deref(),
+        Some("/_s/shop/login?next=%2F")
+    );
+    assert_eq!(
+        rewrite_location("https://shop.example/checkout", PREFIX, ORIGIN).as_deref(),
+        Some("/_s/shop/checkout")
+    );
+    assert_eq!(
+        rewrite_location("https://shop.example", PREFIX, ORIGIN).as_deref(),
+        Some("/_s/shop/")
+    );
+    assert_eq!(
+        rewrite_location("https://other.example/", PREFIX, ORIGIN),
+        None
+    );
+}
@@ -0,0 +1,147 @@
+use crate::capture::proxy::records::{RequestRecord, ResponseRecord};
+use crate::serve::{MockServer, OutputLevel, ServeOptions};
+use crate::storage::Snapshot;
+use chrono::Utc;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use tokio::time::{timeout, Duration};
+use tracing::field::{Field, Visit};
+use tracing::span::{Attributes, Id, Record};
+use tracing::Subscriber;
+use tracing_subscriber::layer::{Context, SubscriberExt};
+use tracing_subscriber::registry::LookupSpan;
+use tracing_subscriber::Layer;
+
+#[derive(Debug, Clone, Default)]
+struct CapturedSpan {
+    name: String,
+    parent: Option<String>,
+    fields: HashMap<String, String>,
+}
+
+/// Keeps every span's name, parent name and latest field values
+#[derive(Clone, Default)]
+struct CapturingLayer {
+    spans: Arc<Mutex<HashMap<u64, CapturedSpan>>>,
+}
+
+impl CapturingLayer {
+    fn spans(&self, name: &str) -> Vec<CapturedSpan> {
+        let spans = self.spans.lock().unwrap();
+        spans
+            .values()
+            .filter(|span| span.name == name)
+            .cloned()
+            .collect()
+    }
+}
+
+struct FieldVisitor<'a>(&'a mut HashMap<String, String>);
+
+impl Visit for FieldVisitor<'_> {
+    fn record_str(&mut self, field: &Field, value: &str) {
+        self.0.insert(field.name().to_string(), value.to_string());
+    }
+
+    fn record_debug(&mut self, field: &Field, value: &dyn std::fmt::Debug) {
+        self.0
+            .insert(field.name().to_string(), format!("{:?}", value));
+    }
+}
+
+impl<S> Layer<S> for CapturingLayer
+where
+    S: Subscriber + for<'span> LookupSpan<'span>,
+{
+    fn on_new_span(&self, attrs: &Attributes<'_>, id: &Id, ctx: Context<'_, S>) {
+        let mut span = CapturedSpan {
+            name: attrs.metadata().name().to_string(),
+            parent: ctx
+                .span(id)
+                .and_then(|span| span.parent())
+                .map(|parent| parent.name().to_string()),
+            fields: HashMap::new(),
+        };
+        attrs.record(&mut FieldVisitor(&mut span.fields));
+        self.spans.lock().unwrap().insert(id.into_u64(), span);
+    }
+
+    fn on_record(&self, id: &Id, values: &Record<'_>, _ctx: Context<'_, S>) {
+        if let Some(span) = self.spans.lock().unwrap().get_mut(&id.into_u64()) {
+            values.record(&mut FieldVisitor(&mut span.fields));
+        }
+    }
+}
+
+#[tokio::test]
+async fn test_serve_spans_carry_request_outcome() {
+    let layer = CapturingLayer::default();
+    let _guard =
+        tracing::subscriber::set_default(tracing_subscriber::registry().with(layer.clone()));
+
+    let snapshot = Snapshot {
+        name: "traced".to_string(),
+        url: "http://traced.example/".to_string(),
+        created_at: Utc::now(),
+        requests: vec![RequestRecord::new(
+            "GET".to_string(),
+            "http://traced.example/".to_string(),
+            HashMap::new(),
+            None,
+            ResponseRecord::new(200, HashMap::new(), b"hello".to_vec(), None),
+        )],
+        ..Default::default()
+    };
+    let options = ServeOptions {
+        output: OutputLevel::Quiet,
+        banner: false,
+        ..ServeOptions::default()
+    };
+    let (ready_tx, ready_rx) = tokio::sync::oneshot::channel();
+    let server = tokio::spawn(async move {
+        MockServer::with_options(snapshot, options)
+            .start_with_ready(0, ready_tx)
+            .await
+    });
+    let addr = timeout(Duration::from_secs(5), ready_rx)
+        .await
+        .expect("Server did not signal readiness")
+        .expect("Ready sender dropped");
+
+    let client = reqwest::Client::builder()
+        .proxy(reqwest::Proxy::http(format!("http://{}", addr)).unwrap())
+        .build()
+        .unwrap();
+    let found = client.get("http://traced.example/").send().await.unwrap();
+    assert_eq!(found.status(), 200);
+    let missing = client
+        .get("http://traced.example/missing")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(missing.status(), 404);
+    server.abort();
+
+    let connections = layer.spans("connection");
+    assert!(!connections.is_empty());
+    assert!(connections[0].fields.contains_key("peer"));
+
+    let requests = layer.spans("request");
+    let by_url = |url: &str| {
+        requests
+            .iter()
+            .find(|span| span.fields.get("url").map(String::as_str) == Some(url))
+            .unwrap_or_else(|| panic!("no request span for {}: {:?}", url, requests))
+            .clone()
+    };
+
+    let found = by_url("http://traced.example/");
+    assert_eq!(found.parent.as_deref(), Some("connection"));
+    assert_eq!(found.fields["method"], "GET");
+    assert_eq!(found.fields["matched"], "true");
+    assert_eq!(found.fields["status"], "200");
+
+    let missing = by_url("http://traced.example/missing");
+    assert_eq!(missing.fields["matched"], "false");
+    assert_eq!(missing.fields["status"], "404");
+}
@@ -0,0 +1,239 @@
+use crate::capture::proxy::records::{RequestRecord, ResponseRecord};
+use crate::serve::throttle::throttle_response;
+use crate::serve::{MockServer, OutputLevel, ServeOptions, Throttle, ThrottleProfile};
+use crate::storage::Snapshot;
+use bytes::Bytes;
+use chrono::Utc;
+use http_body_util::{BodyExt, Full};
+use hyper::Response;
+use std::collections::HashMap;
+use std::convert::Infallible;
+use tokio::time::{timeout, Duration, Instant};
+
+const BODY_SIZE: usize = 20_000;
+
+fn snapshot() -> Snapshot {
+    let mut headers = HashMap::new();
+    headers.insert(
+        "content-type".to_string(),
+        "application/octet-stream".to_string(),
+    );
+    Snapshot {
+        name: "throttle".to_string(),
+        url: "http://throttle.example/".to_string(),
+        created_at: Utc::now(),
+        requests: vec![RequestRecord::new(
+            "GET".to_string(),
+            "http://throttle.example/large.bin".to_string(),
+            HashMap::new(),
+            None,
+            ResponseRecord::new(200, headers, vec![b'x'; BODY_SIZE], None),
+        )],
+        ..Default::default()
+    }
+}
+
+async fn start(throttle: Throttle) -> (String, tokio::task::JoinHandle<()>) {
+    let options = ServeOptions {
+        output: OutputLevel::Quiet,
+        banner: false,
+        throttle: Some(throttle),
+        ..ServeOptions::default()
+    };
+    let (ready_tx, ready_rx) = tokio::sync::oneshot::channel();
+    let server = tokio::spawn(async move {
+        let _ = MockServer::with_options(snapshot(), options)
+            .start_with_ready(0, ready_tx)
+            .await;
+    });
+    let addr = timeout(Duration::from_secs(5), ready_rx)
+        .await
+        .expect("Server did not signal readiness")
+        .expect("Ready sender dropped");
+    (format!("http://{}", addr), server)
+}
+
+/// Fetch the large record over a connection of its own
+async fn fetch(proxy: &str) -> Duration {
+    let client = reqwest::Client::builder()
+        .proxy(reqwest::Proxy::http(proxy).unwrap())
+        .build()
+        .unwrap();
+    let started = Instant::now();
+    let response = client
+        .get("http://throttle.example/large.bin")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 200);
+    assert_eq!(response.bytes().await.unwrap().len(), BODY_SIZE);
+    started.elapsed()
+}
+
+#[test]
+fn test_parse_profiles_and_specs() {
+    assert_eq!(
+        ThrottleProfile::parse("slow-3g").unwrap(),
+        ThrottleProfile::SLOW_3G
+    );
+    assert_eq!(
+        ThrottleProfile::parse("Fast-3G").unwrap(),
+        ThrottleProfile::FAST_3G
+    );
+    assert_eq!(ThrottleProfile::parse("dsl").unwrap(), ThrottleProfile::DSL);
+
+    let custom = ThrottleProfile::parse("down=400kbps,latency=300ms").unwrap();
+    assert_eq!(custom.down_bps, Some(400_000));
+    assert_eq!(custom.latency, Duration::from_millis(300));
+
+    let custom = ThrottleProfile::parse("down=1.5mbps").unwrap();
+    assert_eq!(custom.down_bps, Some(1_500_000));
+    assert_eq!(custom.latency, Duration::ZERO);
+
+    let custom = ThrottleProfile::parse("latency=2s").unwrap();
+    assert_eq!(custom.down_bps, None);
+    assert_eq!(custom.latency, Duration::from_secs(2));
+
+    for invalid in [
+        "",
+        "3g",
+        "down=400",
+        "down=fast",
+        "down=1bps",
+        "latency=300",
+        "up=100kbps",
+        "down=400kbps;latency=300ms",
+    ] {
+        assert!(
+            ThrottleProfile::parse(invalid).is_err(),
+            "'{}' should be rejected",
+            invalid
+        );
+    }
+}
+
+#[test]
+fn test_transfer_time() {
+    // 400kbps is 50,000 bytes a second
+    assert_eq!(
+        ThrottleProfile::SLOW_3G.transfer_time(100_000),
+        Duration::from_secs(2)
+    );
+    assert_eq!(
+        ThrottleProfile::parse("latency=1s")
+            .unwrap()
+            .transfer_time(100_000),
+        Duration::ZERO
+    );
+}
+
+#[tokio::test]
+async fn test_body_is_paced_in_chunks() {
+    let profile = ThrottleProfile::parse("down=800kbps,latency=100ms").unwrap();
+    let throttle = Throttle::new(profile, false).connection();
+    let response = Response::new(Full::new(Bytes::from(vec![0u8; BODY_SIZE])));
+
+    let started = Instant::now();
+    let response = throttle_response(Some(&throttle), Ok::<_, Infallible>(response))
+        .await
+        .unwrap();
+    assert!(started.elapsed() >= Duration::from_millis(100));
+
+    // The length is still known up front for the content-length header
+    assert_eq!(
+        hyper::body::Body::size_hint(response.body()).exact(),
+        Some(BODY_SIZE as u64)
+    );
+
+    let mut body = response.into_body();
+    let mut chunks = 0;
+    let mut received = 0;
+    while let Some(frame) = body.frame().await {
+        received += frame.unwrap().into_data().unwrap().len();
+        chunks += 1;
+    }
+    assert_eq!(received, BODY_SIZE);
+    assert!(chunks > 1, "the body should be written in several chunks");
+    let minimum = profile.latency + profile.transfer_time(BODY_SIZE);
+    assert!(
+        started.elapsed() >= minimum,
+        "took {:?}, expected at least {:?}",
+        started.elapsed(),
+        minimum
+    );
+}
+
+#[tokio::test]
+async fn test_unthrottled_body_passes_through() {
+    let response = Response::new(Full::new(Bytes::from_static(b"hello")));
+    let response = throttle_response(None, Ok::<_, Infallible>(response))
+        .await
+        .unwrap();
+    let body = response.into_body().collect().await.unwrap().to_bytes();
+    assert_eq!(body, Bytes::from_static(b"hello"));
+}
+
+#[tokio::test]
+async fn test_throttled_response_takes_at_least_the_computed_time() {
+    // 100,000 bytes a second: the body alone takes 200ms
+    let profile = ThrottleProfile::parse("down=800kbps,latency=100ms").unwrap();
+    let (proxy, server) = start(Throttle::new(profile, false)).await;
+
+    let elapsed = fetch(&proxy).await;
+    let minimum = profile.latency + profile.transfer_time(BODY_SIZE);
+    assert!(
+        elapsed >= minimum,
+        "took {:?}, expected at least {:?}",
+        elapsed,
+        minimum
+    );
+
+    server.abort();
+}
+
+#[tokio::test]
+async fn test_connections_are_throttled_independently() {
+    let profile = ThrottleProfile::parse("down=800kbps").unwrap();
+    let transfer = profile.transfer_time(BODY_SIZE);
+    let (proxy, server) = start(Throttle::new(profile, false)).await;
+
+    let (first, second) = tokio::join!(fetch(&proxy), fetch(&proxy));
+    for elapsed in [first, second] {
+        assert!(
+            elapsed >= transfer,
+            "took {:?}, expected {:?}",
+            elapsed,
+            transfer
+        );
+    }
+    // Each connection has the full rate, so neither waits for the other's body
+    assert!(
+        first.min(second) < transfer * 2,
+        "connections took {:?} and {:?}",
+        first,
+        second
+    );
+
+    server.abort();
+}
+
+#[tokio::test]
+async fn test_shared_throttle_splits_the_rate_between_connections() {
+    let profile = ThrottleProfile::parse("down=800kbps").unwrap();
+    let throttle = Throttle::new(profile, true);
+    assert!(throttle.is_shared());
+    let (proxy, server) = start(throttle).await;
+
+    // Both bodies go through one link, so the last one finishes after both transfers
+    let (first, second) = tokio::join!(fetch(&proxy), fetch(&proxy));
+    let minimum = profile.transfer_time(BODY_SIZE) * 2;
+    assert!(
+        first.max(second) >= minimum,
+        "connections took {:?} and {:?}, expected one to take at least {:?}",
+        first,
+        second,
+        minimum
+    );
+
+    server.abort();
+}
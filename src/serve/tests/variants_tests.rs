@@ -0,0 +1,120 @@
+use crate::capture::proxy::records::RequestRecord;
+use crate::serve::{select_variant, snapshot_variants, MockServer, OutputLevel, ServeOptions};
+use crate::storage::Snapshot;
+use crate::test_utils::test_helpers::create_test_record;
+use chrono::Utc;
+use tokio::time::{timeout, Duration};
+
+fn record(path: &str, body: &str) -> RequestRecord {
+    let url = format!("http://variants.example{}", path);
+    let headers = [("content-type", "text/html")];
+    create_test_record("GET", &url, 200, &headers, body.as_bytes())
+}
+
+fn snapshot() -> Snapshot {
+    Snapshot {
+        name: "variants".to_string(),
+        url: "http://variants.example/".to_string(),
+        created_at: Utc::now(),
+        requests: vec![
+            record("/", "guest home"),
+            record("/app.js", "shared script"),
+            record("/", "admin home").with_variant("admin"),
+            record("/admin/panel", "admin panel").with_variant("admin"),
+            record("/", "member home").with_variant("member"),
+        ],
+        ..Default::default()
+    }
+}
+
+fn bodies(snapshot: &Snapshot) -> Vec<String> {
+    snapshot
+        .requests
+        .iter()
+        .map(|record| String::from_utf8_lossy(&record.response.body).into_owned())
+        .collect()
+}
+
+#[test]
+fn test_snapshot_variants_lists_tagged_names() {
+    assert_eq!(snapshot_variants(&snapshot()), ["admin", "member"]);
+
+    let mut untagged = snapshot();
+    untagged.requests.retain(|record| record.variant.is_none());
+    assert!(snapshot_variants(&untagged).is_empty());
+}
+
+#[test]
+fn test_default_state_serves_only_untagged_records() {
+    let mut snapshot = snapshot();
+    select_variant(&mut snapshot, None);
+    assert_eq!(bodies(&snapshot), ["guest home", "shared script"]);
+}
+
+#[test]
+fn test_variant_records_replace_untagged_ones_of_the_same_url() {
+    let mut snapshot = snapshot();
+    select_variant(&mut snapshot, Some("admin"));
+    assert_eq!(
+        bodies(&snapshot),
+        ["shared script", "admin home", "admin panel"]
+    );
+
+    let mut snapshot = self::snapshot();
+    select_variant(&mut snapshot, Some("member"));
+    assert_eq!(bodies(&snapshot), ["shared script", "member home"]);
+}
+
+async fn start(variant: Option<&str>) -> (String, tokio::task::JoinHandle<()>) {
+    let options = ServeOptions {
+        output: OutputLevel::Quiet,
+        banner: false,
+        variant: variant.map(str::to_string),
+        ..ServeOptions::default()
+    };
+    let (ready_tx, ready_rx) = tokio::sync::oneshot::channel();
+    let server = tokio::spawn(async move {
+        let _ = MockServer::with_options(snapshot(), options)
+            .start_with_ready(0, ready_tx)
+            .await;
+    });
+    let addr = timeout(Duration::from_secs(5), ready_rx)
+        .await
+        .expect("Server did not signal readiness")
+        .expect("Ready sender dropped");
+    (format!("http://{}", addr), server)
+}
+
+async fn get(proxy: &str, path: &str) -> (u16, String) {
+    let client = reqwest::Client::builder()
+        .proxy(reqwest::Proxy::http(proxy).unwrap())
+        .build()
+        .unwrap();
+    let response = client
+        .get(format!("http://variants.example{}", path))
+        .send()
+        .await
+        .unwrap();
+    (response.status().as_u16(), response.text().await.unwrap())
+}
+
+#[tokio::test]
+async fn test_serve_variant_restricts_matching() {
+    let (proxy, server) = start(Some("admin")).await;
+    assert_eq!(get(&proxy, "/").await, (200, "admin home".to_string()));
+    assert_eq!(
+        get(&proxy, "/admin/panel").await,
+        (200, "admin panel".to_string())
+    );
+    // Falls back to the default load for what the variant didn't request
+    assert_eq!(
+        get(&proxy, "/app.js").await,
+        (200, "shared script".to_string())
+    );
+    server.abort();
+
+    let (proxy, server) = start(None).await;
+    assert_eq!(get(&proxy, "/").await, (200, "guest home".to_string()));
+    assert_eq!(get(&proxy, "/admin/panel").await.0, 404);
+    server.abort();
+}
@@ -0,0 +1,358 @@
+//! Slow network simulation for `webmock serve --throttle`
+//!
+//! Replaying from localhost answers every request at once, which hides how a
+//! page behaves on a phone or a congested link. A throttle holds each
+//! response back by a fixed latency before its first byte, then paces the
+//! body out in small chunks at the target download rate. The body is handed
+//! to hyper a chunk at a time instead of as one buffer, so a large response
+//! takes as long as it would over the simulated link.
+//!
+//! Each connection gets its own bandwidth, so parallel requests are limited
+//! independently, like browsers opening several connections to a host. With
+//! `--throttle-shared` every connection draws from one link instead.
+
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::task::{ready, Context, Poll};
+use std::time::Duration;
+
+use bytes::Bytes;
+use http_body::{Body, Frame, SizeHint};
+use hyper::Response;
+use pin_project_lite::pin_project;
+use tokio::time::{Instant, Sleep};
+
+use crate::error::{Result, WebMockError};
+
+/// Smallest chunk a throttled body is written in
+const MIN_CHUNK_SIZE: usize = 1024;
+
+/// Largest chunk a throttled body is written in
+const MAX_CHUNK_SIZE: usize = 16 * 1024;
+
+/// Download rate and latency of a simulated network
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ThrottleProfile {
+    /// Download rate in bits per second; `None` leaves bandwidth unlimited
+    pub down_bps: Option<u64>,
+    /// Delay before the first byte of every response
+    pub latency: Duration,
+}
+
+impl ThrottleProfile {
+    /// Chrome DevTools' "Slow 3G"
+    pub const SLOW_3G: Self = Self {
+        down_bps: Some(400_000),
+        latency: Duration::from_millis(2000),
+    };
+
+    /// Chrome DevTools' "Fast 3G"
+    pub const FAST_3G: Self = Self {
+        down_bps: Some(1_600_000),
+        latency: Duration::from_millis(560),
+    };
+
+    /// WebPageTest's "DSL"
+    pub const DSL: Self = Self {
+        down_bps: Some(1_500_000),
+        latency: Duration::from_millis(50),
+    };
+
+    /// Parse a `--throttle` value: a profile name or a spec such as `down=400kbps,latency=300ms`
+    pub fn parse(spec: &str) -> Result<Self> {
+        match spec.trim().to_ascii_lowercase().as_str() {
+            "slow-3g" => return Ok(Self::SLOW_3G),
+            "fast-3g" => return Ok(Self::FAST_3G),
+            "dsl" => return Ok(Self::DSL),
+            _ => {}
+        }
+
+        let mut profile = Self {
+            down_bps: None,
+            latency: Duration::ZERO,
+        };
+        let mut limited = false;
+        for pair in spec.split(',').filter(|pair| !pair.trim().is_empty()) {
+            let (key, value) = pair.split_once('=').ok_or_else(|| {
+                WebMockError::config(format!(
+                    "Invalid --throttle '{}': use slow-3g, fast-3g, dsl or a spec such as down=400kbps,latency=300ms",
+                    spec
+                ))
+            })?;
+            match key.trim().to_ascii_lowercase().as_str() {
+                "down" => profile.down_bps = Some(parse_rate(value)?),
+                "latency" => profile.latency = parse_latency(value)?,
+                key => {
+                    return Err(WebMockError::config(format!(
+                        "Unknown --throttle setting '{}': use down or latency",
+                        key
+                    )))
+                }
+            }
+            limited = true;
+        }
+
+        if !limited {
+            return Err(WebMockError::config(
+                "--throttle needs a profile or at least one of down and latency",
+            ));
+        }
+        Ok(profile)
+    }
+
+    /// Download rate in bytes per second
+    pub fn down_bytes_per_sec(&self) -> Option<f64> {
+        self.down_bps.map(|bps| bps as f64 / 8.0)
+    }
+
+    /// Time a body of `bytes` bytes takes at the download rate, latency excluded
+    pub fn transfer_time(&self, bytes: usize) -> Duration {
+        self.down_bytes_per_sec().map_or(Duration::ZERO, |rate| {
+            Duration::from_secs_f64(bytes as f64 / rate)
+        })
+    }
+}
+
+/// Parse a rate such as `400kbps`, `1.5mbps` or `56000bps`, in bits per second
+fn parse_rate(value: &str) -> Result<u64> {
+    let value = value.trim().to_ascii_lowercase();
+    let invalid = || {
+        WebMockError::config(format!(
+            "Invalid --throttle rate '{}': use a number followed by bps, kbps or mbps (e.g. 400kbps)",
+            value
+        ))
+    };
+
+    let (amount, multiplier) = if let Some(amount) = value.strip_suffix("mbps") {
+        (amount, 1_000_000.0)
+    } else if let Some(amount) = value.strip_suffix("kbps") {
+        (amount, 1_000.0)
+    } else if let Some(amount) = value.strip_suffix("bps") {
+        (amount, 1.0)
+    } else {
+        return Err(invalid());
+    };
+    let amount: f64 = amount.trim().parse().map_err(|_| invalid())?;
+    let bps = (amount * multiplier).round();
+    if !bps.is_finite() || bps < 8.0 {
+        return Err(WebMockError::config(format!(
+            "--throttle rate '{}' is too low: use at least 8bps",
+            value
+        )));
+    }
+    Ok(bps as u64)
+}
+
+/// Parse a latency such as `300ms` or `2s`
+fn parse_latency(value: &str) -> Result<Duration> {
+    let value = value.trim().to_ascii_lowercase();
+    let invalid = || {
+        WebMockError::config(format!(
+            "Invalid --throttle latency '{}': use a number followed by ms or s (e.g. 300ms)",
+            value
+        ))
+    };
+
+    let (amount, millis_per_unit) = if let Some(amount) = value.strip_suffix("ms") {
+        (amount, 1)
+    } else if let Some(amount) = value.strip_suffix('s') {
+        (amount, 1000)
+    } else {
+        return Err(invalid());
+    };
+    let amount: u64 = amount.trim().parse().map_err(|_| invalid())?;
+    amount
+        .checked_mul(millis_per_unit)
+        .map(Duration::from_millis)
+        .ok_or_else(invalid)
+}
+
+/// A simulated link responses are paced through
+#[derive(Debug)]
+pub struct Bandwidth {
+    bytes_per_sec: f64,
+    /// When the data sent so far has finished going through the link
+    busy_until: Mutex<Option<Instant>>,
+}
+
+impl Bandwidth {
+    fn new(bytes_per_sec: f64) -> Self {
+        Self {
+            bytes_per_sec,
+            busy_until: Mutex::new(None),
+        }
+    }
+
+    /// Chunk size giving the link about twenty writes a second
+    fn chunk_size(&self) -> usize {
+        ((self.bytes_per_sec / 20.0) as usize).clamp(MIN_CHUNK_SIZE, MAX_CHUNK_SIZE)
+    }
+
+    /// Queue `bytes` on the link, returning when they have gone through it
+    fn reserve(&self, bytes: usize) -> Instant {
+        let mut busy_until = self.busy_until.lock().unwrap_or_else(|e| e.into_inner());
+        let now = Instant::now();
+        // An idle link doesn't bank time for later bursts
+        let start = busy_until.map_or(now, |busy| busy.max(now));
+        let done = start + Duration::from_secs_f64(bytes as f64 / self.bytes_per_sec);
+        *busy_until = Some(done);
+        done
+    }
+}
+
+/// The `--throttle` setting of a server
+#[derive(Debug, Clone)]
+pub struct Throttle {
+    profile: ThrottleProfile,
+    /// The one link of `--throttle-shared`
+    shared: Option<Arc<Bandwidth>>,
+}
+
+impl Throttle {
+    /// Throttle with a link per connection, or one link for all with `shared`
+    pub fn new(profile: ThrottleProfile, shared: bool) -> Self {
+        let shared = shared
+            .then(|| profile.down_bytes_per_sec().map(Bandwidth::new))
+            .flatten()
+            .map(Arc::new);
+        Self { profile, shared }
+    }
+
+    /// The profile responses are throttled to
+    pub fn profile(&self) -> &ThrottleProfile {
+        &self.profile
+    }
+
+    /// Check whether all connections share one link
+    pub fn is_shared(&self) -> bool {
+        self.shared.is_some()
+    }
+
+    /// Throttle for a newly accepted connection
+    pub fn connection(&self) -> ConnectionThrottle {
+        let bandwidth = match &self.shared {
+            Some(shared) => Some(Arc::clone(shared)),
+            None => self
+                .profile
+                .down_bytes_per_sec()
+                .map(|rate| Arc::new(Bandwidth::new(rate))),
+        };
+        ConnectionThrottle {
+            latency: self.profile.latency,
+            bandwidth,
+        }
+    }
+}
+
+/// Throttle applied to the responses of one connection
+#[derive(Debug, Clone)]
+pub struct ConnectionThrottle {
+    latency: Duration,
+    bandwidth: Option<Arc<Bandwidth>>,
+}
+
+/// Apply a connection's throttle to a response, if it has one
+///
+/// Waits out the latency, then returns the response with its body paced to
+/// the download rate. Without a throttle the body passes through untouched.
+pub(crate) async fn throttle_response<B, E>(
+    throttle: Option<&ConnectionThrottle>,
+    response: std::result::Result<Response<B>, E>,
+) -> std::result::Result<Response<ThrottledBody<B>>, E> {
+    let response = response?;
+    let Some(throttle) = throttle else {
+        return Ok(response.map(|body| ThrottledBody::new(body, None)));
+    };
+    if !throttle.latency.is_zero() {
+        tokio::time::sleep(throttle.latency).await;
+    }
+    let bandwidth = throttle.bandwidth.clone();
+    Ok(response.map(|body| ThrottledBody::new(body, bandwidth)))
+}
+
+pin_project! {
+    /// Body written out in chunks no faster than its connection's link allows
+    pub struct ThrottledBody<B> {
+        #[pin]
+        inner: B,
+        bandwidth: Option<Arc<Bandwidth>>,
+        // Data of the current frame not yet queued on the link
+        pending: Bytes,
+        // A queued chunk and the wait until it has gone through the link
+        queued: Option<(Bytes, Pin<Box<Sleep>>)>,
+    }
+}
+
+impl<B> ThrottledBody<B> {
+    fn new(inner: B, bandwidth: Option<Arc<Bandwidth>>) -> Self {
+        Self {
+            inner,
+            bandwidth,
+            pending: Bytes::new(),
+            queued: None,
+        }
+    }
+
+    fn buffered(&self) -> u64 {
+        let queued = self.queued.as_ref().map_or(0, |(chunk, _)| chunk.len());
+        (self.pending.len() + queued) as u64
+    }
+}
+
+impl<B> Body for ThrottledBody<B>
+where
+    B: Body<Data = Bytes>,
+{
+    type Data = Bytes;
+    type Error = B::Error;
+
+    fn poll_frame(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<std::result::Result<Frame<Self::Data>, Self::Error>>> {
+        let mut this = self.project();
+        loop {
+            if let Some((_, wait)) = this.queued.as_mut() {
+                ready!(wait.as_mut().poll(cx));
+                if let Some((chunk, _)) = this.queued.take() {
+                    return Poll::Ready(Some(Ok(Frame::data(chunk))));
+                }
+            }
+
+            let Some(bandwidth) = this.bandwidth.as_ref() else {
+                return this.inner.poll_frame(cx);
+            };
+            if !this.pending.is_empty() {
+                let len = this.pending.len().min(bandwidth.chunk_size());
+                let chunk = this.pending.split_to(len);
+                let done = bandwidth.reserve(len);
+                *this.queued = Some((chunk, Box::pin(tokio::time::sleep_until(done))));
+                continue;
+            }
+
+            match ready!(this.inner.as_mut().poll_frame(cx)) {
+                Some(Ok(frame)) => match frame.into_data() {
+                    Ok(data) => *this.pending = data,
+                    Err(frame) => return Poll::Ready(Some(Ok(frame))),
+                },
+                other => return Poll::Ready(other),
+            }
+        }
+    }
+
+    fn is_end_stream(&self) -> bool {
+        self.buffered() == 0 && self.inner.is_end_stream()
+    }
+
+    fn size_hint(&self) -> SizeHint {
+        let buffered = self.buffered();
+        let inner = self.inner.size_hint();
+        let mut hint = SizeHint::new();
+        hint.set_lower(inner.lower() + buffered);
+        if let Some(upper) = inner.upper() {
+            hint.set_upper(upper + buffered);
+        }
+        hint
+    }
+}
@@ -0,0 +1,45 @@
+//! Choosing the state a multi-variant snapshot is served in
+//!
+//! A snapshot captured with `capture --variant` holds the untagged records of
+//! the default load plus the tagged records of each variant's reload. Serving
+//! replays one state: by default only untagged records are matched, and with
+//! `serve --variant NAME` that variant's records are matched first, falling
+//! back to untagged records for anything the variant's reload didn't request.
+
+use std::collections::{BTreeSet, HashSet};
+
+use crate::storage::Snapshot;
+
+/// Names of the variants a snapshot's records were captured in, sorted
+pub fn snapshot_variants(snapshot: &Snapshot) -> Vec<String> {
+    snapshot
+        .requests
+        .iter()
+        .filter_map(|record| record.variant.clone())
+        .collect::<BTreeSet<_>>()
+        .into_iter()
+        .collect()
+}
+
+/// Keep the records served in `variant`, or the default state with `None`
+///
+/// Records of other variants are dropped, as are untagged records the
+/// variant has its own record of the same method and URL for. The order of
+/// the records that remain is unchanged.
+pub fn select_variant(snapshot: &mut Snapshot, variant: Option<&str>) {
+    let in_variant = |record_variant: &Option<String>| record_variant.as_deref() == variant;
+    let shadowed: HashSet<(String, String)> = match variant {
+        Some(_) => snapshot
+            .requests
+            .iter()
+            .filter(|record| in_variant(&record.variant))
+            .map(|record| (record.method.clone(), record.url.clone()))
+            .collect(),
+        None => HashSet::new(),
+    };
+
+    snapshot.requests.retain(|record| match &record.variant {
+        Some(_) => in_variant(&record.variant),
+        None => !shadowed.contains(&(record.method.clone(), record.url.clone())),
+    });
+}
@@ -0,0 +1,206 @@
+//! Screenshot and DOM files kept next to a snapshot
+//!
+//! Once the page has loaded, a capture takes a full-page screenshot and the
+//! final serialized DOM, so reviewers can see what the page looked like
+//! without replaying it. They are written beside the snapshot as
+//! `<name>.screenshot.png` and `<name>.dom.html` rather than into it, so
+//! loading a snapshot to serve it never reads them. The snapshot's metadata
+//! names the files that were written.
+
+use serde::{Deserialize, Serialize};
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+use tracing::{debug, info, warn};
+
+use super::{validate_snapshot_name, Storage};
+use crate::error::{Result, WebMockError};
+
+/// A kind of file captured alongside the network records
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ArtifactKind {
+    /// Full-page PNG screenshot
+    Screenshot,
+    /// The DOM serialized as HTML after load
+    Dom,
+}
+
+impl ArtifactKind {
+    pub const ALL: [ArtifactKind; 2] = [ArtifactKind::Screenshot, ArtifactKind::Dom];
+
+    /// What follows the snapshot name in the file name
+    pub fn suffix(&self) -> &'static str {
+        match self {
+            ArtifactKind::Screenshot => "screenshot.png",
+            ArtifactKind::Dom => "dom.html",
+        }
+    }
+
+    /// Name of the artifact as shown to users
+    pub fn label(&self) -> &'static str {
+        match self {
+            ArtifactKind::Screenshot => "Screenshot",
+            ArtifactKind::Dom => "DOM",
+        }
+    }
+
+    pub(super) fn file_name(&self, snapshot_name: &str) -> String {
+        format!("{}.{}", snapshot_name, self.suffix())
+    }
+}
+
+/// Artifact files written for a snapshot, by file name in the snapshots directory
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CaptureArtifacts {
+    pub screenshot: Option<String>,
+    pub dom: Option<String>,
+}
+
+impl CaptureArtifacts {
+    /// Check whether no artifact was written
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+
+    /// File name of an artifact, if it was written
+    pub fn get(&self, kind: ArtifactKind) -> Option<&str> {
+        match kind {
+            ArtifactKind::Screenshot => self.screenshot.as_deref(),
+            ArtifactKind::Dom => self.dom.as_deref(),
+        }
+    }
+
+    fn set(&mut self, kind: ArtifactKind, file_name: String) {
+        match kind {
+            ArtifactKind::Screenshot => self.screenshot = Some(file_name),
+            ArtifactKind::Dom => self.dom = Some(file_name),
+        }
+    }
+}
+
+/// Screenshot and DOM taken from the browser, not yet written
+#[derive(Debug, Clone, Default)]
+pub struct PageArtifacts {
+    /// PNG data of the full-page screenshot
+    pub screenshot: Option<Vec<u8>>,
+    /// Serialized DOM of the loaded page
+    pub dom: Option<String>,
+}
+
+impl PageArtifacts {
+    /// Data of the artifact of `kind`, if it was taken
+    pub fn data(&self, kind: ArtifactKind) -> Option<&[u8]> {
+        match kind {
+            ArtifactKind::Screenshot => self.screenshot.as_deref(),
+            ArtifactKind::Dom => self.dom.as_deref().map(str::as_bytes),
+        }
+    }
+}
+
+/// An artifact file present on disk
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ArtifactFile {
+    pub kind: ArtifactKind,
+    pub path: PathBuf,
+    pub size: u64,
+}
+
+impl Storage {
+    /// Get the file path of an artifact of snapshot `name`
+    pub fn get_artifact_path(&self, name: &str, kind: ArtifactKind) -> Result<PathBuf> {
+        validate_snapshot_name(name)?;
+        self.check_path_lengths(name)?;
+        Ok(self.base_path.join("snapshots").join(kind.file_name(name)))
+    }
+
+    /// Write a capture's artifacts next to snapshot `name`
+    ///
+    /// Artifacts only illustrate the capture, so a write that fails is logged
+    /// and left out of the returned references instead of failing it.
+    pub async fn save_artifacts(&self, name: &str, artifacts: &PageArtifacts) -> CaptureArtifacts {
+        let mut written = CaptureArtifacts::default();
+        if let Err(e) = self.ensure_snapshots_dir().await {
+            warn!("Capture artifacts were not saved: {}", e);
+            return written;
+        }
+
+        for kind in ArtifactKind::ALL {
+            let Some(data) = artifacts.data(kind) else {
+                continue;
+            };
+            let path = match self.get_artifact_path(name, kind) {
+                Ok(path) => path,
+                Err(e) => {
+                    warn!("{} was not saved: {}", kind.label(), e);
+                    continue;
+                }
+            };
+            match tokio::fs::write(&path, data).await {
+                Ok(()) => {
+                    debug!(
+                        "Saved {} ({} bytes) to {:?}",
+                        kind.label(),
+                        data.len(),
+                        path
+                    );
+                    written.set(kind, kind.file_name(name));
+                }
+                Err(e) => warn!("{} was not saved to {:?}: {}", kind.label(), path, e),
+            }
+        }
+        written
+    }
+
+    /// Get the artifact files of snapshot `name` that exist
+    pub async fn artifact_files(&self, name: &str) -> Result<Vec<ArtifactFile>> {
+        let mut files = Vec::new();
+        for kind in ArtifactKind::ALL {
+            let path = self.resolve_artifact_path(name, kind)?;
+            match tokio::fs::metadata(&path).await {
+                Ok(metadata) if metadata.is_file() => files.push(ArtifactFile {
+                    kind,
+                    path,
+                    size: metadata.len(),
+                }),
+                Ok(_) => {}
+                Err(e) if e.kind() == ErrorKind::NotFound => {}
+                Err(e) => return Err(WebMockError::io("read metadata of", &path, e)),
+            }
+        }
+        Ok(files)
+    }
+
+    /// Remove the artifact files of snapshot `name`; missing ones are skipped
+    pub async fn delete_artifacts(&self, name: &str) -> Result<()> {
+        for kind in ArtifactKind::ALL {
+            let path = self.get_artifact_path(name, kind)?;
+            match tokio::fs::remove_file(&path).await {
+                Ok(()) => info!("Deleted {} of '{}'", kind.label(), name),
+                Err(e) if e.kind() == ErrorKind::NotFound => {}
+                Err(e) => return Err(WebMockError::io("delete artifact file", &path, e)),
+            }
+        }
+        Ok(())
+    }
+
+    /// Move artifacts written next to a staged snapshot over those of `name`
+    ///
+    /// Artifacts the staged snapshot came without are left alone, so
+    /// rewriting a snapshot's metadata keeps them.
+    pub(crate) async fn move_staged_artifacts(&self, name: &str, staged: &Path) -> Result<()> {
+        for kind in ArtifactKind::ALL {
+            let staged_artifact = staged.with_file_name(kind.file_name(name));
+            if !tokio::fs::try_exists(&staged_artifact)
+                .await
+                .unwrap_or(false)
+            {
+                continue;
+            }
+            let path = self.get_artifact_path(name, kind)?;
+            tokio::fs::rename(&staged_artifact, &path)
+                .await
+                .map_err(|e| WebMockError::io("move artifact into", &path, e))?;
+        }
+        Ok(())
+    }
+}
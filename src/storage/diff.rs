@@ -0,0 +1,71 @@
+//! Summary of how two versions of a snapshot differ
+//!
+//! Records are paired by method and URL; when a URL was requested more than
+//! once, only its first record is compared.
+
+use std::collections::BTreeMap;
+
+use super::types::Snapshot;
+use crate::capture::proxy::RequestRecord;
+
+/// Requests added, removed or answered differently between two snapshots
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SnapshotDiff {
+    /// `METHOD url` of requests only in the new snapshot
+    pub added: Vec<String>,
+    /// `METHOD url` of requests only in the old snapshot
+    pub removed: Vec<String>,
+    /// `METHOD url` of requests whose status or body changed
+    pub changed: Vec<String>,
+    /// Requests answered identically in both
+    pub unchanged: usize,
+}
+
+impl SnapshotDiff {
+    /// Compare `old` against `new`
+    pub fn between(old: &Snapshot, new: &Snapshot) -> Self {
+        let old_records = index(old);
+        let new_records = index(new);
+        let mut diff = Self::default();
+
+        for (key, old_record) in &old_records {
+            match new_records.get(key) {
+                None => diff.removed.push(label(key)),
+                Some(new_record) if differs(old_record, new_record) => {
+                    diff.changed.push(label(key))
+                }
+                Some(_) => diff.unchanged += 1,
+            }
+        }
+        diff.added = new_records
+            .keys()
+            .filter(|key| !old_records.contains_key(*key))
+            .map(label)
+            .collect();
+
+        diff
+    }
+
+    /// Check whether both snapshots answer every request the same way
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.changed.is_empty()
+    }
+}
+
+fn index(snapshot: &Snapshot) -> BTreeMap<(&str, &str), &RequestRecord> {
+    let mut records = BTreeMap::new();
+    for record in &snapshot.requests {
+        records
+            .entry((record.method.as_str(), record.url.as_str()))
+            .or_insert(record);
+    }
+    records
+}
+
+fn label(key: &(&str, &str)) -> String {
+    format!("{} {}", key.0, key.1)
+}
+
+fn differs(old: &RequestRecord, new: &RequestRecord) -> bool {
+    old.response.status != new.response.status || old.response.body != new.response.body
+}
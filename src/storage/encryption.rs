@@ -0,0 +1,264 @@
+//! Snapshot encryption at rest
+//!
+//! An encrypted snapshot file starts with a marker and a small header kept in
+//! the clear: the snapshot's name, URL, creation time and entry points, so
+//! `list` works without the passphrase, and the salt, nonce and iteration
+//! count the key was derived with. The rest is the usual serialized snapshot,
+//! sealed with ChaCha20-Poly1305 under a key derived from the passphrase with
+//! PBKDF2-HMAC-SHA256. The marker and header are authenticated along with the
+//! payload, so neither can be changed without decryption failing.
+//!
+//! The passphrase comes from `WEBMOCK_PASSPHRASE`, or is asked for on the
+//! terminal the first time an encrypted snapshot is read or written.
+
+use std::io::Read;
+use std::num::NonZeroU32;
+use std::sync::{Arc, Mutex};
+
+use chrono::{DateTime, Utc};
+use ring::aead::{Aad, LessSafeKey, Nonce, UnboundKey, CHACHA20_POLY1305, NONCE_LEN};
+use ring::pbkdf2;
+use ring::rand::{SecureRandom, SystemRandom};
+use serde::{Deserialize, Serialize};
+
+use crate::error::{Result, WebMockError};
+
+/// Environment variable holding the passphrase of encrypted snapshots
+pub const PASSPHRASE_ENV: &str = "WEBMOCK_PASSPHRASE";
+
+/// First bytes of an encrypted snapshot file
+const MAGIC: &[u8; 8] = b"WMCRYPT\x01";
+
+/// PBKDF2 rounds for new files, as OWASP recommends for HMAC-SHA256
+#[cfg(not(test))]
+const PBKDF2_ITERATIONS: u32 = 600_000;
+
+/// Files record their rounds, so unit tests can derive keys cheaply
+#[cfg(test)]
+const PBKDF2_ITERATIONS: u32 = 1_000;
+
+/// Most PBKDF2 rounds a file may ask for, so a crafted header can't stall loading
+const MAX_PBKDF2_ITERATIONS: u32 = 10 * PBKDF2_ITERATIONS;
+
+const SALT_LEN: usize = 16;
+
+/// What an encrypted file keeps in the clear
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct EncryptedHeader {
+    pub name: String,
+    pub url: String,
+    pub created_at: DateTime<Utc>,
+    pub entry_points: Vec<String>,
+    iterations: u32,
+    salt: Vec<u8>,
+    nonce: Vec<u8>,
+}
+
+impl EncryptedHeader {
+    /// Header for a snapshot about to be encrypted
+    pub fn new(name: &str, url: &str, created_at: DateTime<Utc>, entry_points: &[String]) -> Self {
+        Self {
+            name: name.to_string(),
+            url: url.to_string(),
+            created_at,
+            entry_points: entry_points.to_vec(),
+            iterations: PBKDF2_ITERATIONS,
+            salt: Vec::new(),
+            nonce: Vec::new(),
+        }
+    }
+}
+
+/// Check whether file data is an encrypted snapshot
+pub fn is_encrypted(data: &[u8]) -> bool {
+    data.starts_with(MAGIC)
+}
+
+/// Seal serialized snapshot data, with `header` kept readable
+pub fn encrypt(plaintext: &[u8], mut header: EncryptedHeader, passphrase: &str) -> Result<Vec<u8>> {
+    let rng = SystemRandom::new();
+    let mut salt = [0u8; SALT_LEN];
+    let mut nonce = [0u8; NONCE_LEN];
+    rng.fill(&mut salt)
+        .and_then(|_| rng.fill(&mut nonce))
+        .map_err(|_| WebMockError::config("No random numbers available for encryption"))?;
+    header.salt = salt.to_vec();
+    header.nonce = nonce.to_vec();
+
+    let mut data = MAGIC.to_vec();
+    let header_bytes = rmp_serde::to_vec(&header)?;
+    data.extend_from_slice(&(header_bytes.len() as u32).to_be_bytes());
+    data.extend_from_slice(&header_bytes);
+
+    let key = derive_key(passphrase, &header)?;
+    let mut sealed = plaintext.to_vec();
+    key.seal_in_place_append_tag(
+        Nonce::assume_unique_for_key(nonce),
+        Aad::from(&data),
+        &mut sealed,
+    )
+    .map_err(|_| WebMockError::config("Failed to encrypt snapshot"))?;
+    data.extend_from_slice(&sealed);
+    Ok(data)
+}
+
+/// Read the header of an encrypted snapshot without decrypting it
+pub fn read_header(data: &[u8]) -> Result<EncryptedHeader> {
+    split(data).map(|(header, _, _)| header)
+}
+
+/// Read the header from the start of an encrypted snapshot file, leaving the payload unread
+pub fn read_header_from(mut reader: impl Read) -> Result<EncryptedHeader> {
+    let mut start = [0u8; MAGIC.len() + 4];
+    read_or_truncated(&mut reader, &mut start)?;
+    let (magic, len) = start.split_at(MAGIC.len());
+    if magic != MAGIC {
+        return Err(WebMockError::invalid_snapshot("not an encrypted snapshot"));
+    }
+    let len = u32::from_be_bytes([len[0], len[1], len[2], len[3]]);
+    let mut header = Vec::new();
+    reader.take(u64::from(len)).read_to_end(&mut header)?;
+    if header.len() < len as usize {
+        return Err(WebMockError::invalid_snapshot(
+            "encrypted snapshot is truncated",
+        ));
+    }
+    Ok(rmp_serde::from_slice(&header)?)
+}
+
+fn read_or_truncated(reader: &mut impl Read, buf: &mut [u8]) -> Result<()> {
+    reader.read_exact(buf).map_err(|e| {
+        if e.kind() == std::io::ErrorKind::UnexpectedEof {
+            WebMockError::invalid_snapshot("encrypted snapshot is truncated")
+        } else {
+            e.into()
+        }
+    })
+}
+
+/// Open an encrypted snapshot, returning the serialized snapshot data
+///
+/// A wrong passphrase and a modified file can't be told apart; both fail
+/// with [`WebMockError::WrongPassphrase`].
+pub fn decrypt(data: &[u8], passphrase: &str) -> Result<Vec<u8>> {
+    let (header, authenticated, sealed) = split(data)?;
+    let nonce = Nonce::try_assume_unique_for_key(&header.nonce)
+        .map_err(|_| WebMockError::invalid_snapshot("encryption header has a bad nonce"))?;
+    let key = derive_key(passphrase, &header)?;
+
+    let mut plaintext = sealed.to_vec();
+    let opened = key
+        .open_in_place(nonce, Aad::from(authenticated), &mut plaintext)
+        .map_err(|_| WebMockError::WrongPassphrase(header.name.clone()))?;
+    let len = opened.len();
+    plaintext.truncate(len);
+    Ok(plaintext)
+}
+
+/// Split an encrypted file into its header, the bytes it authenticates, and the sealed payload
+fn split(data: &[u8]) -> Result<(EncryptedHeader, &[u8], &[u8])> {
+    let truncated = || WebMockError::invalid_snapshot("encrypted snapshot is truncated");
+    let rest = data
+        .strip_prefix(MAGIC)
+        .ok_or_else(|| WebMockError::invalid_snapshot("not an encrypted snapshot"))?;
+    let (len, rest) = rest.split_first_chunk::<4>().ok_or_else(truncated)?;
+    let len = u32::from_be_bytes(*len) as usize;
+    if rest.len() < len {
+        return Err(truncated());
+    }
+    let header: EncryptedHeader = rmp_serde::from_slice(&rest[..len])?;
+    let header_end = MAGIC.len() + 4 + len;
+    Ok((header, &data[..header_end], &data[header_end..]))
+}
+
+fn derive_key(passphrase: &str, header: &EncryptedHeader) -> Result<LessSafeKey> {
+    let iterations = NonZeroU32::new(header.iterations)
+        .ok_or_else(|| WebMockError::invalid_snapshot("encryption header has no iterations"))?;
+    if iterations.get() > MAX_PBKDF2_ITERATIONS {
+        return Err(WebMockError::invalid_snapshot(format!(
+            "encryption header asks for {} key derivation rounds, more than the {} allowed",
+            iterations, MAX_PBKDF2_ITERATIONS
+        )));
+    }
+    let mut key = [0u8; 32];
+    pbkdf2::derive(
+        pbkdf2::PBKDF2_HMAC_SHA256,
+        iterations,
+        &header.salt,
+        passphrase.as_bytes(),
+        &mut key,
+    );
+    let key = UnboundKey::new(&CHACHA20_POLY1305, &key)
+        .map_err(|_| WebMockError::config("Failed to set up snapshot encryption"))?;
+    Ok(LessSafeKey::new(key))
+}
+
+/// Where the passphrase of encrypted snapshots comes from, asked for at most once
+///
+/// Clones share the passphrase, so a snapshot loaded through one storage can
+/// be saved through its staging storage without asking again.
+#[derive(Debug, Clone, Default)]
+pub struct PassphraseSource {
+    passphrase: Arc<Mutex<Option<String>>>,
+}
+
+impl PassphraseSource {
+    /// Use `passphrase` instead of the environment or a prompt
+    pub fn fixed(passphrase: impl Into<String>) -> Self {
+        Self {
+            passphrase: Arc::new(Mutex::new(Some(passphrase.into()))),
+        }
+    }
+
+    /// Get the passphrase for reading or writing the encrypted snapshot `name`
+    pub fn get(&self, name: &str) -> Result<String> {
+        self.resolve(|| prompt(&format!("Passphrase for snapshot '{}'", name), false))
+    }
+
+    /// Get the passphrase to encrypt a snapshot with, asking twice on a terminal
+    pub fn get_new(&self) -> Result<String> {
+        self.resolve(|| prompt("Passphrase to encrypt with", true))
+    }
+
+    /// Drop a passphrase that turned out to be wrong, so the next use asks again
+    pub fn forget(&self) {
+        *self.passphrase.lock().unwrap_or_else(|e| e.into_inner()) = None;
+    }
+
+    fn resolve(&self, ask: impl FnOnce() -> Result<String>) -> Result<String> {
+        let mut cached = self.passphrase.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some(passphrase) = cached.as_ref() {
+            return Ok(passphrase.clone());
+        }
+        let passphrase = match std::env::var(PASSPHRASE_ENV) {
+            Ok(passphrase) if !passphrase.is_empty() => passphrase,
+            _ => ask()?,
+        };
+        *cached = Some(passphrase.clone());
+        Ok(passphrase)
+    }
+}
+
+/// Ask for a passphrase on the terminal without echoing it
+fn prompt(label: &str, confirm: bool) -> Result<String> {
+    let term = console::Term::stderr();
+    if !term.features().is_attended() {
+        return Err(WebMockError::config(format!(
+            "An encrypted snapshot needs a passphrase: set {} or run in a terminal",
+            PASSPHRASE_ENV
+        )));
+    }
+
+    let read = |label: &str| -> Result<String> {
+        term.write_str(&format!("{}: ", label))?;
+        Ok(term.read_secure_line()?)
+    };
+    let passphrase = read(label)?;
+    if passphrase.is_empty() {
+        return Err(WebMockError::config("The passphrase cannot be empty"));
+    }
+    if confirm && read("Repeat the passphrase")? != passphrase {
+        return Err(WebMockError::config("The passphrases don't match"));
+    }
+    Ok(passphrase)
+}
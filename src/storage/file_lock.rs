@@ -0,0 +1,38 @@
+//! Advisory locks on whole files
+//!
+//! PID files, the capture registry and snapshot lock files are all guarded
+//! by an exclusive lock the OS drops when the file is closed, so a process
+//! that dies can't leave one held. The lock is released by closing the file.
+
+use std::fs::File;
+use std::io;
+
+use fs2::FileExt;
+
+/// Why a file could not be locked without waiting
+#[derive(Debug)]
+pub enum TryLockError {
+    /// Another open file holds the lock
+    WouldBlock,
+    /// Locking failed for another reason
+    Error(io::Error),
+}
+
+/// Lock `file` exclusively, waiting until no one else holds it
+pub fn lock(file: &File) -> io::Result<()> {
+    file.lock_exclusive()
+}
+
+/// Lock `file` exclusively if no one else holds it
+pub fn try_lock(file: &File) -> Result<(), TryLockError> {
+    match file.try_lock_exclusive() {
+        Ok(()) => Ok(()),
+        Err(e) if is_contended(&e) => Err(TryLockError::WouldBlock),
+        Err(e) => Err(TryLockError::Error(e)),
+    }
+}
+
+fn is_contended(error: &io::Error) -> bool {
+    error.kind() == io::ErrorKind::WouldBlock
+        || error.raw_os_error() == fs2::lock_contended_error().raw_os_error()
+}
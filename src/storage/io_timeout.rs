@@ -0,0 +1,274 @@
+//! Timeouts and cancellation of snapshot file I/O
+//!
+//! Storage on a network filesystem can stall for minutes. With
+//! `WEBMOCK_IO_TIMEOUT` set, loading and saving give up with
+//! [`WebMockError::Timeout`] naming the operation instead of hanging.
+//!
+//! Storage futures can be dropped at any await point, on a timeout or when
+//! a command is interrupted with Ctrl+C. Snapshots are therefore written to a
+//! temporary file renamed into place once complete, and a [`TempFile`] that
+//! is dropped before then removes what was written, so an interrupted save
+//! leaves the previous snapshot as it was.
+
+use std::future::Future;
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+use tokio::io::{AsyncRead, AsyncReadExt};
+use tracing::{debug, warn};
+
+use crate::error::{Result, WebMockError};
+
+/// Environment variable limiting how long one storage operation may take
+pub const IO_TIMEOUT_ENV: &str = "WEBMOCK_IO_TIMEOUT";
+
+/// Extension of the file a snapshot is written to before it is renamed into place
+pub const TEMP_EXTENSION: &str = "tmp";
+
+/// Numbers the temporary files of this process, so concurrent writes of a file don't share one
+static NEXT_TEMP_FILE: AtomicU64 = AtomicU64::new(0);
+
+/// Path of a temporary file to write before it is renamed to `target`
+///
+/// The file is named after the process and numbered within it, so every
+/// write gets a file of its own.
+pub(crate) fn temp_path(target: &Path) -> PathBuf {
+    let number = NEXT_TEMP_FILE.fetch_add(1, Ordering::Relaxed);
+    target.with_extension(format!(
+        "{}.{}.{}",
+        std::process::id(),
+        number,
+        TEMP_EXTENSION
+    ))
+}
+
+/// Read the I/O timeout from the environment; unset means storage I/O may take any time
+pub fn io_timeout_from_env() -> Option<Duration> {
+    let value = std::env::var(IO_TIMEOUT_ENV).ok()?;
+    let timeout = parse_io_timeout(&value);
+    if timeout.is_none() {
+        warn!(
+            "Ignoring invalid {}='{}', expected e.g. 30, 30s, 500ms or 2m",
+            IO_TIMEOUT_ENV, value
+        );
+    }
+    timeout
+}
+
+/// Parse an I/O timeout: a number of seconds, or a number followed by ms, s or m
+///
+/// Zero is rejected, since no operation could finish in time.
+pub fn parse_io_timeout(value: &str) -> Option<Duration> {
+    let value = value.trim();
+    let unit_start = value
+        .find(|c: char| !c.is_ascii_digit())
+        .unwrap_or(value.len());
+    let (amount, unit) = value.split_at(unit_start);
+    let amount: u64 = amount.parse().ok()?;
+    let timeout = match unit {
+        "ms" => Duration::from_millis(amount),
+        "" | "s" => Duration::from_secs(amount),
+        "m" => Duration::from_secs(amount.checked_mul(60)?),
+        _ => return None,
+    };
+    (!timeout.is_zero()).then_some(timeout)
+}
+
+/// Run storage I/O, failing with [`WebMockError::Timeout`] once it takes longer than `limit`
+///
+/// `operation` names what timed out, e.g. "Loading snapshot 'shop'". The
+/// I/O is dropped on a timeout, so it must be safe to cancel.
+pub async fn with_io_timeout<T>(
+    operation: impl FnOnce() -> String,
+    limit: Option<Duration>,
+    io: impl Future<Output = Result<T>>,
+) -> Result<T> {
+    let Some(limit) = limit else {
+        return io.await;
+    };
+    match tokio::time::timeout(limit, io).await {
+        Ok(result) => result,
+        Err(_) => {
+            let operation = operation();
+            warn!("{} timed out after {:?}", operation, limit);
+            Err(WebMockError::timeout(operation, limit))
+        }
+    }
+}
+
+/// Read all of `reader`, a file at `path`
+pub async fn read_to_end(mut reader: impl AsyncRead + Unpin, path: &Path) -> Result<Vec<u8>> {
+    let mut data = Vec::new();
+    reader
+        .read_to_end(&mut data)
+        .await
+        .map_err(|e| WebMockError::io("read snapshot file", path, e))?;
+    Ok(data)
+}
+
+/// Tells blocking work started by a future that the future was dropped
+///
+/// `spawn_blocking` tasks run to completion even when nobody waits for them
+/// anymore, and the runtime waits for them before the process exits.
+#[derive(Default)]
+pub(crate) struct DropFlag(Arc<AtomicBool>);
+
+impl DropFlag {
+    /// Flag for the blocking work, set once this is dropped
+    pub fn cancelled(&self) -> Cancelled {
+        Cancelled(Arc::clone(&self.0))
+    }
+
+    fn set(&self) {
+        self.0.store(true, Ordering::SeqCst);
+    }
+}
+
+impl Drop for DropFlag {
+    fn drop(&mut self) {
+        self.set();
+    }
+}
+
+/// A file being written that is removed unless it is persisted
+///
+/// Blocking writers may still be running when the guard is dropped, so they
+/// check [`TempFile::cancelled`] and remove the file themselves if the guard
+/// went away while they wrote it.
+pub(crate) struct TempFile {
+    path: PathBuf,
+    flag: DropFlag,
+    persisted: bool,
+}
+
+impl TempFile {
+    /// Guard the temporary file a snapshot at `target` is written to
+    pub fn for_target(target: &Path) -> Self {
+        Self {
+            path: temp_path(target),
+            flag: DropFlag::default(),
+            persisted: false,
+        }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Flag set once the guard is dropped without being persisted
+    pub fn cancelled(&self) -> Cancelled {
+        self.flag.cancelled()
+    }
+
+    /// Move the written file over `target`, atomically replacing it
+    pub async fn persist(mut self, target: &Path) -> Result<()> {
+        tokio::fs::rename(&self.path, target)
+            .await
+            .map_err(|e| WebMockError::io("move snapshot file into", target, e))?;
+        self.persisted = true;
+        Ok(())
+    }
+}
+
+impl Drop for TempFile {
+    fn drop(&mut self) {
+        if self.persisted {
+            return;
+        }
+        // Before removing, so a writer finishing meanwhile removes its file itself
+        self.flag.set();
+        match std::fs::remove_file(&self.path) {
+            Ok(()) => debug!("Removed unfinished {:?}", self.path),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+            Err(e) => warn!("Failed to remove unfinished {:?}: {}", self.path, e),
+        }
+    }
+}
+
+/// Whether the future that started some blocking work was dropped
+#[derive(Clone)]
+pub(crate) struct Cancelled(Arc<AtomicBool>);
+
+impl Cancelled {
+    pub fn is_set(&self) -> bool {
+        self.0.load(Ordering::SeqCst)
+    }
+
+    /// Remove `path` if its guard was dropped while it was written
+    ///
+    /// Called by blocking writers once they are done with the file, so a
+    /// file created after the guard cleaned up doesn't stay behind.
+    pub fn clean_up(&self, path: &Path) {
+        if self.is_set() {
+            let _ = std::fs::remove_file(path);
+        }
+    }
+
+    /// Wrap `writer` to fail instead of writing once the guard was dropped
+    pub fn writer<W: Write>(&self, writer: W) -> CancellableWriter<W> {
+        CancellableWriter {
+            inner: writer,
+            cancelled: self.clone(),
+        }
+    }
+
+    /// Wrap `reader` to fail instead of reading once the load was given up on
+    pub fn reader<R: Read>(&self, reader: R) -> CancellableReader<R> {
+        CancellableReader {
+            inner: reader,
+            cancelled: self.clone(),
+        }
+    }
+
+    fn check(&self, operation: &str) -> std::io::Result<()> {
+        if self.is_set() {
+            // Not `Interrupted`, which `write_all` and `read_to_end` retry
+            return Err(std::io::Error::other(format!(
+                "{} was cancelled",
+                operation
+            )));
+        }
+        Ok(())
+    }
+}
+
+/// Writer that stops a cancelled save instead of writing the rest of it
+pub(crate) struct CancellableWriter<W> {
+    inner: W,
+    cancelled: Cancelled,
+}
+
+impl<W: Write> Write for CancellableWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.cancelled.check("save")?;
+        self.inner.write(buf)
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        self.cancelled.check("save")?;
+        self.inner.flush()
+    }
+}
+
+/// Reader that stops a load given up on instead of reading the rest of the file
+pub(crate) struct CancellableReader<R> {
+    inner: R,
+    cancelled: Cancelled,
+}
+
+impl<R: Read> Read for CancellableReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        self.cancelled.check("load")?;
+        self.inner.read(buf)
+    }
+}
+
+impl<R: Seek> Seek for CancellableReader<R> {
+    fn seek(&mut self, position: SeekFrom) -> std::io::Result<u64> {
+        self.cancelled.check("load")?;
+        self.inner.seek(position)
+    }
+}
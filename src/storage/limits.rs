@@ -0,0 +1,224 @@
+//! Limits applied while loading snapshots
+//!
+//! A corrupted or hostile snapshot can claim far more data than it holds, or
+//! decompress to far more than its file size. Loading checks what it decodes
+//! against these limits and fails with [`WebMockError::InvalidSnapshot`]
+//! naming the limit, instead of exhausting memory first. The defaults are
+//! well above any real capture; each can be changed through an environment
+//! variable.
+
+use std::io::{self, Read};
+use std::str::FromStr;
+
+use tracing::warn;
+
+use crate::capture::proxy::RequestRecord;
+use crate::error::{Result, WebMockError};
+
+/// Largest decoded (decompressed) snapshot loaded by default (4GB)
+pub const DEFAULT_MAX_DECODED_SIZE: u64 = 4 * 1024 * 1024 * 1024;
+
+/// Largest single request or response body loaded by default (1GB)
+pub const DEFAULT_MAX_BODY_SIZE: usize = 1024 * 1024 * 1024;
+
+/// Most records loaded from one snapshot by default
+pub const DEFAULT_MAX_RECORDS: usize = 1_000_000;
+
+/// Most request or response headers loaded for one record by default
+pub const DEFAULT_MAX_HEADERS: usize = 10_000;
+
+/// Deepest nesting of msgpack arrays and maps decoded
+///
+/// Snapshots nest a handful of levels; the limit only stops crafted input
+/// from recursing through the decoder.
+pub const MAX_NESTING_DEPTH: usize = 32;
+
+/// Environment variable overriding the decoded snapshot size limit, in bytes
+pub const MAX_DECODED_SIZE_ENV: &str = "WEBMOCK_MAX_SNAPSHOT_SIZE";
+
+/// Environment variable overriding the body size limit, in bytes
+pub const MAX_BODY_SIZE_ENV: &str = "WEBMOCK_MAX_BODY_SIZE";
+
+/// Environment variable overriding the record count limit
+pub const MAX_RECORDS_ENV: &str = "WEBMOCK_MAX_RECORDS";
+
+/// Environment variable overriding the per-record header count limit
+pub const MAX_HEADERS_ENV: &str = "WEBMOCK_MAX_HEADERS";
+
+/// Limits checked while decoding a snapshot
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DecodeLimits {
+    /// Bytes of msgpack, after decompression
+    pub max_decoded_size: u64,
+    /// Bytes of a single request or response body
+    pub max_body_size: usize,
+    /// Records in the snapshot
+    pub max_records: usize,
+    /// Request or response headers of a single record
+    pub max_headers: usize,
+}
+
+impl Default for DecodeLimits {
+    fn default() -> Self {
+        Self {
+            max_decoded_size: DEFAULT_MAX_DECODED_SIZE,
+            max_body_size: DEFAULT_MAX_BODY_SIZE,
+            max_records: DEFAULT_MAX_RECORDS,
+            max_headers: DEFAULT_MAX_HEADERS,
+        }
+    }
+}
+
+impl DecodeLimits {
+    /// Read the limits from the environment, falling back to the defaults
+    pub fn from_env() -> Self {
+        Self {
+            max_decoded_size: limit_from_env(MAX_DECODED_SIZE_ENV, DEFAULT_MAX_DECODED_SIZE),
+            max_body_size: limit_from_env(MAX_BODY_SIZE_ENV, DEFAULT_MAX_BODY_SIZE),
+            max_records: limit_from_env(MAX_RECORDS_ENV, DEFAULT_MAX_RECORDS),
+            max_headers: limit_from_env(MAX_HEADERS_ENV, DEFAULT_MAX_HEADERS),
+        }
+    }
+
+    /// Check the size of a snapshot's msgpack data
+    pub fn check_decoded_size(&self, size: u64) -> Result<()> {
+        if size > self.max_decoded_size {
+            return Err(decoded_size_exceeded(self.max_decoded_size));
+        }
+        Ok(())
+    }
+
+    /// Check the record count, header counts and body sizes of decoded records
+    pub fn check_records(&self, records: &[RequestRecord]) -> Result<()> {
+        if records.len() > self.max_records {
+            return Err(limit_exceeded(
+                "record count",
+                records.len(),
+                self.max_records,
+                MAX_RECORDS_ENV,
+            ));
+        }
+        for record in records {
+            let headers = record.headers.len().max(record.response.headers.len());
+            if headers > self.max_headers {
+                return Err(limit_exceeded(
+                    &format!("header count of {} {}", record.method, record.url),
+                    headers,
+                    self.max_headers,
+                    MAX_HEADERS_ENV,
+                ));
+            }
+            let body = record
+                .body
+                .as_ref()
+                .map_or(0, Vec::len)
+                .max(record.response.body.len());
+            if body > self.max_body_size {
+                return Err(limit_exceeded(
+                    &format!("body size of {} {}", record.method, record.url),
+                    body,
+                    self.max_body_size,
+                    MAX_BODY_SIZE_ENV,
+                ));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Read a limit from the environment; invalid values and zero are ignored
+fn limit_from_env<T: FromStr + Default + PartialEq + Copy>(name: &str, default: T) -> T {
+    match std::env::var(name) {
+        Ok(value) => match value.trim().parse::<T>() {
+            Ok(limit) if limit != T::default() => limit,
+            _ => {
+                warn!(
+                    "Ignoring invalid {}='{}', expected a positive number",
+                    name, value
+                );
+                default
+            }
+        },
+        Err(_) => default,
+    }
+}
+
+fn limit_exceeded(what: &str, found: usize, limit: usize, env: &str) -> WebMockError {
+    WebMockError::invalid_snapshot(format!(
+        "{} limit exceeded: {} is more than {} (raise it with {})",
+        what, found, limit, env
+    ))
+}
+
+fn decoded_size_exceeded(limit: u64) -> WebMockError {
+    WebMockError::invalid_snapshot(format!(
+        "decoded size limit exceeded: more than {} bytes (raise it with {})",
+        limit, MAX_DECODED_SIZE_ENV
+    ))
+}
+
+/// Map a decode error, naming the limit when the decoder stopped at one
+pub(crate) fn decode_error(error: rmp_serde::decode::Error) -> WebMockError {
+    match error {
+        rmp_serde::decode::Error::DepthLimitExceeded => WebMockError::invalid_snapshot(format!(
+            "nesting depth limit exceeded: more than {} levels",
+            MAX_NESTING_DEPTH
+        )),
+        error => error.into(),
+    }
+}
+
+/// Reader failing once more than a limit of bytes has been read
+///
+/// Wraps the decompressed stream, so a small compressed file can't inflate
+/// past the decoded size limit.
+pub(crate) struct LimitedReader<R> {
+    inner: R,
+    limit: u64,
+    read: u64,
+    exceeded: bool,
+}
+
+impl<R: Read> LimitedReader<R> {
+    pub(crate) fn new(inner: R, limit: u64) -> Self {
+        Self {
+            inner,
+            limit,
+            read: 0,
+            exceeded: false,
+        }
+    }
+
+    /// Check whether reading stopped at the limit
+    pub(crate) fn exceeded(&self) -> bool {
+        self.exceeded
+    }
+
+    /// The error to report once reading stopped at the limit
+    pub(crate) fn error(&self) -> WebMockError {
+        decoded_size_exceeded(self.limit)
+    }
+}
+
+impl<R: Read> Read for LimitedReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+        let remaining = self.limit - self.read;
+        if remaining == 0 {
+            // At the limit, the stream may only end
+            if self.inner.read(&mut [0u8])? == 0 {
+                return Ok(0);
+            }
+            self.exceeded = true;
+            return Err(io::Error::other("decoded size limit exceeded"));
+        }
+        let len = buf
+            .len()
+            .min(usize::try_from(remaining).unwrap_or(usize::MAX));
+        let read = self.inner.read(&mut buf[..len])?;
+        self.read += read as u64;
+        Ok(read)
+    }
+}
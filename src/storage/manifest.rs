@@ -0,0 +1,265 @@
+//! Manifest describing a snapshot file for cache keys
+//!
+//! CI jobs that cache snapshots as build artifacts need to know what a
+//! snapshot holds without reading the whole file. Every save writes
+//! `<name>.manifest.json` next to the snapshot with the format version,
+//! entry URLs, creation time, record count, file size and the SHA-256 of the
+//! serialized file. The manifest is written to a temporary file and renamed
+//! into place, so a reader never sees half of it, and `verify --manifest`
+//! checks the hash against the snapshot file.
+
+use std::io::{ErrorKind, Read};
+use std::path::{Path, PathBuf};
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use tracing::{debug, info};
+
+use super::types::{entry_points, SnapshotMetadata};
+use super::{validate_snapshot_name, Snapshot, Storage};
+use crate::error::{Result, WebMockError};
+
+/// What follows the snapshot name in the manifest's file name
+pub const MANIFEST_SUFFIX: &str = "manifest.json";
+
+/// What a snapshot file contains, as written to `<name>.manifest.json`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SnapshotManifest {
+    /// Version of webmock that wrote the snapshot file
+    pub format_version: String,
+    pub name: String,
+    /// Every entry point, the capture URL first
+    pub urls: Vec<String>,
+    pub created_at: DateTime<Utc>,
+    pub record_count: usize,
+    /// Size of the snapshot file
+    pub total_bytes: u64,
+    /// Hex SHA-256 of the snapshot file
+    pub sha256: String,
+    pub encrypted: bool,
+}
+
+impl SnapshotManifest {
+    /// Describe `snapshot` as serialized to the file at `path`
+    pub fn for_file(snapshot: &Snapshot, path: &Path) -> Result<Self> {
+        Self::describe(snapshot).with_file(path)
+    }
+
+    /// Describe `snapshot` before its file is written, without hash or size yet
+    pub(super) fn describe(snapshot: &Snapshot) -> Self {
+        Self {
+            format_version: env!("CARGO_PKG_VERSION").to_string(),
+            name: snapshot.name.clone(),
+            urls: snapshot.entry_points(),
+            created_at: snapshot.created_at,
+            record_count: snapshot.requests.len(),
+            total_bytes: 0,
+            sha256: String::new(),
+            encrypted: snapshot.encrypted,
+        }
+    }
+
+    /// Describe a snapshot written record by record, from its metadata
+    pub(super) fn from_metadata(
+        metadata: &SnapshotMetadata,
+        record_count: usize,
+        encrypted: bool,
+    ) -> Self {
+        Self {
+            format_version: metadata.version.clone(),
+            name: metadata.name.clone(),
+            urls: entry_points(&metadata.url, &metadata.entry_points),
+            created_at: metadata.created_at,
+            record_count,
+            total_bytes: 0,
+            sha256: String::new(),
+            encrypted,
+        }
+    }
+
+    /// Fill in the hash and size of the snapshot file at `path`
+    fn with_file(mut self, path: &Path) -> Result<Self> {
+        let (sha256, total_bytes) = file_sha256(path)?;
+        self.sha256 = sha256;
+        self.total_bytes = total_bytes;
+        Ok(self)
+    }
+}
+
+/// Outcome of checking a snapshot file against its manifest
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ManifestCheck {
+    /// No manifest was written for the snapshot
+    Missing,
+    /// The file is the one the manifest describes
+    Matches(SnapshotManifest),
+    /// The file changed since the manifest was written
+    Mismatch {
+        manifest: SnapshotManifest,
+        sha256: String,
+        total_bytes: u64,
+    },
+}
+
+/// Hex SHA-256 and size of a file, read in chunks
+pub fn file_sha256(path: &Path) -> Result<(String, u64)> {
+    let mut file =
+        std::fs::File::open(path).map_err(|e| WebMockError::io("open snapshot file", path, e))?;
+    let mut context = ring::digest::Context::new(&ring::digest::SHA256);
+    let mut buffer = vec![0; 64 * 1024];
+    let mut size = 0u64;
+    loop {
+        let read = file
+            .read(&mut buffer)
+            .map_err(|e| WebMockError::io("read snapshot file", path, e))?;
+        if read == 0 {
+            break;
+        }
+        context.update(&buffer[..read]);
+        size += read as u64;
+    }
+    let sha256 = context
+        .finish()
+        .as_ref()
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect();
+    Ok((sha256, size))
+}
+
+fn manifest_file_name(name: &str) -> String {
+    format!("{}.{}", name, MANIFEST_SUFFIX)
+}
+
+impl Storage {
+    /// Get the path the manifest of snapshot `name` is written to
+    pub fn get_manifest_path(&self, name: &str) -> Result<PathBuf> {
+        validate_snapshot_name(name)?;
+        self.check_path_lengths(name)?;
+        Ok(self
+            .base_path
+            .join("snapshots")
+            .join(manifest_file_name(name)))
+    }
+
+    /// Write `manifest`, described before its snapshot was saved to `path`
+    pub(super) async fn write_manifest(
+        &self,
+        manifest: SnapshotManifest,
+        path: &Path,
+    ) -> Result<()> {
+        let manifest_path = self.get_manifest_path(&manifest.name)?;
+        let path = path.to_path_buf();
+        let manifest = tokio::task::spawn_blocking(move || manifest.with_file(&path)).await??;
+
+        let json = serde_json::to_string_pretty(&manifest).map_err(|e| {
+            WebMockError::config(format!("Failed to encode manifest as JSON: {}", e))
+        })?;
+        let temp = super::io_timeout::temp_path(&manifest_path);
+        tokio::fs::write(&temp, json + "\n")
+            .await
+            .map_err(|e| WebMockError::io("write manifest", &temp, e))?;
+        if let Err(e) = tokio::fs::rename(&temp, &manifest_path).await {
+            let _ = tokio::fs::remove_file(&temp).await;
+            return Err(WebMockError::io("write manifest", &manifest_path, e));
+        }
+        debug!(
+            "Wrote manifest of '{}' ({})",
+            manifest.name, manifest.sha256
+        );
+        Ok(())
+    }
+
+    /// Move the manifest written next to a staged snapshot over that of `name`
+    ///
+    /// A staged snapshot written without one leaves no manifest, since the
+    /// old one would describe the replaced file.
+    pub(super) async fn move_staged_manifest(&self, name: &str, staged: &Path) -> Result<()> {
+        let staged_manifest = staged.with_file_name(manifest_file_name(name));
+        let path = self.get_manifest_path(name)?;
+        if tokio::fs::try_exists(&staged_manifest)
+            .await
+            .unwrap_or(false)
+        {
+            tokio::fs::rename(&staged_manifest, &path)
+                .await
+                .map_err(|e| WebMockError::io("move manifest into", &path, e))?;
+            return Ok(());
+        }
+        self.delete_manifest(name).await
+    }
+
+    /// Remove the manifest of `name`, if there is one
+    pub(super) async fn delete_manifest(&self, name: &str) -> Result<()> {
+        let path = self.get_manifest_path(name)?;
+        match tokio::fs::remove_file(&path).await {
+            Ok(()) => {
+                info!("Deleted manifest of '{}'", name);
+                Ok(())
+            }
+            Err(e) if e.kind() == ErrorKind::NotFound => Ok(()),
+            Err(e) => Err(WebMockError::io("delete manifest", &path, e)),
+        }
+    }
+
+    /// Load the manifest of snapshot `name`, or `None` if none was written
+    ///
+    /// A snapshot read from the read-only layer has its manifest there too.
+    pub async fn load_manifest(&self, name: &str) -> Result<Option<SnapshotManifest>> {
+        let path = self
+            .resolve_snapshot_path(name)?
+            .with_file_name(manifest_file_name(name));
+        let json = match tokio::fs::read_to_string(&path).await {
+            Ok(json) => json,
+            Err(e) if e.kind() == ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(WebMockError::io("read manifest", &path, e)),
+        };
+        serde_json::from_str(&json).map(Some).map_err(|e| {
+            WebMockError::config(format!("Manifest {} is not valid: {}", path.display(), e))
+        })
+    }
+
+    /// Check the snapshot file of `name` against the hash in its manifest
+    pub async fn check_manifest(&self, name: &str) -> Result<ManifestCheck> {
+        let path = self.resolve_snapshot_path(name)?;
+        if !tokio::fs::try_exists(&path).await.unwrap_or(false) {
+            return Err(WebMockError::SnapshotNotFound(name.to_string()));
+        }
+        let Some(manifest) = self.load_manifest(name).await? else {
+            return Ok(ManifestCheck::Missing);
+        };
+
+        let (sha256, total_bytes) =
+            tokio::task::spawn_blocking(move || file_sha256(&path)).await??;
+        if sha256 == manifest.sha256 && total_bytes == manifest.total_bytes {
+            Ok(ManifestCheck::Matches(manifest))
+        } else {
+            Ok(ManifestCheck::Mismatch {
+                manifest,
+                sha256,
+                total_bytes,
+            })
+        }
+    }
+
+    /// Get the manifest of `name`, writing it first if the snapshot was saved without one
+    pub async fn manifest(&self, name: &str) -> Result<SnapshotManifest> {
+        if let Some(manifest) = self.load_manifest(name).await? {
+            return Ok(manifest);
+        }
+        let path = self.resolve_snapshot_path(name)?;
+        let snapshot = self.load_snapshot(name).await?;
+        if path != self.get_snapshot_path(name)? {
+            // Read-only snapshots are described without writing anything
+            return tokio::task::spawn_blocking(move || {
+                SnapshotManifest::for_file(&snapshot, &path)
+            })
+            .await?;
+        }
+        self.write_manifest(SnapshotManifest::describe(&snapshot), &path)
+            .await?;
+        self.load_manifest(name)
+            .await?
+            .ok_or_else(|| WebMockError::config(format!("Manifest of '{}' was not written", name)))
+    }
+}
@@ -1,141 +1,443 @@
+pub mod artifacts;
+pub mod diff;
+pub mod encryption;
+pub mod file_lock;
+pub mod io_timeout;
+pub mod limits;
+pub mod manifest;
+pub mod overlay;
+pub mod paths;
+pub mod records;
+pub mod segments;
 pub mod serialization;
+pub mod split;
 pub mod types;
 
 #[cfg(test)]
 mod tests;
 
-pub use serialization::SnapshotSerializer;
-pub use types::{Snapshot, SnapshotData, SnapshotInfo, SnapshotMetadata};
-
-use std::fs;
-use std::path::PathBuf;
-use tracing::{debug, info};
-
-use crate::error::Result;
+pub use artifacts::{ArtifactFile, ArtifactKind, CaptureArtifacts, PageArtifacts};
+pub use diff::SnapshotDiff;
+pub use encryption::PassphraseSource;
+pub use io_timeout::{parse_io_timeout, IO_TIMEOUT_ENV};
+pub use limits::DecodeLimits;
+pub use manifest::{ManifestCheck, SnapshotManifest};
+pub use overlay::set_read_only_layer;
+pub use paths::PathPlatform;
+pub use records::SnapshotRecords;
+pub use segments::{AppendedSegments, SnapshotLock};
+pub use serialization::{RecordReader, RecordWriter, SnapshotSerializer};
+pub use split::SplitSummary;
+pub use types::{
+    ServeDefaults, Snapshot, SnapshotData, SnapshotFileStat, SnapshotInfo, SnapshotMetadata,
+    SplitSource, TrimmedRecord,
+};
+
+use std::collections::HashSet;
+use std::ffi::OsString;
+use std::io::{BufRead, BufReader, ErrorKind, Read, Write};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use tracing::{debug, info, warn};
+
+use crate::error::{Result, WebMockError};
+use io_timeout::{with_io_timeout, TempFile};
+
+/// Snapshots larger than this are serialized and loaded with streaming (50MB)
+pub const DEFAULT_STREAMING_THRESHOLD: usize = 50 * 1024 * 1024;
+
+/// Environment variable overriding the streaming threshold, in bytes
+pub const STREAMING_THRESHOLD_ENV: &str = "WEBMOCK_STREAMING_THRESHOLD";
+
+/// Longest snapshot name accepted, in bytes
+pub const MAX_SNAPSHOT_NAME_LEN: usize = 100;
+
+/// Environment variable naming the XDG data directory, used when `HOME` is unset
+pub const XDG_DATA_HOME_ENV: &str = "XDG_DATA_HOME";
+
+/// Suffix of the backup kept when a snapshot is replaced, as in `<name>.prev.msgpack`
+pub const BACKUP_SUFFIX: &str = "prev";
 
 pub struct Storage {
     base_path: PathBuf,
+    /// Storage directory snapshots missing from `base_path` are read from
+    read_only: Option<PathBuf>,
+    streaming_threshold: usize,
+    passphrase: PassphraseSource,
+    /// Longest a load or save may take; `None` waits as long as the filesystem does
+    io_timeout: Option<Duration>,
 }
 
 impl Storage {
+    /// Create storage using the streaming threshold from `WEBMOCK_STREAMING_THRESHOLD`
+    /// and the I/O timeout from `WEBMOCK_IO_TIMEOUT`
+    ///
+    /// The read-only layer given with `--storage-ro` is used if one was set.
     pub fn new(base_path: PathBuf) -> Self {
-        info!("Creating storage with base path: {:?}", base_path);
-        Self { base_path }
+        let storage = Self::with_streaming_threshold(base_path, streaming_threshold_from_env())
+            .with_io_timeout(io_timeout::io_timeout_from_env());
+        match overlay::configured_read_only_layer() {
+            Some(read_only) => storage.with_read_only_layer(read_only),
+            None => storage,
+        }
+    }
+
+    /// Create storage that streams snapshots larger than `streaming_threshold` bytes
+    pub fn with_streaming_threshold(base_path: PathBuf, streaming_threshold: usize) -> Self {
+        info!(
+            "Creating storage with base path: {:?} (streaming above {} bytes)",
+            base_path, streaming_threshold
+        );
+        Self {
+            base_path: paths::extended_length(&base_path),
+            read_only: None,
+            streaming_threshold,
+            passphrase: PassphraseSource::default(),
+            io_timeout: None,
+        }
+    }
+
+    /// Fail loads and saves that take longer than `io_timeout` with [`WebMockError::Timeout`]
+    pub fn with_io_timeout(mut self, io_timeout: Option<Duration>) -> Self {
+        self.io_timeout = io_timeout;
+        self
+    }
+
+    /// Get the longest a load or save may take
+    pub fn io_timeout(&self) -> Option<Duration> {
+        self.io_timeout
+    }
+
+    /// Read and write encrypted snapshots with the passphrase from `source`
+    pub fn with_passphrase(mut self, source: PassphraseSource) -> Self {
+        self.passphrase = source;
+        self
+    }
+
+    /// Get where the passphrase of encrypted snapshots comes from
+    pub fn passphrase(&self) -> &PassphraseSource {
+        &self.passphrase
+    }
+
+    /// Get the size in bytes above which snapshots are streamed
+    pub fn streaming_threshold(&self) -> usize {
+        self.streaming_threshold
+    }
+
+    /// Get the directory snapshots are written to
+    pub fn base_path(&self) -> &Path {
+        &self.base_path
     }
 
     /// Ensure the snapshots directory exists
-    pub fn ensure_snapshots_dir(&self) -> Result<PathBuf> {
+    pub async fn ensure_snapshots_dir(&self) -> Result<PathBuf> {
         let snapshots_dir = self.base_path.join("snapshots");
-        if !snapshots_dir.exists() {
-            debug!("Creating snapshots directory: {:?}", snapshots_dir);
-            fs::create_dir_all(&snapshots_dir)?;
-        }
+        debug!("Ensuring snapshots directory: {:?}", snapshots_dir);
+        tokio::fs::create_dir_all(&snapshots_dir)
+            .await
+            .map_err(|e| WebMockError::io("create snapshots directory", &snapshots_dir, e))?;
         Ok(snapshots_dir)
     }
 
     /// Get the file path for a snapshot
-    pub fn get_snapshot_path(&self, name: &str) -> PathBuf {
-        self.base_path
-            .join("snapshots")
-            .join(format!("{}.msgpack", name))
+    ///
+    /// Fails for names that are not valid snapshot names, for names whose
+    /// files would exceed the platform's path limits, and for snapshot files
+    /// that resolve outside the snapshots directory (e.g. through a symlink).
+    pub fn get_snapshot_path(&self, name: &str) -> Result<PathBuf> {
+        validate_snapshot_name(name)?;
+        self.check_path_lengths(name)?;
+
+        let snapshots_dir = self.base_path.join("snapshots");
+        let snapshot_path = snapshots_dir.join(format!("{}.msgpack", name));
+
+        // Missing files can't point anywhere else, so only existing ones are resolved
+        if let (Ok(dir), Ok(resolved)) =
+            (snapshots_dir.canonicalize(), snapshot_path.canonicalize())
+        {
+            if resolved.parent() != Some(dir.as_path()) {
+                warn!(
+                    "Snapshot '{}' resolves outside the snapshots directory: {:?}",
+                    name, resolved
+                );
+                return Err(WebMockError::config(format!(
+                    "Snapshot '{}' resolves outside the snapshots directory",
+                    name
+                )));
+            }
+        }
+
+        Ok(snapshot_path)
     }
 
-    /// Check if a snapshot exists
-    pub fn snapshot_exists(&self, name: &str) -> bool {
-        self.get_snapshot_path(name).exists()
+    /// Get the file path of the backup kept when a snapshot is replaced
+    pub fn get_backup_path(&self, name: &str) -> Result<PathBuf> {
+        validate_snapshot_name(name)?;
+        self.check_path_lengths(name)?;
+        Ok(self
+            .base_path
+            .join("snapshots")
+            .join(format!("{}.{}.msgpack", name, BACKUP_SUFFIX)))
     }
 
-    /// Load only the metadata of a snapshot (for listing purposes)
-    async fn load_snapshot_metadata(&self, name: &str) -> Result<SnapshotInfo> {
-        let snapshot_path = self.get_snapshot_path(name);
+    /// Check that every file kept for snapshot `name` has a usable path on this platform
+    fn check_path_lengths(&self, name: &str) -> Result<()> {
+        let snapshots_dir = self.base_path.join("snapshots");
+        for file_name in snapshot_file_names(name) {
+            paths::check_path(&snapshots_dir.join(file_name))?;
+        }
+        Ok(())
+    }
 
-        // Read file contents
-        let file_data = tokio::fs::read(&snapshot_path).await?;
+    /// Storage for snapshots that are being captured but aren't ready to replace their originals
+    ///
+    /// It lives under the same base directory, so staged files can be renamed
+    /// into place.
+    pub fn staging(&self) -> Self {
+        Self {
+            base_path: self.base_path.join("staging"),
+            read_only: None,
+            streaming_threshold: self.streaming_threshold,
+            passphrase: self.passphrase.clone(),
+            io_timeout: self.io_timeout,
+        }
+    }
 
-        // Deserialize only to get metadata
-        let metadata = SnapshotSerializer::deserialize_metadata(&file_data)?;
+    /// Check if a snapshot exists, in either layer
+    pub fn snapshot_exists(&self, name: &str) -> Result<bool> {
+        Ok(self.resolve_snapshot_path(name)?.exists())
+    }
 
-        Ok(SnapshotInfo {
-            name: metadata.name,
-            url: metadata.url,
-            created_at: metadata.created_at,
-        })
+    /// Load only the metadata of a snapshot (for listing purposes)
+    ///
+    /// Only the start of the file is read: the readable header of an
+    /// encrypted snapshot, or the metadata that precedes the records.
+    pub async fn load_snapshot_metadata(&self, name: &str) -> Result<SnapshotInfo> {
+        let snapshot_path = self.resolve_snapshot_path(name)?;
+
+        // Blocking reads, off the async runtime
+        let flag = io_timeout::DropFlag::default();
+        let cancelled = flag.cancelled();
+        let read = tokio::task::spawn_blocking(move || {
+            let file = std::fs::File::open(&snapshot_path)
+                .map_err(|e| WebMockError::io("read snapshot file", &snapshot_path, e))?;
+            read_snapshot_info(cancelled.reader(file))
+                .map_err(|e| io_context(e, "read snapshot file", &snapshot_path))
+        });
+        with_io_timeout(
+            || format!("Reading snapshot '{}'", name),
+            self.io_timeout,
+            async { read.await? },
+        )
+        .await
     }
 
     pub async fn save_snapshot(&self, snapshot: Snapshot) -> Result<()> {
         info!("Saving snapshot: {}", snapshot.name);
 
-        // Ensure snapshots directory exists
-        self.ensure_snapshots_dir()?;
-
-        // Get snapshot file path
-        let snapshot_path = self.get_snapshot_path(&snapshot.name);
-
-        // Estimate snapshot size to decide on serialization method
-        let estimated_size = self.estimate_snapshot_size(&snapshot);
-
-        if estimated_size > 50 * 1024 * 1024 {
-            // 50MB threshold for streaming
-            info!(
-                "Large snapshot detected ({}MB), using streaming serialization",
-                estimated_size / 1024 / 1024
-            );
-
-            // Use streaming serialization for large snapshots
-            let file = tokio::fs::File::create(&snapshot_path).await?;
-            let writer = file.into_std().await;
-            SnapshotSerializer::serialize_streaming(&snapshot, writer)?;
+        // Get snapshot file path, rejecting invalid names before touching the disk
+        validate_snapshot_name(&snapshot.name)?;
+        self.ensure_snapshots_dir().await?;
+        let snapshot_path = self.get_snapshot_path(&snapshot.name)?;
+        let name = snapshot.name.clone();
+        let manifest = SnapshotManifest::describe(&snapshot);
+
+        // Written next to the snapshot and renamed over it, so a save that
+        // times out or is interrupted leaves the previous snapshot as it was
+        let temp = TempFile::for_target(&snapshot_path);
+        let operation = || format!("Saving snapshot '{}'", name);
+        if snapshot.encrypted {
+            // Asked for before the timeout starts, as it may prompt
+            let passphrase = self.passphrase.get(&snapshot.name)?;
+            with_io_timeout(
+                operation,
+                self.io_timeout,
+                Self::write_encrypted(snapshot, passphrase, &temp),
+            )
+            .await?;
         } else {
-            // Use regular serialization for smaller snapshots
-            let serialized_data = SnapshotSerializer::serialize(&snapshot)?;
-            tokio::fs::write(&snapshot_path, serialized_data).await?;
+            with_io_timeout(
+                operation,
+                self.io_timeout,
+                self.write_serialized(snapshot, &temp),
+            )
+            .await?;
         }
+        temp.persist(&snapshot_path).await?;
+        self.write_manifest(manifest, &snapshot_path).await?;
+        self.clear_tombstone(&name).await?;
 
         info!(
             "Successfully saved snapshot '{}' to {:?}",
-            snapshot.name, snapshot_path
+            name, snapshot_path
         );
         Ok(())
     }
 
+    /// Serialize a snapshot into the temporary file of its save
+    async fn write_serialized(&self, snapshot: Snapshot, temp: &TempFile) -> Result<()> {
+        // Estimate snapshot size to decide on serialization method
+        let estimated_size = SnapshotSerializer::estimate_snapshot_size(&snapshot);
+        let streaming_threshold = self.streaming_threshold;
+
+        // Serialization is CPU bound and the streaming paths write with
+        // blocking I/O, so it runs off the async runtime
+        let path = temp.path().to_path_buf();
+        let cancelled = temp.cancelled();
+        tokio::task::spawn_blocking(move || -> Result<()> {
+            let written = if estimated_size > streaming_threshold {
+                info!(
+                    "Large snapshot detected ({}MB), using streaming serialization",
+                    estimated_size / 1024 / 1024
+                );
+
+                // Use streaming serialization for large snapshots
+                let file = std::fs::File::create(&path)
+                    .map_err(|e| WebMockError::io("create snapshot file", &path, e))?;
+                let mut writer = cancelled.writer(std::io::BufWriter::new(file));
+                SnapshotSerializer::serialize_streaming(&snapshot, &mut writer)
+                    .and_then(|_| Ok(writer.flush()?))
+                    .map_err(|e| io_context(e, "write snapshot file", &path))
+            } else {
+                // Serialize in memory, but spill to disk if the estimate was too low
+                SnapshotSerializer::serialize_bounded(&snapshot, streaming_threshold, &path)
+                    .map_err(|e| io_context(e, "write snapshot file", &path))
+                    .and_then(|serialized| match serialized {
+                        Some(serialized_data) => std::fs::write(&path, serialized_data)
+                            .map_err(|e| WebMockError::io("write snapshot file", &path, e)),
+                        None => {
+                            info!(
+                                "Snapshot outgrew its {}MB estimate, fell back to streaming serialization",
+                                estimated_size / 1024 / 1024
+                            );
+                            Ok(())
+                        }
+                    })
+            };
+            // The save may have been given up on while this was writing
+            cancelled.clean_up(&path);
+            written
+        })
+        .await?
+    }
+
+    /// Encrypt a snapshot into the temporary file of its save
+    ///
+    /// The whole file is sealed at once, so encrypted snapshots are never
+    /// written with streaming.
+    async fn write_encrypted(
+        snapshot: Snapshot,
+        passphrase: String,
+        temp: &TempFile,
+    ) -> Result<()> {
+        let path = temp.path().to_path_buf();
+        let cancelled = temp.cancelled();
+        tokio::task::spawn_blocking(move || -> Result<()> {
+            let header = encryption::EncryptedHeader::new(
+                &snapshot.name,
+                &snapshot.url,
+                snapshot.created_at,
+                &snapshot.entry_points,
+            );
+            let plaintext = SnapshotSerializer::serialize(&snapshot)?;
+            let data = encryption::encrypt(&plaintext, header, &passphrase)?;
+            if cancelled.is_set() {
+                return Ok(());
+            }
+            let written = std::fs::write(&path, data)
+                .map_err(|e| WebMockError::io("write snapshot file", &path, e));
+            cancelled.clean_up(&path);
+            written
+        })
+        .await?
+    }
+
+    /// Decrypt and decode the contents of an encrypted snapshot file
+    async fn load_encrypted(&self, name: &str, file_data: Vec<u8>) -> Result<Snapshot> {
+        let passphrase = self.passphrase.get(name)?;
+        let loaded = tokio::task::spawn_blocking(move || {
+            let plaintext = encryption::decrypt(&file_data, &passphrase)?;
+            SnapshotSerializer::deserialize(&plaintext)
+        })
+        .await?;
+
+        match loaded {
+            Ok(mut snapshot) => {
+                snapshot.encrypted = true;
+                Ok(snapshot)
+            }
+            Err(WebMockError::WrongPassphrase(_)) => {
+                // Ask again next time rather than retrying the same passphrase
+                self.passphrase.forget();
+                Err(WebMockError::WrongPassphrase(name.to_string()))
+            }
+            Err(e) => Err(e),
+        }
+    }
+
     pub async fn load_snapshot(&self, name: &str) -> Result<Snapshot> {
         info!("Loading snapshot: {}", name);
 
-        let snapshot_path = self.get_snapshot_path(name);
+        let snapshot_path = self.resolve_snapshot_path(name)?;
+        let loaded = with_io_timeout(
+            || format!("Loading snapshot '{}'", name),
+            self.io_timeout,
+            self.read_snapshot_file(name, &snapshot_path),
+        )
+        .await?;
+        let snapshot = match loaded {
+            SnapshotFile::Decoded(snapshot) => *snapshot,
+            // Decrypted once read, as asking for the passphrase mustn't count against the timeout
+            SnapshotFile::Encrypted(file_data) => self.load_encrypted(name, file_data).await?,
+        };
 
-        // Check if snapshot file exists
-        if !snapshot_path.exists() {
-            return Err(crate::error::WebMockError::SnapshotNotFound(
-                name.to_string(),
-            ));
-        }
+        info!(
+            "Successfully loaded snapshot '{}' from {:?}",
+            name, snapshot_path
+        );
+        Ok(snapshot)
+    }
 
+    /// Read and decode a snapshot file, leaving encrypted ones to be decrypted
+    async fn read_snapshot_file(&self, name: &str, snapshot_path: &Path) -> Result<SnapshotFile> {
         // Check file size to decide on loading method
-        let metadata = tokio::fs::metadata(&snapshot_path).await?;
+        let metadata = match tokio::fs::metadata(snapshot_path).await {
+            Ok(metadata) => metadata,
+            Err(e) if e.kind() == ErrorKind::NotFound => {
+                return Err(WebMockError::SnapshotNotFound(name.to_string()));
+            }
+            Err(e) => return Err(WebMockError::io("read snapshot file", snapshot_path, e)),
+        };
         let file_size = metadata.len();
 
-        let snapshot = if file_size > 50 * 1024 * 1024 {
-            // 50MB threshold for streaming
+        if file_size > self.streaming_threshold as u64 && !is_encrypted_file(snapshot_path).await? {
             info!(
                 "Large snapshot detected ({}MB), using streaming deserialization",
                 file_size / 1024 / 1024
             );
 
-            // Use streaming deserialization for large files
-            let file = tokio::fs::File::open(&snapshot_path).await?;
-            let reader = file.into_std().await;
-            SnapshotSerializer::deserialize_streaming(reader)?
-        } else {
-            // Use regular deserialization for smaller files
-            let file_data = tokio::fs::read(&snapshot_path).await?;
-            SnapshotSerializer::deserialize(&file_data)?
-        };
+            // Use streaming deserialization for large files, off the async runtime
+            let path = snapshot_path.to_path_buf();
+            let flag = io_timeout::DropFlag::default();
+            let cancelled = flag.cancelled();
+            let snapshot = tokio::task::spawn_blocking(move || {
+                let file = std::fs::File::open(&path)
+                    .map_err(|e| WebMockError::io("open snapshot file", &path, e))?;
+                SnapshotSerializer::deserialize_file(cancelled.reader(file))
+                    .map_err(|e| io_context(e, "read snapshot file", &path))
+            })
+            .await??;
+            return Ok(SnapshotFile::Decoded(Box::new(snapshot)));
+        }
 
-        info!(
-            "Successfully loaded snapshot '{}' from {:?}",
-            name, snapshot_path
-        );
-        Ok(snapshot)
+        // Use regular deserialization for smaller files and encrypted ones
+        let file_data = read_file(snapshot_path).await?;
+        if encryption::is_encrypted(&file_data) {
+            Ok(SnapshotFile::Encrypted(file_data))
+        } else {
+            SnapshotSerializer::deserialize(&file_data)
+                .map(|snapshot| SnapshotFile::Decoded(Box::new(snapshot)))
+        }
     }
 
     pub async fn list_snapshots(&self) -> Result<Vec<SnapshotInfo>> {
@@ -143,89 +445,223 @@ impl Storage {
 
         let snapshots_dir = self.base_path.join("snapshots");
 
-        // If snapshots directory doesn't exist, return empty list
-        if !snapshots_dir.exists() {
-            debug!("Snapshots directory doesn't exist, returning empty list");
-            return Ok(Vec::new());
+        let mut names = HashSet::new();
+        match read_snapshots_dir(&snapshots_dir).await? {
+            Some(mut entries) => {
+                while let Some(entry) = entries
+                    .next_entry()
+                    .await
+                    .map_err(|e| WebMockError::io("read snapshots directory", &snapshots_dir, e))?
+                {
+                    let path = entry.path();
+
+                    // Only process .msgpack files
+                    if path
+                        .extension()
+                        .is_some_and(|extension| extension == "msgpack")
+                    {
+                        if let Some(name) = path.file_stem().and_then(|stem| stem.to_str()) {
+                            names.insert(name.to_string());
+                        }
+                    }
+                }
+            }
+            None => debug!("Snapshots directory doesn't exist yet"),
         }
 
+        // Snapshots of the read-only layer that weren't changed or deleted
+        let read_only = self.read_only_snapshots(&names).await?;
+        let names = names
+            .into_iter()
+            .chain(read_only.into_iter().map(|(name, _)| name));
+
         let mut snapshots = Vec::new();
-        let mut entries = tokio::fs::read_dir(&snapshots_dir).await?;
-
-        while let Some(entry) = entries.next_entry().await? {
-            let path = entry.path();
-
-            // Only process .msgpack files
-            if let Some(extension) = path.extension() {
-                if extension == "msgpack" {
-                    if let Some(file_stem) = path.file_stem() {
-                        if let Some(name) = file_stem.to_str() {
-                            // Try to load snapshot metadata
-                            match self.load_snapshot_metadata(name).await {
-                                Ok(info) => snapshots.push(info),
-                                Err(e) => {
-                                    // Log error but continue with other snapshots
-                                    debug!(
-                                        "Failed to load metadata for snapshot '{}': {}",
-                                        name, e
-                                    );
-                                }
-                            }
-                        }
-                    }
+        for name in names {
+            // Try to load snapshot metadata
+            match self.load_snapshot_metadata(&name).await {
+                Ok(info) => snapshots.push(info),
+                Err(e) => {
+                    // Log error but continue with other snapshots
+                    debug!("Failed to load metadata for snapshot '{}': {}", name, e);
                 }
             }
         }
 
         // Sort snapshots by creation date (newest first)
-        snapshots.sort_by(|a, b| b.created_at.cmp(&a.created_at));
+        snapshots.sort_by_key(|s| std::cmp::Reverse(s.created_at));
 
         info!("Found {} snapshots", snapshots.len());
         Ok(snapshots)
     }
 
-    /// Estimate the size of a snapshot in bytes
-    fn estimate_snapshot_size(&self, snapshot: &Snapshot) -> usize {
-        let metadata_size = 1024; // Rough estimate for metadata
-        let requests_size = snapshot
-            .requests
-            .iter()
-            .map(|req| {
-                req.url.len()
-                    + req.method.len()
-                    + req
-                        .headers
-                        .iter()
-                        .map(|(k, v)| k.len() + v.len())
-                        .sum::<usize>()
-                    + req.body.as_ref().map(|b| b.len()).unwrap_or(0)
-                    + req.response.body.len()
-                    + req
-                        .response
-                        .headers
-                        .iter()
-                        .map(|(k, v)| k.len() + v.len())
-                        .sum::<usize>()
-            })
-            .sum::<usize>();
+    /// Get the size and modification time of every snapshot file
+    ///
+    /// Only file metadata is read, so this stays cheap for large snapshots.
+    /// Results are sorted by name.
+    pub async fn snapshot_file_stats(&self) -> Result<Vec<SnapshotFileStat>> {
+        let snapshots_dir = self.base_path.join("snapshots");
+        let mut files = Vec::new();
+        if let Some(mut entries) = read_snapshots_dir(&snapshots_dir).await? {
+            while let Some(entry) = entries
+                .next_entry()
+                .await
+                .map_err(|e| WebMockError::io("read snapshots directory", &snapshots_dir, e))?
+            {
+                let path = entry.path();
+                if path
+                    .extension()
+                    .is_none_or(|extension| extension != "msgpack")
+                {
+                    continue;
+                }
+                let Some(name) = path.file_stem().and_then(|stem| stem.to_str()) else {
+                    continue;
+                };
+                if is_backup_name(name) {
+                    continue;
+                }
+                files.push((name.to_string(), path));
+            }
+        }
+
+        // Read-only snapshots count unless a writable copy shadows them
+        let names: HashSet<String> = files.iter().map(|(name, _)| name.clone()).collect();
+        files.extend(self.read_only_snapshots(&names).await?);
+
+        let mut stats = Vec::new();
+        for (name, path) in files {
+            let metadata = tokio::fs::metadata(&path)
+                .await
+                .map_err(|e| WebMockError::io("read metadata of", &path, e))?;
+            if !metadata.is_file() {
+                continue;
+            }
+            let modified = metadata
+                .modified()
+                .map_err(|e| WebMockError::io("read modification time of", &path, e))?;
+
+            stats.push(SnapshotFileStat {
+                name,
+                size: metadata.len(),
+                modified: modified.into(),
+                path,
+            });
+        }
+
+        stats.sort_by(|a, b| a.name.cmp(&b.name));
+        debug!("Collected file stats for {} snapshots", stats.len());
+        Ok(stats)
+    }
+
+    /// Move a fully written snapshot file over the snapshot `name`
+    ///
+    /// The rename is atomic, so readers see either the old snapshot or the new
+    /// one. With `keep_backup` the previous file is first linked (or copied)
+    /// to [`Storage::get_backup_path`], replacing any older backup; its path is
+    /// returned when one was made.
+    pub async fn replace_snapshot(
+        &self,
+        name: &str,
+        staged: &Path,
+        keep_backup: bool,
+    ) -> Result<Option<PathBuf>> {
+        info!("Replacing snapshot '{}' with {:?}", name, staged);
+
+        self.ensure_snapshots_dir().await?;
+        let snapshot_path = self.get_snapshot_path(name)?;
+        let staged_is_file = tokio::fs::metadata(staged)
+            .await
+            .is_ok_and(|metadata| metadata.is_file());
+        if !staged_is_file {
+            return Err(WebMockError::config(format!(
+                "Replacement for snapshot '{}' was not written",
+                name
+            )));
+        }
+
+        // The previous snapshot may be a read-only copy
+        let previous = self.resolve_snapshot_path(name)?;
+        let has_previous = tokio::fs::try_exists(&previous).await.unwrap_or(false);
+        let backup = if keep_backup && has_previous {
+            let backup_path = self.get_backup_path(name)?;
+            match tokio::fs::remove_file(&backup_path).await {
+                Err(e) if e.kind() != ErrorKind::NotFound => {
+                    return Err(WebMockError::io("remove old backup", &backup_path, e));
+                }
+                _ => {}
+            }
+            // Never link to a read-only copy, which belongs to another tree
+            let linked = previous == snapshot_path
+                && match tokio::fs::hard_link(&previous, &backup_path).await {
+                    Ok(()) => true,
+                    Err(e) => {
+                        debug!("Hard link for backup failed ({}), copying instead", e);
+                        false
+                    }
+                };
+            if !linked {
+                tokio::fs::copy(&previous, &backup_path)
+                    .await
+                    .map_err(|e| WebMockError::io("write backup", &backup_path, e))?;
+            }
+            Some(backup_path)
+        } else {
+            None
+        };
+
+        tokio::fs::rename(staged, &snapshot_path)
+            .await
+            .map_err(|e| WebMockError::io("move replacement into", &snapshot_path, e))?;
+        self.move_staged_artifacts(name, staged).await?;
+        self.move_staged_manifest(name, staged).await?;
+        self.clear_tombstone(name).await?;
+
+        info!("Successfully replaced snapshot '{}'", name);
+        Ok(backup)
+    }
 
-        metadata_size + requests_size
+    /// Write `snapshot` aside, then move it over the snapshot of the same name
+    ///
+    /// Written in full first, the file replaces the old one atomically, and
+    /// it is moved into place under the snapshot's lock, so never in the
+    /// middle of an append. `keep_backup` is as for [`Storage::replace_snapshot`].
+    pub async fn rewrite_snapshot(
+        &self,
+        snapshot: Snapshot,
+        keep_backup: bool,
+    ) -> Result<Option<PathBuf>> {
+        let name = snapshot.name.clone();
+        let staging = self.staging();
+        let staged = staging.get_snapshot_path(&name)?;
+        staging.save_snapshot(snapshot).await?;
+        let _lock = self.lock_snapshot(&name).await?;
+        self.replace_snapshot(&name, &staged, keep_backup).await
     }
 
+    /// Delete a snapshot and its artifacts
+    ///
+    /// A read-only copy can't be removed, so it is hidden with a marker in
+    /// the writable layer instead.
     pub async fn delete_snapshot(&self, name: &str) -> Result<()> {
         info!("Deleting snapshot: {}", name);
 
-        let snapshot_path = self.get_snapshot_path(name);
-
-        // Check if snapshot exists
-        if !snapshot_path.exists() {
-            return Err(crate::error::WebMockError::SnapshotNotFound(
-                name.to_string(),
-            ));
-        }
+        let snapshot_path = self.get_snapshot_path(name)?;
+        let read_only_copy = self.read_only_copy(name)?;
 
         // Delete the snapshot file
-        tokio::fs::remove_file(&snapshot_path).await?;
+        match tokio::fs::remove_file(&snapshot_path).await {
+            Ok(()) => {}
+            Err(e) if e.kind() == ErrorKind::NotFound && read_only_copy.is_some() => {}
+            Err(e) if e.kind() == ErrorKind::NotFound => {
+                return Err(WebMockError::SnapshotNotFound(name.to_string()));
+            }
+            Err(e) => return Err(WebMockError::io("delete snapshot file", &snapshot_path, e)),
+        }
+        if read_only_copy.is_some() {
+            self.write_tombstone(name).await?;
+        }
+        self.delete_artifacts(name).await?;
+        self.delete_manifest(name).await?;
 
         info!(
             "Successfully deleted snapshot '{}' from {:?}",
@@ -234,3 +670,211 @@ impl Storage {
         Ok(())
     }
 }
+
+/// Check that a snapshot name is safe to use as a file name
+///
+/// Names are 1 to 100 letters, digits, hyphens and underscores, so they can
+/// never contain path separators, `..` or NUL bytes.
+pub fn validate_snapshot_name(name: &str) -> Result<()> {
+    if name.trim().is_empty() {
+        return Err(WebMockError::config("Snapshot name cannot be empty"));
+    }
+
+    if name.len() > MAX_SNAPSHOT_NAME_LEN {
+        return Err(WebMockError::config(format!(
+            "Snapshot name cannot exceed {} characters",
+            MAX_SNAPSHOT_NAME_LEN
+        )));
+    }
+
+    if name.contains('/') || name.contains('\\') {
+        return Err(WebMockError::config(
+            "Snapshot name cannot contain path separators (/ or \\)",
+        ));
+    }
+
+    if name.contains(' ') {
+        return Err(WebMockError::config(
+            "Snapshot name cannot contain spaces. Use hyphens or underscores instead",
+        ));
+    }
+
+    // Check for invalid characters
+    if !name
+        .chars()
+        .all(|c| c.is_alphanumeric() || c == '-' || c == '_')
+    {
+        return Err(WebMockError::config(
+            "Snapshot name can only contain letters, numbers, hyphens, and underscores",
+        ));
+    }
+
+    Ok(())
+}
+
+/// Open the snapshots directory for listing, or `None` if it doesn't exist yet
+async fn read_snapshots_dir(snapshots_dir: &Path) -> Result<Option<tokio::fs::ReadDir>> {
+    match tokio::fs::read_dir(snapshots_dir).await {
+        Ok(entries) => Ok(Some(entries)),
+        Err(e) if e.kind() == ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(WebMockError::io(
+            "read snapshots directory",
+            snapshots_dir,
+            e,
+        )),
+    }
+}
+
+/// A snapshot file as read, before any decryption
+enum SnapshotFile {
+    Decoded(Box<Snapshot>),
+    Encrypted(Vec<u8>),
+}
+
+/// Read a whole snapshot file
+async fn read_file(path: &Path) -> Result<Vec<u8>> {
+    let file = tokio::fs::File::open(path)
+        .await
+        .map_err(|e| WebMockError::io("read snapshot file", path, e))?;
+    io_timeout::read_to_end(file, path).await
+}
+
+/// Read what listing shows from the start of a snapshot file
+fn read_snapshot_info(reader: impl Read) -> Result<SnapshotInfo> {
+    let mut reader = BufReader::new(reader);
+
+    // Encrypted snapshots keep what listing needs in a readable header
+    if encryption::is_encrypted(reader.fill_buf()?) {
+        let header = encryption::read_header_from(reader)?;
+        return Ok(SnapshotInfo {
+            entry_points: types::entry_points(&header.url, &header.entry_points),
+            name: header.name,
+            url: header.url,
+            created_at: header.created_at,
+            encrypted: true,
+            provenance: None,
+        });
+    }
+
+    let metadata = SnapshotSerializer::deserialize_metadata(reader)?;
+    Ok(SnapshotInfo {
+        entry_points: types::entry_points(&metadata.url, &metadata.entry_points),
+        name: metadata.name,
+        url: metadata.url,
+        created_at: metadata.created_at,
+        encrypted: false,
+        provenance: metadata.provenance,
+    })
+}
+
+/// Check whether the snapshot file at `path` is encrypted, reading only its first bytes
+pub(crate) async fn is_encrypted_file(path: &Path) -> Result<bool> {
+    use tokio::io::AsyncReadExt;
+
+    let file = tokio::fs::File::open(path)
+        .await
+        .map_err(|e| WebMockError::io("open snapshot file", path, e))?;
+    let mut start = Vec::new();
+    file.take(16)
+        .read_to_end(&mut start)
+        .await
+        .map_err(|e| WebMockError::io("read snapshot file", path, e))?;
+    Ok(encryption::is_encrypted(&start))
+}
+
+/// Add the operation and path to I/O errors raised while (de)serializing
+fn io_context(error: WebMockError, operation: &str, path: &Path) -> WebMockError {
+    match error {
+        WebMockError::Storage(e) => WebMockError::io(operation, path, e),
+        other => other,
+    }
+}
+
+/// Storage directory used without `--storage`
+///
+/// `~/.webmock`, or `$XDG_DATA_HOME/webmock` where `HOME` isn't set, as in
+/// scratch containers.
+pub fn default_storage_path() -> Result<PathBuf> {
+    default_storage_path_from(
+        std::env::var_os("HOME"),
+        std::env::var_os(XDG_DATA_HOME_ENV),
+        dirs::home_dir,
+    )
+}
+
+/// [`default_storage_path`] for the given `HOME` and `XDG_DATA_HOME` values
+///
+/// `account_home` looks the home directory up some other way, such as the
+/// password database, and is only tried when neither variable is usable.
+/// A relative `XDG_DATA_HOME` is ignored, as the XDG spec requires.
+pub fn default_storage_path_from(
+    home: Option<OsString>,
+    xdg_data_home: Option<OsString>,
+    account_home: impl FnOnce() -> Option<PathBuf>,
+) -> Result<PathBuf> {
+    let set = |value: Option<OsString>| value.filter(|value| !value.is_empty()).map(PathBuf::from);
+    if let Some(home) = set(home) {
+        return Ok(home.join(".webmock"));
+    }
+    if let Some(data_home) = set(xdg_data_home).filter(|path| path.is_absolute()) {
+        return Ok(data_home.join("webmock"));
+    }
+    account_home()
+        .map(|home| home.join(".webmock"))
+        .ok_or_else(|| {
+            WebMockError::config(format!(
+                "Could not determine a storage directory: neither HOME nor {} is set. Pass --storage <DIR>",
+                XDG_DATA_HOME_ENV
+            ))
+        })
+}
+
+/// Names of the files kept for snapshot `name` in the snapshots directory
+fn snapshot_file_names(name: &str) -> Vec<String> {
+    let mut names = vec![
+        format!("{}.msgpack", name),
+        format!("{}.{}.msgpack", name, BACKUP_SUFFIX),
+        format!("{}.{}", name, serialization::PARTIAL_EXTENSION),
+        format!("{}.{}", name, overlay::TOMBSTONE_EXTENSION),
+        // The longest names temporary files get, with the largest process and number
+        format!(
+            "{}.manifest.{}.{}.{}",
+            name,
+            u32::MAX,
+            u64::MAX,
+            io_timeout::TEMP_EXTENSION
+        ),
+        format!("{}.{}", name, segments::LOCK_EXTENSION),
+    ];
+    names.extend(
+        ArtifactKind::ALL
+            .iter()
+            .map(|kind| format!("{}.{}", name, kind.suffix())),
+    );
+    names
+}
+
+/// Check whether a file stem names a backup rather than a snapshot
+fn is_backup_name(stem: &str) -> bool {
+    stem.strip_suffix(BACKUP_SUFFIX)
+        .is_some_and(|name| name.ends_with('.'))
+}
+
+/// Read the streaming threshold from the environment, falling back to the default
+fn streaming_threshold_from_env() -> usize {
+    match std::env::var(STREAMING_THRESHOLD_ENV) {
+        Ok(value) => parse_streaming_threshold(&value).unwrap_or_else(|| {
+            warn!(
+                "Ignoring invalid {}='{}', expected a size in bytes",
+                STREAMING_THRESHOLD_ENV, value
+            );
+            DEFAULT_STREAMING_THRESHOLD
+        }),
+        Err(_) => DEFAULT_STREAMING_THRESHOLD,
+    }
+}
+
+/// Parse a streaming threshold in bytes; zero is rejected
+pub fn parse_streaming_threshold(value: &str) -> Option<usize> {
+    value.trim().parse().ok().filter(|&threshold| threshold > 0)
+}
@@ -0,0 +1,168 @@
+//! Read-only storage layer under the writable storage directory
+//!
+//! Snapshots shipped in a read-only location, such as a container image
+//! layer, can be used with `--storage-ro <DIR>`. Reads look in the writable
+//! storage directory first and fall back to the read-only one; every write
+//! goes to the writable directory, so a changed snapshot shadows its
+//! read-only copy. Deleting a snapshot that only exists in the read-only
+//! layer leaves a `<name>.deleted` marker in the writable layer, which hides
+//! it until a snapshot of that name is saved again.
+
+use std::collections::HashSet;
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+
+use tracing::{debug, info};
+
+use super::{is_backup_name, read_snapshots_dir, ArtifactKind, Storage};
+use crate::error::{Result, WebMockError};
+
+/// Extension of the marker hiding a deleted read-only snapshot
+pub const TOMBSTONE_EXTENSION: &str = "deleted";
+
+/// Read-only layer given with `--storage-ro`, used by every [`Storage::new`]
+static READ_ONLY_LAYER: OnceLock<PathBuf> = OnceLock::new();
+
+/// Use `path` as the read-only layer of all storage created from now on
+///
+/// Call once before any storage is created. Fails unless `path` is a directory.
+pub fn set_read_only_layer(path: PathBuf) -> Result<()> {
+    if !path.is_dir() {
+        return Err(WebMockError::config(format!(
+            "Read-only storage directory {} does not exist",
+            path.display()
+        )));
+    }
+    if READ_ONLY_LAYER.set(path).is_err() {
+        debug!("Read-only storage layer was already set");
+    }
+    Ok(())
+}
+
+/// The read-only layer set with [`set_read_only_layer`], if any
+pub(super) fn configured_read_only_layer() -> Option<PathBuf> {
+    READ_ONLY_LAYER.get().cloned()
+}
+
+impl Storage {
+    /// Read snapshots missing from this storage from the storage directory `path`
+    pub fn with_read_only_layer(mut self, path: PathBuf) -> Self {
+        info!("Using read-only storage layer: {:?}", path);
+        self.read_only = Some(path);
+        self
+    }
+
+    /// Get the read-only storage directory, if any
+    pub fn read_only_layer(&self) -> Option<&Path> {
+        self.read_only.as_deref()
+    }
+
+    /// Get the file a snapshot is read from
+    ///
+    /// That is the writable copy if there is one, else the read-only copy
+    /// unless it was deleted. Snapshots that exist in neither layer get the
+    /// writable path, where they would be saved.
+    pub fn resolve_snapshot_path(&self, name: &str) -> Result<PathBuf> {
+        let path = self.get_snapshot_path(name)?;
+        if path.exists() {
+            return Ok(path);
+        }
+        Ok(self.read_only_copy(name)?.unwrap_or(path))
+    }
+
+    /// Get the read-only copy of a snapshot, unless there is none or it was deleted
+    pub(super) fn read_only_copy(&self, name: &str) -> Result<Option<PathBuf>> {
+        let Some(layer) = &self.read_only else {
+            return Ok(None);
+        };
+        let path = layer.join("snapshots").join(format!("{}.msgpack", name));
+        if !path.is_file() || self.get_tombstone_path(name)?.exists() {
+            return Ok(None);
+        }
+        Ok(Some(path))
+    }
+
+    /// Get the artifact file read for snapshot `name`, following its snapshot file
+    pub(super) fn resolve_artifact_path(&self, name: &str, kind: ArtifactKind) -> Result<PathBuf> {
+        let path = self.get_artifact_path(name, kind)?;
+        if self.get_snapshot_path(name)?.exists() {
+            return Ok(path);
+        }
+        Ok(match self.read_only_copy(name)? {
+            Some(snapshot) => snapshot.with_file_name(kind.file_name(name)),
+            None => path,
+        })
+    }
+
+    /// Get the path of the marker hiding the read-only copy of `name`
+    pub fn get_tombstone_path(&self, name: &str) -> Result<PathBuf> {
+        self.get_snapshot_path(name)
+            .map(|path| path.with_extension(TOMBSTONE_EXTENSION))
+    }
+
+    /// Hide the read-only copy of `name`
+    pub(super) async fn write_tombstone(&self, name: &str) -> Result<()> {
+        self.ensure_snapshots_dir().await?;
+        let path = self.get_tombstone_path(name)?;
+        tokio::fs::write(&path, b"")
+            .await
+            .map_err(|e| WebMockError::io("write deletion marker", &path, e))?;
+        info!("Hid read-only snapshot '{}' with {:?}", name, path);
+        Ok(())
+    }
+
+    /// Remove the marker of `name` once a snapshot of that name is saved again
+    pub(super) async fn clear_tombstone(&self, name: &str) -> Result<()> {
+        let path = self.get_tombstone_path(name)?;
+        match tokio::fs::remove_file(&path).await {
+            Err(e) if e.kind() != ErrorKind::NotFound => {
+                Err(WebMockError::io("remove deletion marker", &path, e))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Get the read-only snapshot files not shadowed by one in `shadowed` or deleted
+    ///
+    /// Returns each snapshot's name with the path of its file, sorted by name.
+    pub(super) async fn read_only_snapshots(
+        &self,
+        shadowed: &HashSet<String>,
+    ) -> Result<Vec<(String, PathBuf)>> {
+        let Some(layer) = &self.read_only else {
+            return Ok(Vec::new());
+        };
+        let snapshots_dir = layer.join("snapshots");
+        let Some(mut entries) = read_snapshots_dir(&snapshots_dir).await? else {
+            return Ok(Vec::new());
+        };
+
+        let mut snapshots = Vec::new();
+        while let Some(entry) = entries
+            .next_entry()
+            .await
+            .map_err(|e| WebMockError::io("read snapshots directory", &snapshots_dir, e))?
+        {
+            let path = entry.path();
+            if path
+                .extension()
+                .is_none_or(|extension| extension != "msgpack")
+            {
+                continue;
+            }
+            let Some(name) = path.file_stem().and_then(|stem| stem.to_str()) else {
+                continue;
+            };
+            if is_backup_name(name)
+                || shadowed.contains(name)
+                || self.read_only_copy(name).ok().flatten().is_none()
+            {
+                continue;
+            }
+            snapshots.push((name.to_string(), path));
+        }
+        snapshots.sort();
+        Ok(snapshots)
+    }
+}
@@ -0,0 +1,152 @@
+//! Path limits of snapshot files across platforms
+//!
+//! Snapshot names are validated on their own, but the files kept for a
+//! snapshot live under the storage directory, so a deep `--storage` can push
+//! their full path past what the platform accepts. Windows reports that as an
+//! opaque "os error 3" once a path exceeds MAX_PATH, so storage checks the
+//! resolved paths up front and fails with an error that says what to shorten.
+//! Where possible, storage directories on Windows get the `\\?\`
+//! extended-length prefix, which lifts MAX_PATH altogether.
+
+use std::path::{Path, PathBuf};
+
+use crate::error::{Result, WebMockError};
+
+/// Longest path Windows accepts without the `\\?\` prefix, in UTF-16 units
+/// (MAX_PATH less the terminating NUL)
+pub const WINDOWS_MAX_PATH: usize = 259;
+
+/// Longest path Windows accepts with the `\\?\` prefix, in UTF-16 units
+pub const WINDOWS_MAX_EXTENDED_PATH: usize = 32_767;
+
+/// Longest path accepted on other platforms, in bytes (PATH_MAX less the NUL)
+pub const UNIX_MAX_PATH: usize = 4095;
+
+/// Longest file name accepted by common file systems (NTFS, ext4, APFS)
+pub const MAX_FILE_NAME_LEN: usize = 255;
+
+/// Prefix of Windows paths that bypass MAX_PATH
+const EXTENDED_PREFIX: &str = r"\\?\";
+
+/// Platform whose path limits apply
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PathPlatform {
+    Windows,
+    Unix,
+}
+
+impl PathPlatform {
+    /// The platform this binary runs on
+    pub fn current() -> Self {
+        if cfg!(windows) {
+            PathPlatform::Windows
+        } else {
+            PathPlatform::Unix
+        }
+    }
+
+    /// Characters that separate path components
+    fn separators(&self) -> &'static [char] {
+        match self {
+            PathPlatform::Windows => &['\\', '/'],
+            PathPlatform::Unix => &['/'],
+        }
+    }
+
+    /// Length of `text` as this platform counts it
+    fn measure(&self, text: &str) -> usize {
+        match self {
+            PathPlatform::Windows => text.encode_utf16().count(),
+            PathPlatform::Unix => text.len(),
+        }
+    }
+}
+
+/// Check that `path`, resolved against the working directory, is usable on this platform
+pub fn check_path(path: &Path) -> Result<()> {
+    let resolved = std::path::absolute(path).unwrap_or_else(|_| path.to_path_buf());
+    check_path_for(&resolved, PathPlatform::current())
+}
+
+/// Check an absolute `path` against the path and file name limits of `platform`
+pub fn check_path_for(path: &Path, platform: PathPlatform) -> Result<()> {
+    let text = path.to_string_lossy();
+
+    let file_name = text
+        .rsplit(platform.separators())
+        .next()
+        .unwrap_or_default();
+    if platform.measure(file_name) > MAX_FILE_NAME_LEN {
+        return Err(WebMockError::config(format!(
+            "File name '{}' exceeds the {}-character limit; shorten the snapshot name",
+            file_name, MAX_FILE_NAME_LEN
+        )));
+    }
+
+    let len = platform.measure(&text);
+    match platform {
+        PathPlatform::Windows if !text.starts_with(EXTENDED_PREFIX) && len > WINDOWS_MAX_PATH => {
+            Err(WebMockError::config(format!(
+                "Resulting path exceeds Windows MAX_PATH ({} characters, at most {}); shorten the snapshot name or storage path: {}",
+                len, WINDOWS_MAX_PATH, text
+            )))
+        }
+        PathPlatform::Windows if len > WINDOWS_MAX_EXTENDED_PATH => {
+            Err(WebMockError::config(format!(
+                "Resulting path exceeds {} characters; shorten the snapshot name or storage path: {}",
+                WINDOWS_MAX_EXTENDED_PATH, text
+            )))
+        }
+        PathPlatform::Unix if len > UNIX_MAX_PATH => Err(WebMockError::config(format!(
+            "Resulting path exceeds {} bytes; shorten the snapshot name or storage path: {}",
+            UNIX_MAX_PATH, text
+        ))),
+        _ => Ok(()),
+    }
+}
+
+/// Give a storage directory the `\\?\` extended-length prefix on Windows
+///
+/// The directory is made absolute first, which also normalizes its separators
+/// and `..` components as the prefix requires. Paths that can't take the
+/// prefix, and every path on other platforms, are returned unchanged.
+pub fn extended_length(path: &Path) -> PathBuf {
+    #[cfg(windows)]
+    {
+        if let Some(extended) = std::path::absolute(path)
+            .ok()
+            .and_then(|absolute| absolute.to_str().and_then(extended_length_form))
+        {
+            return PathBuf::from(extended);
+        }
+    }
+    path.to_path_buf()
+}
+
+/// The extended-length form of an absolute, normalized Windows path
+///
+/// Drive paths such as `C:\data` become `\\?\C:\data` and UNC paths such as
+/// `\\server\share` become `\\?\UNC\server\share`. Paths that already are
+/// verbatim or device paths, and relative paths, have no such form.
+pub fn extended_length_form(path: &str) -> Option<String> {
+    if path.starts_with(EXTENDED_PREFIX) || path.starts_with(r"\\.\") {
+        return None;
+    }
+    if let Some(unc) = path.strip_prefix(r"\\") {
+        return Some(format!(r"{}UNC\{}", EXTENDED_PREFIX, unc));
+    }
+    match path.as_bytes() {
+        [drive, b':', b'\\', ..] if drive.is_ascii_alphabetic() => {
+            Some(format!("{}{}", EXTENDED_PREFIX, path))
+        }
+        _ => None,
+    }
+}
+
+/// Normalize the separators of a user-supplied `--storage` value
+///
+/// Repeated and trailing separators are dropped, and on Windows forward
+/// slashes become backslashes, so the value joins cleanly with file names.
+pub fn normalize_separators(value: &str) -> PathBuf {
+    Path::new(value).components().collect()
+}
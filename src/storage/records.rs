@@ -0,0 +1,87 @@
+//! Reading a snapshot's records one at a time, without loading the snapshot
+
+use bytes::Bytes;
+use std::io::{Cursor, ErrorKind};
+use std::path::PathBuf;
+
+use super::encryption;
+use super::serialization::{RecordReader, SnapshotSerializer};
+use super::types::SnapshotMetadata;
+use super::{validate_snapshot_name, Storage};
+use crate::capture::proxy::RequestRecord;
+use crate::error::{Result, WebMockError};
+
+/// A snapshot whose records can be read in passes, one record at a time
+///
+/// Each pass reads the file from the start again, so only the record being
+/// decoded is held in memory however large the snapshot is.
+#[derive(Debug, Clone)]
+pub struct SnapshotRecords {
+    path: PathBuf,
+    /// Decrypted contents of an encrypted snapshot, which can't be read from disk in passes
+    plaintext: Option<Bytes>,
+}
+
+impl Storage {
+    /// Open a snapshot for reading its records one at a time
+    ///
+    /// An encrypted snapshot is decrypted into memory once, here.
+    pub async fn open_records(&self, name: &str) -> Result<SnapshotRecords> {
+        validate_snapshot_name(name)?;
+        let path = self.resolve_snapshot_path(name)?;
+        match tokio::fs::metadata(&path).await {
+            Ok(_) => {}
+            Err(e) if e.kind() == ErrorKind::NotFound => {
+                return Err(WebMockError::SnapshotNotFound(name.to_string()));
+            }
+            Err(e) => return Err(WebMockError::io("read snapshot file", &path, e)),
+        }
+        if !super::is_encrypted_file(&path).await? {
+            return Ok(SnapshotRecords {
+                path,
+                plaintext: None,
+            });
+        }
+
+        let data = tokio::fs::read(&path)
+            .await
+            .map_err(|e| WebMockError::io("read snapshot file", &path, e))?;
+        let passphrase = self.passphrase.get(name)?;
+        let name = name.to_string();
+        let plaintext = tokio::task::spawn_blocking(move || {
+            encryption::decrypt(&data, &passphrase).map_err(|e| match e {
+                WebMockError::WrongPassphrase(_) => WebMockError::WrongPassphrase(name),
+                other => other,
+            })
+        })
+        .await??;
+        Ok(SnapshotRecords {
+            path,
+            plaintext: Some(Bytes::from(plaintext)),
+        })
+    }
+}
+
+impl SnapshotRecords {
+    /// Read the metadata and start a pass over the records
+    ///
+    /// Blocks while reading, so call it off the async runtime.
+    pub fn read(&self) -> Result<(SnapshotMetadata, RecordReader)> {
+        match &self.plaintext {
+            Some(plaintext) => SnapshotSerializer::read_records(Cursor::new(plaintext.clone())),
+            None => SnapshotSerializer::read_records(
+                std::fs::File::open(&self.path)
+                    .map_err(|e| WebMockError::io("open snapshot file", &self.path, e))?,
+            ),
+        }
+    }
+
+    /// Read the record at `index`, passing over the ones before it
+    ///
+    /// Returns `None` when the snapshot has fewer records.
+    pub fn record(&self, index: usize) -> Result<Option<RequestRecord>> {
+        let (_, mut records) = self.read()?;
+        records.skip_records(index)?;
+        records.next().transpose()
+    }
+}
@@ -0,0 +1,613 @@
+//! Records appended to a saved snapshot without rewriting it
+//!
+//! Each call to [`Storage::append_records`] adds a segment after the bytes
+//! of the snapshot as it was saved. A segment is the msgpack encoded records
+//! split into frames of at most 64 KiB, each followed by a fixed size
+//! trailer holding the frame's length and checksum. The trailer of the last
+//! frame commits the segment: it counts the segment's records and all
+//! records appended so far, and since every trailer names where its segment
+//! starts, readers walk the segments back from the end of the file.
+//!
+//! An append that was torn by a crash leaves at most one frame and a trailer
+//! after the last complete trailer, so readers only look for trailers in
+//! that much of the file's end. Segments that were never committed or whose
+//! frames don't match their checksums are ignored, along with any after
+//! them, and the next append cuts them off.
+
+use std::fs::{File, OpenOptions};
+use std::io::{BufReader, BufWriter, Read, Seek, SeekFrom, Write};
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+use serde::Deserialize;
+use tracing::{debug, info};
+
+use super::file_lock;
+use super::limits::{decode_error, DecodeLimits, LimitedReader, MAX_NESTING_DEPTH};
+use super::serialization::SnapshotSerializer;
+use super::{io_context, validate_snapshot_name, Storage};
+use crate::capture::proxy::RequestRecord;
+use crate::error::{Result, WebMockError};
+
+/// Extension of the file locked while records are appended to a snapshot
+pub const LOCK_EXTENSION: &str = "lock";
+
+/// Starts every trailer; the last byte is the layout version
+const TRAILER_MAGIC: &[u8; 8] = b"WMSEGv01";
+
+/// Size of a trailer in bytes
+const TRAILER_LEN: usize = 48;
+
+/// Largest frame payload
+const FRAME_LEN: usize = 64 * 1024;
+
+/// Whether a trailer ends a frame of a segment or commits the whole segment
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum TrailerKind {
+    Frame,
+    Commit,
+}
+
+/// Fixed size record following each frame, little endian
+///
+/// Laid out as the magic, base length, segment start, payload length,
+/// segment records, appended records, payload CRC, kind and the CRC of the
+/// preceding 44 bytes.
+#[derive(Debug, Clone, Copy)]
+struct Trailer {
+    kind: TrailerKind,
+    /// Length of the snapshot as it was saved
+    base_len: u64,
+    /// Offset of the segment's first frame
+    segment_start: u64,
+    /// Length of the frame before the trailer
+    payload_len: u32,
+    /// Records in the segment, on commits
+    records: u32,
+    /// Records in this and all earlier segments, on commits
+    appended: u32,
+    payload_crc: u32,
+}
+
+impl Trailer {
+    fn encode(&self) -> [u8; TRAILER_LEN] {
+        let mut bytes = [0; TRAILER_LEN];
+        bytes[..8].copy_from_slice(TRAILER_MAGIC);
+        bytes[8..16].copy_from_slice(&self.base_len.to_le_bytes());
+        bytes[16..24].copy_from_slice(&self.segment_start.to_le_bytes());
+        bytes[24..28].copy_from_slice(&self.payload_len.to_le_bytes());
+        bytes[28..32].copy_from_slice(&self.records.to_le_bytes());
+        bytes[32..36].copy_from_slice(&self.appended.to_le_bytes());
+        bytes[36..40].copy_from_slice(&self.payload_crc.to_le_bytes());
+        let kind: u32 = match self.kind {
+            TrailerKind::Frame => 0,
+            TrailerKind::Commit => 1,
+        };
+        bytes[40..44].copy_from_slice(&kind.to_le_bytes());
+        let crc = crc32(&bytes[..44]);
+        bytes[44..].copy_from_slice(&crc.to_le_bytes());
+        bytes
+    }
+
+    /// Decode a trailer, or `None` if `bytes` aren't an intact one
+    fn decode(bytes: &[u8]) -> Option<Self> {
+        let bytes = bytes.get(..TRAILER_LEN)?;
+        if !bytes.starts_with(TRAILER_MAGIC) || crc32(&bytes[..44]) != le_u32(&bytes[44..]) {
+            return None;
+        }
+        let kind = match le_u32(&bytes[40..]) {
+            0 => TrailerKind::Frame,
+            1 => TrailerKind::Commit,
+            _ => return None,
+        };
+        Some(Self {
+            kind,
+            base_len: le_u64(&bytes[8..]),
+            segment_start: le_u64(&bytes[16..]),
+            payload_len: le_u32(&bytes[24..]),
+            records: le_u32(&bytes[28..]),
+            appended: le_u32(&bytes[32..]),
+            payload_crc: le_u32(&bytes[36..]),
+        })
+    }
+}
+
+fn le_u32(bytes: &[u8]) -> u32 {
+    u32::from_le_bytes(bytes[..4].try_into().unwrap())
+}
+
+fn le_u64(bytes: &[u8]) -> u64 {
+    u64::from_le_bytes(bytes[..8].try_into().unwrap())
+}
+
+fn crc32(data: &[u8]) -> u32 {
+    let mut crc = flate2::Crc::new();
+    crc.update(data);
+    crc.sum()
+}
+
+/// Payload ranges and checksums of a segment's frames
+type Frames = Vec<(Range<u64>, u32)>;
+
+/// A committed segment, as its frames' payload ranges and checksums
+struct Segment {
+    frames: Frames,
+    records: u32,
+}
+
+/// Where the records appended to a snapshot file are
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AppendedSegments {
+    /// Length of the snapshot as it was saved
+    base_len: u64,
+    /// End of the last intact segment, where the next one is written
+    end: u64,
+    /// Payload ranges of every intact segment's frames, in file order
+    frames: Vec<Range<u64>>,
+    records: u32,
+}
+
+impl AppendedSegments {
+    /// Find the intact segments of the snapshot file read by `reader`
+    ///
+    /// Reads the file's end and every appended frame, to check it.
+    pub fn read<R: Read + Seek>(reader: &mut R) -> Result<Self> {
+        let len = reader.seek(SeekFrom::End(0))?;
+        let tail_start = len.saturating_sub((FRAME_LEN + 2 * TRAILER_LEN) as u64);
+        reader.seek(SeekFrom::Start(tail_start))?;
+        let mut tail = Vec::new();
+        reader
+            .by_ref()
+            .take(len - tail_start)
+            .read_to_end(&mut tail)?;
+
+        // The latest trailer ends the file, unless an append was torn
+        if tail.len() >= TRAILER_LEN {
+            for at in (0..=tail.len() - TRAILER_LEN).rev() {
+                let Some(trailer) = Trailer::decode(&tail[at..]) else {
+                    continue;
+                };
+                let position = tail_start + at as u64;
+                if let Some(segments) = Self::from_trailer(reader, position, trailer)? {
+                    if segments.end < len {
+                        debug!("Ignoring {} bytes of a torn append", len - segments.end);
+                    }
+                    return Ok(segments);
+                }
+            }
+        }
+        Ok(Self::none(len))
+    }
+
+    /// A snapshot of `base_len` bytes nothing was appended to
+    fn none(base_len: u64) -> Self {
+        Self {
+            base_len,
+            end: base_len,
+            frames: Vec::new(),
+            records: 0,
+        }
+    }
+
+    /// Walk the segments back from the trailer at `position`
+    ///
+    /// Returns `None` when the trailer doesn't belong to an intact chain of
+    /// segments, which makes it a chance match in other data.
+    fn from_trailer<R: Read + Seek>(
+        reader: &mut R,
+        position: u64,
+        trailer: Trailer,
+    ) -> Result<Option<Self>> {
+        let base_len = trailer.base_len;
+        if trailer.segment_start < base_len || trailer.segment_start > position {
+            return Ok(None);
+        }
+        let (mut at, mut commit) = match trailer.kind {
+            TrailerKind::Commit => (position, trailer),
+            // A segment torn after this frame; the committed ones end where it starts
+            TrailerKind::Frame if trailer.segment_start == base_len => {
+                return Ok(Some(Self::none(base_len)));
+            }
+            TrailerKind::Frame => {
+                let Some(at) = trailer.segment_start.checked_sub(TRAILER_LEN as u64) else {
+                    return Ok(None);
+                };
+                match read_trailer(reader, at)? {
+                    Some(previous)
+                        if previous.kind == TrailerKind::Commit
+                            && previous.base_len == base_len =>
+                    {
+                        (at, previous)
+                    }
+                    _ => return Ok(None),
+                }
+            }
+        };
+
+        let end = at + TRAILER_LEN as u64;
+        let appended = commit.appended;
+        let mut segments = Vec::new();
+        loop {
+            let Some(frames) = read_frames(reader, at, commit)? else {
+                return Ok(None);
+            };
+            segments.push(Segment {
+                frames,
+                records: commit.records,
+            });
+            let segment_start = commit.segment_start;
+            if segment_start == base_len {
+                break;
+            }
+            let Some(previous_at) = segment_start.checked_sub(TRAILER_LEN as u64) else {
+                return Ok(None);
+            };
+            match read_trailer(reader, previous_at)? {
+                Some(previous)
+                    if previous.kind == TrailerKind::Commit
+                        && previous.base_len == base_len
+                        && previous.segment_start < segment_start
+                        && commit.appended.checked_sub(commit.records)
+                            == Some(previous.appended) =>
+                {
+                    at = previous_at;
+                    commit = previous;
+                }
+                _ => return Ok(None),
+            }
+        }
+        segments.reverse();
+        if segments.first().map(|segment| segment.records) != Some(commit.appended) {
+            return Ok(None);
+        }
+
+        // Frames that don't match their checksums end the intact segments
+        let mut intact = Self::none(base_len);
+        for segment in segments {
+            let start = segment.frames[0].0.start;
+            let mut frames = Vec::new();
+            for (range, crc) in &segment.frames {
+                if frame_crc(reader, range)? != *crc {
+                    debug!(
+                        "Ignoring appended records from offset {}, as a frame doesn't match its checksum",
+                        start
+                    );
+                    intact.end = start;
+                    return Ok(Some(intact));
+                }
+                frames.push(range.clone());
+            }
+            intact.frames.extend(frames);
+            intact.records += segment.records;
+        }
+        debug_assert_eq!(intact.records, appended);
+        intact.end = end;
+        Ok(Some(intact))
+    }
+
+    /// Length of the snapshot as it was saved, before any appended records
+    pub fn base_len(&self) -> u64 {
+        self.base_len
+    }
+
+    /// Number of records in the intact segments
+    pub fn records(&self) -> usize {
+        self.records as usize
+    }
+
+    /// Check whether no records were appended
+    pub fn is_empty(&self) -> bool {
+        self.records == 0
+    }
+
+    /// Read the appended records' bytes from the snapshot file read by `reader`
+    pub fn payload<R: Read + Seek>(&self, reader: R) -> FrameReader<R> {
+        FrameReader {
+            reader,
+            frames: self.frames.clone().into_iter(),
+            current: None,
+        }
+    }
+
+    /// Decode the appended records from the snapshot file read by `reader`
+    ///
+    /// Fails once the records' bytes exceed `limits`, like the saved ones.
+    pub fn read_all<R: Read + Seek>(
+        &self,
+        reader: R,
+        limits: &DecodeLimits,
+    ) -> Result<Vec<RequestRecord>> {
+        let mut payload = LimitedReader::new(
+            BufReader::new(self.payload(reader)),
+            limits.max_decoded_size,
+        );
+        (0..self.records)
+            .map(|_| {
+                let mut deserializer = rmp_serde::Deserializer::new(&mut payload);
+                deserializer.set_max_depth(MAX_NESTING_DEPTH);
+                let record = RequestRecord::deserialize(&mut deserializer);
+                match record {
+                    Ok(record) => Ok(record),
+                    Err(_) if payload.exceeded() => Err(payload.error()),
+                    Err(e) => Err(decode_error(e)),
+                }
+            })
+            .collect()
+    }
+}
+
+/// Read the trailer at `at`, or `None` if there isn't an intact one
+fn read_trailer<R: Read + Seek>(reader: &mut R, at: u64) -> Result<Option<Trailer>> {
+    let mut bytes = [0; TRAILER_LEN];
+    reader.seek(SeekFrom::Start(at))?;
+    match reader.read_exact(&mut bytes) {
+        Ok(()) => Ok(Trailer::decode(&bytes)),
+        Err(e) if e.kind() == std::io::ErrorKind::UnexpectedEof => Ok(None),
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// Payload ranges and checksums of the frames of the segment `commit` at `at` ends
+fn read_frames<R: Read + Seek>(reader: &mut R, at: u64, commit: Trailer) -> Result<Option<Frames>> {
+    let mut frames = Vec::new();
+    let (mut at, mut trailer) = (at, commit);
+    loop {
+        let Some(start) = at.checked_sub(u64::from(trailer.payload_len)) else {
+            return Ok(None);
+        };
+        if start < commit.segment_start {
+            return Ok(None);
+        }
+        frames.push((start..at, trailer.payload_crc));
+        if start == commit.segment_start {
+            break;
+        }
+        let Some(previous_at) = start.checked_sub(TRAILER_LEN as u64) else {
+            return Ok(None);
+        };
+        match read_trailer(reader, previous_at)? {
+            Some(previous)
+                if previous.kind == TrailerKind::Frame
+                    && previous.base_len == commit.base_len
+                    && previous.segment_start == commit.segment_start =>
+            {
+                at = previous_at;
+                trailer = previous;
+            }
+            _ => return Ok(None),
+        }
+    }
+    frames.reverse();
+    Ok(Some(frames))
+}
+
+fn frame_crc<R: Read + Seek>(reader: &mut R, range: &Range<u64>) -> Result<u32> {
+    let mut payload = Vec::with_capacity((range.end - range.start) as usize);
+    reader.seek(SeekFrom::Start(range.start))?;
+    reader
+        .by_ref()
+        .take(range.end - range.start)
+        .read_to_end(&mut payload)?;
+    Ok(crc32(&payload))
+}
+
+/// Reads the payload of appended frames as one stream, skipping their trailers
+///
+/// Created by [`AppendedSegments::payload`].
+pub struct FrameReader<R> {
+    reader: R,
+    frames: std::vec::IntoIter<Range<u64>>,
+    current: Option<Range<u64>>,
+}
+
+impl<R: Read + Seek> Read for FrameReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        loop {
+            match &mut self.current {
+                Some(frame) if frame.start < frame.end => {
+                    let len = buf.len().min((frame.end - frame.start) as usize);
+                    let read = self.reader.read(&mut buf[..len])?;
+                    if read == 0 && len > 0 {
+                        return Err(std::io::ErrorKind::UnexpectedEof.into());
+                    }
+                    frame.start += read as u64;
+                    return Ok(read);
+                }
+                _ => {
+                    let Some(frame) = self.frames.next() else {
+                        return Ok(0);
+                    };
+                    self.reader.seek(SeekFrom::Start(frame.start))?;
+                    self.current = Some(frame);
+                }
+            }
+        }
+    }
+}
+
+/// A reader sharing its source with others, each at its own position
+///
+/// Lets the records of a snapshot and those appended to it be read in turn
+/// from the same file.
+pub(super) struct SharedReader<R> {
+    source: Arc<Mutex<R>>,
+    position: u64,
+}
+
+impl<R> SharedReader<R> {
+    pub fn new(source: Arc<Mutex<R>>) -> Self {
+        Self {
+            source,
+            position: 0,
+        }
+    }
+}
+
+impl<R: Read + Seek> Read for SharedReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let mut source = self.source.lock().unwrap_or_else(|e| e.into_inner());
+        source.seek(SeekFrom::Start(self.position))?;
+        let read = source.read(buf)?;
+        self.position += read as u64;
+        Ok(read)
+    }
+}
+
+impl<R: Read + Seek> Seek for SharedReader<R> {
+    fn seek(&mut self, position: SeekFrom) -> std::io::Result<u64> {
+        self.position = match position {
+            SeekFrom::Start(offset) => offset,
+            other => {
+                let mut source = self.source.lock().unwrap_or_else(|e| e.into_inner());
+                source.seek(SeekFrom::Start(self.position))?;
+                source.seek(other)?
+            }
+        };
+        Ok(self.position)
+    }
+}
+
+/// Held while records are appended to a snapshot; unlocks when dropped
+pub struct SnapshotLock {
+    _file: File,
+}
+
+impl Storage {
+    /// Get the path of the file locked while records are appended to snapshot `name`
+    pub fn get_lock_path(&self, name: &str) -> Result<PathBuf> {
+        Ok(self.get_snapshot_path(name)?.with_extension(LOCK_EXTENSION))
+    }
+
+    /// Wait for and take the lock of snapshot `name`
+    ///
+    /// The lock is on a file, so it also keeps out other processes, and
+    /// other handles within this one.
+    pub async fn lock_snapshot(&self, name: &str) -> Result<SnapshotLock> {
+        self.ensure_snapshots_dir().await?;
+        let path = self.get_lock_path(name)?;
+        tokio::task::spawn_blocking(move || -> Result<SnapshotLock> {
+            let file = OpenOptions::new()
+                .write(true)
+                .create(true)
+                .truncate(false)
+                .open(&path)
+                .map_err(|e| WebMockError::io("open snapshot lock", &path, e))?;
+            file_lock::lock(&file).map_err(|e| WebMockError::io("lock snapshot", &path, e))?;
+            Ok(SnapshotLock { _file: file })
+        })
+        .await?
+    }
+
+    /// Append `records` to snapshot `name` without rewriting it
+    ///
+    /// Appends are serialized by the snapshot's lock. The records are read
+    /// back by [`Storage::load_snapshot`] and [`Storage::open_records`]
+    /// after the saved ones, and the manifest, if there is one, counts them.
+    /// They are numbered after every record already in the snapshot, so
+    /// sequences stay unique.
+    /// Encrypted and read-only snapshots can't be appended to.
+    pub async fn append_records(&self, name: &str, records: Vec<RequestRecord>) -> Result<()> {
+        validate_snapshot_name(name)?;
+        if records.is_empty() {
+            return Ok(());
+        }
+        let path = self.get_snapshot_path(name)?;
+        if !tokio::fs::try_exists(&path).await.unwrap_or(false) {
+            if self.read_only_copy(name)?.is_some() {
+                return Err(WebMockError::config(format!(
+                    "Snapshot '{}' is read-only; save it to the writable storage before appending to it",
+                    name
+                )));
+            }
+            return Err(WebMockError::SnapshotNotFound(name.to_string()));
+        }
+        if super::is_encrypted_file(&path).await? {
+            return Err(WebMockError::config(format!(
+                "Can't append records to encrypted snapshot '{}'",
+                name
+            )));
+        }
+
+        info!("Appending {} records to snapshot '{}'", records.len(), name);
+        let lock = self.lock_snapshot(name).await?;
+        let count = records.len();
+        let appended_path = path.clone();
+        tokio::task::spawn_blocking(move || append_segment(&appended_path, records))
+            .await?
+            .map_err(|e| io_context(e, "append to snapshot file", &path))?;
+
+        // Still under the lock, so concurrent appends count each other's records
+        if let Some(mut manifest) = self.load_manifest(name).await? {
+            manifest.record_count += count;
+            self.write_manifest(manifest, &path).await?;
+        }
+        drop(lock);
+
+        info!("Appended {} records to snapshot '{}'", count, name);
+        Ok(())
+    }
+}
+
+/// Write `records` as a new segment at the end of the snapshot file at `path`
+///
+/// The records are renumbered to follow the snapshot's, so call it under
+/// the snapshot's lock.
+fn append_segment(path: &Path, mut records: Vec<RequestRecord>) -> Result<()> {
+    let mut file = OpenOptions::new().read(true).write(true).open(path)?;
+    let next = next_sequence(&file)?;
+    let segments = AppendedSegments::read(&mut file)?;
+    let too_many = || WebMockError::invalid_snapshot("too many records");
+    let count = u32::try_from(records.len()).map_err(|_| too_many())?;
+    let appended = segments.records.checked_add(count).ok_or_else(too_many)?;
+
+    let mut payload = Vec::new();
+    for (sequence, record) in (next..).zip(&mut records) {
+        record.sequence = Some(sequence);
+        payload.extend_from_slice(&rmp_serde::to_vec(&*record)?);
+    }
+
+    // Drop what a torn append left behind
+    file.set_len(segments.end)?;
+    file.seek(SeekFrom::Start(segments.end))?;
+    let mut writer = BufWriter::new(&mut file);
+    let frames = payload.chunks(FRAME_LEN).count();
+    for (index, frame) in payload.chunks(FRAME_LEN).enumerate() {
+        let commit = index + 1 == frames;
+        let trailer = Trailer {
+            kind: if commit {
+                TrailerKind::Commit
+            } else {
+                TrailerKind::Frame
+            },
+            base_len: segments.base_len,
+            segment_start: segments.end,
+            payload_len: frame.len() as u32,
+            records: if commit { count } else { 0 },
+            appended: if commit { appended } else { 0 },
+            payload_crc: crc32(frame),
+        };
+        writer.write_all(frame)?;
+        writer.write_all(&trailer.encode())?;
+    }
+    writer.flush()?;
+    drop(writer);
+    file.sync_all()?;
+    Ok(())
+}
+
+/// First sequence after those of every record in the snapshot file
+///
+/// Records without a sequence are numbered after the largest one when the
+/// snapshot is loaded, so they are counted past it too.
+fn next_sequence(file: &File) -> Result<u64> {
+    let (_, records) = SnapshotSerializer::read_records(file.try_clone()?)?;
+    let (mut last, mut unnumbered) = (None, 0);
+    for record in records {
+        match record?.sequence {
+            Some(sequence) => last = last.max(Some(sequence)),
+            None => unnumbered += 1,
+        }
+    }
+    Ok(last.map_or(0, |last| last + 1) + unnumbered)
+}
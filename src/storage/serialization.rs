@@ -1,25 +1,37 @@
+use super::limits::{decode_error, DecodeLimits, LimitedReader, MAX_NESTING_DEPTH};
+use super::segments::{AppendedSegments, SharedReader};
 use super::types::{Snapshot, SnapshotData, SnapshotMetadata};
-use crate::error::Result;
+use crate::capture::proxy::RequestRecord;
+use crate::error::{Result, WebMockError};
+use chrono::{DateTime, Timelike, Utc};
 use flate2::{read::GzDecoder, write::GzEncoder, Compression};
-use std::io::{Read, Write};
+use rmp::Marker;
+use serde::de::IgnoredAny;
+use serde::Deserialize;
+use std::fs::{self, File};
+use std::io::{BufRead, BufReader, BufWriter, Cursor, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
 
 /// Threshold for enabling compression (1MB)
 const COMPRESSION_THRESHOLD: usize = 1024 * 1024;
 
+/// Estimated msgpack framing per record: struct arrays, status, content type
+/// and the RFC 3339 timestamp string
+const RECORD_OVERHEAD: usize = 96;
+
+/// Estimated msgpack string headers around a header name and value
+const HEADER_ENTRY_OVERHEAD: usize = 10;
+
+/// Extension of the file a large snapshot is spilled to while it is serialized
+pub const PARTIAL_EXTENSION: &str = "msgpack.partial";
+
 pub struct SnapshotSerializer;
 
 impl SnapshotSerializer {
     /// Serialize snapshot data to MessagePack format with optional compression
     pub fn serialize(snapshot: &Snapshot) -> Result<Vec<u8>> {
-        let snapshot_data = SnapshotData {
-            metadata: SnapshotMetadata {
-                name: snapshot.name.clone(),
-                url: snapshot.url.clone(),
-                created_at: snapshot.created_at,
-                version: env!("CARGO_PKG_VERSION").to_string(),
-            },
-            requests: snapshot.requests.clone(),
-        };
+        let snapshot_data = Self::snapshot_data(snapshot);
 
         // First serialize to MessagePack
         let msgpack_data = rmp_serde::to_vec(&snapshot_data)?;
@@ -32,20 +44,43 @@ impl SnapshotSerializer {
         }
     }
 
+    /// Normalize a snapshot so identical captured content serializes to identical bytes
+    ///
+    /// Used by `capture --deterministic`. Records, including those of an
+    /// offline reload, are put in the order they arrived, tunnels are sorted
+    /// by host, and timestamps and record timings lose their sub-second
+    /// precision. Snapshots saved without it keep full precision; header maps
+    /// are written in sorted order either way.
+    pub fn make_deterministic(snapshot: &mut Snapshot) {
+        snapshot.tunnels.sort_by(|a, b| a.host.cmp(&b.host));
+        snapshot.created_at = whole_seconds(snapshot.created_at);
+
+        let offline_records = snapshot
+            .offline_reload
+            .iter_mut()
+            .map(|reload| &mut reload.records);
+        for records in std::iter::once(&mut snapshot.requests).chain(offline_records) {
+            records.sort_by_key(|record| (record.timing.offset_ms, record.sequence));
+            for record in records {
+                record.timestamp = whole_seconds(record.timestamp);
+                let timing = &mut record.timing;
+                timing.offset_ms = timing.offset_ms.map(|ms| ms / 1000 * 1000);
+                timing.duration_ms = timing.duration_ms.map(|ms| ms / 1000 * 1000);
+            }
+        }
+        for event in &mut snapshot.page_events {
+            event.timestamp = whole_seconds(event.timestamp);
+        }
+        // Timings and memory differ on every run
+        snapshot.metrics = None;
+    }
+
     /// Serialize snapshot data with streaming for very large snapshots
     pub fn serialize_streaming<W: Write>(snapshot: &Snapshot, mut writer: W) -> Result<()> {
-        let snapshot_data = SnapshotData {
-            metadata: SnapshotMetadata {
-                name: snapshot.name.clone(),
-                url: snapshot.url.clone(),
-                created_at: snapshot.created_at,
-                version: env!("CARGO_PKG_VERSION").to_string(),
-            },
-            requests: snapshot.requests.clone(),
-        };
+        let snapshot_data = Self::snapshot_data(snapshot);
 
         // Estimate size to decide on compression
-        let estimated_size = Self::estimate_size(&snapshot_data);
+        let estimated_size = Self::estimate_snapshot_size(snapshot);
 
         if estimated_size > COMPRESSION_THRESHOLD {
             let mut encoder = GzEncoder::new(&mut writer, Compression::default());
@@ -58,62 +93,260 @@ impl SnapshotSerializer {
         Ok(())
     }
 
+    /// Serialize in memory, switching to streaming into `path` once the encoded
+    /// data outgrows `memory_limit`
+    ///
+    /// Returns the data if it fit in memory; otherwise the snapshot has already
+    /// been written to `path` and `None` is returned.
+    pub fn serialize_bounded(
+        snapshot: &Snapshot,
+        memory_limit: usize,
+        path: &Path,
+    ) -> Result<Option<Vec<u8>>> {
+        let snapshot_data = Self::snapshot_data(snapshot);
+        let spill_path = path.with_extension(PARTIAL_EXTENSION);
+
+        let mut writer = SpillWriter::new(memory_limit, spill_path.clone());
+        let encoded = rmp_serde::encode::write(&mut writer, &snapshot_data)
+            .map_err(Into::into)
+            .and_then(|_| writer.finish());
+
+        match encoded {
+            Ok(Spilled::InMemory(msgpack_data)) => {
+                if msgpack_data.len() > COMPRESSION_THRESHOLD {
+                    Self::compress_data(&msgpack_data).map(Some)
+                } else {
+                    Ok(Some(msgpack_data))
+                }
+            }
+            Ok(Spilled::ToFile(total_size)) => {
+                let result = Self::finish_spilled(&spill_path, path, total_size);
+                let _ = fs::remove_file(&spill_path);
+                result.map(|_| None)
+            }
+            Err(e) => {
+                let _ = fs::remove_file(&spill_path);
+                Err(e)
+            }
+        }
+    }
+
+    /// Move spilled msgpack data into place, compressing it like `serialize` would
+    fn finish_spilled(spill_path: &Path, path: &Path, total_size: usize) -> Result<()> {
+        if total_size > COMPRESSION_THRESHOLD {
+            let mut input = BufReader::new(File::open(spill_path)?);
+            let mut encoder =
+                GzEncoder::new(BufWriter::new(File::create(path)?), Compression::default());
+            std::io::copy(&mut input, &mut encoder)?;
+            encoder.finish()?.flush()?;
+        } else {
+            fs::rename(spill_path, path)?;
+        }
+        Ok(())
+    }
+
     /// Deserialize snapshot data from MessagePack format with automatic decompression
+    ///
+    /// Uses the [`DecodeLimits`] from the environment.
     pub fn deserialize(data: &[u8]) -> Result<Snapshot> {
-        let snapshot_data: SnapshotData = if Self::is_compressed(data) {
-            let decompressed = Self::decompress_data(data)?;
-            rmp_serde::from_slice(&decompressed)?
+        Self::deserialize_with_limits(data, &DecodeLimits::from_env())
+    }
+
+    /// Deserialize snapshot data, failing once the data exceeds `limits`
+    ///
+    /// Records appended to the snapshot after it was saved are read too.
+    pub fn deserialize_with_limits(data: &[u8], limits: &DecodeLimits) -> Result<Snapshot> {
+        let segments = AppendedSegments::read(&mut Cursor::new(data))?;
+        // The snapshot as it was saved, without the records appended to it
+        let file = data;
+        let data = &file[..segments.base_len() as usize];
+        let mut snapshot_data: SnapshotData = if Self::is_compressed(data) {
+            let mut decoder = LimitedReader::new(GzDecoder::new(data), limits.max_decoded_size);
+            let mut decompressed = Vec::new();
+            if let Err(e) = decoder.read_to_end(&mut decompressed) {
+                return Err(if decoder.exceeded() {
+                    decoder.error()
+                } else {
+                    e.into()
+                });
+            }
+            Self::decode_slice(&decompressed)?
         } else {
-            rmp_serde::from_slice(data)?
+            limits.check_decoded_size(data.len() as u64)?;
+            Self::decode_slice(data)?
         };
-
-        Ok(Snapshot {
-            name: snapshot_data.metadata.name,
-            url: snapshot_data.metadata.url,
-            created_at: snapshot_data.metadata.created_at,
-            requests: snapshot_data.requests,
-        })
+        // Saved records are numbered first, so appended ones don't take their sequences
+        RequestRecord::fill_missing_sequences(&mut snapshot_data.requests);
+        snapshot_data
+            .requests
+            .extend(segments.read_all(Cursor::new(file), limits)?);
+        Self::into_snapshot(snapshot_data, limits)
     }
 
     /// Deserialize snapshot data with streaming for large files
+    ///
+    /// Uses the [`DecodeLimits`] from the environment.
     pub fn deserialize_streaming<R: Read>(reader: R) -> Result<Snapshot> {
-        // Try to detect if the stream is compressed by reading the first few bytes
-        let mut buffered_reader = std::io::BufReader::new(reader);
-        let _magic_bytes = [0u8; 2];
-
-        use std::io::BufRead;
-        buffered_reader.fill_buf()?;
-        let available = buffered_reader.buffer();
-
-        let snapshot_data: SnapshotData =
-            if available.len() >= 2 && available[0] == 0x1f && available[1] == 0x8b {
-                // Gzip magic bytes detected
-                let decoder = GzDecoder::new(buffered_reader);
-                rmp_serde::decode::from_read(decoder)?
-            } else {
-                rmp_serde::decode::from_read(buffered_reader)?
-            };
+        Self::deserialize_streaming_with_limits(reader, &DecodeLimits::from_env())
+    }
+
+    /// Deserialize snapshot data with streaming, failing once the data exceeds `limits`
+    pub fn deserialize_streaming_with_limits<R: Read>(
+        reader: R,
+        limits: &DecodeLimits,
+    ) -> Result<Snapshot> {
+        let snapshot_data = Self::decode_stream(reader, limits)?;
+        Self::into_snapshot(snapshot_data, limits)
+    }
+
+    /// Deserialize a snapshot file with streaming, with the records appended to it
+    ///
+    /// Uses the [`DecodeLimits`] from the environment.
+    pub fn deserialize_file<R: Read + Seek>(mut reader: R) -> Result<Snapshot> {
+        let limits = DecodeLimits::from_env();
+        let segments = AppendedSegments::read(&mut reader)?;
+        reader.seek(SeekFrom::Start(0))?;
+        let mut snapshot_data =
+            Self::decode_stream(reader.by_ref().take(segments.base_len()), &limits)?;
+        RequestRecord::fill_missing_sequences(&mut snapshot_data.requests);
+        snapshot_data
+            .requests
+            .extend(segments.read_all(&mut reader, &limits)?);
+        Self::into_snapshot(snapshot_data, &limits)
+    }
+
+    fn decode_stream<R: Read>(reader: R, limits: &DecodeLimits) -> Result<SnapshotData> {
+        // Detect whether the stream is compressed from its first bytes
+        let mut buffered_reader = BufReader::new(reader);
+        let compressed = Self::is_compressed(buffered_reader.fill_buf()?);
+        let reader: Box<dyn Read> = if compressed {
+            Box::new(GzDecoder::new(buffered_reader))
+        } else {
+            Box::new(buffered_reader)
+        };
+
+        let mut reader = LimitedReader::new(reader, limits.max_decoded_size);
+        let mut deserializer = rmp_serde::Deserializer::new(&mut reader);
+        deserializer.set_max_depth(MAX_NESTING_DEPTH);
+        match SnapshotData::deserialize(&mut deserializer) {
+            Ok(snapshot_data) => Ok(snapshot_data),
+            Err(_) if reader.exceeded() => Err(reader.error()),
+            Err(e) => Err(decode_error(e)),
+        }
+    }
+
+    fn decode_slice(data: &[u8]) -> Result<SnapshotData> {
+        let mut deserializer = rmp_serde::Deserializer::from_read_ref(data);
+        deserializer.set_max_depth(MAX_NESTING_DEPTH);
+        SnapshotData::deserialize(&mut deserializer).map_err(decode_error)
+    }
+
+    fn into_snapshot(mut snapshot_data: SnapshotData, limits: &DecodeLimits) -> Result<Snapshot> {
+        limits.check_records(&snapshot_data.requests)?;
+        // Snapshots saved before records were timed only have wall clocks
+        RequestRecord::fill_missing_offsets(&mut snapshot_data.requests);
+        RequestRecord::fill_missing_sequences(&mut snapshot_data.requests);
+        RequestRecord::canonicalize_unicode_urls(&mut snapshot_data.requests);
 
         Ok(Snapshot {
             name: snapshot_data.metadata.name,
             url: snapshot_data.metadata.url,
             created_at: snapshot_data.metadata.created_at,
             requests: snapshot_data.requests,
+            environment: snapshot_data.metadata.environment,
+            tls: snapshot_data.metadata.tls,
+            tunnels: snapshot_data.metadata.tunnels,
+            skipped_requests: snapshot_data.metadata.skipped_requests,
+            page_events: snapshot_data.metadata.page_events,
+            retried_requests: snapshot_data.metadata.retried_requests,
+            split_from: snapshot_data.metadata.split_from,
+            serve_defaults: snapshot_data.metadata.serve_defaults,
+            offline_reload: snapshot_data.metadata.offline_reload,
+            artifacts: snapshot_data.metadata.artifacts,
+            metrics: snapshot_data.metadata.metrics,
+            blocked_hosts: snapshot_data.metadata.blocked_hosts,
+            entry_points: snapshot_data.metadata.entry_points,
+            annotations: snapshot_data.metadata.annotations,
+            capture_diagnostics: snapshot_data.metadata.capture_diagnostics,
+            provenance: snapshot_data.metadata.provenance,
+            trimmed: snapshot_data.metadata.trimmed,
+            encrypted: false,
         })
     }
 
-    /// Deserialize only metadata from MessagePack format (optimized for listing)
-    pub fn deserialize_metadata(data: &[u8]) -> Result<SnapshotMetadata> {
-        // For metadata-only access, we still need to deserialize the full structure
-        // but we can optimize this in the future by storing metadata separately
-        let snapshot_data: SnapshotData = if Self::is_compressed(data) {
-            let decompressed = Self::decompress_data(data)?;
-            rmp_serde::from_slice(&decompressed)?
+    /// Deserialize only the metadata of a snapshot (optimized for listing)
+    ///
+    /// Snapshots are encoded as metadata followed by records, so decoding
+    /// stops once the metadata has been read: the records are never read
+    /// from `reader`, and compressed snapshots are only decompressed that
+    /// far. Data laid out any other way is decoded in full.
+    pub fn deserialize_metadata<R: Read>(reader: R) -> Result<SnapshotMetadata> {
+        let mut buffered_reader = BufReader::new(reader);
+        if Self::is_compressed(buffered_reader.fill_buf()?) {
+            Self::read_metadata(BufReader::new(GzDecoder::new(buffered_reader)))
+        } else {
+            Self::read_metadata(buffered_reader)
+        }
+    }
+
+    fn read_metadata<R: BufRead>(mut reader: R) -> Result<SnapshotMetadata> {
+        let layout = reader
+            .fill_buf()?
+            .first()
+            .map(|&byte| Marker::from_u8(byte));
+        if layout != Some(Marker::FixArray(2)) {
+            let snapshot_data: SnapshotData = rmp_serde::decode::from_read(reader)?;
+            return Ok(snapshot_data.metadata);
+        }
+        rmp::decode::read_array_len(&mut reader).map_err(layout_error)?;
+        Ok(rmp_serde::decode::from_read(reader)?)
+    }
+
+    /// Read a snapshot's metadata and return a reader for its records
+    ///
+    /// Records are decoded one at a time as the reader is advanced, and
+    /// compressed snapshots are decompressed as they are read, so memory use
+    /// doesn't grow with the size of the snapshot. Records appended after the
+    /// snapshot was saved follow the others.
+    pub fn read_records<R: Read + Seek + Send + 'static>(
+        mut reader: R,
+    ) -> Result<(SnapshotMetadata, RecordReader)> {
+        let segments = AppendedSegments::read(&mut reader)?;
+        let source = Arc::new(Mutex::new(reader));
+        let base = SharedReader::new(Arc::clone(&source)).take(segments.base_len());
+        let appended = (!segments.is_empty()).then(|| {
+            let payload = segments.payload(SharedReader::new(source));
+            let reader: Box<dyn Read + Send> = Box::new(BufReader::new(payload));
+            (reader, segments.records() as u32)
+        });
+
+        let mut buffered_reader = BufReader::new(base);
+        let compressed = Self::is_compressed(buffered_reader.fill_buf()?);
+        let mut reader: Box<dyn Read + Send> = if compressed {
+            Box::new(BufReader::new(GzDecoder::new(buffered_reader)))
         } else {
-            rmp_serde::from_slice(data)?
+            Box::new(buffered_reader)
         };
 
-        Ok(snapshot_data.metadata)
+        // SnapshotData is encoded as a two element array: metadata, then records
+        let fields = rmp::decode::read_array_len(&mut reader).map_err(layout_error)?;
+        if fields != 2 {
+            return Err(WebMockError::invalid_snapshot(format!(
+                "expected metadata and records, found {} fields",
+                fields
+            )));
+        }
+        let metadata: SnapshotMetadata = rmp_serde::decode::from_read(&mut reader)?;
+        let remaining = rmp::decode::read_array_len(&mut reader).map_err(layout_error)?;
+
+        Ok((
+            metadata,
+            RecordReader {
+                reader,
+                remaining,
+                appended,
+            },
+        ))
     }
 
     /// Compress data using gzip
@@ -123,62 +356,317 @@ impl SnapshotSerializer {
         Ok(encoder.finish()?)
     }
 
-    /// Decompress gzip data
-    fn decompress_data(data: &[u8]) -> Result<Vec<u8>> {
-        let mut decoder = GzDecoder::new(data);
-        let mut decompressed = Vec::new();
-        decoder.read_to_end(&mut decompressed)?;
-        Ok(decompressed)
-    }
-
     /// Check if data is gzip compressed by looking at magic bytes
     pub fn is_compressed(data: &[u8]) -> bool {
         data.len() >= 2 && data[0] == 0x1f && data[1] == 0x8b
     }
 
-    /// Estimate the serialized size of snapshot data
-    fn estimate_size(snapshot_data: &SnapshotData) -> usize {
-        // Rough estimation: metadata + requests
+    /// Estimate the uncompressed serialized size of a snapshot
+    ///
+    /// Bodies are stored base64 encoded, and every record and header carries
+    /// msgpack framing on top of its raw bytes.
+    pub fn estimate_snapshot_size(snapshot: &Snapshot) -> usize {
         let metadata_size = 1024; // Rough estimate for metadata
-        let requests_size = snapshot_data
-            .requests
-            .iter()
-            .map(|req| {
-                req.url.len()
-                    + req.method.len()
-                    + req
-                        .headers
-                        .iter()
-                        .map(|(k, v)| k.len() + v.len())
-                        .sum::<usize>()
-                    + req.body.as_ref().map(|b| b.len()).unwrap_or(0)
-                    + req.response.body.len()
-                    + req
-                        .response
-                        .headers
-                        .iter()
-                        .map(|(k, v)| k.len() + v.len())
-                        .sum::<usize>()
-            })
-            .sum::<usize>();
-
-        metadata_size + requests_size
+        metadata_size
+            + snapshot.name.len()
+            + snapshot.url.len()
+            + snapshot
+                .requests
+                .iter()
+                .map(Self::estimate_record_size)
+                .sum::<usize>()
     }
 
-    /// Get compression ratio for a given snapshot
-    pub fn get_compression_ratio(snapshot: &Snapshot) -> Result<f64> {
-        let uncompressed = rmp_serde::to_vec(&SnapshotData {
+    fn estimate_record_size(record: &RequestRecord) -> usize {
+        let headers_size = |headers: &std::collections::HashMap<String, String>| {
+            headers
+                .iter()
+                .map(|(k, v)| k.len() + v.len() + HEADER_ENTRY_OVERHEAD)
+                .sum::<usize>()
+        };
+        let response = &record.response;
+
+        RECORD_OVERHEAD
+            + record.url.len()
+            + record.method.len()
+            + headers_size(&record.headers)
+            + record
+                .body
+                .as_ref()
+                .map(|b| base64_len(b.len()))
+                .unwrap_or(0)
+            + base64_len(response.body.len())
+            + headers_size(&response.headers)
+            + response.content_type.len()
+            + response.failure.as_ref().map(String::len).unwrap_or(0)
+            + response
+                .set_cookie
+                .iter()
+                .map(|cookie| cookie.len() + HEADER_ENTRY_OVERHEAD)
+                .sum::<usize>()
+    }
+
+    fn snapshot_data(snapshot: &Snapshot) -> SnapshotData {
+        SnapshotData {
             metadata: SnapshotMetadata {
                 name: snapshot.name.clone(),
                 url: snapshot.url.clone(),
                 created_at: snapshot.created_at,
                 version: env!("CARGO_PKG_VERSION").to_string(),
+                environment: snapshot.environment.clone(),
+                tls: snapshot.tls.clone(),
+                tunnels: snapshot.tunnels.clone(),
+                skipped_requests: snapshot.skipped_requests,
+                page_events: snapshot.page_events.clone(),
+                retried_requests: snapshot.retried_requests,
+                split_from: snapshot.split_from.clone(),
+                serve_defaults: snapshot.serve_defaults.clone(),
+                offline_reload: snapshot.offline_reload.clone(),
+                artifacts: snapshot.artifacts.clone(),
+                metrics: snapshot.metrics.clone(),
+                blocked_hosts: snapshot.blocked_hosts.clone(),
+                entry_points: snapshot.entry_points.clone(),
+                annotations: snapshot.annotations.clone(),
+                capture_diagnostics: snapshot.capture_diagnostics.clone(),
+                provenance: snapshot.provenance.clone(),
+                trimmed: snapshot.trimmed.clone(),
             },
             requests: snapshot.requests.clone(),
-        })?;
+        }
+    }
+
+    /// Get compression ratio for a given snapshot
+    pub fn get_compression_ratio(snapshot: &Snapshot) -> Result<f64> {
+        let uncompressed = rmp_serde::to_vec(&Self::snapshot_data(snapshot))?;
 
         let compressed = Self::compress_data(&uncompressed)?;
 
         Ok(compressed.len() as f64 / uncompressed.len() as f64)
     }
 }
+
+fn whole_seconds(time: DateTime<Utc>) -> DateTime<Utc> {
+    time.with_nanosecond(0).unwrap_or(time)
+}
+
+/// Length of `len` bytes once base64 encoded with padding
+fn base64_len(len: usize) -> usize {
+    len.div_ceil(3) * 4
+}
+
+/// Where a `SpillWriter` ended up holding its data
+enum Spilled {
+    InMemory(Vec<u8>),
+    /// Written to the spill file, with the total size in bytes
+    ToFile(usize),
+}
+
+/// Writer that buffers in memory up to a limit, then moves everything to a file
+struct SpillWriter {
+    buffer: Vec<u8>,
+    limit: usize,
+    spill_path: PathBuf,
+    file: Option<BufWriter<File>>,
+    total_size: usize,
+}
+
+impl SpillWriter {
+    fn new(limit: usize, spill_path: PathBuf) -> Self {
+        Self {
+            buffer: Vec::new(),
+            limit,
+            spill_path,
+            file: None,
+            total_size: 0,
+        }
+    }
+
+    fn finish(self) -> Result<Spilled> {
+        match self.file {
+            Some(mut file) => {
+                file.flush()?;
+                Ok(Spilled::ToFile(self.total_size))
+            }
+            None => Ok(Spilled::InMemory(self.buffer)),
+        }
+    }
+}
+
+impl Write for SpillWriter {
+    fn write(&mut self, data: &[u8]) -> std::io::Result<usize> {
+        if self.file.is_none() && self.buffer.len() + data.len() > self.limit {
+            let mut file = BufWriter::new(File::create(&self.spill_path)?);
+            file.write_all(&self.buffer)?;
+            self.buffer = Vec::new();
+            self.file = Some(file);
+        }
+
+        match self.file.as_mut() {
+            Some(file) => file.write_all(data)?,
+            None => self.buffer.extend_from_slice(data),
+        }
+        self.total_size += data.len();
+        Ok(data.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        match self.file.as_mut() {
+            Some(file) => file.flush(),
+            None => Ok(()),
+        }
+    }
+}
+
+fn layout_error<E: std::fmt::Display>(error: E) -> WebMockError {
+    WebMockError::invalid_snapshot(format!("unexpected snapshot layout: {}", error))
+}
+
+/// Records of a snapshot, decoded one at a time
+///
+/// Created by [`SnapshotSerializer::read_records`].
+pub struct RecordReader {
+    reader: Box<dyn Read + Send>,
+    remaining: u32,
+    /// Records appended after the snapshot was saved, read once the others are
+    appended: Option<(Box<dyn Read + Send>, u32)>,
+}
+
+impl RecordReader {
+    /// Number of records not read yet
+    pub fn remaining(&self) -> usize {
+        let appended = self
+            .appended
+            .as_ref()
+            .map_or(0, |(_, remaining)| *remaining);
+        self.remaining as usize + appended as usize
+    }
+
+    /// Read past the next `count` records without decoding them into records
+    pub fn skip_records(&mut self, count: usize) -> Result<()> {
+        for _ in 0..count.min(self.remaining()) {
+            let Some(reader) = self.advance() else {
+                break;
+            };
+            if let Err(e) = rmp_serde::decode::from_read::<_, IgnoredAny>(reader) {
+                self.stop();
+                return Err(e.into());
+            }
+        }
+        Ok(())
+    }
+
+    /// Count off the next record, returning the reader to decode it from
+    fn advance(&mut self) -> Option<&mut (dyn Read + Send + 'static)> {
+        if self.remaining == 0 {
+            let (reader, remaining) = self.appended.take()?;
+            self.reader = reader;
+            self.remaining = remaining;
+        }
+        if self.remaining == 0 {
+            return None;
+        }
+        self.remaining -= 1;
+        Some(self.reader.as_mut())
+    }
+
+    /// Stop reading, as the stream position is unknown after a decode error
+    fn stop(&mut self) {
+        self.remaining = 0;
+        self.appended = None;
+    }
+}
+
+impl Iterator for RecordReader {
+    type Item = Result<RequestRecord>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let reader = self.advance()?;
+        let record = rmp_serde::decode::from_read(reader).map_err(Into::into);
+        if record.is_err() {
+            self.stop();
+        }
+        Some(record)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (0, Some(self.remaining()))
+    }
+}
+
+/// Writes a snapshot from records added one at a time
+///
+/// The record count precedes the records in the file, so records are
+/// spilled to an anonymous temporary file until the snapshot is finished.
+pub struct RecordWriter {
+    spill: BufWriter<File>,
+    count: u32,
+    size: usize,
+}
+
+impl RecordWriter {
+    /// Create a writer spilling into a temporary file in `dir`
+    ///
+    /// Use the destination's directory, so large snapshots don't fill up a
+    /// small temporary file system.
+    pub fn new(dir: &Path) -> Result<Self> {
+        let spill = tempfile::tempfile_in(dir)
+            .map_err(|e| WebMockError::io("create temporary file in", dir, e))?;
+        Ok(Self {
+            spill: BufWriter::new(spill),
+            count: 0,
+            size: 0,
+        })
+    }
+
+    /// Append a record
+    pub fn push(&mut self, record: &RequestRecord) -> Result<()> {
+        if self.count == u32::MAX {
+            return Err(WebMockError::invalid_snapshot("too many records"));
+        }
+        let encoded = rmp_serde::to_vec(record)?;
+        self.spill.write_all(&encoded)?;
+        self.count += 1;
+        self.size += encoded.len();
+        Ok(())
+    }
+
+    /// Number of records added so far
+    pub fn len(&self) -> usize {
+        self.count as usize
+    }
+
+    /// Check whether no records were added
+    pub fn is_empty(&self) -> bool {
+        self.count == 0
+    }
+
+    /// Write the snapshot with `metadata` to `path`, returning the file size
+    ///
+    /// The file is laid out and compressed exactly as
+    /// [`SnapshotSerializer::serialize`] would, so it loads like any other.
+    pub fn finish(self, metadata: &SnapshotMetadata, path: &Path) -> Result<u64> {
+        let mut spill = self.spill.into_inner().map_err(|e| e.into_error())?;
+        spill.seek(SeekFrom::Start(0))?;
+
+        let metadata = rmp_serde::to_vec(metadata)?;
+        let mut header = Vec::new();
+        rmp::encode::write_array_len(&mut header, 2).map_err(std::io::Error::from)?;
+        header.extend_from_slice(&metadata);
+        rmp::encode::write_array_len(&mut header, self.count).map_err(std::io::Error::from)?;
+
+        let mut output = BufWriter::new(File::create(path)?);
+        if header.len() + self.size > COMPRESSION_THRESHOLD {
+            let mut encoder = GzEncoder::new(&mut output, Compression::default());
+            encoder.write_all(&header)?;
+            std::io::copy(&mut spill, &mut encoder)?;
+            encoder.finish()?;
+        } else {
+            output.write_all(&header)?;
+            std::io::copy(&mut spill, &mut output)?;
+        }
+        let file = output.into_inner().map_err(|e| e.into_error())?;
+        file.sync_all()?;
+
+        Ok(file.metadata()?.len())
+    }
+}
@@ -0,0 +1,182 @@
+//! Extracting a subset of a snapshot's records into a new snapshot
+
+use chrono::Utc;
+use std::collections::HashSet;
+use std::io::{Cursor, ErrorKind, Read};
+use tracing::{debug, info, warn};
+
+use super::encryption::{self, EncryptedHeader};
+use super::manifest::SnapshotManifest;
+use super::serialization::{RecordWriter, SnapshotSerializer};
+use super::types::SplitSource;
+use super::{validate_snapshot_name, Storage};
+use crate::capture::proxy::RequestRecord;
+use crate::error::{Result, WebMockError};
+
+/// What a split copied
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SplitSummary {
+    /// Records in the source snapshot
+    pub total: usize,
+    /// Records copied into the new snapshot
+    pub kept: usize,
+    /// Size of the new snapshot file in bytes
+    pub size: u64,
+}
+
+impl Storage {
+    /// Copy the records of `source` that `keep` accepts into a new snapshot
+    ///
+    /// Records are read, filtered and written one at a time, so neither
+    /// snapshot is held in memory. The new snapshot keeps the source's
+    /// metadata and notes `filter` as how it was made. It is written to the
+    /// staging area and only moved into place once complete; an existing
+    /// snapshot named `destination` is never overwritten.
+    ///
+    /// An encrypted source is decrypted into memory first, and the new
+    /// snapshot is encrypted with the same passphrase.
+    pub async fn split_snapshot<F>(
+        &self,
+        source: &str,
+        destination: &str,
+        filter: &str,
+        mut keep: F,
+    ) -> Result<SplitSummary>
+    where
+        F: FnMut(&RequestRecord) -> bool + Send + 'static,
+    {
+        info!(
+            "Splitting snapshot '{}' into '{}' ({})",
+            source, destination, filter
+        );
+        validate_snapshot_name(source)?;
+        validate_snapshot_name(destination)?;
+        if source == destination {
+            return Err(WebMockError::config(
+                "The new snapshot needs a different name than its source",
+            ));
+        }
+
+        let source_path = self.resolve_snapshot_path(source)?;
+        let file = match tokio::fs::File::open(&source_path).await {
+            Ok(file) => file.into_std().await,
+            Err(e) if e.kind() == ErrorKind::NotFound => {
+                return Err(WebMockError::SnapshotNotFound(source.to_string()));
+            }
+            Err(e) => return Err(WebMockError::io("open snapshot file", &source_path, e)),
+        };
+        let passphrase = if super::is_encrypted_file(&source_path).await? {
+            Some(self.passphrase.get(source)?)
+        } else {
+            None
+        };
+        if self.snapshot_exists(destination)? {
+            return Err(WebMockError::config(format!(
+                "Snapshot '{}' already exists; delete it first or choose another name",
+                destination
+            )));
+        }
+
+        let staging = self.staging();
+        let staging_dir = staging.ensure_snapshots_dir().await?;
+        let staged = staging.get_snapshot_path(destination)?;
+
+        let split_from = SplitSource {
+            snapshot: source.to_string(),
+            filter: filter.to_string(),
+        };
+        let name = destination.to_string();
+        let path = staged.clone();
+        let source_name = source.to_string();
+        let source_file = source_path.clone();
+        let written =
+            tokio::task::spawn_blocking(move || -> Result<(SplitSummary, SnapshotManifest)> {
+                let (mut metadata, records) = match &passphrase {
+                    Some(passphrase) => {
+                        let mut data = Vec::new();
+                        let mut file = file;
+                        file.read_to_end(&mut data)
+                            .map_err(|e| WebMockError::io("read snapshot file", &source_file, e))?;
+                        let plaintext =
+                            encryption::decrypt(&data, passphrase).map_err(|e| match e {
+                                WebMockError::WrongPassphrase(_) => {
+                                    WebMockError::WrongPassphrase(source_name)
+                                }
+                                other => other,
+                            })?;
+                        SnapshotSerializer::read_records(Cursor::new(plaintext))?
+                    }
+                    None => SnapshotSerializer::read_records(file)?,
+                };
+                let total = records.remaining();
+                let mut writer = RecordWriter::new(&staging_dir)?;
+                let mut kept_sequences = HashSet::new();
+                for record in records {
+                    let record = record?;
+                    if keep(&record) {
+                        kept_sequences.extend(record.sequence);
+                        writer.push(&record)?;
+                    }
+                }
+
+                let kept = writer.len();
+                metadata.name = name;
+                metadata.created_at = Utc::now();
+                metadata.version = env!("CARGO_PKG_VERSION").to_string();
+                metadata.split_from = Some(split_from);
+                // The screenshot and DOM stay with the source, under its name
+                metadata.artifacts = Default::default();
+                // The capture metrics count every record of the source
+                metadata.metrics = None;
+                // Records keep their sequence, so their notes carry over
+                metadata
+                    .annotations
+                    .retain(|sequence, _| kept_sequences.contains(sequence));
+                let mut size = writer.finish(&metadata, &path).map_err(|e| match e {
+                    WebMockError::Storage(e) => WebMockError::io("write snapshot file", &path, e),
+                    other => other,
+                })?;
+                if let Some(passphrase) = &passphrase {
+                    let plaintext = std::fs::read(&path)
+                        .map_err(|e| WebMockError::io("read snapshot file", &path, e))?;
+                    let header = EncryptedHeader::new(
+                        &metadata.name,
+                        &metadata.url,
+                        metadata.created_at,
+                        &metadata.entry_points,
+                    );
+                    let sealed = encryption::encrypt(&plaintext, header, passphrase)?;
+                    std::fs::write(&path, &sealed)
+                        .map_err(|e| WebMockError::io("write snapshot file", &path, e))?;
+                    size = sealed.len() as u64;
+                }
+
+                let manifest =
+                    SnapshotManifest::from_metadata(&metadata, kept, passphrase.is_some());
+                Ok((SplitSummary { total, kept, size }, manifest))
+            })
+            .await?;
+
+        let (summary, manifest) = match written {
+            Ok(written) => written,
+            Err(e) => {
+                if let Err(cleanup_err) = tokio::fs::remove_file(&staged).await {
+                    if cleanup_err.kind() != ErrorKind::NotFound {
+                        warn!("Failed to remove staged snapshot: {}", cleanup_err);
+                    }
+                }
+                return Err(e);
+            }
+        };
+        debug!(
+            "Kept {} of {} records, {} bytes",
+            summary.kept, summary.total, summary.size
+        );
+
+        self.replace_snapshot(destination, &staged, false).await?;
+        self.write_manifest(manifest, &self.get_snapshot_path(destination)?)
+            .await?;
+        info!("Successfully split '{}' into '{}'", source, destination);
+        Ok(summary)
+    }
+}
@@ -0,0 +1,199 @@
+use crate::capture::proxy::{RequestRecord, ResponseRecord};
+use crate::storage::{ArtifactKind, CaptureArtifacts, PageArtifacts, Snapshot, Storage};
+use chrono::Utc;
+use std::collections::HashMap;
+use tempfile::TempDir;
+
+const PNG: &[u8] = b"\x89PNG\r\n\x1a\nnot really an image";
+const DOM: &str = "<html><body><h1>Loaded</h1></body></html>";
+
+fn snapshot(name: &str) -> Snapshot {
+    Snapshot {
+        name: name.to_string(),
+        url: "https://example.com".to_string(),
+        created_at: Utc::now(),
+        requests: vec![RequestRecord::new(
+            "GET".to_string(),
+            "https://example.com/".to_string(),
+            HashMap::new(),
+            None,
+            ResponseRecord::new(200, HashMap::new(), b"ok".to_vec(), None),
+        )],
+        ..Default::default()
+    }
+}
+
+fn page_artifacts() -> PageArtifacts {
+    PageArtifacts {
+        screenshot: Some(PNG.to_vec()),
+        dom: Some(DOM.to_string()),
+    }
+}
+
+/// Save a snapshot with both artifacts, as a capture does
+async fn save_with_artifacts(storage: &Storage, name: &str) -> CaptureArtifacts {
+    let artifacts = storage.save_artifacts(name, &page_artifacts()).await;
+    storage
+        .save_snapshot(Snapshot {
+            artifacts: artifacts.clone(),
+            ..snapshot(name)
+        })
+        .await
+        .unwrap();
+    artifacts
+}
+
+#[tokio::test]
+async fn test_artifacts_are_saved_next_to_the_snapshot() {
+    let temp_dir = TempDir::new().unwrap();
+    let storage = Storage::new(temp_dir.path().to_path_buf());
+
+    let artifacts = save_with_artifacts(&storage, "site").await;
+    assert_eq!(artifacts.screenshot.as_deref(), Some("site.screenshot.png"));
+    assert_eq!(artifacts.dom.as_deref(), Some("site.dom.html"));
+
+    let snapshots_dir = temp_dir.path().join("snapshots");
+    assert_eq!(
+        std::fs::read(snapshots_dir.join("site.screenshot.png")).unwrap(),
+        PNG
+    );
+    assert_eq!(
+        std::fs::read_to_string(snapshots_dir.join("site.dom.html")).unwrap(),
+        DOM
+    );
+
+    // The references survive a round trip through the snapshot file
+    let loaded = storage.load_snapshot("site").await.unwrap();
+    assert_eq!(loaded.artifacts, artifacts);
+
+    let files = storage.artifact_files("site").await.unwrap();
+    assert_eq!(files.len(), 2);
+    assert_eq!(files[0].kind, ArtifactKind::Screenshot);
+    assert_eq!(files[0].size, PNG.len() as u64);
+    assert_eq!(files[1].kind, ArtifactKind::Dom);
+    assert_eq!(files[1].size, DOM.len() as u64);
+
+    // Artifact files are never mistaken for snapshots
+    let listed = storage.list_snapshots().await.unwrap();
+    assert_eq!(listed.len(), 1);
+}
+
+#[tokio::test]
+async fn test_only_taken_artifacts_are_referenced() {
+    let temp_dir = TempDir::new().unwrap();
+    let storage = Storage::new(temp_dir.path().to_path_buf());
+
+    let dom_only = PageArtifacts {
+        screenshot: None,
+        dom: Some(DOM.to_string()),
+    };
+    let artifacts = storage.save_artifacts("site", &dom_only).await;
+    assert_eq!(artifacts.get(ArtifactKind::Screenshot), None);
+    assert_eq!(artifacts.get(ArtifactKind::Dom), Some("site.dom.html"));
+    assert!(!storage
+        .get_artifact_path("site", ArtifactKind::Screenshot)
+        .unwrap()
+        .exists());
+
+    let nothing = storage
+        .save_artifacts("other", &PageArtifacts::default())
+        .await;
+    assert!(nothing.is_empty());
+}
+
+#[tokio::test]
+async fn test_failed_artifact_writes_are_left_out() {
+    let temp_dir = TempDir::new().unwrap();
+    let storage = Storage::new(temp_dir.path().to_path_buf());
+
+    // A directory in the way of the screenshot file makes its write fail
+    std::fs::create_dir_all(
+        storage
+            .get_artifact_path("site", ArtifactKind::Screenshot)
+            .unwrap(),
+    )
+    .unwrap();
+    let artifacts = storage.save_artifacts("site", &page_artifacts()).await;
+    assert_eq!(artifacts.get(ArtifactKind::Screenshot), None);
+    assert_eq!(artifacts.get(ArtifactKind::Dom), Some("site.dom.html"));
+}
+
+#[tokio::test]
+async fn test_delete_snapshot_removes_its_artifacts() {
+    let temp_dir = TempDir::new().unwrap();
+    let storage = Storage::new(temp_dir.path().to_path_buf());
+    save_with_artifacts(&storage, "site").await;
+    save_with_artifacts(&storage, "other").await;
+
+    storage.delete_snapshot("site").await.unwrap();
+    assert!(storage.artifact_files("site").await.unwrap().is_empty());
+    assert_eq!(storage.artifact_files("other").await.unwrap().len(), 2);
+
+    // A snapshot saved without artifacts deletes cleanly too
+    storage.save_snapshot(snapshot("plain")).await.unwrap();
+    storage.delete_snapshot("plain").await.unwrap();
+    assert!(!storage.snapshot_exists("plain").unwrap());
+}
+
+#[tokio::test]
+async fn test_replace_snapshot_moves_staged_artifacts() {
+    let temp_dir = TempDir::new().unwrap();
+    let storage = Storage::new(temp_dir.path().to_path_buf());
+    let staging = storage.staging();
+    save_with_artifacts(&storage, "site").await;
+
+    // A recapture saves new artifacts with the staged snapshot
+    let recaptured = PageArtifacts {
+        screenshot: Some(b"new screenshot".to_vec()),
+        dom: Some("<html>new</html>".to_string()),
+    };
+    let artifacts = staging.save_artifacts("site", &recaptured).await;
+    staging
+        .save_snapshot(Snapshot {
+            artifacts,
+            ..snapshot("site")
+        })
+        .await
+        .unwrap();
+    let staged = staging.get_snapshot_path("site").unwrap();
+    storage
+        .replace_snapshot("site", &staged, false)
+        .await
+        .unwrap();
+
+    let screenshot = storage
+        .get_artifact_path("site", ArtifactKind::Screenshot)
+        .unwrap();
+    assert_eq!(std::fs::read(&screenshot).unwrap(), b"new screenshot");
+    assert!(staging.artifact_files("site").await.unwrap().is_empty());
+
+    // Rewriting only the snapshot, as `config` does, keeps the artifacts
+    let mut current = storage.load_snapshot("site").await.unwrap();
+    current.serve_defaults.port = Some(8090);
+    staging.save_snapshot(current).await.unwrap();
+    storage
+        .replace_snapshot("site", &staged, false)
+        .await
+        .unwrap();
+    assert_eq!(std::fs::read(&screenshot).unwrap(), b"new screenshot");
+    let current = storage.load_snapshot("site").await.unwrap();
+    assert_eq!(
+        current.artifacts.get(ArtifactKind::Screenshot),
+        Some("site.screenshot.png")
+    );
+}
+
+#[tokio::test]
+async fn test_split_snapshot_leaves_artifacts_with_the_source() {
+    let temp_dir = TempDir::new().unwrap();
+    let storage = Storage::new(temp_dir.path().to_path_buf());
+    save_with_artifacts(&storage, "site").await;
+
+    storage
+        .split_snapshot("site", "part", "all", |_| true)
+        .await
+        .unwrap();
+    let part = storage.load_snapshot("part").await.unwrap();
+    assert!(part.artifacts.is_empty());
+    assert_eq!(storage.artifact_files("site").await.unwrap().len(), 2);
+}
@@ -0,0 +1,157 @@
+use crate::error::WebMockError;
+use crate::storage::encryption::{self, EncryptedHeader};
+use crate::storage::{PassphraseSource, Storage};
+use crate::test_utils::test_helpers::create_multi_request_snapshot;
+use chrono::Utc;
+use tempfile::TempDir;
+
+fn header(name: &str) -> EncryptedHeader {
+    EncryptedHeader::new(
+        name,
+        "https://example.com",
+        Utc::now(),
+        &["https://example.com/home".to_string()],
+    )
+}
+
+async fn save_encrypted(temp_dir: &TempDir, name: &str, passphrase: &str) -> Storage {
+    let storage = Storage::new(temp_dir.path().to_path_buf())
+        .with_passphrase(PassphraseSource::fixed(passphrase));
+    let mut snapshot = create_multi_request_snapshot(name);
+    snapshot.encrypted = true;
+    storage.save_snapshot(snapshot).await.unwrap();
+    storage
+}
+
+#[test]
+fn test_encrypt_round_trips_with_the_right_passphrase() {
+    let sealed = encryption::encrypt(b"snapshot data", header("site"), "secret").unwrap();
+
+    assert!(encryption::is_encrypted(&sealed));
+    assert!(!encryption::is_encrypted(b"snapshot data"));
+    assert_eq!(
+        encryption::decrypt(&sealed, "secret").unwrap(),
+        b"snapshot data"
+    );
+    assert_eq!(encryption::read_header(&sealed).unwrap().name, "site");
+}
+
+#[test]
+fn test_decrypt_rejects_a_wrong_passphrase_and_tampering() {
+    let sealed = encryption::encrypt(b"snapshot data", header("site"), "secret").unwrap();
+
+    let err = encryption::decrypt(&sealed, "guess").unwrap_err();
+    assert!(matches!(err, WebMockError::WrongPassphrase(name) if name == "site"));
+
+    // The readable header is authenticated too
+    let mut tampered = sealed.clone();
+    let at = tampered
+        .windows(b"example.com".len())
+        .position(|w| w == b"example.com")
+        .unwrap();
+    tampered[at] = b'E';
+    assert!(matches!(
+        encryption::decrypt(&tampered, "secret"),
+        Err(WebMockError::WrongPassphrase(_))
+    ));
+
+    assert!(encryption::decrypt(&sealed[..20], "secret").is_err());
+}
+
+#[test]
+fn test_decrypt_rejects_headers_asking_for_too_many_rounds() {
+    let sealed = encryption::encrypt(b"snapshot data", header("site"), "secret").unwrap();
+
+    // Rewrite the rounds, the header's fifth field
+    let len = u32::from_be_bytes(sealed[8..12].try_into().unwrap()) as usize;
+    let mut fields: serde_json::Value = rmp_serde::from_slice(&sealed[12..12 + len]).unwrap();
+    let rounds = fields[4].as_u64().unwrap();
+    fields[4] = serde_json::json!(u32::MAX);
+    let rewritten = rmp_serde::to_vec(&fields).unwrap();
+    let mut crafted = sealed[..8].to_vec();
+    crafted.extend_from_slice(&(rewritten.len() as u32).to_be_bytes());
+    crafted.extend_from_slice(&rewritten);
+    crafted.extend_from_slice(&sealed[12 + len..]);
+
+    let err = encryption::decrypt(&crafted, "secret").unwrap_err();
+    assert!(matches!(err, WebMockError::InvalidSnapshot(_)), "{:?}", err);
+    assert!(err.to_string().contains("key derivation rounds"));
+
+    // Only the rounds changed: putting them back gives the original header
+    fields[4] = serde_json::json!(rounds);
+    assert_eq!(rmp_serde::to_vec(&fields).unwrap(), sealed[12..12 + len]);
+}
+
+#[tokio::test]
+async fn test_encrypted_snapshot_round_trips_through_storage() {
+    let temp_dir = TempDir::new().unwrap();
+    let storage = save_encrypted(&temp_dir, "site", "secret").await;
+
+    let data = std::fs::read(storage.get_snapshot_path("site").unwrap()).unwrap();
+    assert!(encryption::is_encrypted(&data));
+    let body = b"Hello from API";
+    assert!(!data.windows(body.len()).any(|w| w == body));
+
+    let loaded = storage.load_snapshot("site").await.unwrap();
+    let original = create_multi_request_snapshot("site");
+    assert!(loaded.encrypted);
+    assert_eq!(loaded.requests.len(), original.requests.len());
+    assert_eq!(loaded.requests[0].url, original.requests[0].url);
+}
+
+#[tokio::test]
+async fn test_loading_with_a_wrong_passphrase_fails_clearly() {
+    let temp_dir = TempDir::new().unwrap();
+    save_encrypted(&temp_dir, "site", "secret").await;
+
+    let passphrase = PassphraseSource::fixed("guess");
+    let storage = Storage::new(temp_dir.path().to_path_buf()).with_passphrase(passphrase.clone());
+    let err = storage.load_snapshot("site").await.unwrap_err();
+
+    assert!(matches!(&err, WebMockError::WrongPassphrase(name) if name == "site"));
+    assert_eq!(
+        err.to_string(),
+        "Wrong passphrase for snapshot 'site', or its file was modified"
+    );
+    assert!(!err.is_recoverable());
+}
+
+#[tokio::test]
+async fn test_listing_reads_encrypted_snapshots_without_the_passphrase() {
+    let temp_dir = TempDir::new().unwrap();
+    save_encrypted(&temp_dir, "site", "secret").await;
+    Storage::new(temp_dir.path().to_path_buf())
+        .save_snapshot(create_multi_request_snapshot("plain"))
+        .await
+        .unwrap();
+
+    // A storage without a passphrase would have to prompt to decrypt
+    let storage = Storage::new(temp_dir.path().to_path_buf());
+    let snapshots = storage.list_snapshots().await.unwrap();
+    let site = snapshots.iter().find(|info| info.name == "site").unwrap();
+    let plain = snapshots.iter().find(|info| info.name == "plain").unwrap();
+
+    assert!(site.encrypted);
+    assert_eq!(site.url, "https://example.com");
+    assert!(!plain.encrypted);
+}
+
+#[tokio::test]
+async fn test_split_of_an_encrypted_snapshot_stays_encrypted() {
+    let temp_dir = TempDir::new().unwrap();
+    let storage = save_encrypted(&temp_dir, "site", "secret").await;
+
+    let summary = storage
+        .split_snapshot("site", "styles", "*.css", |record| {
+            record.url.ends_with(".css")
+        })
+        .await
+        .unwrap();
+    assert_eq!(summary.kept, 1);
+
+    let data = std::fs::read(storage.get_snapshot_path("styles").unwrap()).unwrap();
+    assert!(encryption::is_encrypted(&data));
+    let split = storage.load_snapshot("styles").await.unwrap();
+    assert!(split.encrypted);
+    assert_eq!(split.requests.len(), 1);
+}
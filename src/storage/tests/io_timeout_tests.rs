@@ -0,0 +1,280 @@
+use std::future::Future;
+use std::path::Path;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use std::time::{Duration, Instant};
+
+use tempfile::TempDir;
+use tokio::io::{AsyncRead, ReadBuf};
+use tokio::time::Sleep;
+
+use crate::error::WebMockError;
+use crate::storage::io_timeout::{read_to_end, with_io_timeout, TempFile, TEMP_EXTENSION};
+use crate::storage::{parse_io_timeout, Snapshot, Storage};
+use crate::test_utils::test_helpers::create_multi_request_snapshot;
+
+/// Reads `chunk` bytes at a time from a slice, waiting `delay` before each chunk
+struct SlowReader<'a> {
+    data: &'a [u8],
+    chunk: usize,
+    delay: Duration,
+    sleep: Option<Pin<Box<Sleep>>>,
+}
+
+impl<'a> SlowReader<'a> {
+    fn new(data: &'a [u8], chunk: usize, delay: Duration) -> Self {
+        Self {
+            data,
+            chunk,
+            delay,
+            sleep: None,
+        }
+    }
+}
+
+impl AsyncRead for SlowReader<'_> {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<std::io::Result<()>> {
+        let delay = self.delay;
+        let sleep = self
+            .sleep
+            .get_or_insert_with(|| Box::pin(tokio::time::sleep(delay)));
+        if sleep.as_mut().poll(cx).is_pending() {
+            return Poll::Pending;
+        }
+        self.sleep = None;
+
+        let len = self.chunk.min(self.data.len()).min(buf.remaining());
+        let (chunk, rest) = self.data.split_at(len);
+        buf.put_slice(chunk);
+        self.data = rest;
+        Poll::Ready(Ok(()))
+    }
+}
+
+/// A snapshot of `requests` bodies of 1MB each that don't compress away
+fn large_snapshot(name: &str, requests: usize) -> Snapshot {
+    let mut snapshot = create_multi_request_snapshot(name);
+    let template = snapshot.requests[0].clone();
+    let mut state = 0x2545_f491_u32;
+    snapshot.requests = (0..requests)
+        .map(|i| {
+            let mut record = template.clone();
+            record.url = format!("https://example.com/large/{}", i);
+            record.response.body = (0..1024 * 1024)
+                .map(|_| {
+                    state ^= state << 13;
+                    state ^= state >> 17;
+                    state ^= state << 5;
+                    state as u8
+                })
+                .collect();
+            record
+        })
+        .collect();
+    snapshot
+}
+
+/// Files in `dir` a save writes before the snapshot is complete
+fn unfinished_files(dir: &Path) -> Vec<String> {
+    std::fs::read_dir(dir.join("snapshots"))
+        .unwrap()
+        .map(|entry| entry.unwrap().file_name().to_string_lossy().into_owned())
+        .filter(|name| name.ends_with(".tmp") || name.ends_with(".partial"))
+        .collect()
+}
+
+/// Wait for blocking writers of a dropped save to clean up after themselves
+async fn wait_for_cleanup(dir: &Path) -> Vec<String> {
+    let started = Instant::now();
+    loop {
+        let files = unfinished_files(dir);
+        if files.is_empty() || started.elapsed() > Duration::from_secs(10) {
+            return files;
+        }
+        tokio::time::sleep(Duration::from_millis(20)).await;
+    }
+}
+
+#[test]
+fn test_parse_io_timeout() {
+    assert_eq!(parse_io_timeout("30"), Some(Duration::from_secs(30)));
+    assert_eq!(parse_io_timeout(" 30s "), Some(Duration::from_secs(30)));
+    assert_eq!(parse_io_timeout("500ms"), Some(Duration::from_millis(500)));
+    assert_eq!(parse_io_timeout("2m"), Some(Duration::from_secs(120)));
+    assert_eq!(parse_io_timeout("0"), None);
+    assert_eq!(parse_io_timeout("0ms"), None);
+    assert_eq!(parse_io_timeout("1.5s"), None);
+    assert_eq!(parse_io_timeout("ten"), None);
+    assert_eq!(parse_io_timeout("10h"), None);
+    assert_eq!(parse_io_timeout(""), None);
+}
+
+#[tokio::test]
+async fn test_slow_read_times_out_with_operation_name() {
+    let data = vec![7u8; 64];
+    let reader = SlowReader::new(&data, 4, Duration::from_millis(20));
+
+    let result = with_io_timeout(
+        || "Loading snapshot 'slow'".to_string(),
+        Some(Duration::from_millis(50)),
+        read_to_end(reader, Path::new("slow.msgpack")),
+    )
+    .await;
+
+    match result {
+        Err(WebMockError::Timeout { operation, after }) => {
+            assert_eq!(operation, "Loading snapshot 'slow'");
+            assert_eq!(after, Duration::from_millis(50));
+        }
+        other => panic!("expected a timeout, got {:?}", other.map(|data| data.len())),
+    }
+    let error = WebMockError::timeout("Loading snapshot 'slow'", Duration::from_millis(50));
+    assert_eq!(
+        error.to_string(),
+        "Loading snapshot 'slow' timed out after 50ms"
+    );
+    assert!(error.user_message().contains("WEBMOCK_IO_TIMEOUT"));
+}
+
+#[tokio::test]
+async fn test_slow_read_without_timeout_completes() {
+    let data: Vec<u8> = (0..32).collect();
+    let reader = SlowReader::new(&data, 5, Duration::from_millis(5));
+
+    let read = with_io_timeout(
+        || unreachable!("no timeout is set"),
+        None,
+        read_to_end(reader, Path::new("slow.msgpack")),
+    )
+    .await
+    .unwrap();
+    assert_eq!(read, data);
+}
+
+#[tokio::test]
+async fn test_save_timeout_leaves_previous_snapshot() {
+    let temp_dir = TempDir::new().unwrap();
+    let storage = Storage::with_streaming_threshold(temp_dir.path().to_path_buf(), 1024);
+    storage
+        .save_snapshot(create_multi_request_snapshot("large"))
+        .await
+        .unwrap();
+    let path = storage.get_snapshot_path("large").unwrap();
+    let before = std::fs::read(&path).unwrap();
+
+    let storage = storage.with_io_timeout(Some(Duration::from_millis(1)));
+    let error = storage
+        .save_snapshot(large_snapshot("large", 20))
+        .await
+        .unwrap_err();
+    assert!(
+        matches!(&error, WebMockError::Timeout { operation, .. } if operation == "Saving snapshot 'large'"),
+        "{:?}",
+        error
+    );
+
+    assert_eq!(
+        wait_for_cleanup(temp_dir.path()).await,
+        Vec::<String>::new()
+    );
+    assert_eq!(std::fs::read(&path).unwrap(), before);
+    let loaded = storage.load_snapshot("large").await.unwrap();
+    assert_eq!(
+        loaded.requests.len(),
+        create_multi_request_snapshot("large").requests.len()
+    );
+}
+
+#[tokio::test]
+async fn test_load_timeout_names_snapshot() {
+    let temp_dir = TempDir::new().unwrap();
+    let storage = Storage::with_streaming_threshold(temp_dir.path().to_path_buf(), 1024);
+    storage
+        .save_snapshot(large_snapshot("large", 20))
+        .await
+        .unwrap();
+
+    let error = Storage::with_streaming_threshold(temp_dir.path().to_path_buf(), 1024)
+        .with_io_timeout(Some(Duration::from_millis(1)))
+        .load_snapshot("large")
+        .await
+        .unwrap_err();
+    assert!(
+        matches!(&error, WebMockError::Timeout { operation, .. } if operation == "Loading snapshot 'large'"),
+        "{:?}",
+        error
+    );
+
+    // Loading is read-only, so a later load without the limit still works
+    let loaded = storage.load_snapshot("large").await.unwrap();
+    assert_eq!(loaded.requests.len(), 20);
+}
+
+#[tokio::test]
+async fn test_aborted_save_leaves_no_temp_files() {
+    let temp_dir = TempDir::new().unwrap();
+    let storage = Storage::with_streaming_threshold(temp_dir.path().to_path_buf(), 1024);
+
+    std::fs::create_dir_all(temp_dir.path().join("snapshots")).unwrap();
+
+    // Like a capture interrupted with Ctrl+C while saving
+    let snapshot = large_snapshot("aborted", 20);
+    let base_path = temp_dir.path().to_path_buf();
+    let save = tokio::spawn(async move {
+        Storage::with_streaming_threshold(base_path, 1024)
+            .save_snapshot(snapshot)
+            .await
+    });
+    tokio::time::sleep(Duration::from_millis(20)).await;
+    save.abort();
+    let _ = save.await;
+
+    assert_eq!(
+        wait_for_cleanup(temp_dir.path()).await,
+        Vec::<String>::new()
+    );
+    assert!(matches!(
+        storage.load_snapshot("aborted").await,
+        Err(WebMockError::SnapshotNotFound(_))
+    ));
+}
+
+#[test]
+fn test_temp_files_of_one_target_are_distinct() {
+    let target = Path::new("snapshots/site.msgpack");
+    let first = TempFile::for_target(target);
+    let second = TempFile::for_target(target);
+    assert_ne!(first.path(), second.path());
+    assert_eq!(first.path().parent(), target.parent());
+    assert!(first
+        .path()
+        .to_string_lossy()
+        .ends_with(&format!(".{}", TEMP_EXTENSION)));
+}
+
+#[tokio::test]
+async fn test_concurrent_saves_of_one_snapshot_all_complete() {
+    let temp_dir = TempDir::new().unwrap();
+    let storage = std::sync::Arc::new(Storage::new(temp_dir.path().to_path_buf()));
+
+    let saves: Vec<_> = (0..8)
+        .map(|_| {
+            let storage = std::sync::Arc::clone(&storage);
+            tokio::spawn(async move {
+                storage
+                    .save_snapshot(create_multi_request_snapshot("site"))
+                    .await
+            })
+        })
+        .collect();
+    for save in saves {
+        save.await.unwrap().unwrap();
+    }
+
+    assert!(storage.load_snapshot("site").await.is_ok());
+    assert!(unfinished_files(temp_dir.path()).is_empty());
+}
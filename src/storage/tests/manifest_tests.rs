@@ -0,0 +1,154 @@
+use crate::storage::manifest::file_sha256;
+use crate::storage::{ManifestCheck, Storage};
+use crate::test_utils::test_helpers::create_multi_request_snapshot;
+use tempfile::TempDir;
+
+fn storage() -> (TempDir, Storage) {
+    let temp_dir = TempDir::new().unwrap();
+    let storage = Storage::new(temp_dir.path().to_path_buf());
+    (temp_dir, storage)
+}
+
+#[tokio::test]
+async fn test_save_writes_manifest_next_to_snapshot() {
+    let (_temp_dir, storage) = storage();
+    let snapshot = create_multi_request_snapshot("site");
+    let created_at = snapshot.created_at;
+    storage.save_snapshot(snapshot).await.unwrap();
+
+    let manifest_path = storage.get_manifest_path("site").unwrap();
+    assert_eq!(
+        manifest_path.file_name().unwrap().to_str(),
+        Some("site.manifest.json")
+    );
+    let manifest = storage.load_manifest("site").await.unwrap().unwrap();
+    let snapshot_path = storage.get_snapshot_path("site").unwrap();
+    let (sha256, size) = file_sha256(&snapshot_path).unwrap();
+
+    assert_eq!(manifest.name, "site");
+    assert_eq!(manifest.format_version, env!("CARGO_PKG_VERSION"));
+    assert_eq!(manifest.urls, vec!["https://example.com".to_string()]);
+    assert_eq!(manifest.created_at, created_at);
+    assert_eq!(manifest.record_count, 3);
+    assert_eq!(manifest.total_bytes, size);
+    assert_eq!(manifest.sha256, sha256);
+    assert_eq!(manifest.sha256.len(), 64);
+    assert!(!manifest.encrypted);
+
+    // Written atomically, so no temporary file is left behind
+    let leftovers: Vec<_> = std::fs::read_dir(manifest_path.parent().unwrap())
+        .unwrap()
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| entry.file_name().to_string_lossy().ends_with(".tmp"))
+        .collect();
+    assert!(leftovers.is_empty());
+}
+
+#[tokio::test]
+async fn test_manifest_hash_is_stable_across_saves() {
+    let (_other_dir, other) = storage();
+    let (_temp_dir, storage) = storage();
+    let snapshot = create_multi_request_snapshot("site");
+
+    storage.save_snapshot(snapshot.clone()).await.unwrap();
+    let first = storage.load_manifest("site").await.unwrap().unwrap();
+    storage.save_snapshot(snapshot.clone()).await.unwrap();
+    let second = storage.load_manifest("site").await.unwrap().unwrap();
+    assert_eq!(first, second);
+
+    // Another storage directory produces the same hash for the same snapshot
+    other.save_snapshot(snapshot).await.unwrap();
+    let elsewhere = other.load_manifest("site").await.unwrap().unwrap();
+    assert_eq!(elsewhere.sha256, first.sha256);
+}
+
+#[tokio::test]
+async fn test_check_manifest_detects_tampered_file() {
+    let (_temp_dir, storage) = storage();
+    storage
+        .save_snapshot(create_multi_request_snapshot("site"))
+        .await
+        .unwrap();
+    assert!(matches!(
+        storage.check_manifest("site").await.unwrap(),
+        ManifestCheck::Matches(_)
+    ));
+
+    // Flip one byte without changing the size
+    let snapshot_path = storage.get_snapshot_path("site").unwrap();
+    let mut data = std::fs::read(&snapshot_path).unwrap();
+    let last = data.len() - 1;
+    data[last] ^= 0xff;
+    std::fs::write(&snapshot_path, &data).unwrap();
+
+    match storage.check_manifest("site").await.unwrap() {
+        ManifestCheck::Mismatch {
+            manifest,
+            sha256,
+            total_bytes,
+        } => {
+            assert_ne!(sha256, manifest.sha256);
+            assert_eq!(total_bytes, manifest.total_bytes);
+        }
+        other => panic!("Expected a mismatch, got {:?}", other),
+    }
+}
+
+#[tokio::test]
+async fn test_delete_removes_manifest() {
+    let (_temp_dir, storage) = storage();
+    storage
+        .save_snapshot(create_multi_request_snapshot("site"))
+        .await
+        .unwrap();
+    let manifest_path = storage.get_manifest_path("site").unwrap();
+    assert!(manifest_path.exists());
+
+    storage.delete_snapshot("site").await.unwrap();
+    assert!(!manifest_path.exists());
+}
+
+#[tokio::test]
+async fn test_replace_moves_staged_manifest() {
+    let (_temp_dir, storage) = storage();
+    let mut snapshot = create_multi_request_snapshot("site");
+    storage.save_snapshot(snapshot.clone()).await.unwrap();
+    let original = storage.load_manifest("site").await.unwrap().unwrap();
+
+    snapshot.requests.pop();
+    let staging = storage.staging();
+    let staged = staging.get_snapshot_path("site").unwrap();
+    staging.save_snapshot(snapshot).await.unwrap();
+    storage
+        .replace_snapshot("site", &staged, false)
+        .await
+        .unwrap();
+
+    let replaced = storage.load_manifest("site").await.unwrap().unwrap();
+    assert_eq!(replaced.record_count, 2);
+    assert_ne!(replaced.sha256, original.sha256);
+    assert!(matches!(
+        storage.check_manifest("site").await.unwrap(),
+        ManifestCheck::Matches(_)
+    ));
+    assert!(!staging.get_manifest_path("site").unwrap().exists());
+}
+
+#[tokio::test]
+async fn test_manifest_is_written_on_demand_when_missing() {
+    let (_temp_dir, storage) = storage();
+    storage
+        .save_snapshot(create_multi_request_snapshot("site"))
+        .await
+        .unwrap();
+    let saved = storage.load_manifest("site").await.unwrap().unwrap();
+    std::fs::remove_file(storage.get_manifest_path("site").unwrap()).unwrap();
+    assert_eq!(
+        storage.check_manifest("site").await.unwrap(),
+        ManifestCheck::Missing
+    );
+
+    let manifest = storage.manifest("site").await.unwrap();
+    assert_eq!(manifest.sha256, saved.sha256);
+    assert!(storage.get_manifest_path("site").unwrap().exists());
+}
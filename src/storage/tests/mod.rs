@@ -1,3 +1,12 @@
+pub mod artifacts_tests;
+pub mod encryption_tests;
+pub mod io_timeout_tests;
+pub mod manifest_tests;
+pub mod overlay_tests;
+pub mod paths_tests;
 pub mod performance_tests;
+pub mod records_tests;
+pub mod segments_tests;
 pub mod serialization_tests;
+pub mod split_tests;
 pub mod storage_tests;
@@ -0,0 +1,236 @@
+use crate::capture::proxy::{RequestRecord, ResponseRecord};
+use crate::error::WebMockError;
+use crate::storage::Snapshot;
+use crate::storage::Storage;
+use chrono::Utc;
+use std::collections::HashMap;
+use std::path::Path;
+use tempfile::TempDir;
+
+fn snapshot(name: &str, body: &[u8]) -> Snapshot {
+    Snapshot {
+        name: name.to_string(),
+        url: "https://example.com/".to_string(),
+        created_at: Utc::now(),
+        requests: vec![RequestRecord::new(
+            "GET".to_string(),
+            "https://example.com/".to_string(),
+            HashMap::new(),
+            None,
+            ResponseRecord::new(200, HashMap::new(), body.to_vec(), None),
+        )],
+        ..Default::default()
+    }
+}
+
+/// A read-only layer holding `names`, made read-only where the platform allows
+async fn read_only_layer(names: &[&str]) -> TempDir {
+    let dir = TempDir::new().unwrap();
+    let storage = Storage::new(dir.path().to_path_buf());
+    for name in names {
+        storage
+            .save_snapshot(snapshot(name, b"shipped"))
+            .await
+            .unwrap();
+    }
+    set_read_only(&dir.path().join("snapshots"), true);
+    dir
+}
+
+#[cfg(unix)]
+fn set_read_only(dir: &Path, read_only: bool) {
+    use std::os::unix::fs::PermissionsExt;
+    let mode = if read_only { 0o555 } else { 0o755 };
+    std::fs::set_permissions(dir, std::fs::Permissions::from_mode(mode)).unwrap();
+}
+
+#[cfg(not(unix))]
+fn set_read_only(dir: &Path, read_only: bool) {
+    let mut permissions = std::fs::metadata(dir).unwrap().permissions();
+    permissions.set_readonly(read_only);
+    std::fs::set_permissions(dir, permissions).unwrap();
+}
+
+fn overlay(writable: &TempDir, read_only: &TempDir) -> Storage {
+    Storage::new(writable.path().to_path_buf()).with_read_only_layer(read_only.path().to_path_buf())
+}
+
+fn body(snapshot: &Snapshot) -> &[u8] {
+    &snapshot.requests[0].response.body
+}
+
+#[tokio::test]
+async fn test_reads_fall_back_to_read_only_layer() {
+    let read_only = read_only_layer(&["shipped"]).await;
+    let writable = TempDir::new().unwrap();
+    let storage = overlay(&writable, &read_only);
+
+    assert!(storage.snapshot_exists("shipped").unwrap());
+    let loaded = storage.load_snapshot("shipped").await.unwrap();
+    assert_eq!(body(&loaded), b"shipped");
+    let info = storage.load_snapshot_metadata("shipped").await.unwrap();
+    assert_eq!(info.name, "shipped");
+    assert_eq!(
+        storage.resolve_snapshot_path("shipped").unwrap(),
+        read_only.path().join("snapshots").join("shipped.msgpack")
+    );
+
+    // Nothing was written to the writable layer
+    assert!(!writable.path().join("snapshots").exists());
+    set_read_only(&read_only.path().join("snapshots"), false);
+}
+
+#[tokio::test]
+async fn test_saves_go_to_writable_layer_and_shadow_read_only_copy() {
+    let read_only = read_only_layer(&["shipped"]).await;
+    let writable = TempDir::new().unwrap();
+    let storage = overlay(&writable, &read_only);
+
+    storage
+        .save_snapshot(snapshot("shipped", b"changed"))
+        .await
+        .unwrap();
+
+    let loaded = storage.load_snapshot("shipped").await.unwrap();
+    assert_eq!(body(&loaded), b"changed");
+    assert!(writable
+        .path()
+        .join("snapshots")
+        .join("shipped.msgpack")
+        .exists());
+
+    // The read-only copy is untouched
+    let read_only_storage = Storage::new(read_only.path().to_path_buf());
+    let original = read_only_storage.load_snapshot("shipped").await.unwrap();
+    assert_eq!(body(&original), b"shipped");
+    set_read_only(&read_only.path().join("snapshots"), false);
+}
+
+#[tokio::test]
+async fn test_list_merges_layers_with_writable_shadowing() {
+    let read_only = read_only_layer(&["shared", "shipped-only"]).await;
+    let writable = TempDir::new().unwrap();
+    let storage = overlay(&writable, &read_only);
+    storage
+        .save_snapshot(snapshot("shared", b"changed"))
+        .await
+        .unwrap();
+    storage
+        .save_snapshot(snapshot("local-only", b"local"))
+        .await
+        .unwrap();
+
+    let mut names: Vec<String> = storage
+        .list_snapshots()
+        .await
+        .unwrap()
+        .into_iter()
+        .map(|info| info.name)
+        .collect();
+    names.sort();
+    assert_eq!(names, vec!["local-only", "shared", "shipped-only"]);
+
+    let stats = storage.snapshot_file_stats().await.unwrap();
+    let shared = stats.iter().find(|stat| stat.name == "shared").unwrap();
+    assert!(shared.path.starts_with(writable.path()));
+    let shipped = stats
+        .iter()
+        .find(|stat| stat.name == "shipped-only")
+        .unwrap();
+    assert!(shipped.path.starts_with(read_only.path()));
+    assert_eq!(stats.len(), 3);
+    set_read_only(&read_only.path().join("snapshots"), false);
+}
+
+#[tokio::test]
+async fn test_delete_of_read_only_snapshot_leaves_tombstone() {
+    let read_only = read_only_layer(&["shipped"]).await;
+    let writable = TempDir::new().unwrap();
+    let storage = overlay(&writable, &read_only);
+
+    storage.delete_snapshot("shipped").await.unwrap();
+
+    assert!(storage.get_tombstone_path("shipped").unwrap().exists());
+    assert!(!storage.snapshot_exists("shipped").unwrap());
+    assert!(matches!(
+        storage.load_snapshot("shipped").await,
+        Err(WebMockError::SnapshotNotFound(_))
+    ));
+    assert!(storage.list_snapshots().await.unwrap().is_empty());
+    assert!(read_only
+        .path()
+        .join("snapshots")
+        .join("shipped.msgpack")
+        .exists());
+
+    // Deleting again finds nothing
+    assert!(matches!(
+        storage.delete_snapshot("shipped").await,
+        Err(WebMockError::SnapshotNotFound(_))
+    ));
+
+    // Saving under the name again clears the marker
+    storage
+        .save_snapshot(snapshot("shipped", b"recaptured"))
+        .await
+        .unwrap();
+    assert!(!storage.get_tombstone_path("shipped").unwrap().exists());
+    let loaded = storage.load_snapshot("shipped").await.unwrap();
+    assert_eq!(body(&loaded), b"recaptured");
+    set_read_only(&read_only.path().join("snapshots"), false);
+}
+
+#[tokio::test]
+async fn test_delete_of_shadowing_copy_hides_read_only_copy_too() {
+    let read_only = read_only_layer(&["shipped"]).await;
+    let writable = TempDir::new().unwrap();
+    let storage = overlay(&writable, &read_only);
+    storage
+        .save_snapshot(snapshot("shipped", b"changed"))
+        .await
+        .unwrap();
+
+    storage.delete_snapshot("shipped").await.unwrap();
+
+    // The read-only copy doesn't come back once the writable one is gone
+    assert!(!storage.snapshot_exists("shipped").unwrap());
+    assert!(storage.list_snapshots().await.unwrap().is_empty());
+    set_read_only(&read_only.path().join("snapshots"), false);
+}
+
+#[tokio::test]
+async fn test_replace_of_read_only_snapshot_keeps_backup_in_writable_layer() {
+    let read_only = read_only_layer(&["shipped"]).await;
+    let writable = TempDir::new().unwrap();
+    let storage = overlay(&writable, &read_only);
+
+    let staging = storage.staging();
+    let staged = staging.get_snapshot_path("shipped").unwrap();
+    staging
+        .save_snapshot(snapshot("shipped", b"replaced"))
+        .await
+        .unwrap();
+    let backup = storage
+        .replace_snapshot("shipped", &staged, true)
+        .await
+        .unwrap()
+        .expect("the read-only copy is backed up");
+
+    assert!(backup.starts_with(writable.path()));
+    let loaded = storage.load_snapshot("shipped").await.unwrap();
+    assert_eq!(body(&loaded), b"replaced");
+    set_read_only(&read_only.path().join("snapshots"), false);
+}
+
+#[tokio::test]
+async fn test_storage_without_read_only_layer_is_unchanged() {
+    let writable = TempDir::new().unwrap();
+    let storage = Storage::with_streaming_threshold(writable.path().to_path_buf(), usize::MAX);
+
+    assert!(storage.read_only_layer().is_none());
+    assert!(matches!(
+        storage.delete_snapshot("missing").await,
+        Err(WebMockError::SnapshotNotFound(_))
+    ));
+    assert!(!storage.get_tombstone_path("missing").unwrap().exists());
+}
@@ -0,0 +1,217 @@
+use crate::error::WebMockError;
+use crate::storage::paths::{
+    check_path_for, extended_length_form, normalize_separators, PathPlatform, MAX_FILE_NAME_LEN,
+    UNIX_MAX_PATH, WINDOWS_MAX_PATH,
+};
+use crate::storage::{snapshot_file_names, Storage, MAX_SNAPSHOT_NAME_LEN};
+use std::path::{Path, PathBuf};
+use tempfile::TempDir;
+
+/// A Windows path of exactly `len` characters under a drive root, in 50-character segments
+fn windows_path(prefix: &str, len: usize) -> PathBuf {
+    let root = format!(r"{}C:\", prefix);
+    let segments = len - root.len();
+    let tail: String = (0..segments)
+        .map(|i| {
+            if i % 51 == 50 && i != segments - 1 {
+                '\\'
+            } else {
+                'd'
+            }
+        })
+        .collect();
+    PathBuf::from(root + &tail)
+}
+
+fn assert_config_error(result: crate::error::Result<()>, expected: &str) {
+    match result {
+        Err(WebMockError::Config(message)) => assert!(
+            message.contains(expected),
+            "'{}' should contain '{}'",
+            message,
+            expected
+        ),
+        other => panic!("Expected a config error, got {:?}", other),
+    }
+}
+
+#[test]
+fn test_windows_paths_up_to_max_path_are_accepted() {
+    let path = windows_path("", WINDOWS_MAX_PATH);
+    assert_eq!(path.to_string_lossy().len(), WINDOWS_MAX_PATH);
+    assert!(check_path_for(&path, PathPlatform::Windows).is_ok());
+}
+
+#[test]
+fn test_windows_paths_past_max_path_are_rejected() {
+    let path = windows_path("", WINDOWS_MAX_PATH + 1);
+    assert_config_error(
+        check_path_for(&path, PathPlatform::Windows),
+        "Resulting path exceeds Windows MAX_PATH",
+    );
+    assert_config_error(
+        check_path_for(&path, PathPlatform::Windows),
+        "shorten the snapshot name or storage path",
+    );
+
+    // The same path is fine elsewhere
+    let unix_path = PathBuf::from(path.to_string_lossy().replace('\\', "/"));
+    assert!(check_path_for(&unix_path, PathPlatform::Unix).is_ok());
+}
+
+#[test]
+fn test_extended_length_windows_paths_bypass_max_path() {
+    let path = windows_path(r"\\?\", 1000);
+    assert!(check_path_for(&path, PathPlatform::Windows).is_ok());
+}
+
+#[test]
+fn test_unix_paths_past_path_max_are_rejected() {
+    let mut path = PathBuf::from("/");
+    while path.as_os_str().len() <= UNIX_MAX_PATH {
+        path.push("d".repeat(200));
+    }
+    assert_config_error(
+        check_path_for(&path, PathPlatform::Unix),
+        "shorten the snapshot name or storage path",
+    );
+
+    let path = PathBuf::from("/")
+        .join("d".repeat(200))
+        .join("snapshot.msgpack");
+    assert!(check_path_for(&path, PathPlatform::Unix).is_ok());
+}
+
+#[test]
+fn test_long_file_names_are_rejected() {
+    let path = PathBuf::from("/storage").join("n".repeat(MAX_FILE_NAME_LEN + 1));
+    assert_config_error(
+        check_path_for(&path, PathPlatform::Unix),
+        "shorten the snapshot name",
+    );
+    assert_config_error(
+        check_path_for(&path, PathPlatform::Windows),
+        "shorten the snapshot name",
+    );
+}
+
+#[test]
+fn test_derived_file_names_stay_within_limits() {
+    let name = "n".repeat(MAX_SNAPSHOT_NAME_LEN);
+    let names = snapshot_file_names(&name);
+    assert!(names.contains(&format!("{}.msgpack", name)));
+    assert!(names.contains(&format!("{}.prev.msgpack", name)));
+    assert!(names.contains(&format!("{}.screenshot.png", name)));
+    assert!(names.contains(&format!("{}.manifest.{}.{}.tmp", name, u32::MAX, u64::MAX)));
+    for file_name in names {
+        assert!(file_name.len() <= MAX_FILE_NAME_LEN, "{}", file_name);
+    }
+}
+
+#[test]
+fn test_storage_rejects_snapshot_paths_past_the_platform_limit() {
+    let temp_dir = TempDir::new().unwrap();
+    let limit = match PathPlatform::current() {
+        PathPlatform::Windows => WINDOWS_MAX_PATH,
+        PathPlatform::Unix => UNIX_MAX_PATH,
+    };
+    let mut deep = temp_dir.path().to_path_buf();
+    while deep.as_os_str().len() < limit {
+        deep.push("d".repeat(100));
+    }
+    let storage = Storage::new(deep);
+
+    if cfg!(windows) {
+        // The storage directory took the extended-length prefix instead
+        assert!(storage.get_snapshot_path("site").is_ok());
+    } else {
+        // The lookup fails up front instead of with an OS error on write
+        assert!(matches!(
+            storage.get_snapshot_path("site"),
+            Err(WebMockError::Config(_))
+        ));
+        assert!(matches!(
+            storage.get_backup_path("site"),
+            Err(WebMockError::Config(_))
+        ));
+    }
+}
+
+#[test]
+fn test_storage_accepts_the_longest_name_in_a_shallow_directory() {
+    let temp_dir = TempDir::new().unwrap();
+    let storage = Storage::new(temp_dir.path().to_path_buf());
+    let name = "n".repeat(MAX_SNAPSHOT_NAME_LEN);
+    assert!(storage.get_snapshot_path(&name).is_ok());
+}
+
+#[test]
+fn test_extended_length_form() {
+    assert_eq!(
+        extended_length_form(r"C:\Users\dev\.webmock").as_deref(),
+        Some(r"\\?\C:\Users\dev\.webmock")
+    );
+    assert_eq!(
+        extended_length_form(r"\\server\share\webmock").as_deref(),
+        Some(r"\\?\UNC\server\share\webmock")
+    );
+    assert_eq!(extended_length_form(r"\\?\C:\webmock"), None);
+    assert_eq!(extended_length_form(r"\\.\pipe\webmock"), None);
+    assert_eq!(extended_length_form(r"relative\webmock"), None);
+    assert_eq!(extended_length_form("/home/dev/.webmock"), None);
+}
+
+#[test]
+fn test_normalize_separators_drops_repeated_and_trailing_separators() {
+    assert_eq!(
+        normalize_separators("storage//webmock/"),
+        Path::new("storage").join("webmock")
+    );
+    assert_eq!(normalize_separators("/"), PathBuf::from("/"));
+}
+
+#[cfg(windows)]
+mod windows {
+    use super::*;
+
+    #[test]
+    fn test_normalize_separators_uses_backslashes() {
+        assert_eq!(
+            normalize_separators("C:/Users//dev/webmock/"),
+            PathBuf::from(r"C:\Users\dev\webmock")
+        );
+    }
+
+    #[test]
+    fn test_storage_directories_get_the_extended_length_prefix() {
+        let temp_dir = TempDir::new().unwrap();
+        let storage = Storage::new(temp_dir.path().to_path_buf());
+        let path = storage.get_snapshot_path("site").unwrap();
+        assert!(path.to_string_lossy().starts_with(r"\\?\"));
+    }
+
+    #[tokio::test]
+    async fn test_snapshots_past_max_path_are_saved() {
+        let temp_dir = TempDir::new().unwrap();
+        let mut deep = temp_dir.path().to_path_buf();
+        while deep.as_os_str().len() < WINDOWS_MAX_PATH {
+            deep.push("d".repeat(50));
+        }
+        let storage = Storage::new(deep);
+        let mut snapshot = crate::test_utils::test_helpers::create_test_snapshot();
+        snapshot.name = "n".repeat(MAX_SNAPSHOT_NAME_LEN);
+
+        storage.save_snapshot(snapshot.clone()).await.unwrap();
+        let loaded = storage.load_snapshot(&snapshot.name).await.unwrap();
+        assert_eq!(loaded.requests.len(), snapshot.requests.len());
+    }
+
+    #[test]
+    fn test_unprefixed_paths_past_max_path_are_rejected() {
+        let path = windows_path("", WINDOWS_MAX_PATH + 1);
+        assert_config_error(
+            crate::storage::paths::check_path(&path),
+            "Resulting path exceeds Windows MAX_PATH",
+        );
+    }
+}
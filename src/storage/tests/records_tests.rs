@@ -0,0 +1,82 @@
+use crate::error::WebMockError;
+use crate::storage::{PassphraseSource, SnapshotSerializer, Storage};
+use crate::test_utils::test_helpers::create_multi_request_snapshot;
+use tempfile::TempDir;
+
+#[test]
+fn test_skip_records_passes_over_records() {
+    let snapshot = create_multi_request_snapshot("skipping");
+    let serialized = SnapshotSerializer::serialize(&snapshot).unwrap();
+
+    let (_, mut records) =
+        SnapshotSerializer::read_records(std::io::Cursor::new(serialized)).unwrap();
+    let total = records.remaining();
+    records.skip_records(2).unwrap();
+    assert_eq!(records.remaining(), total - 2);
+    assert_eq!(
+        records.next().unwrap().unwrap().url,
+        snapshot.requests[2].url
+    );
+
+    // Skipping past the end stops at the end
+    records.skip_records(usize::MAX).unwrap();
+    assert_eq!(records.remaining(), 0);
+    assert!(records.next().is_none());
+}
+
+#[tokio::test]
+async fn test_open_records_reads_one_record_at_a_time() {
+    let temp_dir = TempDir::new().unwrap();
+    let storage = Storage::new(temp_dir.path().to_path_buf());
+    let snapshot = create_multi_request_snapshot("paged");
+    let expected = snapshot.requests.clone();
+    storage.save_snapshot(snapshot).await.unwrap();
+
+    let records = storage.open_records("paged").await.unwrap();
+    let (metadata, reader) = records.read().unwrap();
+    assert_eq!(metadata.name, "paged");
+    assert_eq!(reader.count(), expected.len());
+
+    // Each pass starts over, so records can be read in any order
+    let last = expected.len() - 1;
+    assert_eq!(
+        records.record(last).unwrap().unwrap().url,
+        expected[last].url
+    );
+    assert_eq!(
+        records.record(0).unwrap().unwrap().response.body,
+        expected[0].response.body
+    );
+    assert!(records.record(expected.len()).unwrap().is_none());
+}
+
+#[tokio::test]
+async fn test_open_records_decrypts_encrypted_snapshots() {
+    let temp_dir = TempDir::new().unwrap();
+    let storage = Storage::new(temp_dir.path().to_path_buf())
+        .with_passphrase(PassphraseSource::fixed("secret"));
+    let mut snapshot = create_multi_request_snapshot("sealed");
+    snapshot.encrypted = true;
+    let expected = snapshot.requests[1].url.clone();
+    storage.save_snapshot(snapshot).await.unwrap();
+
+    let records = storage.open_records("sealed").await.unwrap();
+    assert_eq!(records.record(1).unwrap().unwrap().url, expected);
+
+    let wrong = Storage::new(temp_dir.path().to_path_buf())
+        .with_passphrase(PassphraseSource::fixed("guess"));
+    assert!(matches!(
+        wrong.open_records("sealed").await,
+        Err(WebMockError::WrongPassphrase(name)) if name == "sealed"
+    ));
+}
+
+#[tokio::test]
+async fn test_open_records_of_missing_snapshot() {
+    let temp_dir = TempDir::new().unwrap();
+    let storage = Storage::new(temp_dir.path().to_path_buf());
+    assert!(matches!(
+        storage.open_records("missing").await,
+        Err(WebMockError::SnapshotNotFound(name)) if name == "missing"
+    ));
+}
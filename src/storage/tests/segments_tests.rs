@@ -0,0 +1,335 @@
+use std::collections::HashSet;
+use std::sync::Arc;
+
+use tempfile::TempDir;
+
+use crate::capture::proxy::records::RequestRecord;
+use crate::error::WebMockError;
+use crate::storage::{
+    AppendedSegments, DecodeLimits, ManifestCheck, PassphraseSource, SnapshotSerializer, Storage,
+};
+use crate::test_utils::test_helpers::{
+    create_large_test_snapshot, create_multi_request_snapshot, create_test_record,
+};
+
+/// Size of a frame's payload and of a trailer, as laid out on disk
+const FRAME_LEN: usize = 64 * 1024;
+const TRAILER_LEN: usize = 48;
+
+fn record(path: &str, body_size: usize) -> RequestRecord {
+    let url = format!("https://example.com/{}", path);
+    create_test_record("GET", &url, 200, &[], &vec![b'a'; body_size])
+}
+
+fn urls(records: &[RequestRecord]) -> Vec<&str> {
+    records.iter().map(|record| record.url.as_str()).collect()
+}
+
+async fn saved(temp_dir: &TempDir, name: &str) -> (Storage, usize) {
+    let storage = Storage::new(temp_dir.path().to_path_buf());
+    let snapshot = create_multi_request_snapshot(name);
+    let saved = snapshot.requests.len();
+    storage.save_snapshot(snapshot).await.unwrap();
+    (storage, saved)
+}
+
+#[tokio::test]
+async fn test_appended_records_follow_the_saved_ones() {
+    let temp_dir = TempDir::new().unwrap();
+    let (storage, saved) = saved(&temp_dir, "site").await;
+    let path = storage.get_snapshot_path("site").unwrap();
+    let file = std::fs::read(&path).unwrap();
+    let segments = AppendedSegments::read(&mut std::io::Cursor::new(&file)).unwrap();
+    assert!(segments.is_empty());
+    assert_eq!(segments.base_len(), file.len() as u64);
+
+    storage
+        .append_records("site", vec![record("a", 10), record("b", 10)])
+        .await
+        .unwrap();
+    storage
+        .append_records("site", vec![record("c", 10)])
+        .await
+        .unwrap();
+    storage.append_records("site", Vec::new()).await.unwrap();
+
+    let snapshot = storage.load_snapshot("site").await.unwrap();
+    assert_eq!(snapshot.requests.len(), saved + 3);
+    assert_eq!(
+        urls(&snapshot.requests[saved..]),
+        vec![
+            "https://example.com/a",
+            "https://example.com/b",
+            "https://example.com/c"
+        ]
+    );
+
+    // Read lazily, the appended records come after the saved ones too
+    let records = storage.open_records("site").await.unwrap();
+    let (metadata, reader) = records.read().unwrap();
+    assert_eq!(metadata.name, "site");
+    assert_eq!(reader.remaining(), saved + 3);
+    let read: Vec<RequestRecord> = reader.map(Result::unwrap).collect();
+    assert_eq!(urls(&read), urls(&snapshot.requests));
+    let last = records.record(saved + 2).unwrap().unwrap();
+    assert_eq!(last.url, "https://example.com/c");
+    assert!(records.record(saved + 3).unwrap().is_none());
+
+    // Listing still reads only the metadata
+    let listed = storage.list_snapshots().await.unwrap();
+    assert_eq!(listed.len(), 1);
+
+    let manifest = storage.load_manifest("site").await.unwrap().unwrap();
+    assert_eq!(manifest.record_count, saved + 3);
+    assert!(matches!(
+        storage.check_manifest("site").await.unwrap(),
+        ManifestCheck::Matches(_)
+    ));
+}
+
+#[tokio::test]
+async fn test_appended_records_stream_from_compressed_snapshots() {
+    let temp_dir = TempDir::new().unwrap();
+    let storage = Storage::with_streaming_threshold(temp_dir.path().to_path_buf(), 1024);
+    storage
+        .save_snapshot(create_large_test_snapshot("large", 20, 100_000))
+        .await
+        .unwrap();
+    let path = storage.get_snapshot_path("large").unwrap();
+    assert!(SnapshotSerializer::is_compressed(
+        &std::fs::read(&path).unwrap()
+    ));
+
+    // Larger than a frame, so the segment spans several
+    storage
+        .append_records(
+            "large",
+            vec![record("big", 3 * FRAME_LEN), record("small", 1)],
+        )
+        .await
+        .unwrap();
+
+    let snapshot = storage.load_snapshot("large").await.unwrap();
+    assert_eq!(snapshot.requests.len(), 22);
+    assert_eq!(
+        urls(&snapshot.requests[20..]),
+        vec!["https://example.com/big", "https://example.com/small"]
+    );
+    assert_eq!(snapshot.requests[20].response.body.len(), 3 * FRAME_LEN);
+
+    let in_memory = SnapshotSerializer::deserialize(&std::fs::read(&path).unwrap()).unwrap();
+    assert_eq!(urls(&in_memory.requests), urls(&snapshot.requests));
+    let file = std::fs::File::open(&path).unwrap();
+    let (_, reader) = SnapshotSerializer::read_records(file).unwrap();
+    assert_eq!(reader.count(), 22);
+}
+
+#[tokio::test]
+async fn test_torn_appends_are_ignored_and_cut_off() {
+    let temp_dir = TempDir::new().unwrap();
+    let (storage, saved) = saved(&temp_dir, "site").await;
+    let path = storage.get_snapshot_path("site").unwrap();
+    let before = std::fs::read(&path).unwrap();
+
+    storage
+        .append_records("site", vec![record("first", 10)])
+        .await
+        .unwrap();
+    let committed = std::fs::read(&path).unwrap();
+    storage
+        .append_records("site", vec![record("second", 2 * FRAME_LEN)])
+        .await
+        .unwrap();
+    let appended = std::fs::read(&path).unwrap();
+
+    // A crash could have stopped the second append after any byte
+    let start = committed.len();
+    let cuts = [
+        start + 1,
+        start + TRAILER_LEN,
+        start + FRAME_LEN,
+        // Right after the trailer of the segment's first frame
+        start + FRAME_LEN + TRAILER_LEN,
+        start + 2 * (FRAME_LEN + TRAILER_LEN) + 1,
+        appended.len() - TRAILER_LEN,
+        appended.len() - 1,
+    ];
+    for cut in cuts {
+        std::fs::write(&path, &appended[..cut]).unwrap();
+        let snapshot = storage.load_snapshot("site").await.unwrap();
+        assert_eq!(snapshot.requests.len(), saved + 1, "torn at {}", cut);
+        assert_eq!(
+            snapshot.requests[saved].url, "https://example.com/first",
+            "torn at {}",
+            cut
+        );
+        let (_, reader) = storage.open_records("site").await.unwrap().read().unwrap();
+        assert_eq!(reader.remaining(), saved + 1, "torn at {}", cut);
+    }
+
+    // Torn during the first append, the snapshot reads as saved
+    for cut in [before.len() + 1, committed.len() - 1] {
+        std::fs::write(&path, &committed[..cut]).unwrap();
+        let snapshot = storage.load_snapshot("site").await.unwrap();
+        assert_eq!(snapshot.requests.len(), saved, "torn at {}", cut);
+    }
+
+    // The next append cuts off what the torn one left behind
+    std::fs::write(&path, &appended[..start + FRAME_LEN]).unwrap();
+    storage
+        .append_records("site", vec![record("third", 10)])
+        .await
+        .unwrap();
+    let snapshot = storage.load_snapshot("site").await.unwrap();
+    assert_eq!(
+        urls(&snapshot.requests[saved..]),
+        vec!["https://example.com/first", "https://example.com/third"]
+    );
+    assert!(std::fs::metadata(&path).unwrap().len() < (start + FRAME_LEN) as u64);
+}
+
+#[tokio::test]
+async fn test_segments_failing_their_checksum_are_ignored() {
+    let temp_dir = TempDir::new().unwrap();
+    let (storage, saved) = saved(&temp_dir, "site").await;
+    let path = storage.get_snapshot_path("site").unwrap();
+    storage
+        .append_records("site", vec![record("first", 10)])
+        .await
+        .unwrap();
+    let committed = std::fs::read(&path).unwrap().len();
+    storage
+        .append_records("site", vec![record("second", 100), record("third", 100)])
+        .await
+        .unwrap();
+
+    // A byte of the second segment, changed so its records still decode
+    let mut data = std::fs::read(&path).unwrap();
+    let body = committed + data[committed..].iter().position(|&b| b == b'a').unwrap();
+    data[body] = b'b';
+    std::fs::write(&path, &data).unwrap();
+
+    let snapshot = storage.load_snapshot("site").await.unwrap();
+    assert_eq!(
+        urls(&snapshot.requests[saved..]),
+        vec!["https://example.com/first"]
+    );
+    let in_memory = SnapshotSerializer::deserialize(&data).unwrap();
+    assert_eq!(in_memory.requests.len(), saved + 1);
+}
+
+#[tokio::test]
+async fn test_concurrent_appends_are_serialized() {
+    let temp_dir = TempDir::new().unwrap();
+    let (storage, saved) = saved(&temp_dir, "site").await;
+    let storage = Arc::new(storage);
+
+    let appends: Vec<_> = (0..8)
+        .map(|task| {
+            let storage = Arc::clone(&storage);
+            tokio::spawn(async move {
+                let records = (0..5)
+                    .map(|i| record(&format!("{}-{}", task, i), 1000))
+                    .collect();
+                storage.append_records("site", records).await
+            })
+        })
+        .collect();
+    for append in appends {
+        append.await.unwrap().unwrap();
+    }
+
+    let snapshot = storage.load_snapshot("site").await.unwrap();
+    assert_eq!(snapshot.requests.len(), saved + 40);
+    let appended = urls(&snapshot.requests[saved..]);
+    assert_eq!(appended.iter().collect::<HashSet<_>>().len(), 40);
+    // Each append's records stay together, in order
+    for batch in appended.chunks(5) {
+        let task = batch[0].trim_end_matches("-0");
+        for (i, url) in batch.iter().enumerate() {
+            assert_eq!(*url, format!("{}-{}", task, i));
+        }
+    }
+
+    let manifest = storage.load_manifest("site").await.unwrap().unwrap();
+    assert_eq!(manifest.record_count, saved + 40);
+    assert!(matches!(
+        storage.check_manifest("site").await.unwrap(),
+        ManifestCheck::Matches(_)
+    ));
+}
+
+#[tokio::test]
+async fn test_appended_records_are_numbered_after_the_snapshot() {
+    let temp_dir = TempDir::new().unwrap();
+    let (storage, saved) = saved(&temp_dir, "site").await;
+
+    // Numbered by a capture of their own, clashing with the saved records
+    let numbered = |path| record(path, 10).with_sequence(0);
+    storage
+        .append_records("site", vec![numbered("a"), numbered("b")])
+        .await
+        .unwrap();
+    storage
+        .append_records("site", vec![numbered("c")])
+        .await
+        .unwrap();
+
+    let snapshot = storage.load_snapshot("site").await.unwrap();
+    let sequences: Vec<u64> = snapshot
+        .requests
+        .iter()
+        .map(|record| record.sequence.unwrap())
+        .collect();
+    assert_eq!(sequences.iter().collect::<HashSet<_>>().len(), saved + 3);
+    let last_saved = sequences[..saved].iter().max().unwrap();
+    assert!(sequences[saved..]
+        .iter()
+        .all(|sequence| sequence > last_saved));
+    assert!(sequences[saved..].windows(2).all(|pair| pair[0] < pair[1]));
+}
+
+#[tokio::test]
+async fn test_appended_records_are_decoded_within_the_limits() {
+    let temp_dir = TempDir::new().unwrap();
+    let (storage, _) = saved(&temp_dir, "site").await;
+    storage
+        .append_records("site", vec![record("large", 2 * FRAME_LEN)])
+        .await
+        .unwrap();
+    let file = std::fs::read(storage.get_snapshot_path("site").unwrap()).unwrap();
+
+    let limits = DecodeLimits {
+        max_decoded_size: FRAME_LEN as u64,
+        ..Default::default()
+    };
+    let error = SnapshotSerializer::deserialize_with_limits(&file, &limits).unwrap_err();
+    assert!(error.to_string().contains("WEBMOCK_MAX_SNAPSHOT_SIZE"));
+    assert!(SnapshotSerializer::deserialize_with_limits(&file, &DecodeLimits::default()).is_ok());
+}
+
+#[tokio::test]
+async fn test_append_needs_a_plain_snapshot() {
+    let temp_dir = TempDir::new().unwrap();
+    let storage = Storage::new(temp_dir.path().to_path_buf())
+        .with_passphrase(PassphraseSource::fixed("secret"));
+
+    let missing = storage
+        .append_records("missing", vec![record("a", 10)])
+        .await
+        .unwrap_err();
+    assert!(matches!(missing, WebMockError::SnapshotNotFound(_)));
+
+    let mut snapshot = create_multi_request_snapshot("sealed");
+    snapshot.encrypted = true;
+    storage.save_snapshot(snapshot).await.unwrap();
+    let path = storage.get_snapshot_path("sealed").unwrap();
+    let sealed = std::fs::read(&path).unwrap();
+    let error = storage
+        .append_records("sealed", vec![record("a", 10)])
+        .await
+        .unwrap_err()
+        .to_string();
+    assert!(error.contains("encrypted snapshot 'sealed'"), "{}", error);
+    assert_eq!(std::fs::read(&path).unwrap(), sealed);
+}
@@ -1,6 +1,7 @@
+use crate::capture::browser::{PageEvent, PageEventLevel, PageEventSource};
 use crate::capture::proxy::{RequestRecord, ResponseRecord};
-use crate::storage::{Snapshot, SnapshotData, SnapshotSerializer};
-use chrono::Utc;
+use crate::storage::{DecodeLimits, Snapshot, SnapshotData, SnapshotSerializer};
+use chrono::{DateTime, Duration, TimeZone, Utc};
 use std::collections::HashMap;
 
 fn create_test_snapshot() -> Snapshot {
@@ -18,9 +19,21 @@ fn create_test_snapshot() -> Snapshot {
                 headers: HashMap::new(),
                 body: b"<html><body>Test</body></html>".to_vec(),
                 content_type: "text/html".to_string(),
+                failure: None,
+                set_cookie: Vec::new(),
+                http_version: None,
+                reason: None,
+                informational: Vec::new(),
             },
             timestamp: Utc::now(),
+            initiator: None,
+            timing: Default::default(),
+            vary_key: Default::default(),
+            variant: None,
+            sequence: None,
+            frame_id: None,
         }],
+        ..Default::default()
     }
 }
 
@@ -68,7 +81,7 @@ fn test_deserialize_metadata_only() {
     let snapshot = create_test_snapshot();
 
     let serialized = SnapshotSerializer::serialize(&snapshot).unwrap();
-    let metadata = SnapshotSerializer::deserialize_metadata(&serialized).unwrap();
+    let metadata = SnapshotSerializer::deserialize_metadata(serialized.as_slice()).unwrap();
 
     assert_eq!(metadata.name, snapshot.name);
     assert_eq!(metadata.url, snapshot.url);
@@ -107,8 +120,19 @@ fn test_serialize_multiple_requests() {
             headers: HashMap::new(),
             body: b"{\"id\": 123}".to_vec(),
             content_type: "application/json".to_string(),
+            failure: None,
+            set_cookie: Vec::new(),
+            http_version: None,
+            reason: None,
+            informational: Vec::new(),
         },
         timestamp: Utc::now(),
+        initiator: None,
+        timing: Default::default(),
+        vary_key: Default::default(),
+        variant: None,
+        sequence: None,
+        frame_id: None,
     });
 
     let serialized = SnapshotSerializer::serialize(&snapshot).unwrap();
@@ -139,6 +163,616 @@ fn test_deserialize_invalid_data() {
 #[test]
 fn test_deserialize_metadata_invalid_data() {
     let invalid_data = b"invalid msgpack data";
-    let result = SnapshotSerializer::deserialize_metadata(invalid_data);
+    let result = SnapshotSerializer::deserialize_metadata(&invalid_data[..]);
     assert!(result.is_err());
 }
+
+#[test]
+fn test_serialize_preserves_capture_environment() {
+    use crate::capture::CaptureEnvironment;
+
+    let mut snapshot = create_test_snapshot();
+    snapshot.environment =
+        CaptureEnvironment::from_args(Some("fr-FR"), Some("Europe/Paris"), Some("1440x900"))
+            .unwrap()
+            .with_device(Some("iphone-13"), None)
+            .unwrap();
+
+    let serialized = SnapshotSerializer::serialize(&snapshot).unwrap();
+    let deserialized = SnapshotSerializer::deserialize(&serialized).unwrap();
+    assert_eq!(deserialized.environment, snapshot.environment);
+
+    let metadata = SnapshotSerializer::deserialize_metadata(serialized.as_slice()).unwrap();
+    assert_eq!(metadata.environment, snapshot.environment);
+}
+
+#[test]
+fn test_deserialize_snapshot_without_environment() {
+    #[derive(serde::Serialize)]
+    struct LegacyMetadata {
+        name: String,
+        url: String,
+        created_at: chrono::DateTime<Utc>,
+        version: String,
+    }
+
+    #[derive(serde::Serialize)]
+    struct LegacyData {
+        metadata: LegacyMetadata,
+        requests: Vec<RequestRecord>,
+    }
+
+    let snapshot = create_test_snapshot();
+    let legacy = rmp_serde::to_vec(&LegacyData {
+        metadata: LegacyMetadata {
+            name: snapshot.name.clone(),
+            url: snapshot.url.clone(),
+            created_at: snapshot.created_at,
+            version: "0.1.0".to_string(),
+        },
+        requests: snapshot.requests.clone(),
+    })
+    .unwrap();
+
+    let deserialized = SnapshotSerializer::deserialize(&legacy).unwrap();
+    assert_eq!(deserialized.name, snapshot.name);
+    assert!(deserialized.environment.is_default());
+    assert!(deserialized.tunnels.is_empty());
+    assert_eq!(deserialized.skipped_requests, 0);
+    assert!(deserialized.page_events.is_empty());
+    assert!(deserialized.annotations.is_empty());
+    assert!(deserialized.capture_diagnostics.is_empty());
+    assert!(deserialized.provenance.is_none());
+}
+
+#[test]
+fn test_deserialize_environment_without_device() {
+    use crate::capture::CaptureEnvironment;
+
+    // Environment as written before the user agent and device were recorded
+    #[derive(serde::Serialize)]
+    struct LegacyEnvironment {
+        locale: Option<String>,
+        timezone: Option<String>,
+        viewport: Option<crate::capture::Viewport>,
+    }
+
+    #[derive(serde::Serialize)]
+    struct LegacyMetadata {
+        name: String,
+        url: String,
+        created_at: chrono::DateTime<Utc>,
+        version: String,
+        environment: LegacyEnvironment,
+    }
+
+    #[derive(serde::Serialize)]
+    struct LegacyData {
+        metadata: LegacyMetadata,
+        requests: Vec<RequestRecord>,
+    }
+
+    let snapshot = create_test_snapshot();
+    let legacy = rmp_serde::to_vec(&LegacyData {
+        metadata: LegacyMetadata {
+            name: snapshot.name.clone(),
+            url: snapshot.url.clone(),
+            created_at: snapshot.created_at,
+            version: "0.1.0".to_string(),
+            environment: LegacyEnvironment {
+                locale: Some("fr-FR".to_string()),
+                timezone: None,
+                viewport: None,
+            },
+        },
+        requests: snapshot.requests.clone(),
+    })
+    .unwrap();
+
+    let deserialized = SnapshotSerializer::deserialize(&legacy).unwrap();
+    assert_eq!(
+        deserialized.environment,
+        CaptureEnvironment::from_args(Some("fr-FR"), None, None).unwrap()
+    );
+}
+
+#[test]
+fn test_serialize_preserves_tls_details() {
+    use crate::capture::proxy::TlsDetails;
+
+    let mut snapshot = create_test_snapshot();
+    snapshot.tls.insert(
+        "example.com:443".to_string(),
+        TlsDetails {
+            protocol_version: Some("TLSv1.3".to_string()),
+            cipher_suite: Some("TLS13_AES_128_GCM_SHA256".to_string()),
+            alpn: None,
+        },
+    );
+
+    let serialized = SnapshotSerializer::serialize(&snapshot).unwrap();
+    let deserialized = SnapshotSerializer::deserialize(&serialized).unwrap();
+    assert_eq!(deserialized.tls, snapshot.tls);
+
+    let metadata = SnapshotSerializer::deserialize_metadata(serialized.as_slice()).unwrap();
+    assert_eq!(metadata.tls, snapshot.tls);
+}
+
+#[test]
+fn test_serialize_preserves_tunnels() {
+    use crate::capture::proxy::TunnelRecord;
+
+    let mut snapshot = create_test_snapshot();
+    snapshot.tunnels.push(TunnelRecord {
+        host: "pinned.example.com:443".to_string(),
+        bytes_up: 512,
+        bytes_down: 4096,
+        mitm_failed: true,
+    });
+
+    let serialized = SnapshotSerializer::serialize(&snapshot).unwrap();
+    let deserialized = SnapshotSerializer::deserialize(&serialized).unwrap();
+    assert_eq!(deserialized.tunnels, snapshot.tunnels);
+
+    let metadata = SnapshotSerializer::deserialize_metadata(serialized.as_slice()).unwrap();
+    assert_eq!(metadata.tunnels, snapshot.tunnels);
+}
+
+#[test]
+fn test_serialize_preserves_request_counts() {
+    let snapshot = Snapshot {
+        skipped_requests: 1_234,
+        retried_requests: 7,
+        ..create_test_snapshot()
+    };
+
+    let serialized = SnapshotSerializer::serialize(&snapshot).unwrap();
+    let deserialized = SnapshotSerializer::deserialize(&serialized).unwrap();
+    assert_eq!(deserialized.skipped_requests, 1_234);
+    assert_eq!(deserialized.retried_requests, 7);
+
+    let mut streamed = Vec::new();
+    SnapshotSerializer::serialize_streaming(&snapshot, &mut streamed).unwrap();
+    let deserialized = SnapshotSerializer::deserialize_streaming(streamed.as_slice()).unwrap();
+    assert_eq!(deserialized.skipped_requests, 1_234);
+    assert_eq!(deserialized.retried_requests, 7);
+}
+
+#[test]
+fn test_serialize_preserves_page_events() {
+    let event = PageEvent {
+        timestamp: Utc::now(),
+        level: PageEventLevel::Error,
+        source: PageEventSource::Exception,
+        message: "Uncaught ReferenceError: dataLayer is not defined".to_string(),
+        location: Some("https://example.com/app.js:12".to_string()),
+    };
+    let snapshot = Snapshot {
+        page_events: vec![event.clone()],
+        ..create_test_snapshot()
+    };
+
+    let serialized = SnapshotSerializer::serialize(&snapshot).unwrap();
+    let deserialized = SnapshotSerializer::deserialize(&serialized).unwrap();
+    assert_eq!(deserialized.page_events, vec![event.clone()]);
+
+    let metadata = SnapshotSerializer::deserialize_metadata(serialized.as_slice()).unwrap();
+    assert_eq!(metadata.page_events, vec![event]);
+}
+
+/// A capture of the same fixed site, with the run-to-run noise of a real one
+fn capture_run(started: DateTime<Utc>, jitter_ms: u64, reversed: bool) -> Snapshot {
+    let mut header_names: Vec<String> = (0..16).map(|i| format!("x-header-{}", i)).collect();
+    if reversed {
+        header_names.reverse();
+    }
+    let record = |url: &str, offset_ms: u64| {
+        let mut headers = HashMap::new();
+        for name in &header_names {
+            headers.insert(name.clone(), format!("value of {}", name));
+        }
+        let mut record = RequestRecord::new(
+            "GET".to_string(),
+            url.to_string(),
+            headers.clone(),
+            None,
+            ResponseRecord::new(200, headers, b"<html></html>".to_vec(), Some(url)),
+        )
+        .with_timing(offset_ms + jitter_ms, offset_ms + jitter_ms + 120);
+        record.timestamp = started + Duration::milliseconds((offset_ms + jitter_ms) as i64);
+        record
+    };
+    let mut requests = vec![
+        record("https://example.com/", 0),
+        record("https://example.com/app.js", 2_000),
+    ];
+    if reversed {
+        requests.reverse();
+    }
+
+    Snapshot {
+        created_at: started + Duration::milliseconds(5_000 + jitter_ms as i64),
+        requests,
+        ..create_test_snapshot()
+    }
+}
+
+#[test]
+fn test_deterministic_snapshots_serialize_identically() {
+    let started = Utc.with_ymd_and_hms(2026, 10, 1, 12, 0, 0).unwrap();
+    let mut first = capture_run(started, 0, false);
+    let mut second = capture_run(started, 137, true);
+
+    SnapshotSerializer::make_deterministic(&mut first);
+    SnapshotSerializer::make_deterministic(&mut second);
+    assert_eq!(
+        SnapshotSerializer::serialize(&first).unwrap(),
+        SnapshotSerializer::serialize(&second).unwrap()
+    );
+
+    // Records keep the order they arrived in
+    let urls: Vec<&str> = second.requests.iter().map(|r| r.url.as_str()).collect();
+    assert_eq!(urls, ["https://example.com/", "https://example.com/app.js"]);
+    assert_eq!(second.requests[1].timing.offset_ms, Some(2_000));
+}
+
+#[test]
+fn test_normal_serialization_keeps_full_precision() {
+    let started = Utc.with_ymd_and_hms(2026, 10, 1, 12, 0, 0).unwrap();
+    let snapshot = capture_run(started, 137, true);
+
+    let deserialized =
+        SnapshotSerializer::deserialize(&SnapshotSerializer::serialize(&snapshot).unwrap())
+            .unwrap();
+    assert_eq!(deserialized.created_at, snapshot.created_at);
+    assert_eq!(deserialized.requests[0].url, "https://example.com/app.js");
+    assert_eq!(deserialized.requests[0].timing.offset_ms, Some(2_137));
+    assert_eq!(
+        deserialized.requests[0].timestamp,
+        started + Duration::milliseconds(2_137)
+    );
+    assert_eq!(deserialized.requests[0].headers.len(), 16);
+}
+
+fn assert_limit_error(result: crate::error::Result<Snapshot>, limit: &str) {
+    match result {
+        Err(crate::error::WebMockError::InvalidSnapshot(message)) => {
+            assert!(message.contains(limit), "unexpected message: {}", message)
+        }
+        other => panic!("expected an invalid snapshot error, got {:?}", other.err()),
+    }
+}
+
+fn decode_both_ways(data: &[u8], limits: &DecodeLimits) -> [crate::error::Result<Snapshot>; 2] {
+    [
+        SnapshotSerializer::deserialize_with_limits(data, limits),
+        SnapshotSerializer::deserialize_streaming_with_limits(data, limits),
+    ]
+}
+
+#[test]
+fn test_deserialize_rejects_huge_length_prefixes() {
+    let mut snapshot = create_test_snapshot();
+    snapshot.requests.clear();
+    let data = SnapshotSerializer::serialize(&snapshot).unwrap();
+    // The records array is the last byte of a snapshot without records
+    assert_eq!(data.last(), Some(&0x90));
+
+    // A record array, a string and a binary claiming 4GB, followed by a few bytes
+    for prefix in [
+        &[0xdd, 0xff, 0xff, 0xff, 0xff][..],
+        &[0x91, 0x95, 0xdb, 0xff, 0xff, 0xff, 0xff],
+        &[0x91, 0x95, 0xc6, 0xff, 0xff, 0xff, 0xff],
+    ] {
+        let mut crafted = data[..data.len() - 1].to_vec();
+        crafted.extend_from_slice(prefix);
+        crafted.extend_from_slice(b"GET");
+        for result in decode_both_ways(&crafted, &DecodeLimits::default()) {
+            assert!(result.is_err());
+        }
+    }
+}
+
+#[test]
+fn test_deserialize_rejects_deeply_nested_data() {
+    let mut crafted = vec![0x91; 100_000];
+    crafted.push(0xc0);
+    for result in decode_both_ways(&crafted, &DecodeLimits::default()) {
+        assert!(result.is_err());
+    }
+
+    let mut crafted = vec![0x81, 0xa1, b'k'];
+    crafted.extend(std::iter::repeat_n(0x91, 100_000));
+    crafted.push(0xc0);
+    for result in decode_both_ways(&crafted, &DecodeLimits::default()) {
+        assert!(result.is_err());
+    }
+}
+
+#[test]
+fn test_deserialize_stops_decompressing_at_decoded_size_limit() {
+    let mut snapshot = create_test_snapshot();
+    snapshot.requests[0].response.body = vec![0; 2 * 1024 * 1024];
+    let data = SnapshotSerializer::serialize(&snapshot).unwrap();
+    assert!(SnapshotSerializer::is_compressed(&data));
+    assert!(data.len() < 64 * 1024);
+
+    let limits = DecodeLimits {
+        max_decoded_size: 64 * 1024,
+        ..Default::default()
+    };
+    for result in decode_both_ways(&data, &limits) {
+        assert_limit_error(result, "decoded size limit exceeded");
+    }
+
+    // Uncompressed data is checked against the same limit
+    let limits = DecodeLimits {
+        max_decoded_size: 16,
+        ..Default::default()
+    };
+    let data = SnapshotSerializer::serialize(&create_test_snapshot()).unwrap();
+    for result in decode_both_ways(&data, &limits) {
+        assert_limit_error(result, "WEBMOCK_MAX_SNAPSHOT_SIZE");
+    }
+}
+
+#[test]
+fn test_deserialize_enforces_record_limits() {
+    let mut snapshot = create_test_snapshot();
+    let mut record = snapshot.requests[0].clone();
+    record.url = "https://example.com/big".to_string();
+    record.response.body = vec![b'x'; 4096];
+    for i in 0..20 {
+        record
+            .headers
+            .insert(format!("x-header-{}", i), "1".to_string());
+    }
+    snapshot.requests.push(record);
+    let data = SnapshotSerializer::serialize(&snapshot).unwrap();
+
+    let limits = DecodeLimits {
+        max_records: 1,
+        ..Default::default()
+    };
+    for result in decode_both_ways(&data, &limits) {
+        assert_limit_error(result, "record count limit exceeded: 2 is more than 1");
+    }
+
+    let limits = DecodeLimits {
+        max_headers: 10,
+        ..Default::default()
+    };
+    for result in decode_both_ways(&data, &limits) {
+        assert_limit_error(result, "header count of GET https://example.com/big");
+    }
+
+    let limits = DecodeLimits {
+        max_body_size: 1024,
+        ..Default::default()
+    };
+    for result in decode_both_ways(&data, &limits) {
+        assert_limit_error(result, "WEBMOCK_MAX_BODY_SIZE");
+    }
+
+    let limits = DecodeLimits {
+        max_records: 2,
+        max_headers: 20,
+        max_body_size: 4096,
+        ..Default::default()
+    };
+    for result in decode_both_ways(&data, &limits) {
+        assert_eq!(result.unwrap().requests.len(), 2);
+    }
+}
+
+#[test]
+fn test_serialize_preserves_capture_metrics() {
+    use crate::capture::metrics::PerformanceMetrics;
+
+    // Snapshots saved before metrics were collected load without them
+    let serialized = SnapshotSerializer::serialize(&create_test_snapshot()).unwrap();
+    assert!(SnapshotSerializer::deserialize(&serialized)
+        .unwrap()
+        .metrics
+        .is_none());
+
+    let metrics = PerformanceMetrics {
+        requests_processed: 12,
+        total_data_transferred: 40_960,
+        capture_duration_ms: 3_500,
+        browser_wait_ms: 2_100,
+        proxy_forward_ms: 900,
+        requests_per_second: 12.0 / 3.5,
+        ..PerformanceMetrics::default()
+    };
+    let mut snapshot = Snapshot {
+        metrics: Some(metrics),
+        ..create_test_snapshot()
+    };
+    let serialized = SnapshotSerializer::serialize(&snapshot).unwrap();
+    let deserialized = SnapshotSerializer::deserialize(&serialized).unwrap();
+    let restored = deserialized.metrics.unwrap();
+    assert_eq!(restored.requests_processed, 12);
+    assert_eq!(restored.total_data_transferred, 40_960);
+    assert_eq!(restored.capture_duration_ms, 3_500);
+    assert_eq!(restored.browser_wait_ms, 2_100);
+    assert_eq!(restored.proxy_forward_ms, 900);
+    let metadata = SnapshotSerializer::deserialize_metadata(serialized.as_slice()).unwrap();
+    assert_eq!(metadata.metrics.unwrap().capture_duration_ms, 3_500);
+
+    // Timings differ on every run, so deterministic snapshots leave them out
+    SnapshotSerializer::make_deterministic(&mut snapshot);
+    assert!(snapshot.metrics.is_none());
+}
+
+#[test]
+fn test_serialize_preserves_blocked_hosts() {
+    let snapshot = Snapshot {
+        blocked_hosts: [("ads.example.net".to_string(), 14)].into_iter().collect(),
+        ..create_test_snapshot()
+    };
+
+    let serialized = SnapshotSerializer::serialize(&snapshot).unwrap();
+    let deserialized = SnapshotSerializer::deserialize(&serialized).unwrap();
+    assert_eq!(deserialized.blocked_hosts, snapshot.blocked_hosts);
+    let metadata = SnapshotSerializer::deserialize_metadata(serialized.as_slice()).unwrap();
+    assert_eq!(metadata.blocked_hosts.get("ads.example.net"), Some(&14));
+}
+
+#[test]
+fn test_serialize_preserves_entry_points() {
+    let snapshot = Snapshot {
+        entry_points: vec![
+            "https://example.com".to_string(),
+            "https://www.example.com/".to_string(),
+        ],
+        ..create_test_snapshot()
+    };
+
+    let serialized = SnapshotSerializer::serialize(&snapshot).unwrap();
+    let deserialized = SnapshotSerializer::deserialize(&serialized).unwrap();
+    assert_eq!(deserialized.entry_points, snapshot.entry_points);
+    let metadata = SnapshotSerializer::deserialize_metadata(serialized.as_slice()).unwrap();
+    assert_eq!(metadata.entry_points, snapshot.entry_points);
+}
+
+#[test]
+fn test_serialize_preserves_annotations() {
+    let snapshot = Snapshot {
+        annotations: [(3, "stale, re-capture after the v2 rollout".to_string())]
+            .into_iter()
+            .collect(),
+        ..create_test_snapshot()
+    };
+
+    let serialized = SnapshotSerializer::serialize(&snapshot).unwrap();
+    let deserialized = SnapshotSerializer::deserialize(&serialized).unwrap();
+    assert_eq!(deserialized.annotations, snapshot.annotations);
+    let metadata = SnapshotSerializer::deserialize_metadata(serialized.as_slice()).unwrap();
+    assert_eq!(
+        metadata.annotations.get(&3).map(String::as_str),
+        Some("stale, re-capture after the v2 rollout")
+    );
+}
+
+#[test]
+fn test_serialize_preserves_capture_diagnostics() {
+    use crate::capture::proxy::{CaptureDiagnostics, DiagnosticEvent, DiagnosticKind};
+
+    let snapshot = Snapshot {
+        capture_diagnostics: CaptureDiagnostics {
+            accepted_connections: 12,
+            tls_handshake_failures: [("pinned.example.com".to_string(), 2)]
+                .into_iter()
+                .collect(),
+            upstream_connect_failures: [("down.example.com".to_string(), 1)].into_iter().collect(),
+            unrecorded_requests: 1,
+            recent_errors: vec![DiagnosticEvent {
+                offset_ms: 1500,
+                kind: DiagnosticKind::UpstreamConnect,
+                target: "down.example.com".to_string(),
+                error: "connection refused: Connection refused (os error 111)".to_string(),
+            }],
+        },
+        ..create_test_snapshot()
+    };
+
+    let serialized = SnapshotSerializer::serialize(&snapshot).unwrap();
+    let deserialized = SnapshotSerializer::deserialize(&serialized).unwrap();
+    assert_eq!(
+        deserialized.capture_diagnostics,
+        snapshot.capture_diagnostics
+    );
+    let metadata = SnapshotSerializer::deserialize_metadata(serialized.as_slice()).unwrap();
+    assert_eq!(metadata.capture_diagnostics, snapshot.capture_diagnostics);
+}
+
+/// Counts the bytes read through it
+struct CountingReader<R> {
+    inner: R,
+    count: std::rc::Rc<std::cell::Cell<usize>>,
+}
+
+impl<R: std::io::Read> std::io::Read for CountingReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let read = self.inner.read(buf)?;
+        self.count.set(self.count.get() + read);
+        Ok(read)
+    }
+}
+
+fn bytes_read_for_metadata(data: &[u8]) -> usize {
+    let count = std::rc::Rc::new(std::cell::Cell::new(0));
+    let reader = CountingReader {
+        inner: data,
+        count: count.clone(),
+    };
+    let metadata = SnapshotSerializer::deserialize_metadata(reader).unwrap();
+    assert_eq!(metadata.name, "large");
+    count.get()
+}
+
+#[test]
+fn test_deserialize_metadata_reads_only_the_start_of_large_snapshots() {
+    use std::io::Read;
+
+    // Bodies that don't compress, so the compressed file is large too
+    let mut state = 0x2545_f491_4f6c_dd1du64;
+    let mut noise = |len: usize| -> Vec<u8> {
+        (0..len)
+            .map(|_| {
+                state ^= state << 13;
+                state ^= state >> 7;
+                state ^= state << 17;
+                state as u8
+            })
+            .collect()
+    };
+    let template = create_test_snapshot().requests[0].clone();
+    let mut snapshot = create_test_snapshot();
+    snapshot.name = "large".to_string();
+    snapshot.requests = (0..400)
+        .map(|i| {
+            let mut record = template.clone();
+            record.url = format!("https://example.com/asset/{}", i);
+            record.response.body = noise(16 * 1024);
+            record
+        })
+        .collect();
+
+    let compressed = SnapshotSerializer::serialize(&snapshot).unwrap();
+    assert!(SnapshotSerializer::is_compressed(&compressed));
+    let mut plain = Vec::new();
+    flate2::read::GzDecoder::new(compressed.as_slice())
+        .read_to_end(&mut plain)
+        .unwrap();
+    assert!(compressed.len() > 4 * 1024 * 1024);
+
+    for data in [&compressed, &plain] {
+        let read = bytes_read_for_metadata(data);
+        assert!(
+            read < 256 * 1024,
+            "read {} of {} bytes for the metadata",
+            read,
+            data.len()
+        );
+    }
+}
+
+#[test]
+fn test_deserialize_metadata_falls_back_to_a_full_read_for_other_layouts() {
+    let snapshot = create_test_snapshot();
+    let snapshot_data = SnapshotData {
+        metadata: SnapshotSerializer::deserialize_metadata(
+            SnapshotSerializer::serialize(&snapshot).unwrap().as_slice(),
+        )
+        .unwrap(),
+        requests: snapshot.requests.clone(),
+    };
+    // Field names instead of positions: metadata isn't guaranteed to come first
+    let named = rmp_serde::to_vec_named(&snapshot_data).unwrap();
+
+    let metadata = SnapshotSerializer::deserialize_metadata(named.as_slice()).unwrap();
+    assert_eq!(metadata.name, snapshot.name);
+    assert_eq!(metadata.url, snapshot.url);
+}
@@ -0,0 +1,218 @@
+use crate::capture::proxy::RequestRecord;
+use crate::error::WebMockError;
+use crate::storage::{ManifestCheck, Snapshot, SnapshotSerializer, SplitSource, Storage};
+use crate::test_utils::test_helpers::create_test_record;
+use chrono::Utc;
+use tempfile::TempDir;
+
+fn record(method: &str, url: &str, body: Vec<u8>) -> RequestRecord {
+    create_test_record(method, url, 200, &[], &body)
+}
+
+fn full_app() -> Snapshot {
+    Snapshot {
+        name: "full-app".to_string(),
+        url: "https://app.example.com/".to_string(),
+        created_at: Utc::now(),
+        requests: vec![
+            record("GET", "https://app.example.com/", b"<html></html>".to_vec()),
+            record(
+                "GET",
+                "https://app.example.com/api/billing/invoices",
+                b"[]".to_vec(),
+            ),
+            record(
+                "POST",
+                "https://app.example.com/api/billing/pay",
+                b"{}".to_vec(),
+            ),
+            record("GET", "https://app.example.com/api/users", b"[]".to_vec()),
+            // Large enough that both snapshots end up compressed
+            record(
+                "GET",
+                "https://app.example.com/intro.mp4",
+                vec![7; 2 * 1024 * 1024],
+            ),
+        ],
+        skipped_requests: 3,
+        ..Default::default()
+    }
+}
+
+async fn storage_with_full_app(temp_dir: &TempDir) -> Storage {
+    let storage = Storage::new(temp_dir.path().to_path_buf());
+    storage.save_snapshot(full_app()).await.unwrap();
+    storage
+}
+
+fn urls(snapshot: &Snapshot) -> Vec<&str> {
+    snapshot.requests.iter().map(|r| r.url.as_str()).collect()
+}
+
+#[test]
+fn test_read_records_streams_serialized_snapshot() {
+    let snapshot = full_app();
+    let serialized = SnapshotSerializer::serialize(&snapshot).unwrap();
+    assert!(SnapshotSerializer::is_compressed(&serialized));
+
+    let (metadata, records) =
+        SnapshotSerializer::read_records(std::io::Cursor::new(serialized)).unwrap();
+    assert_eq!(metadata.name, "full-app");
+    assert_eq!(metadata.skipped_requests, 3);
+    assert_eq!(records.remaining(), 5);
+
+    let records: Vec<RequestRecord> = records.map(Result::unwrap).collect();
+    assert_eq!(records.len(), 5);
+    assert_eq!(records[4].response.body.len(), 2 * 1024 * 1024);
+    assert_eq!(records[2].method, "POST");
+}
+
+#[tokio::test]
+async fn test_split_keeps_matching_records() {
+    let temp_dir = TempDir::new().unwrap();
+    let storage = storage_with_full_app(&temp_dir).await;
+
+    let summary = storage
+        .split_snapshot("full-app", "billing", "billing API", |record| {
+            record.url.contains("/api/billing/")
+        })
+        .await
+        .unwrap();
+    assert_eq!(summary.total, 5);
+    assert_eq!(summary.kept, 2);
+    assert_eq!(
+        summary.size,
+        std::fs::metadata(storage.get_snapshot_path("billing").unwrap())
+            .unwrap()
+            .len()
+    );
+
+    let billing = storage.load_snapshot("billing").await.unwrap();
+    assert_eq!(billing.name, "billing");
+    assert_eq!(billing.url, "https://app.example.com/");
+    assert_eq!(
+        urls(&billing),
+        [
+            "https://app.example.com/api/billing/invoices",
+            "https://app.example.com/api/billing/pay"
+        ]
+    );
+    assert_eq!(
+        billing.split_from,
+        Some(SplitSource {
+            snapshot: "full-app".to_string(),
+            filter: "billing API".to_string(),
+        })
+    );
+
+    // The new snapshot is described by its own manifest
+    let manifest = storage.load_manifest("billing").await.unwrap().unwrap();
+    assert_eq!(manifest.record_count, 2);
+    assert_eq!(manifest.total_bytes, summary.size);
+    assert!(matches!(
+        storage.check_manifest("billing").await.unwrap(),
+        ManifestCheck::Matches(_)
+    ));
+
+    // The source is untouched and nothing is left in the staging area
+    assert_eq!(
+        storage
+            .load_snapshot("full-app")
+            .await
+            .unwrap()
+            .requests
+            .len(),
+        5
+    );
+    assert!(!storage
+        .staging()
+        .get_snapshot_path("billing")
+        .unwrap()
+        .exists());
+}
+
+#[tokio::test]
+async fn test_split_keeps_notes_of_kept_records() {
+    let temp_dir = TempDir::new().unwrap();
+    let storage = Storage::new(temp_dir.path().to_path_buf());
+    let mut snapshot = full_app();
+    RequestRecord::fill_missing_sequences(&mut snapshot.requests);
+    snapshot.annotations = [
+        (1, "invoices are stale".to_string()),
+        (3, "users need a fresh capture".to_string()),
+    ]
+    .into_iter()
+    .collect();
+    storage.save_snapshot(snapshot).await.unwrap();
+
+    storage
+        .split_snapshot("full-app", "billing", "billing API", |record| {
+            record.url.contains("/api/billing/")
+        })
+        .await
+        .unwrap();
+
+    let billing = storage.load_snapshot("billing").await.unwrap();
+    assert_eq!(
+        billing.annotations,
+        [(1, "invoices are stale".to_string())]
+            .into_iter()
+            .collect()
+    );
+    assert_eq!(
+        billing.annotation(&billing.requests[0]),
+        Some("invoices are stale")
+    );
+    assert_eq!(billing.annotation(&billing.requests[1]), None);
+}
+
+#[tokio::test]
+async fn test_split_of_large_records_stays_compressed() {
+    let temp_dir = TempDir::new().unwrap();
+    let storage = storage_with_full_app(&temp_dir).await;
+
+    storage
+        .split_snapshot("full-app", "media", "media", |record| {
+            record.url.ends_with(".mp4")
+        })
+        .await
+        .unwrap();
+
+    let data = std::fs::read(storage.get_snapshot_path("media").unwrap()).unwrap();
+    assert!(SnapshotSerializer::is_compressed(&data));
+    let media = SnapshotSerializer::deserialize(&data).unwrap();
+    assert_eq!(urls(&media), ["https://app.example.com/intro.mp4"]);
+    assert_eq!(media.requests[0].response.body, vec![7; 2 * 1024 * 1024]);
+}
+
+#[tokio::test]
+async fn test_split_refuses_existing_or_missing_snapshots() {
+    let temp_dir = TempDir::new().unwrap();
+    let storage = storage_with_full_app(&temp_dir).await;
+
+    assert!(matches!(
+        storage
+            .split_snapshot("missing", "copy", "all", |_| true)
+            .await,
+        Err(WebMockError::SnapshotNotFound(name)) if name == "missing"
+    ));
+    assert!(storage
+        .split_snapshot("full-app", "full-app", "all", |_| true)
+        .await
+        .is_err());
+
+    storage
+        .split_snapshot("full-app", "copy", "all", |_| true)
+        .await
+        .unwrap();
+    let error = storage
+        .split_snapshot("full-app", "copy", "nothing", |_| false)
+        .await
+        .unwrap_err();
+    assert!(error.to_string().contains("already exists"));
+    // The existing snapshot was not replaced
+    assert_eq!(
+        storage.load_snapshot("copy").await.unwrap().requests.len(),
+        5
+    );
+}
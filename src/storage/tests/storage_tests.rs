@@ -1,4 +1,5 @@
 use crate::capture::proxy::{RequestRecord, ResponseRecord};
+use crate::error::WebMockError;
 use crate::storage::{Snapshot, Storage};
 use chrono::Utc;
 use std::collections::HashMap;
@@ -19,9 +20,21 @@ fn create_test_snapshot() -> Snapshot {
                 headers: HashMap::new(),
                 body: b"<html><body>Test</body></html>".to_vec(),
                 content_type: "text/html".to_string(),
+                failure: None,
+                set_cookie: Vec::new(),
+                http_version: None,
+                reason: None,
+                informational: Vec::new(),
             },
             timestamp: Utc::now(),
+            initiator: None,
+            timing: Default::default(),
+            vary_key: Default::default(),
+            variant: None,
+            sequence: None,
+            frame_id: None,
         }],
+        ..Default::default()
     }
 }
 
@@ -32,12 +45,12 @@ fn test_storage_new() {
     // Storage is created successfully - we can't access private fields but that's OK
 }
 
-#[test]
-fn test_ensure_snapshots_dir() {
+#[tokio::test]
+async fn test_ensure_snapshots_dir() {
     let temp_dir = TempDir::new().unwrap();
     let storage = Storage::new(temp_dir.path().to_path_buf());
 
-    let snapshots_dir = storage.ensure_snapshots_dir().unwrap();
+    let snapshots_dir = storage.ensure_snapshots_dir().await.unwrap();
     assert!(snapshots_dir.exists());
     assert!(snapshots_dir.is_dir());
     assert_eq!(snapshots_dir, temp_dir.path().join("snapshots"));
@@ -48,7 +61,7 @@ fn test_get_snapshot_path() {
     let temp_dir = TempDir::new().unwrap();
     let storage = Storage::new(temp_dir.path().to_path_buf());
 
-    let path = storage.get_snapshot_path("test-snapshot");
+    let path = storage.get_snapshot_path("test-snapshot").unwrap();
     let expected = temp_dir
         .path()
         .join("snapshots")
@@ -56,21 +69,21 @@ fn test_get_snapshot_path() {
     assert_eq!(path, expected);
 }
 
-#[test]
-fn test_snapshot_exists() {
+#[tokio::test]
+async fn test_snapshot_exists() {
     let temp_dir = TempDir::new().unwrap();
     let storage = Storage::new(temp_dir.path().to_path_buf());
 
     // Should not exist initially
-    assert!(!storage.snapshot_exists("test-snapshot"));
+    assert!(!storage.snapshot_exists("test-snapshot").unwrap());
 
     // Create the snapshots directory and a test file
-    storage.ensure_snapshots_dir().unwrap();
-    let snapshot_path = storage.get_snapshot_path("test-snapshot");
+    storage.ensure_snapshots_dir().await.unwrap();
+    let snapshot_path = storage.get_snapshot_path("test-snapshot").unwrap();
     std::fs::write(&snapshot_path, b"test data").unwrap();
 
     // Should exist now
-    assert!(storage.snapshot_exists("test-snapshot"));
+    assert!(storage.snapshot_exists("test-snapshot").unwrap());
 }
 
 #[tokio::test]
@@ -83,7 +96,7 @@ async fn test_save_and_load_snapshot() {
     storage.save_snapshot(snapshot.clone()).await.unwrap();
 
     // Verify file exists
-    assert!(storage.snapshot_exists(&snapshot.name));
+    assert!(storage.snapshot_exists(&snapshot.name).unwrap());
 
     // Load snapshot
     let loaded_snapshot = storage.load_snapshot(&snapshot.name).await.unwrap();
@@ -165,11 +178,11 @@ async fn test_delete_snapshot() {
 
     // Save snapshot
     storage.save_snapshot(snapshot.clone()).await.unwrap();
-    assert!(storage.snapshot_exists(&snapshot.name));
+    assert!(storage.snapshot_exists(&snapshot.name).unwrap());
 
     // Delete snapshot
     storage.delete_snapshot(&snapshot.name).await.unwrap();
-    assert!(!storage.snapshot_exists(&snapshot.name));
+    assert!(!storage.snapshot_exists(&snapshot.name).unwrap());
 
     // Verify it's gone from list
     let snapshots = storage.list_snapshots().await.unwrap();
@@ -206,7 +219,7 @@ async fn test_save_snapshot_creates_directory() {
 
     assert!(snapshots_dir.exists());
     assert!(snapshots_dir.is_dir());
-    assert!(storage.snapshot_exists(&snapshot.name));
+    assert!(storage.snapshot_exists(&snapshot.name).unwrap());
 }
 
 #[tokio::test]
@@ -216,7 +229,7 @@ async fn test_list_snapshots_ignores_non_msgpack_files() {
     let storage = Storage::new(temp_dir.path().to_path_buf());
 
     // Create snapshots directory
-    storage.ensure_snapshots_dir().unwrap();
+    storage.ensure_snapshots_dir().await.unwrap();
     let snapshots_dir = temp_dir.path().join("snapshots");
 
     // Create a valid snapshot
@@ -239,3 +252,293 @@ async fn test_list_snapshots_ignores_non_msgpack_files() {
     assert_eq!(snapshots.len(), 1);
     assert_eq!(snapshots[0].name, snapshot.name);
 }
+
+#[tokio::test]
+async fn test_load_snapshot_metadata_does_not_read_records() {
+    let temp_dir = TempDir::new().unwrap();
+    let storage = Storage::new(temp_dir.path().to_path_buf());
+    let snapshot = create_test_snapshot();
+    storage.save_snapshot(snapshot.clone()).await.unwrap();
+
+    // Cut off inside the records: loading fails, listing still works
+    let path = storage.get_snapshot_path(&snapshot.name).unwrap();
+    let data = std::fs::read(&path).unwrap();
+    std::fs::write(&path, &data[..data.len() - 8]).unwrap();
+
+    assert!(storage.load_snapshot(&snapshot.name).await.is_err());
+    let info = storage
+        .load_snapshot_metadata(&snapshot.name)
+        .await
+        .unwrap();
+    assert_eq!(info.name, snapshot.name);
+    assert_eq!(info.url, snapshot.url);
+    assert!(!info.encrypted);
+}
+
+#[test]
+fn test_parse_streaming_threshold() {
+    use crate::storage::parse_streaming_threshold;
+
+    assert_eq!(parse_streaming_threshold("1048576"), Some(1048576));
+    assert_eq!(parse_streaming_threshold(" 4096 "), Some(4096));
+    assert_eq!(parse_streaming_threshold("0"), None);
+    assert_eq!(parse_streaming_threshold("50MB"), None);
+}
+
+#[tokio::test]
+async fn test_snapshot_file_stats_reports_size_and_mtime() {
+    let temp_dir = TempDir::new().unwrap();
+    let storage = Storage::new(temp_dir.path().to_path_buf());
+    let snapshots_dir = storage.ensure_snapshots_dir().await.unwrap();
+
+    // Stats come from file metadata, so contents need not be valid snapshots
+    let modified = std::time::UNIX_EPOCH + std::time::Duration::from_secs(1_700_000_000);
+    for (name, size) in [("beta", 2048), ("alpha", 512)] {
+        let file = std::fs::File::create(snapshots_dir.join(format!("{}.msgpack", name))).unwrap();
+        file.set_len(size).unwrap();
+        file.set_modified(modified).unwrap();
+    }
+    std::fs::write(snapshots_dir.join("notes.txt"), b"ignored").unwrap();
+
+    let stats = storage.snapshot_file_stats().await.unwrap();
+    assert_eq!(stats.len(), 2);
+    assert_eq!(stats[0].name, "alpha");
+    assert_eq!(stats[0].size, 512);
+    assert_eq!(stats[1].name, "beta");
+    assert_eq!(stats[1].size, 2048);
+    assert_eq!(stats[1].path, snapshots_dir.join("beta.msgpack"));
+    assert_eq!(stats[1].modified.timestamp(), 1_700_000_000);
+}
+
+#[tokio::test]
+async fn test_snapshot_file_stats_without_snapshots_dir() {
+    let temp_dir = TempDir::new().unwrap();
+    let storage = Storage::new(temp_dir.path().join("missing"));
+
+    assert!(storage.snapshot_file_stats().await.unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn test_storage_rejects_unsafe_snapshot_names() {
+    use crate::error::WebMockError;
+
+    let temp_dir = TempDir::new().unwrap();
+    let storage = Storage::new(temp_dir.path().join("store"));
+    std::fs::write(temp_dir.path().join("secret.msgpack"), b"outside").unwrap();
+
+    let too_long = "a".repeat(crate::storage::MAX_SNAPSHOT_NAME_LEN + 1);
+    for name in [
+        "../secret",
+        "../../etc/passwd",
+        "..",
+        "nested/name",
+        "back\\slash",
+        "nul\0byte",
+        "",
+        too_long.as_str(),
+    ] {
+        let is_config =
+            |result: crate::error::Result<()>| matches!(result, Err(WebMockError::Config(_)));
+        assert!(
+            is_config(storage.get_snapshot_path(name).map(|_| ())),
+            "{:?}",
+            name
+        );
+        assert!(
+            is_config(storage.snapshot_exists(name).map(|_| ())),
+            "{:?}",
+            name
+        );
+        assert!(
+            is_config(storage.load_snapshot(name).await.map(|_| ())),
+            "{:?}",
+            name
+        );
+        assert!(is_config(storage.delete_snapshot(name).await), "{:?}", name);
+
+        let mut snapshot = create_test_snapshot();
+        snapshot.name = name.to_string();
+        assert!(
+            is_config(storage.save_snapshot(snapshot).await),
+            "{:?}",
+            name
+        );
+    }
+
+    // Nothing was written or removed outside the snapshots directory
+    assert!(temp_dir.path().join("secret.msgpack").exists());
+    assert!(!temp_dir.path().join("store").join("snapshots").exists());
+
+    let longest = "a".repeat(crate::storage::MAX_SNAPSHOT_NAME_LEN);
+    assert!(storage.get_snapshot_path(&longest).is_ok());
+}
+
+#[cfg(unix)]
+#[tokio::test]
+async fn test_storage_rejects_snapshot_symlinked_outside() {
+    let temp_dir = TempDir::new().unwrap();
+    let storage = Storage::new(temp_dir.path().join("store"));
+    let snapshots_dir = storage.ensure_snapshots_dir().await.unwrap();
+
+    let outside = temp_dir.path().join("outside.msgpack");
+    std::fs::write(&outside, b"outside").unwrap();
+    std::os::unix::fs::symlink(&outside, snapshots_dir.join("escape.msgpack")).unwrap();
+
+    assert!(storage.get_snapshot_path("escape").is_err());
+    assert!(storage.delete_snapshot("escape").await.is_err());
+    assert!(outside.exists());
+}
+
+#[tokio::test]
+async fn test_replace_snapshot_rotates_backup() {
+    let temp_dir = TempDir::new().unwrap();
+    let storage = Storage::new(temp_dir.path().to_path_buf());
+    let staging = storage.staging();
+
+    let version = |url: &str| Snapshot {
+        url: url.to_string(),
+        ..create_test_snapshot()
+    };
+    storage
+        .save_snapshot(version("https://v1.example"))
+        .await
+        .unwrap();
+
+    for url in ["https://v2.example", "https://v3.example"] {
+        staging.save_snapshot(version(url)).await.unwrap();
+        let staged = staging.get_snapshot_path("test-snapshot").unwrap();
+        let backup = storage
+            .replace_snapshot("test-snapshot", &staged, true)
+            .await
+            .unwrap();
+        assert_eq!(
+            backup,
+            Some(storage.get_backup_path("test-snapshot").unwrap())
+        );
+        assert!(!staged.exists());
+    }
+
+    let current = storage.load_snapshot("test-snapshot").await.unwrap();
+    assert_eq!(current.url, "https://v3.example");
+    let backup = std::fs::read(storage.get_backup_path("test-snapshot").unwrap()).unwrap();
+    let previous = crate::storage::SnapshotSerializer::deserialize(&backup).unwrap();
+    assert_eq!(previous.url, "https://v2.example");
+
+    // A missing staged file never clobbers the snapshot
+    let missing = staging.get_snapshot_path("test-snapshot").unwrap();
+    assert!(storage
+        .replace_snapshot("test-snapshot", &missing, false)
+        .await
+        .is_err());
+    assert!(storage.snapshot_exists("test-snapshot").unwrap());
+}
+
+#[tokio::test]
+async fn test_rewrite_snapshot_waits_for_the_lock() {
+    let temp_dir = TempDir::new().unwrap();
+    let storage = std::sync::Arc::new(Storage::new(temp_dir.path().to_path_buf()));
+    storage.save_snapshot(create_test_snapshot()).await.unwrap();
+
+    let lock = storage.lock_snapshot("test-snapshot").await.unwrap();
+    let rewrite = tokio::spawn({
+        let storage = std::sync::Arc::clone(&storage);
+        async move {
+            let snapshot = Snapshot {
+                url: "https://v2.example".to_string(),
+                ..create_test_snapshot()
+            };
+            storage.rewrite_snapshot(snapshot, false).await
+        }
+    });
+    tokio::time::sleep(std::time::Duration::from_millis(200)).await;
+    let current = storage.load_snapshot("test-snapshot").await.unwrap();
+    assert_eq!(current.url, "https://example.com");
+
+    drop(lock);
+    assert_eq!(rewrite.await.unwrap().unwrap(), None);
+    let current = storage.load_snapshot("test-snapshot").await.unwrap();
+    assert_eq!(current.url, "https://v2.example");
+    let staged = storage
+        .staging()
+        .get_snapshot_path("test-snapshot")
+        .unwrap();
+    assert!(!staged.exists());
+}
+
+#[tokio::test]
+async fn test_storage_io_errors_name_the_path() {
+    let temp_dir = TempDir::new().unwrap();
+
+    // A file where the snapshots directory should be
+    let blocked = Storage::new(temp_dir.path().join("blocked"));
+    std::fs::create_dir_all(temp_dir.path().join("blocked")).unwrap();
+    let snapshots_file = temp_dir.path().join("blocked").join("snapshots");
+    std::fs::write(&snapshots_file, b"not a directory").unwrap();
+
+    let error = blocked.ensure_snapshots_dir().await.unwrap_err();
+    assert!(matches!(error, WebMockError::Storage(_)));
+    let message = error.to_string();
+    assert!(message.contains("failed to create snapshots directory"));
+    assert!(message.contains(&snapshots_file.display().to_string()));
+
+    let error = blocked.list_snapshots().await.unwrap_err().to_string();
+    assert!(error.contains("failed to read snapshots directory"));
+    assert!(error.contains(&snapshots_file.display().to_string()));
+
+    // A directory where a snapshot file should be
+    let storage = Storage::new(temp_dir.path().join("storage"));
+    storage.ensure_snapshots_dir().await.unwrap();
+    let snapshot_path = storage.get_snapshot_path("not-a-file").unwrap();
+    std::fs::create_dir(&snapshot_path).unwrap();
+
+    let error = storage.load_snapshot("not-a-file").await.unwrap_err();
+    let message = error.to_string();
+    assert!(message.contains("failed to read snapshot file"));
+    assert!(message.contains(&snapshot_path.display().to_string()));
+
+    let error = storage.delete_snapshot("not-a-file").await.unwrap_err();
+    let message = error.to_string();
+    assert!(message.contains("failed to delete snapshot file"));
+    assert!(message.contains(&snapshot_path.display().to_string()));
+}
+
+#[tokio::test]
+async fn test_missing_snapshot_is_still_not_found() {
+    let temp_dir = TempDir::new().unwrap();
+    let storage = Storage::new(temp_dir.path().to_path_buf());
+    storage.ensure_snapshots_dir().await.unwrap();
+
+    assert!(matches!(
+        storage.load_snapshot("missing").await,
+        Err(WebMockError::SnapshotNotFound(_))
+    ));
+    assert!(matches!(
+        storage.delete_snapshot("missing").await,
+        Err(WebMockError::SnapshotNotFound(_))
+    ));
+}
+
+#[test]
+fn test_default_storage_path_prefers_home() {
+    use crate::storage::default_storage_path_from;
+    use std::path::PathBuf;
+
+    let path =
+        default_storage_path_from(Some("/home/dev".into()), Some("/data".into()), || None).unwrap();
+    assert_eq!(path, PathBuf::from("/home/dev/.webmock"));
+
+    // Without HOME, as in scratch containers
+    let path = default_storage_path_from(None, Some("/data".into()), || None).unwrap();
+    assert_eq!(path, PathBuf::from("/data/webmock"));
+    let path = default_storage_path_from(Some("".into()), Some("/data".into()), || None).unwrap();
+    assert_eq!(path, PathBuf::from("/data/webmock"));
+
+    // A relative XDG_DATA_HOME is invalid and skipped
+    let path =
+        default_storage_path_from(None, Some("data".into()), || Some(PathBuf::from("/root")))
+            .unwrap();
+    assert_eq!(path, PathBuf::from("/root/.webmock"));
+
+    let error = default_storage_path_from(None, None, || None).unwrap_err();
+    assert!(error.to_string().contains("--storage"));
+}
@@ -1,14 +1,108 @@
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::PathBuf;
 
-use crate::capture::proxy::RequestRecord;
+use crate::capture::browser::{OfflineReload, PageEvent};
+use crate::capture::environment::CaptureEnvironment;
+use crate::capture::metrics::PerformanceMetrics;
+use crate::capture::provenance::Provenance;
+use crate::capture::proxy::{CaptureDiagnostics, RequestRecord, TlsDetails, TunnelRecord};
+use crate::storage::artifacts::CaptureArtifacts;
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct Snapshot {
     pub name: String,
     pub url: String,
     pub created_at: DateTime<Utc>,
     pub requests: Vec<RequestRecord>,
+    /// Browser locale, timezone and viewport used for the capture
+    #[serde(default)]
+    pub environment: CaptureEnvironment,
+    /// TLS parameters negotiated with each origin, keyed by `host:port`
+    #[serde(default)]
+    pub tls: BTreeMap<String, TlsDetails>,
+    /// Tunnels passed through without recording, e.g. to certificate-pinned hosts
+    #[serde(default)]
+    pub tunnels: Vec<TunnelRecord>,
+    /// Requests forwarded but not recorded once `--max-requests` was reached
+    #[serde(default)]
+    pub skipped_requests: u64,
+    /// Console messages and uncaught errors the page logged during capture
+    #[serde(default)]
+    pub page_events: Vec<PageEvent>,
+    /// Requests that needed at least one upstream retry (`--upstream-retries`)
+    #[serde(default)]
+    pub retried_requests: u64,
+    /// Snapshot and filter this one was extracted from by `webmock split`
+    #[serde(default)]
+    pub split_from: Option<SplitSource>,
+    /// Serve settings stored by `webmock config`
+    #[serde(default)]
+    pub serve_defaults: ServeDefaults,
+    /// What the page loaded when reloaded offline (`--emulate-offline-after-load`)
+    #[serde(default)]
+    pub offline_reload: Option<OfflineReload>,
+    /// Screenshot and DOM files written next to the snapshot at capture time
+    #[serde(default)]
+    pub artifacts: CaptureArtifacts,
+    /// Duration, throughput and memory of the capture
+    #[serde(default)]
+    pub metrics: Option<PerformanceMetrics>,
+    /// Requests refused because their host wasn't in `--only-hosts`, by host
+    #[serde(default)]
+    pub blocked_hosts: BTreeMap<String, u64>,
+    /// Pages the capture loaded, including where redirects ended up
+    ///
+    /// `url` stays the primary entry point that `serve --open` opens.
+    #[serde(default)]
+    pub entry_points: Vec<String>,
+    /// Notes left on records by `webmock annotate`, keyed by record sequence
+    #[serde(default)]
+    pub annotations: BTreeMap<u64, String>,
+    /// Connection-level failures of the capture proxy, explaining requests missing from the records
+    #[serde(default)]
+    pub capture_diagnostics: CaptureDiagnostics,
+    /// Where and how the snapshot was captured; `None` for snapshots saved before it was kept
+    #[serde(default)]
+    pub provenance: Option<Provenance>,
+    /// Records removed by `webmock trim`, earliest trim first
+    #[serde(default)]
+    pub trimmed: Vec<TrimmedRecord>,
+    /// Whether the snapshot is stored encrypted with a passphrase
+    ///
+    /// Set when an encrypted snapshot is loaded, so saving it again keeps it
+    /// encrypted. It is part of the file format, not of the payload.
+    #[serde(skip)]
+    pub encrypted: bool,
+}
+
+impl Snapshot {
+    /// Every entry point of the snapshot, the primary `url` first
+    pub fn entry_points(&self) -> Vec<String> {
+        entry_points(&self.url, &self.entry_points)
+    }
+
+    /// The note left on `record`, if any
+    pub fn annotation(&self, record: &RequestRecord) -> Option<&str> {
+        record
+            .sequence
+            .and_then(|sequence| self.annotations.get(&sequence))
+            .map(String::as_str)
+    }
+}
+
+/// The primary URL followed by the other entry points, without duplicates
+///
+/// Snapshots saved before entry points were kept only have the primary.
+pub(crate) fn entry_points(url: &str, others: &[String]) -> Vec<String> {
+    let mut entry_points = vec![url.to_string()];
+    for entry_point in others {
+        if !entry_points.contains(entry_point) {
+            entry_points.push(entry_point.clone());
+        }
+    }
+    entry_points
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -17,6 +111,127 @@ pub struct SnapshotMetadata {
     pub url: String,
     pub created_at: DateTime<Utc>,
     pub version: String,
+    #[serde(default)]
+    pub environment: CaptureEnvironment,
+    #[serde(default)]
+    pub tls: BTreeMap<String, TlsDetails>,
+    #[serde(default)]
+    pub tunnels: Vec<TunnelRecord>,
+    #[serde(default)]
+    pub skipped_requests: u64,
+    #[serde(default)]
+    pub page_events: Vec<PageEvent>,
+    #[serde(default)]
+    pub retried_requests: u64,
+    #[serde(default)]
+    pub split_from: Option<SplitSource>,
+    #[serde(default)]
+    pub serve_defaults: ServeDefaults,
+    #[serde(default)]
+    pub offline_reload: Option<OfflineReload>,
+    #[serde(default)]
+    pub artifacts: CaptureArtifacts,
+    #[serde(default)]
+    pub metrics: Option<PerformanceMetrics>,
+    #[serde(default)]
+    pub blocked_hosts: BTreeMap<String, u64>,
+    #[serde(default)]
+    pub entry_points: Vec<String>,
+    #[serde(default)]
+    pub annotations: BTreeMap<u64, String>,
+    #[serde(default)]
+    pub capture_diagnostics: CaptureDiagnostics,
+    #[serde(default)]
+    pub provenance: Option<Provenance>,
+    #[serde(default)]
+    pub trimmed: Vec<TrimmedRecord>,
+}
+
+/// Where a snapshot made by `webmock split` came from
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SplitSource {
+    /// Name of the source snapshot
+    pub snapshot: String,
+    /// The filter records were selected with, e.g. `GET https://app.example.com/api/*`
+    pub filter: String,
+}
+
+/// A record `webmock trim` removed because a coverage report showed it went unused
+///
+/// Only what identifies the record is kept, so the snapshot still tells
+/// what it was captured with.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TrimmedRecord {
+    pub sequence: Option<u64>,
+    pub method: String,
+    pub url: String,
+    pub status: u16,
+    /// Size of the response body that was dropped
+    pub body_size: u64,
+    /// Times the report showed the record served; `None` when it didn't list the record
+    pub hits: Option<u64>,
+    /// The note left on the record by `webmock annotate`
+    pub annotation: Option<String>,
+    pub trimmed_at: DateTime<Utc>,
+}
+
+/// Serve settings stored with a snapshot by `webmock config`
+///
+/// `serve` uses each one unless the same flag is given on the command line.
+/// The same type holds the flags given to `serve` and `config`, with `None`
+/// for flags that weren't given.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ServeDefaults {
+    pub port: Option<u16>,
+    /// `bad-gateway` or `as-timeout`
+    pub replay_failures: Option<String>,
+    /// Encodings for `--encode`, e.g. `br,gzip`
+    pub encode: Option<String>,
+    pub strict_connect: Option<bool>,
+    pub stateful: Option<bool>,
+}
+
+impl ServeDefaults {
+    /// Check whether no setting is stored
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+
+    /// Take each setting from `self`, or from `fallback` where it is unset
+    pub fn or(&self, fallback: &ServeDefaults) -> ServeDefaults {
+        ServeDefaults {
+            port: self.port.or(fallback.port),
+            replay_failures: self
+                .replay_failures
+                .clone()
+                .or_else(|| fallback.replay_failures.clone()),
+            encode: self.encode.clone().or_else(|| fallback.encode.clone()),
+            strict_connect: self.strict_connect.or(fallback.strict_connect),
+            stateful: self.stateful.or(fallback.stateful),
+        }
+    }
+
+    /// The settings as `serve` flags, e.g. `--port 8090`
+    pub fn flags(&self) -> Vec<String> {
+        let mut flags = Vec::new();
+        if let Some(port) = self.port {
+            flags.push(format!("--port {}", port));
+        }
+        if let Some(mode) = &self.replay_failures {
+            flags.push(format!("--replay-failures {}", mode));
+        }
+        if let Some(encode) = &self.encode {
+            flags.push(format!("--encode {}", encode));
+        }
+        if let Some(strict_connect) = self.strict_connect {
+            flags.push(format!("--strict-connect={}", strict_connect));
+        }
+        if let Some(stateful) = self.stateful {
+            flags.push(format!("--stateful={}", stateful));
+        }
+        flags
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -25,9 +240,24 @@ pub struct SnapshotData {
     pub requests: Vec<RequestRecord>,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize)]
 pub struct SnapshotInfo {
     pub name: String,
     pub url: String,
     pub created_at: DateTime<Utc>,
+    /// Every entry point, `url` first
+    pub entry_points: Vec<String>,
+    /// Whether the snapshot is stored encrypted
+    pub encrypted: bool,
+    /// Where and how the snapshot was captured; not readable without the passphrase when encrypted
+    pub provenance: Option<Provenance>,
+}
+
+/// On-disk size and modification time of a snapshot file
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SnapshotFileStat {
+    pub name: String,
+    pub path: PathBuf,
+    pub size: u64,
+    pub modified: DateTime<Utc>,
 }
@@ -59,13 +59,56 @@ pub mod test_helpers {
                         headers: response_headers,
                         body: b"<html><body><h1>Test Page</h1><p>This is a test page for WebMock CLI.</p></body></html>".to_vec(),
                         content_type: "text/html".to_string(),
+                        failure: None,
+                        set_cookie: Vec::new(),
+                        http_version: None,
+                        reason: None,
+                        informational: Vec::new(),
                     },
                     timestamp: Utc::now(),
+                    initiator: None,
+                    timing: Default::default(),
+                    vary_key: Default::default(),
+                    variant: None,
+                    sequence: None,
+                    frame_id: None,
                 }
             ],
+            ..Default::default()
         }
     }
 
+    /// Create a single test record without request headers or body
+    ///
+    /// Without a content-type header the content type is guessed from the
+    /// body and URL, as for captured records.
+    ///
+    /// # Parameters
+    /// * `method` - Request method
+    /// * `url` - Request URL
+    /// * `status` - Response status
+    /// * `headers` - Response headers; a later duplicate overrides an earlier one
+    /// * `body` - Response body
+    pub fn create_test_record(
+        method: &str,
+        url: &str,
+        status: u16,
+        headers: &[(&str, &str)],
+        body: &[u8],
+    ) -> RequestRecord {
+        let headers = headers
+            .iter()
+            .map(|(name, value)| (name.to_string(), value.to_string()))
+            .collect();
+        RequestRecord::new(
+            method.to_string(),
+            url.to_string(),
+            HashMap::new(),
+            None,
+            ResponseRecord::new(status, headers, body.to_vec(), Some(url)),
+        )
+    }
+
     /// Create a multi-request test snapshot
     ///
     /// Contains multiple types of requests: HTML, CSS, JS, API
@@ -99,8 +142,19 @@ pub mod test_helpers {
                         },
                         body: b"<html><head><link rel=\"stylesheet\" href=\"/style.css\"></head><body><h1>Test</h1></body></html>".to_vec(),
                         content_type: "text/html".to_string(),
+                        failure: None,
+                        set_cookie: Vec::new(),
+                        http_version: None,
+                        reason: None,
+                        informational: Vec::new(),
                     },
                     timestamp: base_time,
+                    initiator: None,
+                    timing: Default::default(),
+                    vary_key: Default::default(),
+                    variant: None,
+                    sequence: None,
+                    frame_id: None,
                 },
                 // CSS file
                 RequestRecord {
@@ -117,8 +171,19 @@ pub mod test_helpers {
                         },
                         body: b"body { font-family: Arial, sans-serif; }".to_vec(),
                         content_type: "text/css".to_string(),
+                        failure: None,
+                        set_cookie: Vec::new(),
+                        http_version: None,
+                        reason: None,
+                        informational: Vec::new(),
                     },
                     timestamp: base_time,
+                    initiator: None,
+                    timing: Default::default(),
+                    vary_key: Default::default(),
+                    variant: None,
+                    sequence: None,
+                    frame_id: None,
                 },
                 // API request
                 RequestRecord {
@@ -139,10 +204,22 @@ pub mod test_helpers {
                         },
                         body: b"{\"message\": \"Hello from API\", \"status\": \"success\"}".to_vec(),
                         content_type: "application/json".to_string(),
+                        failure: None,
+                        set_cookie: Vec::new(),
+                        http_version: None,
+                        reason: None,
+                        informational: Vec::new(),
                     },
                     timestamp: base_time,
+                    initiator: None,
+                    timing: Default::default(),
+                    vary_key: Default::default(),
+                    variant: None,
+                    sequence: None,
+                    frame_id: None,
                 }
             ],
+            ..Default::default()
         }
     }
 
@@ -176,8 +253,19 @@ pub mod test_helpers {
                     },
                     body: large_body.clone(),
                     content_type: "text/html".to_string(),
+                    failure: None,
+                    set_cookie: Vec::new(),
+                    http_version: None,
+                    reason: None,
+                    informational: Vec::new(),
                 },
                 timestamp: base_time,
+                initiator: None,
+                timing: Default::default(),
+                vary_key: Default::default(),
+                variant: None,
+                sequence: None,
+                frame_id: None,
             });
         }
 
@@ -186,6 +274,7 @@ pub mod test_helpers {
             url: "https://example.com".to_string(),
             created_at: base_time,
             requests,
+            ..Default::default()
         }
     }
 
@@ -0,0 +1,295 @@
+//! Persistent certificate used to terminate TLS in the capture proxy and mock server
+//!
+//! The certificate is generated once and cached on disk so it can be added to
+//! the OS trust store with `webmock ca trust`. It is a self-signed certificate
+//! covering common wildcard domains, trusted directly as an anchor.
+
+use rcgen::{CertificateParams, DnType, KeyPair};
+use rustls::pki_types::pem::PemObject;
+use rustls::pki_types::{CertificateDer, PrivateKeyDer};
+use std::path::{Path, PathBuf};
+use tracing::{debug, info, warn};
+
+use crate::error::{Result, WebMockError};
+
+/// Environment variable overriding where the certificate is cached
+pub const CA_DIR_ENV: &str = "WEBMOCK_CA_DIR";
+
+/// File name of the cached certificate
+pub const CERT_FILE: &str = "webmock-ca.pem";
+
+/// File name of the cached private key
+pub const KEY_FILE: &str = "webmock-ca-key.pem";
+
+/// Common name of the generated certificate
+pub const CERT_COMMON_NAME: &str = "WebMock CA";
+
+/// Names covered by the generated certificate
+const SUBJECT_ALT_NAMES: &[&str] = &[
+    "localhost",
+    "127.0.0.1",
+    "::1",
+    "*.com",
+    "*.org",
+    "*.net",
+    "*.io",
+    "*.co.uk",
+    "*.edu",
+    "*.gov",
+    "*.mil",
+    "*.info",
+    "*.biz",
+    "*.name",
+    "*.tv",
+    "*.me",
+    "*.dev",
+    "*.app",
+    "*.cloud",
+    "*.ai",
+    "*.cn",
+    "*.jp",
+    "*.de",
+    "*.fr",
+    "*.ru",
+    "*.in",
+    "*.br",
+    "*.ca",
+    "*.au",
+    "*.kr",
+    "*.it",
+    "*.es",
+    "*.mx",
+    "*.ar",
+    "*.za",
+    "*.ng",
+    "*.eg",
+    "*.sa",
+    "*.ae",
+    "*.tr",
+    "*.id",
+    "*.th",
+    "*.vn",
+    "*.ph",
+    "*.my",
+    "*.sg",
+    "*.tw",
+    "*.hk",
+    "*.mo",
+    "*.baidu.com",
+    "*.google.com",
+    "*.github.com",
+];
+
+/// PEM encoded certificate and private key
+#[derive(Debug, Clone)]
+pub struct WebMockCertificate {
+    cert_pem: String,
+    key_pem: String,
+}
+
+impl WebMockCertificate {
+    /// Generate a fresh certificate without caching it
+    pub fn generate() -> Result<Self> {
+        let names: Vec<String> = SUBJECT_ALT_NAMES
+            .iter()
+            .map(|name| name.to_string())
+            .collect();
+        Self::self_signed(names)
+    }
+
+    /// Generate an uncached certificate for one host the shared certificate doesn't cover
+    pub fn for_host(host: &str) -> Result<Self> {
+        Self::self_signed(vec![host.to_string()])
+    }
+
+    /// Whether the shared certificate's names match `host`
+    ///
+    /// A wildcard covers exactly one label, as in certificate validation.
+    pub fn covers(host: &str) -> bool {
+        let host = host.trim_end_matches('.').to_ascii_lowercase();
+        SUBJECT_ALT_NAMES
+            .iter()
+            .any(|name| match name.strip_prefix("*.") {
+                Some(suffix) => host
+                    .strip_suffix(suffix)
+                    .and_then(|label| label.strip_suffix('.'))
+                    .is_some_and(|label| !label.is_empty() && !label.contains('.')),
+                None => host == *name,
+            })
+    }
+
+    fn self_signed(names: Vec<String>) -> Result<Self> {
+        let mut params = CertificateParams::new(names).map_err(|e| {
+            WebMockError::config(format!("Failed to generate TLS certificate: {}", e))
+        })?;
+        params
+            .distinguished_name
+            .push(DnType::CommonName, CERT_COMMON_NAME);
+
+        let key_pair = KeyPair::generate().map_err(|e| {
+            WebMockError::config(format!("Failed to generate TLS certificate: {}", e))
+        })?;
+        let cert = params.self_signed(&key_pair).map_err(|e| {
+            WebMockError::config(format!("Failed to generate TLS certificate: {}", e))
+        })?;
+
+        Ok(Self {
+            cert_pem: cert.pem(),
+            key_pem: key_pair.serialize_pem(),
+        })
+    }
+
+    /// Directory holding the cached certificate, `ca` in the default storage directory
+    pub fn default_dir() -> Result<PathBuf> {
+        if let Ok(dir) = std::env::var(CA_DIR_ENV) {
+            if !dir.trim().is_empty() {
+                return Ok(PathBuf::from(dir));
+            }
+        }
+
+        Ok(crate::storage::default_storage_path()?.join("ca"))
+    }
+
+    /// Path of the certificate file inside a cache directory
+    pub fn cert_path(dir: &Path) -> PathBuf {
+        dir.join(CERT_FILE)
+    }
+
+    /// Load the cached certificate, generating and caching one if needed
+    pub fn load_or_generate(dir: &Path) -> Result<Self> {
+        if let Some(certificate) = Self::load(dir)? {
+            return Ok(certificate);
+        }
+
+        let certificate = Self::generate()?;
+        certificate.save(dir)?;
+
+        // Another process may have won the race to create the cache
+        Ok(Self::load(dir)?.unwrap_or(certificate))
+    }
+
+    /// Load the certificate from the default directory
+    ///
+    /// Falls back to a temporary certificate when the cache cannot be used, so
+    /// an unwritable home directory never prevents TLS termination.
+    pub fn load_default() -> Result<Self> {
+        match Self::default_dir().and_then(|dir| Self::load_or_generate(&dir)) {
+            Ok(certificate) => Ok(certificate),
+            Err(e) => {
+                warn!("Using a temporary TLS certificate: {}", e);
+                Self::generate()
+            }
+        }
+    }
+
+    /// Load the cached certificate if both files are present
+    pub fn load(dir: &Path) -> Result<Option<Self>> {
+        let cert_path = dir.join(CERT_FILE);
+        let key_path = dir.join(KEY_FILE);
+        if !cert_path.exists() || !key_path.exists() {
+            return Ok(None);
+        }
+
+        let certificate = Self {
+            cert_pem: std::fs::read_to_string(&cert_path)
+                .map_err(|e| WebMockError::io("read TLS certificate", &cert_path, e))?,
+            key_pem: std::fs::read_to_string(&key_path)
+                .map_err(|e| WebMockError::io("read TLS private key", &key_path, e))?,
+        };
+
+        // Validate eagerly so a damaged cache is reported with its location
+        certificate
+            .cert_chain()
+            .and(certificate.private_key())
+            .map_err(|e| {
+                WebMockError::config(format!(
+                    "{} (delete {} to regenerate the certificate)",
+                    e,
+                    dir.display()
+                ))
+            })?;
+
+        debug!("Loaded TLS certificate from {}", cert_path.display());
+        Ok(Some(certificate))
+    }
+
+    /// Write the certificate and key to the cache directory
+    ///
+    /// Both files are written to a staging directory that is renamed into
+    /// place, so concurrent processes never see a mismatched pair.
+    fn save(&self, dir: &Path) -> Result<()> {
+        if dir.exists() {
+            // An existing (possibly empty) directory can't be replaced atomically
+            let (cert_path, key_path) = (dir.join(CERT_FILE), dir.join(KEY_FILE));
+            std::fs::write(&cert_path, &self.cert_pem)
+                .map_err(|e| WebMockError::io("write TLS certificate", &cert_path, e))?;
+            write_private(&key_path, &self.key_pem)
+                .map_err(|e| WebMockError::io("write TLS private key", &key_path, e))?;
+        } else {
+            let parent = dir.parent().unwrap_or_else(|| Path::new("."));
+            std::fs::create_dir_all(parent)
+                .map_err(|e| WebMockError::io("create certificate directory", parent, e))?;
+
+            let staging = tempfile::Builder::new()
+                .prefix(".webmock-ca-")
+                .tempdir_in(parent)
+                .map_err(|e| WebMockError::io("create staging directory in", parent, e))?;
+            let (cert_path, key_path) = (
+                staging.path().join(CERT_FILE),
+                staging.path().join(KEY_FILE),
+            );
+            std::fs::write(&cert_path, &self.cert_pem)
+                .map_err(|e| WebMockError::io("write TLS certificate", &cert_path, e))?;
+            write_private(&key_path, &self.key_pem)
+                .map_err(|e| WebMockError::io("write TLS private key", &key_path, e))?;
+
+            // Losing the rename race is fine, the winner's pair is loaded instead
+            if std::fs::rename(staging.path(), dir).is_err() && !dir.exists() {
+                return Err(WebMockError::config(format!(
+                    "Failed to cache TLS certificate in {}",
+                    dir.display()
+                )));
+            }
+        }
+
+        info!("Generated TLS certificate in {}", dir.display());
+        Ok(())
+    }
+
+    /// Get the PEM encoded certificate
+    pub fn cert_pem(&self) -> &str {
+        &self.cert_pem
+    }
+
+    /// Get the certificate chain for a rustls server config
+    pub fn cert_chain(&self) -> Result<Vec<CertificateDer<'static>>> {
+        let cert = CertificateDer::from_pem_slice(self.cert_pem.as_bytes())
+            .map_err(|e| WebMockError::config(format!("Invalid TLS certificate: {}", e)))?;
+        Ok(vec![cert])
+    }
+
+    /// Get the private key for a rustls server config
+    pub fn private_key(&self) -> Result<PrivateKeyDer<'static>> {
+        PrivateKeyDer::from_pem_slice(self.key_pem.as_bytes())
+            .map_err(|e| WebMockError::config(format!("Invalid TLS private key: {}", e)))
+    }
+}
+
+#[cfg(unix)]
+fn write_private(path: &Path, contents: &str) -> std::io::Result<()> {
+    use std::io::Write;
+    use std::os::unix::fs::OpenOptionsExt;
+
+    let mut file = std::fs::OpenOptions::new()
+        .write(true)
+        .create(true)
+        .truncate(true)
+        .mode(0o600)
+        .open(path)?;
+    file.write_all(contents.as_bytes())
+}
+
+#[cfg(not(unix))]
+fn write_private(path: &Path, contents: &str) -> std::io::Result<()> {
+    std::fs::write(path, contents)
+}
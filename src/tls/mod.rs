@@ -0,0 +1,103 @@
+//! TLS termination shared by the capture proxy and the mock server
+//!
+//! Capture intercepts HTTPS to record it and serve intercepts it to replay
+//! it, so both present the same certificates and offer the same protocols.
+//! Their server configs are built here and nowhere else.
+
+pub mod certificate;
+
+#[cfg(test)]
+mod tests;
+
+use rustls::server::{ClientHello, ResolvesServerCert};
+use rustls::sign::CertifiedKey;
+use rustls::ServerConfig;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use tracing::{debug, warn};
+
+use crate::error::{Result, WebMockError};
+
+pub use certificate::WebMockCertificate;
+
+/// ALPN protocols offered to clients, most preferred first
+///
+/// Both servers accept HTTP/1.1 and HTTP/2 on a connection, but recorded
+/// exchanges replay most faithfully over HTTP/1.1, so clients that speak
+/// both get it.
+pub const ALPN_PROTOCOLS: [&[u8]; 2] = [b"http/1.1", b"h2"];
+
+/// Server config for terminating TLS with the cached webmock certificate
+pub fn server_config() -> Result<Arc<ServerConfig>> {
+    server_config_for(&WebMockCertificate::load_default()?)
+}
+
+/// Server config presenting `certificate`
+///
+/// Hosts outside the certificate's names, such as a CDN reached only to
+/// answer 404s, get a certificate of their own generated on demand.
+pub fn server_config_for(certificate: &WebMockCertificate) -> Result<Arc<ServerConfig>> {
+    let resolver = HostCertResolver::new(certificate)?;
+    let mut config = ServerConfig::builder()
+        .with_no_client_auth()
+        .with_cert_resolver(Arc::new(resolver));
+    config.alpn_protocols = ALPN_PROTOCOLS.iter().map(|alpn| alpn.to_vec()).collect();
+
+    debug!("TLS configuration generated successfully");
+    Ok(Arc::new(config))
+}
+
+/// Picks the shared certificate, or a per-host one for names it doesn't cover
+#[derive(Debug)]
+pub(crate) struct HostCertResolver {
+    shared: Arc<CertifiedKey>,
+    per_host: Mutex<HashMap<String, Arc<CertifiedKey>>>,
+}
+
+impl HostCertResolver {
+    pub(crate) fn new(certificate: &WebMockCertificate) -> Result<Self> {
+        Ok(Self {
+            shared: certified_key(certificate)?,
+            per_host: Mutex::new(HashMap::new()),
+        })
+    }
+
+    /// Key to present for a TLS server name, generating and caching one if needed
+    pub(crate) fn key_for(&self, server_name: Option<&str>) -> Arc<CertifiedKey> {
+        let host = match server_name {
+            Some(host) if !WebMockCertificate::covers(host) => host.to_ascii_lowercase(),
+            _ => return Arc::clone(&self.shared),
+        };
+
+        let mut per_host = self.per_host.lock().unwrap();
+        if let Some(key) = per_host.get(&host) {
+            return Arc::clone(key);
+        }
+
+        match WebMockCertificate::for_host(&host)
+            .and_then(|certificate| certified_key(&certificate))
+        {
+            Ok(key) => {
+                debug!("Generated TLS certificate for {}", host);
+                per_host.insert(host, Arc::clone(&key));
+                key
+            }
+            Err(e) => {
+                warn!("Failed to generate a certificate for {}: {}", host, e);
+                Arc::clone(&self.shared)
+            }
+        }
+    }
+}
+
+impl ResolvesServerCert for HostCertResolver {
+    fn resolve(&self, client_hello: ClientHello<'_>) -> Option<Arc<CertifiedKey>> {
+        Some(self.key_for(client_hello.server_name()))
+    }
+}
+
+fn certified_key(certificate: &WebMockCertificate) -> Result<Arc<CertifiedKey>> {
+    let key = rustls::crypto::aws_lc_rs::sign::any_supported_type(&certificate.private_key()?)
+        .map_err(|e| WebMockError::config(format!("Failed to create TLS config: {}", e)))?;
+    Ok(Arc::new(CertifiedKey::new(certificate.cert_chain()?, key)))
+}
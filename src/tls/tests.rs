@@ -0,0 +1,40 @@
+use std::sync::Arc;
+
+use super::{server_config_for, HostCertResolver, WebMockCertificate, ALPN_PROTOCOLS};
+
+#[test]
+fn test_server_config_offers_http1_before_h2() {
+    let certificate = WebMockCertificate::generate().unwrap();
+    let config = server_config_for(&certificate).unwrap();
+
+    assert_eq!(
+        config.alpn_protocols,
+        vec![b"http/1.1".to_vec(), b"h2".to_vec()]
+    );
+    assert_eq!(config.alpn_protocols.len(), ALPN_PROTOCOLS.len());
+}
+
+#[test]
+fn test_resolver_generates_keys_only_for_uncovered_hosts() {
+    let certificate = WebMockCertificate::generate().unwrap();
+    let resolver = HostCertResolver::new(&certificate).unwrap();
+    let shared = resolver.key_for(None);
+
+    // Names on the shared certificate, and clients sending no name, share its key
+    for host in ["localhost", "example.com", "maps.google.com"] {
+        assert!(
+            Arc::ptr_eq(&resolver.key_for(Some(host)), &shared),
+            "{}",
+            host
+        );
+    }
+
+    // Other hosts get a key of their own, generated once per host
+    let cdn = resolver.key_for(Some("fonts.gstatic.com"));
+    assert!(!Arc::ptr_eq(&cdn, &shared));
+    assert!(Arc::ptr_eq(
+        &resolver.key_for(Some("Fonts.GStatic.com")),
+        &cdn
+    ));
+    assert!(!Arc::ptr_eq(&resolver.key_for(Some("shop.example")), &cdn));
+}
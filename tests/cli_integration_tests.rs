@@ -77,16 +77,17 @@ fn test_cli_list_empty_snapshots() {
     println!("Stderr: {}", String::from_utf8_lossy(&output.stderr));
 
     assert!(output.status.success());
-    let stdout = String::from_utf8_lossy(&output.stdout);
+    let stderr = String::from_utf8_lossy(&output.stderr);
 
     // 期望看到空快照的输出
     assert!(
-        stdout.contains("No Snapshots Found")
-            || stdout.contains("No snapshots")
-            || stdout.contains("You haven't created any snapshots yet"),
+        stderr.contains("No Snapshots Found")
+            || stderr.contains("No snapshots")
+            || stderr.contains("You haven't created any snapshots yet"),
         "Expected empty snapshots message, but got: {}",
-        stdout
+        stderr
     );
+    assert!(output.stdout.is_empty());
 }
 
 #[test]
@@ -184,3 +185,387 @@ fn test_cli_serve_with_invalid_port() -> Result<(), Box<dyn std::error::Error>>
     assert!(combined.contains("not found") || combined.contains("error"));
     Ok(())
 }
+
+#[tokio::test]
+async fn test_cli_serve_exits_when_idle_and_releases_port() {
+    use std::time::{Duration, Instant};
+    use webmock_cli::storage::{Snapshot, Storage};
+
+    let (_temp_dir, data_dir) = setup_test_env();
+    Storage::new(data_dir.clone().into())
+        .save_snapshot(Snapshot {
+            name: "idle-site".to_string(),
+            url: "https://example.com/".to_string(),
+            created_at: chrono::Utc::now(),
+            requests: Vec::new(),
+            ..Default::default()
+        })
+        .await
+        .unwrap();
+
+    let port = std::net::TcpListener::bind("127.0.0.1:0")
+        .unwrap()
+        .local_addr()
+        .unwrap()
+        .port();
+    let binary_path = get_webmock_binary_path();
+    assert!(Path::new(binary_path).exists(), "WebMock binary not found");
+    let mut child = Command::new(binary_path)
+        .args([
+            "serve",
+            "idle-site",
+            "--port",
+            &port.to_string(),
+            "--storage",
+            &data_dir,
+            "--idle-timeout",
+            "1s",
+            "--max-lifetime",
+            "30s",
+        ])
+        .stdout(std::process::Stdio::null())
+        .stderr(std::process::Stdio::piped())
+        .spawn()
+        .expect("Failed to start webmock serve");
+
+    let deadline = Instant::now() + Duration::from_secs(15);
+    let status = loop {
+        if let Some(status) = child.try_wait().unwrap() {
+            break status;
+        }
+        if Instant::now() > deadline {
+            child.kill().unwrap();
+            panic!("serve --idle-timeout did not exit");
+        }
+        tokio::time::sleep(Duration::from_millis(100)).await;
+    };
+
+    assert!(status.success());
+    let mut stderr = String::new();
+    std::io::Read::read_to_string(&mut child.stderr.take().unwrap(), &mut stderr).unwrap();
+    assert!(stderr.contains("--idle-timeout"), "{}", stderr);
+    assert!(std::net::TcpListener::bind(("127.0.0.1", port)).is_ok());
+}
+
+#[tokio::test]
+async fn test_cli_serve_daemon_runs_until_stopped() {
+    use std::time::Duration;
+    use webmock_cli::storage::{Snapshot, Storage};
+
+    let (temp_dir, data_dir) = setup_test_env();
+    Storage::new(data_dir.clone().into())
+        .save_snapshot(Snapshot {
+            name: "daemon-site".to_string(),
+            url: "https://example.com/".to_string(),
+            created_at: chrono::Utc::now(),
+            requests: Vec::new(),
+            ..Default::default()
+        })
+        .await
+        .unwrap();
+
+    let port = std::net::TcpListener::bind("127.0.0.1:0")
+        .unwrap()
+        .local_addr()
+        .unwrap()
+        .port();
+    let pid_file = temp_dir.path().join("webmock.pid");
+    let log_file = temp_dir.path().join("webmock.log");
+    let pid_path = pid_file.to_str().unwrap();
+
+    // The server keeps running after `serve --daemon` returns
+    let output = run_webmock_command(
+        &[
+            "serve",
+            "daemon-site",
+            "--port",
+            &port.to_string(),
+            "--storage",
+            &data_dir,
+            "--daemon",
+            "--pid-file",
+            pid_path,
+            "--log-file",
+            log_file.to_str().unwrap(),
+            "--max-lifetime",
+            "60s",
+        ],
+        &[],
+    );
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    let pid: u32 = std::fs::read_to_string(&pid_file)
+        .unwrap()
+        .trim()
+        .parse()
+        .unwrap();
+    assert_ne!(pid, 0);
+    assert!(std::net::TcpStream::connect_timeout(
+        &([127, 0, 0, 1], port).into(),
+        Duration::from_secs(5)
+    )
+    .is_ok());
+
+    let output = run_webmock_command(&["stop", "--pid-file", pid_path], &[]);
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    assert!(!pid_file.exists());
+    assert!(std::net::TcpListener::bind(("127.0.0.1", port)).is_ok());
+    let log = std::fs::read_to_string(&log_file).unwrap();
+    assert!(log.contains("Serving snapshot: daemon-site"), "{}", log);
+
+    // Stopping again finds nothing to stop and still succeeds
+    let output = run_webmock_command(&["stop", "--pid-file", pid_path], &[]);
+    assert!(output.status.success());
+}
+
+/// Run webmock as in a scratch container, without HOME or XDG_DATA_HOME
+fn run_webmock_without_home(args: &[&str], envs: &[(&str, &str)]) -> std::process::Output {
+    let binary_path = get_webmock_binary_path();
+    assert!(Path::new(binary_path).exists(), "WebMock binary not found");
+    Command::new(binary_path)
+        .args(args)
+        .env_remove("HOME")
+        .env_remove("XDG_DATA_HOME")
+        .env_remove("WEBMOCK_SKIP_PERMISSION_CHECK")
+        .envs(envs.iter().copied())
+        .output()
+        .expect("Failed to execute webmock command")
+}
+
+#[test]
+fn test_cli_list_with_explicit_storage_without_home() {
+    let (_temp_dir, data_dir) = setup_test_env();
+
+    let output = run_webmock_without_home(&["list", "--storage", &data_dir], &[]);
+
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        output.status.success(),
+        "list failed: {}{}",
+        String::from_utf8_lossy(&output.stdout),
+        stderr
+    );
+    assert!(stderr.contains("No Snapshots Found"));
+}
+
+#[test]
+fn test_cli_list_uses_xdg_data_home_without_home() {
+    let temp_dir = TempDir::new().unwrap();
+    let data_home = temp_dir.path().to_string_lossy().into_owned();
+
+    let output = run_webmock_without_home(&["list"], &[("XDG_DATA_HOME", &data_home)]);
+
+    assert!(
+        output.status.success(),
+        "list failed: {}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    assert!(temp_dir.path().join("webmock").is_dir());
+}
+
+#[tokio::test]
+async fn test_cli_capture_validation_with_explicit_storage_without_home() {
+    use webmock_cli::storage::{Snapshot, Storage};
+
+    let (_temp_dir, data_dir) = setup_test_env();
+    Storage::new(data_dir.clone().into())
+        .save_snapshot(Snapshot {
+            name: "existing".to_string(),
+            url: "https://example.com/".to_string(),
+            created_at: chrono::Utc::now(),
+            requests: Vec::new(),
+            ..Default::default()
+        })
+        .await
+        .unwrap();
+
+    // Runs the system checks and input validation, then reuses the snapshot
+    // instead of launching a browser
+    let output = run_webmock_without_home(
+        &[
+            "capture",
+            "https://example.com/",
+            "--name",
+            "existing",
+            "--storage",
+            &data_dir,
+            "--if-missing",
+        ],
+        &[],
+    );
+
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        output.status.success(),
+        "capture failed: {}{}",
+        String::from_utf8_lossy(&output.stdout),
+        stderr
+    );
+    assert!(stderr.contains("nothing to capture"));
+    assert!(!stderr.contains("home directory"));
+}
+
+/// Set up storage holding one empty snapshot named `site`
+async fn setup_test_env_with_snapshot() -> (TempDir, String) {
+    use webmock_cli::storage::{Snapshot, Storage};
+
+    let (temp_dir, data_dir) = setup_test_env();
+    Storage::new(data_dir.clone().into())
+        .save_snapshot(Snapshot {
+            name: "site".to_string(),
+            url: "https://example.com/".to_string(),
+            created_at: chrono::Utc::now(),
+            requests: Vec::new(),
+            ..Default::default()
+        })
+        .await
+        .unwrap();
+    (temp_dir, data_dir)
+}
+
+#[tokio::test]
+async fn test_cli_list_prints_snapshots_to_stdout_and_messages_to_stderr() {
+    let (_temp_dir, data_dir) = setup_test_env_with_snapshot().await;
+
+    let output = run_webmock_command(&["list", "--storage", &data_dir], &[]);
+
+    assert!(output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stdout.contains("site") && stdout.contains("https://example.com/"));
+    assert!(!stdout.contains("Found 1 Snapshot"), "{}", stdout);
+    assert!(!stdout.contains("Next Steps"), "{}", stdout);
+    assert!(stderr.contains("Found 1 Snapshot"), "{}", stderr);
+    assert!(!stderr.contains("https://example.com/"), "{}", stderr);
+}
+
+#[tokio::test]
+async fn test_cli_list_summary_json_is_the_only_stdout() {
+    let (_temp_dir, data_dir) = setup_test_env_with_snapshot().await;
+
+    let output = run_webmock_command(
+        &["list", "--summary", "--json", "--storage", &data_dir],
+        &[],
+    );
+
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    let json: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(json["snapshot_count"], 1);
+}
+
+#[tokio::test]
+async fn test_cli_inspect_prints_details_to_stdout_and_messages_to_stderr() {
+    let (_temp_dir, data_dir) = setup_test_env_with_snapshot().await;
+
+    let output = run_webmock_command(&["inspect", "site", "--storage", &data_dir], &[]);
+
+    assert!(output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stdout.contains("Snapshot Overview"), "{}", stdout);
+    assert!(!stdout.contains("Inspecting snapshot"), "{}", stdout);
+    assert!(stderr.contains("Inspecting snapshot"), "{}", stderr);
+    assert!(!stderr.contains("Snapshot Overview"), "{}", stderr);
+}
+
+#[tokio::test]
+async fn test_cli_quiet_keeps_data_and_warnings() {
+    let (_temp_dir, data_dir) = setup_test_env_with_snapshot().await;
+
+    let output = run_webmock_command(&["--quiet", "list", "--storage", &data_dir], &[]);
+    assert!(output.status.success());
+    assert!(String::from_utf8_lossy(&output.stdout).contains("site"));
+    assert!(
+        output.stderr.is_empty(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+
+    let output = run_webmock_command(&["inspect", "site", "--storage", &data_dir, "-q"], &[]);
+    assert!(output.status.success());
+    assert!(String::from_utf8_lossy(&output.stdout).contains("Snapshot Overview"));
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(!stderr.contains("Inspecting snapshot"), "{}", stderr);
+    assert!(stderr.contains("No records found"), "{}", stderr);
+}
+
+#[tokio::test]
+async fn test_cli_no_color_and_no_color_env_disable_colors() {
+    let (_temp_dir, data_dir) = setup_test_env_with_snapshot().await;
+    let has_ansi = |output: &std::process::Output| {
+        output.stdout.contains(&0x1b) || output.stderr.contains(&0x1b)
+    };
+
+    // Colors are forced on even though the output is piped
+    let colored = run_webmock_command(
+        &["list", "--storage", &data_dir],
+        &[("CLICOLOR_FORCE", "1")],
+    );
+    assert!(has_ansi(&colored));
+
+    let output = run_webmock_command(
+        &["list", "--no-color", "--storage", &data_dir],
+        &[("CLICOLOR_FORCE", "1")],
+    );
+    assert!(output.status.success());
+    assert!(!has_ansi(&output));
+
+    let output = run_webmock_command(
+        &["list", "--storage", &data_dir],
+        &[("CLICOLOR_FORCE", "1"), ("NO_COLOR", "1")],
+    );
+    assert!(output.status.success());
+    assert!(!has_ansi(&output));
+}
+
+#[tokio::test]
+async fn test_cli_inspect_record_table_respects_no_color() {
+    use std::collections::HashMap;
+    use webmock_cli::capture::proxy::records::{RequestRecord, ResponseRecord};
+    use webmock_cli::storage::{Snapshot, Storage};
+
+    let (_temp_dir, data_dir) = setup_test_env();
+    Storage::new(data_dir.clone().into())
+        .save_snapshot(Snapshot {
+            name: "site".to_string(),
+            url: "https://example.com/".to_string(),
+            created_at: chrono::Utc::now(),
+            requests: vec![RequestRecord::new(
+                "GET".to_string(),
+                "https://example.com/".to_string(),
+                HashMap::new(),
+                None,
+                ResponseRecord::new(200, HashMap::new(), b"ok".to_vec(), None),
+            )],
+            ..Default::default()
+        })
+        .await
+        .unwrap();
+
+    let colored = run_webmock_command(
+        &["inspect", "site", "--storage", &data_dir],
+        &[("CLICOLOR_FORCE", "1")],
+    );
+    assert!(colored.status.success());
+    assert!(colored.stdout.contains(&0x1b));
+
+    let output = run_webmock_command(
+        &["inspect", "site", "--no-color", "--storage", &data_dir],
+        &[("CLICOLOR_FORCE", "1")],
+    );
+    assert!(output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("GET"), "{}", stdout);
+    assert!(!output.stdout.contains(&0x1b), "{}", stdout);
+}
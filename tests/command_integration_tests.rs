@@ -13,7 +13,8 @@ use webmock_cli::{
     capture::proxy::records::{RequestRecord, ResponseRecord},
     commands::{list_command, serve_command},
     error::{Result, WebMockError},
-    storage::{Snapshot, Storage},
+    serve::ServeOptions,
+    storage::{ServeDefaults, Snapshot, Storage},
 };
 
 /// Helper to set up a temporary home directory for testing
@@ -46,8 +47,19 @@ async fn create_test_snapshot_in_storage(storage: &Storage, name: &str) -> Resul
             headers,
             body: html_content.as_bytes().to_vec(),
             content_type: "text/html".to_string(),
+            failure: None,
+            set_cookie: Vec::new(),
+            http_version: None,
+            reason: None,
+            informational: Vec::new(),
         },
         timestamp: chrono::Utc::now(),
+        initiator: None,
+        timing: Default::default(),
+        vary_key: Default::default(),
+        variant: None,
+        sequence: None,
+        frame_id: None,
     };
 
     let snapshot = Snapshot {
@@ -55,6 +67,7 @@ async fn create_test_snapshot_in_storage(storage: &Storage, name: &str) -> Resul
         url: format!("https://example.com/{}", name),
         created_at: chrono::Utc::now(),
         requests: vec![request],
+        ..Default::default()
     };
 
     storage.save_snapshot(snapshot).await
@@ -79,6 +92,7 @@ mod command_integration_tests {
         let storage = Storage::new(webmock_dir.clone());
         storage
             .ensure_snapshots_dir()
+            .await
             .expect("Failed to create snapshots dir");
 
         // Create test snapshots
@@ -90,7 +104,7 @@ mod command_integration_tests {
             .expect("Failed to create test snapshot 2");
 
         // Test list command with explicit storage path
-        let result = list_command(Some(webmock_dir.to_string_lossy().to_string())).await;
+        let result = list_command(Some(webmock_dir.to_string_lossy().to_string()), false).await;
         assert!(
             result.is_ok(),
             "List command should succeed with snapshots present"
@@ -112,10 +126,11 @@ mod command_integration_tests {
         let storage = Storage::new(webmock_dir.clone());
         storage
             .ensure_snapshots_dir()
+            .await
             .expect("Failed to create snapshots dir");
 
         // Test list command with explicit storage path
-        let result = list_command(Some(webmock_dir.to_string_lossy().to_string())).await;
+        let result = list_command(Some(webmock_dir.to_string_lossy().to_string()), false).await;
         if let Err(e) = &result {
             println!("List command failed with error: {:?}", e);
         }
@@ -132,7 +147,7 @@ mod command_integration_tests {
         env::set_var("WEBMOCK_SKIP_PERMISSION_CHECK", "1");
 
         // Don't create .webmock directory - test error handling
-        let result = list_command(None).await;
+        let result = list_command(None, false).await;
         // Should handle missing directory gracefully (returns empty list)
         assert!(result.is_ok());
     }
@@ -151,13 +166,16 @@ mod command_integration_tests {
         let storage = Storage::new(webmock_dir);
         storage
             .ensure_snapshots_dir()
+            .await
             .expect("Failed to create snapshots dir");
         create_test_snapshot_in_storage(&storage, "test-serve-snapshot")
             .await
             .expect("Failed to create test snapshot");
 
         // Test serve command with timeout (since it runs indefinitely)
-        let serve_future = serve_command("test-serve-snapshot", 8080, None);
+        let flags = ServeDefaults::default();
+        let serve_future =
+            serve_command("test-serve-snapshot", &flags, None, ServeOptions::default());
         let result = timeout(Duration::from_millis(500), serve_future).await;
 
         // Should timeout (meaning server started successfully) or return port conflict error
@@ -178,10 +196,17 @@ mod command_integration_tests {
         let storage = Storage::new(webmock_dir);
         storage
             .ensure_snapshots_dir()
+            .await
             .expect("Failed to create snapshots dir");
 
         // Test serve command with non-existent snapshot
-        let result = serve_command("non-existent-snapshot", 8080, None).await;
+        let result = serve_command(
+            "non-existent-snapshot",
+            &ServeDefaults::default(),
+            None,
+            ServeOptions::default(),
+        )
+        .await;
         assert!(result.is_err());
         assert!(matches!(
             result.unwrap_err(),
@@ -203,6 +228,7 @@ mod command_integration_tests {
         let storage = Storage::new(webmock_dir);
         storage
             .ensure_snapshots_dir()
+            .await
             .expect("Failed to create snapshots dir");
         create_test_snapshot_in_storage(&storage, "test-delete-snapshot")
             .await
@@ -242,6 +268,7 @@ mod command_integration_tests {
         let storage = Storage::new(webmock_dir);
         storage
             .ensure_snapshots_dir()
+            .await
             .expect("Failed to create snapshots dir");
 
         // Test delete with non-existent snapshot
@@ -276,7 +303,7 @@ mod error_recovery_tests {
         let storage = Storage::new(invalid_path.clone());
 
         // Should handle permission errors gracefully
-        let result = storage.ensure_snapshots_dir();
+        let result = storage.ensure_snapshots_dir().await;
 
         // The test should pass if we get any kind of error (permission denied, path not found, etc.)
         // This makes the test more robust across different environments
@@ -305,10 +332,11 @@ mod error_recovery_tests {
         let storage = Storage::new(temp_dir.path().to_path_buf());
         storage
             .ensure_snapshots_dir()
+            .await
             .expect("Failed to create snapshots dir");
 
         // Create a corrupted snapshot file
-        let snapshot_path = storage.get_snapshot_path("corrupted");
+        let snapshot_path = storage.get_snapshot_path("corrupted").unwrap();
         tokio::fs::write(&snapshot_path, b"invalid msgpack data")
             .await
             .expect("Failed to write corrupted file");
@@ -334,6 +362,7 @@ mod error_recovery_tests {
         let storage = Storage::new(temp_dir.path().to_path_buf());
         storage
             .ensure_snapshots_dir()
+            .await
             .expect("Failed to create snapshots dir");
 
         // Create multiple snapshots sequentially
@@ -368,6 +397,7 @@ mod error_recovery_tests {
         let storage = Storage::new(temp_dir.path().to_path_buf());
         storage
             .ensure_snapshots_dir()
+            .await
             .expect("Failed to create snapshots dir");
 
         // Create a snapshot with many requests (simulating a large capture session)
@@ -392,8 +422,19 @@ mod error_recovery_tests {
                     headers,
                     body: response_body.as_bytes().to_vec(),
                     content_type: "application/json".to_string(),
+                    failure: None,
+                    set_cookie: Vec::new(),
+                    http_version: None,
+                    reason: None,
+                    informational: Vec::new(),
                 },
                 timestamp: chrono::Utc::now(),
+                initiator: None,
+                timing: Default::default(),
+                vary_key: Default::default(),
+                variant: None,
+                sequence: None,
+                frame_id: None,
             });
         }
 
@@ -402,6 +443,7 @@ mod error_recovery_tests {
             url: "https://api.example.com/".to_string(),
             created_at: chrono::Utc::now(),
             requests,
+            ..Default::default()
         };
 
         // Test saving large snapshot
@@ -428,6 +470,7 @@ mod error_recovery_tests {
         let storage = Storage::new(temp_dir.path().to_path_buf());
         storage
             .ensure_snapshots_dir()
+            .await
             .expect("Failed to create snapshots dir");
 
         // Create a very large snapshot to test disk space handling
@@ -450,8 +493,19 @@ mod error_recovery_tests {
                 headers,
                 body: large_body,
                 content_type: "application/octet-stream".to_string(),
+                failure: None,
+                set_cookie: Vec::new(),
+                http_version: None,
+                reason: None,
+                informational: Vec::new(),
             },
             timestamp: chrono::Utc::now(),
+            initiator: None,
+            timing: Default::default(),
+            vary_key: Default::default(),
+            variant: None,
+            sequence: None,
+            frame_id: None,
         };
 
         let large_snapshot = Snapshot {
@@ -459,6 +513,7 @@ mod error_recovery_tests {
             url: "https://example.com/".to_string(),
             created_at: chrono::Utc::now(),
             requests: vec![request],
+            ..Default::default()
         };
 
         // This should succeed in most test environments
@@ -40,8 +40,19 @@ async fn create_minimal_snapshot(name: &str) -> Snapshot {
             headers,
             body: b"<html><body>Test</body></html>".to_vec(),
             content_type: "text/html".to_string(),
+            failure: None,
+            set_cookie: Vec::new(),
+            http_version: None,
+            reason: None,
+            informational: Vec::new(),
         },
         timestamp: chrono::Utc::now(),
+        initiator: None,
+        timing: Default::default(),
+        vary_key: Default::default(),
+        variant: None,
+        sequence: None,
+        frame_id: None,
     };
 
     Snapshot {
@@ -49,6 +60,7 @@ async fn create_minimal_snapshot(name: &str) -> Snapshot {
         url: format!("https://example.com/{}", name),
         created_at: chrono::Utc::now(),
         requests: vec![request],
+        ..Default::default()
     }
 }
 
@@ -62,6 +74,7 @@ mod storage_error_tests {
         let storage = Storage::new(temp_dir.path().to_path_buf());
         storage
             .ensure_snapshots_dir()
+            .await
             .expect("Failed to create snapshots dir");
 
         // Try to load non-existent snapshot
@@ -82,10 +95,11 @@ mod storage_error_tests {
         let storage = Storage::new(temp_dir.path().to_path_buf());
         storage
             .ensure_snapshots_dir()
+            .await
             .expect("Failed to create snapshots dir");
 
         // Create a corrupted snapshot file
-        let snapshot_path = storage.get_snapshot_path("corrupted");
+        let snapshot_path = storage.get_snapshot_path("corrupted").unwrap();
         tokio::fs::write(&snapshot_path, b"invalid msgpack data")
             .await
             .expect("Failed to write corrupted file");
@@ -112,7 +126,7 @@ mod storage_error_tests {
         let storage = Storage::new(invalid_path);
 
         // Should fail to create snapshots directory
-        let result = storage.ensure_snapshots_dir();
+        let result = storage.ensure_snapshots_dir().await;
         assert!(result.is_err());
 
         // The exact error type may vary by system, but it should be an error
@@ -130,10 +144,11 @@ mod storage_error_tests {
         let storage = Storage::new(temp_dir.path().to_path_buf());
         storage
             .ensure_snapshots_dir()
+            .await
             .expect("Failed to create snapshots dir");
 
         // Create an empty snapshot file
-        let snapshot_path = storage.get_snapshot_path("empty");
+        let snapshot_path = storage.get_snapshot_path("empty").unwrap();
         tokio::fs::write(&snapshot_path, b"")
             .await
             .expect("Failed to write empty file");
@@ -159,6 +174,7 @@ mod storage_error_tests {
         let storage = Storage::new(temp_dir.path().to_path_buf());
         storage
             .ensure_snapshots_dir()
+            .await
             .expect("Failed to create snapshots dir");
 
         // Create and save a snapshot
@@ -194,6 +210,7 @@ mod server_error_tests {
         let storage = Storage::new(temp_dir.path().to_path_buf());
         storage
             .ensure_snapshots_dir()
+            .await
             .expect("Failed to create snapshots dir");
 
         let snapshot = create_minimal_snapshot("port-test").await;
@@ -234,6 +251,7 @@ mod server_error_tests {
         let storage = Storage::new(temp_dir.path().to_path_buf());
         storage
             .ensure_snapshots_dir()
+            .await
             .expect("Failed to create snapshots dir");
 
         // Create snapshot with no requests
@@ -242,6 +260,7 @@ mod server_error_tests {
             url: "https://example.com/".to_string(),
             created_at: chrono::Utc::now(),
             requests: Vec::new(),
+            ..Default::default()
         };
 
         storage
@@ -283,6 +302,7 @@ mod server_error_tests {
         let storage = Storage::new(temp_dir.path().to_path_buf());
         storage
             .ensure_snapshots_dir()
+            .await
             .expect("Failed to create snapshots dir");
 
         // Create snapshot with potentially problematic response data
@@ -299,8 +319,19 @@ mod server_error_tests {
                 headers,
                 body: b"{ invalid json content".to_vec(), // Malformed JSON
                 content_type: "application/json".to_string(),
+                failure: None,
+                set_cookie: Vec::new(),
+                http_version: None,
+                reason: None,
+                informational: Vec::new(),
             },
             timestamp: chrono::Utc::now(),
+            initiator: None,
+            timing: Default::default(),
+            vary_key: Default::default(),
+            variant: None,
+            sequence: None,
+            frame_id: None,
         };
 
         let malformed_snapshot = Snapshot {
@@ -308,6 +339,7 @@ mod server_error_tests {
             url: "https://example.com/".to_string(),
             created_at: chrono::Utc::now(),
             requests: vec![request],
+            ..Default::default()
         };
 
         storage
@@ -353,7 +385,7 @@ mod recovery_mechanism_tests {
         let storage = Storage::new(storage_path.clone());
 
         // Should auto-create directory when needed
-        let result = storage.ensure_snapshots_dir();
+        let result = storage.ensure_snapshots_dir().await;
         assert!(result.is_ok());
         assert!(storage_path.exists());
         assert!(storage_path.join("snapshots").exists());
@@ -365,6 +397,7 @@ mod recovery_mechanism_tests {
         let storage = Storage::new(temp_dir.path().to_path_buf());
         storage
             .ensure_snapshots_dir()
+            .await
             .expect("Failed to create snapshots dir");
 
         // Create valid snapshots
@@ -383,6 +416,7 @@ mod recovery_mechanism_tests {
         // Create a corrupted file in the snapshots directory
         let snapshots_dir = storage
             .ensure_snapshots_dir()
+            .await
             .expect("Failed to ensure snapshots dir");
         let corrupted_path = snapshots_dir.join("corrupted.msgpack");
         tokio::fs::write(&corrupted_path, b"invalid data")
@@ -414,6 +448,7 @@ mod recovery_mechanism_tests {
         let storage = Storage::new(temp_dir.path().to_path_buf());
         storage
             .ensure_snapshots_dir()
+            .await
             .expect("Failed to create snapshots dir");
 
         let snapshot = create_minimal_snapshot("disconnect-test").await;
@@ -461,6 +496,7 @@ mod recovery_mechanism_tests {
         let storage = Storage::new(temp_dir.path().to_path_buf());
         storage
             .ensure_snapshots_dir()
+            .await
             .expect("Failed to create snapshots dir");
 
         // Create multiple snapshots concurrently
@@ -506,6 +542,7 @@ mod recovery_mechanism_tests {
         let storage = Storage::new(temp_dir.path().to_path_buf());
         storage
             .ensure_snapshots_dir()
+            .await
             .expect("Failed to create snapshots dir");
 
         // Create a snapshot with a large response body
@@ -527,8 +564,19 @@ mod recovery_mechanism_tests {
                 headers,
                 body: large_body.clone(),
                 content_type: "application/octet-stream".to_string(),
+                failure: None,
+                set_cookie: Vec::new(),
+                http_version: None,
+                reason: None,
+                informational: Vec::new(),
             },
             timestamp: chrono::Utc::now(),
+            initiator: None,
+            timing: Default::default(),
+            vary_key: Default::default(),
+            variant: None,
+            sequence: None,
+            frame_id: None,
         };
 
         let large_snapshot = Snapshot {
@@ -536,6 +584,7 @@ mod recovery_mechanism_tests {
             url: "https://example.com/".to_string(),
             created_at: chrono::Utc::now(),
             requests: vec![request],
+            ..Default::default()
         };
 
         // Test saving large snapshot
@@ -167,6 +167,7 @@ async fn test_snapshot_persistence_and_loading() {
     let storage = Storage::new(temp_dir.path().to_path_buf());
     storage
         .ensure_snapshots_dir()
+        .await
         .expect("Failed to create snapshots dir");
 
     // Create and save a snapshot
@@ -18,6 +18,7 @@ async fn test_snapshot_not_found_error() {
     let storage = Storage::new(temp_dir.path().to_path_buf());
     storage
         .ensure_snapshots_dir()
+        .await
         .expect("Failed to create snapshots dir");
 
     // Try to load non-existent snapshot
@@ -60,10 +61,11 @@ async fn test_corrupted_snapshot_handling() {
     let storage = Storage::new(temp_dir.path().to_path_buf());
     storage
         .ensure_snapshots_dir()
+        .await
         .expect("Failed to create snapshots dir");
 
     // Create a corrupted snapshot file
-    let snapshot_path = storage.get_snapshot_path("corrupted-snapshot");
+    let snapshot_path = storage.get_snapshot_path("corrupted-snapshot").unwrap();
     tokio::fs::write(&snapshot_path, b"invalid msgpack data")
         .await
         .expect("Failed to write corrupted file");
@@ -84,6 +86,7 @@ async fn test_empty_snapshot_handling() {
     let storage = Storage::new(temp_dir.path().to_path_buf());
     storage
         .ensure_snapshots_dir()
+        .await
         .expect("Failed to create snapshots dir");
 
     // Create snapshot with no requests
@@ -92,6 +95,7 @@ async fn test_empty_snapshot_handling() {
         url: "https://example.com/".to_string(),
         created_at: chrono::Utc::now(),
         requests: Vec::new(),
+        ..Default::default()
     };
 
     storage
@@ -131,6 +135,7 @@ async fn test_large_response_handling() {
     let storage = Storage::new(temp_dir.path().to_path_buf());
     storage
         .ensure_snapshots_dir()
+        .await
         .expect("Failed to create snapshots dir");
 
     // Create snapshot with large response body (1MB)
@@ -148,8 +153,19 @@ async fn test_large_response_handling() {
             headers,
             body: large_body.clone(),
             content_type: "text/plain".to_string(),
+            failure: None,
+            set_cookie: Vec::new(),
+            http_version: None,
+            reason: None,
+            informational: Vec::new(),
         },
         timestamp: chrono::Utc::now(),
+        initiator: None,
+        timing: Default::default(),
+        vary_key: Default::default(),
+        variant: None,
+        sequence: None,
+        frame_id: None,
     };
 
     let large_snapshot = Snapshot {
@@ -157,6 +173,7 @@ async fn test_large_response_handling() {
         url: "https://example.com/".to_string(),
         created_at: chrono::Utc::now(),
         requests: vec![request],
+        ..Default::default()
     };
 
     // Test saving and loading large snapshot
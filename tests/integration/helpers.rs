@@ -28,6 +28,7 @@ pub async fn create_test_storage_with_samples() -> (TempDir, Storage) {
     // Ensure snapshots directory exists
     storage
         .ensure_snapshots_dir()
+        .await
         .expect("Failed to create snapshots dir");
 
     // Create sample HTML snapshot
@@ -84,8 +85,19 @@ pub async fn create_sample_html_snapshot() -> Snapshot {
             headers: html_headers,
             body: html_content.as_bytes().to_vec(),
             content_type: "text/html".to_string(),
+            failure: None,
+            set_cookie: Vec::new(),
+            http_version: None,
+            reason: None,
+            informational: Vec::new(),
         },
         timestamp: chrono::Utc::now(),
+        initiator: None,
+        timing: Default::default(),
+        vary_key: Default::default(),
+        variant: None,
+        sequence: None,
+        frame_id: None,
     });
 
     // CSS file
@@ -103,8 +115,19 @@ pub async fn create_sample_html_snapshot() -> Snapshot {
             headers: css_headers,
             body: css_content.as_bytes().to_vec(),
             content_type: "text/css".to_string(),
+            failure: None,
+            set_cookie: Vec::new(),
+            http_version: None,
+            reason: None,
+            informational: Vec::new(),
         },
         timestamp: chrono::Utc::now(),
+        initiator: None,
+        timing: Default::default(),
+        vary_key: Default::default(),
+        variant: None,
+        sequence: None,
+        frame_id: None,
     });
 
     // JavaScript file
@@ -125,8 +148,19 @@ pub async fn create_sample_html_snapshot() -> Snapshot {
             headers: js_headers,
             body: js_content.as_bytes().to_vec(),
             content_type: "application/javascript".to_string(),
+            failure: None,
+            set_cookie: Vec::new(),
+            http_version: None,
+            reason: None,
+            informational: Vec::new(),
         },
         timestamp: chrono::Utc::now(),
+        initiator: None,
+        timing: Default::default(),
+        vary_key: Default::default(),
+        variant: None,
+        sequence: None,
+        frame_id: None,
     });
 
     // Image file (mock PNG)
@@ -144,8 +178,19 @@ pub async fn create_sample_html_snapshot() -> Snapshot {
             headers: png_headers,
             body: png_data,
             content_type: "image/png".to_string(),
+            failure: None,
+            set_cookie: Vec::new(),
+            http_version: None,
+            reason: None,
+            informational: Vec::new(),
         },
         timestamp: chrono::Utc::now(),
+        initiator: None,
+        timing: Default::default(),
+        vary_key: Default::default(),
+        variant: None,
+        sequence: None,
+        frame_id: None,
     });
 
     Snapshot {
@@ -153,6 +198,7 @@ pub async fn create_sample_html_snapshot() -> Snapshot {
         url: "https://example.com/".to_string(),
         created_at: chrono::Utc::now(),
         requests,
+        ..Default::default()
     }
 }
 
@@ -176,8 +222,19 @@ pub async fn create_sample_api_snapshot() -> Snapshot {
             headers: api_headers.clone(),
             body: api_response.as_bytes().to_vec(),
             content_type: "application/json".to_string(),
+            failure: None,
+            set_cookie: Vec::new(),
+            http_version: None,
+            reason: None,
+            informational: Vec::new(),
         },
         timestamp: chrono::Utc::now(),
+        initiator: None,
+        timing: Default::default(),
+        vary_key: Default::default(),
+        variant: None,
+        sequence: None,
+        frame_id: None,
     });
 
     // POST API endpoint
@@ -195,8 +252,19 @@ pub async fn create_sample_api_snapshot() -> Snapshot {
             headers: api_headers,
             body: post_response.as_bytes().to_vec(),
             content_type: "application/json".to_string(),
+            failure: None,
+            set_cookie: Vec::new(),
+            http_version: None,
+            reason: None,
+            informational: Vec::new(),
         },
         timestamp: chrono::Utc::now(),
+        initiator: None,
+        timing: Default::default(),
+        vary_key: Default::default(),
+        variant: None,
+        sequence: None,
+        frame_id: None,
     });
 
     Snapshot {
@@ -204,5 +272,6 @@ pub async fn create_sample_api_snapshot() -> Snapshot {
         url: "https://api.example.com/".to_string(),
         created_at: chrono::Utc::now(),
         requests,
+        ..Default::default()
     }
 }
@@ -17,6 +17,7 @@ async fn test_various_content_types() {
     let storage = Storage::new(temp_dir.path().to_path_buf());
     storage
         .ensure_snapshots_dir()
+        .await
         .expect("Failed to create snapshots dir");
 
     let mut requests = Vec::new();
@@ -49,8 +50,19 @@ async fn test_various_content_types() {
                 headers,
                 body: body.clone(),
                 content_type: content_type.to_string(),
+                failure: None,
+                set_cookie: Vec::new(),
+                http_version: None,
+                reason: None,
+                informational: Vec::new(),
             },
             timestamp: chrono::Utc::now(),
+            initiator: None,
+            timing: Default::default(),
+            vary_key: Default::default(),
+            variant: None,
+            sequence: None,
+            frame_id: None,
         });
     }
 
@@ -59,6 +71,7 @@ async fn test_various_content_types() {
         url: "https://example.com/".to_string(),
         created_at: chrono::Utc::now(),
         requests,
+        ..Default::default()
     };
 
     storage
@@ -120,6 +133,7 @@ async fn test_http_methods_handling() {
     let storage = Storage::new(temp_dir.path().to_path_buf());
     storage
         .ensure_snapshots_dir()
+        .await
         .expect("Failed to create snapshots dir");
 
     let mut requests = Vec::new();
@@ -145,8 +159,19 @@ async fn test_http_methods_handling() {
                 headers,
                 body: response_body.as_bytes().to_vec(),
                 content_type: "application/json".to_string(),
+                failure: None,
+                set_cookie: Vec::new(),
+                http_version: None,
+                reason: None,
+                informational: Vec::new(),
             },
             timestamp: chrono::Utc::now(),
+            initiator: None,
+            timing: Default::default(),
+            vary_key: Default::default(),
+            variant: None,
+            sequence: None,
+            frame_id: None,
         });
     }
 
@@ -155,6 +180,7 @@ async fn test_http_methods_handling() {
         url: "https://api.example.com/".to_string(),
         created_at: chrono::Utc::now(),
         requests,
+        ..Default::default()
     };
 
     storage
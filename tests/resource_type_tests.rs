@@ -92,8 +92,19 @@ async fn create_content_types_snapshot() -> Snapshot {
                 headers,
                 body,
                 content_type: content_type.to_string(),
+                failure: None,
+                set_cookie: Vec::new(),
+                http_version: None,
+                reason: None,
+                informational: Vec::new(),
             },
             timestamp: chrono::Utc::now(),
+            initiator: None,
+            timing: Default::default(),
+            vary_key: Default::default(),
+            variant: None,
+            sequence: None,
+            frame_id: None,
         });
     }
 
@@ -102,6 +113,7 @@ async fn create_content_types_snapshot() -> Snapshot {
         url: "https://example.com/".to_string(),
         created_at: chrono::Utc::now(),
         requests,
+        ..Default::default()
     }
 }
 
@@ -123,8 +135,19 @@ async fn create_http_methods_snapshot() -> Snapshot {
             headers: get_headers,
             body: b"{\"users\": [{\"id\": 1, \"name\": \"John\"}]}".to_vec(),
             content_type: "application/json".to_string(),
+            failure: None,
+            set_cookie: Vec::new(),
+            http_version: None,
+            reason: None,
+            informational: Vec::new(),
         },
         timestamp: chrono::Utc::now(),
+        initiator: None,
+        timing: Default::default(),
+        vary_key: Default::default(),
+        variant: None,
+        sequence: None,
+        frame_id: None,
     });
 
     // POST request
@@ -145,8 +168,19 @@ async fn create_http_methods_snapshot() -> Snapshot {
             headers: post_headers,
             body: b"{\"id\": 2, \"name\": \"Jane\", \"email\": \"jane@example.com\"}".to_vec(),
             content_type: "application/json".to_string(),
+            failure: None,
+            set_cookie: Vec::new(),
+            http_version: None,
+            reason: None,
+            informational: Vec::new(),
         },
         timestamp: chrono::Utc::now(),
+        initiator: None,
+        timing: Default::default(),
+        vary_key: Default::default(),
+        variant: None,
+        sequence: None,
+        frame_id: None,
     });
 
     // PUT request
@@ -170,8 +204,19 @@ async fn create_http_methods_snapshot() -> Snapshot {
                 b"{\"id\": 1, \"name\": \"John Updated\", \"email\": \"john.updated@example.com\"}"
                     .to_vec(),
             content_type: "application/json".to_string(),
+            failure: None,
+            set_cookie: Vec::new(),
+            http_version: None,
+            reason: None,
+            informational: Vec::new(),
         },
         timestamp: chrono::Utc::now(),
+        initiator: None,
+        timing: Default::default(),
+        vary_key: Default::default(),
+        variant: None,
+        sequence: None,
+        frame_id: None,
     });
 
     // DELETE request
@@ -188,8 +233,19 @@ async fn create_http_methods_snapshot() -> Snapshot {
             headers: delete_headers,
             body: Vec::new(),
             content_type: "application/json".to_string(),
+            failure: None,
+            set_cookie: Vec::new(),
+            http_version: None,
+            reason: None,
+            informational: Vec::new(),
         },
         timestamp: chrono::Utc::now(),
+        initiator: None,
+        timing: Default::default(),
+        vary_key: Default::default(),
+        variant: None,
+        sequence: None,
+        frame_id: None,
     });
 
     // PATCH request
@@ -210,8 +266,19 @@ async fn create_http_methods_snapshot() -> Snapshot {
             body: b"{\"id\": 2, \"name\": \"Jane\", \"email\": \"jane.updated@example.com\"}"
                 .to_vec(),
             content_type: "application/json".to_string(),
+            failure: None,
+            set_cookie: Vec::new(),
+            http_version: None,
+            reason: None,
+            informational: Vec::new(),
         },
         timestamp: chrono::Utc::now(),
+        initiator: None,
+        timing: Default::default(),
+        vary_key: Default::default(),
+        variant: None,
+        sequence: None,
+        frame_id: None,
     });
 
     Snapshot {
@@ -219,6 +286,7 @@ async fn create_http_methods_snapshot() -> Snapshot {
         url: "https://api.example.com/".to_string(),
         created_at: chrono::Utc::now(),
         requests,
+        ..Default::default()
     }
 }
 
@@ -232,6 +300,7 @@ mod content_type_tests {
         let storage = Storage::new(temp_dir.path().to_path_buf());
         storage
             .ensure_snapshots_dir()
+            .await
             .expect("Failed to create snapshots dir");
 
         // Create and save snapshot with various content types
@@ -274,6 +343,7 @@ mod content_type_tests {
         let storage = Storage::new(temp_dir.path().to_path_buf());
         storage
             .ensure_snapshots_dir()
+            .await
             .expect("Failed to create snapshots dir");
 
         let snapshot = create_content_types_snapshot().await;
@@ -391,6 +461,7 @@ mod content_type_tests {
         let storage = Storage::new(temp_dir.path().to_path_buf());
         storage
             .ensure_snapshots_dir()
+            .await
             .expect("Failed to create snapshots dir");
 
         // Create snapshot with large content
@@ -409,8 +480,19 @@ mod content_type_tests {
                 headers,
                 body: large_content.as_bytes().to_vec(),
                 content_type: "text/plain".to_string(),
+                failure: None,
+                set_cookie: Vec::new(),
+                http_version: None,
+                reason: None,
+                informational: Vec::new(),
             },
             timestamp: chrono::Utc::now(),
+            initiator: None,
+            timing: Default::default(),
+            vary_key: Default::default(),
+            variant: None,
+            sequence: None,
+            frame_id: None,
         };
 
         let large_snapshot = Snapshot {
@@ -418,6 +500,7 @@ mod content_type_tests {
             url: "https://example.com/".to_string(),
             created_at: chrono::Utc::now(),
             requests: vec![request],
+            ..Default::default()
         };
 
         storage
@@ -462,6 +545,7 @@ mod http_method_tests {
         let storage = Storage::new(temp_dir.path().to_path_buf());
         storage
             .ensure_snapshots_dir()
+            .await
             .expect("Failed to create snapshots dir");
 
         let snapshot = create_http_methods_snapshot().await;
@@ -515,6 +599,7 @@ mod http_method_tests {
         let storage = Storage::new(temp_dir.path().to_path_buf());
         storage
             .ensure_snapshots_dir()
+            .await
             .expect("Failed to create snapshots dir");
 
         let snapshot = create_http_methods_snapshot().await;
@@ -600,6 +685,7 @@ mod http_method_tests {
         let storage = Storage::new(temp_dir.path().to_path_buf());
         storage
             .ensure_snapshots_dir()
+            .await
             .expect("Failed to create snapshots dir");
 
         // Create snapshot with custom headers
@@ -623,8 +709,19 @@ mod http_method_tests {
                 headers: response_headers.clone(),
                 body: b"{\"message\": \"Protected resource\"}".to_vec(),
                 content_type: "application/json".to_string(),
+                failure: None,
+                set_cookie: Vec::new(),
+                http_version: None,
+                reason: None,
+                informational: Vec::new(),
             },
             timestamp: chrono::Utc::now(),
+            initiator: None,
+            timing: Default::default(),
+            vary_key: Default::default(),
+            variant: None,
+            sequence: None,
+            frame_id: None,
         };
 
         let headers_snapshot = Snapshot {
@@ -632,6 +729,7 @@ mod http_method_tests {
             url: "https://api.example.com/".to_string(),
             created_at: chrono::Utc::now(),
             requests: vec![request],
+            ..Default::default()
         };
 
         storage
@@ -71,8 +71,19 @@ async fn create_comprehensive_test_snapshot() -> Snapshot {
             headers: html_headers,
             body: html_content.as_bytes().to_vec(),
             content_type: "text/html".to_string(),
+            failure: None,
+            set_cookie: Vec::new(),
+            http_version: None,
+            reason: None,
+            informational: Vec::new(),
         },
         timestamp: chrono::Utc::now(),
+        initiator: None,
+        timing: Default::default(),
+        vary_key: Default::default(),
+        variant: None,
+        sequence: None,
+        frame_id: None,
     });
 
     // CSS stylesheet
@@ -122,8 +133,19 @@ img {
             headers: css_headers,
             body: css_content.as_bytes().to_vec(),
             content_type: "text/css".to_string(),
+            failure: None,
+            set_cookie: Vec::new(),
+            http_version: None,
+            reason: None,
+            informational: Vec::new(),
         },
         timestamp: chrono::Utc::now(),
+        initiator: None,
+        timing: Default::default(),
+        vary_key: Default::default(),
+        variant: None,
+        sequence: None,
+        frame_id: None,
     });
 
     // JavaScript file
@@ -161,8 +183,19 @@ document.addEventListener('DOMContentLoaded', function() {
             headers: js_headers,
             body: js_content.as_bytes().to_vec(),
             content_type: "application/javascript".to_string(),
+            failure: None,
+            set_cookie: Vec::new(),
+            http_version: None,
+            reason: None,
+            informational: Vec::new(),
         },
         timestamp: chrono::Utc::now(),
+        initiator: None,
+        timing: Default::default(),
+        vary_key: Default::default(),
+        variant: None,
+        sequence: None,
+        frame_id: None,
     });
 
     // API endpoint
@@ -196,8 +229,19 @@ document.addEventListener('DOMContentLoaded', function() {
             headers: api_headers,
             body: api_response.as_bytes().to_vec(),
             content_type: "application/json".to_string(),
+            failure: None,
+            set_cookie: Vec::new(),
+            http_version: None,
+            reason: None,
+            informational: Vec::new(),
         },
         timestamp: chrono::Utc::now(),
+        initiator: None,
+        timing: Default::default(),
+        vary_key: Default::default(),
+        variant: None,
+        sequence: None,
+        frame_id: None,
     });
 
     // Favicon
@@ -220,8 +264,19 @@ document.addEventListener('DOMContentLoaded', function() {
             headers: favicon_headers,
             body: favicon_data,
             content_type: "image/x-icon".to_string(),
+            failure: None,
+            set_cookie: Vec::new(),
+            http_version: None,
+            reason: None,
+            informational: Vec::new(),
         },
         timestamp: chrono::Utc::now(),
+        initiator: None,
+        timing: Default::default(),
+        vary_key: Default::default(),
+        variant: None,
+        sequence: None,
+        frame_id: None,
     });
 
     Snapshot {
@@ -229,6 +284,7 @@ document.addEventListener('DOMContentLoaded', function() {
         url: "https://testsite.example.com/".to_string(),
         created_at: chrono::Utc::now(),
         requests,
+        ..Default::default()
     }
 }
 
@@ -243,6 +299,7 @@ mod workflow_integration_tests {
         let storage = Storage::new(temp_dir.path().to_path_buf());
         storage
             .ensure_snapshots_dir()
+            .await
             .expect("Failed to create snapshots dir");
 
         // Step 1: Simulate capture by creating a comprehensive snapshot
@@ -354,6 +411,7 @@ mod workflow_integration_tests {
         let storage = Storage::new(temp_dir.path().to_path_buf());
         storage
             .ensure_snapshots_dir()
+            .await
             .expect("Failed to create snapshots dir");
 
         // Create multiple test snapshots
@@ -441,6 +499,7 @@ mod workflow_integration_tests {
             let storage = Storage::new(temp_dir.path().to_path_buf());
             storage
                 .ensure_snapshots_dir()
+                .await
                 .expect("Failed to create snapshots dir");
 
             let test_snapshot = create_comprehensive_test_snapshot().await;